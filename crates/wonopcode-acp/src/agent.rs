@@ -5,12 +5,15 @@
 // @ace:design DES-T90R52-IRK
 // @ace:implements COMP-T90R52-1C74
 
+use crate::commands;
+use crate::mode::SessionMode;
 use crate::processor::{load_api_key, Processor, ProcessorConfig};
 use crate::session::SessionManager;
+use crate::store::{self, SessionRecord, SessionStore};
 use crate::transport::{Connection, IncomingMessage, StdioTransport};
 use crate::types::*;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, error, info, warn};
@@ -44,6 +47,10 @@ pub struct Agent {
     session_manager: SessionManager,
     /// Processors for each session.
     processors: Arc<RwLock<HashMap<String, Arc<Processor>>>>,
+    /// Sessions kept for `session/load` after a restart.
+    store: SessionStore,
+    /// File operations the client offers, from `initialize`.
+    client_fs: RwLock<FileSystemCapability>,
 }
 
 impl Agent {
@@ -59,6 +66,8 @@ impl Agent {
             connection,
             session_manager: SessionManager::new(),
             processors: Arc::new(RwLock::new(HashMap::new())),
+            store: SessionStore::open_default(),
+            client_fs: RwLock::new(FileSystemCapability::default()),
         };
 
         (agent, incoming_rx)
@@ -143,7 +152,7 @@ impl Agent {
         &self,
         params: Option<serde_json::Value>,
     ) -> Result<serde_json::Value, JsonRpcError> {
-        let _request: InitializeRequest = params
+        let request: InitializeRequest = params
             .map(serde_json::from_value)
             .transpose()
             .map_err(|e| JsonRpcError::invalid_params(e.to_string()))?
@@ -154,6 +163,17 @@ impl Agent {
 
         info!("Initializing ACP agent");
 
+        let client_fs = request
+            .client_capabilities
+            .and_then(|c| c.fs)
+            .unwrap_or_default();
+        debug!(
+            read = client_fs.read_text_file,
+            write = client_fs.write_text_file,
+            "Client file system"
+        );
+        *self.client_fs.write().await = client_fs;
+
         let response = InitializeResponse {
             protocol_version: 1,
             agent_capabilities: AgentCapabilities {
@@ -241,73 +261,103 @@ impl Agent {
 
         info!("Loading session: {}", request.session_id);
 
-        // Load/create session state
-        // Note: We use the current time as the ACP session load time.
-        // The actual wonopcode session creation time is stored in the session repository,
-        // but ACP sessions track their own lifecycle independently.
-        let _state = self
-            .session_manager
+        // A session this agent already runs keeps its state; otherwise pick
+        // up the model, mode and creation time saved by a previous run.
+        let running = self.session_manager.get(&request.session_id).await.ok();
+        let record = self.store.load(&request.session_id);
+        let (model, mode_id, created_at) = match (running, &record) {
+            (Some(state), _) => (state.model, state.mode_id, state.created_at),
+            (None, Some(record)) => (
+                record.model.clone(),
+                record.mode_id.clone(),
+                record.created_at,
+            ),
+            (None, None) => (self.config.default_model.clone(), None, chrono::Utc::now()),
+        };
+
+        self.session_manager
             .load(
                 request.session_id.clone(),
                 request.cwd.clone(),
                 request.mcp_servers,
-                self.config.default_model.clone(),
-                chrono::Utc::now(),
+                model,
+                created_at,
             )
             .await;
+        if let Some(mode_id) = mode_id {
+            self.session_manager
+                .set_mode(&request.session_id, mode_id)
+                .await?;
+        }
 
         // Build response
         let response = self.build_session_response(&request.session_id).await?;
 
-        // Replay session history if there's an existing processor
-        self.replay_session_history(&request.session_id).await;
+        // Replay the conversation so far into the editor
+        self.replay_session_history(&request.session_id, record)
+            .await;
 
         serde_json::to_value(response).map_err(|e| JsonRpcError::internal_error(e.to_string()))
     }
 
-    /// Replay session history to the client.
-    #[allow(clippy::cognitive_complexity)]
-    async fn replay_session_history(&self, session_id: &str) {
-        let processors = self.processors.read().await;
-        if let Some(processor) = processors.get(session_id) {
-            let history = processor.get_history().await;
+    /// Replay session history to the client, from the running processor or
+    /// else the saved record.
+    async fn replay_session_history(&self, session_id: &str, record: Option<SessionRecord>) {
+        let processor = self.processors.read().await.get(session_id).cloned();
+        let entries = match (processor, record) {
+            (Some(processor), _) => processor.transcript().await,
+            (None, Some(record)) => record.entries,
+            (None, None) => Vec::new(),
+        };
 
-            if history.is_empty() {
-                debug!("No history to replay for session {}", session_id);
-                return;
-            }
+        if entries.is_empty() {
+            debug!("No history to replay for session {}", session_id);
+            return;
+        }
 
-            info!(
-                "Replaying {} messages for session {}",
-                history.len(),
-                session_id
-            );
+        info!(
+            "Replaying {} entries for session {}",
+            entries.len(),
+            session_id
+        );
+
+        for update in store::replay(&entries) {
+            let _ = self
+                .connection
+                .session_update(SessionUpdateNotification {
+                    session_id: session_id.to_string(),
+                    update,
+                })
+                .await;
+        }
+    }
 
-            for (role, text) in history {
-                let update = match role.as_str() {
-                    "user" => SessionUpdate::UserMessageChunk {
-                        content: TextContent::new(&text),
-                    },
-                    "assistant" => SessionUpdate::AgentMessageChunk {
-                        content: TextContent::new(&text),
-                    },
-                    _ => continue,
-                };
+    /// Save a session so `session/load` can continue it after a restart.
+    async fn save_session(&self, session_id: &str) {
+        let Ok(session) = self.session_manager.get(session_id).await else {
+            return;
+        };
+        let processor = self.processors.read().await.get(session_id).cloned();
+        let (entries, cli_session_id) = match processor {
+            Some(processor) => (
+                processor.transcript().await,
+                processor.cli_session_id().await,
+            ),
+            None => match self.store.load(session_id) {
+                Some(record) => (record.entries, record.cli_session_id),
+                None => (Vec::new(), None),
+            },
+        };
 
-                let _ = self
-                    .connection
-                    .session_update(SessionUpdateNotification {
-                        session_id: session_id.to_string(),
-                        update,
-                    })
-                    .await;
-            }
-        } else {
-            debug!(
-                "No processor found for session {} - no history to replay",
-                session_id
-            );
-        }
+        self.store.save(&SessionRecord {
+            id: session.id,
+            cwd: session.cwd,
+            created_at: session.created_at,
+            model: session.model,
+            mode_id: session.mode_id,
+            cli_session_id,
+            entries,
+        });
     }
 
     /// Handle prompt request.
@@ -339,15 +389,24 @@ impl Agent {
 
         // Check for slash command
         if text.trim().starts_with('/') {
-            return self.handle_slash_command(&session.id, &text).await;
+            return self.handle_slash_command(&session, &text).await;
         }
 
+        self.run_prompt(&session, &text).await
+    }
+
+    /// Run a prompt through the session's processor.
+    async fn run_prompt(
+        &self,
+        session: &AcpSessionState,
+        text: &str,
+    ) -> Result<serde_json::Value, JsonRpcError> {
         // Get or create processor for this session
-        let processor = self.get_or_create_processor(&session).await?;
+        let processor = self.get_or_create_processor(session).await?;
 
         // Process the prompt
         match processor
-            .process_prompt(&session.id, &text, &self.connection)
+            .process_prompt(&session.id, text, &self.connection)
             .await
         {
             Ok(()) => {
@@ -368,6 +427,15 @@ impl Agent {
             }
         }
 
+        // The plan mode tools may have switched modes
+        let mode = processor.mode().await;
+        if mode != session_mode(session) {
+            self.session_manager
+                .set_mode(&session.id, mode.id().to_string())
+                .await?;
+        }
+        self.save_session(&session.id).await;
+
         let response = PromptResponse {
             stop_reason: StopReason::EndTurn,
             _meta: None,
@@ -423,7 +491,14 @@ impl Agent {
         let cwd = PathBuf::from(&session.cwd);
         let processor = Processor::new(config, &cwd)
             .await
-            .map_err(|e| JsonRpcError::internal_error(e.to_string()))?;
+            .map_err(|e| JsonRpcError::internal_error(e.to_string()))?
+            .with_client_fs(*self.client_fs.read().await)
+            .with_mode(session_mode(session));
+
+        // Continue where a previous run of the agent left off
+        if let Some(record) = self.store.load(&session.id) {
+            processor.restore(&record).await;
+        }
 
         let processor = Arc::new(processor);
 
@@ -453,6 +528,7 @@ impl Agent {
         self.session_manager
             .set_model(&request.session_id, model)
             .await?;
+        self.save_session(&request.session_id).await;
 
         Ok(serde_json::json!({"_meta": {}}))
     }
@@ -468,9 +544,22 @@ impl Agent {
             .map_err(|e| JsonRpcError::invalid_params(e.to_string()))?
             .ok_or_else(|| JsonRpcError::invalid_params("Missing params"))?;
 
+        let mode = SessionMode::parse(&request.mode_id).ok_or_else(|| {
+            JsonRpcError::invalid_params(format!("Unknown mode: {}", request.mode_id))
+        })?;
         self.session_manager
-            .set_mode(&request.session_id, request.mode_id)
+            .set_mode(&request.session_id, mode.id().to_string())
             .await?;
+        let processor = self
+            .processors
+            .read()
+            .await
+            .get(&request.session_id)
+            .cloned();
+        if let Some(processor) = processor {
+            processor.set_mode(mode).await;
+        }
+        self.save_session(&request.session_id).await;
 
         Ok(serde_json::Value::Null)
     }
@@ -520,27 +609,19 @@ impl Agent {
             .map(|m| m.as_string())
             .unwrap_or_else(|| "anthropic/claude-sonnet-4-5-20250929".to_string());
 
-        // Get available modes from agents configuration
+        // Get available modes
         let available_modes = self.get_available_modes();
 
-        let current_mode_id = session.mode_id.unwrap_or_else(|| "default".to_string());
+        let current_mode_id = session_mode(&session).id().to_string();
 
-        // Send available commands
+        // Send available commands, with the project's custom ones
+        let registry = commands::load(Path::new(&session.cwd)).await;
         let _ = self
             .connection
             .session_update(SessionUpdateNotification {
                 session_id: session_id.to_string(),
                 update: SessionUpdate::AvailableCommandsUpdate {
-                    available_commands: vec![
-                        CommandInfo {
-                            name: "compact".to_string(),
-                            description: "Compact the session history".to_string(),
-                        },
-                        CommandInfo {
-                            name: "clear".to_string(),
-                            description: "Clear the session".to_string(),
-                        },
-                    ],
+                    available_commands: commands::available(&registry),
                 },
             })
             .await;
@@ -562,12 +643,14 @@ impl Agent {
     /// Handle slash commands.
     async fn handle_slash_command(
         &self,
-        session_id: &str,
+        session: &AcpSessionState,
         text: &str,
     ) -> Result<serde_json::Value, JsonRpcError> {
+        let session_id = session.id.as_str();
         let text = text.trim();
         let parts: Vec<&str> = text[1..].splitn(2, char::is_whitespace).collect();
         let command = parts.first().unwrap_or(&"");
+        let arguments = parts.get(1).map_or("", |arguments| arguments.trim());
 
         info!("Handling slash command: /{}", command);
 
@@ -604,9 +687,10 @@ impl Agent {
             }
             "clear" => {
                 // Get processor for session and clear history
-                let processors = self.processors.read().await;
-                if let Some(processor) = processors.get(session_id) {
+                let processor = self.processors.read().await.get(session_id).cloned();
+                if let Some(processor) = processor {
                     processor.clear_history().await;
+                    self.save_session(session_id).await;
                     let _ = self
                         .connection
                         .session_update(SessionUpdateNotification {
@@ -628,7 +712,27 @@ impl Agent {
                         .await;
                 }
             }
+            "sandbox" => {
+                let processor = self.get_or_create_processor(session).await?;
+                let message = processor.sandbox_command(arguments).await;
+                let _ = self
+                    .connection
+                    .session_update(SessionUpdateNotification {
+                        session_id: session_id.to_string(),
+                        update: SessionUpdate::AgentMessageChunk {
+                            content: TextContent::new(message),
+                        },
+                    })
+                    .await;
+            }
             _ => {
+                // Prompt commands run as a prompt made from their template
+                let registry = commands::load(Path::new(&session.cwd)).await;
+                if let Some(prompt_command) = registry.get(command) {
+                    let prompt = prompt_command.expand(arguments);
+                    return self.run_prompt(session, &prompt).await;
+                }
+
                 let _ = self
                     .connection
                     .session_update(SessionUpdateNotification {
@@ -722,32 +826,24 @@ impl Agent {
         ]
     }
 
-    /// Get available agent modes.
-    ///
-    /// Returns the built-in agent modes. Custom agents defined in wonopcode.json
-    /// are loaded separately via the agent registry in wonopcode-core.
+    /// Get available agent modes: code, plan and ask.
     fn get_available_modes(&self) -> Vec<ModeInfo> {
-        // Built-in modes - custom agents are handled via wonopcode-core::AgentRegistry
-        vec![
-            ModeInfo {
-                id: "default".to_string(),
-                name: "default".to_string(),
-                description: Some("General-purpose coding assistant".to_string()),
-            },
-            ModeInfo {
-                id: "plan".to_string(),
-                name: "plan".to_string(),
-                description: Some("Planning mode for complex tasks".to_string()),
-            },
-            ModeInfo {
-                id: "code".to_string(),
-                name: "code".to_string(),
-                description: Some("Code-focused mode with minimal explanations".to_string()),
-            },
-        ]
+        SessionMode::ALL
+            .into_iter()
+            .map(SessionMode::info)
+            .collect()
     }
 }
 
+/// The mode of a session; sessions without one are in the default mode.
+fn session_mode(session: &AcpSessionState) -> SessionMode {
+    session
+        .mode_id
+        .as_deref()
+        .and_then(SessionMode::parse)
+        .unwrap_or_default()
+}
+
 /// Capitalize the first letter of a provider name.
 fn capitalize_provider(s: &str) -> String {
     let mut chars = s.chars();
@@ -0,0 +1,151 @@
+//! Slash commands offered to the editor.
+//!
+//! The session commands (`/compact`, `/clear`, `/sandbox`) are handled by the
+//! agent. The others come from a [`CommandRegistry`] with the built-in prompt
+//! commands and the project's custom ones, and run as a prompt made from the
+//! command's template.
+
+use crate::types::{CommandInfo, CommandInput};
+use std::path::Path;
+use tracing::warn;
+use wonopcode_core::{Command, CommandRegistry, Config};
+
+/// The commands the agent handles itself: name, description and input hint.
+const SESSION_COMMANDS: &[(&str, &str, Option<&str>)] = &[
+    ("compact", "Compact the session history", None),
+    ("clear", "Clear the session", None),
+    (
+        "sandbox",
+        "Control the sandbox",
+        Some("start | stop | restart | status"),
+    ),
+];
+
+/// Whether the agent handles `name` itself.
+pub fn is_session_command(name: &str) -> bool {
+    SESSION_COMMANDS
+        .iter()
+        .any(|(command, _, _)| *command == name)
+}
+
+/// The prompt commands of the project at `cwd`.
+pub async fn load(cwd: &Path) -> CommandRegistry {
+    let config = match Config::load(Some(cwd)).await {
+        Ok((config, _)) => config,
+        Err(e) => {
+            warn!(error = %e, "Failed to load config for commands");
+            Config::default()
+        }
+    };
+    let mut registry = CommandRegistry::with_builtins();
+    registry.load_project(cwd, &config).await;
+    registry
+}
+
+/// The commands to advertise: the session commands, then the prompt
+/// commands by name.
+pub fn available(registry: &CommandRegistry) -> Vec<CommandInfo> {
+    let mut prompt_commands: Vec<&Command> = registry
+        .list()
+        .into_iter()
+        .filter(|command| !is_session_command(&command.name))
+        .collect();
+    prompt_commands.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let session_commands = SESSION_COMMANDS
+        .iter()
+        .map(|(name, description, hint)| CommandInfo {
+            name: name.to_string(),
+            description: description.to_string(),
+            input: hint.map(|hint| CommandInput {
+                hint: hint.to_string(),
+            }),
+        });
+    session_commands
+        .chain(prompt_commands.into_iter().map(|command| CommandInfo {
+            name: command.name.clone(),
+            description: if command.description.is_empty() {
+                format!("Run /{}", command.name)
+            } else {
+                command.description.clone()
+            },
+            input: input_hint(command).map(|hint| CommandInput { hint }),
+        }))
+        .collect()
+}
+
+/// The hint for a command's input: its declared arguments, or a generic
+/// hint when the template uses the raw arguments.
+fn input_hint(command: &Command) -> Option<String> {
+    if !command.arguments.is_empty() {
+        let names: Vec<&str> = command
+            .arguments
+            .iter()
+            .map(|argument| argument.name.as_str())
+            .collect();
+        return Some(names.join(" "));
+    }
+    let uses_arguments = command.template.contains("$ARGUMENTS")
+        || (1..=9).any(|i| command.template.contains(&format!("${i}")));
+    uses_arguments.then(|| "arguments".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_available_commands() {
+        let mut registry = CommandRegistry::with_builtins();
+        registry.register(Command::new("deploy", "Deploy to production"));
+        registry
+            .register(Command::new("greet", "Say hello to $1").with_description("Greet someone"));
+
+        let commands = available(&registry);
+        let names: Vec<&str> = commands.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(&names[..3], &["compact", "clear", "sandbox"]);
+        assert!(names.contains(&"review"));
+
+        // The agent's own /sandbox replaces the built-in prompt command
+        assert_eq!(names.iter().filter(|name| **name == "sandbox").count(), 1);
+        let sandbox = &commands[2];
+        assert_eq!(
+            sandbox.input.as_ref().unwrap().hint,
+            "start | stop | restart | status"
+        );
+
+        let deploy = commands.iter().find(|c| c.name == "deploy").unwrap();
+        assert_eq!(deploy.description, "Run /deploy");
+        assert!(deploy.input.is_none());
+
+        let greet = commands.iter().find(|c| c.name == "greet").unwrap();
+        assert_eq!(greet.description, "Greet someone");
+        assert_eq!(greet.input.as_ref().unwrap().hint, "arguments");
+
+        let json = serde_json::to_value(deploy).unwrap();
+        assert!(json.get("input").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_load_project_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let commands_dir = dir.path().join(".wonopcode").join("command");
+        std::fs::create_dir_all(&commands_dir).unwrap();
+        std::fs::write(
+            commands_dir.join("ship.md"),
+            "---\ndescription: Ship it\narguments: [version]\n---\nRelease {{version}}",
+        )
+        .unwrap();
+
+        let registry = load(dir.path()).await;
+        let ship = registry.get("ship").unwrap();
+        assert_eq!(ship.expand("1.2.0"), "Release 1.2.0");
+
+        let info = available(&registry)
+            .into_iter()
+            .find(|c| c.name == "ship")
+            .unwrap();
+        assert_eq!(info.description, "Ship it");
+        assert_eq!(info.input.unwrap().hint, "version");
+    }
+}
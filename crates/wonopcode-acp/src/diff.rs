@@ -0,0 +1,117 @@
+//! Diffs of the files edit tools change.
+//!
+//! Before an edit tool runs, [`FileSnapshots::take`] keeps the text of each
+//! file it names; afterwards [`FileSnapshots::diffs`] compares them with the
+//! files as they are, giving `diff` content blocks the editor renders as a
+//! native diff preview. Files are read the way the tool reads them: through
+//! the editor when it offers to, so unsaved buffers count.
+
+use crate::types::{Location, ToolCallContent, ToolKind};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wonopcode_tools::ClientFs;
+
+/// The files an edit tool call names, with their text before it ran.
+pub struct FileSnapshots {
+    files: Vec<(PathBuf, Option<String>)>,
+    client_fs: Option<Arc<dyn ClientFs>>,
+}
+
+impl FileSnapshots {
+    /// Keep the files `tool` is about to change; nothing for other tools.
+    pub async fn take(
+        tool: &str,
+        input: &serde_json::Value,
+        cwd: &Path,
+        client_fs: Option<Arc<dyn ClientFs>>,
+    ) -> Self {
+        let client_fs = client_fs.filter(|fs| fs.reads());
+        let mut files = Vec::new();
+        if ToolKind::from_tool_name(tool) == ToolKind::Edit {
+            for location in Location::from_tool_input(tool, input) {
+                let path = cwd.join(&location.path);
+                let text = read(&path, client_fs.as_ref()).await;
+                files.push((path, text));
+            }
+        }
+        Self { files, client_fs }
+    }
+
+    /// A diff for each file that changed since the snapshot.
+    pub async fn diffs(&self) -> Vec<ToolCallContent> {
+        let mut diffs = Vec::new();
+        for (path, before) in &self.files {
+            let Some(after) = read(path, self.client_fs.as_ref()).await else {
+                continue;
+            };
+            if before.as_deref() != Some(after.as_str()) {
+                diffs.push(ToolCallContent::Diff {
+                    path: path.display().to_string(),
+                    old_text: before.clone(),
+                    new_text: after,
+                });
+            }
+        }
+        diffs
+    }
+}
+
+/// The text of a file, or None if it can't be read.
+async fn read(path: &Path, client_fs: Option<&Arc<dyn ClientFs>>) -> Option<String> {
+    match client_fs {
+        Some(fs) => fs.read_text_file(path).await.ok(),
+        None => tokio::fs::read_to_string(path).await.ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_diffs_of_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "old").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "same").unwrap();
+
+        let input = json!({"edits": [
+            {"filePath": "a.rs", "oldString": "old", "newString": "new"},
+            {"filePath": "b.rs", "oldString": "x", "newString": "y"},
+            {"filePath": "c.rs", "oldString": "", "newString": "created"}
+        ]});
+        let snapshots = FileSnapshots::take("multiedit", &input, dir.path(), None).await;
+        std::fs::write(dir.path().join("a.rs"), "new").unwrap();
+        std::fs::write(dir.path().join("c.rs"), "created").unwrap();
+
+        let diffs = snapshots.diffs().await;
+        assert_eq!(diffs.len(), 2);
+        match &diffs[0] {
+            ToolCallContent::Diff {
+                path,
+                old_text,
+                new_text,
+            } => {
+                assert!(path.ends_with("a.rs"));
+                assert_eq!(old_text.as_deref(), Some("old"));
+                assert_eq!(new_text, "new");
+            }
+            other => panic!("expected a diff, got {other:?}"),
+        }
+        match &diffs[1] {
+            ToolCallContent::Diff { old_text, .. } => assert!(old_text.is_none()),
+            other => panic!("expected a diff, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_no_snapshots_for_other_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "text").unwrap();
+
+        let input = json!({"filePath": "a.rs"});
+        let snapshots = FileSnapshots::take("read", &input, dir.path(), None).await;
+        std::fs::write(dir.path().join("a.rs"), "changed").unwrap();
+        assert!(snapshots.diffs().await.is_empty());
+    }
+}
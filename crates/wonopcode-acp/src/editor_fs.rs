@@ -0,0 +1,80 @@
+//! Files read and written through the editor.
+//!
+//! When the client advertises the `fs` capability, the file tools go through
+//! `fs/read_text_file` and `fs/write_text_file` instead of the disk, so they
+//! see unsaved buffers and their edits land in the editor's undo history.
+
+use crate::transport::Connection;
+use crate::types::{FileSystemCapability, ReadTextFileRequest, WriteTextFileRequest};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use wonopcode_tools::ClientFs;
+
+/// The editor's file system, for the tools of one session.
+pub struct EditorFs {
+    connection: Connection,
+    session_id: String,
+    cwd: PathBuf,
+    capability: FileSystemCapability,
+}
+
+impl EditorFs {
+    /// Create the file system of a session, if the client offers any of it.
+    pub fn new(
+        connection: Connection,
+        session_id: &str,
+        cwd: &Path,
+        capability: FileSystemCapability,
+    ) -> Option<Self> {
+        (capability.read_text_file || capability.write_text_file).then(|| Self {
+            connection,
+            session_id: session_id.to_string(),
+            cwd: cwd.to_path_buf(),
+            capability,
+        })
+    }
+
+    /// ACP wants absolute paths.
+    fn absolute(&self, path: &Path) -> String {
+        if path.is_absolute() {
+            path.display().to_string()
+        } else {
+            self.cwd.join(path).display().to_string()
+        }
+    }
+}
+
+#[async_trait]
+impl ClientFs for EditorFs {
+    fn reads(&self) -> bool {
+        self.capability.read_text_file
+    }
+
+    fn writes(&self) -> bool {
+        self.capability.write_text_file
+    }
+
+    async fn read_text_file(&self, path: &Path) -> Result<String, String> {
+        self.connection
+            .read_text_file(ReadTextFileRequest {
+                session_id: self.session_id.clone(),
+                path: self.absolute(path),
+                line: None,
+                limit: None,
+            })
+            .await
+            .map(|response| response.content)
+            .map_err(|e| e.to_string())
+    }
+
+    async fn write_text_file(&self, path: &Path, content: &str) -> Result<(), String> {
+        self.connection
+            .write_text_file(WriteTextFileRequest {
+                session_id: self.session_id.clone(),
+                path: self.absolute(path),
+                content: content.to_string(),
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
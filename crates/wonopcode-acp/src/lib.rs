@@ -46,13 +46,22 @@
 //! Configure in the AI assistant settings to use the wonopcode ACP server.
 
 pub mod agent;
+pub mod commands;
+pub mod diff;
+pub mod editor_fs;
+pub mod mode;
+pub mod permission;
 pub mod processor;
 pub mod session;
+pub mod store;
 pub mod transport;
 pub mod types;
 
 pub use agent::{serve, Agent, AgentConfig};
+pub use editor_fs::EditorFs;
+pub use mode::SessionMode;
 pub use processor::{load_api_key, Processor, ProcessorConfig};
 pub use session::SessionManager;
+pub use store::{SessionRecord, SessionStore};
 pub use transport::{Connection, StdioTransport};
 pub use types::*;
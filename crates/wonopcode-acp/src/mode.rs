@@ -0,0 +1,168 @@
+//! Session modes.
+//!
+//! The editor switches a session between modes with `session/set_mode`. Each
+//! mode is one of wonopcode's agents: `code` is the build agent with full
+//! access, `plan` the plan agent that reads and plans without editing, and
+//! `ask` only reads to answer questions. The plan mode tools move a session
+//! between `code` and `plan` on their own, and the editor is told with a
+//! `current_mode_update`.
+
+use crate::types::ModeInfo;
+
+/// Tools that change files.
+const EDIT_TOOLS: &[&str] = &["write", "edit", "multiedit", "patch"];
+
+/// Tools that only look at the project or the web.
+const READ_TOOLS: &[&str] = &[
+    "read",
+    "glob",
+    "grep",
+    "list",
+    "websearch",
+    "codesearch",
+    "webfetch",
+    "todoread",
+];
+
+const PLAN_INSTRUCTIONS: &str = "You are in plan mode. Explore the code and write a plan, \
+but do not change any files. Shell commands need the user's approval. Call exitplanmode \
+when the plan is ready to implement.";
+
+const ASK_INSTRUCTIONS: &str = "You are in ask mode. Answer the user's questions from \
+reading the code; you cannot change files or run commands.";
+
+/// The mode of an ACP session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SessionMode {
+    /// Full access: edit files and run commands.
+    #[default]
+    Code,
+    /// Read-only planning; shell commands always ask.
+    Plan,
+    /// Read-only questions and answers.
+    Ask,
+}
+
+impl SessionMode {
+    /// All modes, in the order the editor lists them.
+    pub const ALL: [SessionMode; 3] = [SessionMode::Code, SessionMode::Plan, SessionMode::Ask];
+
+    /// The ACP mode ID.
+    pub fn id(self) -> &'static str {
+        match self {
+            SessionMode::Code => "code",
+            SessionMode::Plan => "plan",
+            SessionMode::Ask => "ask",
+        }
+    }
+
+    /// Parse an ACP mode ID. `default` and `build`, from older sessions and
+    /// the agent name, are the code mode.
+    pub fn parse(id: &str) -> Option<Self> {
+        match id {
+            "code" | "default" | "build" => Some(SessionMode::Code),
+            "plan" => Some(SessionMode::Plan),
+            "ask" => Some(SessionMode::Ask),
+            _ => None,
+        }
+    }
+
+    /// The agent the tools run as.
+    pub fn agent(self) -> &'static str {
+        match self {
+            SessionMode::Code => "build",
+            SessionMode::Plan => "plan",
+            SessionMode::Ask => "ask",
+        }
+    }
+
+    /// The mode an `agent_change` from the plan mode tools switches to.
+    pub fn from_agent(agent: &str) -> Option<Self> {
+        match agent {
+            "build" => Some(SessionMode::Code),
+            "plan" => Some(SessionMode::Plan),
+            _ => None,
+        }
+    }
+
+    /// The mode as the editor lists it.
+    pub fn info(self) -> ModeInfo {
+        let (name, description) = match self {
+            SessionMode::Code => ("Code", "Edit files and run commands"),
+            SessionMode::Plan => ("Plan", "Explore and plan without changing files"),
+            SessionMode::Ask => ("Ask", "Answer questions without changing anything"),
+        };
+        ModeInfo {
+            id: self.id().to_string(),
+            name: name.to_string(),
+            description: Some(description.to_string()),
+        }
+    }
+
+    /// Whether the model may call `tool` in this mode.
+    pub fn allows_tool(self, tool: &str) -> bool {
+        match self {
+            SessionMode::Code => true,
+            SessionMode::Plan => !EDIT_TOOLS.contains(&tool),
+            SessionMode::Ask => READ_TOOLS.contains(&tool),
+        }
+    }
+
+    /// Whether every call of `tool` asks the user, whatever the rules say.
+    pub fn always_asks(self, tool: &str) -> bool {
+        self == SessionMode::Plan && tool == "bash"
+    }
+
+    /// Instructions added to the system prompt.
+    pub fn instructions(self) -> Option<&'static str> {
+        match self {
+            SessionMode::Code => None,
+            SessionMode::Plan => Some(PLAN_INSTRUCTIONS),
+            SessionMode::Ask => Some(ASK_INSTRUCTIONS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_ids() {
+        for mode in SessionMode::ALL {
+            assert_eq!(SessionMode::parse(mode.id()), Some(mode));
+            assert_eq!(mode.info().id, mode.id());
+        }
+        assert_eq!(SessionMode::parse("default"), Some(SessionMode::Code));
+        assert_eq!(SessionMode::parse("yolo"), None);
+
+        // The plan mode tools switch between the build and plan agents
+        assert_eq!(
+            SessionMode::from_agent(SessionMode::Plan.agent()),
+            Some(SessionMode::Plan)
+        );
+        assert_eq!(
+            SessionMode::from_agent(SessionMode::Code.agent()),
+            Some(SessionMode::Code)
+        );
+    }
+
+    #[test]
+    fn test_tools_by_mode() {
+        assert!(SessionMode::Code.allows_tool("edit"));
+        assert!(SessionMode::Code.allows_tool("bash"));
+
+        assert!(!SessionMode::Plan.allows_tool("edit"));
+        assert!(!SessionMode::Plan.allows_tool("patch"));
+        assert!(SessionMode::Plan.allows_tool("read"));
+        assert!(SessionMode::Plan.allows_tool("bash"));
+        assert!(SessionMode::Plan.allows_tool("exitplanmode"));
+        assert!(SessionMode::Plan.always_asks("bash"));
+        assert!(!SessionMode::Code.always_asks("bash"));
+
+        assert!(SessionMode::Ask.allows_tool("grep"));
+        assert!(!SessionMode::Ask.allows_tool("bash"));
+        assert!(!SessionMode::Ask.allows_tool("write"));
+        assert!(!SessionMode::Ask.allows_tool("enterplanmode"));
+    }
+}
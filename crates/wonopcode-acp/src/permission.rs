@@ -0,0 +1,162 @@
+//! Permission prompts as ACP permission requests.
+//!
+//! Tools run by the processor are checked by a [`PermissionManager`] with the
+//! usual rules. When a rule says to ask, the manager publishes a request on
+//! the bus; [`forward_requests`] turns it into `session/request_permission`,
+//! so the editor shows its own dialog, and answers the manager with the
+//! option the user picked. "Always" answers become session rules.
+
+use crate::transport::Connection;
+use crate::types::{
+    Location, PermissionKind, PermissionOption, PermissionOutcome, PermissionRequest, ToolCallInfo,
+    ToolKind, ToolStatus,
+};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+use wonopcode_core::bus::PermissionRequest as BusPermissionRequest;
+use wonopcode_core::PermissionManager;
+
+/// The answers offered for every request.
+pub fn permission_options() -> Vec<PermissionOption> {
+    [
+        (PermissionKind::AllowOnce, "allow_once", "Allow"),
+        (PermissionKind::AllowAlways, "allow_always", "Always allow"),
+        (PermissionKind::RejectOnce, "reject_once", "Reject"),
+        (
+            PermissionKind::RejectAlways,
+            "reject_always",
+            "Always reject",
+        ),
+    ]
+    .into_iter()
+    .map(|(kind, id, name)| PermissionOption {
+        option_id: id.to_string(),
+        kind,
+        name: name.to_string(),
+    })
+    .collect()
+}
+
+/// Whether an answer allows the call, and whether to remember it.
+/// A cancelled request rejects it.
+pub fn decision(outcome: &PermissionOutcome) -> (bool, bool) {
+    if outcome.outcome != "selected" {
+        return (false, false);
+    }
+    match outcome.option_id.as_deref() {
+        Some("allow_once") => (true, false),
+        Some("allow_always") => (true, true),
+        Some("reject_always") => (false, true),
+        _ => (false, false),
+    }
+}
+
+/// The ACP request for a permission prompt.
+fn to_acp(session_id: &str, request: &BusPermissionRequest) -> PermissionRequest {
+    let locations = match &request.path {
+        Some(path) => vec![Location { path: path.clone() }],
+        None => Location::from_tool_input(&request.tool, &request.details),
+    };
+    PermissionRequest {
+        session_id: session_id.to_string(),
+        tool_call: ToolCallInfo {
+            tool_call_id: request.id.clone(),
+            status: ToolStatus::Pending,
+            title: request.description.clone(),
+            kind: ToolKind::from_tool_name(&request.tool),
+            locations,
+            raw_input: request.details.clone(),
+        },
+        options: permission_options(),
+    }
+}
+
+/// Ask the client about each permission prompt of `session_id` until the
+/// bus closes, and pass the answers to `permissions`.
+pub async fn forward_requests(
+    mut requests: broadcast::Receiver<BusPermissionRequest>,
+    permissions: Arc<PermissionManager>,
+    connection: Connection,
+    session_id: String,
+) {
+    loop {
+        let request = match requests.recv().await {
+            Ok(request) => request,
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                warn!(missed, "Missed permission requests");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        if request.session_id != session_id {
+            continue;
+        }
+
+        let (allowed, remember) = match connection
+            .request_permission(to_acp(&session_id, &request))
+            .await
+        {
+            Ok(response) => decision(&response.outcome),
+            Err(e) => {
+                warn!(tool = %request.tool, error = %e, "Permission request failed");
+                (false, false)
+            }
+        };
+        debug!(tool = %request.tool, allowed, remember, "Permission answered");
+        permissions.respond(&request.id, allowed, remember).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selected(option: &str) -> PermissionOutcome {
+        PermissionOutcome {
+            outcome: "selected".to_string(),
+            option_id: Some(option.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_decisions() {
+        assert_eq!(decision(&selected("allow_once")), (true, false));
+        assert_eq!(decision(&selected("allow_always")), (true, true));
+        assert_eq!(decision(&selected("reject_once")), (false, false));
+        assert_eq!(decision(&selected("reject_always")), (false, true));
+
+        let cancelled = PermissionOutcome {
+            outcome: "cancelled".to_string(),
+            option_id: None,
+        };
+        assert_eq!(decision(&cancelled), (false, false));
+
+        // Every option offered has a decision
+        for option in permission_options() {
+            let id = option.option_id.as_str();
+            assert_eq!(decision(&selected(id)).0, id.starts_with("allow"));
+        }
+    }
+
+    #[test]
+    fn test_request_describes_the_tool_call() {
+        let request = BusPermissionRequest {
+            id: "call_1".to_string(),
+            session_id: "ses_1".to_string(),
+            tool: "edit".to_string(),
+            action: "edit".to_string(),
+            description: "Edit file: /src/main.rs".to_string(),
+            path: Some("/src/main.rs".to_string()),
+            details: serde_json::json!({"filePath": "/src/main.rs"}),
+        };
+        let acp = to_acp("ses_1", &request);
+        let json = serde_json::to_value(&acp).unwrap();
+        assert_eq!(json["sessionId"], "ses_1");
+        assert_eq!(json["toolCall"]["toolCallId"], "call_1");
+        assert_eq!(json["toolCall"]["kind"], "edit");
+        assert_eq!(json["toolCall"]["locations"][0]["path"], "/src/main.rs");
+        assert_eq!(json["options"].as_array().unwrap().len(), 4);
+        assert_eq!(json["options"][3]["kind"], "reject_always");
+    }
+}
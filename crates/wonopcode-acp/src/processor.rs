@@ -4,6 +4,10 @@
 // @ace:design DES-T90R52-1C94
 // @ace:implements COMP-T90R52-16JS
 
+use crate::diff::FileSnapshots;
+use crate::editor_fs::EditorFs;
+use crate::mode::SessionMode;
+use crate::store::{Entry, SessionRecord};
 use crate::transport::Connection;
 use crate::types::*;
 use futures::StreamExt;
@@ -12,12 +16,14 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info};
-use wonopcode_core::Instance;
+use wonopcode_core::bus::PermissionRequest as BusPermissionRequest;
+use wonopcode_core::{Instance, PermissionCheck, PermissionManager};
 use wonopcode_provider::{
     model::ModelInfo, stream::StreamChunk, BoxedLanguageModel, GenerateOptions,
     Message as ProviderMessage, ToolDefinition,
 };
-use wonopcode_tools::ToolRegistry;
+use wonopcode_sandbox::{SandboxManager, SandboxRuntime};
+use wonopcode_tools::{ClientFs, ToolOutput, ToolRegistry};
 
 /// Processor configuration.
 #[derive(Debug, Clone)]
@@ -48,6 +54,17 @@ pub struct Processor {
     provider: Arc<RwLock<BoxedLanguageModel>>,
     tools: Arc<ToolRegistry>,
     history: RwLock<Vec<ProviderMessage>>,
+    /// What the editor was shown, for replay on `session/load`.
+    transcript: RwLock<Vec<Entry>>,
+    /// File operations the editor performs for the tools.
+    client_fs: FileSystemCapability,
+    /// Checks each tool call, asking the editor when a rule says to.
+    permissions: Arc<PermissionManager>,
+    /// Which tools the model gets, and how they're checked.
+    mode: RwLock<SessionMode>,
+    /// The project's sandbox, when configured. Tools run in it while it is
+    /// started with `/sandbox start`.
+    sandbox: Option<Arc<SandboxManager>>,
     /// Cancellation token for the current operation.
     cancel_token: RwLock<Option<CancellationToken>>,
 }
@@ -64,6 +81,29 @@ impl Processor {
         // Create provider
         let provider = create_provider(&config)?;
 
+        // Permission rules: the defaults, then the project's config
+        let permissions = Arc::new(PermissionManager::new(instance.bus().clone()));
+        for rule in PermissionManager::default_rules() {
+            permissions.add_rule(rule).await;
+        }
+        if let Some(config) = &instance.config().await.permission {
+            for rule in PermissionManager::rules_from_config(config) {
+                permissions.add_rule(rule).await;
+            }
+        }
+
+        let sandbox = instance
+            .config()
+            .await
+            .sandbox
+            .filter(|sandbox| sandbox.enabled.unwrap_or(false))
+            .map(|sandbox| {
+                Arc::new(SandboxManager::new_lazy(
+                    sandbox.to_runtime_config(),
+                    cwd.to_path_buf(),
+                ))
+            });
+
         // Create in-memory todo store
         let todo_store = Arc::new(wonopcode_tools::todo::InMemoryTodoStore::new());
 
@@ -77,6 +117,10 @@ impl Processor {
         tools.register(Arc::new(wonopcode_tools::todo::TodoReadTool::new(
             todo_store,
         )));
+        tools.register(Arc::new(
+            wonopcode_tools::plan_mode::EnterPlanModeTool::new(),
+        ));
+        tools.register(Arc::new(wonopcode_tools::plan_mode::ExitPlanModeTool::new()));
 
         Ok(Self {
             config,
@@ -84,10 +128,37 @@ impl Processor {
             provider: Arc::new(RwLock::new(provider)),
             tools: Arc::new(tools),
             history: RwLock::new(Vec::new()),
+            transcript: RwLock::new(Vec::new()),
+            client_fs: FileSystemCapability::default(),
+            permissions,
+            mode: RwLock::new(SessionMode::default()),
+            sandbox,
             cancel_token: RwLock::new(None),
         })
     }
 
+    /// Read and write files through the editor, as far as it offers to.
+    pub fn with_client_fs(mut self, capability: FileSystemCapability) -> Self {
+        self.client_fs = capability;
+        self
+    }
+
+    /// Start in the given mode.
+    pub fn with_mode(mut self, mode: SessionMode) -> Self {
+        self.mode = RwLock::new(mode);
+        self
+    }
+
+    /// The current mode.
+    pub async fn mode(&self) -> SessionMode {
+        *self.mode.read().await
+    }
+
+    /// Switch modes; takes effect from the next prompt.
+    pub async fn set_mode(&self, mode: SessionMode) {
+        *self.mode.write().await = mode;
+    }
+
     /// Cancel the current operation if one is running.
     pub async fn cancel(&self) {
         let token = self.cancel_token.read().await;
@@ -120,14 +191,17 @@ impl Processor {
             *token = Some(cancel_token.clone());
         }
 
+        let mode = self.mode().await;
+
         // Build system prompt
         let environment = build_environment_info(cwd);
         let system_prompt = wonopcode_core::system_prompt::build_system_prompt(
             &self.config.provider,
             &self.config.model_id,
             None, // agent_prompt
-            None, // custom_instructions
+            mode.instructions(),
             &environment,
+            &wonopcode_core::PromptVars::detect(cwd),
         );
 
         // Add user message to history
@@ -135,11 +209,15 @@ impl Processor {
             let mut history = self.history.write().await;
             history.push(ProviderMessage::user(prompt));
         }
+        self.transcript.write().await.push(Entry::User {
+            text: prompt.to_string(),
+        });
 
-        // Get tool definitions
+        // Get tool definitions for the mode
         let tool_defs: Vec<ToolDefinition> = self
             .tools
             .all()
+            .filter(|t| mode.allows_tool(t.id()))
             .map(|t| ToolDefinition {
                 name: t.id().to_string(),
                 description: t.description().to_string(),
@@ -165,6 +243,7 @@ impl Processor {
         let mut stream = provider.generate(messages, options).await?;
 
         let mut response_text = String::new();
+        let mut thought_text = String::new();
         let mut tool_calls: Vec<(String, String, serde_json::Value)> = Vec::new();
         let mut current_tool_id: Option<String> = None;
         let mut current_tool_name: Option<String> = None;
@@ -221,6 +300,8 @@ impl Processor {
                     tool_calls.push((id, name, args));
                 }
                 Ok(StreamChunk::ReasoningDelta(text)) => {
+                    thought_text.push_str(&text);
+
                     // Send reasoning chunk
                     let _ = connection
                         .session_update(SessionUpdateNotification {
@@ -269,35 +350,123 @@ impl Processor {
             }
         }
 
-        // Execute tool calls
+        {
+            let mut transcript = self.transcript.write().await;
+            if !thought_text.is_empty() {
+                transcript.push(Entry::Thought { text: thought_text });
+            }
+            if !response_text.is_empty() {
+                transcript.push(Entry::Agent {
+                    text: response_text.clone(),
+                });
+            }
+        }
+
+        // Execute tool calls, asking the editor for permission where needed
+        let client_fs = EditorFs::new(connection.clone(), session_id, cwd, self.client_fs)
+            .map(|fs| Arc::new(fs) as Arc<dyn ClientFs>);
+        let forwarder = tokio::spawn(crate::permission::forward_requests(
+            self.instance
+                .bus()
+                .subscribe::<BusPermissionRequest>()
+                .await,
+            self.permissions.clone(),
+            connection.clone(),
+            session_id.to_string(),
+        ));
         for (call_id, name, args) in tool_calls {
+            let check = PermissionCheck::for_tool(call_id.clone(), &name, &args, cwd);
+            let allowed = if !mode.allows_tool(&name) {
+                false
+            } else if mode.always_asks(&name) {
+                self.permissions.confirm(session_id, check).await
+            } else {
+                self.permissions.check(session_id, check).await
+            };
+
             // Update status to in_progress
-            let _ = connection
-                .session_update(SessionUpdateNotification {
-                    session_id: session_id.to_string(),
-                    update: SessionUpdate::ToolCallUpdate {
-                        tool_call_id: call_id.clone(),
-                        status: ToolStatus::InProgress,
-                        kind: Some(ToolKind::from_tool_name(&name)),
-                        title: None,
-                        locations: Some(Location::from_tool_input(&name, &args)),
-                        raw_input: Some(args.clone()),
-                        raw_output: None,
-                        content: None,
-                    },
-                })
-                .await;
+            if allowed {
+                let _ = connection
+                    .session_update(SessionUpdateNotification {
+                        session_id: session_id.to_string(),
+                        update: SessionUpdate::ToolCallUpdate {
+                            tool_call_id: call_id.clone(),
+                            status: ToolStatus::InProgress,
+                            kind: Some(ToolKind::from_tool_name(&name)),
+                            title: None,
+                            locations: Some(Location::from_tool_input(&name, &args)),
+                            raw_input: Some(args.clone()),
+                            raw_output: None,
+                            content: None,
+                        },
+                    })
+                    .await;
+            }
 
-            // Execute tool
-            let result = self.execute_tool(&name, &args, cwd).await;
+            // Execute tool, keeping the files it edits to diff them after
+            let mut diffs = Vec::new();
+            let result = if allowed {
+                let reader = match self.tool_sandbox(&name).await {
+                    Some(_) => None,
+                    None => client_fs.clone(),
+                };
+                let snapshots = FileSnapshots::take(&name, &args, cwd, reader).await;
+                let result = self
+                    .execute_tool(&name, &args, cwd, client_fs.clone())
+                    .await;
+                if result.is_ok() {
+                    diffs = snapshots.diffs().await;
+                }
+                result
+            } else if !mode.allows_tool(&name) {
+                Err(format!("{name} is not available in {} mode", mode.id()).into())
+            } else {
+                Err(format!("Permission denied: {name}").into())
+            };
 
-            // Send completion
+            // The plan mode tools switch modes
+            let result = match result {
+                Ok(output) => {
+                    if let Some(next) = output
+                        .metadata
+                        .get("agent_change")
+                        .and_then(|agent| agent.as_str())
+                        .and_then(SessionMode::from_agent)
+                    {
+                        self.switch_mode(session_id, next, connection).await;
+                    }
+                    Ok(output.output)
+                }
+                Err(e) => Err(e),
+            };
+
+            self.transcript.write().await.push(Entry::ToolCall {
+                id: call_id.clone(),
+                name: name.clone(),
+                input: args.clone(),
+                status: if result.is_ok() {
+                    ToolStatus::Completed
+                } else {
+                    ToolStatus::Failed
+                },
+                output: Some(match &result {
+                    Ok(output) => output.clone(),
+                    Err(e) => e.to_string(),
+                }),
+                content: diffs.clone(),
+            });
+
+            // Send completion; edits show as diffs rather than text
             let (status, content, raw_output) = match result {
                 Ok(output) => (
                     ToolStatus::Completed,
-                    vec![ToolCallContent::Content {
-                        content: TextContent::new(&output),
-                    }],
+                    if diffs.is_empty() {
+                        vec![ToolCallContent::Content {
+                            content: TextContent::new(&output),
+                        }]
+                    } else {
+                        diffs
+                    },
                     serde_json::json!({ "output": output }),
                 ),
                 Err(e) => (
@@ -325,6 +494,7 @@ impl Processor {
                 })
                 .await;
         }
+        forwarder.abort();
 
         // Add assistant response to history
         if !response_text.is_empty() {
@@ -347,7 +517,8 @@ impl Processor {
         name: &str,
         args: &serde_json::Value,
         cwd: &Path,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        client_fs: Option<Arc<dyn ClientFs>>,
+    ) -> Result<ToolOutput, Box<dyn std::error::Error + Send + Sync>> {
         let tool = self
             .tools
             .get(name)
@@ -356,19 +527,102 @@ impl Processor {
         let ctx = wonopcode_tools::ToolContext {
             session_id: "acp".to_string(),
             message_id: "acp".to_string(),
-            agent: "acp".to_string(),
+            agent: self.mode().await.agent().to_string(),
             abort: CancellationToken::new(),
             root_dir: cwd.to_path_buf(),
             cwd: cwd.to_path_buf(),
             snapshot: None,
             file_time: None,
-            sandbox: None, // ACP tools run without sandbox for now
+            sandbox: self.tool_sandbox(name).await,
             event_tx: None,
+            formatter: None,
+            client_fs,
         };
 
         let _timing = wonopcode_util::TimingGuard::tool(tool.id());
-        let result = tool.execute(args.clone(), &ctx).await?;
-        Ok(result.output)
+        Ok(tool.execute(args.clone(), &ctx).await?)
+    }
+
+    /// Switch modes from a tool call and tell the editor.
+    async fn switch_mode(&self, session_id: &str, mode: SessionMode, connection: &Connection) {
+        if self.mode().await == mode {
+            return;
+        }
+        info!(mode = mode.id(), "Mode changed via tool");
+        self.set_mode(mode).await;
+        let _ = connection
+            .session_update(SessionUpdateNotification {
+                session_id: session_id.to_string(),
+                update: SessionUpdate::CurrentModeUpdate {
+                    current_mode_id: mode.id().to_string(),
+                },
+            })
+            .await;
+    }
+
+    /// The sandbox to run a tool in: the started sandbox, unless the tool
+    /// bypasses it.
+    async fn tool_sandbox(&self, tool: &str) -> Option<Arc<dyn SandboxRuntime>> {
+        let manager = self.sandbox.as_ref()?;
+        if manager.should_bypass_tool(tool) || !self.sandbox_running().await {
+            return None;
+        }
+        manager.runtime().await.ok()
+    }
+
+    /// Whether the sandbox is started.
+    async fn sandbox_running(&self) -> bool {
+        match &self.sandbox {
+            Some(manager) => !manager.is_explicitly_stopped().await && manager.is_ready().await,
+            None => false,
+        }
+    }
+
+    /// Run `/sandbox <action>`, returning what to tell the user.
+    pub async fn sandbox_command(&self, action: &str) -> String {
+        let Some(manager) = &self.sandbox else {
+            return "Sandbox not configured. Set `sandbox.enabled` in the config to use it."
+                .to_string();
+        };
+        let runtime = manager.runtime_type_display().to_lowercase();
+
+        match action {
+            "" | "status" => {
+                if self.sandbox_running().await {
+                    format!("Sandbox running ({runtime})")
+                } else {
+                    format!("Sandbox stopped ({runtime})")
+                }
+            }
+            "start" => match manager.start().await {
+                Ok(()) => {
+                    info!(runtime = %runtime, "Sandbox started");
+                    format!(
+                        "Sandbox started ({runtime}) - commands will execute in isolated container"
+                    )
+                }
+                Err(e) => format!("Failed to start sandbox: {e}"),
+            },
+            "stop" => match manager.stop().await {
+                Ok(()) => {
+                    info!("Sandbox stopped");
+                    "Sandbox stopped - commands will execute directly on host".to_string()
+                }
+                Err(e) => format!("Failed to stop sandbox: {e}"),
+            },
+            "restart" => {
+                if let Err(e) = manager.stop().await {
+                    return format!("Failed to stop sandbox: {e}");
+                }
+                match manager.start().await {
+                    Ok(()) => format!("Sandbox restarted ({runtime})"),
+                    Err(e) => format!("Failed to start sandbox: {e}"),
+                }
+            }
+            other => {
+                format!("Unknown sandbox action: {other}. Use start, stop, restart or status.")
+            }
+        }
     }
 
     /// Change the model.
@@ -421,6 +675,7 @@ impl Processor {
     pub async fn clear_history(&self) {
         let mut history = self.history.write().await;
         history.clear();
+        self.transcript.write().await.clear();
     }
 
     /// Compact history by pruning old tool outputs.
@@ -545,6 +800,35 @@ impl Processor {
             .collect()
     }
 
+    /// The transcript so far.
+    pub async fn transcript(&self) -> Vec<Entry> {
+        self.transcript.read().await.clone()
+    }
+
+    /// Session ID of the Claude CLI provider, if it runs one.
+    pub async fn cli_session_id(&self) -> Option<String> {
+        self.provider.read().await.get_cli_session_id().await
+    }
+
+    /// Continue a saved session: its transcript, history and CLI session.
+    pub async fn restore(&self, record: &SessionRecord) {
+        self.restore_history(record.history()).await;
+        *self.transcript.write().await = record.entries.clone();
+        if record.cli_session_id.is_some() {
+            self.provider
+                .read()
+                .await
+                .set_cli_session_id(record.cli_session_id.clone())
+                .await;
+        }
+        info!(
+            session = %record.id,
+            messages = record.entries.len(),
+            cli_session_id = ?record.cli_session_id,
+            "Restored ACP session"
+        );
+    }
+
     /// Restore history from a list of (role, text) pairs.
     pub async fn restore_history(&self, messages: Vec<(String, String)>) {
         let mut history = self.history.write().await;
@@ -0,0 +1,326 @@
+//! Persisted ACP sessions.
+//!
+//! Editors reload a conversation with `session/load` after they restart, by
+//! which time the agent process that held it is gone. [`SessionStore`] keeps
+//! a [`SessionRecord`] per session on disk, saved after every prompt, so a
+//! new agent can replay the transcript into the editor and continue with the
+//! same history and provider CLI session.
+
+use crate::types::{
+    Location, ModelRef, SessionUpdate, TextContent, ToolCallContent, ToolKind, ToolStatus,
+};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// One step of a session's transcript.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Entry {
+    /// A prompt from the user.
+    User { text: String },
+    /// Text the agent answered with.
+    Agent { text: String },
+    /// The agent's reasoning before it answered.
+    Thought { text: String },
+    /// A tool call and how it ended.
+    ToolCall {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+        status: ToolStatus,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<String>,
+        /// Shown instead of the output, such as the diffs of an edit.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        content: Vec<ToolCallContent>,
+    },
+}
+
+/// What is kept of an ACP session between agent runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub id: String,
+    pub cwd: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub model: Option<ModelRef>,
+    #[serde(default)]
+    pub mode_id: Option<String>,
+    /// Session ID of the Claude CLI provider, if it ran one.
+    #[serde(default)]
+    pub cli_session_id: Option<String>,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+impl SessionRecord {
+    /// The conversation as (role, text) pairs, for the provider history.
+    pub fn history(&self) -> Vec<(String, String)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::User { text } => Some(("user".to_string(), text.clone())),
+                Entry::Agent { text } => Some(("assistant".to_string(), text.clone())),
+                Entry::Thought { .. } | Entry::ToolCall { .. } => None,
+            })
+            .collect()
+    }
+}
+
+/// The updates that show `entries` in the editor again.
+pub fn replay(entries: &[Entry]) -> Vec<SessionUpdate> {
+    let mut updates = Vec::new();
+    for entry in entries {
+        match entry {
+            Entry::User { text } => updates.push(SessionUpdate::UserMessageChunk {
+                content: TextContent::new(text),
+            }),
+            Entry::Agent { text } => updates.push(SessionUpdate::AgentMessageChunk {
+                content: TextContent::new(text),
+            }),
+            Entry::Thought { text } => updates.push(SessionUpdate::AgentThoughtChunk {
+                content: TextContent::new(text),
+            }),
+            Entry::ToolCall {
+                id,
+                name,
+                input,
+                status,
+                output,
+                content,
+            } => {
+                updates.push(SessionUpdate::ToolCall {
+                    tool_call_id: id.clone(),
+                    title: name.clone(),
+                    kind: ToolKind::from_tool_name(name),
+                    status: *status,
+                    locations: Location::from_tool_input(name, input),
+                    raw_input: input.clone(),
+                });
+                let content = if content.is_empty() {
+                    output.as_ref().map(|output| {
+                        vec![ToolCallContent::Content {
+                            content: TextContent::new(output),
+                        }]
+                    })
+                } else {
+                    Some(content.clone())
+                };
+                if let Some(content) = content {
+                    updates.push(SessionUpdate::ToolCallUpdate {
+                        tool_call_id: id.clone(),
+                        status: *status,
+                        kind: None,
+                        title: None,
+                        locations: None,
+                        raw_input: None,
+                        raw_output: None,
+                        content: Some(content),
+                    });
+                }
+            }
+        }
+    }
+    updates
+}
+
+/// Session records, one JSON file each.
+pub struct SessionStore {
+    dir: Option<PathBuf>,
+}
+
+impl SessionStore {
+    /// Keep nothing; every load starts empty.
+    pub fn in_memory() -> Self {
+        Self { dir: None }
+    }
+
+    /// Keep records in `dir`.
+    pub fn open(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(dir.into()),
+        }
+    }
+
+    /// Keep records in the data directory, under `acp/sessions`.
+    pub fn open_default() -> Self {
+        match wonopcode_util::path::data_dir() {
+            Some(dir) => Self::open(dir.join("acp").join("sessions")),
+            None => {
+                warn!("No data directory; ACP sessions will not survive a restart");
+                Self::in_memory()
+            }
+        }
+    }
+
+    /// The file of a session, unless its ID could escape the directory.
+    fn path(dir: &Path, id: &str) -> Option<PathBuf> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        valid.then(|| dir.join(format!("{id}.json")))
+    }
+
+    /// Save a record, replacing the previous one.
+    pub fn save(&self, record: &SessionRecord) {
+        let Some(path) = self.dir.as_deref().and_then(|d| Self::path(d, &record.id)) else {
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| {
+                let data = serde_json::to_vec_pretty(record)?;
+                let tmp = path.with_extension("json.tmp");
+                std::fs::write(&tmp, data)?;
+                std::fs::rename(&tmp, &path)
+            });
+        if let Err(e) = result {
+            warn!(session = %record.id, error = %e, "Failed to save ACP session");
+        }
+    }
+
+    /// Load the record of a session, if one was saved.
+    pub fn load(&self, id: &str) -> Option<SessionRecord> {
+        let path = self.dir.as_deref().and_then(|d| Self::path(d, id))?;
+        let data = std::fs::read(&path).ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Skipping unreadable ACP session");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> SessionRecord {
+        SessionRecord {
+            id: "ses_1".to_string(),
+            cwd: "/tmp/project".to_string(),
+            created_at: chrono::Utc::now(),
+            model: ModelRef::parse("anthropic/claude-sonnet-4-5"),
+            mode_id: Some("plan".to_string()),
+            cli_session_id: Some("cli-123".to_string()),
+            entries: vec![
+                Entry::User {
+                    text: "read main.rs".to_string(),
+                },
+                Entry::Agent {
+                    text: "Reading it.".to_string(),
+                },
+                Entry::ToolCall {
+                    id: "call_1".to_string(),
+                    name: "read".to_string(),
+                    input: serde_json::json!({"filePath": "src/main.rs"}),
+                    status: ToolStatus::Completed,
+                    output: Some("fn main() {}".to_string()),
+                    content: Vec::new(),
+                },
+                Entry::Thought {
+                    text: "It needs a greeting.".to_string(),
+                },
+                Entry::ToolCall {
+                    id: "call_2".to_string(),
+                    name: "edit".to_string(),
+                    input: serde_json::json!({"filePath": "src/main.rs"}),
+                    status: ToolStatus::Completed,
+                    output: Some("Edited src/main.rs".to_string()),
+                    content: vec![ToolCallContent::Diff {
+                        path: "/tmp/project/src/main.rs".to_string(),
+                        old_text: Some("fn main() {}".to_string()),
+                        new_text: "fn main() { hello() }".to_string(),
+                    }],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_records_survive_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        SessionStore::open(dir.path()).save(&record());
+
+        let loaded = SessionStore::open(dir.path()).load("ses_1").unwrap();
+        assert_eq!(loaded.cwd, "/tmp/project");
+        assert_eq!(loaded.cli_session_id.as_deref(), Some("cli-123"));
+        assert_eq!(loaded.mode_id.as_deref(), Some("plan"));
+        assert_eq!(loaded.entries, record().entries);
+        assert_eq!(
+            loaded.history(),
+            vec![
+                ("user".to_string(), "read main.rs".to_string()),
+                ("assistant".to_string(), "Reading it.".to_string()),
+            ]
+        );
+
+        assert!(SessionStore::open(dir.path()).load("ses_2").is_none());
+        assert!(SessionStore::in_memory().load("ses_1").is_none());
+    }
+
+    #[test]
+    fn test_session_ids_cannot_escape_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::open(dir.path().join("sessions"));
+        let mut escaping = record();
+        escaping.id = "../ses_1".to_string();
+        store.save(&escaping);
+
+        assert!(!dir.path().join("ses_1.json").exists());
+        assert!(store.load("../ses_1").is_none());
+    }
+
+    #[test]
+    fn test_replay_shows_messages_thoughts_and_tool_calls() {
+        let updates = replay(&record().entries);
+        assert_eq!(updates.len(), 7);
+        assert!(
+            matches!(&updates[0], SessionUpdate::UserMessageChunk { content } if content.text == "read main.rs")
+        );
+        assert!(matches!(
+            &updates[1],
+            SessionUpdate::AgentMessageChunk { .. }
+        ));
+        match &updates[2] {
+            SessionUpdate::ToolCall {
+                tool_call_id,
+                kind,
+                status,
+                locations,
+                ..
+            } => {
+                assert_eq!(tool_call_id, "call_1");
+                assert_eq!(*kind, ToolKind::Read);
+                assert_eq!(*status, ToolStatus::Completed);
+                assert_eq!(locations[0].path, "src/main.rs");
+            }
+            other => panic!("expected a tool call, got {other:?}"),
+        }
+        assert!(matches!(
+            &updates[3],
+            SessionUpdate::ToolCallUpdate {
+                content: Some(_),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &updates[4],
+            SessionUpdate::AgentThoughtChunk { .. }
+        ));
+
+        // Edits replay as their diffs
+        match &updates[6] {
+            SessionUpdate::ToolCallUpdate {
+                content: Some(content),
+                ..
+            } => assert!(matches!(&content[0], ToolCallContent::Diff { .. })),
+            other => panic!("expected a tool call update, got {other:?}"),
+        }
+    }
+}
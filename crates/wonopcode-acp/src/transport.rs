@@ -6,10 +6,18 @@
 use crate::types::{JsonRpcError, JsonRpcId, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tracing::{debug, error, info, warn};
 
+/// How long to wait for the client to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the user has to answer a permission request, as long as the
+/// permission manager waits.
+const PERMISSION_TIMEOUT: Duration = Duration::from_secs(300);
+
 /// Transport error.
 #[derive(Debug, thiserror::Error)]
 pub enum TransportError {
@@ -217,6 +225,17 @@ impl StdioTransport {
         &self,
         method: &str,
         params: serde_json::Value,
+    ) -> Result<serde_json::Value, TransportError> {
+        self.send_request_with_timeout(method, params, REQUEST_TIMEOUT)
+            .await
+    }
+
+    /// Send a request and wait up to `timeout` for the response.
+    pub async fn send_request_with_timeout(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+        timeout: Duration,
     ) -> Result<serde_json::Value, TransportError> {
         let id = {
             let mut next_id = self.next_id.lock().await;
@@ -246,7 +265,7 @@ impl StdioTransport {
             .map_err(|_| TransportError::ChannelClosed)?;
 
         // Wait for response with timeout
-        match tokio::time::timeout(std::time::Duration::from_secs(30), rx).await {
+        match tokio::time::timeout(timeout, rx).await {
             Ok(Ok(result)) => {
                 result.map_err(|e| TransportError::Io(std::io::Error::other(e.message)))
             }
@@ -262,6 +281,7 @@ impl StdioTransport {
 }
 
 /// Connection wrapping the transport for the agent.
+#[derive(Clone)]
 pub struct Connection {
     transport: Arc<StdioTransport>,
 }
@@ -288,11 +308,39 @@ impl Connection {
     ) -> Result<crate::types::PermissionResponse, TransportError> {
         let result = self
             .transport
-            .send_request("permission/request", serde_json::to_value(params)?)
+            .send_request_with_timeout(
+                "session/request_permission",
+                serde_json::to_value(params)?,
+                PERMISSION_TIMEOUT,
+            )
+            .await?;
+
+        serde_json::from_value(result).map_err(|_| TransportError::InvalidResponse)
+    }
+
+    /// Read a text file through the client.
+    pub async fn read_text_file(
+        &self,
+        params: crate::types::ReadTextFileRequest,
+    ) -> Result<crate::types::ReadTextFileResponse, TransportError> {
+        let result = self
+            .transport
+            .send_request("fs/read_text_file", serde_json::to_value(params)?)
             .await?;
 
         serde_json::from_value(result).map_err(|_| TransportError::InvalidResponse)
     }
+
+    /// Write a text file through the client.
+    pub async fn write_text_file(
+        &self,
+        params: crate::types::WriteTextFileRequest,
+    ) -> Result<(), TransportError> {
+        self.transport
+            .send_request("fs/write_text_file", serde_json::to_value(params)?)
+            .await?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
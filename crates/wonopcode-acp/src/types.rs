@@ -102,10 +102,22 @@ pub struct InitializeRequest {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ClientCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fs: Option<FileSystemCapability>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub _meta: Option<HashMap<String, serde_json::Value>>,
 }
 
+/// File operations the client performs for the agent.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSystemCapability {
+    #[serde(default)]
+    pub read_text_file: bool,
+    #[serde(default)]
+    pub write_text_file: bool,
+}
+
 /// Initialize response from agent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -430,10 +442,14 @@ pub enum SessionUpdate {
     AvailableCommandsUpdate {
         available_commands: Vec<CommandInfo>,
     },
+
+    /// The session switched modes on its own.
+    #[serde(rename_all = "camelCase")]
+    CurrentModeUpdate { current_mode_id: String },
 }
 
 /// Text content.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TextContent {
     #[serde(rename = "type")]
     pub content_type: String,
@@ -504,6 +520,20 @@ impl Location {
                     vec![]
                 }
             }
+            "multiedit" => {
+                let mut locations: Vec<Location> = Vec::new();
+                let edits = input.get("edits").and_then(|v| v.as_array());
+                for edit in edits.into_iter().flatten() {
+                    if let Some(path) = edit.get("filePath").and_then(|v| v.as_str()) {
+                        if !locations.iter().any(|l| l.path == path) {
+                            locations.push(Location {
+                                path: path.to_string(),
+                            });
+                        }
+                    }
+                }
+                locations
+            }
             "glob" | "grep" | "list" => {
                 if let Some(path) = input.get("path").and_then(|v| v.as_str()) {
                     vec![Location {
@@ -519,7 +549,7 @@ impl Location {
 }
 
 /// Tool call content.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ToolCallContent {
     Content {
@@ -527,8 +557,9 @@ pub enum ToolCallContent {
     },
     Diff {
         path: String,
+        /// None for a file the tool created.
         #[serde(rename = "oldText")]
-        old_text: String,
+        old_text: Option<String>,
         #[serde(rename = "newText")]
         new_text: String,
     },
@@ -556,6 +587,16 @@ pub enum PlanStatus {
 pub struct CommandInfo {
     pub name: String,
     pub description: String,
+    /// Set when the command takes input after its name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<CommandInput>,
+}
+
+/// Input a command takes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandInput {
+    /// Shown in place of the input until the user types it.
+    pub hint: String,
 }
 
 // ============================================================================
@@ -599,6 +640,7 @@ pub enum PermissionKind {
     AllowOnce,
     AllowAlways,
     RejectOnce,
+    RejectAlways,
 }
 
 /// Permission response.
@@ -616,6 +658,37 @@ pub struct PermissionOutcome {
     pub option_id: Option<String>,
 }
 
+// ============================================================================
+// File System (requests from agent to client)
+// ============================================================================
+
+/// Read a text file, as the client's buffer holds it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadTextFileRequest {
+    pub session_id: String,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+/// Content of a text file read by the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadTextFileResponse {
+    pub content: String,
+}
+
+/// Write a text file through the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteTextFileRequest {
+    pub session_id: String,
+    pub path: String,
+    pub content: String,
+}
+
 // ============================================================================
 // Authenticate
 // ============================================================================
@@ -699,6 +772,35 @@ mod tests {
         let json = serde_json::to_string(&update).unwrap();
         assert!(json.contains("agent_message_chunk"));
         assert!(json.contains("Hello, world!"));
+
+        let update = SessionUpdate::CurrentModeUpdate {
+            current_mode_id: "plan".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&update).unwrap(),
+            r#"{"sessionUpdate":"current_mode_update","currentModeId":"plan"}"#
+        );
+    }
+
+    #[test]
+    fn client_fs_capability_deserializes() {
+        let request: InitializeRequest = serde_json::from_str(
+            r#"{"protocolVersion":1,"clientCapabilities":{"fs":{"readTextFile":true,"writeTextFile":false}}}"#,
+        )
+        .unwrap();
+        let fs = request.client_capabilities.unwrap().fs.unwrap();
+        assert!(fs.read_text_file);
+        assert!(!fs.write_text_file);
+
+        let request = WriteTextFileRequest {
+            session_id: "ses_1".to_string(),
+            path: "/tmp/a.rs".to_string(),
+            content: "fn main() {}".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_string(&request).unwrap(),
+            r#"{"sessionId":"ses_1","path":"/tmp/a.rs","content":"fn main() {}"}"#
+        );
     }
 
     // === JsonRpcId tests ===
@@ -858,6 +960,18 @@ mod tests {
         assert_eq!(locations[0].path, "/tmp/file.rs");
     }
 
+    #[test]
+    fn location_from_multiedit_tool() {
+        let input = serde_json::json!({"edits": [
+            {"filePath": "/tmp/a.rs", "oldString": "a", "newString": "b"},
+            {"filePath": "/tmp/b.rs", "oldString": "a", "newString": "b"},
+            {"filePath": "/tmp/a.rs", "oldString": "c", "newString": "d"}
+        ]});
+        let locations = Location::from_tool_input("multiedit", &input);
+        let paths: Vec<&str> = locations.iter().map(|l| l.path.as_str()).collect();
+        assert_eq!(paths, ["/tmp/a.rs", "/tmp/b.rs"]);
+    }
+
     #[test]
     fn location_from_glob_tool() {
         let input = serde_json::json!({"path": "/home/user/project", "pattern": "*.rs"});
@@ -1066,13 +1180,21 @@ mod tests {
     fn tool_call_content_diff_serializes() {
         let content = ToolCallContent::Diff {
             path: "/tmp/file.rs".to_string(),
-            old_text: "old".to_string(),
+            old_text: Some("old".to_string()),
             new_text: "new".to_string(),
         };
         let json = serde_json::to_string(&content).unwrap();
         assert!(json.contains("\"type\":\"diff\""));
         assert!(json.contains("\"oldText\":\"old\""));
         assert!(json.contains("\"newText\":\"new\""));
+
+        let created = ToolCallContent::Diff {
+            path: "/tmp/new.rs".to_string(),
+            old_text: None,
+            new_text: "new".to_string(),
+        };
+        let json = serde_json::to_string(&created).unwrap();
+        assert!(json.contains("\"oldText\":null"));
     }
 
     // === McpServer tests ===
@@ -8,6 +8,10 @@
 //! - `compaction` - Internal agent for session summarization
 //! - `title` - Internal agent for generating session titles
 //! - `summary` - Internal agent for generating summaries
+//!
+//! Custom agents come from the `agent` config key and from agent files (see
+//! [`AgentFile`]) in the `agents/` directory of the global config directory
+//! and of the project's `.wonopcode/` directory.
 // @ace:design DES-T90R4U-16W8
 // @ace:implements COMP-T90R4U-17D4
 
@@ -15,8 +19,9 @@ use crate::config::{
     AgentConfig, AgentMode as ConfigAgentMode, AgentSandboxConfig, Config, Permission,
     PermissionOrMap,
 };
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Agent information.
 #[derive(Debug, Clone)]
@@ -652,54 +657,207 @@ impl AgentRegistry {
 
     /// Load custom agents from a directory.
     ///
-    /// Looks for `.md` files in the directory and parses them as agent definitions.
+    /// Looks for `.md` and `.toml` agent files in the directory. Invalid
+    /// files are logged and skipped.
     pub async fn load_custom_agents(&mut self, dir: &Path) -> std::io::Result<()> {
         if !dir.exists() {
             return Ok(());
         }
 
-        let mut entries = tokio::fs::read_dir(dir).await?;
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            if path.extension().is_some_and(|e| e == "md") {
-                if let Ok(agent) = Self::parse_agent_file(&path).await {
-                    self.agents.insert(agent.name.clone(), agent);
-                }
-            }
+        for file in AgentFile::load_dir(dir) {
+            let mut agent = Agent {
+                name: file.name.clone(),
+                description: None,
+                mode: AgentMode::All,
+                native: false,
+                hidden: false,
+                is_default: false,
+                temperature: None,
+                top_p: None,
+                color: None,
+                permission: AgentPermission::default(),
+                model: None,
+                prompt: None,
+                tools: HashMap::new(),
+                max_steps: None,
+                sandbox: None,
+            };
+            Self::apply_config_to_agent(&mut agent, &file.config, &HashMap::new());
+            self.agents.insert(file.name, agent);
         }
 
         Ok(())
     }
+}
 
-    /// Parse an agent definition from a markdown file.
-    async fn parse_agent_file(path: &Path) -> std::io::Result<Agent> {
-        let content = tokio::fs::read_to_string(path).await?;
-        let name = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("custom")
-            .to_string();
+/// Agent directories, relative to a config directory.
+pub const AGENT_DIRS: &[&str] = &["agents", "agent"];
 
-        // Simple parsing: use file content as prompt
-        // In a full implementation, we'd parse frontmatter for settings
-        Ok(Agent {
+/// Extensions of agent definition files.
+const AGENT_FILE_EXTENSIONS: &[&str] = &["md", "toml"];
+
+/// Agent directories under a config directory, such as `~/.config/wonopcode`
+/// or a project's `.wonopcode`.
+pub fn agent_dirs(config_dir: &Path) -> Vec<PathBuf> {
+    AGENT_DIRS
+        .iter()
+        .map(|name| config_dir.join(name))
+        .collect()
+}
+
+/// Whether `path` looks like an agent definition file.
+pub fn is_agent_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| AGENT_FILE_EXTENSIONS.contains(&e))
+}
+
+/// Agent settings in a file's frontmatter or TOML table.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AgentFileHeader {
+    /// Agent name; defaults to the file name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(flatten)]
+    config: AgentConfig,
+}
+
+/// An agent defined in its own file.
+///
+/// Markdown files hold the agent settings in YAML frontmatter, and the body
+/// is the prompt:
+///
+/// ```markdown
+/// ---
+/// description: Reviews code for bugs
+/// mode: subagent
+/// tools:
+///   write: false
+/// ---
+///
+/// You are a code reviewer...
+/// ```
+///
+/// TOML files hold the same settings, with the prompt in `prompt`.
+#[derive(Debug, Clone)]
+pub struct AgentFile {
+    /// Agent name.
+    pub name: String,
+    /// Agent settings, including the prompt.
+    pub config: AgentConfig,
+}
+
+impl AgentFile {
+    /// Parse an agent file's content. `path` picks the format and default name.
+    pub fn parse(path: &Path, content: &str) -> Result<Self, String> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let header = match extension {
+            "md" => {
+                let (mut header, body) = parse_markdown_agent(content)?;
+                if !body.is_empty() {
+                    header.config.prompt = Some(body);
+                }
+                header
+            }
+            "toml" => toml::from_str::<AgentFileHeader>(content)
+                .map_err(|e| format!("Invalid agent TOML: {e}"))?,
+            _ => return Err(format!("Unsupported agent file type: .{extension}")),
+        };
+
+        let name = match header.name {
+            Some(name) => name,
+            None => path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .ok_or("Agent file has no name")?
+                .to_string(),
+        };
+        validate_agent_name(&name)?;
+
+        Ok(Self {
             name,
-            description: None,
-            mode: AgentMode::All,
-            native: false,
-            hidden: false,
-            is_default: false,
-            temperature: None,
-            top_p: None,
-            color: None,
-            permission: AgentPermission::default(),
-            model: None,
-            prompt: Some(content),
-            tools: HashMap::new(),
-            max_steps: None,
-            sandbox: None,
+            config: header.config,
         })
     }
+
+    /// Read and parse an agent file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read file: {e}"))?;
+        Self::parse(path, &content)
+    }
+
+    /// Agent files in `dir`, sorted by file name.
+    pub fn paths_in(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && is_agent_file(path))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    /// Load every agent file in `dir`. Invalid files are logged and skipped.
+    pub fn load_dir(dir: &Path) -> Vec<Self> {
+        Self::paths_in(dir)
+            .into_iter()
+            .filter_map(|path| match Self::load(&path) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping invalid agent file");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Render the agent as a markdown agent file.
+    pub fn to_markdown(&self) -> String {
+        let mut config = self.config.clone();
+        let prompt = config.prompt.take().unwrap_or_default();
+        let header = AgentFileHeader {
+            name: Some(self.name.clone()),
+            config,
+        };
+        let yaml = serde_yaml::to_string(&header).unwrap_or_default();
+        format!("---\n{yaml}---\n\n{}\n", prompt.trim())
+    }
+}
+
+/// Split a markdown agent file into its frontmatter and body.
+fn parse_markdown_agent(content: &str) -> Result<(AgentFileHeader, String), String> {
+    let content = content.trim();
+    let Some(rest) = content.strip_prefix("---") else {
+        return Ok((AgentFileHeader::default(), content.to_string()));
+    };
+
+    let end_idx = rest
+        .find("\n---")
+        .ok_or("Missing closing frontmatter delimiter")?;
+    let frontmatter = rest[..end_idx].trim();
+    let body = rest[end_idx + 4..].trim();
+
+    let header = if frontmatter.is_empty() {
+        AgentFileHeader::default()
+    } else {
+        serde_yaml::from_str(frontmatter).map_err(|e| format!("Invalid frontmatter YAML: {e}"))?
+    };
+    Ok((header, body.to_string()))
+}
+
+/// Check that an agent name can be used as a file name.
+pub fn validate_agent_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || name.chars().any(char::is_whitespace)
+    {
+        return Err(format!("Invalid agent name '{name}'"));
+    }
+    Ok(())
 }
 
 // Built-in agent prompts
@@ -1000,6 +1158,7 @@ mod tests {
                     external_directory: Some(Permission::Deny),
                 }),
                 sandbox: None,
+                compaction: None,
                 disable: None,
             },
         );
@@ -1154,6 +1313,7 @@ mod tests {
                 webfetch: Some(Permission::Deny),
                 external_directory: Some(Permission::Deny),
                 allow_all_in_sandbox: None,
+                rules: vec![],
             }),
             ..Default::default()
         };
@@ -1198,4 +1358,83 @@ mod tests {
         assert_eq!(sandbox.workspace_writable, Some(false)); // preserved from original
         assert_eq!(sandbox.network, Some("none".to_string()));
     }
+
+    #[test]
+    fn test_parse_markdown_agent_file() {
+        let content = "---\ndescription: Reviews code\nmode: subagent\ntemperature: 0.2\ntools:\n  write: false\n---\n\nYou review code.\n";
+        let file = AgentFile::parse(Path::new("agents/reviewer.md"), content).unwrap();
+        assert_eq!(file.name, "reviewer");
+        assert_eq!(file.config.description.as_deref(), Some("Reviews code"));
+        assert_eq!(file.config.mode, Some(ConfigAgentMode::Subagent));
+        assert_eq!(file.config.temperature, Some(0.2));
+        assert!(!file.config.tools.as_ref().unwrap()["write"]);
+        assert_eq!(file.config.prompt.as_deref(), Some("You review code."));
+
+        // Without frontmatter the whole file is the prompt
+        let file = AgentFile::parse(Path::new("helper.md"), "Be helpful.").unwrap();
+        assert_eq!(file.config.prompt.as_deref(), Some("Be helpful."));
+
+        assert!(AgentFile::parse(Path::new("bad.md"), "---\nmode: [\n---\nbody").is_err());
+        assert!(AgentFile::parse(Path::new("bad.md"), "---\nname: a/b\n---\nbody").is_err());
+    }
+
+    #[test]
+    fn test_parse_toml_agent_file() {
+        let content = r#"
+name = "docs"
+description = "Writes documentation"
+model = "anthropic/claude-haiku-4-5"
+max_steps = 5
+prompt = "You write docs."
+
+[permission]
+edit = "ask"
+"#;
+        let file = AgentFile::parse(Path::new("writer.toml"), content).unwrap();
+        assert_eq!(file.name, "docs");
+        assert_eq!(file.config.max_steps, Some(5));
+        assert_eq!(file.config.prompt.as_deref(), Some("You write docs."));
+        assert_eq!(
+            file.config.permission.as_ref().unwrap().edit,
+            Some(Permission::Ask)
+        );
+        assert!(AgentFile::parse(Path::new("writer.toml"), "max_steps = \"x\"").is_err());
+        assert!(AgentFile::parse(Path::new("writer.json"), "{}").is_err());
+    }
+
+    #[test]
+    fn test_agent_file_markdown_round_trip() {
+        let file = AgentFile::parse(
+            Path::new("reviewer.md"),
+            "---\ndescription: Reviews code\nmax_steps: 3\n---\nReview carefully.",
+        )
+        .unwrap();
+        let rendered = file.to_markdown();
+        assert!(rendered.starts_with("---\nname: reviewer\n"));
+
+        let parsed = AgentFile::parse(Path::new("other.md"), &rendered).unwrap();
+        assert_eq!(parsed.name, "reviewer");
+        assert_eq!(parsed.config.max_steps, Some(3));
+        assert_eq!(parsed.config.prompt.as_deref(), Some("Review carefully."));
+    }
+
+    #[tokio::test]
+    async fn test_load_custom_agents_from_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("reviewer.md"),
+            "---\nmode: subagent\n---\nReview.",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.toml"), "mode = 3").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let mut registry = AgentRegistry::new(&Config::default());
+        registry.load_custom_agents(dir.path()).await.unwrap();
+        let reviewer = registry.get("reviewer").unwrap();
+        assert_eq!(reviewer.mode, AgentMode::Subagent);
+        assert_eq!(reviewer.prompt.as_deref(), Some("Review."));
+        assert!(registry.get("broken").is_none());
+        assert!(registry.get("notes").is_none());
+    }
 }
@@ -0,0 +1,201 @@
+//! Permission audit log.
+//!
+//! Every permission check is recorded under
+//! `audit/permission/<project>/<entry>`: what was checked, whether it was
+//! allowed, the rule that decided and whether the user was asked. This makes
+//! it possible to review what an unattended run was actually allowed to do.
+
+use crate::error::CoreResult;
+use crate::permission::PermissionRule;
+use serde::{Deserialize, Serialize};
+use wonopcode_storage::json::JsonStorage;
+use wonopcode_storage::Storage;
+use wonopcode_util::Identifier;
+
+/// A recorded permission check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionAuditEntry {
+    /// Entry ID (ascending, so IDs sort chronologically).
+    pub id: String,
+    /// When the check happened (unix millis).
+    pub time: i64,
+    /// Session the check was made for.
+    pub session_id: String,
+    /// Tool name.
+    pub tool: String,
+    /// Action being performed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    /// Path involved, for file operations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// What the tool was about to do, as shown to the user.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Whether the check was allowed.
+    pub allowed: bool,
+    /// Whether the user was asked.
+    pub prompted: bool,
+    /// The rule that decided, or that asked for a prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<PermissionRule>,
+}
+
+impl PermissionAuditEntry {
+    /// Create an entry for a check happening now; the outcome starts out
+    /// as denied without a prompt.
+    pub fn new(session_id: &str, tool: &str, action: Option<&str>, path: Option<&str>) -> Self {
+        Self {
+            id: Identifier::audit(),
+            time: chrono::Utc::now().timestamp_millis(),
+            session_id: session_id.to_string(),
+            tool: tool.to_string(),
+            action: action.map(str::to_string),
+            path: path.map(str::to_string),
+            description: String::new(),
+            allowed: false,
+            prompted: false,
+            rule: None,
+        }
+    }
+
+    /// How the decision was made: by a rule, by the user or by default.
+    pub fn source(&self) -> String {
+        match (&self.rule, self.prompted) {
+            (_, true) => "user".to_string(),
+            (Some(rule), false) => format!("rule: {rule}"),
+            (None, false) => "no rule".to_string(),
+        }
+    }
+}
+
+/// Which entries to list.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionAuditFilter {
+    /// Only entries of this session.
+    pub session_id: Option<String>,
+    /// Only denied checks.
+    pub denied_only: bool,
+    /// Only the most recent entries.
+    pub limit: Option<usize>,
+}
+
+impl PermissionAuditFilter {
+    fn matches(&self, entry: &PermissionAuditEntry) -> bool {
+        let other_session = self
+            .session_id
+            .as_deref()
+            .is_some_and(|id| entry.session_id != id);
+        !other_session && (!self.denied_only || !entry.allowed)
+    }
+}
+
+/// Permission audit log of a project.
+#[derive(Clone)]
+pub struct PermissionAudit {
+    storage: JsonStorage,
+    project_id: String,
+}
+
+impl PermissionAudit {
+    /// Create the audit log of a project.
+    pub fn new(storage: JsonStorage, project_id: impl Into<String>) -> Self {
+        Self {
+            storage,
+            project_id: project_id.into(),
+        }
+    }
+
+    /// Record a permission check.
+    pub async fn record(&self, entry: &PermissionAuditEntry) -> CoreResult<()> {
+        self.storage
+            .write(&["audit", "permission", &self.project_id, &entry.id], entry)
+            .await?;
+        Ok(())
+    }
+
+    /// List recorded checks, oldest first.
+    pub async fn list(
+        &self,
+        filter: &PermissionAuditFilter,
+    ) -> CoreResult<Vec<PermissionAuditEntry>> {
+        let mut keys = self
+            .storage
+            .list(&["audit", "permission", &self.project_id])
+            .await?;
+        // Newest first, so a limit keeps the most recent entries
+        keys.sort_by(|a, b| b.cmp(a));
+
+        let mut entries = Vec::new();
+        for key in keys {
+            if filter.limit.is_some_and(|limit| entries.len() >= limit) {
+                break;
+            }
+            let key_refs: Vec<&str> = key.iter().map(String::as_str).collect();
+            if let Some(entry) = self.storage.read::<PermissionAuditEntry>(&key_refs).await? {
+                if filter.matches(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+        entries.reverse();
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permission::Decision;
+
+    #[tokio::test]
+    async fn test_record_and_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let audit = PermissionAudit::new(JsonStorage::new(dir.path()), "proj_1");
+
+        let mut read = PermissionAuditEntry::new("ses_1", "read", Some("read"), Some("src/a.rs"));
+        read.allowed = true;
+        read.rule = Some(PermissionRule::allow("read"));
+        audit.record(&read).await.unwrap();
+        // IDs only sort reliably across milliseconds
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+
+        let mut bash = PermissionAuditEntry::new("ses_2", "bash", Some("execute"), None);
+        bash.prompted = true;
+        audit.record(&bash).await.unwrap();
+
+        let entries = audit.list(&PermissionAuditFilter::default()).await.unwrap();
+        let tools: Vec<_> = entries.iter().map(|e| e.tool.as_str()).collect();
+        assert_eq!(tools, vec!["read", "bash"]);
+        assert_eq!(entries[0].rule.as_ref().unwrap().decision, Decision::Allow);
+        assert_eq!(entries[0].source(), "rule: allow read");
+        assert_eq!(entries[1].source(), "user");
+
+        let denied = PermissionAuditFilter {
+            denied_only: true,
+            ..Default::default()
+        };
+        assert_eq!(audit.list(&denied).await.unwrap().len(), 1);
+
+        let session = PermissionAuditFilter {
+            session_id: Some("ses_1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(audit.list(&session).await.unwrap()[0].tool, "read");
+
+        // A limit keeps the most recent entries
+        let last = PermissionAuditFilter {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(audit.list(&last).await.unwrap()[0].tool, "bash");
+
+        // Other projects have their own log
+        let other = PermissionAudit::new(JsonStorage::new(dir.path()), "proj_2");
+        assert!(other
+            .list(&PermissionAuditFilter::default())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,463 @@
+//! Batch pipelines.
+//!
+//! A batch file declares steps to run one after another without anyone
+//! watching, e.g. "update deps, run tests, fix failures, open PR":
+//!
+//! ```yaml
+//! name: deps
+//! model: anthropic/claude-sonnet-4-5
+//! steps:
+//!   - name: update
+//!     prompt: Update the dependencies to their latest compatible versions
+//!   - name: test
+//!     run: cargo test
+//!     continue_on_error: true
+//!   - name: fix
+//!     prompt: |
+//!       {{#if steps.test.status == failed}}The tests failed:
+//!       {{steps.test.output}}
+//!       Fix them.{{else}}The tests pass; nothing to fix.{{/if}}
+//!   - command: pr-create
+//! ```
+//!
+//! Each step is a `prompt`, a custom `command` (sent as `/name args`) or a
+//! shell command to `run`. `model`, `agent`, `cwd` and `continue_on_error`
+//! can be set for the whole batch and overridden per step. `cwd` is relative
+//! to the batch file's directory, a step's to the batch's. Prompts and shell
+//! commands are templates (see [`crate::prompt_template`]) that can use the
+//! `output`, `status` and `exit_code` of earlier steps as
+//! `steps.NAME.output` and so on.
+//!
+//! A step that fails stops the batch, unless it may fail; the remaining
+//! steps are skipped. The [`BatchReport`] records how each step went.
+
+use crate::prompt_template::PromptVars;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Error in a batch file.
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    #[error("Failed to read {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Invalid batch file: {0}")]
+    Parse(String),
+
+    #[error("The batch has no steps")]
+    NoSteps,
+
+    #[error("Step {index} needs exactly one of prompt, command or run")]
+    MissingAction { index: usize },
+
+    #[error("Two steps are named '{0}'")]
+    DuplicateName(String),
+}
+
+/// A batch file as written.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchFile {
+    /// Name of the batch, for the report; the file name if not set.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Model for the steps (provider/model).
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Agent for the steps.
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Working directory, relative to the batch file.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Whether the steps may fail without stopping the batch.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// The steps, in order.
+    #[serde(default)]
+    pub steps: Vec<BatchStepConfig>,
+}
+
+/// A step as written.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchStepConfig {
+    /// Step name, for the report and templates; `step-N` if not set.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Prompt to send.
+    #[serde(default)]
+    pub prompt: Option<String>,
+    /// Custom command to send, e.g. "review src/".
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Shell command to run.
+    #[serde(default)]
+    pub run: Option<String>,
+    /// Model override (provider/model).
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Agent override.
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Working directory, relative to the batch's.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Whether this step may fail without stopping the batch.
+    #[serde(default)]
+    pub continue_on_error: Option<bool>,
+}
+
+/// What a step does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepAction {
+    /// Send a prompt, or a custom command as `/name args`.
+    Prompt(String),
+    /// Run a shell command.
+    Shell(String),
+}
+
+impl StepAction {
+    /// The kind of step, as reported.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StepAction::Prompt(_) => "prompt",
+            StepAction::Shell(_) => "shell",
+        }
+    }
+}
+
+/// A step with the batch's settings applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchStep {
+    /// Step name, unique in the batch.
+    pub name: String,
+    /// What the step does.
+    pub action: StepAction,
+    /// Model to run the step with (provider/model), if not the default.
+    pub model: Option<String>,
+    /// Agent to run the step with, if not the default.
+    pub agent: Option<String>,
+    /// Directory the step runs in.
+    pub cwd: PathBuf,
+    /// Whether the batch goes on if the step fails.
+    pub continue_on_error: bool,
+}
+
+/// A valid batch, ready to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch {
+    /// Name of the batch.
+    pub name: String,
+    /// The steps, in order.
+    pub steps: Vec<BatchStep>,
+}
+
+impl Batch {
+    /// Load a batch file.
+    pub fn load(path: &Path) -> Result<Self, BatchError> {
+        let yaml = std::fs::read_to_string(path).map_err(|source| BatchError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "batch".to_string());
+        Self::parse(&yaml, &name, &dir)
+    }
+
+    /// Parse a batch file's YAML. `name` is used if the batch has none, and
+    /// directories are relative to `dir`.
+    pub fn parse(yaml: &str, name: &str, dir: &Path) -> Result<Self, BatchError> {
+        let file: BatchFile =
+            serde_yaml::from_str(yaml).map_err(|e| BatchError::Parse(e.to_string()))?;
+        Self::from_file(file, name, dir)
+    }
+
+    /// Apply the batch's settings to its steps.
+    pub fn from_file(file: BatchFile, name: &str, dir: &Path) -> Result<Self, BatchError> {
+        if file.steps.is_empty() {
+            return Err(BatchError::NoSteps);
+        }
+        let cwd = match &file.cwd {
+            Some(cwd) => dir.join(cwd),
+            None => dir.to_path_buf(),
+        };
+
+        let mut names = HashSet::new();
+        let mut steps = Vec::with_capacity(file.steps.len());
+        for (i, step) in file.steps.into_iter().enumerate() {
+            let index = i + 1;
+            let action = match (&step.prompt, &step.command, &step.run) {
+                (Some(prompt), None, None) if !prompt.trim().is_empty() => {
+                    StepAction::Prompt(prompt.clone())
+                }
+                (None, Some(command), None) if !command.trim().is_empty() => {
+                    StepAction::Prompt(format!("/{}", command.trim().trim_start_matches('/')))
+                }
+                (None, None, Some(run)) if !run.trim().is_empty() => StepAction::Shell(run.clone()),
+                _ => return Err(BatchError::MissingAction { index }),
+            };
+            let name = step.name.unwrap_or_else(|| format!("step-{index}"));
+            if !names.insert(name.clone()) {
+                return Err(BatchError::DuplicateName(name));
+            }
+            steps.push(BatchStep {
+                name,
+                action,
+                model: step.model.or_else(|| file.model.clone()),
+                agent: step.agent.or_else(|| file.agent.clone()),
+                cwd: match step.cwd {
+                    Some(step_cwd) => cwd.join(step_cwd),
+                    None => cwd.clone(),
+                },
+                continue_on_error: step.continue_on_error.unwrap_or(file.continue_on_error),
+            });
+        }
+
+        Ok(Self {
+            name: file.name.unwrap_or_else(|| name.to_string()),
+            steps,
+        })
+    }
+}
+
+/// How a step went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Succeeded,
+    Failed,
+    /// Not run, as an earlier step failed.
+    Skipped,
+}
+
+impl StepStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StepStatus::Succeeded => "succeeded",
+            StepStatus::Failed => "failed",
+            StepStatus::Skipped => "skipped",
+        }
+    }
+}
+
+/// The outcome of a step.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    /// Step name.
+    pub name: String,
+    /// `prompt` or `shell`.
+    pub kind: String,
+    /// How the step went.
+    pub status: StepStatus,
+    /// Prompt or shell command as run, after templating.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    /// Model the step ran with (provider/model); prompts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Agent the step ran with; prompts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    /// Directory the step ran in.
+    pub cwd: String,
+    /// Start time (ms since epoch); 0 if skipped.
+    pub started_at: i64,
+    /// How long the step took.
+    pub duration_ms: u64,
+    /// Exit code of a shell command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// The response, or a shell command's output.
+    pub output: String,
+    /// Why the step failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl StepReport {
+    /// A step that was not run.
+    pub fn skipped(step: &BatchStep) -> Self {
+        Self {
+            name: step.name.clone(),
+            kind: step.action.kind().to_string(),
+            status: StepStatus::Skipped,
+            input: None,
+            model: None,
+            agent: None,
+            cwd: step.cwd.display().to_string(),
+            started_at: 0,
+            duration_ms: 0,
+            exit_code: None,
+            output: String::new(),
+            error: None,
+        }
+    }
+}
+
+/// The outcome of a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchReport {
+    /// Name of the batch.
+    pub name: String,
+    /// Failed if any step did, even one that was allowed to.
+    pub status: StepStatus,
+    /// Start time (ms since epoch).
+    pub started_at: i64,
+    /// How long the batch took.
+    pub duration_ms: u64,
+    /// Step outcomes, in order.
+    pub steps: Vec<StepReport>,
+}
+
+impl BatchReport {
+    /// An empty report of `batch`, starting now.
+    pub fn new(batch: &Batch) -> Self {
+        Self {
+            name: batch.name.clone(),
+            status: StepStatus::Succeeded,
+            started_at: chrono::Utc::now().timestamp_millis(),
+            duration_ms: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Record a step's outcome.
+    pub fn push(&mut self, step: StepReport) {
+        if step.status == StepStatus::Failed {
+            self.status = StepStatus::Failed;
+        }
+        self.steps.push(step);
+    }
+
+    /// Whether every step succeeded.
+    pub fn succeeded(&self) -> bool {
+        self.status == StepStatus::Succeeded
+    }
+
+    /// Number of steps with `status`.
+    pub fn count(&self, status: StepStatus) -> usize {
+        self.steps.iter().filter(|s| s.status == status).count()
+    }
+
+    /// Add the outcomes of the steps so far to template variables, as
+    /// `steps.NAME.output`, `steps.NAME.status` and `steps.NAME.exit_code`.
+    pub fn add_vars(&self, vars: &mut PromptVars) {
+        for step in &self.steps {
+            let prefix = format!("steps.{}", step.name);
+            vars.set(format!("{prefix}.output"), step.output.trim_end());
+            vars.set(format!("{prefix}.status"), step.status.as_str());
+            if let Some(code) = step.exit_code {
+                vars.set(format!("{prefix}.exit_code"), code.to_string());
+            }
+            if let Some(error) = &step.error {
+                vars.set(format!("{prefix}.error"), error.as_str());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prompt_template::render_prompt;
+
+    const BATCH: &str = r#"
+model: anthropic/claude-sonnet-4-5
+cwd: project
+steps:
+  - name: update
+    prompt: Update the dependencies
+    agent: build
+  - name: test
+    run: cargo test
+    cwd: crates/core
+    continue_on_error: true
+  - prompt: "Fix: {{steps.test.output}}"
+    model: openai/gpt-4o
+  - command: /pr-create --draft
+"#;
+
+    #[test]
+    fn test_parse_batch() {
+        let batch = Batch::parse(BATCH, "deps", Path::new("/work")).unwrap();
+        assert_eq!(batch.name, "deps");
+        assert_eq!(batch.steps.len(), 4);
+
+        let update = &batch.steps[0];
+        assert_eq!(
+            update.action,
+            StepAction::Prompt("Update the dependencies".into())
+        );
+        assert_eq!(update.model.as_deref(), Some("anthropic/claude-sonnet-4-5"));
+        assert_eq!(update.agent.as_deref(), Some("build"));
+        assert_eq!(update.cwd, Path::new("/work/project"));
+        assert!(!update.continue_on_error);
+
+        let test = &batch.steps[1];
+        assert_eq!(test.action, StepAction::Shell("cargo test".into()));
+        assert_eq!(test.cwd, Path::new("/work/project/crates/core"));
+        assert!(test.continue_on_error);
+
+        assert_eq!(batch.steps[2].name, "step-3");
+        assert_eq!(batch.steps[2].model.as_deref(), Some("openai/gpt-4o"));
+        assert_eq!(
+            batch.steps[3].action,
+            StepAction::Prompt("/pr-create --draft".into())
+        );
+    }
+
+    #[test]
+    fn test_invalid_batches() {
+        let dir = Path::new(".");
+        assert!(matches!(
+            Batch::parse("steps: []", "b", dir),
+            Err(BatchError::NoSteps)
+        ));
+        assert!(matches!(
+            Batch::parse("steps:\n  - prompt: a\n    run: b", "b", dir),
+            Err(BatchError::MissingAction { index: 1 })
+        ));
+        assert!(matches!(
+            Batch::parse("steps:\n  - name: a\n    run: x\n  - name: a\n    run: y", "b", dir),
+            Err(BatchError::DuplicateName(name)) if name == "a"
+        ));
+        assert!(matches!(
+            Batch::parse("steps:\n  - promt: typo", "b", dir),
+            Err(BatchError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_report_vars() {
+        let batch = Batch::parse(BATCH, "deps", Path::new("/work")).unwrap();
+        let mut report = BatchReport::new(&batch);
+        report.push(StepReport {
+            status: StepStatus::Failed,
+            exit_code: Some(101),
+            output: "1 test failed\n".to_string(),
+            ..StepReport::skipped(&batch.steps[1])
+        });
+        report.push(StepReport::skipped(&batch.steps[2]));
+        assert!(!report.succeeded());
+        assert_eq!(report.count(StepStatus::Skipped), 1);
+
+        let mut vars = PromptVars::default();
+        report.add_vars(&mut vars);
+        let prompt = render_prompt(
+            "{{#if steps.test.status == failed}}Fix ({{steps.test.exit_code}}): {{steps.test.output}}{{/if}}",
+            &vars,
+        );
+        assert_eq!(prompt, "Fix (101): 1 test failed");
+    }
+}
@@ -0,0 +1,691 @@
+//! Session branching and merging.
+//!
+//! A branch is a session forked from a parent at some message, used to explore
+//! an alternative solution. When the branch wins, [`SessionBranch::merge`]
+//! applies its file changes to the working directory and records a summary in
+//! the parent session.
+//!
+//! File changes are taken from the snapshots the branch took before changing
+//! files, and from the diffs recorded in user message summaries. A file is
+//! only written if it still matches the branch's starting content, so changes
+//! made elsewhere in the meantime (for example by another branch) are
+//! reported as conflicts instead of being overwritten. Files outside the
+//! working directory are never written.
+
+use crate::bus::{Bus, SessionMerged};
+use crate::error::{CoreResult, SessionError};
+use crate::message::{FileDiff, Message, MessagePart, TextPart, UserMessage, UserSummary};
+use crate::revert::SessionRevert;
+use crate::session::{BranchInfo, MessageWithParts, Session, SessionRepository};
+use std::path::{Component, Path};
+use std::sync::Arc;
+use tracing::{info, warn};
+use wonopcode_snapshot::SnapshotStore;
+
+/// Net changes made on a branch.
+#[derive(Debug, Clone, Default)]
+pub struct BranchChanges {
+    /// One diff per file, from its content at the fork to its latest content.
+    pub diffs: Vec<FileDiff>,
+    /// Last assistant response on the branch.
+    pub summary: Option<String>,
+}
+
+/// Outcome of a merge.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeResult {
+    /// Files written to the working directory.
+    pub applied: Vec<String>,
+    /// Files that already had the branch's content.
+    pub unchanged: Vec<String>,
+    /// Files not written because they changed since the fork.
+    pub conflicts: Vec<String>,
+}
+
+/// Session branch operations.
+pub struct SessionBranch {
+    session_repo: Arc<SessionRepository>,
+    bus: Bus,
+    snapshots: Option<Arc<SnapshotStore>>,
+}
+
+impl SessionBranch {
+    /// Create a new session branch handler.
+    pub fn new(session_repo: Arc<SessionRepository>, bus: Bus) -> Self {
+        Self {
+            session_repo,
+            bus,
+            snapshots: None,
+        }
+    }
+
+    /// Take file changes from the snapshots of the branch too.
+    pub fn with_snapshots(mut self, snapshots: Option<Arc<SnapshotStore>>) -> Self {
+        self.snapshots = snapshots;
+        self
+    }
+
+    /// Fork a session into a branch.
+    ///
+    /// Messages before `message_id` (or all messages if `None`) are copied.
+    pub async fn fork(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        message_id: Option<&str>,
+    ) -> CoreResult<Session> {
+        let forked = self
+            .session_repo
+            .fork(project_id, session_id, message_id)
+            .await?;
+        let base_messages = self
+            .session_repo
+            .messages(project_id, &forked.id, None)
+            .await?
+            .len();
+
+        let branch = self
+            .session_repo
+            .update(project_id, &forked.id, |s| {
+                s.branch = Some(BranchInfo {
+                    parent_id: session_id.to_string(),
+                    message_id: message_id.map(str::to_string),
+                    base_messages,
+                    merged_at: None,
+                });
+            })
+            .await?;
+
+        info!(
+            session_id = %session_id,
+            branch_id = %branch.id,
+            base_messages,
+            "Forked session branch"
+        );
+
+        Ok(branch)
+    }
+
+    /// Net file changes and summary of a branch since it was forked.
+    ///
+    /// Messages discarded by a pending revert on the branch are ignored.
+    pub async fn changes(&self, project_id: &str, branch_id: &str) -> CoreResult<BranchChanges> {
+        let session = self.session_repo.get(project_id, branch_id).await?;
+        let info = branch_info(&session)?;
+        let messages = self
+            .session_repo
+            .messages(project_id, branch_id, None)
+            .await?;
+
+        let reverted = session
+            .revert
+            .as_ref()
+            .and_then(|r| messages.iter().position(|m| m.message.id() == r.message_id));
+        let end = reverted.unwrap_or(messages.len());
+        let start = info.base_messages.min(end);
+
+        let mut changes = collect_changes(&messages[start..end]);
+        if let Some(store) = &self.snapshots {
+            let until = reverted.and_then(|i| {
+                chrono::DateTime::from_timestamp_millis(messages[i].message.created_at())
+            });
+            match store
+                .session_changes(branch_id, session.created_at(), until)
+                .await
+            {
+                Ok(files) => {
+                    for file in files {
+                        let name = file.path.to_string_lossy().into_owned();
+                        if changes.diffs.iter().any(|d| d.file == name) {
+                            continue;
+                        }
+                        let (additions, deletions) = file.line_counts();
+                        changes.diffs.push(FileDiff {
+                            file: name,
+                            before: file.original,
+                            after: file.current,
+                            additions,
+                            deletions,
+                        });
+                    }
+                }
+                Err(e) => {
+                    warn!(branch_id = %branch_id, error = %e, "Failed to read branch snapshots")
+                }
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Merge a branch back into its parent.
+    ///
+    /// A pending revert on the parent is applied first, so the merge builds on
+    /// what the parent currently shows. Each changed file is written to
+    /// `directory` unless its current content differs from the content at the
+    /// fork, in which case it is reported as a conflict and left untouched.
+    pub async fn merge(
+        &self,
+        project_id: &str,
+        branch_id: &str,
+        directory: &Path,
+    ) -> CoreResult<MergeResult> {
+        let session = self.session_repo.get(project_id, branch_id).await?;
+        let info = branch_info(&session)?;
+        if info.merged_at.is_some() {
+            return Err(SessionError::AlreadyMerged {
+                id: branch_id.to_string(),
+            }
+            .into());
+        }
+        let parent = self.session_repo.get(project_id, &info.parent_id).await?;
+
+        if parent.revert.is_some() {
+            SessionRevert::new(self.session_repo.clone(), self.bus.clone())
+                .cleanup(project_id, &parent.id)
+                .await?;
+        }
+
+        let changes = self.changes(project_id, branch_id).await?;
+        let mut result = MergeResult::default();
+        let mut applied_diffs = Vec::new();
+        for diff in &changes.diffs {
+            if !is_inside(&diff.file) {
+                warn!(file = %diff.file, "Refusing to apply branch change outside the directory");
+                result.conflicts.push(diff.file.clone());
+                continue;
+            }
+            let path = directory.join(&diff.file);
+            match apply_diff(&path, diff) {
+                Ok(MergeOutcome::Applied) => {
+                    result.applied.push(diff.file.clone());
+                    applied_diffs.push(diff.clone());
+                }
+                Ok(MergeOutcome::Unchanged) => result.unchanged.push(diff.file.clone()),
+                Ok(MergeOutcome::Conflict) => result.conflicts.push(diff.file.clone()),
+                Err(e) => {
+                    warn!(file = %diff.file, error = %e, "Failed to apply branch change");
+                    result.conflicts.push(diff.file.clone());
+                }
+            }
+        }
+
+        self.record_merge(
+            project_id,
+            &parent,
+            &session,
+            &changes,
+            &result,
+            applied_diffs,
+        )
+        .await?;
+
+        self.session_repo
+            .update(project_id, branch_id, |s| {
+                if let Some(branch) = s.branch.as_mut() {
+                    branch.merged_at = Some(chrono::Utc::now().timestamp_millis());
+                }
+            })
+            .await?;
+
+        self.bus
+            .publish(SessionMerged {
+                session_id: branch_id.to_string(),
+                parent_id: parent.id.clone(),
+                applied: result.applied.clone(),
+                conflicts: result.conflicts.clone(),
+            })
+            .await;
+
+        info!(
+            branch_id = %branch_id,
+            parent_id = %parent.id,
+            applied = result.applied.len(),
+            conflicts = result.conflicts.len(),
+            "Merged session branch"
+        );
+
+        Ok(result)
+    }
+
+    /// Add a synthetic message to the parent describing the merge.
+    async fn record_merge(
+        &self,
+        project_id: &str,
+        parent: &Session,
+        branch: &Session,
+        changes: &BranchChanges,
+        result: &MergeResult,
+        diffs: Vec<FileDiff>,
+    ) -> CoreResult<()> {
+        // Reuse the agent and model of the latest prompt on the branch
+        let messages = self
+            .session_repo
+            .messages(project_id, &branch.id, None)
+            .await?;
+        let Some(last_user) = messages.iter().rev().find_map(|m| match &m.message {
+            Message::User(u) => Some(u),
+            Message::Assistant(_) => None,
+        }) else {
+            return Ok(());
+        };
+
+        let mut message = UserMessage::new(&parent.id, &last_user.agent, last_user.model.clone());
+        message.summary = Some(UserSummary {
+            title: Some(format!("Merged {}", branch.title)),
+            body: changes.summary.clone(),
+            diffs,
+        });
+        let mut part = TextPart::new(&parent.id, &message.id, merge_note(branch, changes, result));
+        part.synthetic = Some(true);
+
+        self.session_repo
+            .save_message_with_parts(&Message::User(message), &[MessagePart::Text(part)])
+            .await
+    }
+}
+
+/// Branch info of a session, or an error if it isn't a branch.
+fn branch_info(session: &Session) -> CoreResult<BranchInfo> {
+    session.branch.clone().ok_or_else(|| {
+        SessionError::NotABranch {
+            id: session.id.clone(),
+        }
+        .into()
+    })
+}
+
+/// Combine the diffs recorded on messages into one diff per file.
+fn collect_changes(messages: &[MessageWithParts]) -> BranchChanges {
+    let mut changes = BranchChanges::default();
+    for msg in messages {
+        match &msg.message {
+            Message::User(user) => {
+                let Some(summary) = &user.summary else {
+                    continue;
+                };
+                for diff in &summary.diffs {
+                    match changes.diffs.iter_mut().find(|d| d.file == diff.file) {
+                        Some(existing) => {
+                            existing.after = diff.after.clone();
+                            existing.additions += diff.additions;
+                            existing.deletions += diff.deletions;
+                        }
+                        None => changes.diffs.push(diff.clone()),
+                    }
+                }
+            }
+            Message::Assistant(_) => {
+                let text: Vec<&str> = msg
+                    .parts
+                    .iter()
+                    .filter_map(|p| match p {
+                        MessagePart::Text(t) if t.synthetic != Some(true) => Some(t.text.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                if !text.is_empty() {
+                    changes.summary = Some(text.join("\n"));
+                }
+            }
+        }
+    }
+    // Drop files that ended up where they started
+    changes.diffs.retain(|d| d.before != d.after);
+    changes
+}
+
+/// Whether a path stays inside the directory it is relative to: it isn't
+/// absolute and has no `..` components.
+fn is_inside(file: &str) -> bool {
+    !file.is_empty()
+        && Path::new(file)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Result of applying one file diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MergeOutcome {
+    Applied,
+    Unchanged,
+    Conflict,
+}
+
+/// Apply a diff to a file if it still has the content it had at the fork.
+///
+/// An empty `before` means the file was created and an empty `after` that it
+/// was deleted.
+fn apply_diff(path: &Path, diff: &FileDiff) -> std::io::Result<MergeOutcome> {
+    let current = match std::fs::read_to_string(path) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let current_text = current.as_deref().unwrap_or("");
+
+    if (current.is_some() && current_text == diff.after)
+        || (current.is_none() && diff.after.is_empty())
+    {
+        return Ok(MergeOutcome::Unchanged);
+    }
+    if current_text != diff.before {
+        return Ok(MergeOutcome::Conflict);
+    }
+
+    if diff.after.is_empty() {
+        std::fs::remove_file(path)?;
+    } else {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &diff.after)?;
+    }
+    Ok(MergeOutcome::Applied)
+}
+
+/// Text of the note added to the parent session.
+fn merge_note(branch: &Session, changes: &BranchChanges, result: &MergeResult) -> String {
+    let mut note = format!("Merged branch \"{}\".", branch.title);
+    if !result.applied.is_empty() {
+        note.push_str(&format!("\n\nApplied: {}", result.applied.join(", ")));
+    }
+    if !result.conflicts.is_empty() {
+        note.push_str(&format!(
+            "\n\nConflicts (changed since the fork, left untouched): {}",
+            result.conflicts.join(", ")
+        ));
+    }
+    if let Some(summary) = &changes.summary {
+        note.push_str(&format!("\n\nBranch summary:\n{summary}"));
+    }
+    note
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{AssistantMessage, ModelRef};
+    use crate::session::RevertInfo;
+    use wonopcode_storage::json::JsonStorage;
+
+    fn create_test_repo() -> Arc<SessionRepository> {
+        let dir = tempfile::tempdir().unwrap();
+        Arc::new(SessionRepository::new(
+            JsonStorage::new(dir.keep()),
+            Bus::new(),
+        ))
+    }
+
+    fn file_diff(file: &str, before: &str, after: &str) -> FileDiff {
+        FileDiff {
+            file: file.to_string(),
+            before: before.to_string(),
+            after: after.to_string(),
+            additions: 1,
+            deletions: 1,
+        }
+    }
+
+    /// Save a prompt with the given diffs and a reply, returning the prompt ID.
+    async fn add_turn(
+        repo: &SessionRepository,
+        session_id: &str,
+        diffs: Vec<FileDiff>,
+        reply: &str,
+    ) -> String {
+        let mut user = UserMessage::new(
+            session_id,
+            "build",
+            ModelRef {
+                provider_id: "test".to_string(),
+                model_id: "model-1".to_string(),
+            },
+        );
+        user.summary = Some(UserSummary {
+            title: None,
+            body: None,
+            diffs,
+        });
+        let user_id = user.id.clone();
+        repo.save_message(&Message::User(user)).await.unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+
+        let assistant = AssistantMessage::new(
+            session_id, &user_id, "build", "test", "model-1", "/path", "/path",
+        );
+        let part = TextPart::new(session_id, &assistant.id, reply);
+        repo.save_message_with_parts(&Message::Assistant(assistant), &[MessagePart::Text(part)])
+            .await
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        user_id
+    }
+
+    #[tokio::test]
+    async fn test_changes_since_fork() {
+        let repo = create_test_repo();
+        let branches = SessionBranch::new(repo.clone(), Bus::new());
+        let parent = repo.create(Session::new("proj_1", "/path")).await.unwrap();
+        add_turn(
+            &repo,
+            &parent.id,
+            vec![file_diff("a.rs", "", "one")],
+            "Created a",
+        )
+        .await;
+
+        let branch = branches.fork("proj_1", &parent.id, None).await.unwrap();
+        let info = branch.branch.clone().unwrap();
+        assert_eq!(info.parent_id, parent.id);
+        assert_eq!(info.base_messages, 2);
+
+        add_turn(
+            &repo,
+            &branch.id,
+            vec![file_diff("a.rs", "one", "two")],
+            "First",
+        )
+        .await;
+        let second = add_turn(
+            &repo,
+            &branch.id,
+            vec![
+                file_diff("a.rs", "two", "three"),
+                file_diff("b.rs", "", "new"),
+            ],
+            "Second",
+        )
+        .await;
+
+        let changes = branches.changes("proj_1", &branch.id).await.unwrap();
+        assert_eq!(changes.diffs.len(), 2);
+        assert_eq!(changes.diffs[0].before, "one");
+        assert_eq!(changes.diffs[0].after, "three");
+        assert_eq!(changes.summary.as_deref(), Some("Second"));
+
+        // A pending revert hides the reverted turn
+        repo.update("proj_1", &branch.id, |s| {
+            s.revert = Some(RevertInfo {
+                message_id: second,
+                part_id: None,
+                snapshot: None,
+                diff: None,
+            });
+        })
+        .await
+        .unwrap();
+        let changes = branches.changes("proj_1", &branch.id).await.unwrap();
+        assert_eq!(changes.diffs.len(), 1);
+        assert_eq!(changes.diffs[0].after, "two");
+
+        // Sessions that weren't forked aren't branches
+        assert!(branches.changes("proj_1", &parent.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_merge_applies_changes_and_reports_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.rs"), "one").unwrap();
+        std::fs::write(dir.path().join("b.rs"), "edited elsewhere").unwrap();
+
+        let repo = create_test_repo();
+        let bus = Bus::new();
+        let mut rx = bus.subscribe::<SessionMerged>().await;
+        let branches = SessionBranch::new(repo.clone(), bus);
+        let parent = repo.create(Session::new("proj_1", "/path")).await.unwrap();
+        let branch = branches.fork("proj_1", &parent.id, None).await.unwrap();
+        add_turn(
+            &repo,
+            &branch.id,
+            vec![
+                file_diff("a.rs", "one", "two"),
+                file_diff("b.rs", "old", "new"),
+                file_diff("src/c.rs", "", "created"),
+            ],
+            "Done",
+        )
+        .await;
+
+        let result = branches
+            .merge("proj_1", &branch.id, dir.path())
+            .await
+            .unwrap();
+        assert_eq!(result.applied, vec!["a.rs", "src/c.rs"]);
+        assert_eq!(result.conflicts, vec!["b.rs"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("a.rs")).unwrap(),
+            "two"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("b.rs")).unwrap(),
+            "edited elsewhere"
+        );
+        assert_eq!(rx.recv().await.unwrap().conflicts, vec!["b.rs"]);
+
+        // The parent gets a summary of the merge
+        let messages = repo.messages("proj_1", &parent.id, None).await.unwrap();
+        let Message::User(note) = &messages.last().unwrap().message else {
+            panic!("expected merge note");
+        };
+        assert_eq!(note.summary.as_ref().unwrap().diffs.len(), 2);
+
+        // A branch is merged at most once
+        assert!(branches
+            .merge("proj_1", &branch.id, dir.path())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_changes_from_snapshots() {
+        let workspace = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        std::fs::write(workspace.path().join("a.rs"), "one\n").unwrap();
+        std::fs::write(target.path().join("a.rs"), "one\n").unwrap();
+        let store = Arc::new(
+            SnapshotStore::new(
+                workspace.path().join(".snapshots"),
+                workspace.path().to_path_buf(),
+                Default::default(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        let repo = create_test_repo();
+        let branches =
+            SessionBranch::new(repo.clone(), Bus::new()).with_snapshots(Some(store.clone()));
+        let parent = repo.create(Session::new("proj_1", "/path")).await.unwrap();
+        let branch = branches.fork("proj_1", &parent.id, None).await.unwrap();
+
+        // An edit on the branch, recorded only by its snapshot
+        store
+            .take(
+                &[std::path::PathBuf::from("a.rs")],
+                &branch.id,
+                "m1",
+                "Before edit",
+            )
+            .await
+            .unwrap();
+        std::fs::write(workspace.path().join("a.rs"), "two\n").unwrap();
+
+        let changes = branches.changes("proj_1", &branch.id).await.unwrap();
+        assert_eq!(changes.diffs.len(), 1);
+        assert_eq!(changes.diffs[0].before, "one\n");
+        assert_eq!(changes.diffs[0].after, "two\n");
+
+        let result = branches
+            .merge("proj_1", &branch.id, target.path())
+            .await
+            .unwrap();
+        assert_eq!(result.applied, vec!["a.rs"]);
+        assert_eq!(
+            std::fs::read_to_string(target.path().join("a.rs")).unwrap(),
+            "two\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_refuses_paths_outside_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("project");
+        std::fs::create_dir(&dir).unwrap();
+
+        let repo = create_test_repo();
+        let branches = SessionBranch::new(repo.clone(), Bus::new());
+        let parent = repo.create(Session::new("proj_1", "/path")).await.unwrap();
+        let branch = branches.fork("proj_1", &parent.id, None).await.unwrap();
+        let outside = root.path().join("abs.rs").to_string_lossy().into_owned();
+        add_turn(
+            &repo,
+            &branch.id,
+            vec![
+                file_diff("../escape.rs", "", "x"),
+                file_diff(&outside, "", "x"),
+                file_diff("./ok.rs", "", "x"),
+            ],
+            "Done",
+        )
+        .await;
+
+        let result = branches.merge("proj_1", &branch.id, &dir).await.unwrap();
+        assert_eq!(result.applied, vec!["./ok.rs"]);
+        assert_eq!(result.conflicts, vec!["../escape.rs".to_string(), outside]);
+        assert!(!root.path().join("escape.rs").exists());
+        assert!(!root.path().join("abs.rs").exists());
+    }
+
+    #[tokio::test]
+    async fn test_merge_applies_pending_parent_revert() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = create_test_repo();
+        let branches = SessionBranch::new(repo.clone(), Bus::new());
+        let parent = repo.create(Session::new("proj_1", "/path")).await.unwrap();
+        add_turn(&repo, &parent.id, vec![], "Kept").await;
+        let branch = branches.fork("proj_1", &parent.id, None).await.unwrap();
+        let discarded = add_turn(&repo, &parent.id, vec![], "Discarded").await;
+        repo.update("proj_1", &parent.id, |s| {
+            s.revert = Some(RevertInfo {
+                message_id: discarded,
+                part_id: None,
+                snapshot: None,
+                diff: None,
+            });
+        })
+        .await
+        .unwrap();
+
+        add_turn(&repo, &branch.id, vec![file_diff("a.rs", "", "x")], "Done").await;
+        branches
+            .merge("proj_1", &branch.id, dir.path())
+            .await
+            .unwrap();
+
+        let parent = repo.get("proj_1", &parent.id).await.unwrap();
+        assert!(parent.revert.is_none());
+        // Kept turn (2 messages), reverted prompt kept as the revert point, merge note
+        let messages = repo.messages("proj_1", &parent.id, None).await.unwrap();
+        assert_eq!(messages.len(), 4);
+    }
+}
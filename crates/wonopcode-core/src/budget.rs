@@ -0,0 +1,263 @@
+//! Per-session budgets.
+//!
+//! A [`BudgetTracker`] accumulates the cost, tokens and tool calls of a
+//! session and compares them against the configured [`BudgetConfig`]. Callers
+//! warn the user once a limit is [`WARNING_RATIO`] used and pause the agent
+//! when it is reached; [`BudgetTracker::extend`] grants another full budget
+//! once the user confirms.
+
+use crate::config::BudgetConfig;
+use serde::{Deserialize, Serialize};
+
+/// Fraction of a limit at which a warning is emitted.
+pub const WARNING_RATIO: f64 = 0.8;
+
+/// A budgeted resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLimit {
+    /// Cost in USD.
+    Cost,
+    /// Input plus output tokens.
+    Tokens,
+    /// Tool calls.
+    ToolCalls,
+}
+
+impl BudgetLimit {
+    /// Identifier used in events.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BudgetLimit::Cost => "cost",
+            BudgetLimit::Tokens => "tokens",
+            BudgetLimit::ToolCalls => "tool_calls",
+        }
+    }
+
+    /// Format an amount of this resource.
+    fn format(&self, amount: f64) -> String {
+        match self {
+            BudgetLimit::Cost => format!("${amount:.2}"),
+            BudgetLimit::Tokens => format!("{} tokens", amount as u64),
+            BudgetLimit::ToolCalls => format!("{} tool calls", amount as u64),
+        }
+    }
+}
+
+/// Usage of one budgeted resource against its limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    /// The resource.
+    pub limit: BudgetLimit,
+    /// Amount used so far.
+    pub used: f64,
+    /// Current limit.
+    pub max: f64,
+}
+
+impl BudgetStatus {
+    /// Percentage of the limit used.
+    pub fn percent(&self) -> u32 {
+        if self.max <= 0.0 {
+            return 100;
+        }
+        (self.used / self.max * 100.0) as u32
+    }
+
+    /// Human-readable description, e.g. "82% of session budget used ($4.10 of $5.00)".
+    pub fn describe(&self) -> String {
+        format!(
+            "{}% of session budget used ({} of {})",
+            self.percent(),
+            self.limit.format(self.used),
+            self.limit.format(self.max)
+        )
+    }
+}
+
+/// Result of a budget check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetCheck {
+    /// Within budget.
+    Ok,
+    /// A limit crossed the warning threshold for the first time.
+    Warning(BudgetStatus),
+    /// A limit was reached.
+    Exceeded(BudgetStatus),
+}
+
+/// Accumulated usage of a session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BudgetUsage {
+    /// Cost in USD.
+    pub cost: f64,
+    /// Input plus output tokens.
+    pub tokens: u64,
+    /// Tool calls.
+    pub tool_calls: u32,
+}
+
+/// Tracks a session's usage against its budget.
+#[derive(Debug, Clone, Default)]
+pub struct BudgetTracker {
+    config: BudgetConfig,
+    usage: BudgetUsage,
+    /// Number of extra budgets granted after confirmation, per limit.
+    extensions: [u32; 3],
+    /// Limits already warned about since the last extension.
+    warned: Vec<BudgetLimit>,
+}
+
+impl BudgetTracker {
+    /// Create a tracker for the given limits.
+    pub fn new(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Whether any limit is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.config.max_cost.is_some()
+            || self.config.max_tokens.is_some()
+            || self.config.max_tool_calls.is_some()
+    }
+
+    /// Replace the limits, keeping the usage recorded so far.
+    pub fn set_config(&mut self, config: BudgetConfig) {
+        self.config = config;
+    }
+
+    /// Usage recorded so far.
+    pub fn usage(&self) -> &BudgetUsage {
+        &self.usage
+    }
+
+    /// Record the tokens and cost of a model call.
+    pub fn record_step(&mut self, input_tokens: u32, output_tokens: u32, cost: f64) {
+        self.usage.tokens += u64::from(input_tokens) + u64::from(output_tokens);
+        self.usage.cost += cost;
+    }
+
+    /// Record executed tool calls.
+    pub fn record_tool_calls(&mut self, count: usize) {
+        self.usage.tool_calls = self.usage.tool_calls.saturating_add(count as u32);
+    }
+
+    /// Forget all usage, e.g. when a new session starts.
+    pub fn reset(&mut self) {
+        self.usage = BudgetUsage::default();
+        self.extensions = [0; 3];
+        self.warned.clear();
+    }
+
+    /// Status of each configured limit.
+    pub fn statuses(&self) -> Vec<BudgetStatus> {
+        let limits = [
+            (BudgetLimit::Cost, self.config.max_cost, self.usage.cost),
+            (
+                BudgetLimit::Tokens,
+                self.config.max_tokens.map(|m| m as f64),
+                self.usage.tokens as f64,
+            ),
+            (
+                BudgetLimit::ToolCalls,
+                self.config.max_tool_calls.map(f64::from),
+                f64::from(self.usage.tool_calls),
+            ),
+        ];
+        limits
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, (limit, max, used))| {
+                let max = max? * f64::from(self.extensions[i] + 1);
+                Some(BudgetStatus { limit, used, max })
+            })
+            .collect()
+    }
+
+    /// Compare usage against the limits.
+    ///
+    /// Each limit produces at most one warning until it is extended.
+    pub fn check(&mut self) -> BudgetCheck {
+        let statuses = self.statuses();
+        if let Some(status) = statuses.iter().find(|s| s.used >= s.max) {
+            return BudgetCheck::Exceeded(status.clone());
+        }
+        for status in statuses {
+            if status.used >= status.max * WARNING_RATIO && !self.warned.contains(&status.limit) {
+                self.warned.push(status.limit);
+                return BudgetCheck::Warning(status);
+            }
+        }
+        BudgetCheck::Ok
+    }
+
+    /// Grant another full budget for every limit that has been reached.
+    ///
+    /// Called after the user confirms the agent may continue.
+    pub fn extend(&mut self) {
+        for status in self.statuses() {
+            if status.used >= status.max {
+                let i = status.limit as usize;
+                self.extensions[i] += 1;
+                self.warned.retain(|l| *l != status.limit);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_then_exceeded() {
+        let mut tracker = BudgetTracker::new(BudgetConfig {
+            max_cost: Some(1.0),
+            ..Default::default()
+        });
+        assert!(tracker.is_enabled());
+        assert_eq!(tracker.check(), BudgetCheck::Ok);
+
+        tracker.record_step(100, 50, 0.85);
+        let BudgetCheck::Warning(status) = tracker.check() else {
+            panic!("expected warning");
+        };
+        assert_eq!(status.limit, BudgetLimit::Cost);
+        assert_eq!(
+            status.describe(),
+            "85% of session budget used ($0.85 of $1.00)"
+        );
+        // Only warned once
+        assert_eq!(tracker.check(), BudgetCheck::Ok);
+
+        tracker.record_step(100, 50, 0.2);
+        assert!(matches!(tracker.check(), BudgetCheck::Exceeded(_)));
+
+        // Confirming grants another full budget
+        tracker.extend();
+        assert_eq!(tracker.check(), BudgetCheck::Ok);
+        tracker.record_step(0, 0, 0.6);
+        assert!(matches!(tracker.check(), BudgetCheck::Warning(_)));
+    }
+
+    #[test]
+    fn test_tool_call_and_token_limits() {
+        let mut tracker = BudgetTracker::new(BudgetConfig {
+            max_tokens: Some(1_000),
+            max_tool_calls: Some(2),
+            ..Default::default()
+        });
+        tracker.record_tool_calls(2);
+        let BudgetCheck::Exceeded(status) = tracker.check() else {
+            panic!("expected exceeded");
+        };
+        assert_eq!(status.limit, BudgetLimit::ToolCalls);
+
+        tracker.reset();
+        assert_eq!(tracker.check(), BudgetCheck::Ok);
+        assert!(!BudgetTracker::default().is_enabled());
+    }
+}
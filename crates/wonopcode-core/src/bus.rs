@@ -244,6 +244,23 @@ impl Event for SessionDeleted {
     }
 }
 
+/// Session branch merged into its parent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMerged {
+    pub session_id: String,
+    pub parent_id: String,
+    /// Files written to the working directory.
+    pub applied: Vec<String>,
+    /// Files left untouched because they changed on both sides.
+    pub conflicts: Vec<String>,
+}
+
+impl Event for SessionMerged {
+    fn event_type() -> &'static str {
+        "session.merged"
+    }
+}
+
 /// Message updated event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageUpdated {
@@ -462,6 +479,117 @@ impl Event for InstanceDisposed {
     }
 }
 
+/// Configuration reloaded from disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigChanged {
+    /// Changed top-level keys that are applied at runtime.
+    pub applied: Vec<String>,
+    /// Changed top-level keys that need a restart to take effect.
+    pub restart_required: Vec<String>,
+}
+
+impl Event for ConfigChanged {
+    fn event_type() -> &'static str {
+        "config.changed"
+    }
+}
+
+/// A config-declared shell hook failed or timed out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookFailed {
+    /// Hook event name (e.g. "PreToolUse").
+    pub event: String,
+    /// The hook command.
+    pub command: String,
+    /// Failure description.
+    pub error: String,
+}
+
+impl Event for HookFailed {
+    fn event_type() -> &'static str {
+        "hook.failed"
+    }
+}
+
+/// A session budget limit crossed its warning threshold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetWarning {
+    /// Session ID.
+    pub session_id: String,
+    /// Usage of the limit.
+    pub status: crate::budget::BudgetStatus,
+}
+
+impl Event for BudgetWarning {
+    fn event_type() -> &'static str {
+        "budget.warning"
+    }
+}
+
+/// A session budget limit was reached and the agent paused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetExceeded {
+    /// Session ID.
+    pub session_id: String,
+    /// Usage of the limit.
+    pub status: crate::budget::BudgetStatus,
+}
+
+impl Event for BudgetExceeded {
+    fn event_type() -> &'static str {
+        "budget.exceeded"
+    }
+}
+
+/// Progress of an orchestration's agents changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrchestrationUpdated {
+    /// Orchestration ID.
+    pub orchestration_id: String,
+    /// Progress of every agent.
+    pub agents: Vec<crate::orchestrate::AgentProgress>,
+}
+
+impl Event for OrchestrationUpdated {
+    fn event_type() -> &'static str {
+        "orchestration.updated"
+    }
+}
+
+/// Requests to a provider are failing and being retried after a delay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderDegraded {
+    /// Provider ID.
+    pub provider: String,
+    /// Why the provider is considered degraded.
+    pub reason: String,
+    /// Milliseconds until the next attempt.
+    pub retry_in_ms: u64,
+    /// Retry attempt, starting at 1.
+    pub attempt: u32,
+    /// Whether the provider's circuit breaker is open.
+    pub circuit_open: bool,
+}
+
+impl Event for ProviderDegraded {
+    fn event_type() -> &'static str {
+        "provider.degraded"
+    }
+}
+
+/// A provider that was failing answered again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderRecovered {
+    /// Provider ID.
+    pub provider: String,
+}
+
+impl Event for ProviderRecovered {
+    fn event_type() -> &'static str {
+        "provider.recovered"
+    }
+}
+
 // ============================================================================
 // Sandbox Event Types
 // ============================================================================
@@ -529,6 +657,34 @@ impl Event for SandboxToolExecution {
     }
 }
 
+/// A prompt submitted by the user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptSubmitted {
+    /// The prompt as typed, before custom commands are expanded.
+    pub text: String,
+}
+
+impl Event for PromptSubmitted {
+    fn event_type() -> &'static str {
+        "prompt.submitted"
+    }
+}
+
+/// An update sent from the agent to its user interface.
+///
+/// Only published while events are being [recorded](crate::recording), so a
+/// recording can be replayed into a TUI. The payload is the update as sent to
+/// remote clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AgentUpdate(pub serde_json::Value);
+
+impl Event for AgentUpdate {
+    fn event_type() -> &'static str {
+        "agent.update"
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2,14 +2,25 @@
 //!
 //! Commands are defined in:
 //! - Configuration file: `wonopcode.json` -> `command` section
-//! - Markdown files: `.wonopcode/command/**/*.md` with frontmatter
+//! - Markdown files with frontmatter: `.wonopcode/commands/**/*.md` (or
+//!   `.wonopcode/command/`) in the project, and `commands/**/*.md` in the
+//!   user's config directory
+//!
+//! Templates receive the whole argument string as `$ARGUMENTS`, positional
+//! arguments as `$1`..`$20` and declared named arguments as `{{name}}`.
 
+use crate::config::Config;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use tracing::{debug, warn};
 
+pub use crate::config::CommandConfig;
+
+/// Command directories, in order of precedence (later ones win).
+const COMMAND_DIRS: [&str; 2] = ["command", "commands"];
+
 /// A custom command definition.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Command {
@@ -29,6 +40,12 @@ pub struct Command {
     /// Run as subtask.
     #[serde(default)]
     pub subtask: bool,
+    /// Tools the command may use; empty allows all tools.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_tools: Vec<String>,
+    /// Named arguments, available as `{{name}}` in the template.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<CommandArgument>,
 }
 
 impl Command {
@@ -41,6 +58,8 @@ impl Command {
             agent: None,
             model: None,
             subtask: false,
+            allowed_tools: Vec::new(),
+            arguments: Vec::new(),
         }
     }
 
@@ -62,15 +81,166 @@ impl Command {
         self
     }
 
+    /// Restrict the tools the command may use.
+    pub fn with_allowed_tools(mut self, tools: Vec<String>) -> Self {
+        self.allowed_tools = tools;
+        self
+    }
+
+    /// Add a named argument.
+    pub fn with_argument(mut self, argument: CommandArgument) -> Self {
+        self.arguments.push(argument);
+        self
+    }
+
+    /// Whether the command may use a tool.
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        self.allowed_tools.is_empty()
+            || self
+                .allowed_tools
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(tool))
+    }
+
     /// Expand the template with arguments.
+    ///
+    /// Arguments are split on whitespace, keeping quoted strings together.
+    /// `name=value` sets a declared argument; declared arguments not set by
+    /// name take the positional arguments in order, the last one taking the
+    /// rest.
     pub fn expand(&self, arguments: &str) -> String {
-        let args: Vec<&str> = arguments.split_whitespace().collect();
-        expand_template(&self.template, &args, arguments)
+        let mut positional = Vec::new();
+        let mut named = HashMap::new();
+        for token in split_arguments(arguments) {
+            match token.split_once('=') {
+                Some((key, value)) if self.arguments.iter().any(|a| a.name == key) => {
+                    named.insert(key.to_string(), value.to_string());
+                }
+                _ => positional.push(token),
+            }
+        }
+
+        // Once named arguments are taken out, $ARGUMENTS is what remains
+        let full = if named.is_empty() {
+            arguments.to_string()
+        } else {
+            positional.join(" ")
+        };
+        let args: Vec<&str> = positional.iter().map(String::as_str).collect();
+        let expanded = expand_template(&self.template, &args, &full);
+
+        let unset: Vec<&CommandArgument> = self
+            .arguments
+            .iter()
+            .filter(|a| !named.contains_key(&a.name))
+            .collect();
+        let mut remaining = positional.into_iter();
+        for (i, argument) in unset.iter().enumerate() {
+            let value = if i + 1 == unset.len() {
+                let rest: Vec<String> = remaining.by_ref().collect();
+                (!rest.is_empty()).then(|| rest.join(" "))
+            } else {
+                remaining.next()
+            };
+            let value = value
+                .or_else(|| argument.default.clone())
+                .unwrap_or_default();
+            named.insert(argument.name.clone(), value);
+        }
+
+        expand_named(&expanded, &named)
     }
 }
 
-/// Command frontmatter in YAML format.
+/// A named command argument.
+///
+/// In frontmatter and config, an argument is either just its name or a map
+/// with `name`, `description` and `default`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "ArgumentSpec")]
+pub struct CommandArgument {
+    /// Name used as `name=value` and `{{name}}`.
+    pub name: String,
+    /// Human-readable description.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Value used when the argument isn't given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+impl CommandArgument {
+    /// Create an argument without description or default.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: String::new(),
+            default: None,
+        }
+    }
+
+    /// Set the default value.
+    pub fn with_default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+}
+
+/// The accepted forms of an argument declaration.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ArgumentSpec {
+    Name(String),
+    Full {
+        name: String,
+        #[serde(default)]
+        description: String,
+        #[serde(default)]
+        default: Option<String>,
+    },
+}
+
+impl From<ArgumentSpec> for CommandArgument {
+    fn from(spec: ArgumentSpec) -> Self {
+        match spec {
+            ArgumentSpec::Name(name) => Self::new(name),
+            ArgumentSpec::Full {
+                name,
+                description,
+                default,
+            } => Self {
+                name,
+                description,
+                default,
+            },
+        }
+    }
+}
+
+/// A tool list, as a YAML list or a comma-separated string.
 #[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ToolList {
+    List(Vec<String>),
+    Line(String),
+}
+
+impl ToolList {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::List(tools) => tools,
+            Self::Line(line) => line
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect(),
+        }
+    }
+}
+
+/// Command frontmatter in YAML format.
+#[derive(Debug, Default, Deserialize)]
 struct CommandFrontmatter {
     #[serde(default)]
     name: Option<String>,
@@ -82,6 +252,10 @@ struct CommandFrontmatter {
     model: Option<String>,
     #[serde(default)]
     subtask: Option<bool>,
+    #[serde(default, alias = "allowed-tools")]
+    allowed_tools: Option<ToolList>,
+    #[serde(default)]
+    arguments: Vec<CommandArgument>,
 }
 
 /// Command registry for managing custom commands.
@@ -262,14 +436,46 @@ $ARGUMENTS"#,
             if let Some(subtask) = cfg.subtask {
                 command.subtask = subtask;
             }
+            if let Some(tools) = &cfg.allowed_tools {
+                command.allowed_tools = tools.clone();
+            }
+            if let Some(arguments) = &cfg.arguments {
+                command.arguments = arguments.clone();
+            }
             self.register(command);
         }
     }
 
-    /// Discover commands from directories.
+    /// Discover commands from `.wonopcode/commands/` and
+    /// `.wonopcode/command/` of each directory.
     pub async fn discover(&mut self, directories: &[PathBuf]) {
         for dir in directories {
-            let command_dir = dir.join(".wonopcode").join("command");
+            self.scan_command_dirs(&dir.join(".wonopcode")).await;
+        }
+    }
+
+    /// Discover commands from `commands/` in the user's config directory.
+    pub async fn discover_global(&mut self) {
+        if let Some(dir) = Config::global_config_dir() {
+            self.scan_command_dirs(&dir).await;
+        }
+    }
+
+    /// Load the custom commands of a project: the user's commands, then the
+    /// configured ones, then the project's own, each overriding the ones
+    /// before.
+    pub async fn load_project(&mut self, project_dir: &Path, config: &Config) {
+        self.discover_global().await;
+        if let Some(commands) = &config.command {
+            self.register_from_config(commands);
+        }
+        self.discover(&[project_dir.to_path_buf()]).await;
+    }
+
+    /// Scan the command directories under `base`.
+    async fn scan_command_dirs(&mut self, base: &Path) {
+        for name in COMMAND_DIRS {
+            let command_dir = base.join(name);
             if command_dir.exists() {
                 self.scan_directory(&command_dir).await;
             }
@@ -322,6 +528,11 @@ $ARGUMENTS"#,
             agent: frontmatter.agent,
             model: frontmatter.model,
             subtask: frontmatter.subtask.unwrap_or(false),
+            allowed_tools: frontmatter
+                .allowed_tools
+                .map(ToolList::into_vec)
+                .unwrap_or_default(),
+            arguments: frontmatter.arguments,
         })
     }
 
@@ -356,20 +567,6 @@ $ARGUMENTS"#,
     }
 }
 
-/// Command configuration from config file.
-#[derive(Debug, Clone, Deserialize)]
-pub struct CommandConfig {
-    pub template: String,
-    #[serde(default)]
-    pub description: Option<String>,
-    #[serde(default)]
-    pub agent: Option<String>,
-    #[serde(default)]
-    pub model: Option<String>,
-    #[serde(default)]
-    pub subtask: Option<bool>,
-}
-
 /// Parse YAML frontmatter from markdown content.
 fn parse_frontmatter(content: &str) -> Result<(CommandFrontmatter, String), String> {
     let content = content.trim();
@@ -377,16 +574,7 @@ fn parse_frontmatter(content: &str) -> Result<(CommandFrontmatter, String), Stri
     // Check for frontmatter delimiter
     if !content.starts_with("---") {
         // No frontmatter, use entire content as template
-        return Ok((
-            CommandFrontmatter {
-                name: None,
-                description: None,
-                agent: None,
-                model: None,
-                subtask: None,
-            },
-            content.to_string(),
-        ));
+        return Ok((CommandFrontmatter::default(), content.to_string()));
     }
 
     // Find the end of frontmatter
@@ -437,6 +625,61 @@ fn expand_template(template: &str, args: &[&str], full_arguments: &str) -> Strin
     result
 }
 
+/// Replace `{{name}}` placeholders; unknown names are left as they are.
+fn expand_named(template: &str, values: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + len + 4;
+        result.push_str(&rest[..start]);
+        match values.get(rest[start + 2..end - 2].trim()) {
+            Some(value) => result.push_str(value),
+            None => result.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Split arguments on whitespace, keeping quoted strings together.
+///
+/// Quotes only group at the start of an argument or after `name=`, so
+/// apostrophes in words are kept.
+fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    for c in arguments.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if (c == '"' || c == '\'') && (!in_arg || current.ends_with('=')) => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -626,6 +869,8 @@ This is the template $ARGUMENTS
                 agent: Some("test-agent".to_string()),
                 model: Some("test-model".to_string()),
                 subtask: Some(true),
+                allowed_tools: None,
+                arguments: None,
             },
         );
 
@@ -637,6 +882,8 @@ This is the template $ARGUMENTS
                 agent: None,
                 model: None,
                 subtask: None,
+                allowed_tools: None,
+                arguments: None,
             },
         );
 
@@ -708,6 +955,8 @@ No closing delimiter"#;
             agent: Some("plan".to_string()),
             model: None,
             subtask: true,
+            allowed_tools: vec!["read".to_string()],
+            arguments: vec![CommandArgument::new("file")],
         };
 
         let json = serde_json::to_string(&cmd).unwrap();
@@ -716,6 +965,8 @@ No closing delimiter"#;
         assert_eq!(parsed.name, "test");
         assert_eq!(parsed.agent, Some("plan".to_string()));
         assert!(parsed.subtask);
+        assert_eq!(parsed.allowed_tools, vec!["read".to_string()]);
+        assert_eq!(parsed.arguments, vec![CommandArgument::new("file")]);
     }
 
     #[test]
@@ -845,4 +1096,110 @@ Template here
         assert!(config.model.is_none());
         assert!(config.subtask.is_none());
     }
+
+    #[test]
+    fn test_expand_named_arguments() {
+        let cmd = Command::new(
+            "review",
+            "Review {{file}} for {{focus}}. Unknown: {{other}}",
+        )
+        .with_argument(CommandArgument::new("file"))
+        .with_argument(CommandArgument::new("focus").with_default("bugs"));
+
+        // Positional arguments fill declared arguments in order
+        assert_eq!(
+            cmd.expand("src/main.rs security and speed"),
+            "Review src/main.rs for security and speed. Unknown: {{other}}"
+        );
+        // Defaults apply to missing arguments
+        assert_eq!(
+            cmd.expand("src/main.rs"),
+            "Review src/main.rs for bugs. Unknown: {{other}}"
+        );
+        // Named arguments can come in any order
+        assert_eq!(
+            cmd.expand("focus=style \"src/a b.rs\""),
+            "Review src/a b.rs for style. Unknown: {{other}}"
+        );
+    }
+
+    #[test]
+    fn test_expand_named_removes_them_from_arguments() {
+        let cmd = Command::new("fix", "Fix $ARGUMENTS in {{ area }}")
+            .with_argument(CommandArgument::new("area").with_default("core"));
+        assert_eq!(cmd.expand("area=tui the crash"), "Fix the crash in tui");
+        // Without declared arguments, key=value is just text
+        let plain = Command::new("fix", "Fix $ARGUMENTS");
+        assert_eq!(plain.expand("area=tui the crash"), "Fix area=tui the crash");
+    }
+
+    #[test]
+    fn test_split_arguments() {
+        assert_eq!(
+            split_arguments(r#"one "two three" 'four' msg="a b" don't"#),
+            vec!["one", "two three", "four", "msg=a b", "don't"]
+        );
+        assert!(split_arguments("   ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_frontmatter_tools_and_arguments() {
+        let content = r#"---
+description: Review a file
+allowed-tools: read, grep
+arguments:
+  - file
+  - name: focus
+    description: What to look for
+    default: bugs
+---
+
+Review {{file}} for {{focus}}"#;
+
+        let (fm, _) = parse_frontmatter(content).unwrap();
+        assert_eq!(
+            fm.allowed_tools.unwrap().into_vec(),
+            vec!["read".to_string(), "grep".to_string()]
+        );
+        assert_eq!(fm.arguments[0], CommandArgument::new("file"));
+        assert_eq!(fm.arguments[1].description, "What to look for");
+        assert_eq!(fm.arguments[1].default.as_deref(), Some("bugs"));
+    }
+
+    #[test]
+    fn test_allows_tool() {
+        let cmd = Command::new("test", "template");
+        assert!(cmd.allows_tool("bash"));
+
+        let cmd = cmd.with_allowed_tools(vec!["Read".to_string(), "grep".to_string()]);
+        assert!(cmd.allows_tool("read"));
+        assert!(cmd.allows_tool("grep"));
+        assert!(!cmd.allows_tool("bash"));
+    }
+
+    #[tokio::test]
+    async fn test_discover_commands_directory() {
+        use tempfile::tempdir;
+        let dir = tempdir().unwrap();
+
+        let legacy = dir.path().join(".wonopcode").join("command");
+        let commands = dir.path().join(".wonopcode").join("commands");
+        std::fs::create_dir_all(&legacy).unwrap();
+        std::fs::create_dir_all(commands.join("git")).unwrap();
+        std::fs::write(legacy.join("ship.md"), "Old ship").unwrap();
+        std::fs::write(commands.join("ship.md"), "New ship").unwrap();
+        std::fs::write(
+            commands.join("git").join("commit.md"),
+            "---\nallowed-tools: [bash]\n---\nCommit $ARGUMENTS",
+        )
+        .unwrap();
+
+        let mut registry = CommandRegistry::new();
+        registry.discover(&[dir.path().to_path_buf()]).await;
+
+        // `commands/` wins over the legacy `command/` directory
+        assert_eq!(registry.get("ship").unwrap().template, "New ship");
+        let commit = registry.get("commit").unwrap();
+        assert_eq!(commit.allowed_tools, vec!["bash".to_string()]);
+    }
 }
@@ -7,6 +7,10 @@
 //! 4. MCP server config: `.mcp.json` in project directory (standard MCP format)
 //! 5. Environment overrides: `WONOPCODE_*` variables
 //!
+//! Agent files in `~/.config/wonopcode/agents/` and `.wonopcode/agents/` are
+//! merged into the `agent` key after the global and project config
+//! respectively.
+//!
 //! Supports JSONC (JSON with comments) and variable substitution:
 //! - `{env:VAR_NAME}` - Substitute environment variable
 //! - `{file:path}` - Substitute file contents
@@ -46,6 +50,7 @@
 // @ace:design DES-T90R4U-5W8
 // @ace:implements COMP-T90R4U-1A4O
 
+use crate::agent::{agent_dirs, AgentFile};
 use crate::error::{ConfigError, CoreResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -229,10 +234,27 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub snapshot: Option<bool>,
 
+    /// Scan the project around shell commands, so that a revert can undo
+    /// what they changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_commands: Option<bool>,
+
     /// Share mode.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub share: Option<ShareMode>,
 
+    /// Share server to use instead of the hosted one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_server: Option<ShareServerConfig>,
+
+    /// Tasks run on a schedule in headless mode, by name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<HashMap<String, ScheduledTaskConfig>>,
+
+    /// OpenTelemetry export of traces and metrics. Off unless enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<TelemetryConfig>,
+
     /// Auto-update setting.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub autoupdate: Option<AutoUpdate>,
@@ -261,6 +283,11 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub command: Option<HashMap<String, CommandConfig>>,
 
+    /// Formatters run after files are edited, by name. An entry named after
+    /// a built-in formatter overrides or disables it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub formatter: Option<HashMap<String, FormatterConfig>>,
+
     /// Agent configurations.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub agent: Option<HashMap<String, AgentConfig>>,
@@ -285,6 +312,15 @@ pub struct Config {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub instructions: Option<Vec<String>>,
 
+    /// Token budget for instruction files in the system prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instruction_max_tokens: Option<usize>,
+
+    /// Variables for prompt templates, used as `{{name}}` in instructions
+    /// and agent prompts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_variables: Option<HashMap<String, String>>,
+
     /// Compaction settings.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub compaction: Option<CompactionConfig>,
@@ -304,6 +340,59 @@ pub struct Config {
     /// Update configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub update: Option<UpdateConfig>,
+
+    /// Storage settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageConfig>,
+
+    /// WASM plugin settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugins: Option<PluginsConfig>,
+
+    /// Tool hook settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<HooksConfig>,
+
+    /// Per-session token, cost and tool call budgets.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget: Option<BudgetConfig>,
+
+    /// Spending alerts and limits across sessions and projects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spend: Option<SpendConfig>,
+
+    /// Multi-agent orchestration settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orchestration: Option<OrchestrationConfig>,
+
+    /// Automated pull request review settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review: Option<ReviewConfig>,
+
+    /// Issue triage settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub triage: Option<TriageConfig>,
+
+    /// Active profile name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Named profiles. The active profile is merged over the rest of the config.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<HashMap<String, Config>>,
+}
+
+/// Environment variable selecting the active config profile.
+pub const PROFILE_ENV: &str = "WONOPCODE_PROFILE";
+
+/// Profile selected on the command line.
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Select the profile used by every config this process loads, overriding
+/// [`PROFILE_ENV`] and the `profile` config key. Only the first call has an
+/// effect.
+pub fn select_profile(name: impl Into<String>) {
+    let _ = SELECTED_PROFILE.set(name.into());
 }
 
 /// Log levels.
@@ -365,6 +454,28 @@ pub struct TuiConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tables: Option<bool>,
 
+    /// Show tokens and cost under each assistant message.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_usage: Option<bool>,
+
+    /// How to show images in tool results.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub images: Option<ImageMode>,
+
+    /// Open the change review when a prompt finishes with file changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_review: Option<bool>,
+
+    /// How to notify when the agent finishes, fails or asks for permission
+    /// while the terminal is unfocused.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationMode>,
+
+    /// Language of the TUI's strings, such as `"de"` or `"ja"`. Taken from
+    /// the environment when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+
     /// Max frames per second during streaming.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub streaming_fps: Option<u32>,
@@ -377,6 +488,23 @@ pub struct TuiConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub low_memory_mode: Option<bool>,
 
+    /// Use the high-contrast variant of the theme.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub high_contrast: Option<bool>,
+
+    /// Show static indicators instead of spinners and other animations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reduce_motion: Option<bool>,
+
+    /// Draw borders and icons with ASCII instead of Unicode box art.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ascii_borders: Option<bool>,
+
+    /// Show the conversation as plain linear text for screen readers.
+    /// Also turns off markdown, animations and box art unless they're set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen_reader: Option<bool>,
+
     /// Enable test/debug commands like /add_test_messages.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enable_test_commands: Option<bool>,
@@ -427,6 +555,21 @@ impl TuiConfig {
         if other.tables.is_some() {
             self.tables = other.tables;
         }
+        if other.message_usage.is_some() {
+            self.message_usage = other.message_usage;
+        }
+        if other.images.is_some() {
+            self.images = other.images;
+        }
+        if other.auto_review.is_some() {
+            self.auto_review = other.auto_review;
+        }
+        if other.notifications.is_some() {
+            self.notifications = other.notifications;
+        }
+        if other.locale.is_some() {
+            self.locale = other.locale;
+        }
         if other.streaming_fps.is_some() {
             self.streaming_fps = other.streaming_fps;
         }
@@ -436,6 +579,18 @@ impl TuiConfig {
         if other.low_memory_mode.is_some() {
             self.low_memory_mode = other.low_memory_mode;
         }
+        if other.high_contrast.is_some() {
+            self.high_contrast = other.high_contrast;
+        }
+        if other.reduce_motion.is_some() {
+            self.reduce_motion = other.reduce_motion;
+        }
+        if other.ascii_borders.is_some() {
+            self.ascii_borders = other.ascii_borders;
+        }
+        if other.screen_reader.is_some() {
+            self.screen_reader = other.screen_reader;
+        }
         if other.enable_test_commands.is_some() {
             self.enable_test_commands = other.enable_test_commands;
         }
@@ -466,6 +621,36 @@ pub enum PasteMode {
     Direct,
 }
 
+/// How images are shown in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageMode {
+    /// Use the graphics protocol the terminal is detected to support.
+    Auto,
+    /// The kitty graphics protocol.
+    Kitty,
+    /// Sixel graphics.
+    Sixel,
+    /// iTerm2 inline images.
+    Iterm2,
+    /// Show a placeholder line instead.
+    Off,
+}
+
+/// How the TUI notifies while the terminal is unfocused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationMode {
+    /// Don't notify.
+    Off,
+    /// Ring the terminal bell.
+    Bell,
+    /// An OSC 777 terminal notification.
+    Osc777,
+    /// A native desktop notification.
+    Desktop,
+}
+
 /// Server configuration.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
@@ -486,6 +671,95 @@ pub struct ServerConfig {
     /// Supports variable substitution: `{env:WONOPCODE_API_KEY}`
     #[serde(skip_serializing_if = "Option::is_none")]
     pub api_key: Option<String>,
+
+    /// Extra project roots a headless server manages besides its working
+    /// directory. Relative paths are resolved against the working directory.
+    ///
+    /// Clients switch between them with `/project`. Each project gets its own
+    /// config, sessions and sandbox, started when first switched to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<String>>,
+
+    /// Requests per minute each client may make to a headless server
+    /// (default: 300; 0 for no limit). Clients with the API key share one
+    /// budget; others are counted by address.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<u32>,
+
+    /// Largest request body of a headless server's action endpoints, in bytes
+    /// (default: 1 MiB). Image attachments have their own, larger limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_body_bytes: Option<usize>,
+
+    /// Let clients of a headless server write project files at
+    /// `/files/write` (default: false, so files are only readable).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_file_writes: Option<bool>,
+
+    /// URLs a headless server posts events to, so chat or CI can react to
+    /// unattended runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhooks: Option<Vec<WebhookConfig>>,
+
+    /// Share a headless server's session between its clients (default:
+    /// false, when every client may act). One client drives and the others
+    /// watch until they take control with `/drive`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collaborate: Option<bool>,
+}
+
+/// A URL that server events are posted to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// URL to post the events to.
+    pub url: String,
+
+    /// Events to post (default: all of them).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<WebhookEvent>>,
+
+    /// Secret to sign the payloads with, sent as an HMAC-SHA256 of the body
+    /// in the `X-Wonopcode-Signature` header.
+    ///
+    /// Supports variable substitution: `{env:WEBHOOK_SECRET}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+impl WebhookConfig {
+    /// Check if the webhook is posted an event.
+    pub fn wants(&self, event: WebhookEvent) -> bool {
+        match &self.events {
+            Some(events) => events.contains(&event),
+            None => true,
+        }
+    }
+}
+
+/// Server events that can be posted to a webhook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// A prompt finished running.
+    Completed,
+    /// A prompt failed.
+    Error,
+    /// A tool is waiting for permission.
+    PermissionPending,
+    /// Spend crossed an alert amount, or a budget is nearly used up.
+    CostThreshold,
+}
+
+impl WebhookEvent {
+    /// Name used in config files and the `X-Wonopcode-Event` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Completed => "completed",
+            WebhookEvent::Error => "error",
+            WebhookEvent::PermissionPending => "permission_pending",
+            WebhookEvent::CostThreshold => "cost_threshold",
+        }
+    }
 }
 
 /// Keybind configuration.
@@ -520,12 +794,77 @@ pub struct KeybindsConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub session_list: Option<String>,
 
+    /// Menus of key chords opened by a key after the leader, by that key.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub menus: HashMap<String, KeybindMenuConfig>,
+
     // Additional keybinds can be added as needed
     /// Additional keybinds as a map.
     #[serde(flatten)]
     pub extra: HashMap<String, String>,
 }
 
+/// A menu of key chords, such as `<leader>g` followed by `s` for `/git`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeybindMenuConfig {
+    /// Title of the menu.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// What each key in the menu runs: a keybind action name such as
+    /// `git_open`, or a slash command such as `/diff`.
+    pub keys: HashMap<String, String>,
+}
+
+impl KeybindsConfig {
+    /// Merge with another KeybindsConfig, preferring bindings from other.
+    pub fn merge(mut self, other: Self) -> Self {
+        for (name, binding) in other.bindings() {
+            self.set(name, binding);
+        }
+        if other.leader.is_some() {
+            self.leader = other.leader;
+        }
+        self.menus.extend(other.menus);
+        self
+    }
+
+    /// All configured bindings by action name, without the leader.
+    pub fn bindings(&self) -> HashMap<String, String> {
+        let mut bindings = self.extra.clone();
+        for (name, binding) in [
+            ("app_exit", &self.app_exit),
+            ("editor_open", &self.editor_open),
+            ("theme_list", &self.theme_list),
+            ("sidebar_toggle", &self.sidebar_toggle),
+            ("session_new", &self.session_new),
+            ("session_list", &self.session_list),
+        ] {
+            if let Some(binding) = binding {
+                bindings.insert(name.to_string(), binding.clone());
+            }
+        }
+        bindings
+    }
+
+    fn set(&mut self, name: String, binding: String) {
+        let field = match name.as_str() {
+            "app_exit" => &mut self.app_exit,
+            "editor_open" => &mut self.editor_open,
+            "theme_list" => &mut self.theme_list,
+            "sidebar_toggle" => &mut self.sidebar_toggle,
+            "session_new" => &mut self.session_new,
+            "session_list" => &mut self.session_list,
+            _ => {
+                self.extra.insert(name, binding);
+                return;
+            }
+        };
+        *field = Some(binding);
+    }
+}
+
 /// Custom command configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandConfig {
@@ -547,6 +886,43 @@ pub struct CommandConfig {
     /// Run as subtask.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subtask: Option<bool>,
+
+    /// Tools the command may use (all tools if unset).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_tools: Option<Vec<String>>,
+
+    /// Named arguments, filled by position or as `name=value`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Vec<crate::command::CommandArgument>>,
+}
+
+/// Formatter configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormatterConfig {
+    /// Disable this formatter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<bool>,
+
+    /// Command and arguments; `$FILE` is replaced with the file path.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<Vec<String>>,
+
+    /// File extensions to format, e.g. `[".ts"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<Vec<String>>,
+
+    /// Path globs to format, relative to the project root, e.g. `["src/**/*.ts"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub globs: Option<Vec<String>>,
+
+    /// Pipe the file through the command's stdin and stdout instead of
+    /// letting it format the file in place.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdin: Option<bool>,
+
+    /// Environment variables.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<HashMap<String, String>>,
 }
 
 /// Agent configuration.
@@ -600,6 +976,10 @@ pub struct AgentConfig {
     /// Per-agent sandbox configuration overrides.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sandbox: Option<AgentSandboxConfig>,
+
+    /// Per-agent compaction overrides, layered on the global settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compaction: Option<CompactionConfig>,
 }
 
 /// Per-agent sandbox configuration.
@@ -1037,6 +1417,12 @@ pub struct PermissionConfig {
     /// execution environment. Default: true.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub allow_all_in_sandbox: Option<bool>,
+
+    /// Fine-grained rules matching paths, commands and tool arguments.
+    /// They are checked after the settings above and win over them unless
+    /// given a lower priority.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<crate::permission::PermissionRule>,
 }
 
 /// Compaction configuration.
@@ -1048,6 +1434,281 @@ pub struct CompactionConfig {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub prune: Option<bool>,
+
+    /// How older context is condensed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<CompactionStrategy>,
+
+    /// Fraction of the usable context window (0.0-1.0) at which automatic
+    /// compaction starts. Default: 1.0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+
+    /// Number of recent user turns that are never condensed. Default: 2.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preserve_turns: Option<usize>,
+
+    /// Show what will be condensed before compacting. Default: true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<bool>,
+
+    /// Embedding model (provider/model) used to index the session before
+    /// compaction, so relevant compacted context can be retrieved for later
+    /// prompts. Retrieval is off when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+
+    /// Base URL of an OpenAI-compatible embeddings server, e.g. a local
+    /// Ollama, used instead of the provider's API.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedding_url: Option<String>,
+
+    /// Maximum tokens of retrieved context added to a prompt. Default: 4000.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retrieval_tokens: Option<u32>,
+}
+
+impl CompactionConfig {
+    /// Layer `other` on top of this config, field by field.
+    pub fn overlay(&self, other: &Self) -> Self {
+        Self {
+            auto: other.auto.or(self.auto),
+            prune: other.prune.or(self.prune),
+            strategy: other.strategy.or(self.strategy),
+            threshold: other.threshold.or(self.threshold),
+            preserve_turns: other.preserve_turns.or(self.preserve_turns),
+            preview: other.preview.or(self.preview),
+            embedding_model: other
+                .embedding_model
+                .clone()
+                .or_else(|| self.embedding_model.clone()),
+            embedding_url: other
+                .embedding_url
+                .clone()
+                .or_else(|| self.embedding_url.clone()),
+            retrieval_tokens: other.retrieval_tokens.or(self.retrieval_tokens),
+        }
+    }
+}
+
+/// Share server settings.
+///
+/// Points sharing at a self-hosted share server, with defaults for the
+/// links it creates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareServerConfig {
+    /// Base URL of the share server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Bearer token sent to the share server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+
+    /// Default link lifetime, e.g. "12h" or "7d". Links don't expire by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+}
+
+/// A task run on a schedule in headless mode.
+///
+/// Each run is stored as a session tagged `scheduled`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduledTaskConfig {
+    /// Five-field cron expression, e.g. "0 3 * * *", or an alias such as
+    /// "@daily" or "@weekly".
+    pub cron: String,
+
+    /// Prompt to send.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+
+    /// Custom command to run instead of a prompt, e.g. "deps-update --major".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+
+    /// Agent to run the task with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+
+    /// Model to run the task with (provider/model).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// URL to POST the result to after each run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+
+    /// Set to false to pause the task.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+/// OpenTelemetry export settings.
+///
+/// Traces and metrics are sent to an OTLP/HTTP collector. Nothing is sent
+/// unless `enabled` is true.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Export traces and metrics. Default: false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// Base URL of the OTLP/HTTP collector, e.g. "http://localhost:4318".
+    /// Falls back to the standard `OTEL_EXPORTER_OTLP_*` environment variables.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+
+    /// Headers sent with every export, e.g. for authentication.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+
+    /// Service name reported to the collector. Default: "wonopcode".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_name: Option<String>,
+
+    /// Extra resource attributes, e.g. the CI runner or fleet name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, String>>,
+
+    /// Seconds between metric exports. Default: 60.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_interval: Option<u64>,
+}
+
+/// Per-session budget limits.
+///
+/// A warning is shown at 80% of any limit; at 100% the agent pauses until the
+/// user confirms it may continue.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetConfig {
+    /// Maximum cost in USD.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+
+    /// Maximum input plus output tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+
+    /// Maximum number of tool calls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<u32>,
+}
+
+/// Spending alerts and limits across sessions.
+///
+/// Spend is persisted, so it adds up across sessions, restarts and projects.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpendConfig {
+    /// Spend today, across all projects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub daily: Option<SpendLimitConfig>,
+
+    /// Spend this month, across all projects.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub monthly: Option<SpendLimitConfig>,
+
+    /// Spend today, in the current project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_daily: Option<SpendLimitConfig>,
+}
+
+/// Alert and hard limit for one spending period.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpendLimitConfig {
+    /// Amount in USD at which to alert, once per period.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alert: Option<f64>,
+
+    /// Amount in USD at which new prompts are blocked until acknowledged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<f64>,
+}
+
+/// Multi-agent orchestration settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrchestrationConfig {
+    /// Maximum number of agents running at the same time (default: 4).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_parallel: Option<usize>,
+}
+
+/// Automated pull request review settings, for `wonopcode pr review` and
+/// pull request events in GitHub Actions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReviewConfig {
+    /// Agent that reviews (default: "plan", which can't edit files).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+
+    /// Least severe finding posted as a comment (default: "warning").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_severity: Option<ReviewSeverity>,
+
+    /// Findings at least this severe request changes (default: "error").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_changes_at: Option<ReviewSeverity>,
+}
+
+/// Severity of a review finding, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Issue triage settings, for issues labeled for triage on a forge.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriageConfig {
+    /// Label that asks for triage (default: "wonopcode-triage"). It is
+    /// removed once the issue is triaged.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// Agent that triages (default: "build", to reproduce the issue; its
+    /// changes are thrown away).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+
+    /// Apply the labels the agent suggests, out of the repository's
+    /// labels (default: true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub apply_labels: Option<bool>,
+}
+
+/// Context compaction strategy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionStrategy {
+    /// Replace the oldest messages with an AI-generated summary.
+    #[default]
+    SummarizeOldest,
+    /// Drop old tool outputs first, summarizing only if that isn't enough.
+    DropToolOutputs,
+    /// Keep the older turns most relevant to the current request and drop the rest.
+    SemanticRetrieval,
+}
+
+impl CompactionStrategy {
+    /// Name used in config files.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompactionStrategy::SummarizeOldest => "summarize_oldest",
+            CompactionStrategy::DropToolOutputs => "drop_tool_outputs",
+            CompactionStrategy::SemanticRetrieval => "semantic_retrieval",
+        }
+    }
 }
 
 /// Enterprise configuration.
@@ -1075,7 +1736,7 @@ pub struct SandboxConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enabled: Option<bool>,
 
-    /// Sandbox runtime type: "auto", "docker", "podman", "lima", "none".
+    /// Sandbox runtime type: "auto", "docker", "podman", "lima", "wasm", "none".
     #[serde(skip_serializing_if = "Option::is_none")]
     pub runtime: Option<String>,
 
@@ -1102,6 +1763,75 @@ pub struct SandboxConfig {
     /// Keep sandbox running between commands.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub keep_alive: Option<bool>,
+
+    /// Hosts the commands matching a pattern may or may not reach.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts: Option<Vec<wonopcode_sandbox::HostRule>>,
+}
+
+impl SandboxConfig {
+    /// The settings for the sandbox runtime.
+    pub fn to_runtime_config(&self) -> wonopcode_sandbox::SandboxConfig {
+        use wonopcode_sandbox::{MountConfig, NetworkPolicy, ResourceLimits, SandboxRuntimeType};
+
+        // Parse runtime type
+        let runtime = match self.runtime.as_deref() {
+            Some("docker") => SandboxRuntimeType::Docker,
+            Some("podman") => SandboxRuntimeType::Podman,
+            Some("lima") => SandboxRuntimeType::Lima,
+            Some("wasm") => SandboxRuntimeType::Wasm,
+            Some("none") => SandboxRuntimeType::None,
+            _ => SandboxRuntimeType::Auto,
+        };
+
+        // Parse network policy
+        let network = match self.network.as_deref() {
+            Some("full") => NetworkPolicy::Full,
+            Some("none") => NetworkPolicy::None,
+            _ => NetworkPolicy::Limited,
+        };
+
+        // Build resource limits
+        let resources = if let Some(res) = &self.resources {
+            ResourceLimits {
+                memory: res.memory.clone().unwrap_or_else(|| "2G".to_string()),
+                cpus: res.cpus.unwrap_or(2.0),
+                disk: None,
+                pids: res.pids.unwrap_or(256),
+                readonly_rootfs: false,
+            }
+        } else {
+            ResourceLimits::default()
+        };
+
+        // Build mount config
+        let mounts = if let Some(m) = &self.mounts {
+            MountConfig {
+                workspace_writable: m.workspace_writable.unwrap_or(true),
+                readonly: std::collections::HashMap::new(),
+                persist_caches: m.persist_caches.unwrap_or(true),
+                workspace_path: m
+                    .workspace_path
+                    .clone()
+                    .unwrap_or_else(|| "/workspace".to_string()),
+            }
+        } else {
+            MountConfig::default()
+        };
+
+        wonopcode_sandbox::SandboxConfig {
+            enabled: self.enabled.unwrap_or(false),
+            runtime,
+            image: self.image.clone(),
+            resources,
+            network,
+            mounts,
+            bypass_tools: self.bypass_tools.clone().unwrap_or_default(),
+            keep_alive: self.keep_alive.unwrap_or(true),
+            startup_timeout_secs: 60,
+            hosts: self.hosts.clone().unwrap_or_default(),
+        }
+    }
 }
 
 /// Sandbox resource limits.
@@ -1158,6 +1888,113 @@ pub struct UpdateConfig {
     pub check_interval: Option<u32>,
 }
 
+/// Storage configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Soft quota for all stored data (e.g., "2G", "500M").
+    /// Exceeding it shows a warning but never blocks writes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_quota: Option<String>,
+
+    /// Soft quotas per namespace (e.g., `{"message": "1G"}`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace_quotas: Option<HashMap<String, String>>,
+}
+
+/// WASM plugin configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Enable plugin loading (default: true when built with plugin support).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+
+    /// Additional plugin files or directories to load.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<String>>,
+
+    /// Plugin names to skip.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disabled: Option<Vec<String>>,
+
+    /// Fuel budget per plugin call (default: 1,000,000,000).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fuel: Option<u64>,
+}
+
+/// Tool hook configuration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Rules checked before and after every tool call, in order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<Vec<ToolRuleConfig>>,
+
+    /// Shell commands run before a tool call.
+    #[serde(rename = "PreToolUse", skip_serializing_if = "Option::is_none")]
+    pub pre_tool_use: Option<Vec<ShellHookConfig>>,
+
+    /// Shell commands run after a tool call.
+    #[serde(rename = "PostToolUse", skip_serializing_if = "Option::is_none")]
+    pub post_tool_use: Option<Vec<ShellHookConfig>>,
+
+    /// Shell commands run before the first prompt of a session.
+    #[serde(rename = "SessionStart", skip_serializing_if = "Option::is_none")]
+    pub session_start: Option<Vec<ShellHookConfig>>,
+
+    /// Shell commands run when the agent finishes responding.
+    #[serde(rename = "Stop", skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<ShellHookConfig>>,
+}
+
+/// A shell command run for a hook event.
+///
+/// The command receives the event as JSON on stdin and may print a JSON
+/// decision on stdout.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShellHookConfig {
+    /// Tool name glob for tool events; `|` separates alternatives.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+
+    /// Command to run with the system shell.
+    pub command: String,
+
+    /// Timeout in seconds (default: 60).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// A config-declared tool hook.
+///
+/// A rule matches a call when both `tool` and `path` match (an unset field
+/// matches everything).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolRuleConfig {
+    /// Tool name glob (e.g. "edit", "mcp_*").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+
+    /// Glob matched against the call's file path, relative to the project.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Deny matching calls with this reason.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deny: Option<String>,
+
+    /// Arguments to override before a matching call runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_args: Option<serde_json::Map<String, serde_json::Value>>,
+
+    /// Text appended to the output of matching calls.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub append_output: Option<String>,
+}
+
 /// Auto-update mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -1181,7 +2018,60 @@ impl Config {
     /// 4. Project config from working directory
     /// 5. Project `.mcp.json` from working directory
     /// 6. `.vscode/mcp.json` from working directory (VS Code format)
+    ///
+    /// The active profile is then merged over the result; see [`Config::load_with_profile`].
     pub async fn load(project_dir: Option<&Path>) -> CoreResult<(Self, Vec<PathBuf>)> {
+        Self::load_with_profile(project_dir, None).await
+    }
+
+    /// Load configuration from all sources and apply a profile.
+    ///
+    /// The profile is chosen from, in order: `profile`, the profile passed to
+    /// [`select_profile`], the `WONOPCODE_PROFILE` environment variable, then
+    /// the `profile` config key. An unknown profile is an error when chosen by
+    /// one of the first two; otherwise it is ignored with a warning.
+    pub async fn load_with_profile(
+        project_dir: Option<&Path>,
+        profile: Option<&str>,
+    ) -> CoreResult<(Self, Vec<PathBuf>)> {
+        let (mut config, sources) = Self::load_sources(project_dir).await?;
+
+        let selected = profile
+            .map(str::to_string)
+            .or_else(|| SELECTED_PROFILE.get().cloned());
+        let (profile, required) = match selected {
+            Some(name) => (Some(name), true),
+            None => (
+                std::env::var(PROFILE_ENV)
+                    .ok()
+                    .filter(|p| !p.is_empty())
+                    .or_else(|| config.profile.clone()),
+                false,
+            ),
+        };
+
+        let config = match profile {
+            Some(name) if required || config.profile_names().contains(&name) => {
+                tracing::info!(profile = %name, "Using config profile");
+                config.apply_profile(&name)?
+            }
+            Some(name) => {
+                tracing::warn!(
+                    profile = %name,
+                    available = ?config.profile_names(),
+                    "Unknown config profile, using the base config"
+                );
+                config.profile = None;
+                config
+            }
+            None => config,
+        };
+
+        Ok((config, sources))
+    }
+
+    /// Load and merge all config sources, without applying a profile.
+    async fn load_sources(project_dir: Option<&Path>) -> CoreResult<(Self, Vec<PathBuf>)> {
         let mut config = Config::default();
         let mut sources = Vec::new();
 
@@ -1197,6 +2087,9 @@ impl Config {
                 }
             }
 
+            // Global agent files override agents from the global config
+            config.merge_agent_files(&global_dir, &mut sources);
+
             // 2. Load global .mcp.json if it exists
             let global_mcp_path = global_dir.join(".mcp.json");
             if global_mcp_path.exists() {
@@ -1244,6 +2137,9 @@ impl Config {
                 }
             }
 
+            // Project agent files override agents from the project config
+            config.merge_agent_files(&dir.join(".wonopcode"), &mut sources);
+
             // 5. Load project .mcp.json if it exists
             let project_mcp_path = dir.join(".mcp.json");
             if project_mcp_path.exists() {
@@ -1304,6 +2200,29 @@ impl Config {
         Ok((config, sources))
     }
 
+    /// Merge agents defined in agent files under `config_dir`.
+    fn merge_agent_files(&mut self, config_dir: &Path, sources: &mut Vec<PathBuf>) {
+        for dir in agent_dirs(config_dir) {
+            for path in AgentFile::paths_in(&dir) {
+                match AgentFile::load(&path) {
+                    Ok(file) => {
+                        self.agent
+                            .get_or_insert_with(HashMap::new)
+                            .insert(file.name, file.config);
+                        sources.push(path);
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            path = %path.display(),
+                            error = %e,
+                            "Skipping invalid agent file"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     /// Get the global config directory.
     ///
     /// On Unix systems, prefers `~/.config/wonopcode` (XDG standard) over
@@ -1348,10 +2267,38 @@ impl Config {
     /// Load configuration from a file.
     pub async fn load_file(path: &Path) -> CoreResult<Self> {
         let content = tokio::fs::read_to_string(path).await?;
-        let content = substitute_variables(&content, path)?;
+        Self::parse_file_content(&content, path)
+    }
+
+    /// Parse the content of the config file at `path`, e.g. to check an
+    /// edit before saving it.
+    pub fn parse_file_content(content: &str, path: &Path) -> CoreResult<Self> {
+        let content = substitute_variables(content, path)?;
         Self::parse_jsonc(&content, &path.display().to_string())
     }
 
+    /// The config file of the project in `dir`: the existing one, or
+    /// `wonopcode.json`.
+    pub fn project_config_path(dir: &Path) -> PathBuf {
+        ["wonopcode.jsonc", "wonopcode.json"]
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| dir.join("wonopcode.json"))
+    }
+
+    /// The global config file: the existing one, or `config.json`.
+    pub fn global_config_path() -> Option<PathBuf> {
+        let dir = Self::global_config_dir()?;
+        Some(
+            ["config.json", "wonopcode.json", "wonopcode.jsonc"]
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.exists())
+                .unwrap_or_else(|| dir.join("config.json")),
+        )
+    }
+
     /// Save configuration to the project config file.
     /// If project_dir is Some, saves to `{project_dir}/wonopcode.json`.
     /// Otherwise saves to the global config directory.
@@ -1455,6 +2402,9 @@ impl Config {
         if other.snapshot.is_some() {
             self.snapshot = other.snapshot;
         }
+        if other.snapshot_commands.is_some() {
+            self.snapshot_commands = other.snapshot_commands;
+        }
         if other.share.is_some() {
             self.share = other.share;
         }
@@ -1470,6 +2420,12 @@ impl Config {
         if other.instructions.is_some() {
             self.instructions = other.instructions;
         }
+        if other.instruction_max_tokens.is_some() {
+            self.instruction_max_tokens = other.instruction_max_tokens;
+        }
+        if other.profile.is_some() {
+            self.profile = other.profile;
+        }
 
         // Nested structs - merge field by field
         self.tui = match (self.tui, other.tui) {
@@ -1478,24 +2434,94 @@ impl Config {
             (None, other) => other,
         };
         self.server = merge_option(self.server, other.server);
-        self.keybinds = merge_option(self.keybinds, other.keybinds);
+        self.keybinds = match (self.keybinds, other.keybinds) {
+            (Some(base), Some(other)) => Some(base.merge(other)),
+            (base, None) => base,
+            (None, other) => other,
+        };
         self.permission = merge_option(self.permission, other.permission);
         self.compaction = merge_option(self.compaction, other.compaction);
         self.enterprise = merge_option(self.enterprise, other.enterprise);
         self.experimental = merge_option(self.experimental, other.experimental);
         self.sandbox = merge_option(self.sandbox, other.sandbox);
         self.update = merge_option(self.update, other.update);
+        self.storage = merge_option(self.storage, other.storage);
+        self.plugins = merge_option(self.plugins, other.plugins);
+        self.hooks = merge_option(self.hooks, other.hooks);
+        self.budget = merge_option(self.budget, other.budget);
+        self.spend = merge_option(self.spend, other.spend);
+        self.share_server = merge_option(self.share_server, other.share_server);
+        self.telemetry = merge_option(self.telemetry, other.telemetry);
+        self.orchestration = merge_option(self.orchestration, other.orchestration);
+        self.review = merge_option(self.review, other.review);
+        self.triage = merge_option(self.triage, other.triage);
 
         // HashMaps - merge entries
         self.command = merge_hashmap(self.command, other.command);
+        self.formatter = merge_hashmap(self.formatter, other.formatter);
         self.agent = merge_hashmap(self.agent, other.agent);
+        self.schedule = merge_hashmap(self.schedule, other.schedule);
         self.provider = merge_hashmap(self.provider, other.provider);
         self.mcp = merge_hashmap(self.mcp, other.mcp);
         self.tools = merge_hashmap(self.tools, other.tools);
+        self.prompt_variables = merge_hashmap(self.prompt_variables, other.prompt_variables);
+
+        // Profiles - merge profiles with the same name
+        self.profiles = match (self.profiles, other.profiles) {
+            (Some(mut base), Some(other)) => {
+                for (name, profile) in other {
+                    let merged = match base.remove(&name) {
+                        Some(existing) => existing.merge(profile),
+                        None => profile,
+                    };
+                    base.insert(name, merged);
+                }
+                Some(base)
+            }
+            (base, None) => base,
+            (None, other) => other,
+        };
 
         self
     }
 
+    /// Names of the configured profiles, sorted.
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .profiles
+            .as_ref()
+            .map(|p| p.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Merge the named profile over this config.
+    ///
+    /// Returns an error if the profile doesn't exist.
+    pub fn apply_profile(self, name: &str) -> CoreResult<Self> {
+        let Some(mut overlay) = self.profiles.as_ref().and_then(|p| p.get(name)).cloned() else {
+            let available = self.profile_names();
+            let message = if available.is_empty() {
+                format!("unknown profile '{name}' (no profiles configured)")
+            } else {
+                format!(
+                    "unknown profile '{name}' (available: {})",
+                    available.join(", ")
+                )
+            };
+            return Err(ConfigError::Validation { message }.into());
+        };
+
+        // Profiles don't nest
+        overlay.profile = None;
+        overlay.profiles = None;
+
+        let mut config = self.merge(overlay);
+        config.profile = Some(name.to_string());
+        Ok(config)
+    }
+
     /// Get the model ID parts (provider, model).
     pub fn parse_model(model: &str) -> Option<(&str, &str)> {
         model.split_once('/')
@@ -1704,6 +2730,99 @@ mod tests {
         assert_eq!(merged.username, Some("alice".to_string())); // added
     }
 
+    #[test]
+    fn test_apply_profile() {
+        let config = Config::parse_jsonc(
+            r#"{
+                "model": "anthropic/claude-sonnet-4-5",
+                "theme": "dark",
+                "profiles": {
+                    "demo": {
+                        "model": "openai/gpt-4o",
+                        "permission": { "bash": "deny" }
+                    }
+                }
+            }"#,
+            "test",
+        )
+        .unwrap();
+
+        assert_eq!(config.profile_names(), vec!["demo".to_string()]);
+
+        let demo = config.clone().apply_profile("demo").unwrap();
+        assert_eq!(demo.model, Some("openai/gpt-4o".to_string()));
+        assert_eq!(demo.theme, Some("dark".to_string()));
+        assert!(demo.permission.is_some());
+        assert_eq!(demo.profile, Some("demo".to_string()));
+
+        let err = config.apply_profile("work").unwrap_err();
+        assert!(err.to_string().contains("available: demo"));
+    }
+
+    #[test]
+    fn test_merge_profiles() {
+        let global = Config::parse_jsonc(
+            r#"{"profiles": {"work": {"model": "anthropic/claude-sonnet-4-5", "theme": "light"}}}"#,
+            "global",
+        )
+        .unwrap();
+        let project = Config::parse_jsonc(
+            r#"{"profiles": {"work": {"theme": "dark"}, "personal": {}}}"#,
+            "project",
+        )
+        .unwrap();
+
+        let merged = global.merge(project);
+        assert_eq!(merged.profile_names(), vec!["personal", "work"]);
+        let work = merged.apply_profile("work").unwrap();
+        assert_eq!(work.model, Some("anthropic/claude-sonnet-4-5".to_string()));
+        assert_eq!(work.theme, Some("dark".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_load_with_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("wonopcode.json"),
+            r#"{
+                "profile": "work",
+                "profiles": {
+                    "work": { "model": "anthropic/claude-sonnet-4-5" },
+                    "personal": { "model": "openai/gpt-4o" }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let (config, _) = Config::load_with_profile(Some(dir.path()), Some("personal"))
+            .await
+            .unwrap();
+        assert_eq!(config.model, Some("openai/gpt-4o".to_string()));
+        assert_eq!(config.profile, Some("personal".to_string()));
+
+        assert!(Config::load_with_profile(Some(dir.path()), Some("missing"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_ignores_unknown_config_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("wonopcode.json"),
+            r#"{
+                "model": "openai/gpt-4o",
+                "profile": "missing",
+                "profiles": { "work": { "model": "anthropic/claude-sonnet-4-5" } }
+            }"#,
+        )
+        .unwrap();
+
+        let (config, _) = Config::load(Some(dir.path())).await.unwrap();
+        assert_eq!(config.model, Some("openai/gpt-4o".to_string()));
+        assert_eq!(config.profile, None);
+    }
+
     #[test]
     fn test_parse_model() {
         assert_eq!(
@@ -2168,6 +3287,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn permission_config_parses_rules() {
+        let json = r#"{
+            "permission": {
+                "bash": "allow",
+                "rules": [
+                    { "tool": "bash", "command": "^sudo ", "decision": "deny", "priority": 10 },
+                    { "tool": "edit", "path": "migrations/**", "decision": "ask" }
+                ]
+            }
+        }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        let perm = config.permission.unwrap();
+        assert_eq!(perm.rules.len(), 2);
+        assert_eq!(perm.rules[0].command.as_deref(), Some("^sudo "));
+        assert_eq!(perm.rules[0].priority, 10);
+        assert_eq!(perm.rules[1].path.as_deref(), Some("migrations/**"));
+    }
+
     // =========================================================================
     // UX-Critical: Sandbox Configuration Tests
     // If these fail, isolated execution won't work correctly
@@ -2339,9 +3477,18 @@ mod tests {
             syntax_highlighting: Some(true),
             code_backgrounds: Some(false),
             tables: Some(true),
+            message_usage: Some(true),
+            images: Some(ImageMode::Auto),
+            auto_review: Some(false),
+            notifications: Some(NotificationMode::Off),
+            locale: Some("en".to_string()),
             streaming_fps: Some(30),
             max_messages: Some(100),
             low_memory_mode: Some(false),
+            high_contrast: Some(false),
+            reduce_motion: Some(false),
+            ascii_borders: Some(false),
+            screen_reader: Some(false),
             enable_test_commands: Some(false),
             test_model_enabled: Some(false),
             test_emulate_thinking: Some(false),
@@ -2359,9 +3506,18 @@ mod tests {
             syntax_highlighting: Some(false),
             code_backgrounds: Some(true),
             tables: Some(false),
+            message_usage: Some(false),
+            images: Some(ImageMode::Off),
+            auto_review: Some(true),
+            notifications: Some(NotificationMode::Osc777),
+            locale: Some("ja".to_string()),
             streaming_fps: Some(60),
             max_messages: Some(200),
             low_memory_mode: Some(true),
+            high_contrast: Some(true),
+            reduce_motion: Some(true),
+            ascii_borders: Some(true),
+            screen_reader: Some(true),
             enable_test_commands: Some(true),
             test_model_enabled: Some(true),
             test_emulate_thinking: Some(true),
@@ -2380,9 +3536,18 @@ mod tests {
         assert_eq!(merged.syntax_highlighting, Some(false));
         assert_eq!(merged.code_backgrounds, Some(true));
         assert_eq!(merged.tables, Some(false));
+        assert_eq!(merged.message_usage, Some(false));
+        assert_eq!(merged.images, Some(ImageMode::Off));
+        assert_eq!(merged.auto_review, Some(true));
+        assert_eq!(merged.notifications, Some(NotificationMode::Osc777));
+        assert_eq!(merged.locale.as_deref(), Some("ja"));
         assert_eq!(merged.streaming_fps, Some(60));
         assert_eq!(merged.max_messages, Some(200));
         assert_eq!(merged.low_memory_mode, Some(true));
+        assert_eq!(merged.high_contrast, Some(true));
+        assert_eq!(merged.reduce_motion, Some(true));
+        assert_eq!(merged.ascii_borders, Some(true));
+        assert_eq!(merged.screen_reader, Some(true));
         assert_eq!(merged.enable_test_commands, Some(true));
         assert_eq!(merged.test_model_enabled, Some(true));
         assert_eq!(merged.test_emulate_thinking, Some(true));
@@ -2401,9 +3566,18 @@ mod tests {
         assert!(config.syntax_highlighting.is_none());
         assert!(config.code_backgrounds.is_none());
         assert!(config.tables.is_none());
+        assert!(config.message_usage.is_none());
+        assert!(config.images.is_none());
+        assert!(config.auto_review.is_none());
+        assert!(config.notifications.is_none());
+        assert!(config.locale.is_none());
         assert!(config.streaming_fps.is_none());
         assert!(config.max_messages.is_none());
         assert!(config.low_memory_mode.is_none());
+        assert!(config.high_contrast.is_none());
+        assert!(config.reduce_motion.is_none());
+        assert!(config.ascii_borders.is_none());
+        assert!(config.screen_reader.is_none());
         assert!(config.enable_test_commands.is_none());
         assert!(config.test_model_enabled.is_none());
         assert!(config.test_emulate_thinking.is_none());
@@ -2616,6 +3790,43 @@ mod tests {
         assert!(config.extra.is_empty());
     }
 
+    #[test]
+    fn keybinds_config_merge() {
+        let global: KeybindsConfig = serde_json::from_str(
+            r#"{"leader": "ctrl+b", "session_new": "<leader>o", "model_list": "<leader>m"}"#,
+        )
+        .unwrap();
+        let project: KeybindsConfig =
+            serde_json::from_str(r#"{"session_new": "ctrl+n", "agent_list": "none"}"#).unwrap();
+
+        let merged = global.merge(project);
+        assert_eq!(merged.leader.as_deref(), Some("ctrl+b"));
+        let bindings = merged.bindings();
+        assert_eq!(bindings.len(), 3);
+        assert_eq!(bindings["session_new"], "ctrl+n");
+        assert_eq!(bindings["model_list"], "<leader>m");
+        assert_eq!(bindings["agent_list"], "none");
+    }
+
+    #[test]
+    fn keybinds_config_menus() {
+        let global: KeybindsConfig = serde_json::from_str(
+            r#"{"menus": {"g": {"title": "git", "keys": {"s": "/git"}}, "r": {"keys": {"r": "/review"}}}}"#,
+        )
+        .unwrap();
+        let project: KeybindsConfig = serde_json::from_str(
+            r#"{"session_new": "ctrl+n", "menus": {"g": {"keys": {"d": "/preview diff"}}}}"#,
+        )
+        .unwrap();
+        assert!(!project.bindings().contains_key("menus"));
+
+        let merged = global.merge(project);
+        assert_eq!(merged.menus.len(), 2);
+        assert_eq!(merged.menus["g"].title, None);
+        assert_eq!(merged.menus["g"].keys["d"], "/preview diff");
+        assert_eq!(merged.menus["r"].keys["r"], "/review");
+    }
+
     #[test]
     fn server_config_default() {
         let config = ServerConfig::default();
@@ -2686,6 +3897,20 @@ mod tests {
         let config = CompactionConfig::default();
         assert!(config.auto.is_none());
         assert!(config.prune.is_none());
+        assert!(config.strategy.is_none());
+    }
+
+    #[test]
+    fn compaction_config_overlay() {
+        let global: CompactionConfig =
+            serde_json::from_str(r#"{"strategy": "drop_tool_outputs", "threshold": 0.8}"#).unwrap();
+        let agent: CompactionConfig =
+            serde_json::from_str(r#"{"strategy": "semantic_retrieval"}"#).unwrap();
+
+        let merged = global.overlay(&agent);
+        assert_eq!(merged.strategy, Some(CompactionStrategy::SemanticRetrieval));
+        assert_eq!(merged.threshold, Some(0.8));
+        assert!(merged.auto.is_none());
     }
 
     #[test]
@@ -2711,6 +3936,7 @@ mod tests {
         assert!(config.mounts.is_none());
         assert!(config.bypass_tools.is_none());
         assert!(config.keep_alive.is_none());
+        assert!(config.hosts.is_none());
     }
 
     #[test]
@@ -0,0 +1,382 @@
+//! Editing config files in place.
+//!
+//! [`set_value`] changes one key of a JSONC config file and leaves the rest
+//! of the text alone, so comments, key order and formatting survive
+//! `wonopcode config set`. Keys are dot-separated paths such as
+//! `tui.theme` or `provider.anthropic.options.timeout`.
+
+use crate::error::ConfigError;
+use serde_json::Value;
+
+/// Look up a dot-separated `key` in a JSON value.
+pub fn get_value<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(value, |value, segment| value.as_object()?.get(segment))
+}
+
+/// Parse a value given on the command line: JSON if it is, else a string.
+///
+/// `true`, `8192` and `["a"]` are JSON; `dark` is the string `"dark"`.
+pub fn parse_value(input: &str) -> Value {
+    serde_json::from_str(input).unwrap_or_else(|_| Value::String(input.to_string()))
+}
+
+/// Set `key` to `value` in the JSONC `content`, returning the new text.
+///
+/// Missing objects along the key are created. An empty `content` is treated
+/// as an empty object.
+pub fn set_value(content: &str, key: &str, value: &Value) -> Result<String, ConfigError> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(invalid(format!("invalid key '{key}'")));
+    }
+    let content = if content.trim().is_empty() {
+        "{}\n"
+    } else {
+        content
+    };
+
+    let scanner = Scanner {
+        src: content.as_bytes(),
+    };
+    let mut open = scanner.skip(0);
+    if scanner.src.get(open) != Some(&b'{') {
+        return Err(invalid("the config is not a JSON object".to_string()));
+    }
+
+    for (i, segment) in segments.iter().enumerate() {
+        let (members, close) = scanner.object(open)?;
+        let rest = &segments[i + 1..];
+        let Some(member) = members.iter().find(|m| m.key == *segment) else {
+            let edits = scanner.insert_member(open, close, &members, segment, &nest(rest, value));
+            return Ok(apply(content, edits));
+        };
+        if rest.is_empty() || scanner.src[member.value.0] != b'{' {
+            let indent = scanner.line_indent(member.start);
+            let edit = (
+                member.value.0,
+                member.value.1,
+                render(&nest(rest, value), indent),
+            );
+            return Ok(apply(content, vec![edit]));
+        }
+        open = member.value.0;
+    }
+    unreachable!("the loop returns on the last segment")
+}
+
+/// `value` inside objects for the keys in `segments`.
+fn nest(segments: &[&str], value: &Value) -> Value {
+    segments.iter().rev().fold(value.clone(), |value, segment| {
+        let mut object = serde_json::Map::new();
+        object.insert(segment.to_string(), value);
+        Value::Object(object)
+    })
+}
+
+/// `value` as pretty JSON, continuing lines at `indent`.
+fn render(value: &Value, indent: &str) -> String {
+    let json = serde_json::to_string_pretty(value).unwrap_or_default();
+    json.replace('\n', &format!("\n{indent}"))
+}
+
+fn invalid(message: String) -> ConfigError {
+    ConfigError::Validation {
+        message: format!("cannot edit config: {message}"),
+    }
+}
+
+/// Replacements of `content[start..end]`, applied back to front. Insertions
+/// at the same position end up in the order given.
+fn apply(content: &str, edits: Vec<(usize, usize, String)>) -> String {
+    let mut edits: Vec<_> = edits.into_iter().enumerate().collect();
+    edits.sort_by_key(|(i, (start, _, _))| std::cmp::Reverse((*start, *i)));
+    let mut out = content.to_string();
+    for (_, (start, end, text)) in edits {
+        out.replace_range(start..end, &text);
+    }
+    out
+}
+
+/// A member of an object: its key, where the key starts and the value span.
+struct Member {
+    key: String,
+    start: usize,
+    value: (usize, usize),
+}
+
+/// Finds the parts of JSONC text, skipping comments.
+struct Scanner<'a> {
+    src: &'a [u8],
+}
+
+impl Scanner<'_> {
+    /// The position of the next token at or after `pos`.
+    fn skip(&self, mut pos: usize) -> usize {
+        loop {
+            match (self.src.get(pos), self.src.get(pos + 1)) {
+                (Some(c), _) if c.is_ascii_whitespace() => pos += 1,
+                (Some(b'/'), Some(b'/')) => {
+                    while self.src.get(pos).is_some_and(|c| *c != b'\n') {
+                        pos += 1;
+                    }
+                }
+                (Some(b'/'), Some(b'*')) => {
+                    pos += 2;
+                    while pos < self.src.len() && !self.src[pos..].starts_with(b"*/") {
+                        pos += 1;
+                    }
+                    pos += 2;
+                }
+                _ => return pos.min(self.src.len()),
+            }
+        }
+    }
+
+    fn error(&self, pos: usize, expected: &str) -> ConfigError {
+        let line = self.src[..pos.min(self.src.len())]
+            .iter()
+            .filter(|c| **c == b'\n')
+            .count()
+            + 1;
+        invalid(format!("expected {expected} on line {line}"))
+    }
+
+    /// The members of the object opening at `open`, and its closing brace.
+    fn object(&self, open: usize) -> Result<(Vec<Member>, usize), ConfigError> {
+        let mut members = Vec::new();
+        let mut pos = self.skip(open + 1);
+        loop {
+            match self.src.get(pos) {
+                Some(b'}') => return Ok((members, pos)),
+                Some(b'"') => {}
+                _ => return Err(self.error(pos, "a key or '}'")),
+            }
+            let key_end = self.string(pos)?;
+            let key = std::str::from_utf8(&self.src[pos..key_end])
+                .ok()
+                .and_then(|s| serde_json::from_str::<String>(s).ok())
+                .ok_or_else(|| self.error(pos, "a key"))?;
+            let colon = self.skip(key_end);
+            if self.src.get(colon) != Some(&b':') {
+                return Err(self.error(colon, "':'"));
+            }
+            let value_start = self.skip(colon + 1);
+            let value_end = self.value(value_start)?;
+            members.push(Member {
+                key,
+                start: pos,
+                value: (value_start, value_end),
+            });
+
+            pos = self.skip(value_end);
+            match self.src.get(pos) {
+                Some(b',') => pos = self.skip(pos + 1),
+                Some(b'}') => {}
+                _ => return Err(self.error(pos, "',' or '}'")),
+            }
+        }
+    }
+
+    /// The end of the value starting at `pos`.
+    fn value(&self, pos: usize) -> Result<usize, ConfigError> {
+        match self.src.get(pos) {
+            Some(b'{') => Ok(self.object(pos)?.1 + 1),
+            Some(b'[') => {
+                let mut pos = self.skip(pos + 1);
+                loop {
+                    if self.src.get(pos) == Some(&b']') {
+                        return Ok(pos + 1);
+                    }
+                    pos = self.skip(self.value(pos)?);
+                    match self.src.get(pos) {
+                        Some(b',') => pos = self.skip(pos + 1),
+                        Some(b']') => {}
+                        _ => return Err(self.error(pos, "',' or ']'")),
+                    }
+                }
+            }
+            Some(b'"') => self.string(pos),
+            Some(_) => {
+                let end = pos
+                    + self.src[pos..]
+                        .iter()
+                        .position(|c| c.is_ascii_whitespace() || b",}]/".contains(c))
+                        .unwrap_or(self.src.len() - pos);
+                if end == pos {
+                    return Err(self.error(pos, "a value"));
+                }
+                Ok(end)
+            }
+            None => Err(self.error(pos, "a value")),
+        }
+    }
+
+    /// The end of the string starting at `pos`.
+    fn string(&self, pos: usize) -> Result<usize, ConfigError> {
+        let mut i = pos + 1;
+        while let Some(c) = self.src.get(i) {
+            match c {
+                b'\\' => i += 2,
+                b'"' => return Ok(i + 1),
+                _ => i += 1,
+            }
+        }
+        Err(self.error(pos, "the end of the string"))
+    }
+
+    /// The whitespace starting the line of `pos`.
+    fn line_indent(&self, pos: usize) -> &str {
+        let start = self.src[..pos]
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map_or(0, |i| i + 1);
+        let len = self.src[start..]
+            .iter()
+            .take_while(|c| **c == b' ' || **c == b'\t')
+            .count();
+        std::str::from_utf8(&self.src[start..start + len]).unwrap_or_default()
+    }
+
+    /// Edits adding `key: value` at the end of the object from `open` to
+    /// `close`, in the style of its members.
+    fn insert_member(
+        &self,
+        open: usize,
+        close: usize,
+        members: &[Member],
+        key: &str,
+        value: &Value,
+    ) -> Vec<(usize, usize, String)> {
+        let key = Value::String(key.to_string());
+        let Some(last) = members.last() else {
+            let indent = self.line_indent(open);
+            let inner = format!("{indent}  ");
+            let text = format!("{{\n{inner}{key}: {}\n{indent}}}", render(value, &inner));
+            return vec![(open, close + 1, text)];
+        };
+
+        let trailing_comma = self.src.get(self.skip(last.value.1)) == Some(&b',');
+        let mut edits = Vec::new();
+        if !trailing_comma {
+            edits.push((last.value.1, last.value.1, ",".to_string()));
+        }
+
+        let first = &members[0];
+        if !self.src[open..first.start].contains(&b'\n') {
+            // All on one line
+            let at = if trailing_comma {
+                self.skip(last.value.1) + 1
+            } else {
+                last.value.1
+            };
+            let text = format!(" {key}: {}", render(value, ""));
+            edits.push((at, at, text));
+            return edits;
+        }
+
+        let indent = self.line_indent(first.start);
+        let at = close
+            - self.src[..close]
+                .iter()
+                .rev()
+                .take_while(|c| c.is_ascii_whitespace())
+                .count();
+        let comma = if trailing_comma { "," } else { "" };
+        let text = format!("\n{indent}{key}: {}{comma}", render(value, indent));
+        edits.push((at, at, text));
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONFIG: &str = r#"{
+  // Pick a theme
+  "theme": "dark",
+  "tui": {
+    "mouse": true /* for scrolling */
+  }
+}
+"#;
+
+    #[test]
+    fn test_get_value() {
+        let value = json!({"tui": {"mouse": true}, "theme": "dark"});
+        assert_eq!(get_value(&value, "tui.mouse"), Some(&json!(true)));
+        assert_eq!(get_value(&value, "theme"), Some(&json!("dark")));
+        assert_eq!(get_value(&value, "theme.x"), None);
+        assert_eq!(get_value(&value, "missing"), None);
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("true"), json!(true));
+        assert_eq!(parse_value("8192"), json!(8192));
+        assert_eq!(parse_value(r#"["a"]"#), json!(["a"]));
+        assert_eq!(parse_value("dark"), json!("dark"));
+    }
+
+    #[test]
+    fn test_replace_keeps_comments() {
+        let out = set_value(CONFIG, "theme", &json!("light")).unwrap();
+        assert_eq!(out, CONFIG.replace(r#""dark""#, r#""light""#));
+
+        let out = set_value(CONFIG, "tui.mouse", &json!(false)).unwrap();
+        assert!(out.contains(r#""mouse": false /* for scrolling */"#));
+        assert!(out.contains("// Pick a theme"));
+    }
+
+    #[test]
+    fn test_insert_keys() {
+        let out = set_value(CONFIG, "tui.paste", &json!("bracketed")).unwrap();
+        assert_eq!(
+            out,
+            r#"{
+  // Pick a theme
+  "theme": "dark",
+  "tui": {
+    "mouse": true, /* for scrolling */
+    "paste": "bracketed"
+  }
+}
+"#
+        );
+
+        let out = set_value(CONFIG, "server.port", &json!(3000)).unwrap();
+        assert!(out.ends_with(
+            r#"  },
+  "server": {
+    "port": 3000
+  }
+}
+"#
+        ));
+    }
+
+    #[test]
+    fn test_insert_in_other_styles() {
+        assert_eq!(
+            set_value("", "theme", &json!("dark")).unwrap(),
+            "{\n  \"theme\": \"dark\"\n}\n"
+        );
+        assert_eq!(
+            set_value(r#"{"a": 1}"#, "b", &json!(2)).unwrap(),
+            r#"{"a": 1, "b": 2}"#
+        );
+        assert_eq!(
+            set_value("{\n  \"a\": 1,\n}", "b", &json!(2)).unwrap(),
+            "{\n  \"a\": 1,\n  \"b\": 2,\n}"
+        );
+    }
+
+    #[test]
+    fn test_invalid_content() {
+        assert!(set_value("[1, 2]", "a", &json!(1)).is_err());
+        assert!(set_value(r#"{"a": }"#, "a", &json!(1)).is_err());
+        assert!(set_value("{}", "a..b", &json!(1)).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Configuration hot-reload.
+//!
+//! Polls the global and project config files and agent files and reloads
+//! the instance configuration when one of them changes. Changes to keys in
+//! [`RELOADABLE_KEYS`] are applied by listeners of [`ConfigChanged`]; any other
+//! changed key is reported as needing a restart.
+//!
+//! An invalid edit is logged and the previous configuration is kept.
+
+use crate::agent::{agent_dirs, AgentFile};
+use crate::bus::{Bus, ConfigChanged};
+use crate::config::Config;
+use crate::instance::Instance;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Default interval between checks.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Top-level config keys that can be applied without a restart.
+pub const RELOADABLE_KEYS: &[&str] = &[
+    "permission",
+    "mcp",
+    "theme",
+    "hooks",
+    "budget",
+    "spend",
+    "formatter",
+    "instructions",
+    "instruction_max_tokens",
+    "agent",
+    "schedule",
+];
+
+/// Top-level config keys that never need a restart (bookkeeping only).
+const IGNORED_KEYS: &[&str] = &["$schema", "profiles"];
+
+/// Difference between two configurations, by top-level key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigDelta {
+    /// Changed keys that are applied at runtime.
+    pub applied: Vec<String>,
+    /// Changed keys that need a restart.
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigDelta {
+    /// Compare two configurations.
+    pub fn between(old: &Config, new: &Config) -> Self {
+        let old = serde_json::to_value(old).unwrap_or_default();
+        let new = serde_json::to_value(new).unwrap_or_default();
+        let empty = serde_json::Map::new();
+        let old = old.as_object().unwrap_or(&empty);
+        let new = new.as_object().unwrap_or(&empty);
+
+        let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut delta = Self::default();
+        for key in keys {
+            if old.get(key) == new.get(key) || IGNORED_KEYS.contains(&key.as_str()) {
+                continue;
+            }
+            if RELOADABLE_KEYS.contains(&key.as_str()) {
+                delta.applied.push(key.clone());
+            } else {
+                delta.restart_required.push(key.clone());
+            }
+        }
+        delta
+    }
+
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty()
+    }
+}
+
+impl From<ConfigDelta> for ConfigChanged {
+    fn from(delta: ConfigDelta) -> Self {
+        Self {
+            applied: delta.applied,
+            restart_required: delta.restart_required,
+        }
+    }
+}
+
+/// Config files that [`Config::load`] may read, whether or not they exist yet.
+///
+/// Agent directories are included along with the agent files currently in
+/// them, so adding, removing and editing agent files are all noticed.
+pub fn watched_paths(project_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut config_dirs = Vec::new();
+    if let Some(global_dir) = Config::global_config_dir() {
+        for name in [
+            "config.json",
+            "wonopcode.json",
+            "wonopcode.jsonc",
+            ".mcp.json",
+        ] {
+            paths.push(global_dir.join(name));
+        }
+        config_dirs.push(global_dir);
+    }
+    for name in ["wonopcode.jsonc", "wonopcode.json", ".mcp.json"] {
+        paths.push(project_dir.join(name));
+    }
+    paths.push(project_dir.join(".vscode").join("mcp.json"));
+    config_dirs.push(project_dir.join(".wonopcode"));
+
+    for dir in config_dirs.iter().flat_map(|dir| agent_dirs(dir)) {
+        paths.extend(AgentFile::paths_in(&dir));
+        paths.push(dir);
+    }
+    paths
+}
+
+/// Modification time and size of each path, `None` if missing.
+fn fingerprint(paths: &[PathBuf]) -> Vec<Option<(SystemTime, u64)>> {
+    paths
+        .iter()
+        .map(|path| {
+            let meta = std::fs::metadata(path).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        })
+        .collect()
+}
+
+/// Reload the instance config and publish a [`ConfigChanged`] event if it changed.
+///
+/// Returns the delta, or `None` if the new config couldn't be loaded.
+pub async fn reload(instance: &Instance, bus: &Bus) -> Option<ConfigDelta> {
+    let old = instance.config().await;
+    if let Err(e) = instance.reload_config().await {
+        warn!(error = %e, "Config changed but failed to load, keeping previous config");
+        return None;
+    }
+    let new = instance.config().await;
+
+    let delta = ConfigDelta::between(&old, &new);
+    if !delta.is_empty() {
+        info!(
+            applied = ?delta.applied,
+            restart_required = ?delta.restart_required,
+            "Config reloaded"
+        );
+        bus.publish(ConfigChanged::from(delta.clone())).await;
+    }
+    Some(delta)
+}
+
+/// Watch the instance's config files and reload on change.
+///
+/// The task runs until aborted.
+pub fn spawn(instance: Instance, bus: Bus, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last = fingerprint(&watched_paths(instance.directory()));
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+            let current = fingerprint(&watched_paths(instance.directory()));
+            if current == last {
+                continue;
+            }
+            last = current;
+            debug!("Config files changed, reloading");
+            reload(&instance, &bus).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delta_classifies_keys() {
+        let old = Config {
+            theme: Some("dark".to_string()),
+            model: Some("anthropic/claude-sonnet-4-5".to_string()),
+            ..Default::default()
+        };
+        let new = Config {
+            theme: Some("light".to_string()),
+            model: Some("openai/gpt-4o".to_string()),
+            ..Default::default()
+        };
+
+        let delta = ConfigDelta::between(&old, &new);
+        assert_eq!(delta.applied, vec!["theme"]);
+        assert_eq!(delta.restart_required, vec!["model"]);
+        assert!(ConfigDelta::between(&old, &old).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reload_publishes_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("wonopcode.json");
+        std::fs::write(&config_path, r#"{"theme": "dark"}"#).unwrap();
+
+        let instance = Instance::new(dir.path()).await.unwrap();
+        let bus = Bus::new();
+        let mut rx = bus.subscribe::<ConfigChanged>().await;
+
+        std::fs::write(&config_path, r#"{"theme": "light"}"#).unwrap();
+        let delta = reload(&instance, &bus).await.unwrap();
+        assert_eq!(delta.applied, vec!["theme"]);
+        assert_eq!(rx.recv().await.unwrap().applied, vec!["theme"]);
+        assert_eq!(instance.config().await.theme, Some("light".to_string()));
+
+        // Invalid edits keep the previous config
+        std::fs::write(&config_path, "{ not json").unwrap();
+        assert!(reload(&instance, &bus).await.is_none());
+        assert_eq!(instance.config().await.theme, Some("light".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_reload_picks_up_agent_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let instance = Instance::new(dir.path()).await.unwrap();
+        let bus = Bus::new();
+
+        let agents_dir = dir.path().join(".wonopcode").join("agents");
+        std::fs::create_dir_all(&agents_dir).unwrap();
+        let before = fingerprint(&watched_paths(dir.path()));
+        std::fs::write(
+            agents_dir.join("reviewer.md"),
+            "---\ndescription: Reviews code\n---\n\nReview the diff.",
+        )
+        .unwrap();
+        assert_ne!(fingerprint(&watched_paths(dir.path())), before);
+
+        let delta = reload(&instance, &bus).await.unwrap();
+        assert_eq!(delta.applied, vec!["agent"]);
+        let config = instance.config().await;
+        let reviewer = &config.agent.as_ref().unwrap()["reviewer"];
+        assert_eq!(reviewer.prompt.as_deref(), Some("Review the diff."));
+    }
+}
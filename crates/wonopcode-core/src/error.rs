@@ -32,6 +32,18 @@ pub enum CoreError {
     /// Project not found.
     #[error("project not found: {0}")]
     ProjectNotFound(String),
+
+    /// Session template not found.
+    #[error("session template not found: {0}")]
+    TemplateNotFound(String),
+
+    /// A git command failed.
+    #[error("git error: {0}")]
+    Git(String),
+
+    /// Sandbox error.
+    #[error("sandbox error: {0}")]
+    Sandbox(#[from] wonopcode_sandbox::SandboxError),
 }
 
 /// Configuration-specific errors.
@@ -80,6 +92,22 @@ pub enum SessionError {
     /// Session is locked (being compacted, etc.).
     #[error("session is locked: {id}")]
     Locked { id: String },
+
+    /// Session was not forked from a parent.
+    #[error("session is not a branch: {id}")]
+    NotABranch { id: String },
+
+    /// Branch was already merged into its parent.
+    #[error("branch already merged: {id}")]
+    AlreadyMerged { id: String },
+
+    /// Session does not work in a worktree.
+    #[error("session has no worktree: {id}")]
+    NoWorktree { id: String },
+
+    /// Session already works in a worktree.
+    #[error("session already has a worktree: {id}")]
+    HasWorktree { id: String },
 }
 
 /// Result type for core operations.
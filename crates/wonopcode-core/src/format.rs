@@ -1,13 +1,18 @@
 //! Formatter integration for auto-formatting files after edits.
 //!
-//! Supports multiple formatters based on file extension.
+//! Formatters are picked by file extension or path glob. Built-in formatters
+//! only run when their program is installed; formatters defined in the
+//! `formatter` config section take precedence over them and can also
+//! override or disable them by name.
 
+use crate::config::FormatterConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
-use tracing::debug;
+use tracing::{debug, warn};
 
 /// A formatter definition.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,9 +26,18 @@ pub struct Formatter {
     pub environment: HashMap<String, String>,
     /// File extensions this formatter handles.
     pub extensions: Vec<String>,
+    /// Path globs this formatter handles, relative to the project root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub globs: Vec<String>,
+    /// Pipe the file through stdin and stdout instead of formatting in place.
+    #[serde(default)]
+    pub stdin: bool,
     /// Whether this formatter is enabled.
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    /// Whether this is a built-in formatter, which only runs if installed.
+    #[serde(skip)]
+    pub builtin: bool,
 }
 
 fn default_enabled() -> bool {
@@ -38,7 +52,10 @@ impl Formatter {
             command,
             environment: HashMap::new(),
             extensions,
+            globs: Vec::new(),
+            stdin: false,
             enabled: true,
+            builtin: false,
         }
     }
 
@@ -50,6 +67,49 @@ impl Formatter {
         })
     }
 
+    /// Check if this formatter handles `path`, by extension or glob.
+    ///
+    /// Globs are matched against the path relative to `root`; globs without
+    /// a `/` also match the file name alone.
+    pub fn matches(&self, path: &Path, root: Option<&Path>) -> bool {
+        let by_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.handles(ext));
+        if by_extension {
+            return true;
+        }
+
+        let relative = root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let file_name = path.file_name().map(Path::new);
+        self.globs.iter().any(|glob| {
+            let Ok(pattern) = glob::Pattern::new(glob) else {
+                return false;
+            };
+            pattern.matches_path(relative)
+                || (!glob.contains('/') && file_name.is_some_and(|n| pattern.matches_path(n)))
+        })
+    }
+
+    /// Check if the formatter's program is installed.
+    ///
+    /// `npx` formatters need the package installed in the project under
+    /// `root`, as npx would otherwise download it.
+    pub fn is_installed(&self, root: Option<&Path>) -> bool {
+        match self.command.as_slice() {
+            [npx, package, ..] if npx == "npx" => root.is_some_and(|root| {
+                root.join("node_modules")
+                    .join(".bin")
+                    .join(package)
+                    .exists()
+            }),
+            [program, ..] => find_program(program),
+            [] => false,
+        }
+    }
+
     /// Format a file.
     pub async fn format(&self, file_path: &Path) -> Result<(), FormatterError> {
         if !self.enabled {
@@ -75,18 +135,44 @@ impl Formatter {
 
         debug!(formatter = %self.name, file = %file_str, "Running formatter");
 
+        let input = if self.stdin {
+            Some(tokio::fs::read(file_path).await.map_err(|e| {
+                FormatterError::ExecutionFailed(format!("Failed to read {file_str}: {e}"))
+            })?)
+        } else {
+            None
+        };
+
         let mut cmd = Command::new(program);
         cmd.args(args)
-            .stdin(Stdio::null())
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = file_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            cmd.current_dir(dir);
+        }
 
         // Add environment variables
         for (key, value) in &self.environment {
             cmd.env(key, value);
         }
 
-        let output = cmd.output().await.map_err(|e| {
+        let mut child = cmd.spawn().map_err(|e| {
+            FormatterError::ExecutionFailed(format!("Failed to execute {program}: {e}"))
+        })?;
+        if let (Some(input), Some(mut stdin)) = (input.clone(), child.stdin.take()) {
+            // Write from a task, so a formatter that streams its output
+            // doesn't block on a full stdout pipe
+            tokio::spawn(async move {
+                let _ = stdin.write_all(&input).await;
+            });
+        }
+        let output = child.wait_with_output().await.map_err(|e| {
             FormatterError::ExecutionFailed(format!("Failed to execute {program}: {e}"))
         })?;
 
@@ -100,11 +186,43 @@ impl Formatter {
             )));
         }
 
+        if let Some(input) = input {
+            // An empty result for a non-empty file is a broken formatter,
+            // not an empty file
+            if output.stdout.is_empty() && !input.is_empty() {
+                return Err(FormatterError::ExecutionFailed(format!(
+                    "{} produced no output",
+                    self.name
+                )));
+            }
+            if output.stdout != input {
+                tokio::fs::write(file_path, &output.stdout)
+                    .await
+                    .map_err(|e| {
+                        FormatterError::ExecutionFailed(format!("Failed to write {file_str}: {e}"))
+                    })?;
+            }
+        }
+
         debug!(formatter = %self.name, file = %file_str, "Format successful");
         Ok(())
     }
 }
 
+/// Check if `program` is a path to a file or found on `PATH`.
+fn find_program(program: &str) -> bool {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.is_file();
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| {
+            let candidate = dir.join(program);
+            candidate.is_file() || (cfg!(windows) && candidate.with_extension("exe").is_file())
+        })
+    })
+}
+
 /// Formatter error types.
 #[derive(Debug, thiserror::Error)]
 pub enum FormatterError {
@@ -124,6 +242,8 @@ pub struct FormatterRegistry {
     formatters: Vec<Formatter>,
     /// Whether formatting is globally disabled.
     disabled: bool,
+    /// Project root, for glob matching and finding project-local formatters.
+    root: Option<PathBuf>,
 }
 
 impl FormatterRegistry {
@@ -139,6 +259,68 @@ impl FormatterRegistry {
         Self::default()
     }
 
+    /// Create a registry for the project at `root`, with the built-in
+    /// formatters and those from the `formatter` config section.
+    pub fn for_project(
+        root: impl Into<PathBuf>,
+        config: Option<&HashMap<String, FormatterConfig>>,
+    ) -> Self {
+        let mut registry = Self::with_builtins();
+        registry.root = Some(root.into());
+        if let Some(config) = config {
+            registry.configure(config);
+        }
+        registry
+    }
+
+    /// Apply `formatter` config entries.
+    ///
+    /// Entries named after a registered formatter change or disable it; other
+    /// entries add a formatter, tried before the built-in ones.
+    pub fn configure(&mut self, config: &HashMap<String, FormatterConfig>) {
+        let mut names: Vec<&String> = config.keys().collect();
+        names.sort();
+        let mut added = 0;
+
+        for name in names {
+            let entry = &config[name];
+            let existing = self.formatters.iter().position(|f| &f.name == name);
+            let formatter = match existing {
+                Some(index) => &mut self.formatters[index],
+                None if entry.disabled == Some(true) => continue,
+                None => {
+                    let Some(command) = entry.command.clone().filter(|c| !c.is_empty()) else {
+                        warn!(formatter = %name, "Formatter has no command, ignoring it");
+                        continue;
+                    };
+                    self.formatters
+                        .insert(added, Formatter::new(name.clone(), command, Vec::new()));
+                    added += 1;
+                    &mut self.formatters[added - 1]
+                }
+            };
+
+            if let Some(command) = &entry.command {
+                formatter.command = command.clone();
+                // An explicitly configured command is assumed to be installed
+                formatter.builtin = false;
+            }
+            if let Some(extensions) = &entry.extensions {
+                formatter.extensions = extensions.clone();
+            }
+            if let Some(globs) = &entry.globs {
+                formatter.globs = globs.clone();
+            }
+            if let Some(stdin) = entry.stdin {
+                formatter.stdin = stdin;
+            }
+            if let Some(environment) = &entry.environment {
+                formatter.environment = environment.clone();
+            }
+            formatter.enabled = entry.disabled != Some(true);
+        }
+    }
+
     /// Disable all formatting.
     pub fn disable(&mut self) {
         self.disabled = true;
@@ -151,6 +333,13 @@ impl FormatterRegistry {
 
     /// Register built-in formatters.
     fn register_builtins(&mut self) {
+        self.register_builtin_defaults();
+        for formatter in &mut self.formatters {
+            formatter.builtin = true;
+        }
+    }
+
+    fn register_builtin_defaults(&mut self) {
         // Go
         self.formatters.push(Formatter::new(
             "gofmt",
@@ -289,11 +478,23 @@ impl FormatterRegistry {
             return None;
         }
 
-        let extension = path.extension()?.to_str()?;
+        let root = self.root.as_deref();
+        self.formatters
+            .iter()
+            .find(|f| f.enabled && f.matches(path, root))
+    }
+
+    /// Find the formatter to run on a file after it is saved: the first that
+    /// handles it and, if built in, is installed.
+    pub fn find_installed_for_file(&self, path: &Path) -> Option<&Formatter> {
+        if self.disabled {
+            return None;
+        }
 
+        let root = self.root.as_deref();
         self.formatters
             .iter()
-            .find(|f| f.enabled && f.handles(extension))
+            .find(|f| f.enabled && f.matches(path, root) && (!f.builtin || f.is_installed(root)))
     }
 
     /// Format a file if a formatter is available.
@@ -344,6 +545,17 @@ impl FormatterRegistry {
     }
 }
 
+#[async_trait::async_trait]
+impl wonopcode_tools::FileFormatter for FormatterRegistry {
+    async fn format(&self, path: &Path) -> Result<Option<String>, String> {
+        let Some(formatter) = self.find_installed_for_file(path) else {
+            return Ok(None);
+        };
+        formatter.format(path).await.map_err(|e| e.to_string())?;
+        Ok(Some(formatter.name.clone()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -606,7 +818,10 @@ mod tests {
                 env
             },
             extensions: vec![".test".to_string()],
+            globs: Vec::new(),
+            stdin: false,
             enabled: true,
+            builtin: false,
         };
 
         let json = serde_json::to_string(&formatter).unwrap();
@@ -643,7 +858,10 @@ mod tests {
             command: vec![],
             environment: HashMap::new(),
             extensions: vec![".test".to_string()],
+            globs: Vec::new(),
+            stdin: false,
             enabled: true,
+            builtin: false,
         };
         registry.register(formatter);
 
@@ -651,6 +869,122 @@ mod tests {
         assert!(!available);
     }
 
+    #[test]
+    fn test_formatter_matches_globs() {
+        let mut formatter = Formatter::new("test", vec!["fmt".into()], vec![]);
+        formatter.globs = vec!["src/**/*.tpl".into(), "Justfile".into()];
+        let root = Path::new("/project");
+
+        assert!(formatter.matches(Path::new("/project/src/a/page.tpl"), Some(root)));
+        assert!(!formatter.matches(Path::new("/project/docs/page.tpl"), Some(root)));
+        // Globs without a `/` match the file name anywhere
+        assert!(formatter.matches(Path::new("/project/tools/Justfile"), Some(root)));
+        assert!(!formatter.matches(Path::new("/project/Makefile"), Some(root)));
+    }
+
+    #[test]
+    fn test_configure_overrides_and_adds() {
+        let config: HashMap<String, FormatterConfig> = serde_json::from_value(serde_json::json!({
+            "black": {"command": ["black", "$FILE"], "extensions": [".py"]},
+            "gofmt": {"disabled": true},
+            "rustfmt": {"command": ["rustfmt", "--edition", "2021", "$FILE"]},
+            "broken": {"extensions": [".x"]}
+        }))
+        .unwrap();
+        let registry = FormatterRegistry::for_project("/project", Some(&config));
+
+        // Configured formatters win over built-in ones
+        let python = registry
+            .find_for_file(Path::new("/project/main.py"))
+            .unwrap();
+        assert_eq!(python.name, "black");
+        assert!(!python.builtin);
+
+        assert!(registry
+            .find_for_file(Path::new("/project/main.go"))
+            .is_none());
+
+        let rustfmt = registry
+            .find_for_file(Path::new("/project/main.rs"))
+            .unwrap();
+        assert_eq!(rustfmt.command[2], "2021");
+        assert!(!rustfmt.builtin);
+
+        assert!(!registry.list().iter().any(|f| f.name == "broken"));
+    }
+
+    #[test]
+    fn test_builtins_need_installed_program() {
+        let mut registry = FormatterRegistry::with_builtins();
+        let mut missing = Formatter::new(
+            "missing",
+            vec!["this-command-does-not-exist-12345".into()],
+            vec![".zzz".into()],
+        );
+        missing.builtin = true;
+        registry.register(missing);
+
+        let file = PathBuf::from("file.zzz");
+        assert!(registry.find_for_file(&file).is_some());
+        assert!(registry.find_installed_for_file(&file).is_none());
+
+        // npx formatters need the package in the project
+        let prettier = registry.find_for_file(Path::new("a.ts")).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!prettier.is_installed(Some(dir.path())));
+        std::fs::create_dir_all(dir.path().join("node_modules/.bin")).unwrap();
+        std::fs::write(dir.path().join("node_modules/.bin/prettier"), "").unwrap();
+        assert!(prettier.is_installed(Some(dir.path())));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_format_through_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        tokio::fs::write(&file, "hello\n").await.unwrap();
+
+        let mut formatter = Formatter::new(
+            "upper",
+            vec!["tr".into(), "a-z".into(), "A-Z".into()],
+            vec![".txt".into()],
+        );
+        formatter.stdin = true;
+        formatter.format(&file).await.unwrap();
+        assert_eq!(tokio::fs::read_to_string(&file).await.unwrap(), "HELLO\n");
+
+        // A formatter that prints nothing must not empty the file
+        formatter.command = vec!["true".into()];
+        assert!(formatter.format(&file).await.is_err());
+        assert_eq!(tokio::fs::read_to_string(&file).await.unwrap(), "HELLO\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_registry_formats_for_tools() {
+        use wonopcode_tools::FileFormatter;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("notes.txt");
+        tokio::fs::write(&file, "hello\n").await.unwrap();
+
+        let config: HashMap<String, FormatterConfig> = serde_json::from_value(serde_json::json!({
+            "upper": {"command": ["tr", "a-z", "A-Z"], "globs": ["*.txt"], "stdin": true}
+        }))
+        .unwrap();
+        let registry = FormatterRegistry::for_project(dir.path(), Some(&config));
+
+        let name = FileFormatter::format(&registry, &file).await.unwrap();
+        assert_eq!(name.as_deref(), Some("upper"));
+        assert_eq!(tokio::fs::read_to_string(&file).await.unwrap(), "HELLO\n");
+
+        let other = dir.path().join("data.zzz");
+        assert_eq!(
+            FileFormatter::format(&registry, &other).await.unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_default_registry() {
         let registry = FormatterRegistry::default();
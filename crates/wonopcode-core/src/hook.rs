@@ -3,13 +3,41 @@
 //! Supports hooks that run commands in response to events like:
 //! - file_edited: After a file is edited
 //! - session_completed: After a session ends
+//!
+//! Tool hooks run around every tool call and can block it, rewrite its
+//! arguments, or transform its output. They are either native ([`ToolHook`]
+//! implementations) or declared in the config:
+//!
+//! ```json
+//! {
+//!   "hooks": {
+//!     "tool": [
+//!       { "tool": "edit", "path": "migrations/*", "deny": "Migrations are generated" }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! Shell hooks are config-declared commands for the [`ShellHookEvent`]s. Each
+//! receives the event as JSON on stdin and may print a [`ShellHookOutput`] on
+//! stdout; exiting with status 2 blocks, with stderr as the reason.
 
+use crate::bus::{Bus, HookFailed};
+use crate::config::{HooksConfig, ShellHookConfig, ToolRuleConfig};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::Path;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
+
+/// Timeout for shell hooks that don't set one.
+pub const DEFAULT_SHELL_HOOK_TIMEOUT: Duration = Duration::from_secs(60);
 
 /// A hook definition.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -153,6 +181,9 @@ pub enum HookError {
 
     #[error("Execution failed: {0}")]
     ExecutionFailed(String),
+
+    #[error("Timed out after {0}s")]
+    Timeout(u64),
 }
 
 /// Hook event types.
@@ -190,13 +221,310 @@ impl HookEvent {
     }
 }
 
+/// Events that run config-declared shell hooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShellHookEvent {
+    /// Before a tool call runs.
+    PreToolUse,
+    /// After a tool call ran.
+    PostToolUse,
+    /// Before the first prompt of a session.
+    SessionStart,
+    /// When the agent finishes responding.
+    Stop,
+}
+
+impl ShellHookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShellHookEvent::PreToolUse => "PreToolUse",
+            ShellHookEvent::PostToolUse => "PostToolUse",
+            ShellHookEvent::SessionStart => "SessionStart",
+            ShellHookEvent::Stop => "Stop",
+        }
+    }
+}
+
+/// JSON a shell hook may print on stdout.
+///
+/// Plain (non-JSON) output is treated as `additional_context`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShellHookOutput {
+    /// "allow", or "deny"/"block" to block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decision: Option<String>,
+    /// Why the hook decided as it did; shown to the model when blocking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Replacement tool arguments (PreToolUse only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_input: Option<serde_json::Value>,
+    /// Extra context passed to the model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub additional_context: Option<String>,
+}
+
+impl ShellHookOutput {
+    /// Whether the hook blocks the action.
+    pub fn blocks(&self) -> bool {
+        matches!(self.decision.as_deref(), Some("deny" | "block"))
+    }
+
+    fn parse(stdout: &str) -> Self {
+        let stdout = stdout.trim();
+        if stdout.is_empty() {
+            return Self::default();
+        }
+        serde_json::from_str(stdout).unwrap_or_else(|_| Self {
+            additional_context: Some(stdout.to_string()),
+            ..Default::default()
+        })
+    }
+}
+
+impl ShellHookConfig {
+    /// Whether this hook applies to `tool` (hooks without a matcher always apply).
+    pub fn matches(&self, tool: Option<&str>) -> bool {
+        match (&self.matcher, tool) {
+            (Some(matcher), Some(tool)) => matcher.split('|').any(|p| glob_match(p.trim(), tool)),
+            _ => true,
+        }
+    }
+
+    /// Run the command with `input` as JSON on stdin.
+    pub async fn run(
+        &self,
+        input: &serde_json::Value,
+        cwd: &Path,
+    ) -> Result<ShellHookOutput, HookError> {
+        if self.command.trim().is_empty() {
+            return Err(HookError::InvalidCommand("Empty command".into()));
+        }
+
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C");
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c");
+            cmd
+        };
+        cmd.arg(&self.command)
+            .current_dir(cwd)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| HookError::ExecutionFailed(format!("Failed to spawn: {e}")))?;
+        // Write stdin alongside waiting, so that a hook that doesn't read it
+        // can't hold up the call past its timeout
+        if let Some(mut stdin) = child.stdin.take() {
+            let input = input.to_string();
+            tokio::spawn(async move {
+                // A hook may exit without reading stdin; that's not an error
+                let _ = stdin.write_all(input.as_bytes()).await;
+            });
+        }
+
+        let timeout = self
+            .timeout
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_SHELL_HOOK_TIMEOUT);
+        let output = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| HookError::Timeout(timeout.as_secs()))?
+            .map_err(|e| HookError::ExecutionFailed(e.to_string()))?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match output.status.code() {
+            Some(0) => Ok(ShellHookOutput::parse(&stdout)),
+            Some(2) => Ok(ShellHookOutput {
+                decision: Some("block".to_string()),
+                reason: Some(stderr.trim().to_string()),
+                ..Default::default()
+            }),
+            code => Err(HookError::ExecutionFailed(format!(
+                "Hook failed with exit code {:?}: {}",
+                code,
+                stderr.trim()
+            ))),
+        }
+    }
+}
+
+/// Combined result of the shell hooks for one event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShellHookOutcome {
+    /// Reason given by the first hook that blocked.
+    pub blocked: Option<String>,
+    /// Replacement tool arguments from the last hook that set them.
+    pub tool_input: Option<serde_json::Value>,
+    /// Additional context from all hooks, in order.
+    pub context: Vec<String>,
+}
+
+/// Lines of a patch naming a file it touches.
+const PATCH_FILE_HEADERS: [&str; 4] = [
+    "*** Add File: ",
+    "*** Update File: ",
+    "*** Delete File: ",
+    "*** Move to: ",
+];
+
+/// A tool call as seen by tool hooks.
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    /// Tool name.
+    pub tool: String,
+    /// Call arguments.
+    pub args: serde_json::Value,
+    /// Project directory the call runs in.
+    pub cwd: PathBuf,
+}
+
+impl ToolCall {
+    /// Create a new tool call.
+    pub fn new(tool: impl Into<String>, args: serde_json::Value, cwd: impl Into<PathBuf>) -> Self {
+        Self {
+            tool: tool.into(),
+            args,
+            cwd: cwd.into(),
+        }
+    }
+
+    /// The file paths the call operates on, relative to `cwd` when inside
+    /// it, with `.` and `..` resolved.
+    ///
+    /// A patch can touch several files, named in its file headers.
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<&str> = ["file_path", "filePath", "path"]
+            .iter()
+            .filter_map(|key| self.args.get(key).and_then(|v| v.as_str()))
+            .take(1)
+            .collect();
+        if let Some(patch) = self.args.get("patch_text").and_then(|v| v.as_str()) {
+            paths.extend(patch.lines().filter_map(|line| {
+                PATCH_FILE_HEADERS
+                    .iter()
+                    .find_map(|header| line.strip_prefix(header))
+                    .map(str::trim)
+            }));
+        }
+        paths
+            .into_iter()
+            .map(|path| crate::permission::relative_path(path, &self.cwd))
+            .collect()
+    }
+}
+
+/// Outcome of the pre-tool hooks for a call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreToolDecision {
+    /// Run the call unchanged.
+    Allow,
+    /// Block the call; the reason is returned to the model.
+    Deny(String),
+    /// Run the call with these arguments instead.
+    Modify(serde_json::Value),
+}
+
+/// A native hook run around tool calls.
+#[async_trait]
+pub trait ToolHook: Send + Sync {
+    /// Hook name, used in logs.
+    fn name(&self) -> &str;
+
+    /// Inspect a call before it runs.
+    async fn before_tool(&self, _call: &ToolCall) -> PreToolDecision {
+        PreToolDecision::Allow
+    }
+
+    /// Transform the output of a call after it ran.
+    async fn after_tool(&self, _call: &ToolCall, output: String, _success: bool) -> String {
+        output
+    }
+}
+
+#[async_trait]
+impl ToolHook for ToolRuleConfig {
+    fn name(&self) -> &str {
+        "config"
+    }
+
+    async fn before_tool(&self, call: &ToolCall) -> PreToolDecision {
+        if !self.matches(call) {
+            return PreToolDecision::Allow;
+        }
+        if let Some(reason) = &self.deny {
+            return PreToolDecision::Deny(reason.clone());
+        }
+        match (&self.set_args, call.args.as_object()) {
+            (Some(overrides), Some(args)) => {
+                let mut args = args.clone();
+                args.extend(overrides.clone());
+                PreToolDecision::Modify(serde_json::Value::Object(args))
+            }
+            _ => PreToolDecision::Allow,
+        }
+    }
+
+    async fn after_tool(&self, call: &ToolCall, mut output: String, _success: bool) -> String {
+        if let Some(text) = self.append_output.as_ref().filter(|_| self.matches(call)) {
+            output.push_str("\n\n");
+            output.push_str(text);
+        }
+        output
+    }
+}
+
+impl ToolRuleConfig {
+    /// Whether this rule applies to a call.
+    pub fn matches(&self, call: &ToolCall) -> bool {
+        if let Some(tool) = &self.tool {
+            if !glob_match(tool, &call.tool) {
+                return false;
+            }
+        }
+        match &self.path {
+            Some(pattern) => call.paths().iter().any(|path| glob_match(pattern, path)),
+            None => true,
+        }
+    }
+}
+
 /// Hook registry for managing hooks.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct HookRegistry {
     /// Hooks by event type.
     hooks: HashMap<HookEvent, Vec<Hook>>,
     /// File-pattern based hooks for file_edited.
     file_hooks: HashMap<String, Vec<Hook>>,
+    /// Native tool hooks.
+    tool_hooks: Vec<Arc<dyn ToolHook>>,
+    /// Config-declared tool rules, run after native hooks.
+    tool_rules: Vec<ToolRuleConfig>,
+    /// Config-declared shell hooks by event.
+    shell_hooks: HashMap<ShellHookEvent, Vec<ShellHookConfig>>,
+    /// Bus for reporting shell hook failures.
+    bus: Option<Bus>,
+}
+
+impl fmt::Debug for HookRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookRegistry")
+            .field("hooks", &self.hooks)
+            .field("file_hooks", &self.file_hooks)
+            .field("tool_hooks", &self.tool_hooks.len())
+            .field("tool_rules", &self.tool_rules)
+            .field("shell_hooks", &self.shell_hooks)
+            .finish()
+    }
 }
 
 impl HookRegistry {
@@ -205,6 +533,12 @@ impl HookRegistry {
         Self::default()
     }
 
+    /// Publish shell hook failures as [`HookFailed`] events on `bus`.
+    pub fn with_bus(mut self, bus: Bus) -> Self {
+        self.bus = Some(bus);
+        self
+    }
+
     /// Register a hook for an event.
     pub fn register(&mut self, event: HookEvent, hook: Hook) {
         self.hooks.entry(event).or_default().push(hook);
@@ -298,14 +632,190 @@ impl HookRegistry {
     pub fn count(&self, event: HookEvent) -> usize {
         self.hooks.get(&event).map(|h| h.len()).unwrap_or(0)
     }
+
+    /// Register a native tool hook.
+    pub fn register_tool_hook(&mut self, hook: Arc<dyn ToolHook>) {
+        self.tool_hooks.push(hook);
+    }
+
+    /// Replace the config-declared tool rules.
+    pub fn set_tool_rules(&mut self, rules: Vec<ToolRuleConfig>) {
+        self.tool_rules = rules;
+    }
+
+    /// Replace the config-declared tool rules and shell hooks.
+    pub fn configure(&mut self, config: Option<&HooksConfig>) {
+        let config = config.cloned().unwrap_or_default();
+        self.tool_rules = config.tool.unwrap_or_default();
+        self.shell_hooks.clear();
+        for (event, hooks) in [
+            (ShellHookEvent::PreToolUse, config.pre_tool_use),
+            (ShellHookEvent::PostToolUse, config.post_tool_use),
+            (ShellHookEvent::SessionStart, config.session_start),
+            (ShellHookEvent::Stop, config.stop),
+        ] {
+            if let Some(hooks) = hooks.filter(|h| !h.is_empty()) {
+                self.shell_hooks.insert(event, hooks);
+            }
+        }
+    }
+
+    /// Check if any tool hooks or rules are registered.
+    pub fn has_tool_hooks(&self) -> bool {
+        !self.tool_hooks.is_empty()
+            || !self.tool_rules.is_empty()
+            || self.has_shell_hooks(ShellHookEvent::PreToolUse)
+            || self.has_shell_hooks(ShellHookEvent::PostToolUse)
+    }
+
+    /// Check if any shell hooks are configured for an event.
+    pub fn has_shell_hooks(&self, event: ShellHookEvent) -> bool {
+        self.shell_hooks.contains_key(&event)
+    }
+
+    /// Run the shell hooks for an event.
+    ///
+    /// `tool` is matched against each hook's matcher. `input` must be a JSON
+    /// object; `hook_event_name` and `cwd` are added to it. Hooks run in order
+    /// until one blocks. Failures are logged, published on the bus and
+    /// otherwise ignored.
+    pub async fn run_shell_hooks(
+        &self,
+        event: ShellHookEvent,
+        tool: Option<&str>,
+        mut input: serde_json::Value,
+        cwd: &Path,
+    ) -> ShellHookOutcome {
+        let mut outcome = ShellHookOutcome::default();
+        let Some(hooks) = self.shell_hooks.get(&event) else {
+            return outcome;
+        };
+
+        if let Some(obj) = input.as_object_mut() {
+            obj.insert("hook_event_name".into(), event.as_str().into());
+            obj.insert("cwd".into(), cwd.display().to_string().into());
+        }
+
+        for hook in hooks.iter().filter(|h| h.matches(tool)) {
+            match hook.run(&input, cwd).await {
+                Ok(output) => {
+                    debug!(event = event.as_str(), command = %hook.command, "Shell hook executed");
+                    if let Some(context) = output.additional_context.clone() {
+                        outcome.context.push(context);
+                    }
+                    if output.blocks() {
+                        let reason = output
+                            .reason
+                            .filter(|r| !r.is_empty())
+                            .unwrap_or_else(|| format!("blocked by hook `{}`", hook.command));
+                        info!(event = event.as_str(), command = %hook.command, reason = %reason, "Shell hook blocked");
+                        outcome.blocked = Some(reason);
+                        break;
+                    }
+                    if let Some(tool_input) = output.tool_input {
+                        if let Some(obj) = input.as_object_mut() {
+                            obj.insert("tool_input".into(), tool_input.clone());
+                        }
+                        outcome.tool_input = Some(tool_input);
+                    }
+                }
+                Err(e) => {
+                    warn!(event = event.as_str(), command = %hook.command, error = %e, "Shell hook failed");
+                    if let Some(bus) = &self.bus {
+                        bus.publish(HookFailed {
+                            event: event.as_str().to_string(),
+                            command: hook.command.clone(),
+                            error: e.to_string(),
+                        })
+                        .await;
+                    }
+                }
+            }
+        }
+        outcome
+    }
+
+    fn all_tool_hooks(&self) -> impl Iterator<Item = &dyn ToolHook> {
+        self.tool_hooks
+            .iter()
+            .map(|h| h.as_ref())
+            .chain(self.tool_rules.iter().map(|r| r as &dyn ToolHook))
+    }
+
+    /// Run pre-tool hooks in order.
+    ///
+    /// Modified arguments are written back to `call` and seen by later hooks.
+    /// Returns the reason if a hook denies the call.
+    pub async fn before_tool(&self, call: &mut ToolCall) -> Result<(), String> {
+        for hook in self.all_tool_hooks() {
+            match hook.before_tool(call).await {
+                PreToolDecision::Allow => {}
+                PreToolDecision::Deny(reason) => {
+                    info!(hook = hook.name(), tool = %call.tool, reason = %reason, "Tool call denied by hook");
+                    return Err(reason);
+                }
+                PreToolDecision::Modify(args) => {
+                    debug!(hook = hook.name(), tool = %call.tool, "Tool arguments modified by hook");
+                    call.args = args;
+                }
+            }
+        }
+
+        if self.has_shell_hooks(ShellHookEvent::PreToolUse) {
+            let input = serde_json::json!({ "tool_name": call.tool, "tool_input": call.args });
+            let outcome = self
+                .run_shell_hooks(
+                    ShellHookEvent::PreToolUse,
+                    Some(&call.tool),
+                    input,
+                    &call.cwd,
+                )
+                .await;
+            if let Some(reason) = outcome.blocked {
+                return Err(reason);
+            }
+            if let Some(args) = outcome.tool_input {
+                call.args = args;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run post-tool hooks in order, each transforming the previous output.
+    pub async fn after_tool(&self, call: &ToolCall, mut output: String, success: bool) -> String {
+        for hook in self.all_tool_hooks() {
+            output = hook.after_tool(call, output, success).await;
+        }
+
+        if self.has_shell_hooks(ShellHookEvent::PostToolUse) {
+            let input = serde_json::json!({
+                "tool_name": call.tool,
+                "tool_input": call.args,
+                "tool_response": { "output": output, "success": success },
+            });
+            let outcome = self
+                .run_shell_hooks(
+                    ShellHookEvent::PostToolUse,
+                    Some(&call.tool),
+                    input,
+                    &call.cwd,
+                )
+                .await;
+            for text in outcome.context.iter().chain(outcome.blocked.iter()) {
+                output.push_str("\n\n");
+                output.push_str(text);
+            }
+        }
+        output
+    }
 }
 
 /// Simple glob pattern matching.
 fn glob_match(pattern: &str, text: &str) -> bool {
     let pattern_parts: Vec<&str> = pattern.split('*').collect();
 
-    if pattern_parts.is_empty() {
-        return text.is_empty();
+    if pattern_parts.len() == 1 {
+        return pattern == text;
     }
 
     let mut text_pos = 0;
@@ -369,6 +879,8 @@ mod tests {
         assert!(!glob_match("*.rs", "main.go"));
         assert!(glob_match("*", "anything"));
         assert!(glob_match("test*file", "test_some_file"));
+        assert!(glob_match("edit", "edit"));
+        assert!(!glob_match("edit", "editor"));
     }
 
     #[test]
@@ -378,6 +890,274 @@ mod tests {
         assert_eq!(HookEvent::parse("unknown"), None);
     }
 
+    fn rule(json: serde_json::Value) -> ToolRuleConfig {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_tool_call_path() {
+        let call = ToolCall::new(
+            "edit",
+            serde_json::json!({ "file_path": "/project/migrations/001.sql" }),
+            "/project",
+        );
+        assert_eq!(call.paths(), vec!["migrations/001.sql"]);
+
+        let call = ToolCall::new(
+            "write",
+            serde_json::json!({ "filePath": "./src/../migrations/002.sql" }),
+            "/project",
+        );
+        assert_eq!(call.paths(), vec!["migrations/002.sql"]);
+
+        let call = ToolCall::new(
+            "patch",
+            serde_json::json!({ "patch_text": "*** Begin Patch\n*** Update File: src/a.rs\n@@\n-a\n+b\n*** Move to: migrations/003.sql\n*** End Patch" }),
+            "/project",
+        );
+        assert_eq!(call.paths(), vec!["src/a.rs", "migrations/003.sql"]);
+
+        let call = ToolCall::new("bash", serde_json::json!({ "command": "ls" }), "/project");
+        assert!(call.paths().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_rule_deny() {
+        let mut registry = HookRegistry::new();
+        registry.set_tool_rules(vec![rule(serde_json::json!({
+            "tool": "edit",
+            "path": "migrations/*",
+            "deny": "Migrations are generated"
+        }))]);
+        assert!(registry.has_tool_hooks());
+
+        let mut call = ToolCall::new(
+            "edit",
+            serde_json::json!({ "file_path": "/project/migrations/001.sql" }),
+            "/project",
+        );
+        assert_eq!(
+            registry.before_tool(&mut call).await,
+            Err("Migrations are generated".to_string())
+        );
+
+        let mut call = ToolCall::new(
+            "edit",
+            serde_json::json!({ "file_path": "/project/src/main.rs" }),
+            "/project",
+        );
+        assert_eq!(registry.before_tool(&mut call).await, Ok(()));
+
+        // Rules with a path never match calls without one
+        let mut call = ToolCall::new("bash", serde_json::json!({}), "/project");
+        assert_eq!(registry.before_tool(&mut call).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_tool_rule_modify_and_append() {
+        let mut registry = HookRegistry::new();
+        registry.set_tool_rules(vec![rule(serde_json::json!({
+            "tool": "bash",
+            "set_args": { "timeout": 1000 },
+            "append_output": "(checked)"
+        }))]);
+
+        let mut call = ToolCall::new(
+            "bash",
+            serde_json::json!({ "command": "ls", "timeout": 60000 }),
+            "/project",
+        );
+        registry.before_tool(&mut call).await.unwrap();
+        assert_eq!(
+            call.args,
+            serde_json::json!({ "command": "ls", "timeout": 1000 })
+        );
+
+        let output = registry
+            .after_tool(&call, "file.txt".to_string(), true)
+            .await;
+        assert_eq!(output, "file.txt\n\n(checked)");
+    }
+
+    struct Redact;
+
+    #[async_trait]
+    impl ToolHook for Redact {
+        fn name(&self) -> &str {
+            "redact"
+        }
+
+        async fn after_tool(&self, _call: &ToolCall, output: String, _success: bool) -> String {
+            output.replace("secret", "[redacted]")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_native_tool_hook() {
+        let mut registry = HookRegistry::new();
+        registry.register_tool_hook(Arc::new(Redact));
+
+        let mut call = ToolCall::new("read", serde_json::json!({}), "/project");
+        registry.before_tool(&mut call).await.unwrap();
+        let output = registry
+            .after_tool(&call, "the secret key".to_string(), true)
+            .await;
+        assert_eq!(output, "the [redacted] key");
+    }
+
+    fn shell_registry(config: serde_json::Value) -> HookRegistry {
+        let config: HooksConfig = serde_json::from_value(config).unwrap();
+        let mut registry = HookRegistry::new();
+        registry.configure(Some(&config));
+        registry
+    }
+
+    #[test]
+    fn test_shell_hook_output_parse() {
+        assert_eq!(ShellHookOutput::parse("  "), ShellHookOutput::default());
+        assert!(ShellHookOutput::parse(r#"{"decision": "deny"}"#).blocks());
+        assert_eq!(
+            ShellHookOutput::parse("remember the tests").additional_context,
+            Some("remember the tests".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_pre_tool_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = shell_registry(serde_json::json!({
+            "PreToolUse": [
+                {
+                    "matcher": "edit|write",
+                    "command": r#"grep -q '"file_path":"secret.txt"' && echo '{"decision":"deny","reason":"no secrets"}' || true"#
+                },
+                {
+                    "matcher": "bash",
+                    "command": r#"echo '{"tool_input":{"command":"ls -a"}}'"#
+                }
+            ]
+        }));
+        assert!(registry.has_tool_hooks());
+
+        let mut call = ToolCall::new(
+            "write",
+            serde_json::json!({ "file_path": "secret.txt" }),
+            dir.path(),
+        );
+        assert_eq!(
+            registry.before_tool(&mut call).await,
+            Err("no secrets".to_string())
+        );
+
+        let mut call = ToolCall::new(
+            "write",
+            serde_json::json!({ "file_path": "notes.txt" }),
+            dir.path(),
+        );
+        assert_eq!(registry.before_tool(&mut call).await, Ok(()));
+
+        let mut call = ToolCall::new("bash", serde_json::json!({ "command": "ls" }), dir.path());
+        registry.before_tool(&mut call).await.unwrap();
+        assert_eq!(call.args, serde_json::json!({ "command": "ls -a" }));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_hook_exit_code_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = shell_registry(serde_json::json!({
+            "Stop": [{ "command": "echo 'tests are failing' >&2; exit 2" }]
+        }));
+        let outcome = registry
+            .run_shell_hooks(
+                ShellHookEvent::Stop,
+                None,
+                serde_json::json!({}),
+                dir.path(),
+            )
+            .await;
+        assert_eq!(outcome.blocked.as_deref(), Some("tests are failing"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_post_tool_use_and_session_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = shell_registry(serde_json::json!({
+            "PostToolUse": [{ "command": r#"grep -q '"success":true' && echo '{"additional_context":"lint ok"}'"# }],
+            "SessionStart": [{ "command": "cat > input.json; echo 'branch: main'" }]
+        }));
+
+        let call = ToolCall::new("edit", serde_json::json!({}), dir.path());
+        let output = registry.after_tool(&call, "edited".to_string(), true).await;
+        assert_eq!(output, "edited\n\nlint ok");
+
+        let outcome = registry
+            .run_shell_hooks(
+                ShellHookEvent::SessionStart,
+                None,
+                serde_json::json!({}),
+                dir.path(),
+            )
+            .await;
+        assert_eq!(outcome.context, vec!["branch: main"]);
+        let input: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join("input.json")).unwrap())
+                .unwrap();
+        assert_eq!(input["hook_event_name"], "SessionStart");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_hook_failures_are_published() {
+        let dir = tempfile::tempdir().unwrap();
+        let bus = Bus::new();
+        let mut rx = bus.subscribe::<HookFailed>().await;
+        let config: HooksConfig = serde_json::from_value(serde_json::json!({
+            "Stop": [
+                { "command": "exit 1" },
+                { "command": "sleep 5", "timeout": 1 }
+            ]
+        }))
+        .unwrap();
+        let mut registry = HookRegistry::new().with_bus(bus);
+        registry.configure(Some(&config));
+
+        let outcome = registry
+            .run_shell_hooks(
+                ShellHookEvent::Stop,
+                None,
+                serde_json::json!({}),
+                dir.path(),
+            )
+            .await;
+        assert_eq!(outcome, ShellHookOutcome::default());
+
+        let failure = rx.recv().await.unwrap();
+        assert_eq!(failure.event, "Stop");
+        assert_eq!(failure.command, "exit 1");
+        assert_eq!(rx.recv().await.unwrap().error, "Timed out after 1s");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shell_hook_times_out_without_reading_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = ShellHookConfig {
+            matcher: None,
+            command: "sleep 5".to_string(),
+            timeout: Some(1),
+        };
+        // More input than a pipe holds, so writing it blocks until read
+        let input = serde_json::json!({ "last_message": "x".repeat(1 << 20) });
+
+        let started = std::time::Instant::now();
+        let result = hook.run(&input, dir.path()).await;
+        assert!(matches!(result, Err(HookError::Timeout(1))));
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+
     #[test]
     fn test_hook_new() {
         let hook = Hook::new(vec!["echo".to_string(), "hello".to_string()]);
@@ -28,6 +28,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use wonopcode_storage::json::JsonStorage;
+use wonopcode_storage::Storage;
 
 /// An instance managing state for a project directory.
 #[derive(Clone)]
@@ -53,6 +54,12 @@ struct InstanceInner {
 
     /// Config file sources.
     config_sources: RwLock<Vec<PathBuf>>,
+
+    /// Profile selected at runtime, overriding the configured one.
+    profile: RwLock<Option<String>>,
+
+    /// Opens worktree pull requests on the project's forge.
+    pull_requests: RwLock<Option<Arc<dyn crate::worktree::PullRequestOpener>>>,
 }
 
 impl Instance {
@@ -75,6 +82,17 @@ impl Instance {
             .join("storage");
         let storage = JsonStorage::new(storage_dir);
 
+        // Finish or roll back any writes interrupted by a previous crash
+        match storage.recover().await {
+            Ok(report) if !report.is_empty() => tracing::info!(
+                restored = report.restored.len(),
+                discarded = report.discarded.len(),
+                "Recovered interrupted storage writes"
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Storage recovery failed: {}", e),
+        }
+
         // Save/update project in storage
         let project = if let Some(existing) = Project::load(&storage, &project.id).await? {
             let mut existing = existing;
@@ -94,6 +112,8 @@ impl Instance {
                 bus: Bus::new(),
                 storage,
                 config_sources: RwLock::new(config_sources),
+                profile: RwLock::new(None),
+                pull_requests: RwLock::new(None),
             }),
         })
     }
@@ -150,6 +170,16 @@ impl Instance {
         &self.inner.storage
     }
 
+    /// Collect storage usage statistics and check them against soft quotas.
+    pub async fn storage_usage(&self) -> CoreResult<crate::quota::StorageUsage> {
+        let stats = self.inner.storage.stats(&[]).await?;
+        let config = self.inner.config.read().await;
+        Ok(crate::quota::StorageUsage::new(
+            stats,
+            config.storage.as_ref(),
+        ))
+    }
+
     /// Get config file sources.
     pub async fn config_sources(&self) -> Vec<PathBuf> {
         self.inner.config_sources.read().await.clone()
@@ -157,7 +187,21 @@ impl Instance {
 
     /// Reload configuration from disk.
     pub async fn reload_config(&self) -> CoreResult<()> {
-        let (config, sources) = Config::load(Some(&self.inner.directory)).await?;
+        let profile = self.inner.profile.read().await.clone();
+        let (config, sources) =
+            Config::load_with_profile(Some(&self.inner.directory), profile.as_deref()).await?;
+        *self.inner.config.write().await = config;
+        *self.inner.config_sources.write().await = sources;
+        Ok(())
+    }
+
+    /// Switch to a named config profile and reload the configuration.
+    ///
+    /// The current configuration is kept if the profile doesn't exist.
+    pub async fn switch_profile(&self, name: &str) -> CoreResult<()> {
+        let (config, sources) =
+            Config::load_with_profile(Some(&self.inner.directory), Some(name)).await?;
+        *self.inner.profile.write().await = Some(name.to_string());
         *self.inner.config.write().await = config;
         *self.inner.config_sources.write().await = sources;
         Ok(())
@@ -208,6 +252,112 @@ impl Instance {
             .unwrap_or_default()
     }
 
+    /// Search the sessions of the current project by title, tags and message
+    /// text, best matches first.
+    pub async fn search_sessions(
+        &self,
+        query: &str,
+    ) -> crate::error::CoreResult<Vec<crate::search::SearchHit>> {
+        let project_id = self.project_id().await;
+        crate::search::SessionSearch::new(
+            self.inner.storage.clone(),
+            std::sync::Arc::new(self.session_repo()),
+        )
+        .search(&project_id, query)
+        .await
+    }
+
+    /// Worktree manager for the project repository.
+    ///
+    /// Fails if the project isn't a git repository.
+    pub async fn worktrees(&self) -> crate::error::CoreResult<crate::worktree::WorktreeManager> {
+        let project = self.project().await;
+        if project.vcs != Some(crate::project::Vcs::Git) {
+            return Err(crate::error::CoreError::Git(format!(
+                "{} is not a git repository",
+                project.worktree.display()
+            )));
+        }
+        let pull_requests = self.inner.pull_requests.read().await.clone();
+        Ok(crate::worktree::WorktreeManager::new(project.worktree)
+            .with_pull_requests(pull_requests))
+    }
+
+    /// Open worktree pull requests with `opener` instead of the `gh` CLI.
+    pub async fn set_pull_request_opener(
+        &self,
+        opener: Arc<dyn crate::worktree::PullRequestOpener>,
+    ) {
+        *self.inner.pull_requests.write().await = Some(opener);
+    }
+
+    /// Move a session into a new worktree and branch, so the agent works
+    /// there instead of in the main checkout.
+    ///
+    /// The worktree is named after the session unless `name` is given.
+    pub async fn create_session_worktree(
+        &self,
+        session_id: &str,
+        name: Option<&str>,
+    ) -> crate::error::CoreResult<crate::session::Session> {
+        let project_id = self.project_id().await;
+        let repo = self.session_repo();
+        let session = repo.get(&project_id, session_id).await?;
+        if session.worktree.is_some() {
+            return Err(crate::error::SessionError::HasWorktree {
+                id: session_id.to_string(),
+            }
+            .into());
+        }
+
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => session_worktree_name(&session),
+        };
+        let worktree = self.worktrees().await?.create(&name).await?;
+        repo.update(&project_id, session_id, |s| {
+            s.directory = worktree.path.display().to_string();
+            s.worktree = Some(worktree);
+        })
+        .await
+    }
+
+    /// Finish a session's worktree: merge it back, open a pull request or
+    /// discard it.
+    ///
+    /// Unless the merge conflicts, the session moves back to the main
+    /// checkout.
+    pub async fn finish_session_worktree(
+        &self,
+        session_id: &str,
+        finish: crate::worktree::WorktreeFinish,
+    ) -> crate::error::CoreResult<crate::worktree::WorktreeOutcome> {
+        let project_id = self.project_id().await;
+        let repo = self.session_repo();
+        let session = repo.get(&project_id, session_id).await?;
+        let Some(worktree) = &session.worktree else {
+            return Err(crate::error::SessionError::NoWorktree {
+                id: session_id.to_string(),
+            }
+            .into());
+        };
+
+        let outcome = self
+            .worktrees()
+            .await?
+            .finish(worktree, finish, &session.title)
+            .await?;
+        if !matches!(outcome, crate::worktree::WorktreeOutcome::Conflict(_)) {
+            let directory = self.directory().display().to_string();
+            repo.update(&project_id, session_id, |s| {
+                s.directory = directory;
+                s.worktree = None;
+            })
+            .await?;
+        }
+        Ok(outcome)
+    }
+
     /// Get the most recent session.
     pub async fn last_session(&self) -> Option<crate::session::Session> {
         let sessions = self.list_sessions().await;
@@ -276,6 +426,23 @@ impl Default for InstanceRegistry {
     }
 }
 
+/// Default worktree name for a session: its title plus the end of its ID.
+fn session_worktree_name(session: &crate::session::Session) -> String {
+    let id: Vec<char> = session
+        .id
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect();
+    let suffix = id[id.len().saturating_sub(6)..]
+        .iter()
+        .collect::<String>()
+        .to_ascii_lowercase();
+    match crate::worktree::slugify(&session.title, 32) {
+        slug if slug.is_empty() => format!("session-{suffix}"),
+        slug => format!("{slug}-{suffix}"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
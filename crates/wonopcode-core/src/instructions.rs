@@ -0,0 +1,593 @@
+//! Discovery and composition of instruction files for the system prompt.
+//!
+//! Instruction files (AGENTS.md, CLAUDE.md, .cursorrules and friends) are
+//! collected nearest-first: from the working directory up to the project
+//! root, then the files listed in the `instructions` config, then the user's
+//! global files. Files that are the same file or have the same content as an
+//! earlier one are skipped, and files are included until the token budget is
+//! used up, so the nearest instructions win when there are too many.
+//!
+//! Subdirectories can also have a `.wonopcode/rules.md` that is only added
+//! once the agent reads or edits a file under that directory; see
+//! [`ScopedRuleSet`].
+
+use crate::config::Config;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Default token budget for instruction files.
+pub const DEFAULT_MAX_TOKENS: usize = 10_000;
+
+/// A file cut short to fit the budget keeps at least this many tokens;
+/// otherwise it is skipped.
+const MIN_TRUNCATED_TOKENS: usize = 200;
+
+/// Instruction files looked for in each directory, in order.
+pub const INSTRUCTION_FILES: &[&str] = &[
+    ".wonopcode/AGENTS.md",
+    "AGENTS.md",
+    ".claude/CLAUDE.md",
+    "CLAUDE.md",
+    ".wonopcode/instructions.md",
+    ".cursorrules",
+];
+
+/// Directory of Cursor rule files (`*.md`, `*.mdc`), or a single rules file.
+const CURSOR_RULES: &str = ".cursor/rules";
+
+/// Rules file that applies only to files in its directory's subtree.
+pub const SCOPED_RULES_FILE: &str = ".wonopcode/rules.md";
+
+/// Where an instruction file was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionSource {
+    /// The working directory or one of its parents within the project.
+    Directory,
+    /// The `instructions` config.
+    Config,
+    /// The user's global instructions.
+    Global,
+}
+
+/// An instruction file included in the system prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionFile {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Path as shown to the model and the user.
+    pub display: String,
+    /// Where the file was found.
+    pub source: InstructionSource,
+    /// Included content, trimmed.
+    pub content: String,
+    /// Estimated tokens of the included content.
+    pub tokens: usize,
+    /// Whether the content was cut short to fit the token budget.
+    pub truncated: bool,
+}
+
+/// An instruction file that was found but not included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedInstruction {
+    /// Path as shown to the user.
+    pub display: String,
+    /// Estimated tokens of the file.
+    pub tokens: usize,
+    /// Why it was skipped.
+    pub reason: String,
+}
+
+/// Options for [`Instructions::discover`].
+#[derive(Debug, Clone)]
+pub struct InstructionOptions {
+    /// Extra files or globs, relative to the project root or `~/`.
+    pub files: Vec<String>,
+    /// Global instruction files, lowest priority.
+    pub global_files: Vec<PathBuf>,
+    /// Token budget for all instruction files together.
+    pub max_tokens: usize,
+}
+
+impl Default for InstructionOptions {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            global_files: Vec::new(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+        }
+    }
+}
+
+impl InstructionOptions {
+    /// Options from the `instructions` and `instruction_max_tokens` config,
+    /// with the user's global AGENTS.md and CLAUDE.md.
+    pub fn from_config(config: &Config) -> Self {
+        let mut global_files = Vec::new();
+        if let Some(dir) = Config::global_config_dir() {
+            global_files.push(dir.join("AGENTS.md"));
+        }
+        if let Some(home) = dirs::home_dir() {
+            global_files.push(home.join(".claude").join("CLAUDE.md"));
+        }
+        Self {
+            files: config.instructions.clone().unwrap_or_default(),
+            global_files,
+            max_tokens: config.instruction_max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        }
+    }
+}
+
+/// The instruction files composed into a system prompt.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Instructions {
+    /// Included files, highest priority first.
+    pub files: Vec<InstructionFile>,
+    /// Files found but left out.
+    pub skipped: Vec<SkippedInstruction>,
+}
+
+impl Instructions {
+    /// Discover the instruction files for `cwd`.
+    pub fn discover(cwd: &Path, options: &InstructionOptions) -> Self {
+        let root = project_root(cwd);
+        let mut composer = Composer {
+            root: &root,
+            max_tokens: options.max_tokens,
+            used_tokens: 0,
+            seen_paths: HashMap::new(),
+            seen_contents: HashMap::new(),
+            result: Self::default(),
+        };
+
+        // Nearest directory first
+        for dir in cwd.ancestors() {
+            for name in INSTRUCTION_FILES {
+                composer.add(&dir.join(name), InstructionSource::Directory);
+            }
+            let cursor_rules = dir.join(CURSOR_RULES);
+            if cursor_rules.is_dir() {
+                for rule in sorted_files(&cursor_rules, &["md", "mdc"]) {
+                    composer.add(&rule, InstructionSource::Directory);
+                }
+            } else {
+                composer.add(&cursor_rules, InstructionSource::Directory);
+            }
+            if dir == root {
+                break;
+            }
+        }
+
+        for pattern in &options.files {
+            for path in expand_pattern(&root, pattern) {
+                composer.add(&path, InstructionSource::Config);
+            }
+        }
+
+        for path in &options.global_files {
+            composer.add(path, InstructionSource::Global);
+        }
+
+        composer.result
+    }
+
+    /// Estimated tokens of the included files.
+    pub fn tokens(&self) -> usize {
+        self.files.iter().map(|f| f.tokens).sum()
+    }
+
+    /// The instructions as a system prompt section.
+    pub fn render(&self) -> Option<String> {
+        if self.files.is_empty() {
+            return None;
+        }
+        let sections: Vec<String> = self
+            .files
+            .iter()
+            .map(|file| {
+                let mut section =
+                    format!("# Instructions from {}\n\n{}", file.display, file.content);
+                if file.truncated {
+                    section.push_str("\n\n[Truncated to fit the instruction budget]");
+                }
+                section
+            })
+            .collect();
+        Some(sections.join("\n\n"))
+    }
+}
+
+/// A rules file scoped to one directory of the project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopedRules {
+    /// Path of the rules file.
+    pub path: PathBuf,
+    /// Directory the rules apply to, relative to the project root.
+    pub dir: String,
+    /// Path of the rules file as shown to the model and the user.
+    pub display: String,
+    /// Content, trimmed.
+    pub content: String,
+    /// Estimated tokens of the content.
+    pub tokens: usize,
+}
+
+/// Directory-scoped rules files activated by the files the agent works on.
+///
+/// Rules in `dir/.wonopcode/rules.md` are activated the first time a file
+/// under `dir` is read or edited and stay active for the rest of the
+/// session, so packages with their own conventions only cost context once
+/// the agent actually touches them.
+#[derive(Debug, Clone, Default)]
+pub struct ScopedRuleSet {
+    active: Vec<ScopedRules>,
+}
+
+impl ScopedRuleSet {
+    /// Activate the rules files between `path` and the project root of
+    /// `cwd`, nearest first.
+    ///
+    /// The project root's own rules file is not scoped and never activated.
+    /// Returns the rules that were not active before.
+    pub fn activate_for(&mut self, cwd: &Path, path: &Path) -> Vec<ScopedRules> {
+        let root = project_root(cwd);
+        let Some(parent) = path.parent() else {
+            return Vec::new();
+        };
+        if !parent.starts_with(&root) {
+            return Vec::new();
+        }
+
+        let mut activated = Vec::new();
+        for dir in parent.ancestors().take_while(|dir| *dir != root) {
+            let rules_path = dir.join(SCOPED_RULES_FILE);
+            if self.active.iter().any(|rules| rules.path == rules_path) {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(&rules_path) else {
+                continue;
+            };
+            let content = content.trim();
+            if content.is_empty() {
+                continue;
+            }
+            let rules = ScopedRules {
+                dir: format!("{}/", display_path(dir, &root)),
+                display: display_path(&rules_path, &root),
+                content: content.to_string(),
+                tokens: estimate_tokens(content),
+                path: rules_path,
+            };
+            self.active.push(rules.clone());
+            activated.push(rules);
+        }
+        activated
+    }
+
+    /// Active rules, in the order they were activated.
+    pub fn active(&self) -> &[ScopedRules] {
+        &self.active
+    }
+
+    /// Deactivate all rules, e.g. when a new session starts.
+    pub fn clear(&mut self) {
+        self.active.clear();
+    }
+
+    /// The active rules as a system prompt section.
+    pub fn render(&self) -> Option<String> {
+        if self.active.is_empty() {
+            return None;
+        }
+        let sections: Vec<String> = self
+            .active
+            .iter()
+            .map(|rules| {
+                format!(
+                    "# Rules for {} (from {})\n\nFollow these rules when working on files under {}.\n\n{}",
+                    rules.dir, rules.display, rules.dir, rules.content
+                )
+            })
+            .collect();
+        Some(sections.join("\n\n"))
+    }
+}
+
+/// Collects instruction files in priority order.
+struct Composer<'a> {
+    root: &'a Path,
+    max_tokens: usize,
+    used_tokens: usize,
+    /// Canonical path to the display name of the file that was added.
+    seen_paths: HashMap<PathBuf, String>,
+    /// Content to the display name of the file that was added.
+    seen_contents: HashMap<String, String>,
+    result: Instructions,
+}
+
+impl Composer<'_> {
+    fn add(&mut self, path: &Path, source: InstructionSource) {
+        if !path.is_file() {
+            return;
+        }
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => strip_front_matter(&content).trim().to_string(),
+            Err(e) => {
+                warn!(path = %path.display(), "Failed to read instruction file: {}", e);
+                return;
+            }
+        };
+        if content.is_empty() {
+            return;
+        }
+
+        let display = display_path(path, self.root);
+        let tokens = estimate_tokens(&content);
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let duplicate_of = self
+            .seen_paths
+            .get(&canonical)
+            .or_else(|| self.seen_contents.get(&content));
+        if let Some(original) = duplicate_of {
+            // The same file found twice, e.g. through a symlink, isn't worth reporting
+            if self.seen_paths.contains_key(&canonical) && display == *original {
+                return;
+            }
+            self.result.skipped.push(SkippedInstruction {
+                reason: format!("duplicate of {original}"),
+                display,
+                tokens,
+            });
+            return;
+        }
+        self.seen_paths.insert(canonical, display.clone());
+        self.seen_contents.insert(content.clone(), display.clone());
+
+        let remaining = self.max_tokens.saturating_sub(self.used_tokens);
+        let (content, tokens, truncated) = if tokens <= remaining {
+            (content, tokens, false)
+        } else if remaining >= MIN_TRUNCATED_TOKENS {
+            let truncated = truncate_to_tokens(&content, remaining);
+            let tokens = estimate_tokens(&truncated);
+            (truncated, tokens, true)
+        } else {
+            self.result.skipped.push(SkippedInstruction {
+                display,
+                tokens,
+                reason: "over the token budget".to_string(),
+            });
+            return;
+        };
+
+        self.used_tokens += tokens;
+        self.result.files.push(InstructionFile {
+            path: path.to_path_buf(),
+            display,
+            source,
+            content,
+            tokens,
+            truncated,
+        });
+    }
+}
+
+/// The nearest directory containing `.git`, or `cwd` outside a repository.
+fn project_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+/// Files in `dir` with one of `extensions`, sorted by name.
+fn sorted_files(dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| extensions.contains(&e))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Resolve a configured file or glob against the project root.
+fn expand_pattern(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let path = match pattern.strip_prefix("~/") {
+        Some(rest) => match dirs::home_dir() {
+            Some(home) => home.join(rest),
+            None => return Vec::new(),
+        },
+        None => root.join(pattern),
+    };
+    let Some(path_str) = path.to_str() else {
+        return Vec::new();
+    };
+    match glob::glob(path_str) {
+        Ok(paths) => {
+            let mut paths: Vec<PathBuf> = paths.filter_map(|p| p.ok()).collect();
+            paths.sort();
+            paths
+        }
+        Err(_) => vec![path],
+    }
+}
+
+/// `path` relative to the project root or home directory, if under either.
+fn display_path(path: &Path, root: &Path) -> String {
+    if let Ok(relative) = path.strip_prefix(root) {
+        return relative.display().to_string();
+    }
+    if let Some(relative) = dirs::home_dir().and_then(|home| {
+        path.strip_prefix(home)
+            .ok()
+            .map(|relative| relative.to_path_buf())
+    }) {
+        return format!("~/{}", relative.display());
+    }
+    path.display().to_string()
+}
+
+/// Remove YAML front matter, as used by Cursor `.mdc` rules.
+fn strip_front_matter(content: &str) -> &str {
+    let Some(rest) = content.strip_prefix("---\n") else {
+        return content;
+    };
+    match rest.find("\n---") {
+        Some(end) => rest[end + 4..].trim_start_matches(['\r', '\n']),
+        None => content,
+    }
+}
+
+/// Estimate token count for text (roughly 4 chars per token).
+fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Cut `content` to about `tokens` tokens, at a line boundary if possible.
+fn truncate_to_tokens(content: &str, tokens: usize) -> String {
+    let mut end = (tokens * 4).min(content.len());
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let cut = &content[..end];
+    let cut = match cut.rfind('\n') {
+        Some(newline) if newline > 0 => &cut[..newline],
+        _ => cut,
+    };
+    cut.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_discovers_nearest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = &dir.path().join("project");
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        write(&root.join("AGENTS.md"), "Root rules");
+        write(&root.join("app/CLAUDE.md"), "App rules");
+        write(
+            &root.join("app/.cursor/rules/style.mdc"),
+            "---\nglobs: *\n---\nStyle rules",
+        );
+        write(&root.join("docs/guide.md"), "Guide");
+        // Outside the project, so never included
+        write(&dir.path().join("AGENTS.md"), "Outside");
+
+        let options = InstructionOptions {
+            files: vec!["docs/*.md".to_string()],
+            ..Default::default()
+        };
+        let instructions = Instructions::discover(&root.join("app"), &options);
+        let names: Vec<&str> = instructions
+            .files
+            .iter()
+            .map(|f| f.display.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "app/CLAUDE.md",
+                "app/.cursor/rules/style.mdc",
+                "AGENTS.md",
+                "docs/guide.md"
+            ]
+        );
+        assert_eq!(instructions.files[1].content, "Style rules");
+        assert_eq!(instructions.files[3].source, InstructionSource::Config);
+
+        let rendered = instructions.render().unwrap();
+        assert!(rendered.starts_with("# Instructions from app/CLAUDE.md\n\nApp rules"));
+    }
+
+    #[test]
+    fn test_skips_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        write(&root.join("AGENTS.md"), "Same rules\n");
+        write(&root.join("CLAUDE.md"), "Same rules");
+
+        let instructions = Instructions::discover(root, &InstructionOptions::default());
+        assert_eq!(instructions.files.len(), 1);
+        assert_eq!(instructions.skipped.len(), 1);
+        assert_eq!(instructions.skipped[0].display, "CLAUDE.md");
+        assert_eq!(instructions.skipped[0].reason, "duplicate of AGENTS.md");
+    }
+
+    #[test]
+    fn test_respects_token_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        write(&root.join("sub/AGENTS.md"), &"near line\n".repeat(100));
+        write(&root.join("AGENTS.md"), &"far line\n".repeat(400));
+        write(&root.join("CLAUDE.md"), "left out");
+
+        let options = InstructionOptions {
+            max_tokens: 600,
+            ..Default::default()
+        };
+        let instructions = Instructions::discover(&root.join("sub"), &options);
+
+        // The nearest file fits, the next is cut short and the rest left out
+        assert_eq!(instructions.files.len(), 2);
+        assert!(!instructions.files[0].truncated);
+        assert!(instructions.files[1].truncated);
+        assert!(instructions.files[1].content.ends_with("far line"));
+        assert!(instructions.tokens() <= 600);
+        assert_eq!(instructions.skipped[0].display, "CLAUDE.md");
+        assert_eq!(instructions.skipped[0].reason, "over the token budget");
+    }
+
+    #[test]
+    fn test_scoped_rules_activate_for_subtree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".git")).unwrap();
+        write(&root.join(".wonopcode/rules.md"), "Root rules");
+        write(&root.join("frontend/.wonopcode/rules.md"), "Use tabs");
+        write(
+            &root.join("frontend/src/ui/.wonopcode/rules.md"),
+            "Components are PascalCase",
+        );
+        write(&root.join("backend/.wonopcode/rules.md"), "Use spaces");
+
+        let mut rules = ScopedRuleSet::default();
+        assert!(rules.activate_for(root, &root.join("README.md")).is_empty());
+
+        let activated = rules.activate_for(root, &root.join("frontend/src/ui/button.tsx"));
+        let dirs: Vec<&str> = activated.iter().map(|r| r.dir.as_str()).collect();
+        assert_eq!(dirs, ["frontend/src/ui/", "frontend/"]);
+        assert_eq!(activated[1].display, "frontend/.wonopcode/rules.md");
+
+        // Already active rules are not activated again
+        assert!(rules
+            .activate_for(root, &root.join("frontend/index.ts"))
+            .is_empty());
+        assert_eq!(rules.active().len(), 2);
+
+        let rendered = rules.render().unwrap();
+        assert!(rendered.starts_with("# Rules for frontend/src/ui/"));
+        assert!(rendered.contains("Use tabs"));
+        assert!(!rendered.contains("Use spaces"));
+        assert!(!rendered.contains("Root rules"));
+
+        rules.clear();
+        assert!(rules.render().is_none());
+    }
+}
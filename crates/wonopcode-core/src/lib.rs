@@ -1,51 +1,102 @@
 //! Core business logic for wonopcode.
 //!
 //! This crate provides the central coordination layer for wonopcode:
-//! - Configuration management (multi-source, JSONC support)
+//! - Configuration management (multi-source, JSONC support, hot-reload)
+//! - Editing config files in place, keeping their comments
 //! - Event bus for inter-component communication
 //! - Instance/project state management
-//! - Session and message management
-//! - Agent definitions and loading
+//! - Session and message management, including branching and merging
+//! - Session tags and full-text search
+//! - Agent definitions and loading, including per-file agents
+//! - Permission rules and the permission audit log
+//! - Per-session token, cost and tool call budgets
+//! - Provider retries with backoff and per-provider circuit breakers
+//! - Persistent spending alerts and limits across sessions
 //! - Formatter integration for auto-formatting
 //! - Hooks system for automation
+//! - Recording and replay of bus events
+//! - Long-term project memory
 //! - Custom command system
+//! - Instruction file discovery (AGENTS.md, CLAUDE.md, ...) for the system prompt
+//! - Prompt templating (variables, includes and conditional sections)
+//! - Session templates
+//! - Scheduled and recurring tasks for headless mode
+//! - Batch pipelines of prompts and shell commands
+//! - Session worktrees and multi-agent orchestration over git worktrees
+//! - WASM plugins (`plugins` feature)
 
 pub mod agent;
+pub mod audit;
+pub mod batch;
+pub mod branch;
+pub mod budget;
 pub mod bus;
 pub mod command;
 pub mod config;
+pub mod config_edit;
+pub mod config_watch;
 pub mod error;
 pub mod format;
 pub mod hook;
 pub mod instance;
+pub mod instructions;
+pub mod memory;
 pub mod message;
+pub mod orchestrate;
 pub mod permission;
+#[cfg(feature = "plugins")]
+pub mod plugin;
 pub mod project;
 pub mod prompt;
+pub mod prompt_template;
+pub mod quota;
+pub mod recording;
 pub mod retry;
 pub mod revert;
+pub mod schedule;
+pub mod search;
 pub mod session;
 pub mod share;
+pub mod spend;
 pub mod system_prompt;
+pub mod template;
 pub mod version;
+pub mod worktree;
 
-pub use agent::{Agent, AgentMode, AgentPermission, AgentRegistry};
+pub use agent::{Agent, AgentFile, AgentMode, AgentPermission, AgentRegistry};
+pub use audit::{PermissionAudit, PermissionAuditEntry, PermissionAuditFilter};
+pub use batch::{Batch, BatchError, BatchReport, BatchStep, StepAction, StepReport, StepStatus};
+pub use branch::{MergeResult, SessionBranch};
+pub use budget::{BudgetCheck, BudgetTracker};
 // Re-export bash permission types from util to maintain backwards compatibility
-pub use bus::{Bus, SandboxState, SandboxStatusChanged, SandboxToolExecution};
-pub use command::{Command, CommandRegistry};
+pub use bus::{Bus, ConfigChanged, SandboxState, SandboxStatusChanged, SandboxToolExecution};
+pub use command::{Command, CommandArgument, CommandRegistry};
 pub use config::{Config, McpConfig, McpJsonFile, McpJsonServer, McpLocalConfig, McpRemoteConfig};
 pub use error::{CoreError, CoreResult};
 pub use format::{Formatter, FormatterRegistry};
-pub use hook::{Hook, HookContext, HookEvent, HookRegistry};
+pub use hook::{Hook, HookContext, HookEvent, HookRegistry, PreToolDecision, ToolHook};
 pub use instance::Instance;
+pub use instructions::{InstructionOptions, Instructions, ScopedRuleSet, ScopedRules};
+pub use memory::ProjectMemory;
 pub use message::{Message, MessagePart};
+pub use orchestrate::{AgentProgress, AgentState, Orchestrator};
 pub use permission::{Decision, PermissionCheck, PermissionManager, PermissionRule};
 pub use project::Project;
 pub use prompt::{PromptConfig, PromptLoop, PromptResult};
+pub use prompt_template::{render_prompt, PromptVars};
+pub use recording::{read_recording, BusRecorder, EventCapture};
 pub use retry::{
-    calculate_delay, classify_error, should_retry, RateLimitInfo, RetryHelper, RetryableError,
+    calculate_delay, classify_error, classify_provider_error, jittered_delay, should_retry,
+    CircuitBreaker, CircuitState, ProviderBreakers, RateLimitInfo, RetryHelper, RetryableError,
 };
 pub use revert::{RevertInput, SessionRevert};
+pub use schedule::{CronSchedule, ScheduleError, ScheduledRun, ScheduledTask, Scheduler};
+pub use search::{SearchHit, SessionSearch};
 pub use session::Session;
-pub use share::{ShareClient, ShareError, ShareInfo};
+pub use share::{ShareClient, ShareError, ShareInfo, ShareOptions, ShareSettings};
+pub use spend::{SpendAmount, SpendCheck, SpendHistory, SpendScope, SpendStatus, SpendTracker};
+pub use template::{SessionTemplate, TemplateStore};
 pub use wonopcode_util::{BashPermission, BashPermissionConfig};
+pub use worktree::{
+    PullRequestOpener, Worktree, WorktreeFinish, WorktreeManager, WorktreeMerge, WorktreeOutcome,
+};
@@ -0,0 +1,316 @@
+//! Long-term project memory.
+//!
+//! A curated markdown file at `.wonopcode/memory.md` holding conventions,
+//! gotchas and architecture notes. Its contents are added to the system prompt,
+//! the agent proposes additions through the `memory` tool (which asks for
+//! permission like any other write), and users can edit it directly.
+
+use crate::error::CoreResult;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use wonopcode_tools::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
+
+/// Memory file location, relative to the project directory.
+pub const MEMORY_FILE: &str = ".wonopcode/memory.md";
+
+/// Maximum memory size included in the system prompt.
+pub const MAX_PROMPT_CHARS: usize = 16_000;
+
+/// Starting contents offered when editing an empty memory file.
+pub const MEMORY_TEMPLATE: &str =
+    "# Project Memory\n\n## Conventions\n\n## Gotchas\n\n## Architecture\n\n## Notes\n";
+
+/// A section of the memory file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemorySection {
+    /// Coding and workflow conventions.
+    Conventions,
+    /// Pitfalls and surprising behavior.
+    Gotchas,
+    /// Architecture notes.
+    Architecture,
+    /// Anything else.
+    Notes,
+}
+
+impl MemorySection {
+    /// Section heading.
+    pub fn title(&self) -> &'static str {
+        match self {
+            MemorySection::Conventions => "Conventions",
+            MemorySection::Gotchas => "Gotchas",
+            MemorySection::Architecture => "Architecture",
+            MemorySection::Notes => "Notes",
+        }
+    }
+}
+
+/// The memory file of a project.
+#[derive(Debug, Clone)]
+pub struct ProjectMemory {
+    path: PathBuf,
+}
+
+impl ProjectMemory {
+    /// Memory for a project directory.
+    pub fn new(project_dir: &Path) -> Self {
+        Self {
+            path: project_dir.join(MEMORY_FILE),
+        }
+    }
+
+    /// Path of the memory file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the memory file, or an empty string if it doesn't exist.
+    pub fn read(&self) -> CoreResult<String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replace the memory file contents.
+    pub fn write(&self, content: &str) -> CoreResult<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, content)?;
+        Ok(())
+    }
+
+    /// Add an entry to a section.
+    ///
+    /// Returns `false` if the section already contains the entry.
+    pub fn add(&self, section: MemorySection, entry: &str) -> CoreResult<bool> {
+        let content = self.read()?;
+        match insert_entry(&content, section, entry) {
+            Some(updated) => {
+                self.write(&updated)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Memory formatted for the system prompt, or `None` if empty.
+    pub fn prompt_section(&self) -> Option<String> {
+        let content = self.read().ok()?;
+        let content = content.trim();
+        if content.is_empty() {
+            return None;
+        }
+        let content = match content.char_indices().nth(MAX_PROMPT_CHARS) {
+            Some((end, _)) => format!("{}\n\n[Project memory truncated]", &content[..end]),
+            None => content.to_string(),
+        };
+        Some(format!(
+            "# Project memory from {MEMORY_FILE}\n\nCurated notes about this project from \
+             earlier sessions. Propose additions with the memory tool.\n\n{content}"
+        ))
+    }
+}
+
+/// Insert `entry` as a bullet at the end of `section`, creating the section
+/// if needed. Returns `None` if the entry is already present.
+fn insert_entry(content: &str, section: MemorySection, entry: &str) -> Option<String> {
+    let entry = entry.trim().trim_start_matches("- ").trim();
+    let bullet = format!("- {entry}");
+    let heading = format!("## {}", section.title());
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let Some(start) = lines.iter().position(|l| l.trim() == heading) else {
+        let mut updated = content.trim_end().to_string();
+        if updated.is_empty() {
+            updated.push_str("# Project Memory\n");
+        }
+        updated.push_str(&format!("\n{heading}\n\n{bullet}\n"));
+        return Some(updated);
+    };
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|l| l.starts_with("## "))
+        .map_or(lines.len(), |i| start + 1 + i);
+    if lines[start + 1..end].iter().any(|l| l.trim() == bullet) {
+        return None;
+    }
+
+    // Insert after the last non-blank line of the section
+    let insert_at = (start + 1..end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .map_or(start + 1, |i| i + 1);
+    if insert_at == start + 1 {
+        lines.insert(insert_at, String::new());
+        lines.insert(insert_at + 1, bullet);
+    } else {
+        lines.insert(insert_at, bullet);
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    Some(updated)
+}
+
+#[derive(Debug, Deserialize)]
+struct MemoryArgs {
+    section: MemorySection,
+    entry: String,
+}
+
+/// Tool that adds an entry to the project memory.
+pub struct MemoryTool {
+    memory: ProjectMemory,
+}
+
+impl MemoryTool {
+    /// Create a memory tool for a project directory.
+    pub fn new(project_dir: &Path) -> Self {
+        Self {
+            memory: ProjectMemory::new(project_dir),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for MemoryTool {
+    fn id(&self) -> &str {
+        "memory"
+    }
+
+    fn description(&self) -> &str {
+        r#"Propose an addition to the project memory, a curated file of knowledge that is included in every future session.
+
+Only record durable facts that will help in later sessions:
+- conventions: how code in this project is written, tested and reviewed
+- gotchas: surprising behavior, pitfalls, things that broke before
+- architecture: how the main components fit together
+- notes: anything else worth remembering
+
+Keep entries to one or two sentences. Don't record task progress or facts that are obvious from the code. The user approves each addition."#
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "section": {
+                    "type": "string",
+                    "enum": ["conventions", "gotchas", "architecture", "notes"],
+                    "description": "Section to add the entry to"
+                },
+                "entry": {
+                    "type": "string",
+                    "description": "The fact to remember"
+                }
+            },
+            "required": ["section", "entry"]
+        })
+    }
+
+    async fn execute(&self, args: Value, _ctx: &ToolContext) -> ToolResult<ToolOutput> {
+        let args: MemoryArgs = serde_json::from_value(args)
+            .map_err(|e| ToolError::validation(format!("Invalid arguments: {e}")))?;
+        if args.entry.trim().is_empty() {
+            return Err(ToolError::validation("entry must not be empty"));
+        }
+
+        let added = self
+            .memory
+            .add(args.section, &args.entry)
+            .map_err(|e| ToolError::execution_failed(e.to_string()))?;
+        let output = if added {
+            format!("Added to {}: {}", args.section.title(), args.entry.trim())
+        } else {
+            format!("Already in {}: {}", args.section.title(), args.entry.trim())
+        };
+        Ok(
+            ToolOutput::new("Memory updated", output).with_metadata(json!({
+                "path": self.memory.path().display().to_string(),
+                "added": added,
+            })),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_entry_creates_sections() {
+        let content = insert_entry("", MemorySection::Gotchas, "Tests need Docker").unwrap();
+        assert_eq!(
+            content,
+            "# Project Memory\n\n## Gotchas\n\n- Tests need Docker\n"
+        );
+
+        let content =
+            insert_entry(&content, MemorySection::Conventions, "- Use thiserror").unwrap();
+        assert!(content.ends_with("## Conventions\n\n- Use thiserror\n"));
+    }
+
+    #[test]
+    fn test_insert_entry_appends_within_section() {
+        let content = "## Gotchas\n\n- First\n\n## Notes\n\n- Other\n";
+        let updated = insert_entry(content, MemorySection::Gotchas, "Second").unwrap();
+        assert_eq!(
+            updated,
+            "## Gotchas\n\n- First\n- Second\n\n## Notes\n\n- Other\n"
+        );
+
+        // Duplicates are skipped
+        assert_eq!(
+            insert_entry(&updated, MemorySection::Gotchas, "First"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_tool_and_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory = ProjectMemory::new(dir.path());
+        assert_eq!(memory.read().unwrap(), "");
+        assert!(memory.prompt_section().is_none());
+
+        let tool = MemoryTool::new(dir.path());
+        let ctx = ToolContext {
+            session_id: "test".to_string(),
+            message_id: "test".to_string(),
+            agent: "default".to_string(),
+            abort: tokio_util::sync::CancellationToken::new(),
+            root_dir: dir.path().to_path_buf(),
+            cwd: dir.path().to_path_buf(),
+            snapshot: None,
+            file_time: None,
+            sandbox: None,
+            event_tx: None,
+            formatter: None,
+            client_fs: None,
+        };
+        let out = tool
+            .execute(
+                json!({ "section": "architecture", "entry": "Core owns the event bus" }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert_eq!(out.metadata["added"], true);
+
+        let prompt = memory.prompt_section().unwrap();
+        assert!(prompt.starts_with("# Project memory from .wonopcode/memory.md"));
+        assert!(prompt.contains("- Core owns the event bus"));
+
+        assert!(tool
+            .execute(json!({ "section": "bogus", "entry": "x" }), &ctx)
+            .await
+            .is_err());
+    }
+}
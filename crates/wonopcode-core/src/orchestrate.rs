@@ -0,0 +1,700 @@
+//! Multi-agent orchestration over git worktrees.
+//!
+//! An [`Orchestrator`] runs several agents at once, each working on its own
+//! task in its own [`Worktree`] and session, so they can't trample each
+//! other's changes. Progress is published as [`OrchestrationUpdated`] events.
+//! When the agents are done their work is committed on their branches, and
+//! [`Orchestrator::merge`] merges the branches into the main checkout one by
+//! one; a branch that conflicts is left in its worktree for manual resolution.
+//!
+//! Agents' tool calls go through the same permission rules, tool hooks and
+//! session budget as the main session, and each agent gets a sandbox of its
+//! own worktree.
+
+use crate::budget::BudgetTracker;
+use crate::bus::{Bus, OrchestrationUpdated};
+use crate::error::{ConfigError, CoreResult};
+use crate::hook::HookRegistry;
+use crate::message::{AssistantMessage, Message, MessagePart, ModelRef, TextPart, UserMessage};
+use crate::permission::PermissionManager;
+use crate::prompt::{PromptConfig, PromptLoop, PromptResult};
+use crate::session::{Session, SessionRepository};
+use crate::worktree::{slugify, Worktree, WorktreeManager, WorktreeMerge};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+use wonopcode_provider::LanguageModel;
+use wonopcode_sandbox::{SandboxConfig, SandboxManager};
+use wonopcode_tools::ToolRegistry;
+
+/// Default number of agents running at the same time.
+pub const DEFAULT_MAX_PARALLEL: usize = 4;
+
+/// Tag added to the sessions of orchestrated agents.
+pub const SESSION_TAG: &str = "orchestration";
+
+/// State of an orchestrated agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentState {
+    /// Waiting for a free slot.
+    Pending,
+    /// Working on its task.
+    Running,
+    /// Finished; its changes are committed on its branch.
+    Done,
+    /// Stopped with an error.
+    Failed,
+    /// Cancelled before finishing.
+    Cancelled,
+    /// Its branch was merged into the main checkout.
+    Merged,
+    /// Its branch conflicted with the main checkout and was not merged.
+    Conflict,
+}
+
+impl AgentState {
+    /// Identifier used in events.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AgentState::Pending => "pending",
+            AgentState::Running => "running",
+            AgentState::Done => "done",
+            AgentState::Failed => "failed",
+            AgentState::Cancelled => "cancelled",
+            AgentState::Merged => "merged",
+            AgentState::Conflict => "conflict",
+        }
+    }
+
+    /// Whether the agent has stopped working.
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, AgentState::Pending | AgentState::Running)
+    }
+}
+
+/// Progress of one orchestrated agent.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentProgress {
+    /// Agent name, also used for its worktree and branch.
+    pub name: String,
+    /// The task given to the agent.
+    pub task: String,
+    /// Current state.
+    pub state: AgentState,
+    /// Session of the agent, once created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Branch of the agent's worktree, once created.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Model calls made.
+    pub steps: usize,
+    /// Tool calls made.
+    pub tool_calls: usize,
+    /// Files changed on the agent's branch.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed_files: Vec<String>,
+    /// Final response, or what went wrong.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// An agent and its worktree.
+struct AgentRun {
+    progress: AgentProgress,
+    worktree: Option<Worktree>,
+}
+
+/// Runs agents concurrently in separate worktrees.
+pub struct Orchestrator {
+    id: String,
+    provider: Arc<dyn LanguageModel>,
+    tools: Arc<ToolRegistry>,
+    session_repo: Arc<SessionRepository>,
+    bus: Bus,
+    worktrees: WorktreeManager,
+    project_id: String,
+    config: PromptConfig,
+    max_parallel: usize,
+    sandbox: Option<SandboxConfig>,
+    permissions: Option<(Arc<PermissionManager>, bool)>,
+    hooks: Option<Arc<RwLock<HookRegistry>>>,
+    budget: Option<Arc<Mutex<BudgetTracker>>>,
+    cancel: CancellationToken,
+    agents: Mutex<Vec<AgentRun>>,
+}
+
+impl Orchestrator {
+    /// Create an orchestrator for a project.
+    pub fn new(
+        provider: Arc<dyn LanguageModel>,
+        tools: Arc<ToolRegistry>,
+        session_repo: Arc<SessionRepository>,
+        bus: Bus,
+        worktrees: WorktreeManager,
+        project_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: chrono::Utc::now().format("%m%d%H%M%S").to_string(),
+            provider,
+            tools,
+            session_repo,
+            bus,
+            worktrees,
+            project_id: project_id.into(),
+            config: PromptConfig::default(),
+            max_parallel: DEFAULT_MAX_PARALLEL,
+            sandbox: None,
+            permissions: None,
+            hooks: None,
+            budget: None,
+            cancel: CancellationToken::new(),
+            agents: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Prompt settings for every agent. Worktree instructions are appended to
+    /// the system prompt.
+    pub fn with_prompt_config(mut self, config: PromptConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Limit the number of agents running at the same time.
+    pub fn with_max_parallel(mut self, max_parallel: usize) -> Self {
+        self.max_parallel = max_parallel.max(1);
+        self
+    }
+
+    /// Run each agent's tools in a sandbox of its worktree, configured by
+    /// `config`. The sandboxes are removed when the agents finish.
+    pub fn with_sandbox(mut self, config: Option<SandboxConfig>) -> Self {
+        self.sandbox = config.map(|config| SandboxConfig {
+            keep_alive: false,
+            ..config
+        });
+        self
+    }
+
+    /// Check the agents' tool calls against permission rules, asking the
+    /// user where no rule decides. See [`PromptLoop::with_permissions`].
+    pub fn with_permissions(
+        mut self,
+        permissions: Arc<PermissionManager>,
+        allow_all_in_sandbox: bool,
+    ) -> Self {
+        self.permissions = Some((permissions, allow_all_in_sandbox));
+        self
+    }
+
+    /// Run tool hooks around the agents' tool calls.
+    pub fn with_hooks(mut self, hooks: Arc<RwLock<HookRegistry>>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Count the agents' usage against a session budget, stopping them when
+    /// it is used up.
+    pub fn with_budget(mut self, budget: Arc<Mutex<BudgetTracker>>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Orchestration ID, also the prefix of the agents' names.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Current progress of every agent.
+    pub async fn agents(&self) -> Vec<AgentProgress> {
+        self.agents
+            .lock()
+            .await
+            .iter()
+            .map(|a| a.progress.clone())
+            .collect()
+    }
+
+    /// Stop all running agents.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Run one agent per task and wait for all of them.
+    ///
+    /// Each agent's changes are committed on its branch when it finishes.
+    pub async fn run(&self, tasks: Vec<String>) -> CoreResult<Vec<AgentProgress>> {
+        {
+            let mut agents = self.agents.lock().await;
+            if !agents.is_empty() {
+                return Err(ConfigError::Validation {
+                    message: format!("orchestration {} already started", self.id),
+                }
+                .into());
+            }
+            for (i, task) in tasks.into_iter().enumerate() {
+                let slug = slugify(&task, 24);
+                let name = if slug.is_empty() {
+                    format!("{}-{}", self.id, i + 1)
+                } else {
+                    format!("{}-{}-{slug}", self.id, i + 1)
+                };
+                agents.push(AgentRun {
+                    progress: AgentProgress {
+                        name,
+                        task,
+                        state: AgentState::Pending,
+                        session_id: None,
+                        branch: None,
+                        steps: 0,
+                        tool_calls: 0,
+                        changed_files: Vec::new(),
+                        summary: None,
+                    },
+                    worktree: None,
+                });
+            }
+        }
+        self.publish().await;
+
+        let count = self.agents.lock().await.len();
+        info!(id = %self.id, agents = count, "Starting orchestration");
+        futures::stream::iter(0..count)
+            .map(|i| self.run_agent(i))
+            .buffer_unordered(self.max_parallel)
+            .collect::<Vec<()>>()
+            .await;
+
+        Ok(self.agents().await)
+    }
+
+    /// Merge the branches of finished agents into the main checkout, in task
+    /// order.
+    ///
+    /// Merged worktrees and branches are removed; conflicting ones are kept.
+    pub async fn merge(&self) -> CoreResult<Vec<AgentProgress>> {
+        let mut agents = self.agents.lock().await;
+        for agent in agents.iter_mut() {
+            if agent.progress.state != AgentState::Done {
+                continue;
+            }
+            let Some(worktree) = agent.worktree.clone() else {
+                continue;
+            };
+            match self.worktrees.merge(&worktree).await? {
+                WorktreeMerge::Merged(files) => {
+                    agent.progress.state = AgentState::Merged;
+                    agent.progress.changed_files = files;
+                    self.remove_worktree(agent).await;
+                }
+                WorktreeMerge::NothingToMerge => {
+                    agent.progress.state = AgentState::Merged;
+                    self.remove_worktree(agent).await;
+                }
+                WorktreeMerge::Conflict(files) => {
+                    agent.progress.state = AgentState::Conflict;
+                    agent.progress.summary = Some(format!(
+                        "Conflicts in {}; resolve by merging {} manually",
+                        files.join(", "),
+                        worktree.branch
+                    ));
+                }
+            }
+        }
+        drop(agents);
+
+        self.publish().await;
+        Ok(self.agents().await)
+    }
+
+    async fn remove_worktree(&self, agent: &mut AgentRun) {
+        if let Some(worktree) = agent.worktree.take() {
+            if let Err(e) = self.worktrees.remove(&worktree, true).await {
+                warn!(name = %worktree.name, error = %e, "Failed to remove worktree");
+            }
+        }
+    }
+
+    /// Run the agent at `index` and record the outcome.
+    async fn run_agent(&self, index: usize) {
+        if self.cancel.is_cancelled() {
+            self.update(index, |p| p.state = AgentState::Cancelled)
+                .await;
+            return;
+        }
+        self.update(index, |p| p.state = AgentState::Running).await;
+
+        let result = self.try_run_agent(index).await;
+        let cancelled = self.cancel.is_cancelled();
+        self.update(index, |p| match result {
+            Ok(()) if cancelled => p.state = AgentState::Cancelled,
+            Ok(()) => p.state = AgentState::Done,
+            Err(e) => {
+                warn!(name = %p.name, error = %e, "Orchestrated agent failed");
+                p.state = AgentState::Failed;
+                p.summary = Some(e.to_string());
+            }
+        })
+        .await;
+    }
+
+    async fn try_run_agent(&self, index: usize) -> CoreResult<()> {
+        let (name, task) = {
+            let agents = self.agents.lock().await;
+            let progress = &agents[index].progress;
+            (progress.name.clone(), progress.task.clone())
+        };
+
+        let worktree = self.worktrees.create(&name).await?;
+        let directory = worktree.path.display().to_string();
+        let mut session = Session::new(&self.project_id, &directory);
+        session.title = task.lines().next().unwrap_or(&task).to_string();
+        session.tags = vec![SESSION_TAG.to_string(), self.id.clone()];
+        let session = self.session_repo.create(session).await?;
+        {
+            let mut agents = self.agents.lock().await;
+            agents[index].worktree = Some(worktree.clone());
+            agents[index].progress.session_id = Some(session.id.clone());
+            agents[index].progress.branch = Some(worktree.branch.clone());
+        }
+        self.publish().await;
+
+        let user = UserMessage::new(
+            &session.id,
+            "build",
+            ModelRef {
+                provider_id: self.provider.provider_id().to_string(),
+                model_id: self.provider.model_info().id.clone(),
+            },
+        );
+        let user_id = user.id.clone();
+        let part = TextPart::new(&session.id, &user.id, &task);
+        self.session_repo
+            .save_message_with_parts(&Message::User(user), &[MessagePart::Text(part)])
+            .await?;
+
+        let mut config = self.config.clone();
+        let instructions = worktree_instructions(&worktree);
+        config.system = Some(match config.system {
+            Some(system) => format!("{system}\n\n{instructions}"),
+            None => instructions,
+        });
+        let sandbox = match &self.sandbox {
+            Some(config) => Some(SandboxManager::new(config.clone(), worktree.path.clone()).await?),
+            None => None,
+        };
+        let result = self.prompt(sandbox.as_ref(), &session, &task, config).await;
+        if let Some(sandbox) = &sandbox {
+            if let Err(e) = sandbox.stop().await {
+                warn!(name = %name, error = %e, "Failed to stop agent sandbox");
+            }
+        }
+        let result = result?;
+
+        let assistant = AssistantMessage::new(
+            &session.id,
+            &user_id,
+            "build",
+            self.provider.provider_id(),
+            &self.provider.model_info().id,
+            &directory,
+            &directory,
+        );
+        let part = TextPart::new(&session.id, &assistant.id, &result.text);
+        self.session_repo
+            .save_message_with_parts(&Message::Assistant(assistant), &[MessagePart::Text(part)])
+            .await?;
+
+        self.worktrees
+            .commit_all(&worktree, &format!("{}\n\n{task}", session.title))
+            .await?;
+        let changed_files = self.worktrees.changed_files(&worktree).await?;
+        self.update(index, |p| {
+            p.steps = result.steps;
+            p.tool_calls = result.tool_calls.len();
+            p.changed_files = changed_files;
+            p.summary = match &result.budget_exceeded {
+                Some(status) => Some(format!("Stopped: {}", status.describe())),
+                None => Some(result.text.clone()).filter(|t| !t.is_empty()),
+            };
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Run an agent's prompt loop, in `sandbox` if given.
+    async fn prompt(
+        &self,
+        sandbox: Option<&SandboxManager>,
+        session: &Session,
+        task: &str,
+        config: PromptConfig,
+    ) -> CoreResult<PromptResult> {
+        let runtime = match sandbox {
+            Some(sandbox) => {
+                sandbox.start().await?;
+                Some(sandbox.runtime().await?)
+            }
+            None => None,
+        };
+        let mut prompt = PromptLoop::new(
+            self.provider.clone(),
+            self.tools.clone(),
+            self.session_repo.clone(),
+            self.bus.clone(),
+            self.cancel.child_token(),
+        )
+        .with_sandbox(runtime);
+        if let Some((permissions, allow_all_in_sandbox)) = &self.permissions {
+            prompt = prompt.with_permissions(permissions.clone(), *allow_all_in_sandbox);
+        }
+        if let Some(hooks) = &self.hooks {
+            prompt = prompt.with_hooks(hooks.clone());
+        }
+        if let Some(budget) = &self.budget {
+            prompt = prompt.with_budget(budget.clone());
+        }
+        prompt.run(session, task, config).await
+    }
+
+    /// Change an agent's progress and publish the new state.
+    async fn update(&self, index: usize, f: impl FnOnce(&mut AgentProgress)) {
+        f(&mut self.agents.lock().await[index].progress);
+        self.publish().await;
+    }
+
+    async fn publish(&self) {
+        self.bus
+            .publish(OrchestrationUpdated {
+                orchestration_id: self.id.clone(),
+                agents: self.agents().await,
+            })
+            .await;
+    }
+}
+
+/// System prompt instructions telling an agent where to work.
+fn worktree_instructions(worktree: &Worktree) -> String {
+    format!(
+        "# Orchestration\n\n\
+         You are one of several agents working on separate tasks at the same time. \
+         Work only inside the git worktree at {} (branch {}), using absolute paths \
+         within it. Don't commit: your changes are committed and merged for you \
+         when you finish.",
+        worktree.path.display(),
+        worktree.branch
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::worktree::tests::{git_sync, init_repo};
+    use futures::stream::BoxStream;
+    use std::sync::Mutex as StdMutex;
+    use wonopcode_provider::stream::{FinishReason, Usage};
+    use wonopcode_provider::{GenerateOptions, Message as ProviderMessage, ModelInfo};
+    use wonopcode_provider::{ProviderResult, StreamChunk};
+    use wonopcode_storage::json::JsonStorage;
+
+    /// A model replying with scripted responses, in order.
+    struct ScriptedModel {
+        model: ModelInfo,
+        responses: StdMutex<Vec<Vec<StreamChunk>>>,
+    }
+
+    impl ScriptedModel {
+        fn new() -> Self {
+            Self {
+                model: wonopcode_provider::model::anthropic::claude_sonnet_4(),
+                responses: StdMutex::new(Vec::new()),
+            }
+        }
+
+        fn expect_text(&self, text: &str) {
+            self.responses.lock().unwrap().push(vec![
+                StreamChunk::TextDelta(text.to_string()),
+                StreamChunk::FinishStep {
+                    usage: Usage::new(100, 50),
+                    finish_reason: FinishReason::EndTurn,
+                },
+            ]);
+        }
+
+        fn expect_bash(&self, id: &str, command: &str) {
+            let arguments =
+                serde_json::json!({ "command": command, "description": "test" }).to_string();
+            self.responses.lock().unwrap().push(vec![
+                StreamChunk::ToolCallStart {
+                    id: id.to_string(),
+                    name: "bash".to_string(),
+                },
+                StreamChunk::ToolCall {
+                    id: id.to_string(),
+                    name: "bash".to_string(),
+                    arguments,
+                },
+                StreamChunk::FinishStep {
+                    usage: Usage::new(100, 50),
+                    finish_reason: FinishReason::ToolUse,
+                },
+            ]);
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LanguageModel for ScriptedModel {
+        async fn generate(
+            &self,
+            _messages: Vec<ProviderMessage>,
+            _options: GenerateOptions,
+        ) -> ProviderResult<BoxStream<'static, ProviderResult<StreamChunk>>> {
+            let chunks = self.responses.lock().unwrap().remove(0);
+            Ok(Box::pin(futures::stream::iter(chunks.into_iter().map(Ok))))
+        }
+
+        fn model_info(&self) -> &ModelInfo {
+            &self.model
+        }
+
+        fn provider_id(&self) -> &str {
+            "scripted"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_and_merge_agents() {
+        let repo = init_repo();
+        let storage = tempfile::tempdir().unwrap();
+        let session_repo = Arc::new(SessionRepository::new(
+            JsonStorage::new(storage.path()),
+            Bus::new(),
+        ));
+
+        let provider = ScriptedModel::new();
+        provider.expect_bash("call_1", "echo one > one.txt");
+        provider.expect_text("Wrote one");
+        provider.expect_bash("call_2", "echo two > two.txt");
+        provider.expect_text("Wrote two");
+
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(wonopcode_tools::bash::BashTool));
+        let bus = Bus::new();
+        let mut rx = bus.subscribe::<OrchestrationUpdated>().await;
+
+        // One at a time so the scripted responses come in order
+        let orchestrator = Orchestrator::new(
+            Arc::new(provider),
+            Arc::new(tools),
+            session_repo.clone(),
+            bus,
+            WorktreeManager::new(repo.path()),
+            "proj_1",
+        )
+        .with_max_parallel(1);
+
+        let agents = orchestrator
+            .run(vec!["Write one".to_string(), "Write two".to_string()])
+            .await
+            .unwrap();
+        assert!(agents.iter().all(|a| a.state == AgentState::Done));
+        assert_eq!(agents[0].changed_files, vec!["one.txt"]);
+        assert_eq!(agents[1].summary.as_deref(), Some("Wrote two"));
+        assert!(agents[0]
+            .name
+            .ends_with(&format!("{}-1-write-one", orchestrator.id())));
+        assert_eq!(rx.recv().await.unwrap().agents.len(), 2);
+
+        let session = session_repo
+            .get("proj_1", agents[0].session_id.as_ref().unwrap())
+            .await
+            .unwrap();
+        assert!(session.tags.contains(&SESSION_TAG.to_string()));
+        assert_eq!(
+            session_repo
+                .messages("proj_1", &session.id, None)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+        // Nothing leaks into the main checkout before the merge
+        assert!(!repo.path().join("one.txt").exists());
+
+        let agents = orchestrator.merge().await.unwrap();
+        assert!(agents.iter().all(|a| a.state == AgentState::Merged));
+        assert!(repo.path().join("one.txt").exists());
+        assert!(repo.path().join("two.txt").exists());
+        assert_eq!(git_sync(repo.path(), &["status", "--porcelain"]), "");
+        assert_eq!(
+            git_sync(repo.path(), &["worktree", "list"]).lines().count(),
+            1
+        );
+
+        assert!(orchestrator.run(vec!["Again".to_string()]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_agents_obey_permission_rules() {
+        let repo = init_repo();
+        let storage = tempfile::tempdir().unwrap();
+        let provider = ScriptedModel::new();
+        provider.expect_bash("call_1", "echo one > one.txt");
+        provider.expect_text("Tried");
+
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(wonopcode_tools::bash::BashTool));
+        let bus = Bus::new();
+        let permissions = Arc::new(PermissionManager::new(bus.clone()));
+        permissions
+            .add_rule(crate::permission::PermissionRule::deny("bash"))
+            .await;
+
+        let orchestrator = Orchestrator::new(
+            Arc::new(provider),
+            Arc::new(tools),
+            Arc::new(SessionRepository::new(
+                JsonStorage::new(storage.path()),
+                Bus::new(),
+            )),
+            bus,
+            WorktreeManager::new(repo.path()),
+            "proj_1",
+        )
+        .with_permissions(permissions, false);
+
+        let agents = orchestrator
+            .run(vec!["Write one".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(agents[0].state, AgentState::Done);
+        assert_eq!(agents[0].tool_calls, 1);
+        assert!(agents[0].changed_files.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_orchestration() {
+        let repo = init_repo();
+        let storage = tempfile::tempdir().unwrap();
+        let orchestrator = Orchestrator::new(
+            Arc::new(ScriptedModel::new()),
+            Arc::new(ToolRegistry::new()),
+            Arc::new(SessionRepository::new(
+                JsonStorage::new(storage.path()),
+                Bus::new(),
+            )),
+            Bus::new(),
+            WorktreeManager::new(repo.path()),
+            "proj_1",
+        );
+        orchestrator.cancel();
+
+        let agents = orchestrator.run(vec!["Task".to_string()]).await.unwrap();
+        assert_eq!(agents[0].state, AgentState::Cancelled);
+        assert!(agents[0].branch.is_none());
+    }
+}
@@ -4,13 +4,19 @@
 //! executed and with what parameters. It supports:
 //! - Always allow/deny rules
 //! - Per-session permissions
-//! - Wildcard patterns for paths
+//! - Glob patterns for paths, regexes for commands and tool arguments
+//! - Rule priorities and expiry times
+//! - An audit log of every decision (see [`crate::audit`])
 // @ace:design DES-T90R4U-ZQ8
 // @ace:implements COMP-T90R4U-Q60
 
+use crate::audit::{PermissionAudit, PermissionAuditEntry};
 use crate::bus::{Bus, PermissionRequest, PermissionResponse};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
 use tokio::sync::{oneshot, RwLock};
 use wonopcode_util::wildcard;
 
@@ -33,6 +39,10 @@ impl Default for Decision {
 }
 
 /// A permission rule.
+///
+/// Every condition set on the rule must match. When several rules match, the
+/// one with the highest priority decides; on ties, session rules beat global
+/// rules and later rules beat earlier ones.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PermissionRule {
     /// Tool name pattern (supports wildcards).
@@ -40,42 +50,44 @@ pub struct PermissionRule {
     /// Action pattern (supports wildcards).
     #[serde(default)]
     pub action: Option<String>,
-    /// Path pattern (supports wildcards).
+    /// Path glob, e.g. `src/**/*.rs`.
     #[serde(default)]
     pub path: Option<String>,
+    /// Regex matched against the tool's `command` argument, e.g. of `bash`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Regexes matched against tool arguments, by argument name. Arguments
+    /// that aren't strings are matched against their JSON text.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, String>,
     /// Decision for matching requests.
     pub decision: Decision,
+    /// Rules with a higher priority are checked first.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// When the rule stops applying.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+}
+
+fn is_zero(n: &i32) -> bool {
+    *n == 0
 }
 
 impl PermissionRule {
     /// Create a new rule that allows a tool.
     pub fn allow(tool: impl Into<String>) -> Self {
-        Self {
-            tool: tool.into(),
-            action: None,
-            path: None,
-            decision: Decision::Allow,
-        }
+        Self::with_decision(tool, Decision::Allow)
     }
 
     /// Create a new rule that denies a tool.
     pub fn deny(tool: impl Into<String>) -> Self {
-        Self {
-            tool: tool.into(),
-            action: None,
-            path: None,
-            decision: Decision::Deny,
-        }
+        Self::with_decision(tool, Decision::Deny)
     }
 
     /// Create a new rule that asks the user.
     pub fn ask(tool: impl Into<String>) -> Self {
-        Self {
-            tool: tool.into(),
-            action: None,
-            path: None,
-            decision: Decision::Ask,
-        }
+        Self::with_decision(tool, Decision::Ask)
     }
 
     /// Create a rule with a specific decision.
@@ -84,7 +96,11 @@ impl PermissionRule {
             tool: tool.into(),
             action: None,
             path: None,
+            command: None,
+            args: BTreeMap::new(),
             decision,
+            priority: 0,
+            expires: None,
         }
     }
 
@@ -94,8 +110,61 @@ impl PermissionRule {
         self
     }
 
+    /// Add a command regex to the rule.
+    pub fn with_command(mut self, regex: impl Into<String>) -> Self {
+        self.command = Some(regex.into());
+        self
+    }
+
+    /// Add a regex for a tool argument to the rule.
+    pub fn with_arg(mut self, name: impl Into<String>, regex: impl Into<String>) -> Self {
+        self.args.insert(name.into(), regex.into());
+        self
+    }
+
+    /// Set the rule's priority.
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Let the rule expire at the given time.
+    pub fn with_expiry(mut self, expires: DateTime<Utc>) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    /// Whether the rule has expired at `now`.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
     /// Check if this rule matches a request.
+    ///
+    /// Rules with command or argument conditions never match here, since the
+    /// tool arguments are unknown; use [`PermissionRule::matches_check`].
     pub fn matches(&self, tool: &str, action: Option<&str>, path: Option<&str>) -> bool {
+        self.matches_input(tool, action, path, &serde_json::Value::Null)
+    }
+
+    /// Check if this rule matches a permission check, including its tool
+    /// arguments.
+    pub fn matches_check(&self, check: &PermissionCheck) -> bool {
+        self.matches_input(
+            &check.tool,
+            Some(&check.action),
+            check.path.as_deref(),
+            &check.details,
+        )
+    }
+
+    fn matches_input(
+        &self,
+        tool: &str,
+        action: Option<&str>,
+        path: Option<&str>,
+        input: &serde_json::Value,
+    ) -> bool {
         // Check tool pattern
         if !wildcard::matches(&self.tool, tool) {
             return false;
@@ -115,7 +184,7 @@ impl PermissionRule {
         // Check path pattern if specified
         if let Some(ref path_pattern) = self.path {
             if let Some(req_path) = path {
-                if !wildcard::matches(path_pattern, req_path) {
+                if !path_matches(path_pattern, req_path) {
                     return false;
                 }
             } else {
@@ -123,10 +192,112 @@ impl PermissionRule {
             }
         }
 
+        // Check command regex if specified
+        if let Some(ref command_regex) = self.command {
+            match input.get("command").and_then(|v| v.as_str()) {
+                Some(command) if regex_matches(command_regex, command) => {}
+                _ => return false,
+            }
+        }
+
+        // Check argument regexes
+        for (name, arg_regex) in &self.args {
+            let matched = match input.get(name) {
+                Some(serde_json::Value::String(value)) => regex_matches(arg_regex, value),
+                Some(value) => regex_matches(arg_regex, &value.to_string()),
+                None => false,
+            };
+            if !matched {
+                return false;
+            }
+        }
+
         true
     }
 }
 
+impl fmt::Display for PermissionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decision = match self.decision {
+            Decision::Allow => "allow",
+            Decision::Deny => "deny",
+            Decision::Ask => "ask",
+        };
+        write!(f, "{decision} {}", self.tool)?;
+        if let Some(action) = &self.action {
+            write!(f, " action={action}")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, " path={path}")?;
+        }
+        if let Some(command) = &self.command {
+            write!(f, " command=/{command}/")?;
+        }
+        for (name, regex) in &self.args {
+            write!(f, " {name}=/{regex}/")?;
+        }
+        if self.priority != 0 {
+            write!(f, " priority={}", self.priority)?;
+        }
+        Ok(())
+    }
+}
+
+/// Match a path against a glob, falling back to wildcard matching for
+/// patterns that aren't valid globs.
+///
+/// `*` doesn't match across directories; `**` does.
+fn path_matches(pattern: &str, path: &str) -> bool {
+    const OPTIONS: glob::MatchOptions = glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    match glob::Pattern::new(pattern) {
+        Ok(glob) => glob.matches_with(path, OPTIONS),
+        Err(_) => wildcard::matches(pattern, path),
+    }
+}
+
+/// A path as rules see it: relative to `root`, with `.` and `..` resolved.
+///
+/// Paths outside `root` stay absolute.
+pub fn relative_path(path: &str, root: &Path) -> String {
+    let root = normalize(root);
+    let path = normalize(&root.join(path));
+    match path.strip_prefix(&root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Resolve `.` and `..` components without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Match text against a regex. Invalid regexes never match.
+fn regex_matches(pattern: &str, text: &str) -> bool {
+    match regex::Regex::new(pattern) {
+        Ok(re) => re.is_match(text),
+        Err(e) => {
+            tracing::warn!(pattern, error = %e, "Invalid regex in permission rule");
+            false
+        }
+    }
+}
+
 /// Permission request for a tool action.
 #[derive(Debug, Clone)]
 pub struct PermissionCheck {
@@ -144,6 +315,194 @@ pub struct PermissionCheck {
     pub details: serde_json::Value,
 }
 
+impl PermissionCheck {
+    /// The check for a tool call, with the action, path and description
+    /// derived from its input.
+    ///
+    /// The path is made relative to `root`, the project directory, so that
+    /// rules like `src/**` match however the tool was given the path.
+    pub fn for_tool(
+        id: impl Into<String>,
+        tool: &str,
+        input: &serde_json::Value,
+        root: &Path,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            tool: tool.to_string(),
+            action: tool_action(tool, input),
+            description: tool_description(tool, input),
+            path: tool_path(input).map(|path| relative_path(&path, root)),
+            details: input.clone(),
+        }
+    }
+}
+
+/// Extract path from tool input for permission checking.
+fn tool_path(input: &serde_json::Value) -> Option<String> {
+    // Different tools use different field names for paths
+    let path_fields = ["filePath", "path", "file", "directory", "workdir"];
+
+    for field in &path_fields {
+        if let Some(path) = input.get(field).and_then(|v| v.as_str()) {
+            return Some(path.to_string());
+        }
+    }
+
+    // For bash tool, try to extract path from command
+    if let Some(command) = input.get("command").and_then(|v| v.as_str()) {
+        // Extract first path-like argument from command
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        for part in parts.iter().skip(1) {
+            if part.starts_with('/') || part.starts_with("./") || part.starts_with("../") {
+                return Some(part.to_string());
+            }
+        }
+    }
+
+    None
+}
+
+/// Determine the action type for a tool call.
+fn tool_action(tool_name: &str, input: &serde_json::Value) -> String {
+    match tool_name {
+        "read" => "read".to_string(),
+        "write" => "write".to_string(),
+        "edit" | "multiedit" => "edit".to_string(),
+        "glob" | "grep" => "search".to_string(),
+        "bash" => {
+            // Determine if it's a read or write operation
+            if let Some(command) = input.get("command").and_then(|v| v.as_str()) {
+                let read_commands = [
+                    "cat",
+                    "head",
+                    "tail",
+                    "less",
+                    "more",
+                    "ls",
+                    "pwd",
+                    "find",
+                    "grep",
+                    "rg",
+                    "tree",
+                    "git status",
+                    "git log",
+                    "git diff",
+                ];
+                let write_commands = [
+                    "rm",
+                    "mv",
+                    "cp",
+                    "mkdir",
+                    "rmdir",
+                    "touch",
+                    "chmod",
+                    "chown",
+                    "git add",
+                    "git commit",
+                    "git push",
+                ];
+
+                if read_commands.iter().any(|c| command.starts_with(c)) {
+                    "execute_read".to_string()
+                } else if write_commands.iter().any(|c| command.starts_with(c)) {
+                    "execute_write".to_string()
+                } else {
+                    "execute".to_string()
+                }
+            } else {
+                "execute".to_string()
+            }
+        }
+        "webfetch" => "fetch".to_string(),
+        "task" => "spawn_agent".to_string(),
+        "todowrite" | "todoread" => "manage_todos".to_string(),
+        "lsp" => "lsp_query".to_string(),
+        "skill" => "load_skill".to_string(),
+        "memory" => "remember".to_string(),
+        _ => "execute".to_string(),
+    }
+}
+
+/// Format a human-readable description of the tool call for permission prompts.
+fn tool_description(tool_name: &str, input: &serde_json::Value) -> String {
+    match tool_name {
+        "read" => {
+            if let Some(path) = input.get("filePath").and_then(|v| v.as_str()) {
+                format!("Read file: {path}")
+            } else {
+                "Read a file".to_string()
+            }
+        }
+        "write" => {
+            if let Some(path) = input.get("filePath").and_then(|v| v.as_str()) {
+                format!("Write to file: {path}")
+            } else {
+                "Write to a file".to_string()
+            }
+        }
+        "edit" => {
+            if let Some(path) = input.get("filePath").and_then(|v| v.as_str()) {
+                format!("Edit file: {path}")
+            } else {
+                "Edit a file".to_string()
+            }
+        }
+        "bash" => {
+            if let Some(command) = input.get("command").and_then(|v| v.as_str()) {
+                let truncated = if command.len() > 60 {
+                    format!("{}...", &command[..60])
+                } else {
+                    command.to_string()
+                };
+                format!("Execute: {truncated}")
+            } else {
+                "Execute a bash command".to_string()
+            }
+        }
+        "glob" => {
+            if let Some(pattern) = input.get("pattern").and_then(|v| v.as_str()) {
+                format!("Search files: {pattern}")
+            } else {
+                "Search for files".to_string()
+            }
+        }
+        "grep" => {
+            if let Some(pattern) = input.get("pattern").and_then(|v| v.as_str()) {
+                format!("Search content: {pattern}")
+            } else {
+                "Search file contents".to_string()
+            }
+        }
+        "webfetch" => {
+            if let Some(url) = input.get("url").and_then(|v| v.as_str()) {
+                format!("Fetch URL: {url}")
+            } else {
+                "Fetch a web page".to_string()
+            }
+        }
+        "task" => {
+            if let Some(desc) = input.get("description").and_then(|v| v.as_str()) {
+                format!("Run task: {desc}")
+            } else {
+                "Run a sub-task".to_string()
+            }
+        }
+        "memory" => {
+            if let Some(entry) = input.get("entry").and_then(|v| v.as_str()) {
+                let section = input
+                    .get("section")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("notes");
+                format!("Remember ({section}): {entry}")
+            } else {
+                "Add to project memory".to_string()
+            }
+        }
+        _ => format!("Execute tool: {tool_name}"),
+    }
+}
+
 /// Pending permission request info, stored while waiting for user response.
 struct PendingRequest {
     /// Response channel.
@@ -173,6 +532,8 @@ pub struct PermissionManager {
     /// Shared sandbox runtime (set when sandbox starts).
     /// Stored as Any so we can downcast to the concrete type when needed.
     sandbox_runtime: RwLock<Option<std::sync::Arc<dyn std::any::Any + Send + Sync>>>,
+    /// Audit log that records every decision, if set.
+    audit: RwLock<Option<PermissionAudit>>,
 }
 
 impl PermissionManager {
@@ -185,9 +546,15 @@ impl PermissionManager {
             bus,
             sandbox_running: std::sync::atomic::AtomicBool::new(false),
             sandbox_runtime: RwLock::new(None),
+            audit: RwLock::new(None),
         }
     }
 
+    /// Set the audit log that records every permission decision.
+    pub async fn set_audit(&self, audit: Option<PermissionAudit>) {
+        *self.audit.write().await = audit;
+    }
+
     /// Set sandbox running state and optionally the runtime.
     pub fn set_sandbox_running(&self, running: bool) {
         self.sandbox_running
@@ -226,16 +593,18 @@ impl PermissionManager {
     /// Add a global rule.
     pub async fn add_rule(&self, rule: PermissionRule) {
         let mut rules = self.rules.write().await;
+        let now = Utc::now();
+        rules.retain(|r| !r.is_expired(now));
         rules.push(rule);
     }
 
     /// Add a session-specific rule.
     pub async fn add_session_rule(&self, session_id: &str, rule: PermissionRule) {
         let mut session_rules = self.session_rules.write().await;
-        session_rules
-            .entry(session_id.to_string())
-            .or_default()
-            .push(rule);
+        let rules = session_rules.entry(session_id.to_string()).or_default();
+        let now = Utc::now();
+        rules.retain(|r| !r.is_expired(now));
+        rules.push(rule);
     }
 
     /// Clear session-specific rules.
@@ -286,49 +655,50 @@ impl PermissionManager {
         check: PermissionCheck,
         sandbox_running: bool,
     ) -> bool {
-        // If sandbox is running, check sandbox rules first
-        if sandbox_running {
-            for rule in Self::sandbox_allow_all_rules() {
-                if rule.matches(&check.tool, Some(&check.action), check.path.as_deref()) {
-                    match rule.decision {
-                        Decision::Allow => return true,
-                        Decision::Deny => return false,
-                        Decision::Ask => break,
-                    }
-                }
-            }
-        }
-
-        // First check session-specific rules
-        let session_rules = self.session_rules.read().await;
-        if let Some(rules) = session_rules.get(session_id) {
-            for rule in rules.iter().rev() {
-                if rule.matches(&check.tool, Some(&check.action), check.path.as_deref()) {
-                    match rule.decision {
-                        Decision::Allow => return true,
-                        Decision::Deny => return false,
-                        Decision::Ask => break,
-                    }
-                }
-            }
-        }
-        drop(session_rules);
-
-        // Then check global rules
-        let rules = self.rules.read().await;
-        for rule in rules.iter().rev() {
-            if rule.matches(&check.tool, Some(&check.action), check.path.as_deref()) {
-                match rule.decision {
-                    Decision::Allow => return true,
-                    Decision::Deny => return false,
-                    Decision::Ask => break,
-                }
+        let rule = self
+            .rules_in_order(session_id, sandbox_running)
+            .await
+            .into_iter()
+            .find(|rule| rule.matches_check(&check));
+
+        let mut entry = PermissionAuditEntry::new(
+            session_id,
+            &check.tool,
+            Some(&check.action),
+            check.path.as_deref(),
+        );
+        entry.description = check.description.clone();
+        entry.allowed = match rule.as_ref().map(|rule| rule.decision) {
+            Some(Decision::Allow) => true,
+            Some(Decision::Deny) => false,
+            // An "ask" rule or no matching rule: ask the user
+            Some(Decision::Ask) | None => {
+                entry.prompted = true;
+                self.ask_user(session_id, check).await
             }
-        }
-        drop(rules);
+        };
+        entry.rule = rule;
+        self.record(&entry).await;
+        entry.allowed
+    }
 
-        // No matching rule, ask the user
-        self.ask_user(session_id, check).await
+    /// Ask the user directly, ignoring all rules.
+    ///
+    /// Used for confirmations that must never be auto-approved, such as
+    /// continuing past a session budget. Returns false if the user denies or
+    /// doesn't answer in time.
+    pub async fn confirm(&self, session_id: &str, check: PermissionCheck) -> bool {
+        let mut entry = PermissionAuditEntry::new(
+            session_id,
+            &check.tool,
+            Some(&check.action),
+            check.path.as_deref(),
+        );
+        entry.description = check.description.clone();
+        entry.prompted = true;
+        entry.allowed = self.ask_user(session_id, check).await;
+        self.record(&entry).await;
+        entry.allowed
     }
 
     /// Check permission using only rules, without prompting the user.
@@ -341,36 +711,75 @@ impl PermissionManager {
         action: Option<&str>,
         path: Option<&str>,
     ) -> bool {
-        // First check session-specific rules
-        let session_rules = self.session_rules.read().await;
-        if let Some(rules) = session_rules.get(session_id) {
-            for rule in rules.iter().rev() {
-                if rule.matches(tool, action, path) {
-                    match rule.decision {
-                        Decision::Allow => return true,
-                        Decision::Deny => return false,
-                        Decision::Ask => continue, // Skip "Ask" rules in non-interactive mode
-                    }
-                }
+        // "Ask" rules are skipped in non-interactive mode, and no matching
+        // rule means deny
+        let rule = self
+            .rules_in_order(session_id, false)
+            .await
+            .into_iter()
+            .find(|rule| rule.decision != Decision::Ask && rule.matches(tool, action, path));
+
+        let mut entry = PermissionAuditEntry::new(session_id, tool, action, path);
+        entry.allowed = rule
+            .as_ref()
+            .is_some_and(|rule| rule.decision == Decision::Allow);
+        entry.rule = rule;
+        self.record(&entry).await;
+        entry.allowed
+    }
+
+    /// Record a decision in the audit log, if one is set.
+    async fn record(&self, entry: &PermissionAuditEntry) {
+        if let Some(audit) = self.audit.read().await.as_ref() {
+            if let Err(e) = audit.record(entry).await {
+                tracing::warn!(error = %e, tool = %entry.tool, "Failed to record permission decision");
             }
         }
-        drop(session_rules);
-
-        // Then check global rules
-        let rules = self.rules.read().await;
-        for rule in rules.iter().rev() {
-            if rule.matches(tool, action, path) {
-                match rule.decision {
-                    Decision::Allow => return true,
-                    Decision::Deny => return false,
-                    Decision::Ask => continue, // Skip "Ask" rules in non-interactive mode
-                }
-            }
+    }
+
+    /// Rules that apply to a session, in the order they are checked.
+    ///
+    /// Higher priorities come first. On ties, sandbox rules (while the sandbox
+    /// is running) come before session rules, session rules before global
+    /// rules, and later rules before earlier ones. Expired rules are left out.
+    async fn rules_in_order(&self, session_id: &str, sandbox_running: bool) -> Vec<PermissionRule> {
+        let mut ordered: Vec<(u8, usize, PermissionRule)> = Vec::new();
+        if sandbox_running {
+            ordered.extend(
+                Self::sandbox_allow_all_rules()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, rule)| (0, i, rule)),
+            );
         }
-        drop(rules);
+        if let Some(rules) = self.session_rules.read().await.get(session_id) {
+            ordered.extend(
+                rules
+                    .iter()
+                    .cloned()
+                    .enumerate()
+                    .map(|(i, rule)| (1, i, rule)),
+            );
+        }
+        ordered.extend(
+            self.rules
+                .read()
+                .await
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(i, rule)| (2, i, rule)),
+        );
 
-        // No matching rule in non-interactive mode means deny
-        false
+        let now = Utc::now();
+        ordered.retain(|(_, _, rule)| !rule.is_expired(now));
+        ordered.sort_by(|(tier_a, i_a, a), (tier_b, i_b, b)| {
+            b.priority
+                .cmp(&a.priority)
+                .then(tier_a.cmp(tier_b))
+                .then(i_b.cmp(i_a))
+        });
+        ordered.into_iter().map(|(_, _, rule)| rule).collect()
     }
 
     /// Ask the user for permission.
@@ -447,12 +856,8 @@ impl PermissionManager {
                     Decision::Deny
                 };
 
-                let mut rule = PermissionRule {
-                    tool: req.tool.clone(),
-                    action: Some(req.action.clone()),
-                    path: None,
-                    decision,
-                };
+                let mut rule = PermissionRule::with_decision(req.tool.clone(), decision);
+                rule.action = Some(req.action.clone());
 
                 // If the request had a path, create a pattern for it
                 // For now, we match the exact tool+action without path restriction
@@ -612,6 +1017,10 @@ impl PermissionManager {
             rules.push(write_rule);
         }
 
+        // Explicit rules come last, so they win over the settings above on
+        // equal priority
+        rules.extend(config.rules.iter().cloned());
+
         rules
     }
 }
@@ -620,6 +1029,43 @@ impl PermissionManager {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_check_for_tool_call() {
+        let root = Path::new("/project");
+        let check = PermissionCheck::for_tool(
+            "call_1",
+            "bash",
+            &serde_json::json!({"command": "rm -rf ./target"}),
+            root,
+        );
+        assert_eq!(check.action, "execute_write");
+        assert_eq!(check.path.as_deref(), Some("target"));
+        assert_eq!(check.description, "Execute: rm -rf ./target");
+
+        let check = PermissionCheck::for_tool(
+            "call_2",
+            "edit",
+            &serde_json::json!({"filePath": "/project/migrations/001.sql"}),
+            root,
+        );
+        assert_eq!(check.action, "edit");
+        assert_eq!(check.path.as_deref(), Some("migrations/001.sql"));
+        assert_eq!(check.description, "Edit file: /project/migrations/001.sql");
+        let rule = PermissionRule::deny("edit").with_path("migrations/**");
+        assert!(rule.matches_check(&check));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let root = Path::new("/project");
+        assert_eq!(relative_path("src/main.rs", root), "src/main.rs");
+        assert_eq!(relative_path("./src/../lib.rs", root), "lib.rs");
+        assert_eq!(relative_path("/project/src/main.rs", root), "src/main.rs");
+        assert_eq!(relative_path("/project", root), ".");
+        assert_eq!(relative_path("../other/a.rs", root), "/other/a.rs");
+        assert_eq!(relative_path("/etc/passwd", root), "/etc/passwd");
+    }
+
     #[test]
     fn test_decision_default() {
         let decision = Decision::default();
@@ -721,6 +1167,90 @@ mod tests {
         assert_eq!(parsed.decision, Decision::Allow);
     }
 
+    #[test]
+    fn test_permission_rule_matches_path_glob() {
+        let rule = PermissionRule::allow("edit").with_path("src/**/*.rs");
+        assert!(rule.matches("edit", None, Some("src/main.rs")));
+        assert!(rule.matches("edit", None, Some("src/core/lexer.rs")));
+        assert!(!rule.matches("edit", None, Some("src/core/lexer.toml")));
+        assert!(!rule.matches("edit", None, Some("tests/lexer.rs")));
+
+        // A single `*` stays within its directory
+        let rule = PermissionRule::allow("edit").with_path("src/*");
+        assert!(!rule.matches("edit", None, Some("src/core/lexer.rs")));
+    }
+
+    #[test]
+    fn test_permission_rule_matches_command_and_args() {
+        let check = |details: serde_json::Value| PermissionCheck {
+            id: "1".to_string(),
+            tool: "bash".to_string(),
+            action: "execute".to_string(),
+            description: String::new(),
+            path: None,
+            details,
+        };
+
+        let rule = PermissionRule::deny("bash").with_command(r"^\s*(sudo|rm\s+-rf)\b");
+        assert!(rule.matches_check(&check(serde_json::json!({"command": "rm -rf target"}))));
+        assert!(!rule.matches_check(&check(serde_json::json!({"command": "cargo test"}))));
+        assert!(!rule.matches_check(&check(serde_json::json!({}))));
+        // Without the tool arguments, command rules can't match
+        assert!(!rule.matches("bash", Some("execute"), None));
+
+        let rule = PermissionRule::allow("bash")
+            .with_arg("description", "(?i)test")
+            .with_arg("timeout", "^[0-9]{1,5}$");
+        assert!(rule.matches_check(&check(
+            serde_json::json!({"description": "Run tests", "timeout": 60000})
+        )));
+        assert!(!rule.matches_check(&check(
+            serde_json::json!({"description": "Run tests", "timeout": 600000})
+        )));
+        assert!(!rule.matches_check(&check(serde_json::json!({"description": "Run tests"}))));
+
+        // Invalid regexes never match
+        let rule = PermissionRule::allow("bash").with_command("(");
+        assert!(!rule.matches_check(&check(serde_json::json!({"command": "("}))));
+    }
+
+    #[test]
+    fn test_permission_rule_expiry() {
+        let now = Utc::now();
+        let rule = PermissionRule::allow("bash");
+        assert!(!rule.is_expired(now));
+        let rule = rule.with_expiry(now + chrono::Duration::minutes(5));
+        assert!(!rule.is_expired(now));
+        assert!(rule.is_expired(now + chrono::Duration::minutes(5)));
+    }
+
+    #[test]
+    fn test_permission_rule_deserializes_conditions() {
+        let rule: PermissionRule = serde_json::from_str(
+            r#"{
+                "tool": "bash",
+                "command": "^git push",
+                "args": {"description": "deploy"},
+                "decision": "deny",
+                "priority": 10,
+                "expires": "2030-01-01T00:00:00Z"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(rule.command.as_deref(), Some("^git push"));
+        assert_eq!(rule.args["description"], "deploy");
+        assert_eq!(rule.priority, 10);
+        assert_eq!(
+            rule.expires.unwrap().to_rfc3339(),
+            "2030-01-01T00:00:00+00:00"
+        );
+
+        // Unset conditions are left out
+        let json = serde_json::to_string(&PermissionRule::allow("read")).unwrap();
+        assert!(!json.contains("priority"));
+        assert!(!json.contains("expires"));
+    }
+
     #[test]
     fn test_permission_check() {
         let check = PermissionCheck {
@@ -966,6 +1496,7 @@ mod tests {
             webfetch: None,
             external_directory: None,
             allow_all_in_sandbox: None,
+            rules: vec![],
         };
 
         let rules = PermissionManager::rules_from_config(&config);
@@ -983,6 +1514,7 @@ mod tests {
             webfetch: Some(Permission::Deny),
             external_directory: None,
             allow_all_in_sandbox: None,
+            rules: vec![],
         };
 
         let rules = PermissionManager::rules_from_config(&config);
@@ -998,6 +1530,7 @@ mod tests {
             webfetch: None,
             external_directory: None,
             allow_all_in_sandbox: None,
+            rules: vec![],
         };
 
         let rules = PermissionManager::rules_from_config(&config);
@@ -1016,6 +1549,7 @@ mod tests {
             webfetch: None,
             external_directory: None,
             allow_all_in_sandbox: None,
+            rules: vec![],
         };
 
         let rules = PermissionManager::rules_from_config(&config);
@@ -1026,6 +1560,20 @@ mod tests {
         assert!(rules.iter().any(|r| r.action == Some("rm*".to_string())));
     }
 
+    #[test]
+    fn test_rules_from_config_appends_rules() {
+        use crate::config::{Permission, PermissionConfig, PermissionOrMap};
+
+        let config = PermissionConfig {
+            bash: Some(PermissionOrMap::Single(Permission::Allow)),
+            rules: vec![PermissionRule::deny("bash").with_command("^rm ")],
+            ..Default::default()
+        };
+        let rules = PermissionManager::rules_from_config(&config);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].command.as_deref(), Some("^rm "));
+    }
+
     #[test]
     fn test_rules_from_config_external_directory() {
         use crate::config::{Permission, PermissionConfig};
@@ -1036,6 +1584,7 @@ mod tests {
             webfetch: None,
             external_directory: Some(Permission::Ask),
             allow_all_in_sandbox: None,
+            rules: vec![],
         };
 
         let rules = PermissionManager::rules_from_config(&config);
@@ -1080,6 +1629,7 @@ mod tests {
             webfetch: None,
             external_directory: None,
             allow_all_in_sandbox: None,
+            rules: vec![],
         };
 
         manager.reload_from_config(&config).await;
@@ -1235,6 +1785,108 @@ mod tests {
         assert!(allowed);
     }
 
+    #[tokio::test]
+    async fn test_rule_priority_beats_order_and_scope() {
+        let bus = Bus::new();
+        let manager = PermissionManager::new(bus);
+
+        // A high-priority global deny wins over a later global allow and a
+        // remembered session allow
+        manager
+            .add_rule(PermissionRule::deny("bash").with_priority(10))
+            .await;
+        manager.add_rule(PermissionRule::allow("bash")).await;
+        manager
+            .add_session_rule("session_1", PermissionRule::allow("bash"))
+            .await;
+        assert!(
+            !manager
+                .check_rules_only("session_1", "bash", None, None)
+                .await
+        );
+
+        // An even higher priority allow wins over it
+        manager
+            .add_session_rule("session_1", PermissionRule::allow("bash").with_priority(20))
+            .await;
+        assert!(
+            manager
+                .check_rules_only("session_1", "bash", None, None)
+                .await
+        );
+        // Session rules only apply to their session
+        assert!(
+            !manager
+                .check_rules_only("session_2", "bash", None, None)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_rules_are_ignored() {
+        let bus = Bus::new();
+        let manager = PermissionManager::new(bus);
+
+        manager
+            .add_rule(
+                PermissionRule::allow("bash")
+                    .with_expiry(Utc::now() - chrono::Duration::seconds(1)),
+            )
+            .await;
+        manager
+            .add_rule(
+                PermissionRule::allow("edit").with_expiry(Utc::now() + chrono::Duration::hours(1)),
+            )
+            .await;
+
+        assert!(
+            !manager
+                .check_rules_only("session_1", "bash", None, None)
+                .await
+        );
+        assert!(
+            manager
+                .check_rules_only("session_1", "edit", None, None)
+                .await
+        );
+        // Expired rules are dropped when rules are added
+        assert_eq!(manager.rules.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_check_with_sandbox_high_priority_deny() {
+        let bus = Bus::new();
+        let manager = PermissionManager::new(bus);
+        manager
+            .add_rule(
+                PermissionRule::deny("bash")
+                    .with_command(r"git\s+push")
+                    .with_priority(1),
+            )
+            .await;
+
+        let check = |command: &str| PermissionCheck {
+            id: "1".to_string(),
+            tool: "bash".to_string(),
+            action: "execute".to_string(),
+            description: String::new(),
+            path: None,
+            details: serde_json::json!({ "command": command }),
+        };
+
+        // The sandbox allows bash, but not pushing
+        assert!(
+            manager
+                .check_with_sandbox("session_1", check("cargo build"), true)
+                .await
+        );
+        assert!(
+            !manager
+                .check_with_sandbox("session_1", check("git push origin main"), true)
+                .await
+        );
+    }
+
     #[tokio::test]
     async fn test_check_rules_only_with_path() {
         let bus = Bus::new();
@@ -1257,6 +1909,53 @@ mod tests {
         assert!(!allowed);
     }
 
+    #[tokio::test]
+    async fn test_decisions_are_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        let audit = PermissionAudit::new(
+            wonopcode_storage::json::JsonStorage::new(dir.path()),
+            "proj_1",
+        );
+        let manager = PermissionManager::new(Bus::new());
+        manager.set_audit(Some(audit.clone())).await;
+        manager
+            .add_rule(PermissionRule::deny("edit").with_path("secrets/*"))
+            .await;
+
+        let check = PermissionCheck {
+            id: "1".to_string(),
+            tool: "edit".to_string(),
+            action: "edit".to_string(),
+            description: "Edit secrets/key.pem".to_string(),
+            path: Some("secrets/key.pem".to_string()),
+            details: serde_json::Value::Null,
+        };
+        assert!(!manager.check("session_1", check).await);
+
+        let entries = audit
+            .list(&crate::audit::PermissionAuditFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].tool, "edit");
+        assert_eq!(entries[0].path.as_deref(), Some("secrets/key.pem"));
+        assert!(!entries[0].allowed);
+        assert!(!entries[0].prompted);
+        assert_eq!(entries[0].source(), "rule: deny edit path=secrets/*");
+    }
+
+    #[test]
+    fn test_permission_rule_display() {
+        let rule = PermissionRule::deny("bash")
+            .with_command(r"git\s+push")
+            .with_priority(2);
+        assert_eq!(
+            rule.to_string(),
+            r"deny bash command=/git\s+push/ priority=2"
+        );
+        assert_eq!(PermissionRule::ask("*").to_string(), "ask *");
+    }
+
     #[test]
     fn test_permission_check_clone() {
         let check = PermissionCheck {
@@ -0,0 +1,546 @@
+//! WASM plugin system.
+//!
+//! Plugins are WebAssembly components implementing the `plugin` world in
+//! `wit/plugin.wit`. A plugin can contribute:
+//! - Tools the model can call
+//! - Slash commands (prompt templates)
+//! - Handlers for hook events (`file_edited`, `tool_executed`, ...)
+//!
+//! Plugins are discovered in:
+//! - Global: `~/.config/wonopcode/plugins/*.wasm`
+//! - Project: `.wonopcode/plugins/*.wasm`
+//! - Configuration file: `wonopcode.json` -> `plugins.paths`
+//!
+//! Each call runs with a fuel budget, so a misbehaving plugin traps instead of
+//! hanging the agent. A trapped instance is re-instantiated on the next call.
+
+use crate::command::{Command, CommandRegistry};
+use crate::config::{Config, PluginsConfig};
+use crate::hook::HookEvent;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error, info, warn};
+use wasmtime::component::{Component, HasSelf, Linker};
+use wasmtime::{Engine, Store};
+use wonopcode_tools::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "wit",
+        world: "plugin",
+    });
+}
+
+use bindings::wonopcode::plugin::host::{self, LogLevel};
+use bindings::wonopcode::plugin::types::{self, ToolDef};
+
+/// File extension of plugin components.
+pub const PLUGIN_EXTENSION: &str = "wasm";
+
+/// Default fuel budget per plugin call.
+pub const DEFAULT_FUEL: u64 = 1_000_000_000;
+
+/// Plugin error types.
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("Failed to load plugin {path}: {message}")]
+    Load { path: PathBuf, message: String },
+
+    #[error("Plugin {plugin} failed: {message}")]
+    Call { plugin: String, message: String },
+}
+
+/// Per-instance host state.
+struct HostState {
+    plugin: String,
+}
+
+impl host::Host for HostState {
+    fn log(&mut self, level: LogLevel, message: String) {
+        match level {
+            LogLevel::Debug => debug!(plugin = %self.plugin, "{message}"),
+            LogLevel::Info => info!(plugin = %self.plugin, "{message}"),
+            LogLevel::Warn => warn!(plugin = %self.plugin, "{message}"),
+            LogLevel::Error => error!(plugin = %self.plugin, "{message}"),
+        }
+    }
+}
+
+impl types::Host for HostState {}
+
+/// A live component instance.
+struct PluginInstance {
+    store: Store<HostState>,
+    bindings: bindings::Plugin,
+}
+
+/// A loaded plugin.
+pub struct Plugin {
+    name: String,
+    version: String,
+    path: PathBuf,
+    tools: Vec<ToolDef>,
+    commands: Vec<Command>,
+    events: Vec<HookEvent>,
+    fuel: u64,
+    engine: Engine,
+    component: Component,
+    linker: Linker<HostState>,
+    instance: Mutex<Option<PluginInstance>>,
+}
+
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("name", &self.name)
+            .field("version", &self.version)
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Plugin {
+    /// Compile, instantiate and describe a plugin component.
+    ///
+    /// This is CPU-bound; call it from a blocking task.
+    pub fn load(engine: &Engine, path: &Path, fuel: u64) -> Result<Self, PluginError> {
+        let load_err = |e: wasmtime::Error| PluginError::Load {
+            path: path.to_path_buf(),
+            message: format!("{e:#}"),
+        };
+
+        let component = Component::from_file(engine, path).map_err(load_err)?;
+        let mut linker = Linker::new(engine);
+        bindings::Plugin::add_to_linker::<_, HasSelf<_>>(&mut linker, |state| state)
+            .map_err(load_err)?;
+
+        let fallback_name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("plugin")
+            .to_string();
+        let mut instance =
+            instantiate(engine, &component, &linker, &fallback_name, fuel).map_err(load_err)?;
+        let info = instance
+            .bindings
+            .call_info(&mut instance.store)
+            .map_err(load_err)?;
+
+        let name = if info.name.is_empty() {
+            fallback_name
+        } else {
+            info.name
+        };
+        instance.store.data_mut().plugin = name.clone();
+
+        let commands = info
+            .commands
+            .into_iter()
+            .map(|c| Command::new(c.name, c.template).with_description(c.description))
+            .collect();
+        let events = info
+            .events
+            .iter()
+            .filter_map(|e| {
+                let event = HookEvent::parse(e);
+                if event.is_none() {
+                    warn!(plugin = %name, event = %e, "Unknown hook event");
+                }
+                event
+            })
+            .collect();
+
+        Ok(Self {
+            name,
+            version: info.version,
+            path: path.to_path_buf(),
+            tools: info.tools,
+            commands,
+            events,
+            fuel,
+            engine: engine.clone(),
+            component,
+            linker,
+            instance: Mutex::new(Some(instance)),
+        })
+    }
+
+    /// Plugin name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Plugin version.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Path the plugin was loaded from.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Slash commands contributed by the plugin.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Whether the plugin handles an event.
+    pub fn handles(&self, event: HookEvent) -> bool {
+        self.events.contains(&event)
+    }
+
+    /// Call one of the plugin's tools with JSON arguments.
+    ///
+    /// Returns the tool output, or the error message reported by the plugin.
+    pub fn call_tool(
+        &self,
+        name: &str,
+        args: &Value,
+    ) -> Result<Result<String, String>, PluginError> {
+        let args = args.to_string();
+        self.call(|inst| inst.bindings.call_call_tool(&mut inst.store, name, &args))
+    }
+
+    /// Deliver a hook event to the plugin.
+    pub fn on_event(&self, event: HookEvent, payload: &Value) -> Result<(), PluginError> {
+        let payload = payload.to_string();
+        self.call(|inst| {
+            inst.bindings
+                .call_on_event(&mut inst.store, event.as_str(), &payload)
+        })
+    }
+
+    /// Run `f` against the instance with a fresh fuel budget.
+    fn call<R>(
+        &self,
+        f: impl FnOnce(&mut PluginInstance) -> wasmtime::Result<R>,
+    ) -> Result<R, PluginError> {
+        let call_err = |e: wasmtime::Error| PluginError::Call {
+            plugin: self.name.clone(),
+            message: format!("{e:#}"),
+        };
+
+        let mut guard = self.instance.lock().unwrap_or_else(|e| e.into_inner());
+        let inst = match guard.as_mut() {
+            Some(inst) => inst,
+            None => guard.insert(
+                instantiate(
+                    &self.engine,
+                    &self.component,
+                    &self.linker,
+                    &self.name,
+                    self.fuel,
+                )
+                .map_err(call_err)?,
+            ),
+        };
+        inst.store.set_fuel(self.fuel).map_err(call_err)?;
+
+        let result = f(inst);
+        if result.is_err() {
+            // A trapped component can't be entered again; start over next time.
+            *guard = None;
+        }
+        result.map_err(call_err)
+    }
+}
+
+fn instantiate(
+    engine: &Engine,
+    component: &Component,
+    linker: &Linker<HostState>,
+    name: &str,
+    fuel: u64,
+) -> wasmtime::Result<PluginInstance> {
+    let mut store = Store::new(
+        engine,
+        HostState {
+            plugin: name.to_string(),
+        },
+    );
+    store.set_fuel(fuel)?;
+    let bindings = bindings::Plugin::instantiate(&mut store, component, linker)?;
+    Ok(PluginInstance { store, bindings })
+}
+
+/// A plugin tool exposed through the tool registry.
+pub struct PluginTool {
+    plugin: Arc<Plugin>,
+    def: ToolDef,
+    id: String,
+    schema: Value,
+}
+
+impl PluginTool {
+    fn new(plugin: Arc<Plugin>, def: ToolDef) -> Self {
+        let id = format!("{}_{}", plugin.name, def.name);
+        let schema = serde_json::from_str(&def.parameters).unwrap_or_else(|e| {
+            warn!(tool = %id, error = %e, "Invalid tool parameter schema");
+            serde_json::json!({"type": "object", "properties": {}})
+        });
+        Self {
+            plugin,
+            def,
+            id,
+            schema,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for PluginTool {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn description(&self) -> &str {
+        &self.def.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.schema.clone()
+    }
+
+    async fn execute(&self, args: Value, _ctx: &ToolContext) -> ToolResult<ToolOutput> {
+        let plugin = self.plugin.clone();
+        let name = self.def.name.clone();
+        let result = tokio::task::spawn_blocking(move || plugin.call_tool(&name, &args))
+            .await
+            .map_err(|e| ToolError::execution_failed(e.to_string()))?
+            .map_err(|e| ToolError::execution_failed(e.to_string()))?;
+
+        match result {
+            Ok(output) => Ok(ToolOutput::new(&self.id, output)),
+            Err(message) => Err(ToolError::execution_failed(message)),
+        }
+    }
+}
+
+/// Manager for loaded plugins.
+pub struct PluginManager {
+    plugins: Vec<Arc<Plugin>>,
+}
+
+impl PluginManager {
+    /// Create a manager with no plugins.
+    pub fn empty() -> Self {
+        Self {
+            plugins: Vec::new(),
+        }
+    }
+
+    /// Discover and load plugins for a project.
+    ///
+    /// Plugins that fail to load are logged and skipped.
+    pub async fn load(config: Option<&PluginsConfig>, project_dir: &Path) -> Self {
+        if config.and_then(|c| c.enabled) == Some(false) {
+            return Self::empty();
+        }
+
+        let paths = plugin_paths(config, project_dir);
+        if paths.is_empty() {
+            return Self::empty();
+        }
+
+        let fuel = config.and_then(|c| c.fuel).unwrap_or(DEFAULT_FUEL);
+        let disabled = config.and_then(|c| c.disabled.clone()).unwrap_or_default();
+
+        let loaded = tokio::task::spawn_blocking(move || {
+            let mut wasm_config = wasmtime::Config::new();
+            wasm_config.wasm_component_model(true).consume_fuel(true);
+            let engine = match Engine::new(&wasm_config) {
+                Ok(engine) => engine,
+                Err(e) => {
+                    error!(error = %e, "Failed to create plugin engine");
+                    return Vec::new();
+                }
+            };
+
+            let mut plugins: Vec<Arc<Plugin>> = Vec::new();
+            for path in paths {
+                match Plugin::load(&engine, &path, fuel) {
+                    Ok(plugin) if disabled.contains(&plugin.name) => {
+                        debug!(plugin = %plugin.name, "Plugin disabled, skipping");
+                    }
+                    Ok(plugin) if plugins.iter().any(|p| p.name == plugin.name) => {
+                        warn!(plugin = %plugin.name, path = %path.display(), "Duplicate plugin, skipping");
+                    }
+                    Ok(plugin) => {
+                        info!(
+                            plugin = %plugin.name,
+                            version = %plugin.version,
+                            tools = plugin.tools.len(),
+                            commands = plugin.commands.len(),
+                            "Plugin loaded"
+                        );
+                        plugins.push(Arc::new(plugin));
+                    }
+                    Err(e) => warn!(error = %e, "Skipping plugin"),
+                }
+            }
+            plugins
+        })
+        .await
+        .unwrap_or_default();
+
+        Self { plugins: loaded }
+    }
+
+    /// Loaded plugins.
+    pub fn plugins(&self) -> &[Arc<Plugin>] {
+        &self.plugins
+    }
+
+    /// Check if no plugins are loaded.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Tool wrappers for all plugin tools.
+    pub fn tools(&self) -> Vec<Arc<dyn Tool>> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| {
+                plugin.tools.iter().map(|def| {
+                    Arc::new(PluginTool::new(plugin.clone(), def.clone())) as Arc<dyn Tool>
+                })
+            })
+            .collect()
+    }
+
+    /// Register plugin slash commands.
+    ///
+    /// Existing commands with the same name take precedence.
+    pub fn register_commands(&self, registry: &mut CommandRegistry) {
+        for plugin in &self.plugins {
+            for command in &plugin.commands {
+                if registry.contains(&command.name) {
+                    warn!(plugin = %plugin.name, command = %command.name, "Command already defined, skipping");
+                    continue;
+                }
+                registry.register(command.clone());
+            }
+        }
+    }
+
+    /// Deliver an event to every plugin that handles it.
+    ///
+    /// Handlers run in the background; failures are logged.
+    pub fn dispatch(&self, event: HookEvent, payload: Value) {
+        let plugins: Vec<_> = self
+            .plugins
+            .iter()
+            .filter(|p| p.handles(event))
+            .cloned()
+            .collect();
+        if plugins.is_empty() {
+            return;
+        }
+
+        tokio::task::spawn_blocking(move || {
+            for plugin in plugins {
+                if let Err(e) = plugin.on_event(event, &payload) {
+                    warn!(event = event.as_str(), error = %e, "Plugin event handler failed");
+                }
+            }
+        });
+    }
+}
+
+/// Collect plugin files to load, in order: global, project, then configured paths.
+pub fn plugin_paths(config: Option<&PluginsConfig>, project_dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(global_dir) = Config::global_config_dir() {
+        dirs.push(global_dir.join("plugins"));
+    }
+    dirs.push(project_dir.join(".wonopcode").join("plugins"));
+
+    if let Some(paths) = config.and_then(|c| c.paths.as_ref()) {
+        for path in paths {
+            let path = PathBuf::from(path);
+            dirs.push(if path.is_absolute() {
+                path
+            } else {
+                project_dir.join(path)
+            });
+        }
+    }
+
+    let mut files = Vec::new();
+    for path in dirs {
+        if path.is_file() {
+            files.push(path);
+        } else if let Ok(entries) = std::fs::read_dir(&path) {
+            let mut found: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == PLUGIN_EXTENSION))
+                .collect();
+            found.sort();
+            files.extend(found);
+        }
+    }
+    files.dedup();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plugin_paths_discovers_project_plugins() {
+        let dir = tempdir().unwrap();
+        let plugin_dir = dir.path().join(".wonopcode/plugins");
+        std::fs::create_dir_all(&plugin_dir).unwrap();
+        std::fs::write(plugin_dir.join("b.wasm"), b"").unwrap();
+        std::fs::write(plugin_dir.join("a.wasm"), b"").unwrap();
+        std::fs::write(plugin_dir.join("notes.txt"), b"").unwrap();
+
+        let paths = plugin_paths(None, dir.path());
+        let project: Vec<_> = paths.iter().filter(|p| p.starts_with(dir.path())).collect();
+        assert_eq!(
+            project,
+            vec![&plugin_dir.join("a.wasm"), &plugin_dir.join("b.wasm")]
+        );
+    }
+
+    #[test]
+    fn test_plugin_paths_from_config() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("extra.wasm"), b"").unwrap();
+
+        let config = PluginsConfig {
+            paths: Some(vec!["extra.wasm".to_string()]),
+            ..Default::default()
+        };
+        let paths = plugin_paths(Some(&config), dir.path());
+        assert!(paths.contains(&dir.path().join("extra.wasm")));
+    }
+
+    #[tokio::test]
+    async fn test_load_skips_invalid_plugins() {
+        let dir = tempdir().unwrap();
+        let plugin_dir = dir.path().join(".wonopcode/plugins");
+        std::fs::create_dir_all(&plugin_dir).unwrap();
+        std::fs::write(plugin_dir.join("broken.wasm"), b"not wasm").unwrap();
+
+        let manager = PluginManager::load(None, dir.path()).await;
+        assert!(manager.plugins().iter().all(|p| p.name() != "broken"));
+    }
+
+    #[tokio::test]
+    async fn test_load_disabled() {
+        let dir = tempdir().unwrap();
+        let config = PluginsConfig {
+            enabled: Some(false),
+            ..Default::default()
+        };
+        let manager = PluginManager::load(Some(&config), dir.path()).await;
+        assert!(manager.is_empty());
+    }
+}
@@ -3,22 +3,28 @@
 //! This module handles the back-and-forth conversation with AI providers:
 //! - Sending messages to the provider
 //! - Processing streaming responses
-//! - Executing tool calls
+//! - Executing tool calls, subject to permission rules and tool hooks
 //! - Handling continuation (tool_use -> continue)
+//! - Enforcing the session budget
 
-use crate::bus::{Bus, PartUpdated, SessionStatus, Status};
+use crate::budget::{BudgetCheck, BudgetStatus, BudgetTracker};
+use crate::bus::{BudgetExceeded, BudgetWarning, Bus, PartUpdated, SessionStatus, Status};
 use crate::error::CoreResult;
+use crate::hook::{HookRegistry, ToolCall};
 use crate::message::{ModelRef, UserMessage};
+use crate::permission::{PermissionCheck, PermissionManager};
 use crate::session::{Session, SessionRepository};
 use futures::StreamExt;
 use serde_json::Value;
 use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 use wonopcode_provider::{
     stream::{FinishReason, StreamChunk},
     ContentPart, GenerateOptions, LanguageModel, Message as ProviderMessage, Role, ToolDefinition,
 };
+use wonopcode_sandbox::SandboxRuntime;
 use wonopcode_snapshot::SnapshotStore;
 use wonopcode_tools::{ToolContext, ToolOutput, ToolRegistry};
 use wonopcode_util::FileTimeState;
@@ -64,6 +70,8 @@ pub struct PromptResult {
     pub finish_reason: FinishReason,
     /// Number of steps taken.
     pub steps: usize,
+    /// Set when the loop paused because a budget limit was reached.
+    pub budget_exceeded: Option<BudgetStatus>,
 }
 
 /// Result of a tool call.
@@ -105,6 +113,16 @@ pub struct PromptLoop {
     snapshot: Option<Arc<SnapshotStore>>,
     /// Optional file time tracker for concurrent edit detection.
     file_time: Option<Arc<FileTimeState>>,
+    /// Optional budget of the session, shared across prompts.
+    budget: Option<Arc<Mutex<BudgetTracker>>>,
+    /// Optional sandbox runtime for tool execution.
+    sandbox: Option<Arc<dyn SandboxRuntime>>,
+    /// Optional permission checks of tool calls.
+    permissions: Option<Arc<PermissionManager>>,
+    /// Whether everything is allowed while the sandbox runs.
+    allow_all_in_sandbox: bool,
+    /// Optional hooks run around tool calls.
+    hooks: Option<Arc<RwLock<HookRegistry>>>,
 }
 
 impl PromptLoop {
@@ -124,6 +142,11 @@ impl PromptLoop {
             cancel,
             snapshot: None,
             file_time: None,
+            budget: None,
+            sandbox: None,
+            permissions: None,
+            allow_all_in_sandbox: false,
+            hooks: None,
         }
     }
 
@@ -145,9 +168,52 @@ impl PromptLoop {
             cancel,
             snapshot,
             file_time,
+            budget: None,
+            sandbox: None,
+            permissions: None,
+            allow_all_in_sandbox: false,
+            hooks: None,
         }
     }
 
+    /// Enforce a session budget.
+    ///
+    /// The loop stops when a limit is reached and reports it in
+    /// [`PromptResult::budget_exceeded`]; call [`BudgetTracker::extend`] once
+    /// the user confirms before prompting again.
+    pub fn with_budget(mut self, budget: Arc<Mutex<BudgetTracker>>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Run tools in a sandbox.
+    pub fn with_sandbox(mut self, sandbox: Option<Arc<dyn SandboxRuntime>>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Check tool calls against permission rules, asking the user where no
+    /// rule decides. Calls that are denied fail.
+    ///
+    /// With `allow_all_in_sandbox`, everything is allowed while the sandbox
+    /// runs.
+    pub fn with_permissions(
+        mut self,
+        permissions: Arc<PermissionManager>,
+        allow_all_in_sandbox: bool,
+    ) -> Self {
+        self.permissions = Some(permissions);
+        self.allow_all_in_sandbox = allow_all_in_sandbox;
+        self
+    }
+
+    /// Run tool hooks around tool calls, which may deny a call or rewrite its
+    /// arguments and output.
+    pub fn with_hooks(mut self, hooks: Arc<RwLock<HookRegistry>>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
     /// Execute the prompt loop for a user message.
     #[allow(clippy::cognitive_complexity)]
     pub async fn run(
@@ -163,6 +229,7 @@ impl PromptLoop {
         let mut final_text = String::new();
         let mut finish_reason = FinishReason::EndTurn;
         let mut steps = 0;
+        let mut budget_exceeded = None;
 
         // Create user message
         let user_msg = UserMessage::new(
@@ -208,6 +275,13 @@ impl PromptLoop {
                 break;
             }
 
+            if let Some(budget) = &self.budget {
+                if let Some(status) = check_budget(budget, &self.bus, &session.id).await {
+                    budget_exceeded = Some(status);
+                    break;
+                }
+            }
+
             steps += 1;
             debug!(step = steps, "Starting prompt step");
 
@@ -330,6 +404,17 @@ impl PromptLoop {
             // Update totals
             total_input_tokens += step_input_tokens;
             total_output_tokens += step_output_tokens;
+            if let Some(budget) = &self.budget {
+                let cost = self
+                    .provider
+                    .model_info()
+                    .cost
+                    .calculate(step_input_tokens, step_output_tokens);
+                budget
+                    .lock()
+                    .await
+                    .record_step(step_input_tokens, step_output_tokens, cost);
+            }
             final_text = current_text.clone();
 
             // Add assistant message to history
@@ -352,17 +437,15 @@ impl PromptLoop {
             // Handle tool calls
             if !tool_calls.is_empty() {
                 info!("Executing {} tool calls", tool_calls.len());
+                if let Some(budget) = &self.budget {
+                    budget.lock().await.record_tool_calls(tool_calls.len());
+                }
 
                 for (call_id, tool_name, args_str) in tool_calls {
                     let input: Value = serde_json::from_str(&args_str).unwrap_or(Value::Null);
 
-                    // Execute the tool
-                    let result = self.execute_tool(session, &tool_name, input.clone()).await;
-
-                    let (output, success) = match result {
-                        Ok(out) => (out.output, true),
-                        Err(e) => (format!("Error: {e}"), false),
-                    };
+                    let (input, output, success) =
+                        self.call_tool(session, &call_id, &tool_name, input).await;
 
                     all_tool_calls.push(ToolCallResult {
                         tool: tool_name.clone(),
@@ -400,9 +483,85 @@ impl PromptLoop {
             tokens_output: total_output_tokens,
             finish_reason,
             steps,
+            budget_exceeded,
         })
     }
 
+    /// Run a tool call through the hooks and permission checks, and execute
+    /// it if allowed.
+    ///
+    /// Returns the arguments, as rewritten by hooks, the output and whether
+    /// the call succeeded.
+    async fn call_tool(
+        &self,
+        session: &Session,
+        call_id: &str,
+        tool_name: &str,
+        mut input: Value,
+    ) -> (Value, String, bool) {
+        // Normalize tool name - MCP tools have prefix like "mcp__wonopcode-tools__read"
+        let normalized = tool_name.rsplit("__").next().unwrap_or(tool_name);
+        let cwd = std::path::PathBuf::from(&session.directory);
+
+        if let Some(hooks) = &self.hooks {
+            let mut call = ToolCall::new(normalized, input.clone(), &cwd);
+            let before = {
+                let hooks = hooks.read().await;
+                if hooks.has_tool_hooks() {
+                    Some(hooks.before_tool(&mut call).await)
+                } else {
+                    None
+                }
+            };
+            match before {
+                Some(Err(reason)) => {
+                    return (
+                        input,
+                        format!("Tool execution blocked by hook: {reason}"),
+                        false,
+                    )
+                }
+                Some(Ok(())) => input = call.args,
+                None => {}
+            }
+        }
+
+        if let Some(permissions) = &self.permissions {
+            let sandbox_running = match &self.sandbox {
+                Some(sandbox) if self.allow_all_in_sandbox => sandbox.is_ready().await,
+                _ => false,
+            };
+            let check = PermissionCheck::for_tool(call_id, normalized, &input, &cwd);
+            // The user answers for the session that started the prompt
+            if !permissions
+                .check_with_sandbox("default", check, sandbox_running)
+                .await
+            {
+                warn!(tool = %tool_name, "Tool execution denied by permission manager");
+                let output = format!(
+                    "Tool execution denied: permission not granted for '{tool_name}'. \
+                     The user has declined to allow this tool execution."
+                );
+                return (input, output, false);
+            }
+        }
+
+        let (mut output, success) = match self.execute_tool(session, tool_name, input.clone()).await
+        {
+            Ok(out) => (out.output, true),
+            Err(e) => (format!("Error: {e}"), false),
+        };
+
+        if let Some(hooks) = &self.hooks {
+            let hooks = hooks.read().await;
+            if hooks.has_tool_hooks() {
+                let call = ToolCall::new(normalized, input.clone(), &cwd);
+                output = hooks.after_tool(&call, output, success).await;
+            }
+        }
+        (input, output, success)
+    }
+
     /// Execute a single tool.
     async fn execute_tool(
         &self,
@@ -423,8 +582,10 @@ impl PromptLoop {
             cwd: std::path::PathBuf::from(&session.directory),
             snapshot: self.snapshot.clone(),
             file_time: self.file_time.clone(),
-            sandbox: None, // Sandbox not used in prompt executor (yet)
+            sandbox: self.sandbox.clone(),
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         };
 
         let _timing = wonopcode_util::TimingGuard::tool(tool_name);
@@ -480,6 +641,43 @@ pub async fn prompt_once(
     Ok(text)
 }
 
+/// Check a session budget before the next step, publishing a
+/// [`BudgetWarning`] or [`BudgetExceeded`] event.
+///
+/// Returns the exhausted limit if the session must pause.
+pub async fn check_budget(
+    budget: &Mutex<BudgetTracker>,
+    bus: &Bus,
+    session_id: &str,
+) -> Option<BudgetStatus> {
+    let check = budget.lock().await.check();
+    match check {
+        BudgetCheck::Ok => None,
+        BudgetCheck::Warning(status) => {
+            warn!(limit = status.limit.as_str(), "{}", status.describe());
+            bus.publish(BudgetWarning {
+                session_id: session_id.to_string(),
+                status,
+            })
+            .await;
+            None
+        }
+        BudgetCheck::Exceeded(status) => {
+            warn!(
+                limit = status.limit.as_str(),
+                "Session budget reached, pausing: {}",
+                status.describe()
+            );
+            bus.publish(BudgetExceeded {
+                session_id: session_id.to_string(),
+                status: status.clone(),
+            })
+            .await;
+            Some(status)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -547,6 +745,7 @@ mod tests {
             tokens_output: 50,
             finish_reason: FinishReason::EndTurn,
             steps: 1,
+            budget_exceeded: None,
         };
         let debug_str = format!("{:?}", result);
         assert!(debug_str.contains("PromptResult"));
@@ -575,6 +774,7 @@ mod tests {
             tokens_output: 100,
             finish_reason: FinishReason::ToolUse,
             steps: 2,
+            budget_exceeded: None,
         };
         assert_eq!(result.tool_calls.len(), 2);
         assert_eq!(result.tool_calls[0].tool, "read");
@@ -0,0 +1,379 @@
+//! Templating for system prompts and agent prompts.
+//!
+//! Instructions (AGENTS.md and friends), agent prompts and template
+//! instructions may use:
+//! - `{{name}}` - a variable: `project`, `directory`, `date`, `os`,
+//!   `git_branch`, `provider`, `model`, `agent`, any variable from the
+//!   `prompt_variables` config section, or `env.NAME` for an environment
+//!   variable
+//! - `{{include path}}` - the contents of a file, relative to the project
+//!   directory (or `~/` for the home directory), itself rendered as a template
+//! - `{{#if cond}}...{{else}}...{{/if}}` - a conditional section, where `cond`
+//!   is `name` (set and not empty), `!name`, `name == value` or
+//!   `name != value`
+//!
+//! Anything that doesn't parse, like an unknown variable or a missing file,
+//! is left as written, so prompts containing literal braces are unaffected.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Maximum nesting of includes.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Variables available to prompt templates.
+#[derive(Debug, Clone, Default)]
+pub struct PromptVars {
+    values: BTreeMap<String, String>,
+    /// Directory includes are resolved against.
+    root: PathBuf,
+}
+
+impl PromptVars {
+    /// Variables describing a project directory.
+    pub fn detect(project_dir: &Path) -> Self {
+        let mut vars = Self {
+            values: BTreeMap::new(),
+            root: project_dir.to_path_buf(),
+        };
+        if let Some(name) = project_dir.file_name() {
+            vars.set("project", name.to_string_lossy());
+        }
+        vars.set("directory", project_dir.display().to_string());
+        vars.set("date", chrono::Local::now().format("%Y-%m-%d").to_string());
+        vars.set("os", std::env::consts::OS);
+        if let Some(branch) = git_branch(project_dir) {
+            vars.set("git_branch", branch);
+        }
+        vars
+    }
+
+    /// Set a variable.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    /// Set a variable, builder style.
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Add user-defined variables, which override built-in ones.
+    pub fn with_custom<'a>(
+        mut self,
+        variables: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) -> Self {
+        for (name, value) in variables {
+            self.set(name.clone(), value.clone());
+        }
+        self
+    }
+
+    /// Value of a variable; `env.NAME` reads an environment variable.
+    pub fn get(&self, name: &str) -> Option<String> {
+        match name.strip_prefix("env.") {
+            Some(var) => std::env::var(var).ok(),
+            None => self.values.get(name).cloned(),
+        }
+    }
+
+    /// Resolve an include path.
+    fn resolve(&self, path: &str) -> PathBuf {
+        match path.strip_prefix("~/").zip(dirs::home_dir()) {
+            Some((rest, home)) => home.join(rest),
+            None => self.root.join(path),
+        }
+    }
+}
+
+/// Render a prompt template.
+pub fn render_prompt(template: &str, vars: &PromptVars) -> String {
+    if !template.contains("{{") {
+        return template.to_string();
+    }
+    let mut out = String::with_capacity(template.len());
+    render_nodes(&parse(template), vars, 0, &mut out);
+    out
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Tag(&'a str),
+}
+
+enum Node {
+    Text(String),
+    Tag(String),
+    If {
+        condition: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+fn tokenize(template: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        tokens.push(Token::Tag(&rest[start + 2..start + 2 + len]));
+        rest = &rest[start + len + 4..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    tokens
+}
+
+fn parse(template: &str) -> Vec<Node> {
+    let tokens = tokenize(template);
+    let mut pos = 0;
+    let mut nodes = Vec::new();
+    loop {
+        let (block, end) = parse_block(&tokens, &mut pos);
+        nodes.extend(block);
+        match end {
+            // An `else` or `/if` without an `#if`
+            Some(tag) => nodes.push(Node::Text(format!("{{{{{tag}}}}}"))),
+            None => return nodes,
+        }
+    }
+}
+
+/// Parse nodes up to an `else` or `/if` tag, which is returned.
+fn parse_block<'a>(tokens: &[Token<'a>], pos: &mut usize) -> (Vec<Node>, Option<&'a str>) {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text.to_string()));
+                continue;
+            }
+            Token::Tag(tag) => *tag,
+        };
+        match tag.trim() {
+            end @ ("else" | "/if") => return (nodes, Some(end)),
+            trimmed => match trimmed.strip_prefix("#if ") {
+                Some(condition) => {
+                    let start = *pos;
+                    match parse_if(tokens, pos) {
+                        Some((then, otherwise)) => nodes.push(Node::If {
+                            condition: condition.trim().to_string(),
+                            then,
+                            otherwise,
+                        }),
+                        None => {
+                            // Unclosed: keep the tag as text
+                            *pos = start;
+                            nodes.push(Node::Text(format!("{{{{{tag}}}}}")));
+                        }
+                    }
+                }
+                None => nodes.push(Node::Tag(tag.to_string())),
+            },
+        }
+    }
+    (nodes, None)
+}
+
+/// Parse the branches of an `#if` whose tag was just consumed.
+fn parse_if(tokens: &[Token<'_>], pos: &mut usize) -> Option<(Vec<Node>, Vec<Node>)> {
+    let (then, end) = parse_block(tokens, pos);
+    match end? {
+        "else" => {
+            let (otherwise, end) = parse_block(tokens, pos);
+            (end? == "/if").then_some((then, otherwise))
+        }
+        _ => Some((then, Vec::new())),
+    }
+}
+
+fn render_nodes(nodes: &[Node], vars: &PromptVars, depth: usize, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Tag(tag) => match render_tag(tag.trim(), vars, depth) {
+                Some(value) => out.push_str(&value),
+                None => {
+                    out.push_str("{{");
+                    out.push_str(tag);
+                    out.push_str("}}");
+                }
+            },
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let branch = if evaluate(condition, vars) {
+                    then
+                } else {
+                    otherwise
+                };
+                render_nodes(branch, vars, depth, out);
+            }
+        }
+    }
+}
+
+fn render_tag(tag: &str, vars: &PromptVars, depth: usize) -> Option<String> {
+    let Some(path) = tag.strip_prefix("include ") else {
+        return vars.get(tag);
+    };
+    let path = unquote(path.trim());
+    if depth >= MAX_INCLUDE_DEPTH {
+        warn!(path = %path, "Prompt includes nested too deeply");
+        return None;
+    }
+    match std::fs::read_to_string(vars.resolve(path)) {
+        Ok(content) => {
+            let mut out = String::with_capacity(content.len());
+            render_nodes(&parse(content.trim_end()), vars, depth + 1, &mut out);
+            Some(out)
+        }
+        Err(e) => {
+            warn!(path = %path, error = %e, "Failed to include file in prompt");
+            None
+        }
+    }
+}
+
+fn evaluate(condition: &str, vars: &PromptVars) -> bool {
+    if let Some((name, value)) = condition.split_once("!=") {
+        return vars.get(name.trim()).unwrap_or_default() != unquote(value.trim());
+    }
+    if let Some((name, value)) = condition.split_once("==") {
+        return vars.get(name.trim()).unwrap_or_default() == unquote(value.trim());
+    }
+    match condition.strip_prefix('!') {
+        Some(name) => !is_set(name.trim(), vars),
+        None => is_set(condition, vars),
+    }
+}
+
+fn is_set(name: &str, vars: &PromptVars) -> bool {
+    vars.get(name).is_some_and(|value| !value.is_empty())
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+        .unwrap_or(value)
+}
+
+/// Current git branch of a directory, if it is in a repository.
+fn git_branch(dir: &Path) -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !branch.is_empty()).then_some(branch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> PromptVars {
+        PromptVars::default()
+            .with("project", "wonopcode")
+            .with("model", "claude-sonnet")
+            .with("empty", "")
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(
+            render_prompt("Working on {{project}} with {{ model }}.", &vars()),
+            "Working on wonopcode with claude-sonnet."
+        );
+        // Unknown variables and stray braces are left alone
+        assert_eq!(
+            render_prompt("Use {{unknown}} and {{ in JSX", &vars()),
+            "Use {{unknown}} and {{ in JSX"
+        );
+        std::env::set_var("WONOPCODE_PROMPT_TEST", "from env");
+        assert_eq!(
+            render_prompt("{{env.WONOPCODE_PROMPT_TEST}}", &vars()),
+            "from env"
+        );
+    }
+
+    #[test]
+    fn test_conditionals() {
+        let vars = vars();
+        let render = |template: &str| render_prompt(template, &vars);
+        assert_eq!(render("{{#if project}}yes{{/if}}"), "yes");
+        assert_eq!(render("{{#if empty}}yes{{else}}no{{/if}}"), "no");
+        assert_eq!(render("{{#if !missing}}yes{{/if}}"), "yes");
+        assert_eq!(
+            render("{{#if model == \"claude-sonnet\"}}claude{{else}}other{{/if}}"),
+            "claude"
+        );
+        assert_eq!(render("{{#if model != claude-sonnet}}other{{/if}}"), "");
+        // Nested sections
+        assert_eq!(
+            render("{{#if project}}a{{#if missing}}b{{else}}c{{/if}}d{{/if}}"),
+            "acd"
+        );
+        // Unbalanced tags are left as written
+        assert_eq!(render("{{#if project}}open"), "{{#if project}}open");
+        assert_eq!(render("close{{/if}}"), "close{{/if}}");
+    }
+
+    #[test]
+    fn test_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("prompts")).unwrap();
+        std::fs::write(
+            dir.path().join("prompts/style.md"),
+            "Style for {{project}}.\n{{include prompts/extra.md}}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("prompts/extra.md"), "Extra.").unwrap();
+        // A file that includes itself stops at the depth limit
+        std::fs::write(dir.path().join("loop.md"), "x{{include loop.md}}").unwrap();
+
+        let vars = PromptVars::detect(dir.path()).with("project", "demo");
+        assert_eq!(
+            render_prompt("{{include \"prompts/style.md\"}}", &vars),
+            "Style for demo.\nExtra."
+        );
+        assert_eq!(
+            render_prompt("{{include missing.md}}", &vars),
+            "{{include missing.md}}"
+        );
+        let looped = render_prompt("{{include loop.md}}", &vars);
+        assert!(looped.starts_with(&"x".repeat(MAX_INCLUDE_DEPTH)));
+    }
+
+    #[test]
+    fn test_detect() {
+        let dir = tempfile::tempdir().unwrap();
+        let vars = PromptVars::detect(dir.path());
+        assert_eq!(
+            vars.get("project").as_deref(),
+            dir.path().file_name().and_then(|n| n.to_str())
+        );
+        assert_eq!(vars.get("os").as_deref(), Some(std::env::consts::OS));
+        assert!(vars.get("date").is_some());
+
+        let mut custom = std::collections::HashMap::new();
+        custom.insert("team".to_string(), "platform".to_string());
+        custom.insert("project".to_string(), "Platform API".to_string());
+        let vars = vars.with_custom(&custom);
+        assert_eq!(vars.get("team").as_deref(), Some("platform"));
+        assert_eq!(vars.get("project").as_deref(), Some("Platform API"));
+    }
+}
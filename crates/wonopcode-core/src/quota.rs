@@ -0,0 +1,181 @@
+//! Storage usage and soft quotas.
+//!
+//! Quotas are configured under `storage` in the config file and never block
+//! writes; exceeding one only produces a [`QuotaWarning`]:
+//!
+//! ```json
+//! {
+//!   "storage": {
+//!     "soft_quota": "2G",
+//!     "namespace_quotas": { "message": "1G" }
+//!   }
+//! }
+//! ```
+
+use crate::config::StorageConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use wonopcode_storage::StorageStats;
+pub use wonopcode_util::size::{format_bytes, parse_size};
+
+/// A soft quota that has been exceeded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaWarning {
+    /// Namespace the quota applies to, or `None` for the overall quota.
+    pub namespace: Option<String>,
+    /// Bytes currently used.
+    pub used: u64,
+    /// Configured limit in bytes.
+    pub limit: u64,
+}
+
+impl fmt::Display for QuotaWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.namespace {
+            Some(ns) => write!(
+                f,
+                "Storage for '{ns}' uses {} (soft quota {})",
+                format_bytes(self.used),
+                format_bytes(self.limit)
+            ),
+            None => write!(
+                f,
+                "Storage uses {} (soft quota {})",
+                format_bytes(self.used),
+                format_bytes(self.limit)
+            ),
+        }
+    }
+}
+
+/// Storage statistics together with any exceeded quotas.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageUsage {
+    /// Per-namespace statistics.
+    pub stats: StorageStats,
+    /// Overall soft quota in bytes, if configured.
+    pub quota: Option<u64>,
+    /// Quotas that are currently exceeded.
+    pub warnings: Vec<QuotaWarning>,
+}
+
+impl StorageUsage {
+    /// Build usage information from stats and the storage config.
+    pub fn new(stats: StorageStats, config: Option<&StorageConfig>) -> Self {
+        let quota = config
+            .and_then(|c| c.soft_quota.as_deref())
+            .and_then(parse_size);
+        let warnings = config.map(|c| check_quotas(&stats, c)).unwrap_or_default();
+        Self {
+            stats,
+            quota,
+            warnings,
+        }
+    }
+}
+
+/// Compare stats against the configured soft quotas.
+pub fn check_quotas(stats: &StorageStats, config: &StorageConfig) -> Vec<QuotaWarning> {
+    let mut warnings = Vec::new();
+
+    if let Some(limit) = config.soft_quota.as_deref().and_then(parse_size) {
+        let used = stats.total_bytes();
+        if used > limit {
+            warnings.push(QuotaWarning {
+                namespace: None,
+                used,
+                limit,
+            });
+        }
+    }
+
+    if let Some(quotas) = &config.namespace_quotas {
+        let mut quotas: Vec<_> = quotas.iter().collect();
+        quotas.sort_by(|a, b| a.0.cmp(b.0));
+        for (namespace, limit) in quotas {
+            let Some(limit) = parse_size(limit) else {
+                tracing::warn!(namespace = %namespace, value = %limit, "Invalid storage quota");
+                continue;
+            };
+            let used = stats.namespaces.get(namespace).map_or(0, |n| n.bytes);
+            if used > limit {
+                warnings.push(QuotaWarning {
+                    namespace: Some(namespace.clone()),
+                    used,
+                    limit,
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn stats() -> StorageStats {
+        let mut stats = StorageStats::default();
+        stats.record("message", 3 * 1024);
+        stats.record("part", 1024);
+        stats
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2G"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("512M"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_size("512MB"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_size("1KiB"), Some(1024));
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("10B"), Some(10));
+        assert_eq!(parse_size("lots"), None);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(2 * 1024 * 1024 * 1024), "2.0 GiB");
+    }
+
+    #[test]
+    fn test_check_quotas_overall() {
+        let config = StorageConfig {
+            soft_quota: Some("2K".to_string()),
+            namespace_quotas: None,
+        };
+        let warnings = check_quotas(&stats(), &config);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].namespace, None);
+        assert_eq!(warnings[0].used, 4 * 1024);
+        assert_eq!(
+            warnings[0].to_string(),
+            "Storage uses 4.0 KiB (soft quota 2.0 KiB)"
+        );
+    }
+
+    #[test]
+    fn test_check_quotas_namespace() {
+        let config = StorageConfig {
+            soft_quota: Some("1M".to_string()),
+            namespace_quotas: Some(HashMap::from([
+                ("message".to_string(), "2K".to_string()),
+                ("part".to_string(), "2K".to_string()),
+                ("session".to_string(), "bogus".to_string()),
+            ])),
+        };
+        let warnings = check_quotas(&stats(), &config);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].namespace.as_deref(), Some("message"));
+    }
+
+    #[test]
+    fn test_storage_usage_without_config() {
+        let usage = StorageUsage::new(stats(), None);
+        assert_eq!(usage.quota, None);
+        assert!(usage.warnings.is_empty());
+    }
+}
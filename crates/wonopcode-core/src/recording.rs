@@ -0,0 +1,299 @@
+//! Recording of bus events.
+//!
+//! A [`BusRecorder`] appends every event published on one or more buses to a
+//! JSONL file, one [`SequencedEvent`] per line, so a session can be replayed
+//! later to see why the agent did what it did. Events are renumbered in the
+//! order they were recorded, as each bus has its own sequence numbers.
+//!
+//! [`EventCapture`] collects events in memory instead, for tests that assert
+//! on the events a component emits.
+
+use crate::bus::{Bus, SequencedEvent};
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Records bus events to a JSONL file.
+pub struct BusRecorder {
+    path: PathBuf,
+    tx: Option<mpsc::UnboundedSender<SequencedEvent>>,
+    stop: watch::Sender<bool>,
+    forwarders: Vec<JoinHandle<()>>,
+    writer: Option<JoinHandle<()>>,
+}
+
+impl BusRecorder {
+    /// Create (or truncate) `path` and start recording to it.
+    ///
+    /// Nothing is recorded until a bus is [attached](Self::attach).
+    pub async fn create(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::File::create(&path).await?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<SequencedEvent>();
+
+        let writer_path = path.clone();
+        let writer = tokio::spawn(async move {
+            let mut seq = 0;
+            while let Some(mut event) = rx.recv().await {
+                seq += 1;
+                event.seq = seq;
+                let Ok(mut line) = serde_json::to_vec(&event) else {
+                    continue;
+                };
+                line.push(b'\n');
+                // Flush every event, so the recording survives a crash
+                let written = match file.write_all(&line).await {
+                    Ok(()) => file.flush().await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = written {
+                    warn!(path = %writer_path.display(), "Failed to record event: {}", e);
+                    return;
+                }
+            }
+        });
+
+        Ok(Self {
+            path,
+            tx: Some(tx),
+            stop: watch::channel(false).0,
+            forwarders: Vec::new(),
+            writer: Some(writer),
+        })
+    }
+
+    /// The file being recorded to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record the events published on `bus` from now on.
+    pub fn attach(&mut self, bus: &Bus) {
+        let Some(tx) = self.tx.clone() else {
+            return;
+        };
+        let mut rx = bus.subscribe_all();
+        let mut stop = self.stop.subscribe();
+        self.forwarders.push(tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    received = rx.recv() => match received {
+                        Ok(event) => {
+                            if tx.send(event).is_err() {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!(skipped, "Event recording fell behind, events were lost");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = stop.changed() => {
+                        // Events already published are recorded before stopping
+                        while let Ok(event) = rx.try_recv() {
+                            let _ = tx.send(event);
+                        }
+                        break;
+                    }
+                }
+            }
+        }));
+    }
+
+    /// Stop recording, writing out the events recorded so far.
+    pub async fn finish(mut self) {
+        let _ = self.stop.send(true);
+        for forwarder in self.forwarders.drain(..) {
+            let _ = forwarder.await;
+        }
+        self.tx = None;
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.await;
+        }
+    }
+}
+
+impl Drop for BusRecorder {
+    fn drop(&mut self) {
+        for forwarder in &self.forwarders {
+            forwarder.abort();
+        }
+    }
+}
+
+/// Read a recording made by [`BusRecorder`].
+///
+/// Lines that are not events, such as a final line cut short by a crash, are
+/// skipped.
+pub async fn read_recording(path: impl AsRef<Path>) -> io::Result<Vec<SequencedEvent>> {
+    let content = tokio::fs::read_to_string(path.as_ref()).await?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(event) => Some(event),
+            Err(e) => {
+                warn!(path = %path.as_ref().display(), "Skipping unreadable event: {}", e);
+                None
+            }
+        })
+        .collect())
+}
+
+/// Events captured from a bus, for tests.
+///
+/// Events are published synchronously, so everything published before a call
+/// is visible to it.
+///
+/// ```ignore
+/// let mut events = bus.capture();
+/// session_repo.create(...).await?;
+/// events.assert_sequence(&["session.created", "session.updated"]);
+/// ```
+pub struct EventCapture {
+    rx: broadcast::Receiver<SequencedEvent>,
+    events: Vec<SequencedEvent>,
+}
+
+impl Bus {
+    /// Capture the events published from now on.
+    pub fn capture(&self) -> EventCapture {
+        EventCapture {
+            rx: self.subscribe_all(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl EventCapture {
+    /// All captured events, oldest first.
+    pub fn events(&mut self) -> &[SequencedEvent] {
+        self.drain();
+        &self.events
+    }
+
+    /// Types of all captured events, oldest first.
+    pub fn event_types(&mut self) -> Vec<String> {
+        self.events().iter().map(|e| e.event_type.clone()).collect()
+    }
+
+    /// Captured events of one type, oldest first.
+    pub fn of_type(&mut self, event_type: &str) -> Vec<SequencedEvent> {
+        self.events()
+            .iter()
+            .filter(|e| e.event_type == event_type)
+            .cloned()
+            .collect()
+    }
+
+    /// Whether the given event types were captured in this order, possibly
+    /// with other events in between.
+    pub fn contains_sequence(&mut self, expected: &[&str]) -> bool {
+        let types = self.event_types();
+        let mut types = types.iter();
+        expected.iter().all(|expected| types.any(|t| t == expected))
+    }
+
+    /// Panic unless the given event types were captured in this order.
+    #[track_caller]
+    pub fn assert_sequence(&mut self, expected: &[&str]) {
+        if !self.contains_sequence(expected) {
+            panic!(
+                "expected events {:?} in order, got {:?}",
+                expected,
+                self.event_types()
+            );
+        }
+    }
+
+    /// Forget the events captured so far.
+    pub fn clear(&mut self) {
+        self.drain();
+        self.events.clear();
+    }
+
+    fn drain(&mut self) {
+        loop {
+            match self.rx.try_recv() {
+                Ok(event) => self.events.push(event),
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{SessionCreated, SessionUpdated};
+
+    #[tokio::test]
+    async fn test_capture_asserts_sequence() {
+        let bus = Bus::new();
+        let mut events = bus.capture();
+
+        bus.publish(SessionCreated {
+            session_id: "ses_1".to_string(),
+            project_id: "proj_1".to_string(),
+            title: "Test".to_string(),
+        })
+        .await;
+        bus.publish(SessionUpdated {
+            session_id: "ses_1".to_string(),
+        })
+        .await;
+
+        events.assert_sequence(&["session.created", "session.updated"]);
+        assert!(!events.contains_sequence(&["session.updated", "session.created"]));
+        assert_eq!(
+            events.of_type("session.updated")[0].payload["session_id"],
+            "ses_1"
+        );
+
+        events.clear();
+        assert!(events.events().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_record_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events/session.jsonl");
+        let (first, second) = (Bus::new(), Bus::new());
+
+        let mut recorder = BusRecorder::create(&path).await.unwrap();
+        recorder.attach(&first);
+        recorder.attach(&second);
+        // Events are renumbered across buses, in the order recorded
+        first
+            .publish(SessionUpdated {
+                session_id: "ses_1".to_string(),
+            })
+            .await;
+        second
+            .publish(SessionUpdated {
+                session_id: "ses_2".to_string(),
+            })
+            .await;
+        recorder.finish().await;
+
+        // A line cut short by a crash is skipped
+        let mut content = tokio::fs::read_to_string(&path).await.unwrap();
+        content.push_str("{\"seq\":3,\"times");
+        tokio::fs::write(&path, content).await.unwrap();
+
+        let events = read_recording(&path).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].seq, 1);
+        assert_eq!(events[0].payload["session_id"], "ses_1");
+        assert_eq!(events[1].seq, 2);
+        assert_eq!(events[1].payload["session_id"], "ses_2");
+    }
+}
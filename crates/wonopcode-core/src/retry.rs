@@ -1,10 +1,18 @@
 //! Session retry functionality.
 //!
-//! Provides retry logic for failed API requests with exponential backoff
-//! and rate limit handling.
-
-use std::time::Duration;
-use tracing::debug;
+//! Provides retry logic for failed API requests with jittered exponential
+//! backoff, rate limit handling and a circuit breaker per provider. Retries
+//! and recoveries are published on the [`Bus`] so the UI can show that a
+//! provider is degraded instead of silently stalling.
+
+use crate::bus::{Bus, ProviderDegraded, ProviderRecovered};
+use rand::Rng;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use wonopcode_provider::{ProviderError, ProviderResult};
 
 /// Initial retry delay in milliseconds.
 pub const RETRY_INITIAL_DELAY_MS: u64 = 2000;
@@ -18,39 +26,53 @@ pub const RETRY_MAX_DELAY_NO_HEADERS_MS: u64 = 30_000;
 /// Maximum number of retry attempts.
 pub const RETRY_MAX_ATTEMPTS: u32 = 5;
 
+/// Consecutive failures that open a provider's circuit.
+pub const CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+
+/// How long a circuit stays open the first time it opens.
+pub const CIRCUIT_OPEN_MS: u64 = 30_000;
+
+/// Longest a circuit stays open. The open time doubles every time a
+/// half-open probe fails, up to this.
+pub const CIRCUIT_MAX_OPEN_MS: u64 = 300_000;
+
+/// How long other requests wait while a half-open probe is in flight.
+const CIRCUIT_PROBE_WAIT_MS: u64 = 1000;
+
+/// A probe that has not finished after this long (its caller went away)
+/// no longer blocks other requests.
+const CIRCUIT_PROBE_TIMEOUT_MS: u64 = 120_000;
+
 /// Calculate the delay before retrying.
 ///
 /// If rate limit headers are provided, uses those. Otherwise,
 /// uses exponential backoff with a maximum delay.
 pub fn calculate_delay(attempt: u32, rate_limit_info: Option<&RateLimitInfo>) -> Duration {
-    if let Some(info) = rate_limit_info {
-        // Use retry-after-ms if available
-        if let Some(ms) = info.retry_after_ms {
-            return Duration::from_millis(ms);
-        }
-
-        // Use retry-after (seconds) if available
-        if let Some(secs) = info.retry_after_secs {
-            return Duration::from_secs(secs);
-        }
-
-        // Use reset time if available
-        if let Some(reset_at) = info.reset_at {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_secs())
-                .unwrap_or(0);
-            if reset_at > now {
-                return Duration::from_secs(reset_at - now);
-            }
-        }
+    if let Some(delay) = rate_limit_info.and_then(RateLimitInfo::delay) {
+        return delay;
     }
 
     // Exponential backoff with cap
-    let delay = RETRY_INITIAL_DELAY_MS * RETRY_BACKOFF_FACTOR.pow(attempt.saturating_sub(1));
+    let delay = RETRY_INITIAL_DELAY_MS
+        .saturating_mul(RETRY_BACKOFF_FACTOR.saturating_pow(attempt.saturating_sub(1)));
     Duration::from_millis(delay.min(RETRY_MAX_DELAY_NO_HEADERS_MS))
 }
 
+/// Calculate the delay before retrying, with jitter.
+///
+/// Computed backoff is spread over 50-100% of the delay so clients that
+/// failed together don't retry together. A delay the provider asked for is
+/// only ever lengthened, by up to 10%.
+pub fn jittered_delay(attempt: u32, rate_limit_info: Option<&RateLimitInfo>) -> Duration {
+    let delay = calculate_delay(attempt, rate_limit_info);
+    let mut rng = rand::thread_rng();
+    if rate_limit_info.and_then(RateLimitInfo::delay).is_some() {
+        delay.mul_f64(1.0 + rng.gen_range(0.0..0.1))
+    } else {
+        delay.mul_f64(rng.gen_range(0.5..=1.0))
+    }
+}
+
 /// Rate limit information from response headers.
 #[derive(Debug, Clone, Default)]
 pub struct RateLimitInfo {
@@ -63,6 +85,27 @@ pub struct RateLimitInfo {
 }
 
 impl RateLimitInfo {
+    /// Delay the provider asked for, if any.
+    pub fn delay(&self) -> Option<Duration> {
+        // Use retry-after-ms if available
+        if let Some(ms) = self.retry_after_ms {
+            return Some(Duration::from_millis(ms));
+        }
+
+        // Use retry-after (seconds) if available
+        if let Some(secs) = self.retry_after_secs {
+            return Some(Duration::from_secs(secs));
+        }
+
+        // Use reset time if available
+        let reset_at = self.reset_at?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        (reset_at > now).then(|| Duration::from_secs(reset_at - now))
+    }
+
     /// Parse rate limit info from response headers.
     pub fn from_headers(headers: &[(String, String)]) -> Option<Self> {
         let mut info = RateLimitInfo::default();
@@ -161,6 +204,53 @@ pub fn classify_error(status: Option<u16>, message: &str) -> RetryableError {
     RetryableError::NotRetryable
 }
 
+impl RetryableError {
+    /// Short description of the failure.
+    pub fn message(&self) -> &str {
+        match self {
+            RetryableError::RateLimited { message }
+            | RetryableError::Overloaded { message }
+            | RetryableError::ServerError { message } => message,
+            RetryableError::NotRetryable => "Not retryable",
+        }
+    }
+}
+
+/// Classify a provider error, with any rate limit information it carries.
+pub fn classify_provider_error(error: &ProviderError) -> (RetryableError, Option<RateLimitInfo>) {
+    let classified = match error {
+        ProviderError::RateLimited { retry_after } => {
+            let info = retry_after.map(|delay| RateLimitInfo {
+                retry_after_ms: Some(delay.as_millis() as u64),
+                ..Default::default()
+            });
+            return (
+                RetryableError::RateLimited {
+                    message: "Rate limited".to_string(),
+                },
+                info,
+            );
+        }
+        ProviderError::ApiError { status, message } => classify_error(Some(*status), message),
+        ProviderError::RequestFailed(e) if e.is_timeout() || e.is_connect() => {
+            RetryableError::ServerError {
+                message: "Connection failed".to_string(),
+            }
+        }
+        ProviderError::RequestFailed(e) => {
+            classify_error(e.status().map(|s| s.as_u16()), &e.to_string())
+        }
+        ProviderError::StreamInterrupted => RetryableError::ServerError {
+            message: "Stream interrupted".to_string(),
+        },
+        ProviderError::InvalidResponse(message) | ProviderError::Internal { message } => {
+            classify_error(None, message)
+        }
+        _ => RetryableError::NotRetryable,
+    };
+    (classified, None)
+}
+
 /// Check if we should retry based on the error.
 pub fn should_retry(error: &RetryableError) -> bool {
     !matches!(error, RetryableError::NotRetryable)
@@ -229,6 +319,265 @@ impl RetryHelper {
     }
 }
 
+/// State of a [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests go through.
+    Closed,
+    /// Too many consecutive failures; requests wait until the circuit
+    /// half-opens.
+    Open,
+    /// The open period is over; one probe request decides whether the
+    /// circuit closes or opens again.
+    HalfOpen,
+}
+
+/// Circuit breaker for one provider.
+///
+/// Opens after [`CIRCUIT_FAILURE_THRESHOLD`] consecutive failures, so
+/// requests back off for a while instead of hammering a provider that is
+/// down. After the open period a single probe is let through: success
+/// closes the circuit, failure opens it again for twice as long.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    state: CircuitState,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    open_duration: Duration,
+    probe_started: Option<Instant>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(CIRCUIT_FAILURE_THRESHOLD)
+    }
+}
+
+impl CircuitBreaker {
+    /// Create a circuit breaker that opens after `failure_threshold`
+    /// consecutive failures.
+    pub fn new(failure_threshold: u32) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            open_until: None,
+            open_duration: Duration::from_millis(CIRCUIT_OPEN_MS),
+            probe_started: None,
+        }
+    }
+
+    /// Current state.
+    pub fn state(&self) -> CircuitState {
+        self.state
+    }
+
+    /// Consecutive failures since the last success.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Ask to send a request at `now`.
+    ///
+    /// Returns how long to wait if the circuit is open or a half-open probe
+    /// is already in flight.
+    pub fn acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        match self.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::Open => match self.open_until {
+                Some(until) if until > now => Err(until - now),
+                _ => {
+                    self.state = CircuitState::HalfOpen;
+                    self.probe_started = Some(now);
+                    Ok(())
+                }
+            },
+            CircuitState::HalfOpen => match self.probe_started {
+                Some(started)
+                    if now.duration_since(started)
+                        < Duration::from_millis(CIRCUIT_PROBE_TIMEOUT_MS) =>
+                {
+                    Err(Duration::from_millis(CIRCUIT_PROBE_WAIT_MS))
+                }
+                _ => {
+                    self.probe_started = Some(now);
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// Record a successful request. Returns whether the provider had been
+    /// failing.
+    pub fn record_success(&mut self) -> bool {
+        let was_failing = self.consecutive_failures > 0 || self.state != CircuitState::Closed;
+        *self = Self::new(self.failure_threshold);
+        was_failing
+    }
+
+    /// Record a request that failed for reasons unrelated to the provider's
+    /// health, such as a bad request. Lets another half-open probe through.
+    pub fn record_ignored(&mut self) {
+        self.probe_started = None;
+    }
+
+    /// Record a failed request at `now`, with the delay the provider asked
+    /// for, if any.
+    ///
+    /// Returns how long the circuit stays open if this failure opened it.
+    pub fn record_failure(
+        &mut self,
+        now: Instant,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        self.consecutive_failures += 1;
+        self.probe_started = None;
+        let open_for = match self.state {
+            CircuitState::HalfOpen => {
+                (self.open_duration * 2).min(Duration::from_millis(CIRCUIT_MAX_OPEN_MS))
+            }
+            CircuitState::Closed if self.consecutive_failures >= self.failure_threshold => {
+                self.open_duration
+            }
+            _ => return None,
+        };
+        let open_for = open_for.max(retry_after.unwrap_or_default());
+        self.state = CircuitState::Open;
+        self.open_duration = open_for;
+        self.open_until = Some(now + open_for);
+        Some(open_for)
+    }
+}
+
+/// Circuit breakers for every provider, shared by all callers of a
+/// provider so they back off together.
+#[derive(Clone, Default)]
+pub struct ProviderBreakers {
+    breakers: Arc<Mutex<HashMap<String, CircuitBreaker>>>,
+    bus: Option<Bus>,
+}
+
+impl ProviderBreakers {
+    /// Create circuit breakers that publish [`ProviderDegraded`] and
+    /// [`ProviderRecovered`] events on `bus`.
+    pub fn new(bus: Bus) -> Self {
+        Self {
+            breakers: Arc::default(),
+            bus: Some(bus),
+        }
+    }
+
+    /// State of the circuit for `provider`.
+    pub fn state(&self, provider: &str) -> CircuitState {
+        self.with_breaker(provider, |breaker| breaker.state())
+    }
+
+    fn with_breaker<T>(&self, provider: &str, f: impl FnOnce(&mut CircuitBreaker) -> T) -> T {
+        let mut breakers = self.breakers.lock().unwrap_or_else(|e| e.into_inner());
+        f(breakers.entry(provider.to_string()).or_default())
+    }
+
+    async fn degraded(&self, event: ProviderDegraded) {
+        if let Some(bus) = &self.bus {
+            bus.publish(event).await;
+        }
+    }
+
+    /// Send `request` to `provider`, retrying failures that are worth
+    /// retrying with jittered backoff.
+    ///
+    /// Waits while the provider's circuit is open. Returns
+    /// [`ProviderError::Cancelled`] if `cancel` fires while waiting.
+    pub async fn call<T, F, Fut>(
+        &self,
+        provider: &str,
+        cancel: &tokio_util::sync::CancellationToken,
+        mut request: F,
+    ) -> ProviderResult<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = ProviderResult<T>>,
+    {
+        let mut retry = RetryHelper::default_attempts();
+        loop {
+            if let Err(wait) = self.with_breaker(provider, |b| b.acquire(Instant::now())) {
+                self.degraded(ProviderDegraded {
+                    provider: provider.to_string(),
+                    reason: "Circuit open".to_string(),
+                    retry_in_ms: wait.as_millis() as u64,
+                    attempt: retry.current_attempt(),
+                    circuit_open: true,
+                })
+                .await;
+                if !sleep_with_cancel(wait, cancel).await {
+                    return Err(ProviderError::Cancelled);
+                }
+                continue;
+            }
+
+            let error = match request().await {
+                Ok(value) => {
+                    if self.with_breaker(provider, |b| b.record_success()) {
+                        info!(provider, "Provider recovered");
+                        if let Some(bus) = &self.bus {
+                            bus.publish(ProviderRecovered {
+                                provider: provider.to_string(),
+                            })
+                            .await;
+                        }
+                    }
+                    return Ok(value);
+                }
+                Err(error) => error,
+            };
+
+            let (classified, rate_limit_info) = classify_provider_error(&error);
+            if !should_retry(&classified) {
+                // The request was bad, not the provider
+                self.with_breaker(provider, |b| b.record_ignored());
+                return Err(error);
+            }
+
+            let retry_after = rate_limit_info.as_ref().and_then(RateLimitInfo::delay);
+            let opened_for =
+                self.with_breaker(provider, |b| b.record_failure(Instant::now(), retry_after));
+            if let Some(open_for) = opened_for {
+                warn!(
+                    provider,
+                    open_secs = open_for.as_secs(),
+                    "Provider circuit opened: {}",
+                    error
+                );
+            }
+
+            if retry.next_attempt(rate_limit_info.as_ref()).is_none() {
+                return Err(error);
+            }
+            let delay = jittered_delay(retry.current_attempt(), rate_limit_info.as_ref())
+                .max(opened_for.unwrap_or_default());
+            warn!(
+                provider,
+                attempt = retry.current_attempt(),
+                delay_ms = delay.as_millis() as u64,
+                "Provider request failed, retrying: {}",
+                error
+            );
+            self.degraded(ProviderDegraded {
+                provider: provider.to_string(),
+                reason: classified.message().to_string(),
+                retry_in_ms: delay.as_millis() as u64,
+                attempt: retry.current_attempt(),
+                circuit_open: opened_for.is_some(),
+            })
+            .await;
+            if !sleep_with_cancel(delay, cancel).await {
+                return Err(ProviderError::Cancelled);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -478,4 +827,133 @@ mod tests {
         let result = sleep_with_cancel(Duration::from_secs(10), &token).await;
         assert!(!result); // Cancelled
     }
+
+    #[test]
+    fn test_jittered_delay() {
+        for _ in 0..20 {
+            let delay = jittered_delay(2, None);
+            assert!(delay >= Duration::from_millis(2000));
+            assert!(delay <= Duration::from_millis(4000));
+        }
+
+        // Never shorter than what the provider asked for
+        let info = RateLimitInfo {
+            retry_after_secs: Some(90),
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let delay = jittered_delay(1, Some(&info));
+            assert!(delay >= Duration::from_secs(90));
+            assert!(delay <= Duration::from_secs(99));
+        }
+    }
+
+    #[test]
+    fn test_classify_provider_error() {
+        let (error, info) = classify_provider_error(&ProviderError::RateLimited {
+            retry_after: Some(Duration::from_secs(3)),
+        });
+        assert!(matches!(error, RetryableError::RateLimited { .. }));
+        assert_eq!(info.unwrap().delay(), Some(Duration::from_secs(3)));
+
+        let (error, _) = classify_provider_error(&ProviderError::api_error(529, "overloaded"));
+        assert!(matches!(error, RetryableError::ServerError { .. }));
+
+        let (error, _) = classify_provider_error(&ProviderError::api_error(400, "bad request"));
+        assert!(!should_retry(&error));
+
+        let (error, _) = classify_provider_error(&ProviderError::Cancelled);
+        assert!(!should_retry(&error));
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_and_probes() {
+        let mut breaker = CircuitBreaker::new(3);
+        let now = Instant::now();
+
+        assert!(breaker.record_failure(now, None).is_none());
+        assert!(breaker.record_failure(now, None).is_none());
+        assert_eq!(breaker.acquire(now), Ok(()));
+        let open_for = breaker.record_failure(now, None).unwrap();
+        assert_eq!(open_for, Duration::from_millis(CIRCUIT_OPEN_MS));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(breaker.acquire(now), Err(open_for));
+
+        // One probe once the open period is over
+        let later = now + open_for;
+        assert_eq!(breaker.acquire(later), Ok(()));
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.acquire(later).is_err());
+
+        // A failed probe opens the circuit for longer
+        let reopened_for = breaker.record_failure(later, None).unwrap();
+        assert_eq!(reopened_for, open_for * 2);
+
+        let later = later + reopened_for;
+        assert_eq!(breaker.acquire(later), Ok(()));
+        assert!(breaker.record_success());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures(), 0);
+        assert!(!breaker.record_success());
+    }
+
+    #[test]
+    fn test_circuit_breaker_respects_retry_after() {
+        let mut breaker = CircuitBreaker::new(1);
+        let open_for = breaker
+            .record_failure(Instant::now(), Some(Duration::from_secs(90)))
+            .unwrap();
+        assert_eq!(open_for, Duration::from_secs(90));
+    }
+
+    #[tokio::test]
+    async fn test_provider_breakers_retry() {
+        let bus = Bus::new();
+        let mut degraded_rx = bus.subscribe::<ProviderDegraded>().await;
+        let mut recovered_rx = bus.subscribe::<ProviderRecovered>().await;
+        let breakers = ProviderBreakers::new(bus);
+        let cancel = tokio_util::sync::CancellationToken::new();
+
+        let mut calls = 0;
+        let result = breakers
+            .call("test", &cancel, || {
+                calls += 1;
+                let attempt = calls;
+                async move {
+                    if attempt < 3 {
+                        Err(ProviderError::RateLimited {
+                            retry_after: Some(Duration::from_millis(5)),
+                        })
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let event = degraded_rx.recv().await.unwrap();
+        assert_eq!(event.provider, "test");
+        assert_eq!(event.reason, "Rate limited");
+        assert_eq!(event.attempt, 1);
+        assert!(!event.circuit_open);
+        assert_eq!(recovered_rx.recv().await.unwrap().provider, "test");
+        assert_eq!(breakers.state("test"), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_provider_breakers_do_not_retry_bad_requests() {
+        let breakers = ProviderBreakers::default();
+        let cancel = tokio_util::sync::CancellationToken::new();
+
+        let mut calls = 0;
+        let result: ProviderResult<()> = breakers
+            .call("test", &cancel, || {
+                calls += 1;
+                async { Err(ProviderError::api_error(400, "bad request")) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
 }
@@ -0,0 +1,658 @@
+//! Scheduled and recurring tasks.
+//!
+//! Tasks are declared under the `schedule` config key with a cron expression
+//! and a prompt or custom command. In headless mode the [`Scheduler`] decides
+//! when each task is due; every run is stored as a session tagged
+//! [`SCHEDULED_TAG`] and can be reported to a webhook.
+//!
+//! Cron expressions use the usual five fields, evaluated in local time:
+//!
+//! ```text
+//! ┌ minute (0-59)
+//! │ ┌ hour (0-23)
+//! │ │ ┌ day of month (1-31)
+//! │ │ │ ┌ month (1-12 or jan-dec)
+//! │ │ │ │ ┌ day of week (0-7 or sun-sat, 0 and 7 are Sunday)
+//! 0 3 * * 1-5
+//! ```
+//!
+//! Fields accept `*`, values, ranges, lists and steps (`*/15`, `1-5/2`).
+//! `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are also accepted.
+
+use crate::config::{Config, ScheduledTaskConfig};
+use crate::error::CoreResult;
+use crate::message::{AssistantMessage, Message, MessagePart, ModelRef, TextPart, UserMessage};
+use crate::session::{Session, SessionRepository};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::warn;
+
+/// Tag added to sessions created by scheduled runs.
+pub const SCHEDULED_TAG: &str = "scheduled";
+
+/// How far ahead to look for the next run before giving up.
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 5;
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Error in a scheduled task definition.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("Invalid cron expression '{expr}': {reason}")]
+    InvalidCron { expr: String, reason: String },
+
+    #[error("Task '{0}' needs exactly one of prompt or command")]
+    MissingPrompt(String),
+}
+
+/// A parsed cron expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether the day of month field was `*`.
+    any_day: bool,
+    /// Whether the day of week field was `*`.
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// Parse a five-field cron expression or an `@` alias.
+    pub fn parse(expr: &str) -> Result<Self, ScheduleError> {
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let invalid = |reason: String| ScheduleError::InvalidCron {
+            expr: expr.to_string(),
+            reason,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(invalid(format!(
+                "expected 5 fields, found {}",
+                fields.len()
+            )));
+        };
+
+        let mut weekdays = parse_field(weekday, 0, 7, WEEKDAY_NAMES, 0).map_err(invalid)?;
+        // 7 is another name for Sunday
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], 0).map_err(invalid)?,
+            hours: parse_field(hour, 0, 23, &[], 0).map_err(invalid)?,
+            days: parse_field(day, 1, 31, &[], 0).map_err(invalid)?,
+            months: parse_field(month, 1, 12, MONTH_NAMES, 1).map_err(invalid)?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    /// Whether the schedule fires at `time`'s minute.
+    pub fn matches(&self, time: &NaiveDateTime) -> bool {
+        self.matches_date(&time.date())
+            && bit(self.hours, time.hour())
+            && bit(self.minutes, time.minute())
+    }
+
+    /// Whether the schedule fires at some time on `date`.
+    ///
+    /// As in cron, when both day fields are restricted either may match.
+    fn matches_date(&self, date: &NaiveDate) -> bool {
+        if !bit(self.months, date.month()) {
+            return false;
+        }
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().num_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    /// The first time after `after` that the schedule fires.
+    ///
+    /// Local times skipped by a daylight saving change are skipped.
+    pub fn next_after<Tz: TimeZone>(&self, after: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let tz = after.timezone();
+        let start = after.naive_local().with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start + Duration::days(MAX_LOOKAHEAD_DAYS);
+
+        let mut time = start;
+        while time < limit {
+            if !self.matches_date(&time.date()) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !bit(self.hours, time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if bit(self.minutes, time.minute()) {
+                if let Some(next) = tz.from_local_datetime(&time).earliest() {
+                    if next > *after {
+                        return Some(next);
+                    }
+                }
+            }
+            time += Duration::minutes(1);
+        }
+        None
+    }
+}
+
+fn bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+/// Parse one cron field into a bit mask of the values it allows.
+///
+/// `names` are alternative spellings for values starting at `name_base`.
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    name_base: u32,
+) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let v = match s.parse::<u32>() {
+            Ok(v) => v,
+            Err(_) => names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(s))
+                .map(|i| i as u32 + name_base)
+                .ok_or_else(|| format!("invalid value '{s}'"))?,
+        };
+        if v < min || v > max {
+            return Err(format!("'{s}' is outside {min}-{max}"));
+        }
+        Ok(v)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("invalid step '{step}'"))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (value(start)?, value(end)?)
+        } else {
+            let start = value(range)?;
+            // "5/10" means every 10 starting at 5
+            (start, if step.is_some() { max } else { start })
+        };
+        if start > end {
+            return Err(format!("empty range '{range}'"));
+        }
+        for v in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+/// A valid, enabled scheduled task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledTask {
+    /// Task name, the key under `schedule`.
+    pub name: String,
+    /// When the task runs.
+    pub cron: CronSchedule,
+    /// Prompt to send; custom commands are sent as `/name args`.
+    pub prompt: String,
+    /// Agent override.
+    pub agent: Option<String>,
+    /// Model override (provider/model).
+    pub model: Option<String>,
+    /// Webhook notified after each run.
+    pub webhook: Option<String>,
+}
+
+impl ScheduledTask {
+    /// Build a task from its config entry.
+    pub fn from_config(name: &str, config: &ScheduledTaskConfig) -> Result<Self, ScheduleError> {
+        let prompt = match (&config.prompt, &config.command) {
+            (Some(prompt), None) if !prompt.trim().is_empty() => prompt.clone(),
+            (None, Some(command)) if !command.trim().is_empty() => {
+                format!("/{}", command.trim().trim_start_matches('/'))
+            }
+            _ => return Err(ScheduleError::MissingPrompt(name.to_string())),
+        };
+        Ok(Self {
+            name: name.to_string(),
+            cron: CronSchedule::parse(&config.cron)?,
+            prompt,
+            agent: config.agent.clone(),
+            model: config.model.clone(),
+            webhook: config.webhook.clone(),
+        })
+    }
+
+    /// The enabled tasks in the config, sorted by name.
+    ///
+    /// Invalid tasks are logged and skipped.
+    pub fn all(config: &Config) -> Vec<Self> {
+        let mut tasks: Vec<Self> = config
+            .schedule
+            .iter()
+            .flatten()
+            .filter(|(_, task)| task.enabled.unwrap_or(true))
+            .filter_map(|(name, task)| match Self::from_config(name, task) {
+                Ok(task) => Some(task),
+                Err(e) => {
+                    warn!(task = %name, error = %e, "Skipping invalid scheduled task");
+                    None
+                }
+            })
+            .collect();
+        tasks.sort_by(|a, b| a.name.cmp(&b.name));
+        tasks
+    }
+}
+
+/// Decides when scheduled tasks are due.
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    tasks: Vec<ScheduledTask>,
+    next: HashMap<String, DateTime<Local>>,
+}
+
+impl Scheduler {
+    /// Schedule `tasks`, each first running at its next time after `now`.
+    pub fn new(tasks: Vec<ScheduledTask>, now: DateTime<Local>) -> Self {
+        let next = tasks
+            .iter()
+            .filter_map(|task| Some((task.name.clone(), task.cron.next_after(&now)?)))
+            .collect();
+        Self { tasks, next }
+    }
+
+    /// Scheduled tasks.
+    pub fn tasks(&self) -> &[ScheduledTask] {
+        &self.tasks
+    }
+
+    /// When `task` runs next.
+    pub fn next_run(&self, task: &str) -> Option<DateTime<Local>> {
+        self.next.get(task).copied()
+    }
+
+    /// Replace the tasks, keeping the next run time of unchanged ones.
+    pub fn update(&mut self, tasks: Vec<ScheduledTask>, now: DateTime<Local>) {
+        let mut next = HashMap::new();
+        for task in &tasks {
+            let unchanged = self
+                .tasks
+                .iter()
+                .any(|old| old.cron == task.cron && old.name == task.name);
+            let time = match self.next.get(&task.name) {
+                Some(time) if unchanged => Some(*time),
+                _ => task.cron.next_after(&now),
+            };
+            if let Some(time) = time {
+                next.insert(task.name.clone(), time);
+            }
+        }
+        self.tasks = tasks;
+        self.next = next;
+    }
+
+    /// Tasks due at `now`, advancing each to its following run.
+    ///
+    /// A task that was due several times since the last check runs once.
+    pub fn due(&mut self, now: DateTime<Local>) -> Vec<ScheduledTask> {
+        let mut due = Vec::new();
+        for task in &self.tasks {
+            let Some(next) = self.next.get(&task.name) else {
+                continue;
+            };
+            if *next <= now {
+                due.push(task.clone());
+                match task.cron.next_after(&now) {
+                    Some(time) => self.next.insert(task.name.clone(), time),
+                    None => self.next.remove(&task.name),
+                };
+            }
+        }
+        due
+    }
+}
+
+/// Outcome of one scheduled run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledRun {
+    /// Task name.
+    pub task: String,
+    /// Prompt that was sent.
+    pub prompt: String,
+    /// Agent the task ran with.
+    pub agent: String,
+    /// Model the task ran with (provider/model).
+    pub model: String,
+    /// Start time (ms since epoch).
+    pub started_at: i64,
+    /// End time (ms since epoch).
+    pub finished_at: i64,
+    /// Final response text.
+    pub output: String,
+    /// Why the run failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Session the run was stored as.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+impl ScheduledRun {
+    /// Whether the run completed without an error.
+    pub fn success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Store the run as a session tagged [`SCHEDULED_TAG`], holding the
+    /// prompt and the response.
+    pub async fn save(
+        &mut self,
+        repo: &SessionRepository,
+        project_id: &str,
+        directory: &str,
+    ) -> CoreResult<Session> {
+        let mut session = Session::new(project_id, directory);
+        let started = DateTime::from_timestamp_millis(self.started_at)
+            .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        session.title = format!("Scheduled: {} ({started})", self.task);
+        session.tags = vec![SCHEDULED_TAG.to_string(), self.task.clone()];
+        let session = repo.create(session).await?;
+
+        let (provider_id, model_id) = self
+            .model
+            .split_once('/')
+            .unwrap_or(("", self.model.as_str()));
+        let user = Message::User(UserMessage::new(
+            &session.id,
+            &self.agent,
+            ModelRef {
+                provider_id: provider_id.to_string(),
+                model_id: model_id.to_string(),
+            },
+        ));
+        let user_part = MessagePart::Text(TextPart::new(&session.id, user.id(), &self.prompt));
+        repo.save_message_with_parts(&user, &[user_part]).await?;
+
+        let mut reply = AssistantMessage::new(
+            &session.id,
+            user.id(),
+            &self.agent,
+            provider_id,
+            model_id,
+            directory,
+            directory,
+        );
+        reply.complete(None);
+        reply.error = self
+            .error
+            .clone()
+            .map(|message| crate::message::MessageError::Unknown { message });
+        let reply = Message::Assistant(reply);
+        let text = match &self.error {
+            Some(error) if self.output.is_empty() => format!("Scheduled run failed: {error}"),
+            _ => self.output.clone(),
+        };
+        let reply_part = MessagePart::Text(TextPart::new(&session.id, reply.id(), text));
+        repo.save_message_with_parts(&reply, &[reply_part]).await?;
+
+        self.session_id = Some(session.id.clone());
+        Ok(session)
+    }
+
+    /// POST the run as JSON to `url`.
+    pub async fn notify(&self, url: &str) -> Result<(), String> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("wonopcode/", env!("CARGO_PKG_VERSION")))
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let response = client
+            .post(url)
+            .json(&serde_json::json!({
+                "type": "schedule.finished",
+                "success": self.success(),
+                "run": self,
+            }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("webhook returned {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Bus;
+    use chrono::Utc;
+    use wonopcode_storage::json::JsonStorage;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+            .unwrap()
+            .and_utc()
+    }
+
+    fn next(expr: &str, after: &str) -> String {
+        CronSchedule::parse(expr)
+            .unwrap()
+            .next_after(&at(after))
+            .unwrap()
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        // 2026-10-16 is a Friday
+        assert_eq!(next("0 3 * * *", "2026-10-16 12:00"), "2026-10-17 03:00");
+        assert_eq!(next("*/15 * * * *", "2026-10-16 12:00"), "2026-10-16 12:15");
+        assert_eq!(next("0 9 * * mon", "2026-10-16 12:00"), "2026-10-19 09:00");
+        assert_eq!(next("0 9 * * 1-5", "2026-10-16 09:00"), "2026-10-19 09:00");
+        assert_eq!(
+            next("30 6 1 jan,jul *", "2026-10-16 12:00"),
+            "2027-01-01 06:30"
+        );
+        assert_eq!(next("0 0 * * 7", "2026-10-16 12:00"), "2026-10-18 00:00");
+        assert_eq!(next("@weekly", "2026-10-16 12:00"), "2026-10-18 00:00");
+        assert_eq!(next("@hourly", "2026-10-16 12:59"), "2026-10-16 13:00");
+        assert_eq!(next("0 0 29 2 *", "2026-10-16 12:00"), "2028-02-29 00:00");
+
+        // Both day fields restricted: either matches
+        assert_eq!(next("0 0 13 * fri", "2026-10-16 12:00"), "2026-10-23 00:00");
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        for expr in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * foo *",
+        ] {
+            assert!(CronSchedule::parse(expr).is_err(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn test_task_from_config() {
+        let task = ScheduledTask::from_config(
+            "deps",
+            &ScheduledTaskConfig {
+                cron: "@daily".to_string(),
+                command: Some("deps-update --major".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(task.prompt, "/deps-update --major");
+
+        let both = ScheduledTaskConfig {
+            cron: "@daily".to_string(),
+            prompt: Some("hi".to_string()),
+            command: Some("deps".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            ScheduledTask::from_config("x", &both),
+            Err(ScheduleError::MissingPrompt("x".to_string()))
+        );
+
+        let mut config = Config::default();
+        let mut schedule = HashMap::new();
+        schedule.insert(
+            "report".to_string(),
+            ScheduledTaskConfig {
+                cron: "0 8 * * mon".to_string(),
+                prompt: Some("Report dead code".to_string()),
+                ..Default::default()
+            },
+        );
+        schedule.insert(
+            "paused".to_string(),
+            ScheduledTaskConfig {
+                enabled: Some(false),
+                ..schedule["report"].clone()
+            },
+        );
+        schedule.insert(
+            "broken".to_string(),
+            ScheduledTaskConfig {
+                cron: "never".to_string(),
+                ..schedule["report"].clone()
+            },
+        );
+        config.schedule = Some(schedule);
+        let names: Vec<String> = ScheduledTask::all(&config)
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec!["report"]);
+    }
+
+    #[test]
+    fn test_scheduler_due() {
+        let task = |name: &str, cron: &str| ScheduledTask {
+            name: name.to_string(),
+            cron: CronSchedule::parse(cron).unwrap(),
+            prompt: "go".to_string(),
+            agent: None,
+            model: None,
+            webhook: None,
+        };
+        let local = |s: &str| {
+            Local
+                .from_local_datetime(&NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap())
+                .earliest()
+                .unwrap()
+        };
+
+        let mut scheduler = Scheduler::new(
+            vec![task("hourly", "0 * * * *"), task("daily", "0 3 * * *")],
+            local("2026-10-16 12:30"),
+        );
+        assert!(scheduler.due(local("2026-10-16 12:59")).is_empty());
+
+        let due = scheduler.due(local("2026-10-16 13:00"));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].name, "hourly");
+        assert_eq!(
+            scheduler.next_run("hourly"),
+            Some(local("2026-10-16 14:00"))
+        );
+
+        // Missed runs collapse into one
+        let due = scheduler.due(local("2026-10-17 05:00"));
+        assert_eq!(due.len(), 2);
+        assert!(scheduler.due(local("2026-10-17 05:01")).is_empty());
+
+        // Unchanged tasks keep their next run; changed ones are rescheduled
+        scheduler.update(
+            vec![task("hourly", "0 * * * *"), task("daily", "0 4 * * *")],
+            local("2026-10-17 05:30"),
+        );
+        assert_eq!(
+            scheduler.next_run("hourly"),
+            Some(local("2026-10-17 06:00"))
+        );
+        assert_eq!(scheduler.next_run("daily"), Some(local("2026-10-18 04:00")));
+    }
+
+    #[tokio::test]
+    async fn test_run_saved_as_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = SessionRepository::new(JsonStorage::new(dir.path()), Bus::new());
+
+        let mut run = ScheduledRun {
+            task: "report".to_string(),
+            prompt: "Report dead code".to_string(),
+            agent: "build".to_string(),
+            model: "anthropic/claude-sonnet-4-5".to_string(),
+            started_at: 0,
+            finished_at: 1000,
+            output: "Nothing to report".to_string(),
+            error: None,
+            session_id: None,
+        };
+        let session = run.save(&repo, "proj", "/repo").await.unwrap();
+        assert_eq!(run.session_id.as_deref(), Some(session.id.as_str()));
+        assert_eq!(session.tags, vec!["scheduled", "report"]);
+        assert!(session.title.starts_with("Scheduled: report"));
+
+        let messages = repo.messages("proj", &session.id, None).await.unwrap();
+        assert_eq!(messages.len(), 2);
+        let texts: Vec<String> = messages
+            .iter()
+            .flat_map(|m| &m.parts)
+            .filter_map(|p| match p {
+                MessagePart::Text(t) => Some(t.text.clone()),
+                _ => None,
+            })
+            .collect();
+        assert!(texts.contains(&"Report dead code".to_string()));
+        assert!(texts.contains(&"Nothing to report".to_string()));
+    }
+}
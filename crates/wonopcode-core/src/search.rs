@@ -0,0 +1,361 @@
+//! Full-text session search.
+//!
+//! Each session gets an index entry holding the terms of its title, tags and
+//! message text, stored next to the session under `search/<project>/<session>`.
+//! Entries are rebuilt lazily when a search finds them out of date, so
+//! sessions never need to be indexed up front.
+//!
+//! A query matches sessions containing every query term (as a prefix of an
+//! indexed term). Terms written as `tag:name` only match tags. Hits in the
+//! title and tags rank above hits in the conversation.
+
+use crate::error::CoreResult;
+use crate::message::MessagePart;
+use crate::session::{Session, SessionRepository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+use wonopcode_storage::json::JsonStorage;
+use wonopcode_storage::Storage;
+
+/// Index format version; entries with another version are rebuilt.
+const INDEX_VERSION: u32 = 1;
+
+/// Score of a query term found in the title.
+const TITLE_WEIGHT: f64 = 10.0;
+
+/// Score of a query term found in the tags.
+const TAG_WEIGHT: f64 = 8.0;
+
+/// Characters of context shown around a match.
+const SNIPPET_CHARS: usize = 80;
+
+/// A session matching a search.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    /// The matching session.
+    pub session: Session,
+    /// Relevance score (higher is better).
+    pub score: f64,
+    /// Message text around the first match, if the match is in the conversation.
+    pub snippet: Option<String>,
+}
+
+/// Indexed terms of one session.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct IndexEntry {
+    version: u32,
+    /// Session update time when indexed.
+    updated: i64,
+    /// Number of messages when indexed.
+    messages: usize,
+    title: Vec<String>,
+    tags: Vec<String>,
+    /// Term frequencies of the message text.
+    terms: HashMap<String, u32>,
+}
+
+/// Session search.
+pub struct SessionSearch {
+    storage: JsonStorage,
+    session_repo: Arc<SessionRepository>,
+}
+
+impl SessionSearch {
+    /// Create a session search over the given storage.
+    pub fn new(storage: JsonStorage, session_repo: Arc<SessionRepository>) -> Self {
+        Self {
+            storage,
+            session_repo,
+        }
+    }
+
+    /// Search the sessions of a project, best matches first.
+    ///
+    /// An empty query matches nothing.
+    pub async fn search(&self, project_id: &str, query: &str) -> CoreResult<Vec<SearchHit>> {
+        let query = Query::parse(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut hits = Vec::new();
+        for session in self.session_repo.list(project_id).await? {
+            let entry = self.entry(&session).await?;
+            let Some(score) = query.score(&entry) else {
+                continue;
+            };
+            let snippet = if query.text.is_empty() {
+                None
+            } else {
+                self.snippet(&session, &query.text).await?
+            };
+            hits.push(SearchHit {
+                session,
+                score,
+                snippet,
+            });
+        }
+
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.session.time.updated.cmp(&a.session.time.updated))
+        });
+        Ok(hits)
+    }
+
+    /// The index entry of a session, rebuilding it if out of date.
+    async fn entry(&self, session: &Session) -> CoreResult<IndexEntry> {
+        let key = ["search", session.project_id.as_str(), session.id.as_str()];
+        let messages = self.storage.list(&["message", &session.id]).await?.len();
+        let title = tokenize(&session.title);
+        let tags: Vec<String> = session.tags.iter().map(|t| t.to_lowercase()).collect();
+        if let Some(entry) = self.storage.read::<IndexEntry>(&key).await? {
+            if entry.version == INDEX_VERSION
+                && entry.updated == session.time.updated
+                && entry.messages == messages
+                && entry.title == title
+                && entry.tags == tags
+            {
+                return Ok(entry);
+            }
+        }
+
+        debug!(session_id = %session.id, "Indexing session for search");
+        let mut entry = IndexEntry {
+            version: INDEX_VERSION,
+            updated: session.time.updated,
+            messages,
+            title,
+            tags,
+            terms: HashMap::new(),
+        };
+        for message in self
+            .session_repo
+            .messages(&session.project_id, &session.id, None)
+            .await?
+        {
+            for text in message.parts.iter().filter_map(part_text) {
+                for term in tokenize(text) {
+                    *entry.terms.entry(term).or_default() += 1;
+                }
+            }
+        }
+        self.storage.write(&key, &entry).await?;
+        Ok(entry)
+    }
+
+    /// Text around the first message match of any query term.
+    async fn snippet(&self, session: &Session, terms: &[String]) -> CoreResult<Option<String>> {
+        let messages = self
+            .session_repo
+            .messages(&session.project_id, &session.id, None)
+            .await?;
+        for text in messages
+            .iter()
+            .flat_map(|m| m.parts.iter().filter_map(part_text))
+        {
+            let lower = text.to_lowercase();
+            if let Some(pos) = terms.iter().filter_map(|t| lower.find(t.as_str())).min() {
+                return Ok(Some(snippet_at(text, &lower, pos)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Searchable text of a message part.
+fn part_text(part: &MessagePart) -> Option<&str> {
+    match part {
+        MessagePart::Text(p) => Some(&p.text),
+        _ => None,
+    }
+}
+
+/// A parsed search query.
+#[derive(Debug, Default)]
+struct Query {
+    /// Terms matched against the title, tags and message text.
+    text: Vec<String>,
+    /// Terms matched against tags only.
+    tags: Vec<String>,
+}
+
+impl Query {
+    fn parse(query: &str) -> Self {
+        let mut parsed = Self::default();
+        for word in query.split_whitespace() {
+            match word.strip_prefix("tag:") {
+                Some(tag) if !tag.is_empty() => parsed.tags.push(tag.to_lowercase()),
+                _ => parsed.text.extend(tokenize(word)),
+            }
+        }
+        parsed
+    }
+
+    fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.tags.is_empty()
+    }
+
+    /// Score an index entry, or `None` if a term doesn't match.
+    fn score(&self, entry: &IndexEntry) -> Option<f64> {
+        if !self.tags.iter().all(|tag| entry.tags.contains(tag)) {
+            return None;
+        }
+
+        let mut score = 0.0;
+        for term in &self.text {
+            let mut term_score = 0.0;
+            if entry.title.iter().any(|t| t.starts_with(term.as_str())) {
+                term_score += TITLE_WEIGHT;
+            }
+            if entry.tags.iter().any(|t| t.starts_with(term.as_str())) {
+                term_score += TAG_WEIGHT;
+            }
+            let count: u32 = entry
+                .terms
+                .iter()
+                .filter(|(t, _)| t.starts_with(term.as_str()))
+                .map(|(_, n)| n)
+                .sum();
+            if count > 0 {
+                term_score += 1.0 + f64::from(count).ln();
+            }
+            if term_score == 0.0 {
+                return None;
+            }
+            score += term_score;
+        }
+        Some(score)
+    }
+}
+
+/// Split text into lowercase alphanumeric terms of at least two characters.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// About [`SNIPPET_CHARS`] characters of `text` around byte offset `pos` of
+/// its lowercase form, on one line.
+fn snippet_at(text: &str, lower: &str, pos: usize) -> String {
+    // Lowercasing can change byte lengths, so map the offset through chars
+    let char_pos = lower[..pos].chars().count();
+    let chars: Vec<char> = text.chars().collect();
+    let start = char_pos.saturating_sub(SNIPPET_CHARS / 4);
+    let end = (start + SNIPPET_CHARS).min(chars.len());
+
+    let mut snippet: String = chars[start..end]
+        .iter()
+        .map(|&c| if c.is_whitespace() { ' ' } else { c })
+        .collect();
+    snippet = snippet.split_whitespace().collect::<Vec<_>>().join(" ");
+    if start > 0 {
+        snippet.insert_str(0, "...");
+    }
+    if end < chars.len() {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Bus;
+    use crate::message::{Message, ModelRef, TextPart, UserMessage};
+
+    fn create_test_search() -> (SessionSearch, Arc<SessionRepository>) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = JsonStorage::new(dir.keep());
+        let repo = Arc::new(SessionRepository::new(storage.clone(), Bus::new()));
+        (SessionSearch::new(storage, repo.clone()), repo)
+    }
+
+    async fn add_message(repo: &SessionRepository, session_id: &str, text: &str) {
+        let user = UserMessage::new(
+            session_id,
+            "build",
+            ModelRef {
+                provider_id: "test".to_string(),
+                model_id: "model-1".to_string(),
+            },
+        );
+        let part = TextPart::new(session_id, &user.id, text);
+        repo.save_message_with_parts(&Message::User(user), &[MessagePart::Text(part)])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_search_ranks_title_and_content_matches() {
+        let (search, repo) = create_test_search();
+        let mut titled = Session::new("proj_1", "/path");
+        titled.title = "Tokenizer cleanup".to_string();
+        let titled = repo.create(titled).await.unwrap();
+        let chat = repo.create(Session::new("proj_1", "/path")).await.unwrap();
+        add_message(
+            &repo,
+            &chat.id,
+            "The tokenizer splits unicode identifiers wrong, fix it.",
+        )
+        .await;
+        repo.create(Session::new("proj_1", "/path")).await.unwrap();
+
+        let hits = search.search("proj_1", "tokeniz").await.unwrap();
+        let ids: Vec<_> = hits.iter().map(|h| h.session.id.as_str()).collect();
+        assert_eq!(ids, vec![titled.id.as_str(), chat.id.as_str()]);
+        assert_eq!(
+            hits[1].snippet.as_deref(),
+            Some("The tokenizer splits unicode identifiers wrong, fix it.")
+        );
+
+        // Every term must match
+        let hits = search.search("proj_1", "tokenizer unicode").await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(search.search("proj_1", "  ").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_reindexes_changed_sessions() {
+        let (search, repo) = create_test_search();
+        let session = repo.create(Session::new("proj_1", "/path")).await.unwrap();
+        assert!(search.search("proj_1", "lexer").await.unwrap().is_empty());
+
+        add_message(&repo, &session.id, "Rewrite the lexer").await;
+        assert_eq!(search.search("proj_1", "lexer").await.unwrap().len(), 1);
+
+        repo.update("proj_1", &session.id, |s| {
+            s.tags = vec!["Parser".to_string()];
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            search.search("proj_1", "tag:parser").await.unwrap().len(),
+            1
+        );
+        assert!(search
+            .search("proj_1", "tag:pars lexer")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_snippet_at() {
+        let text = format!("{}needle{}", "a ".repeat(40), " b".repeat(40));
+        let lower = text.to_lowercase();
+        let snippet = snippet_at(&text, &lower, lower.find("needle").unwrap());
+        assert!(snippet.starts_with("..."));
+        assert!(snippet.ends_with("..."));
+        assert!(snippet.contains("needle"));
+        assert_eq!(
+            tokenize("Fix the TOKENIZER: a/b"),
+            vec!["fix", "the", "tokenizer"]
+        );
+    }
+}
@@ -10,8 +10,9 @@ use crate::bus::{Bus, SessionCreated, SessionDeleted, SessionUpdated};
 use crate::error::{CoreResult, SessionError};
 use crate::message::{FileDiff, Message, MessagePart};
 use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
 use wonopcode_storage::json::JsonStorage;
-use wonopcode_storage::Storage;
+use wonopcode_storage::{Storage, StorageReader};
 use wonopcode_util::Identifier;
 
 /// Session information.
@@ -45,6 +46,10 @@ pub struct Session {
     #[serde(default)]
     pub title: String,
 
+    /// Tags for finding the session later.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
     /// Application version.
     #[serde(default)]
     pub version: String,
@@ -56,6 +61,14 @@ pub struct Session {
     /// Revert information.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub revert: Option<RevertInfo>,
+
+    /// Branch information (for sessions forked to explore an alternative).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<BranchInfo>,
+
+    /// Git worktree the session works in, instead of the main checkout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worktree: Option<crate::worktree::Worktree>,
 }
 
 /// Session summary (computed from diffs).
@@ -69,9 +82,18 @@ pub struct SessionSummary {
 }
 
 /// Share information.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ShareInfo {
     pub url: String,
+    /// Secret for revoking the share.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// When the link expires (Unix milliseconds).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    /// Whether viewing the share needs a password.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub password_protected: bool,
 }
 
 /// Session timestamps.
@@ -97,6 +119,21 @@ pub struct RevertInfo {
     pub diff: Option<String>,
 }
 
+/// Branch information.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchInfo {
+    /// Session the branch was forked from.
+    pub parent_id: String,
+    /// Message the branch was forked at (`None` forks after the last message).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    /// Number of messages copied from the parent.
+    pub base_messages: usize,
+    /// When the branch was merged back into its parent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merged_at: Option<i64>,
+}
+
 /// A message with its parts.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageWithParts {
@@ -116,6 +153,7 @@ impl Session {
             summary: None,
             share: None,
             title: "New Session".to_string(),
+            tags: Vec::new(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             time: SessionTime {
                 created: now,
@@ -124,6 +162,8 @@ impl Session {
                 archived: None,
             },
             revert: None,
+            branch: None,
+            worktree: None,
         }
     }
 
@@ -232,9 +272,12 @@ impl SessionRepository {
         // Delete messages and parts first
         self.delete_all_messages(project_id, session_id).await?;
 
-        // Delete session
+        // Delete session and its search index entry
         let key = ["session", project_id, session_id];
         self.storage.remove(&key).await?;
+        self.storage
+            .remove(&["search", project_id, session_id])
+            .await?;
 
         // Publish event
         self.bus
@@ -289,6 +332,7 @@ impl SessionRepository {
         // Create the forked session
         let mut forked = Session::new(&original.project_id, &original.directory);
         forked.title = format!("Fork of {}", original.title);
+        forked.tags = original.tags.clone();
         let forked = self.create(forked).await?;
 
         // Get all messages from original session
@@ -318,9 +362,8 @@ impl SessionRepository {
                 }
             }
 
-            self.save_message(&cloned_message).await?;
-
-            // Clone and save all parts with new IDs
+            // Clone all parts with new IDs
+            let mut cloned_parts = Vec::with_capacity(msg_with_parts.parts.len());
             for part in msg_with_parts.parts {
                 let mut cloned_part = part.clone();
                 let new_part_id = Identifier::part();
@@ -388,13 +431,55 @@ impl SessionRepository {
                     }
                 }
 
-                self.save_part(&cloned_part).await?;
+                cloned_parts.push(cloned_part);
             }
+
+            self.save_message_with_parts(&cloned_message, &cloned_parts)
+                .await?;
         }
 
         Ok(forked)
     }
 
+    /// Store a session together with its messages and their parts, such as
+    /// one read back from an archive, in a single transaction.
+    ///
+    /// Either the whole session is stored, or none of it is.
+    pub async fn restore(
+        &self,
+        mut session: Session,
+        messages: &[MessageWithParts],
+    ) -> CoreResult<Session> {
+        session.touch();
+        self.storage
+            .transaction(|txn| {
+                txn.write(&["session", &session.project_id, &session.id], &session)?;
+                for message in messages {
+                    let message_key = [
+                        "message",
+                        message.message.session_id(),
+                        message.message.id(),
+                    ];
+                    txn.write(&message_key, &message.message)?;
+                    for part in &message.parts {
+                        txn.write(&["part", part.message_id(), part.id()], part)?;
+                    }
+                }
+                Ok(())
+            })
+            .await?;
+
+        self.bus
+            .publish(SessionCreated {
+                session_id: session.id.clone(),
+                project_id: session.project_id.clone(),
+                title: session.title.clone(),
+            })
+            .await;
+
+        Ok(session)
+    }
+
     // ========================================================================
     // Message Operations
     // ========================================================================
@@ -406,6 +491,26 @@ impl SessionRepository {
         Ok(())
     }
 
+    /// Save a message together with its parts in a single transaction.
+    ///
+    /// Either the message and all of its parts are stored, or none are.
+    pub async fn save_message_with_parts(
+        &self,
+        message: &Message,
+        parts: &[MessagePart],
+    ) -> CoreResult<()> {
+        self.storage
+            .transaction(|txn| {
+                txn.write(&["message", message.session_id(), message.id()], message)?;
+                for part in parts {
+                    txn.write(&["part", part.message_id(), part.id()], part)?;
+                }
+                Ok(())
+            })
+            .await?;
+        Ok(())
+    }
+
     /// Get a message.
     pub async fn get_message(&self, session_id: &str, message_id: &str) -> CoreResult<Message> {
         let key = ["message", session_id, message_id];
@@ -516,6 +621,48 @@ impl SessionRepository {
         Ok(parts)
     }
 
+    /// Stream a message and its parts as a JSON [`MessageWithParts`] document.
+    ///
+    /// The stored message and part files are spliced together as raw bytes,
+    /// so large messages can be sent to clients without being deserialized.
+    /// Returns `None` if the message doesn't exist.
+    pub async fn message_stream(
+        &self,
+        session_id: &str,
+        message_id: &str,
+    ) -> CoreResult<Option<StorageReader>> {
+        let Some(message) = self
+            .storage
+            .read_stream(&["message", session_id, message_id])
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut part_keys = self.storage.list(&["part", message_id]).await?;
+        part_keys.sort();
+
+        let mut reader: StorageReader =
+            Box::pin(std::io::Cursor::new(b"{\"message\":".to_vec()).chain(message));
+        reader = Box::pin(reader.chain(std::io::Cursor::new(b",\"parts\":[".to_vec())));
+
+        let mut first = true;
+        for key in part_keys {
+            let key_refs: Vec<&str> = key.iter().map(|s| s.as_str()).collect();
+            let Some(part) = self.storage.read_stream(&key_refs).await? else {
+                continue;
+            };
+            if !first {
+                reader = Box::pin(reader.chain(std::io::Cursor::new(b",".to_vec())));
+            }
+            reader = Box::pin(reader.chain(part));
+            first = false;
+        }
+
+        reader = Box::pin(reader.chain(std::io::Cursor::new(b"]}".to_vec())));
+        Ok(Some(reader))
+    }
+
     /// Delete all parts for a message.
     async fn delete_all_parts(&self, _session_id: &str, message_id: &str) -> CoreResult<()> {
         let prefix = ["part", message_id];
@@ -707,6 +854,7 @@ mod tests {
     fn test_share_info_serialization() {
         let share = ShareInfo {
             url: "https://example.com/share/abc123".to_string(),
+            ..Default::default()
         };
         let json = serde_json::to_string(&share).unwrap();
         let parsed: ShareInfo = serde_json::from_str(&json).unwrap();
@@ -898,6 +1046,45 @@ mod tests {
         assert_eq!(retrieved.id(), message.id());
     }
 
+    #[tokio::test]
+    async fn test_message_stream() {
+        let storage = create_test_storage();
+        let bus = Bus::new();
+        let repo = SessionRepository::new(storage, bus);
+
+        let message = Message::User(UserMessage::new(
+            "ses_123",
+            "default",
+            ModelRef {
+                provider_id: "test".to_string(),
+                model_id: "model-1".to_string(),
+            },
+        ));
+        repo.save_message(&message).await.unwrap();
+        for text in ["first", "second"] {
+            let part = MessagePart::Text(TextPart::new("ses_123", message.id(), text));
+            repo.save_part(&part).await.unwrap();
+        }
+
+        let mut reader = repo
+            .message_stream("ses_123", message.id())
+            .await
+            .unwrap()
+            .unwrap();
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.unwrap();
+
+        let streamed: MessageWithParts = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(streamed.message.id(), message.id());
+        assert_eq!(streamed.parts.len(), 2);
+
+        assert!(repo
+            .message_stream("ses_123", "nonexistent")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn test_get_message_not_found() {
         let storage = create_test_storage();
@@ -965,6 +1152,34 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_restore_session_with_messages() {
+        let storage = create_test_storage();
+        let repo = SessionRepository::new(storage, Bus::new());
+
+        let session = Session::new("proj_1", "/path");
+        let message = Message::User(UserMessage::new(
+            &session.id,
+            "default",
+            ModelRef {
+                provider_id: "test".to_string(),
+                model_id: "model-1".to_string(),
+            },
+        ));
+        let part = MessagePart::Text(TextPart::new(&session.id, message.id(), "Hello world"));
+        let messages = vec![MessageWithParts {
+            message,
+            parts: vec![part],
+        }];
+
+        let restored = repo.restore(session, &messages).await.unwrap();
+        let read = repo.get("proj_1", &restored.id).await.unwrap();
+        assert_eq!(read.id, restored.id);
+        let stored = repo.messages("proj_1", &restored.id, None).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].parts.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_messages_with_limit() {
         let storage = create_test_storage();
@@ -1256,6 +1471,7 @@ mod tests {
         });
         session.share = Some(ShareInfo {
             url: "https://share.example.com/abc".to_string(),
+            ..Default::default()
         });
         session.revert = Some(RevertInfo {
             message_id: "msg_123".to_string(),
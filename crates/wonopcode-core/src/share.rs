@@ -3,12 +3,19 @@
 //! This module provides the ability to share sessions with others.
 //! Sessions can be shared either:
 //! - Locally via export/URL
-//! - To a remote sharing service (if configured)
+//! - To a remote sharing service: the hosted one, or a self-hosted share
+//!   server set in the `share_server` config
+//!
+//! Share links can expire and be password protected, and are revoked with
+//! the secret returned when the share was created, which is kept with the
+//! session.
 
+use crate::config::{Config, ShareMode};
 use crate::error::CoreResult;
 use crate::session::SessionRepository;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::time::Duration;
 use tracing::{debug, info};
 
 /// Default sharing service URL (can be overridden via config)
@@ -25,6 +32,14 @@ pub struct ShareInfo {
 
     /// When the share was created
     pub created_at: i64,
+
+    /// When the link expires (Unix milliseconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+
+    /// Whether viewing the share needs a password
+    #[serde(default)]
+    pub password_protected: bool,
 }
 
 /// Response from share creation
@@ -35,18 +50,101 @@ pub struct ShareCreateResponse {
 
     /// Secret for managing the share
     pub secret: String,
+
+    /// When the link expires (Unix milliseconds), as set by the server
+    #[serde(default, rename = "expiresAt", skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+}
+
+/// Options for a new share link
+#[derive(Debug, Clone, Default)]
+pub struct ShareOptions {
+    /// How long the link stays valid; `None` for no expiry
+    pub expires_in: Option<Duration>,
+
+    /// Password needed to view the share
+    pub password: Option<String>,
+}
+
+/// Where sessions are shared, from the `share` and `share_server` config
+#[derive(Debug, Clone, Default)]
+pub struct ShareSettings {
+    /// Share server URL; the hosted service if not set
+    pub url: Option<String>,
+
+    /// Bearer token for the share server
+    pub token: Option<String>,
+
+    /// Default link lifetime
+    pub expires_in: Option<Duration>,
+
+    /// Sharing is disabled by the `share` config
+    pub disabled: bool,
+}
+
+impl ShareSettings {
+    /// Settings from the config.
+    pub fn from_config(config: &Config) -> Result<Self, ShareError> {
+        let server = config.share_server.clone().unwrap_or_default();
+        let expires_in = match &server.expires {
+            Some(expires) => parse_expiry(expires)?,
+            None => None,
+        };
+        Ok(Self {
+            url: server.url,
+            token: server.token,
+            expires_in,
+            disabled: config.share == Some(ShareMode::Disabled),
+        })
+    }
+
+    /// A client for the configured share server.
+    pub fn client(&self) -> ShareClient {
+        ShareClient::new(self.url.as_deref()).with_token(self.token.clone())
+    }
+}
+
+/// Parse a link lifetime such as "30m", "12h", "7d" or "2w".
+///
+/// "never" means the link doesn't expire.
+pub fn parse_expiry(value: &str) -> Result<Option<Duration>, ShareError> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("never") {
+        return Ok(None);
+    }
+    let invalid = || ShareError::InvalidExpiry(value.to_string());
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| invalid())?;
+    let unit_secs = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    if amount == 0 {
+        return Err(invalid());
+    }
+    Ok(Some(Duration::from_secs(amount.saturating_mul(unit_secs))))
 }
 
 /// Session sharing client
 pub struct ShareClient {
     client: reqwest::Client,
     base_url: String,
+    token: Option<String>,
 }
 
 impl ShareClient {
     /// Create a new share client
     pub fn new(base_url: Option<&str>) -> Self {
-        let base_url = base_url.unwrap_or(DEFAULT_SHARE_URL).to_string();
+        let base_url = base_url
+            .unwrap_or(DEFAULT_SHARE_URL)
+            .trim_end_matches('/')
+            .to_string();
 
         Self {
             client: reqwest::Client::builder()
@@ -55,59 +153,95 @@ impl ShareClient {
                 .build()
                 .unwrap_or_default(),
             base_url,
+            token: None,
         }
     }
 
-    /// Create a new share for a session
-    pub async fn create(&self, session_id: &str) -> Result<ShareCreateResponse, ShareError> {
-        let url = format!("{}/share_create", self.base_url);
+    /// Authenticate to the share server with a bearer token
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({ "sessionID": session_id }))
+    /// POST `body` to `endpoint` on the share server
+    async fn post(
+        &self,
+        endpoint: &str,
+        body: serde_json::Value,
+    ) -> Result<reqwest::Response, ShareError> {
+        let url = format!("{}/{}", self.base_url, endpoint);
+
+        let mut request = self.client.post(&url).json(&body);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
             .send()
             .await
             .map_err(|e| ShareError::Network(e.to_string()))?;
 
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().await.unwrap_or_default();
-            return Err(ShareError::Api {
-                status,
-                message: body,
-            });
+        let status = response.status().as_u16();
+        match status {
+            200..=299 => Ok(response),
+            404 => Err(ShareError::ShareNotFound),
+            410 => Err(ShareError::Expired),
+            _ => {
+                let body = response.text().await.unwrap_or_default();
+                Err(ShareError::Api {
+                    status,
+                    message: body,
+                })
+            }
         }
+    }
 
-        response
-            .json()
+    /// Create a new share for a session
+    pub async fn create(&self, session_id: &str) -> Result<ShareCreateResponse, ShareError> {
+        self.create_with_options(session_id, &ShareOptions::default())
             .await
-            .map_err(|e| ShareError::Parse(e.to_string()))
     }
 
-    /// Delete a share
-    pub async fn delete(&self, session_id: &str, secret: &str) -> Result<(), ShareError> {
-        let url = format!("{}/share_delete", self.base_url);
+    /// Create a new share for a session that expires or needs a password
+    pub async fn create_with_options(
+        &self,
+        session_id: &str,
+        options: &ShareOptions,
+    ) -> Result<ShareCreateResponse, ShareError> {
+        let mut body = serde_json::json!({ "sessionID": session_id });
+        if let Some(expires_in) = options.expires_in {
+            let expires_at = chrono::Utc::now().timestamp_millis() + expires_in.as_millis() as i64;
+            body["expiresAt"] = expires_at.into();
+        }
+        if let Some(password) = &options.password {
+            body["password"] = password.clone().into();
+        }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({
-                "sessionID": session_id,
-                "secret": secret
-            }))
-            .send()
+        let mut response: ShareCreateResponse = self
+            .post("share_create", body)
+            .await?
+            .json()
             .await
-            .map_err(|e| ShareError::Network(e.to_string()))?;
+            .map_err(|e| ShareError::Parse(e.to_string()))?;
 
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().await.unwrap_or_default();
-            return Err(ShareError::Api {
-                status,
-                message: body,
-            });
+        // Servers that ignore expiry still get the requested time recorded
+        if response.expires_at.is_none() {
+            response.expires_at = options
+                .expires_in
+                .map(|d| chrono::Utc::now().timestamp_millis() + d.as_millis() as i64);
         }
+        Ok(response)
+    }
+
+    /// Delete a share, revoking its link
+    pub async fn delete(&self, session_id: &str, secret: &str) -> Result<(), ShareError> {
+        self.post(
+            "share_delete",
+            serde_json::json!({
+                "sessionID": session_id,
+                "secret": secret
+            }),
+        )
+        .await?;
 
         Ok(())
     }
@@ -120,29 +254,16 @@ impl ShareClient {
         key: &str,
         content: serde_json::Value,
     ) -> Result<(), ShareError> {
-        let url = format!("{}/share_sync", self.base_url);
-
-        let response = self
-            .client
-            .post(&url)
-            .json(&serde_json::json!({
+        self.post(
+            "share_sync",
+            serde_json::json!({
                 "sessionID": session_id,
                 "secret": secret,
                 "key": key,
                 "content": content
-            }))
-            .send()
-            .await
-            .map_err(|e| ShareError::Network(e.to_string()))?;
-
-        if !response.status().is_success() {
-            let status = response.status().as_u16();
-            let body = response.text().await.unwrap_or_default();
-            return Err(ShareError::Api {
-                status,
-                message: body,
-            });
-        }
+            }),
+        )
+        .await?;
 
         debug!("Synced {} to share", key);
         Ok(())
@@ -172,6 +293,15 @@ pub enum ShareError {
 
     #[error("Share not found")]
     ShareNotFound,
+
+    #[error("Share link has expired")]
+    Expired,
+
+    #[error("Sharing is disabled in the config")]
+    Disabled,
+
+    #[error("Invalid expiry '{0}', expected e.g. 30m, 12h, 7d, 2w or never")]
+    InvalidExpiry(String),
 }
 
 /// Share a session
@@ -179,8 +309,13 @@ pub async fn share_session(
     repo: &SessionRepository,
     project_id: &str,
     session_id: &str,
-    share_url: Option<&str>,
+    settings: &ShareSettings,
+    options: &ShareOptions,
 ) -> Result<ShareInfo, ShareError> {
+    if settings.disabled {
+        return Err(ShareError::Disabled);
+    }
+
     // Get the session (verify it exists)
     let _session = repo
         .get(project_id, session_id)
@@ -188,19 +323,26 @@ pub async fn share_session(
         .map_err(|_| ShareError::SessionNotFound)?;
 
     // Create share via API
-    let client = ShareClient::new(share_url);
-    let response = client.create(session_id).await?;
+    let response = settings
+        .client()
+        .create_with_options(session_id, options)
+        .await?;
 
     let share_info = ShareInfo {
         url: response.url,
         secret: response.secret,
         created_at: chrono::Utc::now().timestamp_millis(),
+        expires_at: response.expires_at,
+        password_protected: options.password.is_some(),
     };
 
-    // Update session with share info
+    // Update session with share info, keeping the secret for revocation
     repo.update(project_id, session_id, |s| {
         s.share = Some(crate::session::ShareInfo {
             url: share_info.url.clone(),
+            secret: Some(share_info.secret.clone()),
+            expires_at: share_info.expires_at,
+            password_protected: share_info.password_protected,
         });
     })
     .await
@@ -210,17 +352,34 @@ pub async fn share_session(
     Ok(share_info)
 }
 
-/// Unshare a session
+/// Unshare a session, revoking its link
+///
+/// Uses the secret stored with the session if `secret` is not given.
 pub async fn unshare_session(
     repo: &SessionRepository,
     project_id: &str,
     session_id: &str,
-    secret: &str,
-    share_url: Option<&str>,
+    secret: Option<&str>,
+    settings: &ShareSettings,
 ) -> Result<(), ShareError> {
-    // Delete share via API
-    let client = ShareClient::new(share_url);
-    client.delete(session_id, secret).await?;
+    let session = repo
+        .get(project_id, session_id)
+        .await
+        .map_err(|_| ShareError::SessionNotFound)?;
+    let secret = match secret {
+        Some(secret) => secret.to_string(),
+        None => session
+            .share
+            .and_then(|share| share.secret)
+            .ok_or(ShareError::ShareNotFound)?,
+    };
+
+    // Delete share via API. A share the server no longer has is as good as
+    // revoked.
+    match settings.client().delete(session_id, &secret).await {
+        Ok(()) | Err(ShareError::ShareNotFound) | Err(ShareError::Expired) => {}
+        Err(e) => return Err(e),
+    }
 
     // Update session to remove share info
     repo.update(project_id, session_id, |s| {
@@ -276,6 +435,8 @@ mod tests {
             url: "https://share.wonopcode.com/abc123".to_string(),
             secret: "secret123".to_string(),
             created_at: 1234567890,
+            expires_at: None,
+            password_protected: false,
         };
 
         let json = serde_json::to_string(&info).unwrap();
@@ -290,6 +451,7 @@ mod tests {
         let response = ShareCreateResponse {
             url: "https://share.wonopcode.com/xyz789".to_string(),
             secret: "secret456".to_string(),
+            expires_at: Some(1234567890),
         };
 
         let json = serde_json::to_string(&response).unwrap();
@@ -297,6 +459,8 @@ mod tests {
 
         assert_eq!(parsed.url, response.url);
         assert_eq!(parsed.secret, response.secret);
+        assert!(json.contains("\"expiresAt\":1234567890"));
+        assert_eq!(parsed.expires_at, response.expires_at);
     }
 
     #[test]
@@ -351,4 +515,62 @@ mod tests {
         assert!(url.starts_with("file://"));
         assert!(url.contains("my session.json"));
     }
+
+    #[test]
+    fn test_share_client_trims_trailing_slash() {
+        let client = ShareClient::new(Some("https://share.example.com/"));
+        assert_eq!(client.base_url, "https://share.example.com");
+    }
+
+    #[test]
+    fn test_parse_expiry() {
+        assert_eq!(
+            parse_expiry("30m").unwrap(),
+            Some(Duration::from_secs(30 * 60))
+        );
+        assert_eq!(
+            parse_expiry("12h").unwrap(),
+            Some(Duration::from_secs(12 * 3600))
+        );
+        assert_eq!(
+            parse_expiry("7d").unwrap(),
+            Some(Duration::from_secs(7 * 86400))
+        );
+        assert_eq!(
+            parse_expiry("2w").unwrap(),
+            Some(Duration::from_secs(14 * 86400))
+        );
+        assert_eq!(parse_expiry("never").unwrap(), None);
+        for invalid in ["", "7", "d", "0d", "7y", "-1d"] {
+            assert!(matches!(
+                parse_expiry(invalid),
+                Err(ShareError::InvalidExpiry(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_share_settings_from_config() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "share": "disabled",
+                "share_server": {
+                    "url": "https://share.example.com",
+                    "token": "abc",
+                    "expires": "7d"
+                }
+            }"#,
+        )
+        .unwrap();
+        let settings = ShareSettings::from_config(&config).unwrap();
+        assert_eq!(settings.url.as_deref(), Some("https://share.example.com"));
+        assert_eq!(settings.token.as_deref(), Some("abc"));
+        assert_eq!(settings.expires_in, Some(Duration::from_secs(7 * 86400)));
+        assert!(settings.disabled);
+
+        let settings = ShareSettings::from_config(&Config::default()).unwrap();
+        assert!(settings.url.is_none());
+        assert!(!settings.disabled);
+        assert_eq!(settings.client().base_url, DEFAULT_SHARE_URL);
+    }
 }
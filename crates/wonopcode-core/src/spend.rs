@@ -0,0 +1,514 @@
+//! Spending across sessions.
+//!
+//! Unlike the per-session [budget](crate::budget), spend is persisted, so it
+//! adds up across sessions, restarts and projects. The cost of every model
+//! call is recorded under `spend/<month>/<project>` by day. A [`SpendTracker`]
+//! compares today's spend (across all projects and for the current project)
+//! and this month's spend against the configured [`SpendConfig`]: crossing an
+//! alert amount is reported once per period, and once a hard limit is reached
+//! no new prompts may run until the user acknowledges it, which grants another
+//! full limit for the rest of the period. The [history](SpendTracker::history)
+//! of recent days, by day and by model, backs the usage dashboard.
+
+use crate::config::{SpendConfig, SpendLimitConfig};
+use crate::error::CoreResult;
+use chrono::{Days, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wonopcode_storage::json::JsonStorage;
+use wonopcode_storage::Storage;
+
+/// A period of spending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendScope {
+    /// Today, across all projects.
+    Daily,
+    /// This month, across all projects.
+    Monthly,
+    /// Today, in the current project.
+    ProjectDaily,
+}
+
+impl SpendScope {
+    /// Identifier used in config and events.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpendScope::Daily => "daily",
+            SpendScope::Monthly => "monthly",
+            SpendScope::ProjectDaily => "project_daily",
+        }
+    }
+
+    /// Human-readable name.
+    fn label(&self) -> &'static str {
+        match self {
+            SpendScope::Daily => "Daily spend",
+            SpendScope::Monthly => "Monthly spend",
+            SpendScope::ProjectDaily => "Project spend today",
+        }
+    }
+}
+
+/// Cost and tokens of model calls.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpendAmount {
+    /// Cost in USD.
+    pub cost: f64,
+    /// Input tokens.
+    #[serde(default)]
+    pub input_tokens: u64,
+    /// Output tokens.
+    #[serde(default)]
+    pub output_tokens: u64,
+}
+
+impl SpendAmount {
+    fn add(&mut self, other: &SpendAmount) {
+        self.cost += other.cost;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Spend of one project in one month, by day (`YYYY-MM-DD`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProjectMonth {
+    days: BTreeMap<String, SpendAmount>,
+    /// Spend by day and model.
+    #[serde(default)]
+    models: BTreeMap<String, BTreeMap<String, SpendAmount>>,
+}
+
+/// Spend over recent days.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpendHistory {
+    /// Spend by day (`YYYY-MM-DD`), oldest first, including days without spend.
+    pub days: Vec<(String, SpendAmount)>,
+    /// Spend by model over all the days.
+    pub models: BTreeMap<String, SpendAmount>,
+}
+
+/// How many times each limit was acknowledged, by scope and period.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Acknowledgements {
+    counts: BTreeMap<String, u32>,
+}
+
+/// Spend of one scope against its alert and limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpendStatus {
+    /// The period.
+    pub scope: SpendScope,
+    /// Amount spent in the period, in USD.
+    pub spent: f64,
+    /// Alert amount, if configured.
+    pub alert: Option<f64>,
+    /// Current hard limit, including acknowledged extensions.
+    pub limit: Option<f64>,
+}
+
+impl SpendStatus {
+    /// Whether the hard limit is reached.
+    pub fn limit_reached(&self) -> bool {
+        self.limit.is_some_and(|limit| self.spent >= limit)
+    }
+
+    /// Human-readable description, e.g. "Daily spend: $41.20 (alert at $20.00, limit $50.00)".
+    pub fn describe(&self) -> String {
+        let mut bounds = Vec::new();
+        if let Some(alert) = self.alert {
+            bounds.push(format!("alert at ${alert:.2}"));
+        }
+        if let Some(limit) = self.limit {
+            bounds.push(format!("limit ${limit:.2}"));
+        }
+        if bounds.is_empty() {
+            format!("{}: ${:.2}", self.scope.label(), self.spent)
+        } else {
+            format!(
+                "{}: ${:.2} ({})",
+                self.scope.label(),
+                self.spent,
+                bounds.join(", ")
+            )
+        }
+    }
+}
+
+/// Result of a spend check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpendCheck {
+    /// Within all alerts and limits.
+    Ok,
+    /// An alert amount was crossed for the first time in its period.
+    Alert(SpendStatus),
+    /// A hard limit is reached and not yet acknowledged.
+    LimitReached(SpendStatus),
+}
+
+/// Persistent spend tracking of a project.
+#[derive(Clone)]
+pub struct SpendTracker {
+    storage: JsonStorage,
+    project_id: String,
+    config: SpendConfig,
+    /// Alerts already reported, by scope and period.
+    alerted: Vec<String>,
+}
+
+impl SpendTracker {
+    /// Create a tracker for a project.
+    pub fn new(storage: JsonStorage, project_id: impl Into<String>, config: SpendConfig) -> Self {
+        Self {
+            storage,
+            project_id: project_id.into(),
+            config,
+            alerted: Vec::new(),
+        }
+    }
+
+    /// Replace the alerts and limits.
+    pub fn set_config(&mut self, config: SpendConfig) {
+        self.config = config;
+    }
+
+    /// Record the cost and tokens of a call to a model.
+    pub async fn record(&self, model: &str, amount: &SpendAmount) -> CoreResult<()> {
+        let today = Local::now().date_naive();
+        let key = ["spend", &month_key(today), self.project_id.as_str()];
+        let mut month: ProjectMonth = self.storage.read(&key).await?.unwrap_or_default();
+        month.days.entry(day_key(today)).or_default().add(amount);
+        month
+            .models
+            .entry(day_key(today))
+            .or_default()
+            .entry(model.to_string())
+            .or_default()
+            .add(amount);
+        self.storage.write(&key, &month).await?;
+        Ok(())
+    }
+
+    /// Spend over the last `days` days, today included, across all projects
+    /// or in one project.
+    pub async fn history(&self, days: u32, project: Option<&str>) -> CoreResult<SpendHistory> {
+        let today = Local::now().date_naive();
+        let first = today
+            .checked_sub_days(Days::new(u64::from(days.max(1) - 1)))
+            .unwrap_or(today);
+        let mut by_day: BTreeMap<String, SpendAmount> = first
+            .iter_days()
+            .take_while(|date| *date <= today)
+            .map(|date| (day_key(date), SpendAmount::default()))
+            .collect();
+        let mut months: Vec<String> = by_day.keys().map(|day| day[..7].to_string()).collect();
+        months.dedup();
+
+        let mut models: BTreeMap<String, SpendAmount> = BTreeMap::new();
+        for month_key in months {
+            for key in self.storage.list(&["spend", &month_key]).await? {
+                if project.is_some_and(|project| key.last().map(String::as_str) != Some(project)) {
+                    continue;
+                }
+                let key_refs: Vec<&str> = key.iter().map(String::as_str).collect();
+                let Some(month) = self.storage.read::<ProjectMonth>(&key_refs).await? else {
+                    continue;
+                };
+                for (day, amount) in &month.days {
+                    if let Some(total) = by_day.get_mut(day) {
+                        total.add(amount);
+                    }
+                }
+                for (_, day_models) in month.models.range(day_key(first)..) {
+                    for (model, amount) in day_models {
+                        models.entry(model.clone()).or_default().add(amount);
+                    }
+                }
+            }
+        }
+        Ok(SpendHistory {
+            days: by_day.into_iter().collect(),
+            models,
+        })
+    }
+
+    /// Spend of every scope, whether or not it has an alert or limit.
+    pub async fn statuses(&self) -> CoreResult<Vec<SpendStatus>> {
+        let today = Local::now().date_naive();
+        let (daily, monthly, project) = self.totals(today).await?;
+        let acks = self.acknowledgements().await?;
+
+        let scopes = [
+            (SpendScope::Daily, &self.config.daily, daily),
+            (SpendScope::Monthly, &self.config.monthly, monthly),
+            (
+                SpendScope::ProjectDaily,
+                &self.config.project_daily,
+                project,
+            ),
+        ];
+        Ok(scopes
+            .into_iter()
+            .map(|(scope, config, spent)| {
+                let config = config.clone().unwrap_or_default();
+                let extensions = acks
+                    .counts
+                    .get(&self.period_key(scope, today))
+                    .copied()
+                    .unwrap_or(0);
+                SpendStatus {
+                    scope,
+                    spent: spent.cost,
+                    alert: config.alert,
+                    limit: config.limit.map(|limit| limit * f64::from(extensions + 1)),
+                }
+            })
+            .collect())
+    }
+
+    /// Compare spend against the alerts and limits.
+    ///
+    /// Each alert is reported at most once per period.
+    pub async fn check(&mut self) -> CoreResult<SpendCheck> {
+        if !self.config.is_enabled() {
+            return Ok(SpendCheck::Ok);
+        }
+        let statuses = self.statuses().await?;
+        if let Some(status) = statuses.iter().find(|s| s.limit_reached()) {
+            return Ok(SpendCheck::LimitReached(status.clone()));
+        }
+        let today = Local::now().date_naive();
+        for status in statuses {
+            if !status.alert.is_some_and(|alert| status.spent >= alert) {
+                continue;
+            }
+            let key = self.period_key(status.scope, today);
+            if !self.alerted.contains(&key) {
+                self.alerted.push(key);
+                return Ok(SpendCheck::Alert(status));
+            }
+        }
+        Ok(SpendCheck::Ok)
+    }
+
+    /// Acknowledge every reached limit, granting another full limit for the
+    /// rest of its period. Returns the acknowledged limits.
+    pub async fn acknowledge(&self) -> CoreResult<Vec<SpendStatus>> {
+        let today = Local::now().date_naive();
+        let reached: Vec<SpendStatus> = self
+            .statuses()
+            .await?
+            .into_iter()
+            .filter(SpendStatus::limit_reached)
+            .collect();
+        if reached.is_empty() {
+            return Ok(reached);
+        }
+
+        let mut acks = self.acknowledgements().await?;
+        for status in &reached {
+            *acks
+                .counts
+                .entry(self.period_key(status.scope, today))
+                .or_default() += 1;
+        }
+        self.storage
+            .write(&["spend", "acknowledged"], &acks)
+            .await?;
+        Ok(reached)
+    }
+
+    /// Spend today across projects, this month and today in this project.
+    async fn totals(
+        &self,
+        today: NaiveDate,
+    ) -> CoreResult<(SpendAmount, SpendAmount, SpendAmount)> {
+        let month_key = month_key(today);
+        let day_key = day_key(today);
+        let mut daily = SpendAmount::default();
+        let mut monthly = SpendAmount::default();
+        let mut project = SpendAmount::default();
+
+        for key in self.storage.list(&["spend", &month_key]).await? {
+            let key_refs: Vec<&str> = key.iter().map(String::as_str).collect();
+            let Some(month) = self.storage.read::<ProjectMonth>(&key_refs).await? else {
+                continue;
+            };
+            for amount in month.days.values() {
+                monthly.add(amount);
+            }
+            if let Some(amount) = month.days.get(&day_key) {
+                daily.add(amount);
+                if key.last() == Some(&self.project_id) {
+                    project.add(amount);
+                }
+            }
+        }
+        Ok((daily, monthly, project))
+    }
+
+    async fn acknowledgements(&self) -> CoreResult<Acknowledgements> {
+        Ok(self
+            .storage
+            .read(&["spend", "acknowledged"])
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Key identifying a scope's current period.
+    fn period_key(&self, scope: SpendScope, today: NaiveDate) -> String {
+        match scope {
+            SpendScope::Daily => format!("daily:{}", day_key(today)),
+            SpendScope::Monthly => format!("monthly:{}", month_key(today)),
+            SpendScope::ProjectDaily => {
+                format!("project:{}:{}", self.project_id, day_key(today))
+            }
+        }
+    }
+}
+
+impl SpendConfig {
+    /// Whether any alert or limit is configured.
+    pub fn is_enabled(&self) -> bool {
+        [&self.daily, &self.monthly, &self.project_daily]
+            .into_iter()
+            .flatten()
+            .any(|c: &SpendLimitConfig| c.alert.is_some() || c.limit.is_some())
+    }
+}
+
+fn day_key(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn month_key(date: NaiveDate) -> String {
+    date.format("%Y-%m").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(alert: f64, limit: f64) -> Option<SpendLimitConfig> {
+        Some(SpendLimitConfig {
+            alert: Some(alert),
+            limit: Some(limit),
+        })
+    }
+
+    fn cost(cost: f64) -> SpendAmount {
+        SpendAmount {
+            cost,
+            input_tokens: 1_000,
+            output_tokens: 100,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spend_adds_up_across_trackers_and_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+        let config = SpendConfig {
+            daily: limits(5.0, 10.0),
+            project_daily: limits(3.0, 6.0),
+            ..Default::default()
+        };
+
+        let first = SpendTracker::new(storage.clone(), "proj_a", config.clone());
+        first.record("model-a", &cost(1.5)).await.unwrap();
+        first.record("model-a", &cost(1.0)).await.unwrap();
+        // Another process, or a later session, sees the same spend
+        let other = SpendTracker::new(storage.clone(), "proj_b", config.clone());
+        other.record("model-b", &cost(2.0)).await.unwrap();
+
+        let statuses = first.statuses().await.unwrap();
+        let daily = &statuses[0];
+        assert_eq!(daily.scope, SpendScope::Daily);
+        assert!((daily.spent - 4.5).abs() < 1e-9);
+        assert!((statuses[1].spent - 4.5).abs() < 1e-9);
+        assert!((statuses[2].spent - 2.5).abs() < 1e-9);
+        assert_eq!(statuses[1].limit, None);
+        assert_eq!(
+            daily.describe(),
+            "Daily spend: $4.50 (alert at $5.00, limit $10.00)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alert_once_then_limit_until_acknowledged() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tracker = SpendTracker::new(
+            JsonStorage::new(dir.path()),
+            "proj_a",
+            SpendConfig {
+                daily: limits(2.0, 4.0),
+                ..Default::default()
+            },
+        );
+        assert_eq!(tracker.check().await.unwrap(), SpendCheck::Ok);
+
+        tracker.record("model-a", &cost(2.5)).await.unwrap();
+        let SpendCheck::Alert(status) = tracker.check().await.unwrap() else {
+            panic!("expected alert");
+        };
+        assert_eq!(status.scope, SpendScope::Daily);
+        assert_eq!(tracker.check().await.unwrap(), SpendCheck::Ok);
+
+        tracker.record("model-a", &cost(2.0)).await.unwrap();
+        assert!(matches!(
+            tracker.check().await.unwrap(),
+            SpendCheck::LimitReached(_)
+        ));
+
+        // Acknowledging grants another full limit, also to other trackers
+        let acknowledged = tracker.acknowledge().await.unwrap();
+        assert_eq!(acknowledged.len(), 1);
+        assert_eq!(tracker.check().await.unwrap(), SpendCheck::Ok);
+        let restarted = SpendTracker::new(
+            JsonStorage::new(dir.path()),
+            "proj_a",
+            tracker.config.clone(),
+        );
+        assert_eq!(restarted.statuses().await.unwrap()[0].limit, Some(8.0));
+        assert!(restarted.acknowledge().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_without_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tracker = SpendTracker::new(
+            JsonStorage::new(dir.path()),
+            "proj_a",
+            SpendConfig::default(),
+        );
+        tracker.record("model-a", &cost(1_000.0)).await.unwrap();
+        assert_eq!(tracker.check().await.unwrap(), SpendCheck::Ok);
+        assert!(!SpendConfig::default().is_enabled());
+    }
+
+    #[tokio::test]
+    async fn test_history_by_day_and_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+        let tracker = SpendTracker::new(storage.clone(), "proj_a", SpendConfig::default());
+        tracker.record("model-a", &cost(1.0)).await.unwrap();
+        tracker.record("model-b", &cost(0.5)).await.unwrap();
+        let other = SpendTracker::new(storage, "proj_b", SpendConfig::default());
+        other.record("model-a", &cost(2.0)).await.unwrap();
+
+        let history = tracker.history(7, None).await.unwrap();
+        assert_eq!(history.days.len(), 7);
+        assert_eq!(history.days[6].0, day_key(Local::now().date_naive()));
+        assert!((history.days[6].1.cost - 3.5).abs() < 1e-9);
+        assert_eq!(history.days[6].1.input_tokens, 3_000);
+        assert_eq!(history.days[0].1, SpendAmount::default());
+        assert!((history.models["model-a"].cost - 3.0).abs() < 1e-9);
+
+        let project = tracker.history(1, Some("proj_a")).await.unwrap();
+        assert_eq!(project.days.len(), 1);
+        assert!((project.days[0].1.cost - 1.5).abs() < 1e-9);
+        assert_eq!(project.models.len(), 2);
+        assert!((project.models["model-a"].cost - 1.0).abs() < 1e-9);
+    }
+}
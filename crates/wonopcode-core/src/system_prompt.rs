@@ -1,8 +1,10 @@
 //! System prompt generation for wonopcode.
 //!
 //! This module provides provider-specific system prompts and environment context
-//! generation.
+//! generation. Agent prompts and custom instructions are rendered as
+//! [prompt templates](crate::prompt_template).
 
+use crate::prompt_template::{render_prompt, PromptVars};
 use std::path::Path;
 
 /// Provider-specific system prompt for Anthropic (Claude) models.
@@ -340,6 +342,7 @@ pub fn build_system_prompt(
     agent_prompt: Option<&str>,
     custom_instructions: Option<&str>,
     environment: &str,
+    vars: &PromptVars,
 ) -> String {
     let mut parts = Vec::new();
 
@@ -350,14 +353,14 @@ pub fn build_system_prompt(
 
     // Add main prompt (agent-specific or provider-specific)
     if let Some(agent) = agent_prompt {
-        parts.push(agent.to_string());
+        parts.push(render_prompt(agent, vars));
     } else {
         parts.push(prompt_for_model(model).to_string());
     }
 
     // Add custom instructions
     if let Some(custom) = custom_instructions {
-        parts.push(custom.to_string());
+        parts.push(render_prompt(custom, vars));
     }
 
     // Add environment context
@@ -406,13 +409,14 @@ mod tests {
             "anthropic",
             "claude-3-sonnet",
             None,
-            Some("Custom instruction"),
+            Some("Custom instruction for {{project}}"),
             "<env>test</env>",
+            &PromptVars::default().with("project", "demo"),
         );
 
         assert!(prompt.contains("Claude Code"));
         assert!(prompt.contains("TodoWrite"));
-        assert!(prompt.contains("Custom instruction"));
+        assert!(prompt.contains("Custom instruction for demo"));
         assert!(prompt.contains("<env>test</env>"));
     }
 }
@@ -0,0 +1,355 @@
+//! Session templates.
+//!
+//! A template captures how a kind of session starts: extra system prompt
+//! instructions, files pinned into the prompt, the agent and model to use and
+//! starter todo phases. Templates are JSON files named after the template in
+//! `.wonopcode/templates/` (project) or `templates/` in the global config
+//! directory; project templates take precedence.
+//!
+//! ```json
+//! {
+//!   "description": "Review the current branch",
+//!   "system": "Focus on correctness and test coverage.",
+//!   "files": ["CONTRIBUTING.md"],
+//!   "agent": "plan",
+//!   "model": "anthropic/claude-sonnet-4-5",
+//!   "phases": [{ "name": "Review", "todos": ["Read the diff", "Run the tests"] }]
+//! }
+//! ```
+
+use crate::config::Config;
+use crate::error::{ConfigError, CoreError, CoreResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use wonopcode_tools::todo::{Phase, PhasedTodos, TodoItem, TodoPriority, TodoStatus};
+
+/// Template directory, relative to the project directory.
+pub const TEMPLATE_DIR: &str = ".wonopcode/templates";
+
+/// Maximum size of a pinned file included in the system prompt.
+pub const MAX_PINNED_FILE_CHARS: usize = 16_000;
+
+/// A session template.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionTemplate {
+    /// Template name (the file name without extension).
+    #[serde(default, skip_serializing)]
+    pub name: String,
+
+    /// Short description shown in the picker.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+
+    /// Instructions added to the system prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+
+    /// Files whose contents are pinned into the system prompt, relative to
+    /// the project directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+
+    /// Agent to start with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+
+    /// Model to start with (provider/model format).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+
+    /// Starter todo phases.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<TemplatePhase>,
+}
+
+/// A starter todo phase.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TemplatePhase {
+    /// Phase name.
+    pub name: String,
+    /// Todo descriptions.
+    #[serde(default)]
+    pub todos: Vec<String>,
+}
+
+impl SessionTemplate {
+    /// Create an empty template.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Use the phases of an existing todo list as starter phases.
+    ///
+    /// Only the todo descriptions are kept; every todo starts out pending.
+    pub fn with_todos(mut self, todos: &PhasedTodos) -> Self {
+        self.phases = todos
+            .phases
+            .iter()
+            .map(|phase| TemplatePhase {
+                name: phase.name.clone(),
+                todos: phase.todos.iter().map(|t| t.content.clone()).collect(),
+            })
+            .collect();
+        self
+    }
+
+    /// Starter phases as a todo list.
+    pub fn todos(&self) -> PhasedTodos {
+        let mut todos = PhasedTodos::new();
+        for (i, template_phase) in self.phases.iter().enumerate() {
+            let mut phase = Phase::new(format!("phase-{}", i + 1), &template_phase.name);
+            for (j, content) in template_phase.todos.iter().enumerate() {
+                phase.add_todo(TodoItem {
+                    id: format!("{}-{}", i + 1, j + 1),
+                    content: content.clone(),
+                    status: TodoStatus::Pending,
+                    priority: TodoPriority::Medium,
+                });
+            }
+            todos.add_phase(phase);
+        }
+        todos
+    }
+
+    /// Instructions and pinned files formatted for the system prompt, or
+    /// `None` if the template adds neither.
+    pub fn prompt_section(&self, project_dir: &Path) -> Option<String> {
+        let mut sections = Vec::new();
+        if let Some(system) = self.system.as_deref().map(str::trim) {
+            if !system.is_empty() {
+                sections.push(format!(
+                    "# Instructions from session template {}\n\n{system}",
+                    self.name
+                ));
+            }
+        }
+        for file in &self.files {
+            match std::fs::read_to_string(project_dir.join(file)) {
+                Ok(content) => {
+                    let content = match content.char_indices().nth(MAX_PINNED_FILE_CHARS) {
+                        Some((end, _)) => format!("{}\n\n[File truncated]", &content[..end]),
+                        None => content,
+                    };
+                    sections.push(format!("# Pinned file {file}\n\n{}", content.trim_end()));
+                }
+                Err(e) => warn!(file = %file, error = %e, "Failed to read pinned file"),
+            }
+        }
+        if sections.is_empty() {
+            None
+        } else {
+            Some(sections.join("\n\n"))
+        }
+    }
+}
+
+/// Reads and writes session templates.
+#[derive(Debug, Clone)]
+pub struct TemplateStore {
+    /// Project template directory, written by [`TemplateStore::save`].
+    project_dir: PathBuf,
+    /// Global template directory.
+    global_dir: Option<PathBuf>,
+}
+
+impl TemplateStore {
+    /// Templates of a project, plus the global ones.
+    pub fn new(project_dir: &Path) -> Self {
+        Self {
+            project_dir: project_dir.join(TEMPLATE_DIR),
+            global_dir: Config::global_config_dir().map(|dir| dir.join("templates")),
+        }
+    }
+
+    /// Templates from explicit directories (used in tests).
+    pub fn with_dirs(project_dir: PathBuf, global_dir: Option<PathBuf>) -> Self {
+        Self {
+            project_dir,
+            global_dir,
+        }
+    }
+
+    /// All templates, sorted by name.
+    ///
+    /// Invalid template files are logged and skipped.
+    pub fn list(&self) -> Vec<SessionTemplate> {
+        let mut templates: Vec<SessionTemplate> = Vec::new();
+        // Project templates first so they shadow global ones
+        for dir in std::iter::once(&self.project_dir).chain(self.global_dir.as_ref()) {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                match read_template(&path) {
+                    Ok(template) if !templates.iter().any(|t| t.name == template.name) => {
+                        templates.push(template);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "Skipping invalid template")
+                    }
+                }
+            }
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+
+    /// Load a template by name.
+    pub fn load(&self, name: &str) -> CoreResult<SessionTemplate> {
+        validate_name(name)?;
+        let file = format!("{name}.json");
+        for dir in std::iter::once(&self.project_dir).chain(self.global_dir.as_ref()) {
+            let path = dir.join(&file);
+            if path.exists() {
+                return read_template(&path);
+            }
+        }
+        Err(CoreError::TemplateNotFound(name.to_string()))
+    }
+
+    /// Save a template to the project template directory.
+    ///
+    /// Returns the path of the written file.
+    pub fn save(&self, template: &SessionTemplate) -> CoreResult<PathBuf> {
+        validate_name(&template.name)?;
+        std::fs::create_dir_all(&self.project_dir)?;
+        let path = self.project_dir.join(format!("{}.json", template.name));
+        let mut content = serde_json::to_string_pretty(template)?;
+        content.push('\n');
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+}
+
+/// Read a template file, naming it after the file.
+fn read_template(path: &Path) -> CoreResult<SessionTemplate> {
+    let content = std::fs::read_to_string(path)?;
+    let mut template: SessionTemplate =
+        serde_json::from_str(&content).map_err(|e| ConfigError::InvalidJson {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+    template.name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(template)
+}
+
+/// Template names become file names, so keep them simple.
+fn validate_name(name: &str) -> CoreResult<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::Validation {
+            message: format!(
+                "invalid template name '{name}': use letters, digits, '-' and '_' only"
+            ),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_list_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store =
+            TemplateStore::with_dirs(dir.path().join("project"), Some(dir.path().join("global")));
+        assert!(store.list().is_empty());
+        assert!(matches!(
+            store.load("code-review"),
+            Err(CoreError::TemplateNotFound(_))
+        ));
+
+        std::fs::create_dir_all(dir.path().join("global")).unwrap();
+        std::fs::write(
+            dir.path().join("global/code-review.json"),
+            r#"{"description": "Global", "agent": "plan"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("global/bugfix.json"),
+            r#"{"phases": [{"name": "Reproduce", "todos": ["Write a failing test"]}]}"#,
+        )
+        .unwrap();
+
+        let mut template = SessionTemplate::new("code-review");
+        template.description = "Project".to_string();
+        template.model = Some("openai/gpt-4o".to_string());
+        store.save(&template).unwrap();
+
+        // Project templates shadow global ones
+        let names: Vec<_> = store.list().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["bugfix", "code-review"]);
+        assert_eq!(store.load("code-review").unwrap(), template);
+        assert_eq!(store.load("bugfix").unwrap().phases[0].name, "Reproduce");
+
+        assert!(store.save(&SessionTemplate::new("../escape")).is_err());
+    }
+
+    #[test]
+    fn test_todos_round_trip() {
+        let template = SessionTemplate {
+            phases: vec![
+                TemplatePhase {
+                    name: "Investigate".to_string(),
+                    todos: vec!["Reproduce".to_string(), "Find cause".to_string()],
+                },
+                TemplatePhase {
+                    name: "Fix".to_string(),
+                    todos: vec!["Patch".to_string()],
+                },
+            ],
+            ..SessionTemplate::new("bugfix")
+        };
+
+        let todos = template.todos();
+        assert_eq!(todos.total_todos(), 3);
+        assert_eq!(todos.phases[1].todos[0].id, "2-1");
+        assert!(todos
+            .all_todos()
+            .iter()
+            .all(|t| t.status == TodoStatus::Pending));
+
+        let copy = SessionTemplate::new("copy").with_todos(&todos);
+        assert_eq!(copy.phases, template.phases);
+    }
+
+    #[test]
+    fn test_prompt_section() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("STYLE.md"), "Use tabs.\n").unwrap();
+
+        assert!(SessionTemplate::new("empty")
+            .prompt_section(dir.path())
+            .is_none());
+
+        let template = SessionTemplate {
+            system: Some("Be terse.".to_string()),
+            files: vec!["STYLE.md".to_string(), "missing.md".to_string()],
+            ..SessionTemplate::new("review")
+        };
+        assert_eq!(
+            template.prompt_section(dir.path()).unwrap(),
+            "# Instructions from session template review\n\nBe terse.\n\n\
+             # Pinned file STYLE.md\n\nUse tabs."
+        );
+    }
+}
@@ -0,0 +1,571 @@
+//! Git worktree management.
+//!
+//! Worktrees give an agent its own checkout on its own branch, so it can
+//! change files without touching the main checkout. They live in
+//! [`WORKTREE_DIR`] inside the repository (excluded from git status) so a
+//! sandbox mounting the project can reach them, on branches named
+//! `wonopcode/<name>`.
+//!
+//! When the agent is done, [`WorktreeManager::finish`] commits its changes and
+//! either merges them back, opens a pull request, or throws them away. Pull
+//! requests are opened with a [`PullRequestOpener`] for the repository's
+//! forge, or with the `gh` CLI if there is none.
+
+use crate::error::{ConfigError, CoreError, CoreResult};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Worktree directory, relative to the repository root.
+pub const WORKTREE_DIR: &str = ".wonopcode/worktrees";
+
+/// Prefix of worktree branch names.
+pub const BRANCH_PREFIX: &str = "wonopcode/";
+
+/// A worktree created for an agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Worktree {
+    /// Worktree name.
+    pub name: String,
+    /// Checkout directory.
+    pub path: PathBuf,
+    /// Branch checked out in the worktree.
+    pub branch: String,
+    /// Commit the branch started from.
+    pub base: String,
+}
+
+/// Outcome of merging a worktree branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorktreeMerge {
+    /// The branch was merged; lists the changed files.
+    Merged(Vec<String>),
+    /// The branch has no changes.
+    NothingToMerge,
+    /// The merge conflicted and was aborted; lists the conflicting files.
+    Conflict(Vec<String>),
+}
+
+/// How to finish working in a worktree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorktreeFinish {
+    /// Merge the branch into the main checkout.
+    Merge,
+    /// Push the branch and open a pull request.
+    PullRequest,
+    /// Delete the worktree and its branch.
+    Discard,
+}
+
+impl WorktreeFinish {
+    /// Parse "merge", "pr" or "discard".
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "merge" => Some(Self::Merge),
+            "pr" => Some(Self::PullRequest),
+            "discard" => Some(Self::Discard),
+            _ => None,
+        }
+    }
+
+    /// Name accepted by [`WorktreeFinish::parse`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::PullRequest => "pr",
+            Self::Discard => "discard",
+        }
+    }
+}
+
+/// Outcome of finishing a worktree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorktreeOutcome {
+    /// Merged or discarded; the worktree was removed.
+    Closed(WorktreeMerge),
+    /// A pull request was opened from the branch; the worktree was removed
+    /// and the branch kept.
+    PullRequest(String),
+    /// The merge conflicted; the worktree was kept.
+    Conflict(Vec<String>),
+}
+
+/// Opens pull requests for worktree branches on the repository's forge.
+#[async_trait]
+pub trait PullRequestOpener: Send + Sync {
+    /// Open a pull request for `branch`, already pushed to `origin`, and
+    /// return its URL.
+    async fn open_pull_request(
+        &self,
+        repo_root: &Path,
+        branch: &str,
+        title: &str,
+        body: &str,
+    ) -> CoreResult<String>;
+}
+
+/// Creates, merges and removes worktrees of a repository.
+#[derive(Clone)]
+pub struct WorktreeManager {
+    repo_root: PathBuf,
+    pull_requests: Option<Arc<dyn PullRequestOpener>>,
+}
+
+impl std::fmt::Debug for WorktreeManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WorktreeManager")
+            .field("repo_root", &self.repo_root)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WorktreeManager {
+    /// Manage worktrees of the repository checked out at `repo_root`.
+    pub fn new(repo_root: impl Into<PathBuf>) -> Self {
+        Self {
+            repo_root: repo_root.into(),
+            pull_requests: None,
+        }
+    }
+
+    /// Open pull requests with `opener` instead of the `gh` CLI.
+    pub fn with_pull_requests(mut self, opener: Option<Arc<dyn PullRequestOpener>>) -> Self {
+        self.pull_requests = opener;
+        self
+    }
+
+    /// Repository root.
+    pub fn repo_root(&self) -> &Path {
+        &self.repo_root
+    }
+
+    /// Create a worktree on a new branch starting at the current `HEAD`.
+    pub async fn create(&self, name: &str) -> CoreResult<Worktree> {
+        validate_name(name)?;
+        let base = git(&self.repo_root, &["rev-parse", "HEAD"]).await?;
+        let path = self.repo_root.join(WORKTREE_DIR).join(name);
+        let branch = format!("{BRANCH_PREFIX}{name}");
+        self.exclude_worktree_dir().await?;
+
+        let path_arg = path.to_string_lossy();
+        git(
+            &self.repo_root,
+            &["worktree", "add", "-b", &branch, &path_arg, &base],
+        )
+        .await?;
+        info!(name, branch = %branch, "Created worktree");
+
+        Ok(Worktree {
+            name: name.to_string(),
+            path,
+            branch,
+            base,
+        })
+    }
+
+    /// Worktrees created by wonopcode, sorted by name.
+    pub async fn list(&self) -> CoreResult<Vec<Worktree>> {
+        let output = git(&self.repo_root, &["worktree", "list", "--porcelain"]).await?;
+        let mut worktrees = Vec::new();
+        // Entries are blocks of "key value" lines separated by blank lines
+        for block in output.split("\n\n") {
+            let mut path = None;
+            let mut branch = None;
+            for line in block.lines() {
+                if let Some(p) = line.strip_prefix("worktree ") {
+                    path = Some(PathBuf::from(p));
+                } else if let Some(b) = line.strip_prefix("branch refs/heads/") {
+                    branch = Some(b.to_string());
+                }
+            }
+            let (Some(path), Some(branch)) = (path, branch) else {
+                continue;
+            };
+            let Some(name) = branch.strip_prefix(BRANCH_PREFIX) else {
+                continue;
+            };
+            let base = git(&self.repo_root, &["merge-base", "HEAD", &branch]).await?;
+            worktrees.push(Worktree {
+                name: name.to_string(),
+                path,
+                branch,
+                base,
+            });
+        }
+        worktrees.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(worktrees)
+    }
+
+    /// Commit all changes in a worktree.
+    ///
+    /// Returns `false` if there was nothing to commit.
+    pub async fn commit_all(&self, worktree: &Worktree, message: &str) -> CoreResult<bool> {
+        git(&worktree.path, &["add", "-A"]).await?;
+        if git(&worktree.path, &["status", "--porcelain"])
+            .await?
+            .is_empty()
+        {
+            return Ok(false);
+        }
+        git(&worktree.path, &["commit", "-q", "-m", message]).await?;
+        Ok(true)
+    }
+
+    /// Files changed on a worktree's branch since it was created.
+    pub async fn changed_files(&self, worktree: &Worktree) -> CoreResult<Vec<String>> {
+        let output = git(
+            &self.repo_root,
+            &["diff", "--name-only", &worktree.base, &worktree.branch],
+        )
+        .await?;
+        Ok(output.lines().map(str::to_string).collect())
+    }
+
+    /// Merge a worktree's branch into the branch checked out in the main
+    /// checkout.
+    ///
+    /// A conflicting merge is aborted, leaving the main checkout unchanged.
+    pub async fn merge(&self, worktree: &Worktree) -> CoreResult<WorktreeMerge> {
+        let files = self.changed_files(worktree).await?;
+        if files.is_empty() {
+            return Ok(WorktreeMerge::NothingToMerge);
+        }
+
+        let message = format!("Merge {}", worktree.branch);
+        match git(
+            &self.repo_root,
+            &["merge", "--no-ff", "-m", &message, &worktree.branch],
+        )
+        .await
+        {
+            Ok(_) => Ok(WorktreeMerge::Merged(files)),
+            Err(e) => {
+                let conflicts =
+                    git(&self.repo_root, &["diff", "--name-only", "--diff-filter=U"]).await?;
+                if conflicts.is_empty() {
+                    // Failed before merging, e.g. local changes in the way
+                    return Err(e);
+                }
+                git(&self.repo_root, &["merge", "--abort"]).await?;
+                Ok(WorktreeMerge::Conflict(
+                    conflicts.lines().map(str::to_string).collect(),
+                ))
+            }
+        }
+    }
+
+    /// Remove a worktree, optionally deleting its branch.
+    pub async fn remove(&self, worktree: &Worktree, delete_branch: bool) -> CoreResult<()> {
+        let path_arg = worktree.path.to_string_lossy();
+        git(
+            &self.repo_root,
+            &["worktree", "remove", "--force", &path_arg],
+        )
+        .await?;
+        if delete_branch {
+            git(&self.repo_root, &["branch", "-D", &worktree.branch]).await?;
+        }
+        info!(name = %worktree.name, "Removed worktree");
+        Ok(())
+    }
+
+    /// Commit the worktree's changes, then merge them, open a pull request or
+    /// discard them.
+    ///
+    /// `title` is used for the commit message and the pull request.
+    pub async fn finish(
+        &self,
+        worktree: &Worktree,
+        finish: WorktreeFinish,
+        title: &str,
+    ) -> CoreResult<WorktreeOutcome> {
+        if finish == WorktreeFinish::Discard {
+            self.remove(worktree, true).await?;
+            return Ok(WorktreeOutcome::Closed(WorktreeMerge::NothingToMerge));
+        }
+
+        self.commit_all(worktree, title).await?;
+        match finish {
+            WorktreeFinish::PullRequest => {
+                if self.changed_files(worktree).await?.is_empty() {
+                    self.remove(worktree, true).await?;
+                    return Ok(WorktreeOutcome::Closed(WorktreeMerge::NothingToMerge));
+                }
+                let url = self.open_pull_request(worktree, title).await?;
+                self.remove(worktree, false).await?;
+                Ok(WorktreeOutcome::PullRequest(url))
+            }
+            _ => match self.merge(worktree).await? {
+                WorktreeMerge::Conflict(files) => Ok(WorktreeOutcome::Conflict(files)),
+                merge => {
+                    self.remove(worktree, true).await?;
+                    Ok(WorktreeOutcome::Closed(merge))
+                }
+            },
+        }
+    }
+
+    /// Push a worktree's branch to `origin` and open a pull request for it.
+    ///
+    /// Returns the pull request URL.
+    async fn open_pull_request(&self, worktree: &Worktree, title: &str) -> CoreResult<String> {
+        git(&worktree.path, &["push", "-u", "origin", &worktree.branch]).await?;
+        let body = format!("Changes made by wonopcode on branch `{}`.", worktree.branch);
+        let url = match &self.pull_requests {
+            Some(opener) => {
+                opener
+                    .open_pull_request(&self.repo_root, &worktree.branch, title, &body)
+                    .await?
+            }
+            None => gh_pr_create(worktree, title, &body).await?,
+        };
+        info!(branch = %worktree.branch, url = %url, "Opened pull request");
+        Ok(url)
+    }
+
+    /// Keep worktrees out of the main checkout's `git status`.
+    async fn exclude_worktree_dir(&self) -> CoreResult<()> {
+        let common_dir = git(&self.repo_root, &["rev-parse", "--git-common-dir"]).await?;
+        let exclude = self.repo_root.join(common_dir).join("info").join("exclude");
+        let pattern = format!("/{WORKTREE_DIR}/");
+        let content = tokio::fs::read_to_string(&exclude)
+            .await
+            .unwrap_or_default();
+        if content.lines().any(|line| line.trim() == pattern) {
+            return Ok(());
+        }
+
+        if let Some(parent) = exclude.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut content = content;
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&pattern);
+        content.push('\n');
+        tokio::fs::write(&exclude, content).await?;
+        Ok(())
+    }
+}
+
+/// Open a pull request for a worktree's branch with the `gh` CLI.
+async fn gh_pr_create(worktree: &Worktree, title: &str, body: &str) -> CoreResult<String> {
+    let output = tokio::process::Command::new("gh")
+        .args(["pr", "create", "--head", &worktree.branch, "--title", title])
+        .args(["--body", body])
+        .current_dir(&worktree.path)
+        .output()
+        .await
+        .map_err(|e| CoreError::Git(format!("failed to run gh: {e}")))?;
+    if !output.status.success() {
+        return Err(CoreError::Git(format!(
+            "gh pr create failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Run git in a directory and return its trimmed output.
+async fn git(dir: &Path, args: &[&str]) -> CoreResult<String> {
+    debug!(dir = %dir.display(), ?args, "Running git");
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(CoreError::Git(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Worktree names become directory and branch names, so keep them simple.
+fn validate_name(name: &str) -> CoreResult<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(ConfigError::Validation {
+            message: format!(
+                "invalid worktree name '{name}': use letters, digits, '-' and '_' only"
+            ),
+        }
+        .into())
+    }
+}
+
+/// Turn free text into a worktree name, e.g. "Fix the tokenizer!" into
+/// "fix-the-tokenizer".
+pub fn slugify(text: &str, max_len: usize) -> String {
+    let mut slug = String::new();
+    for word in text
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        if !slug.is_empty() {
+            if slug.len() + 1 + word.len() > max_len {
+                break;
+            }
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    slug.truncate(max_len);
+    slug
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Run git in a test repository, panicking on failure.
+    pub(crate) fn git_sync(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// A repository with one commit containing `README.md`.
+    pub(crate) fn init_repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        git_sync(dir.path(), &["init", "-q", "-b", "main"]);
+        git_sync(dir.path(), &["config", "user.email", "test@test.com"]);
+        git_sync(dir.path(), &["config", "user.name", "Test"]);
+        std::fs::write(dir.path().join("README.md"), "hello\n").unwrap();
+        git_sync(dir.path(), &["add", "."]);
+        git_sync(dir.path(), &["commit", "-q", "-m", "Initial commit"]);
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_create_commit_and_merge() {
+        let repo = init_repo();
+        let manager = WorktreeManager::new(repo.path());
+        let worktree = manager.create("add-notes").await.unwrap();
+        assert_eq!(worktree.branch, "wonopcode/add-notes");
+        assert!(worktree.path.join("README.md").exists());
+        // The worktree directory doesn't show up in the main checkout
+        assert_eq!(git_sync(repo.path(), &["status", "--porcelain"]), "");
+
+        assert!(!manager.commit_all(&worktree, "Nothing").await.unwrap());
+        assert_eq!(
+            manager.merge(&worktree).await.unwrap(),
+            WorktreeMerge::NothingToMerge
+        );
+
+        std::fs::write(worktree.path.join("NOTES.md"), "notes\n").unwrap();
+        assert!(manager.commit_all(&worktree, "Add notes").await.unwrap());
+        assert_eq!(
+            manager.merge(&worktree).await.unwrap(),
+            WorktreeMerge::Merged(vec!["NOTES.md".to_string()])
+        );
+        assert!(repo.path().join("NOTES.md").exists());
+
+        manager.remove(&worktree, true).await.unwrap();
+        assert!(!worktree.path.exists());
+        assert!(manager.create("../escape").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_conflicting_merge_is_aborted() {
+        let repo = init_repo();
+        let manager = WorktreeManager::new(repo.path());
+        let first = manager.create("first").await.unwrap();
+        let second = manager.create("second").await.unwrap();
+        for (worktree, content) in [(&first, "one\n"), (&second, "two\n")] {
+            std::fs::write(worktree.path.join("README.md"), content).unwrap();
+            manager.commit_all(worktree, "Edit readme").await.unwrap();
+        }
+
+        assert!(matches!(
+            manager.merge(&first).await.unwrap(),
+            WorktreeMerge::Merged(_)
+        ));
+        assert_eq!(
+            manager.merge(&second).await.unwrap(),
+            WorktreeMerge::Conflict(vec!["README.md".to_string()])
+        );
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("README.md")).unwrap(),
+            "one\n"
+        );
+        assert_eq!(git_sync(repo.path(), &["status", "--porcelain"]), "");
+    }
+
+    #[tokio::test]
+    async fn test_list_and_finish() {
+        let repo = init_repo();
+        let manager = WorktreeManager::new(repo.path());
+        let keep = manager.create("keep").await.unwrap();
+        let drop = manager.create("drop").await.unwrap();
+        let names: Vec<_> = manager
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|w| w.name)
+            .collect();
+        assert_eq!(names, vec!["drop", "keep"]);
+
+        std::fs::write(drop.path.join("scratch.txt"), "scratch\n").unwrap();
+        assert_eq!(
+            manager
+                .finish(&drop, WorktreeFinish::Discard, "Scratch")
+                .await
+                .unwrap(),
+            WorktreeOutcome::Closed(WorktreeMerge::NothingToMerge)
+        );
+        assert!(!repo.path().join("scratch.txt").exists());
+
+        // Uncommitted changes are committed before merging
+        std::fs::write(keep.path.join("kept.txt"), "kept\n").unwrap();
+        assert_eq!(
+            manager
+                .finish(&keep, WorktreeFinish::Merge, "Keep this")
+                .await
+                .unwrap(),
+            WorktreeOutcome::Closed(WorktreeMerge::Merged(vec!["kept.txt".to_string()]))
+        );
+        assert!(repo.path().join("kept.txt").exists());
+        assert!(manager.list().await.unwrap().is_empty());
+        assert_eq!(
+            git_sync(repo.path(), &["branch", "--list", "wonopcode/*"]),
+            ""
+        );
+        assert_eq!(
+            WorktreeFinish::parse("pr"),
+            Some(WorktreeFinish::PullRequest)
+        );
+        assert_eq!(WorktreeFinish::parse("squash"), None);
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Fix the tokenizer!", 40), "fix-the-tokenizer");
+        assert_eq!(
+            slugify("Add tests for the parser module", 16),
+            "add-tests-for"
+        );
+        assert_eq!(slugify("???", 10), "");
+    }
+}
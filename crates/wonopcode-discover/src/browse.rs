@@ -2,8 +2,11 @@
 
 use std::collections::HashMap;
 use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use tracing::{debug, info, trace, warn};
 use zeroconf::prelude::*;
 use zeroconf::{BrowserEvent, MdnsBrowser, ServiceType};
@@ -11,6 +14,70 @@ use zeroconf::{BrowserEvent, MdnsBrowser, ServiceType};
 use crate::error::DiscoverError;
 use crate::service::ServerInfo;
 
+/// How long the browse thread waits for mDNS traffic before checking
+/// whether it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long to wait for a server to accept a connection when checking
+/// which of its addresses are reachable.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A change in the servers on the local network.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscoveryEvent {
+    /// A server appeared.
+    Added(ServerInfo),
+    /// A known server announced itself again with a different address or
+    /// details, e.g. after a restart.
+    Updated(ServerInfo),
+    /// The server with this name went away.
+    Removed(String),
+}
+
+/// Live discovery started by [`Browser::watch`].
+///
+/// Yields [`DiscoveryEvent`]s as servers come and go, for as long as it is
+/// kept. Browsing stops when the watcher is dropped. Addresses aren't
+/// checked; use [`ServerInfo::prefer_reachable`] before connecting.
+pub struct Watcher {
+    events: mpsc::Receiver<DiscoveryEvent>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Wait up to `timeout` for the next change.
+    ///
+    /// Fails with [`RecvTimeoutError::Timeout`] if nothing changed, and with
+    /// [`RecvTimeoutError::Disconnected`] once browsing has stopped.
+    pub fn next_timeout(&self, timeout: Duration) -> Result<DiscoveryEvent, RecvTimeoutError> {
+        self.events.recv_timeout(timeout)
+    }
+
+    /// The next change if there is one, without waiting.
+    pub fn try_next(&self) -> Option<DiscoveryEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = DiscoveryEvent;
+
+    /// Block until the next change; None once browsing has stopped.
+    fn next(&mut self) -> Option<DiscoveryEvent> {
+        self.events.recv().ok()
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
 /// Browses for wonopcode servers on the local network via mDNS.
 pub struct Browser {
     // Browser doesn't hold state - created fresh for each browse operation
@@ -22,6 +89,44 @@ impl Browser {
         Ok(Self {})
     }
 
+    /// Watch for servers coming and going.
+    ///
+    /// Browsing runs on its own thread until the returned [`Watcher`] is
+    /// dropped. Servers already on the network arrive as
+    /// [`DiscoveryEvent::Added`] first.
+    pub fn watch(&self) -> Result<Watcher, DiscoverError> {
+        debug!("Watching for wonopcode servers");
+
+        let (events_tx, events) = mpsc::channel();
+        let (started_tx, started) = mpsc::sync_channel(1);
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // The mDNS browser isn't Send, so it is created on the thread it runs on
+        let thread_stop = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("wonopcode-discover".to_string())
+            .spawn(move || watch_loop(events_tx, started_tx, thread_stop))
+            .map_err(|e| DiscoverError::ServiceInfo(e.to_string()))?;
+
+        match started.recv() {
+            Ok(Ok(())) => Ok(Watcher {
+                events,
+                stop,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err(DiscoverError::ServiceInfo(
+                    "browse thread exited".to_string(),
+                ))
+            }
+        }
+    }
+
     /// Browse for servers with a timeout.
     ///
     /// Returns a list of discovered servers after the timeout expires.
@@ -35,36 +140,30 @@ impl Browser {
             "Browsing for wonopcode servers"
         );
 
-        let service_type = ServiceType::new("wonopcode", "tcp")
-            .map_err(|e| DiscoverError::ServiceInfo(e.to_string()))?;
-
-        let mut browser = MdnsBrowser::new(service_type);
-
-        // Shared state for collecting discovered services
-        let servers: Arc<Mutex<HashMap<String, ServerInfo>>> = Arc::new(Mutex::new(HashMap::new()));
-        let servers_clone = servers.clone();
-
-        browser.set_service_callback(Box::new(move |result, _context| {
-            on_service_event(result, servers_clone.clone());
-        }));
-
-        let event_loop = browser
-            .browse_services()
-            .map_err(|e| DiscoverError::ServiceInfo(e.to_string()))?;
-
-        // Poll until timeout
-        let deadline = std::time::Instant::now() + timeout;
-        while std::time::Instant::now() < deadline {
-            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
-            let poll_time = remaining.min(Duration::from_millis(100));
+        let watcher = self.watch()?;
+        let mut servers: HashMap<String, ServerInfo> = HashMap::new();
 
-            event_loop
-                .poll(poll_time)
-                .map_err(|e| DiscoverError::ServiceInfo(e.to_string()))?;
+        let deadline = Instant::now() + timeout;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match watcher.next_timeout(remaining) {
+                Ok(DiscoveryEvent::Added(server)) | Ok(DiscoveryEvent::Updated(server)) => {
+                    servers.insert(server.name.clone(), server);
+                }
+                Ok(DiscoveryEvent::Removed(name)) => {
+                    servers.remove(&name);
+                }
+                Err(_) => break,
+            }
         }
 
-        // Extract results
-        let result: Vec<ServerInfo> = servers.lock().unwrap().values().cloned().collect();
+        // Prefer addresses that answer over, e.g., IPv6 or VPN addresses the
+        // server doesn't listen on
+        let mut result: Vec<ServerInfo> = servers.into_values().collect();
+        for server in &mut result {
+            if !server.prefer_reachable(PROBE_TIMEOUT) {
+                debug!(name = %server.name, "Server did not answer on any address");
+            }
+        }
 
         info!(count = result.len(), "Browse completed");
 
@@ -80,49 +179,110 @@ impl Browser {
             "Browsing for first wonopcode server"
         );
 
-        let service_type = ServiceType::new("wonopcode", "tcp")
-            .map_err(|e| DiscoverError::ServiceInfo(e.to_string()))?;
+        let watcher = self.watch()?;
+
+        let deadline = Instant::now() + timeout;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match watcher.next_timeout(remaining) {
+                Ok(DiscoveryEvent::Added(mut server)) => {
+                    server.prefer_reachable(PROBE_TIMEOUT);
+                    return Ok(Some(server));
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
 
-        let mut browser = MdnsBrowser::new(service_type);
+        Ok(None)
+    }
+}
 
-        // Shared state for collecting discovered services
-        let servers: Arc<Mutex<HashMap<String, ServerInfo>>> = Arc::new(Mutex::new(HashMap::new()));
-        let servers_clone = servers.clone();
+/// Run the mDNS browser until `stop` is set or nobody listens to `events`.
+fn watch_loop(
+    events: mpsc::Sender<DiscoveryEvent>,
+    started: mpsc::SyncSender<Result<(), DiscoverError>>,
+    stop: Arc<AtomicBool>,
+) {
+    let service_type = match ServiceType::new("wonopcode", "tcp") {
+        Ok(service_type) => service_type,
+        Err(e) => {
+            let _ = started.send(Err(DiscoverError::ServiceInfo(e.to_string())));
+            return;
+        }
+    };
 
-        browser.set_service_callback(Box::new(move |result, _context| {
-            on_service_event(result, servers_clone.clone());
-        }));
+    let mut browser = MdnsBrowser::new(service_type);
 
-        let event_loop = browser
-            .browse_services()
-            .map_err(|e| DiscoverError::ServiceInfo(e.to_string()))?;
+    let known = Mutex::new(ServerSet::default());
+    let callback_stop = stop.clone();
+    browser.set_service_callback(Box::new(move |result, _context| {
+        let Some(event) = on_service_event(result, &known) else {
+            return;
+        };
+        if events.send(event).is_err() {
+            // The watcher is gone
+            callback_stop.store(true, Ordering::Relaxed);
+        }
+    }));
+
+    let event_loop = match browser.browse_services() {
+        Ok(event_loop) => event_loop,
+        Err(e) => {
+            let _ = started.send(Err(DiscoverError::ServiceInfo(e.to_string())));
+            return;
+        }
+    };
+    let _ = started.send(Ok(()));
+
+    while !stop.load(Ordering::Relaxed) {
+        if let Err(e) = event_loop.poll(POLL_INTERVAL) {
+            warn!(error = %e, "mDNS browsing failed");
+            break;
+        }
+    }
+
+    debug!("Stopped watching for wonopcode servers");
+}
+
+/// The servers seen so far, turning mDNS announcements into changes.
+#[derive(Debug, Default)]
+struct ServerSet {
+    servers: HashMap<String, ServerInfo>,
+}
+
+impl ServerSet {
+    /// Record an announced server. None if nothing about it changed.
+    ///
+    /// A server is announced once for each interface and protocol it is on;
+    /// the same details at another address add to its addresses.
+    fn add(&mut self, server: ServerInfo) -> Option<DiscoveryEvent> {
+        let Some(known) = self.servers.get_mut(&server.name) else {
+            self.servers.insert(server.name.clone(), server.clone());
+            return Some(DiscoveryEvent::Added(server));
+        };
 
-        // Poll until timeout or we find a server
-        let deadline = std::time::Instant::now() + timeout;
-        while std::time::Instant::now() < deadline {
-            let remaining = deadline.saturating_duration_since(std::time::Instant::now());
-            let poll_time = remaining.min(Duration::from_millis(100));
-
-            event_loop
-                .poll(poll_time)
-                .map_err(|e| DiscoverError::ServiceInfo(e.to_string()))?;
-
-            // Check if we found a server
-            let guard = servers.lock().unwrap();
-            if !guard.is_empty() {
-                let first = guard.values().next().cloned();
-                return Ok(first);
+        if known.same_service(&server) {
+            if !known.merge_addresses(&server.addresses) {
+                return None;
             }
+        } else {
+            *known = server;
         }
+        Some(DiscoveryEvent::Updated(known.clone()))
+    }
 
-        Ok(None)
+    /// Forget a server. None if it was never seen.
+    fn remove(&mut self, name: &str) -> Option<DiscoveryEvent> {
+        self.servers
+            .remove(name)
+            .map(|_| DiscoveryEvent::Removed(name.to_string()))
     }
 }
 
 fn on_service_event(
     result: zeroconf::Result<BrowserEvent>,
-    servers: Arc<Mutex<HashMap<String, ServerInfo>>>,
-) {
+    known: &Mutex<ServerSet>,
+) -> Option<DiscoveryEvent> {
     match result {
         Ok(BrowserEvent::Add(discovery)) => {
             trace!(
@@ -133,27 +293,24 @@ fn on_service_event(
             );
 
             // Parse the discovery into a ServerInfo
-            if let Some(server_info) = parse_discovery(&discovery) {
-                debug!(
-                    name = %server_info.name,
-                    address = %server_info.address,
-                    "Found server"
-                );
-                servers
-                    .lock()
-                    .unwrap()
-                    .insert(discovery.name().to_string(), server_info);
-            }
+            let server_info = parse_discovery(&discovery)?;
+            debug!(
+                name = %server_info.name,
+                address = %server_info.address,
+                "Found server"
+            );
+            known.lock().unwrap().add(server_info)
         }
         Ok(BrowserEvent::Remove(removal)) => {
             debug!(
                 name = %removal.name(),
                 "Service removed"
             );
-            servers.lock().unwrap().remove(removal.name());
+            known.lock().unwrap().remove(removal.name())
         }
         Err(e) => {
             warn!(error = %e, "Service discovery error");
+            None
         }
     }
 }
@@ -215,6 +372,7 @@ fn parse_discovery(discovery: &zeroconf::ServiceDiscovery) -> Option<ServerInfo>
     Some(ServerInfo {
         name,
         address,
+        addresses: vec![address],
         hostname,
         version,
         model,
@@ -250,6 +408,7 @@ pub fn build_server_info(
     ServerInfo {
         name,
         address,
+        addresses: vec![address],
         hostname,
         version,
         model,
@@ -359,6 +518,7 @@ pub fn build_complete_server_info(
     Some(ServerInfo {
         name,
         address,
+        addresses: vec![address],
         hostname,
         version,
         model,
@@ -421,6 +581,7 @@ mod tests {
         let info = ServerInfo {
             name: "TestServer".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: Some("test.local".to_string()),
             version: Some("1.0.0".to_string()),
             model: Some("claude-3".to_string()),
@@ -446,6 +607,7 @@ mod tests {
         let info = ServerInfo {
             name: "MinimalServer".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -466,6 +628,7 @@ mod tests {
         let original = ServerInfo {
             name: "CloneTest".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: Some("clone.local".to_string()),
             version: Some("2.0.0".to_string()),
             model: Some("gpt-4".to_string()),
@@ -495,6 +658,7 @@ mod tests {
         let info1 = ServerInfo {
             name: "Server1".to_string(),
             address: addr1,
+            addresses: vec![addr1],
             hostname: None,
             version: None,
             model: None,
@@ -507,6 +671,7 @@ mod tests {
         let info2 = ServerInfo {
             name: "Server2".to_string(),
             address: addr2,
+            addresses: vec![addr2],
             hostname: None,
             version: None,
             model: None,
@@ -532,6 +697,7 @@ mod tests {
         let info = ServerInfo {
             name: "ToRemove".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -557,6 +723,7 @@ mod tests {
         let info = ServerInfo {
             name: "IPv6Server".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -736,6 +903,7 @@ mod tests {
         let info = ServerInfo {
             name: "TestServer".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -760,6 +928,7 @@ mod tests {
         let info1 = ServerInfo {
             name: "TestServer".to_string(),
             address: addr1,
+            addresses: vec![addr1],
             hostname: None,
             version: Some("1.0".to_string()),
             model: None,
@@ -771,6 +940,7 @@ mod tests {
         let info2 = ServerInfo {
             name: "TestServer".to_string(),
             address: addr2,
+            addresses: vec![addr2],
             hostname: None,
             version: Some("2.0".to_string()),
             model: None,
@@ -796,6 +966,7 @@ mod tests {
         let info = ServerInfo {
             name: "ToRemove".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -834,6 +1005,7 @@ mod tests {
             let info = ServerInfo {
                 name: format!("Server{i}"),
                 address: addr,
+                addresses: vec![addr],
                 hostname: None,
                 version: None,
                 model: None,
@@ -1015,6 +1187,7 @@ mod tests {
         let info = ServerInfo {
             name: "Minimal".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -1035,6 +1208,7 @@ mod tests {
         let info = ServerInfo {
             name: "PortTest".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -1088,6 +1262,7 @@ mod tests {
                     let info = ServerInfo {
                         name: format!("Server{i}"),
                         address: addr,
+                        addresses: vec![addr],
                         hostname: None,
                         version: None,
                         model: None,
@@ -1326,4 +1501,48 @@ mod tests {
         assert!(fields.project.is_none());
         assert!(!fields.auth_required);
     }
+
+    #[test]
+    fn test_server_set_changes() {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), 3000);
+        let server = build_server_info("box".to_string(), addr, None, None);
+        let mut known = ServerSet::default();
+
+        assert_eq!(
+            known.add(server.clone()),
+            Some(DiscoveryEvent::Added(server.clone()))
+        );
+        // The same announcement again changes nothing
+        assert_eq!(known.add(server.clone()), None);
+
+        // Announced on another interface as well
+        let vpn_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 8, 0, 2)), 3000);
+        let vpn = build_server_info("box".to_string(), vpn_addr, None, None);
+        match known.add(vpn.clone()) {
+            Some(DiscoveryEvent::Updated(updated)) => {
+                assert_eq!(updated.address, addr);
+                assert_eq!(updated.addresses, vec![addr, vpn_addr]);
+            }
+            other => panic!("expected an update, got {other:?}"),
+        }
+        assert_eq!(known.add(vpn), None);
+
+        // Back up on another port: the old addresses are stale
+        let restarted_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), 3001);
+        let restarted = build_server_info("box".to_string(), restarted_addr, None, None);
+        assert_eq!(
+            known.add(restarted.clone()),
+            Some(DiscoveryEvent::Updated(restarted))
+        );
+
+        assert_eq!(
+            known.remove("box"),
+            Some(DiscoveryEvent::Removed("box".to_string()))
+        );
+        assert_eq!(known.remove("box"), None);
+        assert_eq!(
+            known.add(server.clone()),
+            Some(DiscoveryEvent::Added(server))
+        );
+    }
 }
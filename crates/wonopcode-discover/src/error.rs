@@ -13,6 +13,10 @@ pub enum DiscoverError {
     #[error("Service info error: {0}")]
     ServiceInfo(String),
 
+    /// Error asking Tailscale for the tailnet's peers.
+    #[error("Tailscale error: {0}")]
+    Tailscale(String),
+
     /// No servers found.
     #[error("No servers found on the local network")]
     NoServersFound,
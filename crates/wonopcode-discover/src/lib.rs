@@ -2,7 +2,7 @@
 //!
 //! This crate provides functionality for advertising and discovering wonopcode
 //! servers on the local network using mDNS (Multicast DNS) and DNS-SD
-//! (DNS-based Service Discovery).
+//! (DNS-based Service Discovery), and on the machines of a Tailscale network.
 //!
 //! # Service Type
 //!
@@ -38,13 +38,33 @@
 //!     println!("Found: {} at {}", server.name, server.address);
 //! }
 //! ```
+//!
+//! # Example: Watching for Changes
+//!
+//! ```no_run
+//! use wonopcode_discover::{Browser, DiscoveryEvent};
+//!
+//! let browser = Browser::new().expect("Failed to create browser");
+//! let watcher = browser.watch().expect("Failed to browse");
+//!
+//! // Runs until the watcher is dropped
+//! for event in watcher {
+//!     match event {
+//!         DiscoveryEvent::Added(server) => println!("Up: {server}"),
+//!         DiscoveryEvent::Updated(server) => println!("Changed: {server}"),
+//!         DiscoveryEvent::Removed(name) => println!("Gone: {name}"),
+//!     }
+//! }
+//! ```
 
 mod advertise;
 mod browse;
 mod error;
 mod service;
+mod tailscale;
 
 pub use advertise::Advertiser;
-pub use browse::Browser;
+pub use browse::{Browser, DiscoveryEvent, Watcher};
 pub use error::DiscoverError;
 pub use service::{AdvertiseConfig, ServerInfo, SERVICE_TYPE};
+pub use tailscale::tailscale_servers;
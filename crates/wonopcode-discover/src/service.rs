@@ -1,6 +1,8 @@
 //! Service information types.
 
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::mpsc;
+use std::time::Duration;
 
 /// The mDNS service type for wonopcode servers.
 pub const SERVICE_TYPE: &str = "_wonopcode._tcp.local.";
@@ -8,7 +10,7 @@ pub const SERVICE_TYPE: &str = "_wonopcode._tcp.local.";
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::net::{IpAddr, Ipv4Addr};
+    use std::net::Ipv4Addr;
 
     #[test]
     fn test_service_type_constant() {
@@ -21,6 +23,7 @@ mod tests {
         let info = ServerInfo {
             name: "TestServer".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -38,6 +41,7 @@ mod tests {
         let info = ServerInfo {
             name: "TestServer".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -55,6 +59,7 @@ mod tests {
         let info = ServerInfo {
             name: "TestServer".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -72,6 +77,7 @@ mod tests {
         let info = ServerInfo {
             name: "TestServer".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -89,6 +95,7 @@ mod tests {
         let info = ServerInfo {
             name: "Original".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: Some("host.local".to_string()),
             version: Some("1.0.0".to_string()),
             model: Some("claude-3".to_string()),
@@ -113,6 +120,7 @@ mod tests {
         let info = ServerInfo {
             name: "Test".to_string(),
             address: addr,
+            addresses: vec![addr],
             hostname: None,
             version: None,
             model: None,
@@ -125,6 +133,59 @@ mod tests {
         assert!(debug_str.contains("Test"));
     }
 
+    #[test]
+    fn test_merge_addresses_best_first() {
+        let v4 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 5)), 3000);
+        let v6 = SocketAddr::new(IpAddr::V6("2001:db8::5".parse().unwrap()), 3000);
+        let link_local: SocketAddr = "[fe80::5]:3000".parse().unwrap();
+        let mut info = ServerInfo {
+            name: "Server".to_string(),
+            address: link_local,
+            addresses: vec![link_local],
+            hostname: None,
+            version: None,
+            model: None,
+            project: None,
+            cwd: None,
+            auth_required: false,
+        };
+
+        assert!(info.merge_addresses(&[v6, v4]));
+        assert_eq!(info.addresses, vec![v4, v6, link_local]);
+        assert_eq!(info.address, v4);
+        assert!(!info.merge_addresses(&[v6]));
+    }
+
+    #[test]
+    fn test_prefer_reachable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        let closed = {
+            let unused = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            unused.local_addr().unwrap()
+        };
+        let mut info = ServerInfo {
+            name: "Server".to_string(),
+            address: closed,
+            addresses: vec![closed, open],
+            hostname: None,
+            version: None,
+            model: None,
+            project: None,
+            cwd: None,
+            auth_required: false,
+        };
+
+        assert!(info.prefer_reachable(Duration::from_millis(500)));
+        assert_eq!(info.address, open);
+        assert_eq!(info.addresses, vec![open, closed]);
+
+        info.addresses = vec![closed];
+        info.address = closed;
+        assert!(!info.prefer_reachable(Duration::from_millis(500)));
+        assert_eq!(info.address, closed);
+    }
+
     #[test]
     fn test_advertise_config_new() {
         let config = AdvertiseConfig::new("MyServer", 8080, "1.0.0");
@@ -220,12 +281,15 @@ mod tests {
 }
 
 /// Information about a discovered wonopcode server.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ServerInfo {
     /// Display name of the server instance.
     pub name: String,
-    /// Socket address (IP:port) of the server.
+    /// Socket address (IP:port) of the server: the best of `addresses`.
     pub address: SocketAddr,
+    /// Every address the server was found at, best first. A server on
+    /// several interfaces, or on both IPv4 and IPv6, is found at several.
+    pub addresses: Vec<SocketAddr>,
     /// Hostname of the server (e.g., "machine.local").
     pub hostname: Option<String>,
     /// Wonopcode version.
@@ -240,6 +304,92 @@ pub struct ServerInfo {
     pub auth_required: bool,
 }
 
+impl ServerInfo {
+    /// Add addresses the server was also found at, keeping the best first.
+    /// Returns whether any were new.
+    pub fn merge_addresses(&mut self, addresses: &[SocketAddr]) -> bool {
+        let before = self.addresses.len();
+        for address in addresses {
+            if !self.addresses.contains(address) {
+                self.addresses.push(*address);
+            }
+        }
+        if self.addresses.len() == before {
+            return false;
+        }
+        // Stable, so addresses of the same rank stay in the order found
+        self.addresses.sort_by_key(address_rank);
+        self.address = self.addresses[0];
+        true
+    }
+
+    /// Make the best address that accepts a connection within `timeout` the
+    /// one to use. All addresses are tried at once.
+    ///
+    /// Returns false, leaving the addresses as they are, if none answers.
+    pub fn prefer_reachable(&mut self, timeout: Duration) -> bool {
+        if self.addresses.is_empty() {
+            self.addresses.push(self.address);
+        }
+
+        let (tx, rx) = mpsc::channel();
+        for (i, address) in self.addresses.iter().copied().enumerate() {
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                let reachable = TcpStream::connect_timeout(&address, timeout).is_ok();
+                let _ = tx.send((i, reachable));
+            });
+        }
+        drop(tx);
+
+        // Wait for every answer unless the best address is reachable, so a
+        // better address that answers a little later still wins
+        let mut best: Option<usize> = None;
+        for (i, reachable) in rx {
+            if reachable {
+                best = Some(best.map_or(i, |b| b.min(i)));
+                if best == Some(0) {
+                    break;
+                }
+            }
+        }
+
+        let Some(best) = best else {
+            return false;
+        };
+        let address = self.addresses.remove(best);
+        self.addresses.insert(0, address);
+        self.address = address;
+        true
+    }
+
+    /// Whether `other` is the same server as announced here, whatever
+    /// address it was found at.
+    pub fn same_service(&self, other: &ServerInfo) -> bool {
+        self.name == other.name
+            && self.address.port() == other.address.port()
+            && self.hostname == other.hostname
+            && self.version == other.version
+            && self.model == other.model
+            && self.project == other.project
+            && self.cwd == other.cwd
+            && self.auth_required == other.auth_required
+    }
+}
+
+/// How good an address is for reaching a server; lower is better.
+///
+/// Link-local addresses come last: they only work on one link, and an IPv6
+/// one needs the interface it was found on, which mDNS doesn't tell us.
+fn address_rank(address: &SocketAddr) -> u8 {
+    match address.ip() {
+        IpAddr::V4(ip) if ip.is_link_local() => 2,
+        IpAddr::V4(_) => 0,
+        IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfe80 => 3,
+        IpAddr::V6(_) => 1,
+    }
+}
+
 impl std::fmt::Display for ServerInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} ({})", self.name, self.address)?;
@@ -0,0 +1,150 @@
+//! Servers on the machines of a Tailscale network.
+//!
+//! The peers come from `tailscale status --json`. Every online peer that
+//! accepts connections on the server port is listed, at its Tailscale
+//! addresses, so a server at home is found from anywhere on the tailnet.
+
+use crate::error::DiscoverError;
+use crate::service::ServerInfo;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::process::Command;
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::debug;
+
+/// The parts of `tailscale status --json` used here.
+#[derive(Debug, Deserialize)]
+struct Status {
+    #[serde(rename = "Peer", default)]
+    peers: HashMap<String, Peer>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Peer {
+    #[serde(rename = "HostName", default)]
+    host_name: String,
+    #[serde(rename = "DNSName", default)]
+    dns_name: String,
+    #[serde(rename = "TailscaleIPs", default)]
+    ips: Vec<IpAddr>,
+    #[serde(rename = "Online", default)]
+    online: bool,
+}
+
+/// The tailnet peers with something listening on `port`.
+///
+/// Each peer gets `timeout` to accept a connection; they are tried at once.
+pub fn tailscale_servers(port: u16, timeout: Duration) -> Result<Vec<ServerInfo>, DiscoverError> {
+    let output = Command::new("tailscale")
+        .args(["status", "--json"])
+        .output()
+        .map_err(|e| DiscoverError::Tailscale(format!("failed to run tailscale: {e}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(DiscoverError::Tailscale(stderr.trim().to_string()));
+    }
+
+    let candidates = peers(&output.stdout, port)?;
+    debug!(count = candidates.len(), "Probing tailnet peers");
+
+    let (tx, rx) = mpsc::channel();
+    for mut server in candidates {
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            if server.prefer_reachable(timeout) {
+                let _ = tx.send(server);
+            }
+        });
+    }
+    drop(tx);
+
+    let mut servers: Vec<ServerInfo> = rx.into_iter().collect();
+    servers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(servers)
+}
+
+/// The online peers in `status`, as servers on `port`.
+fn peers(status: &[u8], port: u16) -> Result<Vec<ServerInfo>, DiscoverError> {
+    let status: Status = serde_json::from_slice(status)
+        .map_err(|e| DiscoverError::Tailscale(format!("unexpected status: {e}")))?;
+
+    let mut servers = Vec::new();
+    for peer in status.peers.into_values() {
+        if !peer.online || peer.ips.is_empty() {
+            continue;
+        }
+        let addresses: Vec<SocketAddr> = peer
+            .ips
+            .iter()
+            .map(|ip| SocketAddr::new(*ip, port))
+            .collect();
+        let hostname = peer.dns_name.trim_end_matches('.');
+        let name = if peer.host_name.is_empty() {
+            hostname.split('.').next().unwrap_or(hostname).to_string()
+        } else {
+            peer.host_name
+        };
+        let mut server = ServerInfo {
+            name,
+            address: addresses[0],
+            addresses: Vec::new(),
+            hostname: (!hostname.is_empty()).then(|| hostname.to_string()),
+            version: None,
+            model: None,
+            project: None,
+            cwd: None,
+            auth_required: false,
+        };
+        server.merge_addresses(&addresses);
+        servers.push(server);
+    }
+    servers.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(servers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUS: &str = r#"{
+        "Self": {"HostName": "laptop", "TailscaleIPs": ["100.64.0.1"], "Online": true},
+        "Peer": {
+            "nodekey:a": {
+                "HostName": "home",
+                "DNSName": "home.tail1234.ts.net.",
+                "TailscaleIPs": ["fd7a:115c:a1e0::2", "100.64.0.2"],
+                "Online": true
+            },
+            "nodekey:b": {
+                "HostName": "phone",
+                "DNSName": "phone.tail1234.ts.net.",
+                "TailscaleIPs": ["100.64.0.3"],
+                "Online": false
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_online_peers() {
+        let servers = peers(STATUS.as_bytes(), 3000).unwrap();
+        assert_eq!(servers.len(), 1);
+
+        let home = &servers[0];
+        assert_eq!(home.name, "home");
+        assert_eq!(home.hostname.as_deref(), Some("home.tail1234.ts.net"));
+        // IPv4 first
+        assert_eq!(home.address, "100.64.0.2:3000".parse().unwrap());
+        assert_eq!(home.addresses.len(), 2);
+    }
+
+    #[test]
+    fn test_unexpected_status() {
+        assert!(matches!(
+            peers(b"not json", 3000),
+            Err(DiscoverError::Tailscale(_))
+        ));
+        assert!(peers(b"{}", 3000).unwrap().is_empty());
+    }
+}
@@ -4,7 +4,7 @@
 use crate::error::{McpError, McpResult};
 use crate::protocol::{
     CallToolParams, InitializeParams, InitializeResult, JsonRpcNotification, JsonRpcRequest,
-    ListToolsResult, McpTool, ToolCallResult,
+    ListToolsResult, McpTool, ProgressParams, RequestMeta, ToolCallResult, METHOD_PROGRESS,
 };
 use crate::server::{ServerConfig, ServerState};
 use crate::sse::{SseConfig, SseTransport};
@@ -13,7 +13,7 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, info, warn};
 
 /// An MCP server connection.
@@ -204,6 +204,25 @@ impl McpClient {
     /// This will find the server that provides the tool and call it.
     /// Returns an error if no server provides the tool or doesn't support tools.
     pub async fn call_tool(&self, name: &str, arguments: Value) -> McpResult<ToolCallResult> {
+        self.call_tool_inner(name, arguments, None).await
+    }
+
+    /// Call a tool, passing on the progress the server reports for the call.
+    pub async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress: mpsc::UnboundedSender<ProgressParams>,
+    ) -> McpResult<ToolCallResult> {
+        self.call_tool_inner(name, arguments, Some(progress)).await
+    }
+
+    async fn call_tool_inner(
+        &self,
+        name: &str,
+        arguments: Value,
+        progress: Option<mpsc::UnboundedSender<ProgressParams>>,
+    ) -> McpResult<ToolCallResult> {
         let servers = self.servers.read().await;
 
         // Find the server that has this tool and supports tools capability
@@ -218,18 +237,32 @@ impl McpClient {
 
         debug!(tool = name, server = %server_name, "Calling MCP tool");
 
+        // The request ID doubles as the progress token
+        let id = self.next_request_id();
         let params = CallToolParams {
             name: name.to_string(),
             arguments: Some(arguments),
+            meta: progress.as_ref().map(|_| RequestMeta {
+                progress_token: Some(Value::from(id)),
+            }),
         };
 
-        let request = JsonRpcRequest::new(
-            self.next_request_id(),
-            "tools/call",
-            Some(serde_json::to_value(&params)?),
-        );
-
-        let response = transport.request(request).await?;
+        let request = JsonRpcRequest::new(id, "tools/call", Some(serde_json::to_value(&params)?));
+
+        let response = match progress {
+            Some(progress) => {
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                tokio::spawn(async move {
+                    while let Some(notification) = rx.recv().await {
+                        if let Some(params) = progress_of(&notification, id) {
+                            let _ = progress.send(params);
+                        }
+                    }
+                });
+                transport.request_with_notifications(request, tx).await?
+            }
+            None => transport.request(request).await?,
+        };
 
         if let Some(error) = response.error {
             return Err(McpError::tool_error(error.message));
@@ -355,6 +388,15 @@ impl Drop for McpClient {
     }
 }
 
+/// The progress a notification reports for the request with a token.
+fn progress_of(notification: &JsonRpcNotification, token: u64) -> Option<ProgressParams> {
+    if notification.method != METHOD_PROGRESS {
+        return None;
+    }
+    let params: ProgressParams = serde_json::from_value(notification.params.clone()?).ok()?;
+    (params.progress_token == token).then_some(params)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -645,4 +687,19 @@ mod tests {
         let result = client.call_tool("complex_tool", args).await;
         assert!(matches!(result, Err(McpError::ToolNotFound(_))));
     }
+
+    #[test]
+    fn test_progress_of_matches_token() {
+        let notification = JsonRpcNotification::new(
+            METHOD_PROGRESS,
+            Some(serde_json::json!({"progressToken": 3, "progress": 1, "total": 4})),
+        );
+        let params = progress_of(&notification, 3).unwrap();
+        assert_eq!(params.progress, 1.0);
+        assert_eq!(params.total, Some(4.0));
+        assert!(progress_of(&notification, 4).is_none());
+
+        let other = JsonRpcNotification::new("notifications/message", notification.params);
+        assert!(progress_of(&other, 3).is_none());
+    }
 }
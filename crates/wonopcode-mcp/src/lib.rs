@@ -63,8 +63,8 @@ pub use oauth::{
     OAuthConfig, OAuthProvider, OAuthTokens, OAUTH_CALLBACK_PATH, OAUTH_CALLBACK_PORT,
 };
 pub use protocol::{
-    McpTool, PermissionRequestParams, PermissionResponseParams, ToolCallResult, ToolContent,
-    METHOD_PERMISSION_REQUEST, METHOD_PERMISSION_RESPONSE,
+    McpTool, PermissionRequestParams, PermissionResponseParams, ProgressParams, ToolCallResult,
+    ToolContent, METHOD_PERMISSION_REQUEST, METHOD_PERMISSION_RESPONSE, METHOD_PROGRESS,
 };
 pub use serve::{
     McpServerTool, McpToolContext, McpToolExecutor, PendingPermissions, PERMISSION_TIMEOUT_SECS,
@@ -213,6 +213,34 @@ pub struct CallToolParams {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arguments: Option<Value>,
+    /// Request metadata, such as the token to report progress with.
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<RequestMeta>,
+}
+
+/// Metadata of a request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestMeta {
+    /// Token the server sends progress notifications for the request with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress_token: Option<Value>,
+}
+
+/// Progress notification parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressParams {
+    /// Token of the request making progress.
+    pub progress_token: Value,
+    /// Progress so far; increases with each notification.
+    pub progress: f64,
+    /// Total the progress is heading for, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<f64>,
+    /// What the server is doing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 }
 
 /// Tool call result.
@@ -310,6 +338,9 @@ pub struct PermissionResponseResult {
     pub message: Option<String>,
 }
 
+/// Method name for progress notifications.
+pub const METHOD_PROGRESS: &str = "notifications/progress";
+
 /// Method name for permission request notifications.
 pub const METHOD_PERMISSION_REQUEST: &str = "wonopcode/permissionRequest";
 
@@ -455,9 +486,39 @@ mod tests {
         let params = CallToolParams {
             name: "bash".to_string(),
             arguments: Some(serde_json::json!({"command": "ls"})),
+            meta: None,
         };
         let json = serde_json::to_string(&params).unwrap();
         assert!(json.contains("\"name\":\"bash\""));
+        assert!(!json.contains("_meta"));
+    }
+
+    #[test]
+    fn test_call_tool_params_with_progress_token() {
+        let params = CallToolParams {
+            name: "build".to_string(),
+            arguments: None,
+            meta: Some(RequestMeta {
+                progress_token: Some(serde_json::json!(7)),
+            }),
+        };
+        let json = serde_json::to_value(&params).unwrap();
+        assert_eq!(json["_meta"]["progressToken"], 7);
+    }
+
+    #[test]
+    fn test_progress_params() {
+        let params: ProgressParams = serde_json::from_value(serde_json::json!({
+            "progressToken": 7,
+            "progress": 30,
+            "total": 120,
+            "message": "Linking"
+        }))
+        .unwrap();
+        assert_eq!(params.progress_token, serde_json::json!(7));
+        assert_eq!(params.progress, 30.0);
+        assert_eq!(params.total, Some(120.0));
+        assert_eq!(params.message.as_deref(), Some("Linking"));
     }
 
     #[test]
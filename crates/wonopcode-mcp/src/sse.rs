@@ -10,7 +10,7 @@ use crate::transport::Transport;
 use async_trait::async_trait;
 use reqwest::{Client, StatusCode};
 use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, warn};
 
 /// SSE transport configuration.
@@ -82,8 +82,47 @@ impl SseTransport {
         req
     }
 
-    /// Parse SSE response.
-    async fn parse_sse_response(&self, response: reqwest::Response) -> McpResult<JsonRpcResponse> {
+    /// Send a request, passing on the notifications sent before its response.
+    async fn send_request(
+        &self,
+        request: JsonRpcRequest,
+        notifications: Option<&mpsc::UnboundedSender<JsonRpcNotification>>,
+    ) -> McpResult<JsonRpcResponse> {
+        let request_json = serde_json::to_string(&request)?;
+
+        debug!(id = request.id, method = %request.method, "Sending SSE request");
+
+        let response = self
+            .build_request(&request_json)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    McpError::Timeout
+                } else if e.is_connect() {
+                    McpError::connection_failed(format!("Connection failed: {e}"))
+                } else {
+                    McpError::protocol_error(format!("Request failed: {e}"))
+                }
+            })?;
+
+        // Update session ID if provided
+        if let Some(session_id) = response.headers().get("x-session-id") {
+            if let Ok(id) = session_id.to_str() {
+                *self.session_id.write().await = Some(id.to_string());
+            }
+        }
+
+        self.connected.store(true, Ordering::SeqCst);
+        self.parse_sse_response(response, notifications).await
+    }
+
+    /// Parse SSE response, passing on the notifications sent before it.
+    async fn parse_sse_response(
+        &self,
+        response: reqwest::Response,
+        notifications: Option<&mpsc::UnboundedSender<JsonRpcNotification>>,
+    ) -> McpResult<JsonRpcResponse> {
         let status = response.status();
 
         if status == StatusCode::UNAUTHORIZED {
@@ -106,7 +145,7 @@ impl SseTransport {
 
         if content_type.contains("text/event-stream") {
             // Parse SSE stream for the response
-            self.parse_sse_stream(response).await
+            self.parse_sse_stream(response, notifications).await
         } else {
             // Regular JSON response
             let text = response
@@ -120,7 +159,11 @@ impl SseTransport {
     }
 
     /// Parse SSE stream for JSON-RPC response.
-    async fn parse_sse_stream(&self, response: reqwest::Response) -> McpResult<JsonRpcResponse> {
+    async fn parse_sse_stream(
+        &self,
+        response: reqwest::Response,
+        notifications: Option<&mpsc::UnboundedSender<JsonRpcNotification>>,
+    ) -> McpResult<JsonRpcResponse> {
         use futures::StreamExt;
 
         let mut stream = response.bytes_stream();
@@ -132,20 +175,21 @@ impl SseTransport {
 
             buffer.push_str(&String::from_utf8_lossy(&chunk));
 
-            // Parse SSE events
-            for line in buffer.lines() {
-                if let Some(data) = line.strip_prefix("data: ") {
-                    // Try to parse as JSON-RPC response
-                    if let Ok(response) = serde_json::from_str::<JsonRpcResponse>(data) {
-                        return Ok(response);
-                    }
+            // Parse the complete SSE lines, keeping the last one if cut off
+            let complete = buffer
+                .rfind('\n')
+                .map_or(0, |last_newline| last_newline + 1);
+            for line in buffer[..complete].lines() {
+                if let Some(response) = sse_event(line, notifications) {
+                    return Ok(response);
                 }
             }
+            buffer.drain(..complete);
+        }
 
-            // Keep only incomplete lines
-            if let Some(last_newline) = buffer.rfind('\n') {
-                buffer = buffer[last_newline + 1..].to_string();
-            }
+        // The last event may lack its line ending
+        if let Some(response) = sse_event(&buffer, notifications) {
+            return Ok(response);
         }
 
         Err(McpError::protocol_error(
@@ -154,36 +198,37 @@ impl SseTransport {
     }
 }
 
+/// Read an SSE line, returning the JSON-RPC response in it and passing on a
+/// notification.
+fn sse_event(
+    line: &str,
+    notifications: Option<&mpsc::UnboundedSender<JsonRpcNotification>>,
+) -> Option<JsonRpcResponse> {
+    let data = line.strip_prefix("data: ")?;
+    if let Ok(response) = serde_json::from_str::<JsonRpcResponse>(data) {
+        return Some(response);
+    }
+    if let (Some(tx), Ok(notification)) = (
+        notifications,
+        serde_json::from_str::<JsonRpcNotification>(data),
+    ) {
+        let _ = tx.send(notification);
+    }
+    None
+}
+
 #[async_trait]
 impl Transport for SseTransport {
     async fn request(&self, request: JsonRpcRequest) -> McpResult<JsonRpcResponse> {
-        let request_json = serde_json::to_string(&request)?;
-
-        debug!(id = request.id, method = %request.method, "Sending SSE request");
-
-        let response = self
-            .build_request(&request_json)
-            .send()
-            .await
-            .map_err(|e| {
-                if e.is_timeout() {
-                    McpError::Timeout
-                } else if e.is_connect() {
-                    McpError::connection_failed(format!("Connection failed: {e}"))
-                } else {
-                    McpError::protocol_error(format!("Request failed: {e}"))
-                }
-            })?;
-
-        // Update session ID if provided
-        if let Some(session_id) = response.headers().get("x-session-id") {
-            if let Ok(id) = session_id.to_str() {
-                *self.session_id.write().await = Some(id.to_string());
-            }
-        }
+        self.send_request(request, None).await
+    }
 
-        self.connected.store(true, Ordering::SeqCst);
-        self.parse_sse_response(response).await
+    async fn request_with_notifications(
+        &self,
+        request: JsonRpcRequest,
+        notifications: mpsc::UnboundedSender<JsonRpcNotification>,
+    ) -> McpResult<JsonRpcResponse> {
+        self.send_request(request, Some(&notifications)).await
     }
 
     async fn notify(&self, notification: JsonRpcNotification) -> McpResult<()> {
@@ -247,6 +292,19 @@ mod tests {
         assert!(debug.contains("SseConfig"));
     }
 
+    #[test]
+    fn test_sse_event_passes_on_notifications() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let progress = r#"data: {"jsonrpc":"2.0","method":"notifications/progress","params":{"progressToken":1,"progress":2}}"#;
+        assert!(sse_event(progress, Some(&tx)).is_none());
+        assert_eq!(rx.try_recv().unwrap().method, "notifications/progress");
+
+        let response = r#"data: {"jsonrpc":"2.0","id":1,"result":{}}"#;
+        assert_eq!(sse_event(response, Some(&tx)).unwrap().id, 1);
+        assert!(sse_event("event: message", Some(&tx)).is_none());
+        assert!(rx.try_recv().is_err());
+    }
+
     #[test]
     fn test_sse_transport_creation() {
         let config = SseConfig {
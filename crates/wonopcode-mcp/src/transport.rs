@@ -3,6 +3,7 @@
 use crate::error::McpResult;
 use crate::protocol::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
 use async_trait::async_trait;
+use tokio::sync::mpsc;
 
 /// Transport trait for MCP communication.
 #[async_trait]
@@ -10,6 +11,19 @@ pub trait Transport: Send + Sync {
     /// Send a request and wait for a response.
     async fn request(&self, request: JsonRpcRequest) -> McpResult<JsonRpcResponse>;
 
+    /// Send a request and wait for a response, passing on the notifications
+    /// the server sends while handling it, such as progress.
+    ///
+    /// Transports that can't receive notifications just send the request.
+    async fn request_with_notifications(
+        &self,
+        request: JsonRpcRequest,
+        notifications: mpsc::UnboundedSender<JsonRpcNotification>,
+    ) -> McpResult<JsonRpcResponse> {
+        let _ = notifications;
+        self.request(request).await
+    }
+
     /// Send a notification (no response expected).
     async fn notify(&self, notification: JsonRpcNotification) -> McpResult<()>;
 
@@ -6,11 +6,28 @@ use serde::{Deserialize, Serialize};
 ///
 /// These map to HTTP POST endpoints on the server.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Action {
     /// Send a prompt to the AI.
     SendPrompt { prompt: String },
 
+    /// Attach a base64 encoded image to the next prompt.
+    AttachImage { media_type: String, data: String },
+
+    /// Pin a file, or the lines of a symbol in it, to the next prompt.
+    AttachContext {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        symbol: Option<String>,
+        /// First and last line (1-based, inclusive); the whole file if unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lines: Option<(u32, u32)>,
+    },
+
+    /// Search the language servers' workspace symbols.
+    SearchSymbols { query: String },
+
     /// Cancel the current operation.
     Cancel,
 
@@ -32,6 +49,30 @@ pub enum Action {
     /// Fork the session from a specific message.
     ForkSession { message_id: Option<String> },
 
+    /// Merge the current branch into its parent session.
+    MergeSession,
+
+    /// Search sessions by title, tags and message text.
+    SearchSessions { query: String },
+
+    /// Run agents on separate tasks in their own git worktrees.
+    Orchestrate { tasks: Vec<String> },
+
+    /// Merge the branches of finished orchestrated agents.
+    MergeOrchestration,
+
+    /// Cancel the running orchestration.
+    CancelOrchestration,
+
+    /// Move the session into its own git worktree and branch.
+    CreateWorktree { name: Option<String> },
+
+    /// List the git worktrees of the project.
+    ListWorktrees,
+
+    /// Finish the session's worktree: `merge`, `pr` or `discard`.
+    FinishWorktree { action: String },
+
     /// Undo the last message.
     Undo,
 
@@ -63,7 +104,14 @@ pub enum Action {
     McpReconnect { name: String },
 
     /// Share the current session.
-    ShareSession,
+    ShareSession {
+        /// Link lifetime such as "7d" or "never"; the configured default if not set.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expires: Option<String>,
+        /// Password needed to view the share.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<String>,
+    },
 
     /// Unshare the current session.
     UnshareSession,
@@ -71,6 +119,27 @@ pub enum Action {
     /// Go to a specific message.
     GotoMessage { message_id: String },
 
+    /// Switch to a named config profile.
+    SwitchProfile { profile: String },
+
+    /// List the projects the server manages.
+    ListProjects,
+
+    /// Send further actions to another project (path or name).
+    SwitchProject { project: String },
+
+    /// Start the session from a template (empty name lists templates).
+    ApplyTemplate { name: String },
+
+    /// Save the current session's setup as a template.
+    SaveTemplate { name: String },
+
+    /// Request the project memory contents.
+    LoadMemory,
+
+    /// Replace the project memory contents.
+    SaveMemory { content: String },
+
     /// Save settings.
     SaveSettings {
         scope: SaveScope,
@@ -84,6 +153,30 @@ pub enum Action {
         remember: bool,
     },
 
+    /// Load the recent permission decisions of the project.
+    LoadPermissionAudit,
+
+    /// Show spend against the configured alerts and limits.
+    LoadSpend,
+
+    /// Acknowledge reached spending limits so prompts can run again.
+    AcknowledgeSpendLimit,
+
+    /// Load usage over the last days for the usage dashboard.
+    LoadUsage { days: u32 },
+
+    /// Load the session's file changes for review.
+    StartReview,
+
+    /// Apply the decisions of a review.
+    ApplyReview { files: Vec<ReviewedFileInfo> },
+
+    /// Stop a background process started by the bash tool.
+    StopProcess { pid: u32 },
+
+    /// Cancel a running tool call, leaving the rest of the prompt running.
+    CancelTool { id: String },
+
     /// Update test provider settings.
     UpdateTestProviderSettings {
         emulate_thinking: bool,
@@ -96,8 +189,31 @@ pub enum Action {
     Quit,
 }
 
+/// The decisions for the hunks of a reviewed file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ReviewedFileInfo {
+    pub path: String,
+    /// Decision per hunk, in order; `null` for hunks left undecided.
+    pub decisions: Vec<Option<HunkDecisionInfo>>,
+}
+
+/// What to do with a reviewed hunk.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HunkDecisionInfo {
+    /// Keep the change.
+    Accept,
+    /// Undo the change.
+    Reject,
+    /// Replace the new side of the hunk, context lines included.
+    Edit { text: String },
+}
+
 /// Scope for saving settings.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum SaveScope {
     /// Save to project-level config.
@@ -111,6 +227,9 @@ impl Action {
     pub fn endpoint(&self) -> &'static str {
         match self {
             Action::SendPrompt { .. } => "/action/prompt",
+            Action::AttachImage { .. } => "/action/image",
+            Action::AttachContext { .. } => "/action/context",
+            Action::SearchSymbols { .. } => "/action/symbols",
             Action::Cancel => "/action/cancel",
             Action::ChangeModel { .. } => "/action/model",
             Action::ChangeAgent { .. } => "/action/agent",
@@ -118,6 +237,14 @@ impl Action {
             Action::SwitchSession { .. } => "/action/session/switch",
             Action::RenameSession { .. } => "/action/session/rename",
             Action::ForkSession { .. } => "/action/session/fork",
+            Action::MergeSession => "/action/session/merge",
+            Action::SearchSessions { .. } => "/action/session/search",
+            Action::Orchestrate { .. } => "/action/orchestration/start",
+            Action::MergeOrchestration => "/action/orchestration/merge",
+            Action::CancelOrchestration => "/action/orchestration/cancel",
+            Action::CreateWorktree { .. } => "/action/worktree/create",
+            Action::ListWorktrees => "/action/worktree/list",
+            Action::FinishWorktree { .. } => "/action/worktree/finish",
             Action::Undo => "/action/undo",
             Action::Redo => "/action/redo",
             Action::Revert { .. } => "/action/revert",
@@ -128,11 +255,26 @@ impl Action {
             Action::SandboxRestart => "/action/sandbox/restart",
             Action::McpToggle { .. } => "/action/mcp/toggle",
             Action::McpReconnect { .. } => "/action/mcp/reconnect",
-            Action::ShareSession => "/action/session/share",
+            Action::ShareSession { .. } => "/action/session/share",
             Action::UnshareSession => "/action/session/unshare",
             Action::GotoMessage { .. } => "/action/goto",
+            Action::SwitchProfile { .. } => "/action/profile",
+            Action::ListProjects => "/action/projects",
+            Action::SwitchProject { .. } => "/action/project/switch",
+            Action::ApplyTemplate { .. } => "/action/template",
+            Action::SaveTemplate { .. } => "/action/template/save",
+            Action::LoadMemory => "/action/memory",
+            Action::SaveMemory { .. } => "/action/memory/save",
             Action::SaveSettings { .. } => "/action/settings",
             Action::PermissionResponse { .. } => "/action/permission",
+            Action::LoadPermissionAudit => "/action/permission/audit",
+            Action::LoadSpend => "/action/spend",
+            Action::LoadUsage { .. } => "/action/usage",
+            Action::AcknowledgeSpendLimit => "/action/spend/ack",
+            Action::StartReview => "/action/review",
+            Action::ApplyReview { .. } => "/action/review/apply",
+            Action::StopProcess { .. } => "/action/process/stop",
+            Action::CancelTool { .. } => "/action/tool/cancel",
             Action::UpdateTestProviderSettings { .. } => "/action/test-settings",
             Action::Quit => "/action/quit",
         }
@@ -220,6 +362,29 @@ mod tests {
         assert!(json.contains("dark"));
     }
 
+    #[test]
+    fn action_attach_context_serializes_lines() {
+        let action = Action::AttachContext {
+            path: "src/lib.rs".to_string(),
+            symbol: Some("parse".to_string()),
+            lines: Some((10, 20)),
+        };
+        let json = serde_json::to_value(&action).unwrap();
+        assert_eq!(json["type"], "attach_context");
+        assert_eq!(json["lines"], serde_json::json!([10, 20]));
+
+        let file: Action =
+            serde_json::from_str(r#"{"type": "attach_context", "path": "README.md"}"#).unwrap();
+        assert!(matches!(
+            file,
+            Action::AttachContext {
+                symbol: None,
+                lines: None,
+                ..
+            }
+        ));
+    }
+
     #[test]
     fn all_simple_actions_serialize() {
         // UX: All actions can be sent over the wire
@@ -233,8 +398,23 @@ mod tests {
             Action::SandboxStart,
             Action::SandboxStop,
             Action::SandboxRestart,
-            Action::ShareSession,
+            Action::MergeSession,
+            Action::MergeOrchestration,
+            Action::CancelOrchestration,
+            Action::ListWorktrees,
+            Action::LoadPermissionAudit,
+            Action::LoadSpend,
+            Action::AcknowledgeSpendLimit,
+            Action::ShareSession {
+                expires: None,
+                password: None,
+            },
+            Action::ShareSession {
+                expires: Some("7d".to_string()),
+                password: Some("secret".to_string()),
+            },
             Action::UnshareSession,
+            Action::ListProjects,
             Action::Quit,
         ];
 
@@ -253,6 +433,18 @@ mod tests {
             Action::SendPrompt {
                 prompt: "".to_string(),
             },
+            Action::AttachImage {
+                media_type: "".to_string(),
+                data: "".to_string(),
+            },
+            Action::AttachContext {
+                path: "".to_string(),
+                symbol: None,
+                lines: None,
+            },
+            Action::SearchSymbols {
+                query: "".to_string(),
+            },
             Action::Cancel,
             Action::ChangeModel {
                 model: "".to_string(),
@@ -268,6 +460,18 @@ mod tests {
                 title: "".to_string(),
             },
             Action::ForkSession { message_id: None },
+            Action::MergeSession,
+            Action::SearchSessions {
+                query: "".to_string(),
+            },
+            Action::Orchestrate { tasks: vec![] },
+            Action::MergeOrchestration,
+            Action::CancelOrchestration,
+            Action::CreateWorktree { name: None },
+            Action::ListWorktrees,
+            Action::FinishWorktree {
+                action: "".to_string(),
+            },
             Action::Undo,
             Action::Redo,
             Action::Revert {
@@ -284,11 +488,31 @@ mod tests {
             Action::McpReconnect {
                 name: "".to_string(),
             },
-            Action::ShareSession,
+            Action::ShareSession {
+                expires: None,
+                password: None,
+            },
             Action::UnshareSession,
             Action::GotoMessage {
                 message_id: "".to_string(),
             },
+            Action::SwitchProfile {
+                profile: "".to_string(),
+            },
+            Action::ListProjects,
+            Action::SwitchProject {
+                project: "".to_string(),
+            },
+            Action::ApplyTemplate {
+                name: "".to_string(),
+            },
+            Action::SaveTemplate {
+                name: "".to_string(),
+            },
+            Action::LoadMemory,
+            Action::SaveMemory {
+                content: "".to_string(),
+            },
             Action::SaveSettings {
                 scope: SaveScope::Project,
                 config: serde_json::Value::Null,
@@ -298,6 +522,14 @@ mod tests {
                 allow: false,
                 remember: false,
             },
+            Action::LoadPermissionAudit,
+            Action::LoadSpend,
+            Action::AcknowledgeSpendLimit,
+            Action::LoadUsage { days: 7 },
+            Action::StartReview,
+            Action::ApplyReview { files: vec![] },
+            Action::StopProcess { pid: 0 },
+            Action::CancelTool { id: "".to_string() },
             Action::UpdateTestProviderSettings {
                 emulate_thinking: false,
                 emulate_tool_calls: false,
@@ -315,6 +547,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn action_apply_review_serializes_decisions() {
+        let action = Action::ApplyReview {
+            files: vec![ReviewedFileInfo {
+                path: "src/lib.rs".to_string(),
+                decisions: vec![
+                    Some(HunkDecisionInfo::Accept),
+                    None,
+                    Some(HunkDecisionInfo::Edit {
+                        text: "fn main() {}\n".to_string(),
+                    }),
+                ],
+            }],
+        };
+        let json = serde_json::to_value(&action).unwrap();
+        assert_eq!(json["type"], "apply_review");
+        assert_eq!(json["files"][0]["decisions"][0]["type"], "accept");
+        assert!(json["files"][0]["decisions"][1].is_null());
+        assert_eq!(json["files"][0]["decisions"][2]["text"], "fn main() {}\n");
+
+        let parsed: Action = serde_json::from_value(json).unwrap();
+        let Action::ApplyReview { files } = parsed else {
+            panic!("Wrong action type");
+        };
+        assert_eq!(files[0].decisions.len(), 3);
+    }
+
     #[test]
     fn save_scope_serialization() {
         // UX: Save scope determines where settings are stored
@@ -0,0 +1,82 @@
+//! Project files served to remote clients.
+//!
+//! A headless server lists, reads and diffs the files of its project at
+//! `/files/*`, so a remote client can browse them without access to the
+//! server's filesystem. Paths are relative to the project root.
+
+use serde::{Deserialize, Serialize};
+
+/// Largest file a client may read, in bytes.
+pub const MAX_FILE_READ_BYTES: u64 = 2 * 1024 * 1024;
+
+/// An entry of a directory.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FileEntry {
+    /// File name.
+    pub name: String,
+
+    /// Path relative to the project root.
+    pub path: String,
+
+    /// Whether this is a directory.
+    pub is_dir: bool,
+
+    /// Size in bytes, for files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// The entries of a directory, directories first, skipping hidden and
+/// ignored files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FileListing {
+    /// Directory relative to the project root; empty for the root.
+    pub path: String,
+
+    /// Entries of the directory.
+    pub entries: Vec<FileEntry>,
+}
+
+/// The content of a text file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FileContent {
+    /// Path relative to the project root.
+    pub path: String,
+
+    /// Content of the file.
+    pub content: String,
+}
+
+/// The changes to a file since the last commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FileDiff {
+    /// Path relative to the project root.
+    pub path: String,
+
+    /// Unified diff of the committed file against the working copy; all
+    /// additions for a new file, empty if it is unchanged.
+    pub diff: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directory_entries_omit_their_size() {
+        let entry = FileEntry {
+            name: "src".to_string(),
+            path: "src".to_string(),
+            is_dir: true,
+            size: None,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"name":"src","path":"src","is_dir":true}"#);
+        let parsed: FileEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, entry);
+    }
+}
@@ -0,0 +1,117 @@
+//! Prompt jobs for automation.
+//!
+//! A CI pipeline can post a prompt to `/jobs` on a long-running headless
+//! server and poll `/jobs/{id}` until it has finished, instead of holding an
+//! SSE stream open. Jobs without a session run one after another, each in a
+//! fresh scoped session; jobs naming a scoped session run in it, in order,
+//! alongside the jobs of other sessions. Their results are kept across
+//! server restarts.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a job is in its life.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for the jobs before it.
+    Queued,
+    /// The prompt is running.
+    Running,
+    /// The prompt completed; see `result`.
+    Completed,
+    /// The prompt failed; see `error`.
+    Failed,
+    /// The job was cancelled.
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job has finished, one way or another.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A prompt to run as a job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct JobRequest {
+    /// The prompt.
+    pub prompt: String,
+
+    /// Scoped session to run the prompt in; a fresh one if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+/// A prompt job and its result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Job {
+    /// Job ID, used in its endpoints.
+    pub id: String,
+
+    /// The prompt.
+    pub prompt: String,
+
+    /// Scoped session the prompt runs in, once known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+
+    /// Where the job is in its life.
+    pub status: JobStatus,
+
+    /// Final response of a completed job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+
+    /// Why a job failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// When the job was submitted (RFC 3339).
+    pub created_at: String,
+
+    /// When the prompt started running (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+
+    /// When the job finished (RFC 3339).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+/// Path of a job.
+pub fn job_endpoint(job_id: &str) -> String {
+    format!("/jobs/{job_id}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queued_jobs_omit_their_results() {
+        let job = Job {
+            id: "job_1".to_string(),
+            prompt: "Fix the tests".to_string(),
+            session: None,
+            status: JobStatus::Queued,
+            result: None,
+            error: None,
+            created_at: "2026-01-01T12:00:00Z".to_string(),
+            started_at: None,
+            finished_at: None,
+        };
+        let json = serde_json::to_string(&job).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"job_1","prompt":"Fix the tests","status":"queued","created_at":"2026-01-01T12:00:00Z"}"#
+        );
+        assert_eq!(serde_json::from_str::<Job>(&json).unwrap(), job);
+        assert!(!job.status.is_finished());
+        assert!(JobStatus::Cancelled.is_finished());
+        assert_eq!(job_endpoint("job_1"), "/jobs/job_1");
+    }
+}
@@ -4,12 +4,30 @@
 //! - TUI client (`wonopcode --connect`)
 //! - Agent server (`wonopcode --headless`)
 //!
-//! Communication uses HTTP for actions and SSE for updates.
+//! Communication uses HTTP for actions and SSE for updates, or a WebSocket
+//! carrying both.
 
 mod action;
+mod files;
+mod jobs;
+mod presence;
+mod scoped;
+mod socket;
 mod state;
 mod update;
+mod version;
 
-pub use action::{Action, SaveScope};
+pub use action::{Action, HunkDecisionInfo, ReviewedFileInfo, SaveScope};
+pub use files::{FileContent, FileDiff, FileEntry, FileListing, MAX_FILE_READ_BYTES};
+pub use jobs::{job_endpoint, Job, JobRequest, JobStatus};
+pub use presence::{Participant, Role, PARTICIPANT_HEADER};
+pub use scoped::{session_action_endpoint, session_events_endpoint, ScopedSessionInfo};
+pub use socket::{
+    ClientFrame, ServerFrame, HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT, SOCKET_ENDPOINT,
+};
 pub use state::*;
 pub use update::*;
+pub use version::{
+    Capability, Handshake, Negotiated, VersionMismatch, HANDSHAKE_ENDPOINT, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
+};
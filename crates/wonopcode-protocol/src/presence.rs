@@ -0,0 +1,68 @@
+//! Presence of the clients sharing a headless session.
+//!
+//! When a server runs with collaboration on, each client on its WebSocket is
+//! a participant. One of them drives: only its actions are accepted. The
+//! others watch the same updates, including the prompts the driver sends, and
+//! any of them can take over with [`ClientFrame::TakeControl`]. When the
+//! driver leaves, the participant who joined first takes over. Requests to
+//! the HTTP endpoints that change the session are only accepted with the
+//! driver's ID in the [`PARTICIPANT_HEADER`].
+//!
+//! [`ClientFrame::TakeControl`]: crate::ClientFrame::TakeControl
+
+use serde::{Deserialize, Serialize};
+
+/// Header carrying the participant ID of an HTTP request to a shared session.
+pub const PARTICIPANT_HEADER: &str = "x-participant-id";
+
+/// What a participant may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Sends prompts and other actions.
+    Driver,
+    /// Only sees the updates.
+    Watcher,
+}
+
+/// A client sharing the session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Participant {
+    /// Participant ID, for the duration of its connection.
+    pub id: String,
+
+    /// Name the client gave, e.g. its user's login.
+    pub name: String,
+
+    /// What the participant may do.
+    pub role: Role,
+
+    /// When the participant joined (RFC 3339).
+    pub joined_at: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn participants_serialize_their_role() {
+        let participant = Participant {
+            id: "ptc_1".to_string(),
+            name: "ada".to_string(),
+            role: Role::Watcher,
+            joined_at: "2026-01-01T12:00:00Z".to_string(),
+        };
+        let json = serde_json::to_string(&participant).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"ptc_1","name":"ada","role":"watcher","joined_at":"2026-01-01T12:00:00Z"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Participant>(&json).unwrap(),
+            participant
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! Sessions that run side by side in one headless server.
+//!
+//! Besides the shared session behind `/events` and `/action/*`, clients can
+//! open scoped sessions at `/sessions`. Each one has its own runner, its own
+//! SSE stream and its own prompt queue, so several clients or automations
+//! can work in parallel without seeing each other's updates.
+
+use serde::{Deserialize, Serialize};
+
+/// A scoped session and its prompt queue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ScopedSessionInfo {
+    /// Session ID, used in its endpoints.
+    pub id: String,
+
+    /// Whether a prompt is running.
+    pub busy: bool,
+
+    /// Prompts waiting for the running one to finish.
+    pub queued: usize,
+}
+
+/// Path of the SSE stream of a scoped session.
+pub fn session_events_endpoint(session_id: &str) -> String {
+    format!("/session/{session_id}/events")
+}
+
+/// Path that actions for a scoped session are posted to, as the same JSON
+/// the shared session's endpoints take, tagged with `type`.
+pub fn session_action_endpoint(session_id: &str) -> String {
+    format!("/session/{session_id}/action")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_endpoints_include_the_session() {
+        assert_eq!(session_events_endpoint("abc"), "/session/abc/events");
+        assert_eq!(session_action_endpoint("abc"), "/session/abc/action");
+    }
+}
@@ -0,0 +1,122 @@
+//! Frames exchanged over the WebSocket transport.
+//!
+//! A client opens `/ws`, sends [`ClientFrame::Resume`] and then sends actions
+//! and receives updates over the same connection. Every update carries a
+//! sequence number, so a client that reconnects can ask for the updates it
+//! missed instead of fetching the whole state again.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Action, Update};
+
+/// Path of the WebSocket endpoint.
+pub const SOCKET_ENDPOINT: &str = "/ws";
+
+/// How often each side pings the other.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long a side waits without hearing anything before dropping the
+/// connection.
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// Frames sent from client to server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "frame", rename_all = "snake_case")]
+pub enum ClientFrame {
+    /// First frame on a connection: the last update the client received,
+    /// or `None` if it only wants updates from now on, and the name to show
+    /// to the others sharing the session.
+    Resume {
+        last_seq: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    /// An action, as it would be posted to its HTTP endpoint.
+    Action { action: Action },
+    /// Heartbeat; answered with [`ServerFrame::Pong`].
+    Ping,
+    /// Drive a shared session, making the current driver a watcher.
+    TakeControl,
+}
+
+/// Frames sent from server to client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "frame", rename_all = "snake_case")]
+pub enum ServerFrame {
+    /// Answer to [`ClientFrame::Resume`]. `complete` is false if updates
+    /// after the client's last one are no longer buffered, in which case the
+    /// client should fetch the state again.
+    Resumed { seq: u64, complete: bool },
+    /// An update and its sequence number.
+    Update { seq: u64, update: Update },
+    /// Answer to [`ClientFrame::Ping`].
+    Pong,
+    /// A frame could not be handled.
+    Error { message: String },
+    /// Sent after [`ServerFrame::Resumed`] when the session is shared: the
+    /// ID of this connection's participant, as it appears in
+    /// [`Update::Presence`].
+    Joined { participant: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_frames_wrap_actions() {
+        let frame = ClientFrame::Action {
+            action: Action::SendPrompt {
+                prompt: "hi".to_string(),
+            },
+        };
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(
+            json,
+            r#"{"frame":"action","action":{"type":"send_prompt","prompt":"hi"}}"#
+        );
+
+        let parsed: ClientFrame =
+            serde_json::from_str(r#"{"frame":"resume","last_seq":7}"#).unwrap();
+        assert!(matches!(
+            parsed,
+            ClientFrame::Resume {
+                last_seq: Some(7),
+                name: None
+            }
+        ));
+        let parsed: ClientFrame =
+            serde_json::from_str(r#"{"frame":"resume","last_seq":null,"name":"ada"}"#).unwrap();
+        assert!(matches!(
+            parsed,
+            ClientFrame::Resume { last_seq: None, name: Some(ref name) } if name == "ada"
+        ));
+    }
+
+    #[test]
+    fn server_frames_carry_sequence_numbers() {
+        let frame = ServerFrame::Update {
+            seq: 3,
+            update: Update::Started,
+        };
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(
+            json,
+            r#"{"frame":"update","seq":3,"update":{"type":"started"}}"#
+        );
+
+        let parsed: ServerFrame =
+            serde_json::from_str(r#"{"frame":"resumed","seq":12,"complete":false}"#).unwrap();
+        assert!(matches!(
+            parsed,
+            ServerFrame::Resumed {
+                seq: 12,
+                complete: false
+            }
+        ));
+    }
+}
@@ -2,10 +2,11 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::update::{LspInfo, McpInfo, ModifiedFileInfo, PhaseInfo, TodoInfo};
+use crate::update::{LspInfo, McpInfo, ModifiedFileInfo, PhaseInfo, ProjectInfo, TodoInfo};
 
 /// Full application state for initial sync.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct State {
     /// Project directory path.
     pub project: String,
@@ -55,6 +56,10 @@ pub struct State {
     /// Available sessions.
     pub sessions: Vec<SessionListItem>,
 
+    /// Projects the server manages; empty for a single-project server.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub projects: Vec<ProjectInfo>,
+
     /// Current configuration (for settings dialog).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub config: Option<ConfigState>,
@@ -62,6 +67,7 @@ pub struct State {
 
 /// Session state including messages.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SessionState {
     /// Session ID.
     pub id: String,
@@ -90,6 +96,7 @@ pub struct SessionState {
 
 /// A message in the conversation.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Message {
     /// Message ID.
     pub id: String,
@@ -118,6 +125,7 @@ pub struct Message {
 
 /// A segment of message content.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum MessageSegment {
     /// Plain text.
@@ -135,6 +143,7 @@ pub enum MessageSegment {
 
 /// A tool call within a message.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ToolCall {
     /// Tool call ID.
     pub id: String,
@@ -158,6 +167,7 @@ pub struct ToolCall {
 
 /// Sandbox state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SandboxState {
     /// Current state: "disabled", "stopped", "starting", "running", "error".
     pub state: String,
@@ -173,6 +183,7 @@ pub struct SandboxState {
 
 /// Token usage statistics.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TokenUsage {
     /// Input tokens used.
     pub input: u32,
@@ -186,6 +197,7 @@ pub struct TokenUsage {
 
 /// Session list item (summary without messages).
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SessionListItem {
     /// Session ID.
     pub id: String,
@@ -199,6 +211,7 @@ pub struct SessionListItem {
 
 /// Configuration state for settings dialog.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ConfigState {
     /// Sandbox configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -211,6 +224,7 @@ pub struct ConfigState {
 
 /// Sandbox configuration state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SandboxConfigState {
     /// Whether sandbox is enabled.
     pub enabled: bool,
@@ -222,6 +236,7 @@ pub struct SandboxConfigState {
 
 /// Permission configuration state.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct PermissionConfigState {
     /// Whether to allow all operations in sandbox.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -256,6 +271,7 @@ impl Default for State {
             token_usage: TokenUsage::default(),
             context_limit: 200000,
             sessions: Vec::new(),
+            projects: Vec::new(),
             config: None,
         }
     }
@@ -2,8 +2,14 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Name of the SSE event sent instead of the missed updates when a client
+/// resumes with a `Last-Event-ID` whose updates are no longer buffered. Its
+/// ID is the server's latest, to resume from next time.
+pub const SSE_RESYNC_EVENT: &str = "resync";
+
 /// Updates sent from server to client via SSE.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum Update {
     /// Processing started.
@@ -19,6 +25,19 @@ pub enum Update {
         input: String,
     },
 
+    /// A running tool call made progress.
+    ToolProgress {
+        id: String,
+        /// Share of the work done, from 0 to 1, if known.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fraction: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+
+    /// A running tool call wrote output.
+    ToolOutput { id: String, text: String },
+
     /// Tool call completed.
     ToolCompleted {
         id: String,
@@ -37,6 +56,12 @@ pub enum Update {
     /// Status message.
     Status { message: String },
 
+    /// A prompt was queued behind the one a scoped session is running.
+    PromptQueued {
+        /// Place in the queue, from 1.
+        position: usize,
+    },
+
     /// Token usage update.
     TokenUsage {
         input: u32,
@@ -62,6 +87,12 @@ pub enum Update {
     /// LSP servers updated.
     LspUpdated { servers: Vec<LspInfo> },
 
+    /// Workspace symbols found for a query.
+    Symbols {
+        query: String,
+        symbols: Vec<SymbolInfo>,
+    },
+
     /// MCP servers updated.
     McpUpdated { servers: Vec<McpInfo> },
 
@@ -80,12 +111,82 @@ pub enum Update {
         error: Option<String>,
     },
 
+    /// Resource usage of the running sandbox.
+    SandboxResources {
+        /// CPU usage, 100 per fully used core.
+        cpu_percent: f64,
+        memory_bytes: u64,
+        memory_limit: u64,
+        /// Number of running processes.
+        pids: u32,
+    },
+
     /// System message to display.
     SystemMessage { message: String },
 
     /// Agent changed.
     AgentChanged { agent: String },
 
+    /// Project memory contents.
+    Memory { content: String },
+
+    /// Available session templates.
+    Templates { templates: Vec<TemplateInfo> },
+
+    /// Session search results, best match first.
+    SessionSearchResults {
+        query: String,
+        results: Vec<SessionSearchHit>,
+    },
+
+    /// Progress of the agents of an orchestration.
+    Orchestration {
+        orchestration_id: String,
+        agents: Vec<OrchestrationAgent>,
+    },
+
+    /// Recent permission decisions, oldest first.
+    PermissionAudit { entries: Vec<PermissionAuditInfo> },
+
+    /// Instruction files found for the system prompt, highest priority first.
+    Instructions { files: Vec<InstructionFileInfo> },
+
+    /// A session budget limit is nearly used up.
+    BudgetWarning {
+        limit: String,
+        used: f64,
+        max: f64,
+        message: String,
+    },
+
+    /// Configuration was reloaded from disk.
+    ConfigReloaded {
+        /// New theme, if it changed.
+        theme: Option<String>,
+        /// Changed settings that were applied.
+        applied: Vec<String>,
+        /// Changed settings that need a restart.
+        restart_required: Vec<String>,
+    },
+
+    /// Spend crossed a configured alert amount.
+    SpendAlert {
+        scope: String,
+        spent: f64,
+        alert: f64,
+        message: String,
+    },
+
+    /// Usage over the last days, for the usage dashboard.
+    Usage {
+        days: u32,
+        /// Spend by day, oldest first.
+        daily: Vec<UsageInfo>,
+        /// Spend by model.
+        models: Vec<UsageInfo>,
+        tools: Vec<ToolLatencyInfo>,
+    },
+
     /// Permission request from the agent.
     PermissionRequest {
         id: String,
@@ -95,18 +196,161 @@ pub enum Update {
         #[serde(skip_serializing_if = "Option::is_none")]
         path: Option<String>,
     },
+
+    /// Projects the server manages.
+    Projects { projects: Vec<ProjectInfo> },
+
+    /// The server switched to another project; session state now belongs to it.
+    ProjectSwitched { path: String, name: String },
+
+    /// The session's file changes to review, as a unified diff.
+    Review { diff: String },
+
+    /// A background process was started by the bash tool.
+    ProcessStarted { pid: u32, command: String },
+
+    /// Output of a background process, as it arrives.
+    ProcessOutput { pid: u32, text: String },
+
+    /// A background process exited; no exit code if it was killed.
+    ProcessExited { pid: u32, exit_code: Option<i32> },
+
+    /// The clients sharing the session changed, or who drives it.
+    Presence {
+        participants: Vec<crate::Participant>,
+    },
+
+    /// The driver of a shared session sent a prompt, so that the others see
+    /// it in the conversation too.
+    Prompted {
+        /// ID of the participant who sent it.
+        participant: String,
+        /// Their name.
+        name: String,
+        prompt: String,
+    },
+}
+
+/// A project root managed by the server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ProjectInfo {
+    /// Project root directory.
+    pub path: String,
+    /// Display name (the directory name).
+    pub name: String,
+    /// Whether actions currently go to this project.
+    pub active: bool,
+    /// Whether the project's runner has been started.
+    pub loaded: bool,
 }
 
 /// Session info for session list updates.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SessionInfo {
     pub id: String,
     pub title: String,
     pub timestamp: String,
 }
 
+/// A session matching a search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SessionSearchHit {
+    pub id: String,
+    pub title: String,
+    /// Matching message text, or the tags if the match isn't in the conversation.
+    #[serde(default)]
+    pub snippet: String,
+}
+
+/// Progress of an orchestrated agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct OrchestrationAgent {
+    pub name: String,
+    pub task: String,
+    /// "pending", "running", "done", "failed", "cancelled", "merged" or "conflict".
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub steps: usize,
+    #[serde(default)]
+    pub tool_calls: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changed_files: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+}
+
+/// A recorded permission decision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PermissionAuditInfo {
+    /// Unix millis.
+    pub time: i64,
+    pub tool: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    pub allowed: bool,
+    #[serde(default)]
+    pub prompted: bool,
+    /// How the decision was made: "user", "rule: ..." or "no rule".
+    pub source: String,
+}
+
+/// Cost and tokens of a day or a model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct UsageInfo {
+    /// The day (`YYYY-MM-DD`) or model.
+    pub name: String,
+    pub cost: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Durations of the calls of a tool.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ToolLatencyInfo {
+    pub tool: String,
+    /// Milliseconds, oldest first.
+    pub durations_ms: Vec<u64>,
+}
+
+/// An instruction file found for the system prompt.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct InstructionFileInfo {
+    pub path: String,
+    /// Estimated tokens.
+    pub tokens: usize,
+    /// Whether the file is in the system prompt.
+    pub included: bool,
+    /// Why the file was truncated or left out.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Session template info for the template picker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct TemplateInfo {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+}
+
 /// Phase info containing grouped todos.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct PhaseInfo {
     pub id: String,
     pub name: String,
@@ -116,6 +360,7 @@ pub struct PhaseInfo {
 
 /// Todo item info.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TodoInfo {
     pub id: String,
     pub content: String,
@@ -128,6 +373,7 @@ pub struct TodoInfo {
 
 /// LSP server info.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct LspInfo {
     pub id: String,
     pub name: String,
@@ -135,8 +381,23 @@ pub struct LspInfo {
     pub connected: bool,
 }
 
+/// A workspace symbol found by a language server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SymbolInfo {
+    pub name: String,
+    pub kind: String,
+    /// Path of the file, relative to the project.
+    pub path: String,
+    /// First line (1-based).
+    pub start_line: u32,
+    /// Last line (1-based, inclusive).
+    pub end_line: u32,
+}
+
 /// MCP server info.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct McpInfo {
     pub name: String,
     pub connected: bool,
@@ -146,6 +407,7 @@ pub struct McpInfo {
 
 /// Modified file info.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ModifiedFileInfo {
     pub path: String,
     pub added: u32,
@@ -159,22 +421,45 @@ impl Update {
             Update::Started => "started",
             Update::TextDelta { .. } => "text_delta",
             Update::ToolStarted { .. } => "tool_started",
+            Update::ToolProgress { .. } => "tool_progress",
+            Update::ToolOutput { .. } => "tool_output",
             Update::ToolCompleted { .. } => "tool_completed",
             Update::Completed { .. } => "completed",
             Update::Error { .. } => "error",
             Update::Status { .. } => "status",
+            Update::PromptQueued { .. } => "prompt_queued",
             Update::TokenUsage { .. } => "token_usage",
             Update::ModelInfo { .. } => "model_info",
             Update::Sessions { .. } => "sessions",
             Update::TodosUpdated { .. } => "todos_updated",
             Update::LspUpdated { .. } => "lsp_updated",
+            Update::Symbols { .. } => "symbols",
             Update::McpUpdated { .. } => "mcp_updated",
             Update::ModifiedFilesUpdated { .. } => "modified_files_updated",
             Update::PermissionsPending { .. } => "permissions_pending",
             Update::SandboxUpdated { .. } => "sandbox_updated",
+            Update::SandboxResources { .. } => "sandbox_resources",
             Update::SystemMessage { .. } => "system_message",
             Update::AgentChanged { .. } => "agent_changed",
+            Update::Memory { .. } => "memory",
+            Update::Templates { .. } => "templates",
+            Update::SessionSearchResults { .. } => "session_search_results",
+            Update::Orchestration { .. } => "orchestration",
+            Update::PermissionAudit { .. } => "permission_audit",
+            Update::Instructions { .. } => "instructions",
+            Update::BudgetWarning { .. } => "budget_warning",
+            Update::ConfigReloaded { .. } => "config_reloaded",
+            Update::SpendAlert { .. } => "spend_alert",
+            Update::Usage { .. } => "usage",
             Update::PermissionRequest { .. } => "permission_request",
+            Update::Projects { .. } => "projects",
+            Update::ProjectSwitched { .. } => "project_switched",
+            Update::Review { .. } => "review",
+            Update::ProcessStarted { .. } => "process_started",
+            Update::ProcessOutput { .. } => "process_output",
+            Update::ProcessExited { .. } => "process_exited",
+            Update::Presence { .. } => "presence",
+            Update::Prompted { .. } => "prompted",
         }
     }
 }
@@ -310,6 +595,33 @@ mod tests {
         assert!(json.contains("docker"));
     }
 
+    #[test]
+    fn update_sandbox_resources_roundtrip() {
+        // UX: Shows live sandbox CPU and memory in the sidebar
+        let update = Update::SandboxResources {
+            cpu_percent: 12.5,
+            memory_bytes: 512 * 1024 * 1024,
+            memory_limit: 2 * 1024 * 1024 * 1024,
+            pids: 7,
+        };
+        let json = serde_json::to_string(&update).unwrap();
+        assert!(json.contains("\"type\":\"sandbox_resources\""));
+
+        let parsed: Update = serde_json::from_str(&json).unwrap();
+        let Update::SandboxResources {
+            cpu_percent,
+            memory_bytes,
+            pids,
+            ..
+        } = parsed
+        else {
+            panic!("Wrong update type");
+        };
+        assert_eq!(cpu_percent, 12.5);
+        assert_eq!(memory_bytes, 512 * 1024 * 1024);
+        assert_eq!(pids, 7);
+    }
+
     #[test]
     fn update_modified_files() {
         // UX: Shows which files were modified
@@ -345,6 +657,15 @@ mod tests {
                 name: "".to_string(),
                 input: "".to_string(),
             },
+            Update::ToolProgress {
+                id: "".to_string(),
+                fraction: Some(0.5),
+                message: None,
+            },
+            Update::ToolOutput {
+                id: "".to_string(),
+                text: "".to_string(),
+            },
             Update::ToolCompleted {
                 id: "".to_string(),
                 success: true,
@@ -360,6 +681,7 @@ mod tests {
             Update::Status {
                 message: "".to_string(),
             },
+            Update::PromptQueued { position: 1 },
             Update::TokenUsage {
                 input: 0,
                 output: 0,
@@ -373,6 +695,10 @@ mod tests {
                 todos: vec![],
             },
             Update::LspUpdated { servers: vec![] },
+            Update::Symbols {
+                query: "".to_string(),
+                symbols: vec![],
+            },
             Update::McpUpdated { servers: vec![] },
             Update::ModifiedFilesUpdated { files: vec![] },
             Update::PermissionsPending { count: 0 },
@@ -381,12 +707,55 @@ mod tests {
                 runtime_type: None,
                 error: None,
             },
+            Update::SandboxResources {
+                cpu_percent: 0.0,
+                memory_bytes: 0,
+                memory_limit: 0,
+                pids: 0,
+            },
             Update::SystemMessage {
                 message: "".to_string(),
             },
             Update::AgentChanged {
                 agent: "".to_string(),
             },
+            Update::Memory {
+                content: "".to_string(),
+            },
+            Update::Templates { templates: vec![] },
+            Update::SessionSearchResults {
+                query: "".to_string(),
+                results: vec![],
+            },
+            Update::Orchestration {
+                orchestration_id: "".to_string(),
+                agents: vec![],
+            },
+            Update::PermissionAudit { entries: vec![] },
+            Update::Instructions { files: vec![] },
+            Update::BudgetWarning {
+                limit: "".to_string(),
+                used: 0.0,
+                max: 0.0,
+                message: "".to_string(),
+            },
+            Update::ConfigReloaded {
+                theme: None,
+                applied: vec![],
+                restart_required: vec![],
+            },
+            Update::SpendAlert {
+                scope: "".to_string(),
+                spent: 0.0,
+                alert: 0.0,
+                message: "".to_string(),
+            },
+            Update::Usage {
+                days: 7,
+                daily: vec![],
+                models: vec![],
+                tools: vec![],
+            },
             Update::PermissionRequest {
                 id: "".to_string(),
                 tool: "".to_string(),
@@ -394,6 +763,26 @@ mod tests {
                 description: "".to_string(),
                 path: None,
             },
+            Update::Projects { projects: vec![] },
+            Update::ProjectSwitched {
+                path: "".to_string(),
+                name: "".to_string(),
+            },
+            Update::Review {
+                diff: "".to_string(),
+            },
+            Update::ProcessStarted {
+                pid: 0,
+                command: "".to_string(),
+            },
+            Update::ProcessOutput {
+                pid: 0,
+                text: "".to_string(),
+            },
+            Update::ProcessExited {
+                pid: 0,
+                exit_code: None,
+            },
         ];
 
         for update in updates {
@@ -0,0 +1,222 @@
+//! Protocol versions and capabilities.
+//!
+//! A client posts its [`Handshake`] to [`HANDSHAKE_ENDPOINT`] when it
+//! connects. The server answers with the version both sides speak and the
+//! capabilities both have, or refuses a client it can't talk to, so a TUI and
+//! server of different releases fail up front with a clear error instead of
+//! somewhere in the middle of a session.
+//!
+//! [`PROTOCOL_VERSION`] goes up when a change breaks older peers, and
+//! [`MIN_PROTOCOL_VERSION`] when support for them is dropped. Features that
+//! older peers can do without are capabilities instead.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version of the protocol this build speaks.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest version of the protocol this build still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Path that a client posts its handshake to.
+pub const HANDSHAKE_ENDPOINT: &str = "/handshake";
+
+/// An optional feature of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Actions and updates over a WebSocket.
+    WebSocket,
+    /// SSE streams that resume from `Last-Event-ID`.
+    SseResume,
+    /// Git operations on the project.
+    Git,
+    /// Listing, reading and diffing project files.
+    Files,
+    /// Writing project files.
+    FileWrites,
+    /// Scoped sessions with their own runners.
+    ScopedSessions,
+    /// Prompts queued as jobs.
+    Jobs,
+    /// Sessions shared by several clients, one of whom drives.
+    Collaboration,
+    /// A capability of a newer peer that this build doesn't know.
+    #[serde(other)]
+    Unknown,
+}
+
+impl Capability {
+    /// The capabilities this build knows.
+    pub const ALL: &'static [Capability] = &[
+        Capability::WebSocket,
+        Capability::SseResume,
+        Capability::Git,
+        Capability::Files,
+        Capability::FileWrites,
+        Capability::ScopedSessions,
+        Capability::Jobs,
+        Capability::Collaboration,
+    ];
+}
+
+/// The protocol versions and capabilities of one side of a connection.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Handshake {
+    /// Newest protocol version spoken.
+    pub version: u32,
+
+    /// Oldest protocol version spoken.
+    pub min_version: u32,
+
+    /// Optional features.
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+}
+
+impl Handshake {
+    /// The versions of this build, with the given capabilities.
+    pub fn new(capabilities: Vec<Capability>) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            min_version: MIN_PROTOCOL_VERSION,
+            capabilities,
+        }
+    }
+
+    /// The version and capabilities this side and `peer` have in common.
+    pub fn negotiate(&self, peer: &Handshake) -> Result<Negotiated, VersionMismatch> {
+        if peer.version < self.min_version || self.version < peer.min_version {
+            return Err(VersionMismatch {
+                local: (self.min_version, self.version),
+                peer: (peer.min_version, peer.version),
+            });
+        }
+        let capabilities = self
+            .capabilities
+            .iter()
+            .copied()
+            .filter(|c| *c != Capability::Unknown && peer.capabilities.contains(c))
+            .collect();
+        Ok(Negotiated {
+            version: self.version.min(peer.version),
+            capabilities,
+        })
+    }
+}
+
+/// What both sides of a connection speak.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Negotiated {
+    /// Protocol version used.
+    pub version: u32,
+
+    /// Capabilities both sides have.
+    pub capabilities: Vec<Capability>,
+}
+
+impl Negotiated {
+    /// Whether both sides have a capability.
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+/// Two sides that have no protocol version in common.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// Oldest and newest version spoken by this side.
+    pub local: (u32, u32),
+    /// Oldest and newest version spoken by the other side.
+    pub peer: (u32, u32),
+}
+
+impl VersionMismatch {
+    /// Whether the other side is the older one, and so the one to upgrade.
+    pub fn peer_is_older(&self) -> bool {
+        self.peer.1 < self.local.0
+    }
+
+    /// Explain the mismatch, naming the sides, e.g. "client" and "server".
+    pub fn describe(&self, local: &str, peer: &str) -> String {
+        let range = |(min, max): (u32, u32)| {
+            if min == max {
+                format!("version {max}")
+            } else {
+                format!("versions {min} to {max}")
+            }
+        };
+        format!(
+            "the {peer} speaks protocol {}, but this {local} only speaks {}; upgrade the {}",
+            range(self.peer),
+            range(self.local),
+            if self.peer_is_older() { peer } else { local }
+        )
+    }
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.describe("side", "other side"))
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(min_version: u32, version: u32, capabilities: &[Capability]) -> Handshake {
+        Handshake {
+            version,
+            min_version,
+            capabilities: capabilities.to_vec(),
+        }
+    }
+
+    #[test]
+    fn negotiates_the_common_version_and_capabilities() {
+        let server = handshake(1, 3, &[Capability::Git, Capability::Jobs]);
+        let client = handshake(2, 2, &[Capability::Git, Capability::Files]);
+        let negotiated = server.negotiate(&client).unwrap();
+        assert_eq!(negotiated.version, 2);
+        assert_eq!(negotiated.capabilities, vec![Capability::Git]);
+        assert!(negotiated.supports(Capability::Git));
+        assert!(!negotiated.supports(Capability::Jobs));
+    }
+
+    #[test]
+    fn refuses_peers_without_a_common_version() {
+        let server = handshake(2, 3, &[]);
+        let old_client = handshake(1, 1, &[]);
+        let mismatch = server.negotiate(&old_client).unwrap_err();
+        assert!(mismatch.peer_is_older());
+        assert_eq!(
+            mismatch.describe("server", "client"),
+            "the client speaks protocol version 1, but this server only speaks \
+             versions 2 to 3; upgrade the client"
+        );
+
+        let new_client = handshake(4, 4, &[]);
+        assert!(!server.negotiate(&new_client).unwrap_err().peer_is_older());
+    }
+
+    #[test]
+    fn unknown_capabilities_are_tolerated() {
+        let json = r#"{"version":1,"min_version":1,"capabilities":["git","teleport"]}"#;
+        let peer: Handshake = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            peer.capabilities,
+            vec![Capability::Git, Capability::Unknown]
+        );
+        let negotiated = Handshake::new(Capability::ALL.to_vec())
+            .negotiate(&peer)
+            .unwrap();
+        assert_eq!(negotiated.capabilities, vec![Capability::Git]);
+    }
+}
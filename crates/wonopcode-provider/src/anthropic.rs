@@ -410,16 +410,20 @@ impl LanguageModel for AnthropicProvider {
 
         if !response.status().is_success() {
             let status = response.status();
+            let retry_after = response
+                .headers()
+                .get("retry-after")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(std::time::Duration::from_secs);
             let body = response.text().await.unwrap_or_default();
             warn!(status = %status, body = %body, "Anthropic request failed");
 
             if status.as_u16() == 429 {
-                return Err(ProviderError::RateLimited { retry_after: None });
+                return Err(ProviderError::RateLimited { retry_after });
             }
 
-            return Err(ProviderError::invalid_response(format!(
-                "HTTP {status}: {body}"
-            )));
+            return Err(ProviderError::api_error(status.as_u16(), body));
         }
 
         Ok(self.parse_stream(response, options.abort))
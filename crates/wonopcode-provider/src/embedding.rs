@@ -0,0 +1,228 @@
+//! Text embeddings.
+//!
+//! Embedding models turn text into vectors whose cosine similarity reflects
+//! how related the texts are. [`OpenAIEmbeddings`] implements the OpenAI
+//! embeddings API, which OpenAI-compatible servers such as Ollama also serve.
+
+use crate::{error::ProviderError, ProviderResult};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Number of texts sent per request.
+const BATCH_SIZE: usize = 64;
+
+/// Longest text sent for embedding, in characters; longer texts are cut off.
+const MAX_INPUT_CHARS: usize = 24_000;
+
+/// A model that embeds text.
+#[async_trait]
+pub trait EmbeddingModel: Send + Sync {
+    /// Embed texts, returning one vector per text in the same order.
+    async fn embed(&self, texts: &[String]) -> ProviderResult<Vec<Vec<f32>>>;
+
+    /// Get the model ID.
+    fn model_id(&self) -> &str;
+}
+
+/// A boxed embedding model for dynamic dispatch.
+pub type BoxedEmbeddingModel = Arc<dyn EmbeddingModel>;
+
+/// Embeddings through the OpenAI embeddings API.
+pub struct OpenAIEmbeddings {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+}
+
+impl OpenAIEmbeddings {
+    /// Create an embedding model for a provider with an OpenAI-compatible
+    /// embeddings endpoint (openai, mistral, together or deepinfra).
+    pub fn for_provider(provider: &str, api_key: &str, model: &str) -> ProviderResult<Self> {
+        let base_url = match provider {
+            "openai" => "https://api.openai.com/v1",
+            "mistral" => "https://api.mistral.ai/v1",
+            "together" => "https://api.together.xyz/v1",
+            "deepinfra" => "https://api.deepinfra.com/v1/openai",
+            _ => {
+                return Err(ProviderError::internal(format!(
+                    "Provider '{provider}' has no embeddings API"
+                )))
+            }
+        };
+        Self::with_base_url(api_key, base_url, model)
+    }
+
+    /// Create an embedding model for an OpenAI-compatible server.
+    ///
+    /// The API key may be empty for servers that don't need one.
+    pub fn with_base_url(api_key: &str, base_url: &str, model: &str) -> ProviderResult<Self> {
+        let mut headers = HeaderMap::new();
+        if !api_key.is_empty() {
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {api_key}"))
+                    .map_err(|_| ProviderError::invalid_api_key("embeddings"))?,
+            );
+        }
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .map_err(|e| ProviderError::internal(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+        })
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> ProviderResult<Vec<Vec<f32>>> {
+        let input: Vec<&str> = texts.iter().map(|t| truncate(t)).collect();
+        let response = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&json!({ "model": self.model, "input": input }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            warn!(status = %status, error = %error_text, "Embeddings API error");
+            return Err(ProviderError::api_error(status.as_u16(), error_text));
+        }
+
+        let body: EmbeddingResponse = response.json().await?;
+        parse_embeddings(body, texts.len())
+    }
+}
+
+#[async_trait]
+impl EmbeddingModel for OpenAIEmbeddings {
+    async fn embed(&self, texts: &[String]) -> ProviderResult<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(BATCH_SIZE) {
+            embeddings.extend(self.embed_batch(batch).await?);
+        }
+        Ok(embeddings)
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Order the embeddings of a response by input index.
+fn parse_embeddings(response: EmbeddingResponse, expected: usize) -> ProviderResult<Vec<Vec<f32>>> {
+    let mut embeddings = vec![Vec::new(); expected];
+    for data in response.data {
+        match embeddings.get_mut(data.index) {
+            Some(slot) => *slot = data.embedding,
+            None => {
+                return Err(ProviderError::invalid_response(format!(
+                    "Embedding index {} out of range",
+                    data.index
+                )))
+            }
+        }
+    }
+    if embeddings.iter().any(Vec::is_empty) {
+        return Err(ProviderError::invalid_response(
+            "Missing embeddings in response",
+        ));
+    }
+    Ok(embeddings)
+}
+
+/// Cut a text off at [`MAX_INPUT_CHARS`], on a character boundary.
+fn truncate(text: &str) -> &str {
+    match text.char_indices().nth(MAX_INPUT_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Cosine similarity of two vectors, 0.0 if either is empty or zero.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let mut dot = 0.0;
+    let mut norm_a = 0.0;
+    let mut norm_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_parse_embeddings_orders_by_index() {
+        let response: EmbeddingResponse = serde_json::from_value(json!({
+            "data": [
+                { "index": 1, "embedding": [0.0, 1.0] },
+                { "index": 0, "embedding": [1.0, 0.0] }
+            ]
+        }))
+        .unwrap();
+        let embeddings = parse_embeddings(response, 2).unwrap();
+        assert_eq!(embeddings, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    }
+
+    #[test]
+    fn test_parse_embeddings_rejects_missing() {
+        let response: EmbeddingResponse = serde_json::from_value(json!({
+            "data": [{ "index": 0, "embedding": [1.0] }]
+        }))
+        .unwrap();
+        assert!(parse_embeddings(response, 2).is_err());
+    }
+
+    #[test]
+    fn test_for_provider() {
+        assert!(OpenAIEmbeddings::for_provider("openai", "key", "text-embedding-3-small").is_ok());
+        assert!(OpenAIEmbeddings::for_provider("anthropic", "key", "model").is_err());
+    }
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        let text = "é".repeat(MAX_INPUT_CHARS + 10);
+        assert_eq!(truncate(&text).chars().count(), MAX_INPUT_CHARS);
+        assert_eq!(truncate("short"), "short");
+    }
+}
@@ -16,6 +16,7 @@
 //! - Together AI
 //! - OpenAI-compatible custom providers
 
+pub mod embedding;
 pub mod error;
 pub mod message;
 pub mod model;
@@ -51,6 +52,7 @@ pub mod models_dev;
 pub mod mock;
 pub mod test;
 
+pub use embedding::{cosine_similarity, BoxedEmbeddingModel, EmbeddingModel, OpenAIEmbeddings};
 pub use error::{ProviderError, ProviderResult};
 pub use message::{ContentPart, Message, Role};
 pub use model::{ModelCapabilities, ModelCost, ModelInfo, ModelLimit};
@@ -2,6 +2,8 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use wonopcode_util::size::parse_size;
+use wonopcode_util::wildcard;
 
 /// Main sandbox configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,7 +15,8 @@ pub struct SandboxConfig {
     /// Sandbox runtime type
     pub runtime: SandboxRuntimeType,
 
-    /// Container image to use (default: wonopcode/sandbox:latest)
+    /// Container image to use (default: wonopcode/sandbox:latest), or
+    /// "auto" to build one from the project's Dockerfile
     pub image: Option<String>,
 
     /// Resource limits
@@ -36,6 +39,10 @@ pub struct SandboxConfig {
     /// Timeout for sandbox startup in seconds
     #[serde(default = "default_startup_timeout")]
     pub startup_timeout_secs: u64,
+
+    /// Hosts the commands may or may not reach, on top of the network policy
+    #[serde(default)]
+    pub hosts: Vec<HostRule>,
 }
 
 impl Default for SandboxConfig {
@@ -54,6 +61,7 @@ impl Default for SandboxConfig {
             ],
             keep_alive: true,
             startup_timeout_secs: 60,
+            hosts: Vec::new(),
         }
     }
 }
@@ -64,11 +72,27 @@ impl SandboxConfig {
         self.image.as_deref().unwrap_or(DEFAULT_IMAGE)
     }
 
+    /// Check if the image is built from the project
+    pub fn builds_image(&self) -> bool {
+        self.image() == crate::AUTO_IMAGE
+    }
+
     /// Check if a tool should bypass the sandbox
     pub fn should_bypass(&self, tool_name: &str) -> bool {
         self.bypass_tools.iter().any(|t| t == tool_name)
     }
 
+    /// The hosts `command` may or may not reach, from the host rules
+    /// matching it
+    pub fn host_filter(&self, command: &str) -> HostFilter {
+        let mut filter = HostFilter::default();
+        for rule in self.hosts.iter().filter(|rule| rule.matches(command)) {
+            filter.allow.extend(rule.hosts.allow.iter().cloned());
+            filter.deny.extend(rule.hosts.deny.iter().cloned());
+        }
+        filter
+    }
+
     /// Merge with another config (other takes precedence)
     pub fn merge(self, other: Self) -> Self {
         Self {
@@ -89,6 +113,11 @@ impl SandboxConfig {
             },
             keep_alive: other.keep_alive,
             startup_timeout_secs: other.startup_timeout_secs,
+            hosts: if other.hosts.is_empty() {
+                self.hosts
+            } else {
+                other.hosts
+            },
         }
     }
 }
@@ -113,6 +142,8 @@ pub enum SandboxRuntimeType {
     Podman,
     /// Lima runtime (macOS only)
     Lima,
+    /// WebAssembly runtime (wasmtime), for WASI modules only
+    Wasm,
     /// No sandboxing (passthrough)
     None,
 }
@@ -230,6 +261,44 @@ impl NetworkPolicy {
     }
 }
 
+/// Hosts one execution may or may not reach, on top of the network policy.
+///
+/// Entries are host names, IP addresses or CIDR ranges like `10.0.0.0/8`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HostFilter {
+    /// Only these hosts may be reached, unless empty
+    pub allow: Vec<String>,
+    /// These hosts may not be reached
+    pub deny: Vec<String>,
+}
+
+impl HostFilter {
+    /// Check if the filter restricts nothing
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
+
+/// Hosts the commands matching a pattern may or may not reach.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HostRule {
+    /// Command pattern, where `*` matches any text, like `npm *`; all
+    /// commands if empty
+    pub command: String,
+    /// The hosts
+    #[serde(flatten)]
+    pub hosts: HostFilter,
+}
+
+impl HostRule {
+    /// Check if the rule covers `command`
+    pub fn matches(&self, command: &str) -> bool {
+        self.command.is_empty() || wildcard::matches(&self.command, command.trim())
+    }
+}
+
 /// Mount configuration for the sandbox.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -286,6 +355,8 @@ pub struct SandboxCapabilities {
     pub read_only_workspace: bool,
     /// Allow privileged operations (not recommended)
     pub privileged: bool,
+    /// Hosts the command may or may not reach
+    pub hosts: HostFilter,
 }
 
 // Helper functions
@@ -298,24 +369,6 @@ fn default_startup_timeout() -> u64 {
     60
 }
 
-/// Parse a size string like "2G" or "512M" to bytes
-fn parse_size(s: &str) -> Option<u64> {
-    let s = s.trim().to_uppercase();
-    let (num_str, multiplier) = if let Some(n) = s.strip_suffix('G') {
-        (n, 1024 * 1024 * 1024)
-    } else if let Some(n) = s.strip_suffix('M') {
-        (n, 1024 * 1024)
-    } else if let Some(n) = s.strip_suffix('K') {
-        (n, 1024)
-    } else if let Some(n) = s.strip_suffix('B') {
-        (n, 1)
-    } else {
-        (s.as_str(), 1)
-    };
-
-    num_str.trim().parse::<u64>().ok().map(|n| n * multiplier)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -337,6 +390,31 @@ mod tests {
         assert_eq!(config.image(), DEFAULT_IMAGE);
     }
 
+    #[test]
+    fn test_host_filter() {
+        let config: SandboxConfig = serde_json::from_str(
+            r#"{
+                "hosts": [
+                    {"deny": ["169.254.169.254"]},
+                    {"command": "npm *", "allow": ["registry.npmjs.org"]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let npm = config.host_filter("  npm install ");
+        assert_eq!(npm.allow, vec!["registry.npmjs.org"]);
+        assert_eq!(npm.deny, vec!["169.254.169.254"]);
+
+        let other = config.host_filter("curl example.com");
+        assert!(other.allow.is_empty());
+        assert_eq!(other.deny, vec!["169.254.169.254"]);
+
+        assert!(SandboxConfig::default()
+            .host_filter("npm install")
+            .is_empty());
+    }
+
     #[test]
     fn test_should_bypass() {
         let config = SandboxConfig::default();
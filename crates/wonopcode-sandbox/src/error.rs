@@ -14,6 +14,10 @@ pub enum SandboxError {
     #[error("failed to pull image '{image}': {message}")]
     ImagePullFailed { image: String, message: String },
 
+    /// Failed to build container image
+    #[error("failed to build image '{image}': {message}")]
+    ImageBuildFailed { image: String, message: String },
+
     /// Container image not found
     #[error("image not found: {0}")]
     ImageNotFound(String),
@@ -50,6 +54,10 @@ pub enum SandboxError {
     #[error("command timed out after {0:?}")]
     Timeout(std::time::Duration),
 
+    /// Command was killed before it exited
+    #[error("command was killed")]
+    Killed,
+
     /// File not found in sandbox
     #[error("file not found: {0}")]
     FileNotFound(PathBuf),
@@ -121,6 +129,14 @@ impl SandboxError {
         }
     }
 
+    /// Create an image build failed error
+    pub fn image_build_failed(image: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::ImageBuildFailed {
+            image: image.into(),
+            message: message.into(),
+        }
+    }
+
     /// Create a read failed error
     pub fn read_failed(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
         Self::ReadFailed {
@@ -0,0 +1,342 @@
+//! Commands whose output is read as they run.
+//!
+//! [`SandboxRuntime::execute_streaming`](crate::SandboxRuntime::execute_streaming)
+//! returns a [`SandboxExecution`], a stream of the output a command writes,
+//! which can be killed and waited for. The runtime drives the command through
+//! the [`ExecutionFeed`] going with it.
+
+use crate::{SandboxError, SandboxOutput, SandboxResult};
+use futures::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Child;
+use tokio::sync::{mpsc, oneshot, Notify};
+
+/// Environment variable tagging the processes of a command, so that they can
+/// be found to kill them.
+pub(crate) const MARKER_VAR: &str = "WONOPCODE_EXEC";
+
+/// Timeout for killing the processes of a command.
+pub(crate) const KILL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A chunk of the output of a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputChunk {
+    /// Text written to stdout.
+    Stdout(String),
+    /// Text written to stderr.
+    Stderr(String),
+}
+
+impl OutputChunk {
+    /// The text written.
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Stdout(text) | Self::Stderr(text) => text,
+        }
+    }
+}
+
+/// Kills a running command.
+#[derive(Debug, Clone, Default)]
+pub struct KillHandle(Arc<Notify>);
+
+impl KillHandle {
+    /// Kill the command. Does nothing once it exited.
+    pub fn kill(&self) {
+        self.0.notify_one();
+    }
+
+    /// Wait until the command is killed or runs out of time, returning the
+    /// error it ends with.
+    pub(crate) async fn stopped(&self, timeout: Duration) -> SandboxError {
+        tokio::select! {
+            () = self.0.notified() => SandboxError::Killed,
+            () = tokio::time::sleep(timeout) => SandboxError::Timeout(timeout),
+        }
+    }
+}
+
+/// A command running in the sandbox.
+///
+/// Streams the command's output as it is written, and ends when the command
+/// exits. Dropping the execution before it finished kills the command.
+pub struct SandboxExecution {
+    output: mpsc::UnboundedReceiver<OutputChunk>,
+    result: Option<oneshot::Receiver<SandboxResult<SandboxOutput>>>,
+    pid: Option<u32>,
+    kill: KillHandle,
+}
+
+impl SandboxExecution {
+    /// A new execution, and the feed the runtime drives it through.
+    ///
+    /// `pid` is the ID of the process running the command, if there is one
+    /// on the host.
+    pub(crate) fn channel(pid: Option<u32>) -> (Self, ExecutionFeed) {
+        let (output_tx, output) = mpsc::unbounded_channel();
+        let (result_tx, result) = oneshot::channel();
+        let kill = KillHandle::default();
+        let execution = Self {
+            output,
+            result: Some(result),
+            pid,
+            kill: kill.clone(),
+        };
+        let feed = ExecutionFeed {
+            output: output_tx,
+            result: result_tx,
+            kill,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            stdout_sent: 0,
+            stderr_sent: 0,
+        };
+        (execution, feed)
+    }
+
+    /// An execution of a command that already exited, for runtimes that
+    /// can't stream output. The whole output arrives in one piece.
+    pub fn finished(output: SandboxOutput) -> Self {
+        let (execution, mut feed) = Self::channel(None);
+        feed.stdout(output.stdout.as_bytes());
+        feed.stderr(output.stderr.as_bytes());
+        feed.finish(Ok(output.exit_code));
+        execution
+    }
+
+    /// ID of the host process running the command, if any.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// A handle to kill the command with, usable while the execution is
+    /// being read.
+    pub fn kill_handle(&self) -> KillHandle {
+        self.kill.clone()
+    }
+
+    /// Kill the command.
+    pub fn kill(&self) {
+        self.kill.kill();
+    }
+
+    /// Wait for the command to exit, returning its whole output.
+    ///
+    /// Fails with [`SandboxError::Killed`] if it was killed, and
+    /// [`SandboxError::Timeout`] if it ran out of time.
+    pub async fn wait(mut self) -> SandboxResult<SandboxOutput> {
+        let result = self.result.take().expect("waited for once");
+        result.await.unwrap_or_else(|_| {
+            Err(SandboxError::ExecFailed(
+                "command ended without a result".to_string(),
+            ))
+        })
+    }
+}
+
+impl Stream for SandboxExecution {
+    type Item = OutputChunk;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.output.poll_recv(cx)
+    }
+}
+
+impl Drop for SandboxExecution {
+    fn drop(&mut self) {
+        // Nobody is left to wait for the command
+        if self.result.is_some() {
+            self.kill.kill();
+        }
+    }
+}
+
+/// The runtime's end of a [`SandboxExecution`].
+pub(crate) struct ExecutionFeed {
+    output: mpsc::UnboundedSender<OutputChunk>,
+    result: oneshot::Sender<SandboxResult<SandboxOutput>>,
+    kill: KillHandle,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    /// Bytes of stdout sent as text, short of a character cut off at the end.
+    stdout_sent: usize,
+    /// Bytes of stderr sent as text.
+    stderr_sent: usize,
+}
+
+impl ExecutionFeed {
+    /// The handle killing the command.
+    pub(crate) fn kill_handle(&self) -> KillHandle {
+        self.kill.clone()
+    }
+
+    /// Pass on what the command wrote to stdout.
+    pub(crate) fn stdout(&mut self, bytes: &[u8]) {
+        if let Some(text) = append(&mut self.stdout, &mut self.stdout_sent, bytes) {
+            let _ = self.output.send(OutputChunk::Stdout(text));
+        }
+    }
+
+    /// Pass on what the command wrote to stderr.
+    pub(crate) fn stderr(&mut self, bytes: &[u8]) {
+        if let Some(text) = append(&mut self.stderr, &mut self.stderr_sent, bytes) {
+            let _ = self.output.send(OutputChunk::Stderr(text));
+        }
+    }
+
+    /// End the execution with the command's exit code, or the error it
+    /// failed with.
+    pub(crate) fn finish(self, exit_code: SandboxResult<i32>) {
+        let result = exit_code.map(|exit_code| {
+            // Characters cut off at the very end are left as they are
+            if self.stdout_sent < self.stdout.len() {
+                let text = String::from_utf8_lossy(&self.stdout[self.stdout_sent..]);
+                let _ = self.output.send(OutputChunk::Stdout(text.into_owned()));
+            }
+            if self.stderr_sent < self.stderr.len() {
+                let text = String::from_utf8_lossy(&self.stderr[self.stderr_sent..]);
+                let _ = self.output.send(OutputChunk::Stderr(text.into_owned()));
+            }
+            SandboxOutput::from_output(
+                String::from_utf8_lossy(&self.stdout).into_owned(),
+                String::from_utf8_lossy(&self.stderr).into_owned(),
+                exit_code,
+            )
+        });
+        let _ = self.result.send(result);
+    }
+
+    /// Pass on the output of a child process until it exits, returning its
+    /// exit code.
+    pub(crate) async fn read_child(&mut self, child: &mut Child) -> SandboxResult<i32> {
+        let mut stdout = child.stdout.take();
+        let mut stderr = child.stderr.take();
+        let mut stdout_buf = [0u8; 8192];
+        let mut stderr_buf = [0u8; 8192];
+
+        while stdout.is_some() || stderr.is_some() {
+            tokio::select! {
+                n = read(&mut stdout, &mut stdout_buf), if stdout.is_some() => match n {
+                    Some(n) => self.stdout(&stdout_buf[..n]),
+                    None => stdout = None,
+                },
+                n = read(&mut stderr, &mut stderr_buf), if stderr.is_some() => match n {
+                    Some(n) => self.stderr(&stderr_buf[..n]),
+                    None => stderr = None,
+                },
+            }
+        }
+
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
+        Ok(status.code().unwrap_or(-1))
+    }
+}
+
+/// Read from a pipe, or `None` at its end.
+async fn read(pipe: &mut Option<impl AsyncRead + Unpin>, buf: &mut [u8]) -> Option<usize> {
+    match pipe.as_mut()?.read(buf).await {
+        Ok(0) | Err(_) => None,
+        Ok(n) => Some(n),
+    }
+}
+
+/// Append `bytes` to `buf`, returning the text from `sent` on, short of a
+/// character cut off at the end.
+fn append(buf: &mut Vec<u8>, sent: &mut usize, bytes: &[u8]) -> Option<String> {
+    buf.extend_from_slice(bytes);
+    let unsent = &buf[*sent..];
+    let complete = match std::str::from_utf8(unsent) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => unsent.len(),
+    };
+    if complete == 0 {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&unsent[..complete]).into_owned();
+    *sent += complete;
+    Some(text)
+}
+
+/// Shell script killing the processes tagged with `marker`, found by their
+/// environment, so that children of the command go too.
+pub(crate) fn kill_script(marker: &str) -> String {
+    format!(
+        "for dir in /proc/[0-9]*; do \
+         if tr '\\0' '\\n' < \"$dir/environ\" 2>/dev/null | grep -qx '{MARKER_VAR}={marker}'; then \
+         kill -KILL \"${{dir#/proc/}}\" 2>/dev/null; \
+         fi; \
+         done; true"
+    )
+}
+
+/// A new marker to tag the processes of a command with.
+pub(crate) fn new_marker() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_feed_keeps_characters_whole() {
+        let (mut execution, mut feed) = SandboxExecution::channel(None);
+        let bytes = "héllo".as_bytes();
+        feed.stdout(&bytes[..2]);
+        feed.stdout(&bytes[2..]);
+        feed.stderr(b"oops");
+        feed.finish(Ok(3));
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = execution.next().await {
+            chunks.push(chunk);
+        }
+        assert_eq!(
+            chunks,
+            vec![
+                OutputChunk::Stdout("h".to_string()),
+                OutputChunk::Stdout("éllo".to_string()),
+                OutputChunk::Stderr("oops".to_string()),
+            ]
+        );
+
+        let output = execution.wait().await.unwrap();
+        assert_eq!(output.stdout, "héllo");
+        assert_eq!(output.stderr, "oops");
+        assert_eq!(output.exit_code, 3);
+        assert!(!output.success);
+    }
+
+    #[tokio::test]
+    async fn test_kill_handle_stops_execution() {
+        let (execution, feed) = SandboxExecution::channel(None);
+        let kill = feed.kill_handle();
+        execution.kill_handle().kill();
+        assert!(matches!(
+            kill.stopped(Duration::from_secs(10)).await,
+            SandboxError::Killed
+        ));
+
+        let timeout = Duration::from_millis(10);
+        assert!(matches!(
+            kill.stopped(timeout).await,
+            SandboxError::Timeout(_)
+        ));
+        drop(execution);
+    }
+
+    #[test]
+    fn test_kill_script_matches_marker() {
+        let script = kill_script("abc123");
+        assert!(script.contains("grep -qx 'WONOPCODE_EXEC=abc123'"));
+        assert!(script.contains("kill -KILL \"${dir#/proc/}\""));
+    }
+}
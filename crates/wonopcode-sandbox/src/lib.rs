@@ -6,6 +6,8 @@
 //! - **Docker**: Container-based isolation (Linux, macOS, Windows)
 //! - **Podman**: Rootless container alternative to Docker
 //! - **Lima**: Lightweight VM-based isolation (macOS)
+//! - **Wasm**: WASI modules run with an embedded wasmtime, without a container
+//!   engine (`wasm` feature)
 //! - **Passthrough**: No isolation (for trusted environments)
 //!
 //! # Example
@@ -47,22 +49,27 @@
 
 pub mod config;
 pub mod error;
+pub mod execution;
 pub mod monitor;
 pub mod path;
+pub mod project_image;
 pub mod runtime;
 
 pub use config::{
-    MountConfig, NetworkPolicy, ResourceLimits, SandboxCapabilities, SandboxConfig,
-    SandboxRuntimeType, DEFAULT_IMAGE,
+    HostFilter, HostRule, MountConfig, NetworkPolicy, ResourceLimits, SandboxCapabilities,
+    SandboxConfig, SandboxRuntimeType, DEFAULT_IMAGE,
 };
 pub use error::{SandboxError, SandboxResult};
+pub use execution::{KillHandle, OutputChunk, SandboxExecution};
 pub use monitor::{ContinuousMonitor, ResourceEvent, ResourceMonitor, ResourceStats};
 pub use path::PathMapper;
+pub use project_image::{ImageBuild, ProjectImage, AUTO_IMAGE};
 pub use runtime::SandboxManager;
 
 use async_trait::async_trait;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// Result of command execution in sandbox.
 #[derive(Debug, Clone)]
@@ -217,6 +224,15 @@ impl std::fmt::Display for SandboxStatus {
     }
 }
 
+/// Progress of a sandbox starting, such as pulling its image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartProgress {
+    /// Share of the work done, from 0 to 1, if known.
+    pub fraction: Option<f64>,
+    /// What the sandbox is doing.
+    pub message: String,
+}
+
 /// Information about the sandbox.
 #[derive(Debug, Clone)]
 pub struct SandboxInfo {
@@ -279,6 +295,17 @@ pub trait SandboxRuntime: Send + Sync {
     /// If the sandbox is already running, this is a no-op.
     async fn start(&self) -> SandboxResult<()>;
 
+    /// Start the sandbox, reporting its progress, such as an image pull.
+    ///
+    /// Runtimes that can't report progress just start.
+    async fn start_with_progress(
+        &self,
+        progress: mpsc::UnboundedSender<StartProgress>,
+    ) -> SandboxResult<()> {
+        let _ = progress;
+        self.start().await
+    }
+
     /// Stop the sandbox.
     ///
     /// This terminates the container/VM. Any running processes will be killed.
@@ -292,6 +319,16 @@ pub trait SandboxRuntime: Send + Sync {
         self.start().await
     }
 
+    /// The capabilities a command gets, with the hosts it may or may not
+    /// reach from the host rules of the config.
+    ///
+    /// Runtimes that don't enforce host filters give every command the
+    /// default capabilities.
+    fn capabilities_for(&self, command: &str) -> SandboxCapabilities {
+        let _ = command;
+        SandboxCapabilities::default()
+    }
+
     /// Execute a shell command in the sandbox.
     ///
     /// # Arguments
@@ -312,6 +349,27 @@ pub trait SandboxRuntime: Send + Sync {
         capabilities: &SandboxCapabilities,
     ) -> SandboxResult<SandboxOutput>;
 
+    /// Start a shell command in the sandbox, streaming its output.
+    ///
+    /// Takes the same arguments as [`execute`](Self::execute). The returned
+    /// execution yields the output as the command writes it, can kill the
+    /// command, and is waited for to get the result `execute` returns.
+    ///
+    /// Runtimes that can't stream output run the command to the end, and
+    /// return its whole output at once.
+    async fn execute_streaming(
+        &self,
+        command: &str,
+        workdir: &Path,
+        timeout: Duration,
+        capabilities: &SandboxCapabilities,
+    ) -> SandboxResult<SandboxExecution> {
+        let output = self
+            .execute(command, workdir, timeout, capabilities)
+            .await?;
+        Ok(SandboxExecution::finished(output))
+    }
+
     /// Read a file from the sandbox.
     ///
     /// # Arguments
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::process::Command;
 use tracing::debug;
+use wonopcode_util::size::format_bytes;
 
 /// Resource usage statistics for a sandbox.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -213,23 +214,6 @@ fn parse_size_string(s: &str) -> u64 {
     (num * multiplier as f64) as u64
 }
 
-/// Format bytes as human-readable string.
-fn format_bytes(bytes: u64) -> String {
-    const KIB: u64 = 1024;
-    const MIB: u64 = KIB * 1024;
-    const GIB: u64 = MIB * 1024;
-
-    if bytes >= GIB {
-        format!("{:.1}GiB", bytes as f64 / GIB as f64)
-    } else if bytes >= MIB {
-        format!("{:.1}MiB", bytes as f64 / MIB as f64)
-    } else if bytes >= KIB {
-        format!("{:.1}KiB", bytes as f64 / KIB as f64)
-    } else {
-        format!("{bytes}B")
-    }
-}
-
 /// Resource usage event for monitoring callbacks.
 #[derive(Debug, Clone)]
 pub enum ResourceEvent {
@@ -326,11 +310,11 @@ mod tests {
 
     #[test]
     fn test_format_bytes() {
-        assert_eq!(format_bytes(0), "0B");
-        assert_eq!(format_bytes(512), "512B");
-        assert_eq!(format_bytes(1024), "1.0KiB");
-        assert_eq!(format_bytes(1048576), "1.0MiB");
-        assert_eq!(format_bytes(1073741824), "1.0GiB");
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1024), "1.0 KiB");
+        assert_eq!(format_bytes(1048576), "1.0 MiB");
+        assert_eq!(format_bytes(1073741824), "1.0 GiB");
     }
 
     #[test]
@@ -0,0 +1,555 @@
+//! Sandbox images built from the project.
+//!
+//! With `image: "auto"`, the container runtimes build the sandbox image from
+//! the project's devcontainer configuration or Dockerfile, looked up in this
+//! order:
+//!
+//! 1. `.devcontainer/devcontainer.json`
+//! 2. `.devcontainer.json`
+//! 3. `Dockerfile`
+//!
+//! Built images are tagged with a hash of what went into them: the
+//! Dockerfile, the build arguments and target, and the files it copies from
+//! the build context, minus those excluded by `.dockerignore`. They're only
+//! rebuilt when that hash changes.
+
+use crate::{SandboxError, SandboxResult};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+/// Image setting that asks for an image built from the project.
+pub const AUTO_IMAGE: &str = "auto";
+
+/// Number of output lines of a failed build kept in the error.
+const ERROR_TAIL_LINES: usize = 20;
+
+/// The image for a sandbox with `image: "auto"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProjectImage {
+    /// An image the devcontainer configuration names, pulled as usual.
+    Named(String),
+    /// An image built from a Dockerfile.
+    Build(ImageBuild),
+}
+
+/// How to build an image from a Dockerfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageBuild {
+    /// Tag of the built image, ending in its content hash.
+    pub tag: String,
+    /// The Dockerfile.
+    pub dockerfile: PathBuf,
+    /// The build context directory.
+    pub context: PathBuf,
+    /// Build arguments.
+    pub args: BTreeMap<String, String>,
+    /// Build stage to stop at.
+    pub target: Option<String>,
+}
+
+/// The parts of a devcontainer.json that say where the image comes from.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DevContainer {
+    image: Option<String>,
+    build: Option<DevContainerBuild>,
+    /// Older spelling of `build.dockerfile`.
+    docker_file: Option<String>,
+    /// Older spelling of `build.context`.
+    context: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DevContainerBuild {
+    dockerfile: Option<String>,
+    context: Option<String>,
+    #[serde(default)]
+    args: BTreeMap<String, String>,
+    target: Option<String>,
+}
+
+impl ProjectImage {
+    /// Find the image for the project in `root`. Built images are named
+    /// `name`, and tagged with their content hash.
+    pub fn resolve(root: &Path, name: &str) -> SandboxResult<Self> {
+        for path in [
+            root.join(".devcontainer").join("devcontainer.json"),
+            root.join(".devcontainer.json"),
+        ] {
+            if path.is_file() {
+                return Self::from_devcontainer(&path, name);
+            }
+        }
+
+        let dockerfile = root.join("Dockerfile");
+        if dockerfile.is_file() {
+            return ImageBuild::new(name, dockerfile, root.to_path_buf(), BTreeMap::new(), None)
+                .map(Self::Build);
+        }
+
+        Err(SandboxError::ConfigError(format!(
+            "sandbox image is \"{AUTO_IMAGE}\", but {} has no devcontainer.json or Dockerfile",
+            root.display()
+        )))
+    }
+
+    /// The image described by a devcontainer.json.
+    fn from_devcontainer(path: &Path, name: &str) -> SandboxResult<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let devcontainer: DevContainer = serde_json::from_str(&strip_jsonc(&content))
+            .map_err(|e| SandboxError::ConfigError(format!("{}: {e}", path.display())))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        let build = devcontainer.build.unwrap_or_default();
+        let dockerfile = build.dockerfile.or(devcontainer.docker_file);
+        let Some(dockerfile) = dockerfile else {
+            return match devcontainer.image {
+                Some(image) => Ok(Self::Named(image)),
+                None => Err(SandboxError::ConfigError(format!(
+                    "{} names neither an image nor a Dockerfile",
+                    path.display()
+                ))),
+            };
+        };
+
+        // Both are relative to the devcontainer.json
+        let context = build
+            .context
+            .or(devcontainer.context)
+            .unwrap_or_else(|| ".".to_string());
+        ImageBuild::new(
+            name,
+            dir.join(dockerfile),
+            dir.join(context),
+            build.args,
+            build.target,
+        )
+        .map(Self::Build)
+    }
+
+    /// The name of the image to run.
+    pub fn image(&self) -> &str {
+        match self {
+            Self::Named(image) => image,
+            Self::Build(build) => &build.tag,
+        }
+    }
+}
+
+impl ImageBuild {
+    fn new(
+        name: &str,
+        dockerfile: PathBuf,
+        context: PathBuf,
+        args: BTreeMap<String, String>,
+        target: Option<String>,
+    ) -> SandboxResult<Self> {
+        let mut build = Self {
+            tag: String::new(),
+            dockerfile,
+            context,
+            args,
+            target,
+        };
+        let hash = build.content_hash()?;
+        build.tag = format!("{name}:{}", &hash[..16]);
+        Ok(build)
+    }
+
+    /// Hash of everything that goes into the image.
+    fn content_hash(&self) -> SandboxResult<String> {
+        let dockerfile = std::fs::read(&self.dockerfile)
+            .map_err(|e| SandboxError::read_failed(&self.dockerfile, e.to_string()))?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(&dockerfile);
+        for (key, value) in &self.args {
+            hasher.update(format!("\0arg {key}={value}"));
+        }
+        if let Some(target) = &self.target {
+            hasher.update(format!("\0target {target}"));
+        }
+        let sources = copied_files(&String::from_utf8_lossy(&dockerfile));
+        for file in context_files(&self.context, &sources) {
+            if let Ok(content) = std::fs::read(self.context.join(&file)) {
+                hasher.update(format!("\0file {file}\0"));
+                hasher.update(&content);
+            }
+        }
+
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+
+    /// Arguments of `docker build` or `podman build` for this image.
+    pub fn build_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "build".to_string(),
+            "--tag".to_string(),
+            self.tag.clone(),
+            "--file".to_string(),
+            self.dockerfile.to_string_lossy().to_string(),
+            "--label".to_string(),
+            "wonopcode=true".to_string(),
+        ];
+        for (key, value) in &self.args {
+            args.push("--build-arg".to_string());
+            args.push(format!("{key}={value}"));
+        }
+        if let Some(target) = &self.target {
+            args.push("--target".to_string());
+            args.push(target.clone());
+        }
+        args.push(self.context.to_string_lossy().to_string());
+        args
+    }
+
+    /// Build the image with `program`, `docker` or `podman`.
+    pub async fn run(&self, program: &str) -> SandboxResult<()> {
+        info!(image = %self.tag, dockerfile = %self.dockerfile.display(), "Building image...");
+        let args = self.build_args();
+        debug!(args = ?args, "Running {program} build");
+
+        let output = Command::new(program)
+            .args(&args)
+            .output()
+            .await
+            .map_err(|e| SandboxError::image_build_failed(&self.tag, e.to_string()))?;
+        if !output.status.success() {
+            // Build errors are at the end of the output
+            let log = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let lines: Vec<&str> = log.lines().collect();
+            let tail = lines[lines.len().saturating_sub(ERROR_TAIL_LINES)..].join("\n");
+            return Err(SandboxError::image_build_failed(&self.tag, tail));
+        }
+
+        info!(image = %self.tag, "Image built successfully");
+        Ok(())
+    }
+}
+
+/// Sources of the `COPY` and `ADD` instructions of a Dockerfile that copy
+/// from the build context: files, directories and wildcards.
+fn copied_files(dockerfile: &str) -> Vec<String> {
+    let joined = dockerfile.replace("\\\r\n", " ").replace("\\\n", " ");
+    let mut sources = Vec::new();
+    for line in joined.lines() {
+        let mut words = line.split_whitespace();
+        let Some(instruction) = words.next() else {
+            continue;
+        };
+        if !instruction.eq_ignore_ascii_case("COPY") && !instruction.eq_ignore_ascii_case("ADD") {
+            continue;
+        }
+        let words: Vec<&str> = words.collect();
+        // From another stage or image, or the JSON form
+        if words
+            .iter()
+            .any(|word| word.starts_with("--from") || word.starts_with('['))
+        {
+            continue;
+        }
+        let paths: Vec<&str> = words
+            .into_iter()
+            .filter(|word| !word.starts_with("--"))
+            .collect();
+        // The last path is the destination
+        for source in paths.iter().take(paths.len().saturating_sub(1)) {
+            if !source.contains("://") {
+                sources.push(source.trim_start_matches("./").to_string());
+            }
+        }
+    }
+    sources
+}
+
+/// Files of the build context that `sources` copy, relative to the context
+/// and sorted. Directories are copied with everything in them, and files
+/// excluded by `.dockerignore` are left out.
+fn context_files(context: &Path, sources: &[String]) -> Vec<String> {
+    let options = glob::MatchOptions {
+        case_sensitive: true,
+        require_literal_separator: true,
+        require_literal_leading_dot: false,
+    };
+    let ignore = dockerignore(context);
+    let mut files = BTreeSet::new();
+
+    for source in sources {
+        let source = source.trim_end_matches('/');
+        if Path::new(source)
+            .components()
+            .any(|c| matches!(c, Component::ParentDir | Component::RootDir))
+        {
+            continue;
+        }
+        let (root, pattern) = if source.contains(['*', '?', '[']) {
+            match glob::Pattern::new(source) {
+                Ok(pattern) => (context.to_path_buf(), Some(pattern)),
+                Err(_) => continue,
+            }
+        } else {
+            (context.join(source), None)
+        };
+
+        for entry in WalkBuilder::new(&root)
+            .standard_filters(false)
+            .build()
+            .flatten()
+        {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(context) else {
+                continue;
+            };
+            if ignore
+                .matched_path_or_any_parents(relative, false)
+                .is_ignore()
+            {
+                continue;
+            }
+            if let Some(pattern) = &pattern {
+                if !relative
+                    .ancestors()
+                    .any(|path| pattern.matches_path_with(path, options))
+                {
+                    continue;
+                }
+            }
+            files.insert(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    files.into_iter().collect()
+}
+
+/// The `.dockerignore` rules of a build context, if it has any.
+fn dockerignore(context: &Path) -> Gitignore {
+    let path = context.join(".dockerignore");
+    if !path.is_file() {
+        return Gitignore::empty();
+    }
+    let mut builder = GitignoreBuilder::new(context);
+    if let Some(e) = builder.add(&path) {
+        warn!(path = %path.display(), error = %e, "Failed to read .dockerignore");
+    }
+    builder.build().unwrap_or_else(|_| Gitignore::empty())
+}
+
+/// JSON with the comments and trailing commas of JSONC removed.
+fn strip_jsonc(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    output.push(escaped);
+                }
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            (']' | '}', _) => {
+                // Drop a trailing comma before the closing bracket
+                let trimmed = output.trim_end().len();
+                if output[..trimmed].ends_with(',') {
+                    output.truncate(trimmed - 1);
+                }
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_dockerfile() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::write(
+            root.join("Dockerfile"),
+            "FROM python:3.12\nCOPY requirements.txt /tmp/\nCOPY src/ /src\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("requirements.txt"), "requests\n").unwrap();
+
+        let ProjectImage::Build(build) = ProjectImage::resolve(root, "wonopcode-abc").unwrap()
+        else {
+            panic!("Expected a build");
+        };
+        assert!(build.tag.starts_with("wonopcode-abc:"));
+        assert_eq!(build.context, root);
+
+        // Unchanged inputs give the same tag, changed ones another
+        let again = ProjectImage::resolve(root, "wonopcode-abc").unwrap();
+        assert_eq!(again.image(), build.tag);
+        std::fs::write(root.join("requirements.txt"), "httpx\n").unwrap();
+        let changed = ProjectImage::resolve(root, "wonopcode-abc").unwrap();
+        assert_ne!(changed.image(), build.tag);
+
+        // So do files in copied directories
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.py"), "print()\n").unwrap();
+        let copied = ProjectImage::resolve(root, "wonopcode-abc").unwrap();
+        assert_ne!(copied.image(), changed.image());
+
+        // Files the Dockerfile doesn't copy don't matter
+        std::fs::write(root.join("notes.md"), "todo\n").unwrap();
+        let unrelated = ProjectImage::resolve(root, "wonopcode-abc").unwrap();
+        assert_eq!(unrelated.image(), copied.image());
+    }
+
+    #[test]
+    fn test_resolve_devcontainer() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join(".devcontainer")).unwrap();
+        std::fs::write(root.join(".devcontainer/Dockerfile"), "FROM rust:1\n").unwrap();
+        std::fs::write(
+            root.join(".devcontainer/devcontainer.json"),
+            r#"{
+                // Built from the Dockerfile next to it
+                "name": "dev",
+                "build": {
+                    "dockerfile": "Dockerfile",
+                    "context": "..",
+                    "args": { "VARIANT": "bookworm" },
+                },
+            }"#,
+        )
+        .unwrap();
+        // The devcontainer wins over a Dockerfile at the root
+        std::fs::write(root.join("Dockerfile"), "FROM scratch\n").unwrap();
+
+        let ProjectImage::Build(build) = ProjectImage::resolve(root, "wonopcode-abc").unwrap()
+        else {
+            panic!("Expected a build");
+        };
+        let devcontainer = root.join(".devcontainer");
+        assert_eq!(build.dockerfile, devcontainer.join("Dockerfile"));
+        assert_eq!(build.context, devcontainer.join(".."));
+
+        let args = build.build_args();
+        assert_eq!(args[..3], ["build", "--tag", build.tag.as_str()]);
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--build-arg", "VARIANT=bookworm"]));
+        assert_eq!(
+            args.last().unwrap(),
+            &devcontainer.join("..").to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_resolve_named_and_missing() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        assert!(ProjectImage::resolve(root, "wonopcode-abc").is_err());
+
+        std::fs::write(
+            root.join(".devcontainer.json"),
+            r#"{"image": "mcr.microsoft.com/devcontainers/base:ubuntu"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            ProjectImage::resolve(root, "wonopcode-abc").unwrap(),
+            ProjectImage::Named("mcr.microsoft.com/devcontainers/base:ubuntu".to_string())
+        );
+    }
+
+    #[test]
+    fn test_copied_files() {
+        let dockerfile = "FROM node:20 AS deps\n\
+                          COPY package.json package-lock.json ./\n\
+                          copy --chown=node:node \\\n  tsconfig.json /app/\n\
+                          COPY --from=deps /app/node_modules ./node_modules\n\
+                          ADD src/*.ts /app/src/\n\
+                          COPY [\"a b.txt\", \"/app/\"]\n";
+        assert_eq!(
+            copied_files(dockerfile),
+            vec![
+                "package.json",
+                "package-lock.json",
+                "tsconfig.json",
+                "src/*.ts"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_context_files() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+        for file in [
+            "app/main.py",
+            "app/debug.log",
+            "app/lib/util.py",
+            "src/a.ts",
+            "src/nested/b.ts",
+            "README.md",
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, file).unwrap();
+        }
+        std::fs::write(root.join(".dockerignore"), "*.log\n").unwrap();
+
+        let sources = vec![
+            "app/".to_string(),
+            "src/*.ts".to_string(),
+            "../outside".to_string(),
+        ];
+        assert_eq!(
+            context_files(root, &sources),
+            vec!["app/lib/util.py", "app/main.py", "src/a.ts"]
+        );
+    }
+}
@@ -4,27 +4,30 @@
 //! It supports both Docker and Podman (via Docker-compatible API).
 // @ace:implements COMP-T90R7N-CM8
 
+use super::firewall::{FilterLock, Rules};
 use crate::{
     config::SandboxConfig,
     error::{SandboxError, SandboxResult},
+    execution::{kill_script, new_marker, KILL_TIMEOUT, MARKER_VAR},
     path::PathMapper,
-    SandboxCapabilities, SandboxDirEntry, SandboxInfo, SandboxMetadata, SandboxOutput,
-    SandboxRuntime, SandboxRuntimeType, SandboxStatus,
+    ProjectImage, SandboxCapabilities, SandboxDirEntry, SandboxExecution, SandboxInfo,
+    SandboxMetadata, SandboxOutput, SandboxRuntime, SandboxRuntimeType, SandboxStatus,
+    StartProgress,
 };
 use async_trait::async_trait;
 use bollard::{
     container::{
-        Config, CreateContainerOptions, ListContainersOptions, RemoveContainerOptions,
+        Config, CreateContainerOptions, ListContainersOptions, LogOutput, RemoveContainerOptions,
         StartContainerOptions, StopContainerOptions,
     },
     exec::{CreateExecOptions, StartExecOptions, StartExecResults},
     image::CreateImageOptions,
-    models::HostConfig,
+    models::{CreateImageInfo, HostConfig},
     Docker,
 };
 use futures::StreamExt;
 use std::{collections::HashMap, path::Path, time::Duration};
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, info, warn};
 
 /// Docker-based sandbox runtime.
@@ -41,8 +44,15 @@ pub struct DockerRuntime {
     path_mapper: PathMapper,
     /// Current status
     status: RwLock<SandboxStatus>,
+    /// Taken by each command, alone while it runs under a host filter
+    filter_lock: FilterLock,
+    /// Image of the container, once resolved
+    image: RwLock<String>,
 }
 
+/// Timeout for applying and removing host filters.
+const FILTER_TIMEOUT: Duration = Duration::from_secs(30);
+
 impl DockerRuntime {
     /// Create a new Docker runtime.
     pub async fn new(config: SandboxConfig, path_mapper: PathMapper) -> SandboxResult<Self> {
@@ -67,9 +77,11 @@ impl DockerRuntime {
             id,
             docker,
             container_id: RwLock::new(None),
+            image: RwLock::new(config.image().to_string()),
             config,
             path_mapper,
             status: RwLock::new(SandboxStatus::NotInitialized),
+            filter_lock: FilterLock::default(),
         })
     }
 
@@ -154,11 +166,41 @@ impl DockerRuntime {
         Ok(())
     }
 
-    /// Ensure the container image is available.
-    #[allow(clippy::cognitive_complexity)]
-    async fn ensure_image(&self) -> SandboxResult<()> {
-        let image = self.config.image();
+    /// The image to run: the configured one, or the one for the project if
+    /// it's built from the project, building it if needed.
+    async fn resolve_image(
+        &self,
+        progress: Option<&mpsc::UnboundedSender<StartProgress>>,
+    ) -> SandboxResult<String> {
+        if !self.config.builds_image() {
+            return Ok(self.config.image().to_string());
+        }
+
+        let project = ProjectImage::resolve(self.path_mapper.host_root(), &self.id)?;
+        if let ProjectImage::Build(build) = &project {
+            if self.docker.inspect_image(&build.tag).await.is_err() {
+                if let Some(tx) = progress {
+                    let _ = tx.send(StartProgress {
+                        fraction: None,
+                        message: format!("Building image from {}", build.dockerfile.display()),
+                    });
+                }
+                build.run("docker").await?;
+            }
+        }
+        let image = project.image().to_string();
+        *self.image.write().await = image.clone();
+        Ok(image)
+    }
 
+    /// Ensure the container image is available, reporting the progress of
+    /// pulling it.
+    #[allow(clippy::cognitive_complexity)]
+    async fn ensure_image(
+        &self,
+        image: &str,
+        progress: Option<&mpsc::UnboundedSender<StartProgress>>,
+    ) -> SandboxResult<()> {
         // Check if image exists locally
         match self.docker.inspect_image(image).await {
             Ok(_) => {
@@ -177,10 +219,24 @@ impl DockerRuntime {
         };
 
         let mut stream = self.docker.create_image(Some(options), None, None);
+        let message = format!("Pulling image {image}");
+        let report = |fraction| {
+            if let Some(tx) = progress {
+                let _ = tx.send(StartProgress {
+                    fraction,
+                    message: message.clone(),
+                });
+            }
+        };
+        report(None);
 
+        let mut pull = PullProgress::default();
         while let Some(result) = stream.next().await {
             match result {
                 Ok(info) => {
+                    if let Some(fraction) = pull.update(&info) {
+                        report(Some(fraction));
+                    }
                     if let Some(status) = info.status {
                         debug!(status = %status, "Image pull progress");
                     }
@@ -195,9 +251,95 @@ impl DockerRuntime {
         Ok(())
     }
 
-    /// Build the container configuration.
-    fn container_config(&self) -> Config<String> {
-        let image = self.config.image().to_string();
+    /// Start the container, reporting the progress of pulling its image.
+    async fn start_reporting(
+        &self,
+        progress: Option<&mpsc::UnboundedSender<StartProgress>>,
+    ) -> SandboxResult<()> {
+        // Check if already running
+        if self.is_container_running().await {
+            debug!("Container already running");
+            return Ok(());
+        }
+
+        *self.status.write().await = SandboxStatus::Starting;
+
+        // Clean up any orphaned containers from previous runs
+        // This prevents container accumulation over time
+        if let Err(e) = self.cleanup_orphaned_containers().await {
+            warn!(error = %e, "Failed to cleanup orphaned containers");
+        }
+
+        // Ensure image is available
+        let image = self.resolve_image(progress).await?;
+        self.ensure_image(&image, progress).await?;
+
+        // Check for existing container
+        let mut existing = self.find_existing_container().await;
+        if let Some(existing_id) = existing.as_ref().filter(|_| self.config.builds_image()) {
+            // Replace containers of a project image that has been rebuilt since
+            let current = match self.docker.inspect_container(existing_id, None).await {
+                Ok(info) => info.config.and_then(|config| config.image),
+                Err(_) => None,
+            };
+            if current.as_deref() != Some(image.as_str()) {
+                info!(container_id = %existing_id, image = %image, "Replacing container for rebuilt image");
+                let options = RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                };
+                self.docker
+                    .remove_container(existing_id, Some(options))
+                    .await
+                    .map_err(|e| SandboxError::RemoveFailed(e.to_string()))?;
+                existing = None;
+            }
+        }
+        if let Some(existing_id) = existing {
+            debug!(container_id = %existing_id, "Found existing container");
+            *self.container_id.write().await = Some(existing_id.clone());
+
+            // Try to start it
+            self.docker
+                .start_container(&existing_id, None::<StartContainerOptions<String>>)
+                .await
+                .map_err(|e| SandboxError::StartFailed(e.to_string()))?;
+
+            *self.status.write().await = SandboxStatus::Running;
+            return Ok(());
+        }
+
+        // Create new container
+        let options = CreateContainerOptions {
+            name: &self.id,
+            platform: None,
+        };
+
+        let config = self.container_config(&image);
+
+        let container = self
+            .docker
+            .create_container(Some(options), config)
+            .await
+            .map_err(|e| SandboxError::CreateFailed(e.to_string()))?;
+
+        info!(container_id = %container.id, "Container created");
+        *self.container_id.write().await = Some(container.id.clone());
+
+        // Start container
+        self.docker
+            .start_container(&container.id, None::<StartContainerOptions<String>>)
+            .await
+            .map_err(|e| SandboxError::StartFailed(e.to_string()))?;
+
+        *self.status.write().await = SandboxStatus::Running;
+        info!(container_id = %container.id, "Container started");
+
+        Ok(())
+    }
+
+    /// Build the configuration of a container of `image`.
+    fn container_config(&self, image: &str) -> Config<String> {
         let host_root = self.path_mapper.host_root().to_string_lossy().to_string();
         let sandbox_root = self
             .path_mapper
@@ -251,9 +393,20 @@ impl DockerRuntime {
             "DEBIAN_FRONTEND=noninteractive".to_string(),
         ];
 
+        // Project images may have an entrypoint that runs the project
+        let (entrypoint, cmd) = if self.config.builds_image() {
+            (
+                Some(vec!["sleep".to_string()]),
+                vec!["infinity".to_string()],
+            )
+        } else {
+            (None, vec!["sleep".to_string(), "infinity".to_string()])
+        };
+
         Config {
-            image: Some(image),
-            cmd: Some(vec!["sleep".to_string(), "infinity".to_string()]),
+            image: Some(image.to_string()),
+            entrypoint,
+            cmd: Some(cmd),
             working_dir: Some(sandbox_root),
             host_config: Some(host_config),
             env: Some(env),
@@ -307,88 +460,133 @@ impl DockerRuntime {
         command: &str,
         workdir: &Path,
         timeout: Duration,
+    ) -> SandboxResult<SandboxOutput> {
+        self.exec(command, workdir, timeout, false).await
+    }
+
+    /// Execute a command, with all capabilities if `privileged`.
+    async fn exec(
+        &self,
+        command: &str,
+        workdir: &Path,
+        timeout: Duration,
+        privileged: bool,
     ) -> SandboxResult<SandboxOutput> {
         let container_id = self.container_id.read().await;
         let container_id = container_id.as_ref().ok_or(SandboxError::NotRunning)?;
 
-        let workdir_str = workdir.to_string_lossy().to_string();
-        let exec_config = CreateExecOptions::<String> {
-            cmd: Some(vec![
-                "sh".to_string(),
-                "-c".to_string(),
-                command.to_string(),
-            ]),
-            working_dir: Some(workdir_str),
-            attach_stdout: Some(true),
-            attach_stderr: Some(true),
-            tty: Some(false),
-            ..Default::default()
-        };
-
-        let exec = self
-            .docker
-            .create_exec(container_id, exec_config)
-            .await
-            .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
+        let options = exec_options(command, workdir, privileged, None);
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let result = tokio::time::timeout(
+            timeout,
+            run_exec(&self.docker, container_id, options, |chunk| match chunk {
+                LogOutput::StdOut { message } => stdout.extend_from_slice(&message),
+                LogOutput::StdErr { message } => stderr.extend_from_slice(&message),
+                _ => {}
+            }),
+        )
+        .await;
 
-        let start_config = StartExecOptions {
-            detach: false,
-            ..Default::default()
-        };
+        match result {
+            Ok(exit_code) => Ok(SandboxOutput::from_output(
+                String::from_utf8_lossy(&stdout).to_string(),
+                String::from_utf8_lossy(&stderr).to_string(),
+                exit_code?,
+            )),
+            Err(_) => Err(SandboxError::Timeout(timeout)),
+        }
+    }
+}
 
-        let result = tokio::time::timeout(timeout, async {
-            let start_result = self
-                .docker
-                .start_exec(&exec.id, Some(start_config))
-                .await
-                .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
+/// Options of an exec running `command`, tagged with `marker` if given.
+fn exec_options(
+    command: &str,
+    workdir: &Path,
+    privileged: bool,
+    marker: Option<&str>,
+) -> CreateExecOptions<String> {
+    CreateExecOptions {
+        cmd: Some(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            command.to_string(),
+        ]),
+        env: marker.map(|marker| vec![format!("{MARKER_VAR}={marker}")]),
+        working_dir: Some(workdir.to_string_lossy().to_string()),
+        privileged: Some(privileged),
+        attach_stdout: Some(true),
+        attach_stderr: Some(true),
+        tty: Some(false),
+        ..Default::default()
+    }
+}
 
-            match start_result {
-                StartExecResults::Attached { mut output, .. } => {
-                    let mut stdout = Vec::new();
-                    let mut stderr = Vec::new();
-
-                    while let Some(chunk) = output.next().await {
-                        match chunk {
-                            Ok(bollard::container::LogOutput::StdOut { message }) => {
-                                stdout.extend_from_slice(&message);
-                            }
-                            Ok(bollard::container::LogOutput::StdErr { message }) => {
-                                stderr.extend_from_slice(&message);
-                            }
-                            Ok(_) => {}
-                            Err(e) => {
-                                warn!(error = %e, "Error reading exec output");
-                            }
-                        }
+/// Run an exec in a container, passing its output to `on_output` as it
+/// arrives, and return its exit code.
+async fn run_exec(
+    docker: &Docker,
+    container_id: &str,
+    options: CreateExecOptions<String>,
+    mut on_output: impl FnMut(LogOutput),
+) -> SandboxResult<i32> {
+    let exec = docker
+        .create_exec(container_id, options)
+        .await
+        .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
+
+    let start_config = StartExecOptions {
+        detach: false,
+        ..Default::default()
+    };
+    let start_result = docker
+        .start_exec(&exec.id, Some(start_config))
+        .await
+        .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
+
+    match start_result {
+        StartExecResults::Attached { mut output, .. } => {
+            while let Some(chunk) = output.next().await {
+                match chunk {
+                    Ok(chunk) => on_output(chunk),
+                    Err(e) => {
+                        warn!(error = %e, "Error reading exec output");
                     }
-
-                    // Get exit code
-                    let inspect = self
-                        .docker
-                        .inspect_exec(&exec.id)
-                        .await
-                        .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
-
-                    let exit_code = inspect.exit_code.unwrap_or(-1) as i32;
-
-                    Ok(SandboxOutput {
-                        stdout: String::from_utf8_lossy(&stdout).to_string(),
-                        stderr: String::from_utf8_lossy(&stderr).to_string(),
-                        exit_code,
-                        success: exit_code == 0,
-                    })
                 }
-                StartExecResults::Detached => Err(SandboxError::ExecFailed(
-                    "Unexpected detached exec".to_string(),
-                )),
             }
-        })
-        .await;
 
-        match result {
-            Ok(output) => output,
-            Err(_) => Err(SandboxError::Timeout(timeout)),
+            // Get exit code
+            let inspect = docker
+                .inspect_exec(&exec.id)
+                .await
+                .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
+
+            Ok(inspect.exit_code.unwrap_or(-1) as i32)
+        }
+        StartExecResults::Detached => Err(SandboxError::ExecFailed(
+            "Unexpected detached exec".to_string(),
+        )),
+    }
+}
+
+/// Run a privileged script in a container, logging a failure as `what`.
+/// Returns whether it succeeded.
+async fn run_script(docker: &Docker, container_id: &str, script: &str, what: &str) -> bool {
+    let options = exec_options(script, Path::new("/"), true, None);
+    let run = run_exec(docker, container_id, options, |_| {});
+    match tokio::time::timeout(KILL_TIMEOUT, run).await {
+        Ok(Ok(0)) => true,
+        Ok(Ok(code)) => {
+            warn!(exit_code = code, "Failed to {}", what);
+            false
+        }
+        Ok(Err(e)) => {
+            warn!(error = %e, "Failed to {}", what);
+            false
+        }
+        Err(_) => {
+            warn!("Timed out trying to {}", what);
+            false
         }
     }
 }
@@ -403,6 +601,13 @@ impl SandboxRuntime for DockerRuntime {
         SandboxRuntimeType::Docker
     }
 
+    fn capabilities_for(&self, command: &str) -> SandboxCapabilities {
+        SandboxCapabilities {
+            hosts: self.config.host_filter(command),
+            ..Default::default()
+        }
+    }
+
     async fn status(&self) -> SandboxStatus {
         *self.status.read().await
     }
@@ -412,7 +617,7 @@ impl SandboxRuntime for DockerRuntime {
             id: self.id.clone(),
             runtime_type: SandboxRuntimeType::Docker,
             status: self.status().await,
-            image: self.config.image().to_string(),
+            image: self.image.read().await.clone(),
             host_root: self.path_mapper.host_root().to_path_buf(),
             workspace_path: self.path_mapper.sandbox_root().to_path_buf(),
         }
@@ -423,65 +628,14 @@ impl SandboxRuntime for DockerRuntime {
     }
 
     async fn start(&self) -> SandboxResult<()> {
-        // Check if already running
-        if self.is_container_running().await {
-            debug!("Container already running");
-            return Ok(());
-        }
-
-        *self.status.write().await = SandboxStatus::Starting;
-
-        // Clean up any orphaned containers from previous runs
-        // This prevents container accumulation over time
-        if let Err(e) = self.cleanup_orphaned_containers().await {
-            warn!(error = %e, "Failed to cleanup orphaned containers");
-        }
-
-        // Ensure image is available
-        self.ensure_image().await?;
-
-        // Check for existing container
-        if let Some(existing_id) = self.find_existing_container().await {
-            debug!(container_id = %existing_id, "Found existing container");
-            *self.container_id.write().await = Some(existing_id.clone());
-
-            // Try to start it
-            self.docker
-                .start_container(&existing_id, None::<StartContainerOptions<String>>)
-                .await
-                .map_err(|e| SandboxError::StartFailed(e.to_string()))?;
-
-            *self.status.write().await = SandboxStatus::Running;
-            return Ok(());
-        }
-
-        // Create new container
-        let options = CreateContainerOptions {
-            name: &self.id,
-            platform: None,
-        };
-
-        let config = self.container_config();
-
-        let container = self
-            .docker
-            .create_container(Some(options), config)
-            .await
-            .map_err(|e| SandboxError::CreateFailed(e.to_string()))?;
-
-        info!(container_id = %container.id, "Container created");
-        *self.container_id.write().await = Some(container.id.clone());
-
-        // Start container
-        self.docker
-            .start_container(&container.id, None::<StartContainerOptions<String>>)
-            .await
-            .map_err(|e| SandboxError::StartFailed(e.to_string()))?;
-
-        *self.status.write().await = SandboxStatus::Running;
-        info!(container_id = %container.id, "Container started");
+        self.start_reporting(None).await
+    }
 
-        Ok(())
+    async fn start_with_progress(
+        &self,
+        progress: mpsc::UnboundedSender<StartProgress>,
+    ) -> SandboxResult<()> {
+        self.start_reporting(Some(&progress)).await
     }
 
     async fn stop(&self) -> SandboxResult<()> {
@@ -521,14 +675,92 @@ impl SandboxRuntime for DockerRuntime {
         command: &str,
         workdir: &Path,
         timeout: Duration,
-        _capabilities: &SandboxCapabilities,
+        capabilities: &SandboxCapabilities,
     ) -> SandboxResult<SandboxOutput> {
+        self.execute_streaming(command, workdir, timeout, capabilities)
+            .await?
+            .wait()
+            .await
+    }
+
+    async fn execute_streaming(
+        &self,
+        command: &str,
+        workdir: &Path,
+        timeout: Duration,
+        capabilities: &SandboxCapabilities,
+    ) -> SandboxResult<SandboxExecution> {
         if !self.is_ready().await {
             return Err(SandboxError::NotRunning);
         }
+        let container_id = self
+            .container_id
+            .read()
+            .await
+            .clone()
+            .ok_or(SandboxError::NotRunning)?;
 
         debug!(command = %command, workdir = %workdir.display(), "Executing command");
-        self.exec_command(command, workdir, timeout).await
+        let rules = Rules::resolve(&capabilities.hosts, &self.config.network).await?;
+
+        // The rules apply to the whole container, so a filtered command runs alone
+        let turn = self.filter_lock.turn(rules.is_some()).await;
+        if let Some(rules) = &rules {
+            let applied = self
+                .exec(&rules.apply_script(), Path::new("/"), FILTER_TIMEOUT, true)
+                .await?;
+            if !applied.success {
+                return Err(SandboxError::ExecFailed(format!(
+                    "failed to apply host filter: {}",
+                    applied.stderr.trim()
+                )));
+            }
+        }
+
+        let marker = new_marker();
+        let options = exec_options(command, workdir, false, Some(&marker));
+        let docker = self.docker.clone();
+        let (execution, mut feed) = SandboxExecution::channel(None);
+        tokio::spawn(async move {
+            let kill = feed.kill_handle();
+            let exit_code = tokio::select! {
+                exit_code = run_exec(&docker, &container_id, options, |chunk| match chunk {
+                    LogOutput::StdOut { message } => feed.stdout(&message),
+                    LogOutput::StdErr { message } => feed.stderr(&message),
+                    _ => {}
+                }) => exit_code,
+                error = kill.stopped(timeout) => Err(error),
+            };
+
+            // Processes a filtered command left running would get out once
+            // the rules are removed
+            let filtered = turn.is_filtered();
+            let killed = if exit_code.is_err() || filtered {
+                run_script(
+                    &docker,
+                    &container_id,
+                    &kill_script(&marker),
+                    "kill command",
+                )
+                .await
+            } else {
+                true
+            };
+            if filtered && killed {
+                run_script(
+                    &docker,
+                    &container_id,
+                    &Rules::reset_script(),
+                    "remove host filter",
+                )
+                .await;
+            } else if filtered {
+                warn!("Keeping the host filter, since processes of the command may still run");
+            }
+            drop(turn);
+            feed.finish(exit_code);
+        });
+        Ok(execution)
     }
 
     async fn read_file(&self, path: &Path) -> SandboxResult<Vec<u8>> {
@@ -778,10 +1010,89 @@ fn base64_encode(data: &[u8]) -> String {
     result
 }
 
+/// Progress of an image pull, summed over the downloads of its layers.
+#[derive(Debug, Default)]
+struct PullProgress {
+    /// Downloaded and total bytes, by layer.
+    layers: HashMap<String, (i64, i64)>,
+}
+
+impl PullProgress {
+    /// Read a status of the pull, returning the share of the image
+    /// downloaded if it changed.
+    fn update(&mut self, info: &CreateImageInfo) -> Option<f64> {
+        let id = info.id.as_ref()?;
+        match info.status.as_deref()? {
+            "Downloading" => {
+                let detail = info.progress_detail.as_ref()?;
+                let (current, total) = (detail.current?, detail.total?);
+                if total <= 0 {
+                    return None;
+                }
+                self.layers.insert(id.clone(), (current.min(total), total));
+            }
+            "Download complete" | "Pull complete" => {
+                let layer = self.layers.get_mut(id)?;
+                layer.0 = layer.1;
+            }
+            _ => return None,
+        }
+        let (done, total) = self
+            .layers
+            .values()
+            .fold((0, 0), |(done, total), (current, size)| {
+                (done + current, total + size)
+            });
+        Some(done as f64 / total as f64)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn pull_status(id: &str, status: &str, current: i64, total: i64) -> CreateImageInfo {
+        CreateImageInfo {
+            id: Some(id.to_string()),
+            status: Some(status.to_string()),
+            progress_detail: Some(bollard::models::ProgressDetail {
+                current: Some(current),
+                total: Some(total),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pull_progress_sums_layers() {
+        let mut pull = PullProgress::default();
+        assert_eq!(
+            pull.update(&pull_status("a", "Downloading", 50, 100)),
+            Some(0.5)
+        );
+        assert_eq!(
+            pull.update(&pull_status("b", "Downloading", 0, 300)),
+            Some(0.125)
+        );
+        assert_eq!(
+            pull.update(&pull_status("a", "Download complete", 0, 0)),
+            Some(0.25)
+        );
+        // Extraction and statuses without a layer don't move the bar
+        assert_eq!(pull.update(&pull_status("a", "Extracting", 10, 100)), None);
+        assert_eq!(
+            pull.update(&CreateImageInfo {
+                status: Some("Pulling from library/ubuntu".to_string()),
+                ..Default::default()
+            }),
+            None
+        );
+        assert_eq!(
+            pull.update(&pull_status("b", "Pull complete", 0, 0)),
+            Some(1.0)
+        );
+    }
+
     #[test]
     fn test_shell_escape() {
         assert_eq!(shell_escape("hello"), "'hello'");
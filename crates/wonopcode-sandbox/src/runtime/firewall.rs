@@ -0,0 +1,324 @@
+//! Per-execution host filtering for the container runtimes.
+//!
+//! Host names are resolved on the host, and the addresses turned into
+//! iptables rules in a chain of the container's network namespace. A
+//! privileged exec applies the rules before the command and flushes them
+//! after it, so that the command itself, which runs without `NET_ADMIN`,
+//! can't change them. Processes the command leaves behind are killed before
+//! the rules go.
+
+use crate::{HostFilter, NetworkPolicy, SandboxError, SandboxResult};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use tracing::warn;
+
+/// Chain holding the rules of the filtered execution.
+const CHAIN: &str = "WONOPCODE";
+
+/// An address, or a range of addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Target {
+    addr: IpAddr,
+    prefix: Option<u8>,
+}
+
+impl Target {
+    /// Parse an IP address or CIDR range; `None` for host names.
+    fn parse(entry: &str) -> Option<Self> {
+        let (addr, prefix) = match entry.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (entry, None),
+        };
+        let addr: IpAddr = addr.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix.is_some_and(|prefix| prefix > max) {
+            return None;
+        }
+        Some(Self { addr, prefix })
+    }
+
+    fn spec(&self) -> String {
+        match self.prefix {
+            Some(prefix) => format!("{}/{prefix}", self.addr),
+            None => self.addr.to_string(),
+        }
+    }
+}
+
+/// Lock on the host filter of a container. Commands without a filter share
+/// it, and a filtered command holds it alone, since its rules cover the
+/// whole container.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FilterLock(Arc<RwLock<()>>);
+
+impl FilterLock {
+    /// Wait for the turn of a command, which runs alone if `filtered`.
+    pub(crate) async fn turn(&self, filtered: bool) -> FilterTurn {
+        if filtered {
+            FilterTurn {
+                _shared: None,
+                alone: Some(self.0.clone().write_owned().await),
+            }
+        } else {
+            FilterTurn {
+                _shared: Some(self.0.clone().read_owned().await),
+                alone: None,
+            }
+        }
+    }
+}
+
+/// The turn of a command, held until the command and the processes it
+/// started are gone.
+pub(crate) struct FilterTurn {
+    _shared: Option<OwnedRwLockReadGuard<()>>,
+    alone: Option<OwnedRwLockWriteGuard<()>>,
+}
+
+impl FilterTurn {
+    /// Check if the command runs under a host filter.
+    pub(crate) fn is_filtered(&self) -> bool {
+        self.alone.is_some()
+    }
+}
+
+/// A host filter with the host names resolved.
+#[derive(Debug, Default)]
+pub(crate) struct Rules {
+    /// Whether only the allowed hosts may be reached, even if none of them
+    /// resolved
+    restricted: bool,
+    allow: Vec<Target>,
+    deny: Vec<Target>,
+}
+
+impl Rules {
+    /// Resolve the hosts of `filter`, or `None` if there's nothing to
+    /// enforce under `policy`.
+    pub(crate) async fn resolve(
+        filter: &HostFilter,
+        policy: &NetworkPolicy,
+    ) -> SandboxResult<Option<Self>> {
+        // Nothing is reachable anyway
+        if filter.is_empty() || policy.is_disabled() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            restricted: !filter.allow.is_empty(),
+            allow: resolve_all(&filter.allow).await?,
+            deny: resolve_all(&filter.deny).await?,
+        }))
+    }
+
+    /// Shell script that installs the rules.
+    pub(crate) fn apply_script(&self) -> String {
+        let mut script = vec!["set -e".to_string()];
+        script.extend(self.commands("iptables", false));
+
+        // Containers may have IPv6 disabled, and no ip6tables to go with it
+        let ipv6 = self.commands("ip6tables", true);
+        script.push("if [ -e /proc/net/if_inet6 ]; then".to_string());
+        script.extend(ipv6.into_iter().map(|line| format!("  {line}")));
+        script.push("fi".to_string());
+        script.join("\n")
+    }
+
+    /// Shell script that removes the rules again.
+    pub(crate) fn reset_script() -> String {
+        format!("iptables -F {CHAIN} 2>/dev/null; ip6tables -F {CHAIN} 2>/dev/null; true")
+    }
+
+    /// The commands of one `tool` that set up the rules of its address family.
+    fn commands(&self, tool: &str, ipv6: bool) -> Vec<String> {
+        let of_family = |targets: &[Target]| -> Vec<String> {
+            targets
+                .iter()
+                .filter(|target| target.addr.is_ipv6() == ipv6)
+                .map(Target::spec)
+                .collect()
+        };
+
+        let mut commands = vec![
+            format!("{tool} -N {CHAIN} 2>/dev/null || {tool} -F {CHAIN}"),
+            format!("{tool} -C OUTPUT -j {CHAIN} 2>/dev/null || {tool} -I OUTPUT -j {CHAIN}"),
+            format!("{tool} -A {CHAIN} -o lo -j RETURN"),
+        ];
+        // Denials first, so that they win over overlapping allowances
+        for target in of_family(&self.deny) {
+            commands.push(format!("{tool} -A {CHAIN} -d {target} -j REJECT"));
+        }
+        if self.restricted {
+            // Name resolution, to reach the allowed hosts by name, through the
+            // container's own name servers only
+            let other_family = if ipv6 {
+                "*:*) ;; *) continue ;;"
+            } else {
+                "*:*) continue ;;"
+            };
+            commands.push(
+                "for ns in $(awk '/^nameserver/ { print $2 }' /etc/resolv.conf 2>/dev/null); do"
+                    .to_string(),
+            );
+            commands.push(format!("  case \"$ns\" in {other_family} esac"));
+            for proto in ["udp", "tcp"] {
+                commands.push(format!(
+                    "  {tool} -A {CHAIN} -d \"${{ns%%%*}}\" -p {proto} --dport 53 -j RETURN"
+                ));
+            }
+            commands.push("done".to_string());
+            for target in of_family(&self.allow) {
+                commands.push(format!("{tool} -A {CHAIN} -d {target} -j RETURN"));
+            }
+            commands.push(format!("{tool} -A {CHAIN} -j REJECT"));
+        }
+        commands
+    }
+}
+
+/// Resolve host entries to the addresses they stand for.
+async fn resolve_all(entries: &[String]) -> SandboxResult<Vec<Target>> {
+    let mut targets: Vec<Target> = Vec::new();
+    for entry in entries {
+        let entry = entry.trim();
+        if entry.is_empty() || entry.contains(char::is_whitespace) {
+            return Err(SandboxError::ConfigError(format!("invalid host '{entry}'")));
+        }
+        if let Some(target) = Target::parse(entry) {
+            targets.push(target);
+            continue;
+        }
+        match tokio::net::lookup_host((entry, 0)).await {
+            Ok(addrs) => {
+                for addr in addrs {
+                    let target = Target {
+                        addr: addr.ip(),
+                        prefix: None,
+                    };
+                    if !targets.contains(&target) {
+                        targets.push(target);
+                    }
+                }
+            }
+            // An unknown host is unreachable, whichever list it is on
+            Err(e) => warn!(host = %entry, error = %e, "Failed to resolve host"),
+        }
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(Target::parse("10.0.0.1").unwrap().spec(), "10.0.0.1");
+        assert_eq!(Target::parse("10.0.0.0/8").unwrap().spec(), "10.0.0.0/8");
+        assert_eq!(Target::parse("::1/128").unwrap().spec(), "::1/128");
+        assert!(Target::parse("10.0.0.0/33").is_none());
+        assert!(Target::parse("example.com").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let filter = HostFilter {
+            allow: vec!["localhost".to_string(), "10.0.0.0/8".to_string()],
+            deny: vec![],
+        };
+        let rules = Rules::resolve(&filter, &NetworkPolicy::Full)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rules.allow.iter().any(|target| target.addr.is_loopback()));
+        assert!(rules.allow.contains(&Target::parse("10.0.0.0/8").unwrap()));
+
+        // Nothing to enforce
+        let none = Rules::resolve(&filter, &NetworkPolicy::None).await.unwrap();
+        assert!(none.is_none());
+        let empty = Rules::resolve(&HostFilter::default(), &NetworkPolicy::Full)
+            .await
+            .unwrap();
+        assert!(empty.is_none());
+
+        let invalid = HostFilter {
+            allow: vec!["rm -rf".to_string()],
+            deny: vec![],
+        };
+        assert!(Rules::resolve(&invalid, &NetworkPolicy::Full)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_apply_script() {
+        let rules = Rules {
+            restricted: true,
+            allow: vec![
+                Target::parse("140.82.112.0/20").unwrap(),
+                Target::parse("2606:50c0::/32").unwrap(),
+            ],
+            deny: vec![Target::parse("140.82.112.3").unwrap()],
+        };
+        let script = rules.apply_script();
+        let lines: Vec<&str> = script.lines().collect();
+
+        let deny = "iptables -A WONOPCODE -d 140.82.112.3 -j REJECT";
+        let allow = "iptables -A WONOPCODE -d 140.82.112.0/20 -j RETURN";
+        let position = |line: &str| lines.iter().position(|l| *l == line).unwrap();
+        assert!(position(deny) < position(allow));
+        assert!(position(allow) < position("iptables -A WONOPCODE -j REJECT"));
+        assert!(lines.contains(&"  ip6tables -A WONOPCODE -d 2606:50c0::/32 -j RETURN"));
+        assert!(!script.contains("iptables -A WONOPCODE -d 2606"));
+
+        // Name resolution goes to the container's name servers only
+        assert!(script.contains("/etc/resolv.conf"));
+        assert!(
+            lines.contains(&"  iptables -A WONOPCODE -d \"${ns%%%*}\" -p udp --dport 53 -j RETURN")
+        );
+        assert!(!script.contains("-A WONOPCODE -p udp --dport 53"));
+
+        // Without allowances everything else stays reachable
+        let rules = Rules {
+            restricted: false,
+            allow: vec![],
+            deny: vec![Target::parse("169.254.169.254").unwrap()],
+        };
+        let script = rules.apply_script();
+        assert!(!script.contains("WONOPCODE -j REJECT"));
+        assert!(!script.contains("--dport 53"));
+    }
+
+    #[tokio::test]
+    async fn test_unresolved_allow_list_rejects_all() {
+        let filter = HostFilter {
+            allow: vec!["unresolvable.invalid".to_string()],
+            deny: vec![],
+        };
+        let rules = Rules::resolve(&filter, &NetworkPolicy::Full)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(rules.allow.is_empty());
+        assert!(rules
+            .apply_script()
+            .contains("iptables -A WONOPCODE -j REJECT"));
+    }
+
+    #[tokio::test]
+    async fn test_filtered_turn_runs_alone() {
+        let lock = FilterLock::default();
+        let first = lock.turn(false).await;
+        let second = lock.turn(false).await;
+        assert!(!first.is_filtered());
+
+        let filtered = tokio::spawn({
+            let lock = lock.clone();
+            async move { lock.turn(true).await.is_filtered() }
+        });
+        tokio::task::yield_now().await;
+        assert!(!filtered.is_finished());
+        drop((first, second));
+        assert!(filtered.await.unwrap());
+    }
+}
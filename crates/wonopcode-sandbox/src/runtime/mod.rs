@@ -5,18 +5,30 @@
 //! - `docker`: Docker container-based sandbox
 //! - `podman`: Podman container-based sandbox (rootless alternative to Docker)
 //! - `lima`: Lima VM-based sandbox (macOS only)
+//! - `wasm`: WebAssembly sandbox running WASI modules with wasmtime (`wasm`
+//!   feature)
 //! - `passthrough`: No-op implementation for non-sandboxed execution
 
 pub mod docker;
+mod firewall;
 #[cfg(target_os = "macos")]
 pub mod lima;
 pub mod passthrough;
 pub mod podman;
-
-use crate::{PathMapper, SandboxConfig, SandboxResult, SandboxRuntime, SandboxRuntimeType};
+#[cfg(feature = "wasm")]
+mod wasi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use crate::{
+    PathMapper, ResourceMonitor, ResourceStats, SandboxConfig, SandboxResult, SandboxRuntime,
+    SandboxRuntimeType,
+};
 use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 
 pub use docker::DockerRuntime;
@@ -24,6 +36,14 @@ pub use docker::DockerRuntime;
 pub use lima::LimaRuntime;
 pub use passthrough::PassthroughRuntime;
 pub use podman::PodmanRuntime;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmRuntime;
+
+/// How often resource usage is sampled for subscribers.
+pub const RESOURCE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The runtime of a manager, once created.
+type RuntimeSlot = Arc<RwLock<Option<Arc<dyn SandboxRuntime>>>>;
 
 /// Manager for sandbox lifecycle and runtime selection.
 ///
@@ -60,12 +80,16 @@ pub struct SandboxManager {
     /// Project root directory on the host
     project_root: PathBuf,
     /// The active runtime (lazily initialized)
-    runtime: RwLock<Option<Arc<dyn SandboxRuntime>>>,
+    runtime: RuntimeSlot,
     /// Detected runtime type (lazily detected if Auto)
     detected_runtime: RwLock<Option<SandboxRuntimeType>>,
     /// Whether the sandbox was explicitly stopped by the user.
     /// When true, auto-start should be disabled.
     explicitly_stopped: RwLock<bool>,
+    /// Resource usage samples, for subscribers
+    resources: broadcast::Sender<ResourceStats>,
+    /// Task sampling resource usage while anyone subscribes
+    resource_poller: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl SandboxManager {
@@ -88,11 +112,13 @@ impl SandboxManager {
         Ok(Self {
             config,
             project_root,
-            runtime: RwLock::new(None),
+            runtime: Arc::new(RwLock::new(None)),
             detected_runtime: RwLock::new(Some(detected_runtime)),
             // Start with explicitly_stopped = true so sandbox doesn't auto-start.
             // User must explicitly call /sandbox start to enable isolation.
             explicitly_stopped: RwLock::new(true),
+            resources: broadcast::channel(16).0,
+            resource_poller: Mutex::new(None),
         })
     }
 
@@ -118,9 +144,11 @@ impl SandboxManager {
         Self {
             config,
             project_root,
-            runtime: RwLock::new(None),
+            runtime: Arc::new(RwLock::new(None)),
             detected_runtime: RwLock::new(detected_runtime),
             explicitly_stopped: RwLock::new(true),
+            resources: broadcast::channel(16).0,
+            resource_poller: Mutex::new(None),
         }
     }
 
@@ -265,6 +293,16 @@ impl SandboxManager {
                 warn!("Lima runtime is only available on macOS, falling back to passthrough");
                 Ok(Arc::new(PassthroughRuntime::new(path_mapper)))
             }
+            #[cfg(feature = "wasm")]
+            SandboxRuntimeType::Wasm => {
+                debug!("Creating wasm runtime");
+                let runtime = WasmRuntime::new(self.config.clone(), path_mapper).await?;
+                Ok(Arc::new(runtime))
+            }
+            #[cfg(not(feature = "wasm"))]
+            SandboxRuntimeType::Wasm => Err(crate::SandboxError::RuntimeNotAvailable(
+                "wasm (built without the `wasm` feature)".to_string(),
+            )),
             SandboxRuntimeType::None | SandboxRuntimeType::Auto => {
                 debug!("Creating passthrough runtime (no sandbox)");
                 Ok(Arc::new(PassthroughRuntime::new(path_mapper)))
@@ -350,6 +388,28 @@ impl SandboxManager {
             .await
     }
 
+    /// Start a command in the sandbox, streaming its output.
+    ///
+    /// If sandboxing is disabled, this will execute on the host.
+    pub async fn execute_streaming(
+        &self,
+        command: &str,
+        workdir: &std::path::Path,
+        timeout: std::time::Duration,
+        capabilities: &crate::SandboxCapabilities,
+    ) -> SandboxResult<crate::SandboxExecution> {
+        let runtime = self.runtime().await?;
+
+        // Start if not running
+        if !runtime.is_ready().await {
+            runtime.start().await?;
+        }
+
+        runtime
+            .execute_streaming(command, workdir, timeout, capabilities)
+            .await
+    }
+
     /// Read a file from the sandbox.
     pub async fn read_file(&self, path: &std::path::Path) -> SandboxResult<Vec<u8>> {
         let runtime = self.runtime().await?;
@@ -378,11 +438,67 @@ impl SandboxManager {
     pub fn should_bypass_tool(&self, tool_name: &str) -> bool {
         self.config.should_bypass(tool_name)
     }
+
+    /// Subscribe to the resource usage of the sandbox.
+    ///
+    /// Usage is sampled every [`RESOURCE_POLL_INTERVAL`] while the sandbox
+    /// runs and anyone is subscribed. Only the Docker and Podman runtimes
+    /// report usage.
+    pub fn subscribe_resources(&self) -> broadcast::Receiver<ResourceStats> {
+        let receiver = self.resources.subscribe();
+        let mut poller = self
+            .resource_poller
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let polling = matches!(poller.as_ref(), Some(task) if !task.is_finished());
+        if !polling {
+            *poller = Some(tokio::spawn(poll_resources(
+                Arc::clone(&self.runtime),
+                self.resources.clone(),
+            )));
+        }
+        receiver
+    }
+}
+
+/// Sample the resource usage of the runtime in `slot` until nobody is
+/// subscribed anymore.
+async fn poll_resources(slot: RuntimeSlot, sender: broadcast::Sender<ResourceStats>) {
+    let mut interval = tokio::time::interval(RESOURCE_POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    while sender.receiver_count() > 0 {
+        interval.tick().await;
+
+        let Some(runtime) = slot.read().await.clone() else {
+            continue;
+        };
+        let runtime_type = runtime.runtime_type();
+        if !matches!(
+            runtime_type,
+            SandboxRuntimeType::Docker | SandboxRuntimeType::Podman
+        ) || !runtime.is_ready().await
+        {
+            continue;
+        }
+
+        // Containers are named by the sandbox ID
+        let monitor = ResourceMonitor::new(runtime.id().to_string(), runtime_type);
+        match monitor.stats().await {
+            Ok(stats) => {
+                let _ = sender.send(stats);
+            }
+            Err(e) => debug!(error = %e, "Failed to sample sandbox resources"),
+        }
+    }
+    debug!("Stopped sampling sandbox resources");
 }
 
 /// Detect available sandbox runtimes.
 ///
-/// Checks for Docker, Podman, and Lima in order of preference.
+/// Checks for Docker, Podman and Lima in order of preference. The wasm
+/// runtime is never picked, as it only runs WebAssembly modules, not
+/// arbitrary commands; it has to be set explicitly.
 #[allow(clippy::cognitive_complexity)]
 pub async fn detect_runtime() -> SandboxRuntimeType {
     // Check Docker
@@ -465,4 +581,23 @@ mod tests {
         // Starting when disabled should be a no-op
         manager.start().await.unwrap();
     }
+
+    #[tokio::test]
+    async fn test_subscribe_resources() {
+        let manager =
+            SandboxManager::new_lazy(SandboxConfig::default(), PathBuf::from("/tmp/test"));
+
+        let mut first = manager.subscribe_resources();
+        let mut second = manager.subscribe_resources();
+
+        // Without a running container there's nothing to report
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(first.try_recv().is_err());
+        assert!(second.try_recv().is_err());
+
+        // One poller serves all subscribers
+        assert_eq!(manager.resources.receiver_count(), 2);
+        let poller = manager.resource_poller.lock().unwrap();
+        assert!(poller.as_ref().is_some_and(|task| !task.is_finished()));
+    }
 }
@@ -7,12 +7,13 @@
 use crate::{
     error::{SandboxError, SandboxResult},
     path::PathMapper,
-    SandboxCapabilities, SandboxDirEntry, SandboxInfo, SandboxMetadata, SandboxOutput,
-    SandboxRuntime, SandboxRuntimeType, SandboxStatus,
+    SandboxCapabilities, SandboxDirEntry, SandboxExecution, SandboxInfo, SandboxMetadata,
+    SandboxOutput, SandboxRuntime, SandboxRuntimeType, SandboxStatus,
 };
 use async_trait::async_trait;
 use std::{
     path::{Path, PathBuf},
+    process::Stdio,
     time::Duration,
 };
 use tokio::process::Command;
@@ -95,35 +96,50 @@ impl SandboxRuntime for PassthroughRuntime {
         command: &str,
         workdir: &Path,
         timeout: Duration,
-        _capabilities: &SandboxCapabilities,
+        capabilities: &SandboxCapabilities,
     ) -> SandboxResult<SandboxOutput> {
-        debug!(command = %command, workdir = %workdir.display(), "Executing command (passthrough)");
+        self.execute_streaming(command, workdir, timeout, capabilities)
+            .await?
+            .wait()
+            .await
+    }
 
-        let result = tokio::time::timeout(timeout, async {
-            let output = Command::new("sh")
-                .arg("-c")
-                .arg(command)
-                .current_dir(workdir)
-                .env("TERM", "dumb")
-                .env("NO_COLOR", "1")
-                .env("GIT_TERMINAL_PROMPT", "0")
-                .output()
-                .await
-                .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
-
-            Ok(SandboxOutput {
-                stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
-                exit_code: output.status.code().unwrap_or(-1),
-                success: output.status.success(),
-            })
-        })
-        .await;
+    async fn execute_streaming(
+        &self,
+        command: &str,
+        workdir: &Path,
+        timeout: Duration,
+        _capabilities: &SandboxCapabilities,
+    ) -> SandboxResult<SandboxExecution> {
+        debug!(command = %command, workdir = %workdir.display(), "Executing command (passthrough)");
 
-        match result {
-            Ok(output) => output,
-            Err(_) => Err(SandboxError::Timeout(timeout)),
-        }
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(workdir)
+            .env("TERM", "dumb")
+            .env("NO_COLOR", "1")
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| SandboxError::ExecFailed(e.to_string()))?;
+
+        let (execution, mut feed) = SandboxExecution::channel(child.id());
+        tokio::spawn(async move {
+            let kill = feed.kill_handle();
+            let exit_code = tokio::select! {
+                exit_code = feed.read_child(&mut child) => exit_code,
+                error = kill.stopped(timeout) => Err(error),
+            };
+            if exit_code.is_err() {
+                let _ = child.kill().await;
+            }
+            feed.finish(exit_code);
+        });
+        Ok(execution)
     }
 
     async fn read_file(&self, path: &Path) -> SandboxResult<Vec<u8>> {
@@ -221,6 +237,8 @@ impl SandboxRuntime for PassthroughRuntime {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::OutputChunk;
+    use futures::StreamExt;
     use tempfile::TempDir;
 
     #[tokio::test]
@@ -242,6 +260,62 @@ mod tests {
         assert_eq!(output.stdout.trim(), "hello");
     }
 
+    #[tokio::test]
+    async fn test_passthrough_execute_streaming() {
+        let temp_dir = TempDir::new().unwrap();
+        let runtime = PassthroughRuntime::with_root(temp_dir.path().to_path_buf());
+
+        let mut execution = runtime
+            .execute_streaming(
+                "echo one; echo two >&2; exit 2",
+                temp_dir.path(),
+                Duration::from_secs(10),
+                &SandboxCapabilities::default(),
+            )
+            .await
+            .unwrap();
+        assert!(execution.pid().is_some());
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        while let Some(chunk) = execution.next().await {
+            match chunk {
+                OutputChunk::Stdout(text) => stdout.push_str(&text),
+                OutputChunk::Stderr(text) => stderr.push_str(&text),
+            }
+        }
+        assert_eq!(stdout, "one\n");
+        assert_eq!(stderr, "two\n");
+
+        let output = execution.wait().await.unwrap();
+        assert_eq!(output.exit_code, 2);
+        assert_eq!(output.stdout, "one\n");
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_kill() {
+        let temp_dir = TempDir::new().unwrap();
+        let runtime = PassthroughRuntime::with_root(temp_dir.path().to_path_buf());
+
+        let mut execution = runtime
+            .execute_streaming(
+                "echo started; sleep 10",
+                temp_dir.path(),
+                Duration::from_secs(30),
+                &SandboxCapabilities::default(),
+            )
+            .await
+            .unwrap();
+        let first = execution.next().await.unwrap();
+        assert_eq!(first, OutputChunk::Stdout("started\n".to_string()));
+
+        execution.kill();
+        let result = tokio::time::timeout(Duration::from_secs(5), execution.wait())
+            .await
+            .unwrap();
+        assert!(matches!(result, Err(SandboxError::Killed)));
+    }
+
     #[tokio::test]
     async fn test_passthrough_read_write() {
         let temp_dir = TempDir::new().unwrap();
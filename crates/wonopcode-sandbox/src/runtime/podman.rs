@@ -11,19 +11,21 @@
 //! - Compatible with Docker images and Dockerfiles
 // @ace:implements COMP-T90R7O-674
 
+use super::firewall::{FilterLock, Rules};
 use crate::{
     config::SandboxConfig,
     error::{SandboxError, SandboxResult},
+    execution::{kill_script, new_marker, KILL_TIMEOUT, MARKER_VAR},
     path::PathMapper,
-    SandboxCapabilities, SandboxDirEntry, SandboxInfo, SandboxMetadata, SandboxOutput,
-    SandboxRuntime, SandboxRuntimeType, SandboxStatus,
+    ProjectImage, SandboxCapabilities, SandboxDirEntry, SandboxExecution, SandboxInfo,
+    SandboxMetadata, SandboxOutput, SandboxRuntime, SandboxRuntimeType, SandboxStatus,
 };
 use async_trait::async_trait;
 use std::{path::Path, process::Stdio, time::Duration};
 use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 use tokio::sync::RwLock;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 /// Podman-based sandbox runtime.
 pub struct PodmanRuntime {
@@ -37,8 +39,15 @@ pub struct PodmanRuntime {
     path_mapper: PathMapper,
     /// Current status
     status: RwLock<SandboxStatus>,
+    /// Taken by each command, alone while it runs under a host filter
+    filter_lock: FilterLock,
+    /// Image of the container, once resolved
+    image: RwLock<String>,
 }
 
+/// Timeout for applying and removing host filters.
+const FILTER_TIMEOUT: Duration = Duration::from_secs(30);
+
 impl PodmanRuntime {
     /// Create a new Podman runtime.
     pub async fn new(config: SandboxConfig, path_mapper: PathMapper) -> SandboxResult<Self> {
@@ -68,9 +77,11 @@ impl PodmanRuntime {
         Ok(Self {
             id,
             container_id: RwLock::new(None),
+            image: RwLock::new(config.image().to_string()),
             config,
             path_mapper,
             status: RwLock::new(SandboxStatus::NotInitialized),
+            filter_lock: FilterLock::default(),
         })
     }
 
@@ -95,10 +106,27 @@ impl PodmanRuntime {
             .map_err(|e| SandboxError::ExecFailed(format!("Podman command failed: {e}")))
     }
 
-    /// Ensure the container image is available.
-    async fn ensure_image(&self) -> SandboxResult<()> {
-        let image = self.config.image();
+    /// The image to run: the configured one, or the one for the project if
+    /// it's built from the project, building it if needed.
+    async fn resolve_image(&self) -> SandboxResult<String> {
+        if !self.config.builds_image() {
+            return Ok(self.config.image().to_string());
+        }
+
+        let project = ProjectImage::resolve(self.path_mapper.host_root(), &self.id)?;
+        if let ProjectImage::Build(build) = &project {
+            let output = self.podman(&["image", "exists", &build.tag]).await?;
+            if !output.status.success() {
+                build.run("podman").await?;
+            }
+        }
+        let image = project.image().to_string();
+        *self.image.write().await = image.clone();
+        Ok(image)
+    }
 
+    /// Ensure the container image is available.
+    async fn ensure_image(&self, image: &str) -> SandboxResult<()> {
         // Check if image exists locally
         let output = self.podman(&["image", "exists", image]).await?;
         if output.status.success() {
@@ -120,9 +148,8 @@ impl PodmanRuntime {
         }
     }
 
-    /// Build podman run arguments.
-    fn build_run_args(&self) -> Vec<String> {
-        let image = self.config.image().to_string();
+    /// Build podman run arguments for a container of `image`.
+    fn build_run_args(&self, image: &str) -> Vec<String> {
         let host_root = self.path_mapper.host_root().to_string_lossy().to_string();
         let sandbox_root = self
             .path_mapper
@@ -198,9 +225,16 @@ impl PodmanRuntime {
         args.push("-e".to_string());
         args.push("DEBIAN_FRONTEND=noninteractive".to_string());
 
-        // Image and command
-        args.push(image);
-        args.push("sleep".to_string());
+        // Image and command. Project images may have an entrypoint that runs
+        // the project.
+        if self.config.builds_image() {
+            args.push("--entrypoint".to_string());
+            args.push("sleep".to_string());
+            args.push(image.to_string());
+        } else {
+            args.push(image.to_string());
+            args.push("sleep".to_string());
+        }
         args.push("infinity".to_string());
 
         args
@@ -245,24 +279,23 @@ impl PodmanRuntime {
         command: &str,
         workdir: &Path,
         timeout: Duration,
+    ) -> SandboxResult<SandboxOutput> {
+        self.exec(command, workdir, timeout, false).await
+    }
+
+    /// Execute a command in the container, with all capabilities if
+    /// `privileged`.
+    async fn exec(
+        &self,
+        command: &str,
+        workdir: &Path,
+        timeout: Duration,
+        privileged: bool,
     ) -> SandboxResult<SandboxOutput> {
         let container_id = self.container_id.read().await;
         let container_id = container_id.as_ref().ok_or(SandboxError::NotRunning)?;
 
-        let workdir_str = workdir.to_string_lossy().to_string();
-
-        let mut cmd = Command::new("podman");
-        cmd.args([
-            "exec",
-            "-w",
-            &workdir_str,
-            container_id,
-            "sh",
-            "-c",
-            command,
-        ]);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+        let mut cmd = exec_cmd(container_id, command, workdir, privileged, None);
 
         let result = tokio::time::timeout(timeout, async {
             let mut child = cmd
@@ -309,6 +342,56 @@ impl PodmanRuntime {
     }
 }
 
+/// A `podman exec` running `command`, tagged with `marker` if given.
+fn exec_cmd(
+    container_id: &str,
+    command: &str,
+    workdir: &Path,
+    privileged: bool,
+    marker: Option<&str>,
+) -> Command {
+    let workdir_str = workdir.to_string_lossy().to_string();
+
+    let mut cmd = Command::new("podman");
+    cmd.arg("exec");
+    if privileged {
+        cmd.arg("--privileged");
+    }
+    if let Some(marker) = marker {
+        cmd.args(["--env", &format!("{MARKER_VAR}={marker}")]);
+    }
+    cmd.args(["-w", &workdir_str, container_id, "sh", "-c", command]);
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd.kill_on_drop(true);
+    cmd
+}
+
+/// Run a privileged script in a container, logging a failure as `what`.
+/// Returns whether it succeeded.
+async fn run_script(container_id: &str, script: &str, what: &str) -> bool {
+    let run = exec_cmd(container_id, script, Path::new("/"), true, None).output();
+    match tokio::time::timeout(KILL_TIMEOUT, run).await {
+        Ok(Ok(output)) if output.status.success() => true,
+        Ok(Ok(output)) => {
+            warn!(
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "Failed to {}", what
+            );
+            false
+        }
+        Ok(Err(e)) => {
+            warn!(error = %e, "Failed to {}", what);
+            false
+        }
+        Err(_) => {
+            warn!("Timed out trying to {}", what);
+            false
+        }
+    }
+}
+
 #[async_trait]
 impl SandboxRuntime for PodmanRuntime {
     fn id(&self) -> &str {
@@ -319,6 +402,13 @@ impl SandboxRuntime for PodmanRuntime {
         SandboxRuntimeType::Podman
     }
 
+    fn capabilities_for(&self, command: &str) -> SandboxCapabilities {
+        SandboxCapabilities {
+            hosts: self.config.host_filter(command),
+            ..Default::default()
+        }
+    }
+
     async fn status(&self) -> SandboxStatus {
         *self.status.read().await
     }
@@ -328,7 +418,7 @@ impl SandboxRuntime for PodmanRuntime {
             id: self.id.clone(),
             runtime_type: SandboxRuntimeType::Podman,
             status: self.status().await,
-            image: self.config.image().to_string(),
+            image: self.image.read().await.clone(),
             host_root: self.path_mapper.host_root().to_path_buf(),
             workspace_path: self.path_mapper.sandbox_root().to_path_buf(),
         }
@@ -348,10 +438,28 @@ impl SandboxRuntime for PodmanRuntime {
         *self.status.write().await = SandboxStatus::Starting;
 
         // Ensure image is available
-        self.ensure_image().await?;
+        let image = self.resolve_image().await?;
+        self.ensure_image(&image).await?;
 
         // Check for existing container
-        if let Some(existing_id) = self.find_existing_container().await {
+        let mut existing = self.find_existing_container().await;
+        if let Some(existing_id) = existing.as_ref().filter(|_| self.config.builds_image()) {
+            // Replace containers of a project image that has been rebuilt since
+            let output = self
+                .podman(&["inspect", "--format", "{{.ImageName}}", existing_id])
+                .await?;
+            if String::from_utf8_lossy(&output.stdout).trim() != image {
+                info!(container_id = %existing_id, image = %image, "Replacing container for rebuilt image");
+                let output = self.podman(&["rm", "-f", existing_id]).await?;
+                if !output.status.success() {
+                    return Err(SandboxError::RemoveFailed(
+                        String::from_utf8_lossy(&output.stderr).to_string(),
+                    ));
+                }
+                existing = None;
+            }
+        }
+        if let Some(existing_id) = existing {
             debug!(container_id = %existing_id, "Found existing container");
             *self.container_id.write().await = Some(existing_id.clone());
 
@@ -368,7 +476,7 @@ impl SandboxRuntime for PodmanRuntime {
         }
 
         // Create new container
-        let run_args = self.build_run_args();
+        let run_args = self.build_run_args(&image);
         let args: Vec<&str> = run_args.iter().map(|s| s.as_str()).collect();
         let output = self.podman(&args).await?;
 
@@ -413,14 +521,90 @@ impl SandboxRuntime for PodmanRuntime {
         command: &str,
         workdir: &Path,
         timeout: Duration,
-        _capabilities: &SandboxCapabilities,
+        capabilities: &SandboxCapabilities,
     ) -> SandboxResult<SandboxOutput> {
+        self.execute_streaming(command, workdir, timeout, capabilities)
+            .await?
+            .wait()
+            .await
+    }
+
+    async fn execute_streaming(
+        &self,
+        command: &str,
+        workdir: &Path,
+        timeout: Duration,
+        capabilities: &SandboxCapabilities,
+    ) -> SandboxResult<SandboxExecution> {
         if !self.is_ready().await {
             return Err(SandboxError::NotRunning);
         }
+        let container_id = self
+            .container_id
+            .read()
+            .await
+            .clone()
+            .ok_or(SandboxError::NotRunning)?;
 
         debug!(command = %command, workdir = %workdir.display(), "Executing command");
-        self.exec_command(command, workdir, timeout).await
+        let rules = Rules::resolve(&capabilities.hosts, &self.config.network).await?;
+
+        // The rules apply to the whole container, so a filtered command runs alone
+        let turn = self.filter_lock.turn(rules.is_some()).await;
+        if let Some(rules) = &rules {
+            let applied = self
+                .exec(&rules.apply_script(), Path::new("/"), FILTER_TIMEOUT, true)
+                .await?;
+            if !applied.success {
+                return Err(SandboxError::ExecFailed(format!(
+                    "failed to apply host filter: {}",
+                    applied.stderr.trim()
+                )));
+            }
+        }
+
+        let marker = new_marker();
+        let spawned = exec_cmd(&container_id, command, workdir, false, Some(&marker)).spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                if turn.is_filtered() {
+                    run_script(&container_id, &Rules::reset_script(), "remove host filter").await;
+                }
+                return Err(SandboxError::ExecFailed(e.to_string()));
+            }
+        };
+
+        // The pid is of the podman client, which the command outlives if
+        // killed on its own
+        let (execution, mut feed) = SandboxExecution::channel(None);
+        tokio::spawn(async move {
+            let kill = feed.kill_handle();
+            let exit_code = tokio::select! {
+                exit_code = feed.read_child(&mut child) => exit_code,
+                error = kill.stopped(timeout) => Err(error),
+            };
+
+            // Processes a filtered command left running would get out once
+            // the rules are removed
+            let filtered = turn.is_filtered();
+            let killed = if exit_code.is_err() || filtered {
+                run_script(&container_id, &kill_script(&marker), "kill command").await
+            } else {
+                true
+            };
+            if exit_code.is_err() {
+                let _ = child.kill().await;
+            }
+            if filtered && killed {
+                run_script(&container_id, &Rules::reset_script(), "remove host filter").await;
+            } else if filtered {
+                warn!("Keeping the host filter, since processes of the command may still run");
+            }
+            drop(turn);
+            feed.finish(exit_code);
+        });
+        Ok(execution)
     }
 
     async fn read_file(&self, path: &Path) -> SandboxResult<Vec<u8>> {
@@ -0,0 +1,975 @@
+//! WASI host of the wasm runtime.
+//!
+//! Runs a module with wasmtime, implementing the part of
+//! `wasi_snapshot_preview1` command-line tools use: arguments, environment,
+//! clocks, randomness, the standard streams, and files under the preopened
+//! directories. Paths are resolved on the host and kept in the workspace,
+//! symlinks included. Other WASI functions, like sockets, fail with `ENOSYS`.
+
+use super::wasm::confine;
+use crate::{SandboxError, SandboxOutput, SandboxResult};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use wasmtime::{
+    Caller, Config, Engine, Extern, ExternType, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder, Trap, Val, ValType,
+};
+
+/// Module the WASI functions are imported from.
+const WASI: &str = "wasi_snapshot_preview1";
+
+/// Exit code of a module that trapped, as with the wasmtime CLI.
+const TRAP_EXIT_CODE: i32 = 134;
+
+/// A WASI error number.
+type Errno = i32;
+
+const SUCCESS: Errno = 0;
+const EACCES: Errno = 2;
+const EBADF: Errno = 8;
+const EEXIST: Errno = 20;
+const EFAULT: Errno = 21;
+const EILSEQ: Errno = 25;
+const EINVAL: Errno = 28;
+const EIO: Errno = 29;
+const EISDIR: Errno = 31;
+const ENOENT: Errno = 44;
+const ENOSYS: Errno = 52;
+const ENOTDIR: Errno = 54;
+const ESPIPE: Errno = 70;
+const ENOTCAPABLE: Errno = 76;
+
+// File types
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+const FILETYPE_SYMBOLIC_LINK: u8 = 7;
+
+// Flags of `path_open`
+const OFLAGS_CREAT: i32 = 1;
+const OFLAGS_DIRECTORY: i32 = 2;
+const OFLAGS_EXCL: i32 = 4;
+const OFLAGS_TRUNC: i32 = 8;
+const FDFLAGS_APPEND: i32 = 1;
+const RIGHTS_FD_READ: i64 = 1 << 1;
+const RIGHTS_FD_WRITE: i64 = 1 << 6;
+
+/// A module to run.
+pub(crate) struct Invocation {
+    /// Host path of the module.
+    pub module: PathBuf,
+    /// Arguments, starting with the program name.
+    pub args: Vec<String>,
+    /// Environment variables.
+    pub env: Vec<(String, String)>,
+    /// Directories files can be opened in, by the path the module knows
+    /// them by. Host paths are canonical.
+    pub preopens: Vec<(String, PathBuf)>,
+    /// Canonical host path of the workspace, which no file access leaves.
+    pub root: PathBuf,
+    /// Maximum memory size in bytes.
+    pub max_memory: Option<u64>,
+}
+
+/// Run a module to its end, failing if it runs out of `timeout`.
+///
+/// Blocks the thread while the module runs.
+pub(crate) fn run(invocation: Invocation, timeout: Duration) -> SandboxResult<SandboxOutput> {
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config).map_err(exec_failed)?;
+    let module = Module::from_file(&engine, &invocation.module).map_err(exec_failed)?;
+
+    let mut limits = StoreLimitsBuilder::new();
+    if let Some(bytes) = invocation.max_memory {
+        limits = limits.memory_size(usize::try_from(bytes).unwrap_or(usize::MAX));
+    }
+    let mut store = Store::new(&engine, Host::new(invocation, limits.build()));
+    store.limiter(|host| &mut host.limits);
+    store.set_epoch_deadline(1);
+    store.epoch_deadline_trap();
+
+    let mut linker = Linker::new(&engine);
+    add_to_linker(&mut linker).map_err(exec_failed)?;
+    for import in module.imports() {
+        if import.module() != WASI || linker.get_by_import(&mut store, &import).is_some() {
+            continue;
+        }
+        // Functions left out fail like an unsupported system call
+        if let ExternType::Func(ty) = import.ty() {
+            if ty.results().len() == 1 && matches!(ty.results().next(), Some(ValType::I32)) {
+                linker
+                    .func_new(WASI, import.name(), ty, |_, _, results| {
+                        results[0] = Val::I32(ENOSYS);
+                        Ok(())
+                    })
+                    .map_err(exec_failed)?;
+            }
+        }
+    }
+    linker
+        .define_unknown_imports_as_traps(&module)
+        .map_err(exec_failed)?;
+
+    let start = linker
+        .instantiate(&mut store, &module)
+        .and_then(|instance| instance.get_typed_func::<(), ()>(&mut store, "_start"))
+        .map_err(exec_failed)?;
+
+    // Interrupt the module once its time is up
+    let (done, timer) = mpsc::channel::<()>();
+    let clock = std::thread::spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = timer.recv_timeout(timeout) {
+            engine.increment_epoch();
+        }
+    });
+    let result = start.call(&mut store, ());
+    drop(done);
+    let _ = clock.join();
+
+    let exit_code = match result {
+        Ok(()) => 0,
+        Err(e) => match e.downcast_ref::<Exit>() {
+            Some(Exit(code)) => *code,
+            None if e.downcast_ref::<Trap>() == Some(&Trap::Interrupt) => {
+                return Err(SandboxError::Timeout(timeout));
+            }
+            None => {
+                let _ = writeln!(store.data_mut().stderr, "Error: {e:?}");
+                TRAP_EXIT_CODE
+            }
+        },
+    };
+
+    let host = store.into_data();
+    Ok(SandboxOutput::from_output(
+        String::from_utf8_lossy(&host.stdout).into_owned(),
+        String::from_utf8_lossy(&host.stderr).into_owned(),
+        exit_code,
+    ))
+}
+
+fn exec_failed(e: wasmtime::Error) -> SandboxError {
+    SandboxError::ExecFailed(format!("{e:#}"))
+}
+
+/// A module calling `proc_exit`.
+#[derive(Debug)]
+struct Exit(i32);
+
+impl std::fmt::Display for Exit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exited with code {}", self.0)
+    }
+}
+
+impl std::error::Error for Exit {}
+
+/// An open file descriptor of a module.
+enum Descriptor {
+    Stdin,
+    Stdout,
+    Stderr,
+    Dir {
+        host: PathBuf,
+        /// The path of a preopened directory.
+        preopen: Option<String>,
+    },
+    File {
+        file: File,
+        append: bool,
+    },
+}
+
+/// State of a running module.
+struct Host {
+    args: Vec<String>,
+    env: Vec<String>,
+    root: PathBuf,
+    fds: Vec<Option<Descriptor>>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    started: Instant,
+    limits: StoreLimits,
+}
+
+impl Host {
+    fn new(invocation: Invocation, limits: StoreLimits) -> Self {
+        let mut fds = vec![
+            Some(Descriptor::Stdin),
+            Some(Descriptor::Stdout),
+            Some(Descriptor::Stderr),
+        ];
+        fds.extend(invocation.preopens.into_iter().map(|(guest, host)| {
+            Some(Descriptor::Dir {
+                host,
+                preopen: Some(guest),
+            })
+        }));
+        Self {
+            args: invocation.args,
+            env: invocation
+                .env
+                .into_iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect(),
+            root: invocation.root,
+            fds,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            started: Instant::now(),
+            limits,
+        }
+    }
+
+    fn fd(&mut self, fd: i32) -> Result<&mut Descriptor, Errno> {
+        usize::try_from(fd)
+            .ok()
+            .and_then(|fd| self.fds.get_mut(fd))
+            .and_then(Option::as_mut)
+            .ok_or(EBADF)
+    }
+
+    fn open(&mut self, descriptor: Descriptor) -> i32 {
+        let fd = match self.fds.iter().position(Option::is_none) {
+            Some(fd) => {
+                self.fds[fd] = Some(descriptor);
+                fd
+            }
+            None => {
+                self.fds.push(Some(descriptor));
+                self.fds.len() - 1
+            }
+        };
+        fd as i32
+    }
+
+    /// The host path of `path` in the directory `fd`, kept in the
+    /// workspace. With `follow`, a symlink at the end is resolved too.
+    fn resolve(&mut self, fd: i32, path: &str, follow: bool) -> Result<PathBuf, Errno> {
+        let Descriptor::Dir { host, .. } = self.fd(fd)? else {
+            return Err(ENOTDIR);
+        };
+        let mut full = host.clone();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(name) => full.push(name),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    full.pop();
+                }
+                Component::RootDir | Component::Prefix(_) => return Err(ENOTCAPABLE),
+            }
+        }
+
+        if follow {
+            return confine(&self.root, &full).ok_or(ENOTCAPABLE);
+        }
+        let name = full.file_name().ok_or(EINVAL)?.to_os_string();
+        let parent = full.parent().ok_or(EINVAL)?;
+        let parent = confine(&self.root, parent).ok_or(ENOTCAPABLE)?;
+        Ok(parent.join(name))
+    }
+}
+
+/// The memory of the calling module, along with the host state.
+fn memory<'a>(caller: &'a mut Caller<'_, Host>) -> Result<(&'a mut [u8], &'a mut Host), Errno> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or(EFAULT)?;
+    Ok(memory.data_and_store_mut(caller))
+}
+
+fn bytes(mem: &[u8], ptr: i32, len: i32) -> Result<&[u8], Errno> {
+    let start = ptr as u32 as usize;
+    let end = start.checked_add(len as u32 as usize).ok_or(EFAULT)?;
+    mem.get(start..end).ok_or(EFAULT)
+}
+
+fn bytes_mut(mem: &mut [u8], ptr: i32, len: i32) -> Result<&mut [u8], Errno> {
+    let start = ptr as u32 as usize;
+    let end = start.checked_add(len as u32 as usize).ok_or(EFAULT)?;
+    mem.get_mut(start..end).ok_or(EFAULT)
+}
+
+fn string(mem: &[u8], ptr: i32, len: i32) -> Result<&str, Errno> {
+    std::str::from_utf8(bytes(mem, ptr, len)?).map_err(|_| EILSEQ)
+}
+
+fn read_u32(mem: &[u8], ptr: i32) -> Result<u32, Errno> {
+    let bytes = bytes(mem, ptr, 4)?;
+    Ok(u32::from_le_bytes(bytes.try_into().map_err(|_| EFAULT)?))
+}
+
+fn write_bytes(mem: &mut [u8], ptr: i32, value: &[u8]) -> Result<(), Errno> {
+    bytes_mut(mem, ptr, value.len() as i32)?.copy_from_slice(value);
+    Ok(())
+}
+
+fn write_u32(mem: &mut [u8], ptr: i32, value: u32) -> Result<(), Errno> {
+    write_bytes(mem, ptr, &value.to_le_bytes())
+}
+
+fn write_u64(mem: &mut [u8], ptr: i32, value: u64) -> Result<(), Errno> {
+    write_bytes(mem, ptr, &value.to_le_bytes())
+}
+
+/// The buffers of a list of iovecs.
+fn iovecs(mem: &[u8], ptr: i32, len: i32) -> Result<Vec<(i32, i32)>, Errno> {
+    (0..len)
+        .map(|i| {
+            let iovec = ptr.wrapping_add(i.wrapping_mul(8));
+            Ok((
+                read_u32(mem, iovec)? as i32,
+                read_u32(mem, iovec.wrapping_add(4))? as i32,
+            ))
+        })
+        .collect()
+}
+
+fn io_errno(e: &io::Error) -> Errno {
+    match e.kind() {
+        io::ErrorKind::NotFound => ENOENT,
+        io::ErrorKind::PermissionDenied => EACCES,
+        io::ErrorKind::AlreadyExists => EEXIST,
+        io::ErrorKind::InvalidInput => EINVAL,
+        _ => EIO,
+    }
+}
+
+fn errno(result: Result<(), Errno>) -> i32 {
+    result.err().unwrap_or(SUCCESS)
+}
+
+/// Write strings as WASI passes arguments: pointers at `ptrs`, and the
+/// NUL-terminated strings at `buf`.
+fn write_strings(mem: &mut [u8], strings: &[String], ptrs: i32, buf: i32) -> Result<(), Errno> {
+    let mut offset = buf;
+    for (i, string) in strings.iter().enumerate() {
+        write_u32(mem, ptrs.wrapping_add(i as i32 * 4), offset as u32)?;
+        write_bytes(mem, offset, string.as_bytes())?;
+        write_bytes(mem, offset.wrapping_add(string.len() as i32), &[0])?;
+        offset = offset.wrapping_add(string.len() as i32 + 1);
+    }
+    Ok(())
+}
+
+fn write_sizes(mem: &mut [u8], strings: &[String], count: i32, size: i32) -> Result<(), Errno> {
+    let total: usize = strings.iter().map(|string| string.len() + 1).sum();
+    write_u32(mem, count, strings.len() as u32)?;
+    write_u32(mem, size, total as u32)
+}
+
+/// A WASI `filestat`.
+fn filestat(meta: &std::fs::Metadata) -> [u8; 64] {
+    let nanos = |time: io::Result<SystemTime>| {
+        time.ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos() as u64)
+    };
+    let filetype = if meta.is_dir() {
+        FILETYPE_DIRECTORY
+    } else if meta.file_type().is_symlink() {
+        FILETYPE_SYMBOLIC_LINK
+    } else {
+        FILETYPE_REGULAR_FILE
+    };
+    let mut stat = [0u8; 64];
+    stat[16] = filetype;
+    stat[24..32].copy_from_slice(&1u64.to_le_bytes());
+    stat[32..40].copy_from_slice(&meta.len().to_le_bytes());
+    stat[40..48].copy_from_slice(&nanos(meta.accessed()).to_le_bytes());
+    stat[48..56].copy_from_slice(&nanos(meta.modified()).to_le_bytes());
+    stat[56..64].copy_from_slice(&nanos(meta.modified()).to_le_bytes());
+    stat
+}
+
+fn add_to_linker(linker: &mut Linker<Host>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        WASI,
+        "args_sizes_get",
+        |mut caller: Caller<'_, Host>, count: i32, size: i32| -> i32 {
+            errno(
+                memory(&mut caller)
+                    .and_then(|(mem, host)| write_sizes(mem, &host.args, count, size)),
+            )
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "args_get",
+        |mut caller: Caller<'_, Host>, ptrs: i32, buf: i32| -> i32 {
+            errno(
+                memory(&mut caller)
+                    .and_then(|(mem, host)| write_strings(mem, &host.args, ptrs, buf)),
+            )
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "environ_sizes_get",
+        |mut caller: Caller<'_, Host>, count: i32, size: i32| -> i32 {
+            errno(
+                memory(&mut caller)
+                    .and_then(|(mem, host)| write_sizes(mem, &host.env, count, size)),
+            )
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "environ_get",
+        |mut caller: Caller<'_, Host>, ptrs: i32, buf: i32| -> i32 {
+            errno(
+                memory(&mut caller)
+                    .and_then(|(mem, host)| write_strings(mem, &host.env, ptrs, buf)),
+            )
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "clock_res_get",
+        |mut caller: Caller<'_, Host>, id: i32, res: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, _)| match id {
+                0..=3 => write_u64(mem, res, 1),
+                _ => Err(EINVAL),
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "clock_time_get",
+        |mut caller: Caller<'_, Host>, id: i32, _precision: i64, time: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let nanos = match id {
+                    0 => SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |since| since.as_nanos() as u64),
+                    // Process and thread time go by the monotonic clock
+                    1..=3 => host.started.elapsed().as_nanos() as u64,
+                    _ => return Err(EINVAL),
+                };
+                write_u64(mem, time, nanos)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "random_get",
+        |mut caller: Caller<'_, Host>, buf: i32, len: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, _)| {
+                for chunk in bytes_mut(mem, buf, len)?.chunks_mut(16) {
+                    let random = uuid::Uuid::new_v4().into_bytes();
+                    chunk.copy_from_slice(&random[..chunk.len()]);
+                }
+                Ok(())
+            }))
+        },
+    )?;
+    linker.func_wrap(WASI, "sched_yield", || -> i32 { SUCCESS })?;
+    linker.func_wrap(WASI, "proc_exit", |code: i32| -> wasmtime::Result<()> {
+        Err(wasmtime::Error::new(Exit(code)))
+    })?;
+
+    linker.func_wrap(
+        WASI,
+        "fd_write",
+        |mut caller: Caller<'_, Host>, fd: i32, iovs: i32, len: i32, written: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let mut data = Vec::new();
+                for (ptr, len) in iovecs(mem, iovs, len)? {
+                    data.extend_from_slice(bytes(mem, ptr, len)?);
+                }
+                match host.fd(fd)? {
+                    Descriptor::Stdout => host.stdout.extend_from_slice(&data),
+                    Descriptor::Stderr => host.stderr.extend_from_slice(&data),
+                    Descriptor::File { file, .. } => {
+                        file.write_all(&data).map_err(|e| io_errno(&e))?;
+                    }
+                    Descriptor::Stdin => return Err(EBADF),
+                    Descriptor::Dir { .. } => return Err(EISDIR),
+                }
+                write_u32(mem, written, data.len() as u32)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_read",
+        |mut caller: Caller<'_, Host>, fd: i32, iovs: i32, len: i32, read: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let mut total = 0;
+                match host.fd(fd)? {
+                    // Nothing is piped in
+                    Descriptor::Stdin => {}
+                    Descriptor::File { file, .. } => {
+                        for (ptr, len) in iovecs(mem, iovs, len)? {
+                            let buf = bytes_mut(mem, ptr, len)?;
+                            let n = file.read(buf).map_err(|e| io_errno(&e))?;
+                            total += n;
+                            if n < buf.len() {
+                                break;
+                            }
+                        }
+                    }
+                    Descriptor::Dir { .. } => return Err(EISDIR),
+                    Descriptor::Stdout | Descriptor::Stderr => return Err(EBADF),
+                }
+                write_u32(mem, read, total as u32)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_close",
+        |mut caller: Caller<'_, Host>, fd: i32| -> i32 {
+            let host = caller.data_mut();
+            let result = host.fd(fd).map(|_| ());
+            if result.is_ok() {
+                host.fds[fd as usize] = None;
+            }
+            errno(result)
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_seek",
+        |mut caller: Caller<'_, Host>, fd: i32, offset: i64, whence: i32, position: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let Descriptor::File { file, .. } = host.fd(fd)? else {
+                    return Err(ESPIPE);
+                };
+                let from = match whence {
+                    0 => SeekFrom::Start(u64::try_from(offset).map_err(|_| EINVAL)?),
+                    1 => SeekFrom::Current(offset),
+                    2 => SeekFrom::End(offset),
+                    _ => return Err(EINVAL),
+                };
+                let new = file.seek(from).map_err(|e| io_errno(&e))?;
+                write_u64(mem, position, new)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_tell",
+        |mut caller: Caller<'_, Host>, fd: i32, position: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let Descriptor::File { file, .. } = host.fd(fd)? else {
+                    return Err(ESPIPE);
+                };
+                let current = file.stream_position().map_err(|e| io_errno(&e))?;
+                write_u64(mem, position, current)
+            }))
+        },
+    )?;
+    for name in ["fd_sync", "fd_datasync"] {
+        linker.func_wrap(WASI, name, |mut caller: Caller<'_, Host>, fd: i32| -> i32 {
+            errno(
+                caller
+                    .data_mut()
+                    .fd(fd)
+                    .and_then(|descriptor| match descriptor {
+                        Descriptor::File { file, .. } => file.sync_all().map_err(|e| io_errno(&e)),
+                        _ => Ok(()),
+                    }),
+            )
+        })?;
+    }
+    linker.func_wrap(
+        WASI,
+        "fd_fdstat_get",
+        |mut caller: Caller<'_, Host>, fd: i32, stat: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let (filetype, flags) = match host.fd(fd)? {
+                    Descriptor::Stdin | Descriptor::Stdout | Descriptor::Stderr => {
+                        (FILETYPE_CHARACTER_DEVICE, 0u16)
+                    }
+                    Descriptor::Dir { .. } => (FILETYPE_DIRECTORY, 0),
+                    Descriptor::File { append, .. } => (FILETYPE_REGULAR_FILE, u16::from(*append)),
+                };
+                let mut fdstat = [0u8; 24];
+                fdstat[0] = filetype;
+                fdstat[2..4].copy_from_slice(&flags.to_le_bytes());
+                // All rights; access is checked when files are used
+                fdstat[8..24].fill(0xff);
+                write_bytes(mem, stat, &fdstat)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_fdstat_set_flags",
+        |mut caller: Caller<'_, Host>, fd: i32, _flags: i32| -> i32 {
+            errno(caller.data_mut().fd(fd).map(|_| ()))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_filestat_get",
+        |mut caller: Caller<'_, Host>, fd: i32, stat: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let meta = match host.fd(fd)? {
+                    Descriptor::File { file, .. } => file.metadata(),
+                    Descriptor::Dir { host, .. } => std::fs::metadata(host),
+                    _ => {
+                        let mut stat_buf = [0u8; 64];
+                        stat_buf[16] = FILETYPE_CHARACTER_DEVICE;
+                        return write_bytes(mem, stat, &stat_buf);
+                    }
+                };
+                write_bytes(mem, stat, &filestat(&meta.map_err(|e| io_errno(&e))?))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_filestat_set_size",
+        |mut caller: Caller<'_, Host>, fd: i32, size: i64| -> i32 {
+            errno(caller.data_mut().fd(fd).and_then(|descriptor| {
+                let Descriptor::File { file, .. } = descriptor else {
+                    return Err(EBADF);
+                };
+                let size = u64::try_from(size).map_err(|_| EINVAL)?;
+                file.set_len(size).map_err(|e| io_errno(&e))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_prestat_get",
+        |mut caller: Caller<'_, Host>, fd: i32, prestat: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let Descriptor::Dir {
+                    preopen: Some(name),
+                    ..
+                } = host.fd(fd)?
+                else {
+                    return Err(EBADF);
+                };
+                // Tag 0, a directory, then the length of its name
+                let mut buf = [0u8; 8];
+                buf[4..8].copy_from_slice(&(name.len() as u32).to_le_bytes());
+                write_bytes(mem, prestat, &buf)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_prestat_dir_name",
+        |mut caller: Caller<'_, Host>, fd: i32, path: i32, len: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let Descriptor::Dir {
+                    preopen: Some(name),
+                    ..
+                } = host.fd(fd)?
+                else {
+                    return Err(EBADF);
+                };
+                let name = name.as_bytes();
+                let len = (len as u32 as usize).min(name.len());
+                write_bytes(mem, path, &name[..len])
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "fd_readdir",
+        |mut caller: Caller<'_, Host>,
+         fd: i32,
+         buf: i32,
+         len: i32,
+         cookie: i64,
+         used: i32|
+         -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let Descriptor::Dir { host: dir, .. } = host.fd(fd)? else {
+                    return Err(ENOTDIR);
+                };
+                let mut entries: Vec<_> = std::fs::read_dir(dir)
+                    .map_err(|e| io_errno(&e))?
+                    .filter_map(Result::ok)
+                    .collect();
+                entries.sort_by_key(|entry| entry.file_name());
+
+                // Entries from the cookie on, the last one cut off if it
+                // doesn't fit, which tells the module to read on
+                let mut dirents = Vec::new();
+                let skip = usize::try_from(cookie).unwrap_or(usize::MAX);
+                for (i, entry) in entries.iter().enumerate().skip(skip) {
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+                    let filetype = match entry.file_type() {
+                        Ok(kind) if kind.is_dir() => FILETYPE_DIRECTORY,
+                        Ok(kind) if kind.is_symlink() => FILETYPE_SYMBOLIC_LINK,
+                        _ => FILETYPE_REGULAR_FILE,
+                    };
+                    let mut dirent = [0u8; 24];
+                    dirent[0..8].copy_from_slice(&(i as u64 + 1).to_le_bytes());
+                    dirent[16..20].copy_from_slice(&(name.len() as u32).to_le_bytes());
+                    dirent[20] = filetype;
+                    dirents.extend_from_slice(&dirent);
+                    dirents.extend_from_slice(name.as_bytes());
+                    if dirents.len() >= len as u32 as usize {
+                        break;
+                    }
+                }
+                dirents.truncate(len as u32 as usize);
+                write_bytes(mem, buf, &dirents)?;
+                write_u32(mem, used, dirents.len() as u32)
+            }))
+        },
+    )?;
+
+    linker.func_wrap(
+        WASI,
+        "path_open",
+        |mut caller: Caller<'_, Host>,
+         fd: i32,
+         _dirflags: i32,
+         path: i32,
+         path_len: i32,
+         oflags: i32,
+         rights: i64,
+         _inheriting: i64,
+         fdflags: i32,
+         opened: i32|
+         -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let path = string(mem, path, path_len)?.to_string();
+                let full = host.resolve(fd, &path, true)?;
+                let create = oflags & OFLAGS_CREAT != 0;
+
+                let descriptor = if oflags & OFLAGS_DIRECTORY != 0 || (!create && full.is_dir()) {
+                    if !full.is_dir() {
+                        return Err(if full.exists() { ENOTDIR } else { ENOENT });
+                    }
+                    Descriptor::Dir {
+                        host: full,
+                        preopen: None,
+                    }
+                } else {
+                    let append = fdflags & FDFLAGS_APPEND != 0;
+                    let truncate = oflags & OFLAGS_TRUNC != 0;
+                    let write = rights & RIGHTS_FD_WRITE != 0 || append || truncate || create;
+                    let read = rights & RIGHTS_FD_READ != 0 || !write;
+                    let file = OpenOptions::new()
+                        .read(read)
+                        .write(write && !append)
+                        .append(append)
+                        .create(create)
+                        .create_new(create && oflags & OFLAGS_EXCL != 0)
+                        .truncate(truncate)
+                        .open(&full)
+                        .map_err(|e| io_errno(&e))?;
+                    Descriptor::File { file, append }
+                };
+                let new = host.open(descriptor);
+                write_u32(mem, opened, new as u32)
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "path_filestat_get",
+        |mut caller: Caller<'_, Host>,
+         fd: i32,
+         flags: i32,
+         path: i32,
+         len: i32,
+         stat: i32|
+         -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let path = string(mem, path, len)?.to_string();
+                // Flag 1 follows symlinks
+                let follow = flags & 1 != 0;
+                let full = host.resolve(fd, &path, follow)?;
+                let meta = if follow {
+                    std::fs::metadata(&full)
+                } else {
+                    std::fs::symlink_metadata(&full)
+                };
+                write_bytes(mem, stat, &filestat(&meta.map_err(|e| io_errno(&e))?))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "path_create_directory",
+        |mut caller: Caller<'_, Host>, fd: i32, path: i32, len: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let path = string(mem, path, len)?.to_string();
+                let full = host.resolve(fd, &path, false)?;
+                std::fs::create_dir(full).map_err(|e| io_errno(&e))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "path_remove_directory",
+        |mut caller: Caller<'_, Host>, fd: i32, path: i32, len: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let path = string(mem, path, len)?.to_string();
+                let full = host.resolve(fd, &path, false)?;
+                if !full.is_dir() {
+                    return Err(ENOTDIR);
+                }
+                std::fs::remove_dir(full).map_err(|e| io_errno(&e))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "path_unlink_file",
+        |mut caller: Caller<'_, Host>, fd: i32, path: i32, len: i32| -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let path = string(mem, path, len)?.to_string();
+                let full = host.resolve(fd, &path, false)?;
+                if full.is_dir() && !full.is_symlink() {
+                    return Err(EISDIR);
+                }
+                std::fs::remove_file(full).map_err(|e| io_errno(&e))
+            }))
+        },
+    )?;
+    linker.func_wrap(
+        WASI,
+        "path_rename",
+        |mut caller: Caller<'_, Host>,
+         fd: i32,
+         old: i32,
+         old_len: i32,
+         new_fd: i32,
+         new: i32,
+         new_len: i32|
+         -> i32 {
+            errno(memory(&mut caller).and_then(|(mem, host)| {
+                let old = string(mem, old, old_len)?.to_string();
+                let new = string(mem, new, new_len)?.to_string();
+                let from = host.resolve(fd, &old, false)?;
+                let to = host.resolve(new_fd, &new, false)?;
+                std::fs::rename(from, to).map_err(|e| io_errno(&e))
+            }))
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Run a module in the text format, with a workspace at `dir`.
+    fn run_wat(dir: &Path, wat: &str, timeout: Duration) -> SandboxResult<SandboxOutput> {
+        let module = dir.join("module.wasm");
+        std::fs::write(&module, wat::parse_str(wat).unwrap()).unwrap();
+        let root = dir.canonicalize().unwrap();
+        run(
+            Invocation {
+                module,
+                args: vec!["module.wasm".to_string()],
+                env: vec![],
+                preopens: vec![(".".to_string(), root.clone())],
+                root,
+                max_memory: None,
+            },
+            timeout,
+        )
+    }
+
+    /// A module opening the file named at offset 100 with `oflags` and
+    /// `rights`, writing `hello` to it or to stdout if that fails, and
+    /// exiting with the errno of the open.
+    fn open_module(path: &str, oflags: i32, rights: i64) -> String {
+        format!(
+            r#"(module
+                (import "wasi_snapshot_preview1" "path_open"
+                    (func $open (param i32 i32 i32 i32 i32 i64 i64 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $write (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "proc_exit" (func $exit (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 8) "hello")
+                (data (i32.const 16) "\08\00\00\00\05\00\00\00")
+                (data (i32.const 100) "{path}")
+                (func (export "_start")
+                    (local $errno i32)
+                    (local.set $errno
+                        (call $open (i32.const 3) (i32.const 0) (i32.const 100) (i32.const {len})
+                            (i32.const {oflags}) (i64.const {rights}) (i64.const 0) (i32.const 0)
+                            (i32.const 32)))
+                    (if (i32.eqz (local.get $errno))
+                        (then (drop (call $write (i32.load (i32.const 32)) (i32.const 16)
+                            (i32.const 1) (i32.const 40))))
+                        (else (drop (call $write (i32.const 1) (i32.const 16) (i32.const 1)
+                            (i32.const 40)))))
+                    (call $exit (local.get $errno))))"#,
+            len = path.len(),
+        )
+    }
+
+    #[test]
+    fn test_run_writes_files_in_the_workspace() {
+        let dir = TempDir::new().unwrap();
+        let wat = open_module("out.txt", OFLAGS_CREAT | OFLAGS_TRUNC, RIGHTS_FD_WRITE);
+        let output = run_wat(dir.path(), &wat, Duration::from_secs(10)).unwrap();
+        assert_eq!(output.exit_code, 0);
+        assert_eq!(std::fs::read(dir.path().join("out.txt")).unwrap(), b"hello");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_refuses_paths_outside_the_workspace() {
+        let dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+
+        for path in ["../escape.txt", "/etc/passwd", "link/escape.txt"] {
+            let wat = open_module(path, OFLAGS_CREAT, RIGHTS_FD_WRITE);
+            let output = run_wat(dir.path(), &wat, Duration::from_secs(10)).unwrap();
+            assert_eq!(output.exit_code, ENOTCAPABLE, "{path}");
+            assert_eq!(output.stdout, "hello");
+        }
+        assert!(!outside.path().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn test_run_reports_missing_files() {
+        let dir = TempDir::new().unwrap();
+        let wat = open_module("missing.txt", 0, RIGHTS_FD_READ);
+        let output = run_wat(dir.path(), &wat, Duration::from_secs(10)).unwrap();
+        assert_eq!(output.exit_code, ENOENT);
+        assert!(!output.success);
+    }
+
+    #[test]
+    fn test_run_stops_at_timeout() {
+        let dir = TempDir::new().unwrap();
+        let wat = r#"(module
+            (memory (export "memory") 1)
+            (func (export "_start") (loop (br 0))))"#;
+        let result = run_wat(dir.path(), wat, Duration::from_millis(100));
+        assert!(matches!(result, Err(SandboxError::Timeout(_))));
+    }
+
+    #[test]
+    fn test_run_reports_traps() {
+        let dir = TempDir::new().unwrap();
+        let wat = r#"(module
+            (import "wasi_snapshot_preview1" "sock_accept"
+                (func $accept (param i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "_start")
+                (if (i32.ne (call $accept (i32.const 3) (i32.const 0) (i32.const 0))
+                        (i32.const 52))
+                    (then unreachable))
+                unreachable))"#;
+        let output = run_wat(dir.path(), wat, Duration::from_secs(10)).unwrap();
+        assert_eq!(output.exit_code, TRAP_EXIT_CODE);
+        assert!(output.stderr.contains("unreachable"), "{}", output.stderr);
+    }
+}
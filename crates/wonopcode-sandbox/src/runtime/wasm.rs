@@ -0,0 +1,606 @@
+//! WebAssembly runtime using wasmtime.
+//!
+//! This runtime runs WASI-compiled modules with an embedded wasmtime instead
+//! of a container. A module sees only the project directory, at the
+//! workspace path, and has no network access. There is no shell: a command
+//! runs one module, either a `.wasm` file or a module from the project's
+//! `.wonopcode/wasm/` directory named like the command.
+
+use super::wasi::{self, Invocation};
+use crate::{
+    error::{SandboxError, SandboxResult},
+    path::PathMapper,
+    SandboxCapabilities, SandboxConfig, SandboxDirEntry, SandboxInfo, SandboxMetadata,
+    SandboxOutput, SandboxRuntime, SandboxRuntimeType, SandboxStatus,
+};
+use async_trait::async_trait;
+use std::{
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// Directory of the project with modules that can be run by name.
+pub const WASM_TOOLS_DIR: &str = ".wonopcode/wasm";
+
+/// WebAssembly runtime that runs WASI modules with wasmtime.
+pub struct WasmRuntime {
+    /// Unique identifier
+    id: String,
+    /// Sandbox configuration
+    config: SandboxConfig,
+    /// Path mapper
+    path_mapper: PathMapper,
+    /// Current status
+    status: RwLock<SandboxStatus>,
+}
+
+impl WasmRuntime {
+    /// Create a new WebAssembly runtime.
+    pub async fn new(config: SandboxConfig, path_mapper: PathMapper) -> SandboxResult<Self> {
+        info!(project = %path_mapper.host_root().display(), "Wasm runtime created");
+        Ok(Self::with_mapper(config, path_mapper))
+    }
+
+    fn with_mapper(config: SandboxConfig, path_mapper: PathMapper) -> Self {
+        let id = format!("wasm-{}", &uuid::Uuid::new_v4().to_string()[..8]);
+        Self {
+            id,
+            config,
+            path_mapper,
+            status: RwLock::new(SandboxStatus::Stopped),
+        }
+    }
+
+    /// The canonical host path of the workspace.
+    fn host_root(&self) -> SandboxResult<PathBuf> {
+        self.path_mapper
+            .host_root()
+            .canonicalize()
+            .map_err(|_| SandboxError::FileNotFound(self.path_mapper.host_root().to_path_buf()))
+    }
+
+    /// Map a sandbox path to the host, refusing paths outside the workspace.
+    ///
+    /// Files are accessed on the host directly, so `..` or a symlink could
+    /// leave the workspace, unlike in a container.
+    fn host_path(&self, path: &Path) -> SandboxResult<PathBuf> {
+        let outside = || SandboxError::PathOutsideWorkspace {
+            path: path.to_path_buf(),
+        };
+        let escapes = path
+            .components()
+            .any(|component| matches!(component, Component::ParentDir));
+        let host = match self.path_mapper.to_host(path) {
+            Some(host) if !escapes => host,
+            _ => return Err(outside()),
+        };
+        confine(&self.host_root()?, &host).ok_or_else(outside)
+    }
+
+    /// The host path of the module a command runs.
+    fn resolve_module(&self, program: &str, host_workdir: &Path) -> SandboxResult<PathBuf> {
+        if program.ends_with(".wasm") {
+            let path = Path::new(program);
+            let host = if path.is_absolute() {
+                self.host_path(path)?
+            } else {
+                if path.components().any(|c| matches!(c, Component::ParentDir)) {
+                    return Err(SandboxError::PathOutsideWorkspace {
+                        path: path.to_path_buf(),
+                    });
+                }
+                confine(&self.host_root()?, &host_workdir.join(path)).ok_or_else(|| {
+                    SandboxError::PathOutsideWorkspace {
+                        path: path.to_path_buf(),
+                    }
+                })?
+            };
+            if !host.is_file() {
+                return Err(SandboxError::FileNotFound(path.to_path_buf()));
+            }
+            return Ok(host);
+        }
+
+        let tool = self
+            .path_mapper
+            .host_root()
+            .join(WASM_TOOLS_DIR)
+            .join(format!("{program}.wasm"));
+        let tool = confine(&self.host_root()?, &tool);
+        if let Some(tool) = tool.filter(|tool| tool.is_file()) {
+            Ok(tool)
+        } else {
+            Err(SandboxError::ExecFailed(format!(
+                "`{program}` is not a WebAssembly module; the wasm sandbox runs .wasm files \
+                 and the modules in {WASM_TOOLS_DIR}/"
+            )))
+        }
+    }
+
+    /// The invocation running `module` with `args`, the program name first.
+    fn invocation(
+        &self,
+        module: PathBuf,
+        args: &[String],
+        host_workdir: &Path,
+    ) -> SandboxResult<Invocation> {
+        let root = self.host_root()?;
+        Ok(Invocation {
+            module,
+            args: args.to_vec(),
+            env: vec![("NO_COLOR".to_string(), "1".to_string())],
+            preopens: vec![
+                (
+                    self.path_mapper.sandbox_root().display().to_string(),
+                    root.clone(),
+                ),
+                // Relative paths resolve against the working directory
+                (".".to_string(), host_workdir.to_path_buf()),
+            ],
+            root,
+            max_memory: self.config.resources.memory_bytes(),
+        })
+    }
+}
+
+#[async_trait]
+impl SandboxRuntime for WasmRuntime {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn runtime_type(&self) -> SandboxRuntimeType {
+        SandboxRuntimeType::Wasm
+    }
+
+    async fn status(&self) -> SandboxStatus {
+        *self.status.read().await
+    }
+
+    async fn info(&self) -> SandboxInfo {
+        SandboxInfo {
+            id: self.id.clone(),
+            runtime_type: SandboxRuntimeType::Wasm,
+            status: self.status().await,
+            image: "wasmtime".to_string(),
+            host_root: self.path_mapper.host_root().to_path_buf(),
+            workspace_path: self.path_mapper.sandbox_root().to_path_buf(),
+        }
+    }
+
+    async fn start(&self) -> SandboxResult<()> {
+        // Modules run in a fresh instance per command, so there's nothing to
+        // boot
+        *self.status.write().await = SandboxStatus::Running;
+        Ok(())
+    }
+
+    async fn stop(&self) -> SandboxResult<()> {
+        *self.status.write().await = SandboxStatus::Stopped;
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        command: &str,
+        workdir: &Path,
+        timeout: Duration,
+        capabilities: &SandboxCapabilities,
+    ) -> SandboxResult<SandboxOutput> {
+        if !self.is_ready().await {
+            return Err(SandboxError::NotRunning);
+        }
+        if capabilities.read_only_workspace {
+            return Err(SandboxError::OperationNotSupported(
+                "read-only workspace".to_string(),
+            ));
+        }
+
+        let words = split_command(command).map_err(SandboxError::ExecFailed)?;
+        let Some(program) = words.first() else {
+            return Err(SandboxError::ExecFailed("empty command".to_string()));
+        };
+        let host_workdir = self.host_path(workdir)?;
+        let module = self.resolve_module(program, &host_workdir)?;
+
+        debug!(
+            command = %command,
+            module = %module.display(),
+            workdir = %workdir.display(),
+            "Executing module"
+        );
+
+        let invocation = self.invocation(module, &words, &host_workdir)?;
+        tokio::task::spawn_blocking(move || wasi::run(invocation, timeout))
+            .await
+            .map_err(|e| SandboxError::ExecFailed(e.to_string()))?
+    }
+
+    async fn read_file(&self, path: &Path) -> SandboxResult<Vec<u8>> {
+        let host = self.host_path(path)?;
+        tokio::fs::read(&host)
+            .await
+            .map_err(|e| SandboxError::read_failed(path, e.to_string()))
+    }
+
+    async fn write_file(&self, path: &Path, content: &[u8]) -> SandboxResult<()> {
+        let host = self.host_path(path)?;
+        if let Some(parent) = host.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| SandboxError::write_failed(path, e.to_string()))?;
+        }
+
+        tokio::fs::write(&host, content)
+            .await
+            .map_err(|e| SandboxError::write_failed(path, e.to_string()))
+    }
+
+    async fn path_exists(&self, path: &Path) -> SandboxResult<bool> {
+        let host = self.host_path(path)?;
+        Ok(tokio::fs::try_exists(&host).await.unwrap_or(false))
+    }
+
+    async fn metadata(&self, path: &Path) -> SandboxResult<SandboxMetadata> {
+        let host = self.host_path(path)?;
+        let meta = tokio::fs::metadata(&host)
+            .await
+            .map_err(|_| SandboxError::FileNotFound(path.to_path_buf()))?;
+
+        Ok(SandboxMetadata {
+            size: meta.len(),
+            is_dir: meta.is_dir(),
+            is_file: meta.is_file(),
+            is_symlink: meta.is_symlink(),
+            #[cfg(unix)]
+            mode: {
+                use std::os::unix::fs::PermissionsExt;
+                Some(meta.permissions().mode())
+            },
+            #[cfg(not(unix))]
+            mode: None,
+        })
+    }
+
+    async fn read_dir(&self, path: &Path) -> SandboxResult<Vec<SandboxDirEntry>> {
+        let host = self.host_path(path)?;
+        let mut entries = Vec::new();
+        let mut dir = tokio::fs::read_dir(&host)
+            .await
+            .map_err(|e| SandboxError::read_failed(path, e.to_string()))?;
+
+        while let Some(entry) = dir.next_entry().await.transpose() {
+            let entry = entry.map_err(|e| SandboxError::read_failed(path, e.to_string()))?;
+            let file_type = entry.file_type().await.ok();
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            entries.push(SandboxDirEntry {
+                path: path.join(&name),
+                name,
+                is_dir: file_type.map(|t| t.is_dir()).unwrap_or(false),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    async fn create_dir_all(&self, path: &Path) -> SandboxResult<()> {
+        let host = self.host_path(path)?;
+        tokio::fs::create_dir_all(&host)
+            .await
+            .map_err(|e| SandboxError::write_failed(path, e.to_string()))
+    }
+
+    async fn remove_file(&self, path: &Path) -> SandboxResult<()> {
+        let host = self.host_path(path)?;
+        tokio::fs::remove_file(&host)
+            .await
+            .map_err(|e| SandboxError::write_failed(path, e.to_string()))
+    }
+
+    async fn remove_dir(&self, path: &Path, recursive: bool) -> SandboxResult<()> {
+        let host = self.host_path(path)?;
+        let result = if recursive {
+            tokio::fs::remove_dir_all(&host).await
+        } else {
+            tokio::fs::remove_dir(&host).await
+        };
+        result.map_err(|e| SandboxError::write_failed(path, e.to_string()))
+    }
+
+    fn path_mapper(&self) -> &PathMapper {
+        &self.path_mapper
+    }
+}
+
+/// Resolve the symlinks of `path`, as far as it exists, returning it if it
+/// stays in `root`, a canonical path.
+///
+/// A symlink at the end that points nowhere is refused, as following it
+/// could create a file anywhere.
+pub(super) fn confine(root: &Path, path: &Path) -> Option<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    let mut real = loop {
+        match existing.canonicalize() {
+            Ok(real) => break real,
+            Err(_) if existing.symlink_metadata().is_ok() => return None,
+            Err(_) => {
+                missing.push(existing.file_name()?);
+                existing = existing.parent()?;
+            }
+        }
+    };
+    real.extend(missing.into_iter().rev());
+    real.starts_with(root).then_some(real)
+}
+
+/// Split a command into words, honoring quotes and backslash escapes.
+///
+/// Fails on shell syntax such as pipes and redirections, as there is no
+/// shell to run it.
+fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                    in_word = true;
+                }
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, '|' | '&' | ';' | '<' | '>' | '`' | '$' | '(' | ')') => {
+                return Err(format!(
+                    "`{c}` needs a shell, which the wasm sandbox doesn't have; run one module \
+                     per command"
+                ));
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        return Err("unterminated quote".to_string());
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn runtime(root: &Path) -> WasmRuntime {
+        WasmRuntime::with_mapper(
+            SandboxConfig::default(),
+            PathMapper::new(root.to_path_buf(), PathBuf::from("/workspace")),
+        )
+    }
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            split_command(r#"fmt.wasm --check "src/my file.rs" 'a b' c\ d"#).unwrap(),
+            vec!["fmt.wasm", "--check", "src/my file.rs", "a b", "c d"]
+        );
+        assert_eq!(split_command("  ").unwrap(), Vec::<String>::new());
+        assert_eq!(split_command(r#""""#).unwrap(), vec![""]);
+        assert!(split_command("lint.wasm | head").is_err());
+        assert!(split_command("lint.wasm > out.txt").is_err());
+        assert!(split_command("lint.wasm '|'").is_ok());
+        assert!(split_command("lint.wasm 'oops").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_module() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let runtime = runtime(dir.path());
+        std::fs::create_dir_all(root.join(WASM_TOOLS_DIR)).unwrap();
+        std::fs::write(root.join(WASM_TOOLS_DIR).join("lint.wasm"), b"").unwrap();
+        std::fs::write(root.join("app.wasm"), b"").unwrap();
+
+        assert_eq!(
+            runtime.resolve_module("lint", &root).unwrap(),
+            root.join(".wonopcode/wasm/lint.wasm")
+        );
+        assert_eq!(
+            runtime.resolve_module("app.wasm", &root).unwrap(),
+            root.join("app.wasm")
+        );
+        assert_eq!(
+            runtime
+                .resolve_module("/workspace/app.wasm", &root)
+                .unwrap(),
+            root.join("app.wasm")
+        );
+        assert!(runtime.resolve_module("cargo", &root).is_err());
+        assert!(runtime.resolve_module("/etc/app.wasm", &root).is_err());
+        assert!(runtime.resolve_module("../app.wasm", &root).is_err());
+    }
+
+    #[test]
+    fn test_invocation() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let runtime = runtime(dir.path());
+        let module = root.join("lint.wasm");
+        let args = vec!["lint.wasm".to_string(), "--fix".to_string()];
+
+        let invocation = runtime
+            .invocation(module.clone(), &args, &root.join("src"))
+            .unwrap();
+        assert_eq!(invocation.module, module);
+        assert_eq!(invocation.args, args);
+        assert_eq!(
+            invocation.preopens,
+            vec![
+                ("/workspace".to_string(), root.clone()),
+                (".".to_string(), root.join("src")),
+            ]
+        );
+        assert_eq!(invocation.root, root);
+        assert_eq!(invocation.max_memory, Some(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_confine() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+
+        assert_eq!(
+            confine(&root, &root.join("src/new/file.rs")),
+            Some(root.join("src/new/file.rs"))
+        );
+        assert_eq!(confine(&root, &root.join("src/../..")), None);
+
+        #[cfg(unix)]
+        {
+            let outside = TempDir::new().unwrap();
+            std::os::unix::fs::symlink(outside.path(), root.join("out")).unwrap();
+            std::os::unix::fs::symlink(root.join("gone"), root.join("dangling")).unwrap();
+            std::os::unix::fs::symlink(root.join("src"), root.join("inside")).unwrap();
+            assert_eq!(confine(&root, &root.join("out/file.rs")), None);
+            assert_eq!(confine(&root, &root.join("dangling")), None);
+            assert_eq!(
+                confine(&root, &root.join("inside/file.rs")),
+                Some(root.join("src/file.rs"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_operations_stay_in_the_workspace() {
+        let dir = TempDir::new().unwrap();
+        let runtime = runtime(dir.path());
+
+        runtime
+            .write_file(Path::new("/workspace/src/a.txt"), b"hello")
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(dir.path().join("src/a.txt")).unwrap(),
+            b"hello"
+        );
+        assert_eq!(
+            runtime
+                .read_file(Path::new("/workspace/src/a.txt"))
+                .await
+                .unwrap(),
+            b"hello"
+        );
+        let entries = runtime.read_dir(Path::new("/workspace/src")).await.unwrap();
+        assert_eq!(entries[0].path, Path::new("/workspace/src/a.txt"));
+
+        assert!(matches!(
+            runtime.read_file(Path::new("/etc/passwd")).await,
+            Err(SandboxError::PathOutsideWorkspace { .. })
+        ));
+        assert!(matches!(
+            runtime
+                .read_file(Path::new("/workspace/../etc/passwd"))
+                .await,
+            Err(SandboxError::PathOutsideWorkspace { .. })
+        ));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc", dir.path().join("etc")).unwrap();
+            assert!(matches!(
+                runtime.read_file(Path::new("/workspace/etc/passwd")).await,
+                Err(SandboxError::PathOutsideWorkspace { .. })
+            ));
+            assert!(matches!(
+                runtime
+                    .write_file(Path::new("/workspace/etc/evil"), b"")
+                    .await,
+                Err(SandboxError::PathOutsideWorkspace { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_runs_module() {
+        let dir = TempDir::new().unwrap();
+        let runtime = runtime(dir.path());
+        let wat = r#"(module
+            (import "wasi_snapshot_preview1" "fd_write"
+                (func $write (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 8) "hi\n")
+            (data (i32.const 16) "\08\00\00\00\03\00\00\00")
+            (func (export "_start")
+                (drop (call $write (i32.const 1) (i32.const 16) (i32.const 1) (i32.const 32)))))"#;
+        std::fs::write(dir.path().join("hi.wasm"), wat::parse_str(wat).unwrap()).unwrap();
+
+        runtime.start().await.unwrap();
+        let output = runtime
+            .execute(
+                "hi.wasm",
+                Path::new("/workspace"),
+                Duration::from_secs(10),
+                &SandboxCapabilities::default(),
+            )
+            .await
+            .unwrap();
+        assert!(output.success);
+        assert_eq!(output.stdout, "hi\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_needs_start() {
+        let dir = TempDir::new().unwrap();
+        let runtime = runtime(dir.path());
+        let result = runtime
+            .execute(
+                "lint",
+                Path::new("/workspace"),
+                Duration::from_secs(1),
+                &SandboxCapabilities::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(SandboxError::NotRunning)));
+
+        runtime.start().await.unwrap();
+        let result = runtime
+            .execute(
+                "cargo test",
+                Path::new("/workspace"),
+                Duration::from_secs(1),
+                &SandboxCapabilities::default(),
+            )
+            .await;
+        assert!(matches!(result, Err(SandboxError::ExecFailed(_))));
+    }
+}
@@ -0,0 +1,439 @@
+//! Project files for remote clients.
+//!
+//! [`ProjectFiles`] lists, reads and diffs the files under a project root for
+//! the `/files/*` endpoints. Every path is resolved against the root and
+//! refused if it leads outside of it, through `..` or a symlink. Hidden files
+//! and those ignored by git can't be listed, read, diffed or written.
+
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+use git2::Repository;
+use wonopcode_protocol::{FileContent, FileDiff, FileEntry, FileListing, MAX_FILE_READ_BYTES};
+
+/// File access errors.
+#[derive(Debug, thiserror::Error)]
+pub enum FileError {
+    #[error("Path '{0}' is outside the project")]
+    OutsideProject(String),
+
+    #[error("'{0}' does not exist")]
+    NotFound(String),
+
+    #[error("'{0}' is not a directory")]
+    NotADirectory(String),
+
+    #[error("'{0}' is not a file")]
+    NotAFile(String),
+
+    #[error("'{path}' is too large to read ({size} bytes, the limit is {limit})")]
+    TooLarge { path: String, size: u64, limit: u64 },
+
+    #[error("'{0}' is not a text file")]
+    Binary(String),
+
+    #[error("'{0}' is hidden or ignored by git")]
+    Unlisted(String),
+
+    #[error("Git error: {0}")]
+    Git(#[from] git2::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// The files of a project.
+pub struct ProjectFiles {
+    root: PathBuf,
+}
+
+impl ProjectFiles {
+    /// Access the files under `root`.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, FileError> {
+        let root = root.as_ref();
+        let root = root
+            .canonicalize()
+            .map_err(|_| FileError::NotFound(root.display().to_string()))?;
+        Ok(Self { root })
+    }
+
+    /// Resolve a path relative to the root, following symlinks, and check
+    /// that it stays in the project. The path must exist.
+    fn resolve(&self, path: &str) -> Result<PathBuf, FileError> {
+        let relative = Path::new(path);
+        if relative.is_absolute()
+            || relative
+                .components()
+                .any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)))
+        {
+            return Err(FileError::OutsideProject(path.to_string()));
+        }
+        let resolved = self
+            .root
+            .join(relative)
+            .canonicalize()
+            .map_err(|_| FileError::NotFound(path.to_string()))?;
+        if !resolved.starts_with(&self.root) {
+            return Err(FileError::OutsideProject(path.to_string()));
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve a path like [`Self::resolve`], treating hidden and ignored
+    /// paths as missing.
+    fn resolve_listed(&self, path: &str) -> Result<PathBuf, FileError> {
+        let resolved = self.resolve(path)?;
+        if !self.is_listed(&resolved) {
+            return Err(FileError::NotFound(path.to_string()));
+        }
+        Ok(resolved)
+    }
+
+    /// Whether [`Self::list`] shows a resolved path: neither it nor a
+    /// directory it is in is hidden or ignored by git.
+    fn is_listed(&self, resolved: &Path) -> bool {
+        let target = resolved.to_path_buf();
+        walk(&self.root)
+            .filter_entry(move |e| target.starts_with(e.path()))
+            .build()
+            .filter_map(|e| e.ok())
+            .any(|e| e.path() == resolved)
+    }
+
+    /// Path of a resolved file relative to the root, with `/` separators.
+    fn relative(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// List a directory, directories first, skipping hidden files and those
+    /// ignored by git, as the TUI's file browser does.
+    pub fn list(&self, dir: &str) -> Result<FileListing, FileError> {
+        let resolved = self.resolve_listed(dir)?;
+        if !resolved.is_dir() {
+            return Err(FileError::NotADirectory(dir.to_string()));
+        }
+        let walker = walk(&resolved).max_depth(Some(1)).build();
+
+        let mut entries: Vec<FileEntry> = walker
+            .filter_map(|e| e.ok())
+            .filter(|e| e.depth() == 1)
+            .map(|e| {
+                let is_dir = e.file_type().is_some_and(|t| t.is_dir());
+                FileEntry {
+                    name: e.file_name().to_string_lossy().to_string(),
+                    path: self.relative(e.path()),
+                    is_dir,
+                    size: if is_dir {
+                        None
+                    } else {
+                        e.metadata().ok().map(|m| m.len())
+                    },
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        Ok(FileListing {
+            path: self.relative(&resolved),
+            entries,
+        })
+    }
+
+    /// Read a text file of up to [`MAX_FILE_READ_BYTES`].
+    pub fn read(&self, path: &str) -> Result<FileContent, FileError> {
+        let resolved = self.resolve_listed(path)?;
+        let meta = std::fs::metadata(&resolved)?;
+        if !meta.is_file() {
+            return Err(FileError::NotAFile(path.to_string()));
+        }
+        if meta.len() > MAX_FILE_READ_BYTES {
+            return Err(FileError::TooLarge {
+                path: path.to_string(),
+                size: meta.len(),
+                limit: MAX_FILE_READ_BYTES,
+            });
+        }
+        let content = String::from_utf8(std::fs::read(&resolved)?)
+            .map_err(|_| FileError::Binary(path.to_string()))?;
+        Ok(FileContent {
+            path: self.relative(&resolved),
+            content,
+        })
+    }
+
+    /// Diff a file against its last committed version, counting it as new
+    /// if it was never committed.
+    pub fn diff(&self, path: &str) -> Result<FileDiff, FileError> {
+        let resolved = self.resolve_listed(path)?;
+        if !resolved.is_file() {
+            return Err(FileError::NotAFile(path.to_string()));
+        }
+        let repo = Repository::discover(&resolved)?;
+        let workdir = repo
+            .workdir()
+            .and_then(|dir| dir.canonicalize().ok())
+            .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+        let in_repo = resolved
+            .strip_prefix(&workdir)
+            .map_err(|_| FileError::OutsideProject(path.to_string()))?;
+
+        let mut opts = git2::DiffOptions::new();
+        opts.pathspec(in_repo)
+            .disable_pathspec_match(true)
+            .include_untracked(true)
+            .show_untracked_content(true);
+        let head = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None, // Empty repository
+        };
+        let diff = repo.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut opts))?;
+
+        let mut patch = String::new();
+        diff.print(git2::DiffFormat::Patch, |_, _, line| {
+            if let origin @ ('+' | '-' | ' ') = line.origin() {
+                patch.push(origin);
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+        Ok(FileDiff {
+            path: self.relative(&resolved),
+            diff: patch,
+        })
+    }
+
+    /// Write a file, creating it if needed. Its directory must exist, and
+    /// neither may be hidden or ignored by git.
+    pub fn write(&self, path: &str, content: &str) -> Result<(), FileError> {
+        let relative = Path::new(path);
+        let (Some(parent), Some(name)) = (relative.parent(), relative.file_name()) else {
+            return Err(FileError::NotAFile(path.to_string()));
+        };
+        if name.to_string_lossy().starts_with('.') {
+            return Err(FileError::Unlisted(path.to_string()));
+        }
+        let dir = self.resolve(&parent.to_string_lossy())?;
+        if !dir.is_dir() {
+            return Err(FileError::NotADirectory(parent.display().to_string()));
+        }
+        if !self.is_listed(&dir) {
+            return Err(FileError::Unlisted(path.to_string()));
+        }
+        let target = dir.join(name);
+        // An existing file may be a symlink out of the project, even a
+        // dangling one
+        if target.symlink_metadata().is_ok() {
+            let resolved = self.resolve(path)?;
+            if !resolved.is_file() {
+                return Err(FileError::NotAFile(path.to_string()));
+            }
+            if !self.is_listed(&resolved) {
+                return Err(FileError::Unlisted(path.to_string()));
+            }
+        } else {
+            // Whether a new file is ignored can only be told once it exists
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&target)?;
+            if !self.is_listed(&target) {
+                std::fs::remove_file(&target)?;
+                return Err(FileError::Unlisted(path.to_string()));
+            }
+        }
+        std::fs::write(target, content)?;
+        Ok(())
+    }
+}
+
+/// A walk of `dir` that skips hidden files and those ignored by git.
+fn walk(dir: &Path) -> ignore::WalkBuilder {
+    let mut builder = ignore::WalkBuilder::new(dir);
+    builder
+        .hidden(true)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true);
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn setup() -> (tempfile::TempDir, ProjectFiles) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "fn main() {}\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Readme\n").unwrap();
+        fs::write(dir.path().join("debug.log"), "").unwrap();
+        fs::write(dir.path().join(".ignore"), "*.log\n").unwrap();
+        let files = ProjectFiles::new(dir.path()).unwrap();
+        (dir, files)
+    }
+
+    #[test]
+    fn test_list_skips_hidden_and_ignored_files() {
+        let (_dir, files) = setup();
+        let listing = files.list("").unwrap();
+        assert_eq!(listing.path, "");
+        let names: Vec<_> = listing.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["src", "README.md"]);
+        assert_eq!(listing.entries[1].size, Some(9));
+
+        let listing = files.list("src").unwrap();
+        assert_eq!(listing.entries[0].path, "src/lib.rs");
+        assert!(matches!(
+            files.list("README.md"),
+            Err(FileError::NotADirectory(_))
+        ));
+    }
+
+    #[test]
+    fn test_hidden_and_ignored_files_are_not_read() {
+        let (dir, files) = setup();
+        fs::write(dir.path().join(".env"), "TOKEN=secret\n").unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join(".git/config"), "[core]\n").unwrap();
+        fs::create_dir_all(dir.path().join("logs")).unwrap();
+        fs::write(dir.path().join(".ignore"), "*.log\nlogs/\n").unwrap();
+        fs::write(dir.path().join("logs/today.txt"), "log\n").unwrap();
+
+        for path in [".env", ".git/config", "debug.log", "logs/today.txt"] {
+            assert!(
+                matches!(files.read(path), Err(FileError::NotFound(_))),
+                "{path} was read"
+            );
+        }
+        assert!(matches!(files.diff(".env"), Err(FileError::NotFound(_))));
+        assert!(matches!(files.list(".git"), Err(FileError::NotFound(_))));
+        assert!(files.read("README.md").is_ok());
+    }
+
+    #[test]
+    fn test_paths_outside_the_project_are_refused() {
+        let (dir, files) = setup();
+        let outside = tempfile::tempdir().unwrap();
+        fs::write(outside.path().join("secret"), "secret").unwrap();
+
+        assert!(matches!(
+            files.read("../secret"),
+            Err(FileError::OutsideProject(_))
+        ));
+        assert!(matches!(
+            files.read("src/../../secret"),
+            Err(FileError::OutsideProject(_))
+        ));
+        let absolute = outside.path().join("secret");
+        assert!(matches!(
+            files.read(&absolute.to_string_lossy()),
+            Err(FileError::OutsideProject(_))
+        ));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+            assert!(matches!(
+                files.read("link/secret"),
+                Err(FileError::OutsideProject(_))
+            ));
+            assert!(matches!(
+                files.write("link/secret", "changed"),
+                Err(FileError::OutsideProject(_))
+            ));
+            assert_eq!(
+                fs::read_to_string(outside.path().join("secret")).unwrap(),
+                "secret"
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_refuses_large_and_binary_files() {
+        let (dir, files) = setup();
+        assert_eq!(files.read("src/lib.rs").unwrap().content, "fn main() {}\n");
+        assert!(matches!(files.read("src"), Err(FileError::NotAFile(_))));
+        assert!(matches!(files.read("missing"), Err(FileError::NotFound(_))));
+
+        fs::write(dir.path().join("image.png"), [0x89, 0x50, 0xff, 0xfe]).unwrap();
+        assert!(matches!(files.read("image.png"), Err(FileError::Binary(_))));
+        let large = vec![b'a'; MAX_FILE_READ_BYTES as usize + 1];
+        fs::write(dir.path().join("large.txt"), large).unwrap();
+        assert!(matches!(
+            files.read("large.txt"),
+            Err(FileError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_diff_against_the_last_commit() {
+        let (dir, files) = setup();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/lib.rs")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial", &tree, &[])
+            .unwrap();
+
+        assert_eq!(files.diff("src/lib.rs").unwrap().diff, "");
+        fs::write(dir.path().join("src/lib.rs"), "fn main() { run() }\n").unwrap();
+        let diff = files.diff("src/lib.rs").unwrap().diff;
+        assert!(diff.contains("-fn main() {}\n"));
+        assert!(diff.contains("+fn main() { run() }\n"));
+
+        // Never committed
+        let diff = files.diff("README.md").unwrap().diff;
+        assert!(diff.contains("+# Readme\n"));
+    }
+
+    #[test]
+    fn test_write_creates_files_in_existing_directories() {
+        let (dir, files) = setup();
+        files.write("src/new.rs", "// new\n").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("src/new.rs")).unwrap(),
+            "// new\n"
+        );
+        files.write("README.md", "changed").unwrap();
+        assert_eq!(files.read("README.md").unwrap().content, "changed");
+        assert!(matches!(
+            files.write("missing/new.rs", ""),
+            Err(FileError::NotFound(_))
+        ));
+        assert!(matches!(
+            files.write("../new.rs", ""),
+            Err(FileError::OutsideProject(_))
+        ));
+    }
+
+    #[test]
+    fn test_write_refuses_hidden_and_ignored_files() {
+        let (dir, files) = setup();
+        fs::create_dir_all(dir.path().join(".git/hooks")).unwrap();
+        for path in [".git/hooks/pre-commit", ".env", "src/.env", "new.log"] {
+            assert!(
+                matches!(files.write(path, "boom"), Err(FileError::Unlisted(_))),
+                "{path}"
+            );
+            assert!(!dir.path().join(path).exists(), "{path}");
+        }
+        assert!(matches!(
+            files.write("debug.log", "boom"),
+            Err(FileError::Unlisted(_))
+        ));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("debug.log")).unwrap(),
+            ""
+        );
+    }
+}
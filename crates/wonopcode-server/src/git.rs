@@ -6,9 +6,10 @@ use chrono::{DateTime, Utc};
 use git2::{Repository, StatusOptions};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use utoipa::ToSchema;
 
 /// Git file status.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct GitFileStatus {
     /// Relative path to file.
     pub path: String,
@@ -19,7 +20,7 @@ pub struct GitFileStatus {
 }
 
 /// Git file state.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum GitFileState {
     Modified,
@@ -44,7 +45,7 @@ impl std::fmt::Display for GitFileState {
 }
 
 /// Git repository status.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct GitStatus {
     /// Current branch name.
     pub branch: String,
@@ -59,7 +60,7 @@ pub struct GitStatus {
 }
 
 /// A commit in history.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct GitCommitInfo {
     /// Commit hash (short).
     pub id: String,
@@ -342,6 +343,114 @@ impl GitOperations {
         Ok(())
     }
 
+    /// Get the changes as a unified diff.
+    ///
+    /// With `staged`, these are the changes in the index, otherwise the
+    /// changes in the working directory that are not staged yet. A path
+    /// limits the diff to one file.
+    pub fn diff(&self, path: Option<&str>, staged: bool) -> Result<String, GitError> {
+        let repo = self.open_repo()?;
+        let diff = self.diff_for(&repo, path, staged, false)?;
+
+        let mut patch = String::new();
+        diff.print(git2::DiffFormat::Patch, |_, _, line| {
+            if let origin @ ('+' | '-' | ' ') = line.origin() {
+                patch.push(origin);
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+        Ok(patch)
+    }
+
+    /// Stage one hunk of a file's unstaged changes.
+    ///
+    /// Hunks are counted from 0, in the order [`Self::diff`] lists them.
+    pub fn stage_hunk(&self, path: &str, hunk: usize) -> Result<(), GitError> {
+        let repo = self.open_repo()?;
+        let diff = self.diff_for(&repo, Some(path), false, false)?;
+        // A new file is a single hunk, and patches only apply to files the
+        // index knows
+        if repo.index()?.get_path(Path::new(path), 0).is_none() {
+            Self::hunk_count(&diff, hunk)?;
+            return self.stage(&[path.to_string()]);
+        }
+        Self::apply_hunk(&repo, &diff, hunk)
+    }
+
+    /// Unstage one hunk of a file's staged changes.
+    ///
+    /// Hunks are counted from 0, in the order [`Self::diff`] lists them.
+    pub fn unstage_hunk(&self, path: &str, hunk: usize) -> Result<(), GitError> {
+        let repo = self.open_repo()?;
+        // The staged changes reversed take the hunk back out of the index
+        let diff = self.diff_for(&repo, Some(path), true, true)?;
+        Self::apply_hunk(&repo, &diff, hunk)
+    }
+
+    /// Diff the index against HEAD (`staged`) or the working directory
+    /// against the index.
+    fn diff_for<'r>(
+        &self,
+        repo: &'r Repository,
+        path: Option<&str>,
+        staged: bool,
+        reverse: bool,
+    ) -> Result<git2::Diff<'r>, GitError> {
+        let mut opts = git2::DiffOptions::new();
+        opts.reverse(reverse);
+        if let Some(path) = path {
+            if !self.working_dir.join(path).starts_with(&self.working_dir) {
+                return Err(GitError::Path(format!(
+                    "Path '{path}' is outside repository"
+                )));
+            }
+            opts.pathspec(path).disable_pathspec_match(true);
+        }
+
+        if staged {
+            let head = match repo.head() {
+                Ok(head) => Some(head.peel_to_tree()?),
+                Err(_) => None, // Empty repository
+            };
+            Ok(repo.diff_tree_to_index(head.as_ref(), None, Some(&mut opts))?)
+        } else {
+            opts.include_untracked(true)
+                .recurse_untracked_dirs(true)
+                .show_untracked_content(true);
+            Ok(repo.diff_index_to_workdir(None, Some(&mut opts))?)
+        }
+    }
+
+    /// Apply only the hunk at an index of a diff to the index.
+    fn apply_hunk(repo: &Repository, diff: &git2::Diff, hunk: usize) -> Result<(), GitError> {
+        Self::hunk_count(diff, hunk)?;
+        let mut seen = 0;
+        let mut opts = git2::ApplyOptions::new();
+        opts.hunk_callback(|_| {
+            seen += 1;
+            seen == hunk + 1
+        });
+        repo.apply(diff, git2::ApplyLocation::Index, Some(&mut opts))?;
+        Ok(())
+    }
+
+    /// Count the hunks of a diff, failing if there is no hunk at an index.
+    fn hunk_count(diff: &git2::Diff, hunk: usize) -> Result<usize, GitError> {
+        let count = diff
+            .deltas()
+            .enumerate()
+            .map(|(i, _)| git2::Patch::from_diff(diff, i).map(|p| p.map_or(0, |p| p.num_hunks())))
+            .sum::<Result<usize, _>>()?;
+        if hunk >= count {
+            return Err(GitError::Path(format!(
+                "No hunk {} - the file has {count}",
+                hunk + 1
+            )));
+        }
+        Ok(count)
+    }
+
     /// Create a commit with the given message.
     pub fn commit(&self, message: &str) -> Result<GitCommitInfo, GitError> {
         let repo = self.open_repo()?;
@@ -798,6 +907,91 @@ mod tests {
         assert_eq!(status.files[0].status, GitFileState::Deleted);
     }
 
+    /// Commit a file of 20 lines, then change its first and last line.
+    fn commit_and_change_two_hunks(temp_dir: &TempDir, ops: &GitOperations) {
+        let lines: Vec<String> = (1..=20).map(|i| format!("line {i}")).collect();
+        let file_path = temp_dir.path().join("file.txt");
+        fs::write(&file_path, lines.join("\n") + "\n").unwrap();
+        ops.stage(&["file.txt".to_string()]).unwrap();
+        ops.commit("Initial").unwrap();
+
+        let mut changed = lines;
+        changed[0] = "first".to_string();
+        changed[19] = "last".to_string();
+        fs::write(&file_path, changed.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn user_sees_unstaged_and_staged_diff() {
+        let (temp_dir, _repo) = setup_test_repo();
+        let ops = GitOperations::new(temp_dir.path());
+        commit_and_change_two_hunks(&temp_dir, &ops);
+
+        let diff = ops.diff(Some("file.txt"), false).unwrap();
+        assert!(diff.contains("-line 1\n+first\n"));
+        assert!(diff.contains("-line 20\n+last\n"));
+        assert_eq!(diff.matches("@@ -").count(), 2);
+        assert!(ops.diff(None, true).unwrap().is_empty());
+
+        ops.stage(&[]).unwrap();
+        assert!(ops.diff(None, false).unwrap().is_empty());
+        assert_eq!(ops.diff(None, true).unwrap(), diff);
+    }
+
+    #[test]
+    fn user_can_stage_and_unstage_single_hunks() {
+        let (temp_dir, _repo) = setup_test_repo();
+        let ops = GitOperations::new(temp_dir.path());
+        commit_and_change_two_hunks(&temp_dir, &ops);
+
+        // Stage only the second hunk
+        ops.stage_hunk("file.txt", 1).unwrap();
+        let staged = ops.diff(None, true).unwrap();
+        assert!(staged.contains("+last"));
+        assert!(!staged.contains("+first"));
+        let unstaged = ops.diff(None, false).unwrap();
+        assert!(unstaged.contains("+first"));
+        assert!(!unstaged.contains("+last"));
+
+        // Stage the rest, then take the first hunk back out
+        ops.stage_hunk("file.txt", 0).unwrap();
+        ops.unstage_hunk("file.txt", 0).unwrap();
+        let staged = ops.diff(None, true).unwrap();
+        assert!(!staged.contains("+first"));
+        assert!(staged.contains("+last"));
+
+        // The working directory is left alone
+        let content = fs::read_to_string(temp_dir.path().join("file.txt")).unwrap();
+        assert!(content.starts_with("first\n"));
+    }
+
+    #[test]
+    fn user_can_stage_new_file_as_hunk() {
+        let (temp_dir, _repo) = setup_test_repo();
+        let ops = GitOperations::new(temp_dir.path());
+        fs::write(temp_dir.path().join("new.txt"), "hello\n").unwrap();
+
+        ops.stage_hunk("new.txt", 0).unwrap();
+        let status = ops.status().unwrap();
+        assert_eq!(status.files.len(), 1);
+        assert!(status.files[0].staged);
+
+        ops.unstage_hunk("new.txt", 0).unwrap();
+        let status = ops.status().unwrap();
+        assert_eq!(status.files.len(), 1);
+        assert!(!status.files[0].staged);
+    }
+
+    #[test]
+    fn user_sees_error_for_missing_hunk() {
+        let (temp_dir, _repo) = setup_test_repo();
+        let ops = GitOperations::new(temp_dir.path());
+        commit_and_change_two_hunks(&temp_dir, &ops);
+
+        let err = ops.stage_hunk("file.txt", 2).unwrap_err();
+        assert!(err.to_string().contains("No hunk 3"));
+    }
+
     #[test]
     fn git_file_state_display_shows_correct_symbols() {
         assert_eq!(format!("{}", GitFileState::Modified), "M");
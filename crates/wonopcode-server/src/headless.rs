@@ -2,20 +2,77 @@
 //!
 //! This module provides an HTTP server that exposes the full agent functionality
 //! via HTTP endpoints and SSE streaming, allowing remote TUI clients to connect.
+//! The same actions and updates can also go over a WebSocket at `/ws`. Updates
+//! are numbered on both transports, so a client that reconnects resumes after
+//! the last one it got: SSE clients with the `Last-Event-ID` header, WebSocket
+//! clients with their first frame.
+//!
+//! # Protocol Versions
+//!
+//! Clients post their protocol versions and capabilities to `/handshake`
+//! when they connect, and are told what they have in common with the server,
+//! or why they can't talk to it.
+//!
+//! # Scoped Sessions
+//!
+//! Clients can also open sessions of their own at `/sessions`, each run by a
+//! separate runner with its own prompt queue and SSE stream at
+//! `/session/{id}/events`, so several of them can work in parallel.
+//!
+//! # Collaboration
+//!
+//! With collaboration on, the clients on the WebSocket share the session: one
+//! drives and the others watch, seeing each other's presence and the driver's
+//! prompts, until one of them takes control. Requests to the HTTP endpoints
+//! that change the session are refused with `403 Forbidden` unless they carry
+//! the driver's participant ID in the `X-Participant-Id` header. See
+//! [`crate::presence`].
+//!
+//! # Jobs
+//!
+//! Automations that can't hold a stream open post prompts to `/jobs` and poll
+//! `/jobs/{id}` for the result. Jobs run in scoped sessions: those without a
+//! session one after another, each in a fresh one, and those naming a session
+//! in order in it. See [`crate::jobs`].
 //!
 //! # MCP Support
 //!
 //! The headless server can optionally expose MCP (Model Context Protocol) endpoints
 //! at `/mcp/sse` and `/mcp/message`, allowing Claude CLI to connect via HTTP.
 //!
+//! # API Document
+//!
+//! An OpenAPI document of the endpoints is served at `/openapi.json`, with a
+//! Swagger UI at `/docs`, for generating clients.
+//!
+//! # Project Files
+//!
+//! Clients without access to the server's filesystem list, read and diff the
+//! project's files at `/files/*`. Paths leading outside the project are
+//! refused, and files can only be written if the server allows it.
+//!
+//! # Webhooks
+//!
+//! Events of unattended runs can be posted to webhooks; see
+//! [`crate::webhook`].
+//!
 //! # Authentication
 //!
 //! The server can be protected with an API key. When configured, clients must provide
 //! the key via `X-API-Key` header or `Authorization: Bearer <key>` header.
+//!
+//! # Limits
+//!
+//! Each client may make a limited number of requests per minute, answered with
+//! `429 Too Many Requests` beyond that, and action bodies are capped in size.
+//! See [`ServerLimits`].
 
 use axum::{
-    extract::{Query, Request, State},
-    http::{HeaderMap, StatusCode},
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, DefaultBodyLimit, Path, Query, Request, State,
+    },
+    http::{header, HeaderMap, Method, StatusCode},
     middleware::Next,
     response::{
         sse::{Event, KeepAlive, Sse},
@@ -24,20 +81,234 @@ use axum::{
     routing::{get, post},
     Router,
 };
-use futures::stream::Stream;
+use futures::{stream::SplitSink, SinkExt, Stream, StreamExt};
 use serde::Deserialize;
 
-use crate::git::GitOperations;
-use std::{convert::Infallible, sync::Arc, time::Duration};
+use crate::files::{FileError, ProjectFiles};
+use crate::git::{GitCommitInfo, GitOperations, GitStatus};
+use crate::jobs::JobStore;
+use crate::limit::{RateLimiter, ServerLimits};
+use crate::presence::Presence;
+use crate::webhook::Webhooks;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    convert::Infallible,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use subtle::ConstantTimeEq;
-use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::TraceLayer,
 };
 use tracing::{debug, info, warn, Span};
+use utoipa::{
+    openapi::{
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+        RefOr, ResponseBuilder, ResponsesBuilder,
+    },
+    IntoParams, IntoResponses, Modify, OpenApi, ToSchema,
+};
+use utoipa_swagger_ui::SwaggerUi;
 use wonopcode_mcp::{create_mcp_router, McpHttpState};
-use wonopcode_protocol::{Action, State as ProtocolState, Update};
+use wonopcode_protocol::{
+    Action, Capability, ClientFrame, FileContent, FileDiff, FileListing, Handshake, Job,
+    JobRequest, JobStatus, Negotiated, Participant, ScopedSessionInfo, ServerFrame,
+    State as ProtocolState, Update, HANDSHAKE_ENDPOINT, HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT,
+    PARTICIPANT_HEADER, SOCKET_ENDPOINT, SSE_RESYNC_EVENT,
+};
+
+/// Largest image attach request, in bytes. Images are base64 encoded, and
+/// the TUI scales them down to a few megabytes before sending them.
+const MAX_IMAGE_REQUEST_BYTES: usize = 16 * 1024 * 1024;
+
+/// Number of recent updates kept for clients that reconnect; enough for a
+/// minute or so of streaming output.
+const REPLAY_BUFFER_SIZE: usize = 4096;
+
+/// Longest participant name kept, in characters.
+const MAX_PARTICIPANT_NAME: usize = 64;
+
+/// Recent updates and their sequence numbers.
+#[derive(Default)]
+struct UpdateLog {
+    /// Sequence number of the last update.
+    seq: u64,
+    recent: VecDeque<(u64, Update)>,
+}
+
+impl UpdateLog {
+    /// Number an update and keep it for replay.
+    fn push(&mut self, update: Update) -> u64 {
+        self.seq += 1;
+        if self.recent.len() == REPLAY_BUFFER_SIZE {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((self.seq, update));
+        self.seq
+    }
+
+    /// The updates after `last_seq`, or `None` if some of them are no longer
+    /// kept (or `last_seq` is from before a restart).
+    fn since(&self, last_seq: u64) -> Option<Vec<(u64, Update)>> {
+        if last_seq > self.seq {
+            return None;
+        }
+        if last_seq < self.seq {
+            let oldest = self.recent.front().map(|(seq, _)| *seq)?;
+            if oldest > last_seq + 1 {
+                return None;
+            }
+        }
+        Some(
+            self.recent
+                .iter()
+                .filter(|(seq, _)| *seq > last_seq)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// Where a client picks up an update stream.
+struct Resumption {
+    /// Sequence number of the last update sent before `rx` subscribed.
+    seq: u64,
+    /// Missed updates to send first; `None` if they are no longer kept.
+    replay: Option<Vec<(u64, Update)>>,
+    rx: broadcast::Receiver<(u64, Update)>,
+}
+
+/// A request to start a runner for a new scoped session.
+pub struct SessionRequest {
+    /// ID of the session.
+    pub id: String,
+    /// Actions for the session's runner.
+    pub actions: mpsc::UnboundedReceiver<Action>,
+    /// Whether the runner started, and if not, why.
+    pub reply: oneshot::Sender<Result<(), String>>,
+}
+
+/// A scoped session: its runner's channels and prompt queue.
+struct ScopedSession {
+    action_tx: mpsc::UnboundedSender<Action>,
+    update_tx: broadcast::Sender<Update>,
+    /// The same updates with their sequence numbers.
+    sequenced_tx: broadcast::Sender<(u64, Update)>,
+    /// Recent updates, for clients that reconnect.
+    log: UpdateLog,
+    /// Whether the runner is running a prompt.
+    busy: bool,
+    /// Actions held back until the running prompt finishes.
+    queue: VecDeque<Action>,
+}
+
+impl ScopedSession {
+    fn new(action_tx: mpsc::UnboundedSender<Action>) -> Self {
+        let (update_tx, _) = broadcast::channel(256);
+        let (sequenced_tx, _) = broadcast::channel(256);
+        Self {
+            action_tx,
+            update_tx,
+            sequenced_tx,
+            log: UpdateLog::default(),
+            busy: false,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Whether the runner handles an action while it runs a prompt; it
+    /// drops the others, so they are queued instead.
+    fn runs_during_prompt(action: &Action) -> bool {
+        matches!(
+            action,
+            Action::Cancel
+                | Action::Quit
+                | Action::PermissionResponse { .. }
+                | Action::CancelTool { .. }
+                | Action::SearchSymbols { .. }
+        )
+    }
+
+    /// Number of prompts in the queue.
+    fn queued_prompts(&self) -> usize {
+        self.queue
+            .iter()
+            .filter(|action| matches!(action, Action::SendPrompt { .. }))
+            .count()
+    }
+
+    /// Send an action to the runner, or queue it behind the running prompt.
+    ///
+    /// Returns the place of a queued prompt, or `Err` if the runner is gone.
+    fn dispatch(&mut self, action: Action) -> Result<Option<usize>, ()> {
+        if (self.busy || !self.queue.is_empty()) && !Self::runs_during_prompt(&action) {
+            let is_prompt = matches!(action, Action::SendPrompt { .. });
+            self.queue.push_back(action);
+            return Ok(is_prompt.then(|| self.queued_prompts()));
+        }
+        self.send(action)?;
+        Ok(None)
+    }
+
+    fn send(&mut self, action: Action) -> Result<(), ()> {
+        if matches!(action, Action::SendPrompt { .. }) {
+            self.busy = true;
+        }
+        self.action_tx.send(action).map_err(|_| ())
+    }
+
+    /// Broadcast an update, sending queued actions once a prompt finishes.
+    fn publish(&mut self, update: Update) {
+        if self.busy && matches!(update, Update::Completed { .. } | Update::Error { .. }) {
+            self.busy = false;
+        }
+        self.send_update(update);
+        while !self.busy {
+            let Some(action) = self.queue.pop_front() else {
+                break;
+            };
+            if self.send(action).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Number an update and send it to the session's clients.
+    fn send_update(&mut self, update: Update) {
+        let seq = self.log.push(update.clone());
+        let _ = self.sequenced_tx.send((seq, update.clone()));
+        let _ = self.update_tx.send(update);
+    }
+
+    /// Subscribe to the session's sequenced updates, after the updates a
+    /// client missed since `last_seq`.
+    fn resume(&self, last_seq: Option<u64>) -> Resumption {
+        Resumption {
+            seq: self.log.seq,
+            replay: match last_seq {
+                Some(last_seq) => self.log.since(last_seq),
+                None => Some(Vec::new()),
+            },
+            rx: self.sequenced_tx.subscribe(),
+        }
+    }
+
+    fn info(&self, id: &str) -> ScopedSessionInfo {
+        ScopedSessionInfo {
+            id: id.to_string(),
+            busy: self.busy,
+            queued: self.queued_prompts(),
+        }
+    }
+}
+
+/// Senders of the jobs for each lane: a scoped session, or `""` for jobs
+/// that run in fresh sessions.
+type JobLanes = HashMap<String, mpsc::UnboundedSender<Job>>;
 
 /// State for the headless server.
 #[derive(Clone)]
@@ -46,24 +317,55 @@ pub struct HeadlessState {
     pub action_tx: mpsc::UnboundedSender<Action>,
     /// Broadcast sender for updates from the runner.
     pub update_tx: broadcast::Sender<Update>,
+    /// Broadcast sender for the same updates with their sequence numbers.
+    sequenced_tx: broadcast::Sender<(u64, Update)>,
+    /// Recent updates, for clients that reconnect.
+    log: Arc<std::sync::Mutex<UpdateLog>>,
     /// Current state for initial sync.
     pub current_state: Arc<RwLock<ProtocolState>>,
     /// Flag to track if server should shutdown.
     pub shutdown: Arc<RwLock<bool>>,
     /// Sender to trigger server shutdown.
     pub shutdown_tx: Option<mpsc::Sender<()>>,
+    /// Scoped sessions by ID.
+    sessions: Arc<std::sync::Mutex<HashMap<String, ScopedSession>>>,
+    /// Sender for requests to start the runner of a scoped session.
+    session_host: Option<mpsc::UnboundedSender<SessionRequest>>,
+    /// Rate and size limits on requests.
+    limits: ServerLimits,
+    /// Whether clients may write project files.
+    allow_file_writes: bool,
+    /// Webhooks that events are posted to.
+    webhooks: Option<Webhooks>,
+    /// Prompt jobs and their results.
+    jobs: Arc<JobStore>,
+    /// Queues of the jobs waiting to run.
+    job_lanes: Arc<std::sync::Mutex<JobLanes>>,
+    /// Clients sharing the session, if collaboration is on.
+    presence: Option<Arc<Presence>>,
 }
 
 impl HeadlessState {
     /// Create a new headless state.
     pub fn new(action_tx: mpsc::UnboundedSender<Action>) -> Self {
         let (update_tx, _) = broadcast::channel(256);
+        let (sequenced_tx, _) = broadcast::channel(256);
         Self {
             action_tx,
             update_tx,
+            sequenced_tx,
+            log: Arc::default(),
             current_state: Arc::new(RwLock::new(ProtocolState::default())),
             shutdown: Arc::new(RwLock::new(false)),
             shutdown_tx: None,
+            sessions: Arc::default(),
+            session_host: None,
+            limits: ServerLimits::default(),
+            allow_file_writes: false,
+            webhooks: None,
+            jobs: Arc::new(JobStore::in_memory()),
+            job_lanes: Arc::default(),
+            presence: None,
         }
     }
 
@@ -73,11 +375,287 @@ impl HeadlessState {
         self
     }
 
+    /// Accept scoped sessions, sending a request for each one's runner to
+    /// the given sender.
+    pub fn with_session_host(mut self, tx: mpsc::UnboundedSender<SessionRequest>) -> Self {
+        self.session_host = Some(tx);
+        self
+    }
+
+    /// Set the rate and size limits on requests.
+    pub fn with_limits(mut self, limits: ServerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Let clients write project files at `/files/write`. Files are only
+    /// readable by default.
+    pub fn with_file_writes(mut self, allow: bool) -> Self {
+        self.allow_file_writes = allow;
+        self
+    }
+
+    /// Post events to webhooks.
+    pub fn with_webhooks(mut self, webhooks: Webhooks) -> Self {
+        self.webhooks = (!webhooks.is_empty()).then_some(webhooks);
+        self
+    }
+
+    /// Keep jobs in a store, such as one on disk. They are kept in memory
+    /// by default.
+    pub fn with_jobs(mut self, jobs: JobStore) -> Self {
+        self.jobs = Arc::new(jobs);
+        self
+    }
+
+    /// Share the session between the WebSocket clients: one drives and the
+    /// others watch. Off by default, when every client may act.
+    pub fn with_collaboration(mut self, enabled: bool) -> Self {
+        self.presence = enabled.then(Arc::default);
+        self
+    }
+
+    /// The optional features this server offers.
+    fn capabilities(&self) -> Vec<Capability> {
+        let mut capabilities = vec![
+            Capability::WebSocket,
+            Capability::SseResume,
+            Capability::Git,
+            Capability::Files,
+        ];
+        if self.allow_file_writes {
+            capabilities.push(Capability::FileWrites);
+        }
+        if self.session_host.is_some() {
+            capabilities.extend([Capability::ScopedSessions, Capability::Jobs]);
+        }
+        if self.presence.is_some() {
+            capabilities.push(Capability::Collaboration);
+        }
+        capabilities
+    }
+
+    /// Post an update to the webhooks, if it is an event.
+    fn notify_webhooks(&self, update: &Update, session: Option<&str>) {
+        let Some(webhooks) = &self.webhooks else {
+            return;
+        };
+        let project = self
+            .current_state
+            .try_read()
+            .ok()
+            .map(|state| state.project.clone())
+            .filter(|project| !project.is_empty());
+        webhooks.notify(update, project.as_deref(), session);
+    }
+
+    /// Send an update to the clients of a scoped session.
+    pub fn send_session_update(&self, session_id: &str, update: Update) {
+        self.notify_webhooks(&update, Some(session_id));
+        if let Some(session) = self.lock_sessions().get_mut(session_id) {
+            session.publish(update);
+        }
+    }
+
+    fn lock_sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, ScopedSession>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Open a scoped session and wait for its runner to start.
+    async fn start_session(
+        &self,
+        host: &mpsc::UnboundedSender<SessionRequest>,
+    ) -> Result<String, String> {
+        let id = wonopcode_util::Identifier::session();
+        let (action_tx, actions) = mpsc::unbounded_channel();
+        let (reply, started) = oneshot::channel();
+        self.lock_sessions()
+            .insert(id.clone(), ScopedSession::new(action_tx));
+        let request = SessionRequest {
+            id: id.clone(),
+            actions,
+            reply,
+        };
+        let result = match host.send(request) {
+            Ok(()) => started
+                .await
+                .unwrap_or_else(|_| Err("The agent is not running".to_string())),
+            Err(_) => Err("The agent is not running".to_string()),
+        };
+        match result {
+            Ok(()) => {
+                info!(session_id = %id, "Opened scoped session");
+                Ok(id)
+            }
+            Err(e) => {
+                self.lock_sessions().remove(&id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Close a scoped session, stopping its runner. Its queued jobs fail.
+    fn close_session(&self, id: &str) -> bool {
+        let Some(session) = self.lock_sessions().remove(id) else {
+            return false;
+        };
+        // Stop the running prompt; the runner stops once it has finished
+        let _ = session.action_tx.send(Action::Cancel);
+        self.lock_job_lanes().remove(id);
+        info!(session_id = %id, "Closed scoped session");
+        true
+    }
+
+    fn lock_job_lanes(&self) -> std::sync::MutexGuard<'_, JobLanes> {
+        self.job_lanes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Queue a job behind the others in its lane, starting a worker for the
+    /// lane if it has none.
+    fn enqueue_job(&self, job: Job) {
+        let lane = job.session.clone().unwrap_or_default();
+        let mut lanes = self.lock_job_lanes();
+        let job = match lanes.get(&lane) {
+            Some(tx) => match tx.send(job) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(job)) => job,
+            },
+            None => job,
+        };
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let _ = tx.send(job);
+        lanes.insert(lane, tx);
+        let state = self.clone();
+        tokio::spawn(async move {
+            while let Some(job) = rx.recv().await {
+                state.run_job(job).await;
+            }
+        });
+    }
+
+    /// Run a job's prompt in its session, or a fresh one, and record how it
+    /// ended.
+    async fn run_job(&self, job: Job) {
+        // Cancelled while it was queued
+        if self.jobs.get(&job.id).map(|job| job.status) != Some(JobStatus::Queued) {
+            return;
+        }
+        let (session, fresh) = match job.session {
+            Some(session) => (session, false),
+            None => {
+                let started = match &self.session_host {
+                    Some(host) => self.start_session(host).await,
+                    None => Err("This server doesn't run scoped sessions".to_string()),
+                };
+                match started {
+                    Ok(session) => (session, true),
+                    Err(e) => {
+                        self.jobs.finish(&job.id, Err(e));
+                        return;
+                    }
+                }
+            }
+        };
+
+        if self.jobs.start(&job.id, &session).is_some() {
+            info!(job_id = %job.id, session_id = %session, "Running job");
+            let outcome = self.prompt_session(&session, job.prompt).await;
+            if let Some(job) = self.jobs.finish(&job.id, outcome) {
+                info!(job_id = %job.id, status = ?job.status, "Job finished");
+            }
+        }
+        if fresh {
+            self.close_session(&session);
+        }
+    }
+
+    /// Send a prompt to a scoped session and wait for its response.
+    async fn prompt_session(&self, session: &str, prompt: String) -> Result<String, String> {
+        let mut updates = {
+            let mut sessions = self.lock_sessions();
+            let session = sessions
+                .get_mut(session)
+                .ok_or_else(|| "The session was closed".to_string())?;
+            let updates = session.update_tx.subscribe();
+            session
+                .dispatch(Action::SendPrompt { prompt })
+                .map_err(|()| "The session's runner is not running".to_string())?;
+            updates
+        };
+        loop {
+            match updates.recv().await {
+                Ok(Update::Completed { text }) => return Ok(text),
+                Ok(Update::Error { error }) => return Err(error),
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => {
+                    return Err("The session was closed".to_string())
+                }
+            }
+        }
+    }
+
     /// Send an update to all connected clients.
     pub fn send_update(&self, update: Update) {
+        self.notify_webhooks(&update, None);
+        // Numbered under the lock, so a resuming client neither misses nor
+        // repeats an update between its replay and its subscription.
+        let mut log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        let seq = log.push(update.clone());
+        let _ = self.sequenced_tx.send((seq, update.clone()));
+        drop(log);
         let _ = self.update_tx.send(update);
     }
 
+    /// Tell every client who shares the session.
+    fn send_presence(&self) {
+        if let Some(presence) = &self.presence {
+            self.send_update(Update::Presence {
+                participants: presence.list(),
+            });
+        }
+    }
+
+    /// Run an action from a WebSocket client, as its participant in a
+    /// shared session. Watchers' actions are refused, and the driver's
+    /// prompts shown to the others.
+    fn socket_action(
+        &self,
+        participant: Option<&Participant>,
+        action: Action,
+    ) -> Result<(), String> {
+        if let (Some(presence), Some(participant)) = (&self.presence, participant) {
+            if !presence.is_driver(&participant.id) {
+                return Err(
+                    "Only the driver of a shared session can act; take control first".to_string(),
+                );
+            }
+            if let Action::SendPrompt { prompt } = &action {
+                self.send_update(Update::Prompted {
+                    participant: participant.id.clone(),
+                    name: participant.name.clone(),
+                    prompt: prompt.clone(),
+                });
+            }
+        }
+        self.action_tx
+            .send(action)
+            .map_err(|_| "The agent is not running".to_string())
+    }
+
+    /// Subscribe to sequenced updates, after the updates a client missed
+    /// since `last_seq`.
+    fn resume(&self, last_seq: Option<u64>) -> Resumption {
+        let log = self.log.lock().unwrap_or_else(|e| e.into_inner());
+        Resumption {
+            seq: log.seq,
+            replay: match last_seq {
+                Some(last_seq) => log.since(last_seq),
+                None => Some(Vec::new()),
+            },
+            rx: self.sequenced_tx.subscribe(),
+        }
+    }
+
     /// Update the current state.
     pub async fn update_state<F>(&self, f: F)
     where
@@ -101,7 +679,7 @@ struct AuthState {
 /// Extract API key from request headers.
 ///
 /// Supports both `X-API-Key` header and `Authorization: Bearer <key>` format.
-fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
+pub(crate) fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
     // Check X-API-Key header first (case-insensitive in HTTP)
     if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
         return Some(key);
@@ -118,7 +696,7 @@ fn extract_api_key(headers: &HeaderMap) -> Option<&str> {
 }
 
 /// Constant-time comparison to prevent timing attacks.
-fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
     a.ct_eq(b).into()
 }
 
@@ -157,6 +735,78 @@ async fn api_key_auth(
     }
 }
 
+/// State for the rate limiting middleware.
+#[derive(Clone)]
+struct RateLimitState {
+    limiter: Arc<RateLimiter>,
+    api_key: Option<String>,
+}
+
+/// Client a request counts against: a hash of the key it presents if that is
+/// the right one, so its holders keep their budget across addresses, or else
+/// the address it came from. Wrong keys count against the address, so each
+/// guess doesn't get a budget of its own.
+fn rate_limit_client(request: &Request, api_key: Option<&str>) -> String {
+    if let (Some(provided), Some(expected)) = (extract_api_key(request.headers()), api_key) {
+        if constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+            return format!("key:{}", hex::encode(Sha256::digest(provided.as_bytes())));
+        }
+    }
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_default()
+}
+
+/// Middleware to limit the requests per minute of each client.
+async fn rate_limit(State(limit): State<RateLimitState>, request: Request, next: Next) -> Response {
+    let client = rate_limit_client(&request, limit.api_key.as_deref());
+    match limit.limiter.check(&client, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(wait) => {
+            let retry_after = wait.as_secs_f64().ceil() as u64;
+            warn!(client = %client, "Rate limit exceeded");
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(serde_json::json!({
+                    "error": "Too many requests",
+                    "retry_after": retry_after,
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Middleware to refuse requests that change a shared session unless they
+/// carry the driver's participant ID. Reads are open to everyone.
+async fn driver_only(State(state): State<HeadlessState>, request: Request, next: Next) -> Response {
+    let Some(presence) = &state.presence else {
+        return next.run(request).await;
+    };
+    if request.method() == Method::GET {
+        return next.run(request).await;
+    }
+    let driver = request
+        .headers()
+        .get(PARTICIPANT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|id| presence.is_driver(id));
+    if driver {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({
+                "error": "Only the driver of a shared session can act; take control first",
+            })),
+        )
+            .into_response()
+    }
+}
+
 // ============================================================================
 // Router Creation
 // ============================================================================
@@ -194,17 +844,28 @@ pub fn create_headless_router_with_options(
     let auth_state = AuthState {
         api_key: api_key.clone(),
     };
+    let limits = state.limits;
+    let rate_limit_state = RateLimitState {
+        limiter: Arc::new(RateLimiter::new(limits.requests_per_minute)),
+        api_key: api_key.clone(),
+    };
 
-    // Protected routes that require authentication
-    let mut protected_router = Router::new()
-        // Info endpoint for quick agent identification
-        .route("/info", get(get_info))
-        // State endpoint for initial sync
-        .route("/state", get(get_state))
-        // SSE events stream
-        .route("/events", get(events))
+    // Routes that change the session, which only the driver of a shared
+    // session may call
+    let driven_router = Router::new()
+        // Scoped sessions
+        .route("/session/{id}/action", post(session_action))
+        // Prompt jobs
+        .route("/jobs", get(list_jobs).post(create_job))
+        .route("/jobs/{id}/cancel", post(cancel_job))
         // Action endpoints
         .route("/action/prompt", post(action_prompt))
+        .route(
+            "/action/image",
+            post(action_image).layer(DefaultBodyLimit::max(MAX_IMAGE_REQUEST_BYTES)),
+        )
+        .route("/action/context", post(action_context))
+        .route("/action/symbols", post(action_symbols))
         .route("/action/cancel", post(action_cancel))
         .route("/action/model", post(action_model))
         .route("/action/agent", post(action_agent))
@@ -212,6 +873,20 @@ pub fn create_headless_router_with_options(
         .route("/action/session/switch", post(action_session_switch))
         .route("/action/session/rename", post(action_session_rename))
         .route("/action/session/fork", post(action_session_fork))
+        .route("/action/session/merge", post(action_session_merge))
+        .route("/action/session/search", post(action_session_search))
+        .route("/action/orchestration/start", post(action_orchestrate))
+        .route(
+            "/action/orchestration/merge",
+            post(action_orchestration_merge),
+        )
+        .route(
+            "/action/orchestration/cancel",
+            post(action_orchestration_cancel),
+        )
+        .route("/action/worktree/create", post(action_worktree_create))
+        .route("/action/worktree/list", post(action_worktree_list))
+        .route("/action/worktree/finish", post(action_worktree_finish))
         .route("/action/session/share", post(action_session_share))
         .route("/action/session/unshare", post(action_session_unshare))
         .route("/action/undo", post(action_undo))
@@ -225,19 +900,68 @@ pub fn create_headless_router_with_options(
         .route("/action/mcp/toggle", post(action_mcp_toggle))
         .route("/action/mcp/reconnect", post(action_mcp_reconnect))
         .route("/action/goto", post(action_goto))
+        .route("/action/profile", post(action_profile))
+        .route("/action/projects", post(action_projects))
+        .route("/action/project/switch", post(action_project_switch))
+        .route("/action/template", post(action_template))
+        .route("/action/template/save", post(action_template_save))
+        .route("/action/memory", post(action_memory))
+        .route("/action/memory/save", post(action_memory_save))
         .route("/action/settings", post(action_settings))
         .route("/action/permission", post(action_permission))
+        .route("/action/permission/audit", post(action_permission_audit))
+        .route("/action/spend", post(action_spend))
+        .route("/action/usage", post(action_usage))
+        .route("/action/spend/ack", post(action_spend_ack))
+        .route("/action/review", post(action_review))
+        .route("/action/review/apply", post(action_review_apply))
+        .route("/action/process/stop", post(action_process_stop))
+        .route("/action/tool/cancel", post(action_tool_cancel))
         .route("/action/quit", post(action_quit))
         .route("/action/shutdown", post(action_shutdown))
         // Git operations
-        .route("/git/status", get(git_status))
         .route("/git/stage", post(git_stage))
         .route("/git/unstage", post(git_unstage))
         .route("/git/checkout", post(git_checkout))
+        .route("/git/stage-hunk", post(git_stage_hunk))
+        .route("/git/unstage-hunk", post(git_unstage_hunk))
         .route("/git/commit", post(git_commit))
-        .route("/git/history", get(git_history))
         .route("/git/push", post(git_push))
         .route("/git/pull", post(git_pull))
+        // Project files
+        .route("/files/write", post(files_write))
+        .route_layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            driver_only,
+        ));
+
+    // Protected routes that require authentication
+    let mut protected_router = Router::new()
+        // Info endpoint for quick agent identification
+        .route("/info", get(get_info))
+        // Protocol negotiation
+        .route(HANDSHAKE_ENDPOINT, post(handshake))
+        // State endpoint for initial sync
+        .route("/state", get(get_state))
+        // SSE events stream
+        .route("/events", get(events))
+        .route(SOCKET_ENDPOINT, get(socket))
+        // Scoped sessions
+        .route("/sessions", get(list_sessions).post(open_session))
+        .route("/session/{id}", axum::routing::delete(close_session))
+        .route("/session/{id}/events", get(session_events))
+        // Prompt jobs
+        .route("/jobs/{id}", get(get_job))
+        // Git operations
+        .route("/git/status", get(git_status))
+        .route("/git/diff", post(git_diff))
+        .route("/git/history", get(git_history))
+        // Project files
+        .route("/files/list", get(files_list))
+        .route("/files/read", get(files_read))
+        .route("/files/diff", get(files_diff))
+        .merge(driven_router)
+        .layer(DefaultBodyLimit::max(limits.max_body_bytes))
         .with_state(state);
 
     // Add MCP routes if state is provided (with API key auth applied via MCP's own middleware)
@@ -262,9 +986,21 @@ pub fn create_headless_router_with_options(
         protected_router
     };
 
-    // Combine with public routes (health check remains accessible for monitoring)
+    // Limit the rate before authenticating, so guessing keys is limited too
+    let protected_router = if limits.requests_per_minute > 0 {
+        protected_router.layer(axum::middleware::from_fn_with_state(
+            rate_limit_state,
+            rate_limit,
+        ))
+    } else {
+        protected_router
+    };
+
+    // Combine with public routes (health check remains accessible for monitoring,
+    // and the API document and its Swagger UI can be read without a key)
     let router = Router::new()
         .route("/health", get(health))
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()))
         .merge(protected_router);
 
     router.layer(cors).layer(
@@ -295,17 +1031,127 @@ pub fn create_headless_router_with_options(
     )
 }
 
+// ============================================================================
+// OpenAPI Document
+// ============================================================================
+
+/// The OpenAPI document of the headless API, served at `/openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "wonopcode headless API",
+        description = "Drive a wonopcode agent over HTTP. Actions are posted to their \
+                       endpoints and their effects arrive as updates on `/events` or `/ws`."
+    ),
+    paths(
+        health, get_info, handshake, get_state, events, list_sessions, open_session, close_session,
+        session_events, session_action, list_jobs, create_job, get_job, cancel_job, socket, action_prompt, action_image, action_context,
+        action_symbols, action_cancel, action_model, action_agent, action_session_new,
+        action_session_switch, action_session_rename, action_session_fork, action_session_merge,
+        action_session_search, action_orchestrate, action_orchestration_merge,
+        action_orchestration_cancel, action_worktree_create, action_worktree_list,
+        action_worktree_finish, action_session_share, action_session_unshare, action_undo,
+        action_redo, action_revert, action_unrevert, action_compact, action_sandbox_start,
+        action_sandbox_stop, action_sandbox_restart, action_profile, action_projects,
+        action_project_switch, action_template, action_template_save, action_memory,
+        action_memory_save, action_mcp_toggle, action_mcp_reconnect, action_goto, action_settings,
+        action_permission, action_permission_audit, action_spend, action_usage, action_spend_ack,
+        action_review, action_review_apply, action_process_stop, action_tool_cancel, action_quit,
+        action_shutdown, git_status, git_stage, git_unstage, git_checkout, git_diff, git_stage_hunk,
+        git_unstage_hunk, git_commit, git_history, git_push, git_pull, files_list, files_read,
+        files_diff, files_write
+    ),
+    components(schemas(Action, Update, ClientFrame, ServerFrame, Capability)),
+    modifiers(&ApiKeyAuth),
+    security(("api_key" = []), ("bearer" = [])),
+    tags(
+        (name = "server", description = "State, updates and the WebSocket transport"),
+        (name = "sessions", description = "Scoped sessions with their own runners"),
+        (name = "jobs", description = "Prompts queued as jobs for automation"),
+        (name = "actions", description = "Actions for the agent"),
+        (name = "git", description = "Git operations on the project"),
+        (name = "files", description = "Files of the project")
+    )
+)]
+pub struct ApiDoc;
+
+/// Adds the API key schemes checked by [`api_key_auth`].
+struct ApiKeyAuth;
+
+impl Modify for ApiKeyAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))),
+        );
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Responses of the action endpoints. They only hand the action to the
+/// agent; its effects arrive as updates.
+struct ActionResponses;
+
+impl IntoResponses for ActionResponses {
+    fn responses() -> BTreeMap<String, RefOr<utoipa::openapi::Response>> {
+        ResponsesBuilder::new()
+            .response(
+                "200",
+                ResponseBuilder::new().description("The action was sent to the agent"),
+            )
+            .response(
+                "413",
+                ResponseBuilder::new().description("The request body is too large"),
+            )
+            .response(
+                "429",
+                ResponseBuilder::new().description("Too many requests from this client"),
+            )
+            .response(
+                "500",
+                ResponseBuilder::new().description("The agent is not running"),
+            )
+            .build()
+            .into()
+    }
+}
+
 // ============================================================================
 // Health & State Endpoints
 // ============================================================================
 
+/// Check that the server is up. Needs no API key.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "server",
+    security(()),
+    responses((status = 200, description = "The server is up"))
+)]
 async fn health() -> impl IntoResponse {
     Json(serde_json::json!({ "status": "ok" }))
 }
 
 /// Get basic agent info (name, working directory, project_id, work_id).
 /// This is a lightweight endpoint for quick identification.
+#[utoipa::path(
+    get,
+    path = "/info",
+    tag = "server",
+    responses(
+        (
+            status = 200,
+            description = "Name, project, model, project_id, work_id, protocol_version and \
+                           capabilities of the agent"
+        )
+    )
+)]
 async fn get_info(State(state): State<HeadlessState>) -> impl IntoResponse {
+    let handshake = Handshake::new(state.capabilities());
     let current = state.current_state.read().await;
     Json(serde_json::json!({
         "name": current.agent,
@@ -313,9 +1159,58 @@ async fn get_info(State(state): State<HeadlessState>) -> impl IntoResponse {
         "model": current.model,
         "project_id": current.project_id,
         "work_id": current.work_id,
+        "protocol_version": handshake.version,
+        "capabilities": handshake.capabilities,
     }))
 }
 
+/// Negotiate the protocol version and capabilities with a client.
+#[utoipa::path(
+    post,
+    path = "/handshake",
+    tag = "server",
+    request_body = Handshake,
+    responses(
+        (status = 200, description = "What the client and server have in common", body = Negotiated),
+        (
+            status = 426,
+            description = "No protocol version in common; the body has the `error` and the \
+                           server's `handshake`"
+        )
+    )
+)]
+async fn handshake(
+    State(state): State<HeadlessState>,
+    Json(client): Json<Handshake>,
+) -> Result<Json<Negotiated>, (StatusCode, Json<serde_json::Value>)> {
+    let server = Handshake::new(state.capabilities());
+    match server.negotiate(&client) {
+        Ok(negotiated) => {
+            debug!(
+                version = negotiated.version,
+                capabilities = ?negotiated.capabilities,
+                "Negotiated protocol with client"
+            );
+            Ok(Json(negotiated))
+        }
+        Err(mismatch) => {
+            let error = mismatch.describe("server", "client");
+            warn!(error = %error, "Refused client with incompatible protocol");
+            Err((
+                StatusCode::UPGRADE_REQUIRED,
+                Json(serde_json::json!({ "error": error, "handshake": server })),
+            ))
+        }
+    }
+}
+
+/// Get the full state, to sync before following the updates.
+#[utoipa::path(
+    get,
+    path = "/state",
+    tag = "server",
+    responses((status = 200, description = "The current state", body = ProtocolState))
+)]
 async fn get_state(State(state): State<HeadlessState>) -> impl IntoResponse {
     let current = state.current_state.read().await;
     Json(current.clone())
@@ -325,22 +1220,96 @@ async fn get_state(State(state): State<HeadlessState>) -> impl IntoResponse {
 // SSE Events Stream
 // ============================================================================
 
+/// An update as a server-sent event, named by its `type` and identified by
+/// its sequence number.
+fn update_event(seq: u64, update: &Update) -> Option<Event> {
+    let data = serde_json::to_string(update).ok()?;
+    Some(
+        Event::default()
+            .event(update.event_type())
+            .id(seq.to_string())
+            .data(data),
+    )
+}
+
+/// The sequence number in a request's `Last-Event-ID` header.
+fn last_event_id(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+}
+
+/// A `resync` event: updates up to `seq` were missed.
+fn resync_event(seq: u64) -> Event {
+    let data = serde_json::json!({ "seq": seq }).to_string();
+    Event::default()
+        .event(SSE_RESYNC_EVENT)
+        .id(seq.to_string())
+        .data(data)
+}
+
+/// Stream the updates as server-sent events, named by their `type`.
+///
+/// Each event's ID is the update's sequence number. A client that reconnects
+/// with a `Last-Event-ID` header first gets the updates it missed, or a
+/// `resync` event if they are no longer kept.
+#[utoipa::path(
+    get,
+    path = "/events",
+    tag = "server",
+    params(
+        ("Last-Event-ID" = Option<u64>, Header, description = "ID of the last event received")
+    ),
+    responses(
+        (
+            status = 200,
+            description = "One event per update",
+            content_type = "text/event-stream",
+            body = Update
+        )
+    )
+)]
 async fn events(
     State(state): State<HeadlessState>,
+    headers: HeaderMap,
 ) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
-    let mut rx = state.update_tx.subscribe();
+    let last_seq = last_event_id(&headers);
+    let Resumption {
+        seq,
+        replay,
+        mut rx,
+    } = state.resume(last_seq);
+    debug!(
+        ?last_seq,
+        seq,
+        complete = replay.is_some(),
+        "SSE client resumed"
+    );
 
     let stream = async_stream::stream! {
+        match replay {
+            Some(replay) => {
+                for (seq, update) in replay {
+                    if let Some(event) = update_event(seq, &update) {
+                        yield Ok(event);
+                    }
+                }
+            }
+            None => yield Ok(resync_event(seq)),
+        }
         loop {
             match rx.recv().await {
-                Ok(update) => {
-                    let event_type = update.event_type();
-                    if let Ok(data) = serde_json::to_string(&update) {
-                        yield Ok(Event::default().event(event_type).data(data));
+                Ok((seq, update)) => {
+                    if let Some(event) = update_event(seq, &update) {
+                        yield Ok(event);
                     }
                 }
                 Err(broadcast::error::RecvError::Lagged(n)) => {
-                    tracing::warn!("SSE stream lagged by {} events", n);
+                    // End rather than skip: the client reconnects with the
+                    // last event it got, and the missed ones are replayed.
+                    warn!("SSE client lagged by {} updates, closing", n);
+                    break;
                 }
                 Err(broadcast::error::RecvError::Closed) => {
                     break;
@@ -357,42 +1326,634 @@ async fn events(
 }
 
 // ============================================================================
-// Action Endpoints
+// Scoped Sessions
 // ============================================================================
 
-#[derive(Deserialize)]
-struct PromptRequest {
-    prompt: String,
+/// List the scoped sessions.
+#[utoipa::path(
+    get,
+    path = "/sessions",
+    tag = "sessions",
+    responses((status = 200, description = "The open sessions", body = Vec<ScopedSessionInfo>))
+)]
+async fn list_sessions(State(state): State<HeadlessState>) -> impl IntoResponse {
+    let mut sessions: Vec<ScopedSessionInfo> = state
+        .lock_sessions()
+        .iter()
+        .map(|(id, session)| session.info(id))
+        .collect();
+    sessions.sort_by(|a, b| a.id.cmp(&b.id));
+    Json(sessions)
 }
 
-async fn action_prompt(
-    State(state): State<HeadlessState>,
-    Json(req): Json<PromptRequest>,
-) -> impl IntoResponse {
-    debug!(prompt = %req.prompt, "Received prompt action");
-    match state
-        .action_tx
-        .send(Action::SendPrompt { prompt: req.prompt })
-    {
-        Ok(_) => StatusCode::OK,
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
-    }
-}
+/// Open a scoped session with its own runner.
+#[utoipa::path(
+    post,
+    path = "/sessions",
+    tag = "sessions",
+    responses(
+        (status = 201, description = "The session was opened", body = ScopedSessionInfo),
+        (status = 500, description = "The session's runner could not be started"),
+        (status = 501, description = "This server doesn't run scoped sessions")
+    )
+)]
+async fn open_session(State(state): State<HeadlessState>) -> Response {
+    let Some(host) = &state.session_host else {
+        return (
+            StatusCode::NOT_IMPLEMENTED,
+            "This server doesn't run scoped sessions",
+        )
+            .into_response();
+    };
 
-async fn action_cancel(State(state): State<HeadlessState>) -> impl IntoResponse {
-    debug!("Received cancel action");
-    match state.action_tx.send(Action::Cancel) {
-        Ok(_) => StatusCode::OK,
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    match state.start_session(host).await {
+        Ok(id) => (
+            StatusCode::CREATED,
+            Json(ScopedSessionInfo {
+                id,
+                busy: false,
+                queued: 0,
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            warn!(error = %e, "Failed to open scoped session");
+            (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
     }
 }
 
-#[derive(Deserialize)]
-struct ModelRequest {
-    model: String,
+/// Close a scoped session, stopping its runner.
+#[utoipa::path(
+    delete,
+    path = "/session/{id}",
+    tag = "sessions",
+    params(("id" = String, Path, description = "Session ID")),
+    responses(
+        (status = 204, description = "The session was closed"),
+        (status = 404, description = "No such session")
+    )
+)]
+async fn close_session(
+    State(state): State<HeadlessState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    if state.close_session(&id) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
 }
 
-async fn action_model(
+/// Stream the updates of a scoped session as server-sent events.
+///
+/// Events are numbered and resumed with `Last-Event-ID` like those of
+/// `/events`. A client that falls behind gets a `resync` event before the
+/// next update.
+#[utoipa::path(
+    get,
+    path = "/session/{id}/events",
+    tag = "sessions",
+    params(
+        ("id" = String, Path, description = "Session ID"),
+        ("Last-Event-ID" = Option<u64>, Header, description = "ID of the last event received")
+    ),
+    responses(
+        (
+            status = 200,
+            description = "One event per update",
+            content_type = "text/event-stream",
+            body = Update
+        ),
+        (status = 404, description = "No such session")
+    )
+)]
+async fn session_events(
+    State(state): State<HeadlessState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let last_seq = last_event_id(&headers);
+    let Some(Resumption {
+        seq,
+        replay,
+        mut rx,
+    }) = state
+        .lock_sessions()
+        .get(&id)
+        .map(|session| session.resume(last_seq))
+    else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    debug!(session_id = %id, ?last_seq, seq, complete = replay.is_some(), "Session SSE client resumed");
+
+    let stream = async_stream::stream! {
+        match replay {
+            Some(replay) => {
+                for (seq, update) in replay {
+                    if let Some(event) = update_event(seq, &update) {
+                        yield Ok::<_, Infallible>(event);
+                    }
+                }
+            }
+            None => yield Ok(resync_event(seq)),
+        }
+        let mut lagged = false;
+        loop {
+            match rx.recv().await {
+                Ok((seq, update)) => {
+                    // Tell the client what it missed before going on
+                    if std::mem::take(&mut lagged) {
+                        yield Ok(resync_event(seq - 1));
+                    }
+                    if let Some(event) = update_event(seq, &update) {
+                        yield Ok(event);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Session SSE stream lagged by {} updates", n);
+                    lagged = true;
+                }
+                // The session was closed
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+
+    Sse::new(stream)
+        .keep_alive(
+            KeepAlive::new()
+                .interval(Duration::from_secs(15))
+                .text("keep-alive"),
+        )
+        .into_response()
+}
+
+/// Post an action to a scoped session. Prompts sent while one is running
+/// are queued, answered with `202 Accepted` and a `prompt_queued` update.
+#[utoipa::path(
+    post,
+    path = "/session/{id}/action",
+    tag = "sessions",
+    params(("id" = String, Path, description = "Session ID")),
+    request_body = Action,
+    responses(
+        (status = 200, description = "The action was sent to the session"),
+        (status = 202, description = "The prompt was queued behind the running one"),
+        (status = 404, description = "No such session"),
+        (status = 500, description = "The session's runner is not running")
+    )
+)]
+async fn session_action(
+    State(state): State<HeadlessState>,
+    Path(id): Path<String>,
+    Json(action): Json<Action>,
+) -> impl IntoResponse {
+    debug!(session_id = %id, endpoint = action.endpoint(), "Received scoped session action");
+    let mut sessions = state.lock_sessions();
+    let Some(session) = sessions.get_mut(&id) else {
+        return StatusCode::NOT_FOUND;
+    };
+    match session.dispatch(action) {
+        Ok(None) => StatusCode::OK,
+        Ok(Some(position)) => {
+            session.send_update(Update::PromptQueued { position });
+            StatusCode::ACCEPTED
+        }
+        Err(()) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+// ============================================================================
+// Jobs
+// ============================================================================
+
+fn job_error(status: StatusCode, error: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (status, Json(serde_json::json!({ "error": error })))
+}
+
+/// List the jobs, oldest first.
+#[utoipa::path(
+    get,
+    path = "/jobs",
+    tag = "jobs",
+    responses((status = 200, description = "The jobs", body = Vec<Job>))
+)]
+async fn list_jobs(State(state): State<HeadlessState>) -> impl IntoResponse {
+    Json(state.jobs.list())
+}
+
+/// Queue a prompt as a job. Jobs without a session run one after another,
+/// each in a fresh scoped session; those with one run in order in it.
+#[utoipa::path(
+    post,
+    path = "/jobs",
+    tag = "jobs",
+    request_body = JobRequest,
+    responses(
+        (status = 201, description = "The job was queued", body = Job),
+        (status = 400, description = "The prompt is empty"),
+        (status = 404, description = "No such session"),
+        (status = 501, description = "This server doesn't run scoped sessions")
+    )
+)]
+async fn create_job(
+    State(state): State<HeadlessState>,
+    Json(request): Json<JobRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if state.session_host.is_none() {
+        return Err(job_error(
+            StatusCode::NOT_IMPLEMENTED,
+            "This server doesn't run scoped sessions",
+        ));
+    }
+    if request.prompt.trim().is_empty() {
+        return Err(job_error(StatusCode::BAD_REQUEST, "The prompt is empty"));
+    }
+    if let Some(session) = &request.session {
+        if !state.lock_sessions().contains_key(session) {
+            return Err(job_error(StatusCode::NOT_FOUND, "No such session"));
+        }
+    }
+
+    let job = state.jobs.create(request);
+    info!(job_id = %job.id, "Queued job");
+    state.enqueue_job(job.clone());
+    Ok((StatusCode::CREATED, Json(job)))
+}
+
+/// Get a job and, once it has finished, its result.
+#[utoipa::path(
+    get,
+    path = "/jobs/{id}",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Job ID")),
+    responses(
+        (status = 200, description = "The job", body = Job),
+        (status = 404, description = "No such job")
+    )
+)]
+async fn get_job(
+    State(state): State<HeadlessState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    state
+        .jobs
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, "No such job"))
+}
+
+/// Cancel a job, stopping its prompt if it is running.
+#[utoipa::path(
+    post,
+    path = "/jobs/{id}/cancel",
+    tag = "jobs",
+    params(("id" = String, Path, description = "Job ID")),
+    responses(
+        (status = 200, description = "The job was cancelled", body = Job),
+        (status = 404, description = "No such job"),
+        (status = 409, description = "The job has already finished")
+    )
+)]
+async fn cancel_job(
+    State(state): State<HeadlessState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let Some(job) = state.jobs.cancel(&id) else {
+        return Err(match state.jobs.get(&id) {
+            Some(_) => job_error(StatusCode::CONFLICT, "The job has already finished"),
+            None => job_error(StatusCode::NOT_FOUND, "No such job"),
+        });
+    };
+    if let (Some(session), Some(_)) = (&job.session, &job.started_at) {
+        if let Some(session) = state.lock_sessions().get_mut(session) {
+            let _ = session.dispatch(Action::Cancel);
+        }
+    }
+    info!(job_id = %id, "Cancelled job");
+    Ok(Json(job))
+}
+
+// ============================================================================
+// WebSocket Transport
+// ============================================================================
+
+/// Actions and updates over one connection, resuming where the client left
+/// off after a reconnect.
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "server",
+    responses(
+        (
+            status = 101,
+            description = "Switched to the WebSocket protocol; see `ClientFrame` and `ServerFrame`"
+        )
+    )
+)]
+async fn socket(ws: WebSocketUpgrade, State(state): State<HeadlessState>) -> impl IntoResponse {
+    ws.on_upgrade(|socket| handle_socket(socket, state))
+}
+
+/// Send a frame, returning false once the connection is gone.
+async fn send_frame(sender: &mut SplitSink<WebSocket, Message>, frame: &ServerFrame) -> bool {
+    match serde_json::to_string(frame) {
+        Ok(json) => sender.send(Message::Text(json.into())).await.is_ok(),
+        Err(_) => true,
+    }
+}
+
+#[allow(clippy::cognitive_complexity)]
+async fn handle_socket(socket: WebSocket, state: HeadlessState) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // The first frame says which update the client saw last
+    let (last_seq, name) = match tokio::time::timeout(HEARTBEAT_TIMEOUT, receiver.next()).await {
+        Ok(Some(Ok(Message::Text(text)))) => match serde_json::from_str(&text) {
+            Ok(ClientFrame::Resume { last_seq, name }) => (last_seq, name),
+            _ => {
+                let message = "Expected a resume frame".to_string();
+                send_frame(&mut sender, &ServerFrame::Error { message }).await;
+                return;
+            }
+        },
+        _ => return,
+    };
+
+    let Resumption {
+        seq,
+        replay,
+        mut rx,
+    } = state.resume(last_seq);
+    let complete = replay.is_some();
+    debug!(?last_seq, seq, complete, "WebSocket client resumed");
+    if !send_frame(&mut sender, &ServerFrame::Resumed { seq, complete }).await {
+        return;
+    }
+    for (seq, update) in replay.unwrap_or_default() {
+        if !send_frame(&mut sender, &ServerFrame::Update { seq, update }).await {
+            return;
+        }
+    }
+
+    // Join the shared session, if there is one
+    let participant = state.presence.as_ref().map(|presence| {
+        let name: String = name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or("guest")
+            .chars()
+            .take(MAX_PARTICIPANT_NAME)
+            .collect();
+        presence.join(&name)
+    });
+    if let Some(participant) = &participant {
+        debug!(participant = %participant.id, role = ?participant.role, "Participant joined");
+        let joined = ServerFrame::Joined {
+            participant: participant.id.clone(),
+        };
+        let sent = send_frame(&mut sender, &joined).await;
+        state.send_presence();
+        if !sent {
+            leave(&state, participant);
+            return;
+        }
+    }
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    let mut last_heard = tokio::time::Instant::now();
+    loop {
+        tokio::select! {
+            msg = receiver.next() => {
+                last_heard = tokio::time::Instant::now();
+                let frame = match msg {
+                    Some(Ok(Message::Text(text))) => serde_json::from_str::<ClientFrame>(&text),
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let reply = match frame {
+                    Ok(ClientFrame::Action { action }) => {
+                        debug!(endpoint = action.endpoint(), "Received action over WebSocket");
+                        state
+                            .socket_action(participant.as_ref(), action)
+                            .err()
+                            .map(|message| ServerFrame::Error { message })
+                    }
+                    Ok(ClientFrame::TakeControl) => match (&state.presence, &participant) {
+                        (Some(presence), Some(participant)) => {
+                            presence.take_control(&participant.id);
+                            state.send_presence();
+                            None
+                        }
+                        _ => Some(ServerFrame::Error {
+                            message: "The session is not shared".to_string(),
+                        }),
+                    },
+                    Ok(ClientFrame::Ping) => Some(ServerFrame::Pong),
+                    Ok(ClientFrame::Resume { .. }) => Some(ServerFrame::Error {
+                        message: "Already resumed".to_string(),
+                    }),
+                    Err(e) => Some(ServerFrame::Error {
+                        message: format!("Invalid frame: {e}"),
+                    }),
+                };
+                if let Some(reply) = reply {
+                    if !send_frame(&mut sender, &reply).await {
+                        break;
+                    }
+                }
+            }
+            result = rx.recv() => match result {
+                Ok((seq, update)) => {
+                    if !send_frame(&mut sender, &ServerFrame::Update { seq, update }).await {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    // Close rather than skip: the client resumes from the
+                    // last update it got, and the missed ones are replayed.
+                    warn!("WebSocket client lagged by {} updates, closing", n);
+                    break;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = heartbeat.tick() => {
+                if last_heard.elapsed() > HEARTBEAT_TIMEOUT {
+                    debug!("WebSocket client stopped responding");
+                    break;
+                }
+                if sender.send(Message::Ping(Default::default())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(participant) = &participant {
+        leave(&state, participant);
+    }
+    let _ = sender.close().await;
+    debug!("WebSocket connection closed");
+}
+
+/// Remove a participant from the shared session.
+fn leave(state: &HeadlessState, participant: &Participant) {
+    if let Some(presence) = &state.presence {
+        presence.leave(&participant.id);
+        debug!(participant = %participant.id, "Participant left");
+        state.send_presence();
+    }
+}
+
+// ============================================================================
+// Action Endpoints
+// ============================================================================
+
+#[derive(Deserialize, ToSchema)]
+struct PromptRequest {
+    prompt: String,
+}
+
+/// Send a prompt to the AI.
+#[utoipa::path(
+    post,
+    path = "/action/prompt",
+    tag = "actions",
+    request_body = PromptRequest,
+    responses(ActionResponses)
+)]
+async fn action_prompt(
+    State(state): State<HeadlessState>,
+    Json(req): Json<PromptRequest>,
+) -> impl IntoResponse {
+    debug!(prompt = %req.prompt, "Received prompt action");
+    match state
+        .action_tx
+        .send(Action::SendPrompt { prompt: req.prompt })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ImageRequest {
+    media_type: String,
+    data: String,
+}
+
+/// Attach a base64 encoded image to the next prompt.
+#[utoipa::path(
+    post,
+    path = "/action/image",
+    tag = "actions",
+    request_body = ImageRequest,
+    responses(ActionResponses)
+)]
+async fn action_image(
+    State(state): State<HeadlessState>,
+    Json(req): Json<ImageRequest>,
+) -> impl IntoResponse {
+    debug!(media_type = %req.media_type, bytes = req.data.len(), "Received image action");
+    match state.action_tx.send(Action::AttachImage {
+        media_type: req.media_type,
+        data: req.data,
+    }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ContextRequest {
+    path: String,
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    lines: Option<(u32, u32)>,
+}
+
+/// Pin a file, or the lines of a symbol in it, to the next prompt.
+#[utoipa::path(
+    post,
+    path = "/action/context",
+    tag = "actions",
+    request_body = ContextRequest,
+    responses(ActionResponses)
+)]
+async fn action_context(
+    State(state): State<HeadlessState>,
+    Json(req): Json<ContextRequest>,
+) -> impl IntoResponse {
+    debug!(path = %req.path, "Received context action");
+    match state.action_tx.send(Action::AttachContext {
+        path: req.path,
+        symbol: req.symbol,
+        lines: req.lines,
+    }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SymbolsRequest {
+    query: String,
+}
+
+/// Search the language servers' workspace symbols.
+#[utoipa::path(
+    post,
+    path = "/action/symbols",
+    tag = "actions",
+    request_body = SymbolsRequest,
+    responses(ActionResponses)
+)]
+async fn action_symbols(
+    State(state): State<HeadlessState>,
+    Json(req): Json<SymbolsRequest>,
+) -> impl IntoResponse {
+    debug!(query = %req.query, "Received symbols action");
+    match state
+        .action_tx
+        .send(Action::SearchSymbols { query: req.query })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Cancel the current operation.
+#[utoipa::path(
+    post,
+    path = "/action/cancel",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_cancel(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received cancel action");
+    match state.action_tx.send(Action::Cancel) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ModelRequest {
+    model: String,
+}
+
+/// Change the model.
+#[utoipa::path(
+    post,
+    path = "/action/model",
+    tag = "actions",
+    request_body = ModelRequest,
+    responses(ActionResponses)
+)]
+async fn action_model(
     State(state): State<HeadlessState>,
     Json(req): Json<ModelRequest>,
 ) -> impl IntoResponse {
@@ -406,11 +1967,19 @@ async fn action_model(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct AgentRequest {
     agent: String,
 }
 
+/// Change the agent.
+#[utoipa::path(
+    post,
+    path = "/action/agent",
+    tag = "actions",
+    request_body = AgentRequest,
+    responses(ActionResponses)
+)]
 async fn action_agent(
     State(state): State<HeadlessState>,
     Json(req): Json<AgentRequest>,
@@ -425,6 +1994,13 @@ async fn action_agent(
     }
 }
 
+/// Create a new session.
+#[utoipa::path(
+    post,
+    path = "/action/session/new",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_session_new(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received new session action");
     match state.action_tx.send(Action::NewSession) {
@@ -433,11 +2009,19 @@ async fn action_session_new(State(state): State<HeadlessState>) -> impl IntoResp
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct SessionSwitchRequest {
     session_id: String,
 }
 
+/// Switch to a different session.
+#[utoipa::path(
+    post,
+    path = "/action/session/switch",
+    tag = "actions",
+    request_body = SessionSwitchRequest,
+    responses(ActionResponses)
+)]
 async fn action_session_switch(
     State(state): State<HeadlessState>,
     Json(req): Json<SessionSwitchRequest>,
@@ -451,11 +2035,19 @@ async fn action_session_switch(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct SessionRenameRequest {
     title: String,
 }
 
+/// Rename the current session.
+#[utoipa::path(
+    post,
+    path = "/action/session/rename",
+    tag = "actions",
+    request_body = SessionRenameRequest,
+    responses(ActionResponses)
+)]
 async fn action_session_rename(
     State(state): State<HeadlessState>,
     Json(req): Json<SessionRenameRequest>,
@@ -470,11 +2062,19 @@ async fn action_session_rename(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct SessionForkRequest {
     message_id: Option<String>,
 }
 
+/// Fork the session from a specific message.
+#[utoipa::path(
+    post,
+    path = "/action/session/fork",
+    tag = "actions",
+    request_body = SessionForkRequest,
+    responses(ActionResponses)
+)]
 async fn action_session_fork(
     State(state): State<HeadlessState>,
     Json(req): Json<SessionForkRequest>,
@@ -488,14 +2088,212 @@ async fn action_session_fork(
     }
 }
 
-async fn action_session_share(State(state): State<HeadlessState>) -> impl IntoResponse {
+/// Merge the current branch into its parent session.
+#[utoipa::path(
+    post,
+    path = "/action/session/merge",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_session_merge(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received session merge action");
+    match state.action_tx.send(Action::MergeSession) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct SessionSearchRequest {
+    query: String,
+}
+
+/// Search sessions by title, tags and message text.
+#[utoipa::path(
+    post,
+    path = "/action/session/search",
+    tag = "actions",
+    request_body = SessionSearchRequest,
+    responses(ActionResponses)
+)]
+async fn action_session_search(
+    State(state): State<HeadlessState>,
+    Json(req): Json<SessionSearchRequest>,
+) -> impl IntoResponse {
+    debug!(query = %req.query, "Received session search action");
+    match state
+        .action_tx
+        .send(Action::SearchSessions { query: req.query })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct OrchestrateRequest {
+    tasks: Vec<String>,
+}
+
+/// Run agents on separate tasks in their own git worktrees.
+#[utoipa::path(
+    post,
+    path = "/action/orchestration/start",
+    tag = "actions",
+    request_body = OrchestrateRequest,
+    responses(ActionResponses)
+)]
+async fn action_orchestrate(
+    State(state): State<HeadlessState>,
+    Json(req): Json<OrchestrateRequest>,
+) -> impl IntoResponse {
+    debug!(tasks = req.tasks.len(), "Received orchestrate action");
+    match state
+        .action_tx
+        .send(Action::Orchestrate { tasks: req.tasks })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Merge the branches of finished orchestrated agents.
+#[utoipa::path(
+    post,
+    path = "/action/orchestration/merge",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_orchestration_merge(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received orchestration merge action");
+    match state.action_tx.send(Action::MergeOrchestration) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Cancel the running orchestration.
+#[utoipa::path(
+    post,
+    path = "/action/orchestration/cancel",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_orchestration_cancel(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received orchestration cancel action");
+    match state.action_tx.send(Action::CancelOrchestration) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct WorktreeCreateRequest {
+    name: Option<String>,
+}
+
+/// Move the session into its own git worktree and branch.
+#[utoipa::path(
+    post,
+    path = "/action/worktree/create",
+    tag = "actions",
+    request_body = WorktreeCreateRequest,
+    responses(ActionResponses)
+)]
+async fn action_worktree_create(
+    State(state): State<HeadlessState>,
+    Json(req): Json<WorktreeCreateRequest>,
+) -> impl IntoResponse {
+    debug!(name = ?req.name, "Received worktree create action");
+    match state
+        .action_tx
+        .send(Action::CreateWorktree { name: req.name })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// List the git worktrees of the project.
+#[utoipa::path(
+    post,
+    path = "/action/worktree/list",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_worktree_list(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received worktree list action");
+    match state.action_tx.send(Action::ListWorktrees) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct WorktreeFinishRequest {
+    action: String,
+}
+
+/// Finish the session's worktree: `merge`, `pr` or `discard`.
+#[utoipa::path(
+    post,
+    path = "/action/worktree/finish",
+    tag = "actions",
+    request_body = WorktreeFinishRequest,
+    responses(ActionResponses)
+)]
+async fn action_worktree_finish(
+    State(state): State<HeadlessState>,
+    Json(req): Json<WorktreeFinishRequest>,
+) -> impl IntoResponse {
+    debug!(action = %req.action, "Received worktree finish action");
+    match state
+        .action_tx
+        .send(Action::FinishWorktree { action: req.action })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+struct ShareActionRequest {
+    #[serde(default)]
+    expires: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+/// Share the current session.
+#[utoipa::path(
+    post,
+    path = "/action/session/share",
+    tag = "actions",
+    request_body = ShareActionRequest,
+    responses(ActionResponses)
+)]
+async fn action_session_share(
+    State(state): State<HeadlessState>,
+    req: Option<Json<ShareActionRequest>>,
+) -> impl IntoResponse {
     debug!("Received session share action");
-    match state.action_tx.send(Action::ShareSession) {
+    let Json(req) = req.unwrap_or_default();
+    match state.action_tx.send(Action::ShareSession {
+        expires: req.expires,
+        password: req.password,
+    }) {
         Ok(_) => StatusCode::OK,
         Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
 
+/// Unshare the current session.
+#[utoipa::path(
+    post,
+    path = "/action/session/unshare",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_session_unshare(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received session unshare action");
     match state.action_tx.send(Action::UnshareSession) {
@@ -504,6 +2302,13 @@ async fn action_session_unshare(State(state): State<HeadlessState>) -> impl Into
     }
 }
 
+/// Undo the last message.
+#[utoipa::path(
+    post,
+    path = "/action/undo",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_undo(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received undo action");
     match state.action_tx.send(Action::Undo) {
@@ -512,6 +2317,13 @@ async fn action_undo(State(state): State<HeadlessState>) -> impl IntoResponse {
     }
 }
 
+/// Redo an undone message.
+#[utoipa::path(
+    post,
+    path = "/action/redo",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_redo(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received redo action");
     match state.action_tx.send(Action::Redo) {
@@ -520,11 +2332,19 @@ async fn action_redo(State(state): State<HeadlessState>) -> impl IntoResponse {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct RevertRequest {
     message_id: String,
 }
 
+/// Revert to a specific message.
+#[utoipa::path(
+    post,
+    path = "/action/revert",
+    tag = "actions",
+    request_body = RevertRequest,
+    responses(ActionResponses)
+)]
 async fn action_revert(
     State(state): State<HeadlessState>,
     Json(req): Json<RevertRequest>,
@@ -538,6 +2358,13 @@ async fn action_revert(
     }
 }
 
+/// Cancel a pending revert.
+#[utoipa::path(
+    post,
+    path = "/action/unrevert",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_unrevert(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received unrevert action");
     match state.action_tx.send(Action::Unrevert) {
@@ -546,6 +2373,13 @@ async fn action_unrevert(State(state): State<HeadlessState>) -> impl IntoRespons
     }
 }
 
+/// Compact the conversation.
+#[utoipa::path(
+    post,
+    path = "/action/compact",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_compact(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received compact action");
     match state.action_tx.send(Action::Compact) {
@@ -554,6 +2388,13 @@ async fn action_compact(State(state): State<HeadlessState>) -> impl IntoResponse
     }
 }
 
+/// Start the sandbox.
+#[utoipa::path(
+    post,
+    path = "/action/sandbox/start",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_sandbox_start(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received sandbox start action");
     match state.action_tx.send(Action::SandboxStart) {
@@ -562,6 +2403,13 @@ async fn action_sandbox_start(State(state): State<HeadlessState>) -> impl IntoRe
     }
 }
 
+/// Stop the sandbox.
+#[utoipa::path(
+    post,
+    path = "/action/sandbox/stop",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_sandbox_stop(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received sandbox stop action");
     match state.action_tx.send(Action::SandboxStop) {
@@ -570,6 +2418,13 @@ async fn action_sandbox_stop(State(state): State<HeadlessState>) -> impl IntoRes
     }
 }
 
+/// Restart the sandbox.
+#[utoipa::path(
+    post,
+    path = "/action/sandbox/restart",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_sandbox_restart(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received sandbox restart action");
     match state.action_tx.send(Action::SandboxRestart) {
@@ -578,27 +2433,201 @@ async fn action_sandbox_restart(State(state): State<HeadlessState>) -> impl Into
     }
 }
 
-#[derive(Deserialize)]
-struct McpToggleRequest {
-    name: String,
+#[derive(Deserialize, ToSchema)]
+struct ProfileRequest {
+    profile: String,
 }
 
-async fn action_mcp_toggle(
+/// Switch to a named config profile.
+#[utoipa::path(
+    post,
+    path = "/action/profile",
+    tag = "actions",
+    request_body = ProfileRequest,
+    responses(ActionResponses)
+)]
+async fn action_profile(
     State(state): State<HeadlessState>,
-    Json(req): Json<McpToggleRequest>,
+    Json(req): Json<ProfileRequest>,
 ) -> impl IntoResponse {
-    debug!(name = %req.name, "Received MCP toggle action");
-    match state.action_tx.send(Action::McpToggle { name: req.name }) {
+    debug!(profile = %req.profile, "Received profile switch action");
+    match state.action_tx.send(Action::SwitchProfile {
+        profile: req.profile,
+    }) {
         Ok(_) => StatusCode::OK,
         Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
     }
 }
 
-#[derive(Deserialize)]
-struct McpReconnectRequest {
-    name: String,
-}
+/// List the projects the server manages.
+#[utoipa::path(
+    post,
+    path = "/action/projects",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_projects(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received project list action");
+    match state.action_tx.send(Action::ListProjects) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ProjectRequest {
+    project: String,
+}
+
+/// Send further actions to another project (path or name).
+#[utoipa::path(
+    post,
+    path = "/action/project/switch",
+    tag = "actions",
+    request_body = ProjectRequest,
+    responses(ActionResponses)
+)]
+async fn action_project_switch(
+    State(state): State<HeadlessState>,
+    Json(req): Json<ProjectRequest>,
+) -> impl IntoResponse {
+    debug!(project = %req.project, "Received project switch action");
+    match state.action_tx.send(Action::SwitchProject {
+        project: req.project,
+    }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct TemplateRequest {
+    #[serde(default)]
+    name: String,
+}
+
+/// Start the session from a template (empty name lists templates).
+#[utoipa::path(
+    post,
+    path = "/action/template",
+    tag = "actions",
+    request_body = TemplateRequest,
+    responses(ActionResponses)
+)]
+async fn action_template(
+    State(state): State<HeadlessState>,
+    Json(req): Json<TemplateRequest>,
+) -> impl IntoResponse {
+    debug!(name = %req.name, "Received template apply action");
+    match state
+        .action_tx
+        .send(Action::ApplyTemplate { name: req.name })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Save the current session's setup as a template.
+#[utoipa::path(
+    post,
+    path = "/action/template/save",
+    tag = "actions",
+    request_body = TemplateRequest,
+    responses(ActionResponses)
+)]
+async fn action_template_save(
+    State(state): State<HeadlessState>,
+    Json(req): Json<TemplateRequest>,
+) -> impl IntoResponse {
+    debug!(name = %req.name, "Received template save action");
+    match state
+        .action_tx
+        .send(Action::SaveTemplate { name: req.name })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Request the project memory contents.
+#[utoipa::path(
+    post,
+    path = "/action/memory",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_memory(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received memory load action");
+    match state.action_tx.send(Action::LoadMemory) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct MemorySaveRequest {
+    content: String,
+}
+
+/// Replace the project memory contents.
+#[utoipa::path(
+    post,
+    path = "/action/memory/save",
+    tag = "actions",
+    request_body = MemorySaveRequest,
+    responses(ActionResponses)
+)]
+async fn action_memory_save(
+    State(state): State<HeadlessState>,
+    Json(req): Json<MemorySaveRequest>,
+) -> impl IntoResponse {
+    debug!(len = req.content.len(), "Received memory save action");
+    match state.action_tx.send(Action::SaveMemory {
+        content: req.content,
+    }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct McpToggleRequest {
+    name: String,
+}
+
+/// Toggle an MCP server.
+#[utoipa::path(
+    post,
+    path = "/action/mcp/toggle",
+    tag = "actions",
+    request_body = McpToggleRequest,
+    responses(ActionResponses)
+)]
+async fn action_mcp_toggle(
+    State(state): State<HeadlessState>,
+    Json(req): Json<McpToggleRequest>,
+) -> impl IntoResponse {
+    debug!(name = %req.name, "Received MCP toggle action");
+    match state.action_tx.send(Action::McpToggle { name: req.name }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct McpReconnectRequest {
+    name: String,
+}
 
+/// Reconnect an MCP server.
+#[utoipa::path(
+    post,
+    path = "/action/mcp/reconnect",
+    tag = "actions",
+    request_body = McpReconnectRequest,
+    responses(ActionResponses)
+)]
 async fn action_mcp_reconnect(
     State(state): State<HeadlessState>,
     Json(req): Json<McpReconnectRequest>,
@@ -613,11 +2642,19 @@ async fn action_mcp_reconnect(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct GotoRequest {
     message_id: String,
 }
 
+/// Go to a specific message.
+#[utoipa::path(
+    post,
+    path = "/action/goto",
+    tag = "actions",
+    request_body = GotoRequest,
+    responses(ActionResponses)
+)]
 async fn action_goto(
     State(state): State<HeadlessState>,
     Json(req): Json<GotoRequest>,
@@ -631,12 +2668,20 @@ async fn action_goto(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct SettingsRequest {
     scope: wonopcode_protocol::SaveScope,
     config: serde_json::Value,
 }
 
+/// Save settings.
+#[utoipa::path(
+    post,
+    path = "/action/settings",
+    tag = "actions",
+    request_body = SettingsRequest,
+    responses(ActionResponses)
+)]
 async fn action_settings(
     State(state): State<HeadlessState>,
     Json(req): Json<SettingsRequest>,
@@ -651,13 +2696,21 @@ async fn action_settings(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct PermissionRequest {
     request_id: String,
     allow: bool,
     remember: bool,
 }
 
+/// Respond to a permission request.
+#[utoipa::path(
+    post,
+    path = "/action/permission",
+    tag = "actions",
+    request_body = PermissionRequest,
+    responses(ActionResponses)
+)]
 async fn action_permission(
     State(state): State<HeadlessState>,
     Json(req): Json<PermissionRequest>,
@@ -673,6 +2726,172 @@ async fn action_permission(
     }
 }
 
+/// Load the recent permission decisions of the project.
+#[utoipa::path(
+    post,
+    path = "/action/permission/audit",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_permission_audit(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received permission audit action");
+    match state.action_tx.send(Action::LoadPermissionAudit) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Show spend against the configured alerts and limits.
+#[utoipa::path(
+    post,
+    path = "/action/spend",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_spend(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received spend action");
+    match state.action_tx.send(Action::LoadSpend) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct UsageRequest {
+    days: u32,
+}
+
+/// Load usage over the last days for the usage dashboard.
+#[utoipa::path(
+    post,
+    path = "/action/usage",
+    tag = "actions",
+    request_body = UsageRequest,
+    responses(ActionResponses)
+)]
+async fn action_usage(
+    State(state): State<HeadlessState>,
+    Json(req): Json<UsageRequest>,
+) -> impl IntoResponse {
+    debug!(days = req.days, "Received usage action");
+    match state.action_tx.send(Action::LoadUsage { days: req.days }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Acknowledge reached spending limits so prompts can run again.
+#[utoipa::path(
+    post,
+    path = "/action/spend/ack",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_spend_ack(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received spend acknowledge action");
+    match state.action_tx.send(Action::AcknowledgeSpendLimit) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Load the session's file changes for review.
+#[utoipa::path(
+    post,
+    path = "/action/review",
+    tag = "actions",
+    responses(ActionResponses)
+)]
+async fn action_review(State(state): State<HeadlessState>) -> impl IntoResponse {
+    debug!("Received review action");
+    match state.action_tx.send(Action::StartReview) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ReviewApplyRequest {
+    files: Vec<wonopcode_protocol::ReviewedFileInfo>,
+}
+
+/// Apply the decisions of a review.
+#[utoipa::path(
+    post,
+    path = "/action/review/apply",
+    tag = "actions",
+    request_body = ReviewApplyRequest,
+    responses(ActionResponses)
+)]
+async fn action_review_apply(
+    State(state): State<HeadlessState>,
+    Json(req): Json<ReviewApplyRequest>,
+) -> impl IntoResponse {
+    debug!(files = req.files.len(), "Received review apply action");
+    match state
+        .action_tx
+        .send(Action::ApplyReview { files: req.files })
+    {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ProcessStopRequest {
+    pid: u32,
+}
+
+/// Stop a background process started by the bash tool.
+#[utoipa::path(
+    post,
+    path = "/action/process/stop",
+    tag = "actions",
+    request_body = ProcessStopRequest,
+    responses(ActionResponses)
+)]
+async fn action_process_stop(
+    State(state): State<HeadlessState>,
+    Json(req): Json<ProcessStopRequest>,
+) -> impl IntoResponse {
+    debug!(pid = req.pid, "Received process stop action");
+    match state.action_tx.send(Action::StopProcess { pid: req.pid }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct ToolCancelRequest {
+    id: String,
+}
+
+/// Cancel a running tool call, leaving the rest of the prompt running.
+#[utoipa::path(
+    post,
+    path = "/action/tool/cancel",
+    tag = "actions",
+    request_body = ToolCancelRequest,
+    responses(ActionResponses)
+)]
+async fn action_tool_cancel(
+    State(state): State<HeadlessState>,
+    Json(req): Json<ToolCancelRequest>,
+) -> impl IntoResponse {
+    debug!(id = %req.id, "Received tool cancel action");
+    match state.action_tx.send(Action::CancelTool { id: req.id }) {
+        Ok(_) => StatusCode::OK,
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Request to quit (for graceful shutdown).
+#[utoipa::path(
+    post,
+    path = "/action/quit",
+    tag = "actions",
+    responses(ActionResponses)
+)]
 async fn action_quit(State(state): State<HeadlessState>) -> impl IntoResponse {
     debug!("Received quit action");
     *state.shutdown.write().await = true;
@@ -684,6 +2903,12 @@ async fn action_quit(State(state): State<HeadlessState>) -> impl IntoResponse {
 
 /// Shutdown endpoint - triggers graceful server shutdown.
 /// This is used by WARP to stop agents it has spawned.
+#[utoipa::path(
+    post,
+    path = "/action/shutdown",
+    tag = "actions",
+    responses((status = 200, description = "Shutdown was requested, as `{\"status\": ...}`"))
+)]
 async fn action_shutdown(State(state): State<HeadlessState>) -> impl IntoResponse {
     info!("Received shutdown request");
     *state.shutdown.write().await = true;
@@ -708,6 +2933,16 @@ async fn action_shutdown(State(state): State<HeadlessState>) -> impl IntoRespons
 // ============================================================================
 
 /// Get git repository status.
+#[utoipa::path(
+    get,
+    path = "/git/status",
+    tag = "git",
+    responses(
+        (status = 200, description = "Status of the working tree", body = GitStatus),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
 async fn git_status(
     State(state): State<HeadlessState>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
@@ -731,7 +2966,7 @@ async fn git_status(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct GitStageRequest {
     /// Paths to stage. If empty, stages all modified files.
     #[serde(default)]
@@ -739,6 +2974,17 @@ struct GitStageRequest {
 }
 
 /// Stage files in the git index.
+#[utoipa::path(
+    post,
+    path = "/git/stage",
+    tag = "git",
+    request_body = GitStageRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
 async fn git_stage(
     State(state): State<HeadlessState>,
     Json(req): Json<GitStageRequest>,
@@ -763,7 +3009,7 @@ async fn git_stage(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct GitUnstageRequest {
     /// Paths to unstage. If empty, unstages all staged files.
     #[serde(default)]
@@ -771,6 +3017,17 @@ struct GitUnstageRequest {
 }
 
 /// Unstage files from the git index.
+#[utoipa::path(
+    post,
+    path = "/git/unstage",
+    tag = "git",
+    request_body = GitUnstageRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
 async fn git_unstage(
     State(state): State<HeadlessState>,
     Json(req): Json<GitUnstageRequest>,
@@ -795,13 +3052,24 @@ async fn git_unstage(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct GitCheckoutRequest {
     /// Paths to checkout (discard changes). Required - must specify files.
     paths: Vec<String>,
 }
 
 /// Checkout (discard changes to) files.
+#[utoipa::path(
+    post,
+    path = "/git/checkout",
+    tag = "git",
+    request_body = GitCheckoutRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
 async fn git_checkout(
     State(state): State<HeadlessState>,
     Json(req): Json<GitCheckoutRequest>,
@@ -833,13 +3101,150 @@ async fn git_checkout(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
+struct GitDiffRequest {
+    /// File to diff. If absent, diffs all files.
+    #[serde(default)]
+    path: Option<String>,
+    /// Diff the staged changes instead of the unstaged ones.
+    #[serde(default)]
+    staged: bool,
+}
+
+/// Get changes as a unified diff.
+#[utoipa::path(
+    post,
+    path = "/git/diff",
+    tag = "git",
+    request_body = GitDiffRequest,
+    responses(
+        (status = 200, description = "The diff, as `{\"diff\": ...}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
+async fn git_diff(
+    State(state): State<HeadlessState>,
+    Json(req): Json<GitDiffRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let current = state.current_state.read().await;
+    let working_dir = &current.project;
+
+    if working_dir.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No working directory set" })),
+        ));
+    }
+
+    let ops = GitOperations::new(working_dir);
+    match ops.diff(req.path.as_deref(), req.staged) {
+        Ok(diff) => Ok(Json(serde_json::json!({ "diff": diff }))),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct GitHunkRequest {
+    /// File the hunk belongs to.
+    path: String,
+    /// Index of the hunk in the file's diff, from 0.
+    hunk: usize,
+}
+
+/// Stage one hunk of a file.
+#[utoipa::path(
+    post,
+    path = "/git/stage-hunk",
+    tag = "git",
+    request_body = GitHunkRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
+async fn git_stage_hunk(
+    State(state): State<HeadlessState>,
+    Json(req): Json<GitHunkRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let current = state.current_state.read().await;
+    let working_dir = &current.project;
+
+    if working_dir.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No working directory set" })),
+        ));
+    }
+
+    let ops = GitOperations::new(working_dir);
+    match ops.stage_hunk(&req.path, req.hunk) {
+        Ok(()) => Ok(Json(serde_json::json!({ "success": true }))),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+    }
+}
+
+/// Unstage one hunk of a file.
+#[utoipa::path(
+    post,
+    path = "/git/unstage-hunk",
+    tag = "git",
+    request_body = GitHunkRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
+async fn git_unstage_hunk(
+    State(state): State<HeadlessState>,
+    Json(req): Json<GitHunkRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let current = state.current_state.read().await;
+    let working_dir = &current.project;
+
+    if working_dir.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No working directory set" })),
+        ));
+    }
+
+    let ops = GitOperations::new(working_dir);
+    match ops.unstage_hunk(&req.path, req.hunk) {
+        Ok(()) => Ok(Json(serde_json::json!({ "success": true }))),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
 struct GitCommitRequest {
     /// Commit message.
     message: String,
 }
 
 /// Create a git commit.
+#[utoipa::path(
+    post,
+    path = "/git/commit",
+    tag = "git",
+    request_body = GitCommitRequest,
+    responses(
+        (status = 200, description = "The new commit, as `{\"success\": true, \"commit\": ...}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
 async fn git_commit(
     State(state): State<HeadlessState>,
     Json(req): Json<GitCommitRequest>,
@@ -874,7 +3279,8 @@ async fn git_commit(
     }
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GitHistoryQuery {
     /// Maximum number of commits to return (default: 50).
     #[serde(default = "default_history_limit")]
@@ -914,167 +3320,1294 @@ pub fn empty_commit_message_error() -> serde_json::Value {
     serde_json::json!({ "error": "Commit message cannot be empty" })
 }
 
-/// Create success response.
-pub fn success_response() -> serde_json::Value {
-    serde_json::json!({ "success": true })
-}
+/// Create success response.
+pub fn success_response() -> serde_json::Value {
+    serde_json::json!({ "success": true })
+}
+
+/// Create error response from error message.
+pub fn error_response(message: &str) -> serde_json::Value {
+    serde_json::json!({ "error": message })
+}
+
+/// Get default remote name for git operations.
+pub fn default_remote() -> String {
+    "origin".to_string()
+}
+
+/// Get health status response.
+pub fn health_response() -> serde_json::Value {
+    serde_json::json!({ "status": "ok" })
+}
+
+/// Get authentication required error.
+pub fn auth_required_error() -> serde_json::Value {
+    serde_json::json!({ "error": "Authentication required" })
+}
+
+/// Get invalid API key error.
+pub fn invalid_api_key_error() -> serde_json::Value {
+    serde_json::json!({ "error": "Invalid API key" })
+}
+
+/// Get git commit history.
+#[utoipa::path(
+    get,
+    path = "/git/history",
+    tag = "git",
+    params(GitHistoryQuery),
+    responses(
+        (status = 200, description = "Commits, newest first", body = Vec<GitCommitInfo>),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
+async fn git_history(
+    State(state): State<HeadlessState>,
+    Query(query): Query<GitHistoryQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let current = state.current_state.read().await;
+    let working_dir = &current.project;
+
+    if working_dir.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No working directory set" })),
+        ));
+    }
+
+    let ops = GitOperations::new(working_dir);
+    match ops.history(query.limit) {
+        Ok(history) => Ok(Json(history)),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+    }
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+struct GitPushRequest {
+    /// Remote name (default: "origin").
+    remote: Option<String>,
+    /// Branch name (default: current branch).
+    branch: Option<String>,
+}
+
+/// Push to remote.
+#[utoipa::path(
+    post,
+    path = "/git/push",
+    tag = "git",
+    request_body = GitPushRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
+async fn git_push(
+    State(state): State<HeadlessState>,
+    Json(req): Json<GitPushRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let current = state.current_state.read().await;
+    let working_dir = &current.project;
+
+    if working_dir.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No working directory set" })),
+        ));
+    }
+
+    let ops = GitOperations::new(working_dir);
+    match ops.push(req.remote.as_deref(), req.branch.as_deref()) {
+        Ok(()) => Ok(Json(serde_json::json!({ "success": true }))),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+    }
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+struct GitPullRequest {
+    /// Remote name (default: "origin").
+    remote: Option<String>,
+    /// Branch name (default: current branch).
+    branch: Option<String>,
+}
+
+/// Pull from remote.
+#[utoipa::path(
+    post,
+    path = "/git/pull",
+    tag = "git",
+    request_body = GitPullRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or an invalid request"),
+        (status = 500, description = "The git operation failed")
+    )
+)]
+async fn git_pull(
+    State(state): State<HeadlessState>,
+    Json(req): Json<GitPullRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let current = state.current_state.read().await;
+    let working_dir = &current.project;
+
+    if working_dir.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No working directory set" })),
+        ));
+    }
+
+    let ops = GitOperations::new(working_dir);
+    match ops.pull(req.remote.as_deref(), req.branch.as_deref()) {
+        Ok(()) => Ok(Json(serde_json::json!({ "success": true }))),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )),
+    }
+}
+
+// ============================================================================
+// Project File Endpoints
+// ============================================================================
+
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FilePathQuery {
+    /// Path relative to the project root.
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct FileWriteRequest {
+    /// Path relative to the project root. Its directory must exist.
+    path: String,
+    /// New content of the file.
+    content: String,
+}
+
+/// Access the files of the current project.
+async fn project_files(
+    state: &HeadlessState,
+) -> Result<ProjectFiles, (StatusCode, Json<serde_json::Value>)> {
+    let current = state.current_state.read().await;
+    if current.project.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "No working directory set" })),
+        ));
+    }
+    ProjectFiles::new(&current.project).map_err(file_error)
+}
+
+/// Answer a file error with a status matching its cause.
+fn file_error(error: FileError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match error {
+        FileError::OutsideProject(_) | FileError::Unlisted(_) => StatusCode::FORBIDDEN,
+        FileError::NotFound(_) => StatusCode::NOT_FOUND,
+        FileError::NotADirectory(_) | FileError::NotAFile(_) => StatusCode::BAD_REQUEST,
+        FileError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        FileError::Binary(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        FileError::Git(_) | FileError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        Json(serde_json::json!({ "error": error.to_string() })),
+    )
+}
+
+/// List a directory of the project, skipping hidden and ignored files.
+#[utoipa::path(
+    get,
+    path = "/files/list",
+    tag = "files",
+    params(FilePathQuery),
+    responses(
+        (status = 200, description = "Entries of the directory", body = FileListing),
+        (status = 400, description = "No working directory, or not a directory"),
+        (status = 403, description = "The path is outside the project"),
+        (status = 404, description = "The directory does not exist")
+    )
+)]
+async fn files_list(
+    State(state): State<HeadlessState>,
+    Query(query): Query<FilePathQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let files = project_files(&state).await?;
+    files.list(&query.path).map(Json).map_err(file_error)
+}
+
+/// Read a text file of the project.
+#[utoipa::path(
+    get,
+    path = "/files/read",
+    tag = "files",
+    params(FilePathQuery),
+    responses(
+        (status = 200, description = "Content of the file", body = FileContent),
+        (status = 400, description = "No working directory, or not a file"),
+        (status = 403, description = "The path is outside the project"),
+        (status = 404, description = "The file does not exist"),
+        (status = 413, description = "The file is too large to read"),
+        (status = 415, description = "The file is not a text file")
+    )
+)]
+async fn files_read(
+    State(state): State<HeadlessState>,
+    Query(query): Query<FilePathQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let files = project_files(&state).await?;
+    files.read(&query.path).map(Json).map_err(file_error)
+}
+
+/// Diff a file of the project against its last committed version.
+#[utoipa::path(
+    get,
+    path = "/files/diff",
+    tag = "files",
+    params(FilePathQuery),
+    responses(
+        (status = 200, description = "Changes to the file", body = FileDiff),
+        (status = 400, description = "No working directory, or not a file"),
+        (status = 403, description = "The path is outside the project"),
+        (status = 404, description = "The file does not exist"),
+        (status = 500, description = "The project is not a git repository")
+    )
+)]
+async fn files_diff(
+    State(state): State<HeadlessState>,
+    Query(query): Query<FilePathQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let files = project_files(&state).await?;
+    files.diff(&query.path).map(Json).map_err(file_error)
+}
+
+/// Write a file of the project. Refused unless the server allows file
+/// writes.
+#[utoipa::path(
+    post,
+    path = "/files/write",
+    tag = "files",
+    request_body = FileWriteRequest,
+    responses(
+        (status = 200, description = "Done, as `{\"success\": true}`"),
+        (status = 400, description = "No working directory, or not a file"),
+        (status = 403, description = "File writes are disabled, or the path is outside the project"),
+        (status = 404, description = "The directory does not exist")
+    )
+)]
+async fn files_write(
+    State(state): State<HeadlessState>,
+    Json(req): Json<FileWriteRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if !state.allow_file_writes {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "File writes are disabled on this server" })),
+        ));
+    }
+    let files = project_files(&state).await?;
+    files.write(&req.path, &req.content).map_err(file_error)?;
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use wonopcode_protocol::Role;
+
+    // === HeadlessState tests ===
+
+    #[tokio::test]
+    async fn test_headless_state_new() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx);
+
+        assert!(state.shutdown_tx.is_none());
+        let shutdown = state.shutdown.read().await;
+        assert!(!*shutdown);
+    }
+
+    #[tokio::test]
+    async fn test_headless_state_with_shutdown_tx() {
+        let (action_tx, _action_rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, _shutdown_rx) = mpsc::channel(1);
+
+        let state = HeadlessState::new(action_tx).with_shutdown_tx(shutdown_tx);
+        assert!(state.shutdown_tx.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_headless_state_send_update() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx);
+
+        // Subscribe before sending
+        let mut rx = state.update_tx.subscribe();
+
+        let update = Update::Started;
+        state.send_update(update);
+
+        // Should receive the update
+        let received = rx.try_recv();
+        assert!(received.is_ok());
+    }
+
+    #[test]
+    fn test_update_log_replays_missed_updates() {
+        let mut log = UpdateLog::default();
+        assert_eq!(log.since(0).unwrap().len(), 0);
+        for _ in 0..3 {
+            log.push(Update::Started);
+        }
+        let seqs = |updates: Vec<(u64, Update)>| {
+            updates.into_iter().map(|(seq, _)| seq).collect::<Vec<_>>()
+        };
+        assert_eq!(seqs(log.since(1).unwrap()), vec![2, 3]);
+        assert_eq!(seqs(log.since(3).unwrap()), Vec::<u64>::new());
+        // From before a server restart
+        assert!(log.since(4).is_none());
+
+        for _ in 0..REPLAY_BUFFER_SIZE {
+            log.push(Update::Started);
+        }
+        assert!(log.since(2).is_none());
+        assert_eq!(log.since(3).unwrap().len(), REPLAY_BUFFER_SIZE);
+    }
+
+    #[tokio::test]
+    async fn test_headless_state_resume() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx);
+        state.send_update(Update::Started);
+        state.send_update(Update::Completed {
+            text: "done".to_string(),
+        });
+
+        let mut resumption = state.resume(Some(1));
+        assert_eq!(resumption.seq, 2);
+        let replay = resumption.replay.unwrap();
+        assert_eq!(replay.len(), 1);
+        assert!(matches!(replay[0], (2, Update::Completed { .. })));
+
+        state.send_update(Update::Started);
+        assert!(matches!(resumption.rx.try_recv(), Ok((3, Update::Started))));
+
+        let fresh = state.resume(None);
+        assert_eq!(fresh.seq, 3);
+        assert!(fresh.replay.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_events_resume_after_last_event_id() {
+        use tower::ServiceExt;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx);
+        state.send_update(Update::Started);
+        state.send_update(Update::Completed {
+            text: "done".to_string(),
+        });
+        let router = create_headless_router(state);
+        let first_event = |last_event_id: &str| {
+            let request = Request::builder()
+                .uri("/events")
+                .header("last-event-id", last_event_id)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                let mut body = response.into_body().into_data_stream();
+                let chunk = body.next().await.unwrap().unwrap();
+                String::from_utf8(chunk.to_vec()).unwrap()
+            }
+        };
+
+        let event = first_event("1").await;
+        assert!(event.contains("event: completed\n"), "{event}");
+        assert!(event.contains("id: 2\n"), "{event}");
+
+        // From before a server restart
+        let event = first_event("9").await;
+        assert!(event.contains("event: resync\n"), "{event}");
+        assert!(event.contains("id: 2\n"), "{event}");
+    }
+
+    #[tokio::test]
+    async fn test_socket_sends_actions_and_resumes_updates() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (tx, mut action_rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        let router = create_headless_router(state.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        async fn next_frame<S>(socket: &mut S) -> ServerFrame
+        where
+            S: Stream<Item = Result<WsMessage, tokio_tungstenite::tungstenite::Error>> + Unpin,
+        {
+            loop {
+                if let WsMessage::Text(text) = socket.next().await.unwrap().unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+        }
+        let frame =
+            |frame: ClientFrame| WsMessage::Text(serde_json::to_string(&frame).unwrap().into());
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .unwrap();
+        socket
+            .send(frame(ClientFrame::Resume {
+                last_seq: None,
+                name: None,
+            }))
+            .await
+            .unwrap();
+        assert!(matches!(
+            next_frame(&mut socket).await,
+            ServerFrame::Resumed {
+                seq: 0,
+                complete: true
+            }
+        ));
+        socket
+            .send(frame(ClientFrame::Action {
+                action: Action::Cancel,
+            }))
+            .await
+            .unwrap();
+        assert!(matches!(action_rx.recv().await, Some(Action::Cancel)));
+        state.send_update(Update::Started);
+        assert!(matches!(
+            next_frame(&mut socket).await,
+            ServerFrame::Update { seq: 1, .. }
+        ));
+        drop(socket);
+
+        // Updates sent while the client was away are replayed on resume
+        state.send_update(Update::Completed {
+            text: "done".to_string(),
+        });
+        let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .unwrap();
+        socket
+            .send(frame(ClientFrame::Resume {
+                last_seq: Some(1),
+                name: None,
+            }))
+            .await
+            .unwrap();
+        assert!(matches!(
+            next_frame(&mut socket).await,
+            ServerFrame::Resumed {
+                seq: 2,
+                complete: true
+            }
+        ));
+        assert!(matches!(
+            next_frame(&mut socket).await,
+            ServerFrame::Update {
+                seq: 2,
+                update: Update::Completed { .. }
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shared_session_has_one_driver() {
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let (tx, mut action_rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx).with_collaboration(true);
+        assert!(state.capabilities().contains(&Capability::Collaboration));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        let router = create_headless_router(state.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        type Socket = tokio_tungstenite::WebSocketStream<
+            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+        >;
+        async fn next_frame(socket: &mut Socket) -> ServerFrame {
+            loop {
+                if let WsMessage::Text(text) = socket.next().await.unwrap().unwrap() {
+                    return serde_json::from_str(&text).unwrap();
+                }
+            }
+        }
+        // The roles in the next presence update
+        async fn next_roles(socket: &mut Socket) -> Vec<(String, Role)> {
+            loop {
+                if let ServerFrame::Update {
+                    update: Update::Presence { participants },
+                    ..
+                } = next_frame(socket).await
+                {
+                    return participants.into_iter().map(|p| (p.name, p.role)).collect();
+                }
+            }
+        }
+        let frame =
+            |frame: ClientFrame| WsMessage::Text(serde_json::to_string(&frame).unwrap().into());
+        let join = |name: &str| {
+            let url = url.clone();
+            let resume = frame(ClientFrame::Resume {
+                last_seq: None,
+                name: Some(name.to_string()),
+            });
+            async move {
+                let (mut socket, _) = tokio_tungstenite::connect_async(url.as_str())
+                    .await
+                    .unwrap();
+                socket.send(resume).await.unwrap();
+                assert!(matches!(
+                    next_frame(&mut socket).await,
+                    ServerFrame::Resumed { .. }
+                ));
+                let ServerFrame::Joined { participant } = next_frame(&mut socket).await else {
+                    panic!("expected a joined frame");
+                };
+                (socket, participant)
+            }
+        };
+        let prompt = |text: &str| {
+            frame(ClientFrame::Action {
+                action: Action::SendPrompt {
+                    prompt: text.to_string(),
+                },
+            })
+        };
+
+        let (mut ada, ada_id) = join("ada").await;
+        assert_eq!(
+            next_roles(&mut ada).await,
+            vec![("ada".into(), Role::Driver)]
+        );
+        let (mut bob, _) = join("bob").await;
+        let roles = vec![("ada".into(), Role::Driver), ("bob".into(), Role::Watcher)];
+        assert_eq!(next_roles(&mut bob).await, roles);
+        assert_eq!(next_roles(&mut ada).await, roles);
+
+        // The watcher can't act, and sees the driver's prompt
+        bob.send(prompt("from bob")).await.unwrap();
+        assert!(matches!(
+            next_frame(&mut bob).await,
+            ServerFrame::Error { .. }
+        ));
+        ada.send(prompt("from ada")).await.unwrap();
+        assert!(matches!(
+            action_rx.recv().await,
+            Some(Action::SendPrompt { prompt }) if prompt == "from ada"
+        ));
+        assert!(matches!(
+            next_frame(&mut bob).await,
+            ServerFrame::Update {
+                update: Update::Prompted { participant, name, .. },
+                ..
+            } if participant == ada_id && name == "ada"
+        ));
+
+        // Taking control makes the driver a watcher
+        bob.send(frame(ClientFrame::TakeControl)).await.unwrap();
+        let roles = vec![("ada".into(), Role::Watcher), ("bob".into(), Role::Driver)];
+        assert_eq!(next_roles(&mut bob).await, roles);
+        bob.send(prompt("from bob")).await.unwrap();
+        assert!(matches!(
+            action_rx.recv().await,
+            Some(Action::SendPrompt { prompt }) if prompt == "from bob"
+        ));
+
+        // When the driver leaves, the others take over
+        drop(bob);
+        assert_eq!(next_roles(&mut ada).await, roles);
+        assert_eq!(
+            next_roles(&mut ada).await,
+            vec![("ada".into(), Role::Driver)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watchers_cannot_act_over_http() {
+        use tower::ServiceExt;
+
+        let (tx, mut action_rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx).with_collaboration(true);
+        let presence = state.presence.clone().unwrap();
+        let driver = presence.join("ada");
+        let watcher = presence.join("bob");
+        let router = create_headless_router(state);
+        let status = |method: &str, uri: &str, participant: Option<&str>| {
+            let mut request = Request::builder().method(method).uri(uri);
+            if let Some(id) = participant {
+                request = request.header(PARTICIPANT_HEADER, id);
+            }
+            let request = request.body(axum::body::Body::empty()).unwrap();
+            let router = router.clone();
+            async move { router.oneshot(request).await.unwrap().status() }
+        };
+
+        assert_eq!(
+            status("POST", "/action/cancel", Some(&watcher.id)).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status("POST", "/action/cancel", None).await,
+            StatusCode::FORBIDDEN
+        );
+        assert!(action_rx.try_recv().is_err());
+        assert_eq!(
+            status("POST", "/action/cancel", Some(&driver.id)).await,
+            StatusCode::OK
+        );
+        assert!(matches!(action_rx.try_recv(), Ok(Action::Cancel)));
+
+        // Reads stay open to watchers
+        assert_eq!(
+            status("GET", "/jobs", Some(&watcher.id)).await,
+            StatusCode::OK
+        );
+    }
+
+    #[test]
+    fn test_scoped_session_queues_prompts() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut session = ScopedSession::new(tx);
+        let mut updates = session.update_tx.subscribe();
+        let prompt = |text: &str| Action::SendPrompt {
+            prompt: text.to_string(),
+        };
+
+        assert_eq!(session.dispatch(prompt("first")), Ok(None));
+        assert!(session.busy);
+        assert_eq!(session.dispatch(prompt("second")), Ok(Some(1)));
+        // Held back with the prompt it belongs to, unlike a cancel
+        let image = Action::AttachImage {
+            media_type: "image/png".to_string(),
+            data: String::new(),
+        };
+        assert_eq!(session.dispatch(image), Ok(None));
+        assert_eq!(session.dispatch(prompt("third")), Ok(Some(2)));
+        assert_eq!(session.dispatch(Action::Cancel), Ok(None));
+        assert_eq!(session.info("s").queued, 2);
+
+        let sent = |rx: &mut mpsc::UnboundedReceiver<Action>| {
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .map(|action| action.endpoint())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sent(&mut rx), vec!["/action/prompt", "/action/cancel"]);
+
+        // Updates other than the end of the prompt don't release the queue
+        session.publish(Update::Started);
+        assert!(sent(&mut rx).is_empty());
+        session.publish(Update::Error {
+            error: "Cancelled".to_string(),
+        });
+        assert_eq!(sent(&mut rx), vec!["/action/prompt"]);
+        assert!(session.busy);
+        session.publish(Update::Completed {
+            text: String::new(),
+        });
+        assert_eq!(sent(&mut rx), vec!["/action/image", "/action/prompt"]);
+        assert_eq!(session.info("s").queued, 0);
+        assert!(matches!(updates.try_recv(), Ok(Update::Started)));
+    }
 
-/// Create error response from error message.
-pub fn error_response(message: &str) -> serde_json::Value {
-    serde_json::json!({ "error": message })
-}
+    #[tokio::test]
+    async fn test_session_events_resume_after_last_event_id() {
+        use tower::ServiceExt;
 
-/// Get default remote name for git operations.
-pub fn default_remote() -> String {
-    "origin".to_string()
-}
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx);
+        let (action_tx, _actions) = mpsc::unbounded_channel();
+        state
+            .lock_sessions()
+            .insert("ses_1".to_string(), ScopedSession::new(action_tx));
+        state.send_session_update("ses_1", Update::Started);
+        state.send_session_update(
+            "ses_1",
+            Update::Completed {
+                text: "done".to_string(),
+            },
+        );
+        let router = create_headless_router(state.clone());
+        let uri = wonopcode_protocol::session_events_endpoint("ses_1");
+        let open = |last_event_id: Option<&str>| {
+            let mut request = Request::builder().uri(&uri);
+            if let Some(id) = last_event_id {
+                request = request.header("last-event-id", id);
+            }
+            let request = request.body(axum::body::Body::empty()).unwrap();
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(request).await.unwrap();
+                response.into_body().into_data_stream()
+            }
+        };
+        async fn next_event(body: &mut axum::body::BodyDataStream) -> String {
+            let chunk = body.next().await.unwrap().unwrap();
+            String::from_utf8(chunk.to_vec()).unwrap()
+        }
 
-/// Get health status response.
-pub fn health_response() -> serde_json::Value {
-    serde_json::json!({ "status": "ok" })
-}
+        let event = next_event(&mut open(Some("1")).await).await;
+        assert!(event.contains("event: completed\n"), "{event}");
+        assert!(event.contains("id: 2\n"), "{event}");
 
-/// Get authentication required error.
-pub fn auth_required_error() -> serde_json::Value {
-    serde_json::json!({ "error": "Authentication required" })
-}
+        // From before a server restart
+        let event = next_event(&mut open(Some("9")).await).await;
+        assert!(event.contains("event: resync\n"), "{event}");
+        assert!(event.contains("id: 2\n"), "{event}");
 
-/// Get invalid API key error.
-pub fn invalid_api_key_error() -> serde_json::Value {
-    serde_json::json!({ "error": "Invalid API key" })
-}
+        // A client that falls behind is told to resync
+        let mut body = open(None).await;
+        for _ in 0..300 {
+            state.send_session_update("ses_1", Update::Started);
+        }
+        let event = next_event(&mut body).await;
+        assert!(event.contains("event: resync\n"), "{event}");
+    }
 
-/// Get git commit history.
-async fn git_history(
-    State(state): State<HeadlessState>,
-    Query(query): Query<GitHistoryQuery>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let current = state.current_state.read().await;
-    let working_dir = &current.project;
+    #[tokio::test]
+    async fn test_scoped_sessions_have_their_own_streams() {
+        use tower::ServiceExt;
 
-    if working_dir.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "No working directory set" })),
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let (host_tx, mut host_rx) = mpsc::unbounded_channel::<SessionRequest>();
+        let state = HeadlessState::new(tx).with_session_host(host_tx);
+        let router = create_headless_router(state.clone());
+        tokio::spawn(async move {
+            while let Some(request) = host_rx.recv().await {
+                let mut actions = request.actions;
+                let _ = request.reply.send(Ok(()));
+                tokio::spawn(async move { while actions.recv().await.is_some() {} });
+            }
+        });
+
+        let request = |method: &str, uri: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let response = router
+                .clone()
+                .oneshot(request("POST", "/sessions", ""))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let info: ScopedSessionInfo = serde_json::from_slice(&body).unwrap();
+            ids.push(info.id);
+        }
+        assert_ne!(ids[0], ids[1]);
+
+        let mut first = state.lock_sessions()[&ids[0]].update_tx.subscribe();
+        let mut second = state.lock_sessions()[&ids[1]].update_tx.subscribe();
+        state.send_session_update(&ids[0], Update::Started);
+        assert!(first.try_recv().is_ok());
+        assert!(second.try_recv().is_err());
+
+        let prompt = r#"{"type": "send_prompt", "prompt": "hi"}"#;
+        let uri = wonopcode_protocol::session_action_endpoint(&ids[1]);
+        let response = router
+            .clone()
+            .oneshot(request("POST", &uri, prompt))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router
+            .clone()
+            .oneshot(request("POST", &uri, prompt))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert!(matches!(
+            second.try_recv(),
+            Ok(Update::PromptQueued { position: 1 })
         ));
+
+        let uri = format!("/session/{}", ids[0]);
+        let response = router
+            .clone()
+            .oneshot(request("DELETE", &uri, ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let uri = wonopcode_protocol::session_events_endpoint(&ids[0]);
+        let response = router.oneshot(request("GET", &uri, "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 
-    let ops = GitOperations::new(working_dir);
-    match ops.history(query.limit) {
-        Ok(history) => Ok(Json(history)),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e.to_string() })),
-        )),
+    #[tokio::test]
+    async fn test_scoped_sessions_need_a_host() {
+        use tower::ServiceExt;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let router = create_headless_router(HeadlessState::new(tx));
+        let request = Request::builder()
+            .method("POST")
+            .uri("/sessions")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_IMPLEMENTED);
     }
-}
 
-#[derive(Deserialize, Default)]
-struct GitPushRequest {
-    /// Remote name (default: "origin").
-    remote: Option<String>,
-    /// Branch name (default: current branch).
-    branch: Option<String>,
-}
+    #[tokio::test]
+    async fn test_handshake_negotiates_or_refuses_clients() {
+        use tower::ServiceExt;
 
-/// Push to remote.
-async fn git_push(
-    State(state): State<HeadlessState>,
-    Json(req): Json<GitPushRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let current = state.current_state.read().await;
-    let working_dir = &current.project;
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let router = create_headless_router(HeadlessState::new(tx));
+        let handshake = |client: &Handshake| {
+            Request::builder()
+                .method("POST")
+                .uri(HANDSHAKE_ENDPOINT)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(
+                    serde_json::to_string(client).unwrap(),
+                ))
+                .unwrap()
+        };
 
-    if working_dir.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "No working directory set" })),
-        ));
+        let client = Handshake::new(Capability::ALL.to_vec());
+        let response = router.clone().oneshot(handshake(&client)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let negotiated: Negotiated = serde_json::from_slice(&body).unwrap();
+        assert_eq!(negotiated.version, wonopcode_protocol::PROTOCOL_VERSION);
+        assert!(negotiated.supports(Capability::Files));
+        // Neither scoped sessions nor file writes on this server
+        assert!(!negotiated.supports(Capability::Jobs));
+        assert!(!negotiated.supports(Capability::FileWrites));
+
+        let future = Handshake {
+            version: u32::MAX,
+            min_version: u32::MAX,
+            capabilities: Vec::new(),
+        };
+        let response = router.oneshot(handshake(&future)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UPGRADE_REQUIRED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let refusal: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(refusal["error"]
+            .as_str()
+            .unwrap()
+            .ends_with("upgrade the server"));
+        let server: Handshake = serde_json::from_value(refusal["handshake"].clone()).unwrap();
+        assert_eq!(server.version, wonopcode_protocol::PROTOCOL_VERSION);
     }
 
-    let ops = GitOperations::new(working_dir);
-    match ops.push(req.remote.as_deref(), req.branch.as_deref()) {
-        Ok(()) => Ok(Json(serde_json::json!({ "success": true }))),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e.to_string() })),
-        )),
+    #[tokio::test]
+    async fn test_jobs_run_in_order_and_can_be_cancelled() {
+        use tower::ServiceExt;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let (host_tx, mut host_rx) = mpsc::unbounded_channel::<SessionRequest>();
+        let state = HeadlessState::new(tx).with_session_host(host_tx);
+        let router = create_headless_router(state.clone());
+        // Runners that answer prompts at once, except "wait" until cancelled
+        let host_state = state.clone();
+        tokio::spawn(async move {
+            while let Some(request) = host_rx.recv().await {
+                let mut actions = request.actions;
+                let _ = request.reply.send(Ok(()));
+                let state = host_state.clone();
+                tokio::spawn(async move {
+                    while let Some(action) = actions.recv().await {
+                        let update = match action {
+                            Action::SendPrompt { prompt } if prompt == "wait" => continue,
+                            Action::SendPrompt { prompt } => Update::Completed {
+                                text: format!("Done: {prompt}"),
+                            },
+                            Action::Cancel => Update::Error {
+                                error: "Cancelled".to_string(),
+                            },
+                            _ => continue,
+                        };
+                        state.send_session_update(&request.id, update);
+                    }
+                });
+            }
+        });
+
+        let request = |method: &str, uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+        let send = |req: Request| {
+            let router = router.clone();
+            async move {
+                let response = router.oneshot(req).await.unwrap();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (
+                    status,
+                    serde_json::from_slice::<serde_json::Value>(&body).ok(),
+                )
+            }
+        };
+        let wait_for = |id: String, status: &'static str| {
+            let state = state.clone();
+            async move {
+                for _ in 0..100 {
+                    if let Some(job) = state.jobs.get(&id) {
+                        if serde_json::to_value(job.status).unwrap() == status {
+                            return job;
+                        }
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                panic!("job {id} never became {status}");
+            }
+        };
+
+        let mut ids = Vec::new();
+        for prompt in ["one", "wait", "two"] {
+            let (status, job) = send(request(
+                "POST",
+                "/jobs",
+                serde_json::json!({ "prompt": prompt }),
+            ))
+            .await;
+            assert_eq!(status, StatusCode::CREATED);
+            ids.push(job.unwrap()["id"].as_str().unwrap().to_string());
+        }
+
+        let first = wait_for(ids[0].clone(), "completed").await;
+        assert_eq!(first.result.as_deref(), Some("Done: one"));
+        // Its fresh session was closed
+        let session = first.session.unwrap();
+        assert!(!state.lock_sessions().contains_key(&session));
+
+        // The third job waits for the second
+        wait_for(ids[1].clone(), "running").await;
+        assert_eq!(state.jobs.get(&ids[2]).unwrap().status, JobStatus::Queued);
+        let uri = format!("{}/cancel", wonopcode_protocol::job_endpoint(&ids[1]));
+        let (status, job) = send(request("POST", &uri, serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(job.unwrap()["status"], "cancelled");
+        let third = wait_for(ids[2].clone(), "completed").await;
+        assert_eq!(third.result.as_deref(), Some("Done: two"));
+        assert_eq!(
+            state.jobs.get(&ids[1]).unwrap().status,
+            JobStatus::Cancelled
+        );
+
+        let (status, _) = send(request("POST", &uri, serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        let (status, job) = send(request(
+            "GET",
+            &wonopcode_protocol::job_endpoint(&ids[2]),
+            serde_json::Value::Null,
+        ))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(job.unwrap()["result"], "Done: two");
+        let (status, _) = send(request("GET", "/jobs/job_missing", serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(request(
+            "POST",
+            "/jobs",
+            serde_json::json!({ "prompt": "hi", "session": "ses_missing" }),
+        ))
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, jobs) = send(request("GET", "/jobs", serde_json::Value::Null)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(jobs.unwrap().as_array().unwrap().len(), 3);
     }
-}
 
-#[derive(Deserialize, Default)]
-struct GitPullRequest {
-    /// Remote name (default: "origin").
-    remote: Option<String>,
-    /// Branch name (default: current branch).
-    branch: Option<String>,
-}
+    #[tokio::test]
+    async fn test_rate_limit_answers_with_429() {
+        use tower::ServiceExt;
 
-/// Pull from remote.
-async fn git_pull(
-    State(state): State<HeadlessState>,
-    Json(req): Json<GitPullRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let current = state.current_state.read().await;
-    let working_dir = &current.project;
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx).with_limits(ServerLimits {
+            requests_per_minute: 2,
+            ..ServerLimits::default()
+        });
+        let router = create_headless_router(state);
+        let get_info = |addr: [u8; 4]| {
+            let mut request = Request::builder()
+                .uri("/info")
+                .body(axum::body::Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from((addr, 1234))));
+            request
+        };
 
-    if working_dir.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "No working directory set" })),
-        ));
+        for _ in 0..2 {
+            let response = router.clone().oneshot(get_info([10, 0, 0, 1])).await;
+            assert_eq!(response.unwrap().status(), StatusCode::OK);
+        }
+        let response = router
+            .clone()
+            .oneshot(get_info([10, 0, 0, 1]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["retry_after"], 30);
+
+        // Other clients, and the health check, are not affected
+        let response = router.clone().oneshot(get_info([10, 0, 0, 2])).await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+        let request = Request::builder()
+            .uri("/health")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
     }
 
-    let ops = GitOperations::new(working_dir);
-    match ops.pull(req.remote.as_deref(), req.branch.as_deref()) {
-        Ok(()) => Ok(Json(serde_json::json!({ "success": true }))),
-        Err(e) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e.to_string() })),
-        )),
+    #[test]
+    fn test_rate_limit_client_hashes_the_right_key() {
+        let request = |key: Option<&str>| {
+            let mut request = Request::builder().uri("/info");
+            if let Some(key) = key {
+                request = request.header("x-api-key", key);
+            }
+            let mut request = request.body(axum::body::Body::empty()).unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 1234))));
+            request
+        };
+
+        let client = rate_limit_client(&request(Some("secret")), Some("secret"));
+        assert!(client.starts_with("key:"), "{client}");
+        assert!(!client.contains("secret"));
+        assert_eq!(
+            rate_limit_client(&request(Some("guess")), Some("secret")),
+            "10.0.0.1"
+        );
+        assert_eq!(
+            rate_limit_client(&request(None), Some("secret")),
+            "10.0.0.1"
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use axum::http::HeaderValue;
+    #[tokio::test]
+    async fn test_action_bodies_are_limited() {
+        use tower::ServiceExt;
 
-    // === HeadlessState tests ===
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let state = HeadlessState::new(tx).with_limits(ServerLimits {
+            max_body_bytes: 64,
+            ..ServerLimits::default()
+        });
+        let router = create_headless_router(state);
+        let post_prompt = |prompt: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/action/prompt")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(
+                    serde_json::json!({ "prompt": prompt }).to_string(),
+                ))
+                .unwrap()
+        };
+
+        let response = router.clone().oneshot(post_prompt("hi")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router.oneshot(post_prompt(&"x".repeat(100))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
 
     #[tokio::test]
-    async fn test_headless_state_new() {
+    async fn test_files_are_read_only_by_default() {
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hello").unwrap();
         let (tx, _rx) = mpsc::unbounded_channel();
         let state = HeadlessState::new(tx);
+        let project = dir.path().to_string_lossy().to_string();
+        state.update_state(|s| s.project = project).await;
+        let router = create_headless_router(state.clone());
+        let get = |uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap()
+        };
+        let write = || {
+            Request::builder()
+                .method("POST")
+                .uri("/files/write")
+                .header("content-type", "application/json")
+                .body(axum::body::Body::from(
+                    serde_json::json!({ "path": "notes.txt", "content": "changed" }).to_string(),
+                ))
+                .unwrap()
+        };
 
-        assert!(state.shutdown_tx.is_none());
-        let shutdown = state.shutdown.read().await;
-        assert!(!*shutdown);
+        let response = router
+            .clone()
+            .oneshot(get("/files/read?path=notes.txt"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let content: FileContent = serde_json::from_slice(&body).unwrap();
+        assert_eq!(content.content, "hello");
+
+        let response = router.clone().oneshot(get("/files/list")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router
+            .clone()
+            .oneshot(get("/files/read?path=../notes.txt"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = router
+            .clone()
+            .oneshot(get("/files/read?path=missing.txt"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = router.oneshot(write()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "hello"
+        );
+
+        let router = create_headless_router(state.with_file_writes(true));
+        let response = router.oneshot(write()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.txt")).unwrap(),
+            "changed"
+        );
     }
 
-    #[tokio::test]
-    async fn test_headless_state_with_shutdown_tx() {
-        let (action_tx, _action_rx) = mpsc::unbounded_channel();
-        let (shutdown_tx, _shutdown_rx) = mpsc::channel(1);
+    /// A value of the given schema, with only its required fields set.
+    fn sample(schema: &serde_json::Value, schemas: &serde_json::Value) -> serde_json::Value {
+        use serde_json::{json, Value};
 
-        let state = HeadlessState::new(action_tx).with_shutdown_tx(shutdown_tx);
-        assert!(state.shutdown_tx.is_some());
+        if let Some(name) = schema["$ref"].as_str() {
+            let name = name.trim_start_matches("#/components/schemas/");
+            return sample(&schemas[name], schemas);
+        }
+        if let Some(value) = schema["enum"].get(0) {
+            return value.clone();
+        }
+        let kind = match &schema["type"] {
+            Value::Array(kinds) => kinds[0].as_str(),
+            kind => kind.as_str(),
+        };
+        match kind {
+            Some("string") => json!(""),
+            Some("integer") | Some("number") => json!(0),
+            Some("boolean") => json!(false),
+            Some("array") => json!([]),
+            Some("object") => {
+                let mut object = serde_json::Map::new();
+                for field in schema["required"].as_array().into_iter().flatten() {
+                    let field = field.as_str().unwrap();
+                    object.insert(
+                        field.to_string(),
+                        sample(&schema["properties"][field], schemas),
+                    );
+                }
+                Value::Object(object)
+            }
+            _ => Value::Null,
+        }
     }
 
     #[tokio::test]
-    async fn test_headless_state_send_update() {
-        let (tx, _rx) = mpsc::unbounded_channel();
-        let state = HeadlessState::new(tx);
-
-        // Subscribe before sending
-        let mut rx = state.update_tx.subscribe();
+    async fn test_openapi_document_matches_routes() {
+        use tower::ServiceExt;
 
-        let update = Update::Started;
-        state.send_update(update);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let router = create_headless_router(HeadlessState::new(tx));
+        let request = Request::builder()
+            .uri("/openapi.json")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let doc: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // Every documented endpoint is routed
+        let paths = doc["paths"].as_object().unwrap();
+        for (path, operations) in paths {
+            for method in operations.as_object().unwrap().keys() {
+                let request = Request::builder()
+                    .method(method.to_uppercase().as_str())
+                    .uri(path.replace("{id}", "missing"))
+                    .body(axum::body::Body::empty())
+                    .unwrap();
+                let status = router.clone().oneshot(request).await.unwrap().status();
+                assert_ne!(status, StatusCode::METHOD_NOT_ALLOWED, "{method} {path}");
+                if !path.contains("{id}") {
+                    assert_ne!(status, StatusCode::NOT_FOUND, "{method} {path}");
+                }
+            }
+        }
 
-        // Should receive the update
-        let received = rx.try_recv();
-        assert!(received.is_ok());
+        // Every action is documented, at the endpoint it is posted to
+        let schemas = &doc["components"]["schemas"];
+        let variants = schemas["Action"]["oneOf"].as_array().unwrap();
+        assert!(!variants.is_empty());
+        for variant in variants {
+            let action: Action = serde_json::from_value(sample(variant, schemas))
+                .unwrap_or_else(|e| panic!("{variant}: {e}"));
+            if matches!(action, Action::UpdateTestProviderSettings { .. }) {
+                continue;
+            }
+            let endpoint = action.endpoint();
+            assert!(
+                paths.get(endpoint).is_some_and(|p| p.get("post").is_some()),
+                "{endpoint} is not documented"
+            );
+        }
     }
 
     #[tokio::test]
@@ -1226,6 +4759,26 @@ mod tests {
         assert_eq!(req.message, "Initial commit");
     }
 
+    #[test]
+    fn test_git_diff_request_deserialize_defaults() {
+        let req: GitDiffRequest = serde_json::from_str("{}").unwrap();
+        assert!(req.path.is_none());
+        assert!(!req.staged);
+
+        let json = r#"{"path": "src/main.rs", "staged": true}"#;
+        let req: GitDiffRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.path.as_deref(), Some("src/main.rs"));
+        assert!(req.staged);
+    }
+
+    #[test]
+    fn test_git_hunk_request_deserialize() {
+        let json = r#"{"path": "src/main.rs", "hunk": 2}"#;
+        let req: GitHunkRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.path, "src/main.rs");
+        assert_eq!(req.hunk, 2);
+    }
+
     #[test]
     fn test_git_checkout_request_deserialize_with_paths() {
         let json = r#"{"paths": ["src/main.rs"]}"#;
@@ -1325,6 +4878,84 @@ mod tests {
         assert_eq!(req.model, "claude-3-opus");
     }
 
+    #[test]
+    fn test_profile_request_deserialize() {
+        let json = r#"{"profile": "work"}"#;
+        let req: ProfileRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.profile, "work");
+    }
+
+    #[test]
+    fn test_project_request_deserialize() {
+        let json = r#"{"type": "switch_project", "project": "/srv/api"}"#;
+        let req: ProjectRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.project, "/srv/api");
+    }
+
+    #[test]
+    fn test_template_request_deserialize() {
+        let json = r#"{"type": "apply_template", "name": "code-review"}"#;
+        let req: TemplateRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.name, "code-review");
+
+        // An empty request lists templates
+        let req: TemplateRequest = serde_json::from_str("{}").unwrap();
+        assert!(req.name.is_empty());
+    }
+
+    #[test]
+    fn test_memory_save_request_deserialize() {
+        let json = r#"{"type": "save_memory", "content": "- Use tabs"}"#;
+        let req: MemorySaveRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.content, "- Use tabs");
+    }
+
+    #[test]
+    fn test_review_apply_request_deserialize() {
+        let json =
+            r#"{"files": [{"path": "src/lib.rs", "decisions": [{"type": "accept"}, null]}]}"#;
+        let req: ReviewApplyRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.files[0].path, "src/lib.rs");
+        assert_eq!(
+            req.files[0].decisions,
+            vec![Some(wonopcode_protocol::HunkDecisionInfo::Accept), None]
+        );
+    }
+
+    #[test]
+    fn test_process_stop_request_deserialize() {
+        let json = r#"{"pid": 4242}"#;
+        let req: ProcessStopRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.pid, 4242);
+    }
+
+    #[test]
+    fn test_context_request_deserialize() {
+        let json = r#"{"path": "src/lib.rs", "symbol": "parse", "lines": [10, 20]}"#;
+        let req: ContextRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.path, "src/lib.rs");
+        assert_eq!(req.symbol.as_deref(), Some("parse"));
+        assert_eq!(req.lines, Some((10, 20)));
+
+        let req: ContextRequest = serde_json::from_str(r#"{"path": "README.md"}"#).unwrap();
+        assert!(req.symbol.is_none());
+        assert!(req.lines.is_none());
+    }
+
+    #[test]
+    fn test_symbols_request_deserialize() {
+        let json = r#"{"query": "parse"}"#;
+        let req: SymbolsRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.query, "parse");
+    }
+
+    #[test]
+    fn test_tool_cancel_request_deserialize() {
+        let json = r#"{"id": "call_1"}"#;
+        let req: ToolCancelRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.id, "call_1");
+    }
+
     #[test]
     fn test_agent_request_deserialize() {
         let json = r#"{"agent": "coder"}"#;
@@ -1360,6 +4991,31 @@ mod tests {
         assert!(req.message_id.is_none());
     }
 
+    #[test]
+    fn test_session_search_request_deserialize() {
+        let json = r#"{"query": "tokenizer tag:bug"}"#;
+        let req: SessionSearchRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.query, "tokenizer tag:bug");
+    }
+
+    #[test]
+    fn test_orchestrate_request_deserialize() {
+        let json = r#"{"tasks": ["Fix the lexer", "Add parser tests"]}"#;
+        let req: OrchestrateRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.tasks, vec!["Fix the lexer", "Add parser tests"]);
+    }
+
+    #[test]
+    fn test_worktree_requests_deserialize() {
+        let req: WorktreeCreateRequest = serde_json::from_str(r#"{"name": "lexer"}"#).unwrap();
+        assert_eq!(req.name, Some("lexer".to_string()));
+        let req: WorktreeCreateRequest = serde_json::from_str("{}").unwrap();
+        assert!(req.name.is_none());
+
+        let req: WorktreeFinishRequest = serde_json::from_str(r#"{"action": "merge"}"#).unwrap();
+        assert_eq!(req.action, "merge");
+    }
+
     #[test]
     fn test_revert_request_deserialize() {
         let json = r#"{"message_id": "msg-789"}"#;
@@ -0,0 +1,244 @@
+//! Prompt jobs submitted to the headless server.
+//!
+//! [`JobStore`] keeps the jobs posted to `/jobs` and, given a directory,
+//! saves each one there as `<id>.json` whenever it changes, so clients can
+//! still fetch the result of a job after the server restarts. Jobs that were
+//! queued or running when the server stopped are marked failed on load.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{SecondsFormat, Utc};
+use tracing::warn;
+use wonopcode_protocol::{Job, JobRequest, JobStatus};
+
+/// Why a job left unfinished by a previous run failed.
+const INTERRUPTED: &str = "The server stopped before the job finished";
+
+/// The current time, as jobs record it: fixed width, so it sorts.
+pub fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Nanos, true)
+}
+
+/// The jobs of a server.
+pub struct JobStore {
+    dir: Option<PathBuf>,
+    jobs: Mutex<HashMap<String, Job>>,
+}
+
+impl JobStore {
+    /// Keep jobs in memory only.
+    pub fn in_memory() -> Self {
+        Self {
+            dir: None,
+            jobs: Mutex::default(),
+        }
+    }
+
+    /// Keep jobs in `dir`, loading those saved by a previous run.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        let store = Self {
+            dir: Some(dir.clone()),
+            jobs: Mutex::default(),
+        };
+
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let mut job = match std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| serde_json::from_slice::<Job>(&data).map_err(|e| e.to_string()))
+            {
+                Ok(job) => job,
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Skipping unreadable job");
+                    continue;
+                }
+            };
+            if !job.status.is_finished() {
+                job.status = JobStatus::Failed;
+                job.error = Some(INTERRUPTED.to_string());
+                job.finished_at = Some(now());
+                store.save(&job);
+            }
+            store.lock().insert(job.id.clone(), job);
+        }
+        Ok(store)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn path(dir: &Path, id: &str) -> PathBuf {
+        dir.join(format!("{id}.json"))
+    }
+
+    /// Save a job, if jobs are kept on disk.
+    fn save(&self, job: &Job) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let result = serde_json::to_vec_pretty(job)
+            .map_err(io::Error::from)
+            .and_then(|data| std::fs::write(Self::path(dir, &job.id), data));
+        if let Err(e) = result {
+            warn!(job_id = %job.id, error = %e, "Failed to save job");
+        }
+    }
+
+    /// Queue a new job.
+    pub fn create(&self, request: JobRequest) -> Job {
+        let job = Job {
+            id: wonopcode_util::Identifier::job(),
+            prompt: request.prompt,
+            session: request.session,
+            status: JobStatus::Queued,
+            result: None,
+            error: None,
+            created_at: now(),
+            started_at: None,
+            finished_at: None,
+        };
+        self.save(&job);
+        self.lock().insert(job.id.clone(), job.clone());
+        job
+    }
+
+    /// A job by ID.
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.lock().get(id).cloned()
+    }
+
+    /// All jobs, oldest first.
+    pub fn list(&self) -> Vec<Job> {
+        let mut jobs: Vec<Job> = self.lock().values().cloned().collect();
+        jobs.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        jobs
+    }
+
+    /// Change an unfinished job and save it. Returns the changed job, or
+    /// `None` if there is no such job or it has already finished, as a
+    /// cancelled one has.
+    pub fn update(&self, id: &str, f: impl FnOnce(&mut Job)) -> Option<Job> {
+        let mut jobs = self.lock();
+        let job = jobs.get_mut(id).filter(|job| !job.status.is_finished())?;
+        f(job);
+        let job = job.clone();
+        drop(jobs);
+        self.save(&job);
+        Some(job)
+    }
+
+    /// Mark a job as running in a session.
+    pub fn start(&self, id: &str, session: &str) -> Option<Job> {
+        self.update(id, |job| {
+            job.status = JobStatus::Running;
+            job.session = Some(session.to_string());
+            job.started_at = Some(now());
+        })
+    }
+
+    /// Record how a job ended.
+    pub fn finish(&self, id: &str, outcome: Result<String, String>) -> Option<Job> {
+        self.update(id, |job| {
+            match outcome {
+                Ok(result) => {
+                    job.status = JobStatus::Completed;
+                    job.result = Some(result);
+                }
+                Err(error) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(error);
+                }
+            }
+            job.finished_at = Some(now());
+        })
+    }
+
+    /// Cancel an unfinished job.
+    pub fn cancel(&self, id: &str) -> Option<Job> {
+        self.update(id, |job| {
+            job.status = JobStatus::Cancelled;
+            job.finished_at = Some(now());
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(prompt: &str) -> JobRequest {
+        JobRequest {
+            prompt: prompt.to_string(),
+            session: None,
+        }
+    }
+
+    #[test]
+    fn test_finished_jobs_stay_finished() {
+        let store = JobStore::in_memory();
+        let first = store.create(request("first"));
+        let second = store.create(request("second"));
+        assert_eq!(
+            store.list().iter().map(|j| &j.id).collect::<Vec<_>>(),
+            vec![&first.id, &second.id]
+        );
+
+        let running = store.start(&first.id, "ses_1").unwrap();
+        assert_eq!(running.status, JobStatus::Running);
+        assert_eq!(running.session.as_deref(), Some("ses_1"));
+        let done = store.finish(&first.id, Ok("Done".to_string())).unwrap();
+        assert_eq!(done.status, JobStatus::Completed);
+        assert_eq!(done.result.as_deref(), Some("Done"));
+        assert!(store.cancel(&first.id).is_none());
+
+        // A cancelled job ignores the end of its prompt
+        store.start(&second.id, "ses_2").unwrap();
+        assert_eq!(
+            store.cancel(&second.id).unwrap().status,
+            JobStatus::Cancelled
+        );
+        assert!(store
+            .finish(&second.id, Err("Cancelled".to_string()))
+            .is_none());
+        assert_eq!(store.get(&second.id).unwrap().status, JobStatus::Cancelled);
+        assert!(store.get("job_missing").is_none());
+    }
+
+    #[test]
+    fn test_jobs_are_kept_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::open(dir.path()).unwrap();
+        let done = store.create(request("done"));
+        store.start(&done.id, "ses_1");
+        store.finish(&done.id, Ok("Result".to_string()));
+        let running = store.create(request("running"));
+        store.start(&running.id, "ses_2");
+        std::fs::write(dir.path().join("garbage.json"), "not json").unwrap();
+        drop(store);
+
+        let store = JobStore::open(dir.path()).unwrap();
+        assert_eq!(store.list().len(), 2);
+        let done = store.get(&done.id).unwrap();
+        assert_eq!(done.status, JobStatus::Completed);
+        assert_eq!(done.result.as_deref(), Some("Result"));
+        let running = store.get(&running.id).unwrap();
+        assert_eq!(running.status, JobStatus::Failed);
+        assert_eq!(running.error.as_deref(), Some(INTERRUPTED));
+        assert!(running.finished_at.is_some());
+    }
+}
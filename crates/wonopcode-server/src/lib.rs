@@ -2,19 +2,32 @@
 //!
 //! Provides REST API, SSE, and WebSocket endpoints for the TUI and external clients.
 
+pub mod files;
 pub mod git;
 pub mod headless;
+pub mod jobs;
+pub mod limit;
+pub mod presence;
 pub mod prompt;
+pub mod rendezvous;
 pub mod routes;
 pub mod sse;
 pub mod state;
+pub mod tls;
+pub mod webhook;
 pub mod ws;
 
+pub use files::{FileError, ProjectFiles};
 pub use git::{GitCommitInfo, GitError, GitFileState, GitFileStatus, GitOperations, GitStatus};
 pub use headless::{
     create_headless_router, create_headless_router_with_mcp, create_headless_router_with_options,
-    HeadlessState,
+    ApiDoc, HeadlessState, SessionRequest,
 };
+pub use jobs::JobStore;
+pub use limit::ServerLimits;
+pub use presence::Presence;
 pub use prompt::{PromptEvent, PromptRequest, PromptResponse, ServerPromptRunner};
+pub use rendezvous::{Registration, Relay, RendezvousClient, RendezvousError};
 pub use routes::create_router;
 pub use state::AppState;
+pub use webhook::Webhooks;
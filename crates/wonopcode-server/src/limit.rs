@@ -0,0 +1,180 @@
+//! Request rate and size limits for the headless server.
+//!
+//! Servers advertised over mDNS are reachable by anyone on the network, so
+//! each client gets a budget of requests per minute, refilled continuously,
+//! and action bodies are capped in size.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Requests per minute a client may make by default.
+pub const DEFAULT_REQUESTS_PER_MINUTE: u32 = 300;
+
+/// Largest action request body by default, in bytes.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Number of clients tracked before idle ones, or else the ones seen least
+/// recently, are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 4096;
+
+/// Limits on the requests of a headless server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerLimits {
+    /// Requests per minute per client; 0 for no limit.
+    pub requests_per_minute: u32,
+    /// Largest request body of the action endpoints, in bytes. Image
+    /// attachments have their own, larger limit.
+    pub max_body_bytes: usize,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+}
+
+/// Requests left to a client, refilled at the limit's rate.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token buckets of the clients of a server.
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_minute: u32,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Allow each client `requests_per_minute` requests, in bursts of up to
+    /// as many.
+    pub fn new(requests_per_minute: u32) -> Self {
+        Self {
+            requests_per_minute,
+            buckets: Mutex::default(),
+        }
+    }
+
+    /// Take a request from the client's budget, or return how long until it
+    /// may make one.
+    pub fn check(&self, client: &str, now: Instant) -> Result<(), Duration> {
+        if self.requests_per_minute == 0 {
+            return Ok(());
+        }
+        let capacity = f64::from(self.requests_per_minute);
+        let per_second = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            // Clients whose budget has refilled are no different from new ones
+            buckets.retain(|_, bucket| {
+                let elapsed = now.saturating_duration_since(bucket.updated);
+                bucket.tokens + elapsed.as_secs_f64() * per_second < capacity
+            });
+        }
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(client) {
+            let oldest = buckets
+                .iter()
+                .min_by_key(|(_, bucket)| bucket.updated)
+                .map(|(client, _)| client.clone());
+            if let Some(oldest) = oldest {
+                buckets.remove(&oldest);
+            }
+        }
+
+        let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * per_second).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_second))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_refills_over_time() {
+        let limiter = RateLimiter::new(60);
+        let start = Instant::now();
+        for _ in 0..60 {
+            assert!(limiter.check("a", start).is_ok());
+        }
+        let wait = limiter.check("a", start).unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+        // One request a second comes back
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.check("a", later).is_ok());
+        assert!(limiter.check("a", later).is_err());
+    }
+
+    #[test]
+    fn test_clients_have_their_own_budgets() {
+        let limiter = RateLimiter::new(1);
+        let now = Instant::now();
+        assert!(limiter.check("a", now).is_ok());
+        assert!(limiter.check("a", now).is_err());
+        assert!(limiter.check("b", now).is_ok());
+    }
+
+    #[test]
+    fn test_zero_is_unlimited() {
+        let limiter = RateLimiter::new(0);
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert!(limiter.check("a", now).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_idle_clients_are_forgotten() {
+        let limiter = RateLimiter::new(60);
+        let start = Instant::now();
+        for i in 0..MAX_TRACKED_CLIENTS {
+            limiter.check(&i.to_string(), start).unwrap();
+        }
+        limiter
+            .check("new", start + Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_least_recent_client_is_forgotten_when_none_are_idle() {
+        // A request takes ten seconds to come back
+        let limiter = RateLimiter::new(6);
+        let start = Instant::now();
+        for i in 0..MAX_TRACKED_CLIENTS {
+            limiter
+                .check(&i.to_string(), start + Duration::from_millis(i as u64))
+                .unwrap();
+        }
+        let now = start + Duration::from_millis(MAX_TRACKED_CLIENTS as u64);
+        limiter.check("new", now).unwrap();
+
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.len(), MAX_TRACKED_CLIENTS);
+        assert!(!buckets.contains_key("0"));
+        assert!(buckets.contains_key("1"));
+        assert!(buckets.contains_key("new"));
+    }
+}
@@ -0,0 +1,115 @@
+//! Clients sharing a headless session.
+//!
+//! [`Presence`] keeps the participants of a server with collaboration on and
+//! which of them drives. The first to join drives; when the driver leaves,
+//! the participant who joined first takes over, so a shared session always
+//! has a driver while anyone is connected.
+
+use std::sync::Mutex;
+
+use wonopcode_protocol::{Participant, Role};
+
+use crate::jobs::now;
+
+/// The participants of a shared session, in the order they joined.
+#[derive(Default)]
+pub struct Presence {
+    participants: Mutex<Vec<Participant>>,
+}
+
+impl Presence {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Participant>> {
+        self.participants.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Add a participant, who drives if nobody else does.
+    pub fn join(&self, name: &str) -> Participant {
+        let mut participants = self.lock();
+        let role = if participants.iter().any(|p| p.role == Role::Driver) {
+            Role::Watcher
+        } else {
+            Role::Driver
+        };
+        let participant = Participant {
+            id: wonopcode_util::Identifier::participant(),
+            name: name.to_string(),
+            role,
+            joined_at: now(),
+        };
+        participants.push(participant.clone());
+        participant
+    }
+
+    /// Remove a participant, handing control to the one who joined first
+    /// if it was driving.
+    pub fn leave(&self, id: &str) {
+        let mut participants = self.lock();
+        participants.retain(|p| p.id != id);
+        if !participants.iter().any(|p| p.role == Role::Driver) {
+            if let Some(first) = participants.first_mut() {
+                first.role = Role::Driver;
+            }
+        }
+    }
+
+    /// Make a participant the driver and the previous one a watcher.
+    /// Returns false if there is no such participant.
+    pub fn take_control(&self, id: &str) -> bool {
+        let mut participants = self.lock();
+        if !participants.iter().any(|p| p.id == id) {
+            return false;
+        }
+        for participant in participants.iter_mut() {
+            participant.role = if participant.id == id {
+                Role::Driver
+            } else {
+                Role::Watcher
+            };
+        }
+        true
+    }
+
+    /// Whether a participant drives.
+    pub fn is_driver(&self, id: &str) -> bool {
+        self.lock()
+            .iter()
+            .any(|p| p.id == id && p.role == Role::Driver)
+    }
+
+    /// The participants, in the order they joined.
+    pub fn list(&self) -> Vec<Participant> {
+        self.lock().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_one_participant_drives_at_a_time() {
+        let presence = Presence::default();
+        let ada = presence.join("ada");
+        let bob = presence.join("bob");
+        let eve = presence.join("eve");
+        assert_eq!(ada.role, Role::Driver);
+        assert_eq!(bob.role, Role::Watcher);
+        assert!(presence.is_driver(&ada.id));
+        assert!(!presence.is_driver(&bob.id));
+
+        assert!(presence.take_control(&bob.id));
+        assert!(presence.is_driver(&bob.id));
+        assert!(!presence.is_driver(&ada.id));
+        assert!(!presence.take_control("ptc_missing"));
+
+        // The driver leaves; the first to join takes over
+        presence.leave(&bob.id);
+        assert!(presence.is_driver(&ada.id));
+        presence.leave(&ada.id);
+        assert!(presence.is_driver(&eve.id));
+        assert_eq!(presence.list().len(), 1);
+
+        presence.leave(&eve.id);
+        assert_eq!(presence.join("bob").role, Role::Driver);
+    }
+}
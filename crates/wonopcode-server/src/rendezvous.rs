@@ -0,0 +1,422 @@
+//! Finding headless servers beyond the local network.
+//!
+//! mDNS only reaches the local network. To find a server from elsewhere,
+//! servers register with a rendezvous relay the user runs somewhere both
+//! sides can reach, and clients ask the relay which servers there are:
+//!
+//! - `PUT /servers/{name}` registers a server, or keeps its registration
+//!   alive. Registrations not renewed within [`REGISTRATION_TTL`] lapse.
+//! - `DELETE /servers/{name}` removes it when the server stops.
+//! - `GET /servers` lists the servers registered.
+//!
+//! The relay only passes on where servers are. Clients still connect to the
+//! server itself, with its secret and certificate, so a relay can't see or
+//! take part in a session. With a token, only those who have it can
+//! register or list servers.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::headless::{constant_time_eq, extract_api_key};
+
+/// How long a registration lasts without being renewed.
+pub const REGISTRATION_TTL: Duration = Duration::from_secs(90);
+
+/// How often a server renews its registration.
+pub const RENEW_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long the relay has to answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A server as registered with a relay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Registration {
+    /// Display name of the server; unique on the relay.
+    pub name: String,
+    /// Where clients can reach the server, as `host:port`, best first.
+    pub addresses: Vec<String>,
+    /// Wonopcode version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Current AI model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Project name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Whether the server needs a secret.
+    #[serde(default)]
+    pub auth_required: bool,
+    /// When the relay last heard from the server; set by the relay.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<String>,
+}
+
+/// Errors talking to a relay.
+#[derive(Debug, Error)]
+pub enum RendezvousError {
+    /// The relay couldn't be reached.
+    #[error("Failed to reach the rendezvous relay: {0}")]
+    Request(#[from] reqwest::Error),
+
+    /// The relay refused the request.
+    #[error("The rendezvous relay refused the request ({status}): {message}")]
+    Rejected { status: u16, message: String },
+}
+
+/// The servers registered with a relay.
+pub struct Relay {
+    servers: Mutex<HashMap<String, (Registration, Instant)>>,
+    ttl: Duration,
+}
+
+impl Relay {
+    /// A relay whose registrations last `ttl` unless renewed.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            servers: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Registration, Instant)>> {
+        self.servers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Register a server, or renew its registration.
+    pub fn register(&self, mut registration: Registration) {
+        registration.last_seen = Some(crate::jobs::now());
+        self.lock()
+            .insert(registration.name.clone(), (registration, Instant::now()));
+    }
+
+    /// Remove a server. Returns false if it wasn't registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.lock().remove(name).is_some()
+    }
+
+    /// The servers whose registration hasn't lapsed, by name.
+    pub fn list(&self) -> Vec<Registration> {
+        let mut servers = self.lock();
+        servers.retain(|_, (_, renewed)| renewed.elapsed() < self.ttl);
+        let mut list: Vec<Registration> = servers
+            .values()
+            .map(|(registration, _)| registration.clone())
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+}
+
+impl Default for Relay {
+    fn default() -> Self {
+        Self::new(REGISTRATION_TTL)
+    }
+}
+
+/// State of the relay's routes.
+#[derive(Clone)]
+struct RelayState {
+    relay: std::sync::Arc<Relay>,
+    token: Option<String>,
+}
+
+/// The relay's routes, needing `token` to register or list servers if set.
+pub fn relay_router(relay: std::sync::Arc<Relay>, token: Option<String>) -> Router {
+    let state = RelayState { relay, token };
+    let servers = Router::new()
+        .route("/servers", get(list_servers))
+        .route("/servers/{name}", put(register).delete(unregister))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            token_auth,
+        ))
+        .with_state(state);
+    Router::new()
+        .route(
+            "/health",
+            get(|| async { Json(serde_json::json!({ "status": "ok" })) }),
+        )
+        .merge(servers)
+}
+
+async fn token_auth(State(state): State<RelayState>, request: Request, next: Next) -> Response {
+    let Some(ref expected) = state.token else {
+        return next.run(request).await;
+    };
+    match extract_api_key(request.headers()) {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            warn!("Rendezvous request without the relay's token");
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Invalid or missing token" })),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn list_servers(State(state): State<RelayState>) -> Json<Vec<Registration>> {
+    Json(state.relay.list())
+}
+
+async fn register(
+    State(state): State<RelayState>,
+    Path(name): Path<String>,
+    Json(mut registration): Json<Registration>,
+) -> StatusCode {
+    if registration.addresses.is_empty() {
+        return StatusCode::UNPROCESSABLE_ENTITY;
+    }
+    debug!(%name, addresses = ?registration.addresses, "Server registered");
+    registration.name = name;
+    state.relay.register(registration);
+    StatusCode::NO_CONTENT
+}
+
+async fn unregister(State(state): State<RelayState>, Path(name): Path<String>) -> StatusCode {
+    if state.relay.unregister(&name) {
+        debug!(%name, "Server unregistered");
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// A client of a relay, for servers registering and clients looking.
+#[derive(Clone)]
+pub struct RendezvousClient {
+    url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl RendezvousClient {
+    /// A client of the relay at `url`, sending `token` if given.
+    pub fn new(url: &str, token: Option<String>) -> Self {
+        let url = if url.starts_with("http://") || url.starts_with("https://") {
+            url.trim_end_matches('/').to_string()
+        } else {
+            format!("http://{}", url.trim_end_matches('/'))
+        };
+        Self {
+            url,
+            token,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Trust `pem` for an https relay, such as its self-signed certificate.
+    pub fn with_ca_certificate(mut self, pem: &[u8]) -> Result<Self, RendezvousError> {
+        let certificate = reqwest::Certificate::from_pem(pem)?;
+        self.client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .add_root_certificate(certificate)
+            .build()?;
+        Ok(self)
+    }
+
+    /// The relay's URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{path}", self.url));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Register a server, or renew its registration.
+    pub async fn register(&self, registration: &Registration) -> Result<(), RendezvousError> {
+        let path = format!("/servers/{}", encode_name(&registration.name));
+        let response = self
+            .request(reqwest::Method::PUT, &path)
+            .json(registration)
+            .send()
+            .await?;
+        check(response).await.map(|_| ())
+    }
+
+    /// Remove a server's registration.
+    pub async fn unregister(&self, name: &str) -> Result<(), RendezvousError> {
+        let path = format!("/servers/{}", encode_name(name));
+        let response = self.request(reqwest::Method::DELETE, &path).send().await?;
+        check(response).await.map(|_| ())
+    }
+
+    /// The servers registered with the relay.
+    pub async fn list(&self) -> Result<Vec<Registration>, RendezvousError> {
+        let response = self
+            .request(reqwest::Method::GET, "/servers")
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Keep `registration` registered until the task is aborted, renewing
+    /// it every [`RENEW_INTERVAL`]. A relay that is down is retried.
+    pub fn keep_registered(&self, registration: Registration) -> JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut registered = true;
+            loop {
+                match client.register(&registration).await {
+                    Ok(()) if !registered => {
+                        debug!(url = %client.url, "Registered with the rendezvous relay again");
+                        registered = true;
+                    }
+                    Ok(()) => {}
+                    Err(e) if registered => {
+                        warn!(url = %client.url, error = %e, "Failed to register with the rendezvous relay");
+                        registered = false;
+                    }
+                    Err(_) => {}
+                }
+                tokio::time::sleep(RENEW_INTERVAL).await;
+            }
+        })
+    }
+}
+
+/// A server name as a path segment.
+fn encode_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// The response if the relay accepted the request.
+async fn check(response: reqwest::Response) -> Result<reqwest::Response, RendezvousError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body["error"].as_str().map(str::to_string))
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or("").to_string());
+    Err(RendezvousError::Rejected {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn registration(name: &str) -> Registration {
+        Registration {
+            name: name.to_string(),
+            addresses: vec!["100.64.0.2:3000".to_string()],
+            version: Some("0.1.0".to_string()),
+            model: None,
+            project: Some("project".to_string()),
+            auth_required: true,
+            last_seen: None,
+        }
+    }
+
+    async fn serve(relay: Arc<Relay>, token: Option<String>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, relay_router(relay, token)).await });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_registrations_lapse() {
+        let relay = Relay::new(Duration::from_millis(50));
+        relay.register(registration("home"));
+        relay.register(registration("attic"));
+        let names: Vec<String> = relay.list().into_iter().map(|r| r.name).collect();
+        assert_eq!(names, vec!["attic", "home"]);
+        assert!(relay.list()[0].last_seen.is_some());
+
+        std::thread::sleep(Duration::from_millis(60));
+        relay.register(registration("home"));
+        let names: Vec<String> = relay.list().into_iter().map(|r| r.name).collect();
+        assert_eq!(names, vec!["home"]);
+
+        assert!(relay.unregister("home"));
+        assert!(!relay.unregister("home"));
+        assert!(relay.list().is_empty());
+    }
+
+    #[test]
+    fn test_encode_name() {
+        assert_eq!(encode_name("my-box.local"), "my-box.local");
+        assert_eq!(encode_name("Jo's box"), "Jo%27s%20box");
+    }
+
+    #[tokio::test]
+    async fn test_register_and_list_through_relay() {
+        let relay = Arc::new(Relay::default());
+        let url = serve(relay.clone(), Some("relay-token".to_string())).await;
+        let client = RendezvousClient::new(&url, Some("relay-token".to_string()));
+
+        client.register(&registration("home box")).await.unwrap();
+        let servers = client.list().await.unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].name, "home box");
+        assert_eq!(servers[0].addresses, vec!["100.64.0.2:3000"]);
+        assert!(servers[0].auth_required);
+
+        client.unregister("home box").await.unwrap();
+        assert!(client.list().await.unwrap().is_empty());
+        assert!(matches!(
+            client.unregister("home box").await,
+            Err(RendezvousError::Rejected { status: 404, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_relay_needs_token() {
+        let relay = Arc::new(Relay::default());
+        let url = serve(relay.clone(), Some("relay-token".to_string())).await;
+
+        let client = RendezvousClient::new(&url, Some("wrong".to_string()));
+        assert!(matches!(
+            client.register(&registration("home")).await,
+            Err(RendezvousError::Rejected { status: 401, .. })
+        ));
+        let client = RendezvousClient::new(url.trim_start_matches("http://"), None);
+        assert!(matches!(
+            client.list().await,
+            Err(RendezvousError::Rejected { status: 401, .. })
+        ));
+        assert!(relay.list().is_empty());
+    }
+}
@@ -10,8 +10,9 @@ use crate::{
     state::AppState,
 };
 use axum::{
+    body::Body,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{
         sse::{Event, KeepAlive, Sse},
         IntoResponse, Json,
@@ -23,9 +24,9 @@ use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, convert::Infallible, time::Duration};
 use tokio::sync::mpsc;
-use tokio_util::sync::CancellationToken;
+use tokio_util::{io::ReaderStream, sync::CancellationToken};
 use tower_http::cors::{Any, CorsLayer};
-use wonopcode_core::AgentRegistry;
+use wonopcode_core::{AgentRegistry, Instance, ShareOptions, ShareSettings};
 
 /// Create the router with all routes.
 pub fn create_router(state: AppState) -> Router {
@@ -67,6 +68,7 @@ pub fn create_router(state: AppState) -> Router {
         // ===================
         .route("/session", get(session_list))
         .route("/session", post(session_create))
+        .route("/session/search", get(session_search))
         .route("/session/{id}", get(session_get))
         .route("/session/{id}", put(session_update))
         .route("/session/{id}", delete(session_delete))
@@ -76,6 +78,12 @@ pub fn create_router(state: AppState) -> Router {
         .route("/session/{id}/prompt_async", post(session_prompt_async))
         .route("/session/{id}/abort", post(session_abort))
         .route("/session/{id}/fork", post(session_fork))
+        .route("/session/{id}/merge", post(session_merge))
+        .route("/session/{id}/worktree", post(session_worktree_create))
+        .route(
+            "/session/{id}/worktree/finish",
+            post(session_worktree_finish),
+        )
         .route("/session/{id}/children", get(session_children))
         .route("/session/{id}/diff", get(session_diff))
         .route("/session/{id}/status", get(session_status))
@@ -98,6 +106,7 @@ pub fn create_router(state: AppState) -> Router {
         .route("/file/read", get(file_read))
         .route("/file/list", get(file_list))
         .route("/file/status", get(file_status))
+        .route("/worktree", get(worktree_list))
         // ===================
         // Find endpoints
         // ===================
@@ -368,6 +377,8 @@ struct SessionResponse {
     project_id: String,
     title: String,
     directory: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     parent_id: Option<String>,
     created: i64,
@@ -376,6 +387,10 @@ struct SessionResponse {
     summary: Option<SessionSummaryResponse>,
     #[serde(skip_serializing_if = "Option::is_none")]
     share: Option<ShareResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    branch: Option<wonopcode_core::session::BranchInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    worktree: Option<wonopcode_core::Worktree>,
 }
 
 #[derive(Debug, Serialize)]
@@ -397,6 +412,7 @@ impl From<wonopcode_core::session::Session> for SessionResponse {
             project_id: s.project_id,
             title: s.title,
             directory: s.directory,
+            tags: s.tags,
             parent_id: s.parent_id,
             created: s.time.created,
             updated: s.time.updated,
@@ -406,6 +422,8 @@ impl From<wonopcode_core::session::Session> for SessionResponse {
                 files: sum.files,
             }),
             share: s.share.map(|sh| ShareResponse { url: sh.url }),
+            branch: s.branch,
+            worktree: s.worktree,
         }
     }
 }
@@ -417,6 +435,41 @@ async fn session_list(State(state): State<AppState>) -> impl IntoResponse {
     Json(response)
 }
 
+#[derive(Debug, Deserialize)]
+struct SessionSearchQuery {
+    q: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SessionSearchResponse {
+    #[serde(flatten)]
+    session: SessionResponse,
+    score: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<String>,
+}
+
+async fn session_search(
+    State(state): State<AppState>,
+    Query(query): Query<SessionSearchQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let instance = state.instance.read().await;
+    match instance.search_sessions(&query.q).await {
+        Ok(hits) => {
+            let response: Vec<SessionSearchResponse> = hits
+                .into_iter()
+                .map(|hit| SessionSearchResponse {
+                    session: hit.session.into(),
+                    score: hit.score,
+                    snippet: hit.snippet,
+                })
+                .collect();
+            Ok(Json(response))
+        }
+        Err(e) => Err(ApiError::internal(e.to_string())),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct CreateSessionRequest {
     #[serde(default)]
@@ -464,6 +517,8 @@ async fn session_get(
 struct UpdateSessionRequest {
     #[serde(default)]
     title: Option<String>,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
 }
 
 async fn session_update(
@@ -476,11 +531,15 @@ async fn session_update(
     let repo = instance.session_repo();
 
     let title_to_set = req.title.clone();
+    let tags_to_set = req.tags.clone();
     match repo
         .update(&project_id, &id, move |session| {
             if let Some(title) = title_to_set {
                 session.title = title;
             }
+            if let Some(tags) = tags_to_set {
+                session.tags = tags;
+            }
         })
         .await
     {
@@ -526,25 +585,23 @@ async fn session_messages(
     }
 }
 
+/// Stream a single message with its parts straight from storage.
 async fn session_message(
     State(state): State<AppState>,
     Path((session_id, message_id)): Path<(String, String)>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
     let instance = state.instance.read().await;
-    let project_id = instance.project_id().await;
 
     match instance
         .session_repo()
-        .messages(&project_id, &session_id, None)
+        .message_stream(&session_id, &message_id)
         .await
     {
-        Ok(messages) => {
-            if let Some(msg) = messages.into_iter().find(|m| m.message.id() == message_id) {
-                Ok(Json(msg))
-            } else {
-                Err(ApiError::not_found("Message not found"))
-            }
-        }
+        Ok(Some(reader)) => Ok((
+            [(header::CONTENT_TYPE, "application/json")],
+            Body::from_stream(ReaderStream::new(reader)),
+        )),
+        Ok(None) => Err(ApiError::not_found("Message not found")),
         Err(e) => Err(ApiError::internal(e.to_string())),
     }
 }
@@ -825,21 +882,152 @@ fn generate_id() -> String {
     format!("{:x}{:x}", duration.as_secs(), duration.subsec_nanos())
 }
 
+/// Request body for session fork.
+#[derive(Debug, Default, Deserialize)]
+struct ForkRequest {
+    /// Message ID to fork at (messages before it are copied).
+    #[serde(default)]
+    message_id: Option<String>,
+}
+
 async fn session_fork(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    req: Option<Json<ForkRequest>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    let instance = state.instance.read().await;
+    let project_id = instance.project_id().await;
+    if instance.get_session(&id).await.is_none() {
+        return Err(ApiError::not_found("Session not found"));
+    }
+
+    let repo = std::sync::Arc::new(instance.session_repo());
+    let branches = wonopcode_core::SessionBranch::new(repo, state.bus.clone());
+    match branches
+        .fork(&project_id, &id, req.message_id.as_deref())
+        .await
+    {
+        Ok(s) => Ok((StatusCode::CREATED, Json(SessionResponse::from(s)))),
+        Err(e) => Err(ApiError::internal(e.to_string())),
+    }
+}
+
+/// Merge a branch's file changes and summary back into its parent.
+async fn session_merge(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
     let instance = state.instance.read().await;
+    let project_id = instance.project_id().await;
+    let repo = std::sync::Arc::new(instance.session_repo());
+    let branches = wonopcode_core::SessionBranch::new(repo, state.bus.clone());
 
-    match instance.get_session(&id).await {
-        Some(parent) => {
-            let child = wonopcode_core::session::Session::child(&parent);
-            match instance.session_repo().create(child).await {
-                Ok(s) => Ok((StatusCode::CREATED, Json(SessionResponse::from(s)))),
-                Err(e) => Err(ApiError::internal(e.to_string())),
-            }
+    match branches.merge(&project_id, &id, instance.directory()).await {
+        Ok(result) => Ok(Json(serde_json::json!({
+            "id": id,
+            "applied": result.applied,
+            "unchanged": result.unchanged,
+            "conflicts": result.conflicts,
+        }))),
+        Err(wonopcode_core::CoreError::Session(
+            wonopcode_core::error::SessionError::NotFound { .. },
+        )) => Err(ApiError::not_found("Session not found")),
+        Err(e @ wonopcode_core::CoreError::Session(_)) => Err(ApiError::bad_request(e.to_string())),
+        Err(e) => Err(ApiError::internal(e.to_string())),
+    }
+}
+
+/// Request body for creating a session worktree.
+#[derive(Debug, Default, Deserialize)]
+struct WorktreeCreateRequest {
+    /// Worktree name (defaults to one derived from the session title).
+    #[serde(default)]
+    name: Option<String>,
+}
+
+/// Move a session into its own git worktree and branch.
+async fn session_worktree_create(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    req: Option<Json<WorktreeCreateRequest>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let req = req.map(|Json(r)| r).unwrap_or_default();
+    let instance = state.instance.read().await;
+    match instance
+        .create_session_worktree(&id, req.name.as_deref())
+        .await
+    {
+        Ok(s) => Ok((StatusCode::CREATED, Json(SessionResponse::from(s)))),
+        Err(e) => Err(worktree_error(e)),
+    }
+}
+
+/// Request body for finishing a session worktree.
+#[derive(Debug, Deserialize)]
+struct WorktreeFinishRequest {
+    /// `merge`, `pr` or `discard`.
+    action: String,
+}
+
+/// Merge a session's worktree back, open a pull request from it, or discard it.
+async fn session_worktree_finish(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<WorktreeFinishRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let Some(finish) = wonopcode_core::WorktreeFinish::parse(&req.action) else {
+        return Err(ApiError::bad_request(format!(
+            "Unknown worktree action: {} (expected merge, pr or discard)",
+            req.action
+        )));
+    };
+
+    let instance = state.instance.read().await;
+    let outcome = instance
+        .finish_session_worktree(&id, finish)
+        .await
+        .map_err(worktree_error)?;
+    let mut response = serde_json::json!({ "id": id, "action": finish.as_str() });
+    match outcome {
+        wonopcode_core::WorktreeOutcome::Closed(wonopcode_core::WorktreeMerge::Merged(files)) => {
+            response["files"] = serde_json::json!(files);
         }
-        None => Err(ApiError::not_found("Session not found")),
+        wonopcode_core::WorktreeOutcome::Closed(_) => {
+            response["files"] = serde_json::json!([]);
+        }
+        wonopcode_core::WorktreeOutcome::PullRequest(url) => {
+            response["url"] = serde_json::json!(url);
+        }
+        wonopcode_core::WorktreeOutcome::Conflict(files) => {
+            response["conflicts"] = serde_json::json!(files);
+        }
+    }
+    Ok(Json(response))
+}
+
+/// List the worktrees wonopcode created in the project repository.
+async fn worktree_list(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
+    let instance = state.instance.read().await;
+    let manager = instance.worktrees().await.map_err(worktree_error)?;
+    match manager.list().await {
+        Ok(worktrees) => Ok(Json(worktrees)),
+        Err(e) => Err(ApiError::internal(e.to_string())),
+    }
+}
+
+/// Map a worktree operation error to an API error.
+fn worktree_error(e: wonopcode_core::CoreError) -> (StatusCode, Json<ApiError>) {
+    match e {
+        wonopcode_core::CoreError::Session(wonopcode_core::error::SessionError::NotFound {
+            ..
+        }) => ApiError::not_found("Session not found"),
+        e @ (wonopcode_core::CoreError::Session(_)
+        | wonopcode_core::CoreError::Config(_)
+        | wonopcode_core::CoreError::Git(_)) => ApiError::bad_request(e.to_string()),
+        e => ApiError::internal(e.to_string()),
     }
 }
 
@@ -1201,6 +1389,25 @@ async fn session_unrevert(
 struct ShareRequest {
     #[serde(default)]
     share_url: Option<String>,
+    /// Link lifetime such as "7d" or "never"; the configured default if not set.
+    #[serde(default)]
+    expires: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+/// Share settings from the config, with the share server URL overridden.
+async fn share_settings(
+    instance: &Instance,
+    share_url: Option<String>,
+) -> Result<ShareSettings, (StatusCode, Json<ApiError>)> {
+    let config = instance.config().await;
+    let mut settings =
+        ShareSettings::from_config(&config).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    if share_url.is_some() {
+        settings.url = share_url;
+    }
+    Ok(settings)
 }
 
 async fn session_share(
@@ -1212,12 +1419,22 @@ async fn session_share(
     let project_id = instance.project_id().await;
     let repo = instance.session_repo();
 
-    match wonopcode_core::share::share_session(&repo, &project_id, &id, req.share_url.as_deref())
-        .await
-    {
+    let settings = share_settings(&instance, req.share_url).await?;
+    let options = ShareOptions {
+        expires_in: match &req.expires {
+            Some(expires) => wonopcode_core::share::parse_expiry(expires)
+                .map_err(|e| ApiError::bad_request(e.to_string()))?,
+            None => settings.expires_in,
+        },
+        password: req.password,
+    };
+
+    match wonopcode_core::share::share_session(&repo, &project_id, &id, &settings, &options).await {
         Ok(share_info) => Ok(Json(serde_json::json!({
             "url": share_info.url,
-            "created_at": share_info.created_at
+            "created_at": share_info.created_at,
+            "expires_at": share_info.expires_at,
+            "password_protected": share_info.password_protected
         }))),
         Err(e) => Err(ApiError::internal(e.to_string())),
     }
@@ -1225,7 +1442,9 @@ async fn session_share(
 
 #[derive(Debug, Deserialize)]
 struct UnshareRequest {
-    secret: String,
+    /// Share secret; the one stored with the session if not set.
+    #[serde(default)]
+    secret: Option<String>,
     #[serde(default)]
     share_url: Option<String>,
 }
@@ -1239,12 +1458,13 @@ async fn session_unshare(
     let project_id = instance.project_id().await;
     let repo = instance.session_repo();
 
+    let settings = share_settings(&instance, req.share_url).await?;
     match wonopcode_core::share::unshare_session(
         &repo,
         &project_id,
         &id,
-        &req.secret,
-        req.share_url.as_deref(),
+        req.secret.as_deref(),
+        &settings,
     )
     .await
     {
@@ -1266,7 +1486,7 @@ struct CommandRequest {
 }
 
 async fn session_command(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path(_id): Path<String>,
     Json(req): Json<CommandRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ApiError>)> {
@@ -1281,8 +1501,13 @@ async fn session_command(
     let cmd_name = (*parts.first().unwrap_or(&"")).to_string();
     let args = parts.get(1).map(|s| (*s).to_string()).unwrap_or_default();
 
-    // Get the command registry
-    let registry = wonopcode_core::CommandRegistry::with_builtins();
+    // Get the command registry, with the project's custom commands
+    let mut registry = wonopcode_core::CommandRegistry::with_builtins();
+    {
+        let instance = state.instance.read().await;
+        let config = instance.config().await;
+        registry.load_project(instance.directory(), &config).await;
+    }
 
     // Look up the command
     let command = match registry.get(&cmd_name) {
@@ -1302,6 +1527,7 @@ async fn session_command(
         "agent": command.agent,
         "model": command.model,
         "subtask": command.subtask,
+        "allowed_tools": command.allowed_tools,
         "message": format!("Command /{} expanded successfully. Use the prompt field to send a regular prompt request.", cmd_name)
     })))
 }
@@ -2845,6 +3071,17 @@ mod tests {
         let json = r#"{}"#;
         let req: UpdateSessionRequest = serde_json::from_str(json).unwrap();
         assert!(req.title.is_none());
+        assert!(req.tags.is_none());
+    }
+
+    #[test]
+    fn test_update_session_request_deserialize_tags() {
+        let json = r#"{"tags": ["tokenizer", "bug"]}"#;
+        let req: UpdateSessionRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            req.tags,
+            Some(vec!["tokenizer".to_string(), "bug".to_string()])
+        );
     }
 
     // === MessagesQuery tests ===
@@ -2927,6 +3164,35 @@ mod tests {
         assert!(req.part_id.is_none());
     }
 
+    // === ForkRequest tests ===
+
+    #[test]
+    fn test_fork_request_deserialize() {
+        let json = r#"{"message_id": "msg-123"}"#;
+        let req: ForkRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.message_id, Some("msg-123".to_string()));
+
+        let req: ForkRequest = serde_json::from_str("{}").unwrap();
+        assert!(req.message_id.is_none());
+    }
+
+    // === Worktree request tests ===
+
+    #[test]
+    fn test_worktree_requests_deserialize() {
+        let req: WorktreeCreateRequest = serde_json::from_str(r#"{"name": "lexer"}"#).unwrap();
+        assert_eq!(req.name.as_deref(), Some("lexer"));
+        let req: WorktreeCreateRequest = serde_json::from_str("{}").unwrap();
+        assert!(req.name.is_none());
+
+        let req: WorktreeFinishRequest = serde_json::from_str(r#"{"action": "pr"}"#).unwrap();
+        assert_eq!(
+            wonopcode_core::WorktreeFinish::parse(&req.action),
+            Some(wonopcode_core::WorktreeFinish::PullRequest)
+        );
+        assert!(serde_json::from_str::<WorktreeFinishRequest>("{}").is_err());
+    }
+
     // === ShareRequest tests ===
 
     #[test]
@@ -2936,6 +3202,14 @@ mod tests {
         assert_eq!(req.share_url, Some("https://example.com/share".to_string()));
     }
 
+    #[test]
+    fn test_share_request_deserialize_options() {
+        let json = r#"{"expires": "7d", "password": "hunter2"}"#;
+        let req: ShareRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.expires.as_deref(), Some("7d"));
+        assert_eq!(req.password.as_deref(), Some("hunter2"));
+    }
+
     #[test]
     fn test_share_request_deserialize_default() {
         let json = r#"{}"#;
@@ -2949,7 +3223,7 @@ mod tests {
     fn test_unshare_request_deserialize() {
         let json = r#"{"secret": "my-secret", "share_url": "https://example.com/share"}"#;
         let req: UnshareRequest = serde_json::from_str(json).unwrap();
-        assert_eq!(req.secret, "my-secret");
+        assert_eq!(req.secret.as_deref(), Some("my-secret"));
         assert_eq!(req.share_url, Some("https://example.com/share".to_string()));
     }
 
@@ -2957,8 +3231,12 @@ mod tests {
     fn test_unshare_request_deserialize_minimal() {
         let json = r#"{"secret": "my-secret"}"#;
         let req: UnshareRequest = serde_json::from_str(json).unwrap();
-        assert_eq!(req.secret, "my-secret");
+        assert_eq!(req.secret.as_deref(), Some("my-secret"));
         assert!(req.share_url.is_none());
+
+        // The secret stored with the session is used if none is given
+        let req: UnshareRequest = serde_json::from_str("{}").unwrap();
+        assert!(req.secret.is_none());
     }
 
     // === CommandRequest tests ===
@@ -3242,11 +3520,14 @@ mod tests {
             project_id: "proj-456".to_string(),
             title: "Test Session".to_string(),
             directory: "/home/user/project".to_string(),
+            tags: vec![],
             parent_id: None,
             created: 1700000000,
             updated: 1700000001,
             summary: None,
             share: None,
+            branch: None,
+            worktree: None,
         };
         let json = serde_json::to_string(&response).unwrap();
         assert!(json.contains("\"id\":\"sess-123\""));
@@ -3297,6 +3578,7 @@ mod tests {
             project_id: "proj-456".to_string(),
             title: "Test Session".to_string(),
             directory: "/home/user/project".to_string(),
+            tags: vec![],
             parent_id: Some("parent-001".to_string()),
             created: 1700000000,
             updated: 1700000001,
@@ -3308,6 +3590,8 @@ mod tests {
             share: Some(ShareResponse {
                 url: "https://share.example.com/abc".to_string(),
             }),
+            branch: None,
+            worktree: None,
         };
         let json = serde_json::to_string(&response).unwrap();
         assert!(json.contains("\"parent_id\":\"parent-001\""));
@@ -3460,17 +3744,21 @@ mod tests {
             project_id: "proj-1".to_string(),
             title: "Title".to_string(),
             directory: "/dir".to_string(),
+            tags: vec![],
             parent_id: None,
             created: 0,
             updated: 0,
             summary: None,
             share: None,
+            branch: None,
+            worktree: None,
         };
         let json = serde_json::to_string(&response).unwrap();
         // None fields should be omitted due to skip_serializing_if
         assert!(!json.contains("parent_id"));
         assert!(!json.contains("summary"));
         assert!(!json.contains("share"));
+        assert!(!json.contains("tags"));
     }
 
     // === Additional edge case tests ===
@@ -3667,6 +3955,7 @@ mod tests {
     fn test_update_session_request_debug() {
         let req = UpdateSessionRequest {
             title: Some("New Title".to_string()),
+            tags: None,
         };
         let debug = format!("{:?}", req);
         assert!(debug.contains("UpdateSessionRequest"));
@@ -3713,7 +4002,11 @@ mod tests {
 
     #[test]
     fn test_share_request_debug() {
-        let req = ShareRequest { share_url: None };
+        let req = ShareRequest {
+            share_url: None,
+            expires: None,
+            password: None,
+        };
         let debug = format!("{:?}", req);
         assert!(debug.contains("ShareRequest"));
     }
@@ -3721,7 +4014,7 @@ mod tests {
     #[test]
     fn test_unshare_request_debug() {
         let req = UnshareRequest {
-            secret: "secret".to_string(),
+            secret: Some("secret".to_string()),
             share_url: None,
         };
         let debug = format!("{:?}", req);
@@ -3,7 +3,7 @@
 use crate::prompt::{new_session_runners, SessionRunners};
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use wonopcode_core::{Bus, Instance, PermissionManager};
+use wonopcode_core::{Bus, Instance, PermissionAudit, PermissionManager};
 use wonopcode_tools::todo::TodoItem;
 
 #[cfg(test)]
@@ -231,6 +231,13 @@ impl AppState {
             state.permission_manager.add_rule(rule).await;
         }
 
+        // Record every permission decision of the project
+        let audit = {
+            let instance = state.instance.read().await;
+            PermissionAudit::new(instance.storage().clone(), instance.project_id().await)
+        };
+        state.permission_manager.set_audit(Some(audit)).await;
+
         state
     }
 
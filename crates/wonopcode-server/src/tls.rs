@@ -0,0 +1,277 @@
+//! TLS for the headless and web servers.
+//!
+//! [`TlsListener`] accepts TCP connections and completes their TLS handshakes
+//! before handing them to `axum::serve`, so the routers are served the same way
+//! with or without TLS. The certificate is either given by the user or a
+//! self-signed one generated on first run with [`self_signed`].
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{Datelike, Utc};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_native_tls::{TlsAcceptor, TlsStream};
+use tracing::{debug, warn};
+
+pub use native_tls::Identity;
+
+/// How long a client has to complete the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a generated certificate is valid. Kept under the 398 days some
+/// clients accept for server certificates.
+const SELF_SIGNED_VALID_DAYS: i64 = 397;
+
+/// Age after which a generated certificate is replaced on startup, before it
+/// expires.
+const SELF_SIGNED_RENEW_AFTER: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+/// TLS errors.
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("Failed to read {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("Failed to write {path}: {source}")]
+    Write { path: PathBuf, source: io::Error },
+
+    #[error("Invalid certificate or key (the key must be PKCS#8 PEM): {0}")]
+    Identity(#[from] native_tls::Error),
+
+    #[error("Failed to generate a certificate: {0}")]
+    Generate(#[from] rcgen::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// Load a PEM certificate (chain) and its PKCS#8 PEM private key.
+pub fn load_identity(cert: &Path, key: &Path) -> Result<Identity, TlsError> {
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|source| TlsError::Read {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    Ok(Identity::from_pkcs8(&read(cert)?, &read(key)?)?)
+}
+
+/// Paths of the self-signed certificate and key in `dir`, generating them if
+/// they are missing or due for renewal. `hosts` are the names and addresses
+/// the certificate is valid for; they only apply when it is generated.
+pub fn self_signed(dir: &Path, hosts: &[String]) -> Result<(PathBuf, PathBuf), TlsError> {
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+
+    let age = std::fs::metadata(&cert_path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+    if key_path.exists() && age.is_some_and(|age| age < SELF_SIGNED_RENEW_AFTER) {
+        return Ok((cert_path, key_path));
+    }
+
+    let mut params = rcgen::CertificateParams::new(hosts.to_vec())?;
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "wonopcode");
+    let today = Utc::now().date_naive();
+    let expires = today + chrono::Duration::days(SELF_SIGNED_VALID_DAYS);
+    params.not_before = rcgen::date_time_ymd(today.year(), today.month() as u8, today.day() as u8);
+    params.not_after =
+        rcgen::date_time_ymd(expires.year(), expires.month() as u8, expires.day() as u8);
+    let key_pair = rcgen::KeyPair::generate()?;
+    let cert = params.self_signed(&key_pair)?;
+
+    let write = |path: &Path, contents: String| {
+        std::fs::write(path, contents).map_err(|source| TlsError::Write {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    std::fs::create_dir_all(dir).map_err(|source| TlsError::Write {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    write_private(&key_path, &key_pair.serialize_pem()).map_err(|source| TlsError::Write {
+        path: key_path.clone(),
+        source,
+    })?;
+    write(&cert_path, cert.pem())?;
+
+    Ok((cert_path, key_path))
+}
+
+/// Write a file only the owner can read, without it ever being readable by
+/// others.
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // The mode only applies to new files, so tighten an existing one too
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes())
+}
+
+/// A listener that serves TLS connections to `axum::serve`.
+///
+/// Handshakes run in their own tasks, so a slow client doesn't hold up the
+/// others; connections that fail the handshake are dropped.
+pub struct TlsListener {
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    /// Accept TLS connections on `listener` with the given identity.
+    pub fn new(listener: TcpListener, identity: Identity) -> Result<Self, TlsError> {
+        let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity)?);
+        let local_addr = listener.local_addr()?;
+        let (tx, connections) = mpsc::channel(64);
+
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            warn!(error = %e, "Failed to accept connection");
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            continue;
+                        }
+                    },
+                    // The server has stopped
+                    _ = tx.closed() => break,
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => debug!(%addr, error = %e, "TLS handshake failed"),
+                        Err(_) => debug!(%addr, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            connections,
+            local_addr,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept task only stops once the listener is dropped
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_signed_is_generated_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let hosts = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+        let (cert, key) = self_signed(dir.path(), &hosts).unwrap();
+        let pem = std::fs::read_to_string(&cert).unwrap();
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----"));
+        assert!(load_identity(&cert, &key).is_ok());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&key).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let (again, _) = self_signed(dir.path(), &hosts).unwrap();
+        assert_eq!(std::fs::read_to_string(again).unwrap(), pem);
+    }
+
+    #[test]
+    fn test_load_identity_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.pem");
+        assert!(matches!(
+            load_identity(&missing, &missing),
+            Err(TlsError::Read { .. })
+        ));
+
+        let junk = dir.path().join("junk.pem");
+        std::fs::write(&junk, "not a certificate").unwrap();
+        assert!(matches!(
+            load_identity(&junk, &junk),
+            Err(TlsError::Identity(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_to_a_client_trusting_the_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = self_signed(dir.path(), &["localhost".to_string()]).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let listener = TlsListener::new(listener, load_identity(&cert, &key).unwrap()).unwrap();
+        let router = axum::Router::new().route("/health", axum::routing::get(|| async { "ok" }));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let ca = native_tls::Certificate::from_pem(&std::fs::read(&cert).unwrap()).unwrap();
+        let connector = native_tls::TlsConnector::builder()
+            .add_root_certificate(ca)
+            .build()
+            .unwrap();
+        let connector = tokio_native_tls::TlsConnector::from(connector);
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let mut stream = connector.connect("localhost", stream).await.unwrap();
+
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        stream
+            .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"));
+        assert!(response.ends_with("ok"));
+
+        // Clients that don't trust the certificate are turned away
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        let untrusted =
+            tokio_native_tls::TlsConnector::from(native_tls::TlsConnector::new().unwrap());
+        assert!(untrusted.connect("localhost", stream).await.is_err());
+    }
+}
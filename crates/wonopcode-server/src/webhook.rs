@@ -0,0 +1,330 @@
+//! Outbound webhooks for server events.
+//!
+//! A headless server runs unattended, so it can post events to configured
+//! URLs: a prompt completing or failing, a tool waiting for permission, or
+//! spend crossing a threshold. Each event is posted as JSON:
+//!
+//! ```json
+//! {
+//!   "event": "completed",
+//!   "delivery": "5f0c…",
+//!   "timestamp": "2026-01-01T12:00:00Z",
+//!   "project": "/home/user/project",
+//!   "session": null,
+//!   "update": { "type": "completed", "text": "…" }
+//! }
+//! ```
+//!
+//! The event is also sent in the `X-Wonopcode-Event` header and the delivery
+//! ID in `X-Wonopcode-Delivery`; it stays the same across retries. With a
+//! secret, `X-Wonopcode-Signature` is `sha256=` followed by the hex
+//! HMAC-SHA256 of the body.
+
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tracing::{debug, warn};
+use wonopcode_core::config::{WebhookConfig, WebhookEvent};
+use wonopcode_protocol::Update;
+
+/// Attempts made to deliver an event.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Delay before the first retry, doubled after each one.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long a webhook has to answer.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The webhook event of an update, if it is one.
+pub fn event_of(update: &Update) -> Option<WebhookEvent> {
+    match update {
+        Update::Completed { .. } => Some(WebhookEvent::Completed),
+        Update::Error { .. } => Some(WebhookEvent::Error),
+        Update::PermissionRequest { .. } => Some(WebhookEvent::PermissionPending),
+        Update::SpendAlert { .. } | Update::BudgetWarning { .. } => {
+            Some(WebhookEvent::CostThreshold)
+        }
+        _ => None,
+    }
+}
+
+/// Signature of a payload, as sent in `X-Wonopcode-Signature`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Posts server events to the configured webhooks.
+#[derive(Clone)]
+pub struct Webhooks {
+    hooks: Vec<WebhookConfig>,
+    client: reqwest::Client,
+    retry_delay: Duration,
+}
+
+impl Webhooks {
+    /// Post events to the given webhooks.
+    pub fn new(hooks: Vec<WebhookConfig>) -> Self {
+        Self {
+            hooks,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            retry_delay: RETRY_DELAY,
+        }
+    }
+
+    /// Check if there are no webhooks.
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Post an update to the webhooks subscribed to its event, in the
+    /// background. `session` is the scoped session it came from, if any.
+    pub fn notify(&self, update: &Update, project: Option<&str>, session: Option<&str>) {
+        let Some(event) = event_of(update) else {
+            return;
+        };
+        let hooks: Vec<WebhookConfig> = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.wants(event))
+            .cloned()
+            .collect();
+        if hooks.is_empty() {
+            return;
+        }
+
+        let delivery = uuid::Uuid::new_v4().to_string();
+        let payload = serde_json::json!({
+            "event": event.as_str(),
+            "delivery": delivery,
+            "timestamp": Utc::now().to_rfc3339(),
+            "project": project,
+            "session": session,
+            "update": update,
+        });
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(error = %e, "Failed to serialize webhook payload");
+                return;
+            }
+        };
+
+        for hook in hooks {
+            let this = self.clone();
+            let body = body.clone();
+            let delivery = delivery.clone();
+            tokio::spawn(async move { this.deliver(&hook, event, &delivery, body).await });
+        }
+    }
+
+    /// Post a payload to a webhook, retrying with backoff on network errors,
+    /// server errors and rate limiting. Returns whether it was accepted.
+    async fn deliver(
+        &self,
+        hook: &WebhookConfig,
+        event: WebhookEvent,
+        delivery: &str,
+        body: Vec<u8>,
+    ) -> bool {
+        let signature = hook.secret.as_deref().map(|secret| sign(secret, &body));
+        let mut delay = self.retry_delay;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let mut request = self
+                .client
+                .post(&hook.url)
+                .header("Content-Type", "application/json")
+                .header("X-Wonopcode-Event", event.as_str())
+                .header("X-Wonopcode-Delivery", delivery)
+                .body(body.clone());
+            if let Some(signature) = &signature {
+                request = request.header("X-Wonopcode-Signature", signature);
+            }
+
+            let retry = match request.send().await {
+                Ok(resp) if resp.status().is_success() => {
+                    debug!(url = %hook.url, event = event.as_str(), "Webhook delivered");
+                    return true;
+                }
+                Ok(resp) => {
+                    let status = resp.status();
+                    warn!(url = %hook.url, %status, attempt, "Webhook rejected the event");
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => {
+                    warn!(url = %hook.url, error = %e, attempt, "Failed to post to webhook");
+                    true
+                }
+            };
+            if !retry || attempt == MAX_ATTEMPTS {
+                break;
+            }
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// Headers and body of a request.
+    type Received = (HeaderMap, Vec<u8>);
+
+    /// Requests received by a test webhook, which fails the first `failures`.
+    #[derive(Clone, Default)]
+    struct Receiver {
+        requests: Arc<Mutex<Vec<Received>>>,
+        failures: usize,
+    }
+
+    async fn receive(
+        State(receiver): State<Receiver>,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        let mut requests = receiver.requests.lock().unwrap();
+        requests.push((headers, body.into_bytes()));
+        if requests.len() <= receiver.failures {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    async fn serve(receiver: Receiver) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new()
+            .route("/hook", post(receive))
+            .with_state(receiver);
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{addr}/hook")
+    }
+
+    fn webhooks(url: String, events: Option<Vec<WebhookEvent>>) -> Webhooks {
+        let mut webhooks = Webhooks::new(vec![WebhookConfig {
+            url,
+            events,
+            secret: Some("secret".to_string()),
+        }]);
+        webhooks.retry_delay = Duration::from_millis(10);
+        webhooks
+    }
+
+    #[test]
+    fn test_events_of_updates() {
+        let completed = Update::Completed {
+            text: String::new(),
+        };
+        assert_eq!(event_of(&completed), Some(WebhookEvent::Completed));
+        assert_eq!(event_of(&Update::Started), None);
+        let alert = Update::SpendAlert {
+            scope: "daily".to_string(),
+            spent: 10.0,
+            alert: 10.0,
+            message: String::new(),
+        };
+        assert_eq!(event_of(&alert), Some(WebhookEvent::CostThreshold));
+    }
+
+    #[tokio::test]
+    async fn test_delivers_signed_payloads_with_retries() {
+        let receiver = Receiver {
+            failures: 2,
+            ..Receiver::default()
+        };
+        let webhooks = webhooks(serve(receiver.clone()).await, None);
+        let hook = &webhooks.hooks[0];
+        let body = br#"{"event":"error"}"#.to_vec();
+        assert!(
+            webhooks
+                .deliver(hook, WebhookEvent::Error, "d1", body.clone())
+                .await
+        );
+
+        let requests = receiver.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let (headers, received) = &requests[2];
+        assert_eq!(received, &body);
+        assert_eq!(headers["x-wonopcode-event"], "error");
+        assert_eq!(headers["x-wonopcode-delivery"], "d1");
+        assert_eq!(
+            headers["x-wonopcode-signature"].to_str().unwrap(),
+            sign("secret", &body)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_the_last_attempt() {
+        let receiver = Receiver {
+            failures: usize::MAX,
+            ..Receiver::default()
+        };
+        let webhooks = webhooks(serve(receiver.clone()).await, None);
+        let hook = &webhooks.hooks[0];
+        assert!(
+            !webhooks
+                .deliver(hook, WebhookEvent::Error, "d1", Vec::new())
+                .await
+        );
+        assert_eq!(
+            receiver.requests.lock().unwrap().len(),
+            MAX_ATTEMPTS as usize
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notify_posts_subscribed_events() {
+        let receiver = Receiver::default();
+        let url = serve(receiver.clone()).await;
+        let webhooks = webhooks(url, Some(vec![WebhookEvent::PermissionPending]));
+
+        webhooks.notify(
+            &Update::Error {
+                error: "failed".to_string(),
+            },
+            None,
+            None,
+        );
+        webhooks.notify(
+            &Update::PermissionRequest {
+                id: "p1".to_string(),
+                tool: "bash".to_string(),
+                action: "execute".to_string(),
+                description: "rm -rf target".to_string(),
+                path: None,
+            },
+            Some("/project"),
+            Some("s1"),
+        );
+
+        for _ in 0..100 {
+            if !receiver.requests.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let requests = receiver.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let payload: serde_json::Value = serde_json::from_slice(&requests[0].1).unwrap();
+        assert_eq!(payload["event"], "permission_pending");
+        assert_eq!(payload["project"], "/project");
+        assert_eq!(payload["session"], "s1");
+        assert_eq!(payload["update"]["tool"], "bash");
+    }
+}
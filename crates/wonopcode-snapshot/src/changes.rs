@@ -0,0 +1,364 @@
+//! Tracking of file changes made by shell commands.
+//!
+//! Shell commands can change any file, so when command tracking is enabled
+//! the project is scanned before and after each command and the difference
+//! is recorded. On rollback, created files are removed and changed or deleted
+//! files are restored from git when they had no uncommitted changes before
+//! the command. Everything else, including all effects outside the project,
+//! is reported as not undone.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::process::Command;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Maximum number of files scanned before tracking gives up on the rest.
+const MAX_TRACKED_FILES: usize = 50_000;
+
+/// Size and modification time of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// The files of a project at a point in time.
+///
+/// Files ignored by git are not scanned, so changes to build output and
+/// installed dependencies are not tracked.
+#[derive(Debug, Clone, Default)]
+pub struct FileManifest {
+    files: BTreeMap<PathBuf, FileStamp>,
+    /// Whether the scan stopped at [`MAX_TRACKED_FILES`].
+    truncated: bool,
+    /// Commit checked out at the time of the scan.
+    git_head: Option<String>,
+    /// Files with uncommitted changes, or not in git, at the time of the scan.
+    git_dirty: BTreeSet<PathBuf>,
+}
+
+impl FileManifest {
+    /// Scan the files under `root`.
+    pub async fn scan(root: &Path) -> Self {
+        let walk_root = root.to_path_buf();
+        let (files, truncated) = tokio::task::spawn_blocking(move || walk(&walk_root))
+            .await
+            .unwrap_or_default();
+
+        let git_head = git(root, &["rev-parse", "HEAD"])
+            .await
+            .map(|out| out.trim().to_string());
+        // Files differing from HEAD, and files git doesn't know about
+        let mut git_dirty = BTreeSet::new();
+        if git_head.is_some() {
+            for args in [
+                &["diff", "HEAD", "--name-only", "-z", "--relative"][..],
+                &["ls-files", "--others", "--exclude-standard", "-z"][..],
+            ] {
+                if let Some(out) = git(root, args).await {
+                    git_dirty.extend(out.split('\0').filter(|p| !p.is_empty()).map(PathBuf::from));
+                }
+            }
+        }
+
+        Self {
+            files,
+            truncated,
+            git_head,
+            git_dirty,
+        }
+    }
+
+    /// Number of scanned files.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no files were scanned.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Files created, changed and deleted between this scan and `after`.
+    pub fn diff(&self, after: &FileManifest) -> FileChanges {
+        let mut changes = FileChanges::default();
+        for (path, stamp) in &after.files {
+            match self.files.get(path) {
+                None => changes.created.push(path.clone()),
+                Some(before) if before != stamp => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        for path in self.files.keys() {
+            if !after.files.contains_key(path) {
+                changes.deleted.push(path.clone());
+            }
+        }
+        changes
+    }
+
+    /// Whether `path` can be restored from git to its state at scan time.
+    fn restorable(&self, path: &Path) -> bool {
+        self.git_head.is_some() && !self.git_dirty.contains(path)
+    }
+}
+
+/// Files created, changed and deleted, relative to the project root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChanges {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub created: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modified: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<PathBuf>,
+}
+
+impl FileChanges {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+}
+
+/// File changes made by one shell command.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeRecord {
+    /// Unique identifier for this record.
+    pub id: String,
+    /// ID of the session that ran the command.
+    pub session_id: String,
+    /// ID of the message that ran the command.
+    pub message_id: String,
+    /// When the command finished.
+    pub timestamp: DateTime<Utc>,
+    /// The command that was run.
+    pub command: String,
+    /// Files the command changed.
+    pub changes: FileChanges,
+    /// Commit checked out before the command.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_head: Option<String>,
+    /// Changed or deleted files that can be restored from `git_head`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub restorable: Vec<PathBuf>,
+    /// Whether the project had too many files to track them all.
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+impl ChangeRecord {
+    /// Record the changes between two scans.
+    pub fn new(
+        session_id: impl Into<String>,
+        message_id: impl Into<String>,
+        command: impl Into<String>,
+        before: &FileManifest,
+        after: &FileManifest,
+    ) -> Self {
+        let changes = before.diff(after);
+        let restorable = changes
+            .modified
+            .iter()
+            .chain(&changes.deleted)
+            .filter(|path| before.restorable(path))
+            .cloned()
+            .collect();
+        Self {
+            id: Uuid::new_v4().to_string(),
+            session_id: session_id.into(),
+            message_id: message_id.into(),
+            timestamp: Utc::now(),
+            command: command.into(),
+            changes,
+            git_head: before.git_head.clone(),
+            restorable,
+            incomplete: before.truncated || after.truncated,
+        }
+    }
+}
+
+/// What a rollback did and could not do.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RollbackReport {
+    /// Files restored to their earlier content.
+    pub restored: Vec<PathBuf>,
+    /// Files created after the rollback point that were removed.
+    pub removed: Vec<PathBuf>,
+    /// Files changed by commands that could not be restored.
+    pub not_restored: Vec<PathBuf>,
+    /// Commands whose effects outside the project, or in files ignored by
+    /// git, were not undone.
+    pub commands: Vec<String>,
+    /// Whether some commands ran in a project too large to track fully.
+    pub incomplete: bool,
+}
+
+impl RollbackReport {
+    /// Whether nothing was rolled back and nothing is left to report.
+    pub fn is_empty(&self) -> bool {
+        self.restored.is_empty()
+            && self.removed.is_empty()
+            && self.not_restored.is_empty()
+            && self.commands.is_empty()
+    }
+
+    /// A human-readable summary.
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        if !self.restored.is_empty() {
+            lines.push(format!("Restored: {}", join_paths(&self.restored)));
+        }
+        if !self.removed.is_empty() {
+            lines.push(format!("Removed: {}", join_paths(&self.removed)));
+        }
+        if !self.not_restored.is_empty() {
+            lines.push(format!(
+                "Could not restore (no clean copy in git): {}",
+                join_paths(&self.not_restored)
+            ));
+        }
+        if !self.commands.is_empty() {
+            lines.push(format!(
+                "Effects outside the project and in ignored files were not undone for: {}",
+                self.commands.join("; ")
+            ));
+        }
+        if self.incomplete {
+            lines.push(format!(
+                "The project has more than {MAX_TRACKED_FILES} files, so some changes were not tracked"
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Walk `root`, skipping files ignored by git and wonopcode's own data.
+fn walk(root: &Path) -> (BTreeMap<PathBuf, FileStamp>, bool) {
+    let walker = ignore::WalkBuilder::new(root)
+        .hidden(false)
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .filter_entry(|entry| !matches!(entry.file_name().to_str(), Some(".git" | ".wonopcode")))
+        .build();
+
+    let mut files = BTreeMap::new();
+    for entry in walker.filter_map(|e| e.ok()) {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if files.len() >= MAX_TRACKED_FILES {
+            return (files, true);
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        files.insert(
+            relative.to_path_buf(),
+            FileStamp {
+                len: metadata.len(),
+                modified: metadata.modified().ok(),
+            },
+        );
+    }
+    (files, false)
+}
+
+/// Run git in `dir`, returning stdout if it succeeded.
+async fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        debug!(
+            ?args,
+            "git failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Content of `path` (relative to `dir`) at `commit`.
+pub(crate) async fn git_show(dir: &Path, commit: &str, path: &Path) -> Option<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!("{commit}:./{}", path.display()))
+        .current_dir(dir)
+        .output()
+        .await
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_diff_detects_created_modified_deleted() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("keep.txt"), "keep").unwrap();
+        std::fs::write(dir.path().join("change.txt"), "old").unwrap();
+        std::fs::write(dir.path().join("delete.txt"), "gone").unwrap();
+
+        let before = FileManifest::scan(dir.path()).await;
+        assert_eq!(before.len(), 3);
+
+        std::fs::write(dir.path().join("change.txt"), "new content").unwrap();
+        std::fs::remove_file(dir.path().join("delete.txt")).unwrap();
+        std::fs::create_dir(dir.path().join("out")).unwrap();
+        std::fs::write(dir.path().join("out/gen.txt"), "generated").unwrap();
+        // wonopcode's own data is not tracked
+        std::fs::create_dir(dir.path().join(".wonopcode")).unwrap();
+        std::fs::write(dir.path().join(".wonopcode/state.json"), "{}").unwrap();
+
+        let after = FileManifest::scan(dir.path()).await;
+        let changes = before.diff(&after);
+        assert_eq!(changes.created, vec![PathBuf::from("out/gen.txt")]);
+        assert_eq!(changes.modified, vec![PathBuf::from("change.txt")]);
+        assert_eq!(changes.deleted, vec![PathBuf::from("delete.txt")]);
+
+        // Without git nothing can be restored
+        let record = ChangeRecord::new("ses_1", "msg_1", "make", &before, &after);
+        assert!(record.restorable.is_empty());
+        assert!(!record.incomplete);
+    }
+
+    #[test]
+    fn test_report_summary() {
+        let report = RollbackReport {
+            restored: vec![PathBuf::from("a.rs")],
+            not_restored: vec![PathBuf::from("b.rs")],
+            commands: vec!["npm install".to_string()],
+            ..Default::default()
+        };
+        let summary = report.summary();
+        assert!(summary.contains("Restored: a.rs"));
+        assert!(summary.contains("Could not restore (no clean copy in git): b.rs"));
+        assert!(summary.contains("npm install"));
+        assert!(!RollbackReport::default().summary().contains("Restored"));
+        assert!(RollbackReport::default().is_empty());
+    }
+}
@@ -5,6 +5,9 @@
 //! - Diff between versions
 //! - Restore files to previous states
 //! - Track changes across sessions
+//! - Track files changed by shell commands and roll them back
+//! - Review the changes of a session hunk by hunk
+//! - Store each file content once, however many snapshots hold it
 //!
 //! # Example
 //!
@@ -35,10 +38,16 @@
 //! # }
 //! ```
 
+mod changes;
 mod error;
+mod objects;
+mod review;
 mod snapshot;
 mod store;
 
+pub use changes::{ChangeRecord, FileChanges, FileManifest, RollbackReport};
 pub use error::{SnapshotError, SnapshotResult};
+pub use objects::GcReport;
+pub use review::{HunkDecision, ReviewFile};
 pub use snapshot::{Snapshot, SnapshotId};
 pub use store::{SnapshotConfig, SnapshotStore};
@@ -0,0 +1,187 @@
+//! Content-addressed storage of snapshotted file contents.
+//!
+//! Each distinct file content is stored once, named by its SHA-256 hash, so
+//! snapshots of unchanged files share storage:
+//! ```text
+//! objects/
+//!   <first 2 hex digits>/
+//!     <remaining 62 hex digits>
+//! ```
+
+use crate::SnapshotResult;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::fs;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// What a garbage collection of stored file contents removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Number of removed objects.
+    pub removed: u32,
+    /// Bytes freed.
+    pub freed_bytes: u64,
+}
+
+/// Store of file contents by hash.
+pub(crate) struct ObjectStore {
+    dir: PathBuf,
+}
+
+impl ObjectStore {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The hash that names `content`.
+    pub(crate) fn hash(content: &[u8]) -> String {
+        Sha256::digest(content)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Where the object named `hash` is stored.
+    pub(crate) fn path(&self, hash: &str) -> PathBuf {
+        let (prefix, rest) = hash.split_at(2.min(hash.len()));
+        self.dir.join(prefix).join(rest)
+    }
+
+    /// Store `content` unless it is already stored, and return its hash.
+    pub(crate) async fn put(&self, content: &[u8]) -> SnapshotResult<String> {
+        let hash = Self::hash(content);
+        let path = self.path(&hash);
+
+        if path.exists() {
+            // Keep a collection running alongside from removing it before
+            // the snapshot referencing it is saved
+            touch(&path).await?;
+            return Ok(hash);
+        }
+
+        // Write to a temporary file first, so that an object is never seen
+        // half written
+        fs::create_dir_all(path.parent().unwrap_or(&self.dir)).await?;
+        let tmp = self.dir.join(format!(".tmp-{}", Uuid::new_v4()));
+        fs::write(&tmp, content).await?;
+        if let Err(e) = fs::rename(&tmp, &path).await {
+            let _ = fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+        debug!("Stored object {}", hash);
+        Ok(hash)
+    }
+
+    /// Remove the objects not in `referenced` that weren't stored or reused
+    /// within `grace`, and leftover temporary files.
+    pub(crate) async fn gc(
+        &self,
+        referenced: &HashSet<String>,
+        grace: Duration,
+    ) -> SnapshotResult<GcReport> {
+        let mut report = GcReport::default();
+        if !self.dir.exists() {
+            return Ok(report);
+        }
+        let cutoff = SystemTime::now()
+            .checked_sub(grace)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut entries = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if !entry.file_type().await?.is_dir() {
+                if name.starts_with(".tmp-") {
+                    remove_if_older(&path, cutoff, &mut report).await;
+                }
+                continue;
+            }
+
+            let mut objects = fs::read_dir(&path).await?;
+            while let Some(object) = objects.next_entry().await? {
+                let hash = format!("{name}{}", object.file_name().to_string_lossy());
+                if !referenced.contains(&hash) {
+                    remove_if_older(&object.path(), cutoff, &mut report).await;
+                }
+            }
+
+            // Fails unless the directory is empty now
+            let _ = fs::remove_dir(&path).await;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Mark a file as modified now.
+async fn touch(path: &Path) -> SnapshotResult<()> {
+    let file = fs::OpenOptions::new().append(true).open(path).await?;
+    file.into_std().await.set_modified(SystemTime::now())?;
+    Ok(())
+}
+
+/// Remove a file if it was last modified before `cutoff`.
+async fn remove_if_older(path: &Path, cutoff: SystemTime, report: &mut GcReport) {
+    let Ok(metadata) = fs::metadata(path).await else {
+        return;
+    };
+    if metadata.modified().is_ok_and(|modified| modified >= cutoff) {
+        return;
+    }
+    match fs::remove_file(path).await {
+        Ok(()) => {
+            report.removed += 1;
+            report.freed_bytes += metadata.len();
+        }
+        Err(e) => warn!("Failed to remove object {:?}: {}", path, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn put_stores_each_content_once() {
+        let dir = TempDir::new().unwrap();
+        let objects = ObjectStore::new(dir.path().join("objects"));
+
+        let first = objects.put(b"hello").await.unwrap();
+        let second = objects.put(b"hello").await.unwrap();
+        let other = objects.put(b"world").await.unwrap();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(first.len(), 64);
+        assert_eq!(fs::read(objects.path(&first)).await.unwrap(), b"hello");
+    }
+
+    #[tokio::test]
+    async fn gc_removes_unreferenced_objects() {
+        let dir = TempDir::new().unwrap();
+        let objects = ObjectStore::new(dir.path().join("objects"));
+        let kept = objects.put(b"kept").await.unwrap();
+        let dropped = objects.put(b"dropped").await.unwrap();
+        let referenced = HashSet::from([kept.clone()]);
+
+        // Recent objects survive the grace period
+        let report = objects
+            .gc(&referenced, Duration::from_secs(3600))
+            .await
+            .unwrap();
+        assert_eq!(report, GcReport::default());
+
+        let report = objects.gc(&referenced, Duration::ZERO).await.unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.freed_bytes, 7);
+        assert!(objects.path(&kept).exists());
+        assert!(!objects.path(&dropped).exists());
+        assert!(!objects.path(&dropped).parent().unwrap().exists());
+    }
+}
@@ -0,0 +1,196 @@
+//! Hunk-by-hunk review of the file changes of a session.
+//!
+//! The changes to a file are split into the hunks of its unified diff against
+//! the file as it was before the session changed it, or when it was last
+//! reviewed. Each hunk can then be accepted, rejected or replaced, and
+//! [`ReviewFile::apply`] builds the resulting file.
+
+use similar::{ChangeTag, TextDiff};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Lines of context around each hunk.
+const CONTEXT_LINES: usize = 3;
+
+/// Trigger of the snapshots that mark files as reviewed.
+pub(crate) const REVIEW_TRIGGER: &str = "review";
+
+/// What to do with a hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkDecision {
+    /// Keep the change.
+    Accept,
+    /// Undo the change.
+    Reject,
+    /// Replace the new side of the hunk, context lines included, with this text.
+    Edit(String),
+}
+
+/// A file changed by a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewFile {
+    /// Path relative to the project root.
+    pub path: PathBuf,
+    /// Content before the changes under review.
+    pub original: String,
+    /// Content now.
+    pub current: String,
+}
+
+impl ReviewFile {
+    /// The changes as a unified diff, with one `@@` header per hunk.
+    pub fn diff(&self) -> String {
+        let path = self.path.display();
+        TextDiff::from_lines(&self.original, &self.current)
+            .unified_diff()
+            .context_radius(CONTEXT_LINES)
+            .header(&format!("a/{path}"), &format!("b/{path}"))
+            .to_string()
+    }
+
+    /// Number of lines added and removed.
+    pub fn line_counts(&self) -> (u32, u32) {
+        let mut added = 0;
+        let mut removed = 0;
+        for change in TextDiff::from_lines(&self.original, &self.current).iter_all_changes() {
+            match change.tag() {
+                ChangeTag::Insert => added += 1,
+                ChangeTag::Delete => removed += 1,
+                ChangeTag::Equal => {}
+            }
+        }
+        (added, removed)
+    }
+
+    /// Number of hunks in [`Self::diff`].
+    pub fn hunk_count(&self) -> usize {
+        hunks(&TextDiff::from_lines(&self.original, &self.current)).len()
+    }
+
+    /// The file after applying a decision for each hunk, in order.
+    ///
+    /// Hunks without a decision keep their change.
+    pub fn apply(&self, decisions: &[Option<HunkDecision>]) -> String {
+        let diff = TextDiff::from_lines(&self.original, &self.current);
+        let old = diff.old_slices();
+        let new = diff.new_slices();
+
+        let mut out = String::new();
+        let mut pos = 0;
+        for (i, hunk) in hunks(&diff).into_iter().enumerate() {
+            out.extend(old[pos..hunk.old.start].iter().copied());
+            match decisions.get(i).cloned().flatten() {
+                Some(HunkDecision::Reject) => out.extend(old[hunk.old.clone()].iter().copied()),
+                Some(HunkDecision::Edit(text)) => {
+                    let replaced = new[hunk.new.clone()].concat();
+                    out.push_str(&match_final_newline(text, &replaced));
+                }
+                Some(HunkDecision::Accept) | None => {
+                    out.extend(new[hunk.new.clone()].iter().copied())
+                }
+            }
+            pos = hunk.old.end;
+        }
+        out.extend(old[pos..].iter().copied());
+        out
+    }
+}
+
+/// Line ranges of a hunk in the old and new file.
+struct Hunk {
+    old: Range<usize>,
+    new: Range<usize>,
+}
+
+/// The hunks of a diff, grouped the same way as its unified diff.
+fn hunks(diff: &TextDiff<'_, '_, '_, str>) -> Vec<Hunk> {
+    diff.grouped_ops(CONTEXT_LINES)
+        .iter()
+        .filter_map(|group| {
+            let (first, last) = (group.first()?, group.last()?);
+            Some(Hunk {
+                old: first.old_range().start..last.old_range().end,
+                new: first.new_range().start..last.new_range().end,
+            })
+        })
+        .collect()
+}
+
+/// End edited text with a newline exactly when the text it replaces does, so
+/// editors adding or dropping the final newline don't join or split lines.
+fn match_final_newline(mut text: String, replaced: &str) -> String {
+    if text.is_empty() {
+        return text;
+    }
+    match (replaced.ends_with('\n'), text.ends_with('\n')) {
+        (true, false) => text.push('\n'),
+        (false, true) => {
+            text.pop();
+        }
+        _ => {}
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file() -> ReviewFile {
+        let original: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+        let current = original
+            .replace("line 2\n", "line two\n")
+            .replace("line 15\n", "line 15\nextra\n");
+        ReviewFile {
+            path: PathBuf::from("src/lib.rs"),
+            original,
+            current,
+        }
+    }
+
+    #[test]
+    fn test_diff_has_a_header_per_hunk() {
+        let file = file();
+        let diff = file.diff();
+        assert!(diff.starts_with("--- a/src/lib.rs\n+++ b/src/lib.rs\n"));
+        assert_eq!(diff.matches("\n@@ ").count(), 2);
+        assert_eq!(file.hunk_count(), 2);
+    }
+
+    #[test]
+    fn test_apply_decisions() {
+        let file = file();
+        assert_eq!(file.apply(&[]), file.current);
+        assert_eq!(
+            file.apply(&[Some(HunkDecision::Reject), Some(HunkDecision::Reject)]),
+            file.original
+        );
+
+        // Reject only the second hunk
+        let applied = file.apply(&[Some(HunkDecision::Accept), Some(HunkDecision::Reject)]);
+        assert!(applied.contains("line two\n"));
+        assert!(!applied.contains("extra"));
+
+        // Replace the first hunk; the missing final newline is added back
+        let applied = file.apply(&[
+            Some(HunkDecision::Edit(
+                "line 1\nline 2!\nline 3\nline 4\nline 5".to_string(),
+            )),
+            None,
+        ]);
+        assert!(applied.starts_with("line 1\nline 2!\nline 3\nline 4\nline 5\nline 6\n"));
+        assert!(applied.contains("extra\n"));
+    }
+
+    #[test]
+    fn test_edit_keeps_missing_final_newline() {
+        let file = ReviewFile {
+            path: PathBuf::from("a.txt"),
+            original: "a\nb".to_string(),
+            current: "a\nc".to_string(),
+        };
+        let applied = file.apply(&[Some(HunkDecision::Edit("a\nd\n".to_string()))]);
+        assert_eq!(applied, "a\nd");
+        assert_eq!(file.apply(&[Some(HunkDecision::Reject)]), "a\nb");
+    }
+}
@@ -2,6 +2,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use uuid::Uuid;
 
@@ -62,6 +63,11 @@ pub struct Snapshot {
     /// The tool or operation that triggered this snapshot.
     #[serde(default)]
     pub trigger: Option<String>,
+
+    /// Hashes of the saved file contents, by file. Empty for snapshots
+    /// that saved copies of the files instead.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blobs: BTreeMap<PathBuf, String>,
 }
 
 impl Snapshot {
@@ -80,6 +86,7 @@ impl Snapshot {
             description: description.into(),
             files,
             trigger: None,
+            blobs: BTreeMap::new(),
         }
     }
 
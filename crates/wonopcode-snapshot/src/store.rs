@@ -1,9 +1,13 @@
 //! Snapshot storage implementation.
 
+use crate::changes::{git_show, ChangeRecord, FileManifest, RollbackReport};
+use crate::objects::{GcReport, ObjectStore};
+use crate::review::{HunkDecision, ReviewFile, REVIEW_TRIGGER};
 use crate::{Snapshot, SnapshotError, SnapshotId, SnapshotResult};
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use similar::{ChangeTag, TextDiff};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use tracing::{debug, info, warn};
@@ -25,6 +29,11 @@ pub struct SnapshotConfig {
 
     /// Whether to automatically clean up old snapshots.
     pub auto_cleanup: bool,
+
+    /// Whether to scan the project around shell commands to record what
+    /// they change. Scanning a large project takes a while.
+    #[serde(default)]
+    pub track_commands: bool,
 }
 
 impl Default for SnapshotConfig {
@@ -35,26 +44,59 @@ impl Default for SnapshotConfig {
             max_per_session: 100,
             max_total_size_mb: 500,
             auto_cleanup: true,
+            track_commands: false,
         }
     }
 }
 
+/// Trigger of the snapshots saving files before a rollback.
+const ROLLBACK_TRIGGER: &str = "rollback";
+
+/// A rollback that can still be undone.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingRollback {
+    /// Earliest point rolled back to.
+    since: DateTime<Utc>,
+    /// When the latest rollback happened.
+    at: DateTime<Utc>,
+    /// Snapshots of the files as they were before the rollback.
+    saved: Vec<SnapshotId>,
+    /// Files that didn't exist before the rollback.
+    absent: Vec<PathBuf>,
+    /// All files touched by the rollback.
+    files: BTreeSet<PathBuf>,
+}
+
+/// How long a stored file content is kept after it was last stored, even
+/// if no snapshot references it, so that a collection doesn't race with a
+/// snapshot being taken.
+const GC_GRACE: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
 /// Storage for file snapshots.
 ///
-/// Snapshots are stored as simple file copies in a directory structure:
+/// File contents are stored once each, by hash, and snapshots reference
+/// them:
 /// ```text
 /// base_dir/
 ///   metadata.json          # List of all snapshots
 ///   snapshots/
 ///     <snapshot_id>/
-///       metadata.json      # Snapshot metadata
+///       metadata.json      # Snapshot metadata, with the hashes of its files
 ///       files/
-///         <relative_path>  # Actual file copies
+///         <relative_path>  # File copies of snapshots from before objects/
+///   objects/
+///     <hash[..2]>/
+///       <hash[2..]>        # File contents
+///   changes/
+///     <record_id>.json     # Files changed by a shell command
 /// ```
 pub struct SnapshotStore {
     /// Base directory for snapshot storage.
     base_dir: PathBuf,
 
+    /// Saved file contents.
+    objects: ObjectStore,
+
     /// Project root directory (for resolving relative paths).
     project_root: PathBuf,
 
@@ -83,6 +125,7 @@ impl SnapshotStore {
         fs::create_dir_all(base_dir.join("snapshots")).await?;
 
         Ok(Self {
+            objects: ObjectStore::new(base_dir.join("objects")),
             base_dir,
             project_root,
             config,
@@ -96,13 +139,26 @@ impl SnapshotStore {
     /// * `session_id` - ID of the current session
     /// * `message_id` - ID of the current message
     /// * `description` - Description of why the snapshot was taken
-    #[allow(clippy::cognitive_complexity)]
     pub async fn take(
         &self,
         files: &[PathBuf],
         session_id: &str,
         message_id: &str,
         description: &str,
+    ) -> SnapshotResult<Snapshot> {
+        self.take_with_trigger(files, session_id, message_id, description, None)
+            .await
+    }
+
+    /// Take a snapshot, recording what triggered it.
+    #[allow(clippy::cognitive_complexity)]
+    async fn take_with_trigger(
+        &self,
+        files: &[PathBuf],
+        session_id: &str,
+        message_id: &str,
+        description: &str,
+        trigger: Option<&str>,
     ) -> SnapshotResult<Snapshot> {
         if !self.config.enabled {
             return Err(SnapshotError::operation_failed("Snapshots are disabled"));
@@ -123,32 +179,26 @@ impl SnapshotStore {
             return Err(SnapshotError::operation_failed("No files to snapshot"));
         }
 
-        let snapshot = Snapshot::new(session_id, message_id, description, normalized_files);
-
-        // Create snapshot directory
-        let snapshot_dir = self.snapshot_dir(&snapshot.id);
-        let files_dir = snapshot_dir.join("files");
-        fs::create_dir_all(&files_dir).await?;
+        let mut snapshot = Snapshot::new(session_id, message_id, description, normalized_files);
+        if let Some(trigger) = trigger {
+            snapshot = snapshot.with_trigger(trigger);
+        }
 
-        // Copy files
+        // Store file contents
         for file in &snapshot.files {
             let src = self.project_root.join(file);
-            let dst = files_dir.join(file);
-
-            // Create parent directories
-            if let Some(parent) = dst.parent() {
-                fs::create_dir_all(parent).await?;
-            }
-
-            // Copy file
-            fs::copy(&src, &dst).await.map_err(|e| {
-                SnapshotError::operation_failed(format!("Failed to copy {}: {}", src.display(), e))
+            let content = fs::read(&src).await.map_err(|e| {
+                SnapshotError::operation_failed(format!("Failed to read {}: {}", src.display(), e))
             })?;
+            let hash = self.objects.put(&content).await?;
+            snapshot.blobs.insert(file.clone(), hash);
 
             debug!("Snapshotted: {:?}", file);
         }
 
         // Save metadata
+        let snapshot_dir = self.snapshot_dir(&snapshot.id);
+        fs::create_dir_all(&snapshot_dir).await?;
         let metadata_path = snapshot_dir.join("metadata.json");
         let metadata_json = serde_json::to_string_pretty(&snapshot)?;
         fs::write(&metadata_path, metadata_json).await?;
@@ -173,14 +223,41 @@ impl SnapshotStore {
     ///
     /// # Arguments
     /// * `snapshot_id` - ID of the snapshot to restore
-    #[allow(clippy::cognitive_complexity)]
     pub async fn restore(&self, snapshot_id: &SnapshotId) -> SnapshotResult<Snapshot> {
+        self.restore_files(snapshot_id, &[]).await
+    }
+
+    /// Restore some files from a snapshot, or all of them if `files` is
+    /// empty.
+    ///
+    /// Fails without restoring anything if a file is not in the snapshot.
+    #[allow(clippy::cognitive_complexity)]
+    pub async fn restore_files(
+        &self,
+        snapshot_id: &SnapshotId,
+        files: &[PathBuf],
+    ) -> SnapshotResult<Snapshot> {
         let snapshot = self.get(snapshot_id).await?;
-        let snapshot_dir = self.snapshot_dir(snapshot_id);
-        let files_dir = snapshot_dir.join("files");
 
-        for file in &snapshot.files {
-            let src = files_dir.join(file);
+        let mut selected = Vec::new();
+        for file in files {
+            let normalized = self.normalize_path(file)?;
+            if !snapshot.files.contains(&normalized) {
+                return Err(SnapshotError::operation_failed(format!(
+                    "File {} not in snapshot {snapshot_id}",
+                    normalized.display()
+                )));
+            }
+            selected.push(normalized);
+        }
+        let files = if selected.is_empty() {
+            &snapshot.files
+        } else {
+            &selected
+        };
+
+        for file in files {
+            let src = self.saved_file(&snapshot, file);
             let dst = self.project_root.join(file);
 
             if !src.exists() {
@@ -193,8 +270,10 @@ impl SnapshotStore {
                 fs::create_dir_all(parent).await?;
             }
 
-            // Copy file back
-            fs::copy(&src, &dst).await.map_err(|e| {
+            // Write the content back, keeping the file's permissions, as
+            // stored contents are shared between files
+            let content = fs::read(&src).await?;
+            fs::write(&dst, content).await.map_err(|e| {
                 SnapshotError::operation_failed(format!(
                     "Failed to restore {}: {}",
                     dst.display(),
@@ -205,15 +284,31 @@ impl SnapshotStore {
             debug!("Restored: {:?}", file);
         }
 
-        info!(
-            "Restored snapshot {} ({} files)",
-            snapshot_id,
-            snapshot.files.len()
-        );
+        info!("Restored snapshot {} ({} files)", snapshot_id, files.len());
 
         Ok(snapshot)
     }
 
+    /// Find a snapshot by its ID or a unique prefix of it.
+    pub async fn find(&self, id: &str) -> SnapshotResult<Snapshot> {
+        if let Ok(snapshot) = self.get(&SnapshotId::from_string(id)).await {
+            return Ok(snapshot);
+        }
+        let mut matches: Vec<_> = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|s| !id.is_empty() && s.id.as_str().starts_with(id))
+            .collect();
+        match matches.len() {
+            0 => Err(SnapshotError::not_found(id)),
+            1 => Ok(matches.remove(0)),
+            n => Err(SnapshotError::operation_failed(format!(
+                "{n} snapshots start with {id}; use more of the ID"
+            ))),
+        }
+    }
+
     /// Get a snapshot by ID.
     pub async fn get(&self, snapshot_id: &SnapshotId) -> SnapshotResult<Snapshot> {
         let snapshot_dir = self.snapshot_dir(snapshot_id);
@@ -270,6 +365,16 @@ impl SnapshotStore {
             .collect())
     }
 
+    /// List the snapshots of a file (newest first).
+    pub async fn list_for_file(&self, file: &Path) -> SnapshotResult<Vec<Snapshot>> {
+        let normalized = self.normalize_path(file)?;
+        let all = self.list().await?;
+        Ok(all
+            .into_iter()
+            .filter(|s| s.files.contains(&normalized))
+            .collect())
+    }
+
     /// Get the most recent snapshot for a file.
     pub async fn latest_for_file(&self, file: &Path) -> SnapshotResult<Option<Snapshot>> {
         let normalized = self.normalize_path(file)?;
@@ -289,10 +394,7 @@ impl SnapshotStore {
             )));
         }
 
-        let snapshot_file = self
-            .snapshot_dir(snapshot_id)
-            .join("files")
-            .join(&normalized);
+        let snapshot_file = self.saved_file(&snapshot, &normalized);
         let current_file = self.project_root.join(&normalized);
 
         let old_content = fs::read_to_string(&snapshot_file).await.unwrap_or_default();
@@ -302,6 +404,9 @@ impl SnapshotStore {
     }
 
     /// Delete a snapshot.
+    ///
+    /// File contents no other snapshot references are removed by the next
+    /// [`Self::gc`].
     pub async fn delete(&self, snapshot_id: &SnapshotId) -> SnapshotResult<()> {
         let snapshot_dir = self.snapshot_dir(snapshot_id);
 
@@ -357,14 +462,554 @@ impl SnapshotStore {
             info!("Cleaned up {} snapshots", deleted);
         }
 
+        self.gc().await?;
+
         Ok(deleted)
     }
 
+    /// Remove the stored file contents that no snapshot references anymore.
+    ///
+    /// Contents stored within the last minutes are kept, as a snapshot
+    /// taken at the same time may not have been saved yet.
+    pub async fn gc(&self) -> SnapshotResult<GcReport> {
+        self.gc_with_grace(GC_GRACE).await
+    }
+
+    async fn gc_with_grace(&self, grace: std::time::Duration) -> SnapshotResult<GcReport> {
+        let referenced: HashSet<String> = self
+            .list()
+            .await?
+            .into_iter()
+            .flat_map(|snapshot| snapshot.blobs.into_values())
+            .collect();
+        let report = self.objects.gc(&referenced, grace).await?;
+        if report.removed > 0 {
+            info!(
+                "Removed {} unreferenced file contents ({} bytes)",
+                report.removed, report.freed_bytes
+            );
+        }
+        Ok(report)
+    }
+
+    /// Whether shell commands should be scanned around, to record what they
+    /// change.
+    pub fn tracks_commands(&self) -> bool {
+        self.config.enabled && self.config.track_commands
+    }
+
+    /// Scan the project files, to find out later what a command changed.
+    pub async fn scan(&self) -> FileManifest {
+        FileManifest::scan(&self.project_root).await
+    }
+
+    /// Record what a shell command changed since `before` was scanned.
+    ///
+    /// Returns `None` if snapshots are disabled or nothing changed.
+    pub async fn record_changes(
+        &self,
+        before: &FileManifest,
+        session_id: &str,
+        message_id: &str,
+        command: &str,
+    ) -> SnapshotResult<Option<ChangeRecord>> {
+        if !self.config.enabled {
+            return Ok(None);
+        }
+
+        let after = self.scan().await;
+        let record = ChangeRecord::new(session_id, message_id, command, before, &after);
+        if record.changes.is_empty() {
+            return Ok(None);
+        }
+
+        let changes_dir = self.base_dir.join("changes");
+        fs::create_dir_all(&changes_dir).await?;
+        let record_json = serde_json::to_string_pretty(&record)?;
+        fs::write(changes_dir.join(format!("{}.json", record.id)), record_json).await?;
+
+        debug!(
+            command = %command,
+            created = record.changes.created.len(),
+            modified = record.changes.modified.len(),
+            deleted = record.changes.deleted.len(),
+            "Recorded command changes"
+        );
+
+        Ok(Some(record))
+    }
+
+    /// List the recorded command changes of a session (oldest first).
+    pub async fn list_changes(&self, session_id: &str) -> SnapshotResult<Vec<ChangeRecord>> {
+        let changes_dir = self.base_dir.join("changes");
+        let mut records = Vec::new();
+        if !changes_dir.exists() {
+            return Ok(records);
+        }
+
+        let mut entries = fs::read_dir(&changes_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let content = fs::read_to_string(entry.path()).await?;
+            match serde_json::from_str::<ChangeRecord>(&content) {
+                Ok(record) if record.session_id == session_id => records.push(record),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to load change record {:?}: {}", entry.path(), e),
+            }
+        }
+
+        records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(records)
+    }
+
+    /// Undo the file changes a session made since `since`.
+    ///
+    /// Snapshots and command changes are undone newest first, so every file
+    /// ends up as it was at `since` where possible. They are kept, along with
+    /// the content the rolled back files had, until the rollback is either
+    /// committed with [`Self::commit_rollback`] or undone with
+    /// [`Self::undo_rollback`].
+    #[allow(clippy::cognitive_complexity)]
+    pub async fn rollback(
+        &self,
+        session_id: &str,
+        since: DateTime<Utc>,
+    ) -> SnapshotResult<RollbackReport> {
+        enum Step {
+            Snapshot(Snapshot),
+            Changes(ChangeRecord),
+        }
+
+        let mut steps: Vec<(DateTime<Utc>, Step)> = Vec::new();
+        for snapshot in self.list_by_session(session_id).await? {
+            if snapshot.timestamp >= since && snapshot.trigger.as_deref() != Some(ROLLBACK_TRIGGER)
+            {
+                steps.push((snapshot.timestamp, Step::Snapshot(snapshot)));
+            }
+        }
+        for record in self.list_changes(session_id).await? {
+            if record.timestamp >= since {
+                steps.push((record.timestamp, Step::Changes(record)));
+            }
+        }
+        steps.sort_by(|a, b| b.0.cmp(&a.0));
+
+        // Save what the files look like now, so the rollback can be undone
+        let mut touched = BTreeSet::new();
+        for (_, step) in &steps {
+            match step {
+                Step::Snapshot(snapshot) => touched.extend(snapshot.files.iter().cloned()),
+                Step::Changes(record) => touched.extend(
+                    record
+                        .changes
+                        .created
+                        .iter()
+                        .chain(&record.changes.modified)
+                        .chain(&record.changes.deleted)
+                        .cloned(),
+                ),
+            }
+        }
+        if !touched.is_empty() {
+            self.save_before_rollback(session_id, since, touched)
+                .await?;
+        }
+
+        let mut restored = BTreeSet::new();
+        let mut removed = BTreeSet::new();
+        let mut not_restored = BTreeSet::new();
+        let mut report = RollbackReport::default();
+
+        for (_, step) in steps {
+            match step {
+                Step::Snapshot(snapshot) => {
+                    self.restore(&snapshot.id).await?;
+                    for file in snapshot.files {
+                        removed.remove(&file);
+                        not_restored.remove(&file);
+                        restored.insert(file);
+                    }
+                }
+                Step::Changes(record) => {
+                    for file in &record.changes.created {
+                        let path = self.project_root.join(file);
+                        if path.is_file() {
+                            fs::remove_file(&path).await?;
+                        }
+                        restored.remove(file);
+                        not_restored.remove(file);
+                        removed.insert(file.clone());
+                    }
+
+                    for file in record
+                        .changes
+                        .modified
+                        .iter()
+                        .chain(&record.changes.deleted)
+                    {
+                        let content = match (&record.git_head, record.restorable.contains(file)) {
+                            (Some(head), true) => git_show(&self.project_root, head, file).await,
+                            _ => None,
+                        };
+                        match content {
+                            Some(content) => {
+                                let path = self.project_root.join(file);
+                                if let Some(parent) = path.parent() {
+                                    fs::create_dir_all(parent).await?;
+                                }
+                                fs::write(&path, content).await?;
+                                not_restored.remove(file);
+                                restored.insert(file.clone());
+                            }
+                            None => {
+                                restored.remove(file);
+                                not_restored.insert(file.clone());
+                            }
+                        }
+                    }
+
+                    report.commands.push(record.command);
+                    report.incomplete |= record.incomplete;
+                }
+            }
+        }
+
+        // Commands in the order they ran
+        report.commands.reverse();
+        report.restored = restored.into_iter().collect();
+        report.removed = removed.into_iter().collect();
+        report.not_restored = not_restored.into_iter().collect();
+
+        info!(
+            session_id = %session_id,
+            restored = report.restored.len(),
+            removed = report.removed.len(),
+            not_restored = report.not_restored.len(),
+            "Rolled back file changes"
+        );
+
+        Ok(report)
+    }
+
+    /// Save the content of files about to be rolled back, adding to the
+    /// pending rollback of the session if there is one.
+    async fn save_before_rollback(
+        &self,
+        session_id: &str,
+        since: DateTime<Utc>,
+        files: BTreeSet<PathBuf>,
+    ) -> SnapshotResult<()> {
+        let mut pending =
+            self.pending_rollback(session_id)
+                .await?
+                .unwrap_or_else(|| PendingRollback {
+                    since,
+                    at: Utc::now(),
+                    saved: Vec::new(),
+                    absent: Vec::new(),
+                    files: BTreeSet::new(),
+                });
+        pending.since = pending.since.min(since);
+        pending.at = Utc::now();
+
+        // Files saved by an earlier rollback keep their content from then
+        let mut existing = Vec::new();
+        for file in files {
+            if !pending.files.insert(file.clone()) {
+                continue;
+            }
+            if self.project_root.join(&file).is_file() {
+                existing.push(file);
+            } else {
+                pending.absent.push(file);
+            }
+        }
+        if !existing.is_empty() {
+            let snapshot = self
+                .take_with_trigger(
+                    &existing,
+                    session_id,
+                    "",
+                    "Before rollback",
+                    Some(ROLLBACK_TRIGGER),
+                )
+                .await?;
+            pending.saved.push(snapshot.id);
+        }
+
+        let path = self.rollback_path(session_id);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&pending)?).await?;
+        Ok(())
+    }
+
+    /// Undo the pending rollback of a session, bringing back the files as
+    /// they were before it. Does nothing if there is none.
+    pub async fn undo_rollback(&self, session_id: &str) -> SnapshotResult<RollbackReport> {
+        let mut report = RollbackReport::default();
+        let Some(pending) = self.pending_rollback(session_id).await? else {
+            return Ok(report);
+        };
+
+        for id in &pending.saved {
+            let snapshot = self.restore(id).await?;
+            report.restored.extend(snapshot.files);
+            self.delete(id).await?;
+        }
+        for file in pending.absent {
+            let path = self.project_root.join(&file);
+            if path.is_file() {
+                fs::remove_file(&path).await?;
+                report.removed.push(file);
+            }
+        }
+        report.restored.sort();
+        fs::remove_file(self.rollback_path(session_id)).await?;
+
+        info!(
+            session_id = %session_id,
+            restored = report.restored.len(),
+            removed = report.removed.len(),
+            "Undid rollback"
+        );
+        Ok(report)
+    }
+
+    /// Make the pending rollback of a session final, deleting the snapshots
+    /// and command changes it undid. Does nothing if there is none.
+    pub async fn commit_rollback(&self, session_id: &str) -> SnapshotResult<()> {
+        let Some(pending) = self.pending_rollback(session_id).await? else {
+            return Ok(());
+        };
+        let undone =
+            |timestamp: DateTime<Utc>| timestamp >= pending.since && timestamp <= pending.at;
+
+        for snapshot in self.list_by_session(session_id).await? {
+            if undone(snapshot.timestamp) || pending.saved.contains(&snapshot.id) {
+                self.delete(&snapshot.id).await?;
+            }
+        }
+        for record in self.list_changes(session_id).await? {
+            if undone(record.timestamp) {
+                let record_path = self
+                    .base_dir
+                    .join("changes")
+                    .join(format!("{}.json", record.id));
+                fs::remove_file(record_path).await?;
+            }
+        }
+        fs::remove_file(self.rollback_path(session_id)).await?;
+
+        debug!(session_id = %session_id, "Committed rollback");
+        Ok(())
+    }
+
+    /// The rollback of a session that can still be undone, if any.
+    async fn pending_rollback(&self, session_id: &str) -> SnapshotResult<Option<PendingRollback>> {
+        match fs::read_to_string(self.rollback_path(session_id)).await {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Where the pending rollback of a session is saved.
+    fn rollback_path(&self, session_id: &str) -> PathBuf {
+        self.base_dir
+            .join("rollbacks")
+            .join(format!("{session_id}.json"))
+    }
+
+    /// The files a session changed since `since` that differ from their
+    /// review baseline: the content when the file was last marked reviewed,
+    /// or else before the session first changed it.
+    ///
+    /// Files without snapshots, such as files the session created, and files
+    /// that aren't text are left out.
+    pub async fn review_files(
+        &self,
+        session_id: &str,
+        since: DateTime<Utc>,
+    ) -> SnapshotResult<Vec<ReviewFile>> {
+        let mut snapshots = self.list_by_session(session_id).await?;
+        snapshots
+            .retain(|s| s.timestamp >= since && s.trigger.as_deref() != Some(ROLLBACK_TRIGGER));
+        snapshots.reverse();
+
+        let mut baselines: BTreeMap<PathBuf, &Snapshot> = BTreeMap::new();
+        for snapshot in &snapshots {
+            let reviewed = snapshot.trigger.as_deref() == Some(REVIEW_TRIGGER);
+            for file in &snapshot.files {
+                if reviewed {
+                    baselines.insert(file.clone(), snapshot);
+                } else {
+                    baselines.entry(file.clone()).or_insert(snapshot);
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        for (path, snapshot) in baselines {
+            let saved = self.saved_file(snapshot, &path);
+            let Ok(original) = fs::read_to_string(&saved).await else {
+                continue;
+            };
+            let current = match fs::read_to_string(self.project_root.join(&path)).await {
+                Ok(content) => content,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                Err(_) => continue,
+            };
+            if original != current {
+                files.push(ReviewFile {
+                    path,
+                    original,
+                    current,
+                });
+            }
+        }
+        Ok(files)
+    }
+
+    /// The net changes a session made to files between `since` and `until`
+    /// (or now): each file's content before the session first changed it,
+    /// and its content at `until`.
+    ///
+    /// Like [`Self::review_files`], files without snapshots and files that
+    /// aren't text are left out.
+    pub async fn session_changes(
+        &self,
+        session_id: &str,
+        since: DateTime<Utc>,
+        until: Option<DateTime<Utc>>,
+    ) -> SnapshotResult<Vec<ReviewFile>> {
+        let mut snapshots = self.list_by_session(session_id).await?;
+        snapshots.retain(|s| {
+            s.timestamp >= since
+                && !matches!(
+                    s.trigger.as_deref(),
+                    Some(REVIEW_TRIGGER | ROLLBACK_TRIGGER)
+                )
+        });
+        snapshots.reverse();
+
+        // The first snapshot of a file before `until` has its starting
+        // content, and the first one after it its content at `until`
+        let mut first: BTreeMap<PathBuf, &Snapshot> = BTreeMap::new();
+        let mut later: BTreeMap<PathBuf, &Snapshot> = BTreeMap::new();
+        for snapshot in &snapshots {
+            let taken = match until {
+                Some(until) if snapshot.timestamp >= until => &mut later,
+                _ => &mut first,
+            };
+            for file in &snapshot.files {
+                taken.entry(file.clone()).or_insert(snapshot);
+            }
+        }
+
+        let mut files = Vec::new();
+        for (path, snapshot) in first {
+            let Ok(original) = fs::read_to_string(self.saved_file(snapshot, &path)).await else {
+                continue;
+            };
+            let current = match later.get(&path) {
+                Some(later) => match fs::read_to_string(self.saved_file(later, &path)).await {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                },
+                None => match fs::read_to_string(self.project_root.join(&path)).await {
+                    Ok(content) => content,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+                    Err(_) => continue,
+                },
+            };
+            if original != current {
+                files.push(ReviewFile {
+                    path,
+                    original,
+                    current,
+                });
+            }
+        }
+        Ok(files)
+    }
+
+    /// Make the current content of files their review baseline, so
+    /// [`Self::review_files`] only shows later changes.
+    pub async fn mark_reviewed(
+        &self,
+        files: &[PathBuf],
+        session_id: &str,
+    ) -> SnapshotResult<Snapshot> {
+        self.take_with_trigger(
+            files,
+            session_id,
+            "",
+            "Reviewed changes",
+            Some(REVIEW_TRIGGER),
+        )
+        .await
+    }
+
+    /// Write a file under review with the decisions for its hunks applied,
+    /// and mark it reviewed if every hunk was decided.
+    ///
+    /// Fails without writing if the file changed since it was read for the
+    /// review.
+    pub async fn apply_review(
+        &self,
+        session_id: &str,
+        file: &ReviewFile,
+        decisions: &[Option<HunkDecision>],
+    ) -> SnapshotResult<()> {
+        let hunks = file.hunk_count();
+        if decisions.len() != hunks {
+            return Err(SnapshotError::operation_failed(format!(
+                "{}: {} hunk decisions for {hunks} hunks",
+                file.path.display(),
+                decisions.len()
+            )));
+        }
+
+        let path = self.project_root.join(&file.path);
+        let current = match fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        if current != file.current {
+            return Err(SnapshotError::operation_failed(format!(
+                "{} changed since the review started",
+                file.path.display()
+            )));
+        }
+
+        let content = file.apply(decisions);
+        if content != current {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(&path, content).await?;
+        }
+        if decisions.iter().all(Option::is_some) && path.exists() {
+            self.mark_reviewed(&[file.path.clone()], session_id).await?;
+        }
+        Ok(())
+    }
+
     /// Get the directory for a snapshot.
     fn snapshot_dir(&self, snapshot_id: &SnapshotId) -> PathBuf {
         self.base_dir.join("snapshots").join(snapshot_id.as_str())
     }
 
+    /// Where the saved content of a file of a snapshot is.
+    fn saved_file(&self, snapshot: &Snapshot, file: &Path) -> PathBuf {
+        match snapshot.blobs.get(file) {
+            Some(hash) => self.objects.path(hash),
+            None => self.snapshot_dir(&snapshot.id).join("files").join(file),
+        }
+    }
+
     /// Normalize a file path to be relative to project root.
     fn normalize_path(&self, path: &Path) -> SnapshotResult<PathBuf> {
         if path.is_absolute() {
@@ -640,6 +1285,79 @@ mod tests {
         assert!(latest.is_none());
     }
 
+    #[tokio::test]
+    async fn restore_files_restores_only_the_given_files() {
+        let (dir, store) = setup_test().await;
+        fs::write(dir.path().join("a.txt"), "a1").await.unwrap();
+        fs::write(dir.path().join("b.txt"), "b1").await.unwrap();
+        let snapshot = store
+            .take(
+                &[PathBuf::from("a.txt"), PathBuf::from("b.txt")],
+                "s1",
+                "m1",
+                "Test",
+            )
+            .await
+            .unwrap();
+        fs::write(dir.path().join("a.txt"), "a2").await.unwrap();
+        fs::write(dir.path().join("b.txt"), "b2").await.unwrap();
+
+        store
+            .restore_files(&snapshot.id, &[dir.path().join("a.txt")])
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).await.unwrap(),
+            "a1"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("b.txt")).await.unwrap(),
+            "b2"
+        );
+
+        // A file outside the snapshot restores nothing
+        let result = store
+            .restore_files(
+                &snapshot.id,
+                &[PathBuf::from("b.txt"), PathBuf::from("c.txt")],
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("b.txt")).await.unwrap(),
+            "b2"
+        );
+    }
+
+    #[tokio::test]
+    async fn find_accepts_unique_id_prefixes() {
+        let (dir, store) = setup_test().await;
+        fs::write(dir.path().join("test.txt"), "content")
+            .await
+            .unwrap();
+        let snapshot = store
+            .take(&[PathBuf::from("test.txt")], "s1", "m1", "Test")
+            .await
+            .unwrap();
+        let id = snapshot.id.as_str();
+
+        assert_eq!(store.find(id).await.unwrap().id, snapshot.id);
+        assert_eq!(store.find(&id[..8]).await.unwrap().id, snapshot.id);
+        assert!(matches!(
+            store.find("not-an-id").await,
+            Err(SnapshotError::NotFound(_))
+        ));
+        assert!(store.find("").await.is_err());
+
+        let files = store.list_for_file(Path::new("test.txt")).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(store
+            .list_for_file(Path::new("other.txt"))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn take_fails_when_snapshots_disabled() {
         let dir = TempDir::new().unwrap();
@@ -789,6 +1507,81 @@ mod tests {
         assert!(after.len() <= 2);
     }
 
+    #[tokio::test]
+    async fn snapshots_share_identical_file_contents() {
+        let (dir, store) = setup_test().await;
+        fs::write(dir.path().join("big.txt"), "x".repeat(10_000))
+            .await
+            .unwrap();
+
+        let first = store
+            .take(&[PathBuf::from("big.txt")], "s1", "m1", "First")
+            .await
+            .unwrap();
+        let second = store
+            .take(&[PathBuf::from("big.txt")], "s1", "m2", "Second")
+            .await
+            .unwrap();
+        let hash = &first.blobs[Path::new("big.txt")];
+        assert_eq!(hash, &second.blobs[Path::new("big.txt")]);
+
+        fs::write(dir.path().join("big.txt"), "changed")
+            .await
+            .unwrap();
+        store.restore(&second.id).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("big.txt"))
+                .await
+                .unwrap(),
+            "x".repeat(10_000)
+        );
+
+        // Still referenced by the first snapshot
+        store.delete(&second.id).await.unwrap();
+        let report = store
+            .gc_with_grace(std::time::Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(report.removed, 0);
+        assert!(store.objects.path(hash).exists());
+
+        store.delete(&first.id).await.unwrap();
+        let report = store
+            .gc_with_grace(std::time::Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.freed_bytes, 10_000);
+        assert!(!store.objects.path(hash).exists());
+    }
+
+    #[tokio::test]
+    async fn restores_snapshots_saved_as_file_copies() {
+        let (dir, store) = setup_test().await;
+        let snapshot = Snapshot::new("s1", "m1", "Old", vec![PathBuf::from("src/a.txt")]);
+        let snapshot_dir = store.snapshot_dir(&snapshot.id);
+        fs::create_dir_all(snapshot_dir.join("files/src"))
+            .await
+            .unwrap();
+        fs::write(snapshot_dir.join("files/src/a.txt"), "old content")
+            .await
+            .unwrap();
+        fs::write(
+            snapshot_dir.join("metadata.json"),
+            serde_json::to_string(&snapshot).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        store.restore(&snapshot.id).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("src/a.txt"))
+                .await
+                .unwrap(),
+            "old content"
+        );
+    }
+
     #[test]
     fn generate_diff_produces_unified_diff_format() {
         let old = "line 1\nline 2\nline 3\n";
@@ -846,4 +1639,235 @@ mod tests {
         let content = fs::read_to_string(&nested).await.unwrap();
         assert_eq!(content, "content");
     }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[tokio::test]
+    async fn rollback_undoes_snapshots_and_command_changes() {
+        let (dir, store) = setup_test().await;
+        let root = dir.path();
+        git(root, &["init", "-q"]);
+        fs::write(root.join("tracked.txt"), "committed")
+            .await
+            .unwrap();
+        fs::write(root.join("edited.txt"), "before edit")
+            .await
+            .unwrap();
+        git(root, &["add", "tracked.txt", "edited.txt"]);
+        git(root, &["commit", "-q", "-m", "init"]);
+        // Uncommitted file, which git can't restore
+        fs::write(root.join("scratch.txt"), "notes").await.unwrap();
+
+        let since = Utc::now();
+
+        // An edit tool snapshot
+        store
+            .take(&[PathBuf::from("edited.txt")], "s1", "m1", "Before edit")
+            .await
+            .unwrap();
+        fs::write(root.join("edited.txt"), "after edit")
+            .await
+            .unwrap();
+
+        // A shell command
+        let before = store.scan().await;
+        fs::write(root.join("tracked.txt"), "overwritten")
+            .await
+            .unwrap();
+        fs::write(root.join("scratch.txt"), "changed notes")
+            .await
+            .unwrap();
+        fs::write(root.join("generated.txt"), "output")
+            .await
+            .unwrap();
+        let record = store
+            .record_changes(&before, "s1", "m1", "./generate.sh")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.restorable, vec![PathBuf::from("tracked.txt")]);
+
+        // Nothing changed, nothing recorded
+        let before = store.scan().await;
+        assert!(store
+            .record_changes(&before, "s1", "m1", "ls")
+            .await
+            .unwrap()
+            .is_none());
+
+        let report = store.rollback("s1", since).await.unwrap();
+        assert_eq!(
+            report.restored,
+            vec![PathBuf::from("edited.txt"), PathBuf::from("tracked.txt")]
+        );
+        assert_eq!(report.removed, vec![PathBuf::from("generated.txt")]);
+        assert_eq!(report.not_restored, vec![PathBuf::from("scratch.txt")]);
+        assert_eq!(report.commands, vec!["./generate.sh".to_string()]);
+
+        let read = |name: &str| std::fs::read_to_string(root.join(name)).unwrap();
+        assert_eq!(read("tracked.txt"), "committed");
+        assert_eq!(read("edited.txt"), "before edit");
+        assert_eq!(read("scratch.txt"), "changed notes");
+        assert!(!root.join("generated.txt").exists());
+
+        // Until committed, the rollback can be undone
+        let report = store.undo_rollback("s1").await.unwrap();
+        assert_eq!(report.restored.len(), 4);
+        assert_eq!(read("edited.txt"), "after edit");
+        assert_eq!(read("tracked.txt"), "overwritten");
+        assert_eq!(read("generated.txt"), "output");
+        assert!(store.undo_rollback("s1").await.unwrap().is_empty());
+
+        // Once committed, undone records and snapshots are gone
+        store.rollback("s1", since).await.unwrap();
+        assert_eq!(read("edited.txt"), "before edit");
+        store.commit_rollback("s1").await.unwrap();
+        assert!(store.list_changes("s1").await.unwrap().is_empty());
+        assert!(store.list_by_session("s1").await.unwrap().is_empty());
+        assert!(store.rollback("s1", since).await.unwrap().is_empty());
+        assert!(store.undo_rollback("s1").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_undo_rollback_removes_restored_files() {
+        let (dir, store) = setup_test().await;
+        let root = dir.path();
+        let since = Utc::now();
+        fs::write(root.join("a.txt"), "one").await.unwrap();
+        store
+            .take(&[PathBuf::from("a.txt")], "s1", "m1", "Before delete")
+            .await
+            .unwrap();
+        fs::remove_file(root.join("a.txt")).await.unwrap();
+
+        // The rollback brings the file back, and undoing it deletes it again
+        store.rollback("s1", since).await.unwrap();
+        assert!(root.join("a.txt").exists());
+        let report = store.undo_rollback("s1").await.unwrap();
+        assert_eq!(report.removed, vec![PathBuf::from("a.txt")]);
+        assert!(!root.join("a.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn test_session_changes() {
+        let (dir, store) = setup_test().await;
+        let root = dir.path();
+        let since = Utc::now();
+        fs::write(root.join("a.txt"), "one\n").await.unwrap();
+        fs::write(root.join("b.txt"), "old\n").await.unwrap();
+
+        for (file, content) in [("a.txt", "two\n"), ("b.txt", "new\n")] {
+            store
+                .take(&[PathBuf::from(file)], "s1", "m1", "Before edit")
+                .await
+                .unwrap();
+            fs::write(root.join(file), content).await.unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let until = Utc::now();
+        store
+            .take(&[PathBuf::from("a.txt")], "s1", "m2", "Before edit")
+            .await
+            .unwrap();
+        fs::write(root.join("a.txt"), "three\n").await.unwrap();
+
+        let files = store.session_changes("s1", since, None).await.unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].original, "one\n");
+        assert_eq!(files[0].current, "three\n");
+        assert_eq!(files[0].line_counts(), (1, 1));
+
+        // Up to a point, later content comes from the next snapshot
+        let files = store
+            .session_changes("s1", since, Some(until))
+            .await
+            .unwrap();
+        assert_eq!(files[0].current, "two\n");
+        assert_eq!(files[1].current, "new\n");
+        assert!(store
+            .session_changes("s2", since, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_review_files() {
+        let (dir, store) = setup_test().await;
+        let root = dir.path();
+        let since = Utc::now();
+        fs::write(root.join("a.txt"), "one\n").await.unwrap();
+        fs::write(root.join("b.txt"), "same\n").await.unwrap();
+
+        for (file, content) in [
+            ("a.txt", "two\n"),
+            ("a.txt", "three\n"),
+            ("b.txt", "same\n"),
+        ] {
+            store
+                .take(&[PathBuf::from(file)], "s1", "m1", "Before edit")
+                .await
+                .unwrap();
+            fs::write(root.join(file), content).await.unwrap();
+        }
+
+        // Against the content before the first edit; unchanged files left out
+        let files = store.review_files("s1", since).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("a.txt"));
+        assert_eq!(files[0].original, "one\n");
+        assert_eq!(files[0].current, "three\n");
+        assert!(store.review_files("s2", since).await.unwrap().is_empty());
+        assert!(store
+            .review_files("s1", Utc::now())
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Only later changes once reviewed
+        store
+            .mark_reviewed(&[PathBuf::from("a.txt")], "s1")
+            .await
+            .unwrap();
+        assert!(store.review_files("s1", since).await.unwrap().is_empty());
+        store
+            .take(&[PathBuf::from("a.txt")], "s1", "m2", "Before edit")
+            .await
+            .unwrap();
+        fs::write(root.join("a.txt"), "four\n").await.unwrap();
+        let files = store.review_files("s1", since).await.unwrap();
+        assert_eq!(files[0].original, "three\n");
+        assert_eq!(files[0].current, "four\n");
+
+        // Rejecting restores the baseline and marks the file reviewed
+        store
+            .apply_review("s1", &files[0], &[Some(HunkDecision::Reject)])
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join("a.txt")).unwrap(),
+            "three\n"
+        );
+        assert!(store.review_files("s1", since).await.unwrap().is_empty());
+
+        // Files changed since the review started aren't touched
+        let stale = ReviewFile {
+            current: "five\n".to_string(),
+            ..files[0].clone()
+        };
+        assert!(store
+            .apply_review("s1", &stale, &[Some(HunkDecision::Accept)])
+            .await
+            .is_err());
+        assert!(store.apply_review("s1", &files[0], &[]).await.is_err());
+    }
 }
@@ -2,13 +2,56 @@
 //!
 //! This storage backend stores each key as a separate JSON file.
 //! Keys are mapped to file paths: `["session", "proj_123", "ses_456"]` -> `session/proj_123/ses_456.json`
-
-use crate::{Storage, StorageError, StorageResult};
+//!
+//! Writes are crash-safe: the new content is first written to a sibling
+//! `*.json.tmp` file and fsynced, then atomically renamed over the target and
+//! the parent directory is fsynced. If the process dies between those steps,
+//! [`JsonStorage::recover`] rolls complete temp files forward and discards
+//! partial ones.
+//!
+//! Transactions are journaled: the whole [`Transaction`] is durably written to
+//! `.journal/` before any key is touched, and the journal entry is removed
+//! once every operation has been applied. Recovery replays journal entries
+//! that are still present, so a committed transaction is never half-applied.
+
+use crate::{
+    Storage, StorageError, StorageReader, StorageResult, StorageStats, Transaction, TxnOp,
+};
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs;
-use tracing::debug;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::{debug, warn};
+
+/// Extension used for write-ahead temp files.
+const TEMP_EXTENSION: &str = "json.tmp";
+
+/// Directory (under the base path) holding transaction journal entries.
+const JOURNAL_DIR: &str = ".journal";
+
+/// Disambiguates journal entries created within the same nanosecond.
+static JOURNAL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Outcome of a [`JsonStorage::recover`] pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Temp files that were complete and have been renamed into place.
+    pub restored: Vec<PathBuf>,
+    /// Temp files that were truncated or corrupt and have been removed.
+    pub discarded: Vec<PathBuf>,
+    /// Transaction journal entries that were replayed.
+    pub replayed: Vec<PathBuf>,
+}
+
+impl RecoveryReport {
+    /// Whether the recovery pass found nothing to do.
+    pub fn is_empty(&self) -> bool {
+        self.restored.is_empty() && self.discarded.is_empty() && self.replayed.is_empty()
+    }
+}
 
 /// JSON file-based storage.
 #[derive(Clone)]
@@ -61,6 +104,188 @@ impl JsonStorage {
         }
         path
     }
+
+    /// Get the base path of this storage.
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
+    /// Recover from writes that were interrupted by a crash.
+    ///
+    /// Walks the storage tree looking for leftover `*.json.tmp` files. A temp
+    /// file that contains valid JSON was fully written and fsynced before the
+    /// crash, so it is renamed over its target. Anything else is a partial
+    /// write and is removed, leaving the previous value intact.
+    ///
+    /// Afterwards, any transaction journal entries left behind by a crash
+    /// mid-commit are replayed and removed. The journal directory is left to
+    /// that step: a temp file there is a transaction that was never committed.
+    ///
+    /// This should be called once at startup, before the storage is shared.
+    pub async fn recover(&self) -> StorageResult<RecoveryReport> {
+        let mut report = RecoveryReport::default();
+        let mut pending = vec![self.base_path.clone()];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(StorageError::Io(e)),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    if dir != self.base_path || entry.file_name() != JOURNAL_DIR {
+                        pending.push(path);
+                    }
+                    continue;
+                }
+
+                let Some(target) = temp_target(&path) else {
+                    continue;
+                };
+
+                let complete = match fs::read(&path).await {
+                    Ok(bytes) => serde_json::from_slice::<serde_json::Value>(&bytes).is_ok(),
+                    Err(_) => false,
+                };
+
+                if complete {
+                    fs::rename(&path, &target).await?;
+                    sync_dir(&dir).await?;
+                    warn!(path = %target.display(), "Recovered interrupted storage write");
+                    report.restored.push(target);
+                } else {
+                    fs::remove_file(&path).await?;
+                    warn!(path = %path.display(), "Discarded partial storage write");
+                    report.discarded.push(path);
+                }
+            }
+        }
+
+        self.replay_journal(&mut report).await?;
+
+        Ok(report)
+    }
+
+    /// Replay committed transactions that were not fully applied.
+    async fn replay_journal(&self, report: &mut RecoveryReport) -> StorageResult<()> {
+        let journal_dir = self.base_path.join(JOURNAL_DIR);
+        let mut entries = match fs::read_dir(&journal_dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(StorageError::Io(e)),
+        };
+
+        let mut journals = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if temp_target(&path).is_some() {
+                // The crash came before the entry was renamed into place, so
+                // the transaction was never committed
+                fs::remove_file(&path).await?;
+                warn!(path = %path.display(), "Discarded uncommitted transaction journal");
+                report.discarded.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "json") {
+                journals.push(path);
+            }
+        }
+        // Entry names start with a timestamp, so this replays in commit order.
+        journals.sort();
+
+        for path in journals {
+            let content = fs::read(&path).await?;
+            match serde_json::from_slice::<Transaction>(&content) {
+                Ok(txn) => {
+                    self.apply_ops(txn.ops()).await?;
+                    warn!(path = %path.display(), "Replayed interrupted transaction");
+                    report.replayed.push(path.clone());
+                }
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Discarded unreadable transaction journal");
+                    report.discarded.push(path.clone());
+                }
+            }
+            fs::remove_file(&path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply transaction operations in order. Each step is idempotent.
+    async fn apply_ops(&self, ops: &[TxnOp]) -> StorageResult<()> {
+        for op in ops {
+            let key: Vec<&str> = op.key().iter().map(|s| s.as_str()).collect();
+            let path = self.key_to_path(&key)?;
+            match op {
+                TxnOp::Write { value, .. } => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).await?;
+                    }
+                    let content = serde_json::to_string_pretty(value)?;
+                    write_durable(&path, content.as_bytes()).await?;
+                }
+                TxnOp::Remove { .. } => match fs::remove_file(&path).await {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(StorageError::Io(e)),
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Generate a unique, time-ordered journal entry name.
+fn journal_entry_name() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let seq = JOURNAL_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{nanos:024}-{}-{seq}.json", std::process::id())
+}
+
+/// Map a temp file path back to the file it was meant to replace.
+fn temp_target(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let stem = name.strip_suffix(".json.tmp")?;
+    if stem.is_empty() {
+        return None;
+    }
+    Some(path.with_file_name(format!("{stem}.json")))
+}
+
+/// Write `content` to `path` durably: temp file, fsync, rename, fsync dir.
+async fn write_durable(path: &Path, content: &[u8]) -> StorageResult<()> {
+    let temp_path = path.with_extension(TEMP_EXTENSION);
+
+    let mut file = fs::File::create(&temp_path).await?;
+    file.write_all(content).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    fs::rename(&temp_path, path).await?;
+
+    if let Some(parent) = path.parent() {
+        sync_dir(parent).await?;
+    }
+
+    Ok(())
+}
+
+/// Fsync a directory so that renames inside it survive a crash.
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> StorageResult<()> {
+    fs::File::open(dir).await?.sync_all().await?;
+    Ok(())
+}
+
+/// Directories cannot be opened for syncing on this platform.
+#[cfg(not(unix))]
+async fn sync_dir(_dir: &Path) -> StorageResult<()> {
+    Ok(())
 }
 
 #[async_trait]
@@ -79,6 +304,50 @@ impl Storage for JsonStorage {
         }
     }
 
+    async fn read_raw(&self, key: &[&str]) -> StorageResult<Option<Vec<u8>>> {
+        let path = self.key_to_path(key)?;
+        debug!(path = %path.display(), "Reading raw bytes from storage");
+
+        match fs::read(&path).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
+    async fn read_range(
+        &self,
+        key: &[&str],
+        offset: u64,
+        len: u64,
+    ) -> StorageResult<Option<Vec<u8>>> {
+        let path = self.key_to_path(key)?;
+        debug!(path = %path.display(), offset, len, "Reading byte range from storage");
+
+        let mut file = match fs::File::open(&path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(StorageError::Io(e)),
+        };
+
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut buf = Vec::new();
+        file.take(len).read_to_end(&mut buf).await?;
+
+        Ok(Some(buf))
+    }
+
+    async fn read_stream(&self, key: &[&str]) -> StorageResult<Option<StorageReader>> {
+        let path = self.key_to_path(key)?;
+        debug!(path = %path.display(), "Streaming from storage");
+
+        match fs::File::open(&path).await {
+            Ok(file) => Ok(Some(Box::pin(file))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(StorageError::Io(e)),
+        }
+    }
+
     async fn write<T: Serialize + Send + Sync>(
         &self,
         key: &[&str],
@@ -95,12 +364,8 @@ impl Storage for JsonStorage {
         // Serialize to JSON
         let content = serde_json::to_string_pretty(value)?;
 
-        // Write atomically (write to temp file, then rename)
-        let temp_path = path.with_extension("json.tmp");
-        fs::write(&temp_path, &content).await?;
-        fs::rename(&temp_path, &path).await?;
-
-        Ok(())
+        // Write atomically and durably (temp file, fsync, rename)
+        write_durable(&path, content.as_bytes()).await
     }
 
     async fn update<T, F>(&self, key: &[&str], editor: F) -> StorageResult<T>
@@ -120,6 +385,27 @@ impl Storage for JsonStorage {
         Ok(value)
     }
 
+    async fn commit(&self, txn: Transaction) -> StorageResult<()> {
+        // Reject bad keys before anything is journaled
+        for op in txn.ops() {
+            let key: Vec<&str> = op.key().iter().map(|s| s.as_str()).collect();
+            self.key_to_path(&key)?;
+        }
+
+        let journal_dir = self.base_path.join(JOURNAL_DIR);
+        fs::create_dir_all(&journal_dir).await?;
+        let journal_path = journal_dir.join(journal_entry_name());
+        debug!(path = %journal_path.display(), ops = txn.len(), "Committing transaction");
+
+        // Once the journal entry is durable the transaction is committed;
+        // if applying fails part-way, recovery finishes the job.
+        write_durable(&journal_path, &serde_json::to_vec(&txn)?).await?;
+        self.apply_ops(txn.ops()).await?;
+        fs::remove_file(&journal_path).await?;
+
+        Ok(())
+    }
+
     async fn remove(&self, key: &[&str]) -> StorageResult<()> {
         let path = self.key_to_path(key)?;
         debug!(path = %path.display(), "Removing from storage");
@@ -162,6 +448,44 @@ impl Storage for JsonStorage {
         Ok(results)
     }
 
+    async fn stats(&self, prefix: &[&str]) -> StorageResult<StorageStats> {
+        let root = self.prefix_to_dir(prefix);
+        debug!(path = %root.display(), "Collecting storage stats");
+
+        let mut stats = StorageStats::default();
+        // (directory, namespace it belongs to; None at the prefix root)
+        let mut pending: Vec<(PathBuf, Option<String>)> = vec![(root, None)];
+
+        while let Some((dir, namespace)) = pending.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(StorageError::Io(e)),
+            };
+
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if name == JOURNAL_DIR {
+                    continue;
+                }
+
+                let metadata = entry.metadata().await?;
+                if metadata.is_dir() {
+                    let ns = namespace.clone().unwrap_or_else(|| name.to_string());
+                    pending.push((path, Some(ns)));
+                } else if let Some(stem) = name.strip_suffix(".json") {
+                    let ns = namespace.clone().unwrap_or_else(|| stem.to_string());
+                    stats.record(ns, metadata.len());
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
     async fn exists(&self, key: &[&str]) -> StorageResult<bool> {
         let path = self.key_to_path(key)?;
         Ok(path.exists())
@@ -390,6 +714,336 @@ mod tests {
         assert!(storage.write(&["valid", "", "path"], &data).await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_read_raw_and_stream() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        let data = TestData {
+            name: "test".to_string(),
+            value: 42,
+        };
+        storage.write(&["test", "data"], &data).await.unwrap();
+
+        let raw = storage.read_raw(&["test", "data"]).await.unwrap().unwrap();
+        let parsed: TestData = serde_json::from_slice(&raw).unwrap();
+        assert_eq!(parsed, data);
+
+        let mut reader = storage
+            .read_stream(&["test", "data"])
+            .await
+            .unwrap()
+            .unwrap();
+        let mut streamed = Vec::new();
+        reader.read_to_end(&mut streamed).await.unwrap();
+        assert_eq!(streamed, raw);
+
+        assert!(storage.read_raw(&["missing"]).await.unwrap().is_none());
+        assert!(storage.read_stream(&["missing"]).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_range() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        storage.write(&["test", "data"], &"abcdef").await.unwrap();
+
+        // Stored as `"abcdef"`, including the quotes.
+        let range = storage.read_range(&["test", "data"], 1, 3).await.unwrap();
+        assert_eq!(range.as_deref(), Some(&b"abc"[..]));
+
+        let tail = storage.read_range(&["test", "data"], 6, 100).await.unwrap();
+        assert_eq!(tail.as_deref(), Some(&b"f\""[..]));
+
+        let past_end = storage.read_range(&["test", "data"], 100, 5).await.unwrap();
+        assert_eq!(past_end.as_deref(), Some(&b""[..]));
+
+        assert!(storage
+            .read_range(&["missing"], 0, 1)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_applies_all_ops() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        let data = TestData {
+            name: "test".to_string(),
+            value: 42,
+        };
+        storage.write(&["old", "item"], &data).await.unwrap();
+
+        storage
+            .transaction(|txn| {
+                txn.write(&["message", "ses_1", "msg_1"], &data)?;
+                txn.write(&["part", "msg_1", "prt_1"], &data)?;
+                txn.remove(&["old", "item"]);
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert!(storage
+            .exists(&["message", "ses_1", "msg_1"])
+            .await
+            .unwrap());
+        assert!(storage.exists(&["part", "msg_1", "prt_1"]).await.unwrap());
+        assert!(!storage.exists(&["old", "item"]).await.unwrap());
+
+        // The journal entry is gone once the commit completes.
+        let journal = std::fs::read_dir(dir.path().join(JOURNAL_DIR)).unwrap();
+        assert_eq!(journal.count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_invalid_key_writes_nothing() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        let result = storage
+            .transaction(|txn| {
+                txn.write(&["good"], &1)?;
+                txn.write(&["..", "bad"], &2)?;
+                Ok(())
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert!(!storage.exists(&["good"]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_builder_error_writes_nothing() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        let result = storage
+            .transaction(|txn| {
+                txn.write(&["good"], &1)?;
+                Err(StorageError::ReadOnly)
+            })
+            .await;
+
+        assert!(matches!(result, Err(StorageError::ReadOnly)));
+        assert!(!storage.exists(&["good"]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_recover_replays_journal() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        // Simulate a crash after the journal was written but before any
+        // operation was applied.
+        let mut txn = Transaction::new();
+        txn.write(&["message", "ses_1", "msg_1"], &TestData::default())
+            .unwrap();
+        txn.write(&["part", "msg_1", "prt_1"], &TestData::default())
+            .unwrap();
+        let journal_dir = dir.path().join(JOURNAL_DIR);
+        std::fs::create_dir_all(&journal_dir).unwrap();
+        std::fs::write(
+            journal_dir.join(journal_entry_name()),
+            serde_json::to_vec(&txn).unwrap(),
+        )
+        .unwrap();
+
+        let report = storage.recover().await.unwrap();
+        assert_eq!(report.replayed.len(), 1);
+
+        assert!(storage
+            .exists(&["message", "ses_1", "msg_1"])
+            .await
+            .unwrap());
+        assert!(storage.exists(&["part", "msg_1", "prt_1"]).await.unwrap());
+        assert_eq!(std::fs::read_dir(&journal_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_recover_discards_partial_journal() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        // A crash while writing the journal leaves only its temp file.
+        let journal_dir = dir.path().join(JOURNAL_DIR);
+        std::fs::create_dir_all(&journal_dir).unwrap();
+        std::fs::write(journal_dir.join("0001.json.tmp"), "{\"ops\": [").unwrap();
+
+        let report = storage.recover().await.unwrap();
+        assert!(report.replayed.is_empty());
+        assert_eq!(report.discarded.len(), 1);
+        assert_eq!(std::fs::read_dir(&journal_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_recover_leaves_the_journal_to_replay() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+        storage
+            .write(&["session", "ses_1"], &TestData::default())
+            .await
+            .unwrap();
+
+        // A pending write, a torn journal entry, and a complete one that was
+        // never renamed into place
+        let new = TestData {
+            name: "new".to_string(),
+            value: 2,
+        };
+        std::fs::write(
+            dir.path().join("session").join("ses_1.json.tmp"),
+            serde_json::to_string(&new).unwrap(),
+        )
+        .unwrap();
+        let journal_dir = dir.path().join(JOURNAL_DIR);
+        std::fs::create_dir_all(&journal_dir).unwrap();
+        std::fs::write(journal_dir.join("0001.json"), "{\"ops\": [").unwrap();
+        let mut txn = Transaction::new();
+        txn.write(&["message", "ses_1", "msg_1"], &TestData::default())
+            .unwrap();
+        std::fs::write(
+            journal_dir.join("0002.json.tmp"),
+            serde_json::to_vec(&txn).unwrap(),
+        )
+        .unwrap();
+
+        let report = storage.recover().await.unwrap();
+        assert_eq!(report.restored, vec![dir.path().join("session/ses_1.json")]);
+        assert!(report.replayed.is_empty());
+        assert_eq!(report.discarded.len(), 2);
+
+        let read: Option<TestData> = storage.read(&["session", "ses_1"]).await.unwrap();
+        assert_eq!(read, Some(new));
+        assert!(!storage
+            .exists(&["message", "ses_1", "msg_1"])
+            .await
+            .unwrap());
+        assert_eq!(std::fs::read_dir(&journal_dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stats_groups_by_namespace() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        storage.write(&["session", "p1", "s1"], &1).await.unwrap();
+        storage.write(&["session", "p1", "s2"], &2).await.unwrap();
+        storage.write(&["message", "s1", "m1"], &3).await.unwrap();
+        storage
+            .transaction(|txn| txn.write(&["part", "m1", "p1"], &4))
+            .await
+            .unwrap();
+
+        let stats = storage.stats(&[]).await.unwrap();
+        assert_eq!(stats.namespaces["session"].items, 2);
+        assert_eq!(stats.namespaces["message"].items, 1);
+        assert_eq!(stats.namespaces["part"].items, 1);
+        assert_eq!(stats.total_items(), 4);
+        assert!(stats.total_bytes() > 0);
+        assert!(!stats.namespaces.contains_key(JOURNAL_DIR));
+
+        // Below a prefix, namespaces are the next key segment
+        let sessions = storage.stats(&["session"]).await.unwrap();
+        assert_eq!(sessions.namespaces["p1"].items, 2);
+
+        let empty = storage.stats(&["missing"]).await.unwrap();
+        assert_eq!(empty.total_items(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_write_leaves_no_temp_file() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        storage
+            .write(&["test", "data"], &TestData::default())
+            .await
+            .unwrap();
+
+        assert!(dir.path().join("test").join("data.json").exists());
+        assert!(!dir.path().join("test").join("data.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_recover_restores_complete_temp_file() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        let old = TestData {
+            name: "old".to_string(),
+            value: 1,
+        };
+        storage.write(&["session", "ses_1"], &old).await.unwrap();
+
+        // Simulate a crash after the temp file was synced but before rename.
+        let new = TestData {
+            name: "new".to_string(),
+            value: 2,
+        };
+        std::fs::write(
+            dir.path().join("session").join("ses_1.json.tmp"),
+            serde_json::to_string(&new).unwrap(),
+        )
+        .unwrap();
+
+        let report = storage.recover().await.unwrap();
+        assert_eq!(report.restored.len(), 1);
+        assert!(report.discarded.is_empty());
+
+        let read: Option<TestData> = storage.read(&["session", "ses_1"]).await.unwrap();
+        assert_eq!(read, Some(new));
+        assert!(!dir.path().join("session").join("ses_1.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn test_recover_discards_partial_temp_file() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path());
+
+        let old = TestData {
+            name: "old".to_string(),
+            value: 1,
+        };
+        storage.write(&["session", "ses_1"], &old).await.unwrap();
+
+        // Simulate a crash halfway through writing the temp file.
+        std::fs::write(
+            dir.path().join("session").join("ses_1.json.tmp"),
+            "{\"name\": \"ne",
+        )
+        .unwrap();
+
+        let report = storage.recover().await.unwrap();
+        assert!(report.restored.is_empty());
+        assert_eq!(report.discarded.len(), 1);
+
+        let read: Option<TestData> = storage.read(&["session", "ses_1"]).await.unwrap();
+        assert_eq!(read, Some(old));
+    }
+
+    #[tokio::test]
+    async fn test_recover_missing_base_dir() {
+        let dir = tempdir().unwrap();
+        let storage = JsonStorage::new(dir.path().join("missing"));
+
+        let report = storage.recover().await.unwrap();
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_temp_target() {
+        assert_eq!(
+            temp_target(Path::new("/a/b/ses_1.json.tmp")),
+            Some(PathBuf::from("/a/b/ses_1.json"))
+        );
+        assert_eq!(temp_target(Path::new("/a/b/ses_1.json")), None);
+        assert_eq!(temp_target(Path::new("/a/b/.json.tmp")), None);
+    }
+
     #[tokio::test]
     async fn test_json_storage_exists_nonexistent() {
         let dir = tempdir().unwrap();
@@ -3,15 +3,27 @@
 //! This crate provides a key-value storage abstraction with multiple backends:
 //! - JSON file storage (default)
 //! - In-memory storage (for testing)
+//!
+//! Multi-key writes that must land together go through
+//! [`Storage::transaction`].
 
 pub mod error;
 pub mod json;
 pub mod memory;
+pub mod stats;
+pub mod transaction;
 
 pub use error::{StorageError, StorageResult};
+pub use stats::{NamespaceStats, StorageStats};
+pub use transaction::{Transaction, TxnOp};
 
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
+use std::pin::Pin;
+use tokio::io::AsyncRead;
+
+/// A boxed async reader over the raw bytes of a stored value.
+pub type StorageReader = Pin<Box<dyn AsyncRead + Send>>;
 
 /// A trait for key-value storage backends.
 ///
@@ -24,6 +36,41 @@ pub trait Storage: Send + Sync {
     /// Returns `None` if the key doesn't exist.
     async fn read<T: DeserializeOwned + Send>(&self, key: &[&str]) -> StorageResult<Option<T>>;
 
+    /// Read the raw serialized bytes of a value without deserializing it.
+    ///
+    /// Returns `None` if the key doesn't exist.
+    async fn read_raw(&self, key: &[&str]) -> StorageResult<Option<Vec<u8>>>;
+
+    /// Read up to `len` raw bytes of a value, starting at byte `offset`.
+    ///
+    /// Reading past the end returns the available bytes (possibly none).
+    /// Returns `None` if the key doesn't exist.
+    async fn read_range(
+        &self,
+        key: &[&str],
+        offset: u64,
+        len: u64,
+    ) -> StorageResult<Option<Vec<u8>>> {
+        Ok(self.read_raw(key).await?.map(|bytes| {
+            let start = usize::try_from(offset)
+                .unwrap_or(usize::MAX)
+                .min(bytes.len());
+            let end = start.saturating_add(usize::try_from(len).unwrap_or(usize::MAX));
+            bytes[start..end.min(bytes.len())].to_vec()
+        }))
+    }
+
+    /// Open a value for streaming reads.
+    ///
+    /// Backends that keep values on disk should override this so that large
+    /// values are never buffered in memory. Returns `None` if the key doesn't exist.
+    async fn read_stream(&self, key: &[&str]) -> StorageResult<Option<StorageReader>> {
+        Ok(self
+            .read_raw(key)
+            .await?
+            .map(|bytes| Box::pin(std::io::Cursor::new(bytes)) as StorageReader))
+    }
+
     /// Write a value to storage.
     ///
     /// Creates parent directories if necessary.
@@ -39,6 +86,26 @@ pub trait Storage: Send + Sync {
         T: DeserializeOwned + Serialize + Send + Sync + Default,
         F: FnOnce(&mut T) + Send;
 
+    /// Apply several writes and removals atomically.
+    ///
+    /// The builder stages operations on a [`Transaction`]; nothing is written
+    /// if it returns an error. Once committed, either every operation is
+    /// visible or (after a crash) none of them are.
+    async fn transaction<F>(&self, build: F) -> StorageResult<()>
+    where
+        F: FnOnce(&mut Transaction) -> StorageResult<()> + Send,
+    {
+        let mut txn = Transaction::new();
+        build(&mut txn)?;
+        if txn.is_empty() {
+            return Ok(());
+        }
+        self.commit(txn).await
+    }
+
+    /// Commit a prepared transaction atomically.
+    async fn commit(&self, txn: Transaction) -> StorageResult<()>;
+
     /// Remove a value from storage.
     async fn remove(&self, key: &[&str]) -> StorageResult<()>;
 
@@ -47,6 +114,9 @@ pub trait Storage: Send + Sync {
     /// Returns the full key paths for each item.
     async fn list(&self, prefix: &[&str]) -> StorageResult<Vec<Vec<String>>>;
 
+    /// Collect item counts and byte sizes under a prefix, grouped by namespace.
+    async fn stats(&self, prefix: &[&str]) -> StorageResult<StorageStats>;
+
     /// Check if a key exists.
     async fn exists(&self, key: &[&str]) -> StorageResult<bool>;
 }
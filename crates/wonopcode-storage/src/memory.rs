@@ -1,6 +1,6 @@
 //! In-memory storage implementation for testing.
 
-use crate::{Storage, StorageError, StorageResult};
+use crate::{Storage, StorageError, StorageResult, StorageStats, Transaction, TxnOp};
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
@@ -51,6 +51,16 @@ impl Storage for MemoryStorage {
         }
     }
 
+    async fn read_raw(&self, key: &[&str]) -> StorageResult<Option<Vec<u8>>> {
+        let key_str = Self::key_to_string(key);
+        let data = self
+            .data
+            .read()
+            .map_err(|e| StorageError::LockPoisoned(e.to_string()))?;
+
+        Ok(data.get(&key_str).map(|json| json.as_bytes().to_vec()))
+    }
+
     async fn write<T: Serialize + Send + Sync>(
         &self,
         key: &[&str],
@@ -79,6 +89,36 @@ impl Storage for MemoryStorage {
         Ok(value)
     }
 
+    async fn commit(&self, txn: Transaction) -> StorageResult<()> {
+        // Serialize everything up front so a failure leaves the map untouched
+        let mut staged = Vec::with_capacity(txn.len());
+        for op in txn.into_ops() {
+            match op {
+                TxnOp::Write { key, value } => {
+                    staged.push((key.join("/"), Some(serde_json::to_string(&value)?)));
+                }
+                TxnOp::Remove { key } => staged.push((key.join("/"), None)),
+            }
+        }
+
+        let mut data = self
+            .data
+            .write()
+            .map_err(|e| StorageError::LockPoisoned(e.to_string()))?;
+        for (key, value) in staged {
+            match value {
+                Some(json) => {
+                    data.insert(key, json);
+                }
+                None => {
+                    data.remove(&key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     async fn remove(&self, key: &[&str]) -> StorageResult<()> {
         let key_str = Self::key_to_string(key);
         let mut data = self
@@ -131,6 +171,24 @@ impl Storage for MemoryStorage {
         Ok(results)
     }
 
+    async fn stats(&self, prefix: &[&str]) -> StorageResult<StorageStats> {
+        let data = self
+            .data
+            .read()
+            .map_err(|e| StorageError::LockPoisoned(e.to_string()))?;
+
+        let mut stats = StorageStats::default();
+        for (key, json) in data.iter() {
+            let segments: Vec<&str> = key.split('/').collect();
+            if segments.len() <= prefix.len() || !segments.starts_with(prefix) {
+                continue;
+            }
+            stats.record(segments[prefix.len()], json.len() as u64);
+        }
+
+        Ok(stats)
+    }
+
     async fn exists(&self, key: &[&str]) -> StorageResult<bool> {
         let key_str = Self::key_to_string(key);
         let data = self
@@ -152,6 +210,66 @@ mod tests {
         value: i32,
     }
 
+    #[tokio::test]
+    async fn test_memory_storage_transaction() {
+        let storage = MemoryStorage::new();
+        storage.write(&["old"], &1).await.unwrap();
+
+        storage
+            .transaction(|txn| {
+                txn.write(&["a", "1"], &"one")?;
+                txn.write(&["a", "2"], &"two")?;
+                txn.remove(&["old"]);
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(storage.list(&["a"]).await.unwrap().len(), 2);
+        assert!(!storage.exists(&["old"]).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_stats() {
+        let storage = MemoryStorage::new();
+        storage.write(&["session", "p1", "s1"], &1).await.unwrap();
+        storage.write(&["message", "s1", "m1"], &22).await.unwrap();
+        storage.write(&["message", "s1", "m2"], &333).await.unwrap();
+
+        let stats = storage.stats(&[]).await.unwrap();
+        assert_eq!(stats.namespaces["session"].items, 1);
+        assert_eq!(stats.namespaces["message"].items, 2);
+        assert_eq!(stats.namespaces["message"].bytes, 5);
+
+        let messages = storage.stats(&["message"]).await.unwrap();
+        assert_eq!(messages.namespaces["s1"].items, 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage_raw_reads() {
+        use tokio::io::AsyncReadExt;
+
+        let storage = MemoryStorage::new();
+        storage.write(&["test", "data"], &"abcdef").await.unwrap();
+
+        let raw = storage.read_raw(&["test", "data"]).await.unwrap();
+        assert_eq!(raw.as_deref(), Some(&b"\"abcdef\""[..]));
+
+        let range = storage.read_range(&["test", "data"], 1, 3).await.unwrap();
+        assert_eq!(range.as_deref(), Some(&b"abc"[..]));
+
+        let mut reader = storage
+            .read_stream(&["test", "data"])
+            .await
+            .unwrap()
+            .unwrap();
+        let mut streamed = Vec::new();
+        reader.read_to_end(&mut streamed).await.unwrap();
+        assert_eq!(streamed, b"\"abcdef\"");
+
+        assert!(storage.read_raw(&["missing"]).await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_memory_storage() {
         let storage = MemoryStorage::new();
@@ -0,0 +1,60 @@
+//! Storage usage statistics.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Item count and size for one namespace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceStats {
+    /// Number of stored values.
+    pub items: u64,
+    /// Total size of the stored values in bytes.
+    pub bytes: u64,
+}
+
+/// Usage statistics for a storage prefix, grouped by namespace.
+///
+/// A namespace is the first key segment below the queried prefix, so
+/// `stats(&[])` groups by `session`, `message`, `part`, etc.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageStats {
+    /// Per-namespace statistics.
+    pub namespaces: BTreeMap<String, NamespaceStats>,
+}
+
+impl StorageStats {
+    /// Record one value of `bytes` size under `namespace`.
+    pub fn record(&mut self, namespace: impl Into<String>, bytes: u64) {
+        let entry = self.namespaces.entry(namespace.into()).or_default();
+        entry.items += 1;
+        entry.bytes += bytes;
+    }
+
+    /// Total number of values across all namespaces.
+    pub fn total_items(&self) -> u64 {
+        self.namespaces.values().map(|n| n.items).sum()
+    }
+
+    /// Total size in bytes across all namespaces.
+    pub fn total_bytes(&self) -> u64 {
+        self.namespaces.values().map(|n| n.bytes).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_stats_record_and_totals() {
+        let mut stats = StorageStats::default();
+        stats.record("message", 100);
+        stats.record("message", 50);
+        stats.record("part", 10);
+
+        assert_eq!(stats.namespaces["message"].items, 2);
+        assert_eq!(stats.namespaces["message"].bytes, 150);
+        assert_eq!(stats.total_items(), 3);
+        assert_eq!(stats.total_bytes(), 160);
+    }
+}
@@ -0,0 +1,125 @@
+//! Multi-key transactions.
+//!
+//! A [`Transaction`] collects writes and removals that a backend then applies
+//! all-or-nothing. Values are serialized when they are staged, so a
+//! transaction that fails to build never touches storage.
+
+use crate::StorageResult;
+use serde::{Deserialize, Serialize};
+
+/// A single staged operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TxnOp {
+    /// Write `value` at `key`.
+    Write {
+        key: Vec<String>,
+        value: serde_json::Value,
+    },
+    /// Remove `key` (a no-op if it doesn't exist).
+    Remove { key: Vec<String> },
+}
+
+impl TxnOp {
+    /// The key this operation touches.
+    pub fn key(&self) -> &[String] {
+        match self {
+            Self::Write { key, .. } | Self::Remove { key } => key,
+        }
+    }
+}
+
+/// A set of operations to apply atomically.
+///
+/// Built inside [`Storage::transaction`](crate::Storage::transaction):
+///
+/// ```ignore
+/// storage
+///     .transaction(|txn| {
+///         txn.write(&["message", session_id, message_id], &message)?;
+///         txn.write(&["part", message_id, part_id], &part)?;
+///         Ok(())
+///     })
+///     .await?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    ops: Vec<TxnOp>,
+}
+
+impl Transaction {
+    /// Create an empty transaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage a write of `value` at `key`.
+    pub fn write<T: Serialize + ?Sized>(&mut self, key: &[&str], value: &T) -> StorageResult<()> {
+        self.ops.push(TxnOp::Write {
+            key: key.iter().map(|s| (*s).to_string()).collect(),
+            value: serde_json::to_value(value)?,
+        });
+        Ok(())
+    }
+
+    /// Stage removal of `key`.
+    pub fn remove(&mut self, key: &[&str]) {
+        self.ops.push(TxnOp::Remove {
+            key: key.iter().map(|s| (*s).to_string()).collect(),
+        });
+    }
+
+    /// The staged operations, in order.
+    pub fn ops(&self) -> &[TxnOp] {
+        &self.ops
+    }
+
+    /// Consume the transaction, returning its operations.
+    pub fn into_ops(self) -> Vec<TxnOp> {
+        self.ops
+    }
+
+    /// Number of staged operations.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether no operations have been staged.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transaction_stages_ops_in_order() {
+        let mut txn = Transaction::new();
+        assert!(txn.is_empty());
+
+        txn.write(&["a", "b"], &42).unwrap();
+        txn.remove(&["c"]);
+
+        assert_eq!(txn.len(), 2);
+        assert_eq!(txn.ops()[0].key(), ["a", "b"]);
+        assert_eq!(
+            txn.ops()[1],
+            TxnOp::Remove {
+                key: vec!["c".into()]
+            }
+        );
+    }
+
+    #[test]
+    fn transaction_roundtrips_through_json() {
+        let mut txn = Transaction::new();
+        txn.write(&["a"], &"value").unwrap();
+        txn.remove(&["b"]);
+
+        let json = serde_json::to_string(&txn).unwrap();
+        let parsed: Transaction = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, txn);
+    }
+}
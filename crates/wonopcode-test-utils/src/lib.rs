@@ -8,6 +8,8 @@
 //! - **Builders**: Builder patterns for constructing test objects
 //! - **Providers**: Test provider implementations for AI model testing
 //! - **Sandbox**: Mock sandbox for testing without containers
+//! - **Scenario**: End-to-end agent runs against a test project, with replayed
+//!   provider responses
 //!
 //! # Example Usage
 //!
@@ -37,9 +39,11 @@ pub mod fixtures;
 pub mod mocks;
 pub mod providers;
 pub mod sandbox;
+pub mod scenario;
 
 // Re-export commonly used items
 pub use fixtures::TestProject;
 pub use mocks::MockCommandExecutor;
 pub use providers::RecordingProvider;
 pub use sandbox::{MockSandbox, SandboxTestScenario};
+pub use scenario::{Scenario, ScenarioRun};
@@ -5,6 +5,7 @@
 use async_stream::try_stream;
 use async_trait::async_trait;
 use futures::stream::BoxStream;
+use serde::Deserialize;
 use std::sync::{Arc, Mutex};
 use wonopcode_provider::{
     error::ProviderError,
@@ -143,6 +144,37 @@ impl RecordingProvider {
         self
     }
 
+    /// Queue the responses of a replay fixture.
+    ///
+    /// A fixture is a JSON array of responses, in the order they are returned:
+    ///
+    /// ```json
+    /// [
+    ///     {"tool_calls": [{"name": "read", "arguments": {"filePath": "a.txt"}}]},
+    ///     {"thinking": "The file is short.", "text": "It says hello."},
+    ///     {"text": "Done."},
+    ///     {"error": "Rate limit exceeded"}
+    /// ]
+    /// ```
+    ///
+    /// Tool calls without an `id` are numbered `call_1`, `call_2` and so on.
+    pub fn with_fixture(self, json: &str) -> Result<Self, serde_json::Error> {
+        let fixture: Vec<FixtureResponse> = serde_json::from_str(json)?;
+        let mut call_count = 0;
+        {
+            let mut responses = self.responses.lock().unwrap();
+            for response in fixture {
+                responses.push(response.into_response(&mut call_count));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Create a provider that replays a fixture; see [`Self::with_fixture`].
+    pub fn from_fixture(json: &str) -> Result<Self, serde_json::Error> {
+        Self::new().with_fixture(json)
+    }
+
     /// Set the default response when queue is empty.
     pub fn with_default_response(self, response: ProviderResponse) -> Self {
         *self.default_response.lock().unwrap() = response;
@@ -185,6 +217,58 @@ impl RecordingProvider {
     }
 }
 
+/// A response in a replay fixture.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FixtureResponse {
+    ToolCalls { tool_calls: Vec<FixtureToolCall> },
+    Thinking { thinking: String, text: String },
+    Text { text: String },
+    Error { error: String },
+}
+
+/// A tool call in a replay fixture.
+#[derive(Debug, Deserialize)]
+struct FixtureToolCall {
+    #[serde(default)]
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+impl FixtureResponse {
+    fn into_response(self, call_count: &mut usize) -> ProviderResponse {
+        match self {
+            FixtureResponse::ToolCalls { tool_calls } => {
+                let mut calls: Vec<(String, String, String)> = tool_calls
+                    .into_iter()
+                    .map(|call| {
+                        *call_count += 1;
+                        let id = call.id.unwrap_or_else(|| format!("call_{call_count}"));
+                        (id, call.name, call.arguments.to_string())
+                    })
+                    .collect();
+                if calls.len() == 1 {
+                    let (id, name, arguments) = calls.remove(0);
+                    ProviderResponse::ToolCall {
+                        id,
+                        name,
+                        arguments,
+                    }
+                } else {
+                    ProviderResponse::MultipleToolCalls(calls)
+                }
+            }
+            FixtureResponse::Thinking { thinking, text } => {
+                ProviderResponse::TextWithThinking { thinking, text }
+            }
+            FixtureResponse::Text { text } => ProviderResponse::Text(text),
+            FixtureResponse::Error { error } => ProviderResponse::Error(error),
+        }
+    }
+}
+
 impl Default for RecordingProvider {
     fn default() -> Self {
         Self::new()
@@ -650,6 +734,51 @@ mod tests {
         assert!(!provider.was_sent("goodbye"));
     }
 
+    #[test]
+    fn test_recording_provider_fixture() {
+        let provider = RecordingProvider::from_fixture(
+            r#"[
+                {"tool_calls": [{"name": "read", "arguments": {"filePath": "a.txt"}}]},
+                {"tool_calls": [{"id": "x", "name": "glob"}, {"name": "grep"}]},
+                {"thinking": "Hmm.", "text": "Done."},
+                {"text": "Bye."},
+                {"error": "Rate limit exceeded"}
+            ]"#,
+        )
+        .unwrap();
+
+        let responses = provider.responses.lock().unwrap();
+        assert_eq!(responses.len(), 5);
+        match &responses[0] {
+            ProviderResponse::ToolCall {
+                id,
+                name,
+                arguments,
+            } => {
+                assert_eq!(id, "call_1");
+                assert_eq!(name, "read");
+                assert_eq!(arguments, r#"{"filePath":"a.txt"}"#);
+            }
+            other => panic!("expected a tool call, got {other:?}"),
+        }
+        match &responses[1] {
+            ProviderResponse::MultipleToolCalls(calls) => {
+                assert_eq!(calls[0].0, "x");
+                assert_eq!(calls[1].0, "call_3");
+                assert_eq!(calls[1].2, "null");
+            }
+            other => panic!("expected tool calls, got {other:?}"),
+        }
+        assert!(matches!(
+            &responses[2],
+            ProviderResponse::TextWithThinking { .. }
+        ));
+        assert!(matches!(&responses[3], ProviderResponse::Text(t) if t == "Bye."));
+        assert!(matches!(&responses[4], ProviderResponse::Error(_)));
+
+        assert!(RecordingProvider::from_fixture(r#"[{"unknown": 1}]"#).is_err());
+    }
+
     #[tokio::test]
     async fn test_replay_provider() {
         let provider = ReplayProvider::new()
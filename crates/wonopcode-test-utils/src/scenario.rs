@@ -0,0 +1,588 @@
+//! End-to-end scenarios.
+//!
+//! A [`Scenario`] runs the headless prompt loop against a [`TestProject`],
+//! with provider responses replayed by a [`RecordingProvider`] and, when
+//! asked for, tools running in a [`MockSandbox`]. Nothing calls a live API
+//! or starts a container, so runs are deterministic. The resulting
+//! [`ScenarioRun`] asserts on the tool calls made, the files left behind,
+//! the events published on the bus and the conversation's transcript.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use serde_json::json;
+//! use wonopcode_test_utils::Scenario;
+//!
+//! #[tokio::test]
+//! async fn test_edit_and_revert() {
+//!     let mut run = Scenario::new()
+//!         .with_file("src/lib.rs", "pub fn answer() -> u32 { 41 }\n")
+//!         .with_tool_call(
+//!             "call_1",
+//!             "edit",
+//!             json!({"filePath": "src/lib.rs", "oldString": "41", "newString": "42"}),
+//!         )
+//!         .with_response("Fixed the answer.")
+//!         .run("Fix the answer")
+//!         .await;
+//!
+//!     run.assert_tool_sequence(&["edit"])
+//!         .assert_file("src/lib.rs", "pub fn answer() -> u32 { 42 }\n");
+//!
+//!     run.revert_edits().await;
+//!     run.assert_file("src/lib.rs", "pub fn answer() -> u32 { 41 }\n");
+//! }
+//! ```
+
+use crate::assertions::assert_strings_equal;
+use crate::fixtures::{BuiltTestProject, TestProject};
+use crate::providers::RecordingProvider;
+use crate::sandbox::MockSandbox;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio_util::sync::CancellationToken;
+use wonopcode_core::prompt::ToolCallResult;
+use wonopcode_core::session::{Session, SessionRepository};
+use wonopcode_core::{Bus, EventCapture, PromptConfig, PromptLoop, PromptResult};
+use wonopcode_provider::message::{ContentPart, Message, Role};
+use wonopcode_sandbox::{SandboxOutput, SandboxRuntime};
+use wonopcode_snapshot::{SnapshotConfig, SnapshotStore};
+use wonopcode_storage::json::JsonStorage;
+use wonopcode_tools::bash::BashTool;
+use wonopcode_tools::ToolRegistry;
+
+/// A scenario to run against a test project.
+///
+/// Responses are returned in the order they are added, one per provider
+/// call: a tool call response makes the loop run the tool and call the
+/// provider again, a text response ends the turn.
+pub struct Scenario {
+    project: TestProject,
+    provider: RecordingProvider,
+    sandbox: bool,
+    command_responses: Vec<(String, SandboxOutput)>,
+    tools: Option<ToolRegistry>,
+    max_steps: Option<usize>,
+}
+
+impl Scenario {
+    /// Create a scenario with an empty project.
+    pub fn new() -> Self {
+        Self {
+            project: TestProject::new(),
+            provider: RecordingProvider::new(),
+            sandbox: false,
+            command_responses: Vec::new(),
+            tools: None,
+            max_steps: None,
+        }
+    }
+
+    /// Use `project` as the project, instead of an empty one.
+    pub fn with_project(mut self, project: TestProject) -> Self {
+        self.project = project;
+        self
+    }
+
+    /// Add a file to the project.
+    pub fn with_file(mut self, path: impl AsRef<Path>, contents: impl Into<String>) -> Self {
+        self.project = self.project.with_file(path, contents);
+        self
+    }
+
+    /// Queue a text response.
+    pub fn with_response(mut self, text: impl Into<String>) -> Self {
+        self.provider = self.provider.with_response(text);
+        self
+    }
+
+    /// Queue a tool call response.
+    pub fn with_tool_call(mut self, id: &str, name: &str, arguments: serde_json::Value) -> Self {
+        self.provider = self
+            .provider
+            .with_tool_call(id, name, &arguments.to_string());
+        self
+    }
+
+    /// Queue the responses of a replay fixture; see
+    /// [`RecordingProvider::with_fixture`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the fixture is invalid.
+    pub fn with_fixture(mut self, json: &str) -> Self {
+        self.provider = self
+            .provider
+            .with_fixture(json)
+            .unwrap_or_else(|e| panic!("Invalid replay fixture: {e}"));
+        self
+    }
+
+    /// Queue the responses of a replay fixture file.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file can't be read or the fixture is invalid.
+    pub fn with_fixture_file(self, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read fixture {}: {}", path.display(), e));
+        self.with_fixture(&json)
+    }
+
+    /// Run tools in a [`MockSandbox`] holding a copy of the project, and
+    /// offer the `bash` tool.
+    ///
+    /// Changes then stay in the sandbox: snapshots and [`ScenarioRun::revert_edits`]
+    /// cover the project on disk, so test them without a sandbox.
+    pub fn with_sandbox(mut self) -> Self {
+        self.sandbox = true;
+        self
+    }
+
+    /// Answer `command` in the sandbox with `output`. Enables the sandbox.
+    pub fn with_command_response(mut self, command: &str, output: SandboxOutput) -> Self {
+        self.sandbox = true;
+        self.command_responses.push((command.to_string(), output));
+        self
+    }
+
+    /// Offer these tools instead of the built-in ones.
+    pub fn with_tools(mut self, tools: ToolRegistry) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Stop a turn after this many provider calls.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Build the project and run the first turn with `prompt`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the session can't be set up or the prompt loop fails.
+    pub async fn run(self, prompt: &str) -> ScenarioRun {
+        let project = self.project.build();
+        let root = project.path().to_path_buf();
+        let state = TempDir::new().expect("Failed to create temp directory");
+
+        let bus = Bus::new();
+        let events = bus.capture();
+        let session_repo = Arc::new(SessionRepository::new(
+            JsonStorage::new(state.path().join("storage")),
+            bus.clone(),
+        ));
+        let session = session_repo
+            .create(Session::new("scenario", root.display().to_string()))
+            .await
+            .unwrap_or_else(|e| panic!("Failed to create session: {e}"));
+        let snapshots = Arc::new(
+            SnapshotStore::new(
+                state.path().join("snapshots"),
+                root.clone(),
+                SnapshotConfig::default(),
+            )
+            .await
+            .unwrap_or_else(|e| panic!("Failed to create snapshot store: {e}")),
+        );
+
+        let mut tools = self.tools.unwrap_or_else(ToolRegistry::with_builtins);
+        let sandbox = if self.sandbox {
+            let mut sandbox = MockSandbox::new(&root);
+            for file in project_files(&root) {
+                let content = std::fs::read(&file)
+                    .unwrap_or_else(|e| panic!("Failed to read {}: {}", file.display(), e));
+                sandbox = sandbox.with_file(file, content);
+            }
+            for (command, output) in self.command_responses {
+                sandbox = sandbox.with_command_response(&command, output);
+            }
+            if tools.get("bash").is_none() {
+                tools.register(Arc::new(BashTool));
+            }
+            Some(Arc::new(sandbox))
+        } else {
+            None
+        };
+
+        let provider = Arc::new(self.provider);
+        let prompt_loop = PromptLoop::with_tracking(
+            provider.clone(),
+            Arc::new(tools),
+            session_repo,
+            bus,
+            CancellationToken::new(),
+            Some(snapshots.clone()),
+            None,
+        )
+        .with_sandbox(
+            sandbox
+                .clone()
+                .map(|sandbox| sandbox as Arc<dyn SandboxRuntime>),
+        );
+
+        let mut config = PromptConfig::default();
+        if let Some(max_steps) = self.max_steps {
+            config.max_steps = max_steps;
+        }
+
+        let mut run = ScenarioRun {
+            project,
+            _state: state,
+            provider,
+            sandbox,
+            events,
+            session,
+            snapshots,
+            prompt_loop,
+            config,
+            results: Vec::new(),
+            transcript: String::new(),
+        };
+        run.prompt(prompt).await;
+        run
+    }
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A scenario that has run, with the project, session and bus it ran on.
+pub struct ScenarioRun {
+    project: BuiltTestProject,
+    /// Session storage and snapshots, kept out of the project.
+    _state: TempDir,
+    provider: Arc<RecordingProvider>,
+    sandbox: Option<Arc<MockSandbox>>,
+    events: EventCapture,
+    session: Session,
+    snapshots: Arc<SnapshotStore>,
+    prompt_loop: PromptLoop,
+    config: PromptConfig,
+    results: Vec<PromptResult>,
+    transcript: String,
+}
+
+impl ScenarioRun {
+    /// Run another turn in the same session.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the prompt loop fails.
+    pub async fn prompt(&mut self, prompt: &str) -> &mut Self {
+        let result = self
+            .prompt_loop
+            .run(&self.session, prompt, self.config.clone())
+            .await
+            .unwrap_or_else(|e| panic!("Prompt loop failed: {e}"));
+
+        // The last provider call holds the whole turn but its final answer
+        if let Some(call) = self.provider.last_call() {
+            for message in &call.messages {
+                render_message(message, &mut self.transcript);
+            }
+        }
+        if !result.text.is_empty() {
+            self.transcript
+                .push_str(&format!("assistant: {}\n", result.text));
+        }
+        let root = self.project.path().display().to_string();
+        self.transcript = self.transcript.replace(&root, ".");
+
+        self.results.push(result);
+        self
+    }
+
+    /// The project the scenario ran on.
+    pub fn project(&self) -> &BuiltTestProject {
+        &self.project
+    }
+
+    /// The session the scenario ran in.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// The result of the latest turn.
+    pub fn result(&self) -> &PromptResult {
+        self.results
+            .last()
+            .expect("A scenario runs at least one turn")
+    }
+
+    /// The tool calls of all turns, in order.
+    pub fn tool_calls(&self) -> Vec<&ToolCallResult> {
+        self.results
+            .iter()
+            .flat_map(|result| &result.tool_calls)
+            .collect()
+    }
+
+    /// The conversation of all turns, one line per message part, with the
+    /// project's path shown as `.`.
+    pub fn transcript(&self) -> &str {
+        &self.transcript
+    }
+
+    /// The provider, with the calls made to it.
+    pub fn provider(&self) -> &RecordingProvider {
+        &self.provider
+    }
+
+    /// The sandbox, if the scenario ran in one.
+    pub fn sandbox(&self) -> Option<&MockSandbox> {
+        self.sandbox.as_deref()
+    }
+
+    /// The snapshot store of the session.
+    pub fn snapshots(&self) -> &SnapshotStore {
+        &self.snapshots
+    }
+
+    /// The events published on the bus.
+    pub fn events(&mut self) -> &mut EventCapture {
+        &mut self.events
+    }
+
+    /// The contents of a project file, read from the sandbox if the scenario
+    /// ran in one.
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<String> {
+        let path = self.project.path().join(path);
+        match &self.sandbox {
+            Some(sandbox) => sandbox.get_text_file_content(&path),
+            None => std::fs::read_to_string(&path).ok(),
+        }
+    }
+
+    /// Restore the files the session changed from its snapshots, newest
+    /// first, and return how many snapshots were restored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a snapshot can't be restored.
+    pub async fn revert_edits(&mut self) -> usize {
+        let snapshots = self
+            .snapshots
+            .list_by_session(&self.session.id)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to list snapshots: {e}"));
+        for snapshot in &snapshots {
+            self.snapshots
+                .restore(&snapshot.id)
+                .await
+                .unwrap_or_else(|e| panic!("Failed to restore snapshot {}: {}", snapshot.id, e));
+        }
+        snapshots.len()
+    }
+
+    /// Assert the tools called, in order, across all turns.
+    #[track_caller]
+    pub fn assert_tool_sequence(&mut self, expected: &[&str]) -> &mut Self {
+        let tools: Vec<&str> = self
+            .tool_calls()
+            .iter()
+            .map(|call| call.tool.as_str())
+            .collect();
+        assert_eq!(tools, expected, "unexpected tool calls");
+        self
+    }
+
+    /// Assert that every tool call succeeded.
+    #[track_caller]
+    pub fn assert_tools_succeeded(&mut self) -> &mut Self {
+        for call in self.tool_calls() {
+            assert!(
+                call.success,
+                "tool {} failed with input {}: {}",
+                call.tool, call.input, call.output
+            );
+        }
+        self
+    }
+
+    /// Assert the contents of a project file.
+    #[track_caller]
+    pub fn assert_file(&mut self, path: &str, expected: &str) -> &mut Self {
+        let content = self
+            .file(path)
+            .unwrap_or_else(|| panic!("File {path} does not exist"));
+        assert_strings_equal(&content, expected);
+        self
+    }
+
+    /// Assert that a project file contains `expected`.
+    #[track_caller]
+    pub fn assert_file_contains(&mut self, path: &str, expected: &str) -> &mut Self {
+        let content = self
+            .file(path)
+            .unwrap_or_else(|| panic!("File {path} does not exist"));
+        assert!(
+            content.contains(expected),
+            "File {path} does not contain expected text.\nExpected to find: {expected}\nActual content:\n{content}"
+        );
+        self
+    }
+
+    /// Assert that a project file does not exist.
+    #[track_caller]
+    pub fn assert_no_file(&mut self, path: &str) -> &mut Self {
+        assert!(self.file(path).is_none(), "File {path} exists");
+        self
+    }
+
+    /// Assert that these event types were published in this order, possibly
+    /// with other events in between.
+    #[track_caller]
+    pub fn assert_events(&mut self, expected: &[&str]) -> &mut Self {
+        self.events.assert_sequence(expected);
+        self
+    }
+
+    /// Assert the transcript of all turns.
+    #[track_caller]
+    pub fn assert_transcript(&mut self, expected: &str) -> &mut Self {
+        assert_strings_equal(&self.transcript, expected);
+        self
+    }
+
+    /// Assert that the transcript contains `expected`.
+    #[track_caller]
+    pub fn assert_transcript_contains(&mut self, expected: &str) -> &mut Self {
+        assert!(
+            self.transcript.contains(expected),
+            "Transcript does not contain expected text.\nExpected to find: {}\nTranscript:\n{}",
+            expected,
+            self.transcript
+        );
+        self
+    }
+}
+
+/// Append the lines of a message to a transcript.
+fn render_message(message: &Message, transcript: &mut String) {
+    let role = match message.role {
+        Role::System => "system",
+        Role::User => "user",
+        Role::Assistant => "assistant",
+        Role::Tool => "tool",
+    };
+    for part in &message.content {
+        let line = match part {
+            ContentPart::Text { text } => format!("{role}: {text}"),
+            ContentPart::Image { .. } => format!("{role}: [image]"),
+            ContentPart::ToolUse { name, input, .. } => format!("tool_use: {name} {input}"),
+            ContentPart::ToolResult {
+                content, is_error, ..
+            } => match is_error {
+                Some(true) => format!("tool_error: {content}"),
+                _ => format!("tool_result: {content}"),
+            },
+            ContentPart::Thinking { text } => format!("thinking: {text}"),
+        };
+        transcript.push_str(&line);
+        transcript.push('\n');
+    }
+}
+
+/// All files under `root`, recursively.
+fn project_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .unwrap_or_else(|e| panic!("Failed to read directory {}: {}", dir.display(), e));
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_edit_snapshot_revert() {
+        let mut run = Scenario::new()
+            .with_file("src/lib.rs", "pub fn answer() -> u32 {\n    41\n}\n")
+            .with_tool_call(
+                "call_1",
+                "edit",
+                json!({"filePath": "src/lib.rs", "oldString": "41", "newString": "42"}),
+            )
+            .with_response("Fixed the answer.")
+            .run("Fix the answer")
+            .await;
+
+        run.assert_tool_sequence(&["edit"])
+            .assert_tools_succeeded()
+            .assert_file("src/lib.rs", "pub fn answer() -> u32 {\n    42\n}\n");
+
+        assert_eq!(run.revert_edits().await, 1);
+        run.assert_file("src/lib.rs", "pub fn answer() -> u32 {\n    41\n}\n");
+    }
+
+    #[tokio::test]
+    async fn test_sandboxed_changes_stay_in_the_sandbox() {
+        let mut run = Scenario::new()
+            .with_file("README.md", "# App\n")
+            .with_command_response("cargo test", SandboxOutput::success("test result: ok"))
+            .with_fixture(
+                r#"[
+                    {"tool_calls": [{"name": "edit", "arguments": {"filePath": "README.md", "oldString": "App", "newString": "Sandboxed app"}}]},
+                    {"tool_calls": [{"name": "bash", "arguments": {"command": "cargo test", "description": "Run tests"}}]},
+                    {"text": "Done."}
+                ]"#,
+            )
+            .run("Rename the app and test")
+            .await;
+
+        run.assert_tool_sequence(&["edit", "bash"])
+            .assert_tools_succeeded()
+            .assert_file("README.md", "# Sandboxed app\n");
+        assert_eq!(run.project().read_file("README.md"), "# App\n");
+        assert!(run.sandbox().unwrap().command_was_executed("cargo test"));
+    }
+
+    #[tokio::test]
+    async fn test_events_and_transcript() {
+        let mut run = Scenario::new()
+            .with_file("a.txt", "alpha\n")
+            .with_tool_call("call_1", "read", json!({"filePath": "a.txt"}))
+            .with_response("It says alpha.")
+            .with_response("You're welcome.")
+            .run("What does a.txt say?")
+            .await;
+        run.prompt("Thanks").await;
+
+        run.assert_tool_sequence(&["read"])
+            .assert_events(&[
+                "session.created",
+                "session.status",
+                "session.status",
+                "session.status",
+            ])
+            .assert_transcript_contains(
+                "user: What does a.txt say?\ntool_use: read {\"filePath\":\"a.txt\"}\n",
+            )
+            .assert_transcript_contains("assistant: It says alpha.\nuser: Thanks\n")
+            .assert_no_file("b.txt");
+        assert!(run.transcript().ends_with("assistant: You're welcome.\n"));
+        assert_eq!(run.result().text, "You're welcome.");
+    }
+}
@@ -4,21 +4,27 @@
 //! - Configurable timeout (default 2 minutes, max 10 minutes)
 //! - Working directory support
 //! - Output truncation for large outputs
-//! - Background execution support
+//! - Background execution, reporting the output as it arrives
+//! - Output of sandboxed commands shown as it arrives
+//! - Progress of test runs, read from their output
 //! - Permission-based command validation
+//! - Recording of changed files, so a revert can undo them
 // @ace:implements COMP-T90R73-LWO
 
-use crate::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
+use crate::{Tool, ToolContext, ToolError, ToolEvent, ToolOutput, ToolResult};
 use async_trait::async_trait;
+use futures::StreamExt;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::Duration;
-use tokio::io::AsyncReadExt;
-use tokio::process::Command;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info, warn};
-use wonopcode_sandbox::SandboxCapabilities;
+use wonopcode_sandbox::OutputChunk;
 use wonopcode_util::{BashPermission, BashPermissionConfig};
 
 /// Default timeout in milliseconds (2 minutes).
@@ -30,6 +36,10 @@ const MAX_TIMEOUT_MS: u64 = 600_000;
 /// Maximum output size in bytes before truncation.
 const MAX_OUTPUT_SIZE: usize = 30_000;
 
+/// How long to wait for the rest of a background process's output once it
+/// has exited; processes it started may keep the pipes open.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Execute shell commands.
 pub struct BashTool;
 
@@ -95,6 +105,33 @@ Usage notes:
         let args: BashArgs = serde_json::from_value(args)
             .map_err(|e| ToolError::validation(format!("Invalid arguments: {e}")))?;
 
+        // Scan the project first, so a revert can undo what the command changes
+        let before = match &ctx.snapshot {
+            Some(store) if !args.run_in_background && store.tracks_commands() => {
+                Some(store.scan().await)
+            }
+            _ => None,
+        };
+        let command = args.command.clone();
+
+        let result = self.run(args, ctx).await;
+
+        if let (Some(store), Some(before)) = (&ctx.snapshot, before) {
+            if let Err(e) = store
+                .record_changes(&before, &ctx.session_id, &ctx.message_id, &command)
+                .await
+            {
+                debug!("Failed to record command changes: {}", e);
+            }
+        }
+
+        result
+    }
+}
+
+impl BashTool {
+    /// Validate and run a command.
+    async fn run(&self, args: BashArgs, ctx: &ToolContext) -> ToolResult<ToolOutput> {
         // Validate command is not empty
         if args.command.trim().is_empty() {
             return Err(ToolError::validation("Command cannot be empty"));
@@ -191,12 +228,16 @@ Usage notes:
             "Executing bash command"
         );
 
-        self.execute_direct(&args.command, &workdir, timeout, args.run_in_background)
-            .await
+        self.execute_direct(
+            &args.command,
+            &workdir,
+            timeout,
+            args.run_in_background,
+            ctx.event_tx.as_ref(),
+        )
+        .await
     }
-}
 
-impl BashTool {
     /// Execute command through sandbox runtime.
     async fn execute_sandboxed(
         &self,
@@ -218,22 +259,38 @@ impl BashTool {
             "Executing bash command in sandbox"
         );
 
-        // Use default capabilities for now.
-        //
-        // Elevated capabilities for specific commands is a future enhancement.
-        // This would allow commands like `git`, `npm`, `cargo` to have network
-        // access while still running in the sandbox. Implementation would require:
-        // 1. A whitelist of commands that can request elevated capabilities
-        // 2. Pattern matching on the command to identify which capability is needed
-        // 3. Audit logging for all elevated capability usage
-        // 4. User confirmation for first use of each elevated command
-        //
-        // For now, all sandbox commands use default (restricted) capabilities.
-        // Network access can be enabled globally via sandbox.network config.
-        let capabilities = SandboxCapabilities::default();
-
-        let result = sandbox
-            .execute(command, &sandbox_workdir, timeout, &capabilities)
+        // Network access is set by sandbox.network, narrowed to the hosts
+        // the sandbox.hosts rules give the command
+        let capabilities = sandbox.capabilities_for(command);
+
+        let mut execution = sandbox
+            .execute_streaming(command, &sandbox_workdir, timeout, &capabilities)
+            .await
+            .map_err(|e| ToolError::execution_failed(format!("Sandbox execution failed: {e}")))?;
+
+        // Show the output as it arrives, so that long builds don't seem stuck.
+        // Dropping the execution when the call is cancelled kills the command.
+        let mut progress = TestProgress::default();
+        let mut line = String::new();
+        while let Some(chunk) = execution.next().await {
+            let Some(tx) = &ctx.event_tx else {
+                continue;
+            };
+            if let OutputChunk::Stdout(text) = &chunk {
+                line.push_str(text);
+                while let Some(end) = line.find('\n') {
+                    if let Some(event) = progress.line(&line[..end]) {
+                        let _ = tx.send(event);
+                    }
+                    line.drain(..=end);
+                }
+            }
+            let _ = tx.send(ToolEvent::Output {
+                text: chunk.text().to_string(),
+            });
+        }
+        let result = execution
+            .wait()
             .await
             .map_err(|e| ToolError::execution_failed(format!("Sandbox execution failed: {e}")))?;
 
@@ -270,12 +327,16 @@ impl BashTool {
     }
 
     /// Execute command directly on host.
+    ///
+    /// Commands run in the background aren't waited for; their output is
+    /// reported through the event sender, if any.
     async fn execute_direct(
         &self,
         command: &str,
         workdir: &PathBuf,
         timeout: Duration,
         run_in_background: bool,
+        event_tx: Option<&mpsc::UnboundedSender<ToolEvent>>,
     ) -> ToolResult<ToolOutput> {
         // Build the command
         let mut cmd = Command::new("bash");
@@ -300,12 +361,17 @@ impl BashTool {
         // Handle background execution
         if run_in_background {
             // Don't wait for the process
+            let pid = child.id().unwrap_or_default();
+            watch_background(child, pid, command, event_tx.cloned());
             return Ok(ToolOutput::new(
                 format!("Started in background: {}", truncate_command(command)),
-                "Command started in background. Output will be available as it becomes ready.",
+                format!(
+                    "Command started in background with PID {pid}. Its output is shown to the user as it arrives."
+                ),
             )
             .with_metadata(json!({
                 "background": true,
+                "pid": pid,
                 "workdir": workdir.display().to_string()
             })));
         }
@@ -318,12 +384,12 @@ impl BashTool {
             let stderr = child.stderr.take();
 
             // Read output concurrently
+            let event_tx = event_tx.cloned();
             let stdout_handle = tokio::spawn(async move {
-                let mut buf = Vec::new();
-                if let Some(mut stdout) = stdout {
-                    stdout.read_to_end(&mut buf).await.ok();
+                match stdout {
+                    Some(stdout) => read_reporting_progress(stdout, event_tx).await,
+                    None => Vec::new(),
                 }
-                buf
             });
 
             let stderr_handle = tokio::spawn(async move {
@@ -400,6 +466,173 @@ impl BashTool {
     }
 }
 
+/// Report a background process's output and exit through the event sender,
+/// stopping the process when its stop token is cancelled.
+fn watch_background(
+    mut child: Child,
+    pid: u32,
+    command: &str,
+    event_tx: Option<mpsc::UnboundedSender<ToolEvent>>,
+) {
+    let stop = CancellationToken::new();
+    if let Some(tx) = &event_tx {
+        let _ = tx.send(ToolEvent::ProcessStarted {
+            pid,
+            command: command.to_string(),
+            stop: stop.clone(),
+        });
+    }
+
+    tokio::spawn(async move {
+        // Always read the pipes, so the process doesn't block on a full one
+        let stdout = child
+            .stdout
+            .take()
+            .map(|out| tokio::spawn(forward_output(out, pid, event_tx.clone())));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|err| tokio::spawn(forward_output(err, pid, event_tx.clone())));
+
+        let exit_code = tokio::select! {
+            status = child.wait() => status.ok().and_then(|s| s.code()),
+            () = stop.cancelled() => {
+                debug!(pid, "Stopping background process");
+                let _ = child.kill().await;
+                None
+            }
+        };
+        for reader in [stdout, stderr].into_iter().flatten() {
+            let _ = tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, reader).await;
+        }
+
+        debug!(pid, ?exit_code, "Background process exited");
+        if let Some(tx) = &event_tx {
+            let _ = tx.send(ToolEvent::ProcessExited { pid, exit_code });
+        }
+    });
+}
+
+/// Send what a background process writes, as it arrives.
+async fn forward_output(
+    mut reader: impl AsyncRead + Unpin,
+    pid: u32,
+    event_tx: Option<mpsc::UnboundedSender<ToolEvent>>,
+) {
+    let mut buf = [0u8; 8192];
+    let mut pending = Vec::new();
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        pending.extend_from_slice(&buf[..n]);
+        let text = take_utf8(&mut pending);
+        if let (Some(tx), false) = (&event_tx, text.is_empty()) {
+            let _ = tx.send(ToolEvent::ProcessOutput { pid, text });
+        }
+    }
+    if let (Some(tx), false) = (&event_tx, pending.is_empty()) {
+        let text = String::from_utf8_lossy(&pending).into_owned();
+        let _ = tx.send(ToolEvent::ProcessOutput { pid, text });
+    }
+}
+
+/// Read a command's output to the end, reporting the progress of a test
+/// run in it through the event sender, if any.
+async fn read_reporting_progress(
+    mut reader: impl AsyncRead + Unpin,
+    event_tx: Option<mpsc::UnboundedSender<ToolEvent>>,
+) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut progress = TestProgress::default();
+    let mut line_start = 0;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        output.extend_from_slice(&buf[..n]);
+        let Some(tx) = &event_tx else {
+            continue;
+        };
+        while let Some(end) = output[line_start..].iter().position(|&b| b == b'\n') {
+            let line = String::from_utf8_lossy(&output[line_start..line_start + end]);
+            if let Some(event) = progress.line(&line) {
+                let _ = tx.send(event);
+            }
+            line_start += end + 1;
+        }
+    }
+    output
+}
+
+/// Progress of a test run, read from its output line by line.
+///
+/// Understands libtest's `running N tests` and `test name ... ok` lines, as
+/// printed by `cargo test` for each test binary, and the `[ 42%]` pytest
+/// puts at the end of its lines.
+#[derive(Debug, Default)]
+struct TestProgress {
+    /// Tests of the running test binary.
+    total: usize,
+    /// Tests of the running test binary that finished.
+    done: usize,
+}
+
+impl TestProgress {
+    /// Read a line of output, returning the progress it shows, if any.
+    fn line(&mut self, line: &str) -> Option<ToolEvent> {
+        let line = line.trim_end();
+        if let Some(count) = line
+            .strip_prefix("running ")
+            .and_then(|rest| rest.strip_suffix(" tests").or(rest.strip_suffix(" test")))
+        {
+            self.total = count.parse().ok()?;
+            self.done = 0;
+            return (self.total > 0).then(|| self.event());
+        }
+        if self.total > 0 && line.starts_with("test ") {
+            let (_, result) = line.rsplit_once(" ... ")?;
+            if ["ok", "FAILED", "ignored"]
+                .iter()
+                .any(|outcome| result.starts_with(outcome))
+            {
+                self.done = (self.done + 1).min(self.total);
+                return Some(self.event());
+            }
+            return None;
+        }
+        let (_, percent) = line.strip_suffix("%]")?.rsplit_once('[')?;
+        let percent: u32 = percent.trim().parse().ok()?;
+        Some(ToolEvent::Progress {
+            fraction: Some(f64::from(percent.min(100)) / 100.0),
+            message: Some(format!("{percent}% of tests run")),
+        })
+    }
+
+    fn event(&self) -> ToolEvent {
+        ToolEvent::Progress {
+            fraction: Some(self.done as f64 / self.total as f64),
+            message: Some(format!("{}/{} tests run", self.done, self.total)),
+        }
+    }
+}
+
+/// Take the text from the start of `bytes`, leaving a character cut off at
+/// the end for the next read.
+fn take_utf8(bytes: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(bytes) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => bytes.len(),
+    };
+    let rest = bytes.split_off(complete);
+    let text = String::from_utf8_lossy(bytes).into_owned();
+    *bytes = rest;
+    text
+}
+
 /// Truncate command for display in title.
 fn truncate_command(cmd: &str) -> String {
     let first_line = cmd.lines().next().unwrap_or(cmd);
@@ -437,8 +670,10 @@ fn truncate_output(output: &str, max_size: usize) -> (String, bool) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
     use tempfile::tempdir;
     use tokio_util::sync::CancellationToken;
+    use wonopcode_sandbox::runtime::PassthroughRuntime;
 
     fn test_context() -> ToolContext {
         ToolContext {
@@ -452,6 +687,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -467,6 +704,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -705,6 +944,210 @@ mod tests {
         assert_eq!(result.metadata["background"], true);
     }
 
+    #[tokio::test]
+    #[cfg_attr(windows, ignore)]
+    async fn test_background_command_reports_output() {
+        let tool = BashTool;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let ctx = ToolContext {
+            event_tx: Some(tx),
+            ..test_context()
+        };
+
+        let result = tool
+            .execute(
+                json!({
+                    "command": "echo one; echo two >&2; exit 3",
+                    "description": "Print in background",
+                    "run_in_background": true
+                }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        let pid = result.metadata["pid"].as_u64().unwrap() as u32;
+        drop(ctx);
+
+        let mut output = String::new();
+        let mut exit = None;
+        while let Some(event) = rx.recv().await {
+            match event {
+                ToolEvent::ProcessStarted {
+                    pid: p, command, ..
+                } => {
+                    assert_eq!(p, pid);
+                    assert!(command.starts_with("echo one"));
+                }
+                ToolEvent::ProcessOutput { text, .. } => output.push_str(&text),
+                ToolEvent::ProcessExited { exit_code, .. } => exit = Some(exit_code),
+                ToolEvent::TodosUpdated(_)
+                | ToolEvent::Progress { .. }
+                | ToolEvent::Output { .. } => {}
+            }
+        }
+        assert!(output.contains("one\n"));
+        assert!(output.contains("two\n"));
+        assert_eq!(exit, Some(Some(3)));
+    }
+
+    #[tokio::test]
+    #[cfg_attr(windows, ignore)]
+    async fn test_sandboxed_command_streams_output() {
+        let dir = tempdir().unwrap();
+        let tool = BashTool;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let ctx = ToolContext {
+            event_tx: Some(tx),
+            sandbox: Some(Arc::new(PassthroughRuntime::with_root(
+                dir.path().to_path_buf(),
+            ))),
+            ..test_context_with_root(dir.path().to_path_buf())
+        };
+
+        let result = tool
+            .execute(
+                json!({
+                    "command": "echo 'running 1 test'; echo 'test a ... ok'; echo done >&2",
+                    "description": "Run tests in sandbox"
+                }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.metadata["sandboxed"], true);
+        drop(ctx);
+
+        let mut output = String::new();
+        let mut fractions = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                ToolEvent::Output { text } => output.push_str(&text),
+                ToolEvent::Progress { fraction, .. } => fractions.push(fraction),
+                _ => {}
+            }
+        }
+        assert!(output.contains("test a ... ok\n"));
+        assert!(output.contains("done\n"));
+        assert_eq!(fractions, [Some(0.0), Some(1.0)]);
+    }
+
+    #[tokio::test]
+    #[cfg_attr(windows, ignore)]
+    async fn test_background_command_stops() {
+        let tool = BashTool;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let ctx = ToolContext {
+            event_tx: Some(tx),
+            ..test_context()
+        };
+        tool.execute(
+            json!({
+                "command": "sleep 30",
+                "description": "Sleep in background",
+                "run_in_background": true
+            }),
+            &ctx,
+        )
+        .await
+        .unwrap();
+        drop(ctx);
+
+        let Some(ToolEvent::ProcessStarted { stop, .. }) = rx.recv().await else {
+            panic!("expected ProcessStarted");
+        };
+        stop.cancel();
+        let exited = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(event) = rx.recv().await {
+                if let ToolEvent::ProcessExited { exit_code, .. } = event {
+                    return Some(exit_code);
+                }
+            }
+            None
+        })
+        .await
+        .unwrap();
+        assert_eq!(exited, Some(None));
+    }
+
+    #[test]
+    fn test_take_utf8_keeps_cut_off_character() {
+        let mut bytes = "añ".as_bytes().to_vec();
+        bytes.pop();
+        assert_eq!(take_utf8(&mut bytes), "a");
+        assert_eq!(bytes.len(), 1);
+        bytes.push("ñ".as_bytes()[1]);
+        assert_eq!(take_utf8(&mut bytes), "ñ");
+        assert!(bytes.is_empty());
+    }
+
+    fn fraction(event: Option<ToolEvent>) -> Option<f64> {
+        match event {
+            Some(ToolEvent::Progress { fraction, .. }) => fraction,
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_progress_of_libtest_run() {
+        let mut progress = TestProgress::default();
+        assert_eq!(fraction(progress.line("running 4 tests")), Some(0.0));
+        assert_eq!(fraction(progress.line("test a ... ok")), Some(0.25));
+        assert_eq!(fraction(progress.line("test b ... FAILED")), Some(0.5));
+        assert_eq!(
+            fraction(progress.line("test c ... ignored, slow")),
+            Some(0.75)
+        );
+        assert!(progress.line("some output").is_none());
+
+        let Some(ToolEvent::Progress { message, .. }) = progress.line("test d ... ok\n") else {
+            panic!("expected Progress");
+        };
+        assert_eq!(message.as_deref(), Some("4/4 tests run"));
+
+        // The next test binary starts over
+        assert!(progress.line("running 0 tests").is_none());
+        assert_eq!(fraction(progress.line("running 1 test")), Some(0.0));
+    }
+
+    #[test]
+    fn test_progress_of_pytest_run() {
+        let mut progress = TestProgress::default();
+        assert_eq!(
+            fraction(progress.line("tests/test_app.py ..F.   [ 40%]")),
+            Some(0.4)
+        );
+        assert_eq!(
+            fraction(progress.line("tests/test_db.py .. [100%]")),
+            Some(1.0)
+        );
+        assert!(progress.line("[notes]").is_none());
+    }
+
+    #[tokio::test]
+    #[cfg_attr(windows, ignore)]
+    async fn test_command_reports_test_progress() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let ctx = ToolContext {
+            event_tx: Some(tx),
+            ..test_context()
+        };
+        let result = BashTool
+            .execute(
+                json!({"command": "printf 'running 2 tests\\ntest a ... ok\\ntest b ... ok\\n'"}),
+                &ctx,
+            )
+            .await
+            .unwrap();
+        assert!(result.output.contains("test b ... ok"));
+        drop(ctx);
+
+        let mut fractions = Vec::new();
+        while let Some(event) = rx.recv().await {
+            fractions.push(fraction(Some(event)));
+        }
+        assert_eq!(fractions, vec![Some(0.0), Some(0.5), Some(1.0)]);
+    }
+
     #[tokio::test]
     #[cfg_attr(windows, ignore)]
     async fn test_nonexistent_workdir() {
@@ -280,6 +280,8 @@ impl Clone for ToolContext {
             file_time: self.file_time.clone(),
             sandbox: self.sandbox.clone(),
             event_tx: self.event_tx.clone(),
+            formatter: self.formatter.clone(),
+            client_fs: self.client_fs.clone(),
         }
     }
 }
@@ -371,6 +373,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -0,0 +1,96 @@
+//! File access through the client.
+//!
+//! An editor driving the agent (over ACP, for instance) can offer to read and
+//! write files itself. Reads then see unsaved buffers, and writes land in the
+//! editor's undo history instead of racing it on disk. The read, write, edit
+//! and multiedit tools go through the context's [`ClientFs`] when it offers
+//! the operation, and fall back to the disk otherwise. Sandboxed tools always
+//! use the sandbox.
+
+use crate::ToolContext;
+use async_trait::async_trait;
+use std::path::Path;
+
+/// Reads and writes text files in the client.
+#[async_trait]
+pub trait ClientFs: Send + Sync {
+    /// Whether the client reads files.
+    fn reads(&self) -> bool;
+
+    /// Whether the client writes files.
+    fn writes(&self) -> bool;
+
+    /// Read `path`, including changes not yet saved in the client.
+    async fn read_text_file(&self, path: &Path) -> Result<String, String>;
+
+    /// Replace the content of `path`, creating it if needed.
+    async fn write_text_file(&self, path: &Path, content: &str) -> Result<(), String>;
+}
+
+/// Read `path` through the client, or `None` if the tool should read the
+/// disk itself.
+pub(crate) async fn read(ctx: &ToolContext, path: &Path) -> Option<Result<String, String>> {
+    match &ctx.client_fs {
+        Some(client) if client.reads() && !ctx.is_sandboxed() => {
+            Some(client.read_text_file(path).await)
+        }
+        _ => None,
+    }
+}
+
+/// Write `path` through the client, or `None` if the tool should write the
+/// disk itself.
+pub(crate) async fn write(
+    ctx: &ToolContext,
+    path: &Path,
+    content: &str,
+) -> Option<Result<(), String>> {
+    match &ctx.client_fs {
+        Some(client) if client.writes() && !ctx.is_sandboxed() => {
+            Some(client.write_text_file(path, content).await)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+
+    /// Keeps files in memory, like editor buffers that were never saved.
+    #[derive(Default)]
+    pub(crate) struct Buffers {
+        pub(crate) files: Mutex<HashMap<PathBuf, String>>,
+    }
+
+    #[async_trait]
+    impl ClientFs for Buffers {
+        fn reads(&self) -> bool {
+            true
+        }
+
+        fn writes(&self) -> bool {
+            true
+        }
+
+        async fn read_text_file(&self, path: &Path) -> Result<String, String> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| format!("no buffer for {}", path.display()))
+        }
+
+        async fn write_text_file(&self, path: &Path, content: &str) -> Result<(), String> {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), content.to_string());
+            Ok(())
+        }
+    }
+}
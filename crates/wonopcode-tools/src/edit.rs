@@ -7,6 +7,8 @@
 //! - Replace-all support
 // @ace:implements COMP-T90R73-1AO8
 
+use crate::client_fs;
+use crate::format::format_written_file;
 use crate::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -124,6 +126,8 @@ Usage:
                 .await
                 .map_err(|e| ToolError::execution_failed(format!("Failed to read file: {e}")))?;
             String::from_utf8_lossy(&bytes).to_string()
+        } else if let Some(read) = client_fs::read(ctx, &file_path).await {
+            read.map_err(|e| ToolError::execution_failed(format!("Failed to read file: {e}")))?
         } else {
             fs::read_to_string(&file_path)
                 .await
@@ -190,9 +194,6 @@ Usage:
             }
         };
 
-        // Generate diff for display
-        let diff = generate_diff(&content, &new_content, &file_path);
-
         // Write file - either through sandbox or directly
         if let Some(sandbox) = ctx.sandbox() {
             let sandbox_path = ctx.to_sandbox_path(&file_path);
@@ -200,6 +201,9 @@ Usage:
                 .write_file(&sandbox_path, new_content.as_bytes())
                 .await
                 .map_err(|e| ToolError::execution_failed(format!("Failed to write file: {e}")))?;
+        } else if let Some(written) = client_fs::write(ctx, &file_path, &new_content).await {
+            written
+                .map_err(|e| ToolError::execution_failed(format!("Failed to write file: {e}")))?;
         } else {
             // Write atomically (write to temp, then rename)
             // Use random temp file name to avoid collisions and predictable names
@@ -235,6 +239,10 @@ Usage:
                 .map_err(|e| ToolError::execution_failed(format!("Failed to rename file: {e}")))?;
         }
 
+        // Format the file, so the diff shows what ended up on disk
+        let formatted = format_written_file(ctx, &file_path, &new_content).await;
+        let diff = generate_diff(&content, &formatted.content, &file_path) + &formatted.note();
+
         // Update file read time after successful write
         if let Some(ref file_time) = ctx.file_time {
             let host_path = if ctx.is_sandboxed() {
@@ -279,7 +287,8 @@ Usage:
             } else {
                 1
             },
-            "swapped": swapped
+            "swapped": swapped,
+            "formatter": formatted.formatter
         })))
     }
 }
@@ -808,7 +817,7 @@ async fn resolve_path(
 }
 
 /// Generate a unified diff.
-fn generate_diff(old: &str, new: &str, path: &std::path::Path) -> String {
+pub(crate) fn generate_diff(old: &str, new: &str, path: &std::path::Path) -> String {
     let diff = TextDiff::from_lines(old, new);
     let mut output = String::new();
 
@@ -860,6 +869,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         };
         (dir, ctx)
     }
@@ -889,6 +900,64 @@ mod tests {
         assert!(result.output.contains("+goodbye"));
     }
 
+    #[tokio::test]
+    async fn test_edit_goes_through_client() {
+        let (dir, mut ctx) = setup_test().await;
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "hello world").await.unwrap();
+
+        // The editor holds unsaved changes to the file
+        let buffers = std::sync::Arc::new(crate::client_fs::tests::Buffers::default());
+        buffers
+            .files
+            .lock()
+            .unwrap()
+            .insert(file_path.clone(), "hello there".to_string());
+        ctx.client_fs = Some(buffers.clone());
+
+        let tool = EditTool;
+        tool.execute(
+            json!({
+                "filePath": file_path.to_str().unwrap(),
+                "oldString": "hello",
+                "newString": "goodbye"
+            }),
+            &ctx,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(buffers.files.lock().unwrap()[&file_path], "goodbye there");
+        assert_eq!(fs::read_to_string(&file_path).await.unwrap(), "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_edit_shows_formatted_diff() {
+        let (dir, mut ctx) = setup_test().await;
+        ctx.formatter = Some(std::sync::Arc::new(
+            crate::format::tests::UppercaseFormatter,
+        ));
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "hello world\n").await.unwrap();
+
+        let result = EditTool
+            .execute(
+                json!({
+                    "filePath": file_path.to_str().unwrap(),
+                    "oldString": "hello",
+                    "newString": "goodbye"
+                }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+
+        let content = fs::read_to_string(&file_path).await.unwrap();
+        assert_eq!(content, "GOODBYE WORLD\n");
+        assert!(result.output.contains("+GOODBYE WORLD"));
+        assert_eq!(result.metadata["formatter"], "upper");
+    }
+
     #[tokio::test]
     async fn test_replace_all() {
         let (dir, ctx) = setup_test().await;
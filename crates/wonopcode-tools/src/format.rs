@@ -0,0 +1,186 @@
+//! Format-on-save support for the file editing tools.
+//!
+//! The edit, write and multiedit tools run the context's [`FileFormatter`]
+//! on every file they modify, and report the formatted content so the model
+//! sees what actually ended up on disk.
+
+use crate::ToolContext;
+use async_trait::async_trait;
+use std::path::Path;
+use tracing::{debug, warn};
+
+/// Formats files after tools modify them.
+#[async_trait]
+pub trait FileFormatter: Send + Sync {
+    /// Format `path` in place.
+    ///
+    /// Returns the name of the formatter that ran, or `None` if no formatter
+    /// handles the file.
+    async fn format(&self, path: &Path) -> Result<Option<String>, String>;
+}
+
+/// Result of formatting a file after a write.
+#[derive(Debug, Clone)]
+pub struct FormatOutcome {
+    /// File content after formatting.
+    pub content: String,
+    /// Formatter that ran, if any.
+    pub formatter: Option<String>,
+    /// Why formatting failed, if it did. The file is left as written.
+    pub error: Option<String>,
+}
+
+impl FormatOutcome {
+    fn unformatted(content: &str) -> Self {
+        Self {
+            content: content.to_string(),
+            formatter: None,
+            error: None,
+        }
+    }
+
+    /// A note about a failed formatter, to append to the tool output.
+    pub fn note(&self) -> String {
+        match &self.error {
+            Some(error) => format!("\n\nFormatting failed: {error}"),
+            None => String::new(),
+        }
+    }
+}
+
+/// Run the context's formatter on `path`, which was just written with
+/// `content`.
+///
+/// A failing formatter does not fail the tool: the outcome then holds the
+/// content as written and the error.
+pub(crate) async fn format_written_file(
+    ctx: &ToolContext,
+    path: &Path,
+    content: &str,
+) -> FormatOutcome {
+    let Some(formatter) = &ctx.formatter else {
+        return FormatOutcome::unformatted(content);
+    };
+
+    // Formatters run on the host, where sandbox workspaces are mounted
+    let host_path = ctx.to_host_path(path);
+    let name = match formatter.format(&host_path).await {
+        Ok(Some(name)) => name,
+        Ok(None) => return FormatOutcome::unformatted(content),
+        Err(error) => {
+            warn!(path = %host_path.display(), "Formatter failed: {}", error);
+            return FormatOutcome {
+                error: Some(error),
+                ..FormatOutcome::unformatted(content)
+            };
+        }
+    };
+    debug!(path = %host_path.display(), formatter = %name, "Formatted file");
+
+    let formatted = match ctx.sandbox() {
+        Some(sandbox) => sandbox
+            .read_file(&ctx.to_sandbox_path(path))
+            .await
+            .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+            .map_err(|e| e.to_string()),
+        None => tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| e.to_string()),
+    };
+    match formatted {
+        Ok(formatted) => FormatOutcome {
+            content: formatted,
+            formatter: Some(name),
+            error: None,
+        },
+        Err(error) => FormatOutcome {
+            formatter: Some(name),
+            error: Some(format!("failed to read formatted file: {error}")),
+            ..FormatOutcome::unformatted(content)
+        },
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    /// Formats `.txt` files by upper-casing them.
+    pub(crate) struct UppercaseFormatter;
+
+    #[async_trait]
+    impl FileFormatter for UppercaseFormatter {
+        async fn format(&self, path: &Path) -> Result<Option<String>, String> {
+            if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+                return Ok(None);
+            }
+            let content = tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| e.to_string())?;
+            tokio::fs::write(path, content.to_uppercase())
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(Some("upper".to_string()))
+        }
+    }
+
+    struct FailingFormatter;
+
+    #[async_trait]
+    impl FileFormatter for FailingFormatter {
+        async fn format(&self, _path: &Path) -> Result<Option<String>, String> {
+            Err("syntax error".to_string())
+        }
+    }
+
+    fn context(root: PathBuf, formatter: Option<Arc<dyn FileFormatter>>) -> ToolContext {
+        ToolContext {
+            session_id: "test".to_string(),
+            message_id: "test".to_string(),
+            agent: "test".to_string(),
+            abort: tokio_util::sync::CancellationToken::new(),
+            root_dir: root.clone(),
+            cwd: root,
+            snapshot: None,
+            file_time: None,
+            sandbox: None,
+            event_tx: None,
+            formatter,
+            client_fs: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_format_written_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(dir.path().to_path_buf(), Some(Arc::new(UppercaseFormatter)));
+
+        let txt = dir.path().join("notes.txt");
+        tokio::fs::write(&txt, "hello").await.unwrap();
+        let outcome = format_written_file(&ctx, &txt, "hello").await;
+        assert_eq!(outcome.content, "HELLO");
+        assert_eq!(outcome.formatter.as_deref(), Some("upper"));
+        assert!(outcome.note().is_empty());
+
+        // Files no formatter handles are left alone
+        let md = dir.path().join("notes.md");
+        tokio::fs::write(&md, "hello").await.unwrap();
+        let outcome = format_written_file(&ctx, &md, "hello").await;
+        assert_eq!(outcome.content, "hello");
+        assert!(outcome.formatter.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_format_failure_keeps_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = context(dir.path().to_path_buf(), Some(Arc::new(FailingFormatter)));
+
+        let txt = dir.path().join("notes.txt");
+        tokio::fs::write(&txt, "hello").await.unwrap();
+        let outcome = format_written_file(&ctx, &txt, "hello").await;
+        assert_eq!(outcome.content, "hello");
+        assert!(outcome.note().contains("syntax error"));
+    }
+}
@@ -245,6 +245,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -452,6 +452,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -9,7 +9,9 @@ pub mod registry;
 // Tool implementations
 pub mod bash;
 pub mod batch;
+pub mod client_fs;
 pub mod edit;
+pub mod format;
 pub mod glob;
 pub mod grep;
 pub mod list;
@@ -26,7 +28,9 @@ pub mod todo;
 pub mod webfetch;
 pub mod write;
 
+pub use client_fs::ClientFs;
 pub use error::{ToolError, ToolResult};
+pub use format::FileFormatter;
 pub use registry::ToolRegistry;
 
 use async_trait::async_trait;
@@ -44,6 +48,41 @@ use wonopcode_util::FileTimeState;
 pub enum ToolEvent {
     /// Todo list was updated with new phased structure.
     TodosUpdated(todo::PhasedTodos),
+    /// A command was started in the background.
+    ProcessStarted {
+        /// OS process ID.
+        pid: u32,
+        /// The command line.
+        command: String,
+        /// Cancel to stop the process.
+        stop: CancellationToken,
+    },
+    /// A background process wrote to stdout or stderr.
+    ProcessOutput {
+        /// OS process ID.
+        pid: u32,
+        /// The text written.
+        text: String,
+    },
+    /// A background process exited or was stopped.
+    ProcessExited {
+        /// OS process ID.
+        pid: u32,
+        /// Exit code, if it exited normally.
+        exit_code: Option<i32>,
+    },
+    /// A long-running tool made progress.
+    Progress {
+        /// Share of the work done, from 0 to 1, if known.
+        fraction: Option<f64>,
+        /// What the tool is doing.
+        message: Option<String>,
+    },
+    /// A running tool wrote output, shown as it arrives.
+    Output {
+        /// The text written.
+        text: String,
+    },
 }
 
 /// Context provided to tools during execution.
@@ -68,6 +107,10 @@ pub struct ToolContext {
     pub sandbox: Option<Arc<dyn SandboxRuntime>>,
     /// Optional event sender for immediate notifications.
     pub event_tx: Option<mpsc::UnboundedSender<ToolEvent>>,
+    /// Optional formatter run on files after they are modified.
+    pub formatter: Option<Arc<dyn FileFormatter>>,
+    /// Optional client that reads and writes files for the editing tools.
+    pub client_fs: Option<Arc<dyn ClientFs>>,
 }
 
 impl ToolContext {
@@ -182,6 +225,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -259,7 +304,9 @@ mod tests {
 
         // Test that we can clone the event
         let cloned = event;
-        let ToolEvent::TodosUpdated(phased_todos) = cloned;
+        let ToolEvent::TodosUpdated(phased_todos) = cloned else {
+            panic!("expected TodosUpdated");
+        };
         assert_eq!(phased_todos.phases.len(), 1);
         assert_eq!(phased_todos.phases[0].todos.len(), 1);
         assert_eq!(phased_todos.phases[0].todos[0].id, "1");
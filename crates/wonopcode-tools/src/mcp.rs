@@ -1,13 +1,17 @@
 //! MCP tool wrapper for wonopcode.
 //!
 //! This module wraps MCP (Model Context Protocol) tools as native wonopcode tools,
-//! allowing the AI to call tools from connected MCP servers.
+//! allowing the AI to call tools from connected MCP servers. The progress
+//! the servers report for a call is passed on as [`ToolEvent::Progress`].
 
-use crate::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
+use crate::{Tool, ToolContext, ToolError, ToolEvent, ToolOutput, ToolResult};
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use std::sync::Arc;
-use wonopcode_mcp::{McpClient, McpTool as McpToolDef, ToolCallResult, ToolContent};
+use tokio::sync::mpsc;
+use wonopcode_mcp::{
+    McpClient, McpTool as McpToolDef, ProgressParams, ToolCallResult, ToolContent,
+};
 
 /// A wrapper that makes an MCP tool available as a wonopcode tool.
 pub struct McpToolWrapper {
@@ -41,9 +45,13 @@ impl McpToolWrapper {
     }
 
     /// Convert MCP tool call result to wonopcode ToolOutput.
+    ///
+    /// Images are described in the output and passed on in the metadata as
+    /// `{"images": [{"mimeType": ..., "data": <base64>}]}` for display.
     fn convert_result(&self, result: ToolCallResult) -> ToolResult<ToolOutput> {
         // Collect text content from the result
         let mut output_parts = Vec::new();
+        let mut images = Vec::new();
         for content in &result.content {
             match content {
                 ToolContent::Text { text } => {
@@ -55,6 +63,7 @@ impl McpToolWrapper {
                         data.len(),
                         mime_type
                     ));
+                    images.push(json!({ "mimeType": mime_type, "data": data }));
                 }
                 ToolContent::Resource { resource } => {
                     if let Some(text) = &resource.text {
@@ -71,10 +80,12 @@ impl McpToolWrapper {
         if result.is_error {
             Err(ToolError::execution_failed(output_text))
         } else {
-            Ok(ToolOutput::new(
-                format!("MCP: {}", self.tool_def.name),
-                output_text,
-            ))
+            let output = ToolOutput::new(format!("MCP: {}", self.tool_def.name), output_text);
+            if images.is_empty() {
+                Ok(output)
+            } else {
+                Ok(output.with_metadata(json!({ "images": images })))
+            }
         }
     }
 }
@@ -99,18 +110,41 @@ impl Tool for McpToolWrapper {
         })
     }
 
-    async fn execute(&self, args: Value, _ctx: &ToolContext) -> ToolResult<ToolOutput> {
+    async fn execute(&self, args: Value, ctx: &ToolContext) -> ToolResult<ToolOutput> {
         // Call the MCP tool
-        let result = self
-            .client
-            .call_tool(&self.tool_def.name, args)
-            .await
-            .map_err(|e| ToolError::execution_failed(e.to_string()))?;
+        let result = match &ctx.event_tx {
+            Some(event_tx) => {
+                let (tx, mut rx) = mpsc::unbounded_channel();
+                let event_tx = event_tx.clone();
+                tokio::spawn(async move {
+                    while let Some(progress) = rx.recv().await {
+                        let _ = event_tx.send(progress_event(progress));
+                    }
+                });
+                self.client
+                    .call_tool_with_progress(&self.tool_def.name, args, tx)
+                    .await
+            }
+            None => self.client.call_tool(&self.tool_def.name, args).await,
+        }
+        .map_err(|e| ToolError::execution_failed(e.to_string()))?;
 
         self.convert_result(result)
     }
 }
 
+/// The tool event for the progress an MCP server reports.
+fn progress_event(progress: ProgressParams) -> ToolEvent {
+    let fraction = progress
+        .total
+        .filter(|total| *total > 0.0)
+        .map(|total| (progress.progress / total).clamp(0.0, 1.0));
+    ToolEvent::Progress {
+        fraction,
+        message: progress.message,
+    }
+}
+
 /// Builder for registering MCP tools.
 pub struct McpToolsBuilder {
     client: Arc<McpClient>,
@@ -311,6 +345,10 @@ mod tests {
         let output = wrapper.convert_result(result).unwrap();
         assert!(output.output.contains("[Image:"));
         assert!(output.output.contains("image/png"));
+        assert_eq!(
+            output.metadata,
+            json!({ "images": [{ "mimeType": "image/png", "data": "base64data" }] })
+        );
     }
 
     #[test]
@@ -437,4 +475,26 @@ mod tests {
         let tools = builder.build_all().await;
         assert!(tools.is_empty()); // No servers connected
     }
+
+    #[test]
+    fn test_progress_event() {
+        let progress = |progress, total| ProgressParams {
+            progress_token: json!(1),
+            progress,
+            total,
+            message: Some("Indexing".to_string()),
+        };
+        let ToolEvent::Progress { fraction, message } = progress_event(progress(30.0, Some(120.0)))
+        else {
+            panic!("expected Progress");
+        };
+        assert_eq!(fraction, Some(0.25));
+        assert_eq!(message.as_deref(), Some("Indexing"));
+
+        // Without a total, the share done is unknown
+        let ToolEvent::Progress { fraction, .. } = progress_event(progress(30.0, None)) else {
+            panic!("expected Progress");
+        };
+        assert_eq!(fraction, None);
+    }
 }
@@ -6,6 +6,8 @@
 //! - Snapshot support for undo
 //! - Combined diff output
 
+use crate::client_fs;
+use crate::format::format_written_file;
 use crate::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
 use async_trait::async_trait;
 use serde::Deserialize;
@@ -122,7 +124,11 @@ Usage:
             let content = if let Some(content) = file_contents.get(&path) {
                 content.clone()
             } else {
-                let content = fs::read_to_string(&path).await.map_err(|e| {
+                let content = match client_fs::read(ctx, &path).await {
+                    Some(read) => read,
+                    None => fs::read_to_string(&path).await.map_err(|e| e.to_string()),
+                }
+                .map_err(|e| {
                     ToolError::validation(format!(
                         "Edit {}: Failed to read file {}: {}",
                         idx + 1,
@@ -199,24 +205,52 @@ Usage:
             }
         }
 
-        // Phase 3: Write all files atomically
-        for (path, (_, modified)) in &final_contents {
-            let temp_path = path.with_extension("tmp");
-            fs::write(&temp_path, modified).await.map_err(|e| {
-                ToolError::execution_failed(format!(
-                    "Failed to write temp file {}: {}",
-                    temp_path.display(),
-                    e
-                ))
-            })?;
-
-            fs::rename(&temp_path, path).await.map_err(|e| {
-                ToolError::execution_failed(format!(
-                    "Failed to rename file {}: {}",
-                    path.display(),
-                    e
-                ))
-            })?;
+        // Phase 3: Write all files atomically, then format them
+        let mut formatters: Vec<String> = Vec::new();
+        let mut format_notes = String::new();
+        for (path, (_, modified)) in &mut final_contents {
+            if let Some(written) = client_fs::write(ctx, path, modified).await {
+                written.map_err(|e| {
+                    ToolError::execution_failed(format!(
+                        "Failed to write file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            } else {
+                let temp_path = path.with_extension("tmp");
+                fs::write(&temp_path, modified.as_str())
+                    .await
+                    .map_err(|e| {
+                        ToolError::execution_failed(format!(
+                            "Failed to write temp file {}: {}",
+                            temp_path.display(),
+                            e
+                        ))
+                    })?;
+
+                fs::rename(&temp_path, path).await.map_err(|e| {
+                    ToolError::execution_failed(format!(
+                        "Failed to rename file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            }
+
+            let formatted = format_written_file(ctx, path, modified).await;
+            if let Some(error) = &formatted.error {
+                format_notes.push_str(&format!(
+                    "\n\nFormatting {} failed: {error}",
+                    path.display()
+                ));
+            }
+            if let Some(name) = formatted.formatter {
+                if !formatters.contains(&name) {
+                    formatters.push(name);
+                }
+            }
+            *modified = formatted.content;
         }
 
         // Phase 4: Generate output
@@ -244,12 +278,15 @@ Usage:
             args.edits.len()
         );
 
+        diff_output.push_str(&format_notes);
+
         Ok(ToolOutput::new(title, diff_output).with_metadata(json!({
             "files": final_contents.len(),
             "edits": args.edits.len(),
             "additions": total_additions,
             "deletions": total_deletions,
-            "paths": final_contents.keys().map(|p| p.display().to_string()).collect::<Vec<_>>()
+            "paths": final_contents.keys().map(|p| p.display().to_string()).collect::<Vec<_>>(),
+            "formatters": formatters
         })))
     }
 }
@@ -435,6 +472,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         };
         (dir, ctx)
     }
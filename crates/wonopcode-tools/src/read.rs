@@ -1,6 +1,7 @@
 //! Read tool - read file contents.
 // @ace:implements COMP-T90R73-2AG
 
+use crate::client_fs;
 use crate::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
 use async_trait::async_trait;
 use serde_json::{json, Value};
@@ -101,12 +102,7 @@ Usage:
                 .map_err(|e| ToolError::execution_failed(format!("Sandbox error: {e}")))?;
 
             if !exists {
-                let suggestion = suggest_similar_file(&file_path).await;
-                let mut message = format!("File not found: {}", file_path.display());
-                if let Some(suggestion) = suggestion {
-                    message.push_str(&format!("\n\nDid you mean: {suggestion}"));
-                }
-                return Err(ToolError::file_not_found(message));
+                return Err(file_not_found(&file_path).await);
             }
 
             // Get file metadata for size check
@@ -127,17 +123,31 @@ Usage:
                 .read_file(&sandbox_path)
                 .await
                 .map_err(|e| ToolError::execution_failed(format!("Sandbox read error: {e}")))?
+        } else if let Some(read) = client_fs::read(ctx, &file_path).await {
+            // The client's buffer, which may hold unsaved changes
+            let text = match read {
+                Ok(text) => text,
+                Err(_) if !file_path.exists() => return Err(file_not_found(&file_path).await),
+                Err(e) => {
+                    return Err(ToolError::execution_failed(format!(
+                        "Failed to read file: {e}"
+                    )))
+                }
+            };
+            if text.len() as u64 > MAX_FILE_SIZE {
+                return Err(ToolError::validation(format!(
+                    "File too large ({} bytes). Maximum allowed size is {} bytes.",
+                    text.len(),
+                    MAX_FILE_SIZE
+                )));
+            }
+            text.into_bytes()
         } else {
             // Direct host filesystem access
 
             // Check if file exists
             if !file_path.exists() {
-                let suggestion = suggest_similar_file(&file_path).await;
-                let mut message = format!("File not found: {}", file_path.display());
-                if let Some(suggestion) = suggestion {
-                    message.push_str(&format!("\n\nDid you mean: {suggestion}"));
-                }
-                return Err(ToolError::file_not_found(message));
+                return Err(file_not_found(&file_path).await);
             }
 
             // Check file size
@@ -235,6 +245,15 @@ fn is_sensitive_file(path: &std::path::Path) -> bool {
     false
 }
 
+/// The error for a missing file, suggesting a similarly named one.
+async fn file_not_found(path: &std::path::Path) -> ToolError {
+    let mut message = format!("File not found: {}", path.display());
+    if let Some(suggestion) = suggest_similar_file(path).await {
+        message.push_str(&format!("\n\nDid you mean: {suggestion}"));
+    }
+    ToolError::file_not_found(message)
+}
+
 /// Suggest a similar file if the requested file doesn't exist.
 async fn suggest_similar_file(path: &std::path::Path) -> Option<String> {
     let parent = path.parent()?;
@@ -299,6 +318,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -351,6 +372,37 @@ mod tests {
         assert!(result.output.contains("line 3"));
     }
 
+    #[tokio::test]
+    async fn test_read_prefers_client_buffer() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        std::fs::write(&file_path, "saved").unwrap();
+
+        let buffers = crate::client_fs::tests::Buffers::default();
+        buffers
+            .files
+            .lock()
+            .unwrap()
+            .insert(file_path.clone(), "unsaved".to_string());
+        let mut ctx = test_context();
+        ctx.client_fs = Some(std::sync::Arc::new(buffers));
+
+        let tool = ReadTool;
+        let result = tool
+            .execute(json!({ "filePath": file_path.display().to_string() }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.output.contains("unsaved"));
+
+        // A file neither the client nor the disk has is not found
+        let missing = dir.path().join("missing.txt");
+        let err = tool
+            .execute(json!({ "filePath": missing.display().to_string() }), &ctx)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not found"));
+    }
+
     #[tokio::test]
     async fn test_read_file_with_offset() {
         let dir = tempdir().unwrap();
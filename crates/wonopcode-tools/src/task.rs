@@ -202,6 +202,8 @@ Usage notes:
             file_time: ctx.file_time.clone(),
             sandbox: ctx.sandbox.clone(),
             event_tx: ctx.event_tx.clone(),
+            formatter: None,
+            client_fs: None,
         };
 
         // Execute the subagent
@@ -313,6 +315,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
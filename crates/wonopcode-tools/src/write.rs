@@ -1,10 +1,13 @@
 //! Write tool - write file contents.
 // @ace:implements COMP-T90R73-F80
 
+use crate::client_fs;
+use crate::edit::generate_diff;
+use crate::format::{format_written_file, FormatOutcome};
 use crate::{Tool, ToolContext, ToolError, ToolOutput, ToolResult};
 use async_trait::async_trait;
 use serde_json::{json, Value};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::{debug, warn};
 
 /// Write file contents.
@@ -122,8 +125,13 @@ Usage:
             tokio::fs::create_dir_all(parent).await?;
         }
 
-        // Write file
-        tokio::fs::write(&file_path, content).await?;
+        // Write file, through the client if it writes files
+        match client_fs::write(ctx, &file_path, content).await {
+            Some(result) => result
+                .map_err(|e| ToolError::execution_failed(format!("Failed to write file: {e}")))?,
+            None => tokio::fs::write(&file_path, content).await?,
+        }
+        let formatted = format_written_file(ctx, &file_path, content).await;
 
         // Update file read time after successful write
         if let Some(ref file_time) = ctx.file_time {
@@ -131,16 +139,22 @@ Usage:
         }
 
         // Generate preview (first 10 lines)
-        let preview: String = content.lines().take(10).collect::<Vec<_>>().join("\n");
+        let preview: String = formatted
+            .content
+            .lines()
+            .take(10)
+            .collect::<Vec<_>>()
+            .join("\n");
 
         Ok(ToolOutput::new(
             format!("Wrote {}", file_path.display()),
-            format!("Successfully wrote {} bytes", content.len()),
+            write_summary(file_path.as_path(), content, &formatted),
         )
         .with_metadata(json!({
             "bytes": content.len(),
             "path": file_path.display().to_string(),
-            "preview": preview
+            "preview": preview,
+            "formatter": formatted.formatter
         })))
     }
 }
@@ -209,6 +223,7 @@ impl WriteTool {
             .write_file(&sandbox_path, content.as_bytes())
             .await
             .map_err(|e| ToolError::execution_failed(format!("Sandbox write error: {e}")))?;
+        let formatted = format_written_file(ctx, file_path, content).await;
 
         // Update file read time after successful write
         if let Some(ref file_time) = ctx.file_time {
@@ -216,23 +231,43 @@ impl WriteTool {
         }
 
         // Generate preview (first 10 lines)
-        let preview: String = content.lines().take(10).collect::<Vec<_>>().join("\n");
+        let preview: String = formatted
+            .content
+            .lines()
+            .take(10)
+            .collect::<Vec<_>>()
+            .join("\n");
 
         Ok(ToolOutput::new(
             format!("Wrote {}", file_path.display()),
-            format!("Successfully wrote {} bytes", content.len()),
+            write_summary(file_path.as_path(), content, &formatted),
         )
         .with_metadata(json!({
             "bytes": content.len(),
             "path": file_path.display().to_string(),
             "sandbox_path": sandbox_path.display().to_string(),
             "sandboxed": true,
-            "preview": preview
+            "preview": preview,
+            "formatter": formatted.formatter
         })))
     }
 }
 
 /// Normalize a path by resolving `.` and `..` components without requiring the path to exist.
+/// Describe a write, including the changes the formatter made.
+fn write_summary(file_path: &Path, content: &str, formatted: &FormatOutcome) -> String {
+    let mut summary = format!("Successfully wrote {} bytes", content.len());
+    if let Some(name) = &formatted.formatter {
+        if formatted.content != content {
+            summary.push_str(&format!(
+                "\n\nFormatted with {name}:\n{}",
+                generate_diff(content, &formatted.content, file_path)
+            ));
+        }
+    }
+    summary + &formatted.note()
+}
+
 fn normalize_path(path: &std::path::Path) -> PathBuf {
     let mut components = Vec::new();
 
@@ -274,6 +309,8 @@ mod tests {
             file_time: None,
             sandbox: None,
             event_tx: None,
+            formatter: None,
+            client_fs: None,
         }
     }
 
@@ -327,6 +364,33 @@ mod tests {
         assert_eq!(content, "Hello, world!");
     }
 
+    #[tokio::test]
+    async fn test_write_runs_formatter() {
+        let dir = tempdir().unwrap();
+        let canonical_dir = dir.path().canonicalize().unwrap();
+        let file_path = canonical_dir.join("test.txt");
+        let mut ctx = test_context_with_root(canonical_dir);
+        ctx.formatter = Some(std::sync::Arc::new(
+            crate::format::tests::UppercaseFormatter,
+        ));
+
+        let result = WriteTool
+            .execute(
+                json!({
+                    "filePath": file_path.display().to_string(),
+                    "content": "hello\n"
+                }),
+                &ctx,
+            )
+            .await
+            .unwrap();
+
+        assert!(result.output.contains("Formatted with upper"));
+        assert!(result.output.contains("+HELLO"));
+        assert_eq!(result.metadata["preview"], "HELLO");
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "HELLO\n");
+    }
+
     #[tokio::test]
     async fn test_write_creates_directories() {
         let dir = tempdir().unwrap();
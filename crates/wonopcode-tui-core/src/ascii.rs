@@ -0,0 +1,91 @@
+//! ASCII replacements for Unicode box art.
+//!
+//! Borders, tool frames, status icons and spinners are drawn with box
+//! drawing characters and symbols that some terminals, fonts and screen
+//! readers handle badly. [`asciify`] rewrites a rendered frame so that each
+//! of them becomes a single ASCII character, leaving text such as CJK
+//! untouched.
+
+use ratatui::buffer::Buffer;
+
+/// Get the ASCII replacement for a symbol of box art, if it is one.
+pub fn ascii_symbol(symbol: &str) -> Option<&'static str> {
+    let mut chars = symbol.chars();
+    let ch = chars.next()?;
+    if chars.next().is_some() {
+        return None;
+    }
+    let ascii = match ch {
+        // Lines
+        '─' | '━' | '═' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '╴' | '╶' | '╸' | '╺' => {
+            "-"
+        }
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' | '╵' | '╷' | '╹' | '╻' => {
+            "|"
+        }
+        // Corners and junctions
+        '┌' | '┐' | '└' | '┘' | '╭' | '╮' | '╰' | '╯' | '┏' | '┓' | '┗' | '┛' | '╔' | '╗' | '╚'
+        | '╝' | '├' | '┤' | '┬' | '┴' | '┼' | '┣' | '┫' | '┳' | '┻' | '╋' | '╠' | '╣' | '╦'
+        | '╩' | '╬' => "+",
+        // Arrows and pointers
+        '▶' | '►' | '▸' | '›' | '→' | '⇒' => ">",
+        '◀' | '◄' | '◂' | '‹' | '←' => "<",
+        '↑' | '▲' | '▴' => "^",
+        '↓' | '▼' | '▾' => "v",
+        // Status icons
+        '●' | '•' | '◉' | '▣' | '■' | '◆' | '⬢' | '★' => "*",
+        '○' | '◯' | '◇' | '⬡' | '□' | '◦' | '☆' => "o",
+        '✓' | '✔' => "+",
+        '✗' | '✘' | '✕' | '×' => "x",
+        '…' | '⋯' | '·' => ".",
+        // Bars and sparklines
+        '▁' | '▂' | '▃' => "_",
+        '▄' | '▅' | '▆' => "=",
+        '▇' | '█' | '▉' | '▊' | '▋' | '▌' | '▍' | '▎' | '▏' | '▐' | '▀' => {
+            "#"
+        }
+        '░' | '▒' | '▓' => ":",
+        // Braille spinners
+        '\u{2800}'..='\u{28ff}' => "*",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// Replace the box art in a rendered buffer with ASCII.
+pub fn asciify(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        if let Some(ascii) = ascii_symbol(cell.symbol()) {
+            cell.set_symbol(ascii);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::layout::Rect;
+    use ratatui::widgets::{Block, Borders, Widget};
+
+    #[test]
+    fn test_ascii_symbol() {
+        assert_eq!(ascii_symbol("╭"), Some("+"));
+        assert_eq!(ascii_symbol("─"), Some("-"));
+        assert_eq!(ascii_symbol("┃"), Some("|"));
+        assert_eq!(ascii_symbol("⠋"), Some("*"));
+        assert_eq!(ascii_symbol("a"), None);
+        assert_eq!(ascii_symbol("日"), None);
+        assert_eq!(ascii_symbol(""), None);
+    }
+
+    #[test]
+    fn test_asciify_block() {
+        let area = Rect::new(0, 0, 4, 3);
+        let mut buffer = Buffer::empty(area);
+        Block::default()
+            .borders(Borders::ALL)
+            .render(area, &mut buffer);
+        asciify(&mut buffer);
+        assert_eq!(buffer, Buffer::with_lines(["+--+", "|  |", "+--+"]));
+    }
+}
@@ -1,8 +1,11 @@
 //! Event handling for the TUI.
 
 use crossterm::event::{
-    self, Event as CrosstermEvent, KeyCode, KeyEvent, KeyModifiers, MouseEvent,
+    self, DisableMouseCapture, EnableMouseCapture, Event as CrosstermEvent, KeyCode, KeyEvent,
+    KeyModifiers, MouseEvent, MouseEventKind,
 };
+use crossterm::execute;
+use std::io;
 use std::time::Duration;
 use tokio::sync::mpsc;
 
@@ -19,6 +22,10 @@ pub enum Event {
     Tick,
     /// Text was pasted (from bracketed paste mode).
     Paste(String),
+    /// The terminal gained focus.
+    FocusGained,
+    /// The terminal lost focus.
+    FocusLost,
     /// A message from the AI.
     Message(String),
     /// Status update (e.g., "thinking", "done").
@@ -65,6 +72,11 @@ impl EventHandler {
                             }
                         }
                         Ok(CrosstermEvent::Mouse(mouse)) => {
+                            // Mouse capture reports every movement; nothing uses
+                            // them, so don't wake the app up for each one
+                            if is_mouse_motion(&mouse) {
+                                continue;
+                            }
                             if sender.send(Event::Mouse(mouse)).is_err() {
                                 break;
                             }
@@ -80,8 +92,16 @@ impl EventHandler {
                                 break;
                             }
                         }
-                        Ok(CrosstermEvent::FocusGained) => {}
-                        Ok(CrosstermEvent::FocusLost) => {}
+                        Ok(CrosstermEvent::FocusGained) => {
+                            if sender.send(Event::FocusGained).is_err() {
+                                break;
+                            }
+                        }
+                        Ok(CrosstermEvent::FocusLost) => {
+                            if sender.send(Event::FocusLost).is_err() {
+                                break;
+                            }
+                        }
                         Err(e) => {
                             tracing::warn!("Error reading event: {}", e);
                         }
@@ -122,6 +142,23 @@ impl EventLoopHandle {
     }
 }
 
+/// Turn mouse capture on or off.
+///
+/// With capture off, the terminal handles the mouse itself, e.g. for native
+/// text selection and scrollback.
+pub fn set_mouse_capture(enabled: bool) -> io::Result<()> {
+    if enabled {
+        execute!(io::stdout(), EnableMouseCapture)
+    } else {
+        execute!(io::stdout(), DisableMouseCapture)
+    }
+}
+
+/// Check if a mouse event is a movement without a button pressed.
+pub fn is_mouse_motion(mouse: &MouseEvent) -> bool {
+    mouse.kind == MouseEventKind::Moved
+}
+
 /// Check if a key event is Ctrl+C.
 pub fn is_quit(key: &KeyEvent) -> bool {
     key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
@@ -275,6 +312,34 @@ mod tests {
         assert!(!is_backspace(&key));
     }
 
+    // === is_mouse_motion tests ===
+
+    fn make_mouse(kind: MouseEventKind) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column: 10,
+            row: 5,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn test_is_mouse_motion_moved() {
+        assert!(is_mouse_motion(&make_mouse(MouseEventKind::Moved)));
+    }
+
+    #[test]
+    fn test_is_mouse_motion_click_and_scroll() {
+        use crossterm::event::MouseButton;
+        assert!(!is_mouse_motion(&make_mouse(MouseEventKind::Down(
+            MouseButton::Left
+        ))));
+        assert!(!is_mouse_motion(&make_mouse(MouseEventKind::Drag(
+            MouseButton::Left
+        ))));
+        assert!(!is_mouse_motion(&make_mouse(MouseEventKind::ScrollUp)));
+    }
+
     // === EventHandler tests ===
 
     #[test]
@@ -0,0 +1,212 @@
+//! Translations of user-facing TUI strings.
+//!
+//! Strings are looked up by key, such as `toast.copied`, in the catalog of
+//! the current locale. The catalogs are the TOML bundles in `locales/`, one
+//! table per area. A key missing from a catalog falls back to English, and a
+//! key missing from English to the key itself. Placeholders are written
+//! `{name}` and filled in by [`tr_args`].
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use once_cell::sync::Lazy;
+
+/// The locale strings are currently shown in.
+static LOCALE: AtomicU8 = AtomicU8::new(Locale::English as u8);
+
+static ENGLISH: Lazy<Catalog> = Lazy::new(|| load("en", include_str!("../locales/en.toml")));
+static GERMAN: Lazy<Catalog> = Lazy::new(|| load("de", include_str!("../locales/de.toml")));
+static JAPANESE: Lazy<Catalog> = Lazy::new(|| load("ja", include_str!("../locales/ja.toml")));
+
+/// Translated strings by key.
+type Catalog = HashMap<String, String>;
+
+/// A language the TUI can be shown in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+    Japanese,
+}
+
+impl Locale {
+    /// All locales with a catalog.
+    pub const ALL: [Locale; 3] = [Locale::English, Locale::German, Locale::Japanese];
+
+    /// Get the language code, such as `de`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::German => "de",
+            Locale::Japanese => "ja",
+        }
+    }
+
+    /// Parse a language code. Region and encoding suffixes, as in `de_AT`,
+    /// `de-AT` or `ja_JP.UTF-8`, are ignored.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let language = code
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        Self::ALL.into_iter().find(|l| l.code() == language)
+    }
+
+    /// Pick the locale from `LC_ALL`, `LC_MESSAGES` or `LANG`, in that order,
+    /// falling back to English.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Self::from_code(&value))
+            .unwrap_or_default()
+    }
+
+    fn catalog(self) -> &'static Catalog {
+        match self {
+            Locale::English => &ENGLISH,
+            Locale::German => &GERMAN,
+            Locale::Japanese => &JAPANESE,
+        }
+    }
+
+    /// Look up a string in this locale, falling back to English and then to
+    /// the key itself.
+    pub fn tr(self, key: &'static str) -> &'static str {
+        self.catalog()
+            .get(key)
+            .or_else(|| ENGLISH.get(key))
+            .map(String::as_str)
+            .unwrap_or(key)
+    }
+}
+
+/// Set the locale strings are shown in.
+pub fn set_locale(locale: Locale) {
+    LOCALE.store(locale as u8, Ordering::Relaxed);
+}
+
+/// Get the locale strings are shown in.
+pub fn locale() -> Locale {
+    Locale::ALL
+        .into_iter()
+        .find(|l| *l as u8 == LOCALE.load(Ordering::Relaxed))
+        .unwrap_or_default()
+}
+
+/// Look up a string in the current locale.
+pub fn tr(key: &'static str) -> &'static str {
+    locale().tr(key)
+}
+
+/// Look up a string in the current locale and fill in its `{name}`
+/// placeholders.
+pub fn tr_args(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    fill(tr(key), args)
+}
+
+fn fill(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter().fold(text.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{{name}}}"), &value.to_string())
+    })
+}
+
+/// Read a catalog, flattening nested tables into dotted keys.
+fn load(code: &str, source: &str) -> Catalog {
+    let table = match source.parse::<toml::Table>() {
+        Ok(table) => table,
+        Err(e) => {
+            tracing::error!(locale = code, error = %e, "Invalid locale catalog");
+            return Catalog::new();
+        }
+    };
+    let mut catalog = Catalog::new();
+    flatten("", table, &mut catalog);
+    catalog
+}
+
+fn flatten(prefix: &str, table: toml::Table, catalog: &mut Catalog) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            toml::Value::String(text) => {
+                catalog.insert(key, text);
+            }
+            toml::Value::Table(table) => flatten(&key, table, catalog),
+            _ => tracing::warn!(key = %key, "Ignoring a locale entry that isn't a string"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The placeholder names in a string, sorted.
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text
+            .split('{')
+            .skip(1)
+            .filter_map(|s| s.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn test_catalogs_match_english() {
+        assert!(!ENGLISH.is_empty());
+        for locale in [Locale::German, Locale::Japanese] {
+            let catalog = locale.catalog();
+            for (key, text) in ENGLISH.iter() {
+                let translated = catalog
+                    .get(key)
+                    .unwrap_or_else(|| panic!("{} is missing {key}", locale.code()));
+                assert_eq!(
+                    placeholders(translated),
+                    placeholders(text),
+                    "{} placeholders differ for {key}",
+                    locale.code()
+                );
+            }
+            for key in catalog.keys() {
+                assert!(
+                    ENGLISH.contains_key(key),
+                    "{} has unknown {key}",
+                    locale.code()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_falls_back() {
+        assert_eq!(Locale::English.tr("toast.copied"), "Copied to clipboard");
+        assert_eq!(
+            Locale::German.tr("toast.copied"),
+            "In die Zwischenablage kopiert"
+        );
+        assert_eq!(Locale::Japanese.tr("no.such.key"), "no.such.key");
+        assert_eq!(
+            fill("Model: {id}, again {id}", &[("id", &"gpt")]),
+            "Model: gpt, again gpt"
+        );
+    }
+
+    #[test]
+    fn test_from_code() {
+        assert_eq!(Locale::from_code("de"), Some(Locale::German));
+        assert_eq!(Locale::from_code("ja_JP.UTF-8"), Some(Locale::Japanese));
+        assert_eq!(Locale::from_code("EN-us"), Some(Locale::English));
+        assert_eq!(Locale::from_code("fr"), None);
+    }
+}
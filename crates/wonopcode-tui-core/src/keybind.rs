@@ -4,11 +4,16 @@
 //! - Leader key sequences (e.g., `<leader>n` for new session)
 //! - Modifier combinations (e.g., `ctrl+c`, `alt+enter`)
 //! - Multiple bindings per action (e.g., `ctrl+c,ctrl+d`)
+//! - Validation of user bindings, reporting conflicts and unknown actions
+//! - Menus of key chords opened by a key after the leader (e.g., `<leader>g s`)
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// The leader key used when none is configured.
+const DEFAULT_LEADER: &str = "ctrl+x";
+
 /// A keybind action identifier.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -25,6 +30,12 @@ pub enum KeyAction {
     // Sidebar
     SidebarToggle,
 
+    // Panes
+    PreviewToggle,
+    FileBrowserToggle,
+    TerminalToggle,
+    GitOpen,
+
     // Session
     SessionNew,
     SessionList,
@@ -33,6 +44,12 @@ pub enum KeyAction {
     SessionCompact,
     SessionTimeline,
 
+    // Tabs
+    TabNew,
+    TabClose,
+    TabNext,
+    TabPrev,
+
     // Messages
     MessagesPageUp,
     MessagesPageDown,
@@ -81,12 +98,20 @@ impl KeyAction {
             EditorOpen,
             ThemeList,
             SidebarToggle,
+            PreviewToggle,
+            FileBrowserToggle,
+            TerminalToggle,
+            GitOpen,
             SessionNew,
             SessionList,
             SessionExport,
             SessionInterrupt,
             SessionCompact,
             SessionTimeline,
+            TabNew,
+            TabClose,
+            TabNext,
+            TabPrev,
             MessagesPageUp,
             MessagesPageDown,
             MessagesHalfPageUp,
@@ -116,22 +141,30 @@ impl KeyAction {
     pub fn default_binding(&self) -> &'static str {
         use KeyAction::*;
         match self {
-            AppExit => "ctrl+x ctrl+c",
+            AppExit => "<leader>ctrl+c",
             EditorOpen => "<leader>e",
             ThemeList => "<leader>t",
             SidebarToggle => "<leader>b",
+            PreviewToggle => "<leader>p",
+            FileBrowserToggle => "<leader>f",
+            TerminalToggle => "<leader>w",
+            GitOpen => "<leader>g",
             SessionNew => "<leader>n",
             SessionList => "<leader>l",
             SessionExport => "<leader>x",
             SessionInterrupt => "escape",
             SessionCompact => "<leader>c",
-            SessionTimeline => "<leader>g",
+            SessionTimeline => "none",
+            TabNew => "alt+t",
+            TabClose => "alt+w",
+            TabNext => "alt+right",
+            TabPrev => "alt+left",
             MessagesPageUp => "pageup",
             MessagesPageDown => "pagedown",
             MessagesHalfPageUp => "ctrl+u",
             MessagesHalfPageDown => "ctrl+d",
-            MessagesFirst => "home",
-            MessagesLast => "end",
+            MessagesFirst => "home,g",
+            MessagesLast => "end,shift+g",
             MessagesNext => "j,down",
             MessagesPrev => "k,up",
             InputSubmit => "enter",
@@ -143,14 +176,77 @@ impl KeyAction {
             EditUndo => "<leader>u",
             EditRedo => "<leader>r",
             EditCopy => "<leader>y",
-            SessionRevert => "<leader>z",
-            SessionUnrevert => "<leader>Z",
+            SessionRevert => "none",
+            SessionUnrevert => "none",
             CommandPalette => "ctrl+p",
-            HelpToggle => "?",
+            HelpToggle => "<leader>?",
             SettingsOpen => "<leader>s",
         }
     }
 
+    /// Get the name of this action in config files (e.g., `session_new`).
+    pub fn name(&self) -> &'static str {
+        use KeyAction::*;
+        match self {
+            AppExit => "app_exit",
+            EditorOpen => "editor_open",
+            ThemeList => "theme_list",
+            SidebarToggle => "sidebar_toggle",
+            PreviewToggle => "preview_toggle",
+            FileBrowserToggle => "file_browser_toggle",
+            TerminalToggle => "terminal_toggle",
+            GitOpen => "git_open",
+            SessionNew => "session_new",
+            SessionList => "session_list",
+            SessionExport => "session_export",
+            SessionInterrupt => "session_interrupt",
+            SessionCompact => "session_compact",
+            SessionTimeline => "session_timeline",
+            TabNew => "tab_new",
+            TabClose => "tab_close",
+            TabNext => "tab_next",
+            TabPrev => "tab_prev",
+            MessagesPageUp => "messages_page_up",
+            MessagesPageDown => "messages_page_down",
+            MessagesHalfPageUp => "messages_half_page_up",
+            MessagesHalfPageDown => "messages_half_page_down",
+            MessagesFirst => "messages_first",
+            MessagesLast => "messages_last",
+            MessagesNext => "messages_next",
+            MessagesPrev => "messages_prev",
+            InputSubmit => "input_submit",
+            InputNewline => "input_newline",
+            InputHistory => "input_history",
+            InputCancel => "input_cancel",
+            ModelList => "model_list",
+            AgentList => "agent_list",
+            EditUndo => "edit_undo",
+            EditRedo => "edit_redo",
+            EditCopy => "edit_copy",
+            SessionRevert => "session_revert",
+            SessionUnrevert => "session_unrevert",
+            CommandPalette => "command_palette",
+            HelpToggle => "help_toggle",
+            SettingsOpen => "settings_open",
+        }
+    }
+
+    /// Look up an action by its config name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().iter().copied().find(|a| a.name() == name)
+    }
+
+    /// Where the action's bindings apply.
+    pub fn scope(&self) -> KeyScope {
+        use KeyAction::*;
+        match self {
+            MessagesPageUp | MessagesPageDown | MessagesHalfPageUp | MessagesHalfPageDown
+            | MessagesFirst | MessagesLast | MessagesNext | MessagesPrev => KeyScope::Messages,
+            InputSubmit | InputNewline | InputHistory | InputCancel => KeyScope::Input,
+            _ => KeyScope::Global,
+        }
+    }
+
     /// Get a human-readable description of this action.
     pub fn description(&self) -> &'static str {
         use KeyAction::*;
@@ -159,20 +255,28 @@ impl KeyAction {
             EditorOpen => "Open external editor",
             ThemeList => "List available themes",
             SidebarToggle => "Toggle sidebar",
+            PreviewToggle => "Toggle preview pane",
+            FileBrowserToggle => "Toggle or focus file browser",
+            TerminalToggle => "Toggle or focus background process output",
+            GitOpen => "Open git dialog",
             SessionNew => "Create a new session",
             SessionList => "List all sessions",
             SessionExport => "Export session",
             SessionInterrupt => "Interrupt current operation",
             SessionCompact => "Compact the session",
             SessionTimeline => "Show session timeline",
+            TabNew => "Open a session in a new tab",
+            TabClose => "Close the current tab",
+            TabNext => "Switch to the next tab",
+            TabPrev => "Switch to the previous tab",
             MessagesPageUp => "Scroll messages up by one page",
             MessagesPageDown => "Scroll messages down by one page",
             MessagesHalfPageUp => "Scroll messages up by half page",
             MessagesHalfPageDown => "Scroll messages down by half page",
             MessagesFirst => "Navigate to first message",
             MessagesLast => "Navigate to last message",
-            MessagesNext => "Navigate to next message",
-            MessagesPrev => "Navigate to previous message",
+            MessagesNext => "Scroll messages down by one line",
+            MessagesPrev => "Scroll messages up by one line",
             InputSubmit => "Submit input",
             InputNewline => "Insert newline",
             InputHistory => "Navigate input history",
@@ -185,12 +289,33 @@ impl KeyAction {
             SessionRevert => "Revert to previous message",
             SessionUnrevert => "Cancel revert",
             CommandPalette => "Open command palette",
-            HelpToggle => "Toggle help",
+            HelpToggle => "Show help",
             SettingsOpen => "Open settings",
         }
     }
 }
 
+/// Where an action's bindings apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScope {
+    /// Anywhere outside dialogs.
+    Global,
+    /// While the messages have focus.
+    Messages,
+    /// While typing. The input handles these itself, so they can't be rebound.
+    Input,
+}
+
+impl KeyScope {
+    /// Whether bindings in both scopes can be active at the same time.
+    fn overlaps(self, other: KeyScope) -> bool {
+        !matches!(
+            (self, other),
+            (KeyScope::Messages, KeyScope::Input) | (KeyScope::Input, KeyScope::Messages)
+        )
+    }
+}
+
 /// A parsed keybind.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Keybind {
@@ -243,7 +368,14 @@ impl Keybind {
     }
 
     /// Parse a keybind string (e.g., "ctrl+c", "`<leader>n`").
+    ///
+    /// An uppercase letter implies shift, so `<leader>Z` is `<leader>shift+z`.
     pub fn parse(s: &str) -> Option<Self> {
+        let shifted_letter = s
+            .trim()
+            .rsplit(['+', '>'])
+            .next()
+            .is_some_and(|key| key.len() == 1 && key.chars().all(|c| c.is_ascii_uppercase()));
         let s = s.trim().to_lowercase();
         if s == "none" || s.is_empty() {
             return None;
@@ -285,6 +417,7 @@ impl Keybind {
         if keybind.key.is_empty() {
             None
         } else {
+            keybind.shift |= shifted_letter;
             Some(keybind)
         }
     }
@@ -303,10 +436,16 @@ impl Keybind {
             return false;
         }
 
-        // Check modifiers
+        // Check modifiers. Shift is part of symbols like '?', and uppercase
+        // letters imply it.
+        let shift = match key.code {
+            KeyCode::Char(c) if !c.is_alphabetic() => self.shift,
+            KeyCode::Char(c) => key.modifiers.contains(KeyModifiers::SHIFT) || c.is_uppercase(),
+            _ => key.modifiers.contains(KeyModifiers::SHIFT),
+        };
         let ctrl_match = self.ctrl == key.modifiers.contains(KeyModifiers::CONTROL);
         let alt_match = self.alt == key.modifiers.contains(KeyModifiers::ALT);
-        let shift_match = self.shift == key.modifiers.contains(KeyModifiers::SHIFT);
+        let shift_match = self.shift == shift;
 
         if !ctrl_match || !alt_match || !shift_match {
             return false;
@@ -336,11 +475,17 @@ impl Keybind {
 
     /// Convert to display string.
     pub fn to_display(&self) -> String {
-        let mut parts = Vec::new();
-
         if self.leader {
-            parts.push("<leader>".to_string());
+            format!("<leader> {}", self.chord_display())
+        } else {
+            self.chord_display()
         }
+    }
+
+    /// Display string of the key and its modifiers, without the leader.
+    pub fn chord_display(&self) -> String {
+        let mut parts = Vec::new();
+
         if self.ctrl {
             parts.push("Ctrl".to_string());
         }
@@ -367,15 +512,14 @@ impl Keybind {
             "down" => "Down",
             "left" => "Left",
             "right" => "Right",
-            k => k,
+            k => {
+                parts.push(k.to_uppercase());
+                return parts.join("+");
+            }
         };
 
-        if self.leader {
-            format!("{} {}", parts.join("+"), key_display.to_uppercase())
-        } else {
-            parts.push(key_display.to_uppercase());
-            parts.join("+")
-        }
+        parts.push(key_display.to_string());
+        parts.join("+")
     }
 }
 
@@ -385,26 +529,78 @@ impl Keybind {
 pub struct KeybindConfig {
     /// The leader key binding.
     pub leader: String,
+    /// Menus opened by a key after the leader, by that key.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub menus: HashMap<String, ChordMenuConfig>,
     /// Action to keybind mappings.
     #[serde(flatten)]
     pub bindings: HashMap<String, String>,
 }
 
+/// Configuration of a menu of key chords.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChordMenuConfig {
+    /// Title of the menu.
+    pub title: Option<String>,
+    /// What each key runs: an action name or a slash command such as `/diff`.
+    pub keys: HashMap<String, String>,
+}
+
 impl Default for KeybindConfig {
     fn default() -> Self {
         let mut bindings = HashMap::new();
         for action in KeyAction::all() {
-            let key = format!("{action:?}").to_lowercase();
-            bindings.insert(key, action.default_binding().to_string());
+            bindings.insert(
+                action.name().to_string(),
+                action.default_binding().to_string(),
+            );
         }
 
         Self {
-            leader: "ctrl+x".to_string(),
+            leader: DEFAULT_LEADER.to_string(),
+            menus: HashMap::new(),
             bindings,
         }
     }
 }
 
+/// What a key in a chord menu runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChordCommand {
+    /// A keybind action.
+    Action(KeyAction),
+    /// A slash command, without the slash.
+    Slash(String),
+}
+
+impl ChordCommand {
+    /// Parse an action name or a slash command.
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        match s.strip_prefix('/') {
+            Some(command) if !command.trim().is_empty() => {
+                Some(Self::Slash(command.trim().to_string()))
+            }
+            Some(_) => None,
+            None => KeyAction::from_name(s)
+                .filter(|action| action.scope() != KeyScope::Input)
+                .map(Self::Action),
+        }
+    }
+}
+
+/// A menu of key chords, opened by a key after the leader.
+#[derive(Debug, Clone)]
+pub struct ChordMenu {
+    /// The key after the leader that opens the menu.
+    pub key: Keybind,
+    /// Title of the menu.
+    pub title: String,
+    /// The keys of the menu and what they run, in key order.
+    pub entries: Vec<(Keybind, ChordCommand)>,
+}
+
 /// Keybind manager that handles matching and lookup.
 #[derive(Debug, Clone)]
 pub struct KeybindManager {
@@ -412,8 +608,12 @@ pub struct KeybindManager {
     leader: Vec<Keybind>,
     /// Action to keybinds mapping.
     actions: HashMap<KeyAction, Vec<Keybind>>,
+    /// Chord menus, in key order.
+    menus: Vec<ChordMenu>,
     /// Whether leader is currently active.
     leader_active: bool,
+    /// Problems found in the configuration.
+    warnings: Vec<String>,
 }
 
 impl Default for KeybindManager {
@@ -424,27 +624,126 @@ impl Default for KeybindManager {
 
 impl KeybindManager {
     /// Create a new keybind manager from configuration.
+    ///
+    /// Actions missing from the configuration keep their default bindings.
+    /// Unknown actions, invalid keys and conflicting bindings are skipped and
+    /// reported in [`Self::warnings`]. Of two actions bound to the same key,
+    /// the input action or else the one listed first in [`KeyAction::all`]
+    /// keeps it. A menu takes its key after the leader from any action.
     pub fn new(config: &KeybindConfig) -> Self {
-        let leader = Keybind::parse_multi(&config.leader);
+        let mut warnings = Vec::new();
+
+        let mut leader = parse_checked(&config.leader, "leader", &mut warnings);
+        if leader.is_empty() {
+            warnings.push(format!(
+                "No valid leader key in '{}', using {DEFAULT_LEADER}",
+                config.leader
+            ));
+            leader = Keybind::parse_multi(DEFAULT_LEADER);
+        }
 
-        let mut actions = HashMap::new();
-        for action in KeyAction::all() {
-            let key = format!("{action:?}").to_lowercase();
+        let mut names: Vec<&String> = config.bindings.keys().collect();
+        names.sort();
+        for name in names {
+            match KeyAction::from_name(name) {
+                None => warnings.push(format!("Unknown keybind action '{name}'")),
+                Some(action)
+                    if action.scope() == KeyScope::Input
+                        && config.bindings[name] != action.default_binding() =>
+                {
+                    warnings.push(format!("'{name}' can't be rebound"));
+                }
+                Some(_) => {}
+            }
+        }
+
+        // Input actions are fixed, so they keep their keys in any conflict
+        let (fixed, rebindable): (Vec<KeyAction>, Vec<KeyAction>) = KeyAction::all()
+            .iter()
+            .partition(|a| a.scope() == KeyScope::Input);
+        let mut actions: HashMap<KeyAction, Vec<Keybind>> = HashMap::new();
+        for action in fixed.into_iter().chain(rebindable) {
             let binding = config
                 .bindings
-                .get(&key)
+                .get(action.name())
+                .filter(|_| action.scope() != KeyScope::Input)
                 .map(|s| s.as_str())
                 .unwrap_or(action.default_binding());
-            actions.insert(*action, Keybind::parse_multi(binding));
+            let mut keybinds = parse_checked(binding, action.name(), &mut warnings);
+
+            keybinds.retain(|kb| {
+                if leader.contains(kb) {
+                    warnings.push(format!(
+                        "{} is the leader key, so it can't be bound to '{}'",
+                        kb.to_display(),
+                        action.name()
+                    ));
+                    return false;
+                }
+                let taken = actions.iter().find(|(other, bound)| {
+                    other.scope().overlaps(action.scope()) && bound.contains(kb)
+                });
+                if let Some((other, _)) = taken {
+                    warnings.push(format!(
+                        "{} is bound to both '{}' and '{}', keeping '{}'",
+                        kb.to_display(),
+                        other.name(),
+                        action.name(),
+                        other.name()
+                    ));
+                    return false;
+                }
+                true
+            });
+            actions.insert(action, keybinds);
+        }
+
+        let menus = parse_menus(&config.menus, &leader, &mut warnings);
+        for (action, keybinds) in &mut actions {
+            keybinds.retain(|kb| {
+                let Some(menu) = menus.iter().find(|m| &m.key == kb) else {
+                    return true;
+                };
+                // Only report bindings the user changed; defaults just give way
+                let rebound = config
+                    .bindings
+                    .get(action.name())
+                    .is_some_and(|binding| binding != action.default_binding());
+                if rebound {
+                    warnings.push(format!(
+                        "{} opens the '{}' menu, so it can't be bound to '{}'",
+                        kb.to_display(),
+                        menu.title,
+                        action.name()
+                    ));
+                }
+                false
+            });
         }
 
         Self {
             leader,
             actions,
+            menus,
             leader_active: false,
+            warnings,
         }
     }
 
+    /// Problems found in the configuration, in a form to show the user.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Display string of the leader key (e.g., "Ctrl+X").
+    pub fn leader_display(&self) -> String {
+        self.leader
+            .iter()
+            .map(Keybind::chord_display)
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
     /// Check if the leader key was pressed.
     pub fn is_leader(&self, key: &KeyEvent) -> bool {
         self.leader.iter().any(|kb| kb.matches(key, false))
@@ -461,15 +760,51 @@ impl KeybindManager {
     }
 
     /// Find the action for a key event.
+    ///
+    /// After the leader, letters match regardless of case unless a binding
+    /// tells the cases apart.
     pub fn find_action(&self, key: &KeyEvent) -> Option<KeyAction> {
-        for (action, keybinds) in &self.actions {
-            for kb in keybinds {
-                if kb.matches(key, self.leader_active) {
-                    return Some(*action);
-                }
+        self.find(key).or_else(|| {
+            if !self.leader_active {
+                return None;
             }
-        }
-        None
+            self.find(&unshifted(key)?)
+        })
+    }
+
+    /// The chord menus, in key order.
+    pub fn menus(&self) -> &[ChordMenu] {
+        &self.menus
+    }
+
+    /// Find the index of the menu a key after the leader opens.
+    ///
+    /// Letters match regardless of case unless a menu tells the cases apart.
+    pub fn find_menu(&self, key: &KeyEvent) -> Option<usize> {
+        let find = |key: &KeyEvent| self.menus.iter().position(|m| m.key.matches(key, true));
+        find(key).or_else(|| find(&unshifted(key)?))
+    }
+
+    /// Find what a key runs in an open menu.
+    ///
+    /// Letters match regardless of case unless the menu tells the cases apart.
+    pub fn find_menu_command(&self, menu: usize, key: &KeyEvent) -> Option<&ChordCommand> {
+        let entries = &self.menus.get(menu)?.entries;
+        let find = |key: &KeyEvent| {
+            entries
+                .iter()
+                .find(|(kb, _)| kb.matches(key, true))
+                .map(|(_, command)| command)
+        };
+        find(key).or_else(|| find(&unshifted(key)?))
+    }
+
+    fn find(&self, key: &KeyEvent) -> Option<KeyAction> {
+        KeyAction::all().iter().copied().find(|action| {
+            self.get_bindings(*action)
+                .iter()
+                .any(|kb| kb.matches(key, self.leader_active))
+        })
     }
 
     /// Get the keybinds for an action.
@@ -480,12 +815,21 @@ impl KeybindManager {
             .unwrap_or(&[])
     }
 
-    /// Get the display string for an action's primary binding.
+    /// Get the display string for an action's bindings, with the leader
+    /// spelled out (e.g., "Ctrl+X N" or "J/Down"). Empty if unbound.
     pub fn get_display(&self, action: KeyAction) -> String {
+        let leader = self.leader_display();
         self.get_bindings(action)
-            .first()
-            .map(|kb| kb.to_display())
-            .unwrap_or_default()
+            .iter()
+            .map(|kb| {
+                if kb.leader {
+                    format!("{leader} {}", kb.chord_display())
+                } else {
+                    kb.chord_display()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
     }
 
     /// Reset leader state (call after handling leader sequence or timeout).
@@ -494,6 +838,119 @@ impl KeybindManager {
     }
 }
 
+/// The key event of a letter without shift, or None for other keys.
+fn unshifted(key: &KeyEvent) -> Option<KeyEvent> {
+    let KeyCode::Char(c) = key.code else {
+        return None;
+    };
+    let mut lower = *key;
+    lower.code = KeyCode::Char(c.to_ascii_lowercase());
+    lower.modifiers.remove(KeyModifiers::SHIFT);
+    Some(lower)
+}
+
+/// Parse the configured chord menus, reporting the problems with them.
+///
+/// Menu keys and the keys inside menus are pressed after the leader, so they
+/// are parsed as leader keybinds.
+fn parse_menus(
+    config: &HashMap<String, ChordMenuConfig>,
+    leader: &[Keybind],
+    warnings: &mut Vec<String>,
+) -> Vec<ChordMenu> {
+    let mut keys: Vec<&String> = config.keys().collect();
+    keys.sort();
+
+    let mut menus: Vec<ChordMenu> = Vec::new();
+    for key in keys {
+        let menu = &config[key];
+        let title = menu
+            .title
+            .clone()
+            .unwrap_or_else(|| format!("Menu {}", key.trim()));
+        let Some(menu_key) = parse_leader_key(key, &title, warnings) else {
+            continue;
+        };
+        if leader.contains(&Keybind {
+            leader: false,
+            ..menu_key.clone()
+        }) {
+            warnings.push(format!(
+                "{} is the leader key, so it can't open the '{title}' menu",
+                menu_key.chord_display()
+            ));
+            continue;
+        }
+        if let Some(other) = menus.iter().find(|m| m.key == menu_key) {
+            warnings.push(format!(
+                "{} opens both the '{}' and '{title}' menus, keeping '{}'",
+                menu_key.to_display(),
+                other.title,
+                other.title
+            ));
+            continue;
+        }
+
+        let mut entry_keys: Vec<&String> = menu.keys.keys().collect();
+        entry_keys.sort();
+        let mut entries: Vec<(Keybind, ChordCommand)> = Vec::new();
+        for entry_key in entry_keys {
+            let Some(keybind) = parse_leader_key(entry_key, &title, warnings) else {
+                continue;
+            };
+            let value = &menu.keys[entry_key];
+            let Some(command) = ChordCommand::parse(value) else {
+                warnings.push(format!("Unknown action '{value}' in the '{title}' menu"));
+                continue;
+            };
+            if entries.iter().any(|(kb, _)| kb == &keybind) {
+                warnings.push(format!(
+                    "{} is in the '{title}' menu twice",
+                    keybind.chord_display()
+                ));
+                continue;
+            }
+            entries.push((keybind, command));
+        }
+
+        if entries.is_empty() {
+            warnings.push(format!("The '{title}' menu has no keys"));
+            continue;
+        }
+        menus.push(ChordMenu {
+            key: menu_key,
+            title,
+            entries,
+        });
+    }
+    menus
+}
+
+/// Parse a single key pressed after the leader, reporting it if it doesn't
+/// parse.
+fn parse_leader_key(s: &str, menu: &str, warnings: &mut Vec<String>) -> Option<Keybind> {
+    match Keybind::parse(s) {
+        Some(keybind) if !s.contains(',') => Some(keybind.leader()),
+        _ => {
+            warnings.push(format!("Invalid key '{s}' in the '{menu}' menu"));
+            None
+        }
+    }
+}
+
+/// Parse comma-separated keybinds, reporting the ones that don't parse.
+fn parse_checked(s: &str, name: &str, warnings: &mut Vec<String>) -> Vec<Keybind> {
+    let mut keybinds = Vec::new();
+    for part in s.split(',').map(str::trim) {
+        match Keybind::parse(part) {
+            Some(kb) => keybinds.push(kb),
+            None if part.eq_ignore_ascii_case("none") => {}
+            None => warnings.push(format!("Invalid key '{part}' for '{name}'")),
+        }
+    }
+    keybinds
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -570,5 +1027,199 @@ mod tests {
 
         let kb2 = Keybind::new("n").leader();
         assert_eq!(kb2.to_display(), "<leader> N");
+
+        let manager = KeybindManager::new(&KeybindConfig::default());
+        assert_eq!(manager.get_display(KeyAction::SessionNew), "Ctrl+X N");
+        assert_eq!(manager.get_display(KeyAction::MessagesNext), "J/Down");
+        assert_eq!(manager.get_display(KeyAction::SessionTimeline), "");
+    }
+
+    #[test]
+    fn test_action_names_round_trip() {
+        for action in KeyAction::all() {
+            assert_eq!(KeyAction::from_name(action.name()), Some(*action));
+            let json = serde_json::to_string(action).unwrap();
+            assert_eq!(json, format!("\"{}\"", action.name()));
+        }
+        assert_eq!(KeyAction::from_name("sessionnew"), None);
+    }
+
+    #[test]
+    fn test_uppercase_implies_shift() {
+        let kb = Keybind::parse("<leader>Z").unwrap();
+        assert!(kb.shift && kb.leader);
+        assert_eq!(kb.key, "z");
+
+        let upper = KeyEvent::new(KeyCode::Char('Z'), KeyModifiers::SHIFT);
+        let lower = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::NONE);
+        assert!(kb.matches(&upper, true));
+        assert!(!kb.matches(&lower, true));
+
+        // Shift is part of symbols
+        let question = KeyEvent::new(KeyCode::Char('?'), KeyModifiers::SHIFT);
+        assert!(Keybind::parse("?").unwrap().matches(&question, false));
+    }
+
+    #[test]
+    fn test_defaults_have_no_warnings() {
+        let manager = KeybindManager::new(&KeybindConfig::default());
+        assert!(manager.warnings().is_empty(), "{:?}", manager.warnings());
+
+        // Defaults that tell cases apart
+        let g = KeyEvent::new(KeyCode::Char('g'), KeyModifiers::NONE);
+        let shift_g = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(manager.find_action(&g), Some(KeyAction::MessagesFirst));
+        assert_eq!(manager.find_action(&shift_g), Some(KeyAction::MessagesLast));
+
+        // Tabs switch without the leader, so they work while the agent runs
+        let alt_right = KeyEvent::new(KeyCode::Right, KeyModifiers::ALT);
+        let alt_t = KeyEvent::new(KeyCode::Char('t'), KeyModifiers::ALT);
+        assert_eq!(manager.find_action(&alt_right), Some(KeyAction::TabNext));
+        assert_eq!(manager.find_action(&alt_t), Some(KeyAction::TabNew));
+    }
+
+    #[test]
+    fn test_user_bindings() {
+        let mut config = KeybindConfig {
+            leader: "ctrl+b".to_string(),
+            ..Default::default()
+        };
+        config
+            .bindings
+            .insert("session_new".to_string(), "<leader>o,ctrl+n".to_string());
+        config
+            .bindings
+            .insert("sidebar_toggle".to_string(), "none".to_string());
+        let mut manager = KeybindManager::new(&config);
+        assert!(manager.warnings().is_empty(), "{:?}", manager.warnings());
+        assert_eq!(manager.leader_display(), "Ctrl+B");
+        assert_eq!(
+            manager.get_display(KeyAction::SessionNew),
+            "Ctrl+B O/Ctrl+N"
+        );
+
+        let ctrl_n = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::CONTROL);
+        assert_eq!(manager.find_action(&ctrl_n), Some(KeyAction::SessionNew));
+
+        manager.set_leader_active(true);
+        let b = KeyEvent::new(KeyCode::Char('b'), KeyModifiers::NONE);
+        assert_eq!(manager.find_action(&b), None);
+        // Letters after the leader match either case
+        let shift_o = KeyEvent::new(KeyCode::Char('O'), KeyModifiers::SHIFT);
+        assert_eq!(manager.find_action(&shift_o), Some(KeyAction::SessionNew));
+    }
+
+    #[test]
+    fn test_invalid_bindings_are_reported() {
+        let mut config = KeybindConfig {
+            leader: "ctrl+".to_string(),
+            ..Default::default()
+        };
+        for (name, binding) in [
+            ("session_lst", "<leader>l"),
+            ("input_submit", "ctrl+s"),
+            ("theme_list", "ctrl+"),
+            // Conflicts with the command palette, which comes first
+            ("settings_open", "ctrl+p"),
+            ("agent_list", "ctrl+x"),
+        ] {
+            config
+                .bindings
+                .insert(name.to_string(), binding.to_string());
+        }
+        let manager = KeybindManager::new(&config);
+        let warnings = manager.warnings().join("\n");
+        assert!(warnings.contains("using ctrl+x"), "{warnings}");
+        assert!(warnings.contains("Unknown keybind action 'session_lst'"));
+        assert!(warnings.contains("'input_submit' can't be rebound"));
+        assert!(warnings.contains("Invalid key 'ctrl+' for 'theme_list'"));
+        assert!(warnings.contains("Ctrl+X is the leader key"));
+        assert!(warnings.contains(
+            "Ctrl+P is bound to both 'command_palette' and 'settings_open', keeping 'command_palette'"
+        ));
+        assert_eq!(manager.warnings().len(), 7);
+
+        // The input action keeps its default, the conflicting one loses its key
+        assert_eq!(manager.get_display(KeyAction::InputSubmit), "Enter");
+        assert_eq!(manager.get_display(KeyAction::SettingsOpen), "");
+        assert_eq!(manager.get_display(KeyAction::CommandPalette), "Ctrl+P");
+    }
+
+    fn git_menu() -> ChordMenuConfig {
+        let mut menu = ChordMenuConfig {
+            title: Some("git".to_string()),
+            ..Default::default()
+        };
+        for (key, command) in [("g", "git_open"), ("d", "/preview diff"), ("S", "/git")] {
+            menu.keys.insert(key.to_string(), command.to_string());
+        }
+        menu
+    }
+
+    #[test]
+    fn test_chord_menus() {
+        let mut config = KeybindConfig::default();
+        config.menus.insert("g".to_string(), git_menu());
+        let mut manager = KeybindManager::new(&config);
+        assert!(manager.warnings().is_empty(), "{:?}", manager.warnings());
+
+        // The menu takes the key from the git dialog without a warning
+        assert_eq!(manager.get_display(KeyAction::GitOpen), "");
+        manager.set_leader_active(true);
+        let g = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(manager.find_action(&g), None);
+        assert_eq!(manager.find_menu(&g), Some(0));
+        assert_eq!(manager.menus()[0].title, "git");
+
+        let key = |c: char| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+        assert_eq!(
+            manager.find_menu_command(0, &key('g')),
+            Some(&ChordCommand::Action(KeyAction::GitOpen))
+        );
+        assert_eq!(
+            manager.find_menu_command(0, &key('D')),
+            Some(&ChordCommand::Slash("preview diff".to_string()))
+        );
+        // Shift+S is in the menu, so plain s doesn't match it
+        assert_eq!(
+            manager.find_menu_command(0, &key('S')),
+            Some(&ChordCommand::Slash("git".to_string()))
+        );
+        assert_eq!(manager.find_menu_command(0, &key('s')), None);
+        assert_eq!(manager.find_menu_command(1, &key('g')), None);
+    }
+
+    #[test]
+    fn test_invalid_chord_menus_are_reported() {
+        let mut config = KeybindConfig::default();
+        config
+            .bindings
+            .insert("git_open".to_string(), "<leader>g,ctrl+g".to_string());
+        config.menus.insert("g".to_string(), git_menu());
+        config.menus.insert("ctrl+x".to_string(), git_menu());
+        let mut broken = ChordMenuConfig::default();
+        broken
+            .keys
+            .insert("a".to_string(), "input_submit".to_string());
+        broken.keys.insert("b".to_string(), "/".to_string());
+        broken
+            .keys
+            .insert("ctrl+".to_string(), "git_open".to_string());
+        config.menus.insert("o".to_string(), broken);
+
+        let manager = KeybindManager::new(&config);
+        let warnings = manager.warnings().join("\n");
+        assert!(
+            warnings.contains("Ctrl+X is the leader key, so it can't open the 'git' menu"),
+            "{warnings}"
+        );
+        assert!(warnings.contains("Unknown action 'input_submit' in the 'Menu o' menu"));
+        assert!(warnings.contains("Unknown action '/' in the 'Menu o' menu"));
+        assert!(warnings.contains("Invalid key 'ctrl+' in the 'Menu o' menu"));
+        assert!(warnings.contains("The 'Menu o' menu has no keys"));
+        assert!(warnings
+            .contains("<leader> G opens the 'git' menu, so it can't be bound to 'git_open'"));
+        assert_eq!(manager.warnings().len(), 6);
+        assert_eq!(manager.menus().len(), 1);
     }
 }
@@ -1,27 +1,44 @@
 //! Core types and utilities for wonopcode TUI.
 //!
 //! This crate provides foundational types shared across all TUI crates:
-//! - Theme system with color definitions
+//! - Theme system with color definitions and user theme files
+//! - ASCII replacements for box art
 //! - Keybind configuration and management
 //! - Event handling
+//! - Translations of user-facing strings
 //! - Performance metrics
+//! - Notifications while the terminal is unfocused
 //! - Model state persistence
 
+pub mod ascii;
 pub mod event;
+pub mod i18n;
 pub mod keybind;
 pub mod metrics;
 pub mod model_state;
+pub mod notify;
 pub mod theme;
+pub mod theme_file;
 
-pub use event::{is_backspace, is_enter, is_escape, is_quit, Event, EventHandler, EventLoopHandle};
-pub use keybind::{KeyAction, Keybind, KeybindConfig, KeybindManager};
+pub use ascii::asciify;
+pub use event::{
+    is_backspace, is_enter, is_escape, is_mouse_motion, is_quit, set_mouse_capture, Event,
+    EventHandler, EventLoopHandle,
+};
+pub use i18n::{locale, set_locale, tr, tr_args, Locale};
+pub use keybind::{
+    ChordCommand, ChordMenu, ChordMenuConfig, KeyAction, KeyScope, Keybind, KeybindConfig,
+    KeybindManager,
+};
 pub use metrics::{
     complete_input_latency, event_timer, frame_timer, get as get_metrics, init as init_metrics,
     is_enabled as metrics_enabled, mark_input_start, record_event, record_frame,
-    record_input_latency, record_scroll, record_widget, reset as reset_metrics,
+    record_input_latency, record_markdown, record_scroll, record_widget, reset as reset_metrics,
     set_enabled as set_metrics_enabled, summary as metrics_summary, widget_timer, EventType,
     MetricsSummary, TimerGuard, TuiMetrics, WidgetSummary, SLOW_FRAME_THRESHOLD_MS,
     VERY_SLOW_FRAME_THRESHOLD_MS,
 };
 pub use model_state::ModelState;
+pub use notify::{Notifier, NotifyMethod};
 pub use theme::{AgentMode, RenderSettings, Theme};
+pub use theme_file::ThemeRegistry;
@@ -6,6 +6,7 @@
 //! - Input responsiveness (time from keypress to screen update)
 //! - Scroll performance
 //! - Per-widget render times
+//! - Markdown reused between renders of streaming text
 //!
 //! # Usage
 //!
@@ -116,6 +117,14 @@ pub fn record_widget(name: &str, duration: Duration) {
     }
 }
 
+/// Record an incremental markdown render: the bytes of text rendered, and
+/// the bytes whose rendered lines were reused.
+pub fn record_markdown(rendered: usize, reused: usize) {
+    if let Some(m) = get() {
+        m.record_markdown(rendered, reused);
+    }
+}
+
 /// Mark the start of input processing (for latency measurement).
 pub fn mark_input_start() -> Option<Instant> {
     if is_enabled() {
@@ -312,6 +321,10 @@ pub struct TuiMetrics {
     slow_frames: AtomicU64,
     /// Very slow frames (>50ms).
     very_slow_frames: AtomicU64,
+    /// Bytes of streaming markdown rendered.
+    markdown_rendered_bytes: AtomicU64,
+    /// Bytes of streaming markdown whose rendered lines were reused.
+    markdown_reused_bytes: AtomicU64,
     /// Session start time.
     start_time: Instant,
 }
@@ -333,6 +346,8 @@ impl TuiMetrics {
             total_frames: AtomicU64::new(0),
             slow_frames: AtomicU64::new(0),
             very_slow_frames: AtomicU64::new(0),
+            markdown_rendered_bytes: AtomicU64::new(0),
+            markdown_reused_bytes: AtomicU64::new(0),
             start_time: Instant::now(),
         }
     }
@@ -397,6 +412,13 @@ impl TuiMetrics {
         }
     }
 
+    fn record_markdown(&self, rendered: usize, reused: usize) {
+        self.markdown_rendered_bytes
+            .fetch_add(rendered as u64, Ordering::Relaxed);
+        self.markdown_reused_bytes
+            .fetch_add(reused as u64, Ordering::Relaxed);
+    }
+
     fn reset(&self) {
         if let Ok(mut s) = self.frame_stats.lock() {
             s.reset();
@@ -418,6 +440,8 @@ impl TuiMetrics {
         self.total_frames.store(0, Ordering::Relaxed);
         self.slow_frames.store(0, Ordering::Relaxed);
         self.very_slow_frames.store(0, Ordering::Relaxed);
+        self.markdown_rendered_bytes.store(0, Ordering::Relaxed);
+        self.markdown_reused_bytes.store(0, Ordering::Relaxed);
     }
 
     /// Get a summary of current metrics.
@@ -431,6 +455,9 @@ impl TuiMetrics {
         let total_frames = self.total_frames.load(Ordering::Relaxed);
         let slow_frames = self.slow_frames.load(Ordering::Relaxed);
         let very_slow_frames = self.very_slow_frames.load(Ordering::Relaxed);
+        let markdown_rendered_bytes = self.markdown_rendered_bytes.load(Ordering::Relaxed);
+        let markdown_reused_bytes = self.markdown_reused_bytes.load(Ordering::Relaxed);
+        let markdown_total = markdown_rendered_bytes + markdown_reused_bytes;
 
         let uptime_secs = self.start_time.elapsed().as_secs_f64();
         let fps = if uptime_secs > 0.0 {
@@ -460,6 +487,13 @@ impl TuiMetrics {
             avg_input_latency_ms: input.as_ref().map(|i| i.avg()).unwrap_or(0.0),
             p99_input_latency_ms: input.as_ref().map(|i| i.percentile(99.0)).unwrap_or(0.0),
             avg_scroll_ms: scroll.as_ref().map(|s| s.avg()).unwrap_or(0.0),
+            markdown_rendered_bytes,
+            markdown_reused_bytes,
+            markdown_reuse_pct: if markdown_total > 0 {
+                (markdown_reused_bytes as f64 / markdown_total as f64) * 100.0
+            } else {
+                0.0
+            },
             widget_stats: widgets
                 .map(|w| {
                     w.iter()
@@ -511,6 +545,12 @@ pub struct MetricsSummary {
     pub p99_input_latency_ms: f64,
     /// Average scroll operation time in ms.
     pub avg_scroll_ms: f64,
+    /// Bytes of streaming markdown rendered.
+    pub markdown_rendered_bytes: u64,
+    /// Bytes of streaming markdown whose rendered lines were reused.
+    pub markdown_reused_bytes: u64,
+    /// Percentage of streaming markdown reused rather than rendered.
+    pub markdown_reuse_pct: f64,
     /// Per-widget statistics.
     pub widget_stats: Vec<WidgetSummary>,
 }
@@ -578,6 +618,18 @@ impl MetricsSummary {
             self.avg_scroll_ms
         ));
 
+        if self.markdown_rendered_bytes + self.markdown_reused_bytes > 0 {
+            report.push_str("Streaming Markdown:\n");
+            report.push_str(&format!(
+                "  Rendered:          {} bytes\n",
+                self.markdown_rendered_bytes
+            ));
+            report.push_str(&format!(
+                "  Reused:            {} bytes ({:.1}%)\n\n",
+                self.markdown_reused_bytes, self.markdown_reuse_pct
+            ));
+        }
+
         if !self.widget_stats.is_empty() {
             report.push_str("Widget Render Times (top 10 by avg):\n");
             let mut sorted = self.widget_stats.clone();
@@ -623,6 +675,11 @@ impl MetricsSummary {
                 "latency_p99": self.p99_input_latency_ms,
                 "scroll_avg": self.avg_scroll_ms,
             },
+            "markdown": {
+                "rendered_bytes": self.markdown_rendered_bytes,
+                "reused_bytes": self.markdown_reused_bytes,
+                "reuse_pct": self.markdown_reuse_pct,
+            },
             "widgets": self.widget_stats.iter().map(|w| {
                 serde_json::json!({
                     "name": w.name,
@@ -717,4 +774,18 @@ mod tests {
         assert!(messages.is_some());
         assert!((messages.unwrap().avg_ms - 7.5).abs() < 0.5);
     }
+
+    #[test]
+    fn test_markdown_metrics() {
+        init();
+
+        record_markdown(100, 0);
+        record_markdown(20, 300);
+
+        let summary = summary().unwrap();
+        assert!(summary.markdown_rendered_bytes >= 120);
+        assert!(summary.markdown_reused_bytes >= 300);
+        assert!(summary.markdown_reuse_pct > 0.0);
+        assert!(summary.to_report().contains("Streaming Markdown"));
+    }
 }
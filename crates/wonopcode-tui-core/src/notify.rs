@@ -0,0 +1,192 @@
+//! Notifications for when the agent needs attention.
+//!
+//! Notifications are only sent while the terminal is unfocused, as reported
+//! by the terminal's focus events. Terminals that don't report focus are
+//! treated as always focused.
+
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Longest notification body, in characters.
+const MAX_BODY_CHARS: usize = 200;
+
+/// How to notify.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NotifyMethod {
+    /// Don't notify.
+    #[default]
+    Off,
+    /// Ring the terminal bell.
+    Bell,
+    /// An OSC 777 notification, shown by terminals such as kitty, foot,
+    /// Ghostty and WezTerm.
+    Osc777,
+    /// A native desktop notification through `notify-send` or `osascript`,
+    /// falling back to the bell.
+    Desktop,
+}
+
+/// Sends notifications while the terminal is unfocused.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    method: NotifyMethod,
+    focused: bool,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new(NotifyMethod::Off)
+    }
+}
+
+impl Notifier {
+    /// Create a notifier for a focused terminal.
+    pub fn new(method: NotifyMethod) -> Self {
+        Self {
+            method,
+            focused: true,
+        }
+    }
+
+    /// Get the notification method.
+    pub fn method(&self) -> NotifyMethod {
+        self.method
+    }
+
+    /// Set the notification method.
+    pub fn set_method(&mut self, method: NotifyMethod) {
+        self.method = method;
+    }
+
+    /// Record whether the terminal has focus.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    /// Check if the terminal has focus.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Check if a notification would be sent now.
+    pub fn should_notify(&self) -> bool {
+        self.method != NotifyMethod::Off && !self.focused
+    }
+
+    /// Notify, unless notifications are off or the terminal has focus.
+    /// Returns whether a notification was sent.
+    pub fn notify(&self, title: &str, body: &str) -> bool {
+        if !self.should_notify() {
+            return false;
+        }
+        let title = clean(title, MAX_BODY_CHARS);
+        let body = clean(body, MAX_BODY_CHARS);
+        let result = match self.method {
+            NotifyMethod::Off => return false,
+            NotifyMethod::Bell => write_terminal("\x07"),
+            NotifyMethod::Osc777 => write_terminal(&osc777(&title, &body)),
+            NotifyMethod::Desktop => desktop(&title, &body).or_else(|e| {
+                tracing::debug!(error = %e, "Desktop notification failed, ringing the bell");
+                write_terminal("\x07")
+            }),
+        };
+        if let Err(e) = &result {
+            tracing::warn!(error = %e, "Failed to send notification");
+        }
+        result.is_ok()
+    }
+}
+
+/// The OSC 777 sequence for a notification. The title can't contain `;`,
+/// which separates the fields.
+fn osc777(title: &str, body: &str) -> String {
+    format!("\x1b]777;notify;{};{body}\x07", title.replace(';', ","))
+}
+
+/// Make text safe to embed in an escape sequence or command line: control
+/// characters become spaces, runs of whitespace collapse, and long text is
+/// cut short.
+fn clean(text: &str, max_chars: usize) -> String {
+    let text = text
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect::<String>();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() > max_chars {
+        let cut: String = text.chars().take(max_chars - 1).collect();
+        format!("{cut}…")
+    } else {
+        text
+    }
+}
+
+fn write_terminal(sequence: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(sequence.as_bytes())?;
+    stdout.flush()
+}
+
+/// Show a native desktop notification, without waiting for it.
+fn desktop(title: &str, body: &str) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(title)
+        );
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(script);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("notify-send");
+        command.arg("--app-name=wonopcode").arg(title).arg(body);
+        command
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "no desktop notifications on this platform",
+        ));
+    };
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Quote text as an AppleScript string literal.
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifies_only_when_unfocused() {
+        let mut notifier = Notifier::new(NotifyMethod::Bell);
+        assert!(!notifier.should_notify());
+        notifier.set_focused(false);
+        assert!(notifier.should_notify());
+        notifier.set_method(NotifyMethod::Off);
+        assert!(!notifier.should_notify());
+        assert!(!notifier.notify("title", "body"));
+    }
+
+    #[test]
+    fn test_osc777() {
+        assert_eq!(
+            osc777("a;b", "done; 3 files"),
+            "\x1b]777;notify;a,b;done; 3 files\x07"
+        );
+    }
+
+    #[test]
+    fn test_clean() {
+        assert_eq!(clean("line one\n\x1b[31mred\x07", 50), "line one [31mred");
+        assert_eq!(clean("abcdef", 4), "abc…");
+        assert_eq!(applescript_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+    }
+}
@@ -139,7 +139,14 @@ impl Default for Theme {
 impl Theme {
     /// Get a theme by name.
     pub fn by_name(name: &str) -> Self {
-        match name.to_lowercase().as_str() {
+        // Default to troels.im theme
+        Self::find(name).unwrap_or_else(Self::troelsim)
+    }
+
+    /// Get a built-in theme by name, if there is one.
+    pub fn find(name: &str) -> Option<Self> {
+        let theme = match name.to_lowercase().as_str() {
+            "troelsim" => Self::troelsim(),
             "light" => Self::light(),
             "catppuccin" | "catppuccin-mocha" => Self::catppuccin_mocha(),
             "dracula" => Self::dracula(),
@@ -148,9 +155,9 @@ impl Theme {
             "tokyo-night" | "tokyonight" => Self::tokyo_night(),
             "rosepine" | "rose-pine" => Self::rose_pine(),
             "wonopcode" => Self::wonopcode(),
-            // Default to troels.im theme
-            _ => Self::troelsim(),
-        }
+            _ => return None,
+        };
+        Some(theme)
     }
 
     /// List available theme names.
@@ -624,6 +631,81 @@ impl Theme {
         }
     }
 
+    /// Get the high-contrast variant of this theme.
+    ///
+    /// Backgrounds become pure black, or pure white for light themes, text
+    /// and borders the opposite, and accents the most saturated color of
+    /// their hue. The name is kept, so the variant follows theme switches.
+    pub fn high_contrast(&self) -> Self {
+        let light = is_light(self.background);
+        let (background, text) = if light {
+            (Color::Rgb(255, 255, 255), Color::Rgb(0, 0, 0))
+        } else {
+            (Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255))
+        };
+        let (red, green, yellow, blue, magenta, cyan) = if light {
+            (
+                Color::Rgb(176, 0, 0),
+                Color::Rgb(0, 110, 0),
+                Color::Rgb(128, 80, 0),
+                Color::Rgb(0, 0, 192),
+                Color::Rgb(128, 0, 128),
+                Color::Rgb(0, 96, 112),
+            )
+        } else {
+            (
+                Color::Rgb(255, 96, 96),
+                Color::Rgb(0, 255, 0),
+                Color::Rgb(255, 255, 0),
+                Color::Rgb(128, 176, 255),
+                Color::Rgb(255, 128, 255),
+                Color::Rgb(0, 255, 255),
+            )
+        };
+
+        Self {
+            name: self.name.clone(),
+
+            background,
+            background_panel: background,
+            background_element: background,
+            background_menu: background,
+
+            text,
+            text_muted: text,
+
+            primary: yellow,
+            secondary: cyan,
+            accent: magenta,
+
+            success: green,
+            warning: yellow,
+            error: red,
+            info: cyan,
+
+            border: text,
+            border_active: yellow,
+            border_subtle: text,
+            tool_border: text,
+
+            diff_added_bg: background,
+            diff_removed_bg: background,
+            diff_added: green,
+            diff_removed: red,
+
+            syntax_comment: text,
+            syntax_keyword: magenta,
+            syntax_function: blue,
+            syntax_variable: text,
+            syntax_string: green,
+            syntax_number: yellow,
+            syntax_type: cyan,
+            syntax_operator: text,
+
+            thinking_opacity: 1.0,
+        }
+    }
+
     // Style helper methods
 
     /// Base text style.
@@ -747,10 +829,24 @@ impl Theme {
     }
 }
 
+/// Whether a color is light enough to need dark text.
+fn is_light(color: Color) -> bool {
+    match color {
+        Color::Rgb(r, g, b) => {
+            // Perceived brightness (ITU-R BT.601)
+            (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000 > 128
+        }
+        Color::White | Color::Gray => true,
+        Color::Indexed(i) => i == 7 || i == 15 || i >= 248,
+        _ => false,
+    }
+}
+
 /// Settings that control rendering performance and features.
 ///
 /// These can be toggled via the Settings dialog (Performance tab)
-/// to optimize for low-memory or low-CPU environments.
+/// to optimize for low-memory or low-CPU environments, or to make the
+/// TUI easier to use with low vision or a screen reader.
 #[derive(Debug, Clone)]
 pub struct RenderSettings {
     /// Enable markdown formatting (bold, italic, lists, etc.)
@@ -761,6 +857,8 @@ pub struct RenderSettings {
     pub code_backgrounds_enabled: bool,
     /// Render markdown tables with borders
     pub tables_enabled: bool,
+    /// Show tokens and cost under each assistant message
+    pub message_usage: bool,
     /// Max frames per second during streaming
     pub streaming_fps: u32,
     /// Maximum messages to keep in memory
@@ -770,6 +868,16 @@ pub struct RenderSettings {
     /// Enable test/debug commands
     pub enable_test_commands: bool,
 
+    // Accessibility
+    /// Use the high-contrast variant of the theme
+    pub high_contrast: bool,
+    /// Show static indicators instead of spinners and other animations
+    pub reduce_motion: bool,
+    /// Draw borders and icons with ASCII instead of Unicode box art
+    pub ascii_borders: bool,
+    /// Show the conversation as plain linear text for screen readers
+    pub linear_output: bool,
+
     // Test provider settings
     /// Enable the test model in the model selector
     pub test_model_enabled: bool,
@@ -790,10 +898,15 @@ impl Default for RenderSettings {
             syntax_highlighting_enabled: true,
             code_backgrounds_enabled: true,
             tables_enabled: true,
+            message_usage: true,
             streaming_fps: 20,
             max_messages: 200,
             low_memory_mode: false,
             enable_test_commands: false,
+            high_contrast: false,
+            reduce_motion: false,
+            ascii_borders: false,
+            linear_output: false,
             // Test provider defaults
             test_model_enabled: false,
             test_emulate_thinking: true,
@@ -812,10 +925,15 @@ impl RenderSettings {
             syntax_highlighting_enabled: false, // Saves ~7MB
             code_backgrounds_enabled: false,
             tables_enabled: false,
+            message_usage: true,
             streaming_fps: 10,
             max_messages: 50,
             low_memory_mode: true,
             enable_test_commands: false,
+            high_contrast: false,
+            reduce_motion: false,
+            ascii_borders: false,
+            linear_output: false,
             // Test provider defaults
             test_model_enabled: false,
             test_emulate_thinking: true,
@@ -832,10 +950,15 @@ impl RenderSettings {
             syntax_highlighting_enabled: false,
             code_backgrounds_enabled: false,
             tables_enabled: false,
+            message_usage: true,
             streaming_fps: 5,
             max_messages: 100,
             low_memory_mode: false,
             enable_test_commands: false,
+            high_contrast: false,
+            reduce_motion: false,
+            ascii_borders: false,
+            linear_output: false,
             // Test provider defaults
             test_model_enabled: false,
             test_emulate_thinking: true,
@@ -845,6 +968,21 @@ impl RenderSettings {
         }
     }
 
+    /// Create settings for screen readers: plain linear text without
+    /// markdown, animations or box art
+    pub fn screen_reader() -> Self {
+        Self {
+            markdown_enabled: false,
+            syntax_highlighting_enabled: false,
+            code_backgrounds_enabled: false,
+            tables_enabled: false,
+            reduce_motion: true,
+            ascii_borders: true,
+            linear_output: true,
+            ..Self::default()
+        }
+    }
+
     /// Get minimum interval between streaming frames in milliseconds
     pub fn streaming_interval_ms(&self) -> u64 {
         if self.streaming_fps == 0 {
@@ -979,6 +1117,30 @@ mod tests {
         assert_eq!(settings.max_messages, 100);
     }
 
+    #[test]
+    fn test_render_settings_screen_reader() {
+        let settings = RenderSettings::screen_reader();
+        assert!(!settings.markdown_enabled);
+        assert!(settings.reduce_motion);
+        assert!(settings.ascii_borders);
+        assert!(settings.linear_output);
+        assert!(!settings.high_contrast);
+        assert_eq!(settings.streaming_fps, 20);
+    }
+
+    #[test]
+    fn test_high_contrast() {
+        let dark = Theme::troelsim().high_contrast();
+        assert_eq!(dark.name, "troelsim");
+        assert_eq!(dark.background, Color::Rgb(0, 0, 0));
+        assert_eq!(dark.text, Color::Rgb(255, 255, 255));
+        assert_eq!(dark.text_muted, dark.text);
+
+        let light = Theme::light().high_contrast();
+        assert_eq!(light.background, Color::Rgb(255, 255, 255));
+        assert_eq!(light.text, Color::Rgb(0, 0, 0));
+    }
+
     #[test]
     fn test_streaming_interval_ms() {
         let settings = RenderSettings::default();
@@ -0,0 +1,335 @@
+//! User themes loaded from files.
+//!
+//! A theme file is a TOML or JSON file in a themes directory, setting any of
+//! the [`Theme`] colors on top of a built-in base theme:
+//!
+//! ```toml
+//! name = "my-theme"
+//! base = "nord"
+//! background = "#101418"
+//! primary = "yellow"
+//! syntax_comment = 244
+//! thinking_opacity = 0.5
+//! ```
+//!
+//! Colors are `#rrggbb` hex values, color names (`red`, `lightblue`, `reset`)
+//! or 256-color palette indices. The name defaults to the file name.
+
+use ratatui::style::Color;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::theme::Theme;
+
+/// A color in a theme file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ColorValue {
+    Index(u8),
+    Name(String),
+}
+
+impl ColorValue {
+    fn to_color(&self) -> Result<Color, String> {
+        match self {
+            ColorValue::Index(i) => Ok(Color::Indexed(*i)),
+            ColorValue::Name(name) => {
+                Color::from_str(name).map_err(|_| format!("invalid color '{name}'"))
+            }
+        }
+    }
+}
+
+/// Declare the theme file format with one optional color per listed
+/// [`Theme`] field.
+macro_rules! theme_file {
+    ($($field:ident),* $(,)?) => {
+        /// Contents of a theme file.
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct ThemeFile {
+            name: Option<String>,
+            base: Option<String>,
+            thinking_opacity: Option<f32>,
+            $($field: Option<ColorValue>,)*
+        }
+
+        impl ThemeFile {
+            /// Set the colors given in the file.
+            fn apply_colors(&self, theme: &mut Theme) -> Result<(), String> {
+                $(
+                    if let Some(value) = &self.$field {
+                        theme.$field = value
+                            .to_color()
+                            .map_err(|e| format!("{}: {e}", stringify!($field)))?;
+                    }
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+theme_file!(
+    background,
+    background_panel,
+    background_element,
+    background_menu,
+    text,
+    text_muted,
+    primary,
+    secondary,
+    accent,
+    success,
+    warning,
+    error,
+    info,
+    border,
+    border_active,
+    border_subtle,
+    tool_border,
+    diff_added_bg,
+    diff_removed_bg,
+    diff_added,
+    diff_removed,
+    syntax_comment,
+    syntax_keyword,
+    syntax_function,
+    syntax_variable,
+    syntax_string,
+    syntax_number,
+    syntax_type,
+    syntax_operator,
+);
+
+impl ThemeFile {
+    fn into_theme(self, default_name: &str) -> Result<Theme, String> {
+        let mut theme = match &self.base {
+            Some(base) => {
+                Theme::find(base).ok_or_else(|| format!("unknown base theme '{base}'"))?
+            }
+            None => Theme::default(),
+        };
+        self.apply_colors(&mut theme)?;
+        if let Some(opacity) = self.thinking_opacity {
+            if !(0.0..=1.0).contains(&opacity) {
+                return Err(format!(
+                    "thinking_opacity: {opacity} is not between 0.0 and 1.0"
+                ));
+            }
+            theme.thinking_opacity = opacity;
+        }
+        theme.name = self.name.unwrap_or_else(|| default_name.to_string());
+        Ok(theme)
+    }
+}
+
+/// Parse a theme from TOML.
+pub fn parse_toml(content: &str, default_name: &str) -> Result<Theme, String> {
+    let file: ThemeFile = toml::from_str(content).map_err(|e| e.message().to_string())?;
+    file.into_theme(default_name)
+}
+
+/// Parse a theme from JSON.
+pub fn parse_json(content: &str, default_name: &str) -> Result<Theme, String> {
+    let file: ThemeFile = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    file.into_theme(default_name)
+}
+
+/// Load a theme from a `.toml` or `.json` file.
+pub fn load_theme_file(path: &Path) -> Result<Theme, String> {
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => parse_toml(&content, &name),
+        Some("json") => parse_json(&content, &name),
+        _ => Err("theme files must be .toml or .json".to_string()),
+    }
+}
+
+/// Built-in themes plus user themes loaded from themes directories.
+#[derive(Debug, Clone, Default)]
+pub struct ThemeRegistry {
+    /// Directories searched for theme files, later ones overriding earlier.
+    dirs: Vec<PathBuf>,
+    /// Loaded user themes, sorted by name.
+    custom: Vec<Theme>,
+    /// Theme files that failed to load, with the reason.
+    errors: Vec<String>,
+}
+
+impl ThemeRegistry {
+    /// Load the themes in the given directories. Missing directories are
+    /// skipped; a theme in a later directory replaces one of the same name.
+    pub fn load(dirs: Vec<PathBuf>) -> Self {
+        let mut registry = Self {
+            dirs,
+            ..Default::default()
+        };
+        registry.reload();
+        registry
+    }
+
+    /// Read the theme files again.
+    pub fn reload(&mut self) {
+        self.custom.clear();
+        self.errors.clear();
+
+        for dir in &self.dirs {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| {
+                    p.is_file()
+                        && matches!(
+                            p.extension().and_then(|e| e.to_str()),
+                            Some("toml" | "json")
+                        )
+                })
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                match load_theme_file(&path) {
+                    Ok(theme) => {
+                        self.custom
+                            .retain(|t| !t.name.eq_ignore_ascii_case(&theme.name));
+                        self.custom.push(theme);
+                    }
+                    Err(e) => self.errors.push(format!("{}: {e}", path.display())),
+                }
+            }
+        }
+        self.custom.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    /// The loaded user themes, sorted by name.
+    pub fn custom(&self) -> &[Theme] {
+        &self.custom
+    }
+
+    /// Theme files that failed to load in the last (re)load.
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    /// Names of all themes: built-in ones first, then user themes.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Theme::available().into_iter().map(str::to_string).collect();
+        for theme in &self.custom {
+            if !names.iter().any(|n| n.eq_ignore_ascii_case(&theme.name)) {
+                names.push(theme.name.clone());
+            }
+        }
+        names
+    }
+
+    /// Get a theme by name, preferring user themes over built-in ones.
+    pub fn get(&self, name: &str) -> Theme {
+        self.custom
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+            .cloned()
+            .unwrap_or_else(|| Theme::by_name(name))
+    }
+
+    /// Whether a user theme has this name.
+    pub fn is_custom(&self, name: &str) -> bool {
+        self.custom
+            .iter()
+            .any(|t| t.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml() {
+        let theme = parse_toml(
+            r##"
+base = "nord"
+background = "#101418"
+primary = "yellow"
+syntax_comment = 244
+thinking_opacity = 0.4
+"##,
+            "mine",
+        )
+        .unwrap();
+        assert_eq!(theme.name, "mine");
+        assert_eq!(theme.background, Color::Rgb(0x10, 0x14, 0x18));
+        assert_eq!(theme.primary, Color::Yellow);
+        assert_eq!(theme.syntax_comment, Color::Indexed(244));
+        assert_eq!(theme.thinking_opacity, 0.4);
+        // Everything else comes from the base
+        assert_eq!(theme.text, Theme::nord().text);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let theme = parse_json(r##"{"name": "Solar", "text": "#eee8d5"}"##, "file").unwrap();
+        assert_eq!(theme.name, "Solar");
+        assert_eq!(theme.text, Color::Rgb(0xee, 0xe8, 0xd5));
+        assert_eq!(theme.background, Theme::default().background);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse_toml(r#"primary = "not-a-color""#, "t").unwrap_err();
+        assert_eq!(err, "primary: invalid color 'not-a-color'");
+
+        let err = parse_toml(r#"base = "nope""#, "t").unwrap_err();
+        assert_eq!(err, "unknown base theme 'nope'");
+
+        let err = parse_toml(r#"primry = "red""#, "t").unwrap_err();
+        assert!(err.contains("unknown field `primry`"), "{err}");
+
+        let err = parse_json(r#"{"thinking_opacity": 2.0}"#, "t").unwrap_err();
+        assert!(err.contains("between 0.0 and 1.0"));
+    }
+
+    #[test]
+    fn test_registry() {
+        let global = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(global.path().join("ocean.toml"), r##"text = "#000001""##).unwrap();
+        std::fs::write(global.path().join("broken.json"), "{").unwrap();
+        std::fs::write(global.path().join("notes.txt"), "ignored").unwrap();
+        std::fs::write(
+            project.path().join("ocean.json"),
+            r##"{"text": "#000002"}"##,
+        )
+        .unwrap();
+
+        let mut registry = ThemeRegistry::load(vec![
+            global.path().to_path_buf(),
+            project.path().to_path_buf(),
+            PathBuf::from("/nonexistent/themes"),
+        ]);
+        assert_eq!(registry.custom().len(), 1);
+        assert_eq!(registry.errors().len(), 1);
+        assert!(registry.errors()[0].contains("broken.json"));
+
+        // The project theme wins
+        assert_eq!(registry.get("Ocean").text, Color::Rgb(0, 0, 2));
+        assert!(registry.is_custom("ocean"));
+        assert_eq!(registry.get("nord").name, "nord");
+        assert_eq!(registry.names().last().map(String::as_str), Some("ocean"));
+
+        std::fs::write(
+            project.path().join("ocean.json"),
+            r##"{"text": "#000003"}"##,
+        )
+        .unwrap();
+        registry.reload();
+        assert_eq!(registry.get("ocean").text, Color::Rgb(0, 0, 3));
+    }
+}
@@ -7,7 +7,7 @@
 use crossterm::event::KeyEvent;
 use ratatui::{layout::Rect, Frame};
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, Theme, ThemeRegistry};
 
 use crate::common::{DialogItem, SelectDialog};
 
@@ -30,6 +30,14 @@ impl CommandPalette {
                 .with_description("Browse previous sessions")
                 .with_keybind("Ctrl+X L")
                 .with_category("Session"),
+            DialogItem::new("new_tab", "New Tab")
+                .with_description("Open a session in a new tab, next to the running one")
+                .with_keybind("Alt+T")
+                .with_category("Session"),
+            DialogItem::new("close_tab", "Close Tab")
+                .with_description("Cancel and close the current tab")
+                .with_keybind("Alt+W")
+                .with_category("Session"),
             DialogItem::new("model_select", "Select Model")
                 .with_description("Change the AI model")
                 .with_keybind("Ctrl+X M")
@@ -42,6 +50,23 @@ impl CommandPalette {
                 .with_description("Show/hide the sidebar")
                 .with_keybind("Ctrl+X B")
                 .with_category("View"),
+            DialogItem::new("toggle_preview", "Toggle Preview Pane")
+                .with_description("Show/hide the diff of the edit in view")
+                .with_keybind("Ctrl+X P")
+                .with_category("View"),
+            DialogItem::new("toggle_file_browser", "Toggle File Browser")
+                .with_description(
+                    "Browse project files, add them as context or jump to their diffs",
+                )
+                .with_keybind("Ctrl+X F")
+                .with_category("View"),
+            DialogItem::new("toggle_terminal", "Toggle Process Output")
+                .with_description("Watch the output of background processes, one tab each")
+                .with_keybind("Ctrl+X W")
+                .with_category("View"),
+            DialogItem::new("preview_mode", "Switch Preview Diff/File")
+                .with_description("Show the diff or the edited file in the preview pane")
+                .with_category("View"),
             DialogItem::new("theme_select", "Select Theme")
                 .with_description("Change color theme")
                 .with_keybind("Ctrl+X T")
@@ -62,6 +87,10 @@ impl CommandPalette {
                 .with_description("Redo undone message")
                 .with_keybind("Ctrl+X R")
                 .with_category("Edit"),
+            DialogItem::new("review_changes", "Review Changes")
+                .with_description("Accept, reject or edit the session's changes hunk by hunk")
+                .with_keybind("/review")
+                .with_category("Edit"),
             DialogItem::new("clear_history", "Clear History")
                 .with_description("Clear conversation history")
                 .with_category("Session"),
@@ -87,7 +116,7 @@ impl CommandPalette {
         ];
 
         Self {
-            select: SelectDialog::new("Command Palette", items),
+            select: SelectDialog::new(tr("dialog.command_palette"), items),
         }
     }
 
@@ -338,7 +367,7 @@ impl ModelDialog {
         }
 
         Self {
-            select: SelectDialog::new("Select Model", items),
+            select: SelectDialog::new(tr("dialog.select_model"), items),
         }
     }
 
@@ -369,13 +398,95 @@ pub struct SessionDialog {
 impl SessionDialog {
     /// Create a new session dialog.
     pub fn new(sessions: Vec<(String, String, String)>) -> Self {
+        Self::with_title(tr("dialog.sessions"), sessions)
+    }
+
+    /// Create a session dialog with a custom title, e.g. for search results.
+    ///
+    /// Sessions are (id, title, description) triples.
+    pub fn with_title(title: impl Into<String>, sessions: Vec<(String, String, String)>) -> Self {
         let items: Vec<DialogItem> = sessions
             .into_iter()
-            .map(|(id, title, updated)| DialogItem::new(&id, &title).with_description(updated))
+            .map(|(id, title, description)| {
+                DialogItem::new(&id, &title).with_description(description)
+            })
+            .collect();
+
+        Self {
+            select: SelectDialog::new(title, items),
+        }
+    }
+
+    /// Handle a key event.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<String> {
+        self.select.handle_key(key)
+    }
+
+    /// Render the dialog.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        self.select.render(frame, area, theme);
+    }
+}
+
+/// Session template picker.
+#[derive(Debug, Clone)]
+pub struct TemplateDialog {
+    /// Inner select dialog.
+    select: SelectDialog,
+}
+
+impl TemplateDialog {
+    /// Create a new template dialog from (name, description) pairs.
+    pub fn new(templates: Vec<(String, String)>) -> Self {
+        let items: Vec<DialogItem> = templates
+            .into_iter()
+            .map(|(name, description)| {
+                let item = DialogItem::new(&name, &name);
+                if description.is_empty() {
+                    item
+                } else {
+                    item.with_description(description)
+                }
+            })
+            .collect();
+
+        Self {
+            select: SelectDialog::new(tr("dialog.templates"), items),
+        }
+    }
+
+    /// Handle a key event.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<String> {
+        self.select.handle_key(key)
+    }
+
+    /// Render the dialog.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        self.select.render(frame, area, theme);
+    }
+}
+
+/// Project picker for servers that manage several project roots.
+#[derive(Debug, Clone)]
+pub struct ProjectDialog {
+    /// Inner select dialog.
+    select: SelectDialog,
+}
+
+impl ProjectDialog {
+    /// Create a new project dialog from (path, name, description) triples.
+    ///
+    /// The selected item's id is the project path.
+    pub fn new(projects: Vec<(String, String, String)>) -> Self {
+        let items: Vec<DialogItem> = projects
+            .into_iter()
+            .map(|(path, name, description)| {
+                DialogItem::new(&path, &name).with_description(description)
+            })
             .collect();
 
         Self {
-            select: SelectDialog::new("Sessions", items),
+            select: SelectDialog::new(tr("dialog.projects"), items),
         }
     }
 
@@ -390,6 +501,93 @@ impl SessionDialog {
     }
 }
 
+/// A server found on the local network, as the connect dialog lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredServer {
+    /// Advertised name.
+    pub name: String,
+    /// Address to connect to (host:port).
+    pub address: String,
+    /// Project the server works on.
+    pub project: Option<String>,
+    /// Model the server uses.
+    pub model: Option<String>,
+    /// Whether the server needs a secret.
+    pub auth_required: bool,
+}
+
+/// Picker for servers on the local network, kept up to date as they come
+/// and go.
+#[derive(Debug, Clone)]
+pub struct ConnectDialog {
+    /// Inner select dialog.
+    select: SelectDialog,
+    /// Servers in the order they were found.
+    servers: Vec<DiscoveredServer>,
+}
+
+impl ConnectDialog {
+    /// Create an empty connect dialog; servers are added as they are found.
+    pub fn new() -> Self {
+        Self {
+            select: SelectDialog::new(tr("dialog.connect"), Vec::new()),
+            servers: Vec::new(),
+        }
+    }
+
+    /// Add a server, or update the one with the same name.
+    pub fn upsert(&mut self, server: DiscoveredServer) {
+        match self.servers.iter_mut().find(|s| s.name == server.name) {
+            Some(known) => *known = server,
+            None => self.servers.push(server),
+        }
+        self.refresh();
+    }
+
+    /// Remove the server with this name.
+    pub fn remove(&mut self, name: &str) {
+        self.servers.retain(|s| s.name != name);
+        self.refresh();
+    }
+
+    fn refresh(&mut self) {
+        let items = self
+            .servers
+            .iter()
+            .map(|server| {
+                let mut details = vec![server.address.clone()];
+                details.extend(server.project.clone());
+                details.extend(server.model.clone());
+                let item = DialogItem::new(&server.name, &server.name)
+                    .with_description(details.join(" · "));
+                if server.auth_required {
+                    item.with_keybind("secret")
+                } else {
+                    item
+                }
+            })
+            .collect();
+        self.select.set_items(items);
+    }
+
+    /// Handle a key event. Returns the server picked.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<DiscoveredServer> {
+        let name = self.select.handle_key(key)?;
+        self.servers.iter().find(|s| s.name == name).cloned()
+    }
+
+    /// Render the dialog.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        self.select.render(frame, area, theme);
+    }
+}
+
+impl Default for ConnectDialog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Theme selection dialog.
 #[derive(Debug, Clone)]
 pub struct ThemeDialog {
@@ -398,27 +596,51 @@ pub struct ThemeDialog {
 }
 
 impl ThemeDialog {
-    /// Create a new theme dialog.
+    /// Create a new theme dialog listing the built-in themes.
     pub fn new() -> Self {
-        let items = vec![
-            DialogItem::new("dark", "Dark").with_description("Default dark theme"),
-            DialogItem::new("light", "Light").with_description("Light theme"),
-            DialogItem::new("catppuccin", "Catppuccin").with_description("Soothing pastel theme"),
-            DialogItem::new("dracula", "Dracula").with_description("Dark purple theme"),
-            DialogItem::new("gruvbox", "Gruvbox").with_description("Retro groove colors"),
-            DialogItem::new("nord", "Nord").with_description("Arctic, bluish colors"),
-            DialogItem::new("tokyo-night", "Tokyo Night").with_description("Dark Tokyo theme"),
-            DialogItem::new("one-dark", "One Dark").with_description("Atom One Dark"),
-            DialogItem::new("monokai", "Monokai").with_description("Sublime Text classic"),
-            DialogItem::new("solarized-dark", "Solarized Dark")
-                .with_description("Ethan Schoonover's theme"),
-        ];
+        Self::with_registry(&ThemeRegistry::default())
+    }
+
+    /// Create a theme dialog listing the built-in themes and the user themes
+    /// of a registry.
+    pub fn with_registry(registry: &ThemeRegistry) -> Self {
+        let mut items: Vec<DialogItem> = Theme::available()
+            .into_iter()
+            .filter(|name| !registry.is_custom(name))
+            .map(|name| {
+                let (label, description) = match name {
+                    "troelsim" => ("troels.im", "Default neon dark theme"),
+                    "wonopcode" => ("Wonopcode", "Warm dark theme"),
+                    "light" => ("Light", "Light theme"),
+                    "catppuccin" => ("Catppuccin", "Soothing pastel theme"),
+                    "dracula" => ("Dracula", "Dark purple theme"),
+                    "gruvbox" => ("Gruvbox", "Retro groove colors"),
+                    "nord" => ("Nord", "Arctic, bluish colors"),
+                    "tokyo-night" => ("Tokyo Night", "Dark Tokyo theme"),
+                    "rosepine" => ("Rosé Pine", "Muted, natural colors"),
+                    other => (other, ""),
+                };
+                DialogItem::new(name, label)
+                    .with_description(description)
+                    .with_category("Built-in")
+            })
+            .collect();
+        items.extend(registry.custom().iter().map(|theme| {
+            DialogItem::new(theme.name.clone(), theme.name.clone())
+                .with_description("User theme")
+                .with_category("User")
+        }));
 
         Self {
-            select: SelectDialog::new("Select Theme", items),
+            select: SelectDialog::new(tr("dialog.select_theme"), items),
         }
     }
 
+    /// Get the ID of the highlighted theme, to preview it.
+    pub fn highlighted(&self) -> Option<&str> {
+        self.select.selected_item().map(|item| item.id.as_str())
+    }
+
     /// Handle a key event.
     pub fn handle_key(&mut self, key: KeyEvent) -> Option<String> {
         self.select.handle_key(key)
@@ -426,7 +648,7 @@ impl ThemeDialog {
 
     /// Render the dialog.
     pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
-        self.select.render(frame, area, theme);
+        self.select.render_with_sections(frame, area, theme, true);
     }
 }
 
@@ -461,7 +683,7 @@ impl AgentDialog {
             .collect();
 
         Self {
-            select: SelectDialog::new("Select Agent", items),
+            select: SelectDialog::new(tr("dialog.select_agent"), items),
         }
     }
 
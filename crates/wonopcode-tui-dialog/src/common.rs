@@ -12,7 +12,7 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, Theme};
 
 /// A selectable item in a dialog.
 #[derive(Debug, Clone)]
@@ -103,6 +103,20 @@ impl SelectDialog {
             .and_then(|&idx| self.items.get(idx))
     }
 
+    /// Replace the items, keeping the filter and, if it is still there, the
+    /// selected item.
+    pub fn set_items(&mut self, items: Vec<DialogItem>) {
+        let selected_id = self.selected_item().map(|item| item.id.clone());
+        self.items = items;
+        self.update_filter();
+        if let Some(index) =
+            selected_id.and_then(|id| self.filtered.iter().position(|&i| self.items[i].id == id))
+        {
+            self.selected = index;
+            self.list_state.select(Some(index));
+        }
+    }
+
     /// Handle a key event. Returns Some(id) if an item was selected.
     pub fn handle_key(&mut self, key: KeyEvent) -> Option<String> {
         match key.code {
@@ -230,7 +244,7 @@ impl SelectDialog {
 
         // Render filter input
         let filter_block = Block::default()
-            .title(" Filter ")
+            .title(format!(" {} ", tr("dialog.filter")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
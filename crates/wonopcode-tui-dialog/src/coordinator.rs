@@ -0,0 +1,233 @@
+//! Coordinator dialog for multi-agent orchestration.
+//!
+//! Shows the progress of every orchestrated agent, each working on its own
+//! task in its own git worktree, and offers merging their branches once all
+//! of them have finished.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+    Frame,
+};
+
+use wonopcode_tui_core::{tr_args, Theme};
+
+use crate::common::centered_rect;
+
+/// An orchestrated agent shown in the coordinator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinatorAgent {
+    /// Agent name.
+    pub name: String,
+    /// The agent's task.
+    pub task: String,
+    /// State: "pending", "running", "done", "failed", "cancelled", "merged"
+    /// or "conflict".
+    pub state: String,
+    /// Branch of the agent's worktree.
+    pub branch: Option<String>,
+    /// Model calls made.
+    pub steps: usize,
+    /// Tool calls made.
+    pub tool_calls: usize,
+    /// Files changed on the agent's branch.
+    pub changed_files: Vec<String>,
+    /// Final response, or what went wrong.
+    pub summary: Option<String>,
+}
+
+impl CoordinatorAgent {
+    /// Whether the agent has stopped working.
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.state.as_str(), "pending" | "running")
+    }
+}
+
+/// Coordinator dialog.
+#[derive(Debug, Clone)]
+pub struct CoordinatorDialog {
+    /// Orchestration ID.
+    id: String,
+    /// Agents, in task order.
+    agents: Vec<CoordinatorAgent>,
+    /// List state for rendering.
+    list_state: ListState,
+}
+
+impl CoordinatorDialog {
+    /// Create a coordinator for an orchestration.
+    pub fn new(id: impl Into<String>, agents: Vec<CoordinatorAgent>) -> Self {
+        let mut list_state = ListState::default();
+        if !agents.is_empty() {
+            list_state.select(Some(0));
+        }
+        Self {
+            id: id.into(),
+            agents,
+            list_state,
+        }
+    }
+
+    /// Orchestration ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Replace the agents with newer progress, keeping the selection.
+    pub fn set_agents(&mut self, agents: Vec<CoordinatorAgent>) {
+        let selected = self
+            .list_state
+            .selected()
+            .map(|i| i.min(agents.len().saturating_sub(1)));
+        self.agents = agents;
+        self.list_state
+            .select(selected.or(Some(0)).filter(|_| !self.agents.is_empty()));
+    }
+
+    /// Whether every agent has stopped working.
+    pub fn all_finished(&self) -> bool {
+        self.agents.iter().all(CoordinatorAgent::is_finished)
+    }
+
+    /// Whether any agent has work waiting to be merged.
+    pub fn has_unmerged(&self) -> bool {
+        self.agents.iter().any(|a| a.state == "done")
+    }
+
+    /// Handle a key event. Returns `merge` or `cancel` when triggered.
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<String> {
+        let selected = self.list_state.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Char('m') => return Some("merge".to_string()),
+            KeyCode::Char('c') => return Some("cancel".to_string()),
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.list_state.select(Some(selected.saturating_sub(1)));
+            }
+            KeyCode::Down | KeyCode::Char('j') if selected + 1 < self.agents.len() => {
+                self.list_state.select(Some(selected + 1));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Render the coordinator dialog.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let dialog_width = (area.width * 70 / 100).clamp(50, 100);
+        let dialog_height = (area.height * 70 / 100).clamp(12, 30);
+        let dialog_area = centered_rect(dialog_width, dialog_height, area);
+
+        frame.render_widget(Clear, dialog_area);
+
+        let finished = self.agents.iter().filter(|a| a.is_finished()).count();
+        let block = Block::default()
+            .title(format!(
+                " {} ",
+                tr_args(
+                    "dialog.orchestration",
+                    &[
+                        ("id", &self.id),
+                        ("finished", &finished),
+                        ("total", &self.agents.len()),
+                    ],
+                )
+            ))
+            .borders(Borders::ALL)
+            .border_style(theme.border_active_style());
+
+        let inner = block.inner(dialog_area);
+        frame.render_widget(block, dialog_area);
+
+        // Split into agent list, details of the selected agent, and help text
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(5),
+                Constraint::Length(1),
+            ])
+            .split(inner);
+
+        let list_items: Vec<ListItem> = self
+            .agents
+            .iter()
+            .map(|agent| {
+                let (symbol, style) = match agent.state.as_str() {
+                    "running" => ("⋯", Style::default().fg(theme.warning)),
+                    "done" | "merged" => ("✓", Style::default().fg(theme.success)),
+                    "failed" | "conflict" => ("✗", Style::default().fg(theme.error)),
+                    _ => ("○", theme.dim_style()),
+                };
+                let mut stats = format!(
+                    "{} · {} steps · {} tool calls",
+                    agent.state, agent.steps, agent.tool_calls
+                );
+                if !agent.changed_files.is_empty() {
+                    stats.push_str(&format!(" · {} files", agent.changed_files.len()));
+                }
+                ListItem::new(vec![
+                    Line::from(vec![
+                        Span::styled(format!("{symbol} "), style),
+                        Span::styled(agent.task.lines().next().unwrap_or(""), theme.text_style()),
+                    ]),
+                    Line::from(Span::styled(format!("  {stats}"), theme.dim_style())),
+                ])
+            })
+            .collect();
+
+        let list = List::new(list_items)
+            .highlight_style(
+                Style::default()
+                    .bg(theme.border_active)
+                    .fg(theme.background)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, chunks[0], &mut self.list_state);
+
+        // Details of the selected agent
+        let details = match self.list_state.selected().and_then(|i| self.agents.get(i)) {
+            Some(agent) => {
+                let mut lines = Vec::new();
+                if let Some(branch) = &agent.branch {
+                    lines.push(Line::from(vec![
+                        Span::styled("Branch: ", theme.dim_style()),
+                        Span::styled(branch.as_str(), theme.text_style()),
+                    ]));
+                }
+                if !agent.changed_files.is_empty() {
+                    lines.push(Line::from(vec![
+                        Span::styled("Files: ", theme.dim_style()),
+                        Span::styled(agent.changed_files.join(", "), theme.text_style()),
+                    ]));
+                }
+                if let Some(summary) = &agent.summary {
+                    lines.push(Line::from(Span::styled(
+                        summary.as_str(),
+                        theme.muted_style(),
+                    )));
+                }
+                lines
+            }
+            None => vec![Line::from(Span::styled("No agents", theme.dim_style()))],
+        };
+        let details = Paragraph::new(details)
+            .block(Block::default().borders(Borders::TOP))
+            .wrap(Wrap { trim: true });
+        frame.render_widget(details, chunks[1]);
+
+        let help_text = Line::from(vec![
+            Span::styled("m", theme.highlight_style()),
+            Span::styled(" merge  ", theme.dim_style()),
+            Span::styled("c", theme.highlight_style()),
+            Span::styled(" cancel  ", theme.dim_style()),
+            Span::styled("Esc", theme.highlight_style()),
+            Span::styled(" close", theme.dim_style()),
+        ]);
+        let help_para = Paragraph::new(help_text).alignment(Alignment::Center);
+        frame.render_widget(help_para, chunks[2]);
+    }
+}
@@ -4,13 +4,13 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Modifier, Style},
-    text::{Line, Span},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
 use std::collections::HashSet;
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, Theme};
 
 use crate::common::centered_rect;
 
@@ -48,6 +48,8 @@ pub enum GitView {
     Stage,
     /// Unstage files view.
     Unstage,
+    /// Hunks of one file, to stage or unstage one at a time.
+    Hunks,
     /// Commit view (message input).
     Commit,
     /// History view.
@@ -69,8 +71,18 @@ pub enum GitDialogResult {
     Unstage(Vec<String>),
     /// Checkout (discard) selected files.
     Checkout(Vec<String>),
+    /// Request a file's unstaged or staged changes.
+    Diff { path: String, staged: bool },
+    /// Stage one hunk of a file.
+    StageHunk { path: String, hunk: usize },
+    /// Unstage one hunk of a file.
+    UnstageHunk { path: String, hunk: usize },
+    /// Draft a commit message for the staged changes.
+    GenerateMessage,
     /// Create commit with message.
     Commit(String),
+    /// Create commit with message, then push it.
+    CommitAndPush(String),
     /// Push to remote.
     Push,
     /// Pull from remote.
@@ -90,6 +102,14 @@ pub struct GitDialog {
     files: Vec<GitFileDisplay>,
     /// Selected files (indices).
     selected_files: HashSet<usize>,
+    /// File shown in the hunks view.
+    hunk_path: String,
+    /// Whether the hunks shown are staged.
+    hunk_staged: bool,
+    /// Lines of each hunk shown, starting with its `@@` header.
+    hunks: Vec<Vec<String>>,
+    /// Hunk list state.
+    hunk_state: ListState,
     /// Commit message input.
     commit_message: String,
     /// History entries.
@@ -121,6 +141,10 @@ impl GitDialog {
             file_list_state: ListState::default(),
             files: Vec::new(),
             selected_files: HashSet::new(),
+            hunk_path: String::new(),
+            hunk_staged: false,
+            hunks: Vec::new(),
+            hunk_state: ListState::default(),
             commit_message: String::new(),
             history: Vec::new(),
             history_state: ListState::default(),
@@ -160,6 +184,36 @@ impl GitDialog {
         self.loading = false;
     }
 
+    /// Update the hunks view with a file's diff.
+    ///
+    /// Once a file has no hunks left, the view goes back to the file list.
+    pub fn set_diff(&mut self, path: &str, staged: bool, diff: &str) {
+        if self.view != GitView::Hunks || path != self.hunk_path || staged != self.hunk_staged {
+            return;
+        }
+        self.hunks = parse_hunks(diff);
+        self.loading = false;
+        if self.hunks.is_empty() {
+            self.view = if staged {
+                GitView::Unstage
+            } else {
+                GitView::Stage
+            };
+            return;
+        }
+        let selected = self.hunk_state.selected().unwrap_or(0);
+        self.hunk_state
+            .select(Some(selected.min(self.hunks.len() - 1)));
+    }
+
+    /// Show a drafted commit message, ready to be edited.
+    pub fn set_commit_message(&mut self, message: impl Into<String>) {
+        self.commit_message = message.into();
+        self.view = GitView::Commit;
+        self.loading = false;
+        self.status_message = None;
+    }
+
     /// Set a status message (shown at bottom of dialog).
     pub fn set_message(&mut self, msg: impl Into<String>) {
         self.status_message = Some(msg.into());
@@ -185,6 +239,7 @@ impl GitDialog {
         match self.view {
             GitView::Menu => self.handle_menu_key(key),
             GitView::Stage | GitView::Unstage => self.handle_file_list_key(key),
+            GitView::Hunks => self.handle_hunks_key(key),
             GitView::Commit => self.handle_commit_key(key),
             GitView::History => self.handle_history_key(key),
         }
@@ -205,12 +260,13 @@ impl GitDialog {
                 self.commit_message.clear();
                 GitDialogResult::None
             }
-            KeyCode::Char('h') | KeyCode::Char('4') => {
+            KeyCode::Char('g') | KeyCode::Char('4') => self.generate_message(),
+            KeyCode::Char('h') | KeyCode::Char('5') => {
                 self.view = GitView::History;
                 GitDialogResult::RefreshHistory
             }
-            KeyCode::Char('p') | KeyCode::Char('5') => GitDialogResult::Push,
-            KeyCode::Char('l') | KeyCode::Char('6') => GitDialogResult::Pull,
+            KeyCode::Char('p') | KeyCode::Char('6') => GitDialogResult::Push,
+            KeyCode::Char('l') | KeyCode::Char('7') => GitDialogResult::Pull,
             KeyCode::Esc | KeyCode::Char('q') => GitDialogResult::Close,
             _ => GitDialogResult::None,
         }
@@ -304,6 +360,26 @@ impl GitDialog {
                     GitDialogResult::Unstage(paths)
                 }
             }
+            KeyCode::Tab | KeyCode::Right => {
+                // Show the hunks of the current file
+                let Some(&file_idx) = self
+                    .file_list_state
+                    .selected()
+                    .and_then(|visual_idx| filtered_indices.get(visual_idx))
+                else {
+                    return GitDialogResult::None;
+                };
+                self.hunk_path = self.files[file_idx].path.clone();
+                self.hunk_staged = !is_stage_view;
+                self.hunks.clear();
+                self.hunk_state.select(Some(0));
+                self.view = GitView::Hunks;
+                self.loading = true;
+                GitDialogResult::Diff {
+                    path: self.hunk_path.clone(),
+                    staged: self.hunk_staged,
+                }
+            }
             KeyCode::Char('d') if !is_stage_view => {
                 // Checkout (discard) in unstage view
                 let paths: Vec<String> = if self.selected_files.is_empty() {
@@ -333,17 +409,83 @@ impl GitDialog {
         }
     }
 
+    fn handle_hunks_key(&mut self, key: KeyEvent) -> GitDialogResult {
+        match key.code {
+            KeyCode::Esc | KeyCode::Left => {
+                self.view = if self.hunk_staged {
+                    GitView::Unstage
+                } else {
+                    GitView::Stage
+                };
+                self.hunks.clear();
+                GitDialogResult::RefreshStatus
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                if let Some(current) = self.hunk_state.selected() {
+                    self.hunk_state.select(Some(current.saturating_sub(1)));
+                }
+                GitDialogResult::None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if let Some(current) = self.hunk_state.selected() {
+                    if current + 1 < self.hunks.len() {
+                        self.hunk_state.select(Some(current + 1));
+                    }
+                }
+                GitDialogResult::None
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                let Some(hunk) = self.hunk_state.selected().filter(|&i| i < self.hunks.len())
+                else {
+                    return GitDialogResult::None;
+                };
+                self.loading = true;
+                let path = self.hunk_path.clone();
+                if self.hunk_staged {
+                    GitDialogResult::UnstageHunk { path, hunk }
+                } else {
+                    GitDialogResult::StageHunk { path, hunk }
+                }
+            }
+            _ => GitDialogResult::None,
+        }
+    }
+
+    /// Ask for a commit message drafted from the staged changes.
+    fn generate_message(&mut self) -> GitDialogResult {
+        if self.loading {
+            return GitDialogResult::None;
+        }
+        self.view = GitView::Commit;
+        self.loading = true;
+        self.status_message = Some("Drafting commit message...".to_string());
+        GitDialogResult::GenerateMessage
+    }
+
     fn handle_commit_key(&mut self, key: KeyEvent) -> GitDialogResult {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
         match key.code {
             KeyCode::Esc => {
                 self.view = GitView::Menu;
+                self.commit_message.clear();
+                self.status_message = None;
+                GitDialogResult::None
+            }
+            KeyCode::Char('g') if ctrl => self.generate_message(),
+            KeyCode::Char('p') if ctrl => {
+                if self.commit_message.trim().is_empty() {
+                    return GitDialogResult::None;
+                }
+                let msg = std::mem::take(&mut self.commit_message);
+                self.view = GitView::Menu;
+                GitDialogResult::CommitAndPush(msg)
+            }
+            KeyCode::Char('u') if ctrl => {
                 self.commit_message.clear();
                 GitDialogResult::None
             }
             KeyCode::Enter => {
-                if key.modifiers.contains(KeyModifiers::CONTROL)
-                    || key.modifiers.contains(KeyModifiers::ALT)
-                {
+                if ctrl || key.modifiers.contains(KeyModifiers::ALT) {
                     // Ctrl+Enter or Alt+Enter submits
                     if !self.commit_message.trim().is_empty() {
                         let msg = std::mem::take(&mut self.commit_message);
@@ -408,6 +550,7 @@ impl GitDialog {
             GitView::Menu => self.render_menu(frame, dialog_area, theme),
             GitView::Stage => self.render_file_list(frame, dialog_area, theme, true),
             GitView::Unstage => self.render_file_list(frame, dialog_area, theme, false),
+            GitView::Hunks => self.render_hunks(frame, dialog_area, theme),
             GitView::Commit => self.render_commit(frame, dialog_area, theme),
             GitView::History => self.render_history(frame, dialog_area, theme),
         }
@@ -415,7 +558,7 @@ impl GitDialog {
 
     fn render_menu(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
         let block = Block::default()
-            .title(" Git ")
+            .title(format!(" {} ", tr("dialog.git")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -437,6 +580,7 @@ impl GitDialog {
             ("s", "Stage files", "Add files to index"),
             ("u", "Unstage files", "Remove files from index"),
             ("c", "Commit", "Create a commit"),
+            ("g", "Draft commit", "Write the message with the model"),
             ("h", "History", "View commit history"),
             ("p", "Push", "Push to remote"),
             ("l", "Pull", "Pull from remote"),
@@ -553,18 +697,75 @@ impl GitDialog {
 
         // Help text
         let help = if is_stage {
-            "Space: toggle  a: all  Enter: stage  Esc: back"
+            "Space: toggle  a: all  Enter: stage  Tab: hunks  Esc: back"
         } else {
-            "Space: toggle  a: all  Enter: unstage  d: discard  Esc: back"
+            "Space: toggle  a: all  Enter: unstage  Tab: hunks  d: discard  Esc: back"
         };
         let help_para =
             Paragraph::new(Span::styled(help, theme.dim_style())).alignment(Alignment::Center);
         frame.render_widget(help_para, chunks[1]);
     }
 
+    fn render_hunks(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let action = if self.hunk_staged { "Unstage" } else { "Stage" };
+        let block = Block::default()
+            .title(format!(" {action} Hunks: {} ", self.hunk_path))
+            .borders(Borders::ALL)
+            .border_style(theme.border_active_style());
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(2)])
+            .split(inner);
+
+        if self.hunks.is_empty() {
+            let msg = if self.loading {
+                "Loading..."
+            } else {
+                "No hunks - binary or renamed file"
+            };
+            let para = Paragraph::new(Span::styled(msg, theme.dim_style()));
+            frame.render_widget(para, chunks[0]);
+        } else {
+            let list_items: Vec<ListItem> = self
+                .hunks
+                .iter()
+                .map(|hunk| {
+                    let lines: Vec<Line> = hunk
+                        .iter()
+                        .map(|line| {
+                            let style = match line.chars().next() {
+                                Some('@') => Style::default().fg(theme.accent),
+                                Some('+') => Style::default().fg(theme.success),
+                                Some('-') => Style::default().fg(theme.error),
+                                _ => theme.text_style(),
+                            };
+                            Line::from(Span::styled(line.as_str(), style))
+                        })
+                        .collect();
+                    ListItem::new(lines)
+                })
+                .collect();
+
+            let list = List::new(list_items)
+                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .highlight_symbol("> ");
+
+            frame.render_stateful_widget(list, chunks[0], &mut self.hunk_state);
+        }
+
+        let help = format!("j/k: navigate  Enter: {}  Esc: back", action.to_lowercase());
+        let help_para =
+            Paragraph::new(Span::styled(help, theme.dim_style())).alignment(Alignment::Center);
+        frame.render_widget(help_para, chunks[1]);
+    }
+
     fn render_commit(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
         let block = Block::default()
-            .title(" Commit ")
+            .title(format!(" {} ", tr("dialog.commit")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -579,14 +780,19 @@ impl GitDialog {
 
         // Message input
         let msg_block = Block::default()
-            .title(" Message ")
+            .title(format!(" {} ", tr("dialog.commit_message")))
             .borders(Borders::ALL)
             .border_style(theme.border_style());
 
         let msg_text = if self.commit_message.is_empty() {
-            Span::styled("Enter commit message...", theme.dim_style())
+            let hint = self
+                .status_message
+                .as_deref()
+                .filter(|_| self.loading)
+                .unwrap_or("Enter commit message...");
+            Text::from(Span::styled(hint, theme.dim_style()))
         } else {
-            Span::styled(&self.commit_message, theme.text_style())
+            Text::styled(self.commit_message.as_str(), theme.text_style())
         };
 
         let msg_para = Paragraph::new(msg_text)
@@ -595,7 +801,8 @@ impl GitDialog {
         frame.render_widget(msg_para, chunks[0]);
 
         // Help
-        let help = "Ctrl+Enter: commit  Esc: cancel";
+        let help =
+            "Ctrl+Enter: commit  Ctrl+P: commit & push  Ctrl+G: draft  Ctrl+U: clear  Esc: cancel";
         let help_para =
             Paragraph::new(Span::styled(help, theme.dim_style())).alignment(Alignment::Center);
         frame.render_widget(help_para, chunks[1]);
@@ -603,7 +810,7 @@ impl GitDialog {
 
     fn render_history(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
         let block = Block::default()
-            .title(" History ")
+            .title(format!(" {} ", tr("dialog.history")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -654,3 +861,20 @@ impl GitDialog {
         frame.render_widget(help_para, chunks[1]);
     }
 }
+
+/// Split a unified diff into its hunks, leaving out the file headers.
+fn parse_hunks(diff: &str) -> Vec<Vec<String>> {
+    let mut hunks: Vec<Vec<String>> = Vec::new();
+    for line in diff.lines() {
+        if line.starts_with("@@") {
+            hunks.push(vec![line.to_string()]);
+        } else if line.starts_with("diff --git") {
+            // Hunks of the next file follow its headers
+            hunks.push(Vec::new());
+        } else if let Some(hunk) = hunks.last_mut().filter(|hunk| !hunk.is_empty()) {
+            hunk.push(line.to_string());
+        }
+    }
+    hunks.retain(|hunk| !hunk.is_empty());
+    hunks
+}
@@ -24,6 +24,8 @@ pub struct InputDialog {
     pub value: String,
     /// Cursor position.
     cursor: usize,
+    /// Whether the value is hidden, e.g. for a secret.
+    masked: bool,
 }
 
 impl InputDialog {
@@ -34,6 +36,7 @@ impl InputDialog {
             prompt: prompt.into(),
             value: String::new(),
             cursor: 0,
+            masked: false,
         }
     }
 
@@ -44,6 +47,12 @@ impl InputDialog {
         self
     }
 
+    /// Hide the value as it is typed.
+    pub fn masked(mut self) -> Self {
+        self.masked = true;
+        self
+    }
+
     /// Get the current value.
     pub fn value(&self) -> &str {
         &self.value
@@ -127,9 +136,17 @@ impl InputDialog {
         frame.render_widget(prompt, chunks[0]);
 
         // Input field with cursor
-        let display_value = if self.cursor < self.value.len() {
-            let (before, after) = self.value.split_at(self.cursor);
-            let (cursor_char, rest) = after.split_at(1);
+        let (value, cursor) = if self.masked {
+            let dots = "•".repeat(self.value.chars().count());
+            let cursor = self.value[..self.cursor].chars().count() * '•'.len_utf8();
+            (dots, cursor)
+        } else {
+            (self.value.clone(), self.cursor)
+        };
+        let display_value = if cursor < value.len() {
+            let (before, after) = value.split_at(cursor);
+            let (cursor_char, rest) =
+                after.split_at(after.chars().next().map_or(0, char::len_utf8));
             Line::from(vec![
                 Span::styled(before, theme.text_style()),
                 Span::styled(
@@ -140,7 +157,7 @@ impl InputDialog {
             ])
         } else {
             Line::from(vec![
-                Span::styled(&self.value, theme.text_style()),
+                Span::styled(value.as_str(), theme.text_style()),
                 Span::styled(" ", Style::default().bg(theme.primary)),
             ])
         };
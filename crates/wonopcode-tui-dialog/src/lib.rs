@@ -12,10 +12,13 @@
 //! - [`StatusDialog`] - Session status display
 //! - [`HelpDialog`] - Keyboard shortcuts reference
 //! - [`PerfDialog`] - Performance metrics
+//! - [`UsageDialog`] - Usage dashboard with cost trends and tool latency
 //! - [`TimelineDialog`] - Message timeline navigation
+//! - [`CoordinatorDialog`] - Multi-agent orchestration progress
 
 mod command;
 mod common;
+mod coordinator;
 mod git;
 mod input;
 mod mcp;
@@ -24,12 +27,15 @@ mod sandbox;
 mod settings;
 mod status;
 mod timeline;
+mod usage;
 
 // Re-export all public types
 pub use command::{
-    AgentDialog, AgentInfo, CommandPalette, ModelDialog, SessionDialog, ThemeDialog,
+    AgentDialog, AgentInfo, CommandPalette, ConnectDialog, DiscoveredServer, ModelDialog,
+    ProjectDialog, SessionDialog, TemplateDialog, ThemeDialog,
 };
 pub use common::{centered_rect, DialogItem, SelectDialog};
+pub use coordinator::{CoordinatorAgent, CoordinatorDialog};
 pub use git::{GitCommitDisplay, GitDialog, GitDialogResult, GitFileDisplay, GitView};
 pub use input::{InputDialog, InputDialogResult};
 pub use mcp::{McpDialog, McpServerInfo, McpStatus};
@@ -38,5 +44,6 @@ pub use sandbox::{SandboxAction, SandboxDialog, SandboxState};
 pub use settings::{
     SaveScope, SettingItem, SettingValue, SettingsDialog, SettingsResult, SettingsTab,
 };
-pub use status::{HelpDialog, PerfDialog, StatusDialog};
+pub use status::{HelpDialog, InstructionFileEntry, PerfDialog, PermissionLogEntry, StatusDialog};
 pub use timeline::{TimelineDialog, TimelineItem};
+pub use usage::{ToolLatency, UsageAmount, UsageDialog, UsageDialogResult};
@@ -13,7 +13,7 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, Theme};
 
 use crate::common::centered_rect;
 
@@ -150,6 +150,18 @@ impl McpDialog {
         self.selected_server().map(|s| s.name.as_str())
     }
 
+    /// Select the server with the given name, if it's listed.
+    pub fn select(&mut self, name: &str) {
+        if let Some(pos) = self
+            .filtered
+            .iter()
+            .position(|&idx| self.servers[idx].name == name)
+        {
+            self.selected = pos;
+            self.list_state.select(Some(pos));
+        }
+    }
+
     /// Update the filter.
     fn update_filter(&mut self) {
         if self.filter.is_empty() {
@@ -244,7 +256,7 @@ impl McpDialog {
         frame.render_widget(Clear, dialog_area);
 
         let block = Block::default()
-            .title(" MCP Servers ")
+            .title(format!(" {} ", tr("dialog.mcp_servers")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -263,7 +275,7 @@ impl McpDialog {
 
         // Render filter input
         let filter_block = Block::default()
-            .title(" Filter ")
+            .title(format!(" {} ", tr("dialog.filter")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
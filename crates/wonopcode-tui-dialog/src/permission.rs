@@ -8,7 +8,7 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, Theme};
 
 use crate::common::centered_rect;
 
@@ -126,7 +126,7 @@ impl PermissionDialog {
         frame.render_widget(Clear, dialog_area);
 
         let block = Block::default()
-            .title(" Permission Required ")
+            .title(format!(" {} ", tr("dialog.permission")))
             .borders(Borders::ALL)
             .border_style(theme.accent_style());
 
@@ -9,7 +9,7 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, Theme};
 
 use crate::common::centered_rect;
 
@@ -187,7 +187,7 @@ impl SandboxDialog {
         frame.render_widget(Clear, dialog_area);
 
         let block = Block::default()
-            .title(" Sandbox ")
+            .title(format!(" {} ", tr("dialog.sandbox")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
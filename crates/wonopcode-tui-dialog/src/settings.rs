@@ -9,7 +9,7 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::{RenderSettings, Theme};
+use wonopcode_tui_core::{tr, RenderSettings, Theme};
 
 /// Helper function to create a centered rectangle.
 fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
@@ -494,6 +494,7 @@ impl SettingsDialog {
                             "docker".to_string(),
                             "podman".to_string(),
                             "lima".to_string(),
+                            "wasm".to_string(),
                             "none".to_string(),
                         ],
                     },
@@ -661,6 +662,12 @@ impl SettingsDialog {
                     "Render markdown tables with borders",
                     SettingValue::Bool(true),
                 ),
+                SettingItem::new(
+                    "perf.message_usage",
+                    "Message Usage",
+                    "Show tokens and cost under each response",
+                    SettingValue::Bool(true),
+                ),
                 SettingItem::new(
                     "perf.streaming_fps",
                     "Streaming FPS",
@@ -698,6 +705,30 @@ impl SettingsDialog {
                     "Aggressive memory optimization (disables some features)",
                     SettingValue::Bool(false),
                 ),
+                SettingItem::new(
+                    "perf.high_contrast",
+                    "High Contrast",
+                    "Use the high-contrast variant of the theme",
+                    SettingValue::Bool(false),
+                ),
+                SettingItem::new(
+                    "perf.reduce_motion",
+                    "Reduce Motion",
+                    "Show static indicators instead of spinners",
+                    SettingValue::Bool(false),
+                ),
+                SettingItem::new(
+                    "perf.ascii_borders",
+                    "ASCII Borders",
+                    "Draw borders and icons with ASCII instead of box art",
+                    SettingValue::Bool(false),
+                ),
+                SettingItem::new(
+                    "perf.screen_reader",
+                    "Screen Reader Mode",
+                    "Show the conversation as plain linear text",
+                    SettingValue::Bool(false),
+                ),
                 SettingItem::new(
                     "perf.enable_test_commands",
                     "Enable Test Commands",
@@ -858,6 +889,9 @@ impl SettingsDialog {
                     "perf.tables" => {
                         update_item(item, SettingValue::Bool(render_settings.tables_enabled));
                     }
+                    "perf.message_usage" => {
+                        update_item(item, SettingValue::Bool(render_settings.message_usage));
+                    }
                     "perf.streaming_fps" => {
                         if let SettingValue::Select { options, .. } = &item.value {
                             update_item(
@@ -883,6 +917,18 @@ impl SettingsDialog {
                     "perf.low_memory_mode" => {
                         update_item(item, SettingValue::Bool(render_settings.low_memory_mode));
                     }
+                    "perf.high_contrast" => {
+                        update_item(item, SettingValue::Bool(render_settings.high_contrast));
+                    }
+                    "perf.reduce_motion" => {
+                        update_item(item, SettingValue::Bool(render_settings.reduce_motion));
+                    }
+                    "perf.ascii_borders" => {
+                        update_item(item, SettingValue::Bool(render_settings.ascii_borders));
+                    }
+                    "perf.screen_reader" => {
+                        update_item(item, SettingValue::Bool(render_settings.linear_output));
+                    }
                     "perf.enable_test_commands" => {
                         update_item(
                             item,
@@ -931,7 +977,14 @@ impl SettingsDialog {
     /// Load settings from a config.
     #[allow(clippy::cognitive_complexity)]
     pub fn from_config(config: &wonopcode_core::config::Config) -> Self {
-        let mut dialog = Self::new();
+        // Rendering options that aren't set follow the screen reader preset
+        // when it's on
+        let screen_reader = config.tui.as_ref().and_then(|t| t.screen_reader) == Some(true);
+        let mut dialog = if screen_reader {
+            Self::with_render_settings(&RenderSettings::screen_reader(), &Theme::default().name)
+        } else {
+            Self::new()
+        };
 
         // Helper to update a setting item
         fn update_item(item: &mut SettingItem, new_value: SettingValue) {
@@ -1231,6 +1284,11 @@ impl SettingsDialog {
                                 update_item(item, SettingValue::Bool(v));
                             }
                         }
+                        "perf.message_usage" => {
+                            if let Some(v) = tui_config.message_usage {
+                                update_item(item, SettingValue::Bool(v));
+                            }
+                        }
                         "perf.streaming_fps" => {
                             if let Some(fps) = tui_config.streaming_fps {
                                 if let SettingValue::Select { options, .. } = &item.value {
@@ -1262,6 +1320,26 @@ impl SettingsDialog {
                                 update_item(item, SettingValue::Bool(v));
                             }
                         }
+                        "perf.high_contrast" => {
+                            if let Some(v) = tui_config.high_contrast {
+                                update_item(item, SettingValue::Bool(v));
+                            }
+                        }
+                        "perf.reduce_motion" => {
+                            if let Some(v) = tui_config.reduce_motion {
+                                update_item(item, SettingValue::Bool(v));
+                            }
+                        }
+                        "perf.ascii_borders" => {
+                            if let Some(v) = tui_config.ascii_borders {
+                                update_item(item, SettingValue::Bool(v));
+                            }
+                        }
+                        "perf.screen_reader" => {
+                            if let Some(v) = tui_config.screen_reader {
+                                update_item(item, SettingValue::Bool(v));
+                            }
+                        }
                         "perf.enable_test_commands" => {
                             if let Some(v) = tui_config.enable_test_commands {
                                 update_item(item, SettingValue::Bool(v));
@@ -1575,6 +1653,12 @@ impl SettingsDialog {
                             tui.tables = Some(*b);
                         }
                     }
+                    (SettingsTab::Performance, "perf.message_usage") => {
+                        if let SettingValue::Bool(b) = &item.value {
+                            let tui = config.tui.get_or_insert_with(Default::default);
+                            tui.message_usage = Some(*b);
+                        }
+                    }
                     (SettingsTab::Performance, "perf.streaming_fps") => {
                         if let SettingValue::Select { value, .. } = &item.value {
                             let tui = config.tui.get_or_insert_with(Default::default);
@@ -1593,6 +1677,30 @@ impl SettingsDialog {
                             tui.low_memory_mode = Some(*b);
                         }
                     }
+                    (SettingsTab::Performance, "perf.high_contrast") => {
+                        if let SettingValue::Bool(b) = &item.value {
+                            let tui = config.tui.get_or_insert_with(Default::default);
+                            tui.high_contrast = Some(*b);
+                        }
+                    }
+                    (SettingsTab::Performance, "perf.reduce_motion") => {
+                        if let SettingValue::Bool(b) = &item.value {
+                            let tui = config.tui.get_or_insert_with(Default::default);
+                            tui.reduce_motion = Some(*b);
+                        }
+                    }
+                    (SettingsTab::Performance, "perf.ascii_borders") => {
+                        if let SettingValue::Bool(b) = &item.value {
+                            let tui = config.tui.get_or_insert_with(Default::default);
+                            tui.ascii_borders = Some(*b);
+                        }
+                    }
+                    (SettingsTab::Performance, "perf.screen_reader") => {
+                        if let SettingValue::Bool(b) = &item.value {
+                            let tui = config.tui.get_or_insert_with(Default::default);
+                            tui.screen_reader = Some(*b);
+                        }
+                    }
                     (SettingsTab::Performance, "perf.enable_test_commands") => {
                         if let SettingValue::Bool(b) = &item.value {
                             let tui = config.tui.get_or_insert_with(Default::default);
@@ -2070,12 +2178,12 @@ impl SettingsDialog {
 
         // Main block with title
         let title = if self.has_changes {
-            " Settings * "
+            tr("dialog.settings_modified")
         } else {
-            " Settings "
+            tr("dialog.settings")
         };
         let block = Block::default()
-            .title(title)
+            .title(format!(" {title} "))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -2333,6 +2441,11 @@ impl SettingsDialog {
                         settings.tables_enabled = *v;
                     }
                 }
+                "perf.message_usage" => {
+                    if let SettingValue::Bool(v) = &item.value {
+                        settings.message_usage = *v;
+                    }
+                }
                 "perf.streaming_fps" => {
                     if let SettingValue::Select { value, .. } = &item.value {
                         settings.streaming_fps = value.parse().unwrap_or(20);
@@ -2350,6 +2463,26 @@ impl SettingsDialog {
                         // explicit settings in the dialog take precedence.
                     }
                 }
+                "perf.high_contrast" => {
+                    if let SettingValue::Bool(v) = &item.value {
+                        settings.high_contrast = *v;
+                    }
+                }
+                "perf.reduce_motion" => {
+                    if let SettingValue::Bool(v) = &item.value {
+                        settings.reduce_motion = *v;
+                    }
+                }
+                "perf.ascii_borders" => {
+                    if let SettingValue::Bool(v) = &item.value {
+                        settings.ascii_borders = *v;
+                    }
+                }
+                "perf.screen_reader" => {
+                    if let SettingValue::Bool(v) = &item.value {
+                        settings.linear_output = *v;
+                    }
+                }
                 "perf.enable_test_commands" => {
                     if let SettingValue::Bool(v) = &item.value {
                         settings.enable_test_commands = *v;
@@ -12,10 +12,39 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::Theme;
+use wonopcode_core::quota::format_bytes;
+use wonopcode_tui_core::{tr, KeyAction, KeyScope, KeybindManager, Theme};
 
 use crate::common::centered_rect;
 
+/// A permission decision shown in the status dialog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionLogEntry {
+    /// When the check happened (unix millis).
+    pub time: i64,
+    /// Tool name.
+    pub tool: String,
+    /// Path or action that was checked.
+    pub target: Option<String>,
+    /// Whether the check was allowed.
+    pub allowed: bool,
+    /// How the decision was made: "user", "rule: ..." or "no rule".
+    pub source: String,
+}
+
+/// An instruction file shown in the status dialog.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstructionFileEntry {
+    /// Path, relative to the project or home directory where possible.
+    pub path: String,
+    /// Estimated tokens.
+    pub tokens: usize,
+    /// Whether the file is in the system prompt.
+    pub included: bool,
+    /// Why the file was truncated or left out.
+    pub note: Option<String>,
+}
+
 /// Status dialog showing current configuration and state.
 #[derive(Debug, Clone, Default)]
 pub struct StatusDialog {
@@ -49,6 +78,18 @@ pub struct StatusDialog {
     pub lsp_total: usize,
     /// Permissions pending.
     pub permissions_pending: usize,
+    /// Bytes used by stored sessions and messages.
+    pub storage_bytes: u64,
+    /// Soft storage quota in bytes, if configured.
+    pub storage_quota: Option<u64>,
+    /// Whether any storage quota is exceeded.
+    pub storage_over_quota: bool,
+    /// Recent permission decisions, oldest first.
+    pub permission_log: Vec<PermissionLogEntry>,
+    /// Instruction files found for the system prompt, highest priority first.
+    pub instruction_files: Vec<InstructionFileEntry>,
+    /// Scroll offset in lines.
+    scroll_offset: u16,
 }
 
 impl StatusDialog {
@@ -57,16 +98,42 @@ impl StatusDialog {
         Self::default()
     }
 
+    /// Replace the permission log and scroll back to the top.
+    pub fn set_permission_log(&mut self, entries: Vec<PermissionLogEntry>) {
+        self.permission_log = entries;
+        self.scroll_offset = 0;
+    }
+
+    /// Handle key events. Returns true if dialog should close.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => true,
+            KeyCode::Down | KeyCode::Char('j') => {
+                // Fixed status lines plus the lists, so the last entry can reach the top
+                let max = 28
+                    + self.instruction_files.len().max(1) as u16
+                    + self.permission_log.len() as u16;
+                self.scroll_offset = (self.scroll_offset + 1).min(max);
+                false
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(1);
+                false
+            }
+            _ => false,
+        }
+    }
+
     /// Render the status dialog.
     pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
         let dialog_width = (area.width * 60 / 100).clamp(45, 60);
-        let dialog_height = (area.height * 70 / 100).clamp(16, 22);
+        let dialog_height = (area.height * 70 / 100).clamp(16, 32);
         let dialog_area = centered_rect(dialog_width, dialog_height, area);
 
         frame.render_widget(Clear, dialog_area);
 
         let block = Block::default()
-            .title(" Status ")
+            .title(format!(" {} ", tr("dialog.status")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -89,7 +156,17 @@ impl StatusDialog {
             format!("{}", self.input_tokens + self.output_tokens)
         };
 
-        let status_lines = vec![
+        // Format storage usage
+        let storage_str = match self.storage_quota {
+            Some(quota) => format!(
+                "{} / {}",
+                format_bytes(self.storage_bytes),
+                format_bytes(quota)
+            ),
+            None => format_bytes(self.storage_bytes),
+        };
+
+        let mut status_lines = vec![
             Line::from(Span::styled("-- Provider --", theme.dim_style())),
             Line::from(vec![
                 Span::styled("Provider:    ", theme.muted_style()),
@@ -120,6 +197,17 @@ impl StatusDialog {
                 Span::styled("Messages:    ", theme.muted_style()),
                 Span::styled(format!("{}", self.message_count), theme.text_style()),
             ]),
+            Line::from(vec![
+                Span::styled("Storage:     ", theme.muted_style()),
+                Span::styled(
+                    storage_str,
+                    if self.storage_over_quota {
+                        theme.warning_style()
+                    } else {
+                        theme.text_style()
+                    },
+                ),
+            ]),
             Line::from(""),
             Line::from(Span::styled("-- Usage --", theme.dim_style())),
             Line::from(vec![
@@ -173,11 +261,87 @@ impl StatusDialog {
                 ),
             ]),
             Line::from(""),
-            Line::from(Span::styled("Press Escape to close", theme.dim_style())),
+            Line::from(Span::styled("-- Instructions --", theme.dim_style())),
         ];
 
-        let paragraph = Paragraph::new(status_lines);
-        frame.render_widget(paragraph, inner);
+        if self.instruction_files.is_empty() {
+            status_lines.push(Line::from(Span::styled(
+                "No instruction files",
+                theme.muted_style(),
+            )));
+        }
+        for file in &self.instruction_files {
+            let (symbol, style) = if file.included {
+                ("✓", theme.success_style())
+            } else {
+                ("-", theme.muted_style())
+            };
+            let mut spans = vec![
+                Span::styled(format!("{symbol} "), style),
+                Span::styled(file.path.as_str(), theme.text_style()),
+                Span::styled(format!("  ~{} tokens", file.tokens), theme.dim_style()),
+            ];
+            if let Some(note) = &file.note {
+                spans.push(Span::styled(format!("  {note}"), theme.muted_style()));
+            }
+            status_lines.push(Line::from(spans));
+        }
+
+        status_lines.push(Line::from(""));
+        status_lines.push(Line::from(Span::styled(
+            "-- Permission log --",
+            theme.dim_style(),
+        )));
+
+        if self.permission_log.is_empty() {
+            status_lines.push(Line::from(Span::styled(
+                "No permission checks yet",
+                theme.muted_style(),
+            )));
+        }
+        // Newest first
+        for entry in self.permission_log.iter().rev() {
+            let time = chrono::DateTime::from_timestamp_millis(entry.time)
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%H:%M:%S")
+                        .to_string()
+                })
+                .unwrap_or_default();
+            let (symbol, style) = if entry.allowed {
+                ("✓", theme.success_style())
+            } else {
+                ("✗", theme.error_style())
+            };
+            let mut spans = vec![
+                Span::styled(format!("{time} "), theme.dim_style()),
+                Span::styled(format!("{symbol} "), style),
+                Span::styled(entry.tool.as_str(), theme.text_style()),
+            ];
+            if let Some(target) = &entry.target {
+                spans.push(Span::styled(format!(" {target}"), theme.muted_style()));
+            }
+            spans.push(Span::styled(
+                format!("  {}", entry.source),
+                theme.dim_style(),
+            ));
+            status_lines.push(Line::from(spans));
+        }
+
+        // Keep the footer visible below the scrolled content
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        let paragraph = Paragraph::new(status_lines).scroll((self.scroll_offset, 0));
+        frame.render_widget(paragraph, chunks[0]);
+
+        let footer = Line::from(Span::styled(
+            "↑/↓ to scroll, Escape to close",
+            theme.dim_style(),
+        ));
+        frame.render_widget(Paragraph::new(vec![footer]), chunks[1]);
     }
 }
 
@@ -253,7 +417,7 @@ impl PerfDialog {
         frame.render_widget(Clear, dialog_area);
 
         let block = Block::default()
-            .title(" Performance Metrics ")
+            .title(format!(" {} ", tr("dialog.metrics")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -425,8 +589,20 @@ impl PerfDialog {
 }
 
 /// Help dialog showing keybindings.
-#[derive(Debug, Clone, Copy)]
-pub struct HelpDialog;
+#[derive(Debug, Clone)]
+pub struct HelpDialog {
+    /// Effective keybindings.
+    keybinds: KeybindManager,
+    /// Scroll offset in lines.
+    scroll_offset: u16,
+}
+
+/// A line of the help dialog.
+enum HelpLine {
+    Blank,
+    Section(String),
+    Key(String, &'static str),
+}
 
 impl Default for HelpDialog {
     fn default() -> Self {
@@ -434,109 +610,192 @@ impl Default for HelpDialog {
     }
 }
 impl HelpDialog {
-    /// Create a new help dialog.
+    /// Create a new help dialog showing the default keybindings.
     pub fn new() -> Self {
-        Self
+        Self::with_keybinds(KeybindManager::default())
+    }
+
+    /// Create a help dialog showing the given keybindings.
+    pub fn with_keybinds(keybinds: KeybindManager) -> Self {
+        Self {
+            keybinds,
+            scroll_offset: 0,
+        }
+    }
+
+    /// Handle key events. Returns true if dialog should close.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => {
+                self.scroll_offset = 0;
+                true
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                let max = self.help_lines().len() as u16;
+                self.scroll_offset = (self.scroll_offset + 1).min(max);
+                false
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(1);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// The lines to show, with the keys as currently bound.
+    fn help_lines(&self) -> Vec<HelpLine> {
+        let keys = |actions: &[KeyAction]| {
+            actions
+                .iter()
+                .map(|a| self.keybinds.get_display(*a))
+                .filter(|d| !d.is_empty())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut lines = Vec::new();
+        let push = |lines: &mut Vec<HelpLine>, key: String, description: &'static str| {
+            if !key.is_empty() {
+                lines.push(HelpLine::Key(key, description));
+            }
+        };
+
+        push(
+            &mut lines,
+            keys(&[KeyAction::CommandPalette]),
+            "Command palette",
+        );
+        push(
+            &mut lines,
+            keys(&[KeyAction::InputCancel]),
+            "Cancel / Clear input",
+        );
+        push(
+            &mut lines,
+            keys(&[KeyAction::SessionInterrupt]),
+            "Interrupt / Close dialog",
+        );
+        push(
+            &mut lines,
+            keys(&[KeyAction::InputSubmit]),
+            "Send message / Confirm",
+        );
+        push(
+            &mut lines,
+            keys(&[KeyAction::InputNewline]),
+            "New line in input",
+        );
+        push(
+            &mut lines,
+            keys(&[KeyAction::InputHistory]),
+            "Input history",
+        );
+
+        lines.push(HelpLine::Blank);
+        lines.push(HelpLine::Section(
+            "-- Navigation (in scroll mode) --".to_string(),
+        ));
+        for (actions, description) in [
+            (
+                [KeyAction::MessagesPrev, KeyAction::MessagesNext],
+                "Scroll one line",
+            ),
+            (
+                [
+                    KeyAction::MessagesHalfPageUp,
+                    KeyAction::MessagesHalfPageDown,
+                ],
+                "Scroll half a page",
+            ),
+            (
+                [KeyAction::MessagesPageUp, KeyAction::MessagesPageDown],
+                "Scroll page",
+            ),
+            (
+                [KeyAction::MessagesFirst, KeyAction::MessagesLast],
+                "First/Last message",
+            ),
+        ] {
+            push(&mut lines, keys(&actions), description);
+        }
+
+        lines.push(HelpLine::Blank);
+        lines.push(HelpLine::Section(format!(
+            "-- Leader Commands ({}) --",
+            self.keybinds.leader_display()
+        )));
+        for action in KeyAction::all() {
+            if action.scope() == KeyScope::Global
+                && !matches!(
+                    action,
+                    KeyAction::CommandPalette | KeyAction::SessionInterrupt
+                )
+            {
+                push(&mut lines, keys(&[*action]), action.description());
+            }
+        }
+
+        lines.push(HelpLine::Blank);
+        lines.push(HelpLine::Section(
+            "-- Selection Mode (in scroll mode) --".to_string(),
+        ));
+        push(&mut lines, "v".to_string(), "Enter selection mode");
+        push(&mut lines, "j/k".to_string(), "Select message up/down");
+        push(&mut lines, "y".to_string(), "Copy selected message");
+        lines
     }
 
     /// Render the help dialog.
     pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
         let dialog_width = (area.width * 70 / 100).clamp(50, 70);
-        let dialog_height = (area.height * 80 / 100).clamp(15, 25);
+        let dialog_height = (area.height * 80 / 100).clamp(15, 32);
         let dialog_area = centered_rect(dialog_width, dialog_height, area);
 
         frame.render_widget(Clear, dialog_area);
 
         let block = Block::default()
-            .title(" Help - Keybindings ")
+            .title(format!(" {} ", tr("dialog.help")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
         let inner = block.inner(dialog_area);
         frame.render_widget(block, dialog_area);
 
-        let help_text = vec![
-            Line::from(vec![
-                Span::styled("Ctrl+P", theme.highlight_style()),
-                Span::styled("        Command palette", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Ctrl+C", theme.highlight_style()),
-                Span::styled("        Quit / Cancel", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Escape", theme.highlight_style()),
-                Span::styled("        Cancel / Close dialog", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Enter", theme.highlight_style()),
-                Span::styled("         Send message / Confirm", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Ctrl+J", theme.highlight_style()),
-                Span::styled("        New line in input", theme.text_style()),
-            ]),
-            Line::from(""),
-            Line::from(Span::styled("-- Navigation --", theme.dim_style())),
-            Line::from(vec![
-                Span::styled("Up/Down", theme.highlight_style()),
-                Span::styled("       Scroll messages / History", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("PageUp/Down", theme.highlight_style()),
-                Span::styled("   Scroll page", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Home/End", theme.highlight_style()),
-                Span::styled("      First/Last message", theme.text_style()),
-            ]),
-            Line::from(""),
-            Line::from(Span::styled(
-                "-- Leader Commands (Ctrl+X) --",
-                theme.dim_style(),
-            )),
-            Line::from(vec![
-                Span::styled("Ctrl+X N", theme.highlight_style()),
-                Span::styled("      New session", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Ctrl+X L", theme.highlight_style()),
-                Span::styled("      Session list", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Ctrl+X M", theme.highlight_style()),
-                Span::styled("      Model selection", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Ctrl+X B", theme.highlight_style()),
-                Span::styled("      Toggle sidebar", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("Ctrl+X T", theme.highlight_style()),
-                Span::styled("      Theme selection", theme.text_style()),
-            ]),
-            Line::from(""),
-            Line::from(Span::styled(
-                "-- Selection Mode (in scroll mode) --",
-                theme.dim_style(),
-            )),
-            Line::from(vec![
-                Span::styled("v", theme.highlight_style()),
-                Span::styled("             Enter selection mode", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("j/k", theme.highlight_style()),
-                Span::styled("           Select message up/down", theme.text_style()),
-            ]),
-            Line::from(vec![
-                Span::styled("y", theme.highlight_style()),
-                Span::styled("             Copy selected message", theme.text_style()),
-            ]),
-            Line::from(""),
-            Line::from(Span::styled("Press Escape to close", theme.dim_style())),
-        ];
+        let help_lines = self.help_lines();
+        let key_width = help_lines
+            .iter()
+            .filter_map(|l| match l {
+                HelpLine::Key(key, _) => Some(key.chars().count()),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .clamp(12, 24);
+        let help_text: Vec<Line> = help_lines
+            .into_iter()
+            .map(|line| match line {
+                HelpLine::Blank => Line::from(""),
+                HelpLine::Section(title) => Line::from(Span::styled(title, theme.dim_style())),
+                HelpLine::Key(key, description) => Line::from(vec![
+                    Span::styled(format!("{key:<key_width$}"), theme.highlight_style()),
+                    Span::styled(format!("  {description}"), theme.text_style()),
+                ]),
+            })
+            .collect();
+
+        // Keep the footer visible below the scrolled content
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
 
-        let paragraph = Paragraph::new(help_text);
-        frame.render_widget(paragraph, inner);
+        let paragraph = Paragraph::new(help_text).scroll((self.scroll_offset, 0));
+        frame.render_widget(paragraph, chunks[0]);
+
+        let footer = Line::from(Span::styled(
+            "↑/↓ to scroll, Escape to close",
+            theme.dim_style(),
+        ));
+        frame.render_widget(Paragraph::new(vec![footer]), chunks[1]);
     }
 }
@@ -9,7 +9,7 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, Theme};
 
 use crate::common::centered_rect;
 
@@ -139,7 +139,7 @@ impl TimelineDialog {
         frame.render_widget(Clear, dialog_area);
 
         let block = Block::default()
-            .title(" Message Timeline ")
+            .title(format!(" {} ", tr("dialog.timeline")))
             .borders(Borders::ALL)
             .border_style(theme.border_active_style());
 
@@ -0,0 +1,470 @@
+//! Usage dashboard dialog.
+//!
+//! Shows spend over recent days as cost and token bars, the spend of each
+//! model, and the latency of recent calls of each tool as sparklines.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Clear, Paragraph, Sparkline},
+    Frame,
+};
+
+use wonopcode_tui_core::{tr_args, Theme};
+
+use crate::common::centered_rect;
+
+/// Day ranges the dashboard cycles through.
+const RANGES: [u32; 3] = [7, 30, 90];
+
+/// Cost and tokens of a day or a model.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageAmount {
+    /// The day (`YYYY-MM-DD`) or model.
+    pub name: String,
+    /// Cost in USD.
+    pub cost: f64,
+    /// Input tokens.
+    pub input_tokens: u64,
+    /// Output tokens.
+    pub output_tokens: u64,
+}
+
+impl UsageAmount {
+    fn tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+}
+
+/// Durations of the calls of a tool.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolLatency {
+    /// Tool name.
+    pub tool: String,
+    /// Milliseconds, oldest first.
+    pub durations_ms: Vec<u64>,
+}
+
+impl ToolLatency {
+    /// Average duration in milliseconds.
+    fn average(&self) -> u64 {
+        let count = self.durations_ms.len().max(1) as u64;
+        self.durations_ms.iter().sum::<u64>() / count
+    }
+
+    /// 95th percentile duration in milliseconds.
+    fn p95(&self) -> u64 {
+        let mut sorted = self.durations_ms.clone();
+        sorted.sort_unstable();
+        let index = (sorted.len() * 95).div_ceil(100).saturating_sub(1);
+        sorted.get(index).copied().unwrap_or(0)
+    }
+}
+
+/// Result of a key press in the usage dialog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UsageDialogResult {
+    /// Nothing to do.
+    None,
+    /// Close the dialog.
+    Close,
+    /// Load usage over this many days.
+    Load(u32),
+}
+
+/// Usage dashboard with cost trends, model breakdown and tool latency.
+#[derive(Debug, Clone)]
+pub struct UsageDialog {
+    /// Days shown.
+    days: u32,
+    /// Whether usage is being loaded.
+    loading: bool,
+    /// Spend by day, oldest first.
+    daily: Vec<UsageAmount>,
+    /// Spend by model.
+    models: Vec<UsageAmount>,
+    /// Tool latencies, slowest on average first.
+    tools: Vec<ToolLatency>,
+    /// Scroll offset in the model and tool lists.
+    scroll_offset: usize,
+}
+
+impl Default for UsageDialog {
+    fn default() -> Self {
+        Self::new(RANGES[0])
+    }
+}
+
+impl UsageDialog {
+    /// Create a dialog waiting for usage over `days` days.
+    pub fn new(days: u32) -> Self {
+        Self {
+            days,
+            loading: true,
+            daily: Vec::new(),
+            models: Vec::new(),
+            tools: Vec::new(),
+            scroll_offset: 0,
+        }
+    }
+
+    /// Get the number of days shown.
+    pub fn days(&self) -> u32 {
+        self.days
+    }
+
+    /// Show loaded usage.
+    pub fn set_usage(
+        &mut self,
+        days: u32,
+        daily: Vec<UsageAmount>,
+        mut models: Vec<UsageAmount>,
+        mut tools: Vec<ToolLatency>,
+    ) {
+        models.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+        tools.retain(|t| !t.durations_ms.is_empty());
+        tools.sort_by_key(|t| std::cmp::Reverse(t.average()));
+        self.days = days;
+        self.loading = false;
+        self.daily = daily;
+        self.models = models;
+        self.tools = tools;
+        self.scroll_offset = 0;
+    }
+
+    /// Handle key events.
+    pub fn handle_key(&mut self, key: KeyEvent) -> UsageDialogResult {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Enter => UsageDialogResult::Close,
+            KeyCode::Tab | KeyCode::Right | KeyCode::Char('l') => self.load(1),
+            KeyCode::BackTab | KeyCode::Left | KeyCode::Char('h') => self.load(RANGES.len() - 1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                let rows = self.models.len() + self.tools.len();
+                self.scroll_offset = (self.scroll_offset + 1).min(rows.saturating_sub(1));
+                UsageDialogResult::None
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(1);
+                UsageDialogResult::None
+            }
+            _ => UsageDialogResult::None,
+        }
+    }
+
+    /// Move `step` ranges forward, wrapping around, and load it.
+    fn load(&mut self, step: usize) -> UsageDialogResult {
+        let current = RANGES.iter().position(|&d| d == self.days).unwrap_or(0);
+        self.days = RANGES[(current + step) % RANGES.len()];
+        self.loading = true;
+        UsageDialogResult::Load(self.days)
+    }
+
+    /// Render the usage dialog.
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let dialog_width = (area.width * 85 / 100).clamp(50, 110);
+        let dialog_height = (area.height * 85 / 100).clamp(20, 40);
+        let dialog_area = centered_rect(dialog_width, dialog_height, area);
+
+        frame.render_widget(Clear, dialog_area);
+
+        let block = Block::default()
+            .title(format!(
+                " {} ",
+                tr_args("dialog.usage", &[("days", &self.days)])
+            ))
+            .borders(Borders::ALL)
+            .border_style(theme.border_active_style());
+
+        let inner = block.inner(dialog_area);
+        frame.render_widget(block, dialog_area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(2), // Summary
+                Constraint::Length(7), // Cost per day
+                Constraint::Length(7), // Tokens per day
+                Constraint::Min(3),    // Models and tools
+                Constraint::Length(1), // Footer
+            ])
+            .split(inner);
+
+        frame.render_widget(Paragraph::new(self.summary_lines(theme)), chunks[0]);
+        let cost: Vec<(u64, String)> = self
+            .daily
+            .iter()
+            .map(|day| {
+                (
+                    (day.cost * 100.0).round() as u64,
+                    format!("${:.2}", day.cost),
+                )
+            })
+            .collect();
+        let tokens: Vec<(u64, String)> = self
+            .daily
+            .iter()
+            .map(|day| (day.tokens(), format_tokens(day.tokens())))
+            .collect();
+        let cost_style = Style::default().fg(theme.success);
+        let tokens_style = Style::default().fg(theme.info);
+        self.render_daily(
+            frame,
+            chunks[1],
+            "── Cost per day ──",
+            &cost,
+            cost_style,
+            theme,
+        );
+        self.render_daily(
+            frame,
+            chunks[2],
+            "── Tokens per day ──",
+            &tokens,
+            tokens_style,
+            theme,
+        );
+        self.render_breakdown(frame, chunks[3], theme);
+
+        let footer = Line::from(Span::styled(
+            "←/→ to change the range, ↑/↓ to scroll, Escape to close",
+            theme.dim_style(),
+        ));
+        frame.render_widget(Paragraph::new(vec![footer]), chunks[4]);
+    }
+
+    fn summary_lines(&self, theme: &Theme) -> Vec<Line<'static>> {
+        if self.loading {
+            return vec![Line::from(Span::styled(
+                "Loading usage...",
+                theme.dim_style(),
+            ))];
+        }
+        let cost: f64 = self.daily.iter().map(|d| d.cost).sum();
+        let tokens: u64 = self.daily.iter().map(UsageAmount::tokens).sum();
+        let today = self.daily.last().map_or(0.0, |d| d.cost);
+        let per_day = cost / self.daily.len().max(1) as f64;
+        vec![Line::from(vec![
+            Span::styled("Total: ", theme.muted_style()),
+            Span::styled(format!("${cost:.2}"), theme.highlight_style()),
+            Span::raw("    "),
+            Span::styled("Tokens: ", theme.muted_style()),
+            Span::styled(format_tokens(tokens), theme.text_style()),
+            Span::raw("    "),
+            Span::styled("Avg/day: ", theme.muted_style()),
+            Span::styled(format!("${per_day:.2}"), theme.text_style()),
+            Span::raw("    "),
+            Span::styled("Today: ", theme.muted_style()),
+            Span::styled(format!("${today:.2}"), theme.text_style()),
+        ])]
+    }
+
+    /// Render a bar per day, with a heading. Each day has a value and the
+    /// text shown above wide bars.
+    fn render_daily(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        heading: &str,
+        values: &[(u64, String)],
+        style: Style,
+        theme: &Theme,
+    ) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(1)])
+            .split(area);
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled(
+                heading.to_string(),
+                theme.dim_style(),
+            ))),
+            chunks[0],
+        );
+        if self.daily.is_empty() {
+            return;
+        }
+
+        let count = self.daily.len() as u16;
+        let (bar_width, bar_gap) = bar_layout(chunks[1].width, count);
+        // Values only fit above wide bars, and labels every few days
+        let label_every = (6 / (bar_width + bar_gap)).max(1) as usize;
+        let bars: Vec<Bar> = self
+            .daily
+            .iter()
+            .enumerate()
+            .zip(values)
+            .map(|((i, day), (value, text))| {
+                let mut bar = Bar::default().value(*value).text_value(if bar_width >= 6 {
+                    text.clone()
+                } else {
+                    String::new()
+                });
+                // Count back from today, so today is always labelled
+                let skipped = (self.daily.len() - 1 - i) % label_every;
+                if skipped == 0 {
+                    bar = bar.label(Line::from(day_label(&day.name, bar_width)));
+                }
+                bar
+            })
+            .collect();
+        let chart = BarChart::default()
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(bar_width)
+            .bar_gap(bar_gap)
+            .bar_style(style)
+            .value_style(theme.text_style().add_modifier(Modifier::REVERSED))
+            .label_style(theme.dim_style());
+        frame.render_widget(chart, chunks[1]);
+    }
+
+    /// Render the model breakdown and the tool latency sparklines.
+    fn render_breakdown(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let mut rows: Vec<Row> = Vec::new();
+        rows.push(Row::Heading("── By model ──"));
+        if self.models.is_empty() {
+            rows.push(Row::Empty("  No model usage recorded"));
+        }
+        let total: f64 = self.models.iter().map(|m| m.cost).sum();
+        rows.extend(self.models.iter().map(|m| Row::Model(m, total)));
+        rows.push(Row::Heading("── Tool latency ──"));
+        if self.tools.is_empty() {
+            rows.push(Row::Empty("  No tool calls recorded"));
+        }
+        rows.extend(self.tools.iter().map(Row::Tool));
+
+        let visible = rows
+            .into_iter()
+            .skip(self.scroll_offset)
+            .take(area.height as usize);
+        for (i, row) in visible.enumerate() {
+            let row_area = Rect::new(area.x, area.y + i as u16, area.width, 1);
+            match row {
+                Row::Heading(text) => frame.render_widget(
+                    Paragraph::new(Span::styled(text, theme.dim_style())),
+                    row_area,
+                ),
+                Row::Empty(text) => frame.render_widget(
+                    Paragraph::new(Span::styled(text, theme.dim_style())),
+                    row_area,
+                ),
+                Row::Model(model, total) => {
+                    let share = if total > 0.0 { model.cost / total } else { 0.0 };
+                    let bar = "█".repeat((share * 20.0).round() as usize);
+                    let line = Line::from(vec![
+                        Span::styled(
+                            format!("  {:24} ", truncate(&model.name, 24)),
+                            theme.muted_style(),
+                        ),
+                        Span::styled(
+                            format!("{:>9} ", format!("${:.2}", model.cost)),
+                            theme.text_style(),
+                        ),
+                        Span::styled(
+                            format!("{:>8} tokens ", format_tokens(model.tokens())),
+                            theme.dim_style(),
+                        ),
+                        Span::styled(format!("{bar:20}"), Style::default().fg(theme.success)),
+                        Span::styled(format!(" {:>3.0}%", share * 100.0), theme.dim_style()),
+                    ]);
+                    frame.render_widget(Paragraph::new(line), row_area);
+                }
+                Row::Tool(tool) => {
+                    let stats = format!(
+                        " avg {} p95 {} ({} calls)",
+                        format_ms(tool.average()),
+                        format_ms(tool.p95()),
+                        tool.durations_ms.len()
+                    );
+                    let name_width = 16u16;
+                    let stats_width = (stats.chars().count() as u16).min(row_area.width / 2);
+                    let spark_width = row_area.width.saturating_sub(name_width + stats_width + 2);
+                    let name = Rect::new(row_area.x, row_area.y, name_width, 1);
+                    let spark = Rect::new(row_area.x + name_width, row_area.y, spark_width, 1);
+                    let rest = Rect::new(
+                        spark.x + spark_width,
+                        row_area.y,
+                        row_area.width.saturating_sub(name_width + spark_width),
+                        1,
+                    );
+                    frame.render_widget(
+                        Paragraph::new(Span::styled(
+                            format!("  {:13} ", truncate(&tool.tool, 13)),
+                            theme.muted_style(),
+                        )),
+                        name,
+                    );
+                    // The most recent calls that fit
+                    let skip = tool.durations_ms.len().saturating_sub(spark_width as usize);
+                    frame.render_widget(
+                        Sparkline::default()
+                            .data(&tool.durations_ms[skip..])
+                            .style(Style::default().fg(theme.warning)),
+                        spark,
+                    );
+                    frame.render_widget(
+                        Paragraph::new(Span::styled(stats, theme.text_style())),
+                        rest,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// A row of the model and tool lists.
+enum Row<'a> {
+    Heading(&'static str),
+    Empty(&'static str),
+    Model(&'a UsageAmount, f64),
+    Tool(&'a ToolLatency),
+}
+
+/// Bar width and gap to fit `count` bars in `width` columns.
+fn bar_layout(width: u16, count: u16) -> (u16, u16) {
+    let count = count.max(1);
+    if width >= count * 2 {
+        (((width + 1) / count).saturating_sub(1).clamp(1, 8), 1)
+    } else {
+        ((width / count).max(1), 0)
+    }
+}
+
+/// The label of a day (`YYYY-MM-DD`) under a bar: month and day when it
+/// fits, otherwise the day of the month.
+fn day_label(day: &str, bar_width: u16) -> String {
+    match day.get(5..) {
+        Some(month_day) if bar_width >= 5 => month_day.to_string(),
+        _ => day.get(8..).unwrap_or(day).to_string(),
+    }
+}
+
+/// Format a token count with K/M suffixes.
+fn format_tokens(tokens: u64) -> String {
+    if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{:.1}K", tokens as f64 / 1_000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
+/// Format a duration in milliseconds, as seconds from a second on.
+fn format_ms(ms: u64) -> String {
+    if ms >= 1_000 {
+        format!("{:.1}s", ms as f64 / 1_000.0)
+    } else {
+        format!("{ms}ms")
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() > max_chars {
+        let cut: String = text.chars().take(max_chars - 1).collect();
+        format!("{cut}…")
+    } else {
+        text.to_string()
+    }
+}
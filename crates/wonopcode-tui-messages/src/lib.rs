@@ -8,9 +8,13 @@ use ratatui::{
     Frame,
 };
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use wonopcode_tui_core::metrics;
 use wonopcode_tui_core::{AgentMode, RenderSettings, Theme};
+use wonopcode_tui_render::image::{ImageCanvas, ImageProtocol, InlineImage};
 use wonopcode_tui_render::markdown::{render_markdown_with_settings, wrap_line};
+use wonopcode_tui_render::IncrementalMarkdown;
 
 /// Maximum length for stored tool outputs (10KB).
 const MAX_TOOL_OUTPUT_LEN: usize = 10_000;
@@ -27,6 +31,36 @@ const CACHE_BUFFER_SIZE: usize = 20;
 /// Interval for periodic cache cleanup (in render frames).
 const CACHE_CLEANUP_INTERVAL: usize = 60;
 
+/// Columns left of an inline image, for the tool border.
+const IMAGE_INDENT: u16 = 4;
+
+/// Width in cells of the progress bar of a running tool.
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// Lines of output shown of a running tool.
+const LIVE_OUTPUT_LINES: usize = 6;
+
+/// Longest line of output kept of a running tool, in bytes.
+const LIVE_OUTPUT_LINE_LEN: usize = 500;
+
+/// Drop all but the last `count` lines of `output`, and the start of lines
+/// longer than [`LIVE_OUTPUT_LINE_LEN`].
+fn keep_last_lines(output: &mut String, count: usize) {
+    let body = output.strip_suffix('\n').unwrap_or(output);
+    if let Some((start, _)) = body.rmatch_indices('\n').nth(count.saturating_sub(1)) {
+        output.drain(..=start);
+    }
+    let last = output.strip_suffix('\n').unwrap_or(output);
+    let line_start = last.rfind('\n').map_or(0, |i| i + 1);
+    if last.len() - line_start > LIVE_OUTPUT_LINE_LEN {
+        let mut cut = last.len() - LIVE_OUTPUT_LINE_LEN;
+        while !output.is_char_boundary(cut) {
+            cut += 1;
+        }
+        output.drain(line_start..cut);
+    }
+}
+
 /// Truncate tool output if it exceeds the maximum length.
 fn truncate_tool_output(output: Option<String>) -> Option<String> {
     output.map(|s| {
@@ -117,9 +151,45 @@ pub enum MessageSegment {
     Tool(DisplayToolCall),
 }
 
+/// Tokens and cost of an assistant message's turn.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MessageUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost: f64,
+}
+
+impl MessageUsage {
+    /// Short parts such as `12.3K in`, `845 out` and `$0.0123`. The cost is
+    /// left out when it's unknown.
+    pub fn parts(&self) -> Vec<String> {
+        let mut parts = vec![
+            format!("{} in", format_tokens(self.input_tokens)),
+            format!("{} out", format_tokens(self.output_tokens)),
+        ];
+        if self.cost > 0.0 {
+            parts.push(format!("${:.4}", self.cost));
+        }
+        parts
+    }
+}
+
+/// Format a token count with K/M suffixes.
+fn format_tokens(tokens: u32) -> String {
+    if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{:.1}K", tokens as f64 / 1_000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
 /// A message in the conversation.
 #[derive(Debug, Clone)]
 pub struct DisplayMessage {
+    /// Message ID, for messages the backend can refer back to.
+    pub id: Option<String>,
     pub role: MessageRole,
     /// Legacy single content field (used for user/system messages)
     pub content: String,
@@ -130,6 +200,8 @@ pub struct DisplayMessage {
     pub agent: AgentMode,
     pub model: Option<String>,
     pub duration: Option<String>,
+    /// Tokens and cost of the turn that produced an assistant message.
+    pub usage: Option<MessageUsage>,
     /// Cache for rendered markdown (interior mutability for rendering).
     render_cache: RefCell<RenderCache>,
 }
@@ -137,6 +209,7 @@ pub struct DisplayMessage {
 impl DisplayMessage {
     pub fn user(content: impl Into<String>) -> Self {
         Self {
+            id: None,
             role: MessageRole::User,
             content: content.into(),
             segments: vec![],
@@ -144,6 +217,7 @@ impl DisplayMessage {
             agent: AgentMode::Build,
             model: None,
             duration: None,
+            usage: None,
             render_cache: RefCell::new(RenderCache::default()),
         }
     }
@@ -151,6 +225,7 @@ impl DisplayMessage {
     pub fn assistant(content: impl Into<String>) -> Self {
         let content_str = content.into();
         Self {
+            id: None,
             role: MessageRole::Assistant,
             content: content_str,
             segments: vec![], // Will be populated when created with segments
@@ -158,6 +233,7 @@ impl DisplayMessage {
             agent: AgentMode::Build,
             model: None,
             duration: None,
+            usage: None,
             render_cache: RefCell::new(RenderCache::default()),
         }
     }
@@ -184,6 +260,7 @@ impl DisplayMessage {
             .collect();
 
         Self {
+            id: None,
             role: MessageRole::Assistant,
             content,
             segments,
@@ -191,12 +268,14 @@ impl DisplayMessage {
             agent: AgentMode::Build,
             model: None,
             duration: None,
+            usage: None,
             render_cache: RefCell::new(RenderCache::default()),
         }
     }
 
     pub fn system(content: impl Into<String>) -> Self {
         Self {
+            id: None,
             role: MessageRole::System,
             content: content.into(),
             segments: vec![],
@@ -204,6 +283,7 @@ impl DisplayMessage {
             agent: AgentMode::Build,
             model: None,
             duration: None,
+            usage: None,
             render_cache: RefCell::new(RenderCache::default()),
         }
     }
@@ -273,6 +353,12 @@ impl DisplayMessage {
         !self.render_cache.borrow().lines.is_empty()
     }
 
+    /// Set the message ID (builder pattern).
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
     /// Set model and agent info (builder pattern).
     pub fn with_model_agent(mut self, model: Option<String>, agent: Option<AgentMode>) -> Self {
         if let Some(m) = model {
@@ -361,6 +447,15 @@ pub enum MessageRole {
     Tool,
 }
 
+/// Progress a running tool reported.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ToolProgress {
+    /// Share of the work done, from 0 to 1, if known.
+    pub fraction: Option<f64>,
+    /// What the tool is doing.
+    pub message: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DisplayToolCall {
     pub id: String,
@@ -370,6 +465,10 @@ pub struct DisplayToolCall {
     pub output: Option<String>,
     pub metadata: Option<serde_json::Value>,
     pub expanded: bool,
+    /// Latest progress, while the tool runs.
+    pub progress: Option<ToolProgress>,
+    /// Last lines of output, while the tool runs.
+    pub live_output: String,
 }
 
 impl DisplayToolCall {
@@ -382,7 +481,93 @@ impl DisplayToolCall {
             output: None,
             metadata: None,
             expanded: false,
+            progress: None,
+            live_output: String::new(),
+        }
+    }
+
+    /// Whether this call changes files (edit, multiedit, patch or write).
+    pub fn is_edit(&self) -> bool {
+        matches!(
+            normalize_tool_name(&self.name),
+            "edit" | "multiedit" | "patch" | "write"
+        )
+    }
+
+    /// The file this call changes, from its metadata or input.
+    /// For calls that change several files, the first one.
+    pub fn edited_path(&self) -> Option<String> {
+        if let Some(metadata) = &self.metadata {
+            let path = metadata
+                .get("file")
+                .or_else(|| metadata.get("path"))
+                .or_else(|| metadata.get("paths").and_then(|p| p.get(0)))
+                .and_then(|v| v.as_str());
+            if let Some(path) = path {
+                return Some(path.to_string());
+            }
         }
+        let input = self.parsed_input()?;
+        if let Some(path) = input
+            .get("filePath")
+            .or_else(|| input.pointer("/edits/0/filePath"))
+            .and_then(|v| v.as_str())
+        {
+            return Some(path.to_string());
+        }
+        let patch = input.get("patch_text")?.as_str()?;
+        patch.lines().find_map(|line| {
+            ["*** Update File: ", "*** Add File: ", "*** Delete File: "]
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+                .map(|path| path.trim().to_string())
+        })
+    }
+
+    /// The changes this call makes, as diff lines.
+    ///
+    /// Edits show their output once done and the replaced strings before
+    /// that; writes show the written content as added lines and patches
+    /// their patch text.
+    pub fn edit_diff(&self) -> Option<String> {
+        let base_name = normalize_tool_name(&self.name);
+        if matches!(base_name, "edit" | "multiedit") {
+            if let Some(output) = self.output.as_ref().filter(|o| !o.is_empty()) {
+                if self.status != ToolStatus::Error {
+                    return Some(output.clone());
+                }
+            }
+        }
+        let input = self.parsed_input()?;
+        let prefixed = |text: &str, prefix: char| -> String {
+            text.lines().map(|l| format!("{prefix}{l}\n")).collect()
+        };
+        match base_name {
+            "edit" => {
+                let old = input.get("oldString")?.as_str()?;
+                let new = input.get("newString")?.as_str()?;
+                Some(prefixed(old, '-') + &prefixed(new, '+'))
+            }
+            "multiedit" => {
+                let edits = input.get("edits")?.as_array()?;
+                let parts: Vec<String> = edits
+                    .iter()
+                    .filter_map(|edit| {
+                        let old = edit.get("oldString")?.as_str()?;
+                        let new = edit.get("newString")?.as_str()?;
+                        Some(prefixed(old, '-') + &prefixed(new, '+'))
+                    })
+                    .collect();
+                (!parts.is_empty()).then(|| parts.join("...\n"))
+            }
+            "write" => Some(prefixed(input.get("content")?.as_str()?, '+')),
+            "patch" => Some(input.get("patch_text")?.as_str()?.to_string()),
+            _ => None,
+        }
+    }
+
+    fn parsed_input(&self) -> Option<serde_json::Value> {
+        serde_json::from_str(self.input.as_deref()?).ok()
     }
 }
 
@@ -394,6 +579,129 @@ pub enum ToolStatus {
     Error,
 }
 
+/// The tool calls of a message, in order.
+fn message_tools(msg: &DisplayMessage) -> Box<dyn Iterator<Item = &DisplayToolCall> + '_> {
+    if msg.segments.is_empty() {
+        Box::new(msg.tool_calls.iter())
+    } else {
+        Box::new(msg.segments.iter().filter_map(|s| match s {
+            MessageSegment::Tool(tool) => Some(tool),
+            MessageSegment::Text(_) => None,
+        }))
+    }
+}
+
+/// Restyle the case-insensitive matches of a query in the spans of a line.
+/// Matches spanning two spans aren't highlighted.
+fn highlight_matches(line: &mut Line<'static>, query: &str, style: Style) {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return;
+    }
+    let mut spans = Vec::with_capacity(line.spans.len());
+    for span in line.spans.drain(..) {
+        let text = span.content.as_ref();
+        let mut ranges = Vec::new();
+        let mut pos = 0;
+        for (start, _) in text.char_indices() {
+            if start < pos {
+                continue;
+            }
+            let mut chars = text[start..].chars().flat_map(char::to_lowercase);
+            if query.iter().all(|q| chars.next() == Some(*q)) {
+                // End after as many source characters as the query has
+                let len: usize = text[start..]
+                    .chars()
+                    .scan(0, |lowered, c| {
+                        (*lowered < query.len()).then(|| {
+                            *lowered += c.to_lowercase().count();
+                            c.len_utf8()
+                        })
+                    })
+                    .sum();
+                ranges.push(start..start + len);
+                pos = start + len;
+            }
+        }
+        if ranges.is_empty() {
+            spans.push(span);
+            continue;
+        }
+        let mut last = 0;
+        for range in ranges {
+            if range.start > last {
+                spans.push(Span::styled(
+                    text[last..range.start].to_string(),
+                    span.style,
+                ));
+            }
+            spans.push(Span::styled(
+                text[range.clone()].to_string(),
+                span.style.patch(style),
+            ));
+            last = range.end;
+        }
+        if last < text.len() {
+            spans.push(Span::styled(text[last..].to_string(), span.style));
+        }
+    }
+    line.spans = spans;
+}
+
+/// Decode the images in a tool call's metadata and keep them by tool call
+/// ID, removing the image data from the metadata.
+///
+/// Tools return images as `{"images": [{"mimeType": ..., "data": <base64>}]}`.
+fn take_tool_images(
+    tool_images: &mut HashMap<String, Vec<Arc<InlineImage>>>,
+    id: &str,
+    metadata: &mut Option<serde_json::Value>,
+) {
+    let Some(images) = metadata
+        .as_mut()
+        .and_then(|m| m.as_object_mut())
+        .and_then(|m| m.remove("images"))
+    else {
+        return;
+    };
+    let decoded: Vec<Arc<InlineImage>> = images
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|image| {
+            let data = image.get("data")?.as_str()?;
+            let media_type = image
+                .get("mimeType")
+                .and_then(|v| v.as_str())
+                .unwrap_or("image/png");
+            match InlineImage::from_base64(media_type, data) {
+                Ok(image) => Some(Arc::new(image)),
+                Err(e) => {
+                    tracing::debug!(tool = id, error = %e, "Skipping tool result image");
+                    None
+                }
+            }
+        })
+        .collect();
+    if !decoded.is_empty() {
+        tool_images.insert(id.to_string(), decoded);
+    }
+}
+
+/// [`take_tool_images`] for every tool call of a message.
+fn take_message_images(
+    tool_images: &mut HashMap<String, Vec<Arc<InlineImage>>>,
+    msg: &mut DisplayMessage,
+) {
+    let segment_tools = msg.segments.iter_mut().filter_map(|s| match s {
+        MessageSegment::Tool(tool) => Some(tool),
+        MessageSegment::Text(_) => None,
+    });
+    for tool in msg.tool_calls.iter_mut().chain(segment_tools) {
+        take_tool_images(tool_images, &tool.id, &mut tool.metadata);
+    }
+}
+
 /// Get icon for a tool by name.
 fn tool_icon(name: &str) -> &'static str {
     let base_name = normalize_tool_name(name);
@@ -667,17 +975,12 @@ enum StreamSegment {
 
 /// Cache for streaming content to avoid re-rendering on every frame.
 ///
-/// Key optimization: We cache rendered lines for text that has already been processed.
-/// When new text arrives, we only need to render the NEW portion and append it.
+/// Each text segment is rendered incrementally: the lines of its complete
+/// blocks are kept, and only the text after them is rendered again as it grows.
 #[derive(Debug, Clone, Default)]
 struct StreamingCache {
-    /// The width used for rendering (cache key).
-    width: usize,
-    /// Cached rendered lines for each text segment.
-    /// Key is segment index, value is (text_prefix_length, rendered_lines).
-    /// We cache lines for text[0..text_prefix_length] - when text grows, we only
-    /// need to re-render if text changed (not just appended).
-    segment_cache: Vec<(usize, Vec<Line<'static>>)>,
+    /// Incremental render of each text segment, by text segment index.
+    segment_cache: Vec<IncrementalMarkdown>,
     /// Total line count from cached segments (for scroll calculation).
     total_cached_lines: usize,
     /// Whether the cache is valid.
@@ -690,7 +993,6 @@ impl StreamingCache {
     }
 
     fn clear(&mut self) {
-        self.width = 0;
         self.segment_cache.clear();
         self.total_cached_lines = 0;
         self.valid = false;
@@ -716,10 +1018,38 @@ struct RenderedLinesCache {
     /// Cumulative line count at the END of each message (for binary search).
     /// cumulative_lines[i] = total lines from message 0 through message i (inclusive).
     cumulative_lines: Vec<usize>,
+    /// Line ranges of the tool calls in each message, by message index.
+    /// Only meaningful while the message's lines are cached.
+    tool_lines: HashMap<usize, Vec<ToolLines>>,
+    /// Lines reserved for inline images in each message, by message index.
+    image_lines: HashMap<usize, Vec<ImageLines>>,
     /// Whether the cache is valid.
     valid: bool,
 }
 
+/// The lines a tool call occupies within its rendered message.
+#[derive(Debug, Clone)]
+struct ToolLines {
+    /// First line (index within the message).
+    start: usize,
+    /// End line (exclusive).
+    end: usize,
+    /// The tool call ID.
+    id: String,
+}
+
+/// The lines an inline image is drawn over.
+#[derive(Debug, Clone)]
+struct ImageLines {
+    /// First line (index within the message, or within the streaming content).
+    start: usize,
+    /// Width in columns.
+    cols: u16,
+    /// Height in rows (and lines).
+    rows: u16,
+    image: Arc<InlineImage>,
+}
+
 /// A clickable code region tracked after rendering.
 #[derive(Debug, Clone)]
 pub struct ClickableCodeRegion {
@@ -774,6 +1104,18 @@ pub struct MessagesWidget {
     last_render_scroll: usize,
     /// Last rendered area (for click coordinate conversion).
     last_render_area: ratatui::layout::Rect,
+    /// Draws inline images with the terminal's graphics protocol.
+    images: ImageCanvas,
+    /// Images in tool results, by tool call ID.
+    tool_images: HashMap<String, Vec<Arc<InlineImage>>>,
+    /// Whether images are drawn (false while something covers the messages).
+    show_images: bool,
+    /// Lines reserved for images in the streaming content.
+    streaming_images: Vec<ImageLines>,
+    /// Search query whose matches are highlighted.
+    search_query: Option<String>,
+    /// Message of the current search match, highlighted more strongly.
+    search_current: Option<usize>,
 }
 
 impl Default for MessagesWidget {
@@ -801,6 +1143,12 @@ impl Default for MessagesWidget {
             code_regions: Vec::new(),
             last_render_scroll: 0,
             last_render_area: ratatui::layout::Rect::default(),
+            images: ImageCanvas::default(),
+            tool_images: HashMap::new(),
+            show_images: true,
+            streaming_images: Vec::new(),
+            search_query: None,
+            search_current: None,
         }
     }
 }
@@ -833,8 +1181,9 @@ impl MessagesWidget {
         self.show_thinking
     }
 
-    /// Get a transcript of all messages for export.
-    pub fn get_transcript(&self) -> Option<String> {
+    /// Get a markdown transcript of the visible messages for export, with or
+    /// without the output of tool calls.
+    pub fn get_transcript(&self, include_tool_output: bool) -> Option<String> {
         if self.messages.is_empty() {
             return None;
         }
@@ -862,7 +1211,7 @@ impl MessagesWidget {
                     transcript.push_str(input);
                     transcript.push_str("\n```\n");
                 }
-                if let Some(output) = &tool.output {
+                if let Some(output) = tool.output.as_ref().filter(|_| include_tool_output) {
                     transcript.push_str("\n**Output:**\n```\n");
                     // Truncate long outputs
                     if output.chars().count() > 1000 {
@@ -881,7 +1230,49 @@ impl MessagesWidget {
         Some(transcript)
     }
 
-    pub fn add_message(&mut self, message: DisplayMessage) {
+    /// Get the visible messages as JSON for export, with or without the
+    /// output of tool calls. Tool inputs that are JSON are kept as JSON.
+    pub fn get_transcript_json(&self, include_tool_output: bool) -> Vec<serde_json::Value> {
+        let visible_count = self.revert_index.unwrap_or(self.messages.len());
+        self.messages
+            .iter()
+            .take(visible_count)
+            .map(|msg| {
+                let role = match msg.role {
+                    MessageRole::User => "user",
+                    MessageRole::Assistant => "assistant",
+                    MessageRole::System => "system",
+                    MessageRole::Tool => "tool",
+                };
+                let tools: Vec<_> = message_tools(msg)
+                    .map(|tool| {
+                        let mut value = serde_json::json!({
+                            "name": tool.name,
+                            "status": format!("{:?}", tool.status).to_lowercase(),
+                            "input": tool.input.as_deref().map(|input| {
+                                serde_json::from_str(input)
+                                    .unwrap_or_else(|_| serde_json::Value::from(input))
+                            }),
+                        });
+                        if include_tool_output {
+                            value["output"] = serde_json::Value::from(tool.output.clone());
+                        }
+                        value
+                    })
+                    .collect();
+                serde_json::json!({
+                    "id": msg.id,
+                    "role": role,
+                    "content": msg.content,
+                    "model": msg.model,
+                    "tool_calls": tools,
+                })
+            })
+            .collect()
+    }
+
+    pub fn add_message(&mut self, mut message: DisplayMessage) {
+        take_message_images(&mut self.tool_images, &mut message);
         self.messages.push(message);
         self.invalidate_render_cache();
 
@@ -893,11 +1284,14 @@ impl MessagesWidget {
 
     /// Replace all messages with a new set (used when loading a session).
     /// Scrolls to the bottom to show the most recent messages.
-    pub fn set_messages(&mut self, messages: Vec<DisplayMessage>) {
+    pub fn set_messages(&mut self, mut messages: Vec<DisplayMessage>) {
         // Clear existing caches
         for msg in &self.messages {
             msg.clear_cache();
         }
+        for msg in &mut messages {
+            take_message_images(&mut self.tool_images, msg);
+        }
         self.messages = messages;
         self.revert_index = None;
         self.invalidate_render_cache();
@@ -940,6 +1334,47 @@ impl MessagesWidget {
         &self.render_settings
     }
 
+    /// Set the graphics protocol images are drawn with.
+    pub fn set_image_protocol(&mut self, protocol: ImageProtocol) {
+        if self.images.protocol() != protocol {
+            self.images = ImageCanvas::new(protocol);
+            self.invalidate_render_cache();
+        }
+    }
+
+    /// Get the graphics protocol images are drawn with.
+    pub fn image_protocol(&self) -> ImageProtocol {
+        self.images.protocol()
+    }
+
+    /// Query the terminal's cell size again, e.g. after a resize, so images
+    /// keep their aspect ratio.
+    pub fn refresh_image_cell_size(&mut self) {
+        if self.images.refresh_cell_size() {
+            self.invalidate_render_cache();
+        }
+    }
+
+    /// Set whether images are drawn. Hide them while a dialog or overlay
+    /// covers the messages, since graphics are drawn on top of text.
+    pub fn set_show_images(&mut self, show: bool) {
+        self.show_images = show;
+    }
+
+    /// Highlight the matches of a search query, those in the message of the
+    /// current match more strongly. `None` removes the highlighting.
+    pub fn set_search_highlight(&mut self, query: Option<&str>, current: Option<usize>) {
+        self.search_query = query.filter(|q| !q.is_empty()).map(str::to_string);
+        self.search_current = current;
+        self.dirty = true;
+    }
+
+    /// End a frame, returning the escape sequences to write after it to
+    /// remove images that are no longer shown.
+    pub fn finish_image_frame(&mut self) -> String {
+        self.images.finish_frame()
+    }
+
     /// Prune old messages to prevent unbounded memory growth.
     fn prune_old_messages(&mut self) {
         if self.messages.len() <= TARGET_MESSAGES_AFTER_PRUNE {
@@ -958,6 +1393,16 @@ impl MessagesWidget {
             // Remove messages from index 1 to (1 + to_remove)
             self.messages.drain(1..(1 + to_remove));
 
+            // Drop the images of removed tool calls
+            let ids: HashSet<&str> = self
+                .messages
+                .iter()
+                .flat_map(message_tools)
+                .chain(&self.active_tools)
+                .map(|t| t.id.as_str())
+                .collect();
+            self.tool_images.retain(|id, _| ids.contains(id.as_str()));
+
             // Update revert index if needed
             if let Some(ref mut idx) = self.revert_index {
                 *idx = idx.saturating_sub(to_remove);
@@ -1089,10 +1534,47 @@ impl MessagesWidget {
         if let Some(tool) = self.active_tools.iter_mut().find(|t| t.id == id) {
             tool.status = status;
             tool.output = truncate_tool_output(output);
+            tool.progress = None;
+            tool.live_output.clear();
+            self.dirty = true;
+        }
+    }
+
+    /// Show the progress a running tool reported.
+    pub fn set_tool_progress(&mut self, id: &str, progress: ToolProgress) {
+        if let Some(tool) = self
+            .active_tools
+            .iter_mut()
+            .find(|t| t.id == id && t.status == ToolStatus::Running)
+        {
+            tool.progress = Some(progress);
+            self.dirty = true;
+        }
+    }
+
+    /// Show output a running tool wrote, keeping its last lines.
+    pub fn append_tool_output(&mut self, id: &str, text: &str) {
+        if let Some(tool) = self
+            .active_tools
+            .iter_mut()
+            .find(|t| t.id == id && t.status == ToolStatus::Running)
+        {
+            tool.live_output.push_str(text);
+            keep_last_lines(&mut tool.live_output, LIVE_OUTPUT_LINES);
             self.dirty = true;
         }
     }
 
+    /// ID of the latest running tool showing progress, which pressing x
+    /// cancels.
+    pub fn cancellable_tool(&self) -> Option<&str> {
+        self.active_tools
+            .iter()
+            .rev()
+            .find(|t| t.status == ToolStatus::Running && t.progress.is_some())
+            .map(|t| t.id.as_str())
+    }
+
     pub fn update_tool_status_with_metadata(
         &mut self,
         id: &str,
@@ -1104,6 +1586,9 @@ impl MessagesWidget {
             tool.status = status;
             tool.output = truncate_tool_output(output);
             tool.metadata = metadata;
+            tool.progress = None;
+            tool.live_output.clear();
+            take_tool_images(&mut self.tool_images, &tool.id, &mut tool.metadata);
             self.dirty = true;
         }
     }
@@ -1252,10 +1737,38 @@ impl MessagesWidget {
 
     /// Scroll to bring a specific message into view.
     pub fn scroll_to_message(&mut self, message_index: usize) {
-        // This is a rough approximation - scroll position is line-based
-        // We estimate ~5 lines per message on average
-        let estimated_line = message_index.saturating_mul(5);
-        self.scroll = estimated_line;
+        let cumulative = &self.rendered_cache.cumulative_lines;
+        self.scroll = if message_index == 0 {
+            0
+        } else if message_index <= cumulative.len() {
+            cumulative[message_index - 1]
+        } else {
+            // Not rendered yet, so estimate ~5 lines per message
+            message_index.saturating_mul(5)
+        };
+        self.auto_scroll = false;
+    }
+
+    /// Scroll to a tool call of a message, or to the message if the tool
+    /// call's lines aren't known yet.
+    pub fn scroll_to_tool(&mut self, message_index: usize, tool_index: usize) {
+        self.scroll_to_message(message_index);
+        let Some(id) = self
+            .messages
+            .get(message_index)
+            .and_then(|m| m.tool_calls.get(tool_index))
+            .map(|t| t.id.as_str())
+        else {
+            return;
+        };
+        if let Some(line) = self
+            .rendered_cache
+            .tool_lines
+            .get(&message_index)
+            .and_then(|lines| lines.iter().find(|l| l.id == id))
+        {
+            self.scroll += line.start;
+        }
     }
 
     pub fn set_focused(&mut self, focused: bool) {
@@ -1328,6 +1841,17 @@ impl MessagesWidget {
         }
     }
 
+    /// Get the index and the selected message.
+    pub fn selected_message(&self) -> Option<(usize, &DisplayMessage)> {
+        if !self.selection.active {
+            return None;
+        }
+        let index = self.selection.message_index;
+        self.messages[..self.visible_count()]
+            .get(index)
+            .map(|msg| (index, msg))
+    }
+
     /// Handle a click at the given terminal coordinates.
     /// Returns the code content if a code block or inline code was clicked, None otherwise.
     pub fn handle_click(&self, x: u16, y: u16) -> Option<String> {
@@ -1675,6 +2199,19 @@ impl MessagesWidget {
         }
     }
 
+    /// Get the index of the first hidden message, if messages are hidden.
+    pub fn revert_point(&self) -> Option<usize> {
+        self.revert_index
+    }
+
+    /// Hide the messages from `index` on until they are committed with
+    /// [`Self::commit_revert`], or show all messages again with `None`.
+    pub fn set_revert_point(&mut self, index: Option<usize>) {
+        self.revert_index = index.filter(|&i| i < self.messages.len());
+        self.selection.active = false;
+        self.invalidate_render_cache();
+    }
+
     /// Check if we're in a reverted state.
     pub fn is_reverted(&self) -> bool {
         self.revert_index.is_some()
@@ -1703,6 +2240,8 @@ impl MessagesWidget {
             if width_changed {
                 // Width changed - all cached renders are invalid
                 self.rendered_cache.message_lines.clear();
+                self.rendered_cache.tool_lines.clear();
+                self.rendered_cache.image_lines.clear();
                 self.streaming_cache.clear();
             }
             self.rendered_cache
@@ -1819,10 +2358,21 @@ impl MessagesWidget {
             if self.rendered_cache.message_lines[idx].is_empty() {
                 let msg = &self.messages[idx];
                 let mut msg_lines: Vec<Line<'static>> = Vec::new();
-                self.render_message(&mut msg_lines, msg, theme, is_selected);
+                let mut tool_lines = Vec::new();
+                let mut image_lines = Vec::new();
+                self.render_message(
+                    &mut msg_lines,
+                    &mut tool_lines,
+                    &mut image_lines,
+                    msg,
+                    theme,
+                    is_selected,
+                );
                 msg_lines.push(Line::from("")); // Spacing
 
                 self.rendered_cache.message_lines[idx] = msg_lines;
+                self.rendered_cache.tool_lines.insert(idx, tool_lines);
+                self.rendered_cache.image_lines.insert(idx, image_lines);
                 any_rendered = true;
             }
 
@@ -1858,6 +2408,7 @@ impl MessagesWidget {
             0
         };
 
+        let mut streaming_start = None;
         let (lines, lines_skipped) = if self.streaming {
             // During streaming: simpler approach without line-level virtualization
             // This ensures scrolling works correctly while content is being added
@@ -1875,12 +2426,15 @@ impl MessagesWidget {
             for idx in start_msg..end_msg {
                 if let Some(msg_lines) = self.rendered_cache.message_lines.get(idx) {
                     if !msg_lines.is_empty() {
+                        let from = lines.len();
                         lines.extend(msg_lines.iter().cloned());
+                        self.highlight_search(&mut lines[from..], idx, theme);
                     }
                 }
             }
 
             // Add streaming content
+            streaming_start = Some(lines_above + lines.len());
             self.render_streaming_lines_cached(&mut lines, theme);
 
             // Bottom padding
@@ -1915,7 +2469,9 @@ impl MessagesWidget {
                                 (skip_lines + take_lines - current_line).min(msg_line_count);
 
                             if start_in_msg < end_in_msg {
+                                let from = lines.len();
                                 lines.extend(msg_lines[start_in_msg..end_in_msg].iter().cloned());
+                                self.highlight_search(&mut lines[from..], idx, theme);
                                 if start_in_msg > 0 {
                                     lines_skipped += start_in_msg;
                                 }
@@ -2016,6 +2572,34 @@ impl MessagesWidget {
             .scroll((safe_scroll_offset.min(u16::MAX as usize) as u16, 0));
         frame.render_widget(paragraph, area);
 
+        // Inline images, drawn over the lines reserved for them when fully in view
+        if self.show_images && self.images.protocol() != ImageProtocol::None {
+            let top = adjusted_lines_above + safe_scroll_offset;
+            let cumulative = &self.rendered_cache.cumulative_lines;
+            let mut placements: Vec<(usize, &ImageLines)> = Vec::new();
+            for idx in start_msg..end_msg {
+                let msg_start = if idx > 0 { cumulative[idx - 1] } else { 0 };
+                if let Some(images) = self.rendered_cache.image_lines.get(&idx) {
+                    placements.extend(images.iter().map(|i| (msg_start + i.start, i)));
+                }
+            }
+            if let Some(start) = streaming_start {
+                placements.extend(self.streaming_images.iter().map(|i| (start + i.start, i)));
+            }
+            for (line, image) in placements {
+                let fits_x = IMAGE_INDENT + image.cols <= area.width;
+                if fits_x && line >= top && line + image.rows as usize <= top + visible_height {
+                    let rect = Rect::new(
+                        area.x + IMAGE_INDENT,
+                        area.y + (line - top) as u16,
+                        image.cols,
+                        image.rows,
+                    );
+                    self.images.draw(frame.buffer_mut(), rect, &image.image);
+                }
+            }
+        }
+
         // Scrollbar
         if actual_total > visible_height && self.focused {
             let scrollbar = Scrollbar::default()
@@ -2072,100 +2656,80 @@ impl MessagesWidget {
         (first_msg, last_msg, skip_lines)
     }
 
+    /// Highlight the search matches in the lines of a message.
+    fn highlight_search(&self, lines: &mut [Line<'static>], message_index: usize, theme: &Theme) {
+        let Some(query) = &self.search_query else {
+            return;
+        };
+        let style = if self.search_current == Some(message_index) {
+            Style::default()
+                .fg(theme.background)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.background).bg(theme.warning)
+        };
+        for line in lines {
+            highlight_matches(line, query, style);
+        }
+    }
+
     /// Render streaming lines with incremental caching.
     ///
-    /// Key optimization: We cache rendered lines and only re-render when text CHANGES
-    /// (not when it grows). For streaming, text typically only appends, so we can
-    /// often skip re-rendering entirely for segments that haven't changed.
+    /// Text segments only grow while streaming, so each keeps the lines of
+    /// its complete markdown blocks and only renders the text after them.
     fn render_streaming_lines_cached(&mut self, lines: &mut Vec<Line<'static>>, theme: &Theme) {
-        // Invalidate cache if width changed
-        if self.streaming_cache.width != self.render_width {
-            self.streaming_cache.clear();
-            self.streaming_cache.width = self.render_width;
-        }
-
         let mut text_segment_idx = 0;
         let mut total_cached_lines = 0usize;
+        let base = lines.len();
+        let mut images = Vec::new();
 
         for segment in &self.stream_segments {
             match segment {
                 StreamSegment::Text(text) => {
                     if !text.is_empty() {
-                        // Check cache for this segment
-                        let cached = self.streaming_cache.segment_cache.get(text_segment_idx);
-
-                        // Use cache if:
-                        // 1. We have a cache entry for this segment
-                        // 2. The cached length matches OR the text is a prefix extension
-                        //    (common case: streaming appends to existing text)
-                        let (use_cache, needs_rerender) = if let Some((cached_len, _)) = cached {
-                            if *cached_len == text.len() {
-                                (true, false) // Exact match - use cache
-                            } else {
-                                // Text changed - need to re-render
-                                // (Could optimize to only render new portion, but markdown
-                                // context makes this complex)
-                                (false, true)
-                            }
+                        if text_segment_idx >= self.streaming_cache.segment_cache.len() {
+                            self.streaming_cache
+                                .segment_cache
+                                .push(IncrementalMarkdown::new());
+                        }
+                        let markdown = &mut self.streaming_cache.segment_cache[text_segment_idx];
+                        markdown.update(text, theme, self.render_width, &self.render_settings);
+                        let indent = if self.render_settings.linear_output {
+                            ""
                         } else {
-                            (false, true) // No cache - need to render
+                            "  "
                         };
-
-                        if use_cache {
-                            let (_, cached_lines) =
-                                &self.streaming_cache.segment_cache[text_segment_idx];
-                            for line in cached_lines {
-                                let mut new_line = vec![Span::styled("  ", theme.text_style())];
-                                new_line.extend(line.spans.iter().cloned());
-                                lines.push(Line::from(new_line));
-                            }
-                            total_cached_lines += cached_lines.len();
-                        } else if needs_rerender {
-                            // Render the text
-                            let content_text = render_markdown_with_settings(
-                                text,
-                                theme,
-                                self.render_width,
-                                &self.render_settings,
-                            );
-                            let rendered_lines: Vec<Line<'static>> =
-                                content_text.lines.into_iter().collect();
-
-                            // Add to output
-                            for line in &rendered_lines {
-                                let mut new_line = vec![Span::styled("  ", theme.text_style())];
-                                new_line.extend(line.spans.iter().cloned());
-                                lines.push(Line::from(new_line));
-                            }
-
-                            total_cached_lines += rendered_lines.len();
-
-                            // Update cache
-                            if text_segment_idx < self.streaming_cache.segment_cache.len() {
-                                self.streaming_cache.segment_cache[text_segment_idx] =
-                                    (text.len(), rendered_lines);
-                            } else {
-                                self.streaming_cache
-                                    .segment_cache
-                                    .push((text.len(), rendered_lines));
-                            }
+                        for line in markdown.lines() {
+                            let mut new_line = vec![Span::styled(indent, theme.text_style())];
+                            new_line.extend(line.spans.iter().cloned());
+                            lines.push(Line::from(new_line));
                         }
+                        total_cached_lines += markdown.line_count();
                     }
                     text_segment_idx += 1;
                 }
                 StreamSegment::Tool(index) => {
                     if let Some(tool) = self.active_tools.get(*index) {
-                        self.render_tool_call(lines, tool, theme);
+                        self.render_tool_call(lines, &mut images, tool, theme);
                         total_cached_lines += 5; // Estimate for tool display
                     }
                 }
             }
         }
 
+        for image in &mut images {
+            image.start -= base;
+        }
+        self.streaming_images = images;
+
         self.streaming_cache.total_cached_lines = total_cached_lines;
         self.streaming_cache.valid = true;
 
         // Streaming cursor
+        if self.render_settings.linear_output {
+            return;
+        }
         lines.push(Line::from(vec![
             Span::styled("  ", theme.text_style()),
             Span::styled("▌", theme.primary_style()),
@@ -2175,10 +2739,17 @@ impl MessagesWidget {
     fn render_message(
         &self,
         lines: &mut Vec<Line<'static>>,
+        tool_lines: &mut Vec<ToolLines>,
+        image_lines: &mut Vec<ImageLines>,
         msg: &DisplayMessage,
         theme: &Theme,
         is_selected: bool,
     ) {
+        if self.render_settings.linear_output {
+            self.render_message_linear(lines, tool_lines, msg, theme, is_selected);
+            return;
+        }
+
         let agent_color = theme.agent_color(msg.agent);
 
         // When selected, add a visual indicator
@@ -2256,7 +2827,13 @@ impl MessagesWidget {
                                 }
                             }
                             MessageSegment::Tool(tool) => {
-                                self.render_tool_call(lines, tool, theme);
+                                let start = lines.len();
+                                self.render_tool_call(lines, image_lines, tool, theme);
+                                tool_lines.push(ToolLines {
+                                    start,
+                                    end: lines.len(),
+                                    id: tool.id.clone(),
+                                });
                             }
                         }
                     }
@@ -2281,12 +2858,23 @@ impl MessagesWidget {
 
                     // Tool calls (legacy)
                     for tool in &msg.tool_calls {
-                        self.render_tool_call(lines, tool, theme);
+                        let start = lines.len();
+                        self.render_tool_call(lines, image_lines, tool, theme);
+                        tool_lines.push(ToolLines {
+                            start,
+                            end: lines.len(),
+                            id: tool.id.clone(),
+                        });
                     }
                 }
 
                 // Completion indicator
-                if msg.model.is_some() || msg.duration.is_some() {
+                let usage = msg
+                    .usage
+                    .filter(|_| self.render_settings.message_usage)
+                    .map(|usage| usage.parts())
+                    .unwrap_or_default();
+                if msg.model.is_some() || msg.duration.is_some() || !usage.is_empty() {
                     let mut completion_spans = vec![
                         Span::styled("  ", theme.text_style()),
                         Span::styled("▣ ", Style::default().fg(agent_color)),
@@ -2303,6 +2891,11 @@ impl MessagesWidget {
                         completion_spans.push(Span::styled(duration.clone(), theme.muted_style()));
                     }
 
+                    for part in usage {
+                        completion_spans.push(Span::styled(" · ", theme.muted_style()));
+                        completion_spans.push(Span::styled(part, theme.muted_style()));
+                    }
+
                     lines.push(Line::from(completion_spans));
                 }
             }
@@ -2324,12 +2917,119 @@ impl MessagesWidget {
         }
     }
 
+    /// Render a message as plain linear text for screen readers: a line
+    /// naming the speaker, then the content without borders or indentation.
+    fn render_message_linear(
+        &self,
+        lines: &mut Vec<Line<'static>>,
+        tool_lines: &mut Vec<ToolLines>,
+        msg: &DisplayMessage,
+        theme: &Theme,
+        is_selected: bool,
+    ) {
+        let text_style = if is_selected {
+            theme.text_style().add_modifier(Modifier::REVERSED)
+        } else {
+            theme.text_style()
+        };
+        let speaker = match msg.role {
+            MessageRole::User => "You".to_string(),
+            MessageRole::Assistant => msg.agent.name().to_string(),
+            MessageRole::System => "System".to_string(),
+            MessageRole::Tool => "Tool".to_string(),
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{speaker}:"),
+            text_style.add_modifier(Modifier::BOLD),
+        )));
+
+        let mut push_tool = |lines: &mut Vec<Line<'static>>, tool: &DisplayToolCall| {
+            let start = lines.len();
+            lines.push(Self::linear_tool_line(tool, theme));
+            tool_lines.push(ToolLines {
+                start,
+                end: lines.len(),
+                id: tool.id.clone(),
+            });
+        };
+
+        if msg.role != MessageRole::Assistant {
+            for line in msg.content.lines() {
+                let line = Line::from(Span::styled(line.to_string(), text_style));
+                lines.extend(wrap_line(line, self.render_width));
+            }
+        } else if !msg.segments.is_empty() {
+            msg.ensure_segment_cache(self.render_width, theme, &self.render_settings);
+            let mut text_segment_idx = 0;
+            for segment in &msg.segments {
+                match segment {
+                    MessageSegment::Text(_) => {
+                        lines.extend(msg.get_segment_lines(text_segment_idx));
+                        text_segment_idx += 1;
+                    }
+                    MessageSegment::Tool(tool) => push_tool(lines, tool),
+                }
+            }
+        } else {
+            lines.extend(msg.get_or_render_content(
+                self.render_width,
+                theme,
+                &self.render_settings,
+            ));
+            for tool in &msg.tool_calls {
+                push_tool(lines, tool);
+            }
+        }
+
+        if msg.role == MessageRole::Assistant {
+            let mut details: Vec<String> = [msg.model.clone(), msg.duration.clone()]
+                .into_iter()
+                .flatten()
+                .collect();
+            if let Some(usage) = msg.usage.filter(|_| self.render_settings.message_usage) {
+                details.extend(usage.parts());
+            }
+            if !details.is_empty() {
+                lines.push(Line::from(Span::styled(
+                    format!("Done, {}.", details.join(", ")),
+                    theme.muted_style(),
+                )));
+            }
+        }
+    }
+
+    /// A tool call as a single line of plain text, such as
+    /// `Tool Read src/main.rs: done`.
+    fn linear_tool_line(tool: &DisplayToolCall, theme: &Theme) -> Line<'static> {
+        let (title, params) = tool_title(&tool.name, tool.input.as_deref(), tool.metadata.as_ref());
+        let status = match (tool.status, tool.progress.as_ref().and_then(|p| p.fraction)) {
+            (ToolStatus::Pending, _) => "pending".to_string(),
+            (ToolStatus::Running, Some(fraction)) => {
+                format!("running, {:.0}% done", fraction.clamp(0.0, 1.0) * 100.0)
+            }
+            (ToolStatus::Running, None) => "running".to_string(),
+            (ToolStatus::Success, _) => "done".to_string(),
+            (ToolStatus::Error, _) => "failed".to_string(),
+        };
+        let text = match params {
+            Some(params) => format!("Tool {title} ({params}): {status}"),
+            None => format!("Tool {title}: {status}"),
+        };
+        Line::from(Span::styled(text, theme.muted_style()))
+    }
+
     fn render_tool_call(
         &self,
         lines: &mut Vec<Line<'static>>,
+        images: &mut Vec<ImageLines>,
         tool: &DisplayToolCall,
         theme: &Theme,
     ) {
+        if self.render_settings.linear_output {
+            lines.push(Self::linear_tool_line(tool, theme));
+            return;
+        }
+
         let icon = tool_icon(&tool.name);
         let is_block = is_block_tool(&tool.name);
         let (title, params) = tool_title(&tool.name, tool.input.as_deref(), tool.metadata.as_ref());
@@ -2364,6 +3064,9 @@ impl MessagesWidget {
 
             // Tool-specific content
             self.render_block_tool_content(lines, tool, theme);
+            Self::render_live_output(lines, tool, theme, "  │ ");
+            Self::render_tool_progress(lines, tool, theme, "  │ ");
+            self.render_tool_images(lines, images, tool, theme, "  │ ");
 
             // Bottom border
             lines.push(Line::from(vec![Span::styled(
@@ -2386,6 +3089,111 @@ impl MessagesWidget {
             spans.push(Span::styled(" ", theme.text_style()));
             spans.push(Span::styled(status_icon, status_style));
             lines.push(Line::from(spans));
+            Self::render_live_output(lines, tool, theme, "    ");
+            Self::render_tool_progress(lines, tool, theme, "    ");
+            self.render_tool_images(lines, images, tool, theme, "    ");
+        }
+    }
+
+    /// Add the last lines of output of a running tool.
+    fn render_live_output(
+        lines: &mut Vec<Line<'static>>,
+        tool: &DisplayToolCall,
+        theme: &Theme,
+        prefix: &'static str,
+    ) {
+        if tool.status != ToolStatus::Running {
+            return;
+        }
+        for line in tool.live_output.lines() {
+            // A carriage return redraws the line, as progress bars do
+            let line = line.rsplit('\r').next().unwrap_or(line);
+            let text: String = line.chars().filter(|c| !c.is_control()).collect();
+            lines.push(Line::from(vec![
+                Span::styled(prefix, theme.tool_border_style()),
+                Span::styled(text, theme.dim_style()),
+            ]));
+        }
+    }
+
+    /// Add the progress of a running tool: a bar if the share done is known,
+    /// what the tool is doing, and how to cancel it.
+    fn render_tool_progress(
+        lines: &mut Vec<Line<'static>>,
+        tool: &DisplayToolCall,
+        theme: &Theme,
+        prefix: &'static str,
+    ) {
+        let Some(progress) = tool
+            .progress
+            .as_ref()
+            .filter(|_| tool.status == ToolStatus::Running)
+        else {
+            return;
+        };
+        let mut spans = vec![Span::styled(prefix, theme.tool_border_style())];
+        if let Some(fraction) = progress.fraction {
+            let fraction = fraction.clamp(0.0, 1.0);
+            let filled = (fraction * PROGRESS_BAR_WIDTH as f64).round() as usize;
+            spans.push(Span::styled("█".repeat(filled), theme.accent_style()));
+            spans.push(Span::styled(
+                "░".repeat(PROGRESS_BAR_WIDTH - filled),
+                theme.dim_style(),
+            ));
+            spans.push(Span::styled(
+                format!(" {:>3.0}% ", fraction * 100.0),
+                theme.text_style(),
+            ));
+        }
+        if let Some(message) = &progress.message {
+            spans.push(Span::styled(format!("{message} "), theme.muted_style()));
+        }
+        spans.push(Span::styled(
+            "· press x to cancel this tool",
+            theme.dim_style(),
+        ));
+        lines.push(Line::from(spans));
+    }
+
+    /// Add the images of a tool's result: a label line for each, followed by
+    /// blank lines the image is drawn over when the terminal supports it.
+    fn render_tool_images(
+        &self,
+        lines: &mut Vec<Line<'static>>,
+        images: &mut Vec<ImageLines>,
+        tool: &DisplayToolCall,
+        theme: &Theme,
+        prefix: &'static str,
+    ) {
+        let Some(tool_images) = self.tool_images.get(&tool.id) else {
+            return;
+        };
+        let drawable = self.images.protocol() != ImageProtocol::None;
+        let max_cols =
+            (self.render_width.min(u16::MAX as usize) as u16).saturating_sub(IMAGE_INDENT + 2);
+        for image in tool_images {
+            let (width, height) = image.dimensions();
+            lines.push(Line::from(vec![
+                Span::styled(prefix, theme.tool_border_style()),
+                Span::styled("▣ ", theme.accent_style()),
+                Span::styled(
+                    format!("{} {width}×{height}", image.media_type()),
+                    theme.dim_style(),
+                ),
+            ]));
+            if !drawable || max_cols == 0 {
+                continue;
+            }
+            let (cols, rows) = image.size_in_cells(max_cols, self.images.cell_size());
+            images.push(ImageLines {
+                start: lines.len(),
+                cols,
+                rows,
+                image: Arc::clone(image),
+            });
+            for _ in 0..rows {
+                lines.push(Line::from(Span::styled(prefix, theme.tool_border_style())));
+            }
         }
     }
 
@@ -2535,6 +3343,149 @@ impl MessagesWidget {
                 tool.expanded = any_collapsed;
             }
         }
+
+        self.invalidate_message(message_index);
+    }
+
+    /// Toggle the tool call at the given terminal coordinates between
+    /// collapsed and expanded.
+    /// Returns true if a tool call was clicked.
+    pub fn toggle_tool_at(&mut self, x: u16, y: u16) -> bool {
+        let area = self.last_render_area;
+        if x < area.x || x >= area.x + area.width || y < area.y || y >= area.y + area.height {
+            return false;
+        }
+        let line = self.last_render_scroll + (y - area.y) as usize;
+
+        // Find the message the line belongs to
+        let cumulative = &self.rendered_cache.cumulative_lines;
+        let idx = cumulative.partition_point(|&end| end <= line);
+        let rendered = self
+            .rendered_cache
+            .message_lines
+            .get(idx)
+            .is_some_and(|lines| !lines.is_empty());
+        if idx >= self.visible_count() || !rendered {
+            return false;
+        }
+        let line_in_msg = line - if idx > 0 { cumulative[idx - 1] } else { 0 };
+
+        let Some(id) = self
+            .rendered_cache
+            .tool_lines
+            .get(&idx)
+            .and_then(|tools| {
+                tools
+                    .iter()
+                    .find(|t| line_in_msg >= t.start && line_in_msg < t.end)
+            })
+            .map(|t| t.id.clone())
+        else {
+            return false;
+        };
+
+        let msg = &mut self.messages[idx];
+        let expanded = !msg
+            .tool_calls
+            .iter()
+            .chain(msg.segments.iter().filter_map(|s| match s {
+                MessageSegment::Tool(tool) => Some(tool),
+                MessageSegment::Text(_) => None,
+            }))
+            .any(|t| t.id == id && t.expanded);
+        for tool in msg.tool_calls.iter_mut().filter(|t| t.id == id) {
+            tool.expanded = expanded;
+        }
+        for segment in &mut msg.segments {
+            if let MessageSegment::Tool(ref mut tool) = segment {
+                if tool.id == id {
+                    tool.expanded = expanded;
+                }
+            }
+        }
+
+        self.invalidate_message(idx);
+        true
+    }
+
+    /// The last file-changing tool call at or above the bottom of the view,
+    /// for showing next to the messages.
+    ///
+    /// While streaming with the view at the bottom, this is the latest such
+    /// call of the response in progress.
+    pub fn edit_in_view(&self) -> Option<&DisplayToolCall> {
+        let bottom = self.last_render_scroll + self.last_render_area.height as usize;
+        let cumulative = &self.rendered_cache.cumulative_lines;
+        let count = self.visible_count().min(cumulative.len());
+        let messages_end = cumulative[..count].last().copied().unwrap_or(0);
+
+        if self.streaming && bottom > messages_end {
+            if let Some(tool) = self.active_tools.iter().rev().find(|t| t.is_edit()) {
+                return Some(tool);
+            }
+        }
+        if count == 0 || bottom == 0 {
+            return None;
+        }
+
+        // The message at the bottom of the view, then the ones above it
+        let last = cumulative[..count]
+            .partition_point(|&end| end < bottom)
+            .min(count - 1);
+        for idx in (0..=last).rev() {
+            let start = if idx > 0 { cumulative[idx - 1] } else { 0 };
+            let tool_lines = self.rendered_cache.tool_lines.get(&idx);
+            let found = message_tools(&self.messages[idx])
+                .filter(|tool| tool.is_edit())
+                .filter(|tool| {
+                    let offset = tool_lines
+                        .and_then(|lines| lines.iter().find(|l| l.id == tool.id))
+                        .map_or(0, |l| l.start);
+                    start + offset < bottom
+                })
+                .last();
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+
+    /// Scroll to the last file-changing tool call whose path `edited`
+    /// accepts, and return it.
+    pub fn scroll_to_edit(&mut self, edited: impl Fn(&str) -> bool) -> Option<&DisplayToolCall> {
+        let (idx, id) = (0..self.visible_count()).rev().find_map(|idx| {
+            message_tools(&self.messages[idx])
+                .filter(|tool| tool.is_edit() && tool.edited_path().is_some_and(|p| edited(&p)))
+                .last()
+                .map(|tool| (idx, tool.id.clone()))
+        })?;
+
+        let cumulative = &self.rendered_cache.cumulative_lines;
+        if idx < cumulative.len() {
+            let start = if idx > 0 { cumulative[idx - 1] } else { 0 };
+            let offset = self
+                .rendered_cache
+                .tool_lines
+                .get(&idx)
+                .and_then(|lines| lines.iter().find(|l| l.id == id))
+                .map_or(0, |l| l.start);
+            self.scroll = start + offset;
+        } else {
+            self.scroll_to_message(idx);
+        }
+        self.auto_scroll = false;
+        message_tools(&self.messages[idx]).find(|tool| tool.id == id)
+    }
+
+    /// Drop the cached lines of a message so it's rendered again.
+    fn invalidate_message(&mut self, idx: usize) {
+        if let Some(lines) = self.rendered_cache.message_lines.get_mut(idx) {
+            lines.clear();
+        }
+        self.rendered_cache.valid = false;
+        self.line_count_cache = None;
+        self.dirty = true;
     }
 
     /// Toggle expansion of tools in the currently selected message (in selection mode).
@@ -3143,6 +4094,500 @@ mod tests {
         assert!(result.contains("truncated"));
     }
 
+    // === Tool click tests ===
+
+    fn render_widget(widget: &mut MessagesWidget) {
+        let backend = ratatui::backend::TestBackend::new(80, 40);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let theme = Theme::default();
+        terminal
+            .draw(|frame| widget.render(frame, frame.area(), &theme))
+            .unwrap();
+    }
+
+    fn tool_expanded(widget: &MessagesWidget, id: &str) -> bool {
+        widget.messages[1].segments.iter().any(|s| match s {
+            MessageSegment::Tool(tool) => tool.id == id && tool.expanded,
+            MessageSegment::Text(_) => false,
+        })
+    }
+
+    #[test]
+    fn test_toggle_tool_at() {
+        let mut widget = MessagesWidget::new();
+        widget.add_message(DisplayMessage::user("List the files"));
+        let mut tool = DisplayToolCall::new("call-1", "bash");
+        tool.status = ToolStatus::Success;
+        tool.output = Some((1..=30).map(|i| format!("file{i}\n")).collect());
+        widget.add_message(DisplayMessage::assistant_with_segments(vec![
+            MessageSegment::Text("Here they are:".to_string()),
+            MessageSegment::Tool(tool),
+        ]));
+        render_widget(&mut widget);
+
+        let tools = widget.rendered_cache.tool_lines.get(&1).unwrap();
+        assert_eq!(tools.len(), 1);
+        let row = (widget.rendered_cache.cumulative_lines[0] + tools[0].start) as u16;
+
+        // Clicking the user message does nothing
+        assert!(!widget.toggle_tool_at(5, 0));
+        assert!(!tool_expanded(&widget, "call-1"));
+
+        assert!(widget.toggle_tool_at(5, row));
+        assert!(tool_expanded(&widget, "call-1"));
+        assert!(widget.rendered_cache.message_lines[1].is_empty());
+
+        render_widget(&mut widget);
+        assert!(widget.toggle_tool_at(5, row));
+        assert!(!tool_expanded(&widget, "call-1"));
+
+        // Outside the widget
+        assert!(!widget.toggle_tool_at(200, row));
+    }
+
+    #[test]
+    fn test_linear_output() {
+        let mut widget = MessagesWidget::with_render_settings(RenderSettings::screen_reader());
+        widget.add_message(DisplayMessage::user("List the files"));
+        let mut tool = DisplayToolCall::new("call-1", "bash");
+        tool.status = ToolStatus::Success;
+        widget.add_message(DisplayMessage::assistant_with_segments(vec![
+            MessageSegment::Text("Here they are:".to_string()),
+            MessageSegment::Tool(tool),
+        ]));
+        render_widget(&mut widget);
+
+        let text: Vec<String> = widget
+            .rendered_cache
+            .message_lines
+            .iter()
+            .flatten()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(text[..3], ["You:", "List the files", ""]);
+        assert_eq!(text[3], "Build:");
+        assert_eq!(text[4], "Here they are:");
+        assert!(text[5].starts_with("Tool ") && text[5].ends_with(": done"));
+        assert!(text.iter().all(|line| !line.contains('┃')));
+
+        let tools = widget.rendered_cache.tool_lines.get(&1).unwrap();
+        assert_eq!(tools[0].start, 2);
+    }
+
+    #[test]
+    fn test_message_usage_parts() {
+        let usage = MessageUsage {
+            input_tokens: 12_345,
+            output_tokens: 845,
+            cost: 0.0123,
+        };
+        assert_eq!(usage.parts(), ["12.3K in", "845 out", "$0.0123"]);
+
+        let free = MessageUsage {
+            input_tokens: 2_500_000,
+            ..Default::default()
+        };
+        assert_eq!(free.parts(), ["2.5M in", "0 out"]);
+    }
+
+    #[test]
+    fn test_message_usage_annotation() {
+        fn completion_line(settings: RenderSettings) -> String {
+            let mut widget = MessagesWidget::with_render_settings(settings);
+            let mut msg = DisplayMessage::assistant("Done");
+            msg.model = Some("anthropic/claude".to_string());
+            msg.duration = Some("4.2s".to_string());
+            msg.usage = Some(MessageUsage {
+                input_tokens: 1_200,
+                output_tokens: 300,
+                cost: 0.5,
+            });
+            widget.add_message(msg);
+            render_widget(&mut widget);
+            widget
+                .rendered_cache
+                .message_lines
+                .iter()
+                .flatten()
+                .map(|line| line.to_string())
+                .rfind(|line| !line.trim().is_empty())
+                .unwrap()
+        }
+
+        let line = completion_line(RenderSettings::default());
+        assert!(line.ends_with("anthropic/claude · 4.2s · 1.2K in · 300 out · $0.5000"));
+
+        let line = completion_line(RenderSettings {
+            message_usage: false,
+            ..Default::default()
+        });
+        assert!(line.ends_with("anthropic/claude · 4.2s"));
+
+        let line = completion_line(RenderSettings::screen_reader());
+        assert_eq!(
+            line,
+            "Done, anthropic/claude, 4.2s, 1.2K in, 300 out, $0.5000."
+        );
+    }
+
+    #[test]
+    fn test_tool_progress_can_be_cancelled() {
+        let mut widget = MessagesWidget::new();
+        widget.start_streaming();
+        widget.add_tool_call_with_input("t1".to_string(), "bash".to_string(), "{}".to_string());
+        widget.add_tool_call_with_input("t2".to_string(), "read".to_string(), "{}".to_string());
+        assert_eq!(widget.cancellable_tool(), None);
+
+        let progress = ToolProgress {
+            fraction: Some(0.25),
+            message: Some("1/4 tests run".to_string()),
+        };
+        widget.set_tool_progress("t1", progress.clone());
+        assert_eq!(widget.cancellable_tool(), Some("t1"));
+
+        let theme = Theme::default();
+        let mut lines = Vec::new();
+        MessagesWidget::render_tool_progress(&mut lines, &widget.active_tools[0], &theme, "  │ ");
+        assert_eq!(
+            lines[0].to_string(),
+            "  │ █████░░░░░░░░░░░░░░░  25% 1/4 tests run · press x to cancel this tool"
+        );
+        assert_eq!(
+            MessagesWidget::linear_tool_line(&widget.active_tools[0], &theme).to_string(),
+            "Tool Shell: running, 25% done"
+        );
+
+        // Finished tools drop their progress and can't be cancelled
+        widget.update_tool_status("t1", ToolStatus::Success, None);
+        assert_eq!(widget.cancellable_tool(), None);
+        widget.set_tool_progress("t1", progress);
+        assert!(widget.active_tools[0].progress.is_none());
+        lines.clear();
+        MessagesWidget::render_tool_progress(&mut lines, &widget.active_tools[0], &theme, "  │ ");
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_live_output_keeps_last_lines() {
+        let mut widget = MessagesWidget::new();
+        widget.start_streaming();
+        widget.add_tool_call_with_input("t1".to_string(), "bash".to_string(), "{}".to_string());
+        for i in 1..=8 {
+            widget.append_tool_output("t1", &format!("line {i}\n"));
+        }
+        widget.append_tool_output("t1", "Building [==> ]\rBuilding [===>]");
+
+        let theme = Theme::default();
+        let mut lines = Vec::new();
+        MessagesWidget::render_live_output(&mut lines, &widget.active_tools[0], &theme, "  │ ");
+        let lines: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            [
+                "  │ line 4",
+                "  │ line 5",
+                "  │ line 6",
+                "  │ line 7",
+                "  │ line 8",
+                "  │ Building [===>]",
+            ]
+        );
+
+        let mut output = "x".repeat(LIVE_OUTPUT_LINE_LEN + 10);
+        keep_last_lines(&mut output, LIVE_OUTPUT_LINES);
+        assert_eq!(output.len(), LIVE_OUTPUT_LINE_LEN);
+
+        // Finished tools show their result instead
+        widget.update_tool_status("t1", ToolStatus::Success, None);
+        assert!(widget.active_tools[0].live_output.is_empty());
+        widget.append_tool_output("t1", "late\n");
+        assert!(widget.active_tools[0].live_output.is_empty());
+    }
+
+    fn edit_tool(id: &str, path: &str) -> DisplayToolCall {
+        let mut tool = DisplayToolCall::new(id, "edit");
+        tool.status = ToolStatus::Success;
+        tool.input = Some(
+            serde_json::json!({ "filePath": path, "oldString": "a", "newString": "b" }).to_string(),
+        );
+        tool.output = Some(format!("--- a/{path}\n+++ b/{path}\n-a\n+b\n"));
+        tool.metadata = Some(serde_json::json!({ "file": path }));
+        tool
+    }
+
+    #[test]
+    fn test_edit_in_view_follows_scroll() {
+        let mut widget = MessagesWidget::new();
+        widget.add_message(DisplayMessage::user("Fix both files"));
+        widget.add_message(DisplayMessage::assistant_with_segments(vec![
+            MessageSegment::Tool(edit_tool("call-1", "src/a.rs")),
+        ]));
+        for i in 0..20 {
+            widget.add_message(DisplayMessage::assistant(format!("Step {i}")));
+        }
+        widget.add_message(DisplayMessage::assistant_with_segments(vec![
+            MessageSegment::Text("Now the second one".to_string()),
+            MessageSegment::Tool(edit_tool("call-2", "src/b.rs")),
+        ]));
+
+        widget.scroll_to_bottom();
+        render_widget(&mut widget);
+        assert_eq!(widget.edit_in_view().unwrap().id, "call-2");
+
+        widget.scroll_up(usize::MAX);
+        render_widget(&mut widget);
+        assert_eq!(widget.edit_in_view().unwrap().id, "call-1");
+
+        // While streaming at the bottom, the response in progress wins
+        widget.start_streaming();
+        widget.add_tool_call_with_input(
+            "call-3".to_string(),
+            "write".to_string(),
+            serde_json::json!({ "filePath": "src/c.rs", "content": "fn c() {}" }).to_string(),
+        );
+        render_widget(&mut widget);
+        let tool = widget.edit_in_view().unwrap();
+        assert_eq!(tool.id, "call-3");
+        assert_eq!(tool.edited_path().as_deref(), Some("src/c.rs"));
+        assert_eq!(tool.edit_diff().as_deref(), Some("+fn c() {}\n"));
+    }
+
+    #[test]
+    fn test_scroll_to_edit() {
+        let mut widget = MessagesWidget::new();
+        widget.add_message(DisplayMessage::assistant_with_segments(vec![
+            MessageSegment::Tool(edit_tool("call-1", "src/a.rs")),
+        ]));
+        for i in 0..20 {
+            widget.add_message(DisplayMessage::assistant(format!("Step {i}")));
+        }
+        widget.add_message(DisplayMessage::assistant_with_segments(vec![
+            MessageSegment::Tool(edit_tool("call-2", "src/b.rs")),
+        ]));
+        widget.scroll_to_bottom();
+        render_widget(&mut widget);
+
+        let tool = widget.scroll_to_edit(|p| p == "src/a.rs").unwrap();
+        assert_eq!(tool.id, "call-1");
+        render_widget(&mut widget);
+        assert_eq!(widget.edit_in_view().unwrap().id, "call-1");
+        assert!(widget.scroll_to_edit(|p| p == "src/c.rs").is_none());
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        let mark = Style::default().add_modifier(Modifier::REVERSED);
+        let mut line = Line::from(vec![
+            Span::raw("Error: the ERROR log "),
+            Span::styled("no errors", Style::default().fg(ratatui::style::Color::Red)),
+        ]);
+        highlight_matches(&mut line, "error", mark);
+        let spans: Vec<(&str, bool)> = line
+            .spans
+            .iter()
+            .map(|s| {
+                (
+                    s.content.as_ref(),
+                    s.style.add_modifier.contains(Modifier::REVERSED),
+                )
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Error", true),
+                (": the ", false),
+                ("ERROR", true),
+                (" log ", false),
+                ("no ", false),
+                ("error", true),
+                ("s", false),
+            ]
+        );
+        // Highlighting keeps the span's own style
+        assert_eq!(line.spans[5].style.fg, Some(ratatui::style::Color::Red));
+
+        let mut line = Line::from("Größe grösse");
+        highlight_matches(&mut line, "GRÖ", mark);
+        assert_eq!(line.spans[0].content, "Grö");
+        assert_eq!(line.spans[2].content, "grö");
+    }
+
+    #[test]
+    fn test_scroll_to_message() {
+        let mut widget = MessagesWidget::new();
+        for i in 0..30 {
+            widget.add_message(DisplayMessage::assistant(format!(
+                "Line one {i}\n\nLine two"
+            )));
+        }
+        widget.scroll_to_bottom();
+        render_widget(&mut widget);
+
+        widget.scroll_to_message(12);
+        assert_eq!(widget.scroll, widget.rendered_cache.cumulative_lines[11]);
+        widget.set_search_highlight(Some("one 12"), Some(12));
+        let text = draw_text(&mut widget);
+        assert!(text.contains("Line one 12"));
+        assert_eq!(widget.search_query.as_deref(), Some("one 12"));
+
+        widget.set_search_highlight(Some(""), None);
+        assert!(widget.search_query.is_none());
+    }
+
+    #[test]
+    fn test_select_and_revert_to_prompt() {
+        let mut widget = MessagesWidget::new();
+        widget.add_message(DisplayMessage::user("first").with_id("msg_1"));
+        widget.add_message(DisplayMessage::assistant("answer"));
+        widget.add_message(DisplayMessage::user("second"));
+        widget.add_message(DisplayMessage::assistant("answer"));
+
+        assert!(widget.selected_message().is_none());
+        widget.enter_selection_mode();
+        widget.select_prev_message();
+        widget.select_prev_message();
+        widget.select_prev_message();
+        let (index, msg) = widget.selected_message().unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(msg.id.as_deref(), Some("msg_1"));
+
+        widget.set_revert_point(Some(0));
+        assert_eq!(widget.visible_count(), 0);
+        assert!(!widget.is_selecting());
+        widget.set_revert_point(None);
+        assert_eq!(widget.visible_count(), 4);
+
+        widget.set_revert_point(Some(2));
+        widget.commit_revert();
+        assert_eq!(widget.get_messages().len(), 2);
+        widget.set_revert_point(Some(5));
+        assert_eq!(widget.revert_point(), None);
+    }
+
+    #[test]
+    fn test_transcript_with_and_without_tool_output() {
+        let mut widget = MessagesWidget::new();
+        assert!(widget.get_transcript(true).is_none());
+
+        let mut tool = DisplayToolCall::new("call_1", "read");
+        tool.input = Some(r#"{"path":"a.rs"}"#.to_string());
+        tool.output = Some("fn main() {}".to_string());
+        tool.status = ToolStatus::Success;
+        widget.add_message(DisplayMessage::user("show a.rs").with_id("msg_1"));
+        widget.add_message(DisplayMessage::assistant_with_segments(vec![
+            MessageSegment::Text("Here it is".to_string()),
+            MessageSegment::Tool(tool),
+        ]));
+
+        let full = widget.get_transcript(true).unwrap();
+        assert!(full.contains("### Tool: read"));
+        assert!(full.contains("fn main() {}"));
+        let short = widget.get_transcript(false).unwrap();
+        assert!(short.contains("### Tool: read"));
+        assert!(!short.contains("fn main() {}"));
+
+        let json = widget.get_transcript_json(true);
+        assert_eq!(json.len(), 2);
+        assert_eq!(json[0]["id"], "msg_1");
+        assert_eq!(json[1]["role"], "assistant");
+        assert_eq!(json[1]["tool_calls"][0]["input"]["path"], "a.rs");
+        assert_eq!(json[1]["tool_calls"][0]["status"], "success");
+        assert_eq!(json[1]["tool_calls"][0]["output"], "fn main() {}");
+        let json = widget.get_transcript_json(false);
+        assert!(json[1]["tool_calls"][0].get("output").is_none());
+
+        // Messages after a pending revert are left out
+        widget.set_revert_point(Some(1));
+        assert_eq!(widget.get_transcript_json(true).len(), 1);
+    }
+
+    #[test]
+    fn test_edit_diff_before_output() {
+        let mut tool = DisplayToolCall::new("call-1", "edit");
+        assert!(tool.is_edit());
+        assert!(tool.edit_diff().is_none());
+        tool.input = Some(
+            serde_json::json!({ "filePath": "x.rs", "oldString": "a\nb", "newString": "c" })
+                .to_string(),
+        );
+        assert_eq!(tool.edit_diff().as_deref(), Some("-a\n-b\n+c\n"));
+        assert_eq!(tool.edited_path().as_deref(), Some("x.rs"));
+
+        let mut patch = DisplayToolCall::new("call-2", "patch");
+        patch.input = Some(
+            serde_json::json!({
+                "patch_text": "*** Begin Patch\n*** Update File: src/lib.rs\n-a\n+b\n*** End Patch"
+            })
+            .to_string(),
+        );
+        assert_eq!(patch.edited_path().as_deref(), Some("src/lib.rs"));
+        assert!(!DisplayToolCall::new("call-3", "read").is_edit());
+    }
+
+    /// A 1x1 PNG.
+    const PIXEL_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==";
+
+    fn draw_text(widget: &mut MessagesWidget) -> String {
+        let backend = ratatui::backend::TestBackend::new(80, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let theme = Theme::default();
+        terminal
+            .draw(|frame| widget.render(frame, frame.area(), &theme))
+            .unwrap();
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect()
+    }
+
+    #[test]
+    fn test_tool_result_images() {
+        let mut widget = MessagesWidget::new();
+        widget.set_image_protocol(ImageProtocol::Kitty);
+        widget.start_streaming();
+        widget.add_tool_call_with_input(
+            "call-1".to_string(),
+            "mcp__browser__screenshot".to_string(),
+            "{}".to_string(),
+        );
+        widget.update_tool_status_with_metadata(
+            "call-1",
+            ToolStatus::Success,
+            Some("[Image: 68 bytes, type: image/png]".to_string()),
+            Some(serde_json::json!({
+                "images": [
+                    { "mimeType": "image/png", "data": PIXEL_PNG },
+                    { "mimeType": "image/png", "data": "bm90IGFuIGltYWdl" }
+                ]
+            })),
+        );
+        // The image data is moved out of the metadata; undecodable images are skipped
+        assert_eq!(widget.active_tools[0].metadata, Some(serde_json::json!({})));
+        assert_eq!(widget.tool_images["call-1"].len(), 1);
+
+        let text = draw_text(&mut widget);
+        assert!(text.contains("image/png 1×1"));
+        assert!(text.contains("\x1b_Ga=T,f=100"));
+        assert_eq!(widget.finish_image_frame(), "");
+
+        // Hidden images are removed from the terminal
+        widget.set_show_images(false);
+        let text = draw_text(&mut widget);
+        assert!(!text.contains("\x1b_G"));
+        assert!(widget.finish_image_frame().contains("a=d"));
+
+        // Without a graphics protocol only the placeholder is shown
+        widget.set_show_images(true);
+        widget.set_image_protocol(ImageProtocol::None);
+        let text = draw_text(&mut widget);
+        assert!(text.contains("image/png 1×1"));
+        assert!(!text.contains("\x1b_G"));
+    }
+
     // === Selection mode tests ===
 
     #[test]
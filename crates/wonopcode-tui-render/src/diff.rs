@@ -194,6 +194,8 @@ pub struct DiffWidget {
     hunk_positions: Vec<(usize, usize, usize)>, // (file_idx, hunk_idx, line_pos)
     /// Display style (unified or side-by-side).
     style: DiffStyle,
+    /// Label shown after each hunk header, by global hunk index.
+    hunk_labels: Vec<Option<String>>,
 }
 
 impl DiffWidget {
@@ -205,6 +207,7 @@ impl DiffWidget {
     /// Set the diffs.
     pub fn set_diffs(&mut self, diffs: Vec<FileDiff>) {
         self.diffs = diffs;
+        self.hunk_labels.clear();
         self.update_hunk_positions();
         self.current_file = 0;
         self.current_hunk = 0;
@@ -213,6 +216,7 @@ impl DiffWidget {
     /// Parse and set from unified diff string.
     pub fn set_unified_diff(&mut self, diff: &str) {
         self.diffs = FileDiff::parse_unified(diff);
+        self.hunk_labels.clear();
         self.update_hunk_positions();
         self.current_file = 0;
         self.current_hunk = 0;
@@ -288,6 +292,45 @@ impl DiffWidget {
             .unwrap_or(0)
     }
 
+    /// Get the current file and hunk within it.
+    pub fn current_position(&self) -> (usize, usize) {
+        (self.current_file, self.current_hunk)
+    }
+
+    /// Get the diffs.
+    pub fn diffs(&self) -> &[FileDiff] {
+        &self.diffs
+    }
+
+    /// Jump to a hunk by its global index.
+    pub fn select_hunk(&mut self, index: usize) {
+        if let Some(&(file_idx, hunk_idx, line_pos)) = self.hunk_positions.get(index) {
+            self.current_file = file_idx;
+            self.current_hunk = hunk_idx;
+            self.scroll = line_pos;
+        }
+    }
+
+    /// Set the label shown after a hunk's header, by global hunk index.
+    pub fn set_hunk_label(&mut self, index: usize, label: Option<String>) {
+        if index >= self.hunk_positions.len() {
+            return;
+        }
+        if self.hunk_labels.len() < self.hunk_positions.len() {
+            self.hunk_labels.resize(self.hunk_positions.len(), None);
+        }
+        self.hunk_labels[index] = label;
+    }
+
+    /// The label of a hunk, by file and hunk within it.
+    fn hunk_label(&self, file_idx: usize, hunk_idx: usize) -> Option<&str> {
+        let index = self
+            .hunk_positions
+            .iter()
+            .position(|(f, h, _)| *f == file_idx && *h == hunk_idx)?;
+        self.hunk_labels.get(index)?.as_deref()
+    }
+
     /// Jump to the next hunk.
     pub fn next_hunk(&mut self) {
         let current_idx = self.current_hunk_index();
@@ -479,11 +522,17 @@ impl DiffWidget {
                         " "
                     };
 
-                    lines.push(Line::from(vec![
+                    let mut spans = vec![
                         Span::styled(marker, theme.primary_style()),
                         Span::styled(prefix, style),
                         Span::styled(content.to_string(), style),
-                    ]));
+                    ];
+                    if matches!(diff_line, DiffLine::Hunk(_)) {
+                        if let Some(label) = self.hunk_label(file_idx, hunk_idx) {
+                            spans.push(Span::styled(format!("  {label}"), theme.accent_style()));
+                        }
+                    }
+                    lines.push(Line::from(spans));
                 }
             }
 
@@ -552,10 +601,14 @@ impl DiffWidget {
                         Span::styled(marker, theme.primary_style()),
                         Span::styled(header.clone(), theme.dim_style()),
                     ]));
-                    right_lines.push(Line::from(vec![
+                    let mut right = vec![
                         Span::styled(marker, theme.primary_style()),
                         Span::styled(header.clone(), theme.dim_style()),
-                    ]));
+                    ];
+                    if let Some(label) = self.hunk_label(file_idx, hunk_idx) {
+                        right.push(Span::styled(format!("  {label}"), theme.accent_style()));
+                    }
+                    right_lines.push(Line::from(right));
                 }
 
                 // Collect removed and added lines, pair them with context
@@ -851,6 +904,25 @@ mod tests {
         assert_eq!(widget.current_hunk, 0);
     }
 
+    #[test]
+    fn test_select_hunk_and_labels() {
+        let mut widget = DiffWidget::new();
+        widget.set_unified_diff(SAMPLE_DIFF);
+        widget.select_hunk(1);
+        assert_eq!(widget.current_position(), (1, 0));
+        widget.select_hunk(5);
+        assert_eq!(widget.current_position(), (1, 0));
+
+        widget.set_hunk_label(1, Some("accepted".to_string()));
+        widget.set_hunk_label(9, Some("ignored".to_string()));
+        assert_eq!(widget.hunk_label(0, 0), None);
+        assert_eq!(widget.hunk_label(1, 0), Some("accepted"));
+
+        // New diffs drop the labels
+        widget.set_unified_diff(SAMPLE_DIFF);
+        assert_eq!(widget.hunk_label(1, 0), None);
+    }
+
     #[test]
     fn test_file_navigation() {
         let mut widget = DiffWidget::new();
@@ -0,0 +1,595 @@
+//! Inline images through terminal graphics protocols.
+//!
+//! Terminals that speak the kitty, sixel or iTerm2 graphics protocol can show
+//! images between lines of text. [`ImageProtocol::detect`] picks one from the
+//! environment; without one, callers show a placeholder line instead.
+//!
+//! An image is drawn by putting its escape sequence in the top-left cell of
+//! its area and skipping the other cells, so ratatui writes it out like any
+//! other change to the screen.
+
+use base64::Engine;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use ratatui::{buffer::Buffer, layout::Rect};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Cell size in pixels assumed when the terminal doesn't report it.
+const DEFAULT_CELL_SIZE: (u16, u16) = (8, 16);
+
+/// Most rows an image takes.
+const MAX_ROWS: u16 = 20;
+
+/// Base64 bytes per kitty graphics chunk.
+const KITTY_CHUNK: usize = 4096;
+
+/// Longest side in pixels of an image attached to a prompt. Providers scale
+/// larger images down anyway.
+const MAX_ATTACHMENT_SIDE: u32 = 1568;
+
+/// Largest image in bytes attached to a prompt as it is.
+const MAX_ATTACHMENT_BYTES: usize = 3_750_000;
+
+/// Next kitty image ID.
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A terminal graphics protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ImageProtocol {
+    /// The kitty graphics protocol (kitty, Ghostty).
+    Kitty,
+    /// Sixel graphics (foot, mlterm, Konsole, Windows Terminal).
+    Sixel,
+    /// iTerm2 inline images (iTerm2, WezTerm).
+    Iterm2,
+    /// No graphics; images are shown as placeholders.
+    #[default]
+    None,
+}
+
+impl ImageProtocol {
+    /// Guess the protocol the terminal supports from the environment.
+    pub fn detect() -> Self {
+        Self::detect_from(|key| std::env::var(key).ok())
+    }
+
+    fn detect_from(var: impl Fn(&str) -> Option<String>) -> Self {
+        // Multiplexers don't pass graphics through
+        if var("TMUX").is_some() || var("STY").is_some() {
+            return Self::None;
+        }
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+
+        if var("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || program == "ghostty"
+        {
+            Self::Kitty
+        } else if program == "iTerm.app"
+            || program == "WezTerm"
+            || var("LC_TERMINAL").as_deref() == Some("iTerm2")
+        {
+            Self::Iterm2
+        } else if term.starts_with("foot")
+            || term.starts_with("mlterm")
+            || var("KONSOLE_VERSION").is_some()
+            || var("WT_SESSION").is_some()
+        {
+            Self::Sixel
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// Size of a terminal cell in pixels, as reported by the terminal.
+pub fn cell_size() -> (u16, u16) {
+    match ratatui::crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => {
+            (size.width / size.columns, size.height / size.rows)
+        }
+        _ => DEFAULT_CELL_SIZE,
+    }
+}
+
+/// An escape sequence with the protocol and cell size it was built for.
+type Encoded = ((ImageProtocol, u16, u16), Arc<str>);
+
+/// An image to show inline.
+#[derive(Debug)]
+pub struct InlineImage {
+    id: u32,
+    media_type: String,
+    bytes: Vec<u8>,
+    width: u32,
+    height: u32,
+    /// The last escape sequence built, with the protocol and size it is for.
+    encoded: Mutex<Option<Encoded>>,
+}
+
+impl InlineImage {
+    /// Create an image from base64 data.
+    pub fn from_base64(media_type: &str, data: &str) -> Result<Self, String> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(|e| format!("Invalid image data: {e}"))?;
+        Self::from_bytes(media_type, bytes)
+    }
+
+    /// Create an image from encoded bytes (PNG, JPEG, GIF or WebP).
+    pub fn from_bytes(media_type: &str, bytes: Vec<u8>) -> Result<Self, String> {
+        let (width, height) = ImageReader::new(Cursor::new(&bytes))
+            .with_guessed_format()
+            .map_err(|e| e.to_string())?
+            .into_dimensions()
+            .map_err(|e| format!("Unsupported image: {e}"))?;
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            media_type: media_type.to_string(),
+            bytes,
+            width,
+            height,
+            encoded: Mutex::new(None),
+        })
+    }
+
+    /// The media type, e.g. "image/png".
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    /// Width and height in pixels.
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Columns and rows the image takes when at most `max_cols` wide, keeping
+    /// its aspect ratio and never scaling it up.
+    pub fn size_in_cells(&self, max_cols: u16, cell: (u16, u16)) -> (u16, u16) {
+        let (cell_w, cell_h) = (u32::from(cell.0.max(1)), u32::from(cell.1.max(1)));
+        let (w, h) = (self.width.max(1), self.height.max(1));
+
+        let mut cols = w.div_ceil(cell_w).clamp(1, u32::from(max_cols.max(1)));
+        let mut rows = (h * cols * cell_w).div_ceil(w * cell_h).max(1);
+        if rows > u32::from(MAX_ROWS) {
+            rows = u32::from(MAX_ROWS);
+            cols = (w * rows * cell_h).div_ceil(h * cell_w).clamp(1, cols);
+        }
+        (cols as u16, rows as u16)
+    }
+
+    /// The escape sequence drawing the image over `cols` x `rows` cells.
+    /// For kitty, this transmits the image; [`kitty_place`] shows it again.
+    fn escape(
+        &self,
+        protocol: ImageProtocol,
+        cols: u16,
+        rows: u16,
+        cell: (u16, u16),
+    ) -> Option<Arc<str>> {
+        // Kitty only transmits once, so there's nothing to keep
+        if protocol == ImageProtocol::Kitty {
+            return Some(kitty_transmit(self.id, &self.png()?, cols, rows).into());
+        }
+        let key = (protocol, cols, rows);
+        let mut cached = self.encoded.lock().ok()?;
+        if let Some((cached_key, escape)) = cached.as_ref() {
+            if *cached_key == key {
+                return Some(Arc::clone(escape));
+            }
+        }
+        let escape: Arc<str> = match protocol {
+            ImageProtocol::Iterm2 => iterm2(&self.bytes, cols, rows),
+            ImageProtocol::Sixel => {
+                let width = u32::from(cols) * u32::from(cell.0.max(1));
+                let height = u32::from(rows) * u32::from(cell.1.max(1));
+                sixel(
+                    &self
+                        .decode()?
+                        .resize(width, height, image::imageops::FilterType::Triangle),
+                )
+            }
+            ImageProtocol::Kitty | ImageProtocol::None => return None,
+        }
+        .into();
+        *cached = Some((key, Arc::clone(&escape)));
+        Some(escape)
+    }
+
+    fn decode(&self) -> Option<DynamicImage> {
+        image::load_from_memory(&self.bytes).ok()
+    }
+
+    /// The image as PNG, converting it if needed.
+    fn png(&self) -> Option<Vec<u8>> {
+        if image::guess_format(&self.bytes).ok() == Some(ImageFormat::Png) {
+            return Some(self.bytes.clone());
+        }
+        let mut png = Vec::new();
+        self.decode()?
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .ok()?;
+        Some(png)
+    }
+}
+
+/// Draws images into frames and removes the ones that are gone.
+#[derive(Debug, Clone)]
+pub struct ImageCanvas {
+    protocol: ImageProtocol,
+    cell_size: (u16, u16),
+    /// Kitty images the terminal has the data of.
+    transmitted: HashSet<u32>,
+    /// Images drawn in the current frame.
+    drawn: HashSet<u32>,
+    /// Images drawn in the previous frame.
+    shown: HashSet<u32>,
+}
+
+impl Default for ImageCanvas {
+    fn default() -> Self {
+        Self::new(ImageProtocol::None)
+    }
+}
+
+impl ImageCanvas {
+    /// Create a canvas for a protocol.
+    pub fn new(protocol: ImageProtocol) -> Self {
+        Self {
+            protocol,
+            cell_size: if protocol == ImageProtocol::None {
+                DEFAULT_CELL_SIZE
+            } else {
+                cell_size()
+            },
+            transmitted: HashSet::new(),
+            drawn: HashSet::new(),
+            shown: HashSet::new(),
+        }
+    }
+
+    /// The protocol images are drawn with.
+    pub fn protocol(&self) -> ImageProtocol {
+        self.protocol
+    }
+
+    /// Cell size in pixels.
+    pub fn cell_size(&self) -> (u16, u16) {
+        self.cell_size
+    }
+
+    /// Query the cell size again, e.g. after a resize or font change.
+    /// Returns true if it changed.
+    pub fn refresh_cell_size(&mut self) -> bool {
+        if self.protocol == ImageProtocol::None {
+            return false;
+        }
+        let size = cell_size();
+        let changed = size != self.cell_size;
+        self.cell_size = size;
+        changed
+    }
+
+    /// Draw an image over an area of the buffer, which must lie within it.
+    pub fn draw(&mut self, buf: &mut Buffer, area: Rect, image: &InlineImage) {
+        if area.is_empty() || !buf.area.contains(area.as_position()) {
+            return;
+        }
+        let escape =
+            if self.protocol == ImageProtocol::Kitty && self.transmitted.contains(&image.id) {
+                Some(kitty_place(image.id, area.width, area.height).into())
+            } else {
+                image.escape(self.protocol, area.width, area.height, self.cell_size)
+            };
+        let Some(escape) = escape else {
+            return;
+        };
+        if self.protocol == ImageProtocol::Kitty {
+            self.transmitted.insert(image.id);
+        }
+        self.drawn.insert(image.id);
+
+        let area = area.intersection(buf.area);
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                buf[(x, y)].set_skip(true);
+            }
+        }
+        let cell = &mut buf[(area.x, area.y)];
+        cell.set_skip(false);
+        cell.set_symbol(&escape);
+    }
+
+    /// End a frame, returning what to write to the terminal after it to
+    /// remove images that weren't drawn again. Only kitty needs this; other
+    /// protocols draw into cells, which later frames overwrite.
+    pub fn finish_frame(&mut self) -> String {
+        let drawn = std::mem::take(&mut self.drawn);
+        let mut out = String::new();
+        if self.protocol == ImageProtocol::Kitty {
+            for id in self.shown.difference(&drawn) {
+                let _ = write!(out, "\x1b_Ga=d,d=i,i={id},q=2\x1b\\");
+            }
+        }
+        self.shown = drawn;
+        out
+    }
+}
+
+/// Encode RGBA pixels, such as an image from the clipboard, as PNG.
+pub fn rgba_to_png(width: u32, height: u32, rgba: Vec<u8>) -> Result<Vec<u8>, String> {
+    let image = image::RgbaImage::from_raw(width, height, rgba)
+        .ok_or_else(|| format!("Invalid {width}x{height} image data"))?;
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(image)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+/// Prepare an image to attach to a prompt, returning its media type and the
+/// bytes to send. Images too large to send are scaled down.
+pub fn prepare_attachment(bytes: Vec<u8>) -> Result<(&'static str, Vec<u8>), String> {
+    let format = image::guess_format(&bytes).map_err(|e| format!("Unsupported image: {e}"))?;
+    let media_type = match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::WebP => "image/webp",
+        _ => return Err(format!("Unsupported image format: {format:?}")),
+    };
+    let (width, height) = ImageReader::with_format(Cursor::new(&bytes), format)
+        .into_dimensions()
+        .map_err(|e| format!("Unsupported image: {e}"))?;
+    let side = width.max(height);
+    if side <= MAX_ATTACHMENT_SIDE && bytes.len() <= MAX_ATTACHMENT_BYTES {
+        return Ok((media_type, bytes));
+    }
+
+    let side = side.min(MAX_ATTACHMENT_SIDE);
+    let image = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|e| format!("Unsupported image: {e}"))?
+        .resize(side, side, image::imageops::FilterType::Triangle);
+    // Photos stay JPEG; everything else, animations included, becomes a
+    // still PNG
+    let (media_type, image, format) = if format == ImageFormat::Jpeg {
+        (
+            "image/jpeg",
+            DynamicImage::ImageRgb8(image.to_rgb8()),
+            format,
+        )
+    } else {
+        ("image/png", image, ImageFormat::Png)
+    };
+    let mut out = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut out), format)
+        .map_err(|e| e.to_string())?;
+    Ok((media_type, out))
+}
+
+/// Kitty: transmit PNG data and show it over `cols` x `rows` cells.
+fn kitty_transmit(id: u32, png: &[u8], cols: u16, rows: u16) -> String {
+    let data = base64::engine::general_purpose::STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut out = String::with_capacity(data.len() + chunks.len() * 16 + 64);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f=100,i={id},p=1,c={cols},r={rows},C=1,q=2,m={more};{chunk}\x1b\\"
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={more};{chunk}\x1b\\");
+        }
+    }
+    out
+}
+
+/// Kitty: show an already transmitted image, replacing its placement.
+fn kitty_place(id: u32, cols: u16, rows: u16) -> String {
+    format!("\x1b_Ga=p,i={id},p=1,c={cols},r={rows},C=1,q=2\x1b\\")
+}
+
+/// iTerm2: an inline file scaled to `cols` x `rows` cells.
+fn iterm2(bytes: &[u8], cols: u16, rows: u16) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={cols};height={rows};preserveAspectRatio=1;doNotMoveCursor=1:{}\x07",
+        bytes.len(),
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// Sixel: the image with its colors reduced to a 6x6x6 color cube.
+/// Transparent pixels are left as they are.
+fn sixel(image: &DynamicImage) -> String {
+    let rgba = image.to_rgba8();
+    let (width, height) = rgba.dimensions();
+    let level = |v: u8| (u16::from(v) * 5 + 127) / 255;
+    let color = |x: u32, y: u32| -> Option<usize> {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        (a >= 128).then(|| usize::from(level(r) * 36 + level(g) * 6 + level(b)))
+    };
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for i in 0..216u16 {
+        let _ = write!(
+            out,
+            "#{i};2;{};{};{}",
+            i / 36 * 20,
+            i / 6 % 6 * 20,
+            i % 6 * 20
+        );
+    }
+
+    let mut bands: Vec<Vec<u8>> = vec![Vec::new(); 216];
+    for top in (0..height).step_by(6) {
+        let mut used: Vec<usize> = Vec::new();
+        for x in 0..width {
+            for dy in 0..6.min(height - top) {
+                if let Some(c) = color(x, top + dy) {
+                    if bands[c].is_empty() {
+                        bands[c] = vec![0; width as usize];
+                        used.push(c);
+                    }
+                    bands[c][x as usize] |= 1 << dy;
+                }
+            }
+        }
+        for (n, &c) in used.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{c}");
+            push_sixel_row(&mut out, &bands[c]);
+            bands[c].clear();
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Append a row of sixels, run-length encoded.
+fn push_sixel_row(out: &mut String, bits: &[u8]) {
+    let mut i = 0;
+    while i < bits.len() {
+        let run = bits[i..].iter().take_while(|&&b| b == bits[i]).count();
+        let ch = char::from(63 + bits[i]);
+        if run > 3 {
+            let _ = write!(out, "!{run}{ch}");
+        } else {
+            for _ in 0..run {
+                out.push(ch);
+            }
+        }
+        i += run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255]));
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn detect(vars: &[(&str, &str)]) -> ImageProtocol {
+        ImageProtocol::detect_from(|key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), ImageProtocol::Kitty);
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "iTerm.app")]),
+            ImageProtocol::Iterm2
+        );
+        assert_eq!(detect(&[("TERM", "foot")]), ImageProtocol::Sixel);
+        assert_eq!(detect(&[("TERM", "xterm-256color")]), ImageProtocol::None);
+        assert_eq!(
+            detect(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")]),
+            ImageProtocol::None
+        );
+    }
+
+    #[test]
+    fn test_size_in_cells() {
+        let image = InlineImage::from_bytes("image/png", png(160, 80)).unwrap();
+        assert_eq!(image.dimensions(), (160, 80));
+        // Natural size: 20 columns of 8px, 80px tall is 5 rows of 16px
+        assert_eq!(image.size_in_cells(100, (8, 16)), (20, 5));
+        // Narrower than the image
+        assert_eq!(image.size_in_cells(10, (8, 16)), (10, 3));
+
+        // Tall images are capped at MAX_ROWS
+        let tall = InlineImage::from_bytes("image/png", png(80, 2000)).unwrap();
+        let (cols, rows) = tall.size_in_cells(100, (8, 16));
+        assert_eq!(rows, MAX_ROWS);
+        assert!(cols < 10);
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        assert!(InlineImage::from_base64("image/png", "not base64!").is_err());
+        assert!(InlineImage::from_bytes("image/png", b"not an image".to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_prepare_attachment() {
+        let small = png(40, 20);
+        assert_eq!(prepare_attachment(small.clone()), Ok(("image/png", small)));
+
+        // Large images are scaled down, keeping their aspect ratio
+        let (media_type, bytes) = prepare_attachment(png(3136, 100)).unwrap();
+        assert_eq!(media_type, "image/png");
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((image.width(), image.height()), (MAX_ATTACHMENT_SIDE, 50));
+
+        assert!(prepare_attachment(b"not an image".to_vec()).is_err());
+        let rgba = vec![0; 2 * 3 * 4];
+        assert!(prepare_attachment(rgba_to_png(2, 3, rgba).unwrap()).is_ok());
+        assert!(rgba_to_png(2, 3, vec![0; 5]).is_err());
+    }
+
+    #[test]
+    fn test_sixel() {
+        let image = image::load_from_memory(&png(4, 7)).unwrap();
+        let sixel = sixel(&image);
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;4;7"));
+        assert!(sixel.ends_with("\x1b\\"));
+        // Red is color 180 of the cube; two bands, the second one row high
+        assert!(sixel.contains("#180!4~-#180!4@-"));
+    }
+
+    #[test]
+    fn test_kitty_transmits_once_and_cleans_up() {
+        let image = InlineImage::from_bytes("image/png", png(16, 16)).unwrap();
+        let mut canvas = ImageCanvas::new(ImageProtocol::Kitty);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 20, 10));
+        let area = Rect::new(2, 1, 2, 1);
+
+        canvas.draw(&mut buf, area, &image);
+        assert!(buf[(2, 1)].symbol().contains("a=T,f=100"));
+        assert!(buf[(3, 1)].skip);
+        assert_eq!(canvas.finish_frame(), "");
+
+        canvas.draw(&mut buf, area, &image);
+        assert!(buf[(2, 1)].symbol().contains("a=p"));
+        assert_eq!(canvas.finish_frame(), "");
+
+        // Not drawn in this frame: its placement is removed
+        let cleanup = canvas.finish_frame();
+        assert!(cleanup.contains(&format!("a=d,d=i,i={}", image.id)));
+    }
+
+    #[test]
+    fn test_iterm2() {
+        let image = InlineImage::from_bytes("image/png", png(16, 16)).unwrap();
+        let mut canvas = ImageCanvas::new(ImageProtocol::Iterm2);
+        let mut buf = Buffer::empty(Rect::new(0, 0, 20, 10));
+        canvas.draw(&mut buf, Rect::new(0, 0, 2, 1), &image);
+        assert!(buf[(0, 0)]
+            .symbol()
+            .starts_with("\x1b]1337;File=inline=1;size="));
+        assert_eq!(canvas.finish_frame(), "");
+    }
+}
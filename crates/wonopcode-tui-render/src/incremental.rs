@@ -0,0 +1,215 @@
+//! Incremental markdown rendering for text that only grows.
+//!
+//! A streaming response is re-rendered on every frame. Rendering is
+//! line-based, and a blank line outside a fenced code block ends every block,
+//! so the lines rendered for the text before such a break never change. They
+//! are kept, and each update only renders the text after the last break.
+
+use ratatui::text::Line;
+use wonopcode_tui_core::{metrics, RenderSettings, Theme};
+
+use crate::markdown::{render_markdown_with_regions, CodeRegion};
+
+/// Markdown rendering for text that only grows, such as a streaming response.
+///
+/// The text is split into a stable prefix of complete blocks, which later
+/// text can't change, and the tail after it. The lines of the prefix are kept
+/// between updates, so an update renders the blocks completed since the last
+/// one and the tail.
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalMarkdown {
+    /// Width the lines were rendered for.
+    width: usize,
+    /// Length of the text rendered last.
+    text_len: usize,
+    /// Length of the stable prefix.
+    stable_len: usize,
+    /// Start of the first line not scanned for block breaks yet.
+    scanned: usize,
+    /// Whether the scanned text ends inside a fenced code block.
+    in_code_block: bool,
+    /// Whether the last scanned line was blank.
+    after_blank: bool,
+    stable_lines: Vec<Line<'static>>,
+    stable_regions: Vec<CodeRegion>,
+    tail_lines: Vec<Line<'static>>,
+    tail_regions: Vec<CodeRegion>,
+}
+
+impl IncrementalMarkdown {
+    /// Create an empty render.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the rendered lines, e.g. when the theme or settings change.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Render the text, reusing the lines of its stable prefix.
+    ///
+    /// The text must extend the text of the last update. Shorter text or a
+    /// new width starts over.
+    pub fn update(&mut self, text: &str, theme: &Theme, width: usize, settings: &RenderSettings) {
+        if width != self.width
+            || text.len() < self.text_len
+            || !text.is_char_boundary(self.stable_len)
+        {
+            self.clear();
+            self.width = width;
+        } else if text.len() == self.text_len && self.text_len > 0 {
+            return;
+        }
+        let _timer = metrics::widget_timer("markdown");
+        let reused = self.stable_len;
+
+        let boundary = self.scan(text);
+        if boundary > self.stable_len {
+            let block = render_markdown_with_regions(
+                &text[self.stable_len..boundary],
+                theme,
+                width,
+                settings,
+            );
+            let offset = self.stable_lines.len();
+            self.stable_regions
+                .extend(shift_regions(block.code_regions, offset));
+            self.stable_lines.extend(block.text.lines);
+            self.stable_len = boundary;
+        }
+
+        let tail = render_markdown_with_regions(&text[self.stable_len..], theme, width, settings);
+        self.tail_regions = shift_regions(tail.code_regions, self.stable_lines.len());
+        self.tail_lines = tail.text.lines;
+        self.text_len = text.len();
+
+        metrics::record_markdown(text.len() - reused, reused);
+    }
+
+    /// Scan the complete lines not scanned yet, and return the start of the
+    /// last line that begins a block after a blank line.
+    fn scan(&mut self, text: &str) -> usize {
+        let mut boundary = self.stable_len;
+        while let Some(end) = text[self.scanned..].find('\n') {
+            let start = self.scanned;
+            let line = &text[start..start + end];
+            self.scanned += end + 1;
+
+            if self.in_code_block {
+                self.in_code_block = !line.starts_with("```");
+                continue;
+            }
+            let blank = line.trim().is_empty();
+            if !blank && self.after_blank {
+                boundary = start;
+            }
+            self.after_blank = blank;
+            self.in_code_block = line.starts_with("```");
+        }
+        boundary
+    }
+
+    /// Get the rendered lines.
+    pub fn lines(&self) -> impl Iterator<Item = &Line<'static>> {
+        self.stable_lines.iter().chain(&self.tail_lines)
+    }
+
+    /// Get the number of rendered lines.
+    pub fn line_count(&self) -> usize {
+        self.stable_lines.len() + self.tail_lines.len()
+    }
+
+    /// Get the code regions, with line indices into [`Self::lines`].
+    pub fn code_regions(&self) -> impl Iterator<Item = &CodeRegion> {
+        self.stable_regions.iter().chain(&self.tail_regions)
+    }
+
+    /// Get the length of the text whose lines are kept between updates.
+    pub fn stable_len(&self) -> usize {
+        self.stable_len
+    }
+}
+
+/// Move code regions down by `offset` lines.
+fn shift_regions(mut regions: Vec<CodeRegion>, offset: usize) -> Vec<CodeRegion> {
+    for region in &mut regions {
+        region.start_line += offset;
+        region.end_line += offset;
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "# Title\n\nSome *text* here\nand more.\n\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n```rust\nfn main() {\n\n    println!(\"hi\");\n}\n```\n\n- one\n- two\n\n1. first\n> quote\n\nlast line";
+
+    fn render_full(text: &str) -> (Vec<Line<'static>>, Vec<(usize, usize)>) {
+        let rendered =
+            render_markdown_with_regions(text, &Theme::default(), 60, &RenderSettings::default());
+        let regions = rendered
+            .code_regions
+            .iter()
+            .map(|r| (r.start_line, r.end_line))
+            .collect();
+        (rendered.text.lines, regions)
+    }
+
+    fn render_incremental(
+        markdown: &IncrementalMarkdown,
+    ) -> (Vec<Line<'static>>, Vec<(usize, usize)>) {
+        let regions = markdown
+            .code_regions()
+            .map(|r| (r.start_line, r.end_line))
+            .collect();
+        (markdown.lines().cloned().collect(), regions)
+    }
+
+    #[test]
+    fn test_matches_full_render_at_every_length() {
+        let theme = Theme::default();
+        let settings = RenderSettings::default();
+        let mut markdown = IncrementalMarkdown::new();
+        for (end, _) in TEXT.char_indices().skip(1) {
+            let text = &TEXT[..end];
+            markdown.update(text, &theme, 60, &settings);
+            assert_eq!(
+                render_incremental(&markdown),
+                render_full(text),
+                "differs at {end}: {text:?}"
+            );
+        }
+        markdown.update(TEXT, &theme, 60, &settings);
+        assert_eq!(render_incremental(&markdown), render_full(TEXT));
+        // The last line may still grow, so only complete lines are scanned
+        assert_eq!(markdown.stable_len(), TEXT.rfind("1. first").unwrap());
+    }
+
+    #[test]
+    fn test_blank_lines_in_code_block_are_not_breaks() {
+        let theme = Theme::default();
+        let settings = RenderSettings::default();
+        let mut markdown = IncrementalMarkdown::new();
+        markdown.update("intro\n\n```\na\n\nb\n", &theme, 60, &settings);
+        assert_eq!(markdown.stable_len(), "intro\n\n".len());
+    }
+
+    #[test]
+    fn test_starts_over_for_new_width_or_shorter_text() {
+        let theme = Theme::default();
+        let settings = RenderSettings::default();
+        let mut markdown = IncrementalMarkdown::new();
+        markdown.update("one\n\ntwo\n", &theme, 60, &settings);
+        assert_eq!(markdown.stable_len(), 5);
+
+        markdown.update("one\n\ntwo\n", &theme, 40, &settings);
+        assert_eq!(markdown.stable_len(), 5);
+        assert_eq!(render_incremental(&markdown).0.len(), 3);
+
+        markdown.update("new", &theme, 40, &settings);
+        assert_eq!(markdown.stable_len(), 0);
+        assert_eq!(markdown.line_count(), 1);
+    }
+}
@@ -2,20 +2,32 @@
 //!
 //! This crate provides:
 //! - Markdown rendering with syntax highlighting
+//! - Incremental markdown rendering for streaming text
 //! - Syntax highlighting for code blocks
 //! - Diff display widgets
+//! - Inline images through terminal graphics protocols
+//! - Preview pane for the changes of an edit
+//! - Hunk-by-hunk review of changed files
 //! - Text sanitization for safe TUI display
 
 pub mod diff;
+pub mod image;
+pub mod incremental;
 pub mod markdown;
+pub mod preview;
+pub mod review;
 pub mod sanitize;
 pub mod syntax;
 
 // Re-export commonly used types
 pub use diff::{DiffHunk, DiffLine, DiffStyle, DiffWidget, FileDiff};
+pub use image::{prepare_attachment, rgba_to_png, ImageCanvas, ImageProtocol, InlineImage};
+pub use incremental::IncrementalMarkdown;
 pub use markdown::{
     render_markdown, render_markdown_with_settings, render_markdown_with_width, wrap_line,
     CodeRegion, RenderedMarkdown,
 };
+pub use preview::{PreviewMode, PreviewPane};
+pub use review::{HunkDecision, ReviewView, ReviewedFile};
 pub use sanitize::{needs_sanitization, sanitize_for_display};
 pub use syntax::{highlight_code, highlight_code_with_settings, highlight_diff, is_diff};
@@ -0,0 +1,389 @@
+//! Side pane showing the changes of an edit next to the messages.
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use wonopcode_tui_core::metrics;
+use wonopcode_tui_core::Theme;
+
+use crate::syntax::{highlight_code, highlight_diff_with_language, language_from_path};
+
+/// Lines of context kept above the change when showing a file.
+const CONTEXT_LINES: usize = 3;
+
+/// What the preview pane shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreviewMode {
+    /// The diff of the edit.
+    #[default]
+    Diff,
+    /// The edited file, scrolled to the change.
+    File,
+}
+
+/// Side pane showing the diff or file of an edit.
+#[derive(Debug, Clone, Default)]
+pub struct PreviewPane {
+    visible: bool,
+    mode: PreviewMode,
+    /// Path of the edited file.
+    path: Option<String>,
+    /// Diff lines of the edit.
+    diff: Vec<String>,
+    /// Lines of the edited file once loaded, or why it couldn't be read.
+    file: Option<Result<Vec<String>, String>>,
+    scroll: usize,
+}
+
+impl PreviewPane {
+    /// Create a hidden pane.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if the pane is visible.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the pane.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Toggle visibility.
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Get the current mode.
+    pub fn mode(&self) -> PreviewMode {
+        self.mode
+    }
+
+    /// Switch between the diff and the file.
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            PreviewMode::Diff => PreviewMode::File,
+            PreviewMode::File => PreviewMode::Diff,
+        };
+        self.scroll = self.focus_line();
+    }
+
+    /// Show an edit. The file is loaded separately through [`Self::set_file`].
+    pub fn set_edit(&mut self, path: Option<String>, diff: &str) {
+        if self.path != path {
+            self.file = None;
+        }
+        self.path = path;
+        self.diff = diff.lines().map(str::to_string).collect();
+        self.scroll = self.focus_line();
+    }
+
+    /// Show a whole file, without a diff, and make the pane visible.
+    pub fn show_file(&mut self, path: String) {
+        self.clear();
+        self.path = Some(path);
+        self.mode = PreviewMode::File;
+        self.visible = true;
+    }
+
+    /// Show nothing.
+    pub fn clear(&mut self) {
+        self.path = None;
+        self.diff.clear();
+        self.file = None;
+        self.scroll = 0;
+    }
+
+    /// The file that should be loaded, if the file is shown but not loaded.
+    pub fn file_to_load(&self) -> Option<&str> {
+        match self.mode {
+            PreviewMode::File if self.file.is_none() => self.path.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Set the content of the edited file, or why it couldn't be read.
+    pub fn set_file(&mut self, content: Result<String, String>) {
+        self.file = Some(content.map(|c| c.lines().map(str::to_string).collect()));
+        self.scroll = self.focus_line();
+    }
+
+    /// Drop the loaded file so it's read again.
+    pub fn reload_file(&mut self) {
+        self.file = None;
+    }
+
+    /// Scroll up.
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.scroll = self.scroll.saturating_sub(amount);
+    }
+
+    /// Scroll down.
+    pub fn scroll_down(&mut self, amount: usize) {
+        self.scroll = self.scroll.saturating_add(amount);
+    }
+
+    /// The line to scroll to: the first change, with some context above it
+    /// when showing the file.
+    fn focus_line(&self) -> usize {
+        let PreviewMode::File = self.mode else {
+            return 0;
+        };
+        let Some(Ok(file)) = &self.file else {
+            return 0;
+        };
+        let changed = self
+            .diff
+            .iter()
+            .filter(|l| !l.starts_with("+++"))
+            .find_map(|l| l.strip_prefix('+'))
+            .or_else(|| self.diff.iter().find_map(|l| l.strip_prefix(' ')))
+            .filter(|l| !l.trim().is_empty());
+        changed
+            .and_then(|changed| file.iter().position(|l| l == changed))
+            .map_or(0, |line| line.saturating_sub(CONTEXT_LINES))
+    }
+
+    /// Render the pane.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let _timer = metrics::widget_timer("preview");
+
+        if !self.visible || area.width < 20 || area.height < 6 {
+            return;
+        }
+
+        // Background fill with panel color (leaving 1 row at top for status bar)
+        let bg_area = Rect::new(
+            area.x,
+            area.y + 1,
+            area.width,
+            area.height.saturating_sub(1),
+        );
+        frame.render_widget(
+            Block::default().style(Style::default().bg(theme.background_panel)),
+            bg_area,
+        );
+
+        let inner = Rect::new(
+            area.x + 1,
+            area.y + 2,
+            area.width.saturating_sub(2),
+            area.height.saturating_sub(4),
+        );
+        let header_area = Rect::new(inner.x, inner.y, inner.width, 1);
+        let content_area = Rect::new(
+            inner.x,
+            inner.y + 2,
+            inner.width,
+            inner.height.saturating_sub(3),
+        );
+        let footer_area = Rect::new(inner.x, inner.y + inner.height - 1, inner.width, 1);
+
+        frame.render_widget(Paragraph::new(self.header(inner.width, theme)), header_area);
+
+        let height = content_area.height as usize;
+        let language = self.path.as_deref().map(language_from_path).unwrap_or("");
+        let (lines, total) =
+            if self.diff.is_empty() && (self.mode == PreviewMode::Diff || self.path.is_none()) {
+                let note = if self.path.is_some() {
+                    "No changes to this file"
+                } else {
+                    "No edits in view"
+                };
+                (vec![Line::from(Span::styled(note, theme.muted_style()))], 0)
+            } else {
+                match self.mode {
+                    PreviewMode::Diff => {
+                        self.scroll = self.scroll.min(self.diff.len().saturating_sub(height));
+                        let end = (self.scroll + height).min(self.diff.len());
+                        let shown = self.diff[self.scroll..end].join("\n");
+                        let language = (!language.is_empty()).then_some(language);
+                        (
+                            highlight_diff_with_language(&shown, theme, language),
+                            self.diff.len(),
+                        )
+                    }
+                    PreviewMode::File => match &self.file {
+                        Some(Ok(file)) => {
+                            self.scroll = self.scroll.min(file.len().saturating_sub(height));
+                            let end = (self.scroll + height).min(file.len());
+                            let gutter = file.len().to_string().len();
+                            let shown = file[self.scroll..end].join("\n");
+                            let lines = highlight_code(&shown, language, theme)
+                                .into_iter()
+                                .enumerate()
+                                .map(|(i, line)| {
+                                    let mut spans = vec![Span::styled(
+                                        format!("{:>gutter$} ", self.scroll + i + 1),
+                                        theme.dim_style(),
+                                    )];
+                                    spans.extend(line.spans);
+                                    Line::from(spans)
+                                })
+                                .collect();
+                            (lines, file.len())
+                        }
+                        Some(Err(e)) => (
+                            vec![Line::from(Span::styled(e.clone(), theme.error_style()))],
+                            0,
+                        ),
+                        None => {
+                            let note = if self.path.is_some() {
+                                "Loading…"
+                            } else {
+                                "No file for this edit"
+                            };
+                            (vec![Line::from(Span::styled(note, theme.muted_style()))], 0)
+                        }
+                    },
+                }
+            };
+        frame.render_widget(Paragraph::new(lines), content_area);
+
+        let position = if total > height {
+            format!(
+                "{}-{} of {}",
+                self.scroll + 1,
+                (self.scroll + height).min(total),
+                total
+            )
+        } else {
+            String::new()
+        };
+        let hint = match self.mode {
+            PreviewMode::Diff => "click to show file",
+            PreviewMode::File => "click to show diff",
+        };
+        let gap = (inner.width as usize).saturating_sub(position.len() + hint.len());
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(position, theme.dim_style()),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(hint, theme.dim_style()),
+            ])),
+            footer_area,
+        );
+    }
+
+    /// The mode tabs and the edited path, cut from the left to fit.
+    fn header(&self, width: u16, theme: &Theme) -> Line<'static> {
+        let tab = |label: &'static str, mode: PreviewMode| {
+            if self.mode == mode {
+                Span::styled(label, theme.accent_style().add_modifier(Modifier::BOLD))
+            } else {
+                Span::styled(label, theme.muted_style())
+            }
+        };
+        let mut spans = vec![
+            tab("Diff", PreviewMode::Diff),
+            Span::raw("  "),
+            tab("File", PreviewMode::File),
+        ];
+        if let Some(path) = &self.path {
+            let room = (width as usize).saturating_sub(12);
+            let chars = path.chars().count();
+            let shown = if chars > room {
+                let tail: String = path.chars().skip(chars - room + 1).collect();
+                format!("…{tail}")
+            } else {
+                path.clone()
+            };
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(shown, theme.text_style()));
+        }
+        Line::from(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff() -> &'static str {
+        "--- a/src/lib.rs\n+++ b/src/lib.rs\n fn main() {\n-    old();\n+    new();\n }\n"
+    }
+
+    #[test]
+    fn test_file_scrolls_to_change() {
+        let mut pane = PreviewPane::new();
+        pane.set_edit(Some("src/lib.rs".to_string()), diff());
+        assert_eq!(pane.file_to_load(), None);
+
+        pane.toggle_mode();
+        assert_eq!(pane.mode(), PreviewMode::File);
+        assert_eq!(pane.file_to_load(), Some("src/lib.rs"));
+
+        let mut file: Vec<String> = (0..20).map(|i| format!("// line {i}")).collect();
+        file.extend(["fn main() {", "    new();", "}"].map(String::from));
+        pane.set_file(Ok(file.join("\n")));
+        assert_eq!(pane.file_to_load(), None);
+        assert_eq!(pane.scroll, 21 - CONTEXT_LINES);
+
+        // Back to the diff, from the top
+        pane.toggle_mode();
+        assert_eq!(pane.scroll, 0);
+    }
+
+    #[test]
+    fn test_show_file() {
+        let mut pane = PreviewPane::new();
+        pane.set_edit(Some("a.rs".to_string()), diff());
+        pane.show_file("b.rs".to_string());
+        assert!(pane.is_visible());
+        assert_eq!(pane.mode(), PreviewMode::File);
+        assert_eq!(pane.file_to_load(), Some("b.rs"));
+        assert!(pane.diff.is_empty());
+
+        pane.set_file(Ok("fn b() {}\n".to_string()));
+        assert_eq!(pane.scroll, 0);
+    }
+
+    #[test]
+    fn test_new_path_drops_file() {
+        let mut pane = PreviewPane::new();
+        pane.toggle_mode();
+        pane.set_edit(Some("a.rs".to_string()), diff());
+        pane.set_file(Err("not found".to_string()));
+        assert_eq!(pane.file_to_load(), None);
+
+        // Same file: keep it
+        pane.set_edit(Some("a.rs".to_string()), diff());
+        assert_eq!(pane.file_to_load(), None);
+
+        pane.set_edit(Some("b.rs".to_string()), diff());
+        assert_eq!(pane.file_to_load(), Some("b.rs"));
+
+        pane.clear();
+        assert_eq!(pane.file_to_load(), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let backend = ratatui::backend::TestBackend::new(60, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let theme = Theme::default();
+        let mut pane = PreviewPane::new();
+        pane.set_visible(true);
+        pane.set_edit(Some("src/lib.rs".to_string()), diff());
+        terminal
+            .draw(|frame| pane.render(frame, frame.area(), &theme))
+            .unwrap();
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(text.contains("src/lib.rs"));
+        assert!(text.contains("new();"));
+        assert!(text.contains("click to show file"));
+    }
+}
@@ -0,0 +1,311 @@
+//! Hunk-by-hunk review of the changes of a session.
+
+use ratatui::{
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use wonopcode_tui_core::Theme;
+
+use crate::diff::{DiffLine, DiffNavAction, DiffWidget, FileDiff};
+
+/// What to do with a reviewed hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkDecision {
+    /// Keep the change.
+    Accept,
+    /// Undo the change.
+    Reject,
+    /// Replace the new side of the hunk, context lines included, with this text.
+    Edit(String),
+}
+
+impl HunkDecision {
+    fn label(&self) -> &'static str {
+        match self {
+            HunkDecision::Accept => "✓ accepted",
+            HunkDecision::Reject => "✗ rejected",
+            HunkDecision::Edit(_) => "✎ edited",
+        }
+    }
+}
+
+/// The decisions for the hunks of a file, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewedFile {
+    /// File path.
+    pub path: String,
+    /// Decision per hunk; `None` for hunks left undecided.
+    pub decisions: Vec<Option<HunkDecision>>,
+}
+
+/// Review of changed files, one hunk at a time.
+#[derive(Debug, Clone, Default)]
+pub struct ReviewView {
+    diff: DiffWidget,
+    /// Decision per global hunk index.
+    decisions: Vec<Option<HunkDecision>>,
+}
+
+impl ReviewView {
+    /// Review the hunks of the diffs, starting at the first.
+    pub fn new(diffs: Vec<FileDiff>) -> Self {
+        let mut diff = DiffWidget::new();
+        diff.set_diffs(diffs);
+        diff.set_focused(true);
+        diff.first_hunk();
+        let decisions = vec![None; diff.hunk_count()];
+        Self { diff, decisions }
+    }
+
+    /// Check if there is nothing to review.
+    pub fn is_empty(&self) -> bool {
+        self.decisions.is_empty()
+    }
+
+    /// Get the number of hunks.
+    pub fn hunk_count(&self) -> usize {
+        self.decisions.len()
+    }
+
+    /// Get the number of decided hunks.
+    pub fn decided_count(&self) -> usize {
+        self.decisions.iter().filter(|d| d.is_some()).count()
+    }
+
+    /// Get the global index of the current hunk.
+    pub fn current(&self) -> usize {
+        self.diff.current_hunk_index()
+    }
+
+    /// Get the decision for a hunk by global index.
+    pub fn decision(&self, index: usize) -> Option<&HunkDecision> {
+        self.decisions.get(index)?.as_ref()
+    }
+
+    /// Path of the file of the current hunk.
+    pub fn current_path(&self) -> Option<&str> {
+        let (file, _) = self.diff.current_position();
+        self.diff.diffs().get(file).map(|d| d.path.as_str())
+    }
+
+    /// Decide the current hunk and move on to the next undecided one.
+    pub fn decide(&mut self, decision: HunkDecision) {
+        let current = self.current();
+        self.set(current, Some(decision));
+        self.next_undecided(current);
+    }
+
+    /// Decide the current hunk and the undecided hunks after it in the same
+    /// file, then move on to the next undecided hunk.
+    pub fn decide_file(&mut self, decision: HunkDecision) {
+        let current = self.current();
+        let (file, hunk) = self.diff.current_position();
+        let count = self.diff.diffs().get(file).map_or(0, |d| d.hunks.len());
+        let last = current + count.saturating_sub(hunk + 1);
+        self.set(current, Some(decision.clone()));
+        for index in current + 1..=last {
+            if self.decisions[index].is_none() {
+                self.set(index, Some(decision.clone()));
+            }
+        }
+        self.next_undecided(last);
+    }
+
+    /// Forget the decision for the current hunk.
+    pub fn undo(&mut self) {
+        self.set(self.current(), None);
+    }
+
+    /// Handle a navigation action.
+    pub fn handle_nav(&mut self, action: DiffNavAction) {
+        self.diff.handle_nav(action);
+    }
+
+    /// The text to edit for the current hunk: its edited text if it was
+    /// edited, otherwise its new side with the context lines.
+    pub fn hunk_text(&self) -> Option<String> {
+        if let Some(HunkDecision::Edit(text)) = self.decision(self.current()) {
+            return Some(text.clone());
+        }
+        let (file, hunk) = self.diff.current_position();
+        let hunk = self.diff.diffs().get(file)?.hunks.get(hunk)?;
+        let mut text = String::new();
+        for line in &hunk.lines {
+            if let DiffLine::Context(s) | DiffLine::Added(s) = line {
+                text.push_str(s);
+                text.push('\n');
+            }
+        }
+        Some(text)
+    }
+
+    /// The decisions by file, in the order the files were given.
+    pub fn files(&self) -> Vec<ReviewedFile> {
+        let mut decisions = self.decisions.iter();
+        self.diff
+            .diffs()
+            .iter()
+            .map(|file| ReviewedFile {
+                path: file.path.clone(),
+                decisions: decisions.by_ref().take(file.hunks.len()).cloned().collect(),
+            })
+            .collect()
+    }
+
+    fn set(&mut self, index: usize, decision: Option<HunkDecision>) {
+        if index >= self.decisions.len() {
+            return;
+        }
+        let label = decision.as_ref().map(|d| d.label().to_string());
+        self.decisions[index] = decision;
+        self.diff.set_hunk_label(index, label);
+    }
+
+    /// Move to the first undecided hunk after `from`, wrapping around.
+    fn next_undecided(&mut self, from: usize) {
+        let count = self.decisions.len();
+        if let Some(index) = (1..=count)
+            .map(|offset| (from + offset) % count)
+            .find(|&i| self.decisions[i].is_none())
+        {
+            self.diff.select_hunk(index);
+        } else if from + 1 < count {
+            self.diff.select_hunk(from + 1);
+        }
+    }
+
+    /// Render the review over most of the area.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let width = area.width.saturating_sub(4).max(area.width.min(20));
+        let height = area.height.saturating_sub(2).max(area.height.min(6));
+        let view = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        frame.render_widget(Clear, view);
+
+        let diff_area = Rect::new(view.x, view.y, view.width, view.height.saturating_sub(1));
+        self.diff.render(frame, diff_area, theme);
+
+        let footer = Rect::new(
+            view.x,
+            view.y + view.height.saturating_sub(1),
+            view.width,
+            1,
+        );
+        let progress = format!(
+            " Hunk {}/{} · {} decided ",
+            (self.current() + 1).min(self.hunk_count()),
+            self.hunk_count(),
+            self.decided_count()
+        );
+        let mut spans = vec![Span::styled(progress, theme.accent_style())];
+        for (key, what) in [
+            ("a", "accept"),
+            ("r", "reject"),
+            ("e", "edit"),
+            ("A/R", "rest of file"),
+            ("u", "undo"),
+            ("n/p", "move"),
+            ("enter", "apply"),
+            ("esc", "cancel"),
+        ] {
+            spans.push(Span::styled(format!(" {key} "), theme.primary_style()));
+            spans.push(Span::styled(what, theme.muted_style()));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), footer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "--- a/a.rs\n+++ b/a.rs\n@@ -1,2 +1,2 @@\n fn a() {\n-    old();\n+    new();\n@@ -10,1 +10,2 @@\n x\n+y\n--- a/b.rs\n+++ b/b.rs\n@@ -1,1 +1,1 @@\n-b\n+c\n";
+
+    fn view() -> ReviewView {
+        ReviewView::new(FileDiff::parse_unified(DIFF))
+    }
+
+    #[test]
+    fn test_decide_moves_to_next_undecided() {
+        let mut view = view();
+        assert_eq!(view.hunk_count(), 3);
+        assert_eq!(view.current_path(), Some("a.rs"));
+
+        view.handle_nav(DiffNavAction::NextHunk);
+        view.decide(HunkDecision::Reject);
+        assert_eq!(view.current(), 2);
+        view.decide(HunkDecision::Accept);
+        // Wraps around to the first hunk
+        assert_eq!(view.current(), 0);
+        view.decide(HunkDecision::Edit("fn a() {\n    mine();\n".to_string()));
+        assert_eq!(view.decided_count(), 3);
+
+        assert_eq!(
+            view.files(),
+            vec![
+                ReviewedFile {
+                    path: "a.rs".to_string(),
+                    decisions: vec![
+                        Some(HunkDecision::Edit("fn a() {\n    mine();\n".to_string())),
+                        Some(HunkDecision::Reject),
+                    ],
+                },
+                ReviewedFile {
+                    path: "b.rs".to_string(),
+                    decisions: vec![Some(HunkDecision::Accept)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decide_file() {
+        let mut view = view();
+        view.decide_file(HunkDecision::Reject);
+        assert_eq!(view.decision(1), Some(&HunkDecision::Reject));
+        assert_eq!(view.decision(2), None);
+        assert_eq!(view.current_path(), Some("b.rs"));
+
+        view.undo();
+        view.handle_nav(DiffNavAction::FirstHunk);
+        view.undo();
+        assert_eq!(view.decided_count(), 1);
+    }
+
+    #[test]
+    fn test_hunk_text() {
+        let mut view = view();
+        assert_eq!(view.hunk_text().as_deref(), Some("fn a() {\n    new();\n"));
+        view.decide(HunkDecision::Edit("edited\n".to_string()));
+        view.handle_nav(DiffNavAction::FirstHunk);
+        assert_eq!(view.hunk_text().as_deref(), Some("edited\n"));
+    }
+
+    #[test]
+    fn test_render() {
+        let backend = ratatui::backend::TestBackend::new(100, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let theme = Theme::default();
+        let mut view = view();
+        view.decide(HunkDecision::Accept);
+        terminal
+            .draw(|frame| view.render(frame, frame.area(), &theme))
+            .unwrap();
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(text.contains("accepted"));
+        assert!(text.contains("Hunk 2/3"));
+    }
+}
@@ -1,6 +1,7 @@
 //! File autocomplete widget.
 //!
-//! Provides autocomplete suggestions for file paths when typing '@'.
+//! Provides autocomplete suggestions for file paths when typing '@', followed
+//! by workspace symbols from the language servers once they arrive.
 
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
@@ -16,6 +17,21 @@ use wonopcode_tui_core::Theme;
 /// Maximum number of suggestions to show.
 const MAX_SUGGESTIONS: usize = 10;
 
+/// A workspace symbol suggested for an `@` mention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolSuggestion {
+    /// Name of the symbol.
+    pub name: String,
+    /// Kind of the symbol (e.g., "function").
+    pub kind: String,
+    /// Path of the file defining the symbol, relative to the project.
+    pub path: String,
+    /// First line of the symbol (1-based).
+    pub start_line: u32,
+    /// Last line of the symbol (1-based, inclusive).
+    pub end_line: u32,
+}
+
 /// File autocomplete state and logic.
 #[derive(Debug, Clone, Default)]
 pub struct FileAutocomplete {
@@ -27,7 +43,9 @@ pub struct FileAutocomplete {
     trigger_pos: usize,
     /// Current suggestions.
     suggestions: Vec<String>,
-    /// Selected index.
+    /// Symbols matching the filter, listed after the files.
+    symbols: Vec<SymbolSuggestion>,
+    /// Selected index, over the files and then the symbols.
     selected: usize,
     /// Working directory for file search.
     cwd: PathBuf,
@@ -63,6 +81,7 @@ impl FileAutocomplete {
         self.visible = false;
         self.filter.clear();
         self.suggestions.clear();
+        self.symbols.clear();
         self.selected = 0;
     }
 
@@ -88,9 +107,26 @@ impl FileAutocomplete {
         self.suggestions.get(self.selected).map(|s| s.as_str())
     }
 
+    /// Show the workspace symbols found for a filter, unless the filter has
+    /// changed since.
+    pub fn set_symbols(&mut self, filter: &str, symbols: Vec<SymbolSuggestion>) {
+        if !self.visible || filter != self.filter {
+            return;
+        }
+        self.symbols = symbols;
+        self.symbols.truncate(MAX_SUGGESTIONS);
+        self.selected = self.selected.min(self.len().saturating_sub(1));
+    }
+
+    /// Number of suggestions, files and symbols.
+    fn len(&self) -> usize {
+        self.suggestions.len() + self.symbols.len()
+    }
+
     /// Update suggestions based on current filter.
     fn update_suggestions(&mut self) {
         self.suggestions.clear();
+        self.symbols.clear();
 
         if self.cwd.as_os_str().is_empty() {
             return;
@@ -170,13 +206,13 @@ impl FileAutocomplete {
             KeyCode::Up => {
                 if self.selected > 0 {
                     self.selected -= 1;
-                } else if !self.suggestions.is_empty() {
-                    self.selected = self.suggestions.len() - 1;
+                } else if self.len() > 0 {
+                    self.selected = self.len() - 1;
                 }
                 AutocompleteAction::Handled
             }
             KeyCode::Down => {
-                if self.selected < self.suggestions.len().saturating_sub(1) {
+                if self.selected < self.len().saturating_sub(1) {
                     self.selected += 1;
                 } else {
                     self.selected = 0;
@@ -188,6 +224,14 @@ impl FileAutocomplete {
                     let result = suggestion.to_string();
                     self.hide();
                     AutocompleteAction::Select(result)
+                } else if let Some(symbol) = self
+                    .selected
+                    .checked_sub(self.suggestions.len())
+                    .and_then(|i| self.symbols.get(i))
+                {
+                    let symbol = symbol.clone();
+                    self.hide();
+                    AutocompleteAction::SelectSymbol(symbol)
                 } else {
                     self.hide();
                     AutocompleteAction::Handled
@@ -203,12 +247,12 @@ impl FileAutocomplete {
 
     /// Render the autocomplete popup.
     pub fn render(&self, frame: &mut Frame, input_area: Rect, theme: &Theme) {
-        if !self.visible || self.suggestions.is_empty() {
+        if !self.visible || self.len() == 0 {
             return;
         }
 
         // Position above the input
-        let height = (self.suggestions.len() as u16 + 2).min(12);
+        let height = (self.len() as u16 + 2).min(12);
         let width = input_area.width.min(60);
 
         let popup_area = Rect::new(
@@ -221,14 +265,16 @@ impl FileAutocomplete {
         // Clear the area first
         frame.render_widget(Clear, popup_area);
 
+        let selected_style = Style::default().fg(theme.background).bg(theme.primary);
+
         // Create list items
-        let items: Vec<ListItem> = self
+        let mut items: Vec<ListItem> = self
             .suggestions
             .iter()
             .enumerate()
             .map(|(i, s)| {
                 let style = if i == self.selected {
-                    Style::default().fg(theme.background).bg(theme.primary)
+                    selected_style
                 } else {
                     theme.text_style()
                 };
@@ -241,12 +287,33 @@ impl FileAutocomplete {
                 ]))
             })
             .collect();
-
+        items.extend(self.symbols.iter().enumerate().map(|(i, symbol)| {
+            let selected = self.suggestions.len() + i == self.selected;
+            let (style, location_style) = if selected {
+                (selected_style, selected_style)
+            } else {
+                (theme.text_style(), theme.muted_style())
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled("🔣 ", style),
+                Span::styled(symbol.name.clone(), style),
+                Span::styled(
+                    format!("  {} {}:{}", symbol.kind, symbol.path, symbol.start_line),
+                    location_style,
+                ),
+            ]))
+        }));
+
+        let title = if self.symbols.is_empty() {
+            " Files "
+        } else {
+            " Files · Symbols "
+        };
         let block = Block::default()
             .borders(Borders::ALL)
             .border_style(Style::default().fg(theme.border))
             .style(Style::default().bg(theme.background_element))
-            .title(" Files ");
+            .title(title);
 
         let list = List::new(items).block(block);
 
@@ -282,6 +349,8 @@ pub enum AutocompleteAction {
     Handled,
     /// A suggestion was selected.
     Select(String),
+    /// A workspace symbol was selected.
+    SelectSymbol(SymbolSuggestion),
 }
 
 #[cfg(test)]
@@ -454,6 +523,41 @@ mod tests {
         assert_eq!(ac.selected_suggestion(), None);
     }
 
+    fn symbol(name: &str) -> SymbolSuggestion {
+        SymbolSuggestion {
+            name: name.to_string(),
+            kind: "function".to_string(),
+            path: "src/lib.rs".to_string(),
+            start_line: 10,
+            end_line: 20,
+        }
+    }
+
+    #[test]
+    fn test_file_autocomplete_symbols() {
+        let mut ac = FileAutocomplete::new();
+        ac.show(0, "par");
+        ac.suggestions = vec!["src/parser.rs".to_string()];
+
+        // Symbols for an outdated filter are dropped
+        ac.set_symbols("pa", vec![symbol("parse")]);
+        assert!(ac.symbols.is_empty());
+        ac.set_symbols("par", vec![symbol("parse"), symbol("parse_all")]);
+        assert_eq!(ac.len(), 3);
+
+        let down = KeyEvent::new(KeyCode::Down, crossterm::event::KeyModifiers::NONE);
+        ac.handle_key(down);
+        ac.handle_key(down);
+        assert_eq!(ac.selected_suggestion(), None);
+        let enter = KeyEvent::new(KeyCode::Enter, crossterm::event::KeyModifiers::NONE);
+        assert_eq!(
+            ac.handle_key(enter),
+            AutocompleteAction::SelectSymbol(symbol("parse_all"))
+        );
+        assert!(!ac.is_visible());
+        assert!(ac.symbols.is_empty());
+    }
+
     #[test]
     fn test_file_autocomplete_clone() {
         let mut ac = FileAutocomplete::new();
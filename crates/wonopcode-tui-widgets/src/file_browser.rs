@@ -0,0 +1,667 @@
+//! File browser widget.
+//!
+//! Shows the project as a tree, skipping files ignored by git, and marks the
+//! files the agent modified. For a project on another machine, the tree is
+//! built from directory listings the server sends instead.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use wonopcode_tui_core::Theme;
+
+/// Width of the browser pane.
+const BROWSER_WIDTH: u16 = 32;
+
+/// A visible row of the tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TreeEntry {
+    /// Path relative to the root.
+    path: PathBuf,
+    /// Nesting level, 0 for entries in the root.
+    depth: usize,
+    is_dir: bool,
+}
+
+/// An entry of a directory listed by a server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedFile {
+    /// File name.
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Directory listings of a project on another machine.
+#[derive(Debug, Clone, Default)]
+struct RemoteListings {
+    /// Listed directories, relative to the root.
+    listings: HashMap<PathBuf, Vec<ListedFile>>,
+    /// Directories asked for since the last refresh.
+    requested: BTreeSet<PathBuf>,
+    /// Directories to ask the server for.
+    wanted: Vec<PathBuf>,
+}
+
+/// Action returned from file browser key handling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileBrowserAction {
+    /// No action taken.
+    None,
+    /// Key was handled.
+    Handled,
+    /// Open a file in the viewer.
+    Open(String),
+    /// Add a file or directory as `@` context.
+    AddContext(String),
+    /// Jump to the changes the agent made to a file.
+    ShowDiff(String),
+    /// Give focus back to the input.
+    Unfocus,
+    /// Close the browser.
+    Close,
+}
+
+/// Tree view of the project files.
+#[derive(Debug, Clone, Default)]
+pub struct FileBrowser {
+    visible: bool,
+    focused: bool,
+    /// Project root.
+    root: PathBuf,
+    /// Expanded directories, relative to the root.
+    expanded: BTreeSet<PathBuf>,
+    /// Files modified by the agent, relative to the root.
+    modified: BTreeSet<PathBuf>,
+    /// Visible rows, in tree order.
+    entries: Vec<TreeEntry>,
+    selected: usize,
+    scroll: usize,
+    /// Rows that fit in the last render.
+    page: usize,
+    /// Where the tree was last rendered, for clicks.
+    list_area: Rect,
+    /// Listings from the server, if the files are not local.
+    remote: Option<RemoteListings>,
+}
+
+impl FileBrowser {
+    /// Create a hidden browser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the project root.
+    pub fn set_root(&mut self, root: PathBuf) {
+        if self.root != root {
+            self.root = root;
+            self.expanded.clear();
+            if let Some(remote) = &mut self.remote {
+                *remote = RemoteListings::default();
+            }
+            self.selected = 0;
+            self.scroll = 0;
+            if self.visible {
+                self.refresh();
+            }
+        }
+    }
+
+    /// Build the tree from listings given to [`Self::set_listing`] instead of
+    /// reading the local files, for a project on another machine.
+    pub fn set_remote(&mut self, remote: bool) {
+        self.remote = remote.then(RemoteListings::default);
+        if self.visible {
+            self.refresh();
+        }
+    }
+
+    /// Directories to list, relative to the root, when the tree is built
+    /// from a server's listings.
+    pub fn take_listing_requests(&mut self) -> Vec<String> {
+        let Some(remote) = &mut self.remote else {
+            return Vec::new();
+        };
+        remote
+            .wanted
+            .drain(..)
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// Set the entries of a directory listed by the server.
+    pub fn set_listing(&mut self, dir: &str, entries: Vec<ListedFile>) {
+        let Some(remote) = &mut self.remote else {
+            return;
+        };
+        remote.listings.insert(PathBuf::from(dir), entries);
+        self.rebuild();
+    }
+
+    /// Check if the browser is visible.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the browser, reading the tree when shown.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if visible {
+            self.refresh();
+        } else {
+            self.focused = false;
+        }
+    }
+
+    /// Toggle visibility.
+    pub fn toggle(&mut self) {
+        self.set_visible(!self.visible);
+    }
+
+    /// Check if the browser has focus.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Give or take focus.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused && self.visible;
+    }
+
+    /// Get the pane width.
+    pub fn width(&self) -> u16 {
+        BROWSER_WIDTH
+    }
+
+    /// Mark a file as modified by the agent. Absolute paths under the root
+    /// are made relative.
+    pub fn add_modified(&mut self, path: &str) {
+        let path = Path::new(path);
+        let path = path.strip_prefix(&self.root).unwrap_or(path);
+        self.modified.insert(path.to_path_buf());
+    }
+
+    /// Forget the modified files.
+    pub fn clear_modified(&mut self) {
+        self.modified.clear();
+    }
+
+    /// Check if the agent modified a file.
+    pub fn is_modified(&self, path: &str) -> bool {
+        self.modified.contains(Path::new(path))
+    }
+
+    /// Path of the selected entry, relative to the root.
+    pub fn selected_path(&self) -> Option<String> {
+        self.entries
+            .get(self.selected)
+            .map(|e| e.path.to_string_lossy().to_string())
+    }
+
+    /// Read the expanded directories again, keeping the selection.
+    pub fn refresh(&mut self) {
+        match &mut self.remote {
+            // Listed again as the tree is rebuilt
+            Some(remote) => remote.requested.clear(),
+            None => self.expanded.retain(|dir| self.root.join(dir).is_dir()),
+        }
+        self.rebuild();
+    }
+
+    /// Rebuild the visible rows, keeping the selection.
+    fn rebuild(&mut self) {
+        let selected = self.entries.get(self.selected).map(|e| e.path.clone());
+        self.entries.clear();
+        self.read_dir(Path::new(""), 0);
+        self.selected = selected
+            .and_then(|path| self.entries.iter().position(|e| e.path == path))
+            .unwrap_or(self.selected)
+            .min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Append a directory's entries, and those of its expanded
+    /// subdirectories, directories first.
+    fn read_dir(&mut self, dir: &Path, depth: usize) {
+        if self.root.as_os_str().is_empty() {
+            return;
+        }
+        let mut children: Vec<TreeEntry> = match &mut self.remote {
+            Some(remote) => {
+                // Shown as last listed until the new listing arrives
+                if remote.requested.insert(dir.to_path_buf()) {
+                    remote.wanted.push(dir.to_path_buf());
+                }
+                remote
+                    .listings
+                    .get(dir)
+                    .into_iter()
+                    .flatten()
+                    .map(|e| TreeEntry {
+                        path: dir.join(&e.name),
+                        depth,
+                        is_dir: e.is_dir,
+                    })
+                    .collect()
+            }
+            None => ignore::WalkBuilder::new(self.root.join(dir))
+                .hidden(true)
+                .git_ignore(true)
+                .git_global(true)
+                .git_exclude(true)
+                .max_depth(Some(1))
+                .build()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.depth() == 1)
+                .map(|e| TreeEntry {
+                    path: dir.join(e.file_name()),
+                    depth,
+                    is_dir: e.file_type().is_some_and(|t| t.is_dir()),
+                })
+                .collect(),
+        };
+        children.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.path.cmp(&b.path)));
+
+        for child in children {
+            let expand = child.is_dir && self.expanded.contains(&child.path);
+            let path = child.path.clone();
+            self.entries.push(child);
+            if expand {
+                self.read_dir(&path, depth + 1);
+            }
+        }
+    }
+
+    /// Expand or collapse the selected directory.
+    fn toggle_selected(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        if !entry.is_dir {
+            return;
+        }
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+        self.rebuild();
+    }
+
+    /// Select the parent directory of the selected entry.
+    fn select_parent(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        let Some(parent) = entry.path.parent().filter(|p| !p.as_os_str().is_empty()) else {
+            return;
+        };
+        if let Some(index) = self.entries.iter().position(|e| e.path == parent) {
+            self.selected = index;
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Handle a key event.
+    pub fn handle_key(&mut self, key: KeyEvent) -> FileBrowserAction {
+        if !self.visible || !self.focused {
+            return FileBrowserAction::None;
+        }
+        let entry = self.entries.get(self.selected).cloned();
+        let path = entry.as_ref().map(|e| e.path.to_string_lossy().to_string());
+
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected + 1),
+            KeyCode::PageUp => self.select(self.selected.saturating_sub(self.page.max(1))),
+            KeyCode::PageDown => self.select(self.selected + self.page.max(1)),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+            KeyCode::Right | KeyCode::Char('l') => match &entry {
+                Some(e) if e.is_dir && !self.expanded.contains(&e.path) => self.toggle_selected(),
+                Some(e) if e.is_dir => self.select(self.selected + 1),
+                _ => {}
+            },
+            KeyCode::Left | KeyCode::Char('h') => match &entry {
+                Some(e) if e.is_dir && self.expanded.contains(&e.path) => self.toggle_selected(),
+                _ => self.select_parent(),
+            },
+            KeyCode::Enter | KeyCode::Char('o') => match (&entry, path) {
+                (Some(e), _) if e.is_dir => self.toggle_selected(),
+                (Some(_), Some(path)) => return FileBrowserAction::Open(path),
+                _ => {}
+            },
+            KeyCode::Char('@') | KeyCode::Char('a') => {
+                if let Some(path) = path {
+                    return FileBrowserAction::AddContext(path);
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(path) = path.filter(|_| entry.is_some_and(|e| !e.is_dir)) {
+                    return FileBrowserAction::ShowDiff(path);
+                }
+            }
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Tab | KeyCode::Esc => {
+                self.focused = false;
+                return FileBrowserAction::Unfocus;
+            }
+            KeyCode::Char('q') => {
+                self.set_visible(false);
+                return FileBrowserAction::Close;
+            }
+            _ => return FileBrowserAction::None,
+        }
+        FileBrowserAction::Handled
+    }
+
+    /// Handle a click on the last rendered tree: select the clicked entry,
+    /// or open it if it was already selected. Returns `None` if the click is
+    /// outside the tree.
+    pub fn handle_click(&mut self, x: u16, y: u16) -> Option<FileBrowserAction> {
+        let area = self.list_area;
+        if !self.visible
+            || x < area.x
+            || x >= area.x + area.width
+            || y < area.y
+            || y >= area.y + area.height
+        {
+            return None;
+        }
+        let index = self.scroll + (y - area.y) as usize;
+        if index >= self.entries.len() {
+            return Some(FileBrowserAction::Handled);
+        }
+        if index != self.selected {
+            self.selected = index;
+            return Some(FileBrowserAction::Handled);
+        }
+        let focused = self.focused;
+        self.focused = true;
+        let action = self.handle_key(KeyEvent::from(KeyCode::Enter));
+        self.focused = focused;
+        Some(action)
+    }
+
+    /// Scroll the view.
+    pub fn handle_scroll(&mut self, up: bool) {
+        if up {
+            self.scroll = self.scroll.saturating_sub(3);
+        } else {
+            self.scroll = (self.scroll + 3).min(self.entries.len().saturating_sub(1));
+        }
+    }
+
+    /// Check if a directory contains files modified by the agent.
+    fn has_modified(&self, dir: &Path) -> bool {
+        self.modified.iter().any(|m| m.starts_with(dir) && m != dir)
+    }
+
+    /// Render the browser.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        if !self.visible || area.width < 10 || area.height < 6 {
+            self.list_area = Rect::default();
+            return;
+        }
+
+        // Background fill with panel color (leaving 1 row at top for status bar)
+        let bg_area = Rect::new(
+            area.x,
+            area.y + 1,
+            area.width,
+            area.height.saturating_sub(1),
+        );
+        frame.render_widget(
+            Block::default().style(Style::default().bg(theme.background_panel)),
+            bg_area,
+        );
+
+        let inner = Rect::new(
+            area.x + 1,
+            area.y + 2,
+            area.width.saturating_sub(2),
+            area.height.saturating_sub(4),
+        );
+        let header_area = Rect::new(inner.x, inner.y, inner.width, 1);
+        self.list_area = Rect::new(
+            inner.x,
+            inner.y + 2,
+            inner.width,
+            inner.height.saturating_sub(3),
+        );
+        let footer_area = Rect::new(inner.x, inner.y + inner.height - 1, inner.width, 1);
+
+        let title_style = if self.focused {
+            theme.accent_style().add_modifier(Modifier::BOLD)
+        } else {
+            theme.muted_style().add_modifier(Modifier::BOLD)
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled("Files", title_style))),
+            header_area,
+        );
+
+        // Keep the selection in view
+        self.page = self.list_area.height as usize;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.page > 0 && self.selected >= self.scroll + self.page {
+            self.scroll = self.selected + 1 - self.page;
+        }
+
+        let lines: Vec<Line> = if self.entries.is_empty() {
+            vec![Line::from(Span::styled("No files", theme.muted_style()))]
+        } else {
+            self.entries
+                .iter()
+                .enumerate()
+                .skip(self.scroll)
+                .take(self.page)
+                .map(|(i, entry)| self.entry_line(i, entry, theme))
+                .collect()
+        };
+        frame.render_widget(Paragraph::new(lines), self.list_area);
+
+        let hint = if self.focused {
+            "enter open · @ context · d diff"
+        } else {
+            "<leader>f to focus"
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(Span::styled(hint, theme.dim_style()))),
+            footer_area,
+        );
+    }
+
+    /// A row of the tree: indentation, icon, name and modified marker.
+    fn entry_line(&self, index: usize, entry: &TreeEntry, theme: &Theme) -> Line<'static> {
+        let name = entry
+            .path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let (icon, name) = if !entry.is_dir {
+            ("  ", name)
+        } else if self.expanded.contains(&entry.path) {
+            ("▾ ", format!("{name}/"))
+        } else {
+            ("▸ ", format!("{name}/"))
+        };
+        let marker = if self.modified.contains(&entry.path) {
+            Span::styled(" ●", Style::default().fg(theme.warning))
+        } else if entry.is_dir && self.has_modified(&entry.path) {
+            Span::styled(" •", theme.muted_style())
+        } else {
+            Span::raw("")
+        };
+
+        let style = if index == self.selected && self.focused {
+            Style::default().fg(theme.background).bg(theme.primary)
+        } else if index == self.selected {
+            theme.text_style().add_modifier(Modifier::REVERSED)
+        } else if entry.is_dir {
+            theme.text_style().add_modifier(Modifier::BOLD)
+        } else {
+            theme.text_style()
+        };
+        Line::from(vec![
+            Span::raw("  ".repeat(entry.depth)),
+            Span::styled(format!("{icon}{name}"), style),
+            marker,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn setup() -> (tempfile::TempDir, FileBrowser) {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        fs::write(dir.path().join("src/nested/mod.rs"), "").unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+        fs::write(dir.path().join("ignored.log"), "").unwrap();
+        fs::write(dir.path().join(".ignore"), "*.log\n").unwrap();
+
+        let mut browser = FileBrowser::new();
+        browser.set_root(dir.path().to_path_buf());
+        browser.set_visible(true);
+        browser.set_focused(true);
+        (dir, browser)
+    }
+
+    fn paths(browser: &FileBrowser) -> Vec<String> {
+        browser
+            .entries
+            .iter()
+            .map(|e| e.path.to_string_lossy().to_string())
+            .collect()
+    }
+
+    fn press(browser: &mut FileBrowser, code: KeyCode) -> FileBrowserAction {
+        browser.handle_key(KeyEvent::from(code))
+    }
+
+    #[test]
+    fn test_tree_skips_ignored_and_lists_directories_first() {
+        let (_dir, browser) = setup();
+        assert_eq!(paths(&browser), vec!["src", "README.md"]);
+    }
+
+    #[test]
+    fn test_expand_collapse_and_open() {
+        let (_dir, mut browser) = setup();
+        assert_eq!(
+            press(&mut browser, KeyCode::Right),
+            FileBrowserAction::Handled
+        );
+        assert_eq!(
+            paths(&browser),
+            vec!["src", "src/nested", "src/lib.rs", "README.md"]
+        );
+
+        press(&mut browser, KeyCode::Down);
+        press(&mut browser, KeyCode::Down);
+        assert_eq!(browser.selected_path().as_deref(), Some("src/lib.rs"));
+        assert_eq!(
+            press(&mut browser, KeyCode::Enter),
+            FileBrowserAction::Open("src/lib.rs".to_string())
+        );
+        assert_eq!(
+            press(&mut browser, KeyCode::Char('@')),
+            FileBrowserAction::AddContext("src/lib.rs".to_string())
+        );
+        assert_eq!(
+            press(&mut browser, KeyCode::Char('d')),
+            FileBrowserAction::ShowDiff("src/lib.rs".to_string())
+        );
+
+        // Left goes to the parent, then collapses it
+        press(&mut browser, KeyCode::Left);
+        assert_eq!(browser.selected_path().as_deref(), Some("src"));
+        press(&mut browser, KeyCode::Left);
+        assert_eq!(paths(&browser), vec!["src", "README.md"]);
+    }
+
+    #[test]
+    fn test_refresh_keeps_selection() {
+        let (dir, mut browser) = setup();
+        press(&mut browser, KeyCode::Down);
+        fs::write(dir.path().join("CHANGELOG.md"), "").unwrap();
+        browser.refresh();
+        assert_eq!(paths(&browser), vec!["src", "CHANGELOG.md", "README.md"]);
+        assert_eq!(browser.selected_path().as_deref(), Some("README.md"));
+    }
+
+    #[test]
+    fn test_modified_markers() {
+        let (dir, mut browser) = setup();
+        browser.add_modified(&dir.path().join("src/lib.rs").to_string_lossy());
+        assert!(browser.is_modified("src/lib.rs"));
+        assert!(browser.has_modified(Path::new("src")));
+        assert!(!browser.has_modified(Path::new("src/nested")));
+
+        let backend = ratatui::backend::TestBackend::new(40, 12);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let theme = Theme::default();
+        terminal
+            .draw(|frame| browser.render(frame, frame.area(), &theme))
+            .unwrap();
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(text.contains("src/ •"));
+    }
+
+    #[test]
+    fn test_unfocused_ignores_keys() {
+        let (_dir, mut browser) = setup();
+        assert_eq!(
+            press(&mut browser, KeyCode::Tab),
+            FileBrowserAction::Unfocus
+        );
+        assert!(!browser.is_focused());
+        assert_eq!(press(&mut browser, KeyCode::Down), FileBrowserAction::None);
+    }
+
+    #[test]
+    fn test_remote_tree_is_built_from_listings() {
+        let listed = |name: &str, is_dir: bool| ListedFile {
+            name: name.to_string(),
+            is_dir,
+        };
+        let mut browser = FileBrowser::new();
+        browser.set_remote(true);
+        browser.set_root(PathBuf::from("/srv/project"));
+        browser.set_visible(true);
+        browser.set_focused(true);
+        assert_eq!(browser.take_listing_requests(), vec![""]);
+        assert!(browser.take_listing_requests().is_empty());
+
+        browser.set_listing("", vec![listed("src", true), listed("README.md", false)]);
+        assert_eq!(paths(&browser), vec!["src", "README.md"]);
+
+        press(&mut browser, KeyCode::Enter);
+        assert_eq!(browser.take_listing_requests(), vec!["src"]);
+        browser.set_listing("src", vec![listed("lib.rs", false)]);
+        assert_eq!(paths(&browser), vec!["src", "src/lib.rs", "README.md"]);
+
+        // Listed again on refresh, keeping the old listings meanwhile
+        browser.refresh();
+        assert_eq!(browser.take_listing_requests(), vec!["", "src"]);
+        assert_eq!(paths(&browser), vec!["src", "src/lib.rs", "README.md"]);
+    }
+}
@@ -11,7 +11,7 @@ use ratatui::{
 };
 use std::time::{Duration, Instant};
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{tr, tr_args, Theme};
 
 /// Status to display in the footer.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -55,36 +55,54 @@ impl FooterMode {
     /// Get the display name for the mode.
     pub fn name(&self) -> &'static str {
         match self {
-            FooterMode::Input => "INPUT",
-            FooterMode::Scroll => "SCROLL",
-            FooterMode::Select => "SELECT",
-            FooterMode::Search => "SEARCH",
-            FooterMode::Waiting => "WAITING",
-            FooterMode::Leader => "CTRL+X",
+            FooterMode::Input => tr("footer.mode.input"),
+            FooterMode::Scroll => tr("footer.mode.scroll"),
+            FooterMode::Select => tr("footer.mode.select"),
+            FooterMode::Search => tr("footer.mode.search"),
+            FooterMode::Waiting => tr("footer.mode.waiting"),
+            FooterMode::Leader => tr("footer.mode.leader"),
         }
     }
 
     /// Get contextual keybinding hints for the mode.
-    pub fn hints(&self) -> &'static [(&'static str, &'static str)] {
-        match self {
+    pub fn hints(&self) -> Vec<(&'static str, &'static str)> {
+        let hints: &[(&str, &str)] = match self {
             FooterMode::Input => &[
-                ("Enter", "send"),
-                ("Esc", "scroll"),
-                ("^X", "leader"),
-                ("^P", "commands"),
+                ("Enter", "footer.hint.send"),
+                ("Esc", "footer.hint.scroll"),
+                ("^X", "footer.hint.leader"),
+                ("^P", "footer.hint.commands"),
             ],
             FooterMode::Scroll => &[
-                ("j/k", "scroll"),
-                ("v", "select"),
-                ("y", "copy"),
-                ("i", "input"),
-                ("^X", "leader"),
+                ("j/k", "footer.hint.scroll"),
+                ("v", "footer.hint.select"),
+                ("y", "footer.hint.copy"),
+                ("i", "footer.hint.input"),
+                ("^X", "footer.hint.leader"),
             ],
-            FooterMode::Select => &[("j/k", "navigate"), ("y", "copy"), ("Esc", "cancel")],
-            FooterMode::Search => &[("n/N", "next/prev"), ("Enter", "go to"), ("Esc", "cancel")],
-            FooterMode::Waiting => &[("Esc", "cancel")],
-            FooterMode::Leader => &[("N", "new"), ("L", "sessions"), ("M", "model")],
-        }
+            FooterMode::Select => &[
+                ("j/k", "footer.hint.navigate"),
+                ("y", "footer.hint.copy"),
+                ("e", "footer.hint.edit"),
+                ("Esc", "footer.hint.cancel"),
+            ],
+            FooterMode::Search => &[
+                ("↑/↓", "footer.hint.prev_next"),
+                ("Enter", "footer.hint.go_to"),
+                ("Tab", "footer.hint.all_sessions"),
+                ("Esc", "footer.hint.cancel"),
+            ],
+            FooterMode::Waiting => &[("Esc", "footer.hint.cancel")],
+            FooterMode::Leader => &[
+                ("N", "footer.hint.new"),
+                ("L", "footer.hint.sessions"),
+                ("M", "footer.hint.model"),
+            ],
+        };
+        hints
+            .iter()
+            .map(|&(key, action)| (key, tr(action)))
+            .collect()
     }
 }
 
@@ -101,6 +119,10 @@ pub struct FooterWidget {
     provider: String,
     /// Whether connected.
     connected: bool,
+    /// Attempt to reconnect, while not connected.
+    reconnect_attempt: u32,
+    /// Clients sharing the session and whether this one watches, if shared.
+    presence: Option<(usize, bool)>,
     /// Status (Ready/Thinking/Running).
     status: FooterStatus,
     /// Token counts (input, output).
@@ -123,6 +145,8 @@ pub struct FooterWidget {
     spinner_last_update: Instant,
     /// Spinner animation frames (braille spinner).
     spinner_frames: Vec<&'static str>,
+    /// Whether the spinner animates, or shows a static indicator.
+    animated: bool,
 }
 
 impl Default for FooterWidget {
@@ -133,6 +157,8 @@ impl Default for FooterWidget {
             model: String::new(),
             provider: String::new(),
             connected: true,
+            reconnect_attempt: 0,
+            presence: None,
             status: FooterStatus::default(),
             tokens: None,
             pending_permissions: 0,
@@ -144,6 +170,7 @@ impl Default for FooterWidget {
             spinner_frame: 0,
             spinner_last_update: Instant::now(),
             spinner_frames: vec!["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            animated: true,
         }
     }
 }
@@ -172,6 +199,19 @@ impl FooterWidget {
     /// Set connection status.
     pub fn set_connected(&mut self, connected: bool) {
         self.connected = connected;
+        self.reconnect_attempt = 0;
+    }
+
+    /// Show how many clients share the session and whether this one
+    /// watches. Nothing is shown while it has it to itself and drives.
+    pub fn set_presence(&mut self, count: usize, watching: bool) {
+        self.presence = (count > 1 || watching).then_some((count, watching));
+    }
+
+    /// Show that the connection dropped and is being retried.
+    pub fn set_reconnecting(&mut self, attempt: u32) {
+        self.connected = false;
+        self.reconnect_attempt = attempt;
     }
 
     /// Set status (Ready/Thinking/Running).
@@ -187,17 +227,44 @@ impl FooterWidget {
         )
     }
 
+    /// Get the status.
+    pub fn status(&self) -> &FooterStatus {
+        &self.status
+    }
+
     /// Set the token counts.
     pub fn set_tokens(&mut self, input: u32, output: u32) {
         self.tokens = Some((input, output));
     }
 
+    /// Get the token counts (input, output), if any were reported.
+    pub fn tokens(&self) -> Option<(u32, u32)> {
+        self.tokens
+    }
+
+    /// Forget the token counts.
+    pub fn clear_tokens(&mut self) {
+        self.tokens = None;
+    }
+
+    /// Set whether the spinner animates.
+    pub fn set_animated(&mut self, animated: bool) {
+        self.animated = animated;
+    }
+
+    /// Check if the footer needs redrawing to animate.
+    pub fn is_animating(&self) -> bool {
+        self.animated && self.is_busy()
+    }
+
     /// Tick the spinner animation.
     pub fn tick(&mut self) {
-        if matches!(
-            self.status,
-            FooterStatus::Thinking | FooterStatus::Running(_)
-        ) {
+        if self.animated
+            && matches!(
+                self.status,
+                FooterStatus::Thinking | FooterStatus::Running(_)
+            )
+        {
             let speed = Duration::from_millis(80);
             if self.spinner_last_update.elapsed() >= speed {
                 self.spinner_frame = (self.spinner_frame + 1) % self.spinner_frames.len();
@@ -208,7 +275,11 @@ impl FooterWidget {
 
     /// Get the current spinner character.
     fn spinner_char(&self) -> &'static str {
-        self.spinner_frames[self.spinner_frame]
+        if self.animated {
+            self.spinner_frames[self.spinner_frame]
+        } else {
+            "•"
+        }
     }
 
     /// Set the number of pending permissions.
@@ -261,11 +332,14 @@ impl FooterWidget {
         // Status indicator (Ready/Thinking/Running with spinner)
         match &self.status {
             FooterStatus::Idle => {
-                spans.push(Span::styled("Ready", theme.success_style()));
+                spans.push(Span::styled(tr("footer.ready"), theme.success_style()));
             }
             FooterStatus::Thinking => {
                 spans.push(Span::styled(self.spinner_char(), theme.warning_style()));
-                spans.push(Span::styled(" Thinking", theme.warning_style()));
+                spans.push(Span::styled(
+                    format!(" {}", tr("footer.thinking")),
+                    theme.warning_style(),
+                ));
             }
             FooterStatus::Running(action) => {
                 spans.push(Span::styled(self.spinner_char(), theme.warning_style()));
@@ -293,11 +367,11 @@ impl FooterWidget {
         // Key hints for current mode (keys in bold white)
         for (key, action) in self.mode.hints() {
             spans.push(Span::styled(
-                *key,
+                key,
                 theme.text_style().add_modifier(Modifier::BOLD),
             ));
             spans.push(Span::styled(":", theme.muted_style()));
-            spans.push(Span::styled(*action, theme.muted_style()));
+            spans.push(Span::styled(action, theme.muted_style()));
             spans.push(Span::styled(" ", theme.text_style()));
         }
 
@@ -340,13 +414,41 @@ impl FooterWidget {
         // Build right side
         let mut right_parts = vec![];
 
+        // Dropped connection (error style, first so it's seen)
+        if !self.connected {
+            right_parts.push(Span::styled("⚠ ", theme.error_style()));
+            right_parts.push(Span::styled(
+                tr_args(
+                    "footer.reconnecting",
+                    &[("attempt", &self.reconnect_attempt)],
+                ),
+                theme.error_style(),
+            ));
+            right_parts.push(Span::styled("  ", theme.text_style()));
+        }
+
+        // Shared session (info style while driving, warning while watching)
+        if let Some((count, watching)) = self.presence {
+            let (key, style) = if watching {
+                ("footer.watching", theme.warning_style())
+            } else {
+                ("footer.driving", theme.info_style())
+            };
+            right_parts.push(Span::styled("◎ ", style));
+            right_parts.push(Span::styled(tr_args(key, &[("count", &count)]), style));
+            right_parts.push(Span::styled("  ", theme.text_style()));
+        }
+
         // Pending permissions (warning style, prominent)
         if self.pending_permissions > 0 {
             right_parts.push(Span::styled("◉ ", theme.warning_style()));
             let label = if self.pending_permissions == 1 {
-                "1 permission".to_string()
+                tr("footer.permission").to_string()
             } else {
-                format!("{} permissions", self.pending_permissions)
+                tr_args(
+                    "footer.permissions",
+                    &[("count", &self.pending_permissions)],
+                )
             };
             right_parts.push(Span::styled(label, theme.warning_style()));
             right_parts.push(Span::styled("  ", theme.text_style()));
@@ -519,7 +621,7 @@ mod tests {
     #[test]
     fn test_footer_mode_hints_search() {
         let hints = FooterMode::Search.hints();
-        assert!(hints.iter().any(|(k, _)| *k == "n/N"));
+        assert!(hints.iter().any(|(k, _)| *k == "↑/↓"));
     }
 
     #[test]
@@ -592,6 +694,28 @@ mod tests {
         assert!(!widget.connected);
     }
 
+    #[test]
+    fn test_footer_widget_set_presence() {
+        let mut widget = FooterWidget::new();
+        widget.set_presence(1, false);
+        assert_eq!(widget.presence, None);
+        widget.set_presence(3, true);
+        assert_eq!(widget.presence, Some((3, true)));
+        widget.set_presence(2, false);
+        assert_eq!(widget.presence, Some((2, false)));
+    }
+
+    #[test]
+    fn test_footer_widget_set_reconnecting() {
+        let mut widget = FooterWidget::new();
+        widget.set_reconnecting(3);
+        assert!(!widget.connected);
+        assert_eq!(widget.reconnect_attempt, 3);
+        widget.set_connected(true);
+        assert!(widget.connected);
+        assert_eq!(widget.reconnect_attempt, 0);
+    }
+
     #[test]
     fn test_footer_widget_set_status() {
         let mut widget = FooterWidget::new();
@@ -649,6 +773,19 @@ mod tests {
         assert_eq!(widget.spinner_char(), "⠋");
     }
 
+    #[test]
+    fn test_footer_widget_not_animated() {
+        let mut widget = FooterWidget::new();
+        widget.set_animated(false);
+        widget.set_status(FooterStatus::Thinking);
+        widget.spinner_last_update = Instant::now() - Duration::from_millis(100);
+        widget.tick();
+        assert_eq!(widget.spinner_frame, 0);
+        assert_eq!(widget.spinner_char(), "•");
+        assert!(widget.is_busy());
+        assert!(!widget.is_animating());
+    }
+
     #[test]
     fn test_footer_widget_set_pending_permissions() {
         let mut widget = FooterWidget::new();
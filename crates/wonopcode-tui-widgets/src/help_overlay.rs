@@ -210,6 +210,11 @@ impl HelpOverlay {
                     description: "Search messages",
                     category: "Search",
                 },
+                HelpEntry {
+                    key: "n / N",
+                    description: "Next/previous search match",
+                    category: "Search",
+                },
                 HelpEntry {
                     key: "i",
                     description: "Return to input mode",
@@ -237,6 +242,11 @@ impl HelpOverlay {
                     description: "Copy and stay",
                     category: "Clipboard",
                 },
+                HelpEntry {
+                    key: "e",
+                    description: "Edit prompt and resubmit",
+                    category: "Edit",
+                },
                 HelpEntry {
                     key: "o",
                     description: "Expand/collapse tools",
@@ -255,20 +265,25 @@ impl HelpOverlay {
                     category: "Search",
                 },
                 HelpEntry {
-                    key: "n",
+                    key: "↓ / Ctrl+N",
                     description: "Next match",
                     category: "Navigation",
                 },
                 HelpEntry {
-                    key: "N",
+                    key: "↑ / Ctrl+P",
                     description: "Previous match",
                     category: "Navigation",
                 },
                 HelpEntry {
                     key: "Enter",
-                    description: "Go to match and close",
+                    description: "Go to match and close (n/N keep moving)",
                     category: "Navigation",
                 },
+                HelpEntry {
+                    key: "Tab",
+                    description: "Search all sessions instead",
+                    category: "Search",
+                },
                 HelpEntry {
                     key: "Esc",
                     description: "Cancel search",
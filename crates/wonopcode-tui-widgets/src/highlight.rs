@@ -0,0 +1,208 @@
+//! Lightweight highlighting of fenced code blocks in the input.
+//!
+//! The input is re-rendered on every keystroke, so this is a simple
+//! tokenizer for keywords, strings, comments, numbers, types and calls rather
+//! than a full grammar.
+
+use ratatui::style::Color;
+use wonopcode_tui_core::Theme;
+
+/// Words highlighted as keywords, across common languages.
+const KEYWORDS: &str = "and as async await bool break case catch chan char class const continue \
+    crate def default defer del do done dyn echo elif else enum esac except export extends false \
+    False fi finally fn for from func function go if impl import in int interface is lambda let \
+    loop match mod move mut new nil None not null or package pass pub raise range ref return \
+    select self Self static struct super switch then this throw trait true True try type use var \
+    void where while with yield";
+
+/// What a line of input is, for highlighting fenced code blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LineKind<'a> {
+    /// Plain text.
+    Text,
+    /// A line opening or closing a fenced code block.
+    Fence,
+    /// A line of code in a fenced block, with the language of the fence.
+    Code(&'a str),
+}
+
+/// Check if text contains a fenced code block.
+pub(crate) fn has_code_fence(text: &str) -> bool {
+    text.lines().any(is_fence)
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```")
+}
+
+/// Classify lines, tracking the fenced code blocks they open and close.
+pub(crate) fn line_kinds<'a>(lines: &[&'a str]) -> Vec<LineKind<'a>> {
+    let mut language = None;
+    lines
+        .iter()
+        .map(|line| {
+            if !is_fence(line) {
+                return match language {
+                    Some(language) => LineKind::Code(language),
+                    None => LineKind::Text,
+                };
+            }
+            language = match language {
+                Some(_) => None,
+                None => Some(
+                    line.trim_start()
+                        .trim_start_matches('`')
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or(""),
+                ),
+            };
+            LineKind::Fence
+        })
+        .collect()
+}
+
+/// The color of each character of a line of code; `None` for plain text.
+pub(crate) fn code_colors(line: &str, language: &str, theme: &Theme) -> Vec<Option<Color>> {
+    let chars: Vec<char> = line.chars().collect();
+    let comment = comment_prefix(language);
+    let mut colors = vec![None; chars.len()];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let color = if starts_with(&chars, i, comment) || starts_with(&chars, i, "/*") {
+            let end = if starts_with(&chars, i, "/*") {
+                find(&chars, i + 2, "*/").map_or(chars.len(), |end| end + 2)
+            } else {
+                chars.len()
+            };
+            i = end;
+            theme.syntax_comment
+        } else if matches!(c, '"' | '\'' | '`') {
+            match string_end(&chars, i, language) {
+                Some(end) => {
+                    i = end;
+                    theme.syntax_string
+                }
+                None => {
+                    i += 1;
+                    continue;
+                }
+            }
+        } else if c.is_ascii_digit() && (i == 0 || !is_word(chars[i - 1])) {
+            while i < chars.len() && (is_word(chars[i]) || chars[i] == '.') {
+                i += 1;
+            }
+            theme.syntax_number
+        } else if is_word(c) && !c.is_ascii_digit() {
+            while i < chars.len() && is_word(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let next = chars[i..].iter().find(|c| !c.is_whitespace());
+            if KEYWORDS.split_whitespace().any(|k| k == word) {
+                theme.syntax_keyword
+            } else if next == Some(&'(') || next == Some(&'!') {
+                theme.syntax_function
+            } else if c.is_uppercase() {
+                theme.syntax_type
+            } else {
+                continue;
+            }
+        } else {
+            i += 1;
+            continue;
+        };
+        colors[start..i].fill(Some(color));
+    }
+    colors
+}
+
+/// The line comment prefix of a language.
+fn comment_prefix(language: &str) -> &'static str {
+    match language.to_lowercase().as_str() {
+        "python" | "py" | "sh" | "bash" | "zsh" | "shell" | "ruby" | "rb" | "yaml" | "yml"
+        | "toml" | "perl" | "r" | "make" | "makefile" | "dockerfile" | "ini" | "conf" => "#",
+        "sql" | "lua" | "haskell" | "hs" => "--",
+        _ => "//",
+    }
+}
+
+/// The end of the string starting at `start`, if it ends on this line.
+fn string_end(chars: &[char], start: usize, language: &str) -> Option<usize> {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            c if c == quote => {
+                // In Rust a single quote is a lifetime unless it closes a char
+                let is_char = quote != '\'' || !matches!(language, "rust" | "rs") || i - start <= 3;
+                return is_char.then_some(i + 1);
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn starts_with(chars: &[char], at: usize, prefix: &str) -> bool {
+    prefix
+        .chars()
+        .enumerate()
+        .all(|(i, p)| chars.get(at + i) == Some(&p))
+}
+
+fn find(chars: &[char], from: usize, needle: &str) -> Option<usize> {
+    (from..chars.len()).find(|&i| starts_with(chars, i, needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_kinds() {
+        let lines = ["intro", "```rust", "let x = 1;", "```", "outro", "```"];
+        assert_eq!(
+            line_kinds(&lines),
+            vec![
+                LineKind::Text,
+                LineKind::Fence,
+                LineKind::Code("rust"),
+                LineKind::Fence,
+                LineKind::Text,
+                LineKind::Fence,
+            ]
+        );
+        assert!(has_code_fence("a\n  ```\nb"));
+        assert!(!has_code_fence("a\nb ```"));
+    }
+
+    #[test]
+    fn test_code_colors() {
+        let theme = Theme::default();
+        let line = "let s = \"hi\"; foo(42) // done";
+        let colors = code_colors(line, "rust", &theme);
+        let at = |needle: &str| colors[line.find(needle).unwrap()];
+        assert_eq!(at("let"), Some(theme.syntax_keyword));
+        assert_eq!(at("s ="), None);
+        assert_eq!(at("\"hi"), Some(theme.syntax_string));
+        assert_eq!(at("foo"), Some(theme.syntax_function));
+        assert_eq!(at("42"), Some(theme.syntax_number));
+        assert_eq!(at("// done"), Some(theme.syntax_comment));
+        assert_eq!(colors[line.len() - 1], Some(theme.syntax_comment));
+
+        // Comment prefixes depend on the language, lifetimes aren't strings
+        let colors = code_colors("x = 1 # note", "python", &theme);
+        assert_eq!(colors[6], Some(theme.syntax_comment));
+        let colors = code_colors("fn f<'a>(s: &'a str) {}", "rust", &theme);
+        assert_eq!(colors[5], None);
+        assert_eq!(colors[10], None);
+    }
+}
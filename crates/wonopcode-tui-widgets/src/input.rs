@@ -1,17 +1,26 @@
 //! Input widget for the TUI with multi-line support and history.
+//!
+//! Multi-line input is soft-wrapped with line numbers, and fenced code blocks
+//! are highlighted. Multi-line pastes are collapsed into a placeholder unless
+//! they contain a fenced code block, which stays editable. Attached images
+//! show as `[Image #n]` chips, and files and symbols attached with `@` as
+//! `[@path]` chips. Backspace removes a chip as a whole.
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Constraint, Direction, Layout, Position, Rect},
     style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Paragraph},
     Frame,
 };
+use serde::{Deserialize, Serialize};
 use tui_textarea::TextArea;
 use wonopcode_tui_core::metrics;
 use wonopcode_tui_core::{AgentMode, Theme};
 
+use crate::highlight::{code_colors, has_code_fence, line_kinds, LineKind};
+
 /// Prompt history manager with optional file persistence.
 #[derive(Debug, Clone, Default)]
 pub struct PromptHistory {
@@ -154,6 +163,8 @@ pub struct InputWidget {
     paste_count: usize,
     /// Tracks ongoing paste for terminals that send line-by-line.
     paste_tracker: Option<PasteTracker>,
+    /// Screen position of the cursor in the last render, if shown.
+    cursor_position: Option<Position>,
 }
 
 /// Tracks an ongoing paste operation for terminals that send line-by-line.
@@ -193,6 +204,34 @@ const PASTE_WRAP_MIN_LINES: usize = 2;
 const PASTE_TAG_OPEN: &str = "<wonopcode__paste>";
 /// Closing tag for paste content.
 const PASTE_TAG_CLOSE: &str = "</wonopcode__paste>";
+/// Opening tag for the path of an attached image.
+const IMAGE_TAG_OPEN: &str = "<wonopcode__image>";
+/// Closing tag for the path of an attached image.
+const IMAGE_TAG_CLOSE: &str = "</wonopcode__image>";
+/// Opening tag for an attached file or symbol, as JSON.
+const CONTEXT_TAG_OPEN: &str = "<wonopcode__context>";
+/// Closing tag for an attached file or symbol.
+const CONTEXT_TAG_CLOSE: &str = "</wonopcode__context>";
+
+/// A file, or the lines of a symbol in it, attached to a prompt with `@`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextAttachment {
+    /// Path of the file, relative to the project.
+    pub path: String,
+    /// Name of the symbol, if a symbol was attached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// First and last line of the symbol (1-based, inclusive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<(u32, u32)>,
+}
+
+impl ContextAttachment {
+    /// The `@` mention that stands for the attachment in the prompt.
+    pub fn mention(&self) -> String {
+        format!("@{}", self.symbol.as_deref().unwrap_or(&self.path))
+    }
+}
 
 impl Default for InputWidget {
     fn default() -> Self {
@@ -215,6 +254,7 @@ impl InputWidget {
             last_text_width: 80, // Default, will be updated on render
             paste_count: 0,
             paste_tracker: None,
+            cursor_position: None,
         }
     }
 
@@ -230,6 +270,11 @@ impl InputWidget {
         self.history = history;
     }
 
+    /// Get where the cursor was drawn in the last render, if it was.
+    pub fn cursor_position(&self) -> Option<Position> {
+        self.cursor_position
+    }
+
     pub fn set_focused(&mut self, focused: bool) {
         self.focused = focused;
     }
@@ -254,9 +299,100 @@ impl InputWidget {
         self.textarea.lines().join("\n")
     }
 
-    /// Get the text with paste tags removed (for submission).
+    /// Get the text with paste tags removed, images as `[Image #n]` and
+    /// attachments as their `@` mention (for submission).
     pub fn text(&self) -> String {
-        strip_paste_tags(&self.raw_text())
+        strip_paste_tags(&replace_attachment_tags(&self.raw_text()))
+    }
+
+    /// Get the paths of the attached images, in order.
+    pub fn images(&self) -> Vec<std::path::PathBuf> {
+        let raw_text = self.raw_text();
+        let mut images = Vec::new();
+        let mut pos = 0;
+        while let Some((kind, _, content, close)) = next_tagged(&raw_text, pos) {
+            if kind == RegionKind::Image {
+                images.push(std::path::PathBuf::from(&raw_text[content..close]));
+            }
+            pos = close + kind.tags().1.len();
+        }
+        images
+    }
+
+    /// Get the attached files and symbols, in order.
+    pub fn contexts(&self) -> Vec<ContextAttachment> {
+        let raw_text = self.raw_text();
+        let mut contexts = Vec::new();
+        let mut pos = 0;
+        while let Some((kind, _, content, close)) = next_tagged(&raw_text, pos) {
+            if kind == RegionKind::Context {
+                if let Ok(context) = serde_json::from_str(&raw_text[content..close]) {
+                    contexts.push(context);
+                }
+            }
+            pos = close + kind.tags().1.len();
+        }
+        contexts
+    }
+
+    /// Attach a file or symbol at the cursor, shown as an `[@path]` chip.
+    pub fn insert_context(&mut self, context: &ContextAttachment) {
+        let Ok(json) = serde_json::to_string(context) else {
+            return;
+        };
+        self.finalize_paste_tracking();
+        self.textarea
+            .insert_str(format!("{CONTEXT_TAG_OPEN}{json}{CONTEXT_TAG_CLOSE}"));
+        self.history.reset();
+    }
+
+    /// Get the `@` mention typed right before the cursor: the column of the
+    /// `@` in its line and the text after it.
+    pub fn mention_before_cursor(&self) -> Option<(usize, String)> {
+        let (row, col) = self.textarea.cursor();
+        let line = self.textarea.lines().get(row)?;
+        let before: String = line.chars().take(col).collect();
+        let at = before.rfind('@')?;
+        let filter = &before[at + 1..];
+        let starts_word = at == 0 || before[..at].ends_with(char::is_whitespace);
+        // A tag means the `@` is part of a paste or attachment before the cursor
+        if !starts_word || filter.contains(char::is_whitespace) || filter.contains('<') {
+            return None;
+        }
+        Some((before[..at].chars().count(), filter.to_string()))
+    }
+
+    /// Replace the `@` mention before the cursor with an attachment chip.
+    pub fn attach_mention(&mut self, context: &ContextAttachment) {
+        self.delete_mention();
+        self.insert_context(context);
+        self.textarea.insert_char(' ');
+    }
+
+    /// Replace the `@` mention before the cursor with text.
+    pub fn replace_mention(&mut self, text: &str) {
+        self.delete_mention();
+        self.textarea.insert_str(text);
+        self.history.reset();
+    }
+
+    /// Delete the `@` mention before the cursor, if any.
+    fn delete_mention(&mut self) {
+        if let Some((_, filter)) = self.mention_before_cursor() {
+            for _ in 0..=filter.chars().count() {
+                self.textarea.delete_char();
+            }
+        }
+    }
+
+    /// Attach an image at the cursor, shown as an `[Image #n]` chip.
+    pub fn insert_image(&mut self, path: &std::path::Path) {
+        self.finalize_paste_tracking();
+        self.textarea.insert_str(format!(
+            "{IMAGE_TAG_OPEN}{}{IMAGE_TAG_CLOSE}",
+            path.display()
+        ));
+        self.history.reset();
     }
 
     /// Alias for text() - get the current content (with tags stripped).
@@ -348,8 +484,8 @@ impl InputWidget {
             self.finalize_paste_tracking();
         }
 
-        // Wrap multi-line pastes in tags
-        if line_count >= PASTE_WRAP_MIN_LINES {
+        // Wrap multi-line pastes in tags, keeping code blocks editable
+        if line_count >= PASTE_WRAP_MIN_LINES && !has_code_fence(text) {
             self.paste_count += 1;
             let wrapped = format!("{PASTE_TAG_OPEN}{text}{PASTE_TAG_CLOSE}");
             tracing::info!(
@@ -359,6 +495,9 @@ impl InputWidget {
             );
             self.textarea.insert_str(&wrapped);
             self.paste_tracker = None; // Complete paste, no tracking needed
+        } else if line_count >= PASTE_WRAP_MIN_LINES {
+            self.textarea.insert_str(text);
+            self.paste_tracker = None;
         } else {
             // Single line - insert and start tracking in case more lines come
             self.textarea.insert_str(text);
@@ -391,7 +530,10 @@ impl InputWidget {
                 // For simplicity, if we detected multiple lines were pasted,
                 // wrap the entire current content (this works for empty-start pastes)
                 // A more sophisticated approach would track exact positions
-                if !raw_text.is_empty() && !raw_text.contains(PASTE_TAG_OPEN) {
+                if !raw_text.is_empty()
+                    && !raw_text.contains(PASTE_TAG_OPEN)
+                    && !has_code_fence(&raw_text)
+                {
                     self.paste_count += 1;
                     let wrapped = format!("{PASTE_TAG_OPEN}{raw_text}{PASTE_TAG_CLOSE}");
                     self.textarea.select_all();
@@ -481,7 +623,10 @@ impl InputWidget {
                 }
             }
             KeyCode::Backspace => {
-                self.textarea.delete_char();
+                // Pastes, images and attachments are deleted as a whole
+                if !self.delete_region_before_cursor() {
+                    self.textarea.delete_char();
+                }
                 self.history.reset();
                 if self.is_empty() {
                     self.shell_mode = false;
@@ -582,27 +727,22 @@ impl InputWidget {
 
     /// Move cursor to a specific column on the current line.
     fn move_to_column(&mut self, col: usize) {
-        self.textarea.move_cursor(tui_textarea::CursorMove::Head);
-        for _ in 0..col {
-            self.textarea.move_cursor(tui_textarea::CursorMove::Forward);
-        }
+        let (row, _) = self.textarea.cursor();
+        self.jump_to(row, col);
     }
 
     /// Move cursor to a specific offset in the text.
     fn move_to_offset(&mut self, offset: usize) {
         let raw_text = self.raw_text();
         let (row, col) = offset_to_cursor(&raw_text, offset);
+        self.jump_to(row, col);
+    }
 
-        // Move to target row
-        self.textarea.move_cursor(tui_textarea::CursorMove::Top);
-        for _ in 0..row {
-            self.textarea.move_cursor(tui_textarea::CursorMove::Down);
-        }
-        // Move to target column
-        self.textarea.move_cursor(tui_textarea::CursorMove::Head);
-        for _ in 0..col {
-            self.textarea.move_cursor(tui_textarea::CursorMove::Forward);
-        }
+    /// Move cursor to a row and column, clamped to the text.
+    fn jump_to(&mut self, row: usize, col: usize) {
+        let clamp = |n: usize| n.min(u16::MAX as usize) as u16;
+        self.textarea
+            .move_cursor(tui_textarea::CursorMove::Jump(clamp(row), clamp(col)));
     }
 
     /// Get current cursor position as character offset.
@@ -644,6 +784,21 @@ impl InputWidget {
         }
     }
 
+    /// Delete the paste or image ending at the cursor, if any.
+    fn delete_region_before_cursor(&mut self) -> bool {
+        let raw_text = self.raw_text();
+        let offset = self.cursor_offset();
+        let Some((start, end)) = find_paste_regions(&raw_text)
+            .into_iter()
+            .find(|&(_, end)| end == offset)
+        else {
+            return false;
+        };
+        self.set_text(&format!("{}{}", &raw_text[..start], &raw_text[end..]));
+        self.move_to_offset(start);
+        true
+    }
+
     /// Ensure cursor is not inside a paste region.
     /// If it is, snap to the nearest edge (start or end of region).
     fn snap_cursor_outside_paste_region(&mut self) {
@@ -808,24 +963,23 @@ impl InputWidget {
 
     /// Calculate the required height for a given width, accounting for line wrapping.
     pub fn height_for_width(&self, width: u16) -> u16 {
-        // Account for horizontal padding (2 cols each side) and border (1 col)
-        let text_width = width.saturating_sub(5).max(1) as usize;
+        // Collapsed pastes take a single line
+        let (display_text, _) = transform_for_display(&self.raw_text());
+        let line_count = display_text.split('\n').count();
+
+        // Account for horizontal padding (2 cols each side), border (1 col)
+        // and line numbers
+        let text_width = (width.saturating_sub(5) as usize)
+            .saturating_sub(gutter_width(line_count))
+            .max(1);
 
         // Calculate wrapped line count
-        let wrapped_lines: u16 = self
-            .textarea
-            .lines()
-            .iter()
-            .map(|line| {
-                if line.is_empty() {
-                    1
-                } else {
-                    line.len().div_ceil(text_width).max(1) as u16
-                }
-            })
+        let wrapped_lines: usize = display_text
+            .split('\n')
+            .map(|line| visual_rows(line, text_width))
             .sum();
 
-        let content_lines = wrapped_lines.max(1);
+        let content_lines = wrapped_lines.clamp(1, 15) as u16;
         // +1 for the mode indicator line, +1 for space between text and mode, +2 for vertical padding (1 top + 1 bottom)
         // Minimum height of 6, max of 15
         (content_lines + 4).clamp(6, 15)
@@ -911,24 +1065,50 @@ impl InputWidget {
             text_area.height,
         );
 
+        // Transform once; pastes show as placeholders
+        let raw_text = self.raw_text();
+        let (display_text, paste_regions) = transform_for_display(&raw_text);
+        let display_lines: Vec<&str> = display_text.split('\n').collect();
+
         // Store the text width for visual cursor movement calculations
-        self.last_text_width = inner_area.width as usize;
+        self.last_text_width =
+            (inner_area.width as usize).saturating_sub(gutter_width(display_lines.len()));
 
         if self.is_empty() && !self.focused {
             let placeholder = Paragraph::new(Span::styled(&self.placeholder, theme.muted_style()))
                 .style(bg_style);
             frame.render_widget(placeholder, inner_area);
+            self.cursor_position = None;
         } else {
             // Custom wrapped rendering with cursor support
-            self.render_wrapped_text(frame, inner_area, theme, bg_style);
+            self.cursor_position = self.render_wrapped_text(
+                frame,
+                inner_area,
+                &display_text,
+                &display_lines,
+                &paste_regions,
+                theme,
+                bg_style,
+            );
         }
 
         // Mode indicator line
         let mode_area = content_chunks[3];
 
         // Check for paste tags to show indicator
-        let raw_for_mode = self.textarea.lines().join("\n");
-        let has_paste_tags = raw_for_mode.contains(PASTE_TAG_OPEN);
+        let paste_count = paste_regions
+            .iter()
+            .filter(|r| r.kind == RegionKind::Paste)
+            .count();
+        let image_count = paste_regions
+            .iter()
+            .filter(|r| r.kind == RegionKind::Image)
+            .count();
+        let context_count = paste_regions
+            .iter()
+            .filter(|r| r.kind == RegionKind::Context)
+            .count();
+        let has_paste_tags = paste_count > 0;
 
         let mode_name = if self.shell_mode {
             "Shell"
@@ -955,8 +1135,6 @@ impl InputWidget {
         }
 
         // Calculate character and line count from display text (not raw)
-        let raw_text = self.textarea.lines().join("\n");
-        let (display_text, paste_regions) = transform_for_display(&raw_text);
         let display_char_count = display_text.len();
         let display_line_count = display_text.lines().count().max(1);
 
@@ -964,17 +1142,19 @@ impl InputWidget {
         if display_char_count > 0 {
             // Calculate how much space we have
             let left_content_len: usize = mode_spans.iter().map(|s| s.content.len()).sum();
-            let count_text = if !paste_regions.is_empty() {
-                format!(
-                    "{} chars | {} pastes",
-                    display_char_count,
-                    paste_regions.len()
-                )
+            let mut count_text = if paste_count > 0 {
+                format!("{display_char_count} chars | {paste_count} pastes")
             } else if display_line_count > 1 {
                 format!("{display_char_count} chars | {display_line_count} lines")
             } else {
                 format!("{display_char_count} chars")
             };
+            if image_count > 0 {
+                count_text.push_str(&format!(" | {image_count} images"));
+            }
+            if context_count > 0 {
+                count_text.push_str(&format!(" | {context_count} attached"));
+            }
 
             let available_width = mode_area.width as usize;
             let spacing = available_width.saturating_sub(left_content_len + count_text.len() + 2);
@@ -990,18 +1170,34 @@ impl InputWidget {
         frame.render_widget(mode_line, mode_area);
     }
 
-    /// Render text with wrapping and cursor support.
-    #[allow(clippy::cognitive_complexity)]
-    fn render_wrapped_text(&self, frame: &mut Frame, area: Rect, theme: &Theme, bg_style: Style) {
-        let width = area.width as usize;
+    /// Render text with wrapping, line numbers and cursor support,
+    /// returning where the cursor was drawn.
+    ///
+    /// Only the rows in view are styled and drawn, so long input such as a
+    /// large paste stays cheap to render.
+    #[allow(clippy::too_many_arguments)]
+    fn render_wrapped_text(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        display_text: &str,
+        display_lines: &[&str],
+        paste_regions: &[PasteRegion],
+        theme: &Theme,
+        bg_style: Style,
+    ) -> Option<Position> {
+        let gutter = gutter_width(display_lines.len());
+        let width = (area.width as usize).saturating_sub(gutter);
         if width == 0 {
-            return;
+            return None;
         }
 
-        let (cursor_row, cursor_col) = self.textarea.cursor();
-        let raw_lines = self.textarea.lines();
         let text_style = bg_style.fg(theme.text);
         let paste_style = bg_style.fg(theme.text_muted);
+        let image_style = bg_style.fg(theme.accent).add_modifier(Modifier::BOLD);
+        let fence_style = bg_style.fg(theme.text_muted);
+        let number_style = bg_style.fg(theme.border);
+        let current_number_style = bg_style.fg(theme.text_muted);
         let cursor_style = if self.focused {
             Style::default()
                 .fg(theme.background)
@@ -1011,124 +1207,114 @@ impl InputWidget {
             text_style
         };
 
-        // Transform raw text to display text with paste placeholders
-        let raw_text = raw_lines.join("\n");
-        let (display_text, paste_regions) = transform_for_display(&raw_text);
-
-        // Debug: log when we have paste regions
-        if !paste_regions.is_empty() {
-            let preview: String = display_text.chars().take(100).collect();
-            tracing::debug!(
-                "render_wrapped_text: {} paste regions, display_text={:?}",
-                paste_regions.len(),
-                preview
-            );
-        }
-
         // Map cursor position from raw to display coordinates
-        let raw_cursor_offset = cursor_to_offset(raw_lines, cursor_row, cursor_col);
-        let display_cursor_offset = map_cursor_to_display(raw_cursor_offset, &paste_regions);
+        let (cursor_row, cursor_col) = self.textarea.cursor();
+        let raw_cursor_offset = cursor_to_offset(self.textarea.lines(), cursor_row, cursor_col);
+        let display_cursor_offset = map_cursor_to_display(raw_cursor_offset, paste_regions);
         let (display_cursor_row, display_cursor_col) =
-            offset_to_cursor(&display_text, display_cursor_offset);
-
-        // Split display text into lines
-        let display_lines: Vec<&str> = display_text.split('\n').collect();
-
-        // Build wrapped lines and track cursor position
-        let mut wrapped_lines: Vec<Line> = Vec::new();
-        let mut cursor_wrapped_row = 0usize;
-        let mut cursor_wrapped_col = 0usize;
-        let mut found_cursor = false;
-
-        // Track character offset in display text for paste region detection
-        let mut display_char_offset = 0usize;
-
-        for (line_idx, line) in display_lines.iter().enumerate() {
-            if line.is_empty() {
-                // Empty line - check if cursor is here
-                if line_idx == display_cursor_row && display_cursor_col == 0 {
-                    cursor_wrapped_row = wrapped_lines.len();
-                    cursor_wrapped_col = 0;
-                    found_cursor = true;
-                }
-                wrapped_lines.push(Line::from(""));
-                display_char_offset += 1; // newline
-            } else {
-                // Wrap the line
-                let chars: Vec<char> = line.chars().collect();
-                let mut char_idx = 0usize;
-
-                while char_idx < chars.len() {
-                    let wrap_start = char_idx;
-                    let wrap_end = (char_idx + width).min(chars.len());
-                    let segment: String = chars[wrap_start..wrap_end].iter().collect();
-
-                    // Check if cursor is in this segment
-                    if line_idx == display_cursor_row && !found_cursor {
-                        if display_cursor_col >= wrap_start && display_cursor_col < wrap_end {
-                            cursor_wrapped_row = wrapped_lines.len();
-                            cursor_wrapped_col = display_cursor_col - wrap_start;
-                            found_cursor = true;
-                        } else if display_cursor_col == wrap_end && wrap_end == chars.len() {
-                            // Cursor at end of line
-                            cursor_wrapped_row = wrapped_lines.len();
-                            cursor_wrapped_col = segment.chars().count();
-                            found_cursor = true;
-                        }
-                    }
-
-                    // Check if this segment contains a paste placeholder and style accordingly
-                    let segment_start_offset = display_char_offset + wrap_start;
-                    let segment_end_offset = display_char_offset + wrap_end;
-                    let is_in_paste = paste_regions.iter().any(|r| {
-                        segment_start_offset < r.display_end && segment_end_offset > r.display_start
-                    });
+            offset_to_cursor(display_text, display_cursor_offset);
 
-                    let style = if is_in_paste { paste_style } else { text_style };
-                    wrapped_lines.push(Line::from(Span::styled(segment, style)));
-                    char_idx = wrap_end;
-                }
-                display_char_offset += line.len() + 1; // +1 for newline
-            }
-        }
+        // Find the wrapped row of the cursor, staying on the last row of its
+        // line when it is at the end
+        let rows: Vec<usize> = display_lines
+            .iter()
+            .map(|line| visual_rows(line, width))
+            .collect();
+        let cursor_line = display_cursor_row.min(display_lines.len() - 1);
+        let cursor_line_row = (display_cursor_col / width).min(rows[cursor_line] - 1);
+        let cursor_wrapped_row = rows[..cursor_line].iter().sum::<usize>() + cursor_line_row;
+        let cursor_wrapped_col = display_cursor_col - cursor_line_row * width;
 
         // Calculate scroll offset to keep cursor visible
         let visible_height = area.height as usize;
-        let scroll_offset = if cursor_wrapped_row >= visible_height {
-            cursor_wrapped_row - visible_height + 1
-        } else {
-            0
-        };
+        let scroll_offset = (cursor_wrapped_row + 1).saturating_sub(visible_height);
+        let visible = scroll_offset..scroll_offset + visible_height;
 
-        // Render wrapped lines with scroll offset
+        let kinds = line_kinds(display_lines);
         let buffer = frame.buffer_mut();
-        for (row_offset, wrapped_line) in wrapped_lines
-            .iter()
-            .skip(scroll_offset)
-            .take(visible_height)
-            .enumerate()
-        {
-            let y = area.y + row_offset as u16;
-            if y >= area.y + area.height {
+        let mut first_row = 0usize;
+        let mut line_start = 0usize;
+        for (line_idx, line) in display_lines.iter().enumerate() {
+            if first_row >= visible.end {
                 break;
             }
+            let line_rows = rows[line_idx];
+            if first_row + line_rows <= visible.start {
+                first_row += line_rows;
+                line_start += line.len() + 1; // +1 for newline
+                continue;
+            }
+
+            let colors = match kinds[line_idx] {
+                LineKind::Code(language) => code_colors(line, language, theme),
+                LineKind::Text | LineKind::Fence => Vec::new(),
+            };
+            let chars: Vec<(usize, char)> = line.char_indices().collect();
+            for line_row in 0..line_rows {
+                let row = first_row + line_row;
+                if !visible.contains(&row) {
+                    continue;
+                }
+                let y = area.y + (row - scroll_offset) as u16;
+                let mut x = area.x;
+
+                // Line number on the first row of each line
+                if gutter > 0 {
+                    let number = if line_row == 0 {
+                        format!("{:>w$} ", line_idx + 1, w = gutter - 1)
+                    } else {
+                        " ".repeat(gutter)
+                    };
+                    let style = if line_idx == cursor_line {
+                        current_number_style
+                    } else {
+                        number_style
+                    };
+                    for ch in number.chars() {
+                        buffer[(x, y)].set_char(ch).set_style(style);
+                        x += 1;
+                    }
+                }
 
-            // Render the line content
-            let mut x = area.x;
-            for span in wrapped_line.spans.iter() {
-                for ch in span.content.chars() {
+                // Render the line content
+                let segment_start = line_row * width;
+                let segment_end = (segment_start + width).min(chars.len());
+                for (char_idx, &(byte, ch)) in chars
+                    .iter()
+                    .enumerate()
+                    .take(segment_end)
+                    .skip(segment_start)
+                {
+                    let offset = line_start + byte;
+                    let region = paste_regions
+                        .iter()
+                        .find(|r| offset >= r.display_start && offset < r.display_end);
+                    let style = if let Some(region) = region {
+                        match region.kind {
+                            RegionKind::Paste => paste_style,
+                            RegionKind::Image | RegionKind::Context => image_style,
+                        }
+                    } else if let Some(Some(color)) = colors.get(char_idx) {
+                        bg_style.fg(*color)
+                    } else if kinds[line_idx] == LineKind::Fence {
+                        fence_style
+                    } else {
+                        text_style
+                    };
                     if x < area.x + area.width {
-                        buffer[(x, y)].set_char(ch).set_style(span.style);
+                        buffer[(x, y)].set_char(ch).set_style(style);
                         x += 1;
                     }
                 }
-            }
 
-            // Fill remaining space with background
-            while x < area.x + area.width {
-                buffer[(x, y)].set_char(' ').set_style(bg_style);
-                x += 1;
+                // Fill remaining space with background
+                while x < area.x + area.width {
+                    buffer[(x, y)].set_char(' ').set_style(bg_style);
+                    x += 1;
+                }
             }
+            first_row += line_rows;
+            line_start += line.len() + 1;
         }
 
         // Render cursor
@@ -1136,7 +1322,7 @@ impl InputWidget {
             let cursor_screen_row = cursor_wrapped_row.saturating_sub(scroll_offset);
             if cursor_screen_row < visible_height {
                 let cursor_y = area.y + cursor_screen_row as u16;
-                let cursor_x = area.x + cursor_wrapped_col as u16;
+                let cursor_x = area.x + (gutter + cursor_wrapped_col) as u16;
 
                 if cursor_x < area.x + area.width && cursor_y < area.y + area.height {
                     let cell = &mut buffer[(cursor_x, cursor_y)];
@@ -1146,9 +1332,11 @@ impl InputWidget {
                         cell.symbol().chars().next().unwrap_or(' ')
                     };
                     cell.set_char(ch).set_style(cursor_style);
+                    return Some(Position::new(cursor_x, cursor_y));
                 }
             }
         }
+        None
     }
 }
 
@@ -1168,6 +1356,20 @@ pub enum InputAction {
     Paste,
 }
 
+/// Width of the line number gutter; none for a single line.
+fn gutter_width(line_count: usize) -> usize {
+    if line_count > 1 {
+        line_count.to_string().len() + 1
+    } else {
+        0
+    }
+}
+
+/// Number of rows a line takes when wrapped at `width` characters.
+fn visual_rows(line: &str, width: usize) -> usize {
+    line.chars().count().div_ceil(width.max(1)).max(1)
+}
+
 /// Strip paste tags from text, keeping the content inside.
 fn strip_paste_tags(text: &str) -> String {
     let mut result = text.to_string();
@@ -1177,8 +1379,75 @@ fn strip_paste_tags(text: &str) -> String {
     result
 }
 
+/// Kind of a tagged region of the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RegionKind {
+    /// Pasted text, shown as `[Paste #n - N lines]`.
+    Paste,
+    /// The path of an attached image, shown as `[Image #n]`.
+    Image,
+    /// An attached file or symbol, shown as `[@path]`.
+    Context,
+}
+
+impl RegionKind {
+    /// The opening and closing tag.
+    fn tags(self) -> (&'static str, &'static str) {
+        match self {
+            RegionKind::Paste => (PASTE_TAG_OPEN, PASTE_TAG_CLOSE),
+            RegionKind::Image => (IMAGE_TAG_OPEN, IMAGE_TAG_CLOSE),
+            RegionKind::Context => (CONTEXT_TAG_OPEN, CONTEXT_TAG_CLOSE),
+        }
+    }
+}
+
+/// Find the first tagged region starting at or after `from`: its kind, the
+/// start of its opening tag, and the start and end of its content.
+fn next_tagged(text: &str, from: usize) -> Option<(RegionKind, usize, usize, usize)> {
+    let (kind, open) = [RegionKind::Paste, RegionKind::Image, RegionKind::Context]
+        .into_iter()
+        .filter_map(|kind| Some((kind, from + text[from..].find(kind.tags().0)?)))
+        .min_by_key(|&(_, open)| open)?;
+    let (open_tag, close_tag) = kind.tags();
+    let content = open + open_tag.len();
+    let close = content + text[content..].find(close_tag)?;
+    Some((kind, open, content, close))
+}
+
+/// Replace image tags with the `[Image #n]` label shown for them, and
+/// attachment tags with their `@` mention.
+fn replace_attachment_tags(text: &str) -> String {
+    let mut result = String::new();
+    let mut pos = 0;
+    let mut image_num = 1;
+    while let Some((kind, open, content, close)) = next_tagged(text, pos) {
+        let end = close + kind.tags().1.len();
+        result.push_str(&text[pos..open]);
+        match kind {
+            RegionKind::Paste => result.push_str(&text[open..end]),
+            RegionKind::Image => {
+                result.push_str(&format!("[Image #{image_num}]"));
+                image_num += 1;
+            }
+            RegionKind::Context => result.push_str(&context_mention(&text[content..close])),
+        }
+        pos = end;
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+/// The `@` mention of an attachment tag's content.
+fn context_mention(json: &str) -> String {
+    serde_json::from_str::<ContextAttachment>(json)
+        .map(|context| context.mention())
+        .unwrap_or_default()
+}
+
 /// Information about a paste region for cursor mapping.
 struct PasteRegion {
+    /// Whether the region is a paste or an image.
+    kind: RegionKind,
     /// Start offset in raw text (at opening tag).
     raw_start: usize,
     /// End offset in raw text (after closing tag).
@@ -1189,58 +1458,50 @@ struct PasteRegion {
     display_end: usize,
 }
 
-/// Transform text for display, replacing paste regions with placeholders.
-/// Returns the display text and information for cursor mapping.
+/// Transform text for display, replacing paste regions and images with
+/// placeholders. Returns the display text and information for cursor mapping.
 fn transform_for_display(text: &str) -> (String, Vec<PasteRegion>) {
     let mut result = String::new();
     let mut regions = Vec::new();
-    let mut remaining = text;
     let mut paste_num = 1;
-    let mut raw_offset = 0usize;
+    let mut image_num = 1;
+    let mut pos = 0usize;
 
-    while let Some(start_pos) = remaining.find(PASTE_TAG_OPEN) {
+    while let Some((kind, open, content, close)) = next_tagged(text, pos) {
         // Add text before the tag
-        result.push_str(&remaining[..start_pos]);
-        raw_offset += start_pos;
-
+        result.push_str(&text[pos..open]);
         let display_start = result.len();
-        let raw_start = raw_offset;
-
-        // Find the closing tag
-        let after_open = &remaining[start_pos + PASTE_TAG_OPEN.len()..];
-        if let Some(end_pos) = after_open.find(PASTE_TAG_CLOSE) {
-            // Extract the paste content to count lines
-            let paste_content = &after_open[..end_pos];
-            let line_count = paste_content.lines().count().max(1);
-
-            // Add placeholder
-            let placeholder = format!("[Paste #{paste_num} - {line_count} lines]");
-            result.push_str(&placeholder);
-            paste_num += 1;
-
-            // Calculate raw end position (after closing tag)
-            let raw_end = raw_start + PASTE_TAG_OPEN.len() + end_pos + PASTE_TAG_CLOSE.len();
-            let display_end = result.len();
-
-            regions.push(PasteRegion {
-                raw_start,
-                raw_end,
-                display_start,
-                display_end,
-            });
-
-            // Move past the closing tag
-            remaining = &after_open[end_pos + PASTE_TAG_CLOSE.len()..];
-            raw_offset = raw_end;
-        } else {
-            // No closing tag found, include the rest as-is
-            result.push_str(&remaining[start_pos..]);
-            break;
+
+        // Add placeholder
+        match kind {
+            RegionKind::Paste => {
+                let line_count = text[content..close].lines().count().max(1);
+                result.push_str(&format!("[Paste #{paste_num} - {line_count} lines]"));
+                paste_num += 1;
+            }
+            RegionKind::Image => {
+                result.push_str(&format!("[Image #{image_num}]"));
+                image_num += 1;
+            }
+            RegionKind::Context => {
+                result.push_str(&format!("[{}]", context_mention(&text[content..close])));
+            }
         }
+
+        // Move past the closing tag
+        let raw_end = close + kind.tags().1.len();
+        regions.push(PasteRegion {
+            kind,
+            raw_start: open,
+            raw_end,
+            display_start,
+            display_end: result.len(),
+        });
+        pos = raw_end;
     }
 
     // Add any remaining text
-    result.push_str(remaining);
+    result.push_str(&text[pos..]);
     (result, regions)
 }
 
@@ -1298,23 +1559,16 @@ fn offset_to_cursor(text: &str, offset: usize) -> (usize, usize) {
     (row, col)
 }
 
-/// Find all paste tag regions in the raw text.
-/// Returns Vec of (start_offset, end_offset) for each paste region.
+/// Find all paste and image tag regions in the raw text.
+/// Returns Vec of (start_offset, end_offset) for each region.
 fn find_paste_regions(text: &str) -> Vec<(usize, usize)> {
     let mut regions = Vec::new();
     let mut search_start = 0;
 
-    while let Some(open_pos) = text[search_start..].find(PASTE_TAG_OPEN) {
-        let abs_open = search_start + open_pos;
-        let after_open = abs_open + PASTE_TAG_OPEN.len();
-
-        if let Some(close_pos) = text[after_open..].find(PASTE_TAG_CLOSE) {
-            let abs_close = after_open + close_pos + PASTE_TAG_CLOSE.len();
-            regions.push((abs_open, abs_close));
-            search_start = abs_close;
-        } else {
-            break;
-        }
+    while let Some((kind, open, _, close)) = next_tagged(text, search_start) {
+        let end = close + kind.tags().1.len();
+        regions.push((open, end));
+        search_start = end;
     }
 
     regions
@@ -1733,4 +1987,163 @@ mod tests {
         );
         assert_eq!(raw_before, raw_after);
     }
+
+    fn render_input(input: &mut InputWidget, width: u16, height: u16) -> ratatui::buffer::Buffer {
+        let backend = ratatui::backend::TestBackend::new(width, height);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal
+            .draw(|frame| input.render(frame, frame.area(), &Theme::default()))
+            .unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    fn row_text(buffer: &ratatui::buffer::Buffer, y: u16) -> String {
+        (0..buffer.area.width)
+            .map(|x| buffer[(x, y)].symbol())
+            .collect()
+    }
+
+    #[test]
+    fn test_fenced_paste_stays_editable() {
+        let mut input = InputWidget::new();
+        input.insert_paste("```rust\nfn main() {}\n```");
+        assert!(!input.raw_text().contains(PASTE_TAG_OPEN));
+        assert_eq!(input.text(), "```rust\nfn main() {}\n```");
+        assert!(input.paste_tracker.is_none());
+    }
+
+    #[test]
+    fn test_render_line_numbers_and_code() {
+        let theme = Theme::default();
+        let mut input = InputWidget::new();
+        input.set_focused(true);
+        input.set_text("intro\n```rust\nlet x = 1;\n```");
+        let buffer = render_input(&mut input, 40, 10);
+
+        // Border and padding, then the gutter
+        let row = row_text(&buffer, 1);
+        assert!(row.starts_with("   1 intro"), "{row:?}");
+        let row = row_text(&buffer, 3);
+        assert!(row.starts_with("   3 let x = 1;"), "{row:?}");
+        assert_eq!(buffer[(5, 3)].fg, theme.syntax_keyword);
+        assert_eq!(buffer[(13, 3)].fg, theme.syntax_number);
+        assert_eq!(buffer[(5, 1)].fg, theme.text);
+    }
+
+    #[test]
+    fn test_render_long_input_keeps_cursor_in_view() {
+        let mut input = InputWidget::new();
+        input.set_focused(true);
+        let text: String = (1..=2000).map(|i| format!("line {i}\n")).collect();
+        input.set_text(text.trim_end());
+        assert_eq!(input.height_for_width(40), 15);
+
+        let buffer = render_input(&mut input, 40, 15);
+        // The text area has 11 rows, ending at the cursor
+        let row = row_text(&buffer, 11);
+        assert!(row.starts_with("   2000 line 2000"), "{row:?}");
+        let row = row_text(&buffer, 1);
+        assert!(row.starts_with("   1990 line 1990"), "{row:?}");
+        assert_eq!(input.last_text_width, 35 - 5);
+    }
+
+    #[test]
+    fn test_collapsed_paste_height() {
+        let mut input = InputWidget::new();
+        let text: String = (1..=200).map(|i| format!("at frame {i}\n")).collect();
+        input.insert_paste(&text);
+        assert_eq!(input.height_for_width(80), 6);
+        assert_eq!(input.text(), text.trim_end());
+    }
+
+    #[test]
+    fn test_image_chips() {
+        let mut input = InputWidget::new();
+        input.set_text("look at ");
+        input.insert_image(std::path::Path::new("/tmp/a.png"));
+        input.insert_text(" and ");
+        input.insert_paste("x\ny");
+        input.insert_image(std::path::Path::new("/tmp/b.png"));
+
+        assert_eq!(
+            input.images(),
+            vec![
+                std::path::PathBuf::from("/tmp/a.png"),
+                std::path::PathBuf::from("/tmp/b.png")
+            ]
+        );
+        assert_eq!(input.text(), "look at [Image #1] and x\ny[Image #2]");
+        let (display, regions) = transform_for_display(&input.raw_text());
+        assert_eq!(
+            display,
+            "look at [Image #1] and [Paste #1 - 2 lines][Image #2]"
+        );
+        assert_eq!(regions.len(), 3);
+
+        // Backspace removes the image before the cursor as a whole
+        let backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        input.handle_key(backspace);
+        assert_eq!(input.images().len(), 1);
+        assert_eq!(input.text(), "look at [Image #1] and x\ny");
+        input.handle_key(backspace);
+        assert_eq!(input.text(), "look at [Image #1] and ");
+    }
+
+    #[test]
+    fn test_context_chips() {
+        let file = ContextAttachment {
+            path: "src/main.rs".to_string(),
+            symbol: None,
+            lines: None,
+        };
+        let symbol = ContextAttachment {
+            path: "src/lib.rs".to_string(),
+            symbol: Some("parse".to_string()),
+            lines: Some((10, 20)),
+        };
+        let mut input = InputWidget::new();
+        input.set_text("explain ");
+        input.insert_context(&file);
+        input.insert_text(" and ");
+        input.insert_context(&symbol);
+
+        assert_eq!(input.contexts(), vec![file.clone(), symbol]);
+        assert_eq!(input.text(), "explain @src/main.rs and @parse");
+        let (display, _) = transform_for_display(&input.raw_text());
+        assert_eq!(display, "explain [@src/main.rs] and [@parse]");
+
+        // Backspace removes the attachment as a whole
+        let backspace = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        input.handle_key(backspace);
+        assert_eq!(input.contexts(), vec![file]);
+        assert_eq!(input.text(), "explain @src/main.rs and ");
+    }
+
+    #[test]
+    fn test_mentions_become_chips() {
+        let mut input = InputWidget::new();
+        input.set_text("see @src/ma");
+        assert_eq!(
+            input.mention_before_cursor(),
+            Some((4, "src/ma".to_string()))
+        );
+
+        input.replace_mention("@src/");
+        assert_eq!(input.text(), "see @src/");
+        input.insert_text("main");
+        input.attach_mention(&ContextAttachment {
+            path: "src/main.rs".to_string(),
+            symbol: None,
+            lines: None,
+        });
+        assert_eq!(input.text(), "see @src/main.rs ");
+        assert_eq!(input.contexts().len(), 1);
+
+        // Neither the chip nor an address is a mention being typed
+        assert_eq!(input.mention_before_cursor(), None);
+        input.handle_key(KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE));
+        assert_eq!(input.mention_before_cursor(), None);
+        input.set_text("mail me@example.com");
+        assert_eq!(input.mention_before_cursor(), None);
+    }
 }
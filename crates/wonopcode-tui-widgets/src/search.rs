@@ -56,7 +56,14 @@ impl SearchWidget {
         self.cursor = 0;
     }
 
-    /// Deactivate search mode.
+    /// Activate search mode with a query, the cursor after it.
+    pub fn activate_with(&mut self, query: &str) {
+        self.activate();
+        self.query = query.to_string();
+        self.cursor = self.query.len();
+    }
+
+    /// Deactivate search mode, keeping the query and matches.
     pub fn deactivate(&mut self) {
         self.active = false;
     }
@@ -255,12 +262,13 @@ impl SearchWidget {
         }
 
         // Hints
-        let hints_text = " │ n:next  N:prev  Enter:go  Esc:close";
+        let hints_text = " │ ↓:next  ↑:prev  Enter:go  Tab:all sessions  Esc:close";
         let available_width = area.width as usize;
-        let current_width: usize = spans.iter().map(|s| s.content.len()).sum();
+        let current_width: usize = spans.iter().map(|s| s.content.chars().count()).sum();
+        let hints_width = hints_text.chars().count();
 
-        if current_width + hints_text.len() < available_width {
-            let padding = available_width - current_width - hints_text.len();
+        if current_width + hints_width < available_width {
+            let padding = available_width - current_width - hints_width;
             spans.push(Span::styled(" ".repeat(padding), theme.text_style()));
             spans.push(Span::styled(hints_text, theme.muted_style()));
         }
@@ -300,8 +308,9 @@ pub fn extract_preview(text: &str, query: &str, max_len: usize) -> String {
     let text_lower = text.to_lowercase();
 
     if let Some(pos) = text_lower.find(&query_lower) {
-        let start = pos.saturating_sub(max_len / 4);
-        let end = (pos + query.len() + max_len / 2).min(text.len());
+        let pos = original_offset(text, pos);
+        let start = floor_char_boundary(text, pos.saturating_sub(max_len / 4));
+        let end = floor_char_boundary(text, (pos + query.len() + max_len / 2).min(text.len()));
 
         let mut preview = String::new();
         if start > 0 {
@@ -317,6 +326,28 @@ pub fn extract_preview(text: &str, query: &str, max_len: usize) -> String {
     }
 }
 
+/// The offset in `text` of the character at `lower_offset` in its lowercase
+/// version, since lowercasing can change byte lengths.
+fn original_offset(text: &str, lower_offset: usize) -> usize {
+    let mut lowered = 0;
+    for (offset, c) in text.char_indices() {
+        if lowered >= lower_offset {
+            return offset;
+        }
+        lowered += c.to_lowercase().map(char::len_utf8).sum::<usize>();
+    }
+    text.len()
+}
+
+/// The largest character boundary of `text` at or before `index`.
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -381,6 +412,16 @@ mod tests {
         assert!(!widget.is_active());
     }
 
+    #[test]
+    fn test_search_widget_activate_with() {
+        let mut widget = SearchWidget::new();
+        widget.activate_with("héllo");
+        assert!(widget.is_active());
+        assert_eq!(widget.query(), "héllo");
+        widget.insert_char('!');
+        assert_eq!(widget.query(), "héllo!");
+    }
+
     #[test]
     fn test_search_widget_insert_char() {
         let mut widget = SearchWidget::new();
@@ -587,6 +628,14 @@ mod tests {
         assert!(!preview.is_empty());
     }
 
+    #[test]
+    fn test_extract_preview_multibyte() {
+        // 'İ' lowercases to two characters, shifting byte offsets
+        let text = format!("{}ééé match", "İ".repeat(20));
+        let preview = extract_preview(&text, "match", 12);
+        assert!(preview.ends_with("match"));
+    }
+
     #[test]
     fn test_extract_preview_short_text() {
         let preview = extract_preview("short", "short", 100);
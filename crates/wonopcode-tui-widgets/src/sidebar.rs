@@ -11,6 +11,7 @@ use ratatui::{
 
 use wonopcode_tui_core::metrics;
 use wonopcode_tui_core::Theme;
+use wonopcode_util::size::format_bytes;
 
 /// Format a number with comma separators (e.g., 67360 -> "67,360").
 fn format_number(n: u32) -> String {
@@ -33,6 +34,15 @@ pub struct ContextInfo {
     pub cost: f64,
 }
 
+/// Live resource usage of the sandbox.
+#[derive(Debug, Clone, Default)]
+pub struct SandboxUsage {
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+    pub memory_limit: u64,
+    pub pids: u32,
+}
+
 /// A phase containing grouped todos.
 #[derive(Debug, Clone)]
 pub struct PhaseItem {
@@ -122,6 +132,32 @@ pub enum SidebarSection {
     Modified,
 }
 
+/// Result of a click on the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SidebarClick {
+    /// A section was toggled or a link opened.
+    Handled,
+    /// The session title was clicked.
+    Session,
+    /// The context stats were clicked.
+    Context,
+    /// An LSP server was clicked.
+    Lsp,
+    /// An MCP server was clicked.
+    Mcp(String),
+    /// A modified file was clicked.
+    ModifiedFile(String),
+}
+
+/// What a rendered sidebar line does when clicked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LineTarget {
+    /// A section header, which toggles the section.
+    Header(SidebarSection),
+    /// An entry that opens something.
+    Entry(SidebarClick),
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SidebarWidget {
     visible: bool,
@@ -137,6 +173,8 @@ pub struct SidebarWidget {
     agent: String,
     model: String,
     version: String,
+    /// Resource usage of the running sandbox.
+    sandbox_usage: Option<SandboxUsage>,
     /// Which sections are explicitly collapsed by user.
     collapsed: std::collections::HashSet<u8>,
     /// Whether to auto-collapse empty sections.
@@ -147,6 +185,8 @@ pub struct SidebarWidget {
     total_height: u16,
     /// Whether the sidebar is focused for scrolling.
     focused: bool,
+    /// Click target of each content line, from the last render.
+    line_targets: Vec<Option<LineTarget>>,
 }
 
 impl SidebarWidget {
@@ -200,6 +240,11 @@ impl SidebarWidget {
         self.context.max_tokens = max;
     }
 
+    /// Set the sandbox's resource usage, or `None` to hide it.
+    pub fn set_sandbox_usage(&mut self, usage: Option<SandboxUsage>) {
+        self.sandbox_usage = usage;
+    }
+
     /// Get current token counts.
     pub fn get_tokens(&self) -> (u32, u32) {
         (self.context.input_tokens, self.context.output_tokens)
@@ -379,15 +424,17 @@ impl SidebarWidget {
     }
 
     /// Handle a mouse click at the given position.
-    /// Returns true if a section header was clicked and toggled, or if a link was opened.
-    pub fn handle_click(&mut self, x: u16, y: u16, area: Rect) -> bool {
+    ///
+    /// Section headers are toggled here; clicks on entries are returned for
+    /// the caller to act on. Returns `None` if nothing clickable was hit.
+    pub fn handle_click(&mut self, x: u16, y: u16, area: Rect) -> Option<SidebarClick> {
         if !self.visible || area.width < 20 {
-            return false;
+            return None;
         }
 
         // Check if click is within sidebar bounds
         if x < area.x || x >= area.x + area.width || y < area.y || y >= area.y + area.height {
-            return false;
+            return None;
         }
 
         // Check if click is on the "troels.im" link in the footer
@@ -407,7 +454,7 @@ impl SidebarWidget {
         if y == hyperlink_y && x >= hyperlink_x && x < hyperlink_x + hyperlink_len {
             // Open the URL in the default browser
             let _ = open_url("https://troels.im");
-            return true;
+            return Some(SidebarClick::Handled);
         }
 
         // Content area with padding (same as in render: 2 cols horizontal, 1 row vertical, plus 1 row for status bar)
@@ -418,89 +465,37 @@ impl SidebarWidget {
             area.height.saturating_sub(footer_height + 3),
         );
 
-        // Calculate the actual line being clicked (accounting for scroll)
-        // Guard against clicks above the content area (e.g., on status bar)
-        if y < content_area.y {
-            return false;
-        }
-        let clicked_line = (y - content_area.y) + self.scroll_offset;
-
-        // Calculate line positions for each section header
-        // Session: lines 0-1, then spacer
-        // Context: lines 3-7, then spacer
-        // LSP header is after context section
-        let mut current_line: u16 = 0;
-
-        // Session (2 lines + spacer)
-        current_line += 3;
-
-        // Context (4 lines + spacer)
-        current_line += 5;
-
-        // LSP header
-        let lsp_header_line = current_line;
-        current_line += 1; // header
-        if !self.is_collapsed(SidebarSection::Lsp) {
-            current_line += if self.lsp_servers.is_empty() {
-                1
-            } else {
-                self.lsp_servers.len() as u16
-            };
+        // Guard against clicks outside the content area (e.g., on status bar)
+        if y < content_area.y || y >= content_area.y + content_area.height {
+            return None;
         }
-        current_line += 1; // spacer
-
-        // MCP header
-        let mcp_header_line = current_line;
-        current_line += 1; // header
-        if !self.is_collapsed(SidebarSection::Mcp) {
-            current_line += if self.mcp_servers.is_empty() {
-                1
-            } else {
-                self.mcp_servers.len() as u16
-            };
-        }
-        current_line += 1; // spacer
+        let clicked_line = (y - content_area.y + self.scroll_offset) as usize;
 
-        // Todos header
-        let todos_header_line = current_line;
-        current_line += 1; // header
-        if !self.is_collapsed(SidebarSection::Todos) {
-            if !self.phases.is_empty() {
-                // Each phase: 1 header + N todos
-                for phase in &self.phases {
-                    current_line += 1; // phase header
-                    current_line += phase.todos.len() as u16;
-                }
-            } else if !self.todos.is_empty() {
-                current_line += self.todos.len() as u16;
-            } else {
-                current_line += 1; // "No todos" message
+        match self.line_targets.get(clicked_line).cloned().flatten()? {
+            LineTarget::Header(section) => {
+                self.toggle_section(section);
+                Some(SidebarClick::Handled)
             }
+            LineTarget::Entry(click) => Some(click),
         }
-        current_line += 1; // spacer
-
-        // Modified header
-        let modified_header_line = current_line;
+    }
 
-        // Check which header was clicked
-        if clicked_line == lsp_header_line {
-            self.toggle_section(SidebarSection::Lsp);
-            return true;
-        }
-        if clicked_line == mcp_header_line {
-            self.toggle_section(SidebarSection::Mcp);
-            return true;
-        }
-        if clicked_line == todos_header_line {
-            self.toggle_section(SidebarSection::Todos);
-            return true;
-        }
-        if clicked_line == modified_header_line {
-            self.toggle_section(SidebarSection::Modified);
-            return true;
+    /// Click targets for the lines a section builder just added, starting
+    /// at `start`: the header toggles the section and the entry lines map
+    /// one-to-one onto `entries` (placeholder lines get no target).
+    fn section_targets(
+        targets: &mut Vec<Option<LineTarget>>,
+        start: usize,
+        end: usize,
+        section: SidebarSection,
+        entries: Vec<SidebarClick>,
+    ) {
+        targets.push(Some(LineTarget::Header(section)));
+        if end - start - 1 == entries.len() {
+            targets.extend(entries.into_iter().map(|e| Some(LineTarget::Entry(e))));
+        } else {
+            targets.resize(end, None);
         }
-
-        false
     }
 
     /// Handle mouse scroll events.
@@ -554,28 +549,79 @@ impl SidebarWidget {
         let mut lines: Vec<Line<'static>> = Vec::new();
         let width = content_area.width as usize;
 
+        // Click target of each line, kept in step with `lines`
+        let mut targets: Vec<Option<LineTarget>> = Vec::new();
+
         // Session info
         self.build_session_lines(&mut lines, width, theme);
+        targets.resize(lines.len(), Some(LineTarget::Entry(SidebarClick::Session)));
         lines.push(Line::from("")); // Spacer
+        targets.push(None);
 
         // Context stats
         self.build_context_lines(&mut lines, theme);
+        targets.resize(lines.len(), Some(LineTarget::Entry(SidebarClick::Context)));
         lines.push(Line::from("")); // Spacer
+        targets.push(None);
+
+        // Sandbox resources
+        if self.sandbox_usage.is_some() {
+            self.build_sandbox_lines(&mut lines, theme);
+            lines.push(Line::from("")); // Spacer
+            targets.resize(lines.len(), None);
+        }
 
         // Todos
         self.build_todo_lines(&mut lines, width, theme);
+        targets.push(Some(LineTarget::Header(SidebarSection::Todos)));
+        targets.resize(lines.len(), None);
         lines.push(Line::from("")); // Spacer
+        targets.push(None);
 
         // Modified files
+        let start_modified = lines.len();
         self.build_modified_lines(&mut lines, width, theme);
+        Self::section_targets(
+            &mut targets,
+            start_modified,
+            lines.len(),
+            SidebarSection::Modified,
+            self.modified_files
+                .iter()
+                .map(|f| SidebarClick::ModifiedFile(f.path.clone()))
+                .collect(),
+        );
         lines.push(Line::from("")); // Spacer
+        targets.push(None);
 
         // LSP servers
+        let start_lsp = lines.len();
         self.build_lsp_lines(&mut lines, width, theme);
+        Self::section_targets(
+            &mut targets,
+            start_lsp,
+            lines.len(),
+            SidebarSection::Lsp,
+            self.lsp_servers.iter().map(|_| SidebarClick::Lsp).collect(),
+        );
         lines.push(Line::from("")); // Spacer
+        targets.push(None);
 
         // MCP servers
+        let start_mcp = lines.len();
         self.build_mcp_lines(&mut lines, width, theme);
+        Self::section_targets(
+            &mut targets,
+            start_mcp,
+            lines.len(),
+            SidebarSection::Mcp,
+            self.mcp_servers
+                .iter()
+                .map(|s| SidebarClick::Mcp(s.name.clone()))
+                .collect(),
+        );
+        debug_assert_eq!(targets.len(), lines.len());
+        self.line_targets = targets;
 
         // Store total height for scroll calculations
         self.total_height = lines.len() as u16;
@@ -696,6 +742,52 @@ impl SidebarWidget {
         ]));
     }
 
+    /// Build sandbox resource usage lines.
+    fn build_sandbox_lines(&self, lines: &mut Vec<Line<'static>>, theme: &Theme) {
+        let Some(usage) = &self.sandbox_usage else {
+            return;
+        };
+        let title_style = Style::default().fg(theme.text).add_modifier(Modifier::BOLD);
+
+        lines.push(Line::from(Span::styled("Sandbox", title_style)));
+
+        // Format: "12.5% CPU"
+        let cpu_style = if usage.cpu_percent > 90.0 {
+            theme.warning_style()
+        } else {
+            theme.text_style()
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:.1}%", usage.cpu_percent), cpu_style),
+            Span::styled(" CPU", theme.muted_style()),
+        ]));
+
+        // Format: "512.0 MiB / 2.0 GiB memory"
+        let memory_style = if usage.memory_limit > 0
+            && usage.memory_bytes as f64 / usage.memory_limit as f64 > 0.9
+        {
+            theme.warning_style()
+        } else {
+            theme.text_style()
+        };
+        let mut memory = vec![Span::styled(format_bytes(usage.memory_bytes), memory_style)];
+        if usage.memory_limit > 0 {
+            memory.push(Span::styled(" / ", theme.muted_style()));
+            memory.push(Span::styled(
+                format_bytes(usage.memory_limit),
+                theme.text_style(),
+            ));
+        }
+        memory.push(Span::styled(" memory", theme.muted_style()));
+        lines.push(Line::from(memory));
+
+        // Format: "7 processes"
+        lines.push(Line::from(vec![
+            Span::styled(usage.pids.to_string(), theme.text_style()),
+            Span::styled(" processes", theme.muted_style()),
+        ]));
+    }
+
     /// Build LSP server lines.
     fn build_lsp_lines(&self, lines: &mut Vec<Line<'static>>, width: usize, theme: &Theme) {
         let title_style = Style::default().fg(theme.text).add_modifier(Modifier::BOLD);
@@ -80,34 +80,80 @@ impl SlashCommandAutocomplete {
             SlashCommand::new("new", "Create a new session").with_alias("clear"),
             SlashCommand::new("undo", "Undo the last message"),
             SlashCommand::new("redo", "Redo an undone message"),
+            SlashCommand::new("review", "Review file changes hunk by hunk"),
             SlashCommand::new("compact", "Compact conversation history").with_alias("summarize"),
             SlashCommand::new("rename", "Rename the current session"),
             SlashCommand::new("copy", "Copy session transcript to clipboard"),
-            SlashCommand::new("export", "Export session transcript to file"),
+            SlashCommand::new(
+                "export",
+                "Export the conversation to a file, as markdown or json, with --no-tools",
+            ),
             SlashCommand::new("timeline", "Jump to a specific message"),
             SlashCommand::new("fork", "Fork from a message"),
+            SlashCommand::new("merge", "Merge this branch into its parent session"),
             SlashCommand::new("thinking", "Toggle thinking visibility"),
-            SlashCommand::new("share", "Share the current session"),
-            SlashCommand::new("unshare", "Unshare a session"),
+            SlashCommand::new(
+                "share",
+                "Share the current session, with --expires and --password",
+            ),
+            SlashCommand::new("unshare", "Revoke the current session's share link"),
+            SlashCommand::new("drive", "Take control of a session shared with others"),
             // Navigation commands
             SlashCommand::new("sessions", "List all sessions")
                 .with_alias("session")
                 .with_alias("resume")
                 .with_alias("continue"),
+            SlashCommand::new("search", "Search this session, or all sessions with --all"),
             SlashCommand::new("models", "List and select a model"),
             SlashCommand::new("agents", "List and select an agent").with_alias("agent"),
-            SlashCommand::new("theme", "Change the theme"),
+            SlashCommand::new(
+                "theme",
+                "Change the theme, or reload user themes with reload",
+            ),
             SlashCommand::new("status", "Show configuration status"),
+            SlashCommand::new(
+                "spend",
+                "Show spend, or acknowledge a spending limit with ack",
+            ),
+            SlashCommand::new(
+                "usage",
+                "Show cost, tokens and tool latency over recent days",
+            ),
             SlashCommand::new("settings", "Open settings dialog")
                 .with_alias("config")
                 .with_alias("preferences"),
             SlashCommand::new("mcp", "Toggle MCP servers"),
+            SlashCommand::new("profile", "Switch configuration profile"),
+            SlashCommand::new("project", "Switch between the server's projects")
+                .with_alias("projects"),
+            SlashCommand::new(
+                "connect",
+                "Connect to a server on the local network, or at an address",
+            ),
+            SlashCommand::new("memory", "Edit the project memory"),
+            SlashCommand::new(
+                "orchestrate",
+                "Run agents on tasks in parallel git worktrees",
+            ),
+            SlashCommand::new(
+                "worktree",
+                "Work in a separate git worktree, then merge or open a PR",
+            ),
+            SlashCommand::new("template", "Start from or save a session template")
+                .with_alias("templates"),
             SlashCommand::new("sandbox", "Manage sandbox"),
-            SlashCommand::new("connect", "Connect to a provider"),
             SlashCommand::new("git", "Git operations (stage, commit, push, pull)"),
             // UI commands
             SlashCommand::new("editor", "Open input in external editor"),
+            SlashCommand::new(
+                "paste-image",
+                "Attach the clipboard's image, or an image file, to the prompt",
+            ),
             SlashCommand::new("sidebar", "Toggle the sidebar"),
+            SlashCommand::new("preview", "Toggle the diff/file preview pane"),
+            SlashCommand::new("files", "Toggle the file browser").with_alias("tree"),
+            SlashCommand::new("terminal", "Toggle background process output")
+                .with_alias("processes"),
             SlashCommand::new("commands", "Show all commands"),
             SlashCommand::new("help", "Show help"),
             // Debug/testing commands (hidden by default)
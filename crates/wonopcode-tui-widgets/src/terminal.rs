@@ -0,0 +1,545 @@
+//! Terminal panel showing the output of background processes.
+
+use std::collections::VecDeque;
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use wonopcode_tui_core::metrics;
+use wonopcode_tui_core::Theme;
+
+/// Lines of output kept per process.
+const MAX_SCROLLBACK: usize = 5000;
+
+/// Rows the panel takes below the messages.
+const PANEL_HEIGHT: u16 = 14;
+
+/// Action returned from terminal panel key handling.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalAction {
+    /// No action taken.
+    None,
+    /// Key was handled.
+    Handled,
+    /// Stop the process with this PID.
+    Stop(u32),
+    /// Give focus back to the input.
+    Unfocus,
+    /// Close the panel.
+    Close,
+}
+
+/// A background process and its output.
+#[derive(Debug, Clone)]
+struct ProcessTab {
+    pid: u32,
+    command: String,
+    /// Complete lines of output, oldest first.
+    lines: VecDeque<String>,
+    /// Output after the last newline.
+    partial: String,
+    /// `None` while running, then the exit code if it exited normally.
+    exit: Option<Option<i32>>,
+    /// Lines scrolled back from the end; 0 follows the output.
+    scroll_back: usize,
+}
+
+impl ProcessTab {
+    fn push(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n");
+        let mut parts = text.split('\n');
+        if let Some(first) = parts.next() {
+            self.partial.push_str(first);
+        }
+        for part in parts {
+            let line = std::mem::replace(&mut self.partial, part.to_string());
+            self.lines.push_back(clean_line(&line));
+            if self.scroll_back > 0 {
+                self.scroll_back += 1;
+            }
+        }
+        while self.lines.len() > MAX_SCROLLBACK {
+            self.lines.pop_front();
+        }
+    }
+
+    /// All lines, the partial one included.
+    fn line_count(&self) -> usize {
+        self.lines.len() + usize::from(!self.partial.is_empty())
+    }
+
+    fn line(&self, index: usize) -> String {
+        match self.lines.get(index) {
+            Some(line) => line.clone(),
+            None => clean_line(&self.partial),
+        }
+    }
+
+    fn title(&self) -> String {
+        let command = self.command.lines().next().unwrap_or_default();
+        let command: String = command.chars().take(24).collect();
+        format!("{} {command}", self.pid)
+    }
+}
+
+/// Make a line of process output displayable: drop ANSI escape sequences,
+/// keep only what follows the last carriage return like a terminal would,
+/// and make other control characters visible.
+fn clean_line(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1B' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte in '@'..='~'
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: up to BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1B' {
+                        chars.next_if_eq(&'\\');
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let out = out.rsplit('\r').next().unwrap_or_default();
+    out.chars()
+        .map(|c| match c {
+            '\t' => c,
+            '\x00'..='\x1F' => char::from_u32(0x2400 + c as u32).unwrap_or(c),
+            '\x7F' => '␡',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Panel with a tab per background process and its scrollback.
+#[derive(Debug, Clone, Default)]
+pub struct TerminalPanel {
+    visible: bool,
+    focused: bool,
+    tabs: Vec<ProcessTab>,
+    active: usize,
+    /// Output rows in the last render.
+    page: usize,
+    /// Where the tabs were last rendered, with the columns of each tab.
+    tab_bar: (Rect, Vec<(u16, u16)>),
+}
+
+impl TerminalPanel {
+    /// Create a hidden panel.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if the panel is visible.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the panel.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if !visible {
+            self.focused = false;
+        }
+    }
+
+    /// Toggle visibility.
+    pub fn toggle(&mut self) {
+        self.set_visible(!self.visible);
+    }
+
+    /// Check if the panel has focus.
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// Give or take focus.
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused && self.visible;
+    }
+
+    /// Get the panel height.
+    pub fn height(&self) -> u16 {
+        PANEL_HEIGHT
+    }
+
+    /// Check if there are no processes.
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// Number of processes still running.
+    pub fn running_count(&self) -> usize {
+        self.tabs.iter().filter(|t| t.exit.is_none()).count()
+    }
+
+    /// PID of the process in the active tab.
+    pub fn active_pid(&self) -> Option<u32> {
+        self.tabs.get(self.active).map(|t| t.pid)
+    }
+
+    /// Add a tab for a process that was started, and switch to it.
+    pub fn start(&mut self, pid: u32, command: impl Into<String>) {
+        self.tabs.retain(|t| t.pid != pid);
+        self.tabs.push(ProcessTab {
+            pid,
+            command: command.into(),
+            lines: VecDeque::new(),
+            partial: String::new(),
+            exit: None,
+            scroll_back: 0,
+        });
+        self.active = self.tabs.len() - 1;
+    }
+
+    /// Append output of a process.
+    pub fn append(&mut self, pid: u32, text: &str) {
+        if let Some(tab) = self.tabs.iter_mut().find(|t| t.pid == pid) {
+            tab.push(text);
+        }
+    }
+
+    /// Record that a process exited.
+    pub fn exit(&mut self, pid: u32, exit_code: Option<i32>) {
+        if let Some(tab) = self.tabs.iter_mut().find(|t| t.pid == pid) {
+            tab.exit = Some(exit_code);
+        }
+    }
+
+    /// Remove the active tab if its process has exited.
+    pub fn close_tab(&mut self) {
+        if self.tabs.get(self.active).is_some_and(|t| t.exit.is_some()) {
+            self.tabs.remove(self.active);
+            self.active = self.active.min(self.tabs.len().saturating_sub(1));
+        }
+    }
+
+    /// Switch to the next tab, wrapping around.
+    pub fn next_tab(&mut self) {
+        if !self.tabs.is_empty() {
+            self.active = (self.active + 1) % self.tabs.len();
+        }
+    }
+
+    /// Switch to the previous tab, wrapping around.
+    pub fn prev_tab(&mut self) {
+        if !self.tabs.is_empty() {
+            self.active = (self.active + self.tabs.len() - 1) % self.tabs.len();
+        }
+    }
+
+    /// Scroll back through the output.
+    pub fn scroll_up(&mut self, amount: usize) {
+        let page = self.page;
+        if let Some(tab) = self.tabs.get_mut(self.active) {
+            let max = tab.line_count().saturating_sub(page);
+            tab.scroll_back = (tab.scroll_back + amount).min(max);
+        }
+    }
+
+    /// Scroll towards the end of the output.
+    pub fn scroll_down(&mut self, amount: usize) {
+        if let Some(tab) = self.tabs.get_mut(self.active) {
+            tab.scroll_back = tab.scroll_back.saturating_sub(amount);
+        }
+    }
+
+    /// Follow the output again.
+    pub fn scroll_to_bottom(&mut self) {
+        if let Some(tab) = self.tabs.get_mut(self.active) {
+            tab.scroll_back = 0;
+        }
+    }
+
+    /// Handle a key event.
+    pub fn handle_key(&mut self, key: crossterm::event::KeyEvent) -> TerminalAction {
+        use crossterm::event::KeyCode;
+
+        if !self.visible || !self.focused {
+            return TerminalAction::None;
+        }
+        let page = self.page.max(1);
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::BackTab => self.prev_tab(),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Tab => self.next_tab(),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_up(1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_down(1),
+            KeyCode::PageUp => self.scroll_up(page),
+            KeyCode::PageDown => self.scroll_down(page),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll_up(usize::MAX / 2),
+            KeyCode::End | KeyCode::Char('G') => self.scroll_to_bottom(),
+            KeyCode::Char('x') => {
+                return match self.tabs.get(self.active) {
+                    Some(tab) if tab.exit.is_none() => TerminalAction::Stop(tab.pid),
+                    _ => TerminalAction::Handled,
+                };
+            }
+            KeyCode::Char('c') => self.close_tab(),
+            KeyCode::Esc => {
+                self.focused = false;
+                return TerminalAction::Unfocus;
+            }
+            KeyCode::Char('q') => {
+                self.set_visible(false);
+                return TerminalAction::Close;
+            }
+            _ => return TerminalAction::None,
+        }
+        TerminalAction::Handled
+    }
+
+    /// Handle a click on the tab bar, switching to the clicked tab.
+    /// Returns `false` if no tab was clicked.
+    pub fn handle_click(&mut self, x: u16, y: u16) -> bool {
+        let (area, tabs) = &self.tab_bar;
+        if !self.visible || y != area.y || x < area.x || x >= area.x + area.width {
+            return false;
+        }
+        match tabs.iter().position(|&(start, end)| x >= start && x < end) {
+            Some(index) => {
+                self.active = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Render the panel.
+    pub fn render(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let _timer = metrics::widget_timer("terminal");
+
+        if !self.visible || area.width < 20 || area.height < 4 {
+            self.tab_bar = (Rect::default(), Vec::new());
+            return;
+        }
+
+        frame.render_widget(
+            Block::default().style(Style::default().bg(theme.background_panel)),
+            area,
+        );
+        let inner = Rect::new(
+            area.x + 1,
+            area.y,
+            area.width.saturating_sub(2),
+            area.height,
+        );
+        let tab_area = Rect::new(inner.x, inner.y, inner.width, 1);
+        let output_area = Rect::new(
+            inner.x,
+            inner.y + 1,
+            inner.width,
+            inner.height.saturating_sub(2),
+        );
+        let footer_area = Rect::new(inner.x, inner.y + inner.height - 1, inner.width, 1);
+        self.page = output_area.height as usize;
+
+        self.render_tabs(frame, tab_area, theme);
+
+        let lines: Vec<Line> = match self.tabs.get(self.active) {
+            None => vec![Line::from(Span::styled(
+                "No background processes",
+                theme.muted_style(),
+            ))],
+            Some(tab) => {
+                let count = tab.line_count();
+                let end = count.saturating_sub(tab.scroll_back);
+                let start = end.saturating_sub(self.page);
+                (start..end)
+                    .map(|i| Line::from(Span::styled(tab.line(i), theme.text_style())))
+                    .collect()
+            }
+        };
+        frame.render_widget(Paragraph::new(lines), output_area);
+
+        let position = match self.tabs.get(self.active) {
+            Some(tab) if tab.scroll_back > 0 => format!("{} lines back", tab.scroll_back),
+            _ => String::new(),
+        };
+        let hint = if self.focused {
+            "←/→ tab · x stop · c close · esc back"
+        } else {
+            "click to focus"
+        };
+        let gap = (inner.width as usize).saturating_sub(position.len() + hint.chars().count());
+        frame.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(position, theme.dim_style()),
+                Span::raw(" ".repeat(gap)),
+                Span::styled(hint, theme.dim_style()),
+            ])),
+            footer_area,
+        );
+    }
+
+    /// The tab bar: a tab per process with its state.
+    fn render_tabs(&mut self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let title_style = if self.focused {
+            theme.accent_style().add_modifier(Modifier::BOLD)
+        } else {
+            theme.muted_style().add_modifier(Modifier::BOLD)
+        };
+        let mut spans = vec![Span::styled("Processes ", title_style)];
+        let mut x = area.x + 10;
+        let mut columns = Vec::new();
+        for (i, tab) in self.tabs.iter().enumerate() {
+            let (state, state_style) = match tab.exit {
+                None => ("●".to_string(), Style::default().fg(theme.success)),
+                Some(Some(0)) => ("✓".to_string(), theme.muted_style()),
+                Some(Some(code)) => (format!("✗{code}"), theme.error_style()),
+                Some(None) => ("■".to_string(), theme.muted_style()),
+            };
+            let title = format!(" {} ", tab.title());
+            let style = if i == self.active {
+                theme.text_style().add_modifier(Modifier::REVERSED)
+            } else {
+                theme.muted_style()
+            };
+            let width = (title.chars().count() + state.chars().count() + 2) as u16;
+            columns.push((x, x + width));
+            x += width;
+            spans.push(Span::styled(title, style));
+            spans.push(Span::styled(state, state_style));
+            spans.push(Span::raw("  "));
+        }
+        self.tab_bar = (area, columns);
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent};
+
+    fn press(panel: &mut TerminalPanel, code: KeyCode) -> TerminalAction {
+        panel.handle_key(KeyEvent::from(code))
+    }
+
+    #[test]
+    fn test_output_is_split_into_lines() {
+        let mut panel = TerminalPanel::new();
+        panel.start(42, "npm run dev");
+        panel.append(42, "compiling");
+        panel.append(42, "...\r\nready on :3000\n50%\r100%");
+        panel.append(7, "unknown process");
+
+        let tab = &panel.tabs[0];
+        assert_eq!(tab.lines, ["compiling...", "ready on :3000"]);
+        assert_eq!(tab.line_count(), 3);
+        assert_eq!(tab.line(2), "100%");
+    }
+
+    #[test]
+    fn test_clean_line() {
+        assert_eq!(clean_line("\x1b[32mready\x1b[0m in 3ms"), "ready in 3ms");
+        assert_eq!(clean_line("\x1b]0;title\x07done"), "done");
+        assert_eq!(clean_line("10%\r50%\r100%"), "100%");
+        assert_eq!(clean_line("bell\x07"), "bell␇");
+    }
+
+    #[test]
+    fn test_scrollback_is_capped() {
+        let mut panel = TerminalPanel::new();
+        panel.start(1, "yes");
+        panel.append(1, &"y\n".repeat(MAX_SCROLLBACK + 10));
+        assert_eq!(panel.tabs[0].lines.len(), MAX_SCROLLBACK);
+    }
+
+    #[test]
+    fn test_scrolling_back_holds_position() {
+        let mut panel = TerminalPanel::new();
+        panel.page = 5;
+        panel.start(1, "tail -f log");
+        panel.append(1, &"line\n".repeat(20));
+        panel.scroll_up(3);
+        panel.append(1, "more\n");
+        assert_eq!(panel.tabs[0].scroll_back, 4);
+        panel.scroll_up(usize::MAX / 2);
+        assert_eq!(panel.tabs[0].scroll_back, 16);
+        panel.scroll_to_bottom();
+        assert_eq!(panel.tabs[0].scroll_back, 0);
+    }
+
+    #[test]
+    fn test_tabs_stop_and_close() {
+        let mut panel = TerminalPanel::new();
+        panel.set_visible(true);
+        panel.set_focused(true);
+        panel.start(1, "server");
+        panel.start(2, "watcher");
+        assert_eq!(panel.active_pid(), Some(2));
+        assert_eq!(panel.running_count(), 2);
+
+        press(&mut panel, KeyCode::Right);
+        assert_eq!(panel.active_pid(), Some(1));
+        assert_eq!(
+            press(&mut panel, KeyCode::Char('x')),
+            TerminalAction::Stop(1)
+        );
+
+        // Running processes keep their tab
+        press(&mut panel, KeyCode::Char('c'));
+        assert_eq!(panel.tabs.len(), 2);
+        panel.exit(1, None);
+        assert_eq!(
+            press(&mut panel, KeyCode::Char('x')),
+            TerminalAction::Handled
+        );
+        press(&mut panel, KeyCode::Char('c'));
+        assert_eq!(panel.active_pid(), Some(2));
+
+        assert_eq!(press(&mut panel, KeyCode::Esc), TerminalAction::Unfocus);
+        assert_eq!(press(&mut panel, KeyCode::Left), TerminalAction::None);
+    }
+
+    #[test]
+    fn test_render() {
+        let backend = ratatui::backend::TestBackend::new(80, PANEL_HEIGHT);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        let theme = Theme::default();
+        let mut panel = TerminalPanel::new();
+        panel.set_visible(true);
+        panel.start(42, "npm run dev");
+        panel.append(42, "ready on :3000\n");
+        panel.exit(42, Some(1));
+        terminal
+            .draw(|frame| panel.render(frame, frame.area(), &theme))
+            .unwrap();
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(text.contains("42 npm run dev"));
+        assert!(text.contains("✗1"));
+        assert!(text.contains("ready on :3000"));
+
+        // Clicking the tab selects it
+        assert!(panel.handle_click(12, 0));
+        assert!(!panel.handle_click(12, 3));
+    }
+}
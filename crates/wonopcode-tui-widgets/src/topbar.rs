@@ -1,4 +1,6 @@
 //! Top bar widget showing project directory and session info.
+//!
+//! With several session tabs open, the session title gives way to a tab bar.
 
 use ratatui::{
     layout::Rect,
@@ -10,6 +12,39 @@ use ratatui::{
 
 use wonopcode_tui_core::Theme;
 
+/// Longest tab title shown in the tab bar, in characters.
+const MAX_TAB_TITLE: usize = 18;
+
+/// A session tab in the tab bar.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TabLabel {
+    /// Session title, empty for a new session.
+    pub title: String,
+    /// Whether the tab's agent is working.
+    pub busy: bool,
+    /// Whether the tab finished while in the background.
+    pub unseen: bool,
+}
+
+impl TabLabel {
+    /// Text of the tab, numbered from 1.
+    fn text(&self, index: usize) -> String {
+        let title = if self.title.is_empty() {
+            "New session"
+        } else {
+            &self.title
+        };
+        let mut text = format!(" {} {}", index + 1, truncate(title, MAX_TAB_TITLE));
+        if self.busy {
+            text.push_str(" ●");
+        } else if self.unseen {
+            text.push_str(" ✓");
+        }
+        text.push(' ');
+        text
+    }
+}
+
 /// Top bar widget.
 #[derive(Debug, Clone, Default)]
 pub struct TopBarWidget {
@@ -19,6 +54,10 @@ pub struct TopBarWidget {
     session_title: Option<String>,
     /// Project name (optional).
     project_name: Option<String>,
+    /// Open session tabs, shown when there is more than one.
+    tabs: Vec<TabLabel>,
+    /// Index of the tab shown.
+    active_tab: usize,
 }
 
 impl TopBarWidget {
@@ -42,6 +81,12 @@ impl TopBarWidget {
         self.project_name = name;
     }
 
+    /// Set the open session tabs and the one shown.
+    pub fn set_tabs(&mut self, tabs: Vec<TabLabel>, active: usize) {
+        self.tabs = tabs;
+        self.active_tab = active;
+    }
+
     /// Render the top bar.
     pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
         if area.height == 0 {
@@ -60,8 +105,22 @@ impl TopBarWidget {
             theme.text_style().add_modifier(Modifier::BOLD),
         ));
 
-        // Session title if available
-        if let Some(ref title) = self.session_title {
+        // Tabs, or the session title if there is only one
+        if self.tabs.len() > 1 {
+            spans.push(Span::styled(" │", theme.muted_style()));
+            for (i, tab) in self.tabs.iter().enumerate() {
+                let style = if i == self.active_tab {
+                    theme
+                        .accent_style()
+                        .add_modifier(Modifier::BOLD | Modifier::REVERSED)
+                } else if tab.unseen {
+                    theme.accent_style()
+                } else {
+                    theme.muted_style()
+                };
+                spans.push(Span::styled(tab.text(i), style));
+            }
+        } else if let Some(ref title) = self.session_title {
             if !title.is_empty() {
                 spans.push(Span::styled(" │ ", theme.muted_style()));
                 spans.push(Span::styled(title, theme.accent_style()));
@@ -78,8 +137,8 @@ impl TopBarWidget {
         }
 
         // Calculate spacing
-        let left_len: usize = spans.iter().map(|s| s.content.len()).sum();
-        let right_len: usize = right_parts.iter().map(|s| s.content.len()).sum();
+        let left_len: usize = spans.iter().map(|s| s.width()).sum();
+        let right_len: usize = right_parts.iter().map(|s| s.width()).sum();
         let available = area.width as usize;
         let spacing = available.saturating_sub(left_len + right_len);
 
@@ -120,6 +179,17 @@ impl TopBarWidget {
     }
 }
 
+/// Shorten text to at most `max` characters, ending with an ellipsis.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(max.saturating_sub(1)).collect();
+        short.push('…');
+        short
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,6 +273,34 @@ mod tests {
         assert_eq!(cloned.project_name, Some("Project".to_string()));
     }
 
+    #[test]
+    fn test_tab_label_text() {
+        let tab = TabLabel::default();
+        assert_eq!(tab.text(0), " 1 New session ");
+
+        let tab = TabLabel {
+            title: "Refactor the storage layer".to_string(),
+            busy: true,
+            unseen: false,
+        };
+        assert_eq!(tab.text(1), " 2 Refactor the stor… ● ");
+
+        let tab = TabLabel {
+            title: "Questions".to_string(),
+            busy: false,
+            unseen: true,
+        };
+        assert_eq!(tab.text(2), " 3 Questions ✓ ");
+    }
+
+    #[test]
+    fn test_top_bar_widget_set_tabs() {
+        let mut widget = TopBarWidget::new();
+        widget.set_tabs(vec![TabLabel::default(), TabLabel::default()], 1);
+        assert_eq!(widget.tabs.len(), 2);
+        assert_eq!(widget.active_tab, 1);
+    }
+
     #[test]
     fn test_top_bar_widget_debug() {
         let widget = TopBarWidget::new();
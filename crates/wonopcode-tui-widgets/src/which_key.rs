@@ -1,7 +1,8 @@
 //! Which-key overlay widget for displaying available key sequences.
 //!
-//! Shows available keyboard shortcuts when the leader key (Ctrl+X) is pressed,
-//! similar to vim's which-key plugin.
+//! Shows available keyboard shortcuts when the leader key (Ctrl+X by default)
+//! is pressed, similar to vim's which-key plugin. Menus of key chords from the
+//! configuration are listed as `+title` and show their own keys when opened.
 
 use ratatui::{
     layout::{Alignment, Rect},
@@ -11,15 +12,55 @@ use ratatui::{
     Frame,
 };
 
-use wonopcode_tui_core::Theme;
+use wonopcode_tui_core::{ChordCommand, ChordMenu, KeyAction, KeybindManager, Theme};
+
+/// Actions shown in the overlay when bound after the leader, in order.
+const LEADER_ACTIONS: &[(KeyAction, &str)] = &[
+    (KeyAction::SessionNew, "New session"),
+    (KeyAction::SessionList, "Session list"),
+    (KeyAction::TabNew, "New tab"),
+    (KeyAction::TabClose, "Close tab"),
+    (KeyAction::TabNext, "Next tab"),
+    (KeyAction::TabPrev, "Previous tab"),
+    (KeyAction::ModelList, "Model selection"),
+    (KeyAction::AgentList, "Agent selection"),
+    (KeyAction::SidebarToggle, "Toggle sidebar"),
+    (KeyAction::PreviewToggle, "Toggle preview pane"),
+    (KeyAction::FileBrowserToggle, "File browser"),
+    (KeyAction::TerminalToggle, "Process output"),
+    (KeyAction::GitOpen, "Git"),
+    (KeyAction::ThemeList, "Theme selection"),
+    (KeyAction::EditCopy, "Copy response"),
+    (KeyAction::EditorOpen, "Edit in $EDITOR"),
+    (KeyAction::SessionExport, "Export session"),
+    (KeyAction::EditUndo, "Undo message"),
+    (KeyAction::EditRedo, "Redo message"),
+    (KeyAction::SessionRevert, "Revert message"),
+    (KeyAction::SessionUnrevert, "Unrevert message"),
+    (KeyAction::SessionCompact, "Compact conversation"),
+    (KeyAction::SessionTimeline, "Timeline"),
+    (KeyAction::SettingsOpen, "Settings"),
+    (KeyAction::HelpToggle, "Help"),
+    (KeyAction::CommandPalette, "Command palette"),
+    (KeyAction::AppExit, "Quit"),
+];
 
 /// A key binding entry for the which-key display.
 #[derive(Debug, Clone)]
 pub struct KeyBinding {
     /// The key to press.
-    pub key: &'static str,
+    pub key: String,
     /// Description of what the key does.
-    pub description: &'static str,
+    pub description: String,
+}
+
+/// The keys of a chord menu.
+#[derive(Debug, Clone)]
+struct MenuBindings {
+    /// Title for the overlay while the menu is open.
+    title: String,
+    /// Key bindings of the menu.
+    bindings: Vec<KeyBinding>,
 }
 
 /// Which-key overlay widget.
@@ -31,6 +72,10 @@ pub struct WhichKeyOverlay {
     title: String,
     /// Key bindings to display.
     bindings: Vec<KeyBinding>,
+    /// The configured chord menus.
+    menus: Vec<MenuBindings>,
+    /// Index of the open menu, if any.
+    open_menu: Option<usize>,
 }
 
 impl WhichKeyOverlay {
@@ -40,66 +85,89 @@ impl WhichKeyOverlay {
             visible: false,
             title: "Ctrl+X".to_string(),
             bindings: Self::default_bindings(),
+            menus: Vec::new(),
+            open_menu: None,
         }
     }
 
-    /// Get the default Ctrl+X key bindings.
+    /// Get the key bindings of the default keybinds.
     fn default_bindings() -> Vec<KeyBinding> {
-        vec![
-            KeyBinding {
-                key: "N",
-                description: "New session",
-            },
-            KeyBinding {
-                key: "L",
-                description: "Session list",
-            },
-            KeyBinding {
-                key: "M",
-                description: "Model selection",
-            },
-            KeyBinding {
-                key: "A",
-                description: "Agent selection",
-            },
-            KeyBinding {
-                key: "B",
-                description: "Toggle sidebar",
-            },
-            KeyBinding {
-                key: "T",
-                description: "Theme selection",
-            },
-            KeyBinding {
-                key: "Y",
-                description: "Copy response",
-            },
-            KeyBinding {
-                key: "E",
-                description: "Edit in $EDITOR",
-            },
-            KeyBinding {
-                key: "X",
-                description: "Export session",
-            },
-            KeyBinding {
-                key: "U",
-                description: "Undo message",
-            },
-            KeyBinding {
-                key: "R",
-                description: "Redo message",
-            },
-            KeyBinding {
-                key: "S",
-                description: "Settings",
-            },
-        ]
-    }
-
-    /// Show the overlay.
+        Self::bindings_for(&KeybindManager::default())
+    }
+
+    /// Show the leader bindings of a keybind configuration.
+    pub fn set_keybinds(&mut self, keybinds: &KeybindManager) {
+        self.title = keybinds.leader_display();
+        self.bindings = Self::bindings_for(keybinds);
+        self.bindings
+            .extend(keybinds.menus().iter().map(|menu| KeyBinding {
+                key: menu.key.chord_display(),
+                description: format!("+{}", menu.title),
+            }));
+        self.menus = keybinds
+            .menus()
+            .iter()
+            .map(|menu| MenuBindings {
+                title: format!(
+                    "{} {}: {}",
+                    self.title,
+                    menu.key.chord_display(),
+                    menu.title
+                ),
+                bindings: Self::menu_bindings(menu),
+            })
+            .collect();
+        self.open_menu = None;
+    }
+
+    /// Get the leader bindings of the actions listed in the overlay.
+    fn bindings_for(keybinds: &KeybindManager) -> Vec<KeyBinding> {
+        LEADER_ACTIONS
+            .iter()
+            .filter_map(|(action, description)| {
+                let keybind = keybinds.get_bindings(*action).iter().find(|kb| kb.leader)?;
+                Some(KeyBinding {
+                    key: keybind.chord_display(),
+                    description: description.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Get the bindings of a chord menu, described like the leader actions.
+    fn menu_bindings(menu: &ChordMenu) -> Vec<KeyBinding> {
+        menu.entries
+            .iter()
+            .map(|(keybind, command)| {
+                let description = match command {
+                    ChordCommand::Action(action) => LEADER_ACTIONS
+                        .iter()
+                        .find(|(a, _)| a == action)
+                        .map(|(_, description)| *description)
+                        .unwrap_or(action.description())
+                        .to_string(),
+                    ChordCommand::Slash(command) => format!("/{command}"),
+                };
+                KeyBinding {
+                    key: keybind.chord_display(),
+                    description,
+                }
+            })
+            .collect()
+    }
+
+    /// Show the overlay with the leader bindings.
     pub fn show(&mut self) {
         self.visible = true;
+        self.open_menu = None;
+    }
+
+    /// Show the overlay with the keys of a chord menu.
+    pub fn show_menu(&mut self, menu: usize) {
+        if menu < self.menus.len() {
+            self.visible = true;
+            self.open_menu = Some(menu);
+        }
     }
 
     /// Hide the overlay.
@@ -118,16 +186,25 @@ impl WhichKeyOverlay {
             return;
         }
 
+        let (title, bindings) = match self.open_menu.and_then(|i| self.menus.get(i)) {
+            Some(menu) => (&menu.title, &menu.bindings),
+            None => (&self.title, &self.bindings),
+        };
+
         // Calculate overlay dimensions
-        let max_key_len = self.bindings.iter().map(|b| b.key.len()).max().unwrap_or(1);
-        let max_desc_len = self
-            .bindings
+        let max_key_len = bindings
+            .iter()
+            .map(|b| b.key.chars().count())
+            .max()
+            .unwrap_or(1);
+        let max_desc_len = bindings
             .iter()
-            .map(|b| b.description.len())
+            .map(|b| b.description.chars().count())
             .max()
             .unwrap_or(10);
-        let content_width = max_key_len + 3 + max_desc_len + 4; // key + " - " + desc + padding
-        let content_height = self.bindings.len() as u16 + 2; // bindings + borders
+        let content_width = (max_key_len + 3 + max_desc_len + 4) // key + " - " + desc + padding
+            .max(title.chars().count() + 4);
+        let content_height = bindings.len() as u16 + 2; // bindings + borders
 
         let overlay_width = (content_width as u16)
             .min(area.width.saturating_sub(4))
@@ -145,20 +222,20 @@ impl WhichKeyOverlay {
         // Build the content
         let mut lines: Vec<Line> = vec![];
 
-        for binding in &self.bindings {
+        for binding in bindings {
             let key_span = Span::styled(
                 format!(" {:>width$}", binding.key, width = max_key_len),
                 theme.accent_style().add_modifier(Modifier::BOLD),
             );
             let sep_span = Span::styled(" → ", theme.muted_style());
-            let desc_span = Span::styled(binding.description, theme.text_style());
+            let desc_span = Span::styled(binding.description.as_str(), theme.text_style());
 
             lines.push(Line::from(vec![key_span, sep_span, desc_span]));
         }
 
         let block = Block::default()
             .title(Span::styled(
-                format!(" {} ", self.title),
+                format!(" {title} "),
                 theme.accent_style().add_modifier(Modifier::BOLD),
             ))
             .borders(Borders::ALL)
@@ -176,12 +253,13 @@ impl WhichKeyOverlay {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use wonopcode_tui_core::{ChordMenuConfig, KeybindConfig};
 
     #[test]
     fn test_key_binding_clone() {
         let binding = KeyBinding {
-            key: "N",
-            description: "New session",
+            key: "N".to_string(),
+            description: "New session".to_string(),
         };
         let cloned = binding.clone();
         assert_eq!(cloned.key, "N");
@@ -191,8 +269,8 @@ mod tests {
     #[test]
     fn test_key_binding_debug() {
         let binding = KeyBinding {
-            key: "N",
-            description: "New session",
+            key: "N".to_string(),
+            description: "New session".to_string(),
         };
         let debug = format!("{binding:?}");
         assert!(debug.contains("KeyBinding"));
@@ -237,6 +315,74 @@ mod tests {
         assert!(bindings.iter().any(|b| b.key == "L"));
     }
 
+    #[test]
+    fn test_which_key_overlay_set_keybinds() {
+        let mut config = KeybindConfig {
+            leader: "ctrl+b".to_string(),
+            ..Default::default()
+        };
+        config
+            .bindings
+            .insert("session_new".to_string(), "ctrl+n,<leader>o".to_string());
+        config
+            .bindings
+            .insert("model_list".to_string(), "none".to_string());
+        config.bindings.insert(
+            "session_timeline".to_string(),
+            "<leader>shift+t".to_string(),
+        );
+
+        let mut overlay = WhichKeyOverlay::new();
+        overlay.set_keybinds(&KeybindManager::new(&config));
+        assert_eq!(overlay.title, "Ctrl+B");
+        let find = |description: &str| {
+            overlay
+                .bindings
+                .iter()
+                .find(|b| b.description == description)
+                .map(|b| b.key.as_str())
+        };
+        assert_eq!(find("New session"), Some("O"));
+        assert_eq!(find("Model selection"), None);
+        assert_eq!(find("Timeline"), Some("Shift+T"));
+        assert_eq!(find("Quit"), Some("Ctrl+C"));
+    }
+
+    #[test]
+    fn test_which_key_overlay_menus() {
+        let mut menu = ChordMenuConfig {
+            title: Some("git".to_string()),
+            ..Default::default()
+        };
+        menu.keys.insert("g".to_string(), "git_open".to_string());
+        menu.keys.insert("s".to_string(), "/git".to_string());
+        let mut config = KeybindConfig::default();
+        config.menus.insert("g".to_string(), menu);
+
+        let mut overlay = WhichKeyOverlay::new();
+        overlay.set_keybinds(&KeybindManager::new(&config));
+        let git: Vec<_> = overlay.bindings.iter().filter(|b| b.key == "G").collect();
+        assert_eq!(git.len(), 1);
+        assert_eq!(git[0].description, "+git");
+
+        overlay.show_menu(1);
+        assert!(!overlay.is_visible());
+        overlay.show_menu(0);
+        assert!(overlay.is_visible());
+        assert_eq!(overlay.open_menu, Some(0));
+        let menu = &overlay.menus[0];
+        assert_eq!(menu.title, "Ctrl+X G: git");
+        let keys: Vec<_> = menu
+            .bindings
+            .iter()
+            .map(|b| (b.key.as_str(), b.description.as_str()))
+            .collect();
+        assert_eq!(keys, [("G", "Git"), ("S", "/git")]);
+
+        overlay.show();
+        assert_eq!(overlay.open_menu, None);
+    }
+
     #[test]
     fn test_which_key_overlay_clone() {
         let mut overlay = WhichKeyOverlay::new();
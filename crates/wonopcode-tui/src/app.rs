@@ -1,33 +1,48 @@
 //! Main application for the TUI.
 
 use crate::widgets::{
-    autocomplete::{AutocompleteAction, FileAutocomplete},
+    autocomplete::{AutocompleteAction, FileAutocomplete, SymbolSuggestion},
     dialog::{
-        AgentDialog, AgentInfo, CommandPalette, GitCommitDisplay, GitDialog, GitDialogResult,
-        GitFileDisplay, HelpDialog, InputDialog, InputDialogResult, McpDialog, McpServerInfo,
-        McpStatus as DialogMcpStatus, ModelDialog, PerfDialog, PermissionDialog, PermissionResult,
-        SandboxAction, SandboxDialog, SandboxState as DialogSandboxState, SessionDialog,
-        SettingsDialog, SettingsResult, StatusDialog, ThemeDialog, TimelineDialog, TimelineItem,
+        AgentDialog, AgentInfo, CommandPalette, ConnectDialog, CoordinatorAgent, CoordinatorDialog,
+        DiscoveredServer, GitCommitDisplay, GitDialog, GitDialogResult, GitFileDisplay, HelpDialog,
+        InputDialog, InputDialogResult, InstructionFileEntry, McpDialog, McpServerInfo,
+        McpStatus as DialogMcpStatus, ModelDialog, PerfDialog, PermissionDialog,
+        PermissionLogEntry, PermissionResult, ProjectDialog, SandboxAction, SandboxDialog,
+        SandboxState as DialogSandboxState, SessionDialog, SettingsDialog, SettingsResult,
+        StatusDialog, TemplateDialog, ThemeDialog, TimelineDialog, TimelineItem, UsageDialog,
+        UsageDialogResult,
     },
+    diff::{DiffNavAction, FileDiff},
+    file_browser::{FileBrowser, FileBrowserAction, ListedFile},
     footer::{FooterStatus, FooterWidget},
     help_overlay::{HelpContext, HelpOverlay},
-    input::{InputAction, InputWidget},
+    image::{prepare_attachment, rgba_to_png, ImageProtocol},
+    input::{ContextAttachment, InputAction, InputWidget},
     logo::LogoWidget,
-    messages::{DisplayMessage, DisplayToolCall, MessageSegment, MessagesWidget, ToolStatus},
+    messages::{
+        DisplayMessage, DisplayToolCall, MessageRole, MessageSegment, MessageUsage, MessagesWidget,
+        ToolProgress, ToolStatus,
+    },
     mode_indicator::{DisplayMode, ModeIndicator},
     onboarding::OnboardingOverlay,
+    preview::{PreviewMode, PreviewPane},
+    review::{HunkDecision, ReviewView, ReviewedFile},
     search::{extract_preview, fuzzy_match, SearchMatch, SearchWidget},
-    sidebar::{LspStatus, McpServerStatus, McpStatus, ModifiedFile, SidebarWidget, TodoItem},
-    slash_commands::{SlashCommandAction, SlashCommandAutocomplete},
+    sidebar::{
+        ContextInfo, LspStatus, McpServerStatus, McpStatus, ModifiedFile, SandboxUsage,
+        SidebarClick, SidebarWidget, TodoItem,
+    },
+    slash_commands::{SlashCommand, SlashCommandAction, SlashCommandAutocomplete},
+    terminal::{TerminalAction, TerminalPanel},
     toast::{Toast, ToastManager},
-    topbar::TopBarWidget,
+    topbar::{TabLabel, TopBarWidget},
     which_key::WhichKeyOverlay,
 };
 use arboard::Clipboard;
 use crossterm::{
     event::{
-        DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        KeyCode, KeyModifiers,
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, KeyCode, KeyModifiers,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
@@ -39,16 +54,27 @@ use ratatui::{
     widgets::Paragraph,
     Frame, Terminal,
 };
+use std::collections::HashSet;
 use std::io::{self, Write};
 use std::process::Command;
 use tokio::sync::mpsc;
 use wonopcode_tui_core::{
-    is_escape, metrics, AgentMode, Event, EventHandler, EventType, ModelState, RenderSettings,
-    Theme,
+    asciify, is_escape, metrics, set_locale, set_mouse_capture, tr, tr_args, AgentMode,
+    ChordCommand, ChordMenuConfig, Event, EventHandler, EventType, KeyAction, KeyScope,
+    KeybindConfig, KeybindManager, Locale, ModelState, Notifier, NotifyMethod, RenderSettings,
+    Theme, ThemeRegistry,
 };
+use wonopcode_util::{id::IdPrefix, Identifier};
 
 // Re-export SaveScope for use in runner
 pub use crate::widgets::dialog::SaveScope;
+pub use crate::widgets::dialog::{ToolLatency, UsageAmount};
+
+/// Largest file the preview pane reads.
+const MAX_PREVIEW_FILE_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Shortest `@` mention the language servers are asked for symbols for.
+const MIN_SYMBOL_QUERY_CHARS: usize = 2;
 
 /// Restore terminal to normal state.
 ///
@@ -62,6 +88,7 @@ pub fn restore_terminal() {
         LeaveAlternateScreen,
         DisableMouseCapture,
         DisableBracketedPaste,
+        DisableFocusChange,
         crossterm::cursor::Show
     );
     let _ = io::stdout().flush();
@@ -106,13 +133,21 @@ impl TerminalGuard {
     /// This enables raw mode, enters the alternate screen, enables mouse capture,
     /// and enables bracketed paste.
     pub fn new() -> io::Result<Self> {
+        Self::with_mouse(true)
+    }
+
+    /// Like [`TerminalGuard::new`], with mouse capture only if `mouse` is set.
+    pub fn with_mouse(mouse: bool) -> io::Result<Self> {
         enable_raw_mode()?;
         execute!(
             io::stdout(),
             EnterAlternateScreen,
-            EnableMouseCapture,
-            EnableBracketedPaste
+            EnableBracketedPaste,
+            EnableFocusChange
         )?;
+        if mouse {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
         Ok(Self { initialized: true })
     }
 
@@ -150,17 +185,25 @@ pub enum ActiveDialog {
     ModelSelect,
     AgentSelect,
     SessionList,
+    TemplateSelect,
+    ProjectSelect,
+    Connect,
+    ConnectSecret,
     ThemeSelect,
     Help,
     Status,
     Perf,
+    Usage,
     Rename,
+    Export,
     Mcp,
     Timeline,
     Sandbox,
     Settings,
     Permission,
     Git,
+    Coordinator,
+    Review,
 }
 
 /// State of the application.
@@ -188,6 +231,16 @@ pub enum AppState {
 pub enum AppAction {
     /// Send a prompt to the AI.
     SendPrompt(String),
+    /// Attach a base64 encoded image to the next prompt.
+    AttachImage { media_type: String, data: String },
+    /// Pin a file, or the lines of a symbol in it, to the next prompt.
+    AttachContext {
+        path: String,
+        symbol: Option<String>,
+        lines: Option<(u32, u32)>,
+    },
+    /// Search the language servers' workspace symbols for `@` mentions.
+    SearchSymbols { query: String },
     /// Cancel the current operation.
     Cancel,
     /// Quit the application.
@@ -220,8 +273,15 @@ pub enum AppAction {
     McpReconnect { name: String },
     /// Fork the session from a specific message.
     ForkSession { message_id: Option<String> },
+    /// Merge the current branch into its parent session.
+    MergeSession,
     /// Share the current session.
-    ShareSession,
+    ShareSession {
+        /// Link lifetime such as "7d" or "never"; the configured default if not set.
+        expires: Option<String>,
+        /// Password needed to view the share.
+        password: Option<String>,
+    },
     /// Unshare the current session.
     UnshareSession,
     /// Go to a specific message in the timeline.
@@ -232,6 +292,50 @@ pub enum AppAction {
     SandboxStop,
     /// Restart the sandbox.
     SandboxRestart,
+    /// Switch to a named config profile (empty name lists profiles).
+    SwitchProfile { name: String },
+    /// Search sessions by title, tags and message text.
+    SearchSessions { query: String },
+    /// Run agents on separate tasks in their own git worktrees.
+    Orchestrate { tasks: Vec<String> },
+    /// Merge the branches of finished orchestrated agents.
+    MergeOrchestration,
+    /// Cancel the running orchestration.
+    CancelOrchestration,
+    /// Move the session into its own git worktree and branch.
+    CreateWorktree { name: Option<String> },
+    /// List the git worktrees of the project.
+    ListWorktrees,
+    /// Finish the session's worktree: `merge`, `pr` or `discard`.
+    FinishWorktree { action: String },
+    /// Start the session from a template (empty name lists templates).
+    ApplyTemplate { name: String },
+    /// Save the current session's setup as a template.
+    SaveTemplate { name: String },
+    /// List the projects the server manages.
+    ListProjects,
+    /// Switch to another project by path or name.
+    SwitchProject { project: String },
+    /// Start browsing the local network for servers to connect to.
+    BrowseServers,
+    /// Stop browsing for servers.
+    StopBrowsingServers,
+    /// Connect to a headless server, and send the actions there from then on.
+    /// Without a secret, the one given at startup is used.
+    ConnectServer {
+        address: String,
+        secret: Option<String>,
+    },
+    /// Open a session in a new tab, with its own runner.
+    NewTab,
+    /// Show the tab at an index.
+    SwitchTab(usize),
+    /// Close the tab at an index, cancelling what it runs.
+    CloseTab(usize),
+    /// Request the project memory for editing.
+    LoadMemory,
+    /// Replace the project memory.
+    SaveMemory { content: String },
     /// Save settings to config file.
     SaveSettings {
         /// Where to save (project or global).
@@ -255,6 +359,22 @@ pub enum AppAction {
         /// Remember this decision for future requests.
         remember: bool,
     },
+    /// Load the recent permission decisions of the project.
+    LoadPermissionAudit,
+    /// Show spend against the configured alerts and limits.
+    LoadSpend,
+    /// Acknowledge reached spending limits.
+    AcknowledgeSpendLimit,
+    /// Load usage over the last `days` days for the usage dashboard.
+    LoadUsage { days: u32 },
+    /// Load the session's file changes for review.
+    StartReview,
+    /// Apply the decisions of a review.
+    ApplyReview { files: Vec<ReviewedFile> },
+    /// Stop a background process started by the bash tool.
+    StopProcess { pid: u32 },
+    /// Cancel a running tool call, leaving the rest of the prompt running.
+    CancelTool { id: String },
     /// Git: Get repository status.
     GitStatus,
     /// Git: Stage files.
@@ -263,14 +383,28 @@ pub enum AppAction {
     GitUnstage { paths: Vec<String> },
     /// Git: Checkout (discard changes to) files.
     GitCheckout { paths: Vec<String> },
-    /// Git: Create commit with message.
-    GitCommit { message: String },
+    /// Git: Get a file's unstaged or staged changes as a diff.
+    GitDiff { path: String, staged: bool },
+    /// Git: Stage one hunk of a file's changes.
+    GitStageHunk { path: String, hunk: usize },
+    /// Git: Unstage one hunk of a file's staged changes.
+    GitUnstageHunk { path: String, hunk: usize },
+    /// Git: Draft a commit message for the staged changes with the current model.
+    GitGenerateCommitMessage,
+    /// Git: Create commit with message, pushing it if asked to.
+    GitCommit { message: String, push: bool },
     /// Git: Get commit history.
     GitHistory,
     /// Git: Push to remote.
     GitPush,
     /// Git: Pull from remote.
     GitPull,
+    /// List a directory of a project on another machine, relative to its root.
+    ListFiles { dir: String },
+    /// Read a file of a project on another machine, relative to its root.
+    ReadFile { path: String },
+    /// Drive a shared session, making its driver a watcher.
+    TakeControl,
 }
 
 /// Result from opening external editor.
@@ -315,6 +449,15 @@ pub enum AppUpdate {
         id: String,
         input: String,
     },
+    /// A running tool call made progress.
+    ToolProgress {
+        id: String,
+        /// Share of the work done, from 0 to 1, if known.
+        fraction: Option<f64>,
+        message: Option<String>,
+    },
+    /// A running tool call wrote output.
+    ToolOutput { id: String, text: String },
     /// Tool call completed.
     ToolCompleted {
         id: String,
@@ -346,6 +489,11 @@ pub enum AppUpdate {
     },
     /// LSP servers updated.
     LspUpdated(Vec<LspStatusUpdate>),
+    /// Workspace symbols found for a query.
+    Symbols {
+        query: String,
+        symbols: Vec<SymbolSuggestion>,
+    },
     /// MCP servers updated.
     McpUpdated(Vec<McpStatusUpdate>),
     /// Modified files updated.
@@ -354,10 +502,110 @@ pub enum AppUpdate {
     PermissionsPending(usize),
     /// Sandbox status updated.
     SandboxUpdated(SandboxStatusUpdate),
+    /// Resource usage of the running sandbox.
+    SandboxResources(SandboxResourcesUpdate),
     /// System message to display in the conversation.
     SystemMessage(String),
+    /// Message sent by the user, shown when replaying a recording.
+    UserMessage(String),
     /// Agent changed (e.g., entering/exiting plan mode).
     AgentChanged(String),
+    /// Project memory contents, opened in the external editor.
+    Memory(String),
+    /// Available session templates (name, description), shown in the picker.
+    Templates(Vec<(String, String)>),
+    /// Projects the server manages, shown in the picker.
+    Projects(Vec<ProjectUpdate>),
+    /// The active project changed.
+    ProjectSwitched {
+        /// Project root.
+        path: String,
+        /// Display name.
+        name: String,
+    },
+    /// A server was found on the local network, or its details changed.
+    ServerFound(DiscoveredServer),
+    /// The server with this name left the local network.
+    ServerLost(String),
+    /// Browsing for servers failed or isn't supported.
+    ServerDiscoveryFailed(String),
+    /// The TUI now shows a headless server; updates with its state follow.
+    ServerConnected {
+        /// Address connected to.
+        address: String,
+        /// Project root on the server.
+        project: String,
+        /// Model, as provider/model.
+        model: String,
+        /// Current agent.
+        agent: String,
+        /// Whether the server serves the project's files.
+        remote_files: bool,
+    },
+    /// Connecting to a server failed; the backend in use is kept.
+    ServerConnectFailed { address: String, error: String },
+    /// The tab shown changed. Tabs past the known ones were just opened.
+    TabSwitched {
+        /// Index of the tab shown.
+        index: usize,
+        /// Number of open tabs.
+        count: usize,
+    },
+    /// A tab that isn't shown was closed.
+    TabClosed { index: usize },
+    /// A tab that isn't shown started or stopped working.
+    TabActivity { index: usize, busy: bool },
+    /// Session search results (id, title, snippet), best match first.
+    SessionSearchResults {
+        /// The search query.
+        query: String,
+        /// Matching sessions.
+        results: Vec<(String, String, String)>,
+    },
+    /// Progress of the agents of an orchestration.
+    Orchestration {
+        /// Orchestration ID.
+        id: String,
+        /// Agents, in task order.
+        agents: Vec<OrchestrationAgentUpdate>,
+    },
+    /// Recent permission decisions, oldest first.
+    PermissionAudit(Vec<PermissionAuditUpdate>),
+    /// Instruction files found for the system prompt, highest priority first.
+    Instructions(Vec<InstructionFileUpdate>),
+    /// A session budget limit is nearly used up.
+    BudgetWarning {
+        /// Budgeted resource ("cost", "tokens" or "tool_calls").
+        limit: String,
+        /// Amount used so far.
+        used: f64,
+        /// Current limit.
+        max: f64,
+        /// Human-readable description.
+        message: String,
+    },
+    /// Spend crossed a configured alert amount.
+    SpendAlert {
+        /// Spending period ("daily", "monthly" or "project_daily").
+        scope: String,
+        /// Amount spent in the period, in USD.
+        spent: f64,
+        /// Alert amount, in USD.
+        alert: f64,
+        /// Human-readable description.
+        message: String,
+    },
+    /// Usage over recent days, for the usage dashboard.
+    Usage {
+        /// Number of days covered.
+        days: u32,
+        /// Spend per day, oldest first.
+        daily: Vec<UsageAmount>,
+        /// Spend per model.
+        models: Vec<UsageAmount>,
+        /// Durations of recent calls per tool.
+        tools: Vec<ToolLatency>,
+    },
     /// Permission request from the runner.
     PermissionRequest(PermissionRequestUpdate),
     /// Session loaded with messages (used when connecting to remote server).
@@ -372,6 +620,87 @@ pub enum AppUpdate {
     GitHistoryUpdated(Vec<GitCommitUpdate>),
     /// Git operation result (success/error).
     GitOperationResult { success: bool, message: String },
+    /// A file's unstaged or staged changes as a diff.
+    GitDiffUpdated {
+        path: String,
+        staged: bool,
+        diff: String,
+    },
+    /// Commit message drafted by the model.
+    GitCommitMessageGenerated(String),
+    /// Entries of a directory of a project on another machine.
+    FilesListed {
+        dir: String,
+        entries: Vec<ListedFile>,
+    },
+    /// Content of a file of a project on another machine, or why it couldn't
+    /// be read.
+    FileRead {
+        path: String,
+        content: Result<String, String>,
+    },
+    /// The session's file changes to review, as a unified diff.
+    Review(String),
+    /// A background process was started.
+    ProcessStarted { pid: u32, command: String },
+    /// Output of a background process.
+    ProcessOutput { pid: u32, text: String },
+    /// A background process exited; no exit code if it was killed.
+    ProcessExited { pid: u32, exit_code: Option<i32> },
+    /// Storage usage and any exceeded soft quotas.
+    StorageUsage {
+        total_bytes: u64,
+        quota_bytes: Option<u64>,
+        warnings: Vec<String>,
+    },
+    /// Custom slash commands provided by the runner.
+    CustomCommands(Vec<CustomCommandUpdate>),
+    /// Configuration was reloaded from disk.
+    ConfigReloaded {
+        /// New theme, if it changed.
+        theme: Option<String>,
+        /// Changed settings that were applied.
+        applied: Vec<String>,
+        /// Changed settings that need a restart.
+        restart_required: Vec<String>,
+    },
+    /// The connection to the server dropped, or came back.
+    Connection(ConnectionStatus),
+    /// Updates were missed while disconnected, so the server's state is
+    /// reloaded; the updates that apply it follow.
+    Resync,
+    /// The clients sharing the session changed, or who drives it.
+    Presence(Vec<ParticipantUpdate>),
+    /// Another client driving the shared session sent a prompt.
+    Prompted { name: String, prompt: String },
+}
+
+/// A client sharing the session.
+#[derive(Debug, Clone)]
+pub struct ParticipantUpdate {
+    pub name: String,
+    /// Whether it sends the prompts; the others watch.
+    pub driver: bool,
+    /// Whether it is this client.
+    pub you: bool,
+}
+
+/// State of the connection to a remote server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// Connected, after having reconnected.
+    Connected,
+    /// The connection dropped; reconnecting, for the given attempt.
+    Reconnecting { attempt: u32 },
+}
+
+/// Custom slash command from the runner.
+#[derive(Debug, Clone)]
+pub struct CustomCommandUpdate {
+    /// Command name (used as /name).
+    pub name: String,
+    /// Human-readable description.
+    pub description: String,
 }
 
 /// Git status update from the runner.
@@ -422,6 +751,85 @@ pub struct SandboxStatusUpdate {
     pub error: Option<String>,
 }
 
+/// Sandbox resource usage update.
+#[derive(Debug, Clone)]
+pub struct SandboxResourcesUpdate {
+    /// CPU usage, 100 per fully used core
+    pub cpu_percent: f64,
+    pub memory_bytes: u64,
+    pub memory_limit: u64,
+    /// Number of running processes
+    pub pids: u32,
+}
+
+/// Orchestrated agent update.
+#[derive(Debug, Clone)]
+pub struct OrchestrationAgentUpdate {
+    /// Agent name.
+    pub name: String,
+    /// The agent's task.
+    pub task: String,
+    /// State: "pending", "running", "done", "failed", "cancelled", "merged" or "conflict".
+    pub state: String,
+    /// Branch of the agent's worktree.
+    pub branch: Option<String>,
+    /// Model calls made.
+    pub steps: usize,
+    /// Tool calls made.
+    pub tool_calls: usize,
+    /// Files changed on the agent's branch.
+    pub changed_files: Vec<String>,
+    /// Final response, or what went wrong.
+    pub summary: Option<String>,
+}
+
+/// Recorded permission decision.
+#[derive(Debug, Clone)]
+pub struct PermissionAuditUpdate {
+    /// When the check happened (unix millis).
+    pub time: i64,
+    /// Tool name.
+    pub tool: String,
+    /// Action being performed.
+    pub action: Option<String>,
+    /// Path involved, for file operations.
+    pub path: Option<String>,
+    /// What the tool was about to do.
+    pub description: String,
+    /// Whether the check was allowed.
+    pub allowed: bool,
+    /// Whether the user was asked.
+    pub prompted: bool,
+    /// How the decision was made: "user", "rule: ..." or "no rule".
+    pub source: String,
+}
+
+/// Instruction file found for the system prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionFileUpdate {
+    /// Path, relative to the project or home directory where possible.
+    pub path: String,
+    /// Estimated tokens.
+    pub tokens: usize,
+    /// Whether the file is in the system prompt.
+    pub included: bool,
+    /// Why the file was truncated or left out.
+    pub note: Option<String>,
+}
+
+/// Project managed by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectUpdate {
+    /// Project root.
+    pub path: String,
+    /// Display name.
+    pub name: String,
+    /// Whether this is the active project.
+    pub active: bool,
+    /// Whether the project's runner has been started.
+    pub loaded: bool,
+}
+
 /// Permission request update from the runner.
 #[derive(Debug, Clone)]
 pub struct PermissionRequestUpdate {
@@ -463,6 +871,36 @@ pub struct McpStatusUpdate {
     pub error: Option<String>,
 }
 
+/// A session tab.
+struct SessionTab {
+    /// Whether the tab's agent is working, while it isn't shown.
+    busy: bool,
+    /// Whether the tab finished while it wasn't shown.
+    unseen: bool,
+    /// What the tab shows, kept while another tab is shown.
+    view: Option<TabView>,
+}
+
+/// The prompt the agent is working on, for the annotation under its response.
+#[derive(Debug, Clone, Copy)]
+struct Turn {
+    started: std::time::Instant,
+    /// Latest usage reported for the prompt.
+    usage: Option<MessageUsage>,
+}
+
+/// The parts of the UI that belong to a tab's session.
+struct TabView {
+    route: Route,
+    state: AppState,
+    messages: MessagesWidget,
+    sidebar: SidebarWidget,
+    session_title: String,
+    status: FooterStatus,
+    tokens: Option<(u32, u32)>,
+    turn: Option<Turn>,
+}
+
 /// The main TUI application.
 pub struct App {
     /// Current state.
@@ -473,6 +911,10 @@ pub struct App {
     dialog: ActiveDialog,
     /// Theme.
     theme: Theme,
+    /// Built-in and user themes.
+    themes: ThemeRegistry,
+    /// Theme to restore when the theme dialog is cancelled.
+    theme_before_preview: Option<Theme>,
     /// Logo widget.
     logo: LogoWidget,
     /// Input widget.
@@ -481,6 +923,8 @@ pub struct App {
     autocomplete: FileAutocomplete,
     /// Slash command autocomplete (for / commands).
     slash_autocomplete: SlashCommandAutocomplete,
+    /// Names of custom commands expanded by the runner.
+    custom_commands: HashSet<String>,
     /// Messages widget.
     messages: MessagesWidget,
     /// Top bar widget.
@@ -497,6 +941,13 @@ pub struct App {
     model_dialog: ModelDialog,
     /// Session dialog.
     session_dialog: Option<SessionDialog>,
+    /// Session template dialog.
+    template_dialog: Option<TemplateDialog>,
+    project_dialog: Option<ProjectDialog>,
+    /// Servers on the local network, while `/connect` browses.
+    connect_dialog: Option<ConnectDialog>,
+    /// Server picked in the connect dialog, waiting for its secret.
+    connecting: Option<DiscoveredServer>,
     /// Theme dialog.
     theme_dialog: ThemeDialog,
     /// Agent dialog.
@@ -517,26 +968,53 @@ pub struct App {
     settings_dialog: Option<SettingsDialog>,
     /// Performance metrics dialog.
     perf_dialog: Option<PerfDialog>,
+    /// Usage dashboard dialog.
+    usage_dialog: Option<UsageDialog>,
     /// Permission request dialog.
     permission_dialog: Option<PermissionDialog>,
     /// Queue of pending permission requests (when dialog is already showing).
     permission_queue: std::collections::VecDeque<PermissionRequestUpdate>,
     /// Git dialog.
     git_dialog: Option<GitDialog>,
+    /// Orchestration coordinator dialog.
+    coordinator_dialog: Option<CoordinatorDialog>,
+    /// Hunk-by-hunk review of the session's changes.
+    review: Option<ReviewView>,
+    /// Whether the review was asked for, rather than opened after a prompt.
+    review_requested: bool,
+    /// Open the review when a prompt finishes with file changes.
+    auto_review: bool,
+    /// Notifies when the agent needs attention while the terminal is unfocused.
+    notifier: Notifier,
     /// Mode indicator.
     mode_indicator: ModeIndicator,
     /// Which-key overlay.
     which_key: WhichKeyOverlay,
+    /// Index of the chord menu opened after the leader, if any.
+    open_chord_menu: Option<usize>,
+    /// Effective keybindings.
+    keybinds: KeybindManager,
     /// Context-sensitive help overlay.
     help_overlay: HelpOverlay,
     /// Onboarding overlay for first-time users.
     onboarding: OnboardingOverlay,
     /// Search widget.
     search: SearchWidget,
+    /// Query to search for once the session picked from search results loads.
+    pending_search: Option<String>,
+    /// ID of the earlier prompt being edited, and where messages were hidden
+    /// before the edit started.
+    editing_prompt: Option<(String, Option<usize>)>,
     /// Whether to show thinking/reasoning blocks.
     show_thinking: bool,
     /// Current session title (for rename).
     session_title: String,
+    /// Open session tabs, in order.
+    tabs: Vec<SessionTab>,
+    /// Index of the tab shown.
+    active_tab: usize,
+    /// Prompt the agent is working on.
+    turn: Option<Turn>,
     /// Event handler.
     events: EventHandler,
     /// Action sender.
@@ -569,10 +1047,35 @@ pub struct App {
     messages_area: Rect,
     /// Cached sidebar area rect for click detection.
     sidebar_area: Rect,
+    /// Side pane showing the edit in view.
+    preview: PreviewPane,
+    /// The tool call the preview pane shows, with its status when it was shown.
+    preview_source: Option<(String, ToolStatus)>,
+    /// Keep what the preview pane shows instead of following the edit in view.
+    preview_pinned: bool,
+    /// Whether the project is on another machine, so its files are listed
+    /// and read through the backend.
+    remote_files: bool,
+    /// File the preview pane asked the backend for.
+    preview_requested: Option<String>,
+    /// Whether this client watches a shared session someone else drives.
+    watching: bool,
+    /// Cached preview pane area rect for click detection.
+    preview_area: Rect,
+    /// Tree of the project files, left of the messages.
+    file_browser: FileBrowser,
+    /// Cached file browser area rect for click detection.
+    file_browser_area: Rect,
+    /// Output of background processes, below the messages.
+    terminal: TerminalPanel,
+    /// Cached terminal panel area rect for click detection.
+    terminal_area: Rect,
     /// Whether the UI needs to be redrawn.
     needs_redraw: bool,
     /// Render settings for performance optimization.
     render_settings: RenderSettings,
+    /// Whether mouse capture is on (`tui.mouse`).
+    mouse_enabled: bool,
 }
 
 impl App {
@@ -594,11 +1097,18 @@ impl App {
             route: Route::Home,
             dialog: ActiveDialog::None,
             theme: Theme::default(),
+            themes: ThemeRegistry::load(theme_dirs(None)),
+            theme_before_preview: None,
             logo: LogoWidget::new(),
             input,
             autocomplete: FileAutocomplete::new(),
             slash_autocomplete: SlashCommandAutocomplete::new(),
-            messages: MessagesWidget::new(),
+            custom_commands: HashSet::new(),
+            messages: {
+                let mut messages = MessagesWidget::new();
+                messages.set_image_protocol(ImageProtocol::detect());
+                messages
+            },
             topbar: TopBarWidget::new(),
             footer: FooterWidget::new(),
             sidebar: SidebarWidget::new(),
@@ -606,6 +1116,10 @@ impl App {
             command_palette: CommandPalette::new(),
             model_dialog: ModelDialog::new(),
             session_dialog: None,
+            template_dialog: None,
+            project_dialog: None,
+            connect_dialog: None,
+            connecting: None,
             theme_dialog: ThemeDialog::new(),
             agent_dialog: None,
             help_dialog: HelpDialog::new(),
@@ -616,16 +1130,33 @@ impl App {
             sandbox_dialog: None,
             settings_dialog: None,
             perf_dialog: None,
+            usage_dialog: None,
             permission_dialog: None,
             permission_queue: std::collections::VecDeque::new(),
             git_dialog: None,
+            coordinator_dialog: None,
+            review: None,
+            review_requested: false,
+            auto_review: false,
+            notifier: Notifier::default(),
             mode_indicator: ModeIndicator::new(),
             which_key: WhichKeyOverlay::new(),
+            open_chord_menu: None,
+            keybinds: KeybindManager::default(),
             help_overlay: HelpOverlay::new(),
             onboarding: OnboardingOverlay::new(),
             search: SearchWidget::new(),
+            pending_search: None,
+            editing_prompt: None,
             show_thinking: true,
             session_title: String::new(),
+            tabs: vec![SessionTab {
+                busy: false,
+                unseen: false,
+                view: None,
+            }],
+            active_tab: 0,
+            turn: None,
             events: EventHandler::new(),
             action_tx,
             action_rx: Some(action_rx),
@@ -644,8 +1175,20 @@ impl App {
             input_area: Rect::default(),
             messages_area: Rect::default(),
             sidebar_area: Rect::default(),
+            preview: PreviewPane::new(),
+            preview_source: None,
+            preview_pinned: false,
+            remote_files: false,
+            watching: false,
+            preview_requested: None,
+            preview_area: Rect::default(),
+            file_browser: FileBrowser::new(),
+            file_browser_area: Rect::default(),
+            terminal: TerminalPanel::new(),
+            terminal_area: Rect::default(),
             needs_redraw: true,
             render_settings: RenderSettings::default(),
+            mouse_enabled: true,
         }
     }
 
@@ -745,6 +1288,22 @@ impl App {
         &self.agent
     }
 
+    /// List and read the project's files through the backend, for a project
+    /// on another machine.
+    pub fn set_remote_files(&mut self, remote: bool) {
+        self.remote_files = remote;
+        self.file_browser.set_remote(remote);
+    }
+
+    /// Connect to a server, which the TUI shows once connected.
+    fn connect_to(&mut self, name: &str, address: String, secret: Option<String>) {
+        self.toasts
+            .push(Toast::info(tr_args("toast.connecting", &[("name", &name)])));
+        let _ = self
+            .action_tx
+            .send(AppAction::ConnectServer { address, secret });
+    }
+
     /// Set the available agents (from the registry).
     pub fn set_available_agents(&mut self, agents: Vec<AgentInfo>) {
         self.available_agents = agents;
@@ -757,13 +1316,72 @@ impl App {
         self.topbar.set_directory(&project);
         self.autocomplete
             .set_cwd(std::path::PathBuf::from(&project));
+        self.file_browser
+            .set_root(std::path::PathBuf::from(&project));
+
+        self.themes = ThemeRegistry::load(theme_dirs(Some(std::path::Path::new(&project))));
+        self.report_theme_errors();
+    }
+
+    /// Look up a theme by name, as its high-contrast variant if that's on.
+    fn load_theme(&self, name: &str) -> Theme {
+        let theme = self.themes.get(name);
+        if self.render_settings.high_contrast {
+            theme.high_contrast()
+        } else {
+            theme
+        }
+    }
+
+    /// Apply the accessibility render settings outside the messages: the
+    /// theme's contrast and the footer's animation.
+    fn apply_accessibility(&mut self) {
+        self.theme = self.load_theme(&self.theme.name);
+        self.footer
+            .set_animated(!self.render_settings.reduce_motion);
     }
 
     /// Set the theme by name.
     pub fn set_theme(&mut self, name: &str) {
-        self.theme = Theme::by_name(name);
-        self.toasts
-            .push(Toast::info(format!("Theme: {}", self.theme.name)));
+        self.theme = self.load_theme(name);
+        self.toasts.push(Toast::info(tr_args(
+            "toast.theme",
+            &[("name", &self.theme.name)],
+        )));
+    }
+
+    /// Read the user theme files again, updating the current theme if it's
+    /// one of them.
+    fn reload_themes(&mut self) {
+        self.themes.reload();
+        if self.themes.is_custom(&self.theme.name) {
+            self.theme = self.load_theme(&self.theme.name);
+        }
+        if self.themes.errors().is_empty() {
+            let count = self.themes.custom().len();
+            self.toasts.push(Toast::success(tr_args(
+                "toast.themes_reloaded",
+                &[("count", &count)],
+            )));
+        } else {
+            self.report_theme_errors();
+        }
+    }
+
+    /// Show the theme files that failed to load.
+    fn report_theme_errors(&mut self) {
+        let errors = self.themes.errors();
+        for error in errors {
+            tracing::warn!("Theme: {error}");
+        }
+        if let Some(first) = errors.first() {
+            let message = match errors.len() {
+                1 => first.clone(),
+                n => format!("{first} (+{} more)", n - 1),
+            };
+            self.toasts
+                .push(Toast::warning(tr("toast.invalid_theme")).with_message(message));
+        }
     }
 
     /// Show the onboarding overlay (for first-time users).
@@ -783,7 +1401,7 @@ impl App {
 
     /// Add a user message.
     pub fn add_user_message(&mut self, text: String) {
-        let mut msg = DisplayMessage::user(text);
+        let mut msg = DisplayMessage::user(text).with_id(Identifier::ascending(IdPrefix::Message));
         msg.agent = self.current_agent();
         self.messages.add_message(msg);
         self.messages.scroll_to_bottom();
@@ -830,6 +1448,224 @@ impl App {
         }
     }
 
+    /// Attach the image on the clipboard to the prompt, saving it to a
+    /// temporary PNG file. Returns false if the clipboard has no image.
+    fn paste_clipboard_image(&mut self) -> bool {
+        let Some(Ok(image)) = self.clipboard.as_mut().map(|c| c.get_image()) else {
+            return false;
+        };
+        let saved = rgba_to_png(
+            image.width as u32,
+            image.height as u32,
+            image.bytes.into_owned(),
+        )
+        .and_then(|png| {
+            let dir = std::env::temp_dir().join("wonopcode");
+            let path = dir.join(format!(
+                "paste-{}.png",
+                chrono::Local::now().format("%Y%m%d_%H%M%S%3f")
+            ));
+            std::fs::create_dir_all(&dir)
+                .and_then(|_| std::fs::write(&path, png))
+                .map_err(|e| e.to_string())?;
+            Ok(path)
+        });
+        match saved {
+            Ok(path) => self.attach_image(&path),
+            Err(e) => {
+                tracing::warn!("Failed to save clipboard image: {}", e);
+                self.toasts.push(Toast::error(tr_args(
+                    "toast.paste_image_failed",
+                    &[("error", &e)],
+                )));
+            }
+        }
+        true
+    }
+
+    /// Attach an image file to the prompt.
+    fn attach_image(&mut self, path: &std::path::Path) {
+        self.input.insert_image(path);
+        self.set_state(AppState::Input);
+        self.input.set_focused(true);
+        self.messages.set_focused(false);
+        self.toasts.push(Toast::info(tr_args(
+            "toast.image_attached",
+            &[("number", &self.input.images().len())],
+        )));
+    }
+
+    /// Send the images attached to a prompt, ahead of the prompt.
+    fn send_images(&mut self, images: &[std::path::PathBuf]) {
+        use base64::Engine;
+
+        for path in images {
+            let prepared = std::fs::read(path)
+                .map_err(|e| e.to_string())
+                .and_then(prepare_attachment);
+            match prepared {
+                Ok((media_type, bytes)) => {
+                    let _ = self.action_tx.send(AppAction::AttachImage {
+                        media_type: media_type.to_string(),
+                        data: base64::engine::general_purpose::STANDARD.encode(bytes),
+                    });
+                }
+                Err(e) => self.toasts.push(Toast::warning(tr_args(
+                    "toast.image_skipped",
+                    &[("path", &path.display()), ("error", &e)],
+                ))),
+            }
+        }
+    }
+
+    /// Take the session view of the tab shown, leaving a new session's.
+    fn take_tab_view(&mut self) -> TabView {
+        let mut sidebar = self.sidebar.clone();
+        sidebar.set_session_title("");
+        sidebar.set_context(ContextInfo {
+            max_tokens: self.sidebar.get_max_tokens(),
+            ..Default::default()
+        });
+        sidebar.set_phases(Vec::new());
+        sidebar.set_todos(Vec::new());
+        sidebar.clear_modified_files();
+        sidebar.scroll_to_top();
+
+        let view = TabView {
+            route: std::mem::take(&mut self.route),
+            state: self.state,
+            messages: std::mem::replace(
+                &mut self.messages,
+                MessagesWidget::with_render_settings(self.render_settings.clone()),
+            ),
+            sidebar: std::mem::replace(&mut self.sidebar, sidebar),
+            session_title: std::mem::take(&mut self.session_title),
+            status: self.footer.status().clone(),
+            tokens: self.footer.tokens(),
+            turn: self.turn.take(),
+        };
+        self.footer.set_status(FooterStatus::Idle);
+        self.footer.clear_tokens();
+        view
+    }
+
+    /// Show the session view of a tab.
+    fn show_tab_view(&mut self, view: TabView) {
+        // Pane visibility is the same in every tab
+        let sidebar_visible = self.sidebar.is_visible();
+        self.route = view.route;
+        self.state = view.state;
+        self.messages = view.messages;
+        self.messages
+            .set_render_settings(self.render_settings.clone());
+        self.sidebar = view.sidebar;
+        self.sidebar.set_visible(sidebar_visible);
+        self.session_title = view.session_title;
+        self.turn = view.turn;
+        self.footer.set_status(view.status);
+        match view.tokens {
+            Some((input, output)) => self.footer.set_tokens(input, output),
+            None => self.footer.clear_tokens(),
+        }
+    }
+
+    /// Show another tab, once the runner routes its updates here.
+    ///
+    /// Tabs past the known ones were just opened and start with a new session.
+    fn switch_tab(&mut self, index: usize, count: usize) {
+        while self.tabs.len() < count {
+            self.tabs.push(SessionTab {
+                busy: false,
+                unseen: false,
+                view: None,
+            });
+        }
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
+        }
+
+        let view = self.take_tab_view();
+        self.tabs[self.active_tab].view = Some(view);
+        self.active_tab = index;
+        let tab = &mut self.tabs[index];
+        tab.busy = false;
+        tab.unseen = false;
+        match tab.view.take() {
+            Some(view) => self.show_tab_view(view),
+            None => self.state = AppState::Input,
+        }
+
+        self.clear_search();
+        self.editing_prompt = None;
+        self.unpin_preview();
+        let state = if self.state == AppState::Waiting {
+            AppState::Waiting
+        } else {
+            AppState::Input
+        };
+        self.set_state(state);
+        self.messages.set_focused(false);
+        self.input.set_focused(true);
+    }
+
+    /// Ask the runner to show the tab at an index, wrapping around.
+    fn request_tab(&mut self, index: isize) {
+        let count = self.tabs.len() as isize;
+        if count < 2 {
+            self.toasts.push(Toast::info(tr("toast.one_tab")));
+            return;
+        }
+        let index = index.rem_euclid(count) as usize;
+        if index != self.active_tab {
+            let _ = self.action_tx.send(AppAction::SwitchTab(index));
+        }
+    }
+
+    /// Handle the keys that open, close and switch tabs. Alt+1 to Alt+9
+    /// show a tab by number.
+    fn handle_tab_key(&mut self, key: &crossterm::event::KeyEvent) -> bool {
+        if key.modifiers == KeyModifiers::ALT {
+            if let KeyCode::Char(digit @ '1'..='9') = key.code {
+                let index = digit as usize - '1' as usize;
+                if index < self.tabs.len() {
+                    self.request_tab(index as isize);
+                    return true;
+                }
+                return false;
+            }
+        }
+        match self.keybinds.find_action(key) {
+            Some(
+                action @ (KeyAction::TabNew
+                | KeyAction::TabClose
+                | KeyAction::TabNext
+                | KeyAction::TabPrev),
+            ) => self.run_key_action(action),
+            _ => false,
+        }
+    }
+
+    /// Labels of the open tabs, for the tab bar.
+    fn tab_labels(&self) -> Vec<TabLabel> {
+        self.tabs
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| match &tab.view {
+                Some(view) => TabLabel {
+                    title: tab_title(&view.session_title, &view.messages),
+                    busy: tab.busy,
+                    unseen: tab.unseen,
+                },
+                None if i == self.active_tab => TabLabel {
+                    title: tab_title(&self.session_title, &self.messages),
+                    busy: self.footer.is_busy(),
+                    unseen: false,
+                },
+                None => TabLabel::default(),
+            })
+            .collect()
+    }
+
     /// Set application state and update mode indicators.
     fn set_state(&mut self, state: AppState) {
         use crate::widgets::footer::FooterMode;
@@ -862,6 +1698,7 @@ impl App {
         // Hide which-key when leaving leader mode
         if state != AppState::Leader {
             self.which_key.hide();
+            self.open_chord_menu = None;
         }
 
         // Deactivate search when leaving search mode
@@ -870,11 +1707,42 @@ impl App {
         }
     }
 
+    /// Search the session's messages for a query, jumping to the first match.
+    fn start_search(&mut self, query: &str) {
+        self.search.activate_with(query);
+        self.set_state(AppState::Searching);
+        self.input.set_focused(false);
+        self.messages.set_focused(true);
+        self.perform_search();
+    }
+
+    /// Scroll to the current search match and highlight the matches.
+    fn jump_to_match(&mut self) {
+        let current = self
+            .search
+            .current_match()
+            .map(|m| (m.message_index, m.tool_index));
+        match current {
+            Some((message, Some(tool))) => self.messages.scroll_to_tool(message, tool),
+            Some((message, None)) => self.messages.scroll_to_message(message),
+            None => {}
+        }
+        self.messages
+            .set_search_highlight(Some(self.search.query()), current.map(|(m, _)| m));
+    }
+
+    /// Forget the search and its highlighted matches.
+    fn clear_search(&mut self) {
+        self.search.clear();
+        self.messages.set_search_highlight(None, None);
+    }
+
     /// Perform search across all messages.
     fn perform_search(&mut self) {
         let query = self.search.query().to_string();
         if query.is_empty() {
             self.search.set_matches(vec![]);
+            self.messages.set_search_highlight(None, None);
             return;
         }
 
@@ -924,6 +1792,7 @@ impl App {
         }
 
         self.search.set_matches(matches);
+        self.jump_to_match();
     }
 
     /// Copy the last assistant response to clipboard.
@@ -931,13 +1800,12 @@ impl App {
         if let Some(content) = self.messages.get_last_assistant_content() {
             let content = content.to_string();
             if self.copy_to_clipboard(&content) {
-                self.toasts.push(Toast::success("Copied to clipboard"));
+                self.toasts.push(Toast::success(tr("toast.copied")));
             } else {
-                self.toasts
-                    .push(Toast::error("Failed to copy to clipboard"));
+                self.toasts.push(Toast::error(tr("toast.copy_failed")));
             }
         } else {
-            self.toasts.push(Toast::warning("No response to copy"));
+            self.toasts.push(Toast::warning(tr("toast.no_response")));
         }
     }
 
@@ -968,6 +1836,24 @@ impl App {
         self.status_dialog.permissions_pending = self.footer.get_permissions_pending();
     }
 
+    /// Show the status dialog.
+    fn show_status_dialog(&mut self) {
+        self.update_status_dialog();
+        let _ = self.action_tx.send(AppAction::LoadPermissionAudit);
+        self.dialog = ActiveDialog::Status;
+    }
+
+    /// Turn mouse capture on or off.
+    fn set_mouse_enabled(&mut self, enabled: bool) {
+        if self.mouse_enabled == enabled {
+            return;
+        }
+        self.mouse_enabled = enabled;
+        if let Err(e) = set_mouse_capture(enabled) {
+            tracing::warn!(error = %e, "Failed to change mouse capture");
+        }
+    }
+
     /// Show the MCP servers dialog.
     fn show_mcp_dialog(&mut self) {
         // Get MCP server info from sidebar and convert to dialog format
@@ -1019,15 +1905,16 @@ impl App {
         match action {
             SandboxAction::Start => {
                 let _ = self.action_tx.send(AppAction::SandboxStart);
-                self.toasts.push(Toast::info("Starting sandbox..."));
+                self.toasts.push(Toast::info(tr("toast.sandbox_starting")));
             }
             SandboxAction::Stop => {
                 let _ = self.action_tx.send(AppAction::SandboxStop);
-                self.toasts.push(Toast::info("Stopping sandbox..."));
+                self.toasts.push(Toast::info(tr("toast.sandbox_stopping")));
             }
             SandboxAction::Restart => {
                 let _ = self.action_tx.send(AppAction::SandboxRestart);
-                self.toasts.push(Toast::info("Restarting sandbox..."));
+                self.toasts
+                    .push(Toast::info(tr("toast.sandbox_restarting")));
             }
             SandboxAction::Status => {
                 // Just close the dialog, status is shown in the dialog itself
@@ -1109,8 +1996,31 @@ impl App {
             GitDialogResult::Checkout(paths) => {
                 let _ = self.action_tx.send(AppAction::GitCheckout { paths });
             }
+            GitDialogResult::Diff { path, staged } => {
+                let _ = self.action_tx.send(AppAction::GitDiff { path, staged });
+            }
+            GitDialogResult::StageHunk { path, hunk } => {
+                let _ = self.action_tx.send(AppAction::GitStageHunk { path, hunk });
+            }
+            GitDialogResult::UnstageHunk { path, hunk } => {
+                let _ = self
+                    .action_tx
+                    .send(AppAction::GitUnstageHunk { path, hunk });
+            }
+            GitDialogResult::GenerateMessage => {
+                let _ = self.action_tx.send(AppAction::GitGenerateCommitMessage);
+            }
             GitDialogResult::Commit(message) => {
-                let _ = self.action_tx.send(AppAction::GitCommit { message });
+                let _ = self.action_tx.send(AppAction::GitCommit {
+                    message,
+                    push: false,
+                });
+            }
+            GitDialogResult::CommitAndPush(message) => {
+                let _ = self.action_tx.send(AppAction::GitCommit {
+                    message,
+                    push: true,
+                });
             }
             GitDialogResult::Push => {
                 let _ = self.action_tx.send(AppAction::GitPush);
@@ -1153,9 +2063,48 @@ impl App {
     ///
     /// This should be called after creating the App to restore saved render settings.
     pub fn apply_config(&mut self, config: &wonopcode_core::config::Config) {
+        let code = config.tui.as_ref().and_then(|tui| tui.locale.as_deref());
+        set_locale(match code.map(|code| (code, Locale::from_code(code))) {
+            Some((_, Some(locale))) => locale,
+            Some((code, None)) => {
+                tracing::warn!(locale = code, "Unknown locale, using the environment's");
+                Locale::from_env()
+            }
+            None => Locale::from_env(),
+        });
         if let Some(tui_config) = &config.tui {
-            // Build render settings from config
-            let mut settings = RenderSettings::default();
+            // Takes effect when the terminal is set up
+            if let Some(mouse) = tui_config.mouse {
+                self.mouse_enabled = mouse;
+            }
+            self.auto_review = tui_config.auto_review.unwrap_or(false);
+            if let Some(mode) = tui_config.notifications {
+                use wonopcode_core::config::NotificationMode;
+                self.notifier.set_method(match mode {
+                    NotificationMode::Off => NotifyMethod::Off,
+                    NotificationMode::Bell => NotifyMethod::Bell,
+                    NotificationMode::Osc777 => NotifyMethod::Osc777,
+                    NotificationMode::Desktop => NotifyMethod::Desktop,
+                });
+            }
+            if let Some(mode) = tui_config.images {
+                use wonopcode_core::config::ImageMode;
+                self.messages.set_image_protocol(match mode {
+                    ImageMode::Auto => ImageProtocol::detect(),
+                    ImageMode::Kitty => ImageProtocol::Kitty,
+                    ImageMode::Sixel => ImageProtocol::Sixel,
+                    ImageMode::Iterm2 => ImageProtocol::Iterm2,
+                    ImageMode::Off => ImageProtocol::None,
+                });
+            }
+
+            // Build render settings from config, starting from the screen
+            // reader preset if that's on
+            let mut settings = if tui_config.screen_reader == Some(true) {
+                RenderSettings::screen_reader()
+            } else {
+                RenderSettings::default()
+            };
 
             if let Some(v) = tui_config.markdown {
                 settings.markdown_enabled = v;
@@ -1169,6 +2118,9 @@ impl App {
             if let Some(v) = tui_config.tables {
                 settings.tables_enabled = v;
             }
+            if let Some(v) = tui_config.message_usage {
+                settings.message_usage = v;
+            }
             if let Some(fps) = tui_config.streaming_fps {
                 settings.streaming_fps = fps;
             }
@@ -1181,6 +2133,15 @@ impl App {
                 // The user's explicit settings take precedence. The low_memory preset
                 // is only applied when the user toggles low_memory_mode ON in the UI.
             }
+            if let Some(v) = tui_config.high_contrast {
+                settings.high_contrast = v;
+            }
+            if let Some(v) = tui_config.reduce_motion {
+                settings.reduce_motion = v;
+            }
+            if let Some(v) = tui_config.ascii_borders {
+                settings.ascii_borders = v;
+            }
             if let Some(v) = tui_config.enable_test_commands {
                 settings.enable_test_commands = v;
             }
@@ -1205,6 +2166,7 @@ impl App {
             // Apply settings
             self.render_settings = settings.clone();
             self.messages.set_render_settings(settings.clone());
+            self.apply_accessibility();
 
             // Sync test commands setting to slash autocomplete
             self.slash_autocomplete
@@ -1223,6 +2185,47 @@ impl App {
         if let Some(ref theme_name) = config.theme {
             self.set_theme(theme_name);
         }
+
+        if let Some(keybinds) = &config.keybinds {
+            self.set_keybinds(KeybindManager::new(&KeybindConfig {
+                leader: keybinds
+                    .leader
+                    .clone()
+                    .unwrap_or_else(|| KeybindConfig::default().leader),
+                menus: keybinds
+                    .menus
+                    .iter()
+                    .map(|(key, menu)| {
+                        let menu = ChordMenuConfig {
+                            title: menu.title.clone(),
+                            keys: menu.keys.clone(),
+                        };
+                        (key.clone(), menu)
+                    })
+                    .collect(),
+                bindings: keybinds.bindings(),
+            }));
+        }
+    }
+
+    /// Use the given keybindings, reporting any problems with them.
+    fn set_keybinds(&mut self, keybinds: KeybindManager) {
+        let warnings = keybinds.warnings();
+        for warning in warnings {
+            tracing::warn!("Keybinds: {warning}");
+        }
+        if let Some(first) = warnings.first() {
+            let message = match warnings.len() {
+                1 => first.clone(),
+                n => format!("{first} (+{} more)", n - 1),
+            };
+            self.toasts
+                .push(Toast::warning(tr("toast.invalid_keybinds")).with_message(message));
+        }
+
+        self.which_key.set_keybinds(&keybinds);
+        self.help_dialog = HelpDialog::with_keybinds(keybinds.clone());
+        self.keybinds = keybinds;
     }
 
     /// Handle a settings dialog result.
@@ -1246,6 +2249,10 @@ impl App {
                         self.set_theme(theme_name);
                     }
 
+                    if let Some(mouse) = config.tui.as_ref().and_then(|t| t.mouse) {
+                        self.set_mouse_enabled(mouse);
+                    }
+
                     // Apply render settings immediately
                     if let Some(new_render_settings) = new_render_settings {
                         let settings_changed = self.render_settings.markdown_enabled
@@ -1255,9 +2262,14 @@ impl App {
                             || self.render_settings.code_backgrounds_enabled
                                 != new_render_settings.code_backgrounds_enabled
                             || self.render_settings.tables_enabled
-                                != new_render_settings.tables_enabled;
+                                != new_render_settings.tables_enabled
+                            || self.render_settings.linear_output
+                                != new_render_settings.linear_output
+                            || self.render_settings.message_usage
+                                != new_render_settings.message_usage;
 
                         self.render_settings = new_render_settings.clone();
+                        self.apply_accessibility();
 
                         // Always update messages widget with settings
                         self.messages
@@ -1278,7 +2290,8 @@ impl App {
                         // Invalidate cache if visual settings changed
                         if settings_changed {
                             self.messages.invalidate_cache();
-                            self.toasts.push(Toast::info("Render settings updated"));
+                            self.toasts
+                                .push(Toast::info(tr("toast.render_settings_updated")));
                         }
                     }
 
@@ -1292,8 +2305,9 @@ impl App {
                         SaveScope::Project => "project",
                         SaveScope::Global => "global",
                     };
-                    self.toasts.push(Toast::success(format!(
-                        "Settings saved to {scope_name} config"
+                    self.toasts.push(Toast::success(tr_args(
+                        "toast.settings_saved",
+                        &[("scope", &scope_name)],
                     )));
                 }
                 self.dialog = ActiveDialog::None;
@@ -1309,7 +2323,7 @@ impl App {
                     if let Some(theme_name) = dialog.get_theme() {
                         // Live preview theme changes
                         if self.theme.name != theme_name {
-                            self.theme = Theme::by_name(&theme_name);
+                            self.theme = self.load_theme(&theme_name);
                         }
                     }
                 }
@@ -1433,9 +2447,8 @@ async function fetchUserData(userId) {
             }
         }
 
-        self.toasts.push(Toast::info(
-            "Created 100 test messages for performance testing",
-        ));
+        self.toasts
+            .push(Toast::info(tr("toast.test_messages_created")));
         tracing::info!("Created 100 test messages for performance testing");
     }
 
@@ -1479,14 +2492,12 @@ async function fetchUserData(userId) {
             );
         } else {
             self.toasts
-                .push(Toast::warning("Performance metrics not available"));
+                .push(Toast::warning(tr("toast.metrics_unavailable")));
         }
     }
 
     /// Show the message timeline dialog.
     fn show_timeline_dialog(&mut self) {
-        use crate::widgets::messages::MessageRole;
-
         // Build timeline items from messages
         let items: Vec<TimelineItem> = self
             .messages
@@ -1523,7 +2534,7 @@ async function fetchUserData(userId) {
             .collect();
 
         if items.is_empty() {
-            self.toasts.push(Toast::warning("No messages in timeline"));
+            self.toasts.push(Toast::warning(tr("toast.timeline_empty")));
             return;
         }
 
@@ -1531,44 +2542,108 @@ async function fetchUserData(userId) {
         self.dialog = ActiveDialog::Timeline;
     }
 
-    /// Export the current session to a file.
-    fn export_session(&mut self) {
-        // Build export content
-        let mut content = String::new();
-        content.push_str(&format!(
-            "# Session: {}\n\n",
-            if self.session_title.is_empty() {
-                "Untitled"
-            } else {
-                &self.session_title
+    /// Ask for the path to export the current session to.
+    fn show_export_dialog(&mut self) {
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        self.input_dialog = Some(
+            InputDialog::new(
+                tr("dialog.export_session"),
+                "Path (.md or .json), --no-tools to leave out tool output:",
+            )
+            .with_value(format!("wonopcode_export_{timestamp}.md")),
+        );
+        self.dialog = ActiveDialog::Export;
+    }
+
+    /// Export the visible conversation to a file.
+    ///
+    /// Takes the arguments of `/export [path] [json|markdown] [--no-tools]`.
+    /// The format follows the file extension unless given, and relative paths
+    /// are relative to the project directory.
+    fn export_session(&mut self, args: &str) {
+        let mut path = None;
+        let mut json = None;
+        let mut include_tool_output = true;
+        for arg in args.split_whitespace() {
+            match arg {
+                "json" | "--json" => json = Some(true),
+                "md" | "markdown" | "--md" | "--markdown" => json = Some(false),
+                "--no-tools" => include_tool_output = false,
+                _ if path.is_none() && !arg.starts_with("--") => path = Some(arg),
+                _ => {
+                    self.toasts.push(Toast::warning(tr("toast.usage_export")));
+                    return;
+                }
             }
-        ));
-        content.push_str(&format!("Directory: {}\n", self.directory));
-        content.push_str(&format!("Model: {}\n", self.model));
-        content.push_str(&format!("Agent: {}\n\n", self.agent));
-        content.push_str("---\n\n");
-
-        // Get messages from the widget
-        if let Some(transcript) = self.messages.get_transcript() {
-            content.push_str(&transcript);
-        } else {
-            content.push_str("(No messages)\n");
         }
+        let json =
+            json.unwrap_or_else(|| path.is_some_and(|p| p.to_lowercase().ends_with(".json")));
 
         // Determine export path
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let filename = format!("wonopcode_export_{timestamp}.md");
-        let export_path = std::path::PathBuf::from(&self.directory).join(&filename);
+        let export_path = match path {
+            Some(path) => match path.strip_prefix("~/").zip(dirs::home_dir()) {
+                Some((rest, home)) => home.join(rest),
+                None => std::path::PathBuf::from(&self.directory).join(path),
+            },
+            None => {
+                let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+                let extension = if json { "json" } else { "md" };
+                std::path::PathBuf::from(&self.directory)
+                    .join(format!("wonopcode_export_{timestamp}.{extension}"))
+            }
+        };
+
+        let title = if self.session_title.is_empty() {
+            "Untitled"
+        } else {
+            &self.session_title
+        };
+        let content = if json {
+            let export = serde_json::json!({
+                "version": env!("CARGO_PKG_VERSION"),
+                "exported_at": chrono::Utc::now().to_rfc3339(),
+                "session": {
+                    "title": title,
+                    "directory": self.directory,
+                    "model": self.model,
+                    "agent": self.agent,
+                },
+                "messages": self.messages.get_transcript_json(include_tool_output),
+            });
+            serde_json::to_string_pretty(&export).unwrap_or_default()
+        } else {
+            let mut content = String::new();
+            content.push_str(&format!("# Session: {title}\n\n"));
+            content.push_str(&format!("Directory: {}\n", self.directory));
+            content.push_str(&format!("Model: {}\n", self.model));
+            content.push_str(&format!("Agent: {}\n\n", self.agent));
+            content.push_str("---\n\n");
+
+            // Get messages from the widget
+            if let Some(transcript) = self.messages.get_transcript(include_tool_output) {
+                content.push_str(&transcript);
+            } else {
+                content.push_str("(No messages)\n");
+            }
+            content
+        };
 
         // Write to file
         match std::fs::write(&export_path, &content) {
             Ok(_) => {
-                self.toasts
-                    .push(Toast::success(format!("Exported to: {filename}")));
+                let shown = export_path
+                    .strip_prefix(&self.directory)
+                    .unwrap_or(&export_path);
+                self.toasts.push(Toast::success(tr_args(
+                    "toast.exported",
+                    &[("path", &shown.display())],
+                )));
             }
             Err(e) => {
-                self.toasts
-                    .push(Toast::error(format!("Export failed: {e}")));
+                self.toasts.push(Toast::error(tr_args(
+                    "toast.export_failed",
+                    &[("error", &e)],
+                )));
             }
         }
     }
@@ -1582,7 +2657,8 @@ async function fetchUserData(userId) {
 
     /// Open content in external editor and return the edited content.
     /// Returns None if editor is not available or editing was cancelled.
-    fn open_in_editor(content: &str) -> Option<String> {
+    /// Mouse capture is turned back on afterwards if `mouse` is set.
+    fn open_in_editor(content: &str, mouse: bool) -> Option<String> {
         let editor = Self::get_editor()?;
 
         // Create a temporary file
@@ -1618,7 +2694,10 @@ async function fetchUserData(userId) {
 
         // Resume terminal (enter raw mode again)
         let _ = enable_raw_mode();
-        let _ = execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture);
+        let _ = execute!(io::stdout(), EnterAlternateScreen);
+        if mouse {
+            let _ = set_mouse_capture(true);
+        }
 
         match result {
             Ok(status) if status.success() => {
@@ -1660,31 +2739,23 @@ async function fetchUserData(userId) {
             self.slash_autocomplete.hide();
         }
 
-        // Find the last '@' that could be a trigger (for file mentions)
-        if let Some(at_pos) = text.rfind('@') {
-            // Check if '@' is at start or after whitespace
-            let before_at = if at_pos == 0 {
-                None
-            } else {
-                text.chars().nth(at_pos - 1)
-            };
-            let is_valid_trigger =
-                before_at.is_none() || before_at == Some(' ') || before_at == Some('\n');
-
-            if is_valid_trigger {
-                // Get the filter (text after '@')
-                let filter = &text[at_pos + 1..];
-
-                // Don't trigger if there's a space in the filter (completed already)
-                if !filter.contains(' ') {
-                    if self.autocomplete.is_visible() {
-                        self.autocomplete.set_filter(filter);
-                    } else {
-                        self.autocomplete.show(at_pos, filter);
-                    }
+        // An '@' mention typed before the cursor (for files and symbols)
+        if let Some((at_pos, filter)) = self.input.mention_before_cursor() {
+            if self.autocomplete.is_visible() {
+                if filter == self.autocomplete.filter() {
                     return;
                 }
+                self.autocomplete.set_filter(&filter);
+            } else {
+                self.autocomplete.show(at_pos, &filter);
+            }
+            // Paths don't name symbols
+            if filter.chars().count() >= MIN_SYMBOL_QUERY_CHARS && !filter.contains(['/', '.']) {
+                let _ = self
+                    .action_tx
+                    .send(AppAction::SearchSymbols { query: filter });
             }
+            return;
         }
 
         // No valid trigger found, hide file autocomplete
@@ -1693,54 +2764,108 @@ async function fetchUserData(userId) {
         }
     }
 
-    /// Apply an autocomplete selection.
+    /// Apply an autocomplete selection. A file is attached to the prompt,
+    /// while a directory stays a path to complete further.
     fn apply_autocomplete(&mut self, path: &str) {
-        let trigger_pos = self.autocomplete.trigger_pos();
-        let current = self.input.content();
-
-        // Build new content: text before '@' + '@path ' + text after filter
-        let before = &current[..trigger_pos];
+        if path.ends_with('/') {
+            self.input.replace_mention(&format!("@{path}"));
+        } else {
+            self.input.attach_mention(&ContextAttachment {
+                path: path.to_string(),
+                symbol: None,
+                lines: None,
+            });
+        }
+    }
 
-        // Find where the filter ends (next space or end of string)
-        let after_at = &current[trigger_pos + 1..];
-        let filter_end = after_at
-            .find(' ')
-            .map(|i| trigger_pos + 1 + i)
-            .unwrap_or(current.len());
-        let after = &current[filter_end..];
+    /// Attach the lines of a workspace symbol to the prompt.
+    fn apply_symbol_autocomplete(&mut self, symbol: SymbolSuggestion) {
+        self.input.attach_mention(&ContextAttachment {
+            path: symbol.path,
+            symbol: Some(symbol.name),
+            lines: Some((symbol.start_line, symbol.end_line)),
+        });
+    }
 
-        let new_content = format!("{}@{} {}", before, path, after.trim_start());
-        self.input.set_content(new_content);
+    /// Send the files and symbols attached to a prompt, ahead of the prompt.
+    fn send_contexts(&self, contexts: Vec<ContextAttachment>) {
+        for context in contexts {
+            let _ = self.action_tx.send(AppAction::AttachContext {
+                path: context.path,
+                symbol: context.symbol,
+                lines: context.lines,
+            });
+        }
     }
 
     /// Undo the last message exchange.
     fn undo_message(&mut self) {
         if self.state == AppState::Waiting {
-            self.toasts
-                .push(Toast::warning("Cannot undo while waiting for response"));
+            self.toasts.push(Toast::warning(tr("toast.undo_busy")));
             return;
         }
 
         if let Some(user_content) = self.messages.undo() {
             // Restore user message to input
             self.input.set_content(user_content);
-            self.toasts
-                .push(Toast::info("Message undone - edit and resend"));
+            self.toasts.push(Toast::info(tr("toast.undone")));
             let _ = self.action_tx.send(AppAction::Undo);
         } else {
-            self.toasts.push(Toast::warning("Nothing to undo"));
+            self.toasts
+                .push(Toast::warning(tr("toast.nothing_to_undo")));
         }
     }
 
-    /// Redo an undone message.
-    fn redo_message(&mut self) {
-        if self.messages.redo() {
-            // Clear input since we're restoring the message
+    /// Edit the selected prompt: hide it and what followed, and put it in
+    /// the input to resubmit.
+    fn edit_selected_prompt(&mut self) {
+        if self.footer.is_busy() {
+            self.toasts.push(Toast::warning(tr("toast.edit_busy")));
+            return;
+        }
+        let Some((index, msg)) = self.messages.selected_message() else {
+            return;
+        };
+        if msg.role != MessageRole::User {
+            self.toasts
+                .push(Toast::warning(tr("toast.edit_select_prompt")));
+            return;
+        }
+        let Some(id) = msg.id.clone() else {
+            self.toasts
+                .push(Toast::warning(tr("toast.edit_not_resubmittable")));
+            return;
+        };
+        let content = msg.content.clone();
+
+        self.editing_prompt = Some((id, self.messages.revert_point()));
+        self.messages.set_revert_point(Some(index));
+        self.input.set_content(content);
+        self.set_state(AppState::Input);
+        self.input.set_focused(true);
+        self.messages.set_focused(false);
+        self.toasts.push(Toast::info(tr("toast.editing_prompt")));
+    }
+
+    /// Stop editing an earlier prompt and show the conversation again.
+    fn cancel_prompt_edit(&mut self) {
+        if let Some((_, hidden)) = self.editing_prompt.take() {
+            self.messages.set_revert_point(hidden);
+            self.input.clear();
+            self.toasts.push(Toast::info(tr("toast.edit_cancelled")));
+        }
+    }
+
+    /// Redo an undone message.
+    fn redo_message(&mut self) {
+        if self.messages.redo() {
+            // Clear input since we're restoring the message
             self.input.clear();
-            self.toasts.push(Toast::info("Message restored"));
+            self.toasts.push(Toast::info(tr("toast.restored")));
             let _ = self.action_tx.send(AppAction::Redo);
         } else {
-            self.toasts.push(Toast::warning("Nothing to redo"));
+            self.toasts
+                .push(Toast::warning(tr("toast.nothing_to_redo")));
         }
     }
 
@@ -1748,18 +2873,18 @@ async function fetchUserData(userId) {
     fn edit_input_in_editor(&mut self) {
         let editor = Self::get_editor();
         if editor.is_none() {
-            self.toasts
-                .push(Toast::warning("No $EDITOR or $VISUAL set"));
+            self.toasts.push(Toast::warning(tr("toast.no_editor")));
             return;
         }
 
         let current_content = self.input.content();
 
-        if let Some(edited) = Self::open_in_editor(&current_content) {
+        if let Some(edited) = Self::open_in_editor(&current_content, self.mouse_enabled) {
             let trimmed = edited.trim();
             if !trimmed.is_empty() && trimmed != current_content.trim() {
                 self.input.set_content(trimmed.to_string());
-                self.toasts.push(Toast::success("Updated from editor"));
+                self.toasts
+                    .push(Toast::success(tr("toast.updated_from_editor")));
             }
         }
     }
@@ -1784,7 +2909,7 @@ async function fetchUserData(userId) {
 
         // Setup terminal with RAII guard - terminal will be restored when _guard is dropped,
         // regardless of how this function exits (normal return, early return via ?, or panic)
-        let _guard = TerminalGuard::new()?;
+        let _guard = TerminalGuard::with_mouse(self.mouse_enabled)?;
         let backend = CrosstermBackend::new(io::stdout());
         let mut terminal = Terminal::new(backend)?;
 
@@ -1797,7 +2922,7 @@ async function fetchUserData(userId) {
         // Main loop
         while self.state != AppState::Quit {
             // Only draw if needed (dirty flag set or animations active)
-            let should_draw = self.needs_redraw || self.footer.is_busy();
+            let should_draw = self.needs_redraw || self.footer.is_animating();
 
             if should_draw {
                 // Draw with timing
@@ -1805,6 +2930,12 @@ async function fetchUserData(userId) {
                     let _frame_timer = metrics::frame_timer();
                     terminal.draw(|frame| self.draw(frame))?;
                 }
+                // Remove images that are no longer shown
+                let cleanup = self.messages.finish_image_frame();
+                if !cleanup.is_empty() {
+                    terminal.backend_mut().write_all(cleanup.as_bytes())?;
+                    terminal.backend_mut().flush()?;
+                }
                 self.needs_redraw = false;
             }
 
@@ -1874,9 +3005,19 @@ async function fetchUserData(userId) {
     fn draw(&mut self, frame: &mut Frame) {
         let area = frame.area();
 
+        // Tab bar, once a second tab is open
+        let mut route_area = area;
+        if self.tabs.len() > 1 && area.height > 1 {
+            self.topbar.set_tabs(self.tab_labels(), self.active_tab);
+            self.topbar
+                .render(frame, Rect { height: 1, ..area }, &self.theme);
+            route_area.y += 1;
+            route_area.height -= 1;
+        }
+
         match self.route {
-            Route::Home => self.draw_home(frame, area),
-            Route::Session => self.draw_session(frame, area),
+            Route::Home => self.draw_home(frame, route_area),
+            Route::Session => self.draw_session(frame, route_area),
         }
 
         // Draw toasts on top
@@ -1889,6 +3030,18 @@ async function fetchUserData(userId) {
         if self.onboarding.is_visible() {
             self.onboarding.render(frame, area, &self.theme);
         }
+
+        if self.render_settings.ascii_borders {
+            asciify(frame.buffer_mut());
+        }
+
+        // Screen readers follow the terminal's cursor, so show it at the
+        // input while no dialog is open
+        if self.render_settings.linear_output && matches!(self.dialog, ActiveDialog::None) {
+            if let Some(position) = self.input.cursor_position() {
+                frame.set_cursor_position(position);
+            }
+        }
     }
 
     /// Draw the home screen.
@@ -1913,8 +3066,10 @@ async function fetchUserData(userId) {
         // Center the content horizontally
         let center_input = centered_horizontal(content_width, chunks[3]);
 
-        // Logo
-        self.logo.render(frame, chunks[1], &self.theme);
+        // Logo, left out of linear output where it's noise to a screen reader
+        if !self.render_settings.linear_output {
+            self.logo.render(frame, chunks[1], &self.theme);
+        }
 
         // Input
         self.input.render(frame, center_input, &self.theme);
@@ -1942,25 +3097,37 @@ async function fetchUserData(userId) {
 
     /// Draw the session view.
     fn draw_session(&mut self, frame: &mut Frame, area: Rect) {
-        // Main layout with optional sidebar (with margin between main and sidebar)
-        let main_chunks = if self.sidebar.is_visible() {
-            Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([
-                    Constraint::Min(40),
-                    Constraint::Length(1), // Margin/separator
-                    Constraint::Length(self.sidebar.width()),
-                ])
-                .split(area)
-        } else {
-            Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(100)])
-                .split(area)
-        };
+        // Linear output shows the conversation alone, so a screen reader
+        // reads it top to bottom without side panels in between
+        let linear = self.render_settings.linear_output;
+        let show_file_browser = self.file_browser.is_visible() && !linear;
+        let show_preview = self.preview.is_visible() && !linear;
+        let show_sidebar = self.sidebar.is_visible() && !linear;
+
+        // Main layout with optional file browser, preview pane and sidebar
+        // (with a margin between each)
+        let mut constraints = Vec::new();
+        if show_file_browser {
+            constraints.push(Constraint::Length(self.file_browser.width()));
+            constraints.push(Constraint::Length(1)); // Margin/separator
+        }
+        constraints.push(Constraint::Min(40));
+        if show_preview {
+            constraints.push(Constraint::Length(1)); // Margin/separator
+            constraints.push(Constraint::Percentage(40));
+        }
+        if show_sidebar {
+            constraints.push(Constraint::Length(1)); // Margin/separator
+            constraints.push(Constraint::Length(self.sidebar.width()));
+        }
+        let main_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints)
+            .split(area);
 
-        // Main content area
-        let main_area = main_chunks[0];
+        // Main content area, after the file browser and its margin
+        let main_index = if show_file_browser { 2 } else { 0 };
+        let main_area = main_chunks[main_index];
 
         // Calculate input height based on content
         let input_height = self.input.height().min(10);
@@ -1968,51 +3135,74 @@ async function fetchUserData(userId) {
         // Calculate search bar height
         let search_height = self.search.height();
 
-        // Vertical layout: top padding, messages, search (optional), input, footer
+        // Calculate terminal panel height
+        let terminal_height = if self.terminal.is_visible() {
+            self.terminal.height()
+        } else {
+            0
+        };
+
+        // Vertical layout: top padding, messages, terminal (optional), search (optional),
+        // input, footer
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(2),             // Top padding
-                Constraint::Min(5),                // Messages
-                Constraint::Length(search_height), // Search bar (0 when inactive)
-                Constraint::Length(input_height),  // Input
-                Constraint::Length(1),             // Footer
+                Constraint::Length(if linear { 0 } else { 2 }), // Top padding
+                Constraint::Min(5),                             // Messages
+                Constraint::Length(terminal_height),            // Terminal panel (0 when hidden)
+                Constraint::Length(search_height),              // Search bar (0 when inactive)
+                Constraint::Length(input_height),               // Input
+                Constraint::Length(1),                          // Footer
             ])
             .split(main_area);
 
         // Cache areas for mouse click detection
         self.messages_area = chunks[1];
-        self.input_area = chunks[3];
+        self.terminal_area = chunks[2];
+        self.input_area = chunks[4];
+
+        // Graphics are drawn over text, so hide images while anything covers the messages
+        let covered = !matches!(self.dialog, ActiveDialog::None)
+            || self.onboarding.is_visible()
+            || self.which_key.is_visible()
+            || self.help_overlay.is_visible()
+            || self.slash_autocomplete.is_visible()
+            || self.autocomplete.is_visible();
+        self.messages.set_show_images(!covered);
 
         // Messages (with horizontal padding)
         let messages_area = chunks[1];
+        let padding = if linear { 0 } else { 2 };
         let padded_messages_area = Rect {
-            x: messages_area.x + 2,
+            x: messages_area.x + padding,
             y: messages_area.y,
-            width: messages_area.width.saturating_sub(4),
+            width: messages_area.width.saturating_sub(2 * padding),
             height: messages_area.height,
         };
         self.messages
             .render(frame, padded_messages_area, &self.theme);
 
+        // Terminal panel (when visible)
+        self.terminal.render(frame, chunks[2], &self.theme);
+
         // Search bar (when active)
         if self.search.is_active() {
-            self.search.render(frame, chunks[2], &self.theme);
+            self.search.render(frame, chunks[3], &self.theme);
         }
 
         // Input
-        self.input.render(frame, chunks[3], &self.theme);
+        self.input.render(frame, chunks[4], &self.theme);
 
         // Autocomplete popups (above input) - only show one at a time
         if self.slash_autocomplete.is_visible() {
             self.slash_autocomplete
-                .render(frame, chunks[3], &self.theme);
+                .render(frame, chunks[4], &self.theme);
         } else {
-            self.autocomplete.render(frame, chunks[3], &self.theme);
+            self.autocomplete.render(frame, chunks[4], &self.theme);
         }
 
         // Footer
-        self.footer.render(frame, chunks[4], &self.theme);
+        self.footer.render(frame, chunks[5], &self.theme);
 
         // Which-key overlay (on top of everything)
         if self.which_key.is_visible() {
@@ -2024,15 +3214,106 @@ async function fetchUserData(userId) {
             self.help_overlay.render(frame, main_area, &self.theme);
         }
 
-        // Sidebar (at index 2 because index 1 is the margin)
-        if self.sidebar.is_visible() && main_chunks.len() > 2 {
-            self.sidebar_area = main_chunks[2];
-            self.sidebar.render(frame, main_chunks[2], &self.theme);
+        // File browser (first, before its margin)
+        if show_file_browser {
+            self.file_browser_area = main_chunks[0];
+            self.file_browser
+                .render(frame, self.file_browser_area, &self.theme);
+            for dir in self.file_browser.take_listing_requests() {
+                let _ = self.action_tx.send(AppAction::ListFiles { dir });
+            }
+        } else {
+            self.file_browser_area = Rect::default();
+        }
+
+        // Preview pane (after the messages and a margin)
+        if show_preview {
+            self.preview_area = main_chunks[main_index + 2];
+            self.sync_preview();
+            self.preview.render(frame, self.preview_area, &self.theme);
+        } else {
+            self.preview_area = Rect::default();
+        }
+
+        // Sidebar (last, after its margin)
+        if show_sidebar {
+            self.sidebar_area = main_chunks[main_chunks.len() - 1];
+            self.sidebar.render(frame, self.sidebar_area, &self.theme);
         } else {
             self.sidebar_area = Rect::default();
         }
     }
 
+    /// Show the preview pane in the given mode, following the edit in view.
+    fn show_preview(&mut self, mode: PreviewMode) {
+        self.unpin_preview();
+        self.preview.set_visible(true);
+        if self.preview.mode() != mode {
+            self.preview.toggle_mode();
+        }
+    }
+
+    /// Make the preview pane follow the edit in view again.
+    fn unpin_preview(&mut self) {
+        if self.preview_pinned {
+            self.preview_pinned = false;
+            self.preview_source = None;
+            self.preview.clear();
+        }
+    }
+
+    /// Point the preview pane at the edit in view, unless pinned, loading
+    /// its file if shown.
+    fn sync_preview(&mut self) {
+        match self.messages.edit_in_view() {
+            _ if self.preview_pinned => {
+                // Showing a file or diff picked in the file browser
+            }
+            Some(tool) => {
+                let source = (tool.id.clone(), tool.status);
+                if self.preview_source.as_ref() != Some(&source) {
+                    let diff = tool.edit_diff().unwrap_or_default();
+                    self.preview.set_edit(tool.edited_path(), &diff);
+                    if source.1 == ToolStatus::Success {
+                        // The file changed on disk
+                        self.preview.reload_file();
+                    }
+                    self.preview_source = Some(source);
+                }
+            }
+            None => {
+                if self.preview_source.take().is_some() {
+                    self.preview.clear();
+                }
+            }
+        }
+
+        if self.remote_files {
+            match self.preview.file_to_load() {
+                Some(path) if self.preview_requested.as_deref() != Some(path) => {
+                    let relative = std::path::Path::new(path)
+                        .strip_prefix(&self.directory)
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or_else(|_| path.to_string());
+                    let _ = self.action_tx.send(AppAction::ReadFile { path: relative });
+                    self.preview_requested = Some(path.to_string());
+                }
+                Some(_) => {}
+                None => self.preview_requested = None,
+            }
+        } else if let Some(path) = self.preview.file_to_load() {
+            let path = std::path::Path::new(&self.directory).join(path);
+            let content = match std::fs::metadata(&path) {
+                Ok(meta) if meta.len() > MAX_PREVIEW_FILE_BYTES => {
+                    Err(format!("{} is too large to show", path.display()))
+                }
+                _ => std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Can't read {}: {e}", path.display())),
+            };
+            self.preview.set_file(content);
+        }
+    }
+
     /// Draw the active dialog.
     fn draw_dialog(&mut self, frame: &mut Frame, area: Rect) {
         match &self.dialog {
@@ -2053,6 +3334,21 @@ async function fetchUserData(userId) {
                     dialog.render(frame, area, &self.theme);
                 }
             }
+            ActiveDialog::TemplateSelect => {
+                if let Some(dialog) = &mut self.template_dialog {
+                    dialog.render(frame, area, &self.theme);
+                }
+            }
+            ActiveDialog::ProjectSelect => {
+                if let Some(dialog) = &mut self.project_dialog {
+                    dialog.render(frame, area, &self.theme);
+                }
+            }
+            ActiveDialog::Connect => {
+                if let Some(dialog) = &mut self.connect_dialog {
+                    dialog.render(frame, area, &self.theme);
+                }
+            }
             ActiveDialog::ThemeSelect => {
                 self.theme_dialog.render(frame, area, &self.theme);
             }
@@ -2067,7 +3363,12 @@ async function fetchUserData(userId) {
                     dialog.render(frame, area, &self.theme);
                 }
             }
-            ActiveDialog::Rename => {
+            ActiveDialog::Usage => {
+                if let Some(dialog) = &self.usage_dialog {
+                    dialog.render(frame, area, &self.theme);
+                }
+            }
+            ActiveDialog::Rename | ActiveDialog::Export | ActiveDialog::ConnectSecret => {
                 if let Some(dialog) = &self.input_dialog {
                     dialog.render(frame, area, &self.theme);
                 }
@@ -2102,6 +3403,16 @@ async function fetchUserData(userId) {
                     dialog.render(frame, area, &self.theme);
                 }
             }
+            ActiveDialog::Coordinator => {
+                if let Some(dialog) = &mut self.coordinator_dialog {
+                    dialog.render(frame, area, &self.theme);
+                }
+            }
+            ActiveDialog::Review => {
+                if let Some(review) = &mut self.review {
+                    review.render(frame, area, &self.theme);
+                }
+            }
         }
     }
 
@@ -2147,6 +3458,22 @@ async function fetchUserData(userId) {
                     return;
                 }
 
+                if self.file_browser.is_focused() {
+                    self.handle_file_browser_key(key);
+                    return;
+                }
+
+                if self.terminal.is_focused() {
+                    self.handle_terminal_key(key);
+                    return;
+                }
+
+                // Tabs switch in any state, so another tab can be used while
+                // the agent works
+                if self.handle_tab_key(&key) {
+                    return;
+                }
+
                 match self.state {
                     AppState::Input => {
                         // Handle slash command autocomplete first if visible
@@ -2170,10 +3497,14 @@ async function fetchUserData(userId) {
                         if self.autocomplete.is_visible() {
                             match self.autocomplete.handle_key(key) {
                                 AutocompleteAction::Select(path) => {
-                                    // Replace @filter with @path
+                                    // Replace @filter with the file or directory
                                     self.apply_autocomplete(&path);
                                     return;
                                 }
+                                AutocompleteAction::SelectSymbol(symbol) => {
+                                    self.apply_symbol_autocomplete(symbol);
+                                    return;
+                                }
                                 AutocompleteAction::Handled => {
                                     return;
                                 }
@@ -2183,6 +3514,23 @@ async function fetchUserData(userId) {
                             }
                         }
 
+                        if self.keybinds.is_leader(&key) {
+                            self.autocomplete.hide();
+                            self.slash_autocomplete.hide();
+                            self.set_state(AppState::Leader);
+                            self.which_key.show();
+                            return;
+                        }
+                        if let Some(action) = self.keybinds.find_action(&key) {
+                            if action.scope() == KeyScope::Global {
+                                self.autocomplete.hide();
+                                self.slash_autocomplete.hide();
+                                if self.run_key_action(action) {
+                                    return;
+                                }
+                            }
+                        }
+
                         let action = self.input.handle_key(key);
 
                         // Check for autocomplete triggers after input handling
@@ -2201,10 +3549,28 @@ async function fetchUserData(userId) {
                                     return;
                                 }
 
+                                // A watcher's prompt waits until they drive
+                                if self.watching {
+                                    self.toasts.push(Toast::warning(tr("toast.watching")));
+                                    return;
+                                }
+
                                 self.autocomplete.hide();
                                 self.slash_autocomplete.hide();
+                                let images = self.input.images();
+                                let contexts = self.input.contexts();
                                 let text = self.input.take();
                                 if !text.is_empty() {
+                                    // An edited prompt forks the session, keeping the
+                                    // original conversation, and reverts this one to
+                                    // before the prompt
+                                    if let Some((message_id, _)) = self.editing_prompt.take() {
+                                        let _ = self
+                                            .action_tx
+                                            .send(AppAction::ForkSession { message_id: None });
+                                        let _ =
+                                            self.action_tx.send(AppAction::Revert { message_id });
+                                    }
                                     // Commit any pending revert (discard undone messages)
                                     self.messages.commit_revert();
 
@@ -2212,30 +3578,18 @@ async function fetchUserData(userId) {
                                     self.set_state(AppState::Waiting);
                                     self.footer.set_status(FooterStatus::Thinking);
                                     self.messages.start_streaming();
+                                    self.send_images(&images);
+                                    self.send_contexts(contexts);
                                     let _ = self.action_tx.send(AppAction::SendPrompt(text));
                                 }
                             }
-                            InputAction::CommandPalette => {
-                                self.autocomplete.hide();
-                                self.slash_autocomplete.hide();
-                                self.dialog = ActiveDialog::CommandPalette;
-                                self.command_palette = CommandPalette::new();
-                            }
-                            InputAction::LeaderKey => {
-                                self.autocomplete.hide();
-                                self.slash_autocomplete.hide();
-                                self.set_state(AppState::Leader);
-                                self.which_key.show();
-                            }
                             InputAction::Escape => {
                                 if self.slash_autocomplete.is_visible() {
                                     self.slash_autocomplete.hide();
                                 } else if self.autocomplete.is_visible() {
                                     self.autocomplete.hide();
-                                } else if self.footer.is_busy() {
-                                    // If LLM is running, cancel the operation
-                                    let _ = self.action_tx.send(AppAction::Cancel);
-                                    self.toasts.push(Toast::warning("Cancelling..."));
+                                } else if self.editing_prompt.is_some() {
+                                    self.cancel_prompt_edit();
                                 } else if self.route == Route::Session {
                                     self.set_state(AppState::Scrolling);
                                     self.input.set_focused(false);
@@ -2259,9 +3613,13 @@ async function fetchUserData(userId) {
                             InputAction::Paste => {
                                 self.autocomplete.hide();
                                 self.slash_autocomplete.hide();
-                                // Try to paste from clipboard
+                                // Try to paste text from clipboard, then an image
                                 if let Ok(mut clipboard) = arboard::Clipboard::new() {
                                     if let Ok(text) = clipboard.get_text() {
+                                        if let Some(path) = pasted_image_path(&text) {
+                                            self.attach_image(&path);
+                                            return;
+                                        }
                                         let line_count = text.lines().count();
                                         tracing::debug!(
                                             "Ctrl+V paste: {} lines, {} bytes",
@@ -2269,11 +3627,14 @@ async function fetchUserData(userId) {
                                             text.len()
                                         );
                                         if line_count >= 2 {
-                                            self.toasts.push(Toast::info(format!(
-                                                "Pasted {line_count} lines"
+                                            self.toasts.push(Toast::info(tr_args(
+                                                "toast.pasted_lines",
+                                                &[("count", &line_count)],
                                             )));
                                         }
                                         self.input.insert_paste(&text);
+                                    } else {
+                                        self.paste_clipboard_image();
                                     }
                                 }
                             }
@@ -2290,25 +3651,17 @@ async function fetchUserData(userId) {
                         }
                     }
                     AppState::Scrolling => {
-                        match key.code {
-                            KeyCode::Up | KeyCode::Char('k') => {
-                                self.messages.scroll_up(1);
-                            }
-                            KeyCode::Down | KeyCode::Char('j') => {
-                                self.messages.scroll_down(1);
-                            }
-                            KeyCode::PageUp => {
-                                self.messages.scroll_up(10);
-                            }
-                            KeyCode::PageDown => {
-                                self.messages.scroll_down(10);
-                            }
-                            KeyCode::Home | KeyCode::Char('g') => {
-                                self.messages.scroll_up(usize::MAX);
-                            }
-                            KeyCode::End | KeyCode::Char('G') => {
-                                self.messages.scroll_to_bottom();
+                        if self.keybinds.is_leader(&key) {
+                            self.set_state(AppState::Leader);
+                            self.which_key.show();
+                            return;
+                        }
+                        if let Some(action) = self.keybinds.find_action(&key) {
+                            if self.run_key_action(action) {
+                                return;
                             }
+                        }
+                        match key.code {
                             KeyCode::Char('y') => {
                                 // Copy last response while in scroll mode
                                 self.copy_last_response();
@@ -2317,7 +3670,7 @@ async function fetchUserData(userId) {
                                 // Enter selection mode
                                 self.messages.enter_selection_mode();
                                 self.set_state(AppState::Selecting);
-                                self.toasts.push(Toast::info("Selection mode: j/k to navigate, y to copy, o to expand, Esc to exit"));
+                                self.toasts.push(Toast::info(tr("toast.selection_mode")));
                             }
                             KeyCode::Char('o') => {
                                 // Toggle tool output expansion - expand/collapse all tools in last message
@@ -2340,16 +3693,19 @@ async function fetchUserData(userId) {
                                 // Show context-sensitive help
                                 self.help_overlay.toggle(HelpContext::Scroll);
                             }
+                            KeyCode::Char('n') if self.search.match_count() > 0 => {
+                                self.search.next_match();
+                                self.jump_to_match();
+                            }
+                            KeyCode::Char('N') if self.search.match_count() > 0 => {
+                                self.search.prev_match();
+                                self.jump_to_match();
+                            }
                             _ if is_escape(&key) => {
-                                // If LLM is running, cancel the operation
-                                if self.footer.is_busy() {
-                                    let _ = self.action_tx.send(AppAction::Cancel);
-                                    self.toasts.push(Toast::warning("Cancelling..."));
-                                } else {
-                                    self.set_state(AppState::Input);
-                                    self.input.set_focused(true);
-                                    self.messages.set_focused(false);
-                                }
+                                self.clear_search();
+                                self.set_state(AppState::Input);
+                                self.input.set_focused(true);
+                                self.messages.set_focused(false);
                             }
                             _ => {}
                         }
@@ -2366,9 +3722,10 @@ async function fetchUserData(userId) {
                                 // Copy selected message
                                 if let Some(content) = self.messages.get_selected_content() {
                                     if self.copy_to_clipboard(&content) {
-                                        self.toasts.push(Toast::success("Copied to clipboard"));
+                                        self.toasts.push(Toast::success(tr("toast.copied")));
                                     } else {
-                                        self.toasts.push(Toast::error("Failed to copy"));
+                                        self.toasts
+                                            .push(Toast::error(tr("toast.copy_failed_short")));
                                     }
                                 }
                                 // Exit selection mode after copy
@@ -2379,9 +3736,10 @@ async function fetchUserData(userId) {
                                 // Copy and stay in selection mode
                                 if let Some(content) = self.messages.get_selected_content() {
                                     if self.copy_to_clipboard(&content) {
-                                        self.toasts.push(Toast::success("Copied to clipboard"));
+                                        self.toasts.push(Toast::success(tr("toast.copied")));
                                     } else {
-                                        self.toasts.push(Toast::error("Failed to copy"));
+                                        self.toasts
+                                            .push(Toast::error(tr("toast.copy_failed_short")));
                                     }
                                 }
                             }
@@ -2389,11 +3747,15 @@ async function fetchUserData(userId) {
                                 // Toggle tool output expansion for selected message
                                 self.messages.toggle_selected_tool_expansion();
                             }
+                            KeyCode::Char('e') => {
+                                // Edit the selected prompt and resubmit it
+                                self.edit_selected_prompt();
+                            }
                             _ if is_escape(&key) => {
                                 // If LLM is running, cancel the operation
                                 if self.footer.is_busy() {
                                     let _ = self.action_tx.send(AppAction::Cancel);
-                                    self.toasts.push(Toast::warning("Cancelling..."));
+                                    self.toasts.push(Toast::warning(tr("toast.cancelling")));
                                 }
                                 // Exit selection mode
                                 self.messages.exit_selection_mode();
@@ -2408,35 +3770,43 @@ async function fetchUserData(userId) {
                                 // If LLM is running, cancel the operation
                                 if self.footer.is_busy() {
                                     let _ = self.action_tx.send(AppAction::Cancel);
-                                    self.toasts.push(Toast::warning("Cancelling..."));
+                                    self.toasts.push(Toast::warning(tr("toast.cancelling")));
                                 }
                                 // Exit search mode
-                                self.search.deactivate();
+                                self.clear_search();
                                 self.set_state(AppState::Scrolling);
                             }
                             KeyCode::Enter => {
-                                // Go to current match and exit search
-                                if let Some(m) = self.search.current_match() {
-                                    // Scroll to the message
-                                    self.messages.scroll_to_message(m.message_index);
-                                }
-                                self.search.deactivate();
+                                // Go to current match and exit search, keeping the
+                                // matches for n/N
+                                self.jump_to_match();
                                 self.set_state(AppState::Scrolling);
                             }
-                            KeyCode::Char('n') if !key.modifiers.contains(KeyModifiers::SHIFT) => {
+                            KeyCode::Down => {
                                 self.search.next_match();
-                                // Also scroll to the match
-                                if let Some(m) = self.search.current_match() {
-                                    self.messages.scroll_to_message(m.message_index);
-                                }
+                                self.jump_to_match();
+                            }
+                            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                self.search.next_match();
+                                self.jump_to_match();
+                            }
+                            KeyCode::Up => {
+                                self.search.prev_match();
+                                self.jump_to_match();
                             }
-                            KeyCode::Char('N') | KeyCode::Char('n')
-                                if key.modifiers.contains(KeyModifiers::SHIFT) =>
-                            {
+                            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                                 self.search.prev_match();
-                                if let Some(m) = self.search.current_match() {
-                                    self.messages.scroll_to_message(m.message_index);
+                                self.jump_to_match();
+                            }
+                            KeyCode::Tab => {
+                                // Search all sessions for the query instead
+                                let query = self.search.query().to_string();
+                                if !query.is_empty() {
+                                    let _ =
+                                        self.action_tx.send(AppAction::SearchSessions { query });
                                 }
+                                self.clear_search();
+                                self.set_state(AppState::Scrolling);
                             }
                             KeyCode::Backspace => {
                                 self.search.delete_char();
@@ -2466,16 +3836,25 @@ async function fetchUserData(userId) {
                         }
                     }
                     AppState::Waiting => {
-                        if is_escape(&key) {
+                        if self.keybinds.find_action(&key) == Some(KeyAction::SessionInterrupt) {
                             let _ = self.action_tx.send(AppAction::Cancel);
-                            self.toasts.push(Toast::warning("Cancelling..."));
+                            self.toasts.push(Toast::warning(tr("toast.cancelling")));
+                        } else if key.code == KeyCode::Char('x') && key.modifiers.is_empty() {
+                            // Cancel the tool showing progress, not the whole prompt
+                            if let Some(id) = self.messages.cancellable_tool() {
+                                let id = id.to_string();
+                                let _ = self.action_tx.send(AppAction::CancelTool { id });
+                                self.toasts
+                                    .push(Toast::warning(tr("toast.cancelling_tool")));
+                            }
                         }
                     }
                     AppState::Leader | AppState::Quit => {}
                 }
             }
             Event::Resize(_, _) => {
-                // Terminal will handle resize
+                // Terminal will handle resize; images are sized by the cell size
+                self.messages.refresh_image_cell_size();
             }
             Event::Mouse(mouse) => {
                 self.handle_mouse(mouse);
@@ -2506,6 +3885,16 @@ async function fetchUserData(userId) {
                     self.autocomplete.hide();
                     self.slash_autocomplete.hide();
 
+                    // An empty paste may be an image, and a pasted image file
+                    // path, e.g. of a file dropped on the terminal, attaches it
+                    if let Some(path) = pasted_image_path(&text) {
+                        self.attach_image(&path);
+                        return;
+                    }
+                    if text.is_empty() && self.paste_clipboard_image() {
+                        return;
+                    }
+
                     // insert_paste handles both multi-line wrapping and single-line tracking
                     self.input.insert_paste(&text);
 
@@ -2516,6 +3905,8 @@ async function fetchUserData(userId) {
                     self.messages.set_focused(false);
                 }
             }
+            Event::FocusGained => self.notifier.set_focused(true),
+            Event::FocusLost => self.notifier.set_focused(false),
             Event::Message(_) | Event::Status(_) | Event::Error(_) => {
                 // Legacy events - handled via update channel now
             }
@@ -2549,6 +3940,27 @@ async function fetchUserData(userId) {
                         && y < self.sidebar_area.y + self.sidebar_area.height
                     {
                         self.sidebar.handle_scroll(true, self.sidebar_area);
+                    } else if self.file_browser.is_visible()
+                        && x >= self.file_browser_area.x
+                        && x < self.file_browser_area.x + self.file_browser_area.width
+                        && y >= self.file_browser_area.y
+                        && y < self.file_browser_area.y + self.file_browser_area.height
+                    {
+                        self.file_browser.handle_scroll(true);
+                    } else if self.terminal.is_visible()
+                        && x >= self.terminal_area.x
+                        && x < self.terminal_area.x + self.terminal_area.width
+                        && y >= self.terminal_area.y
+                        && y < self.terminal_area.y + self.terminal_area.height
+                    {
+                        self.terminal.scroll_up(3);
+                    } else if self.preview.is_visible()
+                        && x >= self.preview_area.x
+                        && x < self.preview_area.x + self.preview_area.width
+                        && y >= self.preview_area.y
+                        && y < self.preview_area.y + self.preview_area.height
+                    {
+                        self.preview.scroll_up(3);
                     } else {
                         // Scroll messages up
                         self.messages.scroll_up(3);
@@ -2574,6 +3986,27 @@ async function fetchUserData(userId) {
                         && y < self.sidebar_area.y + self.sidebar_area.height
                     {
                         self.sidebar.handle_scroll(false, self.sidebar_area);
+                    } else if self.file_browser.is_visible()
+                        && x >= self.file_browser_area.x
+                        && x < self.file_browser_area.x + self.file_browser_area.width
+                        && y >= self.file_browser_area.y
+                        && y < self.file_browser_area.y + self.file_browser_area.height
+                    {
+                        self.file_browser.handle_scroll(false);
+                    } else if self.terminal.is_visible()
+                        && x >= self.terminal_area.x
+                        && x < self.terminal_area.x + self.terminal_area.width
+                        && y >= self.terminal_area.y
+                        && y < self.terminal_area.y + self.terminal_area.height
+                    {
+                        self.terminal.scroll_down(3);
+                    } else if self.preview.is_visible()
+                        && x >= self.preview_area.x
+                        && x < self.preview_area.x + self.preview_area.width
+                        && y >= self.preview_area.y
+                        && y < self.preview_area.y + self.preview_area.height
+                    {
+                        self.preview.scroll_down(3);
                     } else {
                         // Scroll messages down
                         self.messages.scroll_down(3);
@@ -2586,14 +4019,47 @@ async function fetchUserData(userId) {
                     let x = mouse.column;
                     let y = mouse.row;
 
-                    // Check if click is in sidebar (section header toggle)
-                    if self.sidebar.is_visible()
-                        && self.sidebar.handle_click(x, y, self.sidebar_area)
+                    // Check if click is in sidebar
+                    if self.sidebar.is_visible() {
+                        if let Some(click) = self.sidebar.handle_click(x, y, self.sidebar_area) {
+                            self.handle_sidebar_click(click);
+                            return;
+                        }
+                    }
+
+                    // Clicking a file browser entry selects it, then opens it
+                    if let Some(action) = self.file_browser.handle_click(x, y) {
+                        self.focus_file_browser();
+                        self.handle_file_browser_action(action);
+                        return;
+                    }
+
+                    // Clicking the preview pane switches between diff and file
+                    if self.preview.is_visible()
+                        && x >= self.preview_area.x
+                        && x < self.preview_area.x + self.preview_area.width
+                        && y >= self.preview_area.y
+                        && y < self.preview_area.y + self.preview_area.height
+                    {
+                        self.preview.toggle_mode();
+                        return;
+                    }
+
+                    // Clicking the terminal panel focuses it, a tab switches to it
+                    if self.terminal.is_visible()
+                        && x >= self.terminal_area.x
+                        && x < self.terminal_area.x + self.terminal_area.width
+                        && y >= self.terminal_area.y
+                        && y < self.terminal_area.y + self.terminal_area.height
                     {
-                        // Section was toggled, nothing else to do
+                        self.terminal.handle_click(x, y);
+                        self.focus_terminal();
                         return;
                     }
 
+                    self.file_browser.set_focused(false);
+                    self.terminal.set_focused(false);
+
                     // Check if click is in input area
                     if x >= self.input_area.x
                         && x < self.input_area.x + self.input_area.width
@@ -2611,14 +4077,17 @@ async function fetchUserData(userId) {
                         && y >= self.messages_area.y
                         && y < self.messages_area.y + self.messages_area.height
                     {
+                        // Clicking a tool call expands or collapses it
+                        if self.messages.toggle_tool_at(x, y) {
+                            return;
+                        }
                         // Check if clicking on a code block
                         if let Some(code_content) = self.messages.handle_click(x, y) {
                             // Copy code to clipboard
                             if self.copy_to_clipboard(&code_content) {
-                                self.toasts.push(Toast::success("Code copied to clipboard"));
+                                self.toasts.push(Toast::success(tr("toast.code_copied")));
                             } else {
-                                self.toasts
-                                    .push(Toast::error("Failed to copy to clipboard"));
+                                self.toasts.push(Toast::error(tr("toast.copy_failed")));
                             }
                         } else {
                             // Focus messages (scroll mode)
@@ -2633,10 +4102,154 @@ async function fetchUserData(userId) {
         }
     }
 
+    /// Give the file browser focus.
+    fn focus_file_browser(&mut self) {
+        self.file_browser.set_focused(true);
+        self.terminal.set_focused(false);
+        self.input.set_focused(false);
+        self.autocomplete.hide();
+        self.slash_autocomplete.hide();
+    }
+
+    /// Handle a key while the file browser has focus.
+    fn handle_file_browser_key(&mut self, key: crossterm::event::KeyEvent) {
+        if self.keybinds.is_leader(&key) {
+            self.set_state(AppState::Leader);
+            self.which_key.show();
+            return;
+        }
+        let action = self.file_browser.handle_key(key);
+        if action == FileBrowserAction::None {
+            if key.code == KeyCode::Char('?') {
+                self.help_overlay.toggle(HelpContext::Scroll);
+            } else if let Some(action) = self.keybinds.find_action(&key) {
+                if action.scope() == KeyScope::Global {
+                    self.run_key_action(action);
+                }
+            }
+            return;
+        }
+        self.handle_file_browser_action(action);
+    }
+
+    /// Act on what was chosen in the file browser.
+    fn handle_file_browser_action(&mut self, action: FileBrowserAction) {
+        match action {
+            FileBrowserAction::None | FileBrowserAction::Handled => {}
+            FileBrowserAction::Open(path) => {
+                self.preview.show_file(path);
+                self.preview_pinned = true;
+                self.preview_source = None;
+            }
+            FileBrowserAction::AddContext(path) => {
+                let content = self.input.content();
+                if !content.is_empty() && !content.ends_with(char::is_whitespace) {
+                    self.input.insert_text(" ");
+                }
+                self.input.insert_text(&format!("@{path} "));
+                self.toasts
+                    .push(Toast::info(tr_args("toast.file_added", &[("path", &path)])));
+            }
+            FileBrowserAction::ShowDiff(path) => {
+                let root = std::path::PathBuf::from(&self.directory);
+                let edited = |p: &str| {
+                    let p = std::path::Path::new(p);
+                    p.strip_prefix(&root).unwrap_or(p) == std::path::Path::new(&path)
+                };
+                match self.messages.scroll_to_edit(edited) {
+                    Some(tool) => {
+                        let source = (tool.id.clone(), tool.status);
+                        let diff = tool.edit_diff().unwrap_or_default();
+                        self.preview.set_edit(tool.edited_path(), &diff);
+                        if self.preview.mode() != PreviewMode::Diff {
+                            self.preview.toggle_mode();
+                        }
+                        self.preview.set_visible(true);
+                        self.preview_source = Some(source);
+                        self.preview_pinned = true;
+                    }
+                    None => self.toasts.push(Toast::info(tr_args(
+                        "toast.no_file_edits",
+                        &[("path", &path)],
+                    ))),
+                }
+            }
+            FileBrowserAction::Unfocus | FileBrowserAction::Close => {
+                self.input.set_focused(self.state != AppState::Scrolling);
+            }
+        }
+    }
+
+    /// Give the terminal panel focus.
+    fn focus_terminal(&mut self) {
+        self.terminal.set_focused(true);
+        self.file_browser.set_focused(false);
+        self.input.set_focused(false);
+        self.autocomplete.hide();
+        self.slash_autocomplete.hide();
+    }
+
+    /// Handle a key while the terminal panel has focus.
+    fn handle_terminal_key(&mut self, key: crossterm::event::KeyEvent) {
+        if self.keybinds.is_leader(&key) {
+            self.set_state(AppState::Leader);
+            self.which_key.show();
+            return;
+        }
+        match self.terminal.handle_key(key) {
+            TerminalAction::None => {
+                if key.code == KeyCode::Char('?') {
+                    self.help_overlay.toggle(HelpContext::Scroll);
+                } else if let Some(action) = self.keybinds.find_action(&key) {
+                    if action.scope() == KeyScope::Global {
+                        self.run_key_action(action);
+                    }
+                }
+            }
+            TerminalAction::Handled => {}
+            TerminalAction::Stop(pid) => {
+                let _ = self.action_tx.send(AppAction::StopProcess { pid });
+                self.toasts.push(Toast::info(tr_args(
+                    "toast.stopping_process",
+                    &[("pid", &pid)],
+                )));
+            }
+            TerminalAction::Unfocus | TerminalAction::Close => {
+                self.input.set_focused(self.state != AppState::Scrolling);
+            }
+        }
+    }
+
+    /// Open what a sidebar entry refers to.
+    fn handle_sidebar_click(&mut self, click: SidebarClick) {
+        match click {
+            SidebarClick::Handled => {}
+            SidebarClick::Session => self.execute_command("session_list"),
+            SidebarClick::Context | SidebarClick::Lsp => self.show_status_dialog(),
+            SidebarClick::Mcp(name) => {
+                self.show_mcp_dialog();
+                if let Some(dialog) = &mut self.mcp_dialog {
+                    dialog.select(&name);
+                }
+            }
+            SidebarClick::ModifiedFile(_) => self.show_git_dialog(),
+        }
+    }
+
     /// Handle dialog key events.
     #[allow(clippy::cognitive_complexity)]
     fn handle_dialog_key(&mut self, key: crossterm::event::KeyEvent) {
         if is_escape(&key) {
+            if let Some(theme) = self.theme_before_preview.take() {
+                self.theme = theme;
+            }
+            if self.review.take().is_some() {
+                self.toasts.push(Toast::info(tr("toast.review_cancelled")));
+            }
+            if self.connect_dialog.take().is_some() {
+                let _ = self.action_tx.send(AppAction::StopBrowsingServers);
+            }
+            self.connecting = None;
             self.dialog = ActiveDialog::None;
             return;
         }
@@ -2653,7 +4266,8 @@ async function fetchUserData(userId) {
                     self.dialog = ActiveDialog::None;
                     let _ = self.action_tx.send(AppAction::ChangeModel(id.clone()));
                     self.set_model(&id);
-                    self.toasts.push(Toast::success(format!("Model: {id}")));
+                    self.toasts
+                        .push(Toast::success(tr_args("toast.model", &[("id", &id)])));
                 }
             }
             ActiveDialog::AgentSelect => {
@@ -2662,7 +4276,8 @@ async function fetchUserData(userId) {
                         self.dialog = ActiveDialog::None;
                         let _ = self.action_tx.send(AppAction::ChangeAgent(id.clone()));
                         self.set_agent(&id);
-                        self.toasts.push(Toast::success(format!("Agent: {id}")));
+                        self.toasts
+                            .push(Toast::success(tr_args("toast.agent", &[("id", &id)])));
                     }
                 }
             }
@@ -2674,10 +4289,68 @@ async function fetchUserData(userId) {
                     }
                 }
             }
+            ActiveDialog::TemplateSelect => {
+                if let Some(dialog) = &mut self.template_dialog {
+                    if let Some(name) = dialog.handle_key(key) {
+                        self.dialog = ActiveDialog::None;
+                        let _ = self.action_tx.send(AppAction::ApplyTemplate { name });
+                    }
+                }
+            }
+            ActiveDialog::ProjectSelect => {
+                if let Some(dialog) = &mut self.project_dialog {
+                    if let Some(project) = dialog.handle_key(key) {
+                        self.dialog = ActiveDialog::None;
+                        let _ = self.action_tx.send(AppAction::SwitchProject { project });
+                    }
+                }
+            }
+            ActiveDialog::Connect => {
+                let picked = self
+                    .connect_dialog
+                    .as_mut()
+                    .and_then(|dialog| dialog.handle_key(key));
+                if let Some(server) = picked {
+                    self.connect_dialog = None;
+                    let _ = self.action_tx.send(AppAction::StopBrowsingServers);
+                    if server.auth_required {
+                        self.input_dialog = Some(
+                            InputDialog::new(
+                                tr("dialog.connect_secret"),
+                                format!("Secret for {}:", server.name),
+                            )
+                            .masked(),
+                        );
+                        self.connecting = Some(server);
+                        self.dialog = ActiveDialog::ConnectSecret;
+                    } else {
+                        self.dialog = ActiveDialog::None;
+                        self.connect_to(&server.name, server.address, None);
+                    }
+                }
+            }
+            ActiveDialog::ConnectSecret => {
+                if let Some(dialog) = &mut self.input_dialog {
+                    if let Some(result) = dialog.handle_key(key) {
+                        self.dialog = ActiveDialog::None;
+                        self.input_dialog = None;
+                        let server = self.connecting.take();
+                        if let (InputDialogResult::Submit(secret), Some(server)) = (result, server)
+                        {
+                            let secret = (!secret.is_empty()).then_some(secret);
+                            self.connect_to(&server.name, server.address, secret);
+                        }
+                    }
+                }
+            }
             ActiveDialog::ThemeSelect => {
                 if let Some(id) = self.theme_dialog.handle_key(key) {
                     self.dialog = ActiveDialog::None;
+                    self.theme_before_preview = None;
                     self.set_theme(&id);
+                } else if let Some(id) = self.theme_dialog.highlighted() {
+                    // Preview the highlighted theme
+                    self.theme = self.load_theme(id);
                 }
             }
             ActiveDialog::Rename => {
@@ -2690,8 +4363,10 @@ async function fetchUserData(userId) {
                                     self.session_title = new_title.clone();
                                     self.sidebar.set_session_title(&new_title);
                                     self.topbar.set_session_title(Some(new_title.clone()));
-                                    self.toasts
-                                        .push(Toast::success(format!("Renamed to: {new_title}")));
+                                    self.toasts.push(Toast::success(tr_args(
+                                        "toast.renamed",
+                                        &[("title", &new_title)],
+                                    )));
                                     // Send action to persist the rename
                                     let _ = self
                                         .action_tx
@@ -2704,21 +4379,34 @@ async function fetchUserData(userId) {
                     }
                 }
             }
-            ActiveDialog::Mcp => {
-                if let Some(dialog) = &mut self.mcp_dialog {
-                    if let Some(action) = dialog.handle_key(key) {
-                        if action == "close" {
-                            self.dialog = ActiveDialog::None;
-                            self.mcp_dialog = None;
+            ActiveDialog::Export => {
+                if let Some(dialog) = &mut self.input_dialog {
+                    if let Some(result) = dialog.handle_key(key) {
+                        self.dialog = ActiveDialog::None;
+                        self.input_dialog = None;
+                        if let InputDialogResult::Submit(args) = result {
+                            self.export_session(&args);
+                        }
+                    }
+                }
+            }
+            ActiveDialog::Mcp => {
+                if let Some(dialog) = &mut self.mcp_dialog {
+                    if let Some(action) = dialog.handle_key(key) {
+                        if action == "close" {
+                            self.dialog = ActiveDialog::None;
+                            self.mcp_dialog = None;
                         } else if let Some(name) = action.strip_prefix("toggle:") {
                             self.toasts
-                                .push(Toast::info(format!("Toggle MCP server: {name}")));
+                                .push(Toast::info(tr_args("toast.mcp_toggle", &[("name", &name)])));
                             let _ = self.action_tx.send(AppAction::McpToggle {
                                 name: name.to_string(),
                             });
                         } else if let Some(name) = action.strip_prefix("reconnect:") {
-                            self.toasts
-                                .push(Toast::info(format!("Reconnecting: {name}")));
+                            self.toasts.push(Toast::info(tr_args(
+                                "toast.mcp_reconnecting",
+                                &[("name", &name)],
+                            )));
                             let _ = self.action_tx.send(AppAction::McpReconnect {
                                 name: name.to_string(),
                             });
@@ -2737,12 +4425,16 @@ async function fetchUserData(userId) {
                             if let Ok(idx) = msg_id.parse::<usize>() {
                                 // Scroll to message index
                                 self.messages.scroll_to_bottom();
-                                self.toasts
-                                    .push(Toast::info(format!("Jumped to message {}", idx + 1)));
+                                self.toasts.push(Toast::info(tr_args(
+                                    "toast.jumped_to",
+                                    &[("number", &(idx + 1))],
+                                )));
                             }
                         } else if let Some(msg_id) = action.strip_prefix("fork:") {
-                            self.toasts
-                                .push(Toast::info(format!("Forking from message {msg_id}...")));
+                            self.toasts.push(Toast::info(tr_args(
+                                "toast.forking_from",
+                                &[("id", &msg_id)],
+                            )));
                             let _ = self.action_tx.send(AppAction::ForkSession {
                                 message_id: Some(msg_id.to_string()),
                             });
@@ -2771,6 +4463,20 @@ async function fetchUserData(userId) {
                     }
                 }
             }
+            ActiveDialog::Usage => {
+                if let Some(dialog) = &mut self.usage_dialog {
+                    match dialog.handle_key(key) {
+                        UsageDialogResult::None => {}
+                        UsageDialogResult::Close => {
+                            self.dialog = ActiveDialog::None;
+                            self.usage_dialog = None;
+                        }
+                        UsageDialogResult::Load(days) => {
+                            let _ = self.action_tx.send(AppAction::LoadUsage { days });
+                        }
+                    }
+                }
+            }
             ActiveDialog::Permission => {
                 if let Some(dialog) = &mut self.permission_dialog {
                     if let Some(result) = dialog.handle_key(key) {
@@ -2784,98 +4490,219 @@ async function fetchUserData(userId) {
                     self.handle_git_dialog_result(result);
                 }
             }
-            ActiveDialog::Help | ActiveDialog::Status | ActiveDialog::None => {
-                // These dialogs close on any key press (already handled escape above)
+            ActiveDialog::Coordinator => {
+                if let Some(dialog) = &mut self.coordinator_dialog {
+                    match dialog.handle_key(key).as_deref() {
+                        Some("merge") => {
+                            if !dialog.all_finished() {
+                                self.toasts.push(Toast::warning(tr("toast.merge_wait")));
+                            } else if !dialog.has_unmerged() {
+                                self.toasts.push(Toast::info(tr("toast.nothing_to_merge")));
+                            } else {
+                                self.toasts.push(Toast::info(tr("toast.merging_agents")));
+                                let _ = self.action_tx.send(AppAction::MergeOrchestration);
+                            }
+                        }
+                        Some("cancel") => {
+                            if dialog.all_finished() {
+                                self.toasts.push(Toast::info(tr("toast.agents_finished")));
+                            } else {
+                                self.toasts
+                                    .push(Toast::info(tr("toast.orchestration_cancelling")));
+                                let _ = self.action_tx.send(AppAction::CancelOrchestration);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            ActiveDialog::Status => {
+                if self.status_dialog.handle_key(key) {
+                    self.dialog = ActiveDialog::None;
+                }
             }
+            ActiveDialog::Help => {
+                if self.help_dialog.handle_key(key) {
+                    self.dialog = ActiveDialog::None;
+                }
+            }
+            ActiveDialog::Review => self.handle_review_key(key),
+            ActiveDialog::None => {}
         }
     }
 
-    /// Handle leader key sequences (Ctrl+X followed by another key).
-    fn handle_leader_key(&mut self, key: crossterm::event::KeyEvent) {
-        self.set_state(AppState::Input);
-
+    /// Handle a key in the change review.
+    fn handle_review_key(&mut self, key: crossterm::event::KeyEvent) {
+        let Some(review) = &mut self.review else {
+            return;
+        };
         match key.code {
-            KeyCode::Char('n') | KeyCode::Char('N') => {
-                // New session
-                let _ = self.action_tx.send(AppAction::NewSession);
-                self.messages = MessagesWidget::with_render_settings(self.render_settings.clone());
-                self.route = Route::Home;
-                self.toasts.push(Toast::info("New session"));
-            }
-            KeyCode::Char('l') | KeyCode::Char('L') => {
-                // Session list
-                self.session_dialog = Some(SessionDialog::new(self.sessions.clone()));
-                self.dialog = ActiveDialog::SessionList;
-            }
-            KeyCode::Char('m') | KeyCode::Char('M') => {
-                // Model select
-                self.model_dialog =
-                    ModelDialog::with_options(self.render_settings.test_model_enabled);
-                self.dialog = ActiveDialog::ModelSelect;
-            }
-            KeyCode::Char('a') | KeyCode::Char('A') => {
-                // Agent select
-                self.agent_dialog = Some(AgentDialog::new(self.available_agents.clone()));
-                self.dialog = ActiveDialog::AgentSelect;
-            }
-            KeyCode::Char('b') | KeyCode::Char('B') => {
-                // Toggle sidebar
-                self.sidebar.toggle();
-            }
-            KeyCode::Char('g') | KeyCode::Char('G') => {
-                // Git dialog
-                self.show_git_dialog();
-            }
-            KeyCode::Char('t') | KeyCode::Char('T') => {
-                // Theme select
-                self.theme_dialog = ThemeDialog::new();
-                self.dialog = ActiveDialog::ThemeSelect;
-            }
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                // Copy last response
-                self.copy_last_response();
-            }
-            KeyCode::Char('e') | KeyCode::Char('E') => {
-                // Open input in external editor
-                self.edit_input_in_editor();
-            }
-            KeyCode::Char('x') | KeyCode::Char('X') => {
-                // Export session
-                self.export_session();
-            }
-            KeyCode::Char('u') | KeyCode::Char('U') => {
-                // Undo last message
-                self.undo_message();
-            }
-            KeyCode::Char('r') | KeyCode::Char('R') => {
-                // Redo undone message
-                self.redo_message();
+            KeyCode::Char('a') => review.decide(HunkDecision::Accept),
+            KeyCode::Char('r') => review.decide(HunkDecision::Reject),
+            KeyCode::Char('A') => review.decide_file(HunkDecision::Accept),
+            KeyCode::Char('R') => review.decide_file(HunkDecision::Reject),
+            KeyCode::Char('u') => review.undo(),
+            KeyCode::Char('e') => {
+                if Self::get_editor().is_none() {
+                    self.toasts.push(Toast::warning(tr("toast.no_editor")));
+                    return;
+                }
+                let Some(text) = review.hunk_text() else {
+                    return;
+                };
+                if let Some(edited) = Self::open_in_editor(&text, self.mouse_enabled) {
+                    if edited == text {
+                        review.decide(HunkDecision::Accept);
+                    } else {
+                        review.decide(HunkDecision::Edit(edited));
+                    }
+                }
             }
-            KeyCode::Char('?') => {
-                // Help
-                self.dialog = ActiveDialog::Help;
+            KeyCode::Char('n') | KeyCode::Char('j') | KeyCode::Down => {
+                review.handle_nav(DiffNavAction::NextHunk)
+            }
+            KeyCode::Char('p') | KeyCode::Char('k') | KeyCode::Up => {
+                review.handle_nav(DiffNavAction::PrevHunk)
+            }
+            KeyCode::Tab => review.handle_nav(DiffNavAction::NextFile),
+            KeyCode::BackTab => review.handle_nav(DiffNavAction::PrevFile),
+            KeyCode::PageDown => review.handle_nav(DiffNavAction::ScrollDown(10)),
+            KeyCode::PageUp => review.handle_nav(DiffNavAction::ScrollUp(10)),
+            KeyCode::Char('s') => review.handle_nav(DiffNavAction::ToggleStyle),
+            KeyCode::Enter => {
+                let undecided = review.hunk_count() - review.decided_count();
+                let files = review.files();
+                self.review = None;
+                self.dialog = ActiveDialog::None;
+                let message = if undecided > 0 {
+                    format!("Applying review, {undecided} hunk(s) left undecided...")
+                } else {
+                    "Applying review...".to_string()
+                };
+                self.toasts.push(Toast::info(message));
+                let _ = self.action_tx.send(AppAction::ApplyReview { files });
             }
-            KeyCode::Char('s') | KeyCode::Char('S')
-                if !key
-                    .modifiers
-                    .contains(crossterm::event::KeyModifiers::CONTROL) =>
-            {
-                // Settings
-                self.show_settings_dialog();
+            _ => {}
+        }
+    }
+
+    /// Open the review of the session's changes, given as a unified diff.
+    fn show_review(&mut self, diff: &str) {
+        let requested = std::mem::take(&mut self.review_requested);
+        let review = ReviewView::new(FileDiff::parse_unified(diff));
+        if review.is_empty() {
+            if requested {
+                self.toasts.push(Toast::info(tr("toast.no_changes")));
             }
-            KeyCode::Char('c') | KeyCode::Char('C')
-                if key
-                    .modifiers
-                    .contains(crossterm::event::KeyModifiers::CONTROL) =>
-            {
-                // Ctrl+X Ctrl+C: Exit application
-                self.set_state(AppState::Quit);
-                let _ = self.action_tx.send(AppAction::Quit);
+            return;
+        }
+        // Don't cover a dialog the user opened meanwhile
+        if !requested && self.dialog != ActiveDialog::None {
+            return;
+        }
+        self.review = Some(review);
+        self.dialog = ActiveDialog::Review;
+    }
+
+    /// Handle the key pressed after the leader key, or in the chord menu it
+    /// opened.
+    fn handle_leader_key(&mut self, key: crossterm::event::KeyEvent) {
+        if let Some(menu) = self.open_chord_menu {
+            let command = self.keybinds.find_menu_command(menu, &key).cloned();
+            self.set_state(AppState::Input);
+            match command {
+                Some(ChordCommand::Action(action)) => {
+                    self.run_key_action(action);
+                }
+                Some(ChordCommand::Slash(command)) => self.execute_slash_command(&command),
+                None => {}
             }
-            _ => {
-                // Unknown leader key
+            return;
+        }
+
+        if let Some(menu) = self.keybinds.find_menu(&key) {
+            self.open_chord_menu = Some(menu);
+            self.which_key.show_menu(menu);
+            return;
+        }
+
+        self.set_state(AppState::Input);
+
+        self.keybinds.set_leader_active(true);
+        let action = self.keybinds.find_action(&key);
+        self.keybinds.reset_leader();
+
+        if let Some(action) = action {
+            self.run_key_action(action);
+        }
+    }
+
+    /// Run the action of a keybinding. Returns false if it didn't apply, so
+    /// the key should be handled as usual.
+    fn run_key_action(&mut self, action: KeyAction) -> bool {
+        match action {
+            KeyAction::AppExit => self.execute_command("quit"),
+            KeyAction::EditorOpen => self.execute_command("edit_input"),
+            KeyAction::ThemeList => self.execute_command("theme_select"),
+            KeyAction::SidebarToggle => self.execute_command("toggle_sidebar"),
+            KeyAction::PreviewToggle => self.execute_command("toggle_preview"),
+            KeyAction::FileBrowserToggle => self.execute_command("toggle_file_browser"),
+            KeyAction::TerminalToggle => self.execute_command("toggle_terminal"),
+            KeyAction::GitOpen => self.show_git_dialog(),
+            KeyAction::SessionNew => {
+                self.execute_command("new_session");
+                self.toasts.push(Toast::info(tr("toast.new_session")));
+            }
+            KeyAction::SessionList => self.execute_command("session_list"),
+            KeyAction::SessionExport => self.execute_command("export_session"),
+            KeyAction::SessionInterrupt => {
+                if !self.footer.is_busy() {
+                    return false;
+                }
+                let _ = self.action_tx.send(AppAction::Cancel);
+                self.toasts.push(Toast::warning(tr("toast.cancelling")));
             }
+            KeyAction::SessionCompact => {
+                let _ = self.action_tx.send(AppAction::Compact);
+                self.toasts.push(Toast::info(tr("toast.compacting")));
+            }
+            KeyAction::SessionTimeline => self.show_timeline_dialog(),
+            KeyAction::TabNew => self.execute_command("new_tab"),
+            KeyAction::TabClose => self.execute_command("close_tab"),
+            KeyAction::TabNext => self.request_tab(self.active_tab as isize + 1),
+            KeyAction::TabPrev => self.request_tab(self.active_tab as isize - 1),
+            KeyAction::MessagesPageUp => self.messages.scroll_up(10),
+            KeyAction::MessagesPageDown => self.messages.scroll_down(10),
+            KeyAction::MessagesHalfPageUp => {
+                let half = (self.messages_area.height as usize / 2).max(1);
+                self.messages.scroll_up(half);
+            }
+            KeyAction::MessagesHalfPageDown => {
+                let half = (self.messages_area.height as usize / 2).max(1);
+                self.messages.scroll_down(half);
+            }
+            KeyAction::MessagesFirst => self.messages.scroll_up(usize::MAX),
+            KeyAction::MessagesLast => self.messages.scroll_to_bottom(),
+            KeyAction::MessagesNext => self.messages.scroll_down(1),
+            KeyAction::MessagesPrev => self.messages.scroll_up(1),
+            // Handled by the input
+            KeyAction::InputSubmit
+            | KeyAction::InputNewline
+            | KeyAction::InputHistory
+            | KeyAction::InputCancel => return false,
+            KeyAction::ModelList => self.execute_command("model_select"),
+            KeyAction::AgentList => self.execute_command("agent_select"),
+            KeyAction::EditUndo | KeyAction::SessionRevert => self.execute_command("undo"),
+            KeyAction::EditRedo | KeyAction::SessionUnrevert => self.execute_command("redo"),
+            KeyAction::EditCopy => self.execute_command("copy_last"),
+            KeyAction::CommandPalette => {
+                self.dialog = ActiveDialog::CommandPalette;
+                self.command_palette = CommandPalette::new();
+            }
+            KeyAction::HelpToggle => self.execute_command("help"),
+            KeyAction::SettingsOpen => self.show_settings_dialog(),
         }
+        true
     }
 
     /// Execute a slash command by name.
@@ -2895,16 +4722,35 @@ async function fetchUserData(userId) {
             // Navigation commands
             "models" => "model_select",
             "agents" | "agent" => "agent_select",
-            "theme" => "theme_select",
+            "theme" => match parts.next() {
+                Some("reload") => {
+                    self.reload_themes();
+                    return;
+                }
+                _ => "theme_select",
+            },
             // UI commands
             "editor" => "edit_input",
             "sidebar" => "toggle_sidebar",
+            "files" | "tree" => "toggle_file_browser",
+            "terminal" | "processes" => "toggle_terminal",
+            "preview" => match parts.next() {
+                Some("diff") => {
+                    self.show_preview(PreviewMode::Diff);
+                    return;
+                }
+                Some("file") => {
+                    self.show_preview(PreviewMode::File);
+                    return;
+                }
+                _ => "toggle_preview",
+            },
             "help" | "commands" => "help",
             "quit" | "exit" | "q" => "quit",
             // Not yet implemented commands
             "compact" | "summarize" => {
                 let _ = self.action_tx.send(AppAction::Compact);
-                self.toasts.push(Toast::info("Compacting conversation..."));
+                self.toasts.push(Toast::info(tr("toast.compacting")));
                 return;
             }
             "rename" => {
@@ -2914,14 +4760,41 @@ async function fetchUserData(userId) {
                     self.session_title.clone()
                 };
                 self.input_dialog = Some(
-                    InputDialog::new("Rename Session", "New session name:")
+                    InputDialog::new(tr("dialog.rename_session"), "New session name:")
                         .with_value(current_title),
                 );
                 self.dialog = ActiveDialog::Rename;
                 return;
             }
             "export" => {
-                self.export_session();
+                let args: Vec<&str> = parts.collect();
+                if args.is_empty() {
+                    self.show_export_dialog();
+                } else {
+                    self.export_session(&args.join(" "));
+                }
+                return;
+            }
+            "paste-image" => {
+                let path = parts.collect::<Vec<_>>().join(" ");
+                if path.is_empty() {
+                    if !self.paste_clipboard_image() {
+                        self.toasts
+                            .push(Toast::warning(tr("toast.no_clipboard_image")));
+                    }
+                    return;
+                }
+                let path = match path.strip_prefix("~/").zip(dirs::home_dir()) {
+                    Some((rest, home)) => home.join(rest),
+                    None => std::path::PathBuf::from(&self.directory).join(&path),
+                };
+                match pasted_image_path(&path.to_string_lossy()) {
+                    Some(path) => self.attach_image(&path),
+                    None => self.toasts.push(Toast::warning(tr_args(
+                        "toast.not_an_image",
+                        &[("path", &path.display())],
+                    ))),
+                }
                 return;
             }
             "timeline" => {
@@ -2932,12 +4805,164 @@ async function fetchUserData(userId) {
                 self.show_settings_dialog();
                 return;
             }
+            "search" => {
+                let args: Vec<&str> = parts.collect();
+                let all = matches!(args.first(), Some(&"--all" | &"-a"));
+                let query = args[usize::from(all)..].join(" ");
+                if all {
+                    if query.is_empty() {
+                        self.toasts.push(Toast::warning(tr("toast.usage_search")));
+                    } else {
+                        let _ = self.action_tx.send(AppAction::SearchSessions { query });
+                    }
+                } else if self.messages.message_count() == 0 {
+                    self.toasts.push(Toast::info(tr("toast.search_empty")));
+                } else {
+                    self.route = Route::Session;
+                    self.start_search(&query);
+                }
+                return;
+            }
+            "orchestrate" => {
+                let rest = parts.collect::<Vec<_>>().join(" ");
+                match rest.as_str() {
+                    "" if self.coordinator_dialog.is_some() => {
+                        self.dialog = ActiveDialog::Coordinator;
+                    }
+                    "" => {
+                        self.toasts
+                            .push(Toast::warning(tr("toast.usage_orchestrate")));
+                    }
+                    "merge" => {
+                        let _ = self.action_tx.send(AppAction::MergeOrchestration);
+                    }
+                    "cancel" => {
+                        let _ = self.action_tx.send(AppAction::CancelOrchestration);
+                    }
+                    _ => {
+                        let tasks: Vec<String> = rest
+                            .split('|')
+                            .map(str::trim)
+                            .filter(|t| !t.is_empty())
+                            .map(str::to_string)
+                            .collect();
+                        self.toasts.push(Toast::info(tr_args(
+                            "toast.orchestrate_starting",
+                            &[("count", &tasks.len())],
+                        )));
+                        let _ = self.action_tx.send(AppAction::Orchestrate { tasks });
+                    }
+                }
+                return;
+            }
+            "worktree" => {
+                match parts.next() {
+                    None | Some("list") => {
+                        let _ = self.action_tx.send(AppAction::ListWorktrees);
+                    }
+                    Some("new") => {
+                        let name = parts.collect::<Vec<_>>().join("-");
+                        let name = (!name.is_empty()).then_some(name);
+                        let _ = self.action_tx.send(AppAction::CreateWorktree { name });
+                    }
+                    Some(action @ ("merge" | "pr" | "discard")) => {
+                        let _ = self.action_tx.send(AppAction::FinishWorktree {
+                            action: action.to_string(),
+                        });
+                    }
+                    Some(_) => {
+                        self.toasts.push(Toast::warning(tr("toast.usage_worktree")));
+                    }
+                }
+                return;
+            }
+            "profile" => {
+                // Without a name the runner lists the available profiles
+                let name = parts.next().unwrap_or("").to_string();
+                if !name.is_empty() {
+                    self.toasts.push(Toast::info(tr_args(
+                        "toast.switching_profile",
+                        &[("name", &name)],
+                    )));
+                }
+                let _ = self.action_tx.send(AppAction::SwitchProfile { name });
+                return;
+            }
+            "project" | "projects" => {
+                // Without a project the runner lists the projects for the picker
+                let project = parts.collect::<Vec<_>>().join(" ");
+                if project.is_empty() {
+                    let _ = self.action_tx.send(AppAction::ListProjects);
+                } else {
+                    self.toasts.push(Toast::info(tr_args(
+                        "toast.switching_project",
+                        &[("name", &project)],
+                    )));
+                    let _ = self.action_tx.send(AppAction::SwitchProject { project });
+                }
+                return;
+            }
+            "connect" => {
+                // Without an address, browse the local network for servers
+                let address = parts.collect::<Vec<_>>().join(" ");
+                if address.is_empty() {
+                    self.connect_dialog = Some(ConnectDialog::new());
+                    self.dialog = ActiveDialog::Connect;
+                    let _ = self.action_tx.send(AppAction::BrowseServers);
+                } else {
+                    self.connect_to(&address, address.clone(), None);
+                }
+                return;
+            }
+            "template" | "templates" => {
+                // Without a name the runner lists the templates for the picker
+                match (parts.next(), parts.next()) {
+                    (Some("save"), Some(name)) => {
+                        let _ = self.action_tx.send(AppAction::SaveTemplate {
+                            name: name.to_string(),
+                        });
+                    }
+                    (Some("save"), None) => {
+                        self.toasts.push(Toast::warning(tr("toast.usage_template")));
+                    }
+                    (name, _) => {
+                        let name = name.unwrap_or("").to_string();
+                        if !name.is_empty() {
+                            self.toasts
+                                .push(Toast::info(format!("Applying template '{name}'...")));
+                        }
+                        let _ = self.action_tx.send(AppAction::ApplyTemplate { name });
+                    }
+                }
+                return;
+            }
+            "review" => {
+                self.execute_command("review_changes");
+                return;
+            }
+            "memory" => {
+                if Self::get_editor().is_none() {
+                    self.toasts.push(Toast::warning(tr("toast.no_editor")));
+                } else {
+                    let _ = self.action_tx.send(AppAction::LoadMemory);
+                }
+                return;
+            }
             "fork" => {
                 // Fork from current position (no specific message)
                 let _ = self
                     .action_tx
                     .send(AppAction::ForkSession { message_id: None });
-                self.toasts.push(Toast::info("Forking session..."));
+                self.toasts.push(Toast::info(tr("toast.forking_session")));
+                return;
+            }
+            "drive" => {
+                let _ = self.action_tx.send(AppAction::TakeControl);
+                return;
+            }
+            "merge" => {
+                let _ = self.action_tx.send(AppAction::MergeSession);
+                self.toasts.push(Toast::info(tr("toast.merging_branch")));
                 return;
             }
             "thinking" => {
@@ -2953,27 +4978,68 @@ async function fetchUserData(userId) {
                 return;
             }
             "share" => {
-                let _ = self.action_tx.send(AppAction::ShareSession);
-                self.toasts.push(Toast::info("Sharing session..."));
+                // /share [--expires <7d|never>] [--password <password>]
+                let mut expires = None;
+                let mut password = None;
+                while let Some(flag) = parts.next() {
+                    match (flag, parts.next()) {
+                        ("--expires", Some(value)) => expires = Some(value.to_string()),
+                        ("--password", Some(value)) => password = Some(value.to_string()),
+                        _ => {
+                            self.toasts.push(Toast::warning(tr("toast.usage_share")));
+                            return;
+                        }
+                    }
+                }
+                let _ = self
+                    .action_tx
+                    .send(AppAction::ShareSession { expires, password });
+                self.toasts.push(Toast::info(tr("toast.sharing")));
                 return;
             }
             "unshare" => {
                 let _ = self.action_tx.send(AppAction::UnshareSession);
-                self.toasts.push(Toast::info("Unsharing session..."));
+                self.toasts.push(Toast::info(tr("toast.unsharing")));
                 return;
             }
             "status" => {
-                self.update_status_dialog();
-                self.dialog = ActiveDialog::Status;
+                self.show_status_dialog();
+                return;
+            }
+            "spend" => {
+                match parts.next() {
+                    None => {
+                        let _ = self.action_tx.send(AppAction::LoadSpend);
+                    }
+                    Some("ack") => {
+                        let _ = self.action_tx.send(AppAction::AcknowledgeSpendLimit);
+                    }
+                    Some(_) => {
+                        self.toasts.push(Toast::warning(tr("toast.usage_spend")));
+                    }
+                }
+                return;
+            }
+            "usage" => {
+                let days = match parts.next().map(str::parse::<u32>) {
+                    None => 7,
+                    Some(Ok(days)) if days > 0 => days,
+                    Some(_) => {
+                        self.toasts.push(Toast::warning(tr("toast.usage_usage")));
+                        return;
+                    }
+                };
+                self.usage_dialog = Some(UsageDialog::new(days));
+                self.dialog = ActiveDialog::Usage;
+                let _ = self.action_tx.send(AppAction::LoadUsage { days });
                 return;
             }
             "perf" => {
                 if self.render_settings.enable_test_commands {
                     self.show_perf_metrics();
                 } else {
-                    self.toasts.push(Toast::warning(
-                        "Test commands are disabled. Enable in Settings > Performance",
-                    ));
+                    self.toasts
+                        .push(Toast::warning(tr("toast.test_commands_disabled")));
                 }
                 return;
             }
@@ -2981,16 +5047,6 @@ async function fetchUserData(userId) {
                 self.show_mcp_dialog();
                 return;
             }
-            "connect" => {
-                // Show a simple dialog to select provider for connection
-                // For now, just show the model dialog as a starting point
-                self.model_dialog =
-                    ModelDialog::with_options(self.render_settings.test_model_enabled);
-                self.dialog = ActiveDialog::ModelSelect;
-                self.toasts
-                    .push(Toast::info("Select a model to connect to a provider"));
-                return;
-            }
             "sandbox" => {
                 self.show_sandbox_dialog();
                 return;
@@ -3003,12 +5059,22 @@ async function fetchUserData(userId) {
                 if self.render_settings.enable_test_commands {
                     self.add_test_messages();
                 } else {
-                    self.toasts.push(Toast::warning(
-                        "Test commands are disabled. Enable in Settings > Performance",
-                    ));
+                    self.toasts
+                        .push(Toast::warning(tr("toast.test_commands_disabled")));
                 }
                 return;
             }
+            _ if self.custom_commands.contains(command) => {
+                // Custom commands are expanded by the runner
+                let text = format!("/{full_command}");
+                self.messages.commit_revert();
+                self.add_user_message(text.clone());
+                self.set_state(AppState::Waiting);
+                self.footer.set_status(FooterStatus::Thinking);
+                self.messages.start_streaming();
+                let _ = self.action_tx.send(AppAction::SendPrompt(text));
+                return;
+            }
             _ => {
                 self.toasts
                     .push(Toast::warning(format!("Unknown command: /{command}")));
@@ -3024,11 +5090,26 @@ async function fetchUserData(userId) {
             "new_session" => {
                 let _ = self.action_tx.send(AppAction::NewSession);
                 self.messages = MessagesWidget::with_render_settings(self.render_settings.clone());
+                self.file_browser.clear_modified();
+                self.search.clear();
+                self.editing_prompt = None;
                 self.route = Route::Home;
             }
+            "new_tab" => {
+                let _ = self.action_tx.send(AppAction::NewTab);
+                self.toasts.push(Toast::info(tr("toast.opening_tab")));
+            }
+            "close_tab" => {
+                if self.tabs.len() < 2 {
+                    self.toasts.push(Toast::info(tr("toast.last_tab")));
+                } else {
+                    let _ = self.action_tx.send(AppAction::CloseTab(self.active_tab));
+                }
+            }
             "session_list" => {
                 self.session_dialog = Some(SessionDialog::new(self.sessions.clone()));
                 self.dialog = ActiveDialog::SessionList;
+                self.pending_search = None;
             }
             "model_select" => {
                 self.model_dialog =
@@ -3042,8 +5123,41 @@ async function fetchUserData(userId) {
             "toggle_sidebar" => {
                 self.sidebar.toggle();
             }
+            "toggle_preview" => {
+                self.unpin_preview();
+                self.preview.toggle();
+            }
+            "toggle_file_browser" => {
+                if self.file_browser.is_visible() && !self.file_browser.is_focused() {
+                    self.focus_file_browser();
+                } else {
+                    self.file_browser.toggle();
+                    if self.file_browser.is_visible() {
+                        self.focus_file_browser();
+                    } else {
+                        self.input.set_focused(self.state != AppState::Scrolling);
+                    }
+                }
+            }
+            "toggle_terminal" => {
+                if self.terminal.is_visible() && !self.terminal.is_focused() {
+                    self.focus_terminal();
+                } else {
+                    self.terminal.toggle();
+                    if self.terminal.is_visible() {
+                        self.focus_terminal();
+                    } else {
+                        self.input.set_focused(self.state != AppState::Scrolling);
+                    }
+                }
+            }
+            "preview_mode" => {
+                self.preview.set_visible(true);
+                self.preview.toggle_mode();
+            }
             "theme_select" => {
-                self.theme_dialog = ThemeDialog::new();
+                self.theme_dialog = ThemeDialog::with_registry(&self.themes);
+                self.theme_before_preview = Some(self.theme.clone());
                 self.dialog = ActiveDialog::ThemeSelect;
             }
             "copy_last" => {
@@ -3052,6 +5166,10 @@ async function fetchUserData(userId) {
             "edit_input" => {
                 self.edit_input_in_editor();
             }
+            "review_changes" => {
+                self.review_requested = true;
+                let _ = self.action_tx.send(AppAction::StartReview);
+            }
             "undo" => {
                 self.undo_message();
             }
@@ -3061,10 +5179,10 @@ async function fetchUserData(userId) {
             "clear_history" => {
                 self.messages = MessagesWidget::with_render_settings(self.render_settings.clone());
                 self.route = Route::Home;
-                self.toasts.push(Toast::info("History cleared"));
+                self.toasts.push(Toast::info(tr("toast.history_cleared")));
             }
             "export_session" => {
-                self.export_session();
+                self.show_export_dialog();
             }
             "sandbox" => {
                 self.show_sandbox_dialog();
@@ -3087,6 +5205,10 @@ async function fetchUserData(userId) {
     fn handle_update(&mut self, update: AppUpdate) {
         match update {
             AppUpdate::Started => {
+                self.turn = Some(Turn {
+                    started: std::time::Instant::now(),
+                    usage: None,
+                });
                 self.footer.set_status(FooterStatus::Thinking);
                 self.messages.start_streaming();
             }
@@ -3102,6 +5224,17 @@ async function fetchUserData(userId) {
                 self.messages
                     .add_tool_call_with_input(id, display_name, input);
             }
+            AppUpdate::ToolProgress {
+                id,
+                fraction,
+                message,
+            } => {
+                self.messages
+                    .set_tool_progress(&id, ToolProgress { fraction, message });
+            }
+            AppUpdate::ToolOutput { id, text } => {
+                self.messages.append_tool_output(&id, &text);
+            }
             AppUpdate::ToolCompleted {
                 id,
                 success,
@@ -3121,23 +5254,40 @@ async function fetchUserData(userId) {
                     self.footer.set_status(FooterStatus::Thinking);
                 }
             }
-            AppUpdate::Completed { text: _ } => {
+            AppUpdate::Completed { text } => {
+                let summary = text
+                    .lines()
+                    .find(|l| !l.trim().is_empty())
+                    .unwrap_or("The agent finished");
+                self.notifier.notify("wonopcode: done", summary);
+
                 // Use atomic end_streaming_and_add_message to avoid flicker
                 let mut msg = DisplayMessage::assistant("");
                 msg.agent = self.current_agent();
                 msg.model = Some(format!("{}/{}", self.provider, self.model));
+                if let Some(turn) = self.turn.take() {
+                    msg.duration = Some(format_turn_duration(turn.started.elapsed()));
+                    msg.usage = turn.usage;
+                }
                 self.messages.end_streaming_and_add_message(msg);
 
                 self.set_state(AppState::Input);
                 self.footer.set_status(FooterStatus::Idle);
                 self.input.set_focused(true);
+
+                if self.auto_review {
+                    self.review_requested = false;
+                    let _ = self.action_tx.send(AppAction::StartReview);
+                }
             }
             AppUpdate::Error(err) => {
+                self.notifier.notify("wonopcode: error", &err);
                 let _ = self.messages.end_streaming_legacy();
                 self.set_state(AppState::Input);
                 self.footer.set_status(FooterStatus::Error(err.clone()));
                 self.input.set_focused(true);
-                self.toasts.push(Toast::error("Error").with_message(err));
+                self.toasts
+                    .push(Toast::error(tr("toast.error")).with_message(err));
             }
             AppUpdate::Status(status) => {
                 self.footer.set_status(FooterStatus::Running(status));
@@ -3148,6 +5298,13 @@ async function fetchUserData(userId) {
                 cost,
                 context_limit,
             } => {
+                if let Some(turn) = &mut self.turn {
+                    turn.usage = Some(MessageUsage {
+                        input_tokens: input,
+                        output_tokens: output,
+                        cost,
+                    });
+                }
                 self.footer.set_tokens(input, output);
                 self.sidebar.update_tokens(input, output);
                 self.sidebar.set_cost(cost);
@@ -3194,6 +5351,9 @@ async function fetchUserData(userId) {
                     .collect();
                 self.sidebar.set_todos(sidebar_todos);
             }
+            AppUpdate::Symbols { query, symbols } => {
+                self.autocomplete.set_symbols(&query, symbols);
+            }
             AppUpdate::LspUpdated(servers) => {
                 use crate::widgets::sidebar::LspServerStatus;
                 let lsp_statuses: Vec<LspStatus> = servers
@@ -3247,12 +5407,92 @@ async function fetchUserData(userId) {
             AppUpdate::ModifiedFilesUpdated(files) => {
                 // Merge incremental updates instead of replacing
                 for f in files {
+                    self.file_browser.add_modified(&f.path);
                     self.sidebar.add_modified_file(f.path, f.added, f.removed);
                 }
+                // Pick up files the agent created
+                if self.file_browser.is_visible() {
+                    self.file_browser.refresh();
+                }
             }
             AppUpdate::PermissionsPending(count) => {
                 self.footer.set_pending_permissions(count);
             }
+            AppUpdate::StorageUsage {
+                total_bytes,
+                quota_bytes,
+                warnings,
+            } => {
+                self.status_dialog.storage_bytes = total_bytes;
+                self.status_dialog.storage_quota = quota_bytes;
+                self.status_dialog.storage_over_quota = !warnings.is_empty();
+                for warning in warnings {
+                    self.toasts.push(Toast::warning(warning));
+                }
+            }
+            AppUpdate::Usage {
+                days,
+                daily,
+                models,
+                tools,
+            } => {
+                if let Some(dialog) = &mut self.usage_dialog {
+                    dialog.set_usage(days, daily, models, tools);
+                }
+            }
+            AppUpdate::PermissionAudit(entries) => {
+                let entries = entries
+                    .into_iter()
+                    .map(|e| PermissionLogEntry {
+                        time: e.time,
+                        tool: e.tool,
+                        target: e.path.or(e.action),
+                        allowed: e.allowed,
+                        source: e.source,
+                    })
+                    .collect();
+                self.status_dialog.set_permission_log(entries);
+            }
+            AppUpdate::Instructions(files) => {
+                self.status_dialog.instruction_files = files
+                    .into_iter()
+                    .map(|f| InstructionFileEntry {
+                        path: f.path,
+                        tokens: f.tokens,
+                        included: f.included,
+                        note: f.note,
+                    })
+                    .collect();
+            }
+            AppUpdate::ConfigReloaded {
+                theme,
+                applied,
+                restart_required,
+            } => {
+                if let Some(theme) = theme {
+                    self.set_theme(&theme);
+                }
+                if !applied.is_empty() {
+                    self.toasts.push(Toast::info(tr_args(
+                        "toast.config_reloaded",
+                        &[("settings", &applied.join(", "))],
+                    )));
+                }
+                if !restart_required.is_empty() {
+                    self.toasts.push(Toast::warning(tr_args(
+                        "toast.restart_to_apply",
+                        &[("settings", &restart_required.join(", "))],
+                    )));
+                }
+            }
+            AppUpdate::CustomCommands(commands) => {
+                for command in commands {
+                    if self.custom_commands.insert(command.name.clone()) {
+                        self.slash_autocomplete
+                            .add_command(SlashCommand::new(command.name, command.description));
+                    }
+                }
+            }
             AppUpdate::SandboxUpdated(status) => {
                 use crate::widgets::footer::SandboxDisplayState;
                 let state = match status.state.as_str() {
@@ -3264,11 +5504,15 @@ async function fetchUserData(userId) {
                     _ => SandboxDisplayState::Disabled,
                 };
                 self.footer.set_sandbox_status(state, status.runtime_type);
+                if state != SandboxDisplayState::Running {
+                    self.sidebar.set_sandbox_usage(None);
+                }
 
                 // Show toast for state changes
                 match state {
                     SandboxDisplayState::Running => {
-                        self.toasts.push(Toast::success("Sandbox is running"));
+                        self.toasts
+                            .push(Toast::success(tr("toast.sandbox_running")));
                     }
                     SandboxDisplayState::Error => {
                         let msg = status.error.unwrap_or_else(|| "Unknown error".to_string());
@@ -3278,6 +5522,17 @@ async function fetchUserData(userId) {
                     _ => {}
                 }
             }
+            AppUpdate::SandboxResources(usage) => {
+                self.sidebar.set_sandbox_usage(Some(SandboxUsage {
+                    cpu_percent: usage.cpu_percent,
+                    memory_bytes: usage.memory_bytes,
+                    memory_limit: usage.memory_limit,
+                    pids: usage.pids,
+                }));
+            }
+            AppUpdate::UserMessage(text) => {
+                self.add_user_message(text);
+            }
             AppUpdate::SystemMessage(msg) => {
                 use crate::widgets::messages::DisplayMessage;
                 self.messages.add_message(DisplayMessage::system(msg));
@@ -3293,7 +5548,180 @@ async function fetchUserData(userId) {
                 self.toasts
                     .push(Toast::info(format!("Switched to {mode_name}")));
             }
+            AppUpdate::BudgetWarning { message, .. } | AppUpdate::SpendAlert { message, .. } => {
+                self.toasts.push(Toast::warning(message));
+            }
+            AppUpdate::SessionSearchResults { query, results } => {
+                if results.is_empty() {
+                    self.toasts.push(Toast::info(tr_args(
+                        "toast.no_sessions_match",
+                        &[("query", &query)],
+                    )));
+                } else {
+                    self.session_dialog = Some(SessionDialog::with_title(
+                        tr_args("dialog.sessions_matching", &[("query", &query)]),
+                        results,
+                    ));
+                    self.dialog = ActiveDialog::SessionList;
+                    self.pending_search = Some(query);
+                }
+            }
+            AppUpdate::Orchestration { id, agents } => {
+                let agents: Vec<CoordinatorAgent> = agents
+                    .into_iter()
+                    .map(|a| CoordinatorAgent {
+                        name: a.name,
+                        task: a.task,
+                        state: a.state,
+                        branch: a.branch,
+                        steps: a.steps,
+                        tool_calls: a.tool_calls,
+                        changed_files: a.changed_files,
+                        summary: a.summary,
+                    })
+                    .collect();
+                match &mut self.coordinator_dialog {
+                    Some(dialog) if dialog.id() == id => {
+                        let was_finished = dialog.all_finished();
+                        dialog.set_agents(agents);
+                        if !was_finished && dialog.all_finished() && dialog.has_unmerged() {
+                            self.toasts
+                                .push(Toast::success(tr("toast.agents_done_merge")));
+                        }
+                    }
+                    _ => {
+                        self.coordinator_dialog = Some(CoordinatorDialog::new(id, agents));
+                        if self.dialog == ActiveDialog::None {
+                            self.dialog = ActiveDialog::Coordinator;
+                        }
+                    }
+                }
+            }
+            AppUpdate::Templates(templates) => {
+                if templates.is_empty() {
+                    self.toasts.push(Toast::info(tr("toast.no_templates")));
+                } else {
+                    self.template_dialog = Some(TemplateDialog::new(templates));
+                    self.dialog = ActiveDialog::TemplateSelect;
+                }
+            }
+            AppUpdate::Projects(projects) => {
+                if projects.len() <= 1 {
+                    self.toasts.push(Toast::info(tr("toast.one_project")));
+                } else {
+                    let items = projects
+                        .into_iter()
+                        .map(|p| {
+                            let state = if p.active {
+                                "active"
+                            } else if p.loaded {
+                                "loaded"
+                            } else {
+                                "not started"
+                            };
+                            (p.path.clone(), p.name, format!("{} ({state})", p.path))
+                        })
+                        .collect();
+                    self.project_dialog = Some(ProjectDialog::new(items));
+                    self.dialog = ActiveDialog::ProjectSelect;
+                }
+            }
+            AppUpdate::ProjectSwitched { path, name } => {
+                self.messages = MessagesWidget::with_render_settings(self.render_settings.clone());
+                self.route = Route::Home;
+                self.set_project(path);
+                self.toasts
+                    .push(Toast::success(format!("Switched to project {name}")));
+            }
+            AppUpdate::ServerFound(server) => {
+                if let Some(dialog) = &mut self.connect_dialog {
+                    dialog.upsert(server);
+                }
+            }
+            AppUpdate::ServerLost(name) => {
+                if let Some(dialog) = &mut self.connect_dialog {
+                    dialog.remove(&name);
+                }
+            }
+            AppUpdate::ServerDiscoveryFailed(error) => {
+                if self.connect_dialog.take().is_some() {
+                    self.dialog = ActiveDialog::None;
+                }
+                self.toasts.push(Toast::error(tr_args(
+                    "toast.discovery_failed",
+                    &[("error", &error)],
+                )));
+            }
+            AppUpdate::ServerConnected {
+                address,
+                project,
+                model,
+                agent,
+                remote_files,
+            } => {
+                // Start over with the server's session, in a single tab
+                self.messages = MessagesWidget::with_render_settings(self.render_settings.clone());
+                self.route = Route::Home;
+                self.tabs = vec![SessionTab {
+                    busy: false,
+                    unseen: false,
+                    view: None,
+                }];
+                self.active_tab = 0;
+                self.turn = None;
+                if self.state == AppState::Waiting {
+                    self.set_state(AppState::Input);
+                    self.input.set_focused(true);
+                }
+                self.footer.set_status(FooterStatus::Idle);
+                self.set_project(project);
+                self.set_model(model);
+                self.set_agent(agent);
+                self.set_remote_files(remote_files);
+                self.toasts.push(Toast::success(tr_args(
+                    "toast.connected",
+                    &[("address", &address)],
+                )));
+            }
+            AppUpdate::ServerConnectFailed { address, error } => {
+                self.toasts.push(Toast::error(tr_args(
+                    "toast.connect_failed",
+                    &[("address", &address), ("error", &error)],
+                )));
+            }
+            AppUpdate::TabSwitched { index, count } => self.switch_tab(index, count),
+            AppUpdate::TabClosed { index } => {
+                if index != self.active_tab && index < self.tabs.len() {
+                    self.tabs.remove(index);
+                    if index < self.active_tab {
+                        self.active_tab -= 1;
+                    }
+                }
+            }
+            AppUpdate::TabActivity { index, busy } => {
+                if let Some(tab) = self.tabs.get_mut(index) {
+                    tab.busy = busy;
+                    if !busy {
+                        tab.unseen = true;
+                        self.toasts.push(Toast::info(tr_args(
+                            "toast.tab_finished",
+                            &[("number", &(index + 1))],
+                        )));
+                    }
+                }
+            }
+            AppUpdate::Memory(content) => {
+                if let Some(edited) = Self::open_in_editor(&content, self.mouse_enabled) {
+                    if edited != content {
+                        let _ = self
+                            .action_tx
+                            .send(AppAction::SaveMemory { content: edited });
+                    }
+                }
+            }
             AppUpdate::PermissionRequest(req) => {
+                self.notifier
+                    .notify("wonopcode: permission needed", &req.description);
                 // If a permission dialog is already showing, queue this request
                 if self.permission_dialog.is_some() {
                     self.permission_queue.push_back(req);
@@ -3321,9 +5749,16 @@ async function fetchUserData(userId) {
                 tracing::info!(session_id = %id, title = %title, message_count = messages.len(), "Loading session");
                 self.messages.set_messages(messages);
                 self.session_title = title;
+                self.editing_prompt = None;
+                self.clear_search();
                 // Move to session view if we have messages
                 if self.messages.message_count() > 0 {
                     self.route = Route::Session;
+                    // Show where the session picked from search results matched
+                    if let Some(query) = self.pending_search.take() {
+                        self.start_search(&query);
+                        self.set_state(AppState::Scrolling);
+                    }
                 }
             }
             AppUpdate::GitStatusUpdated(status) => {
@@ -3354,6 +5789,21 @@ async function fetchUserData(userId) {
                     dialog.set_history(commits);
                 }
             }
+            AppUpdate::Review(diff) => self.show_review(&diff),
+            AppUpdate::ProcessStarted { pid, command } => {
+                self.terminal.start(pid, command);
+                self.terminal.set_visible(true);
+            }
+            AppUpdate::ProcessOutput { pid, text } => self.terminal.append(pid, &text),
+            AppUpdate::ProcessExited { pid, exit_code } => {
+                self.terminal.exit(pid, exit_code);
+                if let Some(code) = exit_code.filter(|&code| code != 0) {
+                    self.toasts.push(Toast::warning(tr_args(
+                        "toast.process_exited",
+                        &[("pid", &pid), ("code", &code)],
+                    )));
+                }
+            }
             AppUpdate::GitOperationResult { success, message } => {
                 if success {
                     self.toasts.push(Toast::success(&message));
@@ -3363,9 +5813,69 @@ async function fetchUserData(userId) {
                     self.toasts.push(Toast::error(&message));
                 }
                 if let Some(dialog) = &mut self.git_dialog {
+                    dialog.set_loading(false);
                     dialog.set_message(&message);
                 }
             }
+            AppUpdate::GitDiffUpdated { path, staged, diff } => {
+                if let Some(dialog) = &mut self.git_dialog {
+                    dialog.set_diff(&path, staged, &diff);
+                }
+            }
+            AppUpdate::GitCommitMessageGenerated(message) => {
+                if let Some(dialog) = &mut self.git_dialog {
+                    dialog.set_commit_message(message);
+                }
+            }
+            AppUpdate::FilesListed { dir, entries } => {
+                self.file_browser.set_listing(&dir, entries);
+            }
+            AppUpdate::FileRead { path, content } => {
+                let requested = self.preview_requested.as_deref().map(|p| {
+                    let p = std::path::Path::new(p);
+                    p.strip_prefix(&self.directory).unwrap_or(p).to_path_buf()
+                });
+                if requested.as_deref() == Some(std::path::Path::new(&path)) {
+                    self.preview.set_file(content);
+                }
+            }
+            AppUpdate::Connection(status) => match status {
+                ConnectionStatus::Connected => self.footer.set_connected(true),
+                ConnectionStatus::Reconnecting { attempt } => {
+                    self.footer.set_reconnecting(attempt);
+                }
+            },
+            AppUpdate::Resync => {
+                // The response being streamed is in the server's state if it
+                // finished, or is streamed again from the start if not
+                let _ = self.messages.end_streaming_legacy();
+                self.turn = None;
+                if self.state == AppState::Waiting {
+                    self.set_state(AppState::Input);
+                    self.input.set_focused(true);
+                }
+                self.footer.set_status(FooterStatus::Idle);
+            }
+            AppUpdate::Presence(participants) => {
+                let watching = participants.iter().any(|p| p.you && !p.driver);
+                if watching && !self.watching {
+                    if let Some(driver) = participants.iter().find(|p| p.driver) {
+                        self.toasts.push(Toast::info(tr_args(
+                            "toast.took_control",
+                            &[("name", &driver.name)],
+                        )));
+                    }
+                } else if self.watching && !watching {
+                    self.toasts.push(Toast::success(tr("toast.driving")));
+                }
+                self.watching = watching;
+                self.footer.set_presence(participants.len(), watching);
+            }
+            AppUpdate::Prompted { name, prompt } => {
+                self.toasts
+                    .push(Toast::info(tr_args("toast.prompted", &[("name", &name)])));
+                self.add_user_message(prompt);
+            }
         }
     }
 
@@ -3400,12 +5910,67 @@ impl Default for App {
     }
 }
 
+/// Directories with user theme files: the global one, then the project's.
+fn theme_dirs(project: Option<&std::path::Path>) -> Vec<std::path::PathBuf> {
+    let mut dirs: Vec<std::path::PathBuf> = wonopcode_core::config::Config::global_config_dir()
+        .map(|dir| dir.join("themes"))
+        .into_iter()
+        .collect();
+    if let Some(project) = project {
+        dirs.push(project.join(".wonopcode").join("themes"));
+    }
+    dirs
+}
+
 /// Helper to create a horizontally centered rectangle.
+/// The image file a paste is the path of, such as a file dropped on the
+/// terminal, which pastes its path, possibly quoted or escaped.
+fn pasted_image_path(text: &str) -> Option<std::path::PathBuf> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('\'')
+        .and_then(|t| t.strip_suffix('\''))
+        .or_else(|| text.strip_prefix('"').and_then(|t| t.strip_suffix('"')))
+        .unwrap_or(text);
+    let text = text.strip_prefix("file://").unwrap_or(text);
+    if text.is_empty() || text.contains('\n') {
+        return None;
+    }
+    let path = std::path::PathBuf::from(text.replace("\\ ", " "));
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    (matches!(extension.as_str(), "png" | "jpg" | "jpeg" | "gif" | "webp") && path.is_file())
+        .then_some(path)
+}
+
 fn centered_horizontal(width: u16, area: Rect) -> Rect {
     let x = area.x + (area.width.saturating_sub(width)) / 2;
     Rect::new(x, area.y, width.min(area.width), area.height)
 }
 
+/// Format how long a prompt took, such as `4.2s` or `2m 5s`.
+fn format_turn_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
+
+/// Title of a tab: its session's, or the first line of its first prompt.
+fn tab_title(session_title: &str, messages: &MessagesWidget) -> String {
+    if !session_title.is_empty() {
+        return session_title.to_string();
+    }
+    messages
+        .get_messages()
+        .iter()
+        .find(|m| m.role == MessageRole::User)
+        .and_then(|m| m.content.lines().map(str::trim).find(|l| !l.is_empty()))
+        .unwrap_or_default()
+        .to_string()
+}
+
 /// Normalize MCP tool names to their base tool name.
 /// e.g., "mcp__wonopcode-tools__bash" -> "bash"
 fn normalize_tool_name(name: &str) -> String {
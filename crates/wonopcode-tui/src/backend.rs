@@ -1,11 +1,22 @@
 //! Backend abstraction for TUI communication.
 //!
 //! This module provides a trait for backend communication, allowing the TUI
-//! to work with either a local runner (direct channels) or a remote server (HTTP/SSE).
+//! to work with either a local runner (direct channels) or a remote server
+//! (HTTP/SSE or WebSocket).
 
-use crate::{AppAction, AppUpdate, GitCommitUpdate, GitFileUpdate, GitStatusUpdate};
+use crate::widgets::file_browser::ListedFile;
+use crate::{
+    AppAction, AppUpdate, ConnectionStatus, GitCommitUpdate, GitFileUpdate, GitStatusUpdate,
+};
 use async_trait::async_trait;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 use tokio::sync::mpsc;
+use wonopcode_protocol::{
+    Capability, ClientFrame, Handshake, Negotiated, HANDSHAKE_ENDPOINT, PARTICIPANT_HEADER,
+};
 
 /// Error type for backend operations.
 #[derive(Debug, thiserror::Error)]
@@ -21,6 +32,9 @@ pub enum BackendError {
 
     #[error("Serialization error: {0}")]
     SerializationError(String),
+
+    #[error("Incompatible server: {0}")]
+    Incompatible(String),
 }
 
 /// Result type for backend operations.
@@ -72,16 +86,41 @@ impl Backend for LocalBackend {
     }
 }
 
-/// Remote backend using HTTP for actions and SSE for updates.
+/// Shortest wait before reconnecting the update stream.
+const RECONNECT_DELAY_MIN: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Longest wait before reconnecting the update stream.
+const RECONNECT_DELAY_MAX: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long the SSE stream may go without even a keep-alive, which the
+/// server sends every 15 seconds, before it is taken for dead.
+const SSE_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(45);
+
+/// Status shown when updates were missed and the state couldn't be fetched.
+const MISSED_UPDATES: &str = "Reconnected, but some updates were missed";
+
+/// Remote backend using HTTP for actions and SSE for updates, or a WebSocket
+/// for both.
 ///
 /// This is used when connecting to a remote headless agent server.
 pub struct RemoteBackend {
     client: reqwest::Client,
+    /// Client for the SSE stream, which has no overall timeout.
+    stream_client: reqwest::Client,
     base_url: String,
     api_key: Option<String>,
-    connected: std::sync::atomic::AtomicBool,
+    connected: Arc<AtomicBool>,
     /// Sender for updates (used for git operations that need to send updates back).
     update_tx: Option<mpsc::UnboundedSender<AppUpdate>>,
+    /// Sender for actions over the WebSocket, once it is opened.
+    socket_tx: Option<mpsc::UnboundedSender<ClientFrame>>,
+    /// TLS connector for `wss://` trusting a custom CA, if one was given.
+    tls: Option<native_tls::TlsConnector>,
+    /// What the client and server have in common, once connected.
+    negotiated: std::sync::Mutex<Option<Negotiated>>,
+    /// This client's participant ID in a shared session, sent with its HTTP
+    /// requests so the server accepts them while it drives.
+    participant: Arc<std::sync::Mutex<Option<String>>>,
 }
 
 impl RemoteBackend {
@@ -98,35 +137,85 @@ impl RemoteBackend {
             format!("http://{address}")
         };
 
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(30))
+        let client = Self::client_builder()
+            .build()
+            .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
+        let stream_client = Self::stream_client_builder()
             .build()
             .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
 
         Ok(Self {
             client,
+            stream_client,
             base_url,
             api_key,
-            connected: std::sync::atomic::AtomicBool::new(false),
+            connected: Arc::new(AtomicBool::new(false)),
             update_tx: None,
+            socket_tx: None,
+            tls: None,
+            negotiated: std::sync::Mutex::new(None),
+            participant: Arc::default(),
         })
     }
 
+    fn client_builder() -> reqwest::ClientBuilder {
+        reqwest::Client::builder().timeout(std::time::Duration::from_secs(30))
+    }
+
+    /// The SSE stream stays open, so it only times out connecting; a dead
+    /// stream is noticed by its missing keep-alives instead.
+    fn stream_client_builder() -> reqwest::ClientBuilder {
+        reqwest::Client::builder().connect_timeout(std::time::Duration::from_secs(10))
+    }
+
+    /// Trust the CA certificate in `pem` for `https://` servers, in addition
+    /// to the system's, e.g. the self-signed certificate of a server.
+    pub fn with_ca_certificate(mut self, pem: &[u8]) -> BackendResult<Self> {
+        let certificate = reqwest::Certificate::from_pem(pem).map_err(invalid_ca_certificate)?;
+        self.client = Self::client_builder()
+            .add_root_certificate(certificate.clone())
+            .build()
+            .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
+        self.stream_client = Self::stream_client_builder()
+            .add_root_certificate(certificate)
+            .build()
+            .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
+        let ca = native_tls::Certificate::from_pem(pem).map_err(invalid_ca_certificate)?;
+        self.tls = Some(
+            native_tls::TlsConnector::builder()
+                .add_root_certificate(ca)
+                .build()
+                .map_err(invalid_ca_certificate)?,
+        );
+        Ok(self)
+    }
+
     /// Get the base URL.
     pub fn base_url(&self) -> &str {
         &self.base_url
     }
 
-    /// Add API key header to a request if configured.
+    /// Add API key header to a request if configured, and the participant
+    /// header in a shared session.
     fn add_auth(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
-        if let Some(ref key) = self.api_key {
+        let request = if let Some(ref key) = self.api_key {
             request.header("X-API-Key", key)
         } else {
             request
+        };
+        match self
+            .participant
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_deref()
+        {
+            Some(id) => request.header(PARTICIPANT_HEADER, id),
+            None => request,
         }
     }
 
-    /// Check server health and mark as connected.
+    /// Check server health, negotiate the protocol version and capabilities,
+    /// and mark as connected.
     pub async fn connect(&self) -> BackendResult<()> {
         let url = format!("{}/health", self.base_url);
         self.client
@@ -137,73 +226,144 @@ impl RemoteBackend {
             .error_for_status()
             .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
 
-        self.connected
-            .store(true, std::sync::atomic::Ordering::SeqCst);
+        let negotiated = self.handshake().await?;
+        tracing::debug!(
+            version = negotiated.version,
+            capabilities = ?negotiated.capabilities,
+            "Negotiated protocol with server"
+        );
+        *self.negotiated.lock().unwrap_or_else(|e| e.into_inner()) = Some(negotiated);
+        self.connected.store(true, Ordering::SeqCst);
         Ok(())
     }
 
-    /// Get the full state from the server.
-    pub async fn get_state(&self) -> BackendResult<wonopcode_protocol::State> {
-        let url = format!("{}/state", self.base_url);
+    /// Post this client's handshake to the server.
+    async fn handshake(&self) -> BackendResult<Negotiated> {
+        let url = format!("{}{HANDSHAKE_ENDPOINT}", self.base_url);
+        let client = Handshake::new(Capability::ALL.to_vec());
         let resp = self
-            .add_auth(self.client.get(&url))
+            .add_auth(self.client.post(&url))
+            .json(&client)
             .send()
             .await
-            .map_err(|e| BackendError::RequestFailed(e.to_string()))?
-            .error_for_status()
-            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+            .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
 
-        resp.json()
-            .await
-            .map_err(|e| BackendError::SerializationError(e.to_string()))
+        match resp.status() {
+            status if status.is_success() => resp
+                .json()
+                .await
+                .map_err(|e| BackendError::SerializationError(e.to_string())),
+            reqwest::StatusCode::NOT_FOUND => Err(BackendError::Incompatible(
+                "the server predates protocol versions; upgrade the server".to_string(),
+            )),
+            reqwest::StatusCode::UPGRADE_REQUIRED => {
+                let body = resp.text().await.unwrap_or_default();
+                Err(refused_handshake(&client, &body))
+            }
+            _ => Err(BackendError::ConnectionFailed(error_message(resp).await)),
+        }
+    }
+
+    /// Whether the server has a capability. False until connected.
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.negotiated
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_some_and(|negotiated| negotiated.supports(capability))
+    }
+
+    /// Where to fetch the server's state from, for background tasks.
+    fn state_source(&self) -> StateSource {
+        StateSource {
+            client: self.client.clone(),
+            url: format!("{}/state", self.base_url),
+            api_key: self.api_key.clone(),
+        }
+    }
+
+    /// Get the full state from the server.
+    pub async fn get_state(&self) -> BackendResult<wonopcode_protocol::State> {
+        self.state_source().fetch().await
     }
 
     /// Subscribe to SSE events and forward them to the given sender.
     ///
     /// This spawns a background task that reads SSE events and sends them
-    /// as AppUpdate messages.
+    /// as AppUpdate messages. When the stream drops or goes quiet, it
+    /// reconnects with backoff and the ID of the last event received, so the
+    /// server replays the missed ones. If it can't, the App is brought back
+    /// in line with the server's state. The connection status is sent as
+    /// [`AppUpdate::Connection`] while reconnecting.
     pub fn subscribe_updates(
         &self,
         update_tx: mpsc::UnboundedSender<AppUpdate>,
     ) -> tokio::task::JoinHandle<()> {
         let url = format!("{}/events", self.base_url);
-        let client = self.client.clone();
+        let client = self.stream_client.clone();
         let api_key = self.api_key.clone();
+        let connected = self.connected.clone();
+        let state = self.state_source();
 
         tokio::spawn(async move {
             use futures::StreamExt;
 
+            let mut last_id: Option<u64> = None;
+            let mut delay = RECONNECT_DELAY_MIN;
+            let mut attempt = 0;
             loop {
                 let mut request = client.get(&url);
                 if let Some(ref key) = api_key {
                     request = request.header("X-API-Key", key);
                 }
+                if let Some(id) = last_id {
+                    request = request.header("Last-Event-ID", id.to_string());
+                }
 
-                match request.send().await {
+                match request.send().await.and_then(|r| r.error_for_status()) {
                     Ok(response) => {
+                        connected.store(true, Ordering::SeqCst);
+                        if attempt > 0 {
+                            let _ =
+                                update_tx.send(AppUpdate::Connection(ConnectionStatus::Connected));
+                            attempt = 0;
+                        }
+                        delay = RECONNECT_DELAY_MIN;
+
                         let mut stream = response.bytes_stream();
                         let mut buffer = String::new();
 
-                        while let Some(chunk) = stream.next().await {
-                            match chunk {
-                                Ok(bytes) => {
-                                    buffer.push_str(&String::from_utf8_lossy(&bytes));
-
-                                    // Process complete SSE events
-                                    while let Some(pos) = buffer.find("\n\n") {
-                                        let event_str = buffer[..pos].to_string();
-                                        buffer = buffer[pos + 2..].to_string();
-
-                                        if let Some(update) = parse_sse_event(&event_str) {
-                                            if update_tx.send(update).is_err() {
-                                                return; // Channel closed
-                                            }
-                                        }
+                        loop {
+                            let bytes =
+                                match tokio::time::timeout(SSE_IDLE_TIMEOUT, stream.next()).await {
+                                    Ok(Some(Ok(bytes))) => bytes,
+                                    Ok(Some(Err(e))) => {
+                                        tracing::warn!("SSE stream error: {}", e);
+                                        break;
+                                    }
+                                    Ok(None) => break,
+                                    Err(_) => {
+                                        tracing::warn!("SSE stream went quiet");
+                                        break;
+                                    }
+                                };
+                            buffer.push_str(&String::from_utf8_lossy(&bytes));
+
+                            // Process complete SSE events
+                            while let Some(pos) = buffer.find("\n\n") {
+                                let event_str = buffer[..pos].to_string();
+                                buffer = buffer[pos + 2..].to_string();
+
+                                let event = parse_sse_event(&event_str);
+                                let updates = match event.resume(&mut last_id) {
+                                    Some(AppUpdate::Resync) => state.resync().await,
+                                    Some(update) => vec![update],
+                                    None => Vec::new(),
+                                };
+                                for update in updates {
+                                    if update_tx.send(update).is_err() {
+                                        return; // Channel closed
                                     }
-                                }
-                                Err(e) => {
-                                    tracing::warn!("SSE stream error: {}", e);
-                                    break;
                                 }
                             }
                         }
@@ -213,14 +373,92 @@ impl RemoteBackend {
                     }
                 }
 
-                // Reconnect after a delay
-                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                connected.store(false, Ordering::SeqCst);
+                attempt += 1;
+                let reconnecting = ConnectionStatus::Reconnecting { attempt };
+                if update_tx.send(AppUpdate::Connection(reconnecting)).is_err() {
+                    return;
+                }
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(RECONNECT_DELAY_MAX);
+            }
+        })
+    }
+
+    /// Open a WebSocket for actions and updates, forwarding updates to the
+    /// given sender.
+    ///
+    /// This spawns a background task that keeps the socket open, reconnecting
+    /// and resuming after the last update received when the connection drops.
+    /// Actions sent while it is down are queued and sent on reconnect.
+    pub fn open_socket(
+        &mut self,
+        update_tx: mpsc::UnboundedSender<AppUpdate>,
+    ) -> tokio::task::JoinHandle<()> {
+        let (frame_tx, frame_rx) = mpsc::unbounded_channel();
+        self.socket_tx = Some(frame_tx);
+
+        let url = socket_url(&self.base_url);
+        let api_key = self.api_key.clone();
+        let tls = self.tls.clone();
+        let connected = self.connected.clone();
+        let state = self.state_source();
+        let joined = self.participant.clone();
+        tokio::spawn(async move {
+            let mut session = SocketSession {
+                update_tx,
+                frame_rx,
+                state,
+                last_seq: None,
+                participant: None,
+                joined,
+                unsent: None,
+                attempt: 0,
+            };
+            let mut delay = RECONNECT_DELAY_MIN;
+
+            loop {
+                match connect_socket(&url, api_key.as_deref(), tls.as_ref()).await {
+                    Ok(socket) => {
+                        let end = session.run(socket, &connected).await;
+                        connected.store(false, Ordering::SeqCst);
+                        match end {
+                            SocketEnd::Closed => return,
+                            SocketEnd::Resumed => delay = RECONNECT_DELAY_MIN,
+                            SocketEnd::Failed => {}
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to connect to WebSocket: {}", e);
+                    }
+                }
+
+                session.attempt += 1;
+                let reconnecting = ConnectionStatus::Reconnecting {
+                    attempt: session.attempt,
+                };
+                if session
+                    .update_tx
+                    .send(AppUpdate::Connection(reconnecting))
+                    .is_err()
+                {
+                    return;
+                }
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(RECONNECT_DELAY_MAX);
             }
         })
     }
 
-    /// Convert AppAction to protocol Action and send via HTTP.
+    /// Convert AppAction to protocol Action and send via the WebSocket if
+    /// one is open, HTTP otherwise.
     async fn send_protocol_action(&self, action: wonopcode_protocol::Action) -> BackendResult<()> {
+        if let Some(ref socket_tx) = self.socket_tx {
+            return socket_tx
+                .send(ClientFrame::Action { action })
+                .map_err(|_| BackendError::ChannelClosed);
+        }
+
         let endpoint = action.endpoint();
         let url = format!("{}{}", self.base_url, endpoint);
 
@@ -235,6 +473,18 @@ impl RemoteBackend {
         Ok(())
     }
 
+    /// Drive the shared session, making its driver a watcher.
+    fn take_control(&self) -> BackendResult<()> {
+        let Some(ref socket_tx) = self.socket_tx else {
+            return Err(BackendError::RequestFailed(
+                "Sharing a session needs the WebSocket; connect without --sse".to_string(),
+            ));
+        };
+        socket_tx
+            .send(ClientFrame::TakeControl)
+            .map_err(|_| BackendError::ChannelClosed)
+    }
+
     /// Set the update sender for git operations.
     pub fn set_update_sender(&mut self, update_tx: mpsc::UnboundedSender<AppUpdate>) {
         self.update_tx = Some(update_tx);
@@ -355,8 +605,70 @@ impl RemoteBackend {
         Ok(())
     }
 
+    /// Handle git diff request.
+    async fn handle_git_diff(&self, path: String, staged: bool) -> BackendResult<()> {
+        let url = format!("{}/git/diff", self.base_url);
+        let resp = self
+            .add_auth(self.client.post(&url))
+            .json(&serde_json::json!({ "path": path, "staged": staged }))
+            .send()
+            .await
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+
+        if resp.status().is_success() {
+            let diff: GitDiffResponse = resp
+                .json()
+                .await
+                .map_err(|e| BackendError::SerializationError(e.to_string()))?;
+            self.send_update(AppUpdate::GitDiffUpdated {
+                path,
+                staged,
+                diff: diff.diff,
+            });
+        } else {
+            let error = resp
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            self.send_update(AppUpdate::GitOperationResult {
+                success: false,
+                message: error,
+            });
+        }
+        Ok(())
+    }
+
+    /// Handle a request to stage or unstage one hunk.
+    async fn handle_git_hunk(
+        &self,
+        endpoint: &str,
+        path: String,
+        hunk: usize,
+    ) -> BackendResult<()> {
+        let url = format!("{}/git/{endpoint}", self.base_url);
+        let resp = self
+            .add_auth(self.client.post(&url))
+            .json(&serde_json::json!({ "path": path, "hunk": hunk }))
+            .send()
+            .await
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+
+        let success = resp.status().is_success();
+        let message = resp.text().await.unwrap_or_default();
+
+        self.send_update(AppUpdate::GitOperationResult { success, message });
+
+        // Refresh the hunks left, in the same place
+        if success {
+            let staged = endpoint == "unstage-hunk";
+            let _ = self.handle_git_diff(path, staged).await;
+            let _ = self.handle_git_status().await;
+        }
+        Ok(())
+    }
+
     /// Handle git commit request.
-    async fn handle_git_commit(&self, message: String) -> BackendResult<()> {
+    async fn handle_git_commit(&self, message: String, push: bool) -> BackendResult<()> {
         let url = format!("{}/git/commit", self.base_url);
         let resp = self
             .add_auth(self.client.post(&url))
@@ -376,6 +688,9 @@ impl RemoteBackend {
         // Refresh status after commit
         if success {
             let _ = self.handle_git_status().await;
+            if push {
+                return self.handle_git_push().await;
+            }
         }
         Ok(())
     }
@@ -460,6 +775,360 @@ impl RemoteBackend {
         }
         Ok(())
     }
+
+    /// Handle a request to list a directory of the project.
+    async fn handle_list_files(&self, dir: String) -> BackendResult<()> {
+        let url = format!("{}/files/list", self.base_url);
+        let resp = self
+            .add_auth(self.client.get(&url))
+            .query(&[("path", &dir)])
+            .send()
+            .await
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+
+        if resp.status().is_success() {
+            let listing: wonopcode_protocol::FileListing = resp
+                .json()
+                .await
+                .map_err(|e| BackendError::SerializationError(e.to_string()))?;
+            let entries = listing
+                .entries
+                .into_iter()
+                .map(|e| ListedFile {
+                    name: e.name,
+                    is_dir: e.is_dir,
+                })
+                .collect();
+            self.send_update(AppUpdate::FilesListed { dir, entries });
+        } else {
+            // The directory stays as last listed
+            let error = error_message(resp).await;
+            tracing::warn!("Failed to list {:?}: {}", dir, error);
+        }
+        Ok(())
+    }
+
+    /// Handle a request to read a file of the project.
+    async fn handle_read_file(&self, path: String) -> BackendResult<()> {
+        let url = format!("{}/files/read", self.base_url);
+        let resp = self
+            .add_auth(self.client.get(&url))
+            .query(&[("path", &path)])
+            .send()
+            .await
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+
+        let content = if resp.status().is_success() {
+            let file: wonopcode_protocol::FileContent = resp
+                .json()
+                .await
+                .map_err(|e| BackendError::SerializationError(e.to_string()))?;
+            Ok(file.content)
+        } else {
+            Err(error_message(resp).await)
+        };
+        self.send_update(AppUpdate::FileRead { path, content });
+        Ok(())
+    }
+}
+
+/// The message of an error response, from its `error` field if it is JSON.
+async fn error_message(resp: reqwest::Response) -> String {
+    let text = resp
+        .text()
+        .await
+        .unwrap_or_else(|_| "Unknown error".to_string());
+    serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|v| v["error"].as_str().map(str::to_string))
+        .unwrap_or(text)
+}
+
+/// The error for a server that refused this client's handshake, explained
+/// from the client's side if the server sent its own handshake.
+fn refused_handshake(client: &Handshake, body: &str) -> BackendError {
+    let refusal: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let mismatch = serde_json::from_value::<Handshake>(refusal["handshake"].clone())
+        .ok()
+        .and_then(|server| client.negotiate(&server).err());
+    BackendError::Incompatible(match mismatch {
+        Some(mismatch) => mismatch.describe("client", "server"),
+        None => refusal["error"]
+            .as_str()
+            .unwrap_or("the server refused this client's protocol version")
+            .to_string(),
+    })
+}
+
+/// Why a WebSocket connection ended.
+#[derive(Debug, PartialEq, Eq)]
+enum SocketEnd {
+    /// The app went away; don't reconnect.
+    Closed,
+    /// The connection dropped after resuming; reconnect right away.
+    Resumed,
+    /// The connection dropped before resuming; back off.
+    Failed,
+}
+
+impl SocketEnd {
+    /// A dropped connection, depending on whether it had resumed.
+    fn dropped(resumed: bool) -> Self {
+        if resumed {
+            Self::Resumed
+        } else {
+            Self::Failed
+        }
+    }
+}
+
+/// State of the WebSocket that outlives one connection.
+struct SocketSession {
+    update_tx: mpsc::UnboundedSender<AppUpdate>,
+    /// Actions and other frames to send.
+    frame_rx: mpsc::UnboundedReceiver<ClientFrame>,
+    /// Where to fetch the state from when updates were missed.
+    state: StateSource,
+    /// Sequence number of the last update received.
+    last_seq: Option<u64>,
+    /// This client's participant ID in a shared session.
+    participant: Option<String>,
+    /// Where the participant ID is shared with the backend's HTTP requests.
+    joined: Arc<std::sync::Mutex<Option<String>>>,
+    /// A frame taken from the queue whose send failed.
+    unsent: Option<ClientFrame>,
+    /// Attempts to reconnect since the connection dropped.
+    attempt: u32,
+}
+
+type Socket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+impl SocketSession {
+    /// Exchange frames over one connection until it ends.
+    #[allow(clippy::cognitive_complexity)]
+    async fn run(&mut self, socket: Socket, connected: &AtomicBool) -> SocketEnd {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+        use wonopcode_protocol::{ServerFrame, Update, HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT};
+
+        let (mut sink, mut stream) = socket.split();
+        let resume = ClientFrame::Resume {
+            last_seq: self.last_seq,
+            name: participant_name(),
+        };
+        if sink.send(client_message(&resume)).await.is_err() {
+            return SocketEnd::Failed;
+        }
+
+        let mut resumed = false;
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut last_heard = tokio::time::Instant::now();
+
+        loop {
+            tokio::select! {
+                msg = stream.next() => {
+                    last_heard = tokio::time::Instant::now();
+                    let text = match msg {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return SocketEnd::dropped(resumed),
+                        Some(Ok(_)) => continue,
+                    };
+                    match serde_json::from_str::<ServerFrame>(&text) {
+                        Ok(ServerFrame::Resumed { seq, complete }) => {
+                            if self.attempt > 0 {
+                                self.attempt = 0;
+                                let _ = self.update_tx.send(AppUpdate::Connection(ConnectionStatus::Connected));
+                            }
+                            if !complete && self.last_seq.is_some() {
+                                for update in self.state.resync().await {
+                                    let _ = self.update_tx.send(update);
+                                }
+                            }
+                            if !complete || self.last_seq.is_none() {
+                                self.last_seq = Some(seq);
+                            }
+                            resumed = true;
+                            connected.store(true, Ordering::SeqCst);
+                            if let Some(frame) = self.unsent.take() {
+                                if !self.send_frame(&mut sink, frame).await {
+                                    return SocketEnd::dropped(resumed);
+                                }
+                            }
+                        }
+                        Ok(ServerFrame::Joined { participant }) => {
+                            *self.joined.lock().unwrap_or_else(|e| e.into_inner()) =
+                                Some(participant.clone());
+                            self.participant = Some(participant);
+                        }
+                        Ok(ServerFrame::Update { seq, update }) => {
+                            if self.last_seq.is_some_and(|last| seq <= last) {
+                                continue;
+                            }
+                            self.last_seq = Some(seq);
+                            let update = match update {
+                                // This client's own prompt is already shown
+                                Update::Prompted { participant, .. }
+                                    if self.participant.as_ref() == Some(&participant) => continue,
+                                Update::Presence { participants } => {
+                                    presence_update(participants, self.participant.as_deref())
+                                }
+                                update => protocol_update_to_app(update),
+                            };
+                            if self.update_tx.send(update).is_err() {
+                                return SocketEnd::Closed;
+                            }
+                        }
+                        Ok(ServerFrame::Pong) => {}
+                        Ok(ServerFrame::Error { message }) => {
+                            tracing::warn!("WebSocket error from server: {}", message);
+                        }
+                        Err(e) => tracing::warn!("Invalid WebSocket frame: {}", e),
+                    }
+                }
+                frame = self.frame_rx.recv(), if resumed => {
+                    let Some(frame) = frame else {
+                        return SocketEnd::Closed;
+                    };
+                    if !self.send_frame(&mut sink, frame).await {
+                        return SocketEnd::dropped(resumed);
+                    }
+                }
+                _ = heartbeat.tick() => {
+                    if last_heard.elapsed() > HEARTBEAT_TIMEOUT {
+                        tracing::warn!("WebSocket server stopped responding");
+                        return SocketEnd::dropped(resumed);
+                    }
+                    if sink.send(client_message(&ClientFrame::Ping)).await.is_err() {
+                        return SocketEnd::dropped(resumed);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send a frame, keeping it for the next connection if that fails.
+    async fn send_frame(
+        &mut self,
+        sink: &mut futures::stream::SplitSink<Socket, tokio_tungstenite::tungstenite::Message>,
+        frame: ClientFrame,
+    ) -> bool {
+        use futures::SinkExt;
+
+        if sink.send(client_message(&frame)).await.is_ok() {
+            return true;
+        }
+        self.unsent = Some(frame);
+        false
+    }
+}
+
+/// Name to show to the others sharing a session: the user's login.
+fn participant_name() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+}
+
+/// Where to fetch the state of a server from.
+#[derive(Clone)]
+struct StateSource {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl StateSource {
+    /// Fetch the full state.
+    async fn fetch(&self) -> BackendResult<wonopcode_protocol::State> {
+        let mut request = self.client.get(&self.url);
+        if let Some(ref key) = self.api_key {
+            request = request.header("X-API-Key", key);
+        }
+        let resp = request
+            .send()
+            .await
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?
+            .error_for_status()
+            .map_err(|e| BackendError::RequestFailed(e.to_string()))?;
+
+        resp.json()
+            .await
+            .map_err(|e| BackendError::SerializationError(e.to_string()))
+    }
+
+    /// The updates that bring the App back in line with the server after
+    /// missing some of its updates: [`AppUpdate::Resync`] and the state.
+    async fn resync(&self) -> Vec<AppUpdate> {
+        match self.fetch().await {
+            Ok(state) => std::iter::once(AppUpdate::Resync)
+                .chain(state_updates(state))
+                .collect(),
+            Err(e) => {
+                tracing::warn!("Failed to fetch state after missing updates: {}", e);
+                vec![AppUpdate::Status(MISSED_UPDATES.to_string())]
+            }
+        }
+    }
+}
+
+fn invalid_ca_certificate(e: impl std::fmt::Display) -> BackendError {
+    BackendError::ConnectionFailed(format!("Invalid CA certificate: {e}"))
+}
+
+/// WebSocket URL of a server, from its HTTP base URL.
+fn socket_url(base_url: &str) -> String {
+    let url = if let Some(rest) = base_url.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else if let Some(rest) = base_url.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else {
+        base_url.to_string()
+    };
+    format!(
+        "{}{}",
+        url.trim_end_matches('/'),
+        wonopcode_protocol::SOCKET_ENDPOINT
+    )
+}
+
+/// Open a WebSocket, authenticating with the API key if there is one.
+async fn connect_socket(
+    url: &str,
+    api_key: Option<&str>,
+    tls: Option<&native_tls::TlsConnector>,
+) -> BackendResult<Socket> {
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    let mut request = url
+        .into_client_request()
+        .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
+    if let Some(key) = api_key {
+        let value = key
+            .parse()
+            .map_err(|_| BackendError::ConnectionFailed("Invalid API key".to_string()))?;
+        request.headers_mut().insert("X-API-Key", value);
+    }
+
+    let (socket, _) = tokio::time::timeout(
+        wonopcode_protocol::HEARTBEAT_TIMEOUT,
+        tokio_tungstenite::connect_async_tls_with_config(
+            request,
+            None,
+            false,
+            tls.cloned().map(tokio_tungstenite::Connector::NativeTls),
+        ),
+    )
+    .await
+    .map_err(|_| BackendError::ConnectionFailed("Timed out".to_string()))?
+    .map_err(|e| BackendError::ConnectionFailed(e.to_string()))?;
+    Ok(socket)
+}
+
+/// A client frame as a text message.
+fn client_message(frame: &ClientFrame) -> tokio_tungstenite::tungstenite::Message {
+    let json = serde_json::to_string(frame).unwrap_or_default();
+    tokio_tungstenite::tungstenite::Message::Text(json.into())
 }
 
 /// Response structure for git status endpoint.
@@ -471,6 +1140,12 @@ struct GitStatusResponse {
     files: Vec<GitFileResponse>,
 }
 
+/// Response structure for git diff endpoint.
+#[derive(Debug, serde::Deserialize)]
+struct GitDiffResponse {
+    diff: String,
+}
+
 /// Response structure for individual git file.
 #[derive(Debug, serde::Deserialize)]
 struct GitFileResponse {
@@ -497,10 +1172,22 @@ impl Backend for RemoteBackend {
             AppAction::GitStage { paths } => return self.handle_git_stage(paths).await,
             AppAction::GitUnstage { paths } => return self.handle_git_unstage(paths).await,
             AppAction::GitCheckout { paths } => return self.handle_git_checkout(paths).await,
-            AppAction::GitCommit { message } => return self.handle_git_commit(message).await,
+            AppAction::GitDiff { path, staged } => return self.handle_git_diff(path, staged).await,
+            AppAction::GitStageHunk { path, hunk } => {
+                return self.handle_git_hunk("stage-hunk", path, hunk).await
+            }
+            AppAction::GitUnstageHunk { path, hunk } => {
+                return self.handle_git_hunk("unstage-hunk", path, hunk).await
+            }
+            AppAction::GitCommit { message, push } => {
+                return self.handle_git_commit(message, push).await
+            }
             AppAction::GitHistory => return self.handle_git_history().await,
             AppAction::GitPush => return self.handle_git_push().await,
             AppAction::GitPull => return self.handle_git_pull().await,
+            AppAction::ListFiles { dir } => return self.handle_list_files(dir).await,
+            AppAction::ReadFile { path } => return self.handle_read_file(path).await,
+            AppAction::TakeControl => return self.take_control(),
             _ => {}
         }
 
@@ -510,7 +1197,7 @@ impl Backend for RemoteBackend {
     }
 
     fn is_connected(&self) -> bool {
-        self.connected.load(std::sync::atomic::Ordering::SeqCst)
+        self.connected.load(Ordering::SeqCst)
     }
 
     fn backend_type(&self) -> &'static str {
@@ -524,6 +1211,17 @@ fn app_action_to_protocol(action: AppAction) -> BackendResult<wonopcode_protocol
 
     Ok(match action {
         AppAction::SendPrompt(prompt) => Action::SendPrompt { prompt },
+        AppAction::AttachImage { media_type, data } => Action::AttachImage { media_type, data },
+        AppAction::AttachContext {
+            path,
+            symbol,
+            lines,
+        } => Action::AttachContext {
+            path,
+            symbol,
+            lines,
+        },
+        AppAction::SearchSymbols { query } => Action::SearchSymbols { query },
         AppAction::Cancel => Action::Cancel,
         AppAction::Quit => Action::Quit,
         AppAction::SwitchSession(session_id) => Action::SwitchSession { session_id },
@@ -539,12 +1237,37 @@ fn app_action_to_protocol(action: AppAction) -> BackendResult<wonopcode_protocol
         AppAction::McpToggle { name } => Action::McpToggle { name },
         AppAction::McpReconnect { name } => Action::McpReconnect { name },
         AppAction::ForkSession { message_id } => Action::ForkSession { message_id },
-        AppAction::ShareSession => Action::ShareSession,
+        AppAction::MergeSession => Action::MergeSession,
+        AppAction::ShareSession { expires, password } => Action::ShareSession { expires, password },
         AppAction::UnshareSession => Action::UnshareSession,
         AppAction::GotoMessage { message_id } => Action::GotoMessage { message_id },
         AppAction::SandboxStart => Action::SandboxStart,
         AppAction::SandboxStop => Action::SandboxStop,
         AppAction::SandboxRestart => Action::SandboxRestart,
+        AppAction::SwitchProfile { name } => Action::SwitchProfile { profile: name },
+        AppAction::SearchSessions { query } => Action::SearchSessions { query },
+        AppAction::Orchestrate { tasks } => Action::Orchestrate { tasks },
+        AppAction::MergeOrchestration => Action::MergeOrchestration,
+        AppAction::CancelOrchestration => Action::CancelOrchestration,
+        AppAction::CreateWorktree { name } => Action::CreateWorktree { name },
+        AppAction::ListWorktrees => Action::ListWorktrees,
+        AppAction::FinishWorktree { action } => Action::FinishWorktree { action },
+        AppAction::ApplyTemplate { name } => Action::ApplyTemplate { name },
+        AppAction::SaveTemplate { name } => Action::SaveTemplate { name },
+        AppAction::ListProjects => Action::ListProjects,
+        AppAction::SwitchProject { project } => Action::SwitchProject { project },
+        AppAction::LoadMemory => Action::LoadMemory,
+        AppAction::LoadPermissionAudit => Action::LoadPermissionAudit,
+        AppAction::LoadSpend => Action::LoadSpend,
+        AppAction::AcknowledgeSpendLimit => Action::AcknowledgeSpendLimit,
+        AppAction::LoadUsage { days } => Action::LoadUsage { days },
+        AppAction::StartReview => Action::StartReview,
+        AppAction::StopProcess { pid } => Action::StopProcess { pid },
+        AppAction::CancelTool { id } => Action::CancelTool { id },
+        AppAction::ApplyReview { files } => Action::ApplyReview {
+            files: files.into_iter().map(reviewed_file_to_protocol).collect(),
+        },
+        AppAction::SaveMemory { content } => Action::SaveMemory { content },
         AppAction::SaveSettings { scope, config } => {
             let protocol_scope = match scope {
                 crate::SaveScope::Project => wonopcode_protocol::SaveScope::Project,
@@ -582,11 +1305,26 @@ fn app_action_to_protocol(action: AppAction) -> BackendResult<wonopcode_protocol
                 "OpenEditor is not supported for remote backend".to_string(),
             ));
         }
+        // The model is called by the local runner
+        AppAction::GitGenerateCommitMessage => {
+            return Err(BackendError::RequestFailed(
+                "Drafting commit messages is not supported for remote backend".to_string(),
+            ));
+        }
+        // Each tab has its own local runner
+        AppAction::NewTab | AppAction::SwitchTab(_) | AppAction::CloseTab(_) => {
+            return Err(BackendError::RequestFailed(
+                "Session tabs are not supported for remote backend".to_string(),
+            ));
+        }
         // Git actions are handled specially via HTTP, should not reach here
         AppAction::GitStatus
         | AppAction::GitStage { .. }
         | AppAction::GitUnstage { .. }
         | AppAction::GitCheckout { .. }
+        | AppAction::GitDiff { .. }
+        | AppAction::GitStageHunk { .. }
+        | AppAction::GitUnstageHunk { .. }
         | AppAction::GitCommit { .. }
         | AppAction::GitHistory
         | AppAction::GitPush
@@ -595,34 +1333,335 @@ fn app_action_to_protocol(action: AppAction) -> BackendResult<wonopcode_protocol
                 "Git actions should be handled via HTTP endpoints".to_string(),
             ));
         }
+        // As are file actions
+        AppAction::ListFiles { .. } | AppAction::ReadFile { .. } => {
+            return Err(BackendError::RequestFailed(
+                "File actions should be handled via HTTP endpoints".to_string(),
+            ));
+        }
+        // And taking control, which is a WebSocket frame
+        AppAction::TakeControl => {
+            return Err(BackendError::RequestFailed(
+                "Taking control should be sent over the WebSocket".to_string(),
+            ));
+        }
+        // Connecting to another server is up to the client
+        AppAction::BrowseServers
+        | AppAction::StopBrowsingServers
+        | AppAction::ConnectServer { .. } => {
+            return Err(BackendError::RequestFailed(
+                "Connecting to servers is handled by the client".to_string(),
+            ));
+        }
     })
 }
 
+/// Convert the decisions for a reviewed file to protocol.
+fn reviewed_file_to_protocol(file: crate::ReviewedFile) -> wonopcode_protocol::ReviewedFileInfo {
+    use wonopcode_protocol::HunkDecisionInfo;
+
+    wonopcode_protocol::ReviewedFileInfo {
+        path: file.path,
+        decisions: file
+            .decisions
+            .into_iter()
+            .map(|decision| {
+                decision.map(|d| match d {
+                    crate::HunkDecision::Accept => HunkDecisionInfo::Accept,
+                    crate::HunkDecision::Reject => HunkDecisionInfo::Reject,
+                    crate::HunkDecision::Edit(text) => HunkDecisionInfo::Edit { text },
+                })
+            })
+            .collect(),
+    }
+}
+
 /// Parse an SSE event string into an AppUpdate.
-fn parse_sse_event(event_str: &str) -> Option<AppUpdate> {
-    let mut data = None;
+fn parse_sse_event(event_str: &str) -> SseEvent {
+    let mut event = SseEvent::default();
 
     for line in event_str.lines() {
         if let Some(rest) = line.strip_prefix("data:") {
-            data = Some(rest.trim().to_string());
+            event.data = Some(rest.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("event:") {
+            event.name = Some(rest.trim().to_string());
+        } else if let Some(rest) = line.strip_prefix("id:") {
+            event.id = rest.trim().parse().ok();
+        }
+    }
+    event
+}
+
+/// An event of the SSE stream.
+#[derive(Debug, Default)]
+struct SseEvent {
+    /// Sequence number of the update.
+    id: Option<u64>,
+    /// Event type; updates are parsed from their data, which has it too.
+    name: Option<String>,
+    data: Option<String>,
+}
+
+impl SseEvent {
+    /// The update to forward for the event, if any, keeping track of the ID
+    /// of the last event received. Missed updates are [`AppUpdate::Resync`],
+    /// for the caller to fetch the state.
+    fn resume(self, last_id: &mut Option<u64>) -> Option<AppUpdate> {
+        if self.name.as_deref() == Some(wonopcode_protocol::SSE_RESYNC_EVENT) {
+            *last_id = self.id.or(*last_id);
+            return Some(AppUpdate::Resync);
+        }
+        if let Some(id) = self.id {
+            if last_id.is_some_and(|last| id <= last) {
+                // Already received before reconnecting
+                return None;
+            }
+            *last_id = Some(id);
+        }
+        self.update()
+    }
+
+    /// The update the event carries, if any.
+    fn update(&self) -> Option<AppUpdate> {
+        let update: wonopcode_protocol::Update =
+            serde_json::from_str(self.data.as_deref()?).ok()?;
+        Some(protocol_update_to_app(update))
+    }
+}
+
+/// The updates that bring the App up to a state fetched from the server:
+/// its sandbox, todos, servers, sessions, usage and modified files, and the
+/// current session with the response it is streaming, if any.
+pub fn state_updates(state: wonopcode_protocol::State) -> Vec<AppUpdate> {
+    let mut updates = vec![AppUpdate::SandboxUpdated(crate::SandboxStatusUpdate {
+        state: state.sandbox.state,
+        runtime_type: state.sandbox.runtime_type,
+        error: state.sandbox.error,
+    })];
+
+    if !state.phases.is_empty() || !state.todos.is_empty() {
+        let todo = |t: wonopcode_protocol::TodoInfo| crate::TodoUpdate {
+            id: t.id,
+            content: t.content,
+            status: t.status,
+            priority: t.priority,
+            phase_id: t.phase_id,
+        };
+        let phases = state
+            .phases
+            .into_iter()
+            .map(|p| crate::PhaseUpdate {
+                id: p.id,
+                name: p.name,
+                status: p.status,
+                todos: p.todos.into_iter().map(todo).collect(),
+            })
+            .collect();
+        let todos = state.todos.into_iter().map(todo).collect();
+        updates.push(AppUpdate::TodosUpdated { phases, todos });
+    }
+
+    if !state.mcp_servers.is_empty() {
+        let servers = state
+            .mcp_servers
+            .into_iter()
+            .map(|s| crate::McpStatusUpdate {
+                name: s.name,
+                connected: s.connected,
+                error: s.error,
+            })
+            .collect();
+        updates.push(AppUpdate::McpUpdated(servers));
+    }
+
+    if !state.lsp_servers.is_empty() {
+        let servers = state
+            .lsp_servers
+            .into_iter()
+            .map(|s| crate::LspStatusUpdate {
+                id: s.id,
+                name: s.name,
+                root: s.root,
+                connected: s.connected,
+            })
+            .collect();
+        updates.push(AppUpdate::LspUpdated(servers));
+    }
+
+    if !state.sessions.is_empty() {
+        let sessions = state
+            .sessions
+            .into_iter()
+            .map(|s| (s.id, s.title, s.timestamp))
+            .collect();
+        updates.push(AppUpdate::Sessions(sessions));
+    }
+
+    updates.push(AppUpdate::TokenUsage {
+        input: state.token_usage.input,
+        output: state.token_usage.output,
+        cost: state.token_usage.cost,
+        context_limit: state.context_limit,
+    });
+
+    if !state.modified_files.is_empty() {
+        let files = state
+            .modified_files
+            .into_iter()
+            .map(|f| crate::ModifiedFileUpdate {
+                path: f.path,
+                added: f.added,
+                removed: f.removed,
+            })
+            .collect();
+        updates.push(AppUpdate::ModifiedFilesUpdated(files));
+    }
+
+    if let Some(session) = state.session {
+        updates.push(AppUpdate::SessionLoaded {
+            id: session.id,
+            title: session.title,
+            messages: session.messages.iter().map(display_message).collect(),
+        });
+
+        // Replay the response being streamed, to pick it up where it is
+        if let (true, Some(streaming)) = (session.is_streaming, session.streaming_message) {
+            updates.push(AppUpdate::Started);
+            for segment in streaming.content {
+                match segment {
+                    wonopcode_protocol::MessageSegment::Text { text } => {
+                        updates.push(AppUpdate::TextDelta(text));
+                    }
+                    wonopcode_protocol::MessageSegment::Code { code, .. } => {
+                        updates.push(AppUpdate::TextDelta(code));
+                    }
+                    wonopcode_protocol::MessageSegment::Thinking { text } => {
+                        updates.push(AppUpdate::TextDelta(format!("*Thinking:* {text}")));
+                    }
+                    wonopcode_protocol::MessageSegment::Tool { tool } => {
+                        let finished = tool.status == "completed" || tool.status == "failed";
+                        updates.push(AppUpdate::ToolStarted {
+                            id: tool.id.clone(),
+                            name: tool.name,
+                            input: tool.input,
+                        });
+                        if finished {
+                            updates.push(AppUpdate::ToolCompleted {
+                                id: tool.id,
+                                success: tool.success,
+                                output: tool.output.unwrap_or_default(),
+                                metadata: None,
+                            });
+                        }
+                    }
+                }
+            }
         }
-        // We ignore the event type since we parse the full Update which has the type embedded
     }
 
-    let data = data?;
-    let update: wonopcode_protocol::Update = serde_json::from_str(&data).ok()?;
+    updates
+}
+
+/// A tool call of a message from the server, for display.
+fn display_tool_call(tool: &wonopcode_protocol::ToolCall) -> crate::DisplayToolCall {
+    let status = match tool.status.as_str() {
+        "completed" => crate::ToolStatus::Success,
+        "failed" => crate::ToolStatus::Error,
+        "running" => crate::ToolStatus::Running,
+        _ => crate::ToolStatus::Pending,
+    };
+    crate::DisplayToolCall {
+        id: tool.id.clone(),
+        name: tool.name.clone(),
+        input: Some(tool.input.clone()),
+        output: tool.output.clone(),
+        status,
+        metadata: None,
+        expanded: false,
+        progress: None,
+        live_output: String::new(),
+    }
+}
+
+/// A message from the server, for display.
+fn display_message(msg: &wonopcode_protocol::Message) -> crate::DisplayMessage {
+    use crate::widgets::MessageSegment;
+    use wonopcode_protocol::MessageSegment as Segment;
+
+    let text = || -> String {
+        msg.content
+            .iter()
+            .filter_map(|seg| match seg {
+                Segment::Text { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    };
+    match msg.role.as_str() {
+        "user" => crate::DisplayMessage::user(text()).with_id(msg.id.clone()),
+        "assistant" => {
+            let mut segments: Vec<MessageSegment> = msg
+                .content
+                .iter()
+                .map(|seg| match seg {
+                    Segment::Text { text } => MessageSegment::Text(text.clone()),
+                    Segment::Code { code, .. } => MessageSegment::Text(code.clone()),
+                    Segment::Thinking { text } => {
+                        MessageSegment::Text(format!("*Thinking:* {text}"))
+                    }
+                    Segment::Tool { tool } => MessageSegment::Tool(display_tool_call(tool)),
+                })
+                .collect();
+            // Tool calls of older servers, kept apart from the content
+            segments.extend(
+                msg.tool_calls
+                    .iter()
+                    .map(|tool| MessageSegment::Tool(display_tool_call(tool))),
+            );
+            let agent = msg.agent.as_deref().map(crate::AgentMode::parse);
+            crate::DisplayMessage::assistant_with_segments(segments)
+                .with_model_agent(msg.model.clone(), agent)
+        }
+        "system" => crate::DisplayMessage::system(text()),
+        role => crate::DisplayMessage::system(format!("Unknown role: {role}")),
+    }
+}
 
-    Some(protocol_update_to_app(update))
+/// The participants of a shared session, marking this client's own.
+fn presence_update(
+    participants: Vec<wonopcode_protocol::Participant>,
+    me: Option<&str>,
+) -> AppUpdate {
+    AppUpdate::Presence(
+        participants
+            .into_iter()
+            .map(|p| crate::ParticipantUpdate {
+                you: me == Some(p.id.as_str()),
+                driver: p.role == wonopcode_protocol::Role::Driver,
+                name: p.name,
+            })
+            .collect(),
+    )
 }
 
 /// Convert protocol Update to AppUpdate.
-fn protocol_update_to_app(update: wonopcode_protocol::Update) -> AppUpdate {
+pub fn protocol_update_to_app(update: wonopcode_protocol::Update) -> AppUpdate {
     use wonopcode_protocol::Update;
 
     match update {
         Update::Started => AppUpdate::Started,
         Update::TextDelta { delta } => AppUpdate::TextDelta(delta),
         Update::ToolStarted { id, name, input } => AppUpdate::ToolStarted { name, id, input },
+        Update::ToolProgress {
+            id,
+            fraction,
+            message,
+        } => AppUpdate::ToolProgress {
+            id,
+            fraction,
+            message,
+        },
+        Update::ToolOutput { id, text } => AppUpdate::ToolOutput { id, text },
         Update::ToolCompleted {
             id,
             success,
@@ -637,6 +1676,9 @@ fn protocol_update_to_app(update: wonopcode_protocol::Update) -> AppUpdate {
         Update::Completed { text } => AppUpdate::Completed { text },
         Update::Error { error } => AppUpdate::Error(error),
         Update::Status { message } => AppUpdate::Status(message),
+        Update::PromptQueued { position } => {
+            AppUpdate::Status(format!("Prompt queued ({position} waiting)"))
+        }
         Update::TokenUsage {
             input,
             output,
@@ -697,6 +1739,19 @@ fn protocol_update_to_app(update: wonopcode_protocol::Update) -> AppUpdate {
                 })
                 .collect(),
         ),
+        Update::Symbols { query, symbols } => AppUpdate::Symbols {
+            query,
+            symbols: symbols
+                .into_iter()
+                .map(|s| crate::SymbolSuggestion {
+                    name: s.name,
+                    kind: s.kind,
+                    path: s.path,
+                    start_line: s.start_line,
+                    end_line: s.end_line,
+                })
+                .collect(),
+        },
         Update::McpUpdated { servers } => AppUpdate::McpUpdated(
             servers
                 .into_iter()
@@ -727,8 +1782,152 @@ fn protocol_update_to_app(update: wonopcode_protocol::Update) -> AppUpdate {
             runtime_type,
             error,
         }),
+        Update::SandboxResources {
+            cpu_percent,
+            memory_bytes,
+            memory_limit,
+            pids,
+        } => AppUpdate::SandboxResources(crate::SandboxResourcesUpdate {
+            cpu_percent,
+            memory_bytes,
+            memory_limit,
+            pids,
+        }),
         Update::SystemMessage { message } => AppUpdate::SystemMessage(message),
         Update::AgentChanged { agent } => AppUpdate::AgentChanged(agent),
+        Update::Memory { content } => AppUpdate::Memory(content),
+        Update::SessionSearchResults { query, results } => AppUpdate::SessionSearchResults {
+            query,
+            results: results
+                .into_iter()
+                .map(|hit| (hit.id, hit.title, hit.snippet))
+                .collect(),
+        },
+        Update::Orchestration {
+            orchestration_id,
+            agents,
+        } => AppUpdate::Orchestration {
+            id: orchestration_id,
+            agents: agents
+                .into_iter()
+                .map(|a| crate::OrchestrationAgentUpdate {
+                    name: a.name,
+                    task: a.task,
+                    state: a.state,
+                    branch: a.branch,
+                    steps: a.steps,
+                    tool_calls: a.tool_calls,
+                    changed_files: a.changed_files,
+                    summary: a.summary,
+                })
+                .collect(),
+        },
+        Update::PermissionAudit { entries } => AppUpdate::PermissionAudit(
+            entries
+                .into_iter()
+                .map(|e| crate::PermissionAuditUpdate {
+                    time: e.time,
+                    tool: e.tool,
+                    action: e.action,
+                    path: e.path,
+                    description: e.description,
+                    allowed: e.allowed,
+                    prompted: e.prompted,
+                    source: e.source,
+                })
+                .collect(),
+        ),
+        Update::Instructions { files } => AppUpdate::Instructions(
+            files
+                .into_iter()
+                .map(|f| crate::InstructionFileUpdate {
+                    path: f.path,
+                    tokens: f.tokens,
+                    included: f.included,
+                    note: f.note,
+                })
+                .collect(),
+        ),
+        Update::Templates { templates } => AppUpdate::Templates(
+            templates
+                .into_iter()
+                .map(|t| (t.name, t.description))
+                .collect(),
+        ),
+        Update::Projects { projects } => AppUpdate::Projects(
+            projects
+                .into_iter()
+                .map(|p| crate::ProjectUpdate {
+                    path: p.path,
+                    name: p.name,
+                    active: p.active,
+                    loaded: p.loaded,
+                })
+                .collect(),
+        ),
+        Update::ProjectSwitched { path, name } => AppUpdate::ProjectSwitched { path, name },
+        Update::Review { diff } => AppUpdate::Review(diff),
+        Update::ProcessStarted { pid, command } => AppUpdate::ProcessStarted { pid, command },
+        Update::ProcessOutput { pid, text } => AppUpdate::ProcessOutput { pid, text },
+        Update::ProcessExited { pid, exit_code } => AppUpdate::ProcessExited { pid, exit_code },
+        Update::Presence { participants } => presence_update(participants, None),
+        Update::Prompted { name, prompt, .. } => AppUpdate::Prompted { name, prompt },
+        Update::BudgetWarning {
+            limit,
+            used,
+            max,
+            message,
+        } => AppUpdate::BudgetWarning {
+            limit,
+            used,
+            max,
+            message,
+        },
+        Update::ConfigReloaded {
+            theme,
+            applied,
+            restart_required,
+        } => AppUpdate::ConfigReloaded {
+            theme,
+            applied,
+            restart_required,
+        },
+        Update::SpendAlert {
+            scope,
+            spent,
+            alert,
+            message,
+        } => AppUpdate::SpendAlert {
+            scope,
+            spent,
+            alert,
+            message,
+        },
+        Update::Usage {
+            days,
+            daily,
+            models,
+            tools,
+        } => {
+            let amount = |u: wonopcode_protocol::UsageInfo| crate::UsageAmount {
+                name: u.name,
+                cost: u.cost,
+                input_tokens: u.input_tokens,
+                output_tokens: u.output_tokens,
+            };
+            AppUpdate::Usage {
+                days,
+                daily: daily.into_iter().map(amount).collect(),
+                models: models.into_iter().map(amount).collect(),
+                tools: tools
+                    .into_iter()
+                    .map(|t| crate::ToolLatency {
+                        tool: t.tool,
+                        durations_ms: t.durations_ms,
+                    })
+                    .collect(),
+            }
+        }
         Update::PermissionRequest {
             id,
             tool,
@@ -744,3 +1943,104 @@ fn protocol_update_to_app(update: wonopcode_protocol::Update) -> AppUpdate {
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refused_handshake() {
+        let client = Handshake::new(Capability::ALL.to_vec());
+        let server = Handshake {
+            version: client.version + 2,
+            min_version: client.version + 1,
+            capabilities: Vec::new(),
+        };
+        let body = serde_json::json!({ "error": "refused", "handshake": server }).to_string();
+        let message = refused_handshake(&client, &body).to_string();
+        assert!(message.starts_with("Incompatible server: the server speaks protocol"));
+        assert!(message.ends_with("upgrade the client"));
+
+        let message = refused_handshake(&client, r#"{"error": "refused"}"#).to_string();
+        assert_eq!(message, "Incompatible server: refused");
+    }
+
+    #[test]
+    fn test_invalid_ca_certificate() {
+        let backend = RemoteBackend::new("https://127.0.0.1:3000").unwrap();
+        let Err(err) = backend.with_ca_certificate(b"not a certificate") else {
+            panic!("accepted an invalid CA certificate");
+        };
+        assert!(err.to_string().contains("Invalid CA certificate"));
+    }
+
+    #[test]
+    fn test_parse_sse_event() {
+        let event = parse_sse_event(
+            "event: completed\ndata: {\"type\":\"completed\",\"text\":\"done\"}\nid: 7",
+        );
+        assert_eq!(event.id, Some(7));
+        assert_eq!(event.name.as_deref(), Some("completed"));
+        assert!(matches!(event.update(), Some(AppUpdate::Completed { .. })));
+
+        let event = parse_sse_event(": keep-alive");
+        assert_eq!(event.id, None);
+        assert!(event.update().is_none());
+    }
+
+    #[test]
+    fn test_sse_events_resume() {
+        let started =
+            |id: u64| parse_sse_event(&format!("data: {{\"type\":\"started\"}}\nid: {id}"));
+        let mut last_id = None;
+        assert!(started(3).resume(&mut last_id).is_some());
+        assert_eq!(last_id, Some(3));
+        // Replayed twice around a reconnect
+        assert!(started(3).resume(&mut last_id).is_none());
+
+        // The server restarted; its IDs start over
+        let resync = parse_sse_event("event: resync\ndata: {\"seq\":1}\nid: 1");
+        assert!(matches!(
+            resync.resume(&mut last_id),
+            Some(AppUpdate::Resync)
+        ));
+        assert_eq!(last_id, Some(1));
+        assert!(started(2).resume(&mut last_id).is_some());
+    }
+
+    #[test]
+    fn test_presence_update_marks_this_client() {
+        let participant = |id: &str, role| wonopcode_protocol::Participant {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            role,
+            joined_at: String::new(),
+        };
+        let participants = vec![
+            participant("a", wonopcode_protocol::Role::Driver),
+            participant("b", wonopcode_protocol::Role::Watcher),
+        ];
+        let AppUpdate::Presence(update) = presence_update(participants, Some("b")) else {
+            panic!("expected a presence update");
+        };
+        assert_eq!(
+            update
+                .iter()
+                .map(|p| (p.name.as_str(), p.driver, p.you))
+                .collect::<Vec<_>>(),
+            vec![("A", true, false), ("B", false, true)]
+        );
+    }
+
+    #[test]
+    fn test_socket_url() {
+        assert_eq!(
+            socket_url("http://127.0.0.1:3000"),
+            "ws://127.0.0.1:3000/ws"
+        );
+        assert_eq!(
+            socket_url("https://agent.example/"),
+            "wss://agent.example/ws"
+        );
+    }
+}
@@ -25,6 +25,7 @@ pub use wonopcode_tui_core::{
     EventLoopHandle,
     // Keybind types
     KeyAction,
+    KeyScope,
     Keybind,
     KeybindConfig,
     KeybindManager,
@@ -40,16 +41,21 @@ pub use wonopcode_tui_core::{
 
 pub use app::{
     install_panic_hook, restore_terminal, ActiveDialog, App, AppAction, AppState, AppUpdate,
-    GitCommitUpdate, GitFileUpdate, GitStatusUpdate, LspStatusUpdate, McpStatusUpdate,
-    ModifiedFileUpdate, PermissionRequestUpdate, PhaseUpdate, Route, SandboxStatusUpdate,
-    SaveScope, TerminalGuard, TodoUpdate,
+    ConnectionStatus, CustomCommandUpdate, GitCommitUpdate, GitFileUpdate, GitStatusUpdate,
+    InstructionFileUpdate, LspStatusUpdate, McpStatusUpdate, ModifiedFileUpdate,
+    OrchestrationAgentUpdate, ParticipantUpdate, PermissionAuditUpdate, PermissionRequestUpdate,
+    PhaseUpdate, ProjectUpdate, Route, SandboxResourcesUpdate, SandboxStatusUpdate, SaveScope,
+    TerminalGuard, TodoUpdate, ToolLatency, UsageAmount,
+};
+pub use backend::{
+    protocol_update_to_app, state_updates, Backend, BackendError, BackendResult, LocalBackend,
+    RemoteBackend,
 };
-pub use backend::{Backend, BackendError, BackendResult, LocalBackend, RemoteBackend};
 pub use widgets::{
     highlight_code, highlight_diff, is_diff, render_markdown, render_markdown_with_width,
-    CommandPalette, ContextInfo, DialogItem, DiffHunk, DiffLine, DiffWidget, DisplayMessage,
-    DisplayToolCall, DotsSpinner, FileDiff, FooterStatus, FooterWidget, HelpDialog, InputAction,
-    InputWidget, LogoWidget, MessageRole, MessagesWidget, ModelDialog, ModifiedFile, PromptHistory,
-    SelectDialog, SessionDialog, SidebarWidget, ThemeDialog, Toast, ToastManager, ToastType,
-    TodoItem, ToolStatus,
+    CommandPalette, ContextInfo, DialogItem, DiffHunk, DiffLine, DiffWidget, DiscoveredServer,
+    DisplayMessage, DisplayToolCall, DotsSpinner, FileDiff, FooterStatus, FooterWidget, HelpDialog,
+    HunkDecision, InputAction, InputWidget, LogoWidget, MessageRole, MessagesWidget, ModelDialog,
+    ModifiedFile, PromptHistory, ReviewedFile, SelectDialog, SessionDialog, SidebarWidget,
+    SymbolSuggestion, ThemeDialog, Toast, ToastManager, ToastType, TodoItem, ToolStatus,
 };
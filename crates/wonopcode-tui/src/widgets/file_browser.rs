@@ -0,0 +1,2 @@
+//! Re-exported from wonop-tui-widgets.
+pub use wonopcode_tui_widgets::file_browser::*;
@@ -0,0 +1,5 @@
+//! Inline images through terminal graphics protocols.
+//!
+//! This module re-exports from wonop-tui-render.
+
+pub use wonopcode_tui_render::image::*;
@@ -2,20 +2,25 @@
 
 pub mod autocomplete;
 pub mod diff;
+pub mod file_browser;
 pub mod footer;
 pub mod help_overlay;
+pub mod image;
 pub mod input;
 pub mod logo;
 pub mod markdown;
 pub mod messages;
 pub mod mode_indicator;
 pub mod onboarding;
+pub mod preview;
+pub mod review;
 pub mod search;
 pub mod sidebar;
 pub mod slash_commands;
 pub mod spinner;
 pub mod status;
 pub mod syntax;
+pub mod terminal;
 pub mod timeline;
 pub mod toast;
 pub mod topbar;
@@ -26,16 +31,18 @@ pub mod dialog {
     pub use wonopcode_tui_dialog::*;
 }
 
-pub use autocomplete::{AutocompleteAction, FileAutocomplete};
+pub use autocomplete::{AutocompleteAction, FileAutocomplete, SymbolSuggestion};
 pub use dialog::{
-    CommandPalette, DialogItem, GitCommitDisplay, GitDialog, GitDialogResult, GitFileDisplay,
-    GitView, HelpDialog, ModelDialog, PerfDialog, SelectDialog, SessionDialog, ThemeDialog,
-    TimelineDialog, TimelineItem,
+    CommandPalette, DialogItem, DiscoveredServer, GitCommitDisplay, GitDialog, GitDialogResult,
+    GitFileDisplay, GitView, HelpDialog, ModelDialog, PerfDialog, SelectDialog, SessionDialog,
+    ThemeDialog, TimelineDialog, TimelineItem,
 };
 pub use diff::{simple_diff, DiffHunk, DiffLine, DiffNavAction, DiffWidget, FileDiff};
+pub use file_browser::{FileBrowser, FileBrowserAction};
 pub use footer::{FooterStatus, FooterWidget};
 pub use help_overlay::{HelpContext, HelpEntry, HelpOverlay};
-pub use input::{InputAction, InputWidget, PromptHistory};
+pub use image::{ImageCanvas, ImageProtocol, InlineImage};
+pub use input::{ContextAttachment, InputAction, InputWidget, PromptHistory};
 pub use logo::LogoWidget;
 pub use markdown::{
     render_markdown, render_markdown_with_regions, render_markdown_with_width, CodeRegion,
@@ -47,11 +54,14 @@ pub use messages::{
 };
 pub use mode_indicator::{DisplayMode, ModeIndicator};
 pub use onboarding::OnboardingOverlay;
+pub use preview::{PreviewMode, PreviewPane};
+pub use review::{HunkDecision, ReviewView, ReviewedFile};
 pub use search::{extract_preview, fuzzy_match, SearchMatch, SearchWidget};
 pub use sidebar::{ContextInfo, ModifiedFile, SidebarSection, SidebarWidget, TodoItem};
 pub use slash_commands::{SlashCommand, SlashCommandAction, SlashCommandAutocomplete};
 pub use spinner::DotsSpinner;
 pub use syntax::{highlight_code, highlight_diff, is_diff};
+pub use terminal::{TerminalAction, TerminalPanel};
 pub use timeline::{TimelineAction, TimelineEntry, TimelineWidget};
 pub use toast::{Toast, ToastManager, ToastType};
 pub use topbar::TopBarWidget;
@@ -0,0 +1,5 @@
+//! Side pane showing the changes of an edit.
+//!
+//! This module re-exports from wonop-tui-render.
+
+pub use wonopcode_tui_render::preview::*;
@@ -0,0 +1,5 @@
+//! Hunk-by-hunk review of the changes of a session.
+//!
+//! This module re-exports from wonop-tui-render.
+
+pub use wonopcode_tui_render::review::*;
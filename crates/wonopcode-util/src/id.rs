@@ -12,6 +12,9 @@ pub enum IdPrefix {
     Message,
     Part,
     Project,
+    Audit,
+    Job,
+    Participant,
 }
 
 impl IdPrefix {
@@ -22,6 +25,9 @@ impl IdPrefix {
             IdPrefix::Message => "msg",
             IdPrefix::Part => "prt",
             IdPrefix::Project => "prj",
+            IdPrefix::Audit => "aud",
+            IdPrefix::Job => "job",
+            IdPrefix::Participant => "ptc",
         }
     }
 
@@ -32,6 +38,9 @@ impl IdPrefix {
             "msg" => Some(IdPrefix::Message),
             "prt" => Some(IdPrefix::Part),
             "prj" => Some(IdPrefix::Project),
+            "aud" => Some(IdPrefix::Audit),
+            "job" => Some(IdPrefix::Job),
+            "ptc" => Some(IdPrefix::Participant),
             _ => None,
         }
     }
@@ -107,6 +116,21 @@ impl Identifier {
     pub fn project() -> String {
         Self::ascending(IdPrefix::Project)
     }
+
+    /// Generate an audit log entry ID (ascending for chronological order).
+    pub fn audit() -> String {
+        Self::ascending(IdPrefix::Audit)
+    }
+
+    /// Generate a job ID (ascending for chronological order).
+    pub fn job() -> String {
+        Self::ascending(IdPrefix::Job)
+    }
+
+    /// Generate a participant ID (ascending for chronological order).
+    pub fn participant() -> String {
+        Self::ascending(IdPrefix::Participant)
+    }
 }
 
 #[cfg(test)]
@@ -163,6 +187,9 @@ mod tests {
         assert!(Identifier::message().starts_with("msg_"));
         assert!(Identifier::part().starts_with("prt_"));
         assert!(Identifier::project().starts_with("prj_"));
+        assert!(Identifier::audit().starts_with("aud_"));
+        assert!(Identifier::job().starts_with("job_"));
+        assert!(Identifier::participant().starts_with("ptc_"));
     }
 
     #[test]
@@ -171,6 +198,9 @@ mod tests {
         assert_eq!(IdPrefix::Message.as_str(), "msg");
         assert_eq!(IdPrefix::Part.as_str(), "prt");
         assert_eq!(IdPrefix::Project.as_str(), "prj");
+        assert_eq!(IdPrefix::Audit.as_str(), "aud");
+        assert_eq!(IdPrefix::Job.as_str(), "job");
+        assert_eq!(IdPrefix::Participant.as_str(), "ptc");
     }
 
     #[test]
@@ -179,6 +209,9 @@ mod tests {
         assert_eq!(IdPrefix::parse("msg"), Some(IdPrefix::Message));
         assert_eq!(IdPrefix::parse("prt"), Some(IdPrefix::Part));
         assert_eq!(IdPrefix::parse("prj"), Some(IdPrefix::Project));
+        assert_eq!(IdPrefix::parse("aud"), Some(IdPrefix::Audit));
+        assert_eq!(IdPrefix::parse("job"), Some(IdPrefix::Job));
+        assert_eq!(IdPrefix::parse("ptc"), Some(IdPrefix::Participant));
         assert_eq!(IdPrefix::parse("unknown"), None);
     }
 
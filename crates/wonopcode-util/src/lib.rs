@@ -10,6 +10,7 @@
 //! - Bash permission configuration
 //! - RAII-based timing for operation measurement
 //! - Performance monitoring and metrics
+//! - Byte size parsing and formatting
 
 pub mod bash_permission;
 pub mod error;
@@ -18,6 +19,7 @@ pub mod id;
 pub mod log;
 pub mod path;
 pub mod perf;
+pub mod size;
 pub mod timing;
 pub mod wildcard;
 
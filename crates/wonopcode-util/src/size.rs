@@ -0,0 +1,80 @@
+//! Byte size parsing and formatting.
+//!
+//! Sizes use binary units throughout: "1K" and "1KiB" are both 1024 bytes.
+
+const KIB: u64 = 1024;
+const MIB: u64 = KIB * 1024;
+const GIB: u64 = MIB * 1024;
+const TIB: u64 = GIB * 1024;
+
+/// Parse a size string like "2G", "512MB", "1KiB" or "1024" to bytes.
+///
+/// Returns `None` if the string is not a size or the size doesn't fit in a
+/// `u64`.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim().to_uppercase();
+    let s = s
+        .strip_suffix("IB")
+        .or_else(|| s.strip_suffix('B'))
+        .unwrap_or(&s);
+    let (num_str, multiplier) = if let Some(n) = s.strip_suffix('T') {
+        (n, TIB)
+    } else if let Some(n) = s.strip_suffix('G') {
+        (n, GIB)
+    } else if let Some(n) = s.strip_suffix('M') {
+        (n, MIB)
+    } else if let Some(n) = s.strip_suffix('K') {
+        (n, KIB)
+    } else {
+        (s, 1)
+    };
+
+    num_str.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Format bytes as a human-readable string, e.g. "1.5 MiB".
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= TIB {
+        format!("{:.1} TiB", bytes as f64 / TIB as f64)
+    } else if bytes >= GIB {
+        format!("{:.1} GiB", bytes as f64 / GIB as f64)
+    } else if bytes >= MIB {
+        format!("{:.1} MiB", bytes as f64 / MIB as f64)
+    } else if bytes >= KIB {
+        format!("{:.1} KiB", bytes as f64 / KIB as f64)
+    } else {
+        format!("{bytes} B")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("2G"), Some(2 * GIB));
+        assert_eq!(parse_size("512M"), Some(512 * MIB));
+        assert_eq!(parse_size("512MB"), Some(512 * MIB));
+        assert_eq!(parse_size("1KiB"), Some(1024));
+        assert_eq!(parse_size("1t"), Some(TIB));
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("10B"), Some(10));
+        assert_eq!(parse_size("lots"), None);
+    }
+
+    #[test]
+    fn test_parse_size_overflow() {
+        assert_eq!(parse_size("99999999999G"), None);
+        assert_eq!(parse_size("18446744073709551615"), Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(MIB), "1.0 MiB");
+        assert_eq!(format_bytes(2 * GIB), "2.0 GiB");
+    }
+}
@@ -0,0 +1,307 @@
+//! Running batch pipelines.
+//!
+//! [`run`] runs the steps of a [`Batch`] in order. Prompt steps go to an
+//! [`Unattended`] runner per working directory, so the prompts of a batch
+//! share a conversation and later ones know what earlier ones did. Shell
+//! steps run with `sh -c` (`cmd /C` on Windows). See
+//! [`wonopcode_core::batch`] for the file format.
+
+use crate::commands::{get_default_model, parse_model_spec};
+use crate::unattended::Unattended;
+use chrono::Utc;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Instant;
+use wonopcode_core::prompt_template::{render_prompt, PromptVars};
+use wonopcode_core::{Batch, BatchReport, BatchStep, Instance, StepAction, StepReport, StepStatus};
+use wonopcode_tui::AppAction;
+
+/// Run `batch`, printing progress to stderr.
+///
+/// `default_provider` is used for models given without a provider and, with
+/// its default model, when neither the step nor the config sets one. After a
+/// failed step the rest are skipped, unless it may fail.
+pub async fn run(batch: &Batch, default_provider: &str) -> BatchReport {
+    let started = Instant::now();
+    let mut report = BatchReport::new(batch);
+    let mut sessions: HashMap<PathBuf, Session> = HashMap::new();
+    let mut stopped = false;
+    let total = batch.steps.len();
+
+    for (i, step) in batch.steps.iter().enumerate() {
+        let label = format!("[{}/{total}] {} ({})", i + 1, step.name, step.action.kind());
+        if stopped {
+            eprintln!("{label} skipped");
+            report.push(StepReport::skipped(step));
+            continue;
+        }
+
+        eprintln!("{label}...");
+        let outcome = run_step(step, &report, &mut sessions, default_provider).await;
+        let seconds = outcome.duration_ms as f64 / 1000.0;
+        match &outcome.error {
+            Some(error) => eprintln!("{label} failed in {seconds:.1}s: {error}"),
+            None => eprintln!("{label} succeeded in {seconds:.1}s"),
+        }
+        if outcome.status == StepStatus::Failed && !step.continue_on_error {
+            stopped = true;
+        }
+        report.push(outcome);
+    }
+
+    for session in sessions.into_values() {
+        session.stop().await;
+    }
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    report
+}
+
+/// Run one step, with the outcomes of earlier steps for its template.
+async fn run_step(
+    step: &BatchStep,
+    report: &BatchReport,
+    sessions: &mut HashMap<PathBuf, Session>,
+    default_provider: &str,
+) -> StepReport {
+    let mut vars = PromptVars::detect(&step.cwd);
+    report.add_vars(&mut vars);
+
+    let started = Instant::now();
+    let mut outcome = StepReport {
+        status: StepStatus::Succeeded,
+        started_at: Utc::now().timestamp_millis(),
+        ..StepReport::skipped(step)
+    };
+
+    match &step.action {
+        StepAction::Shell(command) => {
+            let command = render_prompt(command, &vars);
+            match shell(&command, &step.cwd).await {
+                Ok((code, output)) => {
+                    outcome.exit_code = code;
+                    outcome.output = output;
+                    if code != Some(0) {
+                        outcome.status = StepStatus::Failed;
+                        outcome.error = Some(match code {
+                            Some(code) => format!("exited with code {code}"),
+                            None => "terminated by a signal".to_string(),
+                        });
+                    }
+                }
+                Err(e) => {
+                    outcome.status = StepStatus::Failed;
+                    outcome.error = Some(format!("failed to run: {e}"));
+                }
+            }
+            outcome.input = Some(command);
+        }
+        StepAction::Prompt(prompt) => {
+            let prompt = render_prompt(prompt, &vars);
+            outcome.input = Some(prompt.clone());
+            match send_prompt(step, prompt, sessions, default_provider, &mut outcome).await {
+                Ok(response) => outcome.output = response,
+                Err(e) => {
+                    outcome.status = StepStatus::Failed;
+                    outcome.error = Some(e);
+                }
+            }
+        }
+    }
+
+    outcome.duration_ms = started.elapsed().as_millis() as u64;
+    outcome
+}
+
+/// Send a prompt step to the runner for its directory, switching it to the
+/// step's model and agent first.
+async fn send_prompt(
+    step: &BatchStep,
+    prompt: String,
+    sessions: &mut HashMap<PathBuf, Session>,
+    default_provider: &str,
+    outcome: &mut StepReport,
+) -> Result<String, String> {
+    let model = step
+        .model
+        .as_deref()
+        .map(|model| model_spec(model, default_provider));
+    let session = match sessions.entry(step.cwd.clone()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let session = Session::start(&step.cwd, model.as_deref(), default_provider).await?;
+            entry.insert(session)
+        }
+    };
+
+    let model = model.unwrap_or_else(|| session.default_model.clone());
+    if model != session.model {
+        session
+            .runner
+            .configure(AppAction::ChangeModel(model.clone()))
+            .await?;
+        session.model = model;
+    }
+    let agent = step
+        .agent
+        .clone()
+        .unwrap_or_else(|| session.default_agent.clone());
+    if agent != session.agent {
+        session
+            .runner
+            .configure(AppAction::ChangeAgent(agent.clone()))
+            .await?;
+        session.agent = agent;
+    }
+
+    outcome.model = Some(session.model.clone());
+    outcome.agent = Some(session.agent.clone());
+    session.runner.prompt(prompt).await
+}
+
+/// A runner for the prompt steps in one directory.
+struct Session {
+    instance: Instance,
+    runner: Unattended,
+    /// The model and agent the runner uses now.
+    model: String,
+    agent: String,
+    /// What steps without a model or agent use.
+    default_model: String,
+    default_agent: String,
+}
+
+impl Session {
+    async fn start(
+        cwd: &Path,
+        model: Option<&str>,
+        default_provider: &str,
+    ) -> Result<Self, String> {
+        let instance = Instance::new(cwd)
+            .await
+            .map_err(|e| format!("Failed to open {}: {e}", cwd.display()))?;
+        let config = instance.config().await;
+        let default_model = match &config.model {
+            Some(model) => model_spec(model, default_provider),
+            None => format!("{default_provider}/{}", get_default_model(default_provider)),
+        };
+        let default_agent = config
+            .default_agent
+            .clone()
+            .unwrap_or_else(|| "build".to_string());
+
+        let model = model.map(str::to_string).unwrap_or(default_model.clone());
+        let (provider, model_id) = parse_model_spec(&model, default_provider);
+        let runner = match Unattended::start(&instance, provider, model_id).await {
+            Ok(runner) => runner,
+            Err(e) => {
+                instance.dispose().await;
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            instance,
+            runner,
+            model,
+            agent: default_agent.clone(),
+            default_model,
+            default_agent,
+        })
+    }
+
+    async fn stop(self) {
+        self.runner.stop();
+        self.instance.dispose().await;
+    }
+}
+
+/// A model as `provider/model`.
+fn model_spec(model: &str, default_provider: &str) -> String {
+    let (provider, model_id) = parse_model_spec(model, default_provider);
+    format!("{provider}/{model_id}")
+}
+
+/// Run a shell command in `cwd`, returning its exit code and its stdout
+/// followed by its stderr.
+async fn shell(command: &str, cwd: &Path) -> std::io::Result<(Option<i32>, String)> {
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    };
+
+    let output = cmd.current_dir(cwd).stdin(Stdio::null()).output().await?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok((output.status.code(), text))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    async fn run_yaml(yaml: &str) -> BatchReport {
+        let dir = tempfile::tempdir().unwrap();
+        let batch = Batch::parse(yaml, "test", dir.path()).unwrap();
+        run(&batch, "anthropic").await
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_skips_the_rest() {
+        let report = run_yaml(
+            r#"
+steps:
+  - run: echo one
+  - run: exit 3
+  - run: echo three
+"#,
+        )
+        .await;
+
+        assert!(!report.succeeded());
+        let statuses: Vec<_> = report.steps.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                StepStatus::Succeeded,
+                StepStatus::Failed,
+                StepStatus::Skipped
+            ]
+        );
+        assert_eq!(report.steps[0].output, "one\n");
+        assert_eq!(report.steps[1].exit_code, Some(3));
+        assert_eq!(report.steps[1].error.as_deref(), Some("exited with code 3"));
+    }
+
+    #[tokio::test]
+    async fn test_continue_on_error_and_outputs() {
+        let report = run_yaml(
+            r#"
+steps:
+  - name: check
+    run: echo broken; exit 1
+    continue_on_error: true
+  - name: fix
+    run: echo "fixing {{steps.check.output}} ({{steps.check.exit_code}})"
+"#,
+        )
+        .await;
+
+        // The batch failed, but the second step still ran
+        assert!(!report.succeeded());
+        assert_eq!(report.count(StepStatus::Failed), 1);
+        let fix = &report.steps[1];
+        assert_eq!(fix.status, StepStatus::Succeeded);
+        assert_eq!(fix.input.as_deref(), Some(r#"echo "fixing broken (1)""#));
+        assert_eq!(fix.output, "fixing broken (1)\n");
+    }
+}
@@ -1,9 +1,18 @@
 //! Agent management command handlers.
 //!
-//! Handles listing available agents and showing detailed agent configuration.
+//! Handles listing available agents, showing detailed agent configuration,
+//! and importing and exporting agent packs.
+//!
+//! An agent pack is a `.tar.gz` or `.zip` archive of agent files under
+//! `agents/`.
 
+use anyhow::{bail, Context};
 use clap::Subcommand;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use wonopcode_core::agent::{agent_dirs, is_agent_file, validate_agent_name, AgentFile};
+use wonopcode_core::config::AgentConfig;
 
 /// Agent subcommands.
 #[derive(Subcommand)]
@@ -15,6 +24,24 @@ pub enum AgentCommands {
         /// Agent name
         name: String,
     },
+    /// Export custom agents to an agent pack (.tar.gz or .zip)
+    Export {
+        /// Archive to write
+        output: PathBuf,
+        /// Agents to export (default: all custom agents)
+        names: Vec<String>,
+    },
+    /// Import agents from an agent pack (.tar.gz or .zip)
+    Import {
+        /// Archive to read
+        archive: PathBuf,
+        /// Install into the global agents directory instead of the project
+        #[arg(long)]
+        global: bool,
+        /// Overwrite existing agent files
+        #[arg(long)]
+        force: bool,
+    },
 }
 
 /// Handle agent commands.
@@ -30,6 +57,72 @@ pub async fn handle_agent(command: AgentCommands, cwd: &Path) -> anyhow::Result<
     let registry = AgentRegistry::new(&config);
 
     match command {
+        AgentCommands::Export { output, names } => {
+            let agents = config.agent.unwrap_or_default();
+            let names = if names.is_empty() {
+                let mut names: Vec<String> = agents.keys().cloned().collect();
+                names.sort();
+                names
+            } else {
+                names
+            };
+            if names.is_empty() {
+                bail!("No custom agents to export");
+            }
+
+            let files = pack_entries(&agents, &names)?;
+            write_pack(&output, &files)?;
+            println!("Exported {} agent(s) to {}", files.len(), output.display());
+        }
+        AgentCommands::Import {
+            archive,
+            global,
+            force,
+        } => {
+            let config_dir = if global {
+                Config::global_config_dir()
+                    .context("Could not determine the global config directory")?
+            } else {
+                cwd.join(".wonopcode")
+            };
+            let target = config_dir.join("agents");
+
+            let files = read_pack(&archive)?;
+            if files.is_empty() {
+                bail!("No agent files found in {}", archive.display());
+            }
+
+            // Validate everything before writing anything
+            for (file_name, content) in &files {
+                AgentFile::parse(Path::new(file_name), content)
+                    .map_err(|e| anyhow::anyhow!("{file_name}: {e}"))?;
+            }
+            let existing = existing_agent_files(&config_dir, &files);
+            if !existing.is_empty() && !force {
+                let existing: Vec<_> = existing.iter().map(|p| p.display().to_string()).collect();
+                bail!(
+                    "Agent files already exist: {} (use --force to overwrite)",
+                    existing.join(", ")
+                );
+            }
+
+            // The same agent in another format or directory would shadow
+            // the imported one
+            for path in existing {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            std::fs::create_dir_all(&target)?;
+            for (file_name, content) in &files {
+                std::fs::write(target.join(file_name), content)?;
+                println!("  {file_name}");
+            }
+            println!(
+                "Imported {} agent file(s) into {}",
+                files.len(),
+                target.display()
+            );
+        }
         AgentCommands::List => {
             println!();
             println!("Available Agents");
@@ -176,3 +269,183 @@ pub async fn handle_agent(command: AgentCommands, cwd: &Path) -> anyhow::Result<
 
     Ok(())
 }
+
+/// Agent pack entries for the named custom agents, as `(path, content)`.
+fn pack_entries(
+    agents: &HashMap<String, AgentConfig>,
+    names: &[String],
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for name in names {
+        validate_agent_name(name).map_err(anyhow::Error::msg)?;
+        let config = agents
+            .get(name)
+            .with_context(|| format!("Agent '{name}' is not a custom agent"))?;
+        let file = AgentFile {
+            name: name.clone(),
+            config: config.clone(),
+        };
+        files.push((format!("agents/{name}.md"), file.to_markdown()));
+    }
+    Ok(files)
+}
+
+/// Agent files under `config_dir` with the same stem as one of `files`, in
+/// any format and agent directory.
+fn existing_agent_files(config_dir: &Path, files: &[(String, String)]) -> Vec<PathBuf> {
+    let stems: HashSet<&str> = files
+        .iter()
+        .filter_map(|(name, _)| Path::new(name).file_stem()?.to_str())
+        .collect();
+    agent_dirs(config_dir)
+        .iter()
+        .flat_map(|dir| AgentFile::paths_in(dir))
+        .filter(|path| {
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(|stem| stems.contains(stem))
+        })
+        .collect()
+}
+
+/// Write agent files to a `.zip` or `.tar.gz` archive, by extension.
+fn write_pack(path: &Path, files: &[(String, String)]) -> anyhow::Result<()> {
+    let out = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+
+    if is_zip(path) {
+        let mut zip = zip::ZipWriter::new(out);
+        for (name, content) in files {
+            zip.start_file(name, zip::write::FileOptions::default())?;
+            zip.write_all(content.as_bytes())?;
+        }
+        zip.finish()?;
+    } else {
+        let encoder = flate2::write::GzEncoder::new(out, flate2::Compression::default());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            tar.append_data(&mut header, name, content.as_bytes())?;
+        }
+        tar.into_inner()?.finish()?;
+    }
+    Ok(())
+}
+
+/// Read the agent files in a `.zip` or `.tar.gz` archive, by extension.
+///
+/// Returns `(file name, content)` pairs. Directories inside the archive are
+/// dropped, so files can only be written into the agents directory.
+fn read_pack(path: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let input =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut files = Vec::new();
+    let mut add = |entry_path: &Path, content: String| {
+        if let Some(name) = entry_path.file_name().and_then(|n| n.to_str()) {
+            if is_agent_file(Path::new(name)) && !name.starts_with('.') {
+                files.push((name.to_string(), content));
+            }
+        }
+    };
+
+    if is_zip(path) {
+        let mut archive = zip::ZipArchive::new(input)?;
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let entry_path = PathBuf::from(file.name());
+            let mut content = String::new();
+            file.read_to_string(&mut content)?;
+            add(&entry_path, content);
+        }
+    } else {
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(input));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let entry_path = entry.path()?.into_owned();
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            add(&entry_path, content);
+        }
+    }
+    Ok(files)
+}
+
+/// Whether an agent pack path names a zip archive.
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agents() -> HashMap<String, AgentConfig> {
+        let reviewer = AgentConfig {
+            description: Some("Reviews changes".to_string()),
+            prompt: Some("Review the diff.".to_string()),
+            ..Default::default()
+        };
+        HashMap::from([("reviewer".to_string(), reviewer)])
+    }
+
+    #[test]
+    fn test_pack_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = pack_entries(&agents(), &["reviewer".to_string()]).unwrap();
+        for name in ["agents.tar.gz", "agents.zip"] {
+            let path = dir.path().join(name);
+            write_pack(&path, &files).unwrap();
+            let read = read_pack(&path).unwrap();
+            assert_eq!(read.len(), 1, "{name}");
+            assert_eq!(read[0].0, "reviewer.md");
+            let agent = AgentFile::parse(Path::new(&read[0].0), &read[0].1).unwrap();
+            assert_eq!(agent.name, "reviewer");
+            assert_eq!(agent.config.prompt.as_deref(), Some("Review the diff."));
+        }
+    }
+
+    #[test]
+    fn test_agent_names_with_paths_are_rejected() {
+        let mut agents = agents();
+        for name in ["../evil", "nested/evil", "nested\\evil"] {
+            agents.insert(name.to_string(), AgentConfig::default());
+            assert!(
+                pack_entries(&agents, &[name.to_string()]).is_err(),
+                "{name}"
+            );
+        }
+        let content = "---\nname: ../evil\n---\nPrompt\n";
+        assert!(AgentFile::parse(Path::new("evil.md"), content).is_err());
+    }
+
+    #[test]
+    fn test_existing_agents_are_found_in_any_format() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("agents")).unwrap();
+        std::fs::create_dir_all(dir.path().join("agent")).unwrap();
+        std::fs::write(dir.path().join("agents/reviewer.toml"), "").unwrap();
+        std::fs::write(dir.path().join("agent/reviewer.md"), "").unwrap();
+        std::fs::write(dir.path().join("agents/docs.md"), "").unwrap();
+
+        let files = vec![("reviewer.md".to_string(), String::new())];
+        let mut existing = existing_agent_files(dir.path(), &files);
+        existing.sort();
+        assert_eq!(
+            existing,
+            vec![
+                dir.path().join("agent/reviewer.md"),
+                dir.path().join("agents/reviewer.toml"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Audit log command handlers.
+//!
+//! Handles reviewing the recorded permission decisions of a project.
+
+use clap::Subcommand;
+use std::path::Path;
+use wonopcode_core::{PermissionAudit, PermissionAuditFilter};
+
+/// Audit subcommands.
+#[derive(Subcommand)]
+pub enum AuditCommands {
+    /// Show recorded permission decisions, oldest first
+    Permissions {
+        /// Only decisions of this session
+        #[arg(short, long)]
+        session: Option<String>,
+        /// Only denied checks
+        #[arg(short, long)]
+        denied: bool,
+        /// Show only the most recent N decisions
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+/// Handle audit commands.
+pub async fn handle_audit(command: AuditCommands, cwd: &Path) -> anyhow::Result<()> {
+    let instance = wonopcode_core::Instance::new(cwd).await?;
+
+    match command {
+        AuditCommands::Permissions {
+            session,
+            denied,
+            limit,
+            json,
+        } => {
+            let audit =
+                PermissionAudit::new(instance.storage().clone(), instance.project_id().await);
+            let filter = PermissionAuditFilter {
+                session_id: session,
+                denied_only: denied,
+                limit,
+            };
+            let entries = audit.list(&filter).await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else if entries.is_empty() {
+                println!("No permission decisions recorded.");
+            } else {
+                println!(
+                    "{:<20} {:<7} {:<10} {:<40} SOURCE",
+                    "TIME", "RESULT", "TOOL", "TARGET"
+                );
+                println!("{}", "-".repeat(100));
+
+                for entry in entries {
+                    let time = chrono::DateTime::from_timestamp_millis(entry.time)
+                        .map(|t| {
+                            t.with_timezone(&chrono::Local)
+                                .format("%Y-%m-%d %H:%M:%S")
+                                .to_string()
+                        })
+                        .unwrap_or_default();
+                    let result = if entry.allowed { "allow" } else { "deny" };
+                    let target = entry
+                        .path
+                        .as_deref()
+                        .or(entry.action.as_deref())
+                        .unwrap_or("-");
+                    let target = if target.chars().count() > 40 {
+                        let tail: String =
+                            target.chars().skip(target.chars().count() - 37).collect();
+                        format!("...{tail}")
+                    } else {
+                        target.to_string()
+                    };
+                    println!(
+                        "{:<20} {:<7} {:<10} {:<40} {}",
+                        time,
+                        result,
+                        entry.tool,
+                        target,
+                        entry.source()
+                    );
+                    if !entry.description.is_empty() {
+                        println!("    {}", entry.description);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+//! Batch command handler.
+//!
+//! Runs a batch file with [`crate::batch::run`] and prints the report, as
+//! JSON for scripts or as a summary for people.
+
+use std::path::Path;
+use wonopcode_core::{Batch, BatchReport, StepStatus};
+
+/// Handle the batch command.
+pub async fn handle_batch(
+    file: &Path,
+    format: &str,
+    report_path: Option<&Path>,
+    default_provider: &str,
+) -> anyhow::Result<()> {
+    let batch = Batch::load(file)?;
+    let report = crate::batch::run(&batch, default_provider).await;
+
+    let json = serde_json::to_string_pretty(&report)?;
+    if let Some(path) = report_path {
+        std::fs::write(path, &json)?;
+    }
+    match format {
+        "json" => println!("{json}"),
+        _ => print_summary(&report),
+    }
+
+    if !report.succeeded() {
+        anyhow::bail!(
+            "Batch '{}' failed: {} of {} steps failed",
+            report.name,
+            report.count(StepStatus::Failed),
+            report.steps.len()
+        );
+    }
+    Ok(())
+}
+
+fn print_summary(report: &BatchReport) {
+    for step in &report.steps {
+        let seconds = step.duration_ms as f64 / 1000.0;
+        println!(
+            "  {:<9} {:<20} {:<7} {seconds:>6.1}s",
+            step.status.as_str(),
+            step.name,
+            step.kind
+        );
+        if let Some(error) = &step.error {
+            println!("            {error}");
+        }
+    }
+    println!();
+    println!(
+        "{}: {} succeeded, {} failed, {} skipped in {:.1}s",
+        report.name,
+        report.count(StepStatus::Succeeded),
+        report.count(StepStatus::Failed),
+        report.count(StepStatus::Skipped),
+        report.duration_ms as f64 / 1000.0
+    );
+}
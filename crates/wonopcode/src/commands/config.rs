@@ -0,0 +1,186 @@
+//! Config command handlers.
+//!
+//! Handles reading one config key, setting one in the project or global
+//! config file without losing its comments, and editing a config file in
+//! `$VISUAL`/`$EDITOR`, checked before it is saved.
+
+use anyhow::Context;
+use clap::Subcommand;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use wonopcode_core::config_edit::{get_value, parse_value, set_value};
+use wonopcode_core::Config;
+
+/// Config subcommands.
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print the value of a key, e.g. `tui.theme`, in the merged config
+    Get {
+        /// Dot-separated key
+        key: String,
+    },
+    /// Set a key in the project config (or the global one with --global)
+    Set {
+        /// Dot-separated key
+        key: String,
+        /// Value: JSON such as `true`, `8192` or `["a"]`, or else a string
+        value: String,
+        /// Change the global config instead of the project config
+        #[arg(short, long)]
+        global: bool,
+    },
+    /// Open the project config (or the global one with --global) in $EDITOR
+    Edit {
+        /// Edit the global config instead of the project config
+        #[arg(short, long)]
+        global: bool,
+    },
+}
+
+/// Handle config commands.
+pub async fn handle_config(command: ConfigCommands, cwd: &Path) -> anyhow::Result<()> {
+    match command {
+        ConfigCommands::Get { key } => {
+            let (config, _) = Config::load(Some(cwd)).await?;
+            let config = serde_json::to_value(&config)?;
+            let Some(value) = get_value(&config, &key) else {
+                anyhow::bail!("'{key}' is not set");
+            };
+            match value {
+                serde_json::Value::String(s) => println!("{s}"),
+                value => println!("{}", serde_json::to_string_pretty(value)?),
+            }
+        }
+        ConfigCommands::Set { key, value, global } => {
+            let path = config_path(cwd, global)?;
+            let content = read_config(&path)?;
+            let value = parse_value(&value);
+            let updated = set_value(&content, &key, &value)?;
+
+            check(&updated, &path)?;
+            let config = serde_json::to_value(Config::parse_file_content(&updated, &path)?)?;
+            if get_value(&config, &key).is_none() {
+                anyhow::bail!("'{key}' is not a config key");
+            }
+
+            write_config(&path, &updated)?;
+            println!("Set {key} in {}", path.display());
+        }
+        ConfigCommands::Edit { global } => {
+            let path = config_path(cwd, global)?;
+            let original = read_config(&path)?;
+            let content = if original.trim().is_empty() {
+                "{\n}\n".to_string()
+            } else {
+                original.clone()
+            };
+            let Some(edited) = edit_until_valid(&path, &content)? else {
+                println!("Discarded changes to {}", path.display());
+                return Ok(());
+            };
+            if edited == original {
+                println!("No changes to {}", path.display());
+            } else {
+                write_config(&path, &edited)?;
+                println!("Saved {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The project or global config file.
+fn config_path(cwd: &Path, global: bool) -> anyhow::Result<PathBuf> {
+    if global {
+        Config::global_config_path().context("Could not determine the config directory")
+    } else {
+        Ok(Config::project_config_path(cwd))
+    }
+}
+
+/// The content of the config file at `path`; empty if there is none.
+fn read_config(path: &Path) -> anyhow::Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn write_config(path: &Path, content: &str) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Check that `content` is a valid config for the file at `path`.
+fn check(content: &str, path: &Path) -> anyhow::Result<()> {
+    Config::parse_file_content(content, path)?;
+    Ok(())
+}
+
+/// Let the user edit `content` until it is a valid config, or give up.
+///
+/// Returns the edited content, or `None` if the user gave up.
+fn edit_until_valid(path: &Path, content: &str) -> anyhow::Result<Option<String>> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| default_editor().to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("$EDITOR is empty")?;
+    let args: Vec<&str> = parts.collect();
+
+    // Edit a copy, so an invalid config never replaces the real one
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("json");
+    let temp = std::env::temp_dir().join(format!(
+        "wonopcode-config-{}.{extension}",
+        std::process::id()
+    ));
+    std::fs::write(&temp, content)?;
+    let result = edit_loop(&editor, program, &args, &temp, path);
+    let _ = std::fs::remove_file(&temp);
+    result
+}
+
+fn edit_loop(
+    editor: &str,
+    program: &str,
+    args: &[&str],
+    temp: &Path,
+    path: &Path,
+) -> anyhow::Result<Option<String>> {
+    loop {
+        let status = std::process::Command::new(program)
+            .args(args)
+            .arg(temp)
+            .status()
+            .with_context(|| format!("Failed to run {editor}"))?;
+        if !status.success() {
+            anyhow::bail!("{editor} exited with {status}");
+        }
+
+        let edited = std::fs::read_to_string(temp)?;
+        match check(&edited, path) {
+            Ok(()) => return Ok(Some(edited)),
+            Err(e) => {
+                eprintln!("Invalid config: {e}");
+                eprint!("Edit again? [Y/n] ");
+                std::io::stderr().flush()?;
+                let mut answer = String::new();
+                let read = std::io::stdin().read_line(&mut answer)?;
+                if read == 0 || answer.trim().eq_ignore_ascii_case("n") {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+fn default_editor() -> &'static str {
+    if cfg!(windows) {
+        "notepad"
+    } else {
+        "vi"
+    }
+}
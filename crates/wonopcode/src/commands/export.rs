@@ -271,17 +271,12 @@ pub async fn handle_import(cwd: &Path, input: PathBuf) -> anyhow::Result<()> {
 
         // Save messages and parts
         for msg_with_parts in &session_import.messages {
-            if let Err(e) = repo.save_message(&msg_with_parts.message).await {
+            if let Err(e) = repo
+                .save_message_with_parts(&msg_with_parts.message, &msg_with_parts.parts)
+                .await
+            {
                 eprintln!("Error importing message in session {}: {}", session.id, e);
             }
-            for part in &msg_with_parts.parts {
-                if let Err(e) = repo.save_part(part).await {
-                    eprintln!(
-                        "Error importing message part in session {}: {}",
-                        session.id, e
-                    );
-                }
-            }
         }
 
         println!("Imported session: {} ({})", session.id, session.title);
@@ -0,0 +1,600 @@
+//! Init command handler.
+//!
+//! `wonopcode init` sets a repository up for wonopcode: a `wonopcode.json`
+//! with the model, sandbox and permission preset asked for, an `AGENTS.md`
+//! starter for the detected stack, example commands in
+//! `.wonopcode/commands/`, and `.gitignore` entries for what wonopcode keeps
+//! in `.wonopcode/`. Existing files are left alone unless forced.
+
+use super::get_default_model;
+use serde_json::json;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// What wonopcode writes to `.wonopcode/` that doesn't belong in git.
+const GITIGNORE_ENTRIES: [&str; 4] = [
+    ".wonopcode/snapshots/",
+    ".wonopcode/worktrees/",
+    ".wonopcode/data/",
+    ".wonopcode/todos.json",
+];
+
+/// How much the agent may do without asking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionPreset {
+    /// Ask before edits, shell commands and web fetches.
+    Cautious,
+    /// Edit files freely, ask before shell commands.
+    Balanced,
+    /// Allow everything inside the project.
+    Autonomous,
+}
+
+impl PermissionPreset {
+    const ALL: [Self; 3] = [Self::Cautious, Self::Balanced, Self::Autonomous];
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::Cautious => "cautious - ask before edits, shell commands and web fetches",
+            Self::Balanced => "balanced - edit files freely, ask before shell commands",
+            Self::Autonomous => "autonomous - allow everything inside the project",
+        }
+    }
+
+    /// The `permission` section of the config.
+    fn config(&self) -> serde_json::Value {
+        match self {
+            Self::Cautious => json!({
+                "edit": "ask",
+                "bash": "ask",
+                "webfetch": "ask",
+                "external_directory": "deny"
+            }),
+            Self::Balanced => json!({
+                "edit": "allow",
+                "bash": "ask",
+                "webfetch": "allow",
+                "external_directory": "ask"
+            }),
+            Self::Autonomous => json!({
+                "edit": "allow",
+                "bash": "allow",
+                "webfetch": "allow",
+                "external_directory": "ask"
+            }),
+        }
+    }
+}
+
+/// The answers to the questions of `wonopcode init`.
+#[derive(Debug, Clone)]
+pub struct InitOptions {
+    /// `provider/model`.
+    pub model: String,
+    /// Run tools in a container sandbox.
+    pub sandbox: bool,
+    pub permissions: PermissionPreset,
+}
+
+/// A language or build system found in the project, with its commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stack {
+    pub name: String,
+    pub build: Option<String>,
+    pub test: Option<String>,
+    pub lint: Option<String>,
+    pub format: Option<String>,
+}
+
+impl Stack {
+    fn new(name: &str, build: &str, test: &str, lint: &str, format: &str) -> Self {
+        let command = |c: &str| (!c.is_empty()).then(|| c.to_string());
+        Self {
+            name: name.to_string(),
+            build: command(build),
+            test: command(test),
+            lint: command(lint),
+            format: command(format),
+        }
+    }
+}
+
+/// The stacks of the project in `dir`, from its manifest files.
+pub fn detect_stacks(dir: &Path) -> Vec<Stack> {
+    let has = |name: &str| dir.join(name).exists();
+    let mut stacks = Vec::new();
+
+    if has("Cargo.toml") {
+        stacks.push(Stack::new(
+            "Rust",
+            "cargo build",
+            "cargo test",
+            "cargo clippy --all-targets -- -D warnings",
+            "cargo fmt",
+        ));
+    }
+    if let Ok(content) = std::fs::read_to_string(dir.join("package.json")) {
+        stacks.push(node_stack(dir, &content));
+    }
+    if has("pyproject.toml") || has("requirements.txt") || has("setup.py") {
+        let run = if has("uv.lock") {
+            "uv run "
+        } else if has("poetry.lock") {
+            "poetry run "
+        } else {
+            ""
+        };
+        stacks.push(Stack::new(
+            "Python",
+            "",
+            &format!("{run}pytest"),
+            &format!("{run}ruff check ."),
+            &format!("{run}ruff format ."),
+        ));
+    }
+    if has("go.mod") {
+        stacks.push(Stack::new(
+            "Go",
+            "go build ./...",
+            "go test ./...",
+            "go vet ./...",
+            "gofmt -w .",
+        ));
+    }
+    if has("pom.xml") {
+        stacks.push(Stack::new(
+            "Java (Maven)",
+            "mvn package",
+            "mvn test",
+            "",
+            "",
+        ));
+    } else if has("build.gradle") || has("build.gradle.kts") {
+        let gradle = if has("gradlew") {
+            "./gradlew"
+        } else {
+            "gradle"
+        };
+        stacks.push(Stack::new(
+            "JVM (Gradle)",
+            &format!("{gradle} build"),
+            &format!("{gradle} test"),
+            "",
+            "",
+        ));
+    }
+    stacks
+}
+
+/// The Node stack for a `package.json`: its package manager and scripts.
+fn node_stack(dir: &Path, package_json: &str) -> Stack {
+    let manager = [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+    ]
+    .iter()
+    .find(|(lock, _)| dir.join(lock).exists())
+    .map_or("npm", |(_, manager)| manager);
+
+    let package: serde_json::Value = serde_json::from_str(package_json).unwrap_or_default();
+    let script = |name: &str| {
+        package["scripts"]
+            .get(name)
+            .map(|_| match name {
+                "test" => format!("{manager} test"),
+                _ => format!("{manager} run {name}"),
+            })
+            .unwrap_or_default()
+    };
+    let name = if dir.join("tsconfig.json").exists() {
+        "TypeScript"
+    } else {
+        "JavaScript"
+    };
+    Stack::new(
+        name,
+        &script("build"),
+        &script("test"),
+        &script("lint"),
+        &script("format"),
+    )
+}
+
+/// The project config for `options`.
+pub fn config_json(options: &InitOptions) -> String {
+    let mut config = json!({
+        "model": options.model,
+        "permission": options.permissions.config(),
+    });
+    if options.sandbox {
+        config["sandbox"] = json!({
+            "enabled": true,
+            "runtime": "auto",
+            "network": "limited"
+        });
+    }
+    let mut text = serde_json::to_string_pretty(&config).unwrap_or_default();
+    text.push('\n');
+    text
+}
+
+/// A starter `AGENTS.md` for a project named `project` with `stacks`.
+pub fn agents_md(project: &str, stacks: &[Stack]) -> String {
+    let mut out = format!("# {project}\n\n");
+    out.push_str("Instructions for AI coding agents working in this repository.\n\n");
+
+    out.push_str("## Stack\n\n");
+    if stacks.is_empty() {
+        out.push_str("- TODO: languages, frameworks and tools\n");
+    }
+    for stack in stacks {
+        out.push_str(&format!("- {}\n", stack.name));
+    }
+
+    out.push_str("\n## Commands\n\n");
+    let mut commands = 0;
+    for stack in stacks {
+        for (label, command) in [
+            ("Build", &stack.build),
+            ("Test", &stack.test),
+            ("Lint", &stack.lint),
+            ("Format", &stack.format),
+        ] {
+            if let Some(command) = command {
+                out.push_str(&format!("- {label}: `{command}`\n"));
+                commands += 1;
+            }
+        }
+    }
+    if commands == 0 {
+        out.push_str("- TODO: how to build, test and lint\n");
+    }
+
+    out.push_str(
+        "\n## Conventions\n\n\
+         - Run the tests and the linter before finishing a change\n\
+         - Match the style of the surrounding code\n\
+         - TODO: naming, error handling, module layout\n",
+    );
+    out
+}
+
+/// Example commands for `.wonopcode/commands/`: file name and content.
+pub fn example_commands(stacks: &[Stack]) -> Vec<(&'static str, String)> {
+    let test = stacks
+        .iter()
+        .find_map(|stack| stack.test.as_deref())
+        .map_or("the tests".to_string(), |test| format!("`{test}`"));
+    vec![
+        (
+            "review.md",
+            "---\n\
+             description: Review the uncommitted changes\n\
+             ---\n\
+             Review the uncommitted changes in this repository (`git diff HEAD`). \
+             Point out bugs, missing tests and anything that doesn't follow AGENTS.md. \
+             $ARGUMENTS\n"
+                .to_string(),
+        ),
+        (
+            "test.md",
+            format!(
+                "---\n\
+                 description: Write tests for a file or module\n\
+                 arguments:\n  \
+                 - name: target\n    \
+                 description: File or module to test\n\
+                 ---\n\
+                 Write tests for {{{{target}}}} following the conventions in AGENTS.md, \
+                 then run {test} and fix any failures.\n"
+            ),
+        ),
+    ]
+}
+
+/// `.gitignore` lines to add to `existing` for [`GITIGNORE_ENTRIES`], if any
+/// are missing.
+pub fn gitignore_additions(existing: &str) -> Option<String> {
+    let present: Vec<&str> = existing
+        .lines()
+        .map(|line| line.trim().trim_start_matches('/'))
+        .collect();
+    let ignored = |entry: &str| {
+        present.iter().any(|line| {
+            *line == entry || *line == entry.trim_end_matches('/') || *line == ".wonopcode/"
+        })
+    };
+    let missing: Vec<&str> = GITIGNORE_ENTRIES
+        .into_iter()
+        .filter(|entry| !ignored(entry))
+        .collect();
+    if missing.is_empty() {
+        return None;
+    }
+
+    let mut out = String::new();
+    if !existing.is_empty() && !existing.ends_with('\n') {
+        out.push('\n');
+    }
+    if !existing.is_empty() {
+        out.push('\n');
+    }
+    out.push_str("# wonopcode\n");
+    for entry in missing {
+        out.push_str(entry);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+/// What happened to a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOutcome {
+    Created,
+    Overwritten,
+    Updated,
+    /// It exists and wasn't forced.
+    Kept,
+    /// It already has what it needs.
+    UpToDate,
+}
+
+/// Write the files for `options` to the project in `dir`.
+pub fn scaffold(
+    dir: &Path,
+    options: &InitOptions,
+    force: bool,
+) -> io::Result<Vec<(PathBuf, FileOutcome)>> {
+    let stacks = detect_stacks(dir);
+    let project = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Project".to_string());
+
+    let mut files = vec![
+        (
+            wonopcode_core::Config::project_config_path(dir),
+            config_json(options),
+        ),
+        (dir.join("AGENTS.md"), agents_md(&project, &stacks)),
+    ];
+    let commands_dir = dir.join(".wonopcode").join("commands");
+    for (name, content) in example_commands(&stacks) {
+        files.push((commands_dir.join(name), content));
+    }
+
+    let mut outcomes = Vec::new();
+    for (path, content) in files {
+        let exists = path.exists();
+        let outcome = match (exists, force) {
+            (false, _) => FileOutcome::Created,
+            (true, true) => FileOutcome::Overwritten,
+            (true, false) => FileOutcome::Kept,
+        };
+        if outcome != FileOutcome::Kept {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, content)?;
+        }
+        outcomes.push((path, outcome));
+    }
+
+    let gitignore = dir.join(".gitignore");
+    if gitignore.exists() || dir.join(".git").exists() {
+        let existing = std::fs::read_to_string(&gitignore).unwrap_or_default();
+        let outcome = match gitignore_additions(&existing) {
+            Some(additions) => {
+                let created = existing.is_empty() && !gitignore.exists();
+                std::fs::write(&gitignore, existing + &additions)?;
+                if created {
+                    FileOutcome::Created
+                } else {
+                    FileOutcome::Updated
+                }
+            }
+            None => FileOutcome::UpToDate,
+        };
+        outcomes.push((gitignore, outcome));
+    }
+    Ok(outcomes)
+}
+
+/// Ask `question` on stderr; an empty answer or end of input is `None`.
+fn ask(question: &str) -> io::Result<Option<String>> {
+    eprint!("{question}");
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok((!answer.is_empty()).then(|| answer.to_string()))
+}
+
+/// Ask for the [`InitOptions`], offering `default_model`.
+fn ask_options(default_model: String) -> io::Result<InitOptions> {
+    let model = ask(&format!("Model [{default_model}]: "))?.unwrap_or(default_model);
+
+    let sandbox = ask("Run tools in a container sandbox (needs Docker or Podman)? [y/N] ")?
+        .is_some_and(|answer| {
+            answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes")
+        });
+
+    eprintln!("Permissions:");
+    for (i, preset) in PermissionPreset::ALL.iter().enumerate() {
+        eprintln!("  {}. {}", i + 1, preset.describe());
+    }
+    let permissions = loop {
+        match ask("Choose [2]: ")? {
+            None => break PermissionPreset::Balanced,
+            Some(answer) => match answer.parse::<usize>() {
+                Ok(n) if (1..=PermissionPreset::ALL.len()).contains(&n) => {
+                    break PermissionPreset::ALL[n - 1]
+                }
+                _ => eprintln!("Enter 1, 2 or 3"),
+            },
+        }
+    };
+
+    Ok(InitOptions {
+        model,
+        sandbox,
+        permissions,
+    })
+}
+
+/// Handle `wonopcode init`.
+///
+/// With `yes`, nothing is asked: the model is `model` or the default of
+/// `default_provider`, without a sandbox and with balanced permissions.
+pub fn handle_init(
+    cwd: &Path,
+    yes: bool,
+    force: bool,
+    default_provider: &str,
+    model: Option<&str>,
+) -> anyhow::Result<()> {
+    let default_model = match model {
+        Some(model) if model.contains('/') => model.to_string(),
+        Some(model) => format!("{default_provider}/{model}"),
+        None => format!("{default_provider}/{}", get_default_model(default_provider)),
+    };
+
+    let stacks = detect_stacks(cwd);
+    if !stacks.is_empty() {
+        let names: Vec<&str> = stacks.iter().map(|s| s.name.as_str()).collect();
+        eprintln!("Detected: {}", names.join(", "));
+    }
+
+    let options = if yes {
+        InitOptions {
+            model: default_model,
+            sandbox: false,
+            permissions: PermissionPreset::Balanced,
+        }
+    } else {
+        ask_options(default_model)?
+    };
+
+    let outcomes = scaffold(cwd, &options, force)?;
+    println!();
+    for (path, outcome) in &outcomes {
+        let path = path.strip_prefix(cwd).unwrap_or(path).display();
+        match outcome {
+            FileOutcome::Created => println!("  created      {path}"),
+            FileOutcome::Overwritten => println!("  overwritten  {path}"),
+            FileOutcome::Updated => println!("  updated      {path}"),
+            FileOutcome::Kept => println!("  kept         {path} (exists; --force to overwrite)"),
+            FileOutcome::UpToDate => println!("  up to date   {path}"),
+        }
+    }
+    println!();
+    println!("Review AGENTS.md, or run /init in wonopcode to have the agent fill it in.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> InitOptions {
+        InitOptions {
+            model: "anthropic/claude-sonnet-4-5-20250929".to_string(),
+            sandbox: true,
+            permissions: PermissionPreset::Cautious,
+        }
+    }
+
+    #[test]
+    fn test_detect_stacks() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+        std::fs::write(
+            dir.path().join("package.json"),
+            r#"{"scripts": {"test": "vitest", "lint": "eslint ."}}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("pnpm-lock.yaml"), "").unwrap();
+        std::fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+
+        let stacks = detect_stacks(dir.path());
+        assert_eq!(stacks.len(), 2);
+        assert_eq!(stacks[0].name, "Rust");
+        assert_eq!(
+            stacks[1],
+            Stack {
+                name: "TypeScript".to_string(),
+                build: None,
+                test: Some("pnpm test".to_string()),
+                lint: Some("pnpm run lint".to_string()),
+                format: None,
+            }
+        );
+
+        let agents = agents_md("demo", &stacks);
+        assert!(agents.starts_with("# demo\n"));
+        assert!(agents.contains("- Test: `cargo test`\n"));
+        assert!(agents.contains("- Lint: `pnpm run lint`\n"));
+    }
+
+    #[test]
+    fn test_config_is_valid() {
+        let content = config_json(&options());
+        let config =
+            wonopcode_core::Config::parse_file_content(&content, Path::new("wonopcode.json"))
+                .unwrap();
+        assert_eq!(
+            config.model.as_deref(),
+            Some("anthropic/claude-sonnet-4-5-20250929")
+        );
+        assert_eq!(config.sandbox.unwrap().enabled, Some(true));
+        assert!(config.permission.unwrap().bash.is_some());
+    }
+
+    #[test]
+    fn test_gitignore_additions() {
+        assert_eq!(
+            gitignore_additions("target\n.wonopcode/snapshots\n/.wonopcode/data/\n").unwrap(),
+            "\n# wonopcode\n.wonopcode/worktrees/\n.wonopcode/todos.json\n"
+        );
+        assert!(gitignore_additions(".wonopcode/\n").is_none());
+        assert!(gitignore_additions("")
+            .unwrap()
+            .starts_with("# wonopcode\n"));
+    }
+
+    #[test]
+    fn test_scaffold_keeps_existing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join("AGENTS.md"), "Mine").unwrap();
+
+        let outcomes = scaffold(dir.path(), &options(), false).unwrap();
+        let outcome = |name: &str| {
+            outcomes
+                .iter()
+                .find(|(path, _)| path.ends_with(name))
+                .map(|(_, outcome)| *outcome)
+        };
+        assert_eq!(outcome("wonopcode.json"), Some(FileOutcome::Created));
+        assert_eq!(outcome("AGENTS.md"), Some(FileOutcome::Kept));
+        assert_eq!(outcome("review.md"), Some(FileOutcome::Created));
+        assert_eq!(outcome(".gitignore"), Some(FileOutcome::Created));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("AGENTS.md")).unwrap(),
+            "Mine"
+        );
+
+        // Running again changes nothing, unless forced
+        let again = scaffold(dir.path(), &options(), false).unwrap();
+        assert!(again
+            .iter()
+            .all(|(_, outcome)| matches!(outcome, FileOutcome::Kept | FileOutcome::UpToDate)));
+        let forced = scaffold(dir.path(), &options(), true).unwrap();
+        assert!(forced
+            .iter()
+            .any(|(path, outcome)| path.ends_with("AGENTS.md")
+                && *outcome == FileOutcome::Overwritten));
+    }
+}
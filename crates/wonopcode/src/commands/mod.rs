@@ -4,21 +4,38 @@
 //! split into logical groups for better organization.
 
 pub mod agent;
+pub mod audit;
 pub mod auth;
+pub mod batch;
+pub mod config;
 pub mod export;
+pub mod init;
 pub mod logging;
 pub mod mcp;
 pub mod model;
+pub mod replay;
 pub mod run;
+pub mod schedule;
 pub mod session;
+pub mod snapshot;
+pub mod spend;
+pub mod stream_json;
 pub mod web;
 pub use agent::*;
+pub use audit::*;
 
 pub use auth::*;
+pub use batch::*;
+pub use config::*;
 pub use export::*;
+pub use init::*;
 pub use logging::*;
 pub use mcp::*;
 pub use model::*;
+pub use replay::*;
 pub use run::*;
+pub use schedule::*;
 pub use session::*;
+pub use snapshot::*;
+pub use spend::*;
 pub use web::*;
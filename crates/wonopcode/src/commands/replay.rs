@@ -0,0 +1,123 @@
+//! Event recording and replay.
+//!
+//! With `--record-events <FILE>`, every bus event and every update shown in
+//! the UI is recorded to a JSONL file. `wonopcode replay <FILE>` feeds such a
+//! recording back into the TUI to review what the agent did, and why.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use wonopcode_core::bus::{AgentUpdate, Bus, PromptSubmitted};
+use wonopcode_core::{read_recording, BusRecorder};
+use wonopcode_tui::{App, AppUpdate};
+
+/// Longest pause between replayed events.
+const MAX_REPLAY_DELAY: Duration = Duration::from_secs(2);
+
+/// Start recording the events of `buses` to `path`.
+pub async fn start_recording(path: &Path, buses: &[&Bus]) -> anyhow::Result<BusRecorder> {
+    let mut recorder = BusRecorder::create(path)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to record events to {}: {e}", path.display()))?;
+    for bus in buses {
+        recorder.attach(bus);
+    }
+    info!(path = %path.display(), "Recording events");
+    Ok(recorder)
+}
+
+/// Forward runner updates to `update_tx`, publishing each on `bus` so it is
+/// recorded. Returns the sender for the runner.
+pub fn record_updates(
+    bus: Bus,
+    update_tx: mpsc::UnboundedSender<AppUpdate>,
+) -> mpsc::UnboundedSender<AppUpdate> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<AppUpdate>();
+    tokio::spawn(async move {
+        while let Some(update) = rx.recv().await {
+            publish_update(&bus, update.clone()).await;
+            if update_tx.send(update).is_err() {
+                break;
+            }
+        }
+    });
+    tx
+}
+
+/// Publish an update on `bus` as an [`AgentUpdate`].
+pub async fn publish_update(bus: &Bus, update: AppUpdate) {
+    let Some(update) = crate::app_update_to_protocol(update) else {
+        return;
+    };
+    if let Ok(payload) = serde_json::to_value(&update) {
+        bus.publish(AgentUpdate(payload)).await;
+    }
+}
+
+/// Replay a recording in the TUI.
+///
+/// Events are replayed with their original timing divided by `speed` (pauses
+/// are capped at two seconds); a speed of 0 replays everything at once.
+pub async fn handle_replay(file: PathBuf, speed: f64) -> anyhow::Result<()> {
+    let events = read_recording(&file)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", file.display()))?;
+
+    let mut app = App::new();
+    app.set_project(format!("Replay of {}", file.display()));
+    let update_tx = app.update_sender();
+    let mut action_rx = app
+        .take_action_rx()
+        .ok_or_else(|| anyhow::anyhow!("Action receiver already taken - app state corrupted"))?;
+
+    // The replay is read-only: user actions are ignored
+    tokio::spawn(async move { while action_rx.recv().await.is_some() {} });
+
+    tokio::spawn(async move {
+        let total = events.len();
+        let mut previous: Option<i64> = None;
+        for event in events {
+            if let Some(previous) = previous.filter(|_| speed > 0.0) {
+                let elapsed = (event.timestamp - previous).max(0) as f64 / speed;
+                let delay = Duration::from_millis(elapsed as u64).min(MAX_REPLAY_DELAY);
+                tokio::time::sleep(delay).await;
+            }
+            previous = Some(event.timestamp);
+
+            let update = match event.event_type.as_str() {
+                "agent.update" => {
+                    match serde_json::from_value::<wonopcode_protocol::Update>(event.payload) {
+                        // Show permission prompts without asking again
+                        Ok(wonopcode_protocol::Update::PermissionRequest {
+                            description, ..
+                        }) => {
+                            AppUpdate::SystemMessage(format!("Permission requested: {description}"))
+                        }
+                        Ok(update) => wonopcode_tui::protocol_update_to_app(update),
+                        Err(e) => {
+                            warn!(seq = event.seq, "Skipping unreadable update: {}", e);
+                            continue;
+                        }
+                    }
+                }
+                "prompt.submitted" => {
+                    match serde_json::from_value::<PromptSubmitted>(event.payload) {
+                        Ok(prompt) => AppUpdate::UserMessage(prompt.text),
+                        Err(_) => continue,
+                    }
+                }
+                event_type => AppUpdate::SystemMessage(format!("[{event_type}] {}", event.payload)),
+            };
+            if update_tx.send(update).is_err() {
+                return;
+            }
+        }
+        let _ = update_tx.send(AppUpdate::SystemMessage(format!(
+            "End of recording ({total} events)"
+        )));
+    });
+
+    app.run().await?;
+    Ok(())
+}
@@ -3,6 +3,7 @@
 //! Handles the execution of single prompts in non-interactive mode,
 //! as well as headless server mode for remote operation.
 
+use super::stream_json::{EventStream, StreamEvent, SCHEMA_VERSION};
 use crate::runner::{Runner, RunnerConfig};
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -21,6 +22,8 @@ pub async fn run_server(address: SocketAddr, cwd: &std::path::Path) -> anyhow::R
 
     // Create instance
     let instance = wonopcode_core::Instance::new(cwd).await?;
+    #[cfg(feature = "forge")]
+    crate::forge::use_for_pull_requests(&instance).await;
     let bus = instance.bus().clone();
 
     // Create server state
@@ -38,6 +41,74 @@ pub async fn run_server(address: SocketAddr, cwd: &std::path::Path) -> anyhow::R
     Ok(())
 }
 
+/// Exit code of `wonopcode run` when the run failed.
+pub const EXIT_FAILED: i32 = 1;
+/// Exit code of `wonopcode run` when it can't start, e.g. without a message
+/// or credentials.
+pub const EXIT_USAGE: i32 = 2;
+
+/// A failed `wonopcode run`, which exits with `exit_code`.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct RunFailed {
+    pub exit_code: i32,
+    pub message: String,
+}
+
+/// Output formats of `wonopcode run`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// The response as it streams in.
+    Default,
+    /// Tool calls and the response as JSON lines.
+    Json,
+    /// Every event as JSON lines, see [`super::stream_json`].
+    StreamJson,
+}
+
+impl OutputFormat {
+    fn parse(format: &str) -> Option<Self> {
+        match format {
+            "default" | "text" => Some(Self::Default),
+            "json" => Some(Self::Json),
+            "stream-json" => Some(Self::StreamJson),
+            _ => None,
+        }
+    }
+}
+
+/// The error for a failed run, after reporting it as the final event of a
+/// stream.
+fn fail(stream: Option<&EventStream>, exit_code: i32, message: String) -> anyhow::Error {
+    if let Some(stream) = stream {
+        stream
+            .result(String::new(), Some(message.clone()), exit_code)
+            .emit();
+    }
+    RunFailed { exit_code, message }.into()
+}
+
+/// Join the message parts of `wonopcode run` into a prompt.
+///
+/// For a custom `/command`, parts containing whitespace are quoted again so
+/// the command receives them as single arguments.
+fn join_message(message: &[String]) -> String {
+    if !message.first().is_some_and(|m| m.starts_with('/')) {
+        return message.join(" ");
+    }
+    message
+        .iter()
+        .map(|part| {
+            if part.contains(char::is_whitespace) && !part.contains('"') {
+                format!("\"{part}\"")
+            } else {
+                part.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /// Run a single command and exit (non-interactive mode).
 ///
 /// This function executes a single prompt, prints the response, and exits.
@@ -49,7 +120,8 @@ pub async fn run_server(address: SocketAddr, cwd: &std::path::Path) -> anyhow::R
 /// * `model` - Optional model specification (provider/model format)
 /// * `_continue_session` - Whether to continue the last session (currently unused)
 /// * `_session` - Optional session ID to resume (currently unused)
-/// * `format` - Output format ("json" or plain text)
+/// * `format` - Output format ("default", "json" or "stream-json")
+/// * `template` - Optional session template to start from
 /// * `default_provider` - Default provider to use if not specified in model
 /// * `cli_secret` - Optional API secret for server authentication
 #[allow(clippy::too_many_arguments)]
@@ -61,11 +133,21 @@ pub async fn run_command(
     _continue_session: bool,
     _session: Option<String>,
     format: &str,
+    template: Option<String>,
     default_provider: String,
     cli_secret: Option<String>,
 ) -> anyhow::Result<()> {
     use std::io::{self, Write};
 
+    let Some(format) = OutputFormat::parse(format) else {
+        return Err(RunFailed {
+            exit_code: EXIT_USAGE,
+            message: format!("Unknown format '{format}'; use default, json or stream-json"),
+        }
+        .into());
+    };
+    let mut stream = (format == OutputFormat::StreamJson).then(EventStream::new);
+
     // Join message parts
     let prompt = if message.is_empty() {
         // Read from stdin if no message provided
@@ -73,16 +155,34 @@ pub async fn run_command(
         io::stdin().read_line(&mut input)?;
         input.trim().to_string()
     } else {
-        message.join(" ")
+        join_message(&message)
     };
 
     if prompt.is_empty() {
-        eprintln!("Error: No message provided");
-        return Ok(());
+        return Err(fail(
+            stream.as_ref(),
+            EXIT_USAGE,
+            "No message provided".to_string(),
+        ));
     }
 
     // Create instance
-    let instance = wonopcode_core::Instance::new(cwd).await?;
+    let instance = wonopcode_core::Instance::new(cwd)
+        .await
+        .map_err(|e| fail(stream.as_ref(), EXIT_FAILED, e.to_string()))?;
+
+    // The template's model applies unless --model is given
+    let template = match template {
+        Some(name) => match wonopcode_core::TemplateStore::new(cwd).load(&name) {
+            Ok(template) => Some(template),
+            Err(e) => {
+                instance.dispose().await;
+                return Err(fail(stream.as_ref(), EXIT_USAGE, e.to_string()));
+            }
+        },
+        None => None,
+    };
+    let model = model.or_else(|| template.as_ref().and_then(|t| t.model.clone()));
 
     // Parse model specification (provider/model format)
     let (provider, model_id) = if let Some(ref m) = model {
@@ -109,9 +209,14 @@ pub async fn run_command(
             || !ClaudeCliProvider::is_available()
             || !ClaudeCliProvider::is_authenticated()
         {
-            eprintln!("Error: No API key found for provider '{provider}'");
-            eprintln!("Run: wonopcode auth login {provider}");
-            return Ok(());
+            instance.dispose().await;
+            return Err(fail(
+                stream.as_ref(),
+                EXIT_USAGE,
+                format!(
+                    "No API key found for provider '{provider}'. Run: wonopcode auth login {provider}"
+                ),
+            ));
         }
     }
 
@@ -191,8 +296,15 @@ pub async fn run_command(
     {
         Ok(r) => r,
         Err(e) => {
-            eprintln!("Error creating runner: {e}");
-            return Ok(());
+            if let Some(handle) = mcp_server_handle {
+                handle.abort();
+            }
+            instance.dispose().await;
+            return Err(fail(
+                stream.as_ref(),
+                EXIT_FAILED,
+                format!("Failed to create runner: {e}"),
+            ));
         }
     };
 
@@ -205,17 +317,40 @@ pub async fn run_command(
         runner.run(action_rx, update_tx).await;
     });
 
+    // Apply the template before the prompt; actions are handled in order
+    if let Some(template) = template {
+        let _ = action_tx.send(wonopcode_tui::AppAction::ApplyTemplate {
+            name: template.name,
+        });
+    }
+
+    if stream.is_some() {
+        StreamEvent::Init {
+            schema_version: SCHEMA_VERSION,
+            wonopcode_version: env!("CARGO_PKG_VERSION"),
+            provider: provider.clone(),
+            model: model_id.clone(),
+            cwd: cwd.display().to_string(),
+        }
+        .emit();
+    }
+
     // Send prompt
     let _ = action_tx.send(wonopcode_tui::AppAction::SendPrompt(prompt));
 
     // Collect response
-    let is_json = format == "json";
+    let is_json = format == OutputFormat::Json;
+    let is_text = format == OutputFormat::Default;
     let mut response_text = String::new();
+    let mut outcome = Err("The runner stopped before the response was complete".to_string());
 
     while let Some(update) = update_rx.recv().await {
+        if let Some(event) = stream.as_mut().and_then(|s| s.event(&update)) {
+            event.emit();
+        }
         match update {
             wonopcode_tui::AppUpdate::TextDelta(delta) => {
-                if !is_json {
+                if is_text {
                     print!("{delta}");
                     io::stdout().flush()?;
                 }
@@ -262,9 +397,14 @@ pub async fn run_command(
                             "text": text
                         })
                     );
-                } else if response_text.is_empty() {
+                } else if is_text && response_text.is_empty() {
                     println!("{text}");
                 }
+                outcome = Ok(if text.is_empty() {
+                    response_text.clone()
+                } else {
+                    text
+                });
                 break;
             }
             wonopcode_tui::AppUpdate::Error(e) => {
@@ -276,16 +416,15 @@ pub async fn run_command(
                             "message": e
                         })
                     );
-                } else {
-                    eprintln!("\nError: {e}");
                 }
+                outcome = Err(e);
                 break;
             }
             _ => {}
         }
     }
 
-    if !is_json && !response_text.is_empty() {
+    if is_text && !response_text.is_empty() {
         println!(); // Final newline
     }
 
@@ -299,5 +438,13 @@ pub async fn run_command(
     runner_handle.abort();
     instance.dispose().await;
 
-    Ok(())
+    match outcome {
+        Ok(text) => {
+            if let Some(stream) = &stream {
+                stream.result(text, None, 0).emit();
+            }
+            Ok(())
+        }
+        Err(e) => Err(fail(stream.as_ref(), EXIT_FAILED, e)),
+    }
 }
@@ -0,0 +1,76 @@
+//! Scheduled task command handlers.
+//!
+//! Handles listing the tasks under the `schedule` config key and running one
+//! immediately. Tasks run on their schedule in headless mode.
+
+use clap::Subcommand;
+use std::path::Path;
+use wonopcode_core::{ScheduledTask, Scheduler};
+
+/// Schedule subcommands.
+#[derive(Subcommand)]
+pub enum ScheduleCommands {
+    /// List scheduled tasks and when they run next
+    List,
+    /// Run a scheduled task now
+    Run {
+        /// Task name
+        name: String,
+    },
+}
+
+/// Handle schedule commands.
+pub async fn handle_schedule(
+    command: ScheduleCommands,
+    cwd: &Path,
+    default_provider: &str,
+) -> anyhow::Result<()> {
+    let instance = wonopcode_core::Instance::new(cwd).await?;
+    let config = instance.config().await;
+    let tasks = ScheduledTask::all(&config);
+
+    match command {
+        ScheduleCommands::List => {
+            if tasks.is_empty() {
+                println!("No scheduled tasks. Add them under \"schedule\" in the config.");
+                return Ok(());
+            }
+            let scheduler = Scheduler::new(tasks, chrono::Local::now());
+            for task in scheduler.tasks() {
+                let next = scheduler
+                    .next_run(&task.name)
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "never".to_string());
+                println!("  {:<20} next {next}  {}", task.name, task.prompt);
+            }
+            println!();
+            println!("Scheduled tasks run while 'wonopcode --headless' is running.");
+        }
+        ScheduleCommands::Run { name } => {
+            let Some(task) = tasks.into_iter().find(|t| t.name == name) else {
+                anyhow::bail!("No enabled scheduled task named '{name}'");
+            };
+            let default_model = config.model.clone().unwrap_or_else(|| {
+                format!(
+                    "{default_provider}/{}",
+                    super::get_default_model(default_provider)
+                )
+            });
+
+            let run = crate::schedule::run_and_record(&instance, &task, &default_model).await;
+            if !run.output.is_empty() {
+                println!("{}", run.output);
+            }
+            if let Some(session_id) = &run.session_id {
+                println!();
+                println!("Stored as session {session_id}");
+            }
+            instance.dispose().await;
+            if let Some(error) = run.error {
+                anyhow::bail!("Scheduled task failed: {error}");
+            }
+        }
+    }
+
+    Ok(())
+}
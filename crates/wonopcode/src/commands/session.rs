@@ -1,9 +1,15 @@
 //! Session management command handlers.
 //!
-//! Handles listing, showing, and deleting sessions.
+//! Handles listing, searching, tagging, showing, and deleting sessions, and
+//! pruning old ones or archiving them to compressed files that can be
+//! restored later.
 
+use anyhow::Context;
 use clap::Subcommand;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use wonopcode_core::session::{MessageWithParts, Session};
+use wonopcode_core::Instance;
 
 /// Session subcommands.
 #[derive(Subcommand)]
@@ -20,6 +26,144 @@ pub enum SessionCommands {
         /// Session ID
         id: String,
     },
+    /// Search sessions by title, tags and message text
+    Search {
+        /// Search terms; use tag:<name> to require a tag
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Add tags to a session
+    Tag {
+        /// Session ID
+        id: String,
+        /// Tags to add
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from a session
+    Untag {
+        /// Session ID
+        id: String,
+        /// Tags to remove
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Delete or archive sessions not updated for a while
+    Prune {
+        /// Age of the sessions to prune, e.g. 30d, 12h or 2w
+        #[arg(long, value_parser = parse_age)]
+        older_than: chrono::Duration,
+        /// Archive the sessions instead of deleting them
+        #[arg(long)]
+        archive: bool,
+        /// Only show which sessions would be pruned
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Compress a session to a file and remove it from the session list
+    Archive {
+        /// Session ID
+        id: String,
+        /// Archive file (default: the archive directory in the data directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only show what would be archived
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Restore an archived session
+    Restore {
+        /// Session ID of an archive in the archive directory, or an archive file
+        archive: String,
+    },
+}
+
+/// A session and its messages, as written by `session archive`.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SessionArchive {
+    version: String,
+    archived_at: String,
+    session: Session,
+    messages: Vec<MessageWithParts>,
+}
+
+/// Parse an age such as `30d`: a number with `m` (minutes), `h`, `d` or `w`.
+fn parse_age(s: &str) -> Result<chrono::Duration, String> {
+    let invalid = || format!("invalid age '{}', expected e.g. 30d", s.trim());
+    match wonopcode_core::share::parse_expiry(s) {
+        Ok(Some(age)) => chrono::Duration::from_std(age).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Where archives go by default, per project.
+fn archive_dir(project_id: &str) -> PathBuf {
+    wonopcode_core::config::Config::data_dir()
+        .unwrap_or_else(|| PathBuf::from(".wonopcode"))
+        .join("archive")
+        .join(project_id)
+}
+
+/// Encode an archive as gzipped JSON.
+fn encode_archive(archive: &SessionArchive) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&serde_json::to_vec(archive)?)?;
+    Ok(encoder.finish()?)
+}
+
+fn decode_archive(bytes: &[u8]) -> anyhow::Result<SessionArchive> {
+    let mut json = Vec::new();
+    flate2::read::GzDecoder::new(bytes).read_to_end(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Write `session` with its messages to `output` and delete it.
+async fn archive_session(
+    instance: &Instance,
+    project_id: &str,
+    session: &Session,
+    output: &Path,
+) -> anyhow::Result<()> {
+    let repo = instance.session_repo();
+    let archive = SessionArchive {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        archived_at: chrono::Utc::now().to_rfc3339(),
+        session: session.clone(),
+        messages: repo.messages(project_id, &session.id, None).await?,
+    };
+    if let Some(parent) = output.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // Write the whole file before deleting anything
+    let partial = output.with_extension("partial");
+    tokio::fs::write(&partial, encode_archive(&archive)?).await?;
+    tokio::fs::rename(&partial, output).await?;
+
+    repo.delete(project_id, &session.id).await?;
+    Ok(())
+}
+
+/// Restore the session in the archive at `path`.
+async fn restore_session(
+    instance: &Instance,
+    project_id: &str,
+    path: &Path,
+) -> anyhow::Result<Session> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let archive = decode_archive(&bytes)
+        .with_context(|| format!("{} is not a session archive", path.display()))?;
+    if instance.get_session(&archive.session.id).await.is_some() {
+        anyhow::bail!("Session {} already exists", archive.session.id);
+    }
+
+    let mut session = archive.session;
+    session.project_id = project_id.to_string();
+    Ok(instance
+        .session_repo()
+        .restore(session, &archive.messages)
+        .await?)
 }
 
 /// Handle session commands.
@@ -66,6 +210,9 @@ pub async fn handle_session(command: SessionCommands, cwd: &Path) -> anyhow::Res
                 if let Some(parent) = &session.parent_id {
                     println!("Parent: {parent}");
                 }
+                if !session.tags.is_empty() {
+                    println!("Tags: {}", session.tags.join(", "));
+                }
             }
             None => {
                 println!("Session not found: {id}");
@@ -78,8 +225,218 @@ pub async fn handle_session(command: SessionCommands, cwd: &Path) -> anyhow::Res
                 Err(e) => println!("Error deleting session: {e}"),
             }
         }
+        SessionCommands::Search { query } => {
+            let hits = instance.search_sessions(&query.join(" ")).await?;
+
+            if hits.is_empty() {
+                println!("No matching sessions.");
+            } else {
+                println!("{:<28} {:<30} {:<20}", "ID", "TITLE", "UPDATED");
+                println!("{}", "-".repeat(78));
+
+                for hit in hits {
+                    let session = hit.session;
+                    let updated = session.updated_at().format("%Y-%m-%d %H:%M:%S");
+                    let title = if session.title.len() > 28 {
+                        format!("{}...", &session.title[..25])
+                    } else {
+                        session.title.clone()
+                    };
+                    println!("{:<28} {:<30} {:<20}", session.id, title, updated);
+                    if !session.tags.is_empty() {
+                        println!("    tags: {}", session.tags.join(", "));
+                    }
+                    if let Some(snippet) = hit.snippet {
+                        println!("    {snippet}");
+                    }
+                }
+            }
+        }
+        SessionCommands::Tag { id, tags } => {
+            let project_id = instance.project_id().await;
+            match instance
+                .session_repo()
+                .update(&project_id, &id, |session| {
+                    for tag in tags {
+                        if !session.tags.contains(&tag) {
+                            session.tags.push(tag);
+                        }
+                    }
+                })
+                .await
+            {
+                Ok(session) => println!("Tags: {}", session.tags.join(", ")),
+                Err(e) => println!("Error tagging session: {e}"),
+            }
+        }
+        SessionCommands::Untag { id, tags } => {
+            let project_id = instance.project_id().await;
+            match instance
+                .session_repo()
+                .update(&project_id, &id, |session| {
+                    session.tags.retain(|t| !tags.contains(t));
+                })
+                .await
+            {
+                Ok(session) if session.tags.is_empty() => println!("Session has no tags"),
+                Ok(session) => println!("Tags: {}", session.tags.join(", ")),
+                Err(e) => println!("Error untagging session: {e}"),
+            }
+        }
+        SessionCommands::Prune {
+            older_than,
+            archive,
+            dry_run,
+        } => {
+            let project_id = instance.project_id().await;
+            let cutoff = chrono::Utc::now()
+                .checked_sub_signed(older_than)
+                .context("--older-than reaches back too far")?;
+            let sessions: Vec<_> = instance
+                .list_sessions()
+                .await
+                .into_iter()
+                .filter(|s| s.updated_at() < cutoff)
+                .collect();
+
+            let verb = match (archive, dry_run) {
+                (true, true) => "Would archive",
+                (false, true) => "Would delete",
+                (true, false) => "Archived",
+                (false, false) => "Deleted",
+            };
+            let dir = archive_dir(&project_id);
+            let mut pruned = 0;
+            for session in &sessions {
+                if !dry_run {
+                    let result = if archive {
+                        let output = dir.join(format!("{}.json.gz", session.id));
+                        archive_session(&instance, &project_id, session, &output).await
+                    } else {
+                        instance
+                            .session_repo()
+                            .delete(&project_id, &session.id)
+                            .await
+                            .map_err(Into::into)
+                    };
+                    if let Err(e) = result {
+                        eprintln!("Error pruning session {}: {e}", session.id);
+                        continue;
+                    }
+                }
+                println!(
+                    "{verb} {} {}  {}",
+                    session.id,
+                    session.updated_at().format("%Y-%m-%d"),
+                    session.title
+                );
+                pruned += 1;
+            }
+
+            if sessions.is_empty() {
+                println!(
+                    "No sessions older than {}.",
+                    cutoff.format("%Y-%m-%d %H:%M")
+                );
+            } else {
+                println!();
+                println!("{verb} {pruned} of {} session(s)", sessions.len());
+                if archive && !dry_run {
+                    println!("Archives are in {}", dir.display());
+                }
+            }
+        }
+        SessionCommands::Archive {
+            id,
+            output,
+            dry_run,
+        } => {
+            let project_id = instance.project_id().await;
+            let Some(session) = instance.get_session(&id).await else {
+                instance.dispose().await;
+                anyhow::bail!("Session not found: {id}");
+            };
+            let output =
+                output.unwrap_or_else(|| archive_dir(&project_id).join(format!("{id}.json.gz")));
+
+            if dry_run {
+                println!(
+                    "Would archive {id} ({}) to {}",
+                    session.title,
+                    output.display()
+                );
+            } else {
+                let result = archive_session(&instance, &project_id, &session, &output).await;
+                if let Err(e) = result {
+                    instance.dispose().await;
+                    return Err(e.context(format!("Failed to archive session {id}")));
+                }
+                println!("Archived {id} to {}", output.display());
+                println!("Restore it with: wonopcode session restore {id}");
+            }
+        }
+        SessionCommands::Restore { archive } => {
+            let project_id = instance.project_id().await;
+            let dir = archive_dir(&project_id);
+            let path = match PathBuf::from(&archive) {
+                path if path.is_file() => path,
+                _ => dir.join(format!("{archive}.json.gz")),
+            };
+
+            match restore_session(&instance, &project_id, &path).await {
+                Ok(session) => {
+                    // Archives in the archive directory are only a place to keep sessions
+                    if path.starts_with(&dir) {
+                        let _ = tokio::fs::remove_file(&path).await;
+                    }
+                    println!("Restored session: {} ({})", session.id, session.title);
+                }
+                Err(e) => {
+                    instance.dispose().await;
+                    return Err(e);
+                }
+            }
+        }
     }
 
     instance.dispose().await;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d").unwrap(), chrono::Duration::days(30));
+        assert_eq!(parse_age("12h").unwrap(), chrono::Duration::hours(12));
+        assert_eq!(parse_age("2w").unwrap(), chrono::Duration::weeks(2));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+        assert!(parse_age("never").is_err());
+        assert!(parse_age("99999999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let mut session = Session::new("project", "/work");
+        session.title = "Refactor parser".to_string();
+        let archive = SessionArchive {
+            version: "1.0.0".to_string(),
+            archived_at: chrono::Utc::now().to_rfc3339(),
+            session,
+            messages: Vec::new(),
+        };
+
+        let bytes = encode_archive(&archive).unwrap();
+        // gzip magic
+        assert_eq!(&bytes[..2], &[0x1f, 0x8b]);
+        let decoded = decode_archive(&bytes).unwrap();
+        assert_eq!(decoded.session.id, archive.session.id);
+        assert_eq!(decoded.session.title, "Refactor parser");
+
+        assert!(decode_archive(b"not an archive").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Snapshot command handlers.
+//!
+//! Handles listing the file snapshots taken before the agent edits files,
+//! diffing them against the working tree and restoring them, for recovering
+//! from a bad edit without the TUI.
+
+use clap::Subcommand;
+use std::path::{Path, PathBuf};
+use wonopcode_snapshot::{SnapshotConfig, SnapshotStore};
+
+/// Snapshot subcommands.
+#[derive(Subcommand)]
+pub enum SnapshotCommands {
+    /// List snapshots, newest first
+    Log {
+        /// Only snapshots of this file
+        file: Option<PathBuf>,
+        /// Only snapshots of this session
+        #[arg(short, long)]
+        session: Option<String>,
+        /// Maximum number of snapshots to list
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show how files changed since a snapshot
+    Diff {
+        /// Snapshot ID or a unique prefix of it
+        id: String,
+        /// Only these files
+        files: Vec<PathBuf>,
+    },
+    /// Restore files from a snapshot
+    Restore {
+        /// Snapshot ID or a unique prefix of it
+        id: String,
+        /// Only restore these files
+        #[arg(long, num_args = 1..)]
+        files: Vec<PathBuf>,
+    },
+}
+
+/// Handle snapshot commands.
+pub async fn handle_snapshot(command: SnapshotCommands, cwd: &Path) -> anyhow::Result<()> {
+    let snapshot_dir = cwd.join(".wonopcode").join("snapshots");
+    if !snapshot_dir.exists() {
+        println!("No snapshots in {}", cwd.display());
+        return Ok(());
+    }
+    let store =
+        SnapshotStore::new(snapshot_dir, cwd.to_path_buf(), SnapshotConfig::default()).await?;
+
+    match command {
+        SnapshotCommands::Log {
+            file,
+            session,
+            limit,
+        } => {
+            let snapshots = match &file {
+                Some(file) => store.list_for_file(file).await?,
+                None => store.list().await?,
+            };
+            let snapshots: Vec<_> = snapshots
+                .into_iter()
+                .filter(|s| session.is_none() || session.as_deref() == Some(s.session_id.as_str()))
+                .collect();
+
+            if snapshots.is_empty() {
+                println!("No snapshots found.");
+                return Ok(());
+            }
+            for snapshot in snapshots.iter().take(limit) {
+                let time = snapshot.timestamp.with_timezone(&chrono::Local);
+                let files: Vec<_> = snapshot
+                    .files
+                    .iter()
+                    .map(|f| f.display().to_string())
+                    .collect();
+                println!(
+                    "{}  {}  {:<6}  {}",
+                    short_id(snapshot.id.as_str()),
+                    time.format("%Y-%m-%d %H:%M:%S"),
+                    snapshot.trigger.as_deref().unwrap_or("-"),
+                    snapshot.description
+                );
+                println!("          {}", files.join(", "));
+            }
+            if snapshots.len() > limit {
+                println!();
+                println!("{} more; use -n to list them", snapshots.len() - limit);
+            }
+        }
+        SnapshotCommands::Diff { id, files } => {
+            let snapshot = store.find(&id).await?;
+            let files = if files.is_empty() {
+                snapshot.files.clone()
+            } else {
+                files
+            };
+            let mut changed = false;
+            for file in &files {
+                let diff = store.diff(&snapshot.id, file).await?;
+                // Only the two header lines when nothing changed
+                if diff.lines().count() > 2 {
+                    print!("{diff}");
+                    changed = true;
+                }
+            }
+            if !changed {
+                println!(
+                    "No changes since snapshot {}",
+                    short_id(snapshot.id.as_str())
+                );
+            }
+        }
+        SnapshotCommands::Restore { id, files } => {
+            let snapshot = store.find(&id).await?;
+            let targets = if files.is_empty() {
+                snapshot.files.clone()
+            } else {
+                files.clone()
+            };
+
+            // Keep the current state, so the restore can be undone in turn
+            let backup = store
+                .take(
+                    &targets,
+                    &snapshot.session_id,
+                    &snapshot.message_id,
+                    &format!(
+                        "Before restoring snapshot {}",
+                        short_id(snapshot.id.as_str())
+                    ),
+                )
+                .await
+                .ok();
+
+            store.restore_files(&snapshot.id, &files).await?;
+            for file in &targets {
+                println!("Restored {}", file.display());
+            }
+            if let Some(backup) = backup {
+                println!();
+                println!(
+                    "The previous contents are in snapshot {}; undo with: wonopcode snapshot restore {}",
+                    short_id(backup.id.as_str()),
+                    short_id(backup.id.as_str())
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The first characters of a snapshot ID, enough to find it again.
+fn short_id(id: &str) -> &str {
+    id.get(..8).unwrap_or(id)
+}
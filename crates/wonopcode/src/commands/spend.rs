@@ -0,0 +1,64 @@
+//! Spend command handlers.
+//!
+//! Handles reviewing spend across sessions and acknowledging reached
+//! spending limits.
+
+use clap::Subcommand;
+use std::path::Path;
+use wonopcode_core::SpendTracker;
+
+/// Spend subcommands.
+#[derive(Subcommand)]
+pub enum SpendCommands {
+    /// Show spend today and this month against the configured alerts and limits
+    Show {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Acknowledge reached spending limits, allowing another full limit
+    Ack,
+}
+
+/// Handle spend commands.
+pub async fn handle_spend(command: SpendCommands, cwd: &Path) -> anyhow::Result<()> {
+    let instance = wonopcode_core::Instance::new(cwd).await?;
+    let config = instance.config().await;
+    let tracker = SpendTracker::new(
+        instance.storage().clone(),
+        instance.project_id().await,
+        config.spend.clone().unwrap_or_default(),
+    );
+
+    match command {
+        SpendCommands::Show { json } => {
+            let statuses = tracker.statuses().await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&statuses)?);
+            } else {
+                for status in &statuses {
+                    println!("{}", status.describe());
+                }
+                if let Some(status) = statuses.iter().find(|s| s.limit_reached()) {
+                    println!();
+                    println!(
+                        "Limit reached, new prompts are blocked: {}",
+                        status.describe()
+                    );
+                    println!("Run 'wonopcode spend ack' to allow more spending.");
+                }
+            }
+        }
+        SpendCommands::Ack => {
+            let acknowledged = tracker.acknowledge().await?;
+            if acknowledged.is_empty() {
+                println!("No spending limit is reached.");
+            }
+            for status in acknowledged {
+                println!("Acknowledged: {}", status.describe());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! The `stream-json` output of `wonopcode run`.
+//!
+//! Each line on stdout is one JSON object whose `type` is one of:
+//!
+//! - `init`: `schema_version`, `wonopcode_version`, `provider`, `model`, `cwd`
+//! - `text_delta`: `text`, a piece of the response
+//! - `tool_start`: `id`, `name`, `input` (the arguments, as JSON)
+//! - `tool_progress`: `id`, `fraction` (0 to 1, or null), `message`
+//! - `tool_end`: `id`, `name`, `success`, `output`, `metadata`, `duration_ms`
+//! - `status`: `message`
+//! - `usage`: `input_tokens`, `output_tokens`, `cost` (USD), all so far
+//! - `result`: `status` (`success` or `error`), `text`, `error`, `exit_code`,
+//!   `duration_ms`, `usage`
+//!
+//! `result` is always the last line, also when the run fails before it
+//! starts, and its `exit_code` is what the process exits with. Fields may be
+//! added within a [`SCHEMA_VERSION`]; anything else bumps it.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Instant;
+use wonopcode_tui::AppUpdate;
+
+/// Version of the event format, in the `init` event.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// One line of `stream-json` output.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Init {
+        schema_version: u32,
+        wonopcode_version: &'static str,
+        provider: String,
+        model: String,
+        cwd: String,
+    },
+    TextDelta {
+        text: String,
+    },
+    ToolStart {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolProgress {
+        id: String,
+        fraction: Option<f64>,
+        message: Option<String>,
+    },
+    ToolEnd {
+        id: String,
+        name: String,
+        success: bool,
+        output: String,
+        metadata: Option<serde_json::Value>,
+        duration_ms: u64,
+    },
+    Status {
+        message: String,
+    },
+    Usage(StreamUsage),
+    Result {
+        status: &'static str,
+        text: String,
+        error: Option<String>,
+        exit_code: i32,
+        duration_ms: u64,
+        usage: StreamUsage,
+    },
+}
+
+impl StreamEvent {
+    /// Print the event as a line on stdout.
+    pub fn emit(&self) {
+        match serde_json::to_string(self) {
+            Ok(line) => println!("{line}"),
+            Err(e) => tracing::warn!(error = %e, "Failed to serialize stream event"),
+        }
+    }
+}
+
+/// Tokens and cost of a run so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct StreamUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub cost: f64,
+}
+
+/// Turns runner updates into stream events, keeping what the events need
+/// from earlier updates.
+pub struct EventStream {
+    started: Instant,
+    /// Running tools: name and start.
+    tools: HashMap<String, (String, Instant)>,
+    usage: StreamUsage,
+}
+
+impl EventStream {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            tools: HashMap::new(),
+            usage: StreamUsage::default(),
+        }
+    }
+
+    /// The event for `update`, if it has one.
+    pub fn event(&mut self, update: &AppUpdate) -> Option<StreamEvent> {
+        match update {
+            AppUpdate::TextDelta(text) => Some(StreamEvent::TextDelta { text: text.clone() }),
+            AppUpdate::ToolStarted { name, id, input } => {
+                self.tools
+                    .insert(id.clone(), (name.clone(), Instant::now()));
+                Some(StreamEvent::ToolStart {
+                    id: id.clone(),
+                    name: name.clone(),
+                    input: serde_json::from_str(input)
+                        .unwrap_or_else(|_| serde_json::Value::String(input.clone())),
+                })
+            }
+            AppUpdate::ToolProgress {
+                id,
+                fraction,
+                message,
+            } => Some(StreamEvent::ToolProgress {
+                id: id.clone(),
+                fraction: *fraction,
+                message: message.clone(),
+            }),
+            AppUpdate::ToolCompleted {
+                id,
+                success,
+                output,
+                metadata,
+            } => {
+                let (name, started) = self
+                    .tools
+                    .remove(id)
+                    .unwrap_or_else(|| (String::new(), Instant::now()));
+                Some(StreamEvent::ToolEnd {
+                    id: id.clone(),
+                    name,
+                    success: *success,
+                    output: output.clone(),
+                    metadata: metadata.clone(),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                })
+            }
+            AppUpdate::Status(message) => Some(StreamEvent::Status {
+                message: message.clone(),
+            }),
+            AppUpdate::TokenUsage {
+                input,
+                output,
+                cost,
+                ..
+            } => {
+                self.usage = StreamUsage {
+                    input_tokens: *input,
+                    output_tokens: *output,
+                    cost: *cost,
+                };
+                Some(StreamEvent::Usage(self.usage))
+            }
+            _ => None,
+        }
+    }
+
+    /// The final event: `text` on success, or `error` with `exit_code`.
+    pub fn result(&self, text: String, error: Option<String>, exit_code: i32) -> StreamEvent {
+        StreamEvent::Result {
+            status: if error.is_none() { "success" } else { "error" },
+            text,
+            error,
+            exit_code,
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            usage: self.usage,
+        }
+    }
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn line(event: &StreamEvent) -> serde_json::Value {
+        serde_json::to_value(event).unwrap()
+    }
+
+    #[test]
+    fn test_tool_lifecycle() {
+        let mut stream = EventStream::new();
+        let start = stream
+            .event(&AppUpdate::ToolStarted {
+                name: "read".into(),
+                id: "t1".into(),
+                input: r#"{"path":"src/main.rs"}"#.into(),
+            })
+            .unwrap();
+        assert_eq!(
+            line(&start),
+            json!({"type": "tool_start", "id": "t1", "name": "read", "input": {"path": "src/main.rs"}})
+        );
+
+        let end = line(
+            &stream
+                .event(&AppUpdate::ToolCompleted {
+                    id: "t1".into(),
+                    success: true,
+                    output: "fn main() {}".into(),
+                    metadata: Some(json!({"lines": 1})),
+                })
+                .unwrap(),
+        );
+        assert_eq!(end["type"], "tool_end");
+        assert_eq!(end["name"], "read");
+        assert_eq!(end["metadata"], json!({"lines": 1}));
+        assert!(end["duration_ms"].is_u64());
+    }
+
+    #[test]
+    fn test_result_carries_usage() {
+        let mut stream = EventStream::new();
+        assert!(stream.event(&AppUpdate::Started).is_none());
+        stream.event(&AppUpdate::TokenUsage {
+            input: 120,
+            output: 30,
+            cost: 0.01,
+            context_limit: 200_000,
+        });
+
+        let result = line(&stream.result(String::new(), Some("overloaded".into()), 1));
+        assert_eq!(result["type"], "result");
+        assert_eq!(result["status"], "error");
+        assert_eq!(result["exit_code"], 1);
+        assert_eq!(result["usage"]["input_tokens"], 120);
+        assert_eq!(result["usage"]["output_tokens"], 30);
+    }
+}
@@ -15,6 +15,7 @@ struct ToolExecutorWrapper {
     tool: Arc<dyn wonopcode_tools::Tool>,
     snapshot: Option<Arc<wonopcode_snapshot::SnapshotStore>>,
     file_time: Arc<wonopcode_util::FileTimeState>,
+    formatter: Arc<dyn wonopcode_tools::FileFormatter>,
     cancel: tokio_util::sync::CancellationToken,
     permissions: Arc<wonopcode_core::permission::PermissionManager>,
 }
@@ -36,7 +37,7 @@ impl wonopcode_mcp::McpToolExecutor for ToolExecutorWrapper {
             .or_else(|| args.get("path"))
             .or_else(|| args.get("file"))
             .and_then(|v| v.as_str())
-            .map(String::from);
+            .map(|path| wonopcode_core::permission::relative_path(path, &ctx.root_dir));
 
         // Check permission - this will prompt the user if needed via the shared Bus.
         // When using a shared permission manager with a TUI, "ask" rules will send
@@ -89,6 +90,8 @@ impl wonopcode_mcp::McpToolExecutor for ToolExecutorWrapper {
             file_time: Some(self.file_time.clone()),
             sandbox,
             event_tx: None, // MCP HTTP doesn't need event_tx
+            formatter: Some(self.formatter.clone()),
+            client_fs: None,
         };
 
         tracing::info!(
@@ -255,6 +258,15 @@ pub async fn create_mcp_http_state(
     // Initialize file time tracker
     let file_time = Arc::new(FileTimeState::new());
 
+    // Format files after edits, as configured for the project
+    let formatter_config = wonopcode_core::Config::load(Some(&root_dir))
+        .await
+        .ok()
+        .and_then(|(config, _)| config.formatter);
+    let formatter: Arc<dyn wonopcode_tools::FileFormatter> = Arc::new(
+        wonopcode_core::FormatterRegistry::for_project(&root_dir, formatter_config.as_ref()),
+    );
+
     // Use shared file todo store
     let todo_store: Arc<dyn wonopcode_tools::todo::TodoStore> = if let Some(store) =
         wonopcode_tools::todo::SharedFileTodoStore::from_env()
@@ -296,6 +308,7 @@ pub async fn create_mcp_http_state(
             tool: tool_clone,
             snapshot,
             file_time: ft,
+            formatter: formatter.clone(),
             cancel: cancel_clone,
             permissions: perm,
         };
@@ -334,10 +347,12 @@ pub async fn create_mcp_http_state(
 /// # Arguments
 /// * `address` - Socket address to bind the server to
 /// * `open_browser` - Whether to automatically open a browser to the web interface
+/// * `tls` - Identity to serve over TLS with, if any
 /// * `cwd` - Current working directory for the server instance
 pub async fn run_web_server(
     address: SocketAddr,
     open_browser: bool,
+    tls: Option<wonopcode_server::tls::Identity>,
     cwd: &std::path::Path,
 ) -> anyhow::Result<()> {
     println!();
@@ -348,6 +363,8 @@ pub async fn run_web_server(
 
     // Create instance
     let instance = wonopcode_core::Instance::new(cwd).await?;
+    #[cfg(feature = "forge")]
+    crate::forge::use_for_pull_requests(&instance).await;
     let bus = instance.bus().clone();
 
     // Create server state
@@ -357,20 +374,21 @@ pub async fn run_web_server(
     let app = wonopcode_server::create_router(state);
 
     // Determine URLs to display
+    let scheme = if tls.is_some() { "https" } else { "http" };
     let display_url = if address.ip().is_unspecified() {
         // Show localhost for local access
-        let local_url = format!("http://localhost:{}", address.port());
+        let local_url = format!("{scheme}://localhost:{}", address.port());
         println!("  Local access:      {local_url}");
 
         // Try to find network IPs
         if let Ok(interfaces) = get_network_ips() {
             for ip in interfaces {
-                println!("  Network access:    http://{}:{}", ip, address.port());
+                println!("  Network access:    {scheme}://{}:{}", ip, address.port());
             }
         }
         local_url
     } else {
-        let url = format!("http://{address}");
+        let url = format!("{scheme}://{address}");
         println!("  Web interface:     {url}");
         url
     };
@@ -390,7 +408,13 @@ pub async fn run_web_server(
 
     // Start server
     let listener = tokio::net::TcpListener::bind(address).await?;
-    axum::serve(listener, app).await?;
+    match tls {
+        Some(identity) => {
+            let listener = wonopcode_server::tls::TlsListener::new(listener, identity)?;
+            axum::serve(listener, app).await?
+        }
+        None => axum::serve(listener, app).await?,
+    }
 
     Ok(())
 }
@@ -0,0 +1,121 @@
+//! Commit messages drafted by the model from the staged changes.
+//!
+//! The staged diff is sent to the current model on its own, without the
+//! session's history or tools. Very large diffs are cut, so the draft is
+//! based on their start.
+
+use futures::StreamExt;
+use tracing::warn;
+use wonopcode_provider::{BoxedLanguageModel, GenerateOptions, Message, StreamChunk};
+
+/// Most characters of the diff sent to the model.
+const MAX_DIFF_CHARS: usize = 60_000;
+
+const COMMIT_MESSAGE_SYSTEM_PROMPT: &str = r#"You write git commit messages.
+
+Given a staged diff, reply with a commit message for it and nothing else:
+- A subject line in the imperative mood, at most 72 characters, without a trailing period
+- If the change needs explaining, a blank line and a short body wrapped at 72 characters,
+  saying what changed and why rather than listing every file
+
+Do not wrap the message in quotes or code fences."#;
+
+/// Draft a commit message for a staged diff.
+pub async fn draft(provider: &BoxedLanguageModel, diff: &str) -> Result<String, String> {
+    if diff.trim().is_empty() {
+        return Err("Nothing staged to describe".to_string());
+    }
+
+    let options = GenerateOptions {
+        system: Some(COMMIT_MESSAGE_SYSTEM_PROMPT.to_string()),
+        temperature: Some(0.2),
+        max_tokens: Some(500),
+        ..Default::default()
+    };
+    let stream = provider
+        .generate(vec![Message::user(prompt(diff))], options)
+        .await
+        .map_err(|e| format!("Failed to draft commit message: {e}"))?;
+
+    let mut stream = Box::pin(stream);
+    let mut reply = String::new();
+    while let Some(chunk) = stream.next().await {
+        match chunk {
+            Ok(StreamChunk::TextDelta(text)) => reply.push_str(&text),
+            Ok(StreamChunk::Error(e)) => {
+                warn!("Error drafting commit message: {}", e);
+                return Err(format!("Failed to draft commit message: {e}"));
+            }
+            Err(e) => {
+                warn!("Stream error: {}", e);
+                return Err(format!("Failed to draft commit message: {e}"));
+            }
+            _ => {}
+        }
+    }
+
+    let message = clean(&reply);
+    if message.is_empty() {
+        return Err("The model returned an empty commit message".to_string());
+    }
+    Ok(message)
+}
+
+/// The request for a diff, cut to [`MAX_DIFF_CHARS`].
+fn prompt(diff: &str) -> String {
+    match diff.char_indices().nth(MAX_DIFF_CHARS) {
+        Some((end, _)) => format!(
+            "Staged diff (cut after {MAX_DIFF_CHARS} characters):\n\n{}",
+            &diff[..end]
+        ),
+        None => format!("Staged diff:\n\n{diff}"),
+    }
+}
+
+/// Strip the code fence or quotes a model may put around the message.
+fn clean(reply: &str) -> String {
+    let mut message = reply.trim();
+    if let Some(inner) = message.strip_prefix("```") {
+        // Drop the fence's language tag
+        let inner = inner.split_once('\n').map_or("", |(_, rest)| rest);
+        message = inner.trim_end().strip_suffix("```").unwrap_or(inner).trim();
+    }
+    for quote in ['"', '\'', '`'] {
+        if message.len() > 1 && message.starts_with(quote) && message.ends_with(quote) {
+            message = message[1..message.len() - 1].trim();
+        }
+    }
+    message.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_keeps_plain_message() {
+        let reply = "\nFix hunk staging\n\nThe index was written twice.\n";
+        assert_eq!(
+            clean(reply),
+            "Fix hunk staging\n\nThe index was written twice."
+        );
+    }
+
+    #[test]
+    fn test_clean_strips_fences_and_quotes() {
+        assert_eq!(clean("```text\nAdd tabs\n```"), "Add tabs");
+        assert_eq!(clean("```\nAdd tabs\n\nBody\n```\n"), "Add tabs\n\nBody");
+        assert_eq!(clean("\"Add tabs\""), "Add tabs");
+        assert_eq!(clean("`Add tabs`"), "Add tabs");
+    }
+
+    #[test]
+    fn test_prompt_cuts_large_diffs() {
+        assert_eq!(prompt("+a\n"), "Staged diff:\n\n+a\n");
+
+        let diff = "é".repeat(MAX_DIFF_CHARS + 10);
+        let prompt = prompt(&diff);
+        assert!(prompt.starts_with("Staged diff (cut after"));
+        assert_eq!(prompt.matches('é').count(), MAX_DIFF_CHARS);
+    }
+}
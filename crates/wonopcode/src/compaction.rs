@@ -2,11 +2,20 @@
 //!
 //! Compaction happens in two phases:
 //! 1. **Prune phase**: Mark old tool outputs as compacted (>40K tokens ago)
-//! 2. **Summarize phase**: AI summarization of older messages
+//! 2. **Condense phase**: depends on the configured [`CompactionStrategy`]:
+//!    - `summarize_oldest`: AI summarization of older messages
+//!    - `drop_tool_outputs`: every old tool output is dropped in the prune
+//!      phase; older messages are summarized only if that isn't enough
+//!    - `semantic_retrieval`: older turns relevant to the latest request are
+//!      kept and the rest are dropped, without a model call
 //!
+//! Thresholds use the prompt size reported by the provider for the last
+//! request when available, see [`MeasuredContext`].
 
 use futures::StreamExt;
+use std::collections::HashSet;
 use tracing::{debug, info, warn};
+pub use wonopcode_core::config::CompactionStrategy;
 use wonopcode_provider::{
     BoxedLanguageModel, ContentPart, GenerateOptions, Message as ProviderMessage, Role, StreamChunk,
 };
@@ -24,6 +33,9 @@ pub const PROTECTED_TOOLS: &[&str] = &["skill"];
 /// Default output token reserve.
 pub const OUTPUT_TOKEN_MAX: u32 = 16_000;
 
+/// Default budget for retrieved context per prompt.
+pub const RETRIEVAL_TOKENS: u32 = 4_000;
+
 /// Configuration for compaction behavior.
 #[derive(Debug, Clone)]
 pub struct CompactionConfig {
@@ -41,6 +53,30 @@ pub struct CompactionConfig {
 
     /// Maximum output tokens to reserve.
     pub output_reserve: u32,
+
+    /// How older context is condensed.
+    /// Controlled by config.compaction.strategy
+    pub strategy: CompactionStrategy,
+
+    /// Fraction of the usable context at which automatic compaction starts.
+    /// Controlled by config.compaction.threshold
+    pub threshold: f32,
+
+    /// Whether to show what will be condensed before compacting.
+    /// Controlled by config.compaction.preview
+    pub preview: bool,
+
+    /// Embedding model (provider/model) for retrieving compacted context.
+    /// Controlled by config.compaction.embedding_model
+    pub embedding_model: Option<String>,
+
+    /// OpenAI-compatible embeddings server to use instead of the provider.
+    /// Controlled by config.compaction.embedding_url
+    pub embedding_url: Option<String>,
+
+    /// Maximum tokens of retrieved context per prompt.
+    /// Controlled by config.compaction.retrieval_tokens
+    pub retrieval_tokens: u32,
 }
 
 impl Default for CompactionConfig {
@@ -50,10 +86,103 @@ impl Default for CompactionConfig {
             prune: true,
             preserve_turns: 2,
             output_reserve: OUTPUT_TOKEN_MAX,
+            strategy: CompactionStrategy::default(),
+            threshold: 1.0,
+            preview: true,
+            embedding_model: None,
+            embedding_url: None,
+            retrieval_tokens: RETRIEVAL_TOKENS,
         }
     }
 }
 
+impl CompactionConfig {
+    /// Build from the core config, applying the agent's overrides.
+    pub fn from_config(config: &wonopcode_core::Config, agent: &str) -> Self {
+        let global = config.compaction.clone().unwrap_or_default();
+        let settings = match config
+            .agent
+            .as_ref()
+            .and_then(|agents| agents.get(agent))
+            .and_then(|a| a.compaction.as_ref())
+        {
+            Some(overrides) => global.overlay(overrides),
+            None => global,
+        };
+
+        let defaults = Self::default();
+        Self {
+            auto: settings.auto.unwrap_or(defaults.auto),
+            prune: settings.prune.unwrap_or(defaults.prune),
+            preserve_turns: settings.preserve_turns.unwrap_or(defaults.preserve_turns),
+            output_reserve: defaults.output_reserve,
+            strategy: settings.strategy.unwrap_or(defaults.strategy),
+            threshold: settings
+                .threshold
+                .map_or(defaults.threshold, |t| t.clamp(0.1, 1.0)),
+            preview: settings.preview.unwrap_or(defaults.preview),
+            embedding_model: settings.embedding_model,
+            embedding_url: settings.embedding_url,
+            retrieval_tokens: settings
+                .retrieval_tokens
+                .unwrap_or(defaults.retrieval_tokens),
+        }
+    }
+
+    /// Token count at which compaction starts for a context window.
+    pub fn threshold_tokens(&self, context_limit: u32) -> u32 {
+        let usable = context_limit.saturating_sub(self.output_reserve.min(OUTPUT_TOKEN_MAX));
+        (usable as f64 * self.threshold as f64) as u32
+    }
+}
+
+/// Prompt size reported by the provider for a request.
+///
+/// Used instead of the character-based estimate for the messages that were
+/// part of that request, as long as they haven't changed since.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeasuredContext {
+    /// Number of messages sent.
+    messages: usize,
+    /// Estimated tokens of those messages, to detect later changes.
+    estimated: u32,
+    /// Input tokens reported by the provider, including cache reads.
+    tokens: u32,
+}
+
+impl MeasuredContext {
+    /// Record the reported prompt size for `messages`.
+    pub fn new(messages: &[ProviderMessage], tokens: u32) -> Self {
+        Self {
+            messages: messages.len(),
+            estimated: estimate_messages_tokens(messages),
+            tokens,
+        }
+    }
+}
+
+/// Token usage of `messages`, based on the last measured request if it still
+/// applies, plus estimates for messages added since.
+pub fn context_usage(
+    messages: &[ProviderMessage],
+    measured: Option<MeasuredContext>,
+) -> TokenUsage {
+    let input = match measured {
+        Some(m)
+            if m.tokens > 0
+                && m.messages <= messages.len()
+                && estimate_messages_tokens(&messages[..m.messages]) == m.estimated =>
+        {
+            m.tokens + estimate_messages_tokens(&messages[m.messages..])
+        }
+        _ => estimate_messages_tokens(messages),
+    };
+    TokenUsage {
+        input,
+        ..Default::default()
+    }
+}
+
 /// Token usage from a response.
 #[derive(Debug, Clone, Default)]
 pub struct TokenUsage {
@@ -94,6 +223,7 @@ impl TokenUsage {
 }
 
 /// Check if context is overflowing (needs compaction).
+#[allow(dead_code)] // Public API for library consumers
 pub fn is_overflow(tokens: &TokenUsage, context_limit: u32, output_reserve: u32) -> bool {
     if context_limit == 0 {
         return false;
@@ -106,6 +236,11 @@ pub fn is_overflow(tokens: &TokenUsage, context_limit: u32, output_reserve: u32)
     count > usable
 }
 
+/// Check if usage is past the configured compaction threshold.
+fn exceeds_threshold(tokens: &TokenUsage, context_limit: u32, config: &CompactionConfig) -> bool {
+    context_limit > 0 && tokens.total() > config.threshold_tokens(context_limit)
+}
+
 /// Result of a compaction operation.
 #[derive(Debug)]
 #[allow(dead_code)] // Fields are part of public API for library consumers
@@ -151,12 +286,61 @@ struct PrunablePart {
 /// Goes backwards through messages, protecting the last 40K tokens of tool
 /// outputs, then marks older outputs as compacted if they would prune >20K tokens.
 ///
-#[allow(clippy::cognitive_complexity)]
 fn prune_tool_outputs(messages: &mut [ProviderMessage], config: &CompactionConfig) -> u32 {
     if !config.prune {
         return 0;
     }
 
+    let (parts, prunable_tokens) = find_prunable_outputs(messages, config, PRUNE_PROTECT);
+
+    // Only prune if we'd save enough tokens
+    if prunable_tokens < PRUNE_MINIMUM {
+        return 0;
+    }
+
+    mark_compacted(messages, &parts);
+    info!(
+        pruned = parts.len(),
+        tokens_saved = prunable_tokens,
+        "Pruned tool outputs"
+    );
+
+    prunable_tokens
+}
+
+/// Drop every tool output older than the preserved turns.
+fn drop_tool_outputs(messages: &mut [ProviderMessage], config: &CompactionConfig) -> u32 {
+    let (parts, dropped_tokens) = find_prunable_outputs(messages, config, 0);
+    mark_compacted(messages, &parts);
+    if !parts.is_empty() {
+        info!(
+            dropped = parts.len(),
+            tokens_saved = dropped_tokens,
+            "Dropped tool outputs"
+        );
+    }
+    dropped_tokens
+}
+
+/// Run the prune phase for the configured strategy.
+fn prune_for_strategy(messages: &mut [ProviderMessage], config: &CompactionConfig) -> u32 {
+    match config.strategy {
+        CompactionStrategy::DropToolOutputs => drop_tool_outputs(messages, config),
+        _ => prune_tool_outputs(messages, config),
+    }
+}
+
+/// Find tool outputs that can be pruned.
+///
+/// Goes backwards through messages, skipping the preserved turns and the most
+/// recent `protect` tokens of tool outputs. Returns the `(message, part)`
+/// indices and their estimated token count.
+#[allow(clippy::cognitive_complexity)]
+fn find_prunable_outputs(
+    messages: &[ProviderMessage],
+    config: &CompactionConfig,
+    protect: u32,
+) -> (Vec<(usize, usize)>, u32) {
     let mut total_tokens: u32 = 0;
     let mut prunable_tokens: u32 = 0;
     let mut parts_to_prune: Vec<(usize, usize)> = Vec::new();
@@ -210,7 +394,7 @@ fn prune_tool_outputs(messages: &mut [ProviderMessage], config: &CompactionConfi
                 total_tokens += estimate;
 
                 // If we're past the protection threshold, mark for pruning
-                if total_tokens > PRUNE_PROTECT {
+                if total_tokens > protect {
                     prunable_tokens += estimate;
                     parts_to_prune.push((msg_idx, part_idx));
                 }
@@ -225,35 +409,24 @@ fn prune_tool_outputs(messages: &mut [ProviderMessage], config: &CompactionConfi
         "Prune analysis"
     );
 
-    // Only prune if we'd save enough tokens
-    if prunable_tokens < PRUNE_MINIMUM {
-        return 0;
-    }
+    (parts_to_prune, prunable_tokens)
+}
 
-    // Mark parts as compacted
-    let mut pruned_count = 0;
-    for (msg_idx, part_idx) in parts_to_prune {
+/// Replace the given tool outputs with a compaction marker.
+fn mark_compacted(messages: &mut [ProviderMessage], parts: &[(usize, usize)]) {
+    for &(msg_idx, part_idx) in parts {
         if let ContentPart::ToolResult { tool_use_id, .. } = &messages[msg_idx].content[part_idx] {
             messages[msg_idx].content[part_idx] = ContentPart::ToolResult {
                 tool_use_id: tool_use_id.clone(),
                 content: "[compacted]".to_string(),
                 is_error: None,
             };
-            pruned_count += 1;
         }
     }
-
-    info!(
-        pruned = pruned_count,
-        tokens_saved = prunable_tokens,
-        "Pruned tool outputs"
-    );
-
-    prunable_tokens
 }
 
 /// Check if a message is a compaction summary message.
-fn is_compaction_message(msg: &ProviderMessage) -> bool {
+pub fn is_compaction_message(msg: &ProviderMessage) -> bool {
     if msg.role != Role::Assistant {
         return false;
     }
@@ -270,7 +443,7 @@ fn is_compaction_message(msg: &ProviderMessage) -> bool {
 }
 
 /// Find the tool name for a given tool_use_id by searching messages.
-fn find_tool_name(messages: &[ProviderMessage], tool_use_id: &str) -> Option<String> {
+pub fn find_tool_name(messages: &[ProviderMessage], tool_use_id: &str) -> Option<String> {
     for msg in messages {
         for part in &msg.content {
             if let ContentPart::ToolUse { id, name, .. } = part {
@@ -284,7 +457,7 @@ fn find_tool_name(messages: &[ProviderMessage], tool_use_id: &str) -> Option<Str
 }
 
 /// Estimate token count for text (roughly 4 chars per token).
-fn estimate_tokens(text: &str) -> u32 {
+pub fn estimate_tokens(text: &str) -> u32 {
     (text.len() / 4).max(1) as u32
 }
 
@@ -306,10 +479,11 @@ Format your response as a clear, structured summary. Do not include any preamble
 
 const COMPACTION_USER_PROMPT: &str = r#"Provide a detailed prompt for continuing our conversation above. Focus on information that would be helpful for continuing the conversation, including what we did, what we're doing, which files we're working on, and what we're going to do next considering new session will not have access to our conversation."#;
 
-/// Perform full compaction: prune first, then summarize if needed.
+/// Perform full compaction: prune first, then condense if needed.
 ///
 /// 1. Prune old tool outputs
-/// 2. If still over limit, create AI summary
+/// 2. If still over the threshold, condense older messages using the
+///    configured strategy
 /// 3. Optionally add "Continue if you have next steps" message
 pub async fn compact(
     messages: &mut [ProviderMessage],
@@ -320,7 +494,7 @@ pub async fn compact(
     auto_continue: bool,
 ) -> CompactionResult {
     // Phase 1: Prune tool outputs
-    let pruned_tokens = prune_tool_outputs(messages, config);
+    let pruned_tokens = prune_for_strategy(messages, config);
 
     if pruned_tokens > 0 {
         debug!(pruned = pruned_tokens, "Pruned tool outputs");
@@ -334,7 +508,7 @@ pub async fn compact(
         cache_write: tokens.cache_write,
     };
 
-    if !is_overflow(&adjusted_tokens, context_limit, config.output_reserve) {
+    if !exceeds_threshold(&adjusted_tokens, context_limit, config) {
         if pruned_tokens > 0 {
             return CompactionResult::Compacted {
                 messages: messages.to_vec(),
@@ -345,8 +519,15 @@ pub async fn compact(
         return CompactionResult::NotNeeded;
     }
 
-    // Phase 2: AI summarization
-    let mut result = compact_with_summary(messages, provider, config).await;
+    // Phase 2: Condense older messages
+    let mut result = match config.strategy {
+        CompactionStrategy::SemanticRetrieval => {
+            compact_with_retrieval(messages, config, context_limit)
+        }
+        CompactionStrategy::SummarizeOldest | CompactionStrategy::DropToolOutputs => {
+            compact_with_summary(messages, provider, config).await
+        }
+    };
 
     // Phase 3: Add auto-continue message if requested
     if auto_continue {
@@ -366,6 +547,120 @@ pub async fn compact(
     result
 }
 
+/// What a compaction would condense.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionPreview {
+    /// Strategy that would be used.
+    pub strategy: CompactionStrategy,
+    /// Tool outputs that would be replaced by a marker.
+    pub tool_outputs: usize,
+    /// Messages that would be summarized or dropped.
+    pub messages: usize,
+    /// Estimated tokens condensed.
+    pub tokens: u32,
+}
+
+impl CompactionPreview {
+    /// Whether nothing would be condensed.
+    pub fn is_empty(&self) -> bool {
+        self.tool_outputs == 0 && self.messages == 0
+    }
+
+    /// One-line description for the user.
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if self.tool_outputs > 0 {
+            parts.push(format!("clear {} old tool outputs", self.tool_outputs));
+        }
+        if self.messages > 0 {
+            let verb = match self.strategy {
+                CompactionStrategy::SemanticRetrieval => "drop",
+                _ => "summarize",
+            };
+            parts.push(format!("{verb} {} older messages", self.messages));
+        }
+        if parts.is_empty() {
+            return format!(
+                "Compaction ({}): nothing to condense",
+                self.strategy.as_str()
+            );
+        }
+        format!(
+            "Compaction ({}) will {} (~{} tokens)",
+            self.strategy.as_str(),
+            parts.join(" and "),
+            self.tokens
+        )
+    }
+}
+
+/// Work out what [`compact`] would condense, without changing anything.
+pub fn preview(
+    messages: &[ProviderMessage],
+    config: &CompactionConfig,
+    tokens: &TokenUsage,
+    context_limit: u32,
+) -> CompactionPreview {
+    let mut pruned = messages.to_vec();
+    let before: Vec<(usize, usize)> = compacted_outputs(&pruned);
+    let pruned_tokens = prune_for_strategy(&mut pruned, config);
+    let tool_outputs = compacted_outputs(&pruned).len() - before.len();
+
+    let mut preview = CompactionPreview {
+        strategy: config.strategy,
+        tool_outputs,
+        messages: 0,
+        tokens: pruned_tokens,
+    };
+
+    let adjusted_tokens = TokenUsage {
+        input: tokens.input.saturating_sub(pruned_tokens),
+        ..tokens.clone()
+    };
+    if !exceeds_threshold(&adjusted_tokens, context_limit, config) {
+        return preview;
+    }
+
+    match config.strategy {
+        CompactionStrategy::SemanticRetrieval => {
+            if let Some(plan) = plan_retrieval(&pruned, config, context_limit) {
+                preview.messages = plan.dropped;
+                preview.tokens += plan.dropped_tokens;
+            }
+        }
+        CompactionStrategy::SummarizeOldest | CompactionStrategy::DropToolOutputs => {
+            if let Some(range) = summary_range(&pruned) {
+                preview.messages = range.len();
+                preview.tokens += estimate_messages_tokens(&pruned[range]);
+            }
+        }
+    }
+    preview
+}
+
+/// Indices of tool outputs that are already compacted.
+fn compacted_outputs(messages: &[ProviderMessage]) -> Vec<(usize, usize)> {
+    let mut parts = Vec::new();
+    for (msg_idx, msg) in messages.iter().enumerate() {
+        for (part_idx, part) in msg.content.iter().enumerate() {
+            if matches!(part, ContentPart::ToolResult { content, .. } if content == "[compacted]") {
+                parts.push((msg_idx, part_idx));
+            }
+        }
+    }
+    parts
+}
+
+/// Messages that summarization replaces: all but the first and the last 4.
+fn summary_range(messages: &[ProviderMessage]) -> Option<std::ops::Range<usize>> {
+    if messages.len() < 4 {
+        return None;
+    }
+    let preserve_recent = 4.min(messages.len() - 1);
+    let middle_end = messages.len().saturating_sub(preserve_recent);
+    (middle_end > 1).then_some(1..middle_end)
+}
+
 /// Perform smart compaction by summarizing older messages.
 ///
 /// This creates a summary of older messages using the AI, then returns
@@ -375,19 +670,13 @@ pub async fn compact_with_summary(
     provider: &BoxedLanguageModel,
     _config: &CompactionConfig,
 ) -> CompactionResult {
-    // Need at least a few messages to summarize
-    if messages.len() < 4 {
-        return CompactionResult::InsufficientMessages;
-    }
-
     // Find the split point: keep first message, summarize middle, keep recent
     // "Recent" = last 2 user-assistant exchanges (4 messages)
-    let preserve_recent = 4.min(messages.len() - 1);
-    let middle_end = messages.len().saturating_sub(preserve_recent);
-
-    if middle_end <= 1 {
+    let Some(range) = summary_range(messages) else {
         return CompactionResult::InsufficientMessages;
-    }
+    };
+    let preserve_recent = messages.len() - range.end;
+    let middle_end = range.end;
 
     let first_message = &messages[0];
     let messages_to_summarize = &messages[1..middle_end];
@@ -457,6 +746,185 @@ pub async fn compact_with_summary(
     }
 }
 
+/// Older turns kept and dropped by semantic retrieval.
+#[derive(Debug)]
+struct RetrievalPlan {
+    /// Whether each message is kept.
+    keep: Vec<bool>,
+    /// Number of dropped messages.
+    dropped: usize,
+    /// Estimated tokens of the dropped messages.
+    dropped_tokens: u32,
+}
+
+/// Whether a message is a prompt typed by the user (not a tool result).
+pub fn is_user_prompt(msg: &ProviderMessage) -> bool {
+    msg.role == Role::User
+        && msg
+            .content
+            .iter()
+            .any(|p| matches!(p, ContentPart::Text { .. }))
+        && !msg
+            .content
+            .iter()
+            .any(|p| matches!(p, ContentPart::ToolResult { .. }))
+}
+
+/// Common words ignored when matching turns to a request.
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "this", "that", "from", "into", "now", "you", "are", "can",
+    "please", "what", "how", "then", "also", "not", "but", "all", "any", "have", "has",
+];
+
+/// Lowercase words of at least three characters in a message, minus stop words.
+fn message_terms(msg: &ProviderMessage) -> HashSet<String> {
+    let mut terms = HashSet::new();
+    let mut add = |text: &str| {
+        for word in text.split(|c: char| !c.is_alphanumeric() && c != '_') {
+            if word.len() >= 3 {
+                let word = word.to_lowercase();
+                if !STOP_WORDS.contains(&word.as_str()) {
+                    terms.insert(word);
+                }
+            }
+        }
+    };
+    for part in &msg.content {
+        match part {
+            ContentPart::Text { text } => add(text),
+            ContentPart::ToolUse { name, input, .. } => {
+                add(name);
+                add(&input.to_string());
+            }
+            ContentPart::ToolResult { content, .. } => add(content),
+            ContentPart::Image { .. } | ContentPart::Thinking { .. } => {}
+        }
+    }
+    terms
+}
+
+/// Decide which older turns to keep, by how many terms of the latest user
+/// prompt they share.
+///
+/// The first message and the last `preserve_turns` turns are always kept.
+/// Relevant older turns are kept, best first, while they fit in half of the
+/// compaction threshold.
+fn plan_retrieval(
+    messages: &[ProviderMessage],
+    config: &CompactionConfig,
+    context_limit: u32,
+) -> Option<RetrievalPlan> {
+    let starts: Vec<usize> = (1..messages.len())
+        .filter(|&i| is_user_prompt(&messages[i]))
+        .collect();
+    let recent_start = *starts.iter().rev().nth(config.preserve_turns.max(1) - 1)?;
+    if recent_start <= 1 {
+        return None;
+    }
+
+    let query = message_terms(&messages[*starts.last()?]);
+
+    // Older turns between the first message and the recent ones
+    let mut bounds: Vec<usize> = vec![1];
+    bounds.extend(
+        starts
+            .iter()
+            .copied()
+            .filter(|&i| i > 1 && i < recent_start),
+    );
+    bounds.push(recent_start);
+    let mut turns: Vec<(usize, usize, usize, u32)> = bounds
+        .windows(2)
+        .map(|w| {
+            let (start, end) = (w[0], w[1]);
+            let mut terms = HashSet::new();
+            for msg in &messages[start..end] {
+                terms.extend(message_terms(msg));
+            }
+            let score = query.intersection(&terms).count();
+            (
+                start,
+                end,
+                score,
+                estimate_messages_tokens(&messages[start..end]),
+            )
+        })
+        .collect();
+
+    let mut budget = (config.threshold_tokens(context_limit) / 2)
+        .saturating_sub(estimate_message_tokens(&messages[0]))
+        .saturating_sub(estimate_messages_tokens(&messages[recent_start..]));
+
+    // Most relevant first, later turns winning ties
+    turns.sort_by(|a, b| b.2.cmp(&a.2).then(b.0.cmp(&a.0)));
+
+    let mut keep = vec![true; messages.len()];
+    let mut dropped = 0;
+    let mut dropped_tokens = 0;
+    for (start, end, score, tokens) in turns {
+        if score > 0 && tokens <= budget {
+            budget -= tokens;
+            continue;
+        }
+        for flag in &mut keep[start..end] {
+            *flag = false;
+        }
+        dropped += end - start;
+        dropped_tokens += tokens;
+    }
+
+    (dropped > 0).then_some(RetrievalPlan {
+        keep,
+        dropped,
+        dropped_tokens,
+    })
+}
+
+/// Compact by keeping only the older turns relevant to the latest request.
+fn compact_with_retrieval(
+    messages: &[ProviderMessage],
+    config: &CompactionConfig,
+    context_limit: u32,
+) -> CompactionResult {
+    let Some(plan) = plan_retrieval(messages, config, context_limit) else {
+        return CompactionResult::InsufficientMessages;
+    };
+
+    info!(
+        dropped = plan.dropped,
+        tokens_saved = plan.dropped_tokens,
+        "Compacting messages with semantic retrieval"
+    );
+
+    let summary = format!(
+        "{} earlier messages that were not relevant to the current request were removed.",
+        plan.dropped
+    );
+    let mut new_messages = Vec::with_capacity(messages.len() - plan.dropped + 1);
+    new_messages.push(messages[0].clone());
+    new_messages.push(ProviderMessage {
+        role: Role::Assistant,
+        content: vec![ContentPart::text(format!(
+            "[Previous conversation summary ({} messages)]\n\n{}",
+            plan.dropped, summary
+        ))],
+    });
+    new_messages.extend(
+        messages
+            .iter()
+            .zip(&plan.keep)
+            .skip(1)
+            .filter(|(_, &keep)| keep)
+            .map(|(msg, _)| msg.clone()),
+    );
+
+    CompactionResult::Compacted {
+        messages: new_messages,
+        summary,
+        messages_summarized: plan.dropped,
+    }
+}
+
 /// Generate a summary using the provider.
 async fn generate_summary(
     provider: &BoxedLanguageModel,
@@ -552,10 +1020,12 @@ fn format_messages_for_summary(messages: &[ProviderMessage]) -> String {
     output
 }
 
-/// Legacy function for backward compatibility.
-/// Check if compaction is needed based on estimated token usage.
+/// Check if automatic compaction is needed.
+///
+/// `tokens` should come from [`context_usage`].
 pub fn needs_compaction(
     messages: &[ProviderMessage],
+    tokens: &TokenUsage,
     context_limit: u32,
     config: &CompactionConfig,
 ) -> bool {
@@ -567,18 +1037,15 @@ pub fn needs_compaction(
         return false;
     }
 
-    let estimated_tokens = estimate_messages_tokens(messages);
-    let threshold = context_limit.saturating_sub(config.output_reserve);
-
     debug!(
-        estimated_tokens = estimated_tokens,
-        threshold = threshold,
+        tokens = tokens.total(),
+        threshold = config.threshold_tokens(context_limit),
         context_limit = context_limit,
         messages = messages.len(),
         "Checking if compaction needed"
     );
 
-    estimated_tokens > threshold
+    exceeds_threshold(tokens, context_limit, config)
 }
 
 /// Estimate token count for a list of messages.
@@ -587,6 +1054,7 @@ pub fn estimate_messages_tokens(messages: &[ProviderMessage]) -> u32 {
 }
 
 /// Create estimated TokenUsage from messages (for pre-prompt compaction checks).
+#[allow(dead_code)] // Public API for library consumers
 pub fn estimate_token_usage(messages: &[ProviderMessage]) -> TokenUsage {
     let estimated = estimate_messages_tokens(messages);
     TokenUsage {
@@ -649,12 +1117,155 @@ mod tests {
         let config = CompactionConfig::default();
 
         // Empty messages - no compaction
-        assert!(!needs_compaction(&[], 100_000, &config));
+        let tokens = TokenUsage::default();
+        assert!(!needs_compaction(&[], &tokens, 100_000, &config));
 
         // Few messages - no compaction
         let few_messages: Vec<ProviderMessage> =
             (0..3).map(|_| ProviderMessage::user("test")).collect();
-        assert!(!needs_compaction(&few_messages, 100_000, &config));
+        assert!(!needs_compaction(&few_messages, &tokens, 100_000, &config));
+
+        // The threshold is a fraction of the usable context
+        let messages: Vec<ProviderMessage> =
+            (0..6).map(|_| ProviderMessage::user("test")).collect();
+        let tokens = TokenUsage {
+            input: 60_000,
+            ..Default::default()
+        };
+        assert!(!needs_compaction(&messages, &tokens, 100_000, &config));
+        let config = CompactionConfig {
+            threshold: 0.5,
+            ..Default::default()
+        };
+        assert!(needs_compaction(&messages, &tokens, 100_000, &config));
+    }
+
+    #[test]
+    fn test_context_usage_prefers_measured() {
+        let mut messages = vec![
+            ProviderMessage::user("hello"),
+            ProviderMessage::assistant("hi"),
+        ];
+        let measured = MeasuredContext::new(&messages, 5_000);
+        assert_eq!(context_usage(&messages, Some(measured)).input, 5_000);
+
+        // New messages are estimated on top
+        messages.push(ProviderMessage::user("more"));
+        let added = estimate_message_tokens(&messages[2]);
+        assert_eq!(
+            context_usage(&messages, Some(measured)).input,
+            5_000 + added
+        );
+
+        // A rewritten history falls back to the estimate
+        messages[0] = ProviderMessage::user("something else entirely");
+        assert_eq!(
+            context_usage(&messages, Some(measured)).input,
+            estimate_messages_tokens(&messages)
+        );
+    }
+
+    #[test]
+    fn test_config_from_agent_overrides() {
+        let config: wonopcode_core::Config = serde_json::from_str(
+            r#"{
+                "compaction": {"strategy": "drop_tool_outputs", "threshold": 0.8},
+                "agent": {"plan": {"compaction": {"strategy": "semantic_retrieval"}}}
+            }"#,
+        )
+        .unwrap();
+
+        let build = CompactionConfig::from_config(&config, "build");
+        assert_eq!(build.strategy, CompactionStrategy::DropToolOutputs);
+        assert_eq!(build.threshold, 0.8);
+
+        let plan = CompactionConfig::from_config(&config, "plan");
+        assert_eq!(plan.strategy, CompactionStrategy::SemanticRetrieval);
+        assert_eq!(plan.threshold, 0.8);
+    }
+
+    fn tool_turn(prompt: &str, id: &str, output: &str) -> Vec<ProviderMessage> {
+        vec![
+            ProviderMessage::user(prompt),
+            ProviderMessage {
+                role: Role::Assistant,
+                content: vec![ContentPart::ToolUse {
+                    id: id.to_string(),
+                    name: "read".to_string(),
+                    input: serde_json::json!({}),
+                }],
+            },
+            ProviderMessage {
+                role: Role::Tool,
+                content: vec![ContentPart::ToolResult {
+                    tool_use_id: id.to_string(),
+                    content: output.to_string(),
+                    is_error: None,
+                }],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_drop_tool_outputs_strategy() {
+        let mut messages = vec![ProviderMessage::user("start")];
+        messages.extend(tool_turn("read a", "t1", &"a".repeat(4_000)));
+        messages.extend(tool_turn("read b", "t2", &"b".repeat(4_000)));
+        messages.extend(tool_turn("read c", "t3", &"c".repeat(4_000)));
+
+        // Too small for the default prune, but dropped by the strategy
+        let config = CompactionConfig {
+            strategy: CompactionStrategy::DropToolOutputs,
+            ..Default::default()
+        };
+        let tokens = estimate_token_usage(&messages);
+        let preview = preview(&messages, &config, &tokens, 100_000);
+        // The last two turns are preserved
+        assert_eq!(preview.tool_outputs, 1);
+        assert_eq!(preview.messages, 0);
+        assert!(!preview.is_empty());
+
+        assert_eq!(
+            prune_tool_outputs(&mut messages.clone(), &CompactionConfig::default()),
+            0
+        );
+        assert!(drop_tool_outputs(&mut messages, &config) > 0);
+        assert_eq!(compacted_outputs(&messages), vec![(3, 0)]);
+    }
+
+    #[test]
+    fn test_semantic_retrieval_keeps_relevant_turns() {
+        let mut messages = vec![ProviderMessage::user("start")];
+        messages.extend(tool_turn("look at the parser module", "t1", "parser code"));
+        messages.extend(tool_turn(
+            "check the billing invoices",
+            "t2",
+            "invoice data",
+        ));
+        messages.extend(tool_turn("unrelated weather question", "t3", "sunny"));
+        messages.extend(tool_turn("now fix the parser bug", "t4", "done"));
+
+        let config = CompactionConfig {
+            strategy: CompactionStrategy::SemanticRetrieval,
+            preserve_turns: 1,
+            ..Default::default()
+        };
+        let plan = plan_retrieval(&messages, &config, 100_000).unwrap();
+        assert_eq!(plan.dropped, 6);
+        assert!(plan.keep[1..4].iter().all(|&k| k));
+        assert!(plan.keep[4..10].iter().all(|&k| !k));
+
+        let CompactionResult::Compacted {
+            messages: compacted,
+            messages_summarized,
+            ..
+        } = compact_with_retrieval(&messages, &config, 100_000)
+        else {
+            panic!("expected compaction");
+        };
+        assert_eq!(messages_summarized, 6);
+        assert_eq!(compacted.len(), messages.len() - 6 + 1);
+        assert!(is_compaction_message(&compacted[1]));
     }
 
     #[test]
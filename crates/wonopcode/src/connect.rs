@@ -0,0 +1,370 @@
+//! Switching the TUI's backend while it runs.
+//!
+//! `/connect` browses the local network for headless servers and connects to
+//! the one picked, or to an address. [`BackendSwitch`] sits between the TUI
+//! and its backend: it does the browsing itself, and on a connect it opens a
+//! [`RemoteBackend`], loads the server's state into the TUI and sends the
+//! TUI's actions there from then on. Local runners are stopped once the TUI
+//! has moved to a server.
+
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use wonopcode_protocol::Capability;
+use wonopcode_tui::{AppAction, AppUpdate, Backend, RemoteBackend};
+
+/// How to connect to servers.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectOptions {
+    /// Secret for servers that need one, unless another is given.
+    pub secret: Option<String>,
+    /// CA certificate (PEM) to trust for https servers.
+    pub tls_ca: Option<PathBuf>,
+    /// Receive updates over SSE rather than a WebSocket.
+    pub sse: bool,
+}
+
+impl ConnectOptions {
+    /// Connect to the server at `address`, with `secret` or else the one
+    /// given at startup.
+    pub async fn open(
+        &self,
+        address: &str,
+        secret: Option<String>,
+    ) -> anyhow::Result<RemoteBackend> {
+        let secret = secret.or_else(|| self.secret.clone());
+        let mut backend = RemoteBackend::with_api_key(address, secret)?;
+        if let Some(ref ca) = self.tls_ca {
+            let pem = std::fs::read(ca)
+                .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", ca.display()))?;
+            backend = backend.with_ca_certificate(&pem)?;
+        }
+        backend.connect().await?;
+        Ok(backend)
+    }
+
+    /// Receive the server's updates, and send actions, over a WebSocket; or
+    /// receive them over SSE.
+    pub fn subscribe(
+        &self,
+        backend: &mut RemoteBackend,
+        update_tx: mpsc::UnboundedSender<AppUpdate>,
+    ) -> JoinHandle<()> {
+        let use_sse = self.sse || !backend.supports(Capability::WebSocket);
+        if use_sse && !self.sse {
+            warn!("The server doesn't offer WebSockets, using SSE");
+        }
+        if use_sse {
+            backend.subscribe_updates(update_tx)
+        } else {
+            backend.open_socket(update_tx)
+        }
+    }
+}
+
+/// An address as given, with `:port` meaning a server on this machine.
+pub fn normalize_address(address: &str) -> String {
+    if address.starts_with(':') {
+        format!("127.0.0.1{address}")
+    } else {
+        address.to_string()
+    }
+}
+
+/// Where the TUI's actions go.
+enum Target {
+    /// The session tabs' runners, in this process.
+    Local(mpsc::UnboundedSender<AppAction>),
+    /// A headless server, and the task receiving its updates.
+    Remote {
+        backend: RemoteBackend,
+        updates: JoinHandle<()>,
+    },
+}
+
+/// Sends the TUI's actions to its backend, and moves it to a server on
+/// `/connect`.
+pub struct BackendSwitch {
+    target: Target,
+    options: ConnectOptions,
+    update_tx: mpsc::UnboundedSender<AppUpdate>,
+    #[cfg(feature = "discover")]
+    browsing: Option<discovery::Browsing>,
+}
+
+impl BackendSwitch {
+    /// Send actions to the local runners behind `local`.
+    pub fn local(
+        local: mpsc::UnboundedSender<AppAction>,
+        options: ConnectOptions,
+        update_tx: mpsc::UnboundedSender<AppUpdate>,
+    ) -> Self {
+        Self::new(Target::Local(local), options, update_tx)
+    }
+
+    /// Send actions to a connected server, whose updates `updates` receives.
+    pub fn remote(
+        backend: RemoteBackend,
+        updates: JoinHandle<()>,
+        options: ConnectOptions,
+        update_tx: mpsc::UnboundedSender<AppUpdate>,
+    ) -> Self {
+        Self::new(Target::Remote { backend, updates }, options, update_tx)
+    }
+
+    fn new(
+        target: Target,
+        options: ConnectOptions,
+        update_tx: mpsc::UnboundedSender<AppUpdate>,
+    ) -> Self {
+        Self {
+            target,
+            options,
+            update_tx,
+            #[cfg(feature = "discover")]
+            browsing: None,
+        }
+    }
+
+    /// Handle the TUI's actions until it quits.
+    pub async fn run(mut self, mut action_rx: mpsc::UnboundedReceiver<AppAction>) {
+        while let Some(action) = action_rx.recv().await {
+            match action {
+                AppAction::BrowseServers => self.browse(),
+                AppAction::StopBrowsingServers => self.stop_browsing(),
+                AppAction::ConnectServer { address, secret } => {
+                    self.connect(&address, secret).await;
+                }
+                action => self.forward(action).await,
+            }
+        }
+    }
+
+    async fn forward(&self, action: AppAction) {
+        match &self.target {
+            Target::Local(runners) => {
+                let _ = runners.send(action);
+            }
+            Target::Remote { backend, .. } => {
+                if let Err(e) = backend.send_action(action).await {
+                    warn!("Failed to send action: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Move the TUI to the server at `address`. If it can't be reached, the
+    /// TUI stays where it is.
+    async fn connect(&mut self, address: &str, secret: Option<String>) {
+        let address = normalize_address(address);
+        info!(%address, "Connecting to server");
+
+        let connected = async {
+            let backend = self.options.open(&address, secret).await?;
+            let state = backend.get_state().await?;
+            anyhow::Ok((backend, state))
+        }
+        .await;
+        let (mut backend, state) = match connected {
+            Ok(connected) => connected,
+            Err(e) => {
+                warn!(%address, error = %e, "Failed to connect to server");
+                let _ = self.update_tx.send(AppUpdate::ServerConnectFailed {
+                    address,
+                    error: e.to_string(),
+                });
+                return;
+            }
+        };
+
+        // Leave the backend in use; the local runners clean up as they quit
+        match &self.target {
+            Target::Local(runners) => {
+                let _ = runners.send(AppAction::Quit);
+            }
+            Target::Remote { updates, .. } => updates.abort(),
+        }
+
+        let _ = self.update_tx.send(AppUpdate::ServerConnected {
+            address: address.clone(),
+            project: state.project.clone(),
+            model: state.model.clone(),
+            agent: state.agent.clone(),
+            remote_files: backend.supports(Capability::Files),
+        });
+        for update in wonopcode_tui::state_updates(state) {
+            let _ = self.update_tx.send(update);
+        }
+
+        let updates = self.options.subscribe(&mut backend, self.update_tx.clone());
+        self.target = Target::Remote { backend, updates };
+        info!(%address, "Connected to server");
+    }
+
+    fn browse(&mut self) {
+        #[cfg(feature = "discover")]
+        {
+            self.browsing = Some(discovery::Browsing::start(self.update_tx.clone()));
+        }
+        #[cfg(not(feature = "discover"))]
+        {
+            let _ = self.update_tx.send(AppUpdate::ServerDiscoveryFailed(
+                "this build has no mDNS support".to_string(),
+            ));
+        }
+    }
+
+    fn stop_browsing(&mut self) {
+        #[cfg(feature = "discover")]
+        {
+            self.browsing = None;
+        }
+    }
+}
+
+#[cfg(feature = "discover")]
+mod discovery {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::RecvTimeoutError;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use wonopcode_discover::{Browser, DiscoveryEvent, ServerInfo};
+    use wonopcode_tui::{AppUpdate, DiscoveredServer};
+
+    /// How often the browsing thread checks whether to stop.
+    const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+    /// How long a server's addresses get to answer before it is listed.
+    const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Browsing the local network for servers, until dropped.
+    pub struct Browsing {
+        stop: Arc<AtomicBool>,
+    }
+
+    impl Browsing {
+        /// Browse on a blocking thread, telling the TUI what comes and goes.
+        pub fn start(update_tx: mpsc::UnboundedSender<AppUpdate>) -> Self {
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread_stop = stop.clone();
+            tokio::task::spawn_blocking(move || {
+                let watcher = match Browser::new().and_then(|browser| browser.watch()) {
+                    Ok(watcher) => watcher,
+                    Err(e) => {
+                        let _ = update_tx.send(AppUpdate::ServerDiscoveryFailed(e.to_string()));
+                        return;
+                    }
+                };
+                while !thread_stop.load(Ordering::Relaxed) {
+                    let update = match watcher.next_timeout(POLL_INTERVAL) {
+                        Ok(
+                            DiscoveryEvent::Added(mut server) | DiscoveryEvent::Updated(mut server),
+                        ) => {
+                            // List the server at an address that answers
+                            server.prefer_reachable(PROBE_TIMEOUT);
+                            AppUpdate::ServerFound(discovered(server))
+                        }
+                        Ok(DiscoveryEvent::Removed(name)) => AppUpdate::ServerLost(name),
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    if update_tx.send(update).is_err() {
+                        break;
+                    }
+                }
+            });
+            Self { stop }
+        }
+    }
+
+    impl Drop for Browsing {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn discovered(server: ServerInfo) -> DiscoveredServer {
+        DiscoveredServer {
+            name: server.name,
+            address: server.address.to_string(),
+            project: server.project,
+            model: server.model,
+            auth_required: server.auth_required,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_address() {
+        assert_eq!(normalize_address(":3000"), "127.0.0.1:3000");
+        assert_eq!(normalize_address("box.local:3000"), "box.local:3000");
+        assert_eq!(
+            normalize_address("https://box.local:3000"),
+            "https://box.local:3000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_connect_keeps_local_backend() {
+        let (local_tx, mut local_rx) = mpsc::unbounded_channel();
+        let (update_tx, mut update_rx) = mpsc::unbounded_channel();
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let switch = BackendSwitch::local(local_tx, ConnectOptions::default(), update_tx);
+        let handle = tokio::spawn(switch.run(action_rx));
+
+        action_tx
+            .send(AppAction::SendPrompt("before".to_string()))
+            .unwrap();
+        assert!(matches!(
+            local_rx.recv().await,
+            Some(AppAction::SendPrompt(text)) if text == "before"
+        ));
+
+        // Nothing listens on port 1
+        action_tx
+            .send(AppAction::ConnectServer {
+                address: ":1".to_string(),
+                secret: None,
+            })
+            .unwrap();
+        match update_rx.recv().await {
+            Some(AppUpdate::ServerConnectFailed { address, .. }) => {
+                assert_eq!(address, "127.0.0.1:1");
+            }
+            other => panic!("expected a failed connect, got {other:?}"),
+        }
+
+        action_tx
+            .send(AppAction::SendPrompt("after".to_string()))
+            .unwrap();
+        assert!(matches!(
+            local_rx.recv().await,
+            Some(AppAction::SendPrompt(text)) if text == "after"
+        ));
+
+        drop(action_tx);
+        handle.await.unwrap();
+    }
+
+    #[cfg(not(feature = "discover"))]
+    #[tokio::test]
+    async fn test_browsing_needs_discover_feature() {
+        let (local_tx, _local_rx) = mpsc::unbounded_channel();
+        let (update_tx, mut update_rx) = mpsc::unbounded_channel();
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let switch = BackendSwitch::local(local_tx, ConnectOptions::default(), update_tx);
+        tokio::spawn(switch.run(action_rx));
+
+        action_tx.send(AppAction::BrowseServers).unwrap();
+        assert!(matches!(
+            update_rx.recv().await,
+            Some(AppUpdate::ServerDiscoveryFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+//! Explanations of failed CI runs.
+//!
+//! `wonopcode ci explain` fetches the logs of a GitHub Actions run's failed
+//! jobs, cuts them down to the lines around errors, and asks a read-only
+//! agent for the root cause and a patch, given the diff the run tested. The
+//! analysis is printed, or commented on the run's pull request.
+
+use super::github::{GitHub, WorkflowRun};
+use super::task::{self, truncate, ModelChoice};
+use super::{git, resolve_token, Forge, ForgeKind, Remote, Thread};
+use anyhow::Result;
+use std::path::Path;
+use tracing::{info, warn};
+use wonopcode_core::Instance;
+
+/// Lines kept before and after each error line of a log.
+const CONTEXT_LINES: usize = 5;
+
+/// Lines kept at the end of a log, where the failure usually is.
+const TAIL_LINES: usize = 40;
+
+/// Longest excerpt of one job's log.
+const MAX_EXCERPT_CHARS: usize = 20_000;
+
+/// Longest diff sent to the agent.
+const MAX_DIFF_CHARS: usize = 100_000;
+
+/// Options for `ci explain`.
+pub struct ExplainOptions<'a> {
+    pub model: ModelChoice<'a>,
+    /// Run to explain; the latest failed run of the current branch if unset.
+    pub run: Option<u64>,
+    /// Comment the analysis on the run's pull request instead of printing it.
+    pub comment: bool,
+}
+
+/// Explain why a workflow run of the `origin` repository in `cwd` failed.
+pub async fn explain(cwd: &Path, token: Option<&str>, options: &ExplainOptions<'_>) -> Result<()> {
+    let remote = Remote::from_origin(cwd)?;
+    if remote.kind != ForgeKind::GitHub {
+        anyhow::bail!(
+            "CI explanations need GitHub Actions, but {} is on {}",
+            remote.full_name(),
+            remote.kind.name()
+        );
+    }
+    let token = match token {
+        Some(token) => token.to_string(),
+        None => resolve_token(remote.kind)?,
+    };
+    let github = GitHub::new(remote.clone(), &token)?;
+    let client = github.client();
+    let (owner, repo) = (remote.owner.as_str(), remote.repo.as_str());
+
+    let run = match options.run {
+        Some(id) => client.get_workflow_run(owner, repo, id).await?,
+        None => {
+            let branch = git::current_branch(cwd)?;
+            client
+                .list_workflow_runs(owner, repo, &branch, "failure")
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("No failed workflow runs on {branch}"))?
+        }
+    };
+    info!("Explaining workflow run {}: {}", run.id, run.html_url);
+
+    let failed: Vec<_> = client
+        .list_jobs(owner, repo, run.id)
+        .await?
+        .into_iter()
+        .filter(|job| job.conclusion.as_deref() == Some("failure"))
+        .collect();
+    if failed.is_empty() {
+        anyhow::bail!("Workflow run {} has no failed jobs", run.id);
+    }
+
+    let mut logs = String::new();
+    for job in &failed {
+        let steps: Vec<&str> = job
+            .steps
+            .iter()
+            .filter(|step| step.conclusion.as_deref() == Some("failure"))
+            .map(|step| step.name.as_str())
+            .collect();
+        logs.push_str(&format!("Job \"{}\"", job.name));
+        if !steps.is_empty() {
+            logs.push_str(&format!(", failed at {}", steps.join(", ")));
+        }
+        match client.get_job_log(owner, repo, job.id).await {
+            Ok(log) => logs.push_str(&format!(":\n\n```\n{}\n```\n\n", excerpt(&log))),
+            Err(e) => {
+                // Logs expire, or need more access than the token has
+                warn!("No log for job {}: {e}", job.name);
+                logs.push_str(&format!(" (log unavailable: {e})\n\n"));
+            }
+        }
+    }
+
+    let pull_request = run.pull_requests.first().map(|pr| pr.number);
+    let diff = match pull_request {
+        Some(number) => client.get_pull_request_diff(owner, repo, number).await?,
+        None => client.get_commit_diff(owner, repo, &run.head_sha).await?,
+    };
+
+    let prompt = build_prompt(&remote, &run, &logs, &diff);
+    let instance = Instance::new(cwd).await?;
+    let response = task::prompt_agent(&instance, &prompt, &options.model, Some("plan")).await;
+    instance.dispose().await;
+    let analysis = response?;
+
+    if !options.comment {
+        println!("{}", analysis.trim());
+        return Ok(());
+    }
+    let Some(number) = pull_request else {
+        anyhow::bail!(
+            "Workflow run {} isn't for a pull request; leave out --comment to print the analysis",
+            run.id
+        );
+    };
+    let thread = Thread {
+        number,
+        is_change_request: true,
+    };
+    github
+        .comment(thread, &comment_body(&run, &analysis))
+        .await?;
+    println!("Commented on pull request #{number}");
+    Ok(())
+}
+
+/// The prompt for the agent.
+fn build_prompt(remote: &Remote, run: &WorkflowRun, logs: &str, diff: &str) -> String {
+    let mut prompt = format!(
+        "The workflow run \"{}\" of {} failed",
+        run.name.as_deref().unwrap_or("CI"),
+        remote.full_name()
+    );
+    if let Some(branch) = &run.head_branch {
+        prompt.push_str(&format!(" on {branch}"));
+    }
+    prompt.push_str(&format!(
+        " at {}. Excerpts of the logs of its failed jobs:\n\n{logs}",
+        run.head_sha
+    ));
+
+    let (diff, truncated) = match diff.char_indices().nth(MAX_DIFF_CHARS) {
+        Some((end, _)) => (&diff[..end], true),
+        None => (diff, false),
+    };
+    prompt.push_str(&format!(
+        "The changes the run tested:\n\n```diff\n{diff}\n```\n"
+    ));
+    if truncated {
+        prompt.push_str("\nThe diff is truncated.\n");
+    }
+
+    prompt.push_str(
+        "\nFind the root cause of the failure: whether the changes broke something, and \
+         what, or the failure is unrelated to them, such as a flaky test or a problem of the \
+         CI environment. The working tree may not be at the commit the run tested; read files \
+         for context, but don't change any. Answer with the root cause, the evidence for it \
+         from the logs, and a suggested patch as a unified diff in a ```diff block, if a \
+         change to the code fixes it.",
+    );
+    prompt
+}
+
+/// The pull request comment with the analysis.
+fn comment_body(run: &WorkflowRun, analysis: &str) -> String {
+    format!(
+        "## Why [{}]({}) failed\n\n{}",
+        run.name.as_deref().unwrap_or("CI"),
+        run.html_url,
+        truncate(analysis)
+    )
+}
+
+/// The lines of a job log around errors, and its last lines, with
+/// timestamps removed and skipped lines marked.
+fn excerpt(log: &str) -> String {
+    let lines: Vec<&str> = log.lines().map(strip_timestamp).collect();
+    let mut keep = vec![false; lines.len()];
+    for (i, line) in lines.iter().enumerate() {
+        if is_error_line(line) {
+            let end = (i + CONTEXT_LINES + 1).min(lines.len());
+            keep[i.saturating_sub(CONTEXT_LINES)..end].fill(true);
+        }
+    }
+    let tail = lines.len().saturating_sub(TAIL_LINES);
+    keep[tail..].fill(true);
+
+    let mut excerpt = String::new();
+    let mut skipped = false;
+    for (line, keep) in lines.iter().zip(&keep) {
+        if *keep {
+            if skipped {
+                excerpt.push_str("...\n");
+                skipped = false;
+            }
+            excerpt.push_str(line);
+            excerpt.push('\n');
+        } else {
+            skipped = true;
+        }
+    }
+
+    // Keep the end, which is closest to the failure
+    let count = excerpt.chars().count();
+    if count > MAX_EXCERPT_CHARS {
+        let start = excerpt
+            .char_indices()
+            .nth(count - MAX_EXCERPT_CHARS)
+            .map_or(0, |(i, _)| i);
+        excerpt = format!("...\n{}", &excerpt[start..]);
+    }
+    excerpt.trim_end().to_string()
+}
+
+/// `line` without the timestamp GitHub Actions starts log lines with,
+/// like `2024-05-01T12:00:00.1234567Z`.
+fn strip_timestamp(line: &str) -> &str {
+    match line.split_once(' ') {
+        Some((stamp, rest))
+            if stamp.len() >= 20
+                && stamp.ends_with('Z')
+                && stamp.as_bytes()[4] == b'-'
+                && stamp.as_bytes()[10] == b'T' =>
+        {
+            rest
+        }
+        _ => line,
+    }
+}
+
+/// Whether a log line reports an error.
+fn is_error_line(line: &str) -> bool {
+    const MARKERS: [&str; 8] = [
+        "##[error]",
+        "error:",
+        "error[",
+        "failed",
+        "panicked",
+        "fatal",
+        "exception",
+        "traceback",
+    ];
+    let lower = line.to_lowercase();
+    MARKERS.iter().any(|marker| lower.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_timestamp() {
+        assert_eq!(
+            strip_timestamp("2024-05-01T12:00:00.1234567Z cargo test"),
+            "cargo test"
+        );
+        assert_eq!(strip_timestamp("cargo test"), "cargo test");
+        assert_eq!(strip_timestamp("running 2 tests"), "running 2 tests");
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let mut log: Vec<String> = (0..200).map(|i| format!("line {i}")).collect();
+        log[50] = "2024-05-01T12:00:00.0000000Z error: expected `;`".to_string();
+        let excerpt = excerpt(&log.join("\n"));
+        let lines: Vec<&str> = excerpt.lines().collect();
+
+        assert_eq!(lines[0], "...");
+        assert_eq!(lines[1], "line 45");
+        assert!(lines.contains(&"error: expected `;`"));
+        assert!(lines.contains(&"line 55"));
+        assert!(!lines.contains(&"line 56"));
+        assert_eq!(lines[12], "...");
+        assert_eq!(lines[13], "line 160");
+        assert_eq!(lines.last(), Some(&"line 199"));
+    }
+
+    #[test]
+    fn test_excerpt_keeps_the_end() {
+        let log = "error: x\n".repeat(10_000);
+        let excerpt = excerpt(&log);
+        assert!(excerpt.starts_with("...\n"));
+        assert!(excerpt.chars().count() <= MAX_EXCERPT_CHARS + 4);
+        assert!(excerpt.ends_with("error: x"));
+    }
+}
@@ -0,0 +1,244 @@
+//! PR checkout and git operations for forge integration.
+//!
+//! `checkout_pr` backs `wonopcode pr`. The git operations run in a given
+//! directory, so the agent can work in a worktree or clone of its own: see
+//! [`super::task`].
+
+#![allow(dead_code)]
+
+use super::{ForgeKind, Remote};
+use anyhow::Result;
+use std::path::Path;
+use std::process::Command;
+use tracing::{debug, info};
+
+/// Checkout a PR (or GitLab merge request) by number, on the forge of the
+/// `origin` remote in `dir`.
+pub async fn checkout_pr(dir: &Path, pr_number: u64) -> Result<()> {
+    let kind = Remote::from_origin(dir)?.kind;
+    if kind != ForgeKind::GitHub {
+        // GitLab and Gitea publish the head of each request as a ref
+        let (source, branch) = match kind {
+            ForgeKind::GitLab => (
+                format!("merge-requests/{pr_number}/head"),
+                format!("mr-{pr_number}"),
+            ),
+            _ => (format!("pull/{pr_number}/head"), format!("pr-{pr_number}")),
+        };
+        info!("Checking out {} #{}", kind.change_request_name(), pr_number);
+        git(
+            dir,
+            &["fetch", "--force", "origin", &format!("{source}:{branch}")],
+            "fetch",
+        )?;
+        checkout_branch(dir, &branch)?;
+        info!("Successfully checked out {}", branch);
+        return Ok(());
+    }
+
+    info!("Checking out PR #{}", pr_number);
+
+    // Use gh CLI to checkout the PR
+    let output = Command::new("gh")
+        .args(["pr", "checkout", &pr_number.to_string()])
+        .current_dir(dir)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to checkout PR: {stderr}");
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    debug!("gh pr checkout output: {}", stdout);
+
+    info!("Successfully checked out PR #{}", pr_number);
+    Ok(())
+}
+
+/// Run git in `dir`, returning its trimmed stdout.
+fn git(dir: &Path, args: &[&str], what: &str) -> Result<String> {
+    let output = Command::new("git").args(args).current_dir(dir).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to {what}: {stderr}");
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get the current branch name.
+pub fn current_branch(dir: &Path) -> Result<String> {
+    git(
+        dir,
+        &["rev-parse", "--abbrev-ref", "HEAD"],
+        "get current branch",
+    )
+}
+
+/// Check if a branch exists locally.
+pub fn branch_exists(dir: &Path, branch: &str) -> Result<bool> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", branch])
+        .current_dir(dir)
+        .output()?;
+
+    Ok(output.status.success())
+}
+
+/// Create and checkout a new branch.
+pub fn create_branch(dir: &Path, branch: &str, from: Option<&str>) -> Result<()> {
+    let mut args = vec!["checkout", "-b", branch];
+    if let Some(base) = from {
+        args.push(base);
+    }
+
+    git(dir, &args, "create branch")?;
+    Ok(())
+}
+
+/// Checkout an existing branch.
+pub fn checkout_branch(dir: &Path, branch: &str) -> Result<()> {
+    git(dir, &["checkout", branch], "checkout branch")?;
+    Ok(())
+}
+
+/// Fetch `branch` from origin.
+pub fn fetch(dir: &Path, branch: &str) -> Result<()> {
+    git(dir, &["fetch", "origin", branch], "fetch")?;
+    Ok(())
+}
+
+/// Add a worktree of the repository in `dir` at `path`, on a new `branch`
+/// starting at `base`.
+pub fn add_worktree(dir: &Path, path: &Path, branch: &str, base: &str) -> Result<()> {
+    let path = path.to_string_lossy();
+    git(
+        dir,
+        &["worktree", "add", "-b", branch, &path, base],
+        "add worktree",
+    )?;
+    Ok(())
+}
+
+/// Remove the worktree at `path` of the repository in `dir`.
+pub fn remove_worktree(dir: &Path, path: &Path) -> Result<()> {
+    let path = path.to_string_lossy();
+    git(
+        dir,
+        &["worktree", "remove", "--force", &path],
+        "remove worktree",
+    )?;
+    Ok(())
+}
+
+/// Clone `branch` of `url` into `path`.
+pub fn clone(url: &str, path: &Path, branch: &str) -> Result<()> {
+    let path = path.to_string_lossy();
+    let parent = std::env::temp_dir();
+    git(
+        &parent,
+        &["clone", "--depth", "1", "--branch", branch, url, &path],
+        "clone",
+    )?;
+    Ok(())
+}
+
+/// Stage all changes.
+pub fn stage_all(dir: &Path) -> Result<()> {
+    git(dir, &["add", "-A"], "stage changes")?;
+    Ok(())
+}
+
+/// Create a commit.
+///
+/// Without a git identity, commits are made as the GitHub Actions bot.
+pub fn commit(dir: &Path, message: &str, coauthor: Option<&str>) -> Result<()> {
+    let mut full_message = message.to_string();
+
+    if let Some(author) = coauthor {
+        full_message.push_str(&format!("\n\nCo-authored-by: {author}"));
+    }
+
+    let mut args = Vec::new();
+    if git(dir, &["config", "user.email"], "read git identity").is_err() {
+        args.extend([
+            "-c",
+            "user.name=github-actions[bot]",
+            "-c",
+            "user.email=41898282+github-actions[bot]@users.noreply.github.com",
+        ]);
+    }
+    args.extend(["commit", "-m", &full_message]);
+
+    let output = Command::new("git").args(&args).current_dir(dir).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // Check if there's nothing to commit
+        if stderr.contains("nothing to commit") || stdout.contains("nothing to commit") {
+            return Ok(());
+        }
+        anyhow::bail!("Failed to commit: {stderr}");
+    }
+
+    Ok(())
+}
+
+/// Push `branch` to `remote`, a remote name or URL.
+pub fn push(dir: &Path, remote: &str, branch: &str, force: bool) -> Result<()> {
+    let mut args = vec!["push", remote, branch];
+    if force {
+        args.insert(1, "--force-with-lease");
+    }
+
+    git(dir, &args, "push")?;
+    Ok(())
+}
+
+/// Check if there are uncommitted changes.
+pub fn has_changes(dir: &Path) -> Result<bool> {
+    let status = git(dir, &["status", "--porcelain"], "check git status")?;
+    Ok(!status.is_empty())
+}
+
+/// Summary of the files the last commit changed, as `git show --stat`.
+pub fn last_commit_stat(dir: &Path) -> Result<String> {
+    git(
+        dir,
+        &["show", "--stat", "--format=", "HEAD"],
+        "summarize commit",
+    )
+}
+
+/// Get the default branch (usually main or master).
+pub fn default_branch(dir: &Path) -> Result<String> {
+    // Try to get from remote
+    if let Ok(head) = git(
+        dir,
+        &["symbolic-ref", "refs/remotes/origin/HEAD"],
+        "get default branch",
+    ) {
+        if let Some(branch) = head.strip_prefix("refs/remotes/origin/") {
+            return Ok(branch.to_string());
+        }
+    }
+
+    // Fallback to checking common defaults
+    if branch_exists(dir, "origin/main")? {
+        return Ok("main".to_string());
+    }
+    if branch_exists(dir, "origin/master")? {
+        return Ok("master".to_string());
+    }
+
+    // Last resort
+    Ok("main".to_string())
+}
+
+/// Get the origin remote URL.
+pub fn get_origin_url(dir: &Path) -> Result<String> {
+    git(dir, &["remote", "get-url", "origin"], "get origin URL")
+}
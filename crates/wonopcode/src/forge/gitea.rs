@@ -0,0 +1,346 @@
+//! Gitea (and Forgejo) integration.
+//!
+//! Gitea Actions delivers GitHub-shaped events, handled by
+//! [`super::github::run_agent`]; this is the API those handlers go through.
+
+use super::{Author, ChangeRequest, Forge, Remote, Review, Thread, Verdict};
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use tracing::{debug, error};
+
+/// A repository on a Gitea instance.
+pub struct Gitea {
+    client: Client,
+    remote: Remote,
+    token: String,
+    api_url: String,
+}
+
+impl Gitea {
+    /// Connect to `remote` with an access token.
+    pub fn new(remote: Remote, token: &str) -> Result<Self> {
+        let client = Client::builder().user_agent("wonopcode/1.0").build()?;
+        Ok(Self {
+            client,
+            api_url: format!("{}/api/v1", remote.web_url),
+            remote,
+            token: token.to_string(),
+        })
+    }
+
+    /// URL of `path` under the repository in the API.
+    fn repo_url(&self, path: &str) -> String {
+        format!(
+            "{}/repos/{}/{}{}",
+            self.api_url, self.remote.owner, self.remote.repo, path
+        )
+    }
+
+    /// Send an authenticated request, failing on error statuses.
+    async fn send(&self, request: RequestBuilder, what: &str) -> Result<Response> {
+        let response = request
+            .header("Authorization", format!("token {}", self.token))
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to {}: {} - {}", what, status, body);
+            anyhow::bail!("Failed to {what}: {status}");
+        }
+
+        Ok(response)
+    }
+
+    /// The repository's labels.
+    async fn repo_labels(&self) -> Result<Vec<Label>> {
+        let url = self.repo_url("/labels?limit=50");
+        Ok(self
+            .send(self.client.get(&url), "list labels")
+            .await?
+            .json()
+            .await?)
+    }
+}
+
+/// Gitea pull request.
+#[derive(Debug, Deserialize)]
+struct PullRequest {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    html_url: String,
+    head: Branch,
+}
+
+/// Pull request branch.
+#[derive(Debug, Deserialize)]
+struct Branch {
+    #[serde(rename = "ref")]
+    ref_name: String,
+    sha: String,
+    repo: Option<Repository>,
+}
+
+/// Gitea repository.
+#[derive(Debug, Deserialize)]
+struct Repository {
+    full_name: String,
+}
+
+/// Gitea label. Issues reference labels by id.
+#[derive(Debug, Deserialize)]
+struct Label {
+    id: u64,
+    name: String,
+}
+
+impl PullRequest {
+    fn into_change_request(self, remote: &Remote) -> ChangeRequest {
+        let full_name = remote.full_name();
+        let from_fork = !self
+            .head
+            .repo
+            .as_ref()
+            .is_some_and(|repo| repo.full_name.eq_ignore_ascii_case(&full_name));
+        ChangeRequest {
+            number: self.number,
+            title: self.title,
+            body: self.body,
+            url: self.html_url,
+            head_branch: self.head.ref_name,
+            head_sha: self.head.sha,
+            from_fork,
+        }
+    }
+}
+
+#[async_trait]
+impl Forge for Gitea {
+    fn remote(&self) -> &Remote {
+        &self.remote
+    }
+
+    fn authenticated_url(&self) -> String {
+        let (scheme, host) = self
+            .remote
+            .web_url
+            .split_once("://")
+            .unwrap_or(("https", &self.remote.web_url));
+        format!(
+            "{scheme}://{}@{host}/{}.git",
+            self.token,
+            self.remote.full_name()
+        )
+    }
+
+    fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn coauthor(&self, author: &Author) -> String {
+        // Gitea's default no-reply domain; instances may configure another
+        let domain = self.remote.host().split(':').next().unwrap_or_default();
+        format!("{} <{}@noreply.{domain}>", author.login, author.login)
+    }
+
+    async fn can_write(&self, author: &Author) -> Result<bool> {
+        let url = self.repo_url(&format!("/collaborators/{}/permission", author.login));
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("token {}", self.token))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND || status == StatusCode::FORBIDDEN {
+            return Ok(false);
+        }
+        if !status.is_success() {
+            anyhow::bail!("Failed to get permission level: {status}");
+        }
+
+        #[derive(Deserialize)]
+        struct PermissionResponse {
+            permission: String,
+        }
+
+        let resp: PermissionResponse = response.json().await?;
+        Ok(matches!(
+            resp.permission.as_str(),
+            "owner" | "admin" | "write"
+        ))
+    }
+
+    async fn react(&self, _thread: Thread, comment_id: u64, reaction: &str) -> Result<()> {
+        let url = self.repo_url(&format!("/issues/comments/{comment_id}/reactions"));
+        self.send(
+            self.client
+                .post(&url)
+                .json(&serde_json::json!({ "content": reaction })),
+            "add reaction",
+        )
+        .await?;
+        debug!("Added {} reaction to comment {}", reaction, comment_id);
+        Ok(())
+    }
+
+    async fn comment(&self, thread: Thread, body: &str) -> Result<()> {
+        let url = self.repo_url(&format!("/issues/{}/comments", thread.number));
+        self.send(
+            self.client
+                .post(&url)
+                .json(&serde_json::json!({ "body": body })),
+            "create comment",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn change_request(&self, number: u64) -> Result<ChangeRequest> {
+        let url = self.repo_url(&format!("/pulls/{number}"));
+        let pull: PullRequest = self
+            .send(self.client.get(&url), "get pull request")
+            .await?
+            .json()
+            .await?;
+        Ok(pull.into_change_request(&self.remote))
+    }
+
+    async fn change_request_diff(&self, number: u64) -> Result<String> {
+        let url = self.repo_url(&format!("/pulls/{number}.diff"));
+        Ok(self
+            .send(self.client.get(&url), "get pull request diff")
+            .await?
+            .text()
+            .await?)
+    }
+
+    async fn open_change_request(
+        &self,
+        title: &str,
+        body: &str,
+        head: &str,
+        base: &str,
+    ) -> Result<ChangeRequest> {
+        let url = self.repo_url("/pulls");
+        let pull: PullRequest = self
+            .send(
+                self.client.post(&url).json(&serde_json::json!({
+                    "title": title,
+                    "body": body,
+                    "head": head,
+                    "base": base
+                })),
+                "create pull request",
+            )
+            .await?
+            .json()
+            .await?;
+        debug!("Created pull request: #{}", pull.number);
+        Ok(pull.into_change_request(&self.remote))
+    }
+
+    async fn post_review(&self, number: u64, review: &Review) -> Result<()> {
+        let url = self.repo_url(&format!("/pulls/{number}/reviews"));
+        let comments: Vec<_> = review
+            .comments
+            .iter()
+            .map(|comment| {
+                serde_json::json!({
+                    "path": comment.path,
+                    "new_position": comment.line,
+                    "body": comment.body
+                })
+            })
+            .collect();
+        let event = match review.verdict {
+            Verdict::Approve => "APPROVED",
+            Verdict::Comment => "COMMENT",
+            Verdict::RequestChanges => "REQUEST_CHANGES",
+        };
+        self.send(
+            self.client.post(&url).json(&serde_json::json!({
+                "event": event,
+                "body": review.body,
+                "commit_id": review.commit_id,
+                "comments": comments
+            })),
+            "create review",
+        )
+        .await?;
+        debug!("Created review on pull request #{}", number);
+        Ok(())
+    }
+
+    async fn labels(&self) -> Result<Vec<String>> {
+        let labels = self.repo_labels().await?;
+        Ok(labels.into_iter().map(|label| label.name).collect())
+    }
+
+    async fn add_labels(&self, number: u64, labels: &[String]) -> Result<()> {
+        let ids: Vec<u64> = self
+            .repo_labels()
+            .await?
+            .into_iter()
+            .filter(|label| labels.contains(&label.name))
+            .map(|label| label.id)
+            .collect();
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let url = self.repo_url(&format!("/issues/{number}/labels"));
+        self.send(
+            self.client
+                .post(&url)
+                .json(&serde_json::json!({ "labels": ids })),
+            "add labels",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn remove_label(&self, number: u64, label: &str) -> Result<()> {
+        let Some(id) = self
+            .repo_labels()
+            .await?
+            .into_iter()
+            .find(|candidate| candidate.name == label)
+            .map(|label| label.id)
+        else {
+            return Ok(());
+        };
+        let url = self.repo_url(&format!("/issues/{number}/labels/{id}"));
+        self.send(self.client.delete(&url), "remove label").await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls() {
+        let remote = Remote::parse("http://gitea.local:3000/me/app.git").unwrap();
+        let gitea = Gitea::new(remote, "secret").unwrap();
+        assert_eq!(
+            gitea.repo_url("/pulls/3"),
+            "http://gitea.local:3000/api/v1/repos/me/app/pulls/3"
+        );
+        assert_eq!(
+            gitea.authenticated_url(),
+            "http://secret@gitea.local:3000/me/app.git"
+        );
+        let author = Author {
+            login: "me".to_string(),
+            id: 7,
+        };
+        assert_eq!(gitea.coauthor(&author), "me <me@noreply.gitea.local>");
+    }
+}
@@ -0,0 +1,772 @@
+//! GitHub API client.
+//!
+//! The `/wonopcode` comment flow uses it to check permissions, react, open
+//! pull requests and reply, reviews post through it, and `ci explain` reads
+//! workflow runs and their logs with it. The other methods
+//! are for the events that are still only logged, such as review comments.
+
+#![allow(dead_code)]
+
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error};
+
+/// GitHub API client.
+pub struct GitHubClient {
+    client: Client,
+    token: String,
+    base_url: String,
+}
+
+impl GitHubClient {
+    /// Create a new GitHub client.
+    pub fn new(token: &str) -> Result<Self> {
+        let client = Client::builder().user_agent("wonopcode/1.0").build()?;
+
+        Ok(Self {
+            client,
+            token: token.to_string(),
+            base_url: "https://api.github.com".to_string(),
+        })
+    }
+
+    /// Create a new client with a custom base URL.
+    pub fn with_base_url(token: &str, base_url: &str) -> Result<Self> {
+        let mut client = Self::new(token)?;
+        client.base_url = base_url.to_string();
+        Ok(client)
+    }
+
+    /// Add a reaction to a comment.
+    pub async fn add_reaction(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        reaction: &str,
+    ) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments/{}/reactions",
+            self.base_url, owner, repo, comment_id
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(&serde_json::json!({ "content": reaction }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to add reaction: {} - {}", status, body);
+            anyhow::bail!("Failed to add reaction: {status}");
+        }
+
+        debug!("Added {} reaction to comment {}", reaction, comment_id);
+        Ok(())
+    }
+
+    /// Remove a reaction from a comment.
+    pub async fn remove_reaction(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        reaction_id: u64,
+    ) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments/{}/reactions/{}",
+            self.base_url, owner, repo, comment_id, reaction_id
+        );
+
+        let response = self
+            .client
+            .delete(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to remove reaction: {} - {}", status, body);
+            anyhow::bail!("Failed to remove reaction: {status}");
+        }
+
+        Ok(())
+    }
+
+    /// Create a comment on an issue or PR.
+    pub async fn create_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        body: &str,
+    ) -> Result<Comment> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/comments",
+            self.base_url, owner, repo, issue_number
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(&serde_json::json!({ "body": body }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to create comment: {} - {}", status, body);
+            anyhow::bail!("Failed to create comment: {status}");
+        }
+
+        let comment: Comment = response.json().await?;
+        debug!("Created comment: {}", comment.id);
+        Ok(comment)
+    }
+
+    /// Update a comment.
+    pub async fn update_comment(
+        &self,
+        owner: &str,
+        repo: &str,
+        comment_id: u64,
+        body: &str,
+    ) -> Result<Comment> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/comments/{}",
+            self.base_url, owner, repo, comment_id
+        );
+
+        let response = self
+            .client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(&serde_json::json!({ "body": body }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to update comment: {} - {}", status, body);
+            anyhow::bail!("Failed to update comment: {status}");
+        }
+
+        let comment: Comment = response.json().await?;
+        Ok(comment)
+    }
+
+    /// Get a pull request.
+    pub async fn get_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<PullRequest> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls/{}",
+            self.base_url, owner, repo, number
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to get pull request: {} - {}", status, body);
+            anyhow::bail!("Failed to get pull request: {status}");
+        }
+
+        let pr: PullRequest = response.json().await?;
+        Ok(pr)
+    }
+
+    /// Get the diff of a pull request.
+    pub async fn get_pull_request_diff(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+    ) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls/{}",
+            self.base_url, owner, repo, number
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github.diff")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to get pull request diff: {} - {}", status, body);
+            anyhow::bail!("Failed to get pull request diff: {status}");
+        }
+
+        Ok(response.text().await?)
+    }
+
+    /// Create a review on a pull request, with inline comments.
+    pub async fn create_review(
+        &self,
+        owner: &str,
+        repo: &str,
+        number: u64,
+        review: &NewReview,
+    ) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/pulls/{}/reviews",
+            self.base_url, owner, repo, number
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(review)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to create review: {} - {}", status, body);
+            anyhow::bail!("Failed to create review: {status}");
+        }
+
+        debug!("Created review on pull request #{}", number);
+        Ok(())
+    }
+
+    /// Create a pull request.
+    pub async fn create_pull_request(
+        &self,
+        owner: &str,
+        repo: &str,
+        title: &str,
+        body: &str,
+        head: &str,
+        base: &str,
+    ) -> Result<PullRequest> {
+        let url = format!("{}/repos/{}/{}/pulls", self.base_url, owner, repo);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(&serde_json::json!({
+                "title": title,
+                "body": body,
+                "head": head,
+                "base": base
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to create pull request: {} - {}", status, body);
+            anyhow::bail!("Failed to create pull request: {status}");
+        }
+
+        let pr: PullRequest = response.json().await?;
+        debug!("Created pull request: #{}", pr.number);
+        Ok(pr)
+    }
+
+    /// Get an issue.
+    pub async fn get_issue(&self, owner: &str, repo: &str, number: u64) -> Result<Issue> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}",
+            self.base_url, owner, repo, number
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to get issue: {} - {}", status, body);
+            anyhow::bail!("Failed to get issue: {status}");
+        }
+
+        let issue: Issue = response.json().await?;
+        Ok(issue)
+    }
+
+    /// List a repository's labels.
+    pub async fn list_labels(&self, owner: &str, repo: &str) -> Result<Vec<Label>> {
+        let url = format!(
+            "{}/repos/{}/{}/labels?per_page=100",
+            self.base_url, owner, repo
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to list labels: {} - {}", status, body);
+            anyhow::bail!("Failed to list labels: {status}");
+        }
+
+        let labels: Vec<Label> = response.json().await?;
+        Ok(labels)
+    }
+
+    /// Add labels to an issue or PR.
+    pub async fn add_labels(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        labels: &[String],
+    ) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/labels",
+            self.base_url, owner, repo, issue_number
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(&serde_json::json!({ "labels": labels }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to add labels: {} - {}", status, body);
+            anyhow::bail!("Failed to add labels: {status}");
+        }
+
+        Ok(())
+    }
+
+    /// Remove a label from an issue or PR. `label` is percent-encoded.
+    pub async fn remove_label(
+        &self,
+        owner: &str,
+        repo: &str,
+        issue_number: u64,
+        label: &str,
+    ) -> Result<()> {
+        let url = format!(
+            "{}/repos/{}/{}/issues/{}/labels/{}",
+            self.base_url, owner, repo, issue_number, label
+        );
+
+        let response = self
+            .client
+            .delete(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        // Already gone is fine
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to remove label: {} - {}", status, body);
+            anyhow::bail!("Failed to remove label: {status}");
+        }
+
+        Ok(())
+    }
+
+    /// Get the diff a commit makes.
+    pub async fn get_commit_diff(&self, owner: &str, repo: &str, sha: &str) -> Result<String> {
+        let url = format!("{}/repos/{}/{}/commits/{}", self.base_url, owner, repo, sha);
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github.diff")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to get commit diff: {} - {}", status, body);
+            anyhow::bail!("Failed to get commit diff: {status}");
+        }
+
+        Ok(response.text().await?)
+    }
+
+    /// Get a workflow run.
+    pub async fn get_workflow_run(
+        &self,
+        owner: &str,
+        repo: &str,
+        run_id: u64,
+    ) -> Result<WorkflowRun> {
+        let url = format!(
+            "{}/repos/{}/{}/actions/runs/{}",
+            self.base_url, owner, repo, run_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to get workflow run: {} - {}", status, body);
+            anyhow::bail!("Failed to get workflow run: {status}");
+        }
+
+        let run: WorkflowRun = response.json().await?;
+        Ok(run)
+    }
+
+    /// List the latest workflow runs of a branch with a status such as
+    /// `failure`, newest first.
+    pub async fn list_workflow_runs(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+        status: &str,
+    ) -> Result<Vec<WorkflowRun>> {
+        let url = format!("{}/repos/{}/{}/actions/runs", self.base_url, owner, repo);
+
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("branch", branch), ("status", status), ("per_page", "10")])
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to list workflow runs: {} - {}", status, body);
+            anyhow::bail!("Failed to list workflow runs: {status}");
+        }
+
+        #[derive(Deserialize)]
+        struct WorkflowRuns {
+            workflow_runs: Vec<WorkflowRun>,
+        }
+
+        let runs: WorkflowRuns = response.json().await?;
+        Ok(runs.workflow_runs)
+    }
+
+    /// List the jobs of a workflow run's latest attempt.
+    pub async fn list_jobs(&self, owner: &str, repo: &str, run_id: u64) -> Result<Vec<Job>> {
+        let url = format!(
+            "{}/repos/{}/{}/actions/runs/{}/jobs?filter=latest&per_page=100",
+            self.base_url, owner, repo, run_id
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to list jobs: {} - {}", status, body);
+            anyhow::bail!("Failed to list jobs: {status}");
+        }
+
+        #[derive(Deserialize)]
+        struct Jobs {
+            jobs: Vec<Job>,
+        }
+
+        let jobs: Jobs = response.json().await?;
+        Ok(jobs.jobs)
+    }
+
+    /// Get the log of a job, as plain text.
+    pub async fn get_job_log(&self, owner: &str, repo: &str, job_id: u64) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/actions/jobs/{}/logs",
+            self.base_url, owner, repo, job_id
+        );
+
+        // Redirects to a signed URL; reqwest drops the token on the way
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to get job log: {} - {}", status, body);
+            anyhow::bail!("Failed to get job log: {status}");
+        }
+
+        Ok(response.text().await?)
+    }
+
+    /// Check user permission level.
+    pub async fn get_permission_level(
+        &self,
+        owner: &str,
+        repo: &str,
+        username: &str,
+    ) -> Result<String> {
+        let url = format!(
+            "{}/repos/{}/{}/collaborators/{}/permission",
+            self.base_url, owner, repo, username
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.token))
+            .header("Accept", "application/vnd.github+json")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            if status == reqwest::StatusCode::NOT_FOUND {
+                return Ok("none".to_string());
+            }
+            let body = response.text().await.unwrap_or_default();
+            error!("Failed to get permission level: {} - {}", status, body);
+            anyhow::bail!("Failed to get permission level: {status}");
+        }
+
+        #[derive(Deserialize)]
+        struct PermissionResponse {
+            permission: String,
+        }
+
+        let resp: PermissionResponse = response.json().await?;
+        Ok(resp.permission)
+    }
+}
+
+/// GitHub comment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: u64,
+    pub body: String,
+    pub user: User,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// GitHub user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub login: String,
+    pub id: u64,
+}
+
+/// GitHub pull request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub user: User,
+    pub head: PullRequestRef,
+    pub base: PullRequestRef,
+    pub html_url: String,
+    pub additions: Option<u64>,
+    pub deletions: Option<u64>,
+    pub changed_files: Option<u64>,
+}
+
+/// A pull request review to create.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewReview {
+    /// Commit the review is of; the latest when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_id: Option<String>,
+    pub body: String,
+    /// `APPROVE`, `REQUEST_CHANGES` or `COMMENT`.
+    pub event: String,
+    pub comments: Vec<NewReviewComment>,
+}
+
+/// An inline comment of a [`NewReview`], on a line of the new file.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewReviewComment {
+    pub path: String,
+    pub line: u64,
+    pub side: String,
+    pub body: String,
+}
+
+/// Pull request branch reference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullRequestRef {
+    pub label: String,
+    #[serde(rename = "ref")]
+    pub ref_name: String,
+    pub sha: String,
+    pub repo: Option<Repository>,
+}
+
+/// GitHub repository.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Repository {
+    pub id: u64,
+    pub name: String,
+    pub full_name: String,
+    pub clone_url: String,
+    pub ssh_url: String,
+}
+
+/// GitHub issue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub user: User,
+    pub labels: Vec<Label>,
+}
+
+/// GitHub label.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Label {
+    pub name: String,
+    pub color: String,
+}
+
+/// GitHub Actions workflow run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: u64,
+    pub name: Option<String>,
+    pub head_branch: Option<String>,
+    pub head_sha: String,
+    pub status: Option<String>,
+    pub conclusion: Option<String>,
+    pub html_url: String,
+    /// Pull requests of the run's branch; empty for runs from forks.
+    #[serde(default)]
+    pub pull_requests: Vec<RunPullRequest>,
+}
+
+/// Pull request a workflow run is for.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunPullRequest {
+    pub number: u64,
+}
+
+/// Job of a workflow run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub name: String,
+    pub conclusion: Option<String>,
+    pub html_url: Option<String>,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+/// Step of a job.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Step {
+    pub name: String,
+    pub number: u64,
+    pub conclusion: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_creation() {
+        let client = GitHubClient::new("test-token").unwrap();
+        assert_eq!(client.base_url, "https://api.github.com");
+    }
+
+    #[test]
+    fn test_client_with_base_url() {
+        let client =
+            GitHubClient::with_base_url("test-token", "https://github.example.com/api/v3").unwrap();
+        assert_eq!(client.base_url, "https://github.example.com/api/v3");
+    }
+}
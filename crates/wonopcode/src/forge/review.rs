@@ -0,0 +1,432 @@
+//! Automated pull request reviews.
+//!
+//! A read-only agent reviews the diff and answers with findings as JSON.
+//! Findings below the minimum severity are dropped. Those on lines of the
+//! diff become inline comments, and the rest are listed in the summary,
+//! since forges reject comments on lines outside the diff.
+
+use super::{
+    connect, resolve_token, task, Forge, ModelChoice, Remote, Review, ReviewComment, Verdict,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tracing::{info, warn};
+use wonopcode_core::config::{ReviewConfig, ReviewSeverity};
+use wonopcode_core::Instance;
+
+/// Longest diff sent to the agent.
+const MAX_DIFF_CHARS: usize = 200_000;
+
+/// Options for a review, over the `review` config.
+pub struct ReviewOptions<'a> {
+    pub model: ModelChoice<'a>,
+    pub min_severity: Option<ReviewSeverity>,
+    pub request_changes_at: Option<ReviewSeverity>,
+    /// Print the review instead of posting it.
+    pub dry_run: bool,
+}
+
+/// Parse a severity name, for `--min-severity` and `--request-changes-at`.
+pub fn parse_severity(s: &str) -> Result<ReviewSeverity, String> {
+    match s.to_lowercase().as_str() {
+        "info" => Ok(ReviewSeverity::Info),
+        "warning" => Ok(ReviewSeverity::Warning),
+        "error" => Ok(ReviewSeverity::Error),
+        _ => Err(format!(
+            "unknown severity '{s}', expected info, warning or error"
+        )),
+    }
+}
+
+fn severity_name(severity: ReviewSeverity) -> &'static str {
+    match severity {
+        ReviewSeverity::Info => "info",
+        ReviewSeverity::Warning => "warning",
+        ReviewSeverity::Error => "error",
+    }
+}
+
+/// Which findings are posted, and which request changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Thresholds {
+    min_severity: ReviewSeverity,
+    request_changes_at: ReviewSeverity,
+}
+
+impl Thresholds {
+    fn new(config: Option<&ReviewConfig>, options: &ReviewOptions<'_>) -> Self {
+        Self {
+            min_severity: options
+                .min_severity
+                .or(config.and_then(|c| c.min_severity))
+                .unwrap_or(ReviewSeverity::Warning),
+            request_changes_at: options
+                .request_changes_at
+                .or(config.and_then(|c| c.request_changes_at))
+                .unwrap_or(ReviewSeverity::Error),
+        }
+    }
+}
+
+/// The review as the agent answers it.
+#[derive(Debug, Deserialize)]
+struct AgentReview {
+    summary: String,
+    #[serde(default)]
+    findings: Vec<Finding>,
+}
+
+/// One problem the agent found.
+#[derive(Debug, Deserialize)]
+struct Finding {
+    path: String,
+    /// Line in the new version of the file.
+    line: Option<u64>,
+    severity: ReviewSeverity,
+    message: String,
+}
+
+/// Review pull request `number` from the command line, on the forge of the
+/// `origin` remote in `cwd`.
+pub async fn review_pr(
+    cwd: &Path,
+    number: u64,
+    token: Option<&str>,
+    options: &ReviewOptions<'_>,
+) -> Result<()> {
+    let remote = Remote::from_origin(cwd)?;
+    let token = match token {
+        Some(token) => token.to_string(),
+        None => resolve_token(remote.kind)?,
+    };
+    let forge = connect(&remote, &token)?;
+
+    run(forge.as_ref(), cwd, number, options).await
+}
+
+/// Review pull request `number` and post the review.
+pub async fn run(
+    forge: &dyn Forge,
+    cwd: &Path,
+    number: u64,
+    options: &ReviewOptions<'_>,
+) -> Result<()> {
+    let remote = forge.remote();
+    let kind = remote.kind.change_request_name();
+    let change = forge.change_request(number).await?;
+    let diff = forge.change_request_diff(number).await?;
+    info!("Reviewing {} #{}: {}", kind, number, change.title);
+
+    let instance = Instance::new(cwd).await?;
+    let config = instance.config().await;
+    let thresholds = Thresholds::new(config.review.as_ref(), options);
+    let agent = config
+        .review
+        .as_ref()
+        .and_then(|c| c.agent.clone())
+        .unwrap_or_else(|| "plan".to_string());
+    let prompt = build_prompt(remote, number, &change.title, change.body.as_deref(), &diff);
+    let response = task::prompt_agent(&instance, &prompt, &options.model, Some(&agent)).await;
+    instance.dispose().await;
+
+    let agent_review = parse_agent_review(&response?)?;
+    let mut review = build_review(agent_review, &diff, thresholds);
+    review.commit_id = Some(change.head_sha);
+
+    if options.dry_run {
+        println!("{} ({:?})", review.body, review.verdict);
+        for comment in &review.comments {
+            println!("\n{}:{}\n{}", comment.path, comment.line, comment.body);
+        }
+        return Ok(());
+    }
+
+    if let Err(e) = forge.post_review(number, &review).await {
+        if review.verdict == Verdict::Comment {
+            return Err(e);
+        }
+        // Approving or requesting changes isn't allowed for the author of
+        // the pull request, nor for GitHub Actions unless enabled.
+        warn!(
+            "Posting the review as {:?} failed, commenting instead: {e}",
+            review.verdict
+        );
+        review.verdict = Verdict::Comment;
+        forge.post_review(number, &review).await?;
+    }
+    info!("Posted review on {} #{}", kind, number);
+
+    Ok(())
+}
+
+/// The prompt for the review agent.
+fn build_prompt(
+    remote: &Remote,
+    number: u64,
+    title: &str,
+    body: Option<&str>,
+    diff: &str,
+) -> String {
+    let mut prompt = format!(
+        "Review {} #{number} of {}: {title}\n",
+        remote.kind.change_request_name(),
+        remote.full_name()
+    );
+    if let Some(body) = body.filter(|body| !body.trim().is_empty()) {
+        prompt.push_str(&format!("\nThe description:\n\n{}\n", body.trim()));
+    }
+    prompt.push_str(
+        "\nReview the diff below for bugs, security problems, missing tests and unclear code. \
+         The working tree may not be at the head of the changes, so go by the diff and read \
+         files only for context. Don't change any files.\n\n\
+         End your answer with the review as a JSON block:\n\n\
+         ```json\n\
+         {\"summary\": \"...\", \"findings\": [{\"path\": \"src/lib.rs\", \"line\": 12, \
+         \"severity\": \"error\", \"message\": \"...\"}]}\n\
+         ```\n\n\
+         `severity` is \"error\" for bugs and security problems, \"warning\" for things that \
+         should change and \"info\" for suggestions. `line` is the line in the new version of \
+         the file.\n\n",
+    );
+
+    let (diff, truncated) = match diff.char_indices().nth(MAX_DIFF_CHARS) {
+        Some((end, _)) => (&diff[..end], true),
+        None => (diff, false),
+    };
+    prompt.push_str(&format!("```diff\n{diff}\n```\n"));
+    if truncated {
+        prompt.push_str("\nThe diff is truncated; review what is shown.\n");
+    }
+    prompt
+}
+
+/// The JSON an agent ended its answer with: the last JSON block, or the
+/// outermost braces when there is none.
+pub(super) fn last_json(text: &str) -> Option<&str> {
+    let json = match text.rfind("```json") {
+        Some(start) => {
+            let block = &text[start + "```json".len()..];
+            block.split("```").next().unwrap_or(block)
+        }
+        None => match (text.find('{'), text.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &text[start..=end],
+            _ => return None,
+        },
+    };
+    Some(json.trim())
+}
+
+/// Parse the review from the agent's answer.
+fn parse_agent_review(text: &str) -> Result<AgentReview> {
+    let json =
+        last_json(text).ok_or_else(|| anyhow::anyhow!("The review agent gave no JSON review"))?;
+    serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("The review agent gave an invalid review: {e}"))
+}
+
+/// Lines of each file's new version that are in the diff, and so can be
+/// commented on.
+fn commentable_lines(diff: &str) -> HashMap<String, HashSet<u64>> {
+    let mut lines: HashMap<String, HashSet<u64>> = HashMap::new();
+    let mut path: Option<String> = None;
+    let mut line = 0;
+
+    for text in diff.lines() {
+        if let Some(new_path) = text.strip_prefix("+++ ") {
+            path = new_path.strip_prefix("b/").map(str::to_string);
+        } else if text.starts_with("--- ") || text.starts_with("diff --git") {
+            continue;
+        } else if let Some(hunk) = text.strip_prefix("@@ ") {
+            // @@ -old,count +new,count @@
+            line = hunk
+                .split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(0);
+        } else if let Some(path) = &path {
+            if text.starts_with('+') || text.starts_with(' ') || text.is_empty() {
+                lines.entry(path.clone()).or_default().insert(line);
+                line += 1;
+            }
+        }
+    }
+
+    lines
+}
+
+/// Turn the agent's review into a review to post.
+fn build_review(agent_review: AgentReview, diff: &str, thresholds: Thresholds) -> Review {
+    let findings: Vec<Finding> = agent_review
+        .findings
+        .into_iter()
+        .filter(|f| f.severity >= thresholds.min_severity)
+        .collect();
+
+    let verdict = if findings
+        .iter()
+        .any(|f| f.severity >= thresholds.request_changes_at)
+    {
+        Verdict::RequestChanges
+    } else if findings.is_empty() {
+        Verdict::Approve
+    } else {
+        Verdict::Comment
+    };
+
+    let commentable = commentable_lines(diff);
+    let mut comments = Vec::new();
+    let mut elsewhere = Vec::new();
+    for finding in findings {
+        let in_diff = finding.line.filter(|line| {
+            commentable
+                .get(&finding.path)
+                .is_some_and(|lines| lines.contains(line))
+        });
+        match in_diff {
+            Some(line) => comments.push(ReviewComment {
+                path: finding.path,
+                line,
+                body: format!(
+                    "**{}**: {}",
+                    severity_name(finding.severity),
+                    finding.message
+                ),
+            }),
+            None => elsewhere.push(finding),
+        }
+    }
+
+    let mut body = format!("**{}**\n\n{}", verdict.label(), agent_review.summary.trim());
+    if !elsewhere.is_empty() {
+        body.push_str("\n\nOutside the diff:\n");
+        for finding in elsewhere {
+            let location = match finding.line {
+                Some(line) => format!("{}:{line}", finding.path),
+                None => finding.path,
+            };
+            body.push_str(&format!(
+                "\n- **{}** `{location}`: {}",
+                severity_name(finding.severity),
+                finding.message
+            ));
+        }
+    }
+
+    Review {
+        verdict,
+        body,
+        comments,
+        commit_id: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,3 +10,4 @@ fn main() {
+ let a = 1;
+-let b = 2;
++let b = 3;
++let c = 4;
+ let d = 5;
+diff --git a/old.rs b/old.rs
+deleted file mode 100644
+--- a/old.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-gone
+";
+
+    fn thresholds(min: ReviewSeverity, request_changes_at: ReviewSeverity) -> Thresholds {
+        Thresholds {
+            min_severity: min,
+            request_changes_at,
+        }
+    }
+
+    fn finding(line: Option<u64>, severity: ReviewSeverity) -> Finding {
+        Finding {
+            path: "src/lib.rs".to_string(),
+            line,
+            severity,
+            message: "problem".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_severity() {
+        assert_eq!(parse_severity("Warning"), Ok(ReviewSeverity::Warning));
+        assert!(parse_severity("fatal").is_err());
+        assert!(ReviewSeverity::Error > ReviewSeverity::Warning);
+    }
+
+    #[test]
+    fn test_commentable_lines() {
+        let lines = commentable_lines(DIFF);
+        let lib: HashSet<u64> = [10, 11, 12, 13].into_iter().collect();
+        assert_eq!(lines.get("src/lib.rs"), Some(&lib));
+        assert!(!lines.contains_key("old.rs"));
+    }
+
+    #[test]
+    fn test_parse_agent_review() {
+        let text = "Looked at it.\n\n```json\n{\"summary\": \"Fine\", \"findings\": \
+                    [{\"path\": \"src/lib.rs\", \"line\": 11, \"severity\": \"warning\", \
+                    \"message\": \"b changed\"}]}\n```\n";
+        let review = parse_agent_review(text).unwrap();
+        assert_eq!(review.summary, "Fine");
+        assert_eq!(review.findings[0].severity, ReviewSeverity::Warning);
+
+        let review = parse_agent_review("Done: {\"summary\": \"Fine\"}").unwrap();
+        assert!(review.findings.is_empty());
+
+        assert!(parse_agent_review("No review").is_err());
+    }
+
+    #[test]
+    fn test_build_review() {
+        let default = thresholds(ReviewSeverity::Warning, ReviewSeverity::Error);
+
+        let review = AgentReview {
+            summary: "Fine".to_string(),
+            findings: vec![finding(Some(11), ReviewSeverity::Info)],
+        };
+        let review = build_review(review, DIFF, default);
+        assert_eq!(review.verdict, Verdict::Approve);
+        assert!(review.comments.is_empty());
+
+        let review = AgentReview {
+            summary: "Some issues".to_string(),
+            findings: vec![
+                finding(Some(11), ReviewSeverity::Warning),
+                finding(Some(40), ReviewSeverity::Warning),
+            ],
+        };
+        let review = build_review(review, DIFF, default);
+        assert_eq!(review.verdict, Verdict::Comment);
+        assert_eq!(review.comments.len(), 1);
+        assert_eq!(review.comments[0].line, 11);
+        assert_eq!(review.comments[0].body, "**warning**: problem");
+        assert!(review
+            .body
+            .ends_with("Outside the diff:\n\n- **warning** `src/lib.rs:40`: problem"));
+
+        let review = AgentReview {
+            summary: "Broken".to_string(),
+            findings: vec![finding(Some(12), ReviewSeverity::Warning)],
+        };
+        let strict = thresholds(ReviewSeverity::Info, ReviewSeverity::Warning);
+        let review = build_review(review, DIFF, strict);
+        assert_eq!(review.verdict, Verdict::RequestChanges);
+        assert!(review.body.starts_with("**Changes requested**\n\nBroken"));
+    }
+}
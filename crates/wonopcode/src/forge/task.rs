@@ -0,0 +1,469 @@
+//! The agent flow behind `/wonopcode` comments.
+//!
+//! The agent works in a worktree of its own (or a clone, when the CI job
+//! didn't check the repository out), on a new branch. Its changes are
+//! committed and pushed, a pull request is opened, and the comment gets a
+//! reply with the link and what changed.
+
+use super::{git, Author, Forge, ForgeKind, Remote, Thread};
+use crate::commands::{get_default_model, parse_model_spec};
+use crate::unattended::Unattended;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use wonopcode_core::Instance;
+use wonopcode_tui::AppAction;
+
+/// Longest summary quoted in a pull request or reply; GitHub rejects bodies
+/// over 65536 characters.
+const MAX_SUMMARY_CHARS: usize = 30_000;
+
+/// Phrases that ask wonopcode for something in a comment.
+const TRIGGERS: [&str; 2] = ["/wonopcode", "/wc"];
+
+/// A `/wonopcode` request in an issue or pull request comment.
+pub struct Request {
+    pub thread: Thread,
+    pub issue_title: String,
+    pub issue_body: String,
+    pub comment_id: u64,
+    pub author: Author,
+    pub prompt: String,
+    pub default_branch: String,
+}
+
+/// Which model the agent runs with.
+#[derive(Debug, Clone, Copy)]
+pub struct ModelChoice<'a> {
+    pub default_provider: &'a str,
+    /// `--model`, over the configured model.
+    pub model: Option<&'a str>,
+}
+
+/// Check if a comment contains a trigger phrase.
+pub fn is_trigger_comment(body: &str) -> bool {
+    let lower = body.to_lowercase();
+    TRIGGERS.iter().any(|t| lower.contains(t))
+}
+
+/// Extract the prompt from a trigger comment.
+pub fn extract_prompt(body: &str) -> String {
+    let mut text = body.to_string();
+
+    for trigger in TRIGGERS {
+        if let Some(pos) = text.to_lowercase().find(trigger) {
+            text = text[pos + trigger.len()..].to_string();
+            break;
+        }
+    }
+
+    text.trim().to_string()
+}
+
+/// Handle `request`, replying to the comment with the outcome.
+pub async fn handle(
+    forge: &dyn Forge,
+    cwd: &Path,
+    request: &Request,
+    model: &ModelChoice<'_>,
+) -> Result<()> {
+    if !forge.can_write(&request.author).await? {
+        info!(
+            "Ignoring request from {}, who can't push",
+            request.author.login
+        );
+        forge
+            .comment(
+                request.thread,
+                &format!(
+                    "@{} only members with write access can ask wonopcode for changes.",
+                    request.author.login
+                ),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    forge
+        .react(request.thread, request.comment_id, "eyes")
+        .await?;
+
+    let reply = match run(forge, cwd, request, model).await {
+        Ok(reply) => reply,
+        Err(e) => {
+            // Keep the token, which git errors may show in URLs, out of the reply
+            let error = format!("{e:#}").replace(forge.token(), "***");
+            warn!("Request failed: {error}");
+            failure_reply(&error)
+        }
+    };
+    forge.comment(request.thread, &reply).await?;
+
+    Ok(())
+}
+
+/// Run the agent on `request` and open a pull request with its changes,
+/// returning the reply to the comment.
+async fn run(
+    forge: &dyn Forge,
+    cwd: &Path,
+    request: &Request,
+    model: &ModelChoice<'_>,
+) -> Result<String> {
+    let base = base_branch(forge, request).await?;
+    let branch = branch_name(request.thread.number, request.comment_id);
+    let name = format!("{}-{}", request.thread.number, request.comment_id);
+    let workspace = Workspace::create(forge, cwd, &name, &base, &branch)?;
+    info!(
+        "Working on {} in {}",
+        branch,
+        workspace.dir.to_string_lossy()
+    );
+
+    let prompt = build_prompt(forge.remote(), request);
+    let summary = run_agent(&workspace.dir, &prompt, model, None).await?;
+
+    if !git::has_changes(&workspace.dir)? {
+        return Ok(no_changes_reply(&summary));
+    }
+
+    let title = pr_title(&request.prompt);
+    git::stage_all(&workspace.dir)?;
+    git::commit(
+        &workspace.dir,
+        &title,
+        Some(&forge.coauthor(&request.author)),
+    )?;
+    let stat = git::last_commit_stat(&workspace.dir)?;
+    git::push(&workspace.dir, &forge.authenticated_url(), &branch, false)?;
+
+    let opened = forge
+        .open_change_request(
+            &title,
+            &pr_body(forge.remote().kind, request, &summary),
+            &branch,
+            &base,
+        )
+        .await?;
+    info!(
+        "Opened {} #{}",
+        forge.remote().kind.change_request_name(),
+        opened.number
+    );
+
+    Ok(success_reply(&opened.url, &stat, &summary))
+}
+
+/// The branch changes go into: the pull request's own branch for comments
+/// on pull requests, else the default branch.
+async fn base_branch(forge: &dyn Forge, request: &Request) -> Result<String> {
+    if !request.thread.is_change_request {
+        return Ok(request.default_branch.clone());
+    }
+
+    let change = forge.change_request(request.thread.number).await?;
+    if change.from_fork {
+        anyhow::bail!(
+            "{}s from forks are not supported",
+            capitalize(forge.remote().kind.change_request_name())
+        );
+    }
+    Ok(change.head_branch)
+}
+
+/// Run the agent in `dir`, as `agent` if given, returning its final
+/// response.
+pub(super) async fn run_agent(
+    dir: &Path,
+    prompt: &str,
+    model: &ModelChoice<'_>,
+    agent: Option<&str>,
+) -> Result<String> {
+    let instance = Instance::new(dir).await?;
+    let result = prompt_agent(&instance, prompt, model, agent).await;
+    instance.dispose().await;
+    result
+}
+
+/// Send `prompt` to a runner for `instance`, as `agent` if given, returning
+/// the final response.
+pub(super) async fn prompt_agent(
+    instance: &Instance,
+    prompt: &str,
+    model: &ModelChoice<'_>,
+    agent: Option<&str>,
+) -> Result<String> {
+    let config = instance.config().await;
+    let spec = match (model.model, &config.model) {
+        (Some(model), _) => model.to_string(),
+        (None, Some(model)) => model.clone(),
+        (None, None) => format!(
+            "{}/{}",
+            model.default_provider,
+            get_default_model(model.default_provider)
+        ),
+    };
+    let (provider, model_id) = parse_model_spec(&spec, model.default_provider);
+    info!("Running agent with {}/{}", provider, model_id);
+
+    let mut runner = Unattended::start(instance, provider, model_id)
+        .await
+        .map_err(|e| anyhow::anyhow!("Agent failed: {e}"))?;
+    if let Some(agent) = agent {
+        runner.send(AppAction::ChangeAgent(agent.to_string()));
+    }
+    let result = runner.prompt(prompt.to_string()).await;
+    runner.stop();
+
+    result.map_err(|e| anyhow::anyhow!("Agent failed: {e}"))
+}
+
+/// A checkout for the agent, removed when dropped.
+pub(super) struct Workspace {
+    pub(super) dir: PathBuf,
+    /// The repository `dir` is a worktree of, if it isn't a clone.
+    repo: Option<PathBuf>,
+}
+
+impl Workspace {
+    /// Check `branch` out as a new branch from `base`: in a worktree when
+    /// `cwd` is a checkout of the repository, else in a clone. `name` tells
+    /// the workspace apart from others of the repository.
+    pub(super) fn create(
+        forge: &dyn Forge,
+        cwd: &Path,
+        name: &str,
+        base: &str,
+        branch: &str,
+    ) -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("wonopcode-{}-{name}", forge.remote().repo));
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+
+        let is_checkout = Remote::from_origin(cwd)
+            .ok()
+            .is_some_and(|origin| origin.same_repo(forge.remote()));
+
+        if is_checkout {
+            git::fetch(cwd, base)?;
+            git::add_worktree(cwd, &dir, branch, &format!("origin/{base}"))?;
+            Ok(Self {
+                dir,
+                repo: Some(cwd.to_path_buf()),
+            })
+        } else {
+            git::clone(&forge.authenticated_url(), &dir, base)?;
+            let workspace = Self { dir, repo: None };
+            git::create_branch(&workspace.dir, branch, None)?;
+            Ok(workspace)
+        }
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let removed = match &self.repo {
+            Some(repo) => git::remove_worktree(repo, &self.dir),
+            None => std::fs::remove_dir_all(&self.dir).map_err(Into::into),
+        };
+        if let Err(e) = removed {
+            warn!("Failed to remove {}: {e}", self.dir.display());
+        }
+    }
+}
+
+/// The branch for the request in `comment_id` on issue `issue_number`.
+fn branch_name(issue_number: u64, comment_id: u64) -> String {
+    format!("wonopcode/issue-{issue_number}-{comment_id}")
+}
+
+/// The commit and pull request title: the request's first line, shortened.
+fn pr_title(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or("").trim();
+    if line.is_empty() {
+        return "Changes requested in a comment".to_string();
+    }
+    let mut title: String = line.chars().take(72).collect();
+    if title.len() < line.len() {
+        title.push('…');
+    }
+    title
+}
+
+/// How `thread` is referred to in comments: `#42`, or `!42` for GitLab
+/// merge requests.
+fn reference(kind: ForgeKind, thread: Thread) -> String {
+    if kind == ForgeKind::GitLab && thread.is_change_request {
+        format!("!{}", thread.number)
+    } else {
+        format!("#{}", thread.number)
+    }
+}
+
+pub(super) fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The prompt for the agent, with the issue for context.
+fn build_prompt(remote: &Remote, request: &Request) -> String {
+    let kind = if request.thread.is_change_request {
+        remote.kind.change_request_name()
+    } else {
+        "issue"
+    };
+    let mut prompt = format!(
+        "You are working on {} in response to a comment on {kind} {}: {}\n",
+        remote.full_name(),
+        reference(remote.kind, request.thread),
+        request.issue_title
+    );
+    if !request.issue_body.trim().is_empty() {
+        prompt.push_str(&format!(
+            "\nThe {kind} description:\n\n{}\n",
+            request.issue_body.trim()
+        ));
+    }
+    prompt.push_str(&format!(
+        "\nThe request from @{}:\n\n{}\n\n\
+         Make the changes in this working tree, but don't commit or push them: \
+         that happens once you are done. Finish with a short summary of what you changed.",
+        request.author.login, request.prompt
+    ));
+    prompt
+}
+
+/// The pull request description.
+fn pr_body(kind: ForgeKind, request: &Request, summary: &str) -> String {
+    format!(
+        "{}\n\nRequested by @{} in {}.",
+        truncate(summary),
+        request.author.login,
+        reference(kind, request.thread)
+    )
+}
+
+/// Reply for a request that led to a pull request.
+fn success_reply(pr_url: &str, stat: &str, summary: &str) -> String {
+    format!(
+        "Opened {pr_url}\n\n```\n{stat}\n```\n\n<details><summary>Summary</summary>\n\n{}\n\n</details>",
+        truncate(summary)
+    )
+}
+
+/// Reply for a request the agent made no changes for.
+fn no_changes_reply(summary: &str) -> String {
+    format!("No files changed.\n\n{}", truncate(summary))
+}
+
+/// Reply for a request that failed.
+fn failure_reply(error: &str) -> String {
+    format!("Failed to handle this request:\n\n```\n{error}\n```")
+}
+
+/// Shorten `text` to [`MAX_SUMMARY_CHARS`].
+pub(super) fn truncate(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}\n\n(truncated)", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote() -> Remote {
+        Remote {
+            kind: ForgeKind::GitHub,
+            web_url: "https://github.com".to_string(),
+            owner: "wonop-io".to_string(),
+            repo: "wonopcode".to_string(),
+        }
+    }
+
+    fn request() -> Request {
+        Request {
+            thread: Thread {
+                number: 42,
+                is_change_request: false,
+            },
+            issue_title: "Crash on empty config".to_string(),
+            issue_body: "Starting with an empty config file panics.".to_string(),
+            comment_id: 1001,
+            author: Author {
+                login: "octocat".to_string(),
+                id: 583231,
+            },
+            prompt: "fix the panic".to_string(),
+            default_branch: "main".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_is_trigger_comment() {
+        assert!(is_trigger_comment("/wonopcode fix this bug"));
+        assert!(is_trigger_comment("Hey /wc can you help?"));
+        assert!(!is_trigger_comment("Just a regular comment"));
+    }
+
+    #[test]
+    fn test_extract_prompt() {
+        assert_eq!(extract_prompt("/wonopcode fix this bug"), "fix this bug");
+        assert_eq!(extract_prompt("Hey /wc can you help?"), "can you help?");
+    }
+
+    #[test]
+    fn test_branch_and_title() {
+        assert_eq!(branch_name(42, 1001), "wonopcode/issue-42-1001");
+        assert_eq!(pr_title("fix the panic\nand add a test"), "fix the panic");
+        assert_eq!(pr_title("  "), "Changes requested in a comment");
+        let long = "x".repeat(100);
+        assert_eq!(pr_title(&long).chars().count(), 73);
+    }
+
+    #[test]
+    fn test_build_prompt() {
+        let prompt = build_prompt(&remote(), &request());
+        assert!(prompt.contains(
+            "wonop-io/wonopcode in response to a comment on issue #42: Crash on empty config"
+        ));
+        assert!(prompt.contains("Starting with an empty config file panics."));
+        assert!(prompt.contains("The request from @octocat:\n\nfix the panic"));
+
+        let mut request = request();
+        request.issue_body = String::new();
+        request.thread.is_change_request = true;
+        let prompt = build_prompt(&remote(), &request);
+        assert!(prompt.contains("comment on pull request #42"));
+        assert!(!prompt.contains("description"));
+
+        let mut remote = remote();
+        remote.kind = ForgeKind::GitLab;
+        let prompt = build_prompt(&remote, &request);
+        assert!(prompt.contains("comment on merge request !42"));
+    }
+
+    #[test]
+    fn test_replies() {
+        let body = pr_body(ForgeKind::GitHub, &request(), "Fixed it.\n");
+        assert_eq!(body, "Fixed it.\n\nRequested by @octocat in #42.");
+
+        let reply = success_reply(
+            "https://github.com/wonop-io/wonopcode/pull/43",
+            " src/config.rs | 2 +-",
+            "Fixed it.",
+        );
+        assert!(reply.starts_with("Opened https://github.com/wonop-io/wonopcode/pull/43"));
+        assert!(reply.contains("src/config.rs | 2 +-"));
+
+        let summary = "é".repeat(MAX_SUMMARY_CHARS + 1);
+        assert!(truncate(&summary).ends_with("(truncated)"));
+        assert_eq!(truncate("short"), "short");
+    }
+}
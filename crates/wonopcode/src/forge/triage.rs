@@ -0,0 +1,299 @@
+//! Triage of labeled issues.
+//!
+//! An agent reproduces the issue and locates the code involved in a
+//! throwaway checkout, then answers with its analysis as JSON. The triage
+//! label is removed, so adding it again triages anew, the analysis is posted
+//! on the issue and the labels it suggests that the repository has are
+//! applied. Nothing is pushed: a `/wonopcode` comment asks for the fix.
+
+use super::review::last_json;
+use super::task::{self, truncate, ModelChoice, Workspace};
+use super::{Forge, Remote, Thread};
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::Path;
+use tracing::{info, warn};
+use wonopcode_core::config::{Config, TriageConfig};
+
+/// Label that asks for triage, unless configured otherwise.
+const DEFAULT_LABEL: &str = "wonopcode-triage";
+
+/// Agent that triages, unless configured otherwise. It needs to run
+/// commands to reproduce the issue.
+const DEFAULT_AGENT: &str = "build";
+
+/// An issue to triage.
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    pub default_branch: String,
+}
+
+/// Triage settings for the project in `cwd`, with defaults filled in.
+pub struct Settings {
+    pub label: String,
+    agent: String,
+    apply_labels: bool,
+}
+
+impl Settings {
+    /// Load the `triage` config of the project in `cwd`.
+    pub async fn load(cwd: &Path) -> Result<Self> {
+        let (config, _) = Config::load(Some(cwd)).await?;
+        Ok(Self::new(config.triage.unwrap_or_default()))
+    }
+
+    fn new(config: TriageConfig) -> Self {
+        Self {
+            label: config.label.unwrap_or_else(|| DEFAULT_LABEL.to_string()),
+            agent: config.agent.unwrap_or_else(|| DEFAULT_AGENT.to_string()),
+            apply_labels: config.apply_labels.unwrap_or(true),
+        }
+    }
+}
+
+/// The agent's analysis of an issue.
+#[derive(Debug, Deserialize)]
+struct Triage {
+    summary: String,
+    #[serde(default)]
+    reproduction: Option<String>,
+    #[serde(default)]
+    suspected_cause: Option<String>,
+    #[serde(default)]
+    affected_files: Vec<AffectedFile>,
+    #[serde(default)]
+    plan: Vec<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+}
+
+/// A file the issue involves.
+#[derive(Debug, Deserialize)]
+struct AffectedFile {
+    path: String,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Triage `issue`: remove the triage label, comment the analysis and apply
+/// the suggested labels.
+pub async fn handle(
+    forge: &dyn Forge,
+    cwd: &Path,
+    issue: &Issue,
+    settings: &Settings,
+    model: &ModelChoice<'_>,
+) -> Result<()> {
+    let thread = Thread {
+        number: issue.number,
+        is_change_request: false,
+    };
+    info!("Triaging issue #{}: {}", issue.number, issue.title);
+
+    // Labels the agent may suggest: the repository's, but the trigger
+    let available: Vec<String> = if settings.apply_labels {
+        forge
+            .labels()
+            .await?
+            .into_iter()
+            .filter(|label| !label.eq_ignore_ascii_case(&settings.label))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let result = run(forge, cwd, issue, settings, &available, model).await;
+
+    // Before labeling, so that the label changes don't trigger triage again
+    if let Err(e) = forge.remove_label(issue.number, &settings.label).await {
+        warn!("Failed to remove the {} label: {e}", settings.label);
+    }
+
+    match result {
+        Ok(triage) => {
+            forge.comment(thread, &render(&triage)).await?;
+            let labels = matching_labels(&triage.labels, &available);
+            if !labels.is_empty() {
+                info!("Labeling issue #{}: {}", issue.number, labels.join(", "));
+                if let Err(e) = forge.add_labels(issue.number, &labels).await {
+                    warn!("Failed to label issue #{}: {e}", issue.number);
+                }
+            }
+        }
+        Err(e) => {
+            // Keep the token, which git errors may show in URLs, out of the comment
+            let error = format!("{e:#}").replace(forge.token(), "***");
+            warn!("Triage failed: {error}");
+            forge
+                .comment(
+                    thread,
+                    &format!("Failed to triage this issue:\n\n```\n{error}\n```"),
+                )
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Run the triage agent on `issue` in a throwaway checkout of the default
+/// branch.
+async fn run(
+    forge: &dyn Forge,
+    cwd: &Path,
+    issue: &Issue,
+    settings: &Settings,
+    available: &[String],
+    model: &ModelChoice<'_>,
+) -> Result<Triage> {
+    let name = format!("triage-{}", issue.number);
+    let branch = format!("wonopcode/triage-{}", issue.number);
+    let workspace = Workspace::create(forge, cwd, &name, &issue.default_branch, &branch)?;
+
+    let prompt = build_prompt(forge.remote(), issue, available);
+    let response = task::run_agent(&workspace.dir, &prompt, model, Some(&settings.agent)).await?;
+    parse_triage(&response)
+}
+
+/// The prompt for the triage agent.
+fn build_prompt(remote: &Remote, issue: &Issue, available: &[String]) -> String {
+    let mut prompt = format!(
+        "Triage issue #{} of {}: {}\n",
+        issue.number,
+        remote.full_name(),
+        issue.title
+    );
+    if !issue.body.trim().is_empty() {
+        prompt.push_str(&format!("\nThe issue:\n\n{}\n", issue.body.trim()));
+    }
+    prompt.push_str(
+        "\nFind out what causes it: reproduce the issue if you can, with a test or a command, \
+         and locate the code involved. Don't fix it. This working tree is thrown away \
+         afterwards, so any changes you make are lost.\n\n\
+         End your answer with the triage as a JSON block:\n\n\
+         ```json\n\
+         {\"summary\": \"...\", \"reproduction\": \"...\", \"suspected_cause\": \"...\", \
+         \"affected_files\": [{\"path\": \"src/lib.rs\", \"reason\": \"...\"}], \
+         \"plan\": [\"...\"], \"labels\": [\"...\"]}\n\
+         ```\n\n\
+         `reproduction` says how you reproduced the issue, or why you couldn't. \
+         `plan` is the steps of a fix.",
+    );
+    if available.is_empty() {
+        prompt.push_str(" Leave `labels` empty.\n");
+    } else {
+        prompt.push_str(&format!(
+            " `labels` are those of the repository's labels that fit the issue: {}.\n",
+            available.join(", ")
+        ));
+    }
+    prompt
+}
+
+/// Parse the triage from the agent's answer.
+fn parse_triage(text: &str) -> Result<Triage> {
+    let json =
+        last_json(text).ok_or_else(|| anyhow::anyhow!("The triage agent gave no JSON triage"))?;
+    serde_json::from_str(json)
+        .map_err(|e| anyhow::anyhow!("The triage agent gave an invalid triage: {e}"))
+}
+
+/// The triage comment.
+fn render(triage: &Triage) -> String {
+    let mut comment = format!("## Triage\n\n{}\n", triage.summary.trim());
+    let sections = [
+        ("Reproduction", &triage.reproduction),
+        ("Suspected cause", &triage.suspected_cause),
+    ];
+    for (heading, text) in sections {
+        if let Some(text) = text.as_deref().filter(|text| !text.trim().is_empty()) {
+            comment.push_str(&format!("\n### {heading}\n\n{}\n", text.trim()));
+        }
+    }
+    if !triage.affected_files.is_empty() {
+        comment.push_str("\n### Affected files\n\n");
+        for file in &triage.affected_files {
+            match file.reason.as_deref().filter(|r| !r.trim().is_empty()) {
+                Some(reason) => {
+                    comment.push_str(&format!("- `{}`: {}\n", file.path, reason.trim()))
+                }
+                None => comment.push_str(&format!("- `{}`\n", file.path)),
+            }
+        }
+    }
+    if !triage.plan.is_empty() {
+        comment.push_str("\n### Proposed fix\n\n");
+        for (i, step) in triage.plan.iter().enumerate() {
+            comment.push_str(&format!("{}. {}\n", i + 1, step.trim()));
+        }
+    }
+    comment.push_str("\nComment `/wonopcode fix this` to have a pull request opened with the fix.");
+    truncate(&comment)
+}
+
+/// The suggested labels that are among `available`, spelled as there.
+fn matching_labels(suggested: &[String], available: &[String]) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    for suggestion in suggested {
+        let found = available
+            .iter()
+            .find(|label| label.eq_ignore_ascii_case(suggestion.trim()));
+        if let Some(label) = found {
+            if !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_defaults() {
+        let settings = Settings::new(TriageConfig::default());
+        assert_eq!(settings.label, "wonopcode-triage");
+        assert_eq!(settings.agent, "build");
+        assert!(settings.apply_labels);
+    }
+
+    #[test]
+    fn test_parse_and_render() {
+        let text = "Reproduced it.\n\n```json\n{\"summary\": \"Empty configs panic.\", \
+                    \"reproduction\": \"`cargo run` with an empty file\", \
+                    \"suspected_cause\": \"An unwrap on the parsed value.\", \
+                    \"affected_files\": [{\"path\": \"src/config.rs\", \"reason\": \"the unwrap\"}, \
+                    {\"path\": \"src/main.rs\"}], \
+                    \"plan\": [\"Treat empty files as defaults\", \"Add a test\"], \
+                    \"labels\": [\"Bug\"]}\n```";
+        let triage = parse_triage(text).unwrap();
+        assert_eq!(triage.labels, vec!["Bug"]);
+
+        let comment = render(&triage);
+        assert!(comment.starts_with("## Triage\n\nEmpty configs panic.\n"));
+        assert!(comment.contains("### Suspected cause\n\nAn unwrap on the parsed value.\n"));
+        assert!(comment.contains("- `src/config.rs`: the unwrap\n- `src/main.rs`\n"));
+        assert!(comment.contains("1. Treat empty files as defaults\n2. Add a test\n"));
+        assert!(comment.ends_with("opened with the fix."));
+
+        assert!(parse_triage("No idea.").is_err());
+    }
+
+    #[test]
+    fn test_matching_labels() {
+        let available = vec!["bug".to_string(), "area: cli".to_string()];
+        let suggested = vec![
+            "Bug".to_string(),
+            "bug".to_string(),
+            "needs-info".to_string(),
+            " area: CLI ".to_string(),
+        ];
+        assert_eq!(
+            matching_labels(&suggested, &available),
+            vec!["bug", "area: cli"]
+        );
+    }
+}
@@ -3,19 +3,31 @@
 //! This is the main entry point for the wonopcode CLI.
 // @ace:implements COMP-T90R9Q-UR4
 
+mod batch;
 mod commands;
+mod commit_message;
 mod compaction;
-#[cfg(feature = "github")]
-mod github;
+mod connect;
+#[cfg(feature = "forge")]
+mod forge;
+mod pinned;
+mod projects;
 mod publish;
+mod retrieval;
 mod runner;
+mod schedule;
+mod sessions;
 mod stats;
+mod tabs;
+mod telemetry;
+mod unattended;
 mod upgrade;
 
 // Re-export command types for use in Commands enum
 use commands::{
     create_mcp_http_state, parse_model_spec, parse_release_channel, start_mcp_server,
-    AgentCommands, AuthCommands, McpCommands, SessionCommands,
+    AgentCommands, AuditCommands, AuthCommands, ConfigCommands, McpCommands, ScheduleCommands,
+    SessionCommands, SnapshotCommands, SpendCommands,
 };
 
 use clap::{Parser, Subcommand};
@@ -52,6 +64,10 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// Record all events to a JSONL file, to review later with `wonopcode replay`
+    #[arg(long, value_name = "FILE")]
+    record_events: Option<std::path::PathBuf>,
+
     /// Provider to use (anthropic, openai, openrouter)
     #[arg(long, default_value = "anthropic")]
     provider: String,
@@ -60,6 +76,11 @@ struct Cli {
     #[arg(long, short)]
     model: Option<String>,
 
+    /// Configuration profile to use (from `profiles` in wonopcode.json).
+    /// Can also be set via WONOPCODE_PROFILE environment variable.
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
     /// Run in headless mode (server only, no TUI)
     #[arg(long)]
     headless: bool,
@@ -68,10 +89,34 @@ struct Cli {
     #[arg(long, default_value = "127.0.0.1:3000")]
     address: std::net::SocketAddr,
 
-    /// Connect to a remote headless server
+    /// Connect to a remote headless server (host:port, or an http:// or https:// URL)
     #[arg(long)]
     connect: Option<String>,
 
+    /// Receive updates over SSE and send actions as HTTP requests instead of
+    /// over a WebSocket, for servers or proxies without WebSocket support.
+    #[arg(long)]
+    sse: bool,
+
+    /// TLS certificate (PEM) to serve the headless and web servers with;
+    /// needs --tls-key.
+    #[arg(long, global = true, requires = "tls_key")]
+    tls_cert: Option<std::path::PathBuf>,
+
+    /// Private key (PKCS#8 PEM) of the TLS certificate.
+    #[arg(long, global = true, requires = "tls_cert")]
+    tls_key: Option<std::path::PathBuf>,
+
+    /// Serve the headless and web servers over TLS with a self-signed
+    /// certificate, generated on first run.
+    #[arg(long, global = true, conflicts_with = "tls_cert")]
+    tls_self_signed: bool,
+
+    /// CA certificate (PEM) to trust when connecting to an https server,
+    /// such as the server's self-signed certificate.
+    #[arg(long, global = true)]
+    tls_ca: Option<std::path::PathBuf>,
+
     /// Secret key for server authentication.
     /// When set, clients must provide this key via X-API-Key header or Authorization: Bearer header.
     /// Can also be set via WONOPCODE_SECRET environment variable.
@@ -93,8 +138,8 @@ struct Cli {
     #[arg(long)]
     advertise: bool,
 
-    /// Custom name for mDNS advertisement (default: hostname).
-    #[cfg(feature = "discover")]
+    /// Custom name for mDNS advertisement and rendezvous registration
+    /// (default: hostname).
     #[arg(long)]
     name: Option<String>,
 
@@ -103,6 +148,29 @@ struct Cli {
     #[arg(long, conflicts_with = "connect")]
     discover: bool,
 
+    /// With --discover, also look for servers on the machines of your
+    /// Tailscale network, listening on PORT (default: 3000).
+    #[cfg(feature = "discover")]
+    #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "3000")]
+    tailscale: Option<u16>,
+
+    /// Rendezvous relay (host:port or URL) for finding servers beyond the
+    /// local network: the headless server registers with it, and --discover
+    /// asks it for servers. Can also be set via WONOPCODE_RENDEZVOUS.
+    #[arg(long, global = true)]
+    rendezvous: Option<String>,
+
+    /// Token of the rendezvous relay. Can also be set via
+    /// WONOPCODE_RENDEZVOUS_TOKEN.
+    #[arg(long, global = true)]
+    rendezvous_token: Option<String>,
+
+    /// Address (host:port) clients reach the headless server at, to register
+    /// with the rendezvous relay; repeatable. Defaults to this machine's
+    /// network addresses.
+    #[arg(long = "public-address", value_name = "HOST:PORT")]
+    public_addresses: Vec<String>,
+
     /// Subcommand
     #[command(subcommand)]
     command: Option<Commands>,
@@ -121,10 +189,13 @@ enum Commands {
         /// Session ID to continue
         #[arg(short, long)]
         session: Option<String>,
-        /// Output format: default or json
+        /// Output format: default, json or stream-json
         #[arg(long, default_value = "default")]
         format: String,
-        /// Message to send
+        /// Session template to start from
+        #[arg(short, long)]
+        template: Option<String>,
+        /// Message to send, or a custom /command and its arguments
         #[arg(num_args = 0..)]
         message: Vec<String>,
     },
@@ -136,8 +207,20 @@ enum Commands {
     },
     /// List available models
     Models,
-    /// Show configuration
-    Config,
+    /// Show configuration, or get, set or edit config keys
+    Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
+    },
+    /// Set up wonopcode in this project: config, AGENTS.md, example commands
+    Init {
+        /// Don't ask, use the defaults
+        #[arg(short, long)]
+        yes: bool,
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
     /// Print version information
     Version,
     /// Authenticate with a provider
@@ -150,6 +233,45 @@ enum Commands {
         #[command(subcommand)]
         command: SessionCommands,
     },
+    /// List, diff and restore the file snapshots taken before edits
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+    /// Replay a recording made with --record-events in the TUI
+    Replay {
+        /// The recording
+        file: std::path::PathBuf,
+        /// Playback speed; 0 shows everything at once
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+    },
+    /// Review recorded permission decisions
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommands,
+    },
+    /// Review spend and acknowledge spending limits
+    Spend {
+        #[command(subcommand)]
+        command: SpendCommands,
+    },
+    /// List and run scheduled tasks
+    Schedule {
+        #[command(subcommand)]
+        command: ScheduleCommands,
+    },
+    /// Run a batch of prompts and shell commands from a YAML file
+    Batch {
+        /// The batch file
+        file: std::path::PathBuf,
+        /// Output format: json or text
+        #[arg(long, default_value = "json")]
+        format: String,
+        /// Also write the JSON report to this file
+        #[arg(long)]
+        report: Option<std::path::PathBuf>,
+    },
     /// Export session(s) to a file
     Export {
         /// Session ID to export (exports all if not specified)
@@ -174,17 +296,32 @@ enum Commands {
         #[arg(short, long)]
         cwd: Option<std::path::PathBuf>,
     },
-    /// GitHub integration commands (requires --features github)
-    #[cfg(feature = "github")]
+    /// GitHub and Gitea Actions integration commands (requires --features forge)
+    #[cfg(feature = "forge")]
     Github {
         #[command(subcommand)]
         command: GithubCommands,
     },
-    /// Checkout a GitHub PR (requires --features github)
-    #[cfg(feature = "github")]
+    /// GitLab CI integration commands (requires --features forge)
+    #[cfg(feature = "forge")]
+    Gitlab {
+        #[command(subcommand)]
+        command: GitlabCommands,
+    },
+    /// Explain CI failures (requires --features forge)
+    #[cfg(feature = "forge")]
+    Ci {
+        #[command(subcommand)]
+        command: CiCommands,
+    },
+    /// Checkout or review a pull request on GitHub, GitLab or Gitea (requires --features forge)
+    #[cfg(feature = "forge")]
+    #[command(args_conflicts_with_subcommands = true)]
     Pr {
+        #[command(subcommand)]
+        command: Option<PrCommands>,
         /// PR number to checkout
-        number: u64,
+        number: Option<u64>,
     },
     /// Show token usage and cost statistics
     Stats {
@@ -197,6 +334,15 @@ enum Commands {
         /// Filter by project (empty string for current project)
         #[arg(short, long)]
         project: Option<String>,
+        /// Print a cost report across projects instead: csv or json
+        #[arg(long, value_parser = stats::ReportFormat::parse)]
+        export: Option<stats::ReportFormat>,
+        /// Sum the report by day, model, project or agent
+        #[arg(long, default_value = "day", value_parser = stats::ReportGroup::parse, requires = "export")]
+        group_by: stats::ReportGroup,
+        /// Sum the report by month first
+        #[arg(long, requires = "export")]
+        monthly: bool,
     },
     /// Start web UI server (headless mode)
     Web {
@@ -207,6 +353,13 @@ enum Commands {
         #[arg(long, default_value = "true")]
         open: bool,
     },
+    /// Run a rendezvous relay, for finding headless servers beyond the
+    /// local network
+    Rendezvous {
+        /// Address to bind to
+        #[arg(short, long, default_value = "0.0.0.0:4000")]
+        address: SocketAddr,
+    },
     /// Manage MCP servers
     Mcp {
         #[command(subcommand)]
@@ -235,6 +388,9 @@ enum Commands {
         /// Force reinstall even if up to date
         #[arg(long)]
         force: bool,
+        /// Go back to the version before the last upgrade
+        #[arg(long, conflicts_with_all = ["channel", "version", "force"])]
+        rollback: bool,
     },
     /// Publish a new release (for maintainers)
     Publish {
@@ -260,12 +416,12 @@ enum Commands {
 
 // AuthCommands, SessionCommands, and McpCommands are defined in commands module
 
-#[cfg(feature = "github")]
+#[cfg(feature = "forge")]
 #[derive(Subcommand)]
 enum GithubCommands {
     /// Install GitHub integration
     Install,
-    /// Run GitHub agent (for CI/GitHub Actions)
+    /// Run GitHub agent (for CI/GitHub Actions and Gitea Actions)
     Run {
         /// Path to GitHub event JSON
         #[arg(long)]
@@ -276,10 +432,70 @@ enum GithubCommands {
     },
 }
 
+#[cfg(feature = "forge")]
+#[derive(Subcommand)]
+enum GitlabCommands {
+    /// Install GitLab integration
+    Install,
+    /// Run GitLab agent (for GitLab CI)
+    Run {
+        /// Path to GitLab webhook event JSON (default: TRIGGER_PAYLOAD)
+        #[arg(long)]
+        event: Option<String>,
+        /// GitLab token (default: GITLAB_TOKEN)
+        #[arg(long)]
+        token: Option<String>,
+    },
+}
+
+#[cfg(feature = "forge")]
+#[derive(Subcommand)]
+enum CiCommands {
+    /// Explain why a GitHub Actions run failed, with a suggested patch
+    Explain {
+        /// Workflow run ID (default: the latest failed run of the current branch)
+        #[arg(long)]
+        run: Option<u64>,
+        /// Comment the analysis on the run's PR instead of printing it
+        #[arg(long)]
+        comment: bool,
+        /// GitHub token (default: GITHUB_TOKEN, then `gh auth token`)
+        #[arg(long)]
+        token: Option<String>,
+    },
+}
+
+#[cfg(feature = "forge")]
+#[derive(Subcommand)]
+enum PrCommands {
+    /// Review a PR and post the review on its forge
+    Review {
+        /// PR number to review
+        number: u64,
+        /// Least severe finding to post: info, warning or error (default: from config, then warning)
+        #[arg(long, value_parser = forge::parse_severity)]
+        min_severity: Option<wonopcode_core::config::ReviewSeverity>,
+        /// Request changes for findings at least this severe (default: from config, then error)
+        #[arg(long, value_parser = forge::parse_severity)]
+        request_changes_at: Option<wonopcode_core::config::ReviewSeverity>,
+        /// Forge token (default: GITHUB_TOKEN and `gh auth token`, GITLAB_TOKEN or GITEA_TOKEN)
+        #[arg(long)]
+        token: Option<String>,
+        /// Print the review instead of posting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
+    // Select the config profile before any config is loaded
+    if let Some(profile) = &cli.profile {
+        wonopcode_core::config::select_profile(profile);
+    }
+
     // Initialize logging and get log file path
     // In headless mode, log to stdout instead of file
     let log_file = commands::init_logging(cli.verbose, cli.headless);
@@ -297,6 +513,9 @@ async fn main() -> anyhow::Result<()> {
     // Get current directory
     let cwd = std::env::current_dir()?;
 
+    // Export traces and metrics if the config enables it
+    let telemetry = telemetry::init(&cwd).await;
+
     // Handle subcommands
     let result = match cli.command {
         Some(Commands::Run {
@@ -305,6 +524,7 @@ async fn main() -> anyhow::Result<()> {
             continue_session,
             session,
             format,
+            template,
         }) => {
             commands::run_command(
                 &cwd,
@@ -313,6 +533,7 @@ async fn main() -> anyhow::Result<()> {
                 continue_session,
                 session,
                 &format,
+                template,
                 cli.provider.clone(),
                 cli.secret.clone(),
             )
@@ -323,13 +544,31 @@ async fn main() -> anyhow::Result<()> {
             commands::list_models();
             Ok(())
         }
-        Some(Commands::Config) => show_config(&cwd).await,
+        Some(Commands::Config { command: None }) => show_config(&cwd).await,
+        Some(Commands::Config {
+            command: Some(command),
+        }) => commands::handle_config(command, &cwd).await,
+        Some(Commands::Init { yes, force }) => {
+            commands::handle_init(&cwd, yes, force, &cli.provider, cli.model.as_deref())
+        }
         Some(Commands::Version) => {
             print_version();
             Ok(())
         }
         Some(Commands::Auth { command }) => commands::handle_auth(command).await,
         Some(Commands::Session { command }) => commands::handle_session(command, &cwd).await,
+        Some(Commands::Snapshot { command }) => commands::handle_snapshot(command, &cwd).await,
+        Some(Commands::Audit { command }) => commands::handle_audit(command, &cwd).await,
+        Some(Commands::Replay { file, speed }) => commands::handle_replay(file, speed).await,
+        Some(Commands::Spend { command }) => commands::handle_spend(command, &cwd).await,
+        Some(Commands::Schedule { command }) => {
+            commands::handle_schedule(command, &cwd, &cli.provider).await
+        }
+        Some(Commands::Batch {
+            file,
+            format,
+            report,
+        }) => commands::handle_batch(&file, &format, report.as_deref(), &cli.provider).await,
         Some(Commands::Export {
             session,
             output,
@@ -340,28 +579,56 @@ async fn main() -> anyhow::Result<()> {
             let working_dir = acp_cwd.unwrap_or_else(|| cwd.clone());
             run_acp(&working_dir).await
         }
-        #[cfg(feature = "github")]
-        Some(Commands::Github { command }) => handle_github(command, &cwd).await,
-        #[cfg(feature = "github")]
-        Some(Commands::Pr { number }) => handle_pr(number).await,
+        #[cfg(feature = "forge")]
+        Some(Commands::Github { command }) => {
+            handle_github(command, &cwd, &cli.provider, cli.model.as_deref()).await
+        }
+        #[cfg(feature = "forge")]
+        Some(Commands::Gitlab { command }) => {
+            handle_gitlab(command, &cwd, &cli.provider, cli.model.as_deref()).await
+        }
+        #[cfg(feature = "forge")]
+        Some(Commands::Ci { command }) => {
+            handle_ci(command, &cwd, &cli.provider, cli.model.as_deref()).await
+        }
+        #[cfg(feature = "forge")]
+        Some(Commands::Pr { command, number }) => {
+            handle_pr(command, number, &cwd, &cli.provider, cli.model.as_deref()).await
+        }
         Some(Commands::Stats {
             days,
             tools,
             project,
-        }) => handle_stats(&cwd, days, tools, project).await,
+            export,
+            group_by,
+            monthly,
+        }) => match export {
+            Some(format) => {
+                handle_stats_export(&cwd, days, project, format, group_by, monthly).await
+            }
+            None => handle_stats(&cwd, days, tools, project).await,
+        },
         Some(Commands::Web { address, open }) => {
-            commands::run_web_server(address, open, &cwd).await
+            let tls = tls_identity(&cli, address)?;
+            commands::run_web_server(address, open, tls, &cwd).await
         }
+        Some(Commands::Rendezvous { address }) => run_rendezvous(address, &cli).await,
         Some(Commands::Mcp { command }) => commands::handle_mcp(command, &cwd).await,
         Some(Commands::Check { channel, json }) => {
             let channel = channel.and_then(|s| commands::parse_release_channel(&s));
             upgrade::handle_check(channel, json).await
         }
+        Some(Commands::Upgrade {
+            yes,
+            rollback: true,
+            ..
+        }) => upgrade::handle_rollback(yes).await,
         Some(Commands::Upgrade {
             yes,
             channel,
             version,
             force,
+            rollback: false,
         }) => {
             let channel = channel.and_then(|s| commands::parse_release_channel(&s));
             upgrade::handle_upgrade(yes, channel, version, force).await
@@ -401,11 +668,23 @@ async fn main() -> anyhow::Result<()> {
         }
     };
 
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown().await;
+    }
+
     // Print log file location on exit (for TUI mode)
     if let Some(path) = log_file {
         eprintln!("Logs: {}", path.display());
     }
 
+    // `wonopcode run` documents its exit codes
+    if let Err(e) = &result {
+        if let Some(failed) = e.downcast_ref::<commands::RunFailed>() {
+            eprintln!("Error: {failed}");
+            std::process::exit(failed.exit_code);
+        }
+    }
+
     result
 }
 
@@ -456,6 +735,8 @@ async fn run_interactive(cwd: &std::path::Path, cli: Cli) -> anyhow::Result<()>
 
     // Create instance (this already loads config internally)
     let instance = wonopcode_core::Instance::new(cwd).await?;
+    #[cfg(feature = "forge")]
+    crate::forge::use_for_pull_requests(&instance).await;
 
     // Get config from instance (avoid duplicate loading)
     let config_file = instance.config().await;
@@ -509,6 +790,12 @@ async fn run_interactive(cwd: &std::path::Path, cli: Cli) -> anyhow::Result<()>
     let shared_permission_manager =
         Arc::new(wonopcode_core::PermissionManager::new(shared_bus.clone()));
 
+    // Record events for `wonopcode replay`
+    let recorder = match &cli.record_events {
+        Some(path) => Some(commands::start_recording(path, &[&shared_bus, instance.bus()]).await?),
+        None => None,
+    };
+
     // Initialize default permission rules
     for rule in wonopcode_core::PermissionManager::default_rules() {
         shared_permission_manager.add_rule(rule).await;
@@ -592,6 +879,7 @@ async fn run_interactive(cwd: &std::path::Path, cli: Cli) -> anyhow::Result<()>
         .await?;
     } else {
         // TUI mode - pass shared permission manager for prompting
+        let connect_options = connect_options(&cli);
         run_tui_mode(
             &instance,
             config,
@@ -601,10 +889,16 @@ async fn run_interactive(cwd: &std::path::Path, cli: Cli) -> anyhow::Result<()>
             update_msg,
             shared_bus,
             shared_permission_manager,
+            recorder.is_some(),
+            connect_options,
         )
         .await?;
     }
 
+    if let Some(recorder) = recorder {
+        recorder.finish().await;
+    }
+
     // Shutdown MCP server
     if let Some(handle) = mcp_server_handle {
         handle.abort();
@@ -777,16 +1071,18 @@ async fn run_tui_mode(
     update_notification: Option<String>,
     shared_bus: wonopcode_core::bus::Bus,
     shared_permission_manager: Arc<wonopcode_core::PermissionManager>,
+    record_updates: bool,
+    connect_options: connect::ConnectOptions,
 ) -> anyhow::Result<()> {
     use wonopcode_tui::App;
 
     let mut app = App::new();
 
+    // Set project info first so the project's themes are available
+    app.set_project(instance.directory().display().to_string());
+
     // Apply saved settings from config (theme, render settings, etc.)
     app.apply_config(app_config);
-
-    // Set project info
-    app.set_project(instance.directory().display().to_string());
     app.set_model(format!("{}/{}", config.provider, config.model_id));
 
     // Show update notification as toast if available
@@ -799,15 +1095,20 @@ async fn run_tui_mode(
         .take_action_rx()
         .ok_or_else(|| anyhow::anyhow!("Action receiver already taken - app state corrupted"))?;
     let update_tx = app.update_sender();
+    let update_tx = if record_updates {
+        commands::record_updates(shared_bus.clone(), update_tx)
+    } else {
+        update_tx
+    };
 
     // Create runner with shared Bus and PermissionManager
     // This allows MCP tools to send permission requests to the TUI for user prompts
     let runner = match Runner::new_with_shared(
-        config,
+        config.clone(),
         instance.clone(),
-        mcp_configs,
-        Some(shared_bus),
-        Some(shared_permission_manager),
+        mcp_configs.clone(),
+        Some(shared_bus.clone()),
+        Some(shared_permission_manager.clone()),
     )
     .await
     {
@@ -818,10 +1119,21 @@ async fn run_tui_mode(
         }
     };
 
-    // Spawn runner
-    let runner_handle = tokio::spawn(async move {
-        runner.run(action_rx, update_tx).await;
-    });
+    // Spawn runner in the first tab; new tabs get runners like it
+    let template = tabs::TabTemplate {
+        config,
+        instance: instance.clone(),
+        mcp_configs,
+        bus: shared_bus,
+        permission_manager: shared_permission_manager,
+    };
+    // Actions go to the runners until /connect moves the TUI to a server
+    let switch_update_tx = update_tx.clone();
+    let session_tabs = tabs::SessionTabs::new(runner, template, update_tx);
+    let (local_tx, local_rx) = tokio::sync::mpsc::unbounded_channel();
+    let runner_handle = tokio::spawn(session_tabs.run(local_rx));
+    let switch = connect::BackendSwitch::local(local_tx, connect_options, switch_update_tx);
+    tokio::spawn(switch.run(action_rx));
 
     // Add initial prompt if provided
     if let Some(prompt) = initial_prompt {
@@ -831,9 +1143,9 @@ async fn run_tui_mode(
     // Run the TUI
     app.run().await?;
 
-    // Wait for runner to complete (it will cleanup sandbox on exit)
-    // The runner exits when action_rx is dropped (which happens when app exits)
-    // Give it a short timeout to cleanup
+    // Wait for the runners to complete (they cleanup sandboxes on exit)
+    // The runners exit when action_rx is dropped (which happens when app exits)
+    // Give them a short timeout to cleanup
     let timeout = tokio::time::timeout(std::time::Duration::from_secs(5), runner_handle).await;
 
     match timeout {
@@ -848,6 +1160,44 @@ async fn run_tui_mode(
     Ok(())
 }
 
+/// The TLS identity to serve with: the certificate and key given with
+/// `--tls-cert` and `--tls-key`, or with `--tls-self-signed` a self-signed
+/// certificate for this machine, generated on first run.
+fn tls_identity(
+    cli: &Cli,
+    address: SocketAddr,
+) -> anyhow::Result<Option<wonopcode_server::tls::Identity>> {
+    let (cert, key) = if let (Some(cert), Some(key)) = (&cli.tls_cert, &cli.tls_key) {
+        (cert.clone(), key.clone())
+    } else if cli.tls_self_signed {
+        let dir = wonopcode_util::path::data_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the data directory"))?
+            .join("tls");
+        let mut hosts = vec![
+            "localhost".to_string(),
+            "127.0.0.1".to_string(),
+            "::1".to_string(),
+        ];
+        if address.ip().is_unspecified() {
+            hosts.extend(commands::get_network_ips().unwrap_or_default());
+        } else {
+            hosts.push(address.ip().to_string());
+        }
+        if let Some(name) = hostname::get().ok().and_then(|h| h.into_string().ok()) {
+            hosts.push(format!("{name}.local"));
+            hosts.push(name);
+        }
+        hosts.dedup();
+        let (cert, key) = wonopcode_server::tls::self_signed(&dir, &hosts)?;
+        println!("TLS certificate: {} (self-signed)", cert.display());
+        println!("  Clients trust it with: --tls-ca {}", cert.display());
+        (cert, key)
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(wonopcode_server::tls::load_identity(&cert, &key)?))
+}
+
 /// Run in headless mode (server only, no TUI).
 ///
 /// This starts an HTTP server that exposes the agent via REST API and SSE,
@@ -859,7 +1209,7 @@ async fn run_headless(
     cli: &Cli,
 ) -> anyhow::Result<()> {
     use tokio::sync::mpsc;
-    use wonopcode_protocol::{Action, Update};
+    use wonopcode_protocol::Action;
     use wonopcode_server::{create_headless_router_with_options, HeadlessState};
 
     info!("Starting headless server on {}", address);
@@ -868,6 +1218,8 @@ async fn run_headless(
 
     // Create instance
     let instance = wonopcode_core::Instance::new(cwd).await?;
+    #[cfg(feature = "forge")]
+    crate::forge::use_for_pull_requests(&instance).await;
     let config_file = instance.config().await;
 
     // Determine provider and model
@@ -899,8 +1251,11 @@ async fn run_headless(
         .or_else(|| std::env::var("WONOPCODE_SECRET").ok())
         .or_else(|| config_file.server.as_ref().and_then(|s| s.api_key.clone()));
 
+    let tls = tls_identity(cli, address)?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+
     // Build MCP HTTP URL for headless mode
-    let mcp_sse_url = format!("http://{address}/mcp/sse");
+    let mcp_sse_url = format!("{scheme}://{address}/mcp/sse");
 
     // Create runner config with MCP HTTP transport
     let allow_all_in_sandbox = config_file
@@ -935,6 +1290,13 @@ async fn run_headless(
         wonopcode_core::permission::PermissionManager::new(shared_bus.clone()),
     );
 
+    // Record events for `wonopcode replay`
+    let recorder = match &cli.record_events {
+        Some(path) => Some(commands::start_recording(path, &[&shared_bus, instance.bus()]).await?),
+        None => None,
+    };
+    let record_bus = recorder.as_ref().map(|_| shared_bus.clone());
+
     // Initialize permission rules
     for rule in wonopcode_core::permission::PermissionManager::default_rules() {
         shared_permission_manager.add_rule(rule).await;
@@ -948,15 +1310,66 @@ async fn run_headless(
 
     // Create channels for action/update communication
     let (protocol_action_tx, mut protocol_action_rx) = mpsc::unbounded_channel::<Action>();
-    let (app_action_tx, app_action_rx) = mpsc::unbounded_channel::<wonopcode_tui::AppAction>();
     let (app_update_tx, mut app_update_rx) = mpsc::unbounded_channel::<wonopcode_tui::AppUpdate>();
 
+    // Projects served besides the working directory, each with its own runner
+    let extra_projects = config_file
+        .server
+        .as_ref()
+        .and_then(|s| s.projects.clone())
+        .unwrap_or_default();
+    let mut project_pool = projects::ProjectPool::new(
+        cwd,
+        &extra_projects,
+        config.clone(),
+        format!("{provider}/{model_id}"),
+        app_update_tx.clone(),
+    );
+
     // Create shutdown channel for graceful server shutdown
     let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
 
-    // Create headless state with shutdown channel
-    let headless_state = HeadlessState::new(protocol_action_tx).with_shutdown_tx(shutdown_tx);
-    let update_broadcast = headless_state.update_tx.clone();
+    // Create headless state with shutdown channel and scoped sessions, each
+    // run by a runner of its own
+    let (session_tx, session_rx) = mpsc::unbounded_channel();
+    let server_config = config_file.server.clone().unwrap_or_default();
+    let limits = wonopcode_server::ServerLimits {
+        requests_per_minute: server_config
+            .rate_limit
+            .unwrap_or(wonopcode_server::limit::DEFAULT_REQUESTS_PER_MINUTE),
+        max_body_bytes: server_config
+            .max_body_bytes
+            .unwrap_or(wonopcode_server::limit::DEFAULT_MAX_BODY_BYTES),
+    };
+    // Jobs are kept with the project's data, so their results outlive the
+    // server
+    let project_id = instance.project_id().await;
+    let jobs_dir = wonopcode_util::path::data_dir().map(|dir| dir.join("jobs").join(project_id));
+    let jobs = match jobs_dir.map(wonopcode_server::JobStore::open) {
+        Some(Ok(jobs)) => jobs,
+        Some(Err(e)) => {
+            warn!(error = %e, "Failed to open the job store, keeping jobs in memory");
+            wonopcode_server::JobStore::in_memory()
+        }
+        None => wonopcode_server::JobStore::in_memory(),
+    };
+    let headless_state = HeadlessState::new(protocol_action_tx)
+        .with_shutdown_tx(shutdown_tx)
+        .with_session_host(session_tx)
+        .with_limits(limits)
+        .with_file_writes(server_config.allow_file_writes.unwrap_or(false))
+        .with_collaboration(server_config.collaborate.unwrap_or(false))
+        .with_webhooks(wonopcode_server::Webhooks::new(
+            server_config.webhooks.clone().unwrap_or_default(),
+        ))
+        .with_jobs(jobs);
+    let _sessions_handle = sessions::spawn_host(
+        instance.clone(),
+        config.clone(),
+        headless_state.clone(),
+        session_rx,
+    );
+    let update_broadcast = headless_state.clone();
     let state_handle = headless_state.current_state.clone();
     let _shutdown_flag = headless_state.shutdown.clone();
 
@@ -964,6 +1377,11 @@ async fn run_headless(
     {
         let mut state = state_handle.write().await;
         state.project = cwd.display().to_string();
+        state.projects = project_pool
+            .list()
+            .into_iter()
+            .map(projects::project_info)
+            .collect();
         state.model = format!("{provider}/{model_id}");
         state.project_id = cli.project_id.clone();
         state.work_id = cli.work_id.clone();
@@ -1171,15 +1589,31 @@ async fn run_headless(
     };
 
     // Spawn runner
-    let runner_handle = tokio::spawn(async move {
-        runner.run(app_action_rx, app_update_tx).await;
-    });
+    let runner_handle = project_pool.attach_primary(instance.clone(), runner);
+
+    // Run scheduled tasks, each in its own runner
+    let scheduler_handle = schedule::spawn(instance.clone(), format!("{provider}/{model_id}"));
 
     // Spawn task to convert protocol actions to app actions
     let state_for_actions = state_handle.clone();
     tokio::spawn(async move {
         while let Some(action) = protocol_action_rx.recv().await {
             let app_action = match action {
+                Action::ListProjects => {
+                    let _ =
+                        app_update_tx.send(wonopcode_tui::AppUpdate::Projects(project_pool.list()));
+                    continue;
+                }
+                Action::SwitchProject { project } => {
+                    projects::switch_project(
+                        &mut project_pool,
+                        &project,
+                        &state_for_actions,
+                        &app_update_tx,
+                    )
+                    .await;
+                    continue;
+                }
                 Action::SendPrompt { prompt } => {
                     // Add user message to session state
                     {
@@ -1202,81 +1636,13 @@ async fn run_headless(
                     }
                     wonopcode_tui::AppAction::SendPrompt(prompt)
                 }
-                Action::Cancel => wonopcode_tui::AppAction::Cancel,
-                Action::Quit => {
-                    // In headless mode, Quit from TUI just means client disconnected
-                    // Don't kill the runner - it should keep serving other clients
-                    info!("Client requested quit - ignoring in headless mode");
-                    continue;
-                }
-                Action::ChangeModel { model } => wonopcode_tui::AppAction::ChangeModel(model),
-                Action::ChangeAgent { agent } => wonopcode_tui::AppAction::ChangeAgent(agent),
-                Action::NewSession => wonopcode_tui::AppAction::NewSession,
-                Action::SwitchSession { session_id } => {
-                    wonopcode_tui::AppAction::SwitchSession(session_id)
-                }
-                Action::RenameSession { title } => {
-                    wonopcode_tui::AppAction::RenameSession { title }
-                }
-                Action::ForkSession { message_id } => {
-                    wonopcode_tui::AppAction::ForkSession { message_id }
-                }
-                Action::Undo => wonopcode_tui::AppAction::Undo,
-                Action::Redo => wonopcode_tui::AppAction::Redo,
-                Action::Revert { message_id } => wonopcode_tui::AppAction::Revert { message_id },
-                Action::Unrevert => wonopcode_tui::AppAction::Unrevert,
-                Action::Compact => wonopcode_tui::AppAction::Compact,
-                Action::SandboxStart => wonopcode_tui::AppAction::SandboxStart,
-                Action::SandboxStop => wonopcode_tui::AppAction::SandboxStop,
-                Action::SandboxRestart => wonopcode_tui::AppAction::SandboxRestart,
-                Action::McpToggle { name } => wonopcode_tui::AppAction::McpToggle { name },
-                Action::McpReconnect { name } => wonopcode_tui::AppAction::McpReconnect { name },
-                Action::ShareSession => wonopcode_tui::AppAction::ShareSession,
-                Action::UnshareSession => wonopcode_tui::AppAction::UnshareSession,
-                Action::GotoMessage { message_id } => {
-                    wonopcode_tui::AppAction::GotoMessage { message_id }
-                }
-                Action::SaveSettings { scope, config } => {
-                    // Convert protocol scope to app scope
-                    let app_scope = match scope {
-                        wonopcode_protocol::SaveScope::Project => wonopcode_tui::SaveScope::Project,
-                        wonopcode_protocol::SaveScope::Global => wonopcode_tui::SaveScope::Global,
-                    };
-                    // Try to deserialize config
-                    if let Ok(parsed_config) =
-                        serde_json::from_value::<wonopcode_core::config::Config>(config)
-                    {
-                        wonopcode_tui::AppAction::SaveSettings {
-                            scope: app_scope,
-                            config: Box::new(parsed_config),
-                        }
-                    } else {
-                        continue;
-                    }
-                }
-                Action::PermissionResponse {
-                    request_id,
-                    allow,
-                    remember,
-                } => wonopcode_tui::AppAction::PermissionResponse {
-                    request_id,
-                    allow,
-                    remember,
-                },
-                Action::UpdateTestProviderSettings {
-                    emulate_thinking,
-                    emulate_tool_calls,
-                    emulate_tool_observed,
-                    emulate_streaming,
-                } => wonopcode_tui::AppAction::UpdateTestProviderSettings {
-                    emulate_thinking,
-                    emulate_tool_calls,
-                    emulate_tool_observed,
-                    emulate_streaming,
+                action => match protocol_action_to_app(action) {
+                    Some(app_action) => app_action,
+                    None => continue,
                 },
             };
 
-            if app_action_tx.send(app_action).is_err() {
+            if !project_pool.send(app_action) {
                 break;
             }
         }
@@ -1286,6 +1652,10 @@ async fn run_headless(
     let state_for_updates = state_handle.clone();
     tokio::spawn(async move {
         while let Some(update) = app_update_rx.recv().await {
+            if let Some(bus) = &record_bus {
+                commands::publish_update(bus, update.clone()).await;
+            }
+
             // Update the current state based on the update type
             match &update {
                 wonopcode_tui::AppUpdate::Started => {
@@ -1495,154 +1865,16 @@ async fn run_headless(
                 _ => {}
             }
 
-            // Convert to protocol update
-            let protocol_update = match update {
-                wonopcode_tui::AppUpdate::Started => Update::Started,
-                wonopcode_tui::AppUpdate::TextDelta(delta) => Update::TextDelta { delta },
-                wonopcode_tui::AppUpdate::ToolStarted { name, id, input } => {
-                    Update::ToolStarted { id, name, input }
-                }
-                wonopcode_tui::AppUpdate::ToolCompleted {
-                    id,
-                    success,
-                    output,
-                    metadata,
-                } => Update::ToolCompleted {
-                    id,
-                    success,
-                    output,
-                    metadata,
-                },
-                wonopcode_tui::AppUpdate::Completed { text } => Update::Completed { text },
-                wonopcode_tui::AppUpdate::Error(error) => Update::Error { error },
-                wonopcode_tui::AppUpdate::Status(message) => Update::Status { message },
-                wonopcode_tui::AppUpdate::TokenUsage {
-                    input,
-                    output,
-                    cost,
-                    context_limit,
-                } => Update::TokenUsage {
-                    input,
-                    output,
-                    cost,
-                    context_limit,
-                },
-                wonopcode_tui::AppUpdate::ModelInfo { context_limit } => {
-                    Update::ModelInfo { context_limit }
-                }
-                wonopcode_tui::AppUpdate::Sessions(sessions) => Update::Sessions {
-                    sessions: sessions
-                        .into_iter()
-                        .map(|(id, title, timestamp)| wonopcode_protocol::SessionInfo {
-                            id,
-                            title,
-                            timestamp,
-                        })
-                        .collect(),
-                },
-                wonopcode_tui::AppUpdate::TodosUpdated { phases, todos } => Update::TodosUpdated {
-                    phases: phases
-                        .into_iter()
-                        .map(|p| wonopcode_protocol::PhaseInfo {
-                            id: p.id,
-                            name: p.name,
-                            status: p.status,
-                            todos: p
-                                .todos
-                                .into_iter()
-                                .map(|t| wonopcode_protocol::TodoInfo {
-                                    id: t.id,
-                                    content: t.content,
-                                    status: t.status,
-                                    priority: t.priority,
-                                    phase_id: t.phase_id,
-                                })
-                                .collect(),
-                        })
-                        .collect(),
-                    todos: todos
-                        .into_iter()
-                        .map(|t| wonopcode_protocol::TodoInfo {
-                            id: t.id,
-                            content: t.content,
-                            status: t.status,
-                            priority: t.priority,
-                            phase_id: t.phase_id,
-                        })
-                        .collect(),
-                },
-                wonopcode_tui::AppUpdate::LspUpdated(servers) => Update::LspUpdated {
-                    servers: servers
-                        .into_iter()
-                        .map(|s| wonopcode_protocol::LspInfo {
-                            id: s.id,
-                            name: s.name,
-                            root: s.root,
-                            connected: s.connected,
-                        })
-                        .collect(),
-                },
-                wonopcode_tui::AppUpdate::McpUpdated(servers) => Update::McpUpdated {
-                    servers: servers
-                        .into_iter()
-                        .map(|s| wonopcode_protocol::McpInfo {
-                            name: s.name,
-                            connected: s.connected,
-                            error: s.error,
-                        })
-                        .collect(),
-                },
-                wonopcode_tui::AppUpdate::ModifiedFilesUpdated(files) => {
-                    Update::ModifiedFilesUpdated {
-                        files: files
-                            .into_iter()
-                            .map(|f| wonopcode_protocol::ModifiedFileInfo {
-                                path: f.path,
-                                added: f.added,
-                                removed: f.removed,
-                            })
-                            .collect(),
-                    }
-                }
-                wonopcode_tui::AppUpdate::PermissionsPending(count) => {
-                    Update::PermissionsPending { count }
-                }
-                wonopcode_tui::AppUpdate::SandboxUpdated(status) => Update::SandboxUpdated {
-                    state: status.state,
-                    runtime_type: status.runtime_type,
-                    error: status.error,
-                },
-                wonopcode_tui::AppUpdate::SystemMessage(message) => {
-                    Update::SystemMessage { message }
-                }
-                wonopcode_tui::AppUpdate::AgentChanged(agent) => Update::AgentChanged { agent },
-                wonopcode_tui::AppUpdate::PermissionRequest(req) => Update::PermissionRequest {
-                    id: req.id,
-                    tool: req.tool,
-                    action: req.action,
-                    description: req.description,
-                    path: req.path,
-                },
-                wonopcode_tui::AppUpdate::SessionLoaded { .. } => {
-                    // SessionLoaded is only used by the TUI when connecting to a server,
-                    // it doesn't need to be broadcast from the headless server
-                    continue;
-                }
-                // Git updates are handled via dedicated HTTP endpoints, not SSE
-                wonopcode_tui::AppUpdate::GitStatusUpdated(_)
-                | wonopcode_tui::AppUpdate::GitHistoryUpdated(_)
-                | wonopcode_tui::AppUpdate::GitOperationResult { .. } => {
-                    continue;
-                }
+            let Some(protocol_update) = app_update_to_protocol(update) else {
+                continue;
             };
-
-            let _ = update_broadcast.send(protocol_update);
+            update_broadcast.send_update(protocol_update);
         }
     });
 
     // Create MCP HTTP state for tool serving with shared permission manager.
     // This ensures MCP tools use the same sandbox state as the Runner.
-    let mcp_message_url = format!("http://{address}/mcp/message");
+    let mcp_message_url = format!("{scheme}://{address}/mcp/message");
     let mcp_state = create_mcp_http_state(cwd, &mcp_message_url, Some(shared_permission_manager))
         .await
         .ok();
@@ -1655,12 +1887,18 @@ async fn run_headless(
 
     // Start server
     let listener = tokio::net::TcpListener::bind(address).await?;
-    println!("Server running on http://{address}");
+    println!("Server running on {scheme}://{address}");
     if has_auth {
         println!("API key authentication: enabled");
     }
+    if limits.requests_per_minute > 0 {
+        println!(
+            "Rate limit: {} requests per minute per client",
+            limits.requests_per_minute
+        );
+    }
     if has_mcp {
-        println!("MCP endpoint: http://{address}/mcp/sse");
+        println!("MCP endpoint: {scheme}://{address}/mcp/sse");
     }
 
     // Start mDNS advertisement if enabled
@@ -1670,13 +1908,7 @@ async fn run_headless(
 
         match Advertiser::new() {
             Ok(mut advertiser) => {
-                // Determine the display name
-                let name = cli.name.clone().unwrap_or_else(|| {
-                    hostname::get()
-                        .ok()
-                        .and_then(|h| h.into_string().ok())
-                        .unwrap_or_else(|| "wonopcode".to_string())
-                });
+                let name = server_name(cli);
 
                 // Build advertise config with metadata
                 let mut config =
@@ -1693,6 +1925,12 @@ async fn run_headless(
                 match advertiser.advertise(config) {
                     Ok(_) => {
                         println!("mDNS: advertising as '{name}'");
+                        if address.ip().is_loopback() {
+                            println!(
+                            "mDNS: only reachable from this machine; use --address [::]:{} to listen on every interface",
+                            address.port()
+                        );
+                        }
                         Some(advertiser)
                     }
                     Err(e) => {
@@ -1710,81 +1948,649 @@ async fn run_headless(
         None
     };
 
+    // Register with the rendezvous relay, for clients beyond the local network
+    let registration = match rendezvous_client(cli)? {
+        Some(client) => {
+            let addresses = public_addresses(cli, address);
+            if addresses.is_empty() {
+                println!(
+                    "Rendezvous: not registering, as the server is only reachable from this machine; use --public-address or --address [::]:{}",
+                    address.port()
+                );
+                None
+            } else {
+                let registration = wonopcode_server::Registration {
+                    name: server_name(cli),
+                    addresses,
+                    version: Some(env!("CARGO_PKG_VERSION").to_string()),
+                    model: Some(model_id.clone()),
+                    project: cwd.file_name().and_then(|n| n.to_str()).map(str::to_string),
+                    auth_required: secret.is_some(),
+                    last_seen: None,
+                };
+                println!(
+                    "Rendezvous: registering as '{}' at {} with {}",
+                    registration.name,
+                    registration.addresses.join(", "),
+                    client.url()
+                );
+                let name = registration.name.clone();
+                let task = client.keep_registered(registration);
+                Some((client, name, task))
+            }
+        }
+        None => None,
+    };
+
     println!("Press Ctrl+C to stop");
 
     // Run server until shutdown (graceful shutdown on channel signal or Ctrl+C)
-    axum::serve(listener, app)
-        .with_graceful_shutdown(async move {
-            // Wait for either shutdown signal or Ctrl+C
-            tokio::select! {
-                _ = shutdown_rx.recv() => {
-                    info!("Shutdown signal received");
-                }
-                _ = tokio::signal::ctrl_c() => {
-                    info!("Ctrl+C received");
-                }
+    let shutdown = async move {
+        // Wait for either shutdown signal or Ctrl+C
+        tokio::select! {
+            _ = shutdown_rx.recv() => {
+                info!("Shutdown signal received");
             }
-        })
-        .await?;
+            _ = tokio::signal::ctrl_c() => {
+                info!("Ctrl+C received");
+            }
+        }
+    };
+    // Client addresses are needed to rate limit clients without the API key
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    match tls {
+        Some(identity) => {
+            use axum::serve::ListenerExt;
+            // Tapped, as axum only gives a TcpListener's connections their
+            // address otherwise
+            let listener =
+                wonopcode_server::tls::TlsListener::new(listener, identity)?.tap_io(|_| {});
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown)
+                .await?
+        }
+        None => {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown)
+                .await?
+        }
+    }
+
+    scheduler_handle.abort();
 
     // Wait for runner to complete
     let _ = tokio::time::timeout(std::time::Duration::from_secs(5), runner_handle).await;
 
+    if let Some((client, name, task)) = registration {
+        task.abort();
+        if let Err(e) = client.unregister(&name).await {
+            warn!(error = %e, "Failed to unregister from the rendezvous relay");
+        }
+    }
+
     // Advertiser will be dropped here, stopping the mDNS advertisement
 
+    if let Some(recorder) = recorder {
+        recorder.finish().await;
+    }
     instance.dispose().await;
     Ok(())
 }
 
-/// Discover and connect to a server on the local network via mDNS.
-#[cfg(feature = "discover")]
-async fn run_discover(cli: &Cli) -> anyhow::Result<()> {
-    use std::io::Write;
-    use std::time::Duration;
-    use wonopcode_discover::{Browser, ServerInfo};
-
-    println!("Discovering wonopcode servers on the local network...\n");
+/// Convert the decisions for a reviewed file from a remote client.
+fn reviewed_file_from_protocol(
+    file: wonopcode_protocol::ReviewedFileInfo,
+) -> wonopcode_tui::ReviewedFile {
+    use wonopcode_protocol::HunkDecisionInfo;
+    use wonopcode_tui::HunkDecision;
+
+    wonopcode_tui::ReviewedFile {
+        path: file.path,
+        decisions: file
+            .decisions
+            .into_iter()
+            .map(|decision| {
+                decision.map(|d| match d {
+                    HunkDecisionInfo::Accept => HunkDecision::Accept,
+                    HunkDecisionInfo::Reject => HunkDecision::Reject,
+                    HunkDecisionInfo::Edit { text } => HunkDecision::Edit(text),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Convert a remote client's action to the action sent to a runner.
+///
+/// Returns `None` for actions that don't go to a runner.
+fn protocol_action_to_app(action: wonopcode_protocol::Action) -> Option<wonopcode_tui::AppAction> {
+    use wonopcode_protocol::Action;
+
+    Some(match action {
+        Action::SendPrompt { prompt } => wonopcode_tui::AppAction::SendPrompt(prompt),
+        Action::AttachImage { media_type, data } => {
+            wonopcode_tui::AppAction::AttachImage { media_type, data }
+        }
+        Action::AttachContext {
+            path,
+            symbol,
+            lines,
+        } => wonopcode_tui::AppAction::AttachContext {
+            path,
+            symbol,
+            lines,
+        },
+        Action::SearchSymbols { query } => wonopcode_tui::AppAction::SearchSymbols { query },
+        Action::Cancel => wonopcode_tui::AppAction::Cancel,
+        Action::Quit => {
+            // In headless mode, Quit from TUI just means client disconnected
+            // Don't kill the runner - it should keep serving other clients
+            info!("Client requested quit - ignoring in headless mode");
+            return None;
+        }
+        Action::ChangeModel { model } => wonopcode_tui::AppAction::ChangeModel(model),
+        Action::ChangeAgent { agent } => wonopcode_tui::AppAction::ChangeAgent(agent),
+        Action::NewSession => wonopcode_tui::AppAction::NewSession,
+        Action::SwitchSession { session_id } => wonopcode_tui::AppAction::SwitchSession(session_id),
+        Action::RenameSession { title } => wonopcode_tui::AppAction::RenameSession { title },
+        Action::ForkSession { message_id } => wonopcode_tui::AppAction::ForkSession { message_id },
+        Action::Undo => wonopcode_tui::AppAction::Undo,
+        Action::Redo => wonopcode_tui::AppAction::Redo,
+        Action::Revert { message_id } => wonopcode_tui::AppAction::Revert { message_id },
+        Action::Unrevert => wonopcode_tui::AppAction::Unrevert,
+        Action::Compact => wonopcode_tui::AppAction::Compact,
+        Action::SandboxStart => wonopcode_tui::AppAction::SandboxStart,
+        Action::SandboxStop => wonopcode_tui::AppAction::SandboxStop,
+        Action::SandboxRestart => wonopcode_tui::AppAction::SandboxRestart,
+        Action::SwitchProfile { profile } => {
+            wonopcode_tui::AppAction::SwitchProfile { name: profile }
+        }
+        Action::SearchSessions { query } => wonopcode_tui::AppAction::SearchSessions { query },
+        Action::Orchestrate { tasks } => wonopcode_tui::AppAction::Orchestrate { tasks },
+        Action::MergeOrchestration => wonopcode_tui::AppAction::MergeOrchestration,
+        Action::CancelOrchestration => wonopcode_tui::AppAction::CancelOrchestration,
+        Action::CreateWorktree { name } => wonopcode_tui::AppAction::CreateWorktree { name },
+        Action::ListWorktrees => wonopcode_tui::AppAction::ListWorktrees,
+        Action::FinishWorktree { action } => wonopcode_tui::AppAction::FinishWorktree { action },
+        Action::ApplyTemplate { name } => wonopcode_tui::AppAction::ApplyTemplate { name },
+        Action::SaveTemplate { name } => wonopcode_tui::AppAction::SaveTemplate { name },
+        Action::LoadMemory => wonopcode_tui::AppAction::LoadMemory,
+        Action::LoadPermissionAudit => wonopcode_tui::AppAction::LoadPermissionAudit,
+        Action::LoadSpend => wonopcode_tui::AppAction::LoadSpend,
+        Action::AcknowledgeSpendLimit => wonopcode_tui::AppAction::AcknowledgeSpendLimit,
+        Action::LoadUsage { days } => wonopcode_tui::AppAction::LoadUsage { days },
+        Action::StartReview => wonopcode_tui::AppAction::StartReview,
+        Action::StopProcess { pid } => wonopcode_tui::AppAction::StopProcess { pid },
+        Action::CancelTool { id } => wonopcode_tui::AppAction::CancelTool { id },
+        Action::ApplyReview { files } => wonopcode_tui::AppAction::ApplyReview {
+            files: files.into_iter().map(reviewed_file_from_protocol).collect(),
+        },
+        Action::SaveMemory { content } => wonopcode_tui::AppAction::SaveMemory { content },
+        Action::McpToggle { name } => wonopcode_tui::AppAction::McpToggle { name },
+        Action::McpReconnect { name } => wonopcode_tui::AppAction::McpReconnect { name },
+        Action::MergeSession => wonopcode_tui::AppAction::MergeSession,
+        Action::ShareSession { expires, password } => {
+            wonopcode_tui::AppAction::ShareSession { expires, password }
+        }
+        Action::UnshareSession => wonopcode_tui::AppAction::UnshareSession,
+        Action::GotoMessage { message_id } => wonopcode_tui::AppAction::GotoMessage { message_id },
+        Action::SaveSettings { scope, config } => {
+            // Convert protocol scope to app scope
+            let app_scope = match scope {
+                wonopcode_protocol::SaveScope::Project => wonopcode_tui::SaveScope::Project,
+                wonopcode_protocol::SaveScope::Global => wonopcode_tui::SaveScope::Global,
+            };
+            // Try to deserialize config
+            if let Ok(parsed_config) =
+                serde_json::from_value::<wonopcode_core::config::Config>(config)
+            {
+                wonopcode_tui::AppAction::SaveSettings {
+                    scope: app_scope,
+                    config: Box::new(parsed_config),
+                }
+            } else {
+                return None;
+            }
+        }
+        Action::PermissionResponse {
+            request_id,
+            allow,
+            remember,
+        } => wonopcode_tui::AppAction::PermissionResponse {
+            request_id,
+            allow,
+            remember,
+        },
+        Action::UpdateTestProviderSettings {
+            emulate_thinking,
+            emulate_tool_calls,
+            emulate_tool_observed,
+            emulate_streaming,
+        } => wonopcode_tui::AppAction::UpdateTestProviderSettings {
+            emulate_thinking,
+            emulate_tool_calls,
+            emulate_tool_observed,
+            emulate_streaming,
+        },
+        // Handled by the server, which knows all projects
+        Action::ListProjects | Action::SwitchProject { .. } => return None,
+    })
+}
+
+/// Convert a runner update to the update sent to remote clients.
+///
+/// Returns `None` for updates that remote clients don't receive.
+fn app_update_to_protocol(update: wonopcode_tui::AppUpdate) -> Option<wonopcode_protocol::Update> {
+    use wonopcode_protocol::Update;
+
+    let update = match update {
+        wonopcode_tui::AppUpdate::Started => Update::Started,
+        wonopcode_tui::AppUpdate::TextDelta(delta) => Update::TextDelta { delta },
+        wonopcode_tui::AppUpdate::ToolStarted { name, id, input } => {
+            Update::ToolStarted { id, name, input }
+        }
+        wonopcode_tui::AppUpdate::ToolProgress {
+            id,
+            fraction,
+            message,
+        } => Update::ToolProgress {
+            id,
+            fraction,
+            message,
+        },
+        wonopcode_tui::AppUpdate::ToolOutput { id, text } => Update::ToolOutput { id, text },
+        wonopcode_tui::AppUpdate::ToolCompleted {
+            id,
+            success,
+            output,
+            metadata,
+        } => Update::ToolCompleted {
+            id,
+            success,
+            output,
+            metadata,
+        },
+        wonopcode_tui::AppUpdate::Completed { text } => Update::Completed { text },
+        wonopcode_tui::AppUpdate::Error(error) => Update::Error { error },
+        wonopcode_tui::AppUpdate::Status(message) => Update::Status { message },
+        wonopcode_tui::AppUpdate::TokenUsage {
+            input,
+            output,
+            cost,
+            context_limit,
+        } => Update::TokenUsage {
+            input,
+            output,
+            cost,
+            context_limit,
+        },
+        wonopcode_tui::AppUpdate::ModelInfo { context_limit } => {
+            Update::ModelInfo { context_limit }
+        }
+        wonopcode_tui::AppUpdate::Sessions(sessions) => Update::Sessions {
+            sessions: sessions
+                .into_iter()
+                .map(|(id, title, timestamp)| wonopcode_protocol::SessionInfo {
+                    id,
+                    title,
+                    timestamp,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::TodosUpdated { phases, todos } => Update::TodosUpdated {
+            phases: phases
+                .into_iter()
+                .map(|p| wonopcode_protocol::PhaseInfo {
+                    id: p.id,
+                    name: p.name,
+                    status: p.status,
+                    todos: p
+                        .todos
+                        .into_iter()
+                        .map(|t| wonopcode_protocol::TodoInfo {
+                            id: t.id,
+                            content: t.content,
+                            status: t.status,
+                            priority: t.priority,
+                            phase_id: t.phase_id,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            todos: todos
+                .into_iter()
+                .map(|t| wonopcode_protocol::TodoInfo {
+                    id: t.id,
+                    content: t.content,
+                    status: t.status,
+                    priority: t.priority,
+                    phase_id: t.phase_id,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::Symbols { query, symbols } => Update::Symbols {
+            query,
+            symbols: symbols
+                .into_iter()
+                .map(|s| wonopcode_protocol::SymbolInfo {
+                    name: s.name,
+                    kind: s.kind,
+                    path: s.path,
+                    start_line: s.start_line,
+                    end_line: s.end_line,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::LspUpdated(servers) => Update::LspUpdated {
+            servers: servers
+                .into_iter()
+                .map(|s| wonopcode_protocol::LspInfo {
+                    id: s.id,
+                    name: s.name,
+                    root: s.root,
+                    connected: s.connected,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::McpUpdated(servers) => Update::McpUpdated {
+            servers: servers
+                .into_iter()
+                .map(|s| wonopcode_protocol::McpInfo {
+                    name: s.name,
+                    connected: s.connected,
+                    error: s.error,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::ModifiedFilesUpdated(files) => Update::ModifiedFilesUpdated {
+            files: files
+                .into_iter()
+                .map(|f| wonopcode_protocol::ModifiedFileInfo {
+                    path: f.path,
+                    added: f.added,
+                    removed: f.removed,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::PermissionsPending(count) => Update::PermissionsPending { count },
+        wonopcode_tui::AppUpdate::SandboxUpdated(status) => Update::SandboxUpdated {
+            state: status.state,
+            runtime_type: status.runtime_type,
+            error: status.error,
+        },
+        wonopcode_tui::AppUpdate::SandboxResources(usage) => Update::SandboxResources {
+            cpu_percent: usage.cpu_percent,
+            memory_bytes: usage.memory_bytes,
+            memory_limit: usage.memory_limit,
+            pids: usage.pids,
+        },
+        wonopcode_tui::AppUpdate::SystemMessage(message) => Update::SystemMessage { message },
+        wonopcode_tui::AppUpdate::AgentChanged(agent) => Update::AgentChanged { agent },
+        wonopcode_tui::AppUpdate::Memory(content) => Update::Memory { content },
+        wonopcode_tui::AppUpdate::Orchestration { id, agents } => Update::Orchestration {
+            orchestration_id: id,
+            agents: agents
+                .into_iter()
+                .map(|a| wonopcode_protocol::OrchestrationAgent {
+                    name: a.name,
+                    task: a.task,
+                    state: a.state,
+                    branch: a.branch,
+                    steps: a.steps,
+                    tool_calls: a.tool_calls,
+                    changed_files: a.changed_files,
+                    summary: a.summary,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::SessionSearchResults { query, results } => {
+            Update::SessionSearchResults {
+                query,
+                results: results
+                    .into_iter()
+                    .map(
+                        |(id, title, snippet)| wonopcode_protocol::SessionSearchHit {
+                            id,
+                            title,
+                            snippet,
+                        },
+                    )
+                    .collect(),
+            }
+        }
+        wonopcode_tui::AppUpdate::Templates(templates) => Update::Templates {
+            templates: templates
+                .into_iter()
+                .map(|(name, description)| wonopcode_protocol::TemplateInfo { name, description })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::Projects(projects) => Update::Projects {
+            projects: projects.into_iter().map(projects::project_info).collect(),
+        },
+        wonopcode_tui::AppUpdate::ProjectSwitched { path, name } => {
+            Update::ProjectSwitched { path, name }
+        }
+        // Session tabs are local to the TUI
+        wonopcode_tui::AppUpdate::TabSwitched { .. }
+        | wonopcode_tui::AppUpdate::TabClosed { .. }
+        | wonopcode_tui::AppUpdate::TabActivity { .. } => return None,
+        wonopcode_tui::AppUpdate::Review(diff) => Update::Review { diff },
+        wonopcode_tui::AppUpdate::ProcessStarted { pid, command } => {
+            Update::ProcessStarted { pid, command }
+        }
+        wonopcode_tui::AppUpdate::ProcessOutput { pid, text } => {
+            Update::ProcessOutput { pid, text }
+        }
+        wonopcode_tui::AppUpdate::ProcessExited { pid, exit_code } => {
+            Update::ProcessExited { pid, exit_code }
+        }
+        wonopcode_tui::AppUpdate::PermissionAudit(entries) => Update::PermissionAudit {
+            entries: entries
+                .into_iter()
+                .map(|e| wonopcode_protocol::PermissionAuditInfo {
+                    time: e.time,
+                    tool: e.tool,
+                    action: e.action,
+                    path: e.path,
+                    description: e.description,
+                    allowed: e.allowed,
+                    prompted: e.prompted,
+                    source: e.source,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::Instructions(files) => Update::Instructions {
+            files: files
+                .into_iter()
+                .map(|f| wonopcode_protocol::InstructionFileInfo {
+                    path: f.path,
+                    tokens: f.tokens,
+                    included: f.included,
+                    note: f.note,
+                })
+                .collect(),
+        },
+        wonopcode_tui::AppUpdate::BudgetWarning {
+            limit,
+            used,
+            max,
+            message,
+        } => Update::BudgetWarning {
+            limit,
+            used,
+            max,
+            message,
+        },
+        wonopcode_tui::AppUpdate::ConfigReloaded {
+            theme,
+            applied,
+            restart_required,
+        } => Update::ConfigReloaded {
+            theme,
+            applied,
+            restart_required,
+        },
+        wonopcode_tui::AppUpdate::SpendAlert {
+            scope,
+            spent,
+            alert,
+            message,
+        } => Update::SpendAlert {
+            scope,
+            spent,
+            alert,
+            message,
+        },
+        wonopcode_tui::AppUpdate::Usage {
+            days,
+            daily,
+            models,
+            tools,
+        } => {
+            let amount = |u: wonopcode_tui::UsageAmount| wonopcode_protocol::UsageInfo {
+                name: u.name,
+                cost: u.cost,
+                input_tokens: u.input_tokens,
+                output_tokens: u.output_tokens,
+            };
+            Update::Usage {
+                days,
+                daily: daily.into_iter().map(amount).collect(),
+                models: models.into_iter().map(amount).collect(),
+                tools: tools
+                    .into_iter()
+                    .map(|t| wonopcode_protocol::ToolLatencyInfo {
+                        tool: t.tool,
+                        durations_ms: t.durations_ms,
+                    })
+                    .collect(),
+            }
+        }
+        wonopcode_tui::AppUpdate::PermissionRequest(req) => Update::PermissionRequest {
+            id: req.id,
+            tool: req.tool,
+            action: req.action,
+            description: req.description,
+            path: req.path,
+        },
+        wonopcode_tui::AppUpdate::SessionLoaded { .. } => {
+            // SessionLoaded is only used by the TUI when connecting to a server,
+            // it doesn't need to be broadcast from the headless server
+            return None;
+        }
+        // Git updates are handled via dedicated HTTP endpoints, not SSE
+        wonopcode_tui::AppUpdate::GitStatusUpdated(_)
+        | wonopcode_tui::AppUpdate::GitHistoryUpdated(_)
+        | wonopcode_tui::AppUpdate::GitOperationResult { .. }
+        | wonopcode_tui::AppUpdate::GitDiffUpdated { .. }
+        | wonopcode_tui::AppUpdate::GitCommitMessageGenerated(_) => return None,
+        // Project files are served by the `/files/*` endpoints
+        wonopcode_tui::AppUpdate::FilesListed { .. }
+        | wonopcode_tui::AppUpdate::FileRead { .. } => return None,
+        // Storage usage is local to the machine running the TUI
+        wonopcode_tui::AppUpdate::StorageUsage { .. } => return None,
+        // Custom commands are expanded by the runner before prompting
+        wonopcode_tui::AppUpdate::CustomCommands(_) => return None,
+        // Connection updates come from the TUI's own backend
+        wonopcode_tui::AppUpdate::Connection(_)
+        | wonopcode_tui::AppUpdate::Resync
+        | wonopcode_tui::AppUpdate::Presence(_)
+        | wonopcode_tui::AppUpdate::Prompted { .. } => return None,
+        // Only sent when replaying a recording
+        wonopcode_tui::AppUpdate::UserMessage(_) => return None,
+        // Discovery and connecting are local to each client
+        wonopcode_tui::AppUpdate::ServerFound(_)
+        | wonopcode_tui::AppUpdate::ServerLost(_)
+        | wonopcode_tui::AppUpdate::ServerDiscoveryFailed(_)
+        | wonopcode_tui::AppUpdate::ServerConnected { .. }
+        | wonopcode_tui::AppUpdate::ServerConnectFailed { .. } => return None,
+    };
+    Some(update)
+}
+
+/// Find servers on the local network and connect to the one the user picks.
+///
+/// The list updates live while the user chooses. If no server is up yet,
+/// or the chosen one is down, this waits for it to come (back) up.
+#[cfg(feature = "discover")]
+async fn run_discover(cli: &Cli) -> anyhow::Result<()> {
+    use std::io::Write;
+    use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
+    use std::time::{Duration, Instant};
+    use wonopcode_discover::Browser;
+
+    println!("Discovering wonopcode servers on the local network...\n");
 
     let browser =
         Browser::new().map_err(|e| anyhow::anyhow!("Failed to create mDNS browser: {e}"))?;
-    let servers = browser
-        .browse(Duration::from_secs(3))
+    let mut watcher = browser
+        .watch()
         .map_err(|e| anyhow::anyhow!("Failed to browse for servers: {e}"))?;
+    let stopped = || anyhow::anyhow!("Browsing for servers stopped");
 
-    if servers.is_empty() {
-        println!("No servers found.");
-        println!("\nMake sure a server is running with:");
-        println!("  wonopcode --headless --advertise");
-        return Ok(());
-    }
+    let mut servers = DiscoveredServers::default();
 
-    println!("Found {} server(s):\n", servers.len());
+    // Servers beyond the local network answer at once, if at all
+    for server in remote_servers(cli).await {
+        servers.apply(wonopcode_discover::DiscoveryEvent::Added(server));
+    }
 
-    for (i, server) in servers.iter().enumerate() {
-        println!("  {}. {}", i + 1, server.name);
-        println!("     Address: {}", server.address);
-        if let Some(ref project) = server.project {
-            println!("     Project: {project}");
-        }
-        if let Some(ref model) = server.model {
-            println!("     Model: {model}");
+    // Give servers a few seconds to answer
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match watcher.next_timeout(remaining) {
+            Ok(event) => servers.apply(event),
+            Err(RecvTimeoutError::Timeout) => break,
+            Err(RecvTimeoutError::Disconnected) => return Err(stopped()),
         }
-        if server.auth_required {
-            println!("     Auth: required");
+    }
+
+    if servers.up_count() == 0 {
+        println!("No servers found yet. Waiting for one to come up (Ctrl+C to stop)...");
+        println!("\nStart a server with:");
+        println!("  wonopcode --headless --advertise\n");
+        while servers.up_count() == 0 {
+            servers.apply(watcher.next().ok_or_else(stopped)?);
         }
-        println!();
     }
 
     // Select a server
-    let selected: &ServerInfo = if servers.len() == 1 {
+    let index = if servers.up_count() == 1 && servers.len() == 1 {
         println!("Connecting to the only available server...\n");
-        &servers[0]
+        0
     } else {
-        print!("Select server (1-{}): ", servers.len());
-        std::io::stdout().flush()?;
-
-        let mut input = String::new();
-        std::io::stdin().read_line(&mut input)?;
+        let prompt = |count: usize| -> std::io::Result<()> {
+            print!("Select server (1-{count}): ");
+            std::io::stdout().flush()
+        };
+        prompt(servers.len())?;
+
+        // Read the choice on its own thread so the list keeps updating
+        let (input_tx, input_rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let mut input = String::new();
+            if std::io::stdin().read_line(&mut input).is_ok() {
+                let _ = input_tx.send(input);
+            }
+        });
+        let input = loop {
+            match input_rx.try_recv() {
+                Ok(input) => break input,
+                Err(TryRecvError::Disconnected) => {
+                    return Err(anyhow::anyhow!("No selection made"));
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+            match watcher.next_timeout(Duration::from_millis(100)) {
+                Ok(event) => {
+                    println!();
+                    servers.apply(event);
+                    prompt(servers.len())?;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Err(stopped()),
+            }
+        };
 
         let idx: usize = input
             .trim()
@@ -1795,9 +2601,31 @@ async fn run_discover(cli: &Cli) -> anyhow::Result<()> {
             return Err(anyhow::anyhow!("Selection out of range"));
         }
 
-        &servers[idx - 1]
+        idx - 1
     };
 
+    // A server that is down is often just restarting
+    if servers.get(index).is_none() {
+        println!(
+            "{} is down. Waiting for it to come back up (Ctrl+C to stop)...\n",
+            servers.name(index)
+        );
+        while servers.get(index).is_none() {
+            servers.apply(watcher.next().ok_or_else(stopped)?);
+        }
+    }
+    let mut selected = servers.get(index).cloned().ok_or_else(stopped)?;
+    drop(watcher);
+
+    // A server is often found at addresses it can't be reached at, e.g. on
+    // IPv6 or over a VPN
+    if !selected.prefer_reachable(Duration::from_secs(2)) {
+        println!(
+            "Warning: {} did not answer on any of its addresses; trying {}\n",
+            selected.name, selected.address
+        );
+    }
+
     // Check if auth is required but no secret provided
     if selected.auth_required && cli.secret.is_none() {
         println!("Warning: Server requires authentication. Use --secret to provide credentials.\n");
@@ -1807,335 +2635,234 @@ async fn run_discover(cli: &Cli) -> anyhow::Result<()> {
     run_connect(&selected.address.to_string(), cli).await
 }
 
-/// Connect to a remote headless server.
-#[allow(clippy::cognitive_complexity)]
-async fn run_connect(address: &str, cli: &Cli) -> anyhow::Result<()> {
-    use wonopcode_tui::{App, Backend, RemoteBackend, SandboxStatusUpdate};
-
-    // Parse address
-    let address = if address.starts_with(':') {
-        format!("127.0.0.1{address}")
-    } else {
-        address.to_string()
-    };
-
-    println!("Connecting to {address}...");
-
-    // Get secret for authentication
-    // Priority: CLI arg > environment variable
-    let secret = cli
-        .secret
-        .clone()
-        .or_else(|| std::env::var("WONOPCODE_SECRET").ok());
-
-    // Create remote backend with optional secret
-    let backend = RemoteBackend::with_api_key(&address, secret)?;
-
-    // Check connection
-    backend.connect().await?;
-
-    println!("Connected!");
-
-    // Get initial state
-    let state = backend.get_state().await?;
-
-    // Create TUI app
-    let mut app = App::new();
-
-    // Apply initial state from server
-    app.set_project(state.project);
-    app.set_model(state.model);
-    app.set_agent(state.agent);
-
-    // Apply sandbox state
-    let sandbox_update = SandboxStatusUpdate {
-        state: state.sandbox.state,
-        runtime_type: state.sandbox.runtime_type,
-        error: state.sandbox.error,
-    };
-    // Send as update so it's processed correctly
-    let update_tx = app.update_sender();
-    if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::SandboxUpdated(sandbox_update)) {
-        warn!("Failed to send sandbox update: {}", e);
+/// Servers found beyond the local network: those registered with the
+/// rendezvous relay, and with `--tailscale` those on the tailnet.
+#[cfg(feature = "discover")]
+async fn remote_servers(cli: &Cli) -> Vec<wonopcode_discover::ServerInfo> {
+    let mut found = Vec::new();
+
+    match rendezvous_client(cli) {
+        Ok(Some(client)) => {
+            println!("Asking the rendezvous relay at {}...\n", client.url());
+            match client.list().await {
+                Ok(registrations) => {
+                    for registration in registrations {
+                        if let Some(server) = registered_server(registration).await {
+                            found.push(server);
+                        }
+                    }
+                }
+                Err(e) => println!("Warning: {e}\n"),
+            }
+        }
+        Ok(None) => {}
+        Err(e) => println!("Warning: {e}\n"),
     }
 
-    // Apply todos (phases and flat list)
-    if !state.phases.is_empty() || !state.todos.is_empty() {
-        let phases: Vec<wonopcode_tui::PhaseUpdate> = state
-            .phases
-            .into_iter()
-            .map(|p| wonopcode_tui::PhaseUpdate {
-                id: p.id,
-                name: p.name,
-                status: p.status,
-                todos: p
-                    .todos
-                    .into_iter()
-                    .map(|t| wonopcode_tui::TodoUpdate {
-                        id: t.id,
-                        content: t.content,
-                        status: t.status,
-                        priority: t.priority,
-                        phase_id: t.phase_id,
-                    })
-                    .collect(),
-            })
-            .collect();
-        let todos: Vec<wonopcode_tui::TodoUpdate> = state
-            .todos
-            .into_iter()
-            .map(|t| wonopcode_tui::TodoUpdate {
-                id: t.id,
-                content: t.content,
-                status: t.status,
-                priority: t.priority,
-                phase_id: t.phase_id,
-            })
-            .collect();
-        if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::TodosUpdated { phases, todos }) {
-            warn!("Failed to send todos update: {}", e);
+    if let Some(port) = cli.tailscale {
+        println!("Looking for servers on your tailnet, on port {port}...\n");
+        let peers = tokio::task::spawn_blocking(move || {
+            wonopcode_discover::tailscale_servers(port, std::time::Duration::from_secs(1))
+        })
+        .await;
+        match peers {
+            Ok(Ok(servers)) => found.extend(servers),
+            Ok(Err(e)) => println!("Warning: {e}\n"),
+            Err(e) => warn!(error = %e, "Tailscale lookup failed"),
         }
     }
 
-    // Apply MCP servers
-    if !state.mcp_servers.is_empty() {
-        let servers: Vec<wonopcode_tui::McpStatusUpdate> = state
-            .mcp_servers
-            .into_iter()
-            .map(|s| wonopcode_tui::McpStatusUpdate {
-                name: s.name,
-                connected: s.connected,
-                error: s.error,
-            })
-            .collect();
-        if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::McpUpdated(servers)) {
-            warn!("Failed to send MCP update: {}", e);
+    found
+}
+
+/// A server registered with the relay, at the addresses its names resolve
+/// to; None if none do.
+#[cfg(feature = "discover")]
+async fn registered_server(
+    registration: wonopcode_server::Registration,
+) -> Option<wonopcode_discover::ServerInfo> {
+    let mut addresses = Vec::new();
+    for address in &registration.addresses {
+        match tokio::net::lookup_host(address.as_str()).await {
+            Ok(resolved) => addresses.extend(resolved),
+            Err(e) => warn!(%address, error = %e, "Failed to resolve a registered address"),
         }
     }
+    let Some(&first) = addresses.first() else {
+        println!(
+            "Warning: none of the addresses of {} resolve: {}\n",
+            registration.name,
+            registration.addresses.join(", ")
+        );
+        return None;
+    };
+    let mut server = wonopcode_discover::ServerInfo {
+        name: registration.name,
+        address: first,
+        addresses: vec![first],
+        hostname: None,
+        version: registration.version,
+        model: registration.model,
+        project: registration.project,
+        cwd: None,
+        auth_required: registration.auth_required,
+    };
+    server.merge_addresses(&addresses);
+    Some(server)
+}
 
-    // Apply LSP servers
-    if !state.lsp_servers.is_empty() {
-        let servers: Vec<wonopcode_tui::LspStatusUpdate> = state
-            .lsp_servers
-            .into_iter()
-            .map(|s| wonopcode_tui::LspStatusUpdate {
-                id: s.id,
-                name: s.name,
-                root: s.root,
-                connected: s.connected,
-            })
-            .collect();
-        if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::LspUpdated(servers)) {
-            warn!("Failed to send LSP update: {}", e);
+/// The servers found so far, numbered in the order they were first seen.
+///
+/// A server that goes away keeps its number, so the list doesn't shift
+/// while the user picks, and gets it back when it comes back up.
+#[cfg(feature = "discover")]
+#[derive(Default)]
+struct DiscoveredServers {
+    servers: Vec<(String, Option<wonopcode_discover::ServerInfo>)>,
+}
+
+#[cfg(feature = "discover")]
+impl DiscoveredServers {
+    /// Record a change and print it.
+    fn apply(&mut self, event: wonopcode_discover::DiscoveryEvent) {
+        use wonopcode_discover::DiscoveryEvent;
+
+        match event {
+            DiscoveryEvent::Added(server) => match self.position(&server.name) {
+                Some(i) => match &mut self.servers[i].1 {
+                    // Found another way as well, e.g. over mDNS and the tailnet
+                    Some(known) => {
+                        for address in server
+                            .addresses
+                            .iter()
+                            .filter(|a| !known.addresses.contains(a))
+                        {
+                            println!("  {}. {} is also at {address}\n", i + 1, server.name);
+                        }
+                        known.merge_addresses(&server.addresses);
+                        known.project = known.project.take().or(server.project);
+                        known.model = known.model.take().or(server.model);
+                        known.version = known.version.take().or(server.version);
+                        known.auth_required |= server.auth_required;
+                    }
+                    slot => {
+                        println!(
+                            "  {}. {} is back at {}\n",
+                            i + 1,
+                            server.name,
+                            server.address
+                        );
+                        *slot = Some(server);
+                    }
+                },
+                None => {
+                    Self::print(self.servers.len(), &server);
+                    self.servers.push((server.name.clone(), Some(server)));
+                }
+            },
+            DiscoveryEvent::Updated(server) => {
+                if let Some(i) = self.position(&server.name) {
+                    let previous = self.servers[i].1.as_ref();
+                    if previous.map(|p| p.address) != Some(server.address) {
+                        println!(
+                            "  {}. {} is now at {}\n",
+                            i + 1,
+                            server.name,
+                            server.address
+                        );
+                    } else {
+                        // Found on another interface or protocol as well
+                        let known = previous.map(|p| p.addresses.as_slice()).unwrap_or(&[]);
+                        for address in server.addresses.iter().filter(|a| !known.contains(a)) {
+                            println!("  {}. {} is also at {address}\n", i + 1, server.name);
+                        }
+                    }
+                    self.servers[i].1 = Some(server);
+                }
+            }
+            DiscoveryEvent::Removed(name) => {
+                if let Some(i) = self.position(&name) {
+                    println!("  {}. {name} went away\n", i + 1);
+                    self.servers[i].1 = None;
+                }
+            }
         }
     }
 
-    // Apply sessions list
-    if !state.sessions.is_empty() {
-        let sessions: Vec<(String, String, String)> = state
-            .sessions
-            .into_iter()
-            .map(|s| (s.id, s.title, s.timestamp))
-            .collect();
-        if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::Sessions(sessions)) {
-            warn!("Failed to send sessions update: {}", e);
+    fn print(index: usize, server: &wonopcode_discover::ServerInfo) {
+        println!("  {}. {}", index + 1, server.name);
+        println!("     Address: {}", server.address);
+        for address in server.addresses.iter().filter(|a| **a != server.address) {
+            println!("     Also at: {address}");
+        }
+        if let Some(ref project) = server.project {
+            println!("     Project: {project}");
+        }
+        if let Some(ref model) = server.model {
+            println!("     Model: {model}");
+        }
+        if server.auth_required {
+            println!("     Auth: required");
         }
+        println!();
     }
 
-    // Apply token usage
-    if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::TokenUsage {
-        input: state.token_usage.input,
-        output: state.token_usage.output,
-        cost: state.token_usage.cost,
-        context_limit: state.context_limit,
-    }) {
-        warn!("Failed to send token usage update: {}", e);
+    fn position(&self, name: &str) -> Option<usize> {
+        self.servers.iter().position(|(n, _)| n == name)
     }
 
-    // Apply modified files
-    if !state.modified_files.is_empty() {
-        let files: Vec<wonopcode_tui::ModifiedFileUpdate> = state
-            .modified_files
-            .into_iter()
-            .map(|f| wonopcode_tui::ModifiedFileUpdate {
-                path: f.path,
-                added: f.added,
-                removed: f.removed,
-            })
-            .collect();
-        if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::ModifiedFilesUpdated(files)) {
-            warn!("Failed to send modified files update: {}", e);
-        }
+    /// Every server listed, up or not.
+    fn len(&self) -> usize {
+        self.servers.len()
     }
 
-    // Apply current session with messages
-    if let Some(session) = state.session {
-        // Helper to convert protocol ToolCall to TUI DisplayToolCall
-        let convert_tool = |tool: &wonopcode_protocol::ToolCall| -> wonopcode_tui::DisplayToolCall {
-            let status = match tool.status.as_str() {
-                "completed" => wonopcode_tui::ToolStatus::Success,
-                "failed" => wonopcode_tui::ToolStatus::Error,
-                "running" => wonopcode_tui::ToolStatus::Running,
-                _ => wonopcode_tui::ToolStatus::Pending,
-            };
-            wonopcode_tui::DisplayToolCall {
-                id: tool.id.clone(),
-                name: tool.name.clone(),
-                input: Some(tool.input.clone()),
-                output: tool.output.clone(),
-                status,
-                metadata: None,
-                expanded: false,
-            }
-        };
+    /// The servers that are up.
+    fn up_count(&self) -> usize {
+        self.servers.iter().filter(|(_, s)| s.is_some()).count()
+    }
 
-        // Helper to convert a protocol message to display message
-        let convert_message = |msg: &wonopcode_protocol::Message| -> wonopcode_tui::DisplayMessage {
-            match msg.role.as_str() {
-                "user" => {
-                    // Extract text from content segments
-                    let text: String = msg
-                        .content
-                        .iter()
-                        .filter_map(|seg| match seg {
-                            wonopcode_protocol::MessageSegment::Text { text } => Some(text.clone()),
-                            _ => None,
-                        })
-                        .collect::<Vec<_>>()
-                        .join("");
-                    wonopcode_tui::DisplayMessage::user(text)
-                }
-                "assistant" => {
-                    // Convert segments to TUI segments (now includes inline tools)
-                    let mut all_segments: Vec<wonopcode_tui::widgets::MessageSegment> = msg
-                        .content
-                        .iter()
-                        .map(|seg| match seg {
-                            wonopcode_protocol::MessageSegment::Text { text } => {
-                                wonopcode_tui::widgets::MessageSegment::Text(text.clone())
-                            }
-                            wonopcode_protocol::MessageSegment::Code { code, .. } => {
-                                wonopcode_tui::widgets::MessageSegment::Text(code.clone())
-                            }
-                            wonopcode_protocol::MessageSegment::Thinking { text } => {
-                                wonopcode_tui::widgets::MessageSegment::Text(format!(
-                                    "*Thinking:* {text}"
-                                ))
-                            }
-                            wonopcode_protocol::MessageSegment::Tool { tool } => {
-                                wonopcode_tui::widgets::MessageSegment::Tool(convert_tool(tool))
-                            }
-                        })
-                        .collect();
+    fn name(&self, index: usize) -> &str {
+        &self.servers[index].0
+    }
 
-                    // Also add any legacy tool_calls (for backward compatibility)
-                    for tool in &msg.tool_calls {
-                        all_segments.push(wonopcode_tui::widgets::MessageSegment::Tool(
-                            convert_tool(tool),
-                        ));
-                    }
+    /// The server listed at `index`, if it is up.
+    fn get(&self, index: usize) -> Option<&wonopcode_discover::ServerInfo> {
+        self.servers[index].1.as_ref()
+    }
+}
 
-                    // Convert agent string to AgentMode
-                    let agent_mode = msg
-                        .agent
-                        .as_ref()
-                        .map(|a| wonopcode_tui::AgentMode::parse(a));
+/// Connect to a remote headless server.
+#[allow(clippy::cognitive_complexity)]
+async fn run_connect(address: &str, cli: &Cli) -> anyhow::Result<()> {
+    use wonopcode_protocol::Capability;
+    use wonopcode_tui::App;
 
-                    wonopcode_tui::DisplayMessage::assistant_with_segments(all_segments)
-                        .with_model_agent(msg.model.clone(), agent_mode)
-                }
-                "system" => {
-                    let text: String = msg
-                        .content
-                        .iter()
-                        .filter_map(|seg| match seg {
-                            wonopcode_protocol::MessageSegment::Text { text } => Some(text.clone()),
-                            _ => None,
-                        })
-                        .collect::<Vec<_>>()
-                        .join("");
-                    wonopcode_tui::DisplayMessage::system(text)
-                }
-                _ => wonopcode_tui::DisplayMessage::system(format!("Unknown role: {}", msg.role)),
-            }
-        };
+    let address = connect::normalize_address(address);
+    println!("Connecting to {address}...");
 
-        let messages: Vec<wonopcode_tui::DisplayMessage> =
-            session.messages.iter().map(convert_message).collect();
+    let options = connect_options(cli);
+    let mut backend = options.open(&address, None).await?;
 
-        if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::SessionLoaded {
-            id: session.id.clone(),
-            title: session.title.clone(),
-            messages,
-        }) {
-            warn!("Failed to send session loaded update: {}", e);
-        }
+    println!("Connected!");
 
-        // If there's an in-progress streaming message, restore the streaming state
-        if session.is_streaming {
-            if let Some(ref streaming_msg) = session.streaming_message {
-                info!(
-                    "Restoring streaming state with {} content segments",
-                    streaming_msg.content.len()
-                );
+    // Get initial state
+    let state = backend.get_state().await?;
 
-                // Send Started to put TUI in streaming mode
-                if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::Started) {
-                    warn!("Failed to send Started update: {}", e);
-                }
+    // Create TUI app, with the project's files on the server if it serves
+    // them
+    let mut app = App::new();
+    app.set_remote_files(backend.supports(Capability::Files));
 
-                // Send the accumulated content as events to build up the streaming display
-                for segment in &streaming_msg.content {
-                    match segment {
-                        wonopcode_protocol::MessageSegment::Text { text } => {
-                            if let Err(e) =
-                                update_tx.send(wonopcode_tui::AppUpdate::TextDelta(text.clone()))
-                            {
-                                warn!("Failed to send TextDelta update: {}", e);
-                            }
-                        }
-                        wonopcode_protocol::MessageSegment::Tool { tool } => {
-                            // Send ToolStarted
-                            if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::ToolStarted {
-                                id: tool.id.clone(),
-                                name: tool.name.clone(),
-                                input: tool.input.clone(),
-                            }) {
-                                warn!("Failed to send ToolStarted update: {}", e);
-                            }
+    // Apply initial state from server
+    app.set_project(state.project.clone());
+    app.set_model(state.model.clone());
+    app.set_agent(state.agent.clone());
 
-                            // If tool is completed, send ToolCompleted
-                            if tool.status == "completed" || tool.status == "failed" {
-                                if let Err(e) =
-                                    update_tx.send(wonopcode_tui::AppUpdate::ToolCompleted {
-                                        id: tool.id.clone(),
-                                        success: tool.success,
-                                        output: tool.output.clone().unwrap_or_default(),
-                                        metadata: None,
-                                    })
-                                {
-                                    warn!("Failed to send ToolCompleted update: {}", e);
-                                }
-                            }
-                        }
-                        wonopcode_protocol::MessageSegment::Thinking { text } => {
-                            // Send thinking as text delta with prefix
-                            if let Err(e) = update_tx.send(wonopcode_tui::AppUpdate::TextDelta(
-                                format!("*Thinking:* {text}"),
-                            )) {
-                                warn!("Failed to send Thinking update: {}", e);
-                            }
-                        }
-                        wonopcode_protocol::MessageSegment::Code { code, .. } => {
-                            if let Err(e) =
-                                update_tx.send(wonopcode_tui::AppUpdate::TextDelta(code.clone()))
-                            {
-                                warn!("Failed to send Code update: {}", e);
-                            }
-                        }
-                    }
-                }
-            }
+    // Apply the rest of the state as updates, so they're processed correctly
+    let update_tx = app.update_sender();
+    for update in wonopcode_tui::state_updates(state) {
+        if let Err(e) = update_tx.send(update) {
+            warn!("Failed to send state update: {}", e);
         }
     }
 
@@ -2144,20 +2871,10 @@ async fn run_connect(address: &str, cli: &Cli) -> anyhow::Result<()> {
         .take_action_rx()
         .ok_or_else(|| anyhow::anyhow!("Failed to get action receiver"))?;
 
-    // Start SSE listener to receive updates
-    let _sse_handle = backend.subscribe_updates(update_tx);
-
-    // Spawn task to forward actions to remote server
-    let backend = std::sync::Arc::new(backend);
-    let backend_clone = backend.clone();
-    tokio::spawn(async move {
-        let mut action_rx = action_rx;
-        while let Some(action) = action_rx.recv().await {
-            if let Err(e) = backend_clone.send_action(action).await {
-                tracing::warn!("Failed to send action: {}", e);
-            }
-        }
-    });
+    // Send actions to the server, or to the one /connect moves to
+    let updates = options.subscribe(&mut backend, update_tx.clone());
+    let switch = connect::BackendSwitch::remote(backend, updates, options, update_tx);
+    tokio::spawn(switch.run(action_rx));
 
     // Run TUI
     app.run().await?;
@@ -2165,6 +2882,117 @@ async fn run_connect(address: &str, cli: &Cli) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// How to connect to servers: `--secret` or `WONOPCODE_SECRET`, `--tls-ca`
+/// and `--sse`.
+fn connect_options(cli: &Cli) -> connect::ConnectOptions {
+    connect::ConnectOptions {
+        secret: cli
+            .secret
+            .clone()
+            .or_else(|| std::env::var("WONOPCODE_SECRET").ok()),
+        tls_ca: cli.tls_ca.clone(),
+        sse: cli.sse,
+    }
+}
+
+/// The name to advertise and register the server as: `--name`, or the
+/// hostname.
+fn server_name(cli: &Cli) -> String {
+    cli.name.clone().unwrap_or_else(|| {
+        hostname::get()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .unwrap_or_else(|| "wonopcode".to_string())
+    })
+}
+
+/// A client of the rendezvous relay given with `--rendezvous` or
+/// `WONOPCODE_RENDEZVOUS`, if any.
+fn rendezvous_client(cli: &Cli) -> anyhow::Result<Option<wonopcode_server::RendezvousClient>> {
+    let Some(url) = cli
+        .rendezvous
+        .clone()
+        .or_else(|| std::env::var("WONOPCODE_RENDEZVOUS").ok())
+    else {
+        return Ok(None);
+    };
+    let mut client = wonopcode_server::RendezvousClient::new(&url, rendezvous_token(cli));
+    if let Some(ref ca) = cli.tls_ca {
+        let pem = std::fs::read(ca)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", ca.display()))?;
+        client = client.with_ca_certificate(&pem)?;
+    }
+    Ok(Some(client))
+}
+
+/// The token of the rendezvous relay: `--rendezvous-token` or
+/// `WONOPCODE_RENDEZVOUS_TOKEN`.
+fn rendezvous_token(cli: &Cli) -> Option<String> {
+    cli.rendezvous_token
+        .clone()
+        .or_else(|| std::env::var("WONOPCODE_RENDEZVOUS_TOKEN").ok())
+}
+
+/// The addresses to register the server bound to `address` at: those given
+/// with `--public-address`, or else the ones other machines can reach it at.
+fn public_addresses(cli: &Cli, address: SocketAddr) -> Vec<String> {
+    if !cli.public_addresses.is_empty() {
+        return cli.public_addresses.clone();
+    }
+    if address.ip().is_unspecified() {
+        commands::get_network_ips()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|ip| format!("{ip}:{}", address.port()))
+            .collect()
+    } else if address.ip().is_loopback() {
+        Vec::new()
+    } else {
+        vec![address.to_string()]
+    }
+}
+
+/// Run a rendezvous relay, which headless servers register with so that
+/// `--discover` finds them from other networks.
+async fn run_rendezvous(address: SocketAddr, cli: &Cli) -> anyhow::Result<()> {
+    use wonopcode_server::rendezvous::relay_router;
+
+    let token = rendezvous_token(cli);
+    let tls = tls_identity(cli, address)?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let app = relay_router(Arc::new(wonopcode_server::Relay::default()), token.clone());
+
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    println!("Rendezvous relay running on {scheme}://{address}");
+    if token.is_none() {
+        println!("Warning: without --rendezvous-token, anyone who can reach the relay can list and register servers");
+    }
+    println!("Servers register with:");
+    println!(
+        "  wonopcode --headless --rendezvous {scheme}://HOST:{}",
+        address.port()
+    );
+    println!("Press Ctrl+C to stop");
+
+    let shutdown = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    match tls {
+        Some(identity) => {
+            let listener = wonopcode_server::tls::TlsListener::new(listener, identity)?;
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown)
+                .await?
+        }
+        None => {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown)
+                .await?
+        }
+    }
+    Ok(())
+}
+
 /// Run ACP (Agent Client Protocol) server for IDE integration.
 async fn run_acp(cwd: &std::path::Path) -> anyhow::Result<()> {
     use wonopcode_acp::{serve, AgentConfig};
@@ -2182,8 +3010,13 @@ async fn run_acp(cwd: &std::path::Path) -> anyhow::Result<()> {
     Ok(())
 }
 
-#[cfg(feature = "github")]
-async fn handle_github(command: GithubCommands, cwd: &std::path::Path) -> anyhow::Result<()> {
+#[cfg(feature = "forge")]
+async fn handle_github(
+    command: GithubCommands,
+    cwd: &std::path::Path,
+    default_provider: &str,
+    model: Option<&str>,
+) -> anyhow::Result<()> {
     match command {
         GithubCommands::Install => {
             println!("GitHub Integration Setup");
@@ -2199,6 +3032,15 @@ async fn handle_github(command: GithubCommands, cwd: &std::path::Path) -> anyhow
             println!("       types: [created]");
             println!("     pull_request_review_comment:");
             println!("       types: [created]");
+            println!("     pull_request:");
+            println!("       types: [opened, synchronize, reopened, ready_for_review]");
+            println!("     issues:");
+            println!("       types: [labeled]");
+            println!();
+            println!("   permissions:");
+            println!("     contents: write");
+            println!("     issues: write");
+            println!("     pull-requests: write");
             println!();
             println!("   jobs:");
             println!("     run:");
@@ -2216,18 +3058,141 @@ async fn handle_github(command: GithubCommands, cwd: &std::path::Path) -> anyhow
             println!("2. Add your API key as a repository secret (Settings > Secrets)");
             println!();
             println!("3. Trigger by commenting `/wonopcode <your request>` on issues or PRs");
+            println!();
+            println!("Pull requests are reviewed when opened or pushed to. Set the severity");
+            println!("thresholds in the `review` section of the config, or drop the");
+            println!("`pull_request` trigger to turn reviews off.");
+            println!();
+            println!("Issues labeled `wonopcode-triage` are triaged: the analysis is posted");
+            println!("on the issue and the label removed. Set the label in the `triage`");
+            println!("section of the config, or drop the `issues` trigger to turn it off.");
             Ok(())
         }
         GithubCommands::Run { event, token } => {
-            github::run_agent(cwd, event.as_deref(), token.as_deref()).await
+            let model = forge::ModelChoice {
+                default_provider,
+                model,
+            };
+            forge::github::run_agent(cwd, event.as_deref(), token.as_deref(), &model).await
+        }
+    }
+}
+
+#[cfg(feature = "forge")]
+async fn handle_gitlab(
+    command: GitlabCommands,
+    cwd: &std::path::Path,
+    default_provider: &str,
+    model: Option<&str>,
+) -> anyhow::Result<()> {
+    match command {
+        GitlabCommands::Install => {
+            println!("GitLab Integration Setup");
+            println!("========================");
+            println!();
+            println!("To enable GitLab integration:");
+            println!();
+            println!("1. Add a job to .gitlab-ci.yml:");
+            println!();
+            println!("   wonopcode:");
+            println!("     rules:");
+            println!("       - if: $CI_PIPELINE_SOURCE == \"trigger\"");
+            println!("       - if: $CI_PIPELINE_SOURCE == \"merge_request_event\"");
+            println!("     script:");
+            println!("       - curl -fsSL https://wonopcode.dev/install.sh | sh");
+            println!("       - wonopcode gitlab run");
+            println!();
+            println!("2. Add GITLAB_TOKEN (a project access token with the api and");
+            println!("   write_repository scopes, Developer role) and your provider API key");
+            println!("   as CI/CD variables (Settings > CI/CD > Variables)");
+            println!();
+            println!("3. Create a pipeline trigger token (Settings > CI/CD > Pipeline trigger");
+            println!("   tokens) and a webhook (Settings > Webhooks) for comments and issue");
+            println!("   events, with the URL:");
+            println!();
+            println!("   https://<gitlab host>/api/v4/projects/<project id>/ref/<default branch>/trigger/pipeline?token=<trigger token>");
+            println!();
+            println!("4. Trigger by commenting `/wonopcode <your request>` on issues or merge");
+            println!("   requests");
+            println!();
+            println!("Merge requests are reviewed in merge request pipelines. Set the severity");
+            println!("thresholds in the `review` section of the config, or drop the");
+            println!("`merge_request_event` rule to turn reviews off.");
+            println!();
+            println!("Issues labeled `wonopcode-triage` are triaged: the analysis is posted");
+            println!("on the issue and the label removed. Set the label in the `triage`");
+            println!("section of the config.");
+            Ok(())
+        }
+        GitlabCommands::Run { event, token } => {
+            let model = forge::ModelChoice {
+                default_provider,
+                model,
+            };
+            forge::gitlab::run_ci(cwd, event.as_deref(), token.as_deref(), &model).await
         }
     }
 }
 
-/// Handle PR checkout (requires --features github).
-#[cfg(feature = "github")]
-async fn handle_pr(number: u64) -> anyhow::Result<()> {
-    github::checkout_pr(number).await
+/// Handle PR checkout and review (requires --features forge).
+#[cfg(feature = "forge")]
+async fn handle_ci(
+    command: CiCommands,
+    cwd: &std::path::Path,
+    default_provider: &str,
+    model: Option<&str>,
+) -> anyhow::Result<()> {
+    match command {
+        CiCommands::Explain {
+            run,
+            comment,
+            token,
+        } => {
+            let options = forge::ci::ExplainOptions {
+                model: forge::ModelChoice {
+                    default_provider,
+                    model,
+                },
+                run,
+                comment,
+            };
+            forge::ci::explain(cwd, token.as_deref(), &options).await
+        }
+    }
+}
+
+#[cfg(feature = "forge")]
+async fn handle_pr(
+    command: Option<PrCommands>,
+    number: Option<u64>,
+    cwd: &std::path::Path,
+    default_provider: &str,
+    model: Option<&str>,
+) -> anyhow::Result<()> {
+    match command {
+        Some(PrCommands::Review {
+            number,
+            min_severity,
+            request_changes_at,
+            token,
+            dry_run,
+        }) => {
+            let options = forge::ReviewOptions {
+                model: forge::ModelChoice {
+                    default_provider,
+                    model,
+                },
+                min_severity,
+                request_changes_at,
+                dry_run,
+            };
+            forge::review_pr(cwd, number, token.as_deref(), &options).await
+        }
+        None => match number {
+            Some(number) => forge::checkout_pr(cwd, number).await,
+            None => anyhow::bail!("Pass a PR number to checkout, or `review <number>`"),
+        },
+    }
 }
 
 /// Handle stats command.
@@ -2247,3 +3212,21 @@ async fn handle_stats(
 
     Ok(())
 }
+
+async fn handle_stats_export(
+    cwd: &std::path::Path,
+    days: Option<u32>,
+    project: Option<String>,
+    format: stats::ReportFormat,
+    group_by: stats::ReportGroup,
+    monthly: bool,
+) -> anyhow::Result<()> {
+    let instance = wonopcode_core::Instance::new(cwd).await?;
+    let usage = stats::collect_usage(&instance, days, project).await;
+    instance.dispose().await;
+
+    let rows = stats::group_usage(&usage?, group_by, monthly);
+    let mut stdout = std::io::stdout().lock();
+    stats::write_report(&mut stdout, &rows, format, group_by, monthly)?;
+    Ok(())
+}
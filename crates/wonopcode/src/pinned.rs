@@ -0,0 +1,164 @@
+//! Files and symbols attached to a prompt with `@`.
+//!
+//! Their current content is pinned to the system prompt for the turn of the
+//! prompt they were attached to, instead of being stored in the history.
+
+use std::path::Path;
+
+/// Most bytes of a file pinned to a prompt; the rest is cut off.
+const MAX_PINNED_BYTES: usize = 64 * 1024;
+
+/// A file, or the lines of a symbol in it, pinned to a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedContext {
+    /// Path of the file, relative to the project.
+    pub path: String,
+    /// Name of the symbol, if a symbol was attached.
+    pub symbol: Option<String>,
+    /// First and last line (1-based, inclusive); the whole file if unset.
+    pub lines: Option<(u32, u32)>,
+}
+
+impl PinnedContext {
+    /// Heading of the attachment, e.g. "parse in src/lib.rs (lines 10-20)".
+    fn label(&self) -> String {
+        let mut label = match &self.symbol {
+            Some(symbol) => format!("{symbol} in {}", self.path),
+            None => self.path.clone(),
+        };
+        if let Some((start, end)) = self.lines {
+            label.push_str(&format!(" (lines {start}-{end})"));
+        }
+        label
+    }
+
+    /// Read the attached content from the project at `root`.
+    fn read(&self, root: &Path) -> Result<String, String> {
+        let root = root.canonicalize().map_err(|e| e.to_string())?;
+        let path = root
+            .join(&self.path)
+            .canonicalize()
+            .map_err(|e| e.to_string())?;
+        if !path.starts_with(&root) {
+            return Err("outside the project".to_string());
+        }
+        let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let content = match self.lines {
+            Some((start, end)) => {
+                let skip = start.saturating_sub(1) as usize;
+                let take = (end as usize + 1).saturating_sub(start.max(1) as usize);
+                content
+                    .lines()
+                    .skip(skip)
+                    .take(take)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            None => content,
+        };
+        Ok(truncate(content))
+    }
+}
+
+/// Cut content off at [`MAX_PINNED_BYTES`], on a line boundary if possible.
+fn truncate(mut content: String) -> String {
+    if content.len() <= MAX_PINNED_BYTES {
+        return content;
+    }
+    let mut end = MAX_PINNED_BYTES;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = content[..end].rfind('\n').unwrap_or(end);
+    content.truncate(end);
+    content.push_str("\n[... truncated]");
+    content
+}
+
+/// A code fence longer than any run of backticks in the content.
+fn fence(content: &str) -> String {
+    let mut fence = "```".to_string();
+    while content.contains(&fence) {
+        fence.push('`');
+    }
+    fence
+}
+
+/// Format the attachments of a prompt for the system prompt, reading them
+/// from the project at `root`.
+pub fn render(pinned: &[PinnedContext], root: &Path) -> Option<String> {
+    if pinned.is_empty() {
+        return None;
+    }
+    let mut out = String::from(
+        "# Attached Files\n\nThe user attached these to their message with `@`, \
+         shown as they are now:",
+    );
+    for context in pinned {
+        out.push_str(&format!("\n\n## {}\n\n", context.label()));
+        match context.read(root) {
+            Ok(content) => {
+                let fence = fence(&content);
+                out.push_str(&format!("{fence}\n{content}\n{fence}"));
+            }
+            Err(e) => out.push_str(&format!("(could not be read: {e})")),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pinned(path: &str, lines: Option<(u32, u32)>) -> PinnedContext {
+        PinnedContext {
+            path: path.to_string(),
+            symbol: lines.map(|_| "parse".to_string()),
+            lines,
+        }
+    }
+
+    #[test]
+    fn test_render_files_and_symbols() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.md"), "use ```rust``` fences\n").unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "a\nb\nc\nd\n").unwrap();
+
+        assert_eq!(render(&[], dir.path()), None);
+        let out = render(
+            &[
+                pinned("notes.md", None),
+                pinned("src/lib.rs", Some((2, 3))),
+                pinned("missing.rs", None),
+            ],
+            dir.path(),
+        )
+        .unwrap();
+        assert!(out.contains("## notes.md\n\n````\nuse ```rust``` fences\n\n````"));
+        assert!(out.contains("## parse in src/lib.rs (lines 2-3)\n\n```\nb\nc\n```"));
+        assert!(out.contains("## missing.rs\n\n(could not be read:"));
+    }
+
+    #[test]
+    fn test_files_outside_the_project_are_not_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+
+        let out = render(&[pinned("../secret.txt", None)], &project).unwrap();
+        assert!(out.contains("(could not be read: outside the project)"));
+        assert!(!out.contains("secret\n"));
+    }
+
+    #[test]
+    fn test_truncate_on_line_boundary() {
+        let content = "line\n".repeat(MAX_PINNED_BYTES);
+        let truncated = truncate(content);
+        assert!(truncated.len() <= MAX_PINNED_BYTES + 20);
+        assert!(truncated.ends_with("line\n[... truncated]"));
+        assert_eq!(truncate("short".to_string()), "short");
+    }
+}
@@ -0,0 +1,453 @@
+//! Several project roots in one headless server.
+//!
+//! The server's working directory is the primary project; `server.projects`
+//! adds more. Each project gets its own [`Instance`], permission manager,
+//! MCP server and runner, so config, sessions and sandboxes stay separate.
+//! Extra projects are started the first time a client switches to them.
+//!
+//! Clients only see the active project: updates from other runners are
+//! dropped, except permission requests, which are answered by the runner
+//! that asked.
+
+use crate::commands::start_mcp_server;
+use crate::runner::{Runner, RunnerConfig};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use wonopcode_core::Instance;
+use wonopcode_tui::{AppAction, AppUpdate, ProjectUpdate};
+
+/// A project root and its runner, once started.
+struct ProjectSlot {
+    path: PathBuf,
+    name: String,
+    running: Option<RunningProject>,
+}
+
+struct RunningProject {
+    instance: Instance,
+    action_tx: mpsc::UnboundedSender<AppAction>,
+    /// Tasks owned by the project (MCP server, scheduler), aborted on drop.
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for RunningProject {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// The projects of a headless server.
+pub struct ProjectPool {
+    slots: Vec<ProjectSlot>,
+    active: Arc<AtomicUsize>,
+    /// Pending permission requests and the project that asked.
+    permission_owners: Arc<Mutex<HashMap<String, usize>>>,
+    /// Runner config of the primary project, reused for the others.
+    base_config: RunnerConfig,
+    default_model: String,
+    update_tx: mpsc::UnboundedSender<AppUpdate>,
+}
+
+impl ProjectPool {
+    /// Create a pool with `primary` active and `extra` roots not yet started.
+    ///
+    /// Relative extra roots are resolved against `primary`. Duplicates and
+    /// roots that aren't directories are skipped.
+    pub fn new(
+        primary: &Path,
+        extra: &[String],
+        base_config: RunnerConfig,
+        default_model: String,
+        update_tx: mpsc::UnboundedSender<AppUpdate>,
+    ) -> Self {
+        let primary = primary
+            .canonicalize()
+            .unwrap_or_else(|_| primary.to_path_buf());
+        let mut slots = vec![ProjectSlot::new(primary.clone())];
+        for root in extra {
+            let path = primary.join(root);
+            let path = path.canonicalize().unwrap_or(path);
+            if !path.is_dir() {
+                warn!(path = %path.display(), "Skipping project that isn't a directory");
+                continue;
+            }
+            if slots.iter().any(|s| s.path == path) {
+                continue;
+            }
+            slots.push(ProjectSlot::new(path));
+        }
+
+        Self {
+            slots,
+            active: Arc::new(AtomicUsize::new(0)),
+            permission_owners: Arc::new(Mutex::new(HashMap::new())),
+            base_config,
+            default_model,
+            update_tx,
+        }
+    }
+
+    /// Attach the already created runner of the primary project.
+    pub fn attach_primary(&mut self, instance: Instance, runner: Runner) -> JoinHandle<()> {
+        let (action_tx, handle) = self.spawn_runner(0, runner);
+        self.slots[0].running = Some(RunningProject {
+            instance,
+            action_tx,
+            tasks: Vec::new(),
+        });
+        handle
+    }
+
+    /// Projects for the switcher.
+    pub fn list(&self) -> Vec<ProjectUpdate> {
+        let active = self.active.load(Ordering::SeqCst);
+        self.slots
+            .iter()
+            .enumerate()
+            .map(|(i, slot)| ProjectUpdate {
+                path: slot.path.display().to_string(),
+                name: slot.name.clone(),
+                active: i == active,
+                loaded: slot.running.is_some(),
+            })
+            .collect()
+    }
+
+    /// Instance of the active project.
+    pub fn active_instance(&self) -> Option<&Instance> {
+        let slot = &self.slots[self.active.load(Ordering::SeqCst)];
+        slot.running.as_ref().map(|r| &r.instance)
+    }
+
+    /// Find a project by path or name.
+    fn find(&self, project: &str) -> Option<usize> {
+        let path = Path::new(project);
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.slots
+            .iter()
+            .position(|s| s.path == path)
+            .or_else(|| self.slots.iter().position(|s| s.name == project))
+    }
+
+    /// Make a project active, starting it if needed.
+    ///
+    /// Returns the project's root and name. Only configured projects can be
+    /// switched to.
+    pub async fn switch(&mut self, project: &str) -> Result<(PathBuf, String), String> {
+        let Some(index) = self.find(project) else {
+            return Err(format!(
+                "Unknown project '{project}' - add it under server.projects"
+            ));
+        };
+        if self.slots[index].running.is_none() {
+            let running = self.start(index).await?;
+            self.slots[index].running = Some(running);
+        }
+        self.active.store(index, Ordering::SeqCst);
+
+        let slot = &self.slots[index];
+        info!(project = %slot.path.display(), "Switched project");
+        Ok((slot.path.clone(), slot.name.clone()))
+    }
+
+    /// Send an action to the runner of the active project.
+    ///
+    /// Permission responses go to the project that asked. Returns false if
+    /// the runner is gone.
+    pub fn send(&self, action: AppAction) -> bool {
+        let index = match &action {
+            AppAction::PermissionResponse { request_id, .. } => self
+                .permission_owners
+                .lock()
+                .ok()
+                .and_then(|mut owners| owners.remove(request_id)),
+            _ => None,
+        }
+        .unwrap_or_else(|| self.active.load(Ordering::SeqCst));
+
+        match &self.slots[index].running {
+            Some(running) => running.action_tx.send(action).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Start the runner of an extra project.
+    async fn start(&self, index: usize) -> Result<RunningProject, String> {
+        let path = self.slots[index].path.clone();
+        info!(project = %path.display(), "Starting project");
+
+        let instance = Instance::new(&path)
+            .await
+            .map_err(|e| format!("Failed to open project {}: {e}", path.display()))?;
+        #[cfg(feature = "forge")]
+        crate::forge::use_for_pull_requests(&instance).await;
+        let (runner, mut tasks) = start_runner(&instance, &self.base_config).await?;
+
+        let (action_tx, handle) = self.spawn_runner(index, runner);
+        tasks.push(handle);
+        tasks.push(crate::schedule::spawn(
+            instance.clone(),
+            self.default_model.clone(),
+        ));
+
+        Ok(RunningProject {
+            instance,
+            action_tx,
+            tasks,
+        })
+    }
+
+    /// Run a runner, forwarding its updates while its project is active.
+    fn spawn_runner(
+        &self,
+        index: usize,
+        runner: Runner,
+    ) -> (mpsc::UnboundedSender<AppAction>, JoinHandle<()>) {
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let (update_tx, mut update_rx) = mpsc::unbounded_channel();
+
+        let active = self.active.clone();
+        let owners = self.permission_owners.clone();
+        let forward_tx = self.update_tx.clone();
+        tokio::spawn(async move {
+            while let Some(update) = update_rx.recv().await {
+                if let AppUpdate::PermissionRequest(request) = &update {
+                    if let Ok(mut owners) = owners.lock() {
+                        owners.insert(request.id.clone(), index);
+                    }
+                } else if active.load(Ordering::SeqCst) != index {
+                    continue;
+                }
+                if forward_tx.send(update).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let handle = tokio::spawn(async move {
+            runner.run(action_rx, update_tx).await;
+        });
+        (action_tx, handle)
+    }
+}
+
+/// Create a runner for a project with its own bus, permission manager and
+/// MCP server, so its permission requests only reach its own clients.
+///
+/// Returns the runner and the tasks it needs, which the caller owns.
+pub async fn start_runner(
+    instance: &Instance,
+    base_config: &RunnerConfig,
+) -> Result<(Runner, Vec<JoinHandle<()>>), String> {
+    let path = instance.directory().to_path_buf();
+    let config = instance.config().await;
+
+    let bus = wonopcode_core::bus::Bus::new();
+    let permission_manager = Arc::new(wonopcode_core::PermissionManager::new(bus.clone()));
+    for rule in wonopcode_core::PermissionManager::default_rules() {
+        permission_manager.add_rule(rule).await;
+    }
+    if let Some(perm_config) = &config.permission {
+        for rule in wonopcode_core::PermissionManager::rules_from_config(perm_config) {
+            permission_manager.add_rule(rule).await;
+        }
+    }
+
+    let mut tasks = Vec::new();
+    let mcp_url = match start_mcp_server(&path, permission_manager.clone()).await {
+        Ok((url, handle)) => {
+            tasks.push(handle);
+            Some(url)
+        }
+        Err(e) => {
+            warn!(project = %path.display(), error = %e, "Failed to start MCP server");
+            None
+        }
+    };
+
+    let runner_config = RunnerConfig {
+        allow_all_in_sandbox: config
+            .permission
+            .as_ref()
+            .and_then(|p| p.allow_all_in_sandbox)
+            .unwrap_or(true),
+        mcp_url,
+        mcp_secret: None,
+        ..base_config.clone()
+    };
+    let runner = Runner::new_with_shared(
+        runner_config,
+        instance.clone(),
+        config.mcp.clone(),
+        Some(bus),
+        Some(permission_manager),
+    )
+    .await
+    .map_err(|e| format!("Failed to create runner for {}: {e}", path.display()))?;
+
+    Ok((runner, tasks))
+}
+
+/// Switch the active project and bring clients along.
+///
+/// Starts a fresh session in the project, resets the shared server state to
+/// it and announces the switch. Failures are reported as a status message.
+pub async fn switch_project(
+    pool: &mut ProjectPool,
+    project: &str,
+    state: &RwLock<wonopcode_protocol::State>,
+    update_tx: &mpsc::UnboundedSender<AppUpdate>,
+) {
+    let (path, name) = match pool.switch(project).await {
+        Ok(switched) => switched,
+        Err(e) => {
+            warn!(project, error = %e, "Failed to switch project");
+            let _ = update_tx.send(AppUpdate::Status(e));
+            return;
+        }
+    };
+    pool.send(AppAction::NewSession);
+
+    let mut sessions = Vec::new();
+    if let Some(instance) = pool.active_instance() {
+        let project_id = instance.project_id().await;
+        if let Ok(list) = instance.session_repo().list(&project_id).await {
+            sessions = list
+                .iter()
+                .map(|s| {
+                    (
+                        s.id.clone(),
+                        s.title.clone(),
+                        s.updated_at().format("%Y-%m-%d %H:%M").to_string(),
+                    )
+                })
+                .collect();
+        }
+    }
+
+    {
+        let mut state = state.write().await;
+        state.project = path.display().to_string();
+        state.projects = pool.list().into_iter().map(project_info).collect();
+        state.session = Some(wonopcode_protocol::SessionState {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: "New Session".to_string(),
+            messages: Vec::new(),
+            is_shared: false,
+            share_url: None,
+            is_streaming: false,
+            streaming_message: None,
+        });
+        state.phases.clear();
+        state.todos.clear();
+        state.modified_files.clear();
+    }
+
+    let _ = update_tx.send(AppUpdate::ProjectSwitched {
+        path: path.display().to_string(),
+        name,
+    });
+    let _ = update_tx.send(AppUpdate::Sessions(sessions));
+}
+
+/// Convert a project for remote clients.
+pub fn project_info(project: ProjectUpdate) -> wonopcode_protocol::ProjectInfo {
+    wonopcode_protocol::ProjectInfo {
+        path: project.path,
+        name: project.name,
+        active: project.active,
+        loaded: project.loaded,
+    }
+}
+
+impl ProjectSlot {
+    fn new(path: PathBuf) -> Self {
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| path.display().to_string());
+        Self {
+            path,
+            name,
+            running: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(primary: &Path, extra: &[String]) -> ProjectPool {
+        let config = RunnerConfig {
+            provider: "test".to_string(),
+            model_id: "test-model".to_string(),
+            api_key: String::new(),
+            system_prompt: None,
+            max_tokens: None,
+            temperature: None,
+            doom_loop: wonopcode_core::permission::Decision::Ask,
+            test_provider_settings: None,
+            allow_all: false,
+            allow_all_in_sandbox: true,
+            mcp_url: None,
+            mcp_secret: None,
+            external_mcp_servers: HashMap::new(),
+        };
+        let (update_tx, _update_rx) = mpsc::unbounded_channel();
+        ProjectPool::new(
+            primary,
+            extra,
+            config,
+            "test/test-model".to_string(),
+            update_tx,
+        )
+    }
+
+    #[test]
+    fn test_pool_resolves_extra_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let primary = dir.path().join("api");
+        std::fs::create_dir_all(&primary).unwrap();
+        std::fs::create_dir_all(dir.path().join("web")).unwrap();
+
+        let pool = pool(
+            &primary,
+            &[
+                "../web".to_string(),
+                "../web".to_string(),
+                "../missing".to_string(),
+            ],
+        );
+        let projects = pool.list();
+        assert_eq!(projects.len(), 2);
+        assert_eq!(projects[0].name, "api");
+        assert!(projects[0].active);
+        assert!(!projects[0].loaded);
+        assert_eq!(projects[1].name, "web");
+        assert!(!projects[1].active);
+    }
+
+    #[test]
+    fn test_pool_finds_projects_by_name_or_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let primary = dir.path().join("api");
+        let web = dir.path().join("web");
+        std::fs::create_dir_all(&primary).unwrap();
+        std::fs::create_dir_all(&web).unwrap();
+
+        let pool = pool(&primary, &[web.display().to_string()]);
+        assert_eq!(pool.find("web"), Some(1));
+        assert_eq!(pool.find(&web.display().to_string()), Some(1));
+        assert_eq!(pool.find("api"), Some(0));
+        assert_eq!(pool.find("other"), None);
+    }
+}
@@ -0,0 +1,365 @@
+//! Retrieval of compacted conversation context.
+//!
+//! Before compaction condenses a session, its turns and tool outputs are
+//! embedded into a [`ContextIndex`]. For each later prompt, the indexed
+//! pieces that are no longer in the active context are ranked by similarity
+//! to the prompt, and the most relevant ones are added to the system prompt.
+//! Details that compaction summarized or cleared can come back when a
+//! request needs them.
+
+use crate::compaction::{estimate_tokens, find_tool_name, is_compaction_message, is_user_prompt};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use wonopcode_provider::{
+    cosine_similarity, ContentPart, EmbeddingModel, Message as ProviderMessage, ProviderResult,
+    Role,
+};
+
+/// Longest piece of text indexed as one chunk, in characters.
+const CHUNK_CHARS: usize = 4_000;
+
+/// Shortest text worth indexing, in characters.
+const MIN_CHUNK_CHARS: usize = 40;
+
+/// Minimum similarity for a chunk to be retrieved.
+const MIN_SIMILARITY: f32 = 0.3;
+
+/// Maximum number of chunks retrieved for a prompt.
+const MAX_RETRIEVED: usize = 8;
+
+/// A piece of the conversation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    /// Where the text came from, e.g. "User" or "Tool output (read)".
+    pub label: String,
+    /// The text.
+    pub text: String,
+    hash: u64,
+}
+
+impl Chunk {
+    fn new(label: impl Into<String>, text: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        Self {
+            label: label.into(),
+            text: text.to_string(),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+/// Embedded chunks of a session.
+#[derive(Debug, Default)]
+pub struct ContextIndex {
+    /// Chunks in conversation order, with their embeddings.
+    chunks: Vec<(Chunk, Vec<f32>)>,
+    indexed: HashSet<u64>,
+}
+
+impl ContextIndex {
+    /// Whether nothing has been indexed.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Number of indexed chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Forget everything, e.g. for a new session.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.indexed.clear();
+    }
+
+    /// Chunks of the messages that aren't indexed yet.
+    pub fn unindexed(&self, messages: &[ProviderMessage]) -> Vec<Chunk> {
+        let mut seen = HashSet::new();
+        chunk_messages(messages)
+            .into_iter()
+            .filter(|c| !self.indexed.contains(&c.hash) && seen.insert(c.hash))
+            .collect()
+    }
+
+    /// Add chunks with their embeddings.
+    pub fn insert(&mut self, chunks: Vec<Chunk>, embeddings: Vec<Vec<f32>>) {
+        for (chunk, embedding) in chunks.into_iter().zip(embeddings) {
+            if self.indexed.insert(chunk.hash) {
+                self.chunks.push((chunk, embedding));
+            }
+        }
+    }
+
+    /// The chunks most similar to the query that aren't in the active
+    /// messages, in conversation order and within the token budget.
+    pub fn retrieve(
+        &self,
+        query: &[f32],
+        active: &[ProviderMessage],
+        max_tokens: u32,
+    ) -> Vec<&Chunk> {
+        let present: HashSet<u64> = chunk_messages(active).iter().map(|c| c.hash).collect();
+        let mut scored: Vec<(usize, f32)> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, (chunk, _))| !present.contains(&chunk.hash))
+            .map(|(i, (_, embedding))| (i, cosine_similarity(query, embedding)))
+            .filter(|&(_, score)| score >= MIN_SIMILARITY)
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut budget = max_tokens;
+        let mut picked: Vec<usize> = Vec::new();
+        for (i, _) in scored {
+            let tokens = estimate_tokens(&self.chunks[i].0.text);
+            if tokens > budget {
+                continue;
+            }
+            budget -= tokens;
+            picked.push(i);
+            if picked.len() == MAX_RETRIEVED {
+                break;
+            }
+        }
+        picked.sort_unstable();
+        picked.into_iter().map(|i| &self.chunks[i].0).collect()
+    }
+}
+
+/// Embed and index the chunks of the messages that aren't indexed yet.
+///
+/// Returns the number of chunks added.
+pub async fn index_messages(
+    index: &tokio::sync::RwLock<ContextIndex>,
+    model: &dyn EmbeddingModel,
+    messages: &[ProviderMessage],
+) -> ProviderResult<usize> {
+    let chunks = index.read().await.unindexed(messages);
+    if chunks.is_empty() {
+        return Ok(0);
+    }
+    let texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+    let embeddings = model.embed(&texts).await?;
+    let added = chunks.len();
+    index.write().await.insert(chunks, embeddings);
+    Ok(added)
+}
+
+/// Format retrieved chunks for the system prompt.
+pub fn render(chunks: &[&Chunk]) -> Option<String> {
+    if chunks.is_empty() {
+        return None;
+    }
+    let mut out = String::from(
+        "# Earlier Context\n\nParts of this conversation that were compacted away, \
+         retrieved because they look relevant to the current request:",
+    );
+    for chunk in chunks {
+        out.push_str(&format!("\n\n## {}\n\n{}", chunk.label, chunk.text));
+    }
+    Some(out)
+}
+
+/// Split messages into the chunks that get indexed: user prompts, assistant
+/// text and tool outputs. Compaction summaries and markers are skipped.
+fn chunk_messages(messages: &[ProviderMessage]) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    for msg in messages {
+        if is_compaction_message(msg) {
+            continue;
+        }
+        let label = match msg.role {
+            Role::User if is_user_prompt(msg) => "User",
+            Role::Assistant => "Assistant",
+            _ => "",
+        };
+        if !label.is_empty() {
+            let text: Vec<&str> = msg
+                .content
+                .iter()
+                .filter_map(|p| match p {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            push_chunks(&mut chunks, label, &text.join("\n\n"));
+        }
+        for part in &msg.content {
+            if let ContentPart::ToolResult {
+                tool_use_id,
+                content,
+                ..
+            } = part
+            {
+                if content.starts_with("[compacted]") {
+                    continue;
+                }
+                let label = match find_tool_name(messages, tool_use_id) {
+                    Some(name) => format!("Tool output ({name})"),
+                    None => "Tool output".to_string(),
+                };
+                push_chunks(&mut chunks, &label, content);
+            }
+        }
+    }
+    chunks
+}
+
+/// Add a text as chunks of at most [`CHUNK_CHARS`] characters.
+fn push_chunks(chunks: &mut Vec<Chunk>, label: &str, text: &str) {
+    let text = text.trim();
+    if text.len() < MIN_CHUNK_CHARS {
+        return;
+    }
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= CHUNK_CHARS {
+        chunks.push(Chunk::new(label, text));
+        return;
+    }
+    for (n, piece) in chars.chunks(CHUNK_CHARS).enumerate() {
+        let piece: String = piece.iter().collect();
+        if piece.trim().len() >= MIN_CHUNK_CHARS {
+            chunks.push(Chunk::new(format!("{label} (part {})", n + 1), &piece));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// Embeds a text as how often it mentions "parser" and "database".
+    struct KeywordEmbeddings;
+
+    #[async_trait]
+    impl EmbeddingModel for KeywordEmbeddings {
+        async fn embed(&self, texts: &[String]) -> ProviderResult<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.matches("parser").count() as f32,
+                        t.matches("database").count() as f32,
+                        0.1,
+                    ]
+                })
+                .collect())
+        }
+
+        fn model_id(&self) -> &str {
+            "keywords"
+        }
+    }
+
+    fn tool_result(id: &str, content: &str) -> ProviderMessage {
+        ProviderMessage {
+            role: Role::Tool,
+            content: vec![ContentPart::ToolResult {
+                tool_use_id: id.to_string(),
+                content: content.to_string(),
+                is_error: None,
+            }],
+        }
+    }
+
+    fn conversation() -> Vec<ProviderMessage> {
+        vec![
+            ProviderMessage::user("Please look into why the parser rejects trailing commas"),
+            ProviderMessage {
+                role: Role::Assistant,
+                content: vec![ContentPart::ToolUse {
+                    id: "call_1".to_string(),
+                    name: "read".to_string(),
+                    input: serde_json::json!({ "path": "src/parser.rs" }),
+                }],
+            },
+            tool_result(
+                "call_1",
+                "fn parse_list() { /* parser: trailing commas are rejected here */ }",
+            ),
+            ProviderMessage::user("Now migrate the database schema to add an index on users"),
+            ProviderMessage::assistant("Added the migration for the database index on users.email"),
+        ]
+    }
+
+    #[test]
+    fn test_chunks_skip_markers_and_short_text() {
+        let mut messages = conversation();
+        messages.push(tool_result("call_2", "[compacted]"));
+        messages.push(ProviderMessage::assistant("ok"));
+        messages.push(ProviderMessage::assistant(
+            "[Previous conversation summary (4 messages)]\n\nWe fixed the parser.",
+        ));
+
+        let chunks = chunk_messages(&messages);
+        let labels: Vec<&str> = chunks.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["User", "Tool output (read)", "User", "Assistant"]
+        );
+    }
+
+    #[test]
+    fn test_long_text_is_split() {
+        let mut chunks = Vec::new();
+        push_chunks(
+            &mut chunks,
+            "Tool output",
+            &"x".repeat(CHUNK_CHARS * 2 + 100),
+        );
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].label, "Tool output (part 1)");
+        assert_eq!(chunks[2].text.len(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_index_and_retrieve_compacted_context() {
+        let index = tokio::sync::RwLock::new(ContextIndex::default());
+        let messages = conversation();
+        let added = index_messages(&index, &KeywordEmbeddings, &messages)
+            .await
+            .unwrap();
+        assert_eq!(added, 4);
+        // Indexing again adds nothing
+        let added = index_messages(&index, &KeywordEmbeddings, &messages)
+            .await
+            .unwrap();
+        assert_eq!(added, 0);
+
+        // After compaction only the database turn is still in context
+        let active = messages[3..].to_vec();
+        let query = KeywordEmbeddings
+            .embed(&["Does the parser still reject commas?".to_string()])
+            .await
+            .unwrap();
+        let index = index.read().await;
+        let retrieved = index.retrieve(&query[0], &active, 1_000);
+        let labels: Vec<&str> = retrieved.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, vec!["User", "Tool output (read)"]);
+
+        // Nothing fits in a tiny budget
+        assert!(index.retrieve(&query[0], &active, 1).is_empty());
+
+        let rendered = render(&retrieved).unwrap();
+        assert!(rendered.starts_with("# Earlier Context"));
+        assert!(rendered.contains("## Tool output (read)"));
+        assert!(render(&[]).is_none());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut index = ContextIndex::default();
+        let chunks = index.unindexed(&conversation());
+        let embeddings = vec![vec![1.0]; chunks.len()];
+        index.insert(chunks, embeddings);
+        assert_eq!(index.len(), 4);
+        index.clear();
+        assert!(index.is_empty());
+        assert_eq!(index.unindexed(&conversation()).len(), 4);
+    }
+}
@@ -2,44 +2,69 @@
 // @ace:implements COMP-T90R9Q-8J4
 
 use futures::future::join_all;
+use opentelemetry::context::FutureExt as _;
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, Mutex, RwLock};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
+use wonopcode_core::budget::{BudgetStatus, BudgetTracker};
 use wonopcode_core::bus::{
-    Bus, PermissionRequest as BusPermissionRequest, SandboxState, SandboxStatusChanged,
+    BudgetExceeded, BudgetWarning, Bus, HookFailed, OrchestrationUpdated,
+    PermissionRequest as BusPermissionRequest, PromptSubmitted, ProviderDegraded,
+    ProviderRecovered, SandboxState, SandboxStatusChanged,
 };
-use wonopcode_core::config::{McpConfig, McpRemoteConfig, SandboxConfig as CoreSandboxConfig};
+use wonopcode_core::config::{McpConfig, McpRemoteConfig};
+use wonopcode_core::hook::{ShellHookEvent, ToolCall};
+use wonopcode_core::memory::MEMORY_TEMPLATE;
 use wonopcode_core::permission::{Decision, PermissionCheck, PermissionManager};
+use wonopcode_core::project::Vcs;
+use wonopcode_core::prompt;
+use wonopcode_core::spend::{SpendAmount, SpendCheck, SpendStatus, SpendTracker};
 use wonopcode_core::system_prompt;
-use wonopcode_core::Instance;
+use wonopcode_core::{
+    config_watch, render_prompt, AgentState, Command, CommandRegistry, ConfigChanged,
+    FormatterRegistry, HookEvent, HookRegistry, Instance, InstructionOptions, Instructions,
+    Orchestrator, PermissionAudit, PermissionAuditFilter, ProjectMemory, PromptConfig, PromptVars,
+    ProviderBreakers, ScopedRuleSet, ScopedRules, SessionTemplate, ShareError, ShareOptions,
+    ShareSettings, TemplateStore, Worktree, WorktreeFinish, WorktreeManager, WorktreeMerge,
+    WorktreeOutcome,
+};
 use wonopcode_mcp::{McpClient, ServerConfig as McpServerConfig};
 use wonopcode_provider::{
     anthropic::AnthropicProvider,
     claude_cli::ClaudeCliProvider,
     google::GoogleProvider,
+    message::ImageSource,
     model::ModelInfo,
     openai::OpenAIProvider,
     openrouter::OpenRouterProvider,
     stream::{FinishReason, StreamChunk},
-    BoxedLanguageModel, GenerateOptions, Message as ProviderMessage, ToolDefinition,
+    BoxedEmbeddingModel, BoxedLanguageModel, ContentPart, GenerateOptions,
+    Message as ProviderMessage, OpenAIEmbeddings, ProviderError, ToolDefinition,
 };
-use wonopcode_sandbox::{SandboxConfig, SandboxManager, SandboxRuntime, SandboxRuntimeType};
+use wonopcode_sandbox::{SandboxManager, SandboxRuntime, SandboxRuntimeType, StartProgress};
 use wonopcode_server::GitOperations;
-use wonopcode_snapshot::{SnapshotConfig, SnapshotStore};
-use wonopcode_tools::{mcp::McpToolsBuilder, task, todo, ToolRegistry};
+use wonopcode_snapshot::{ReviewFile, SnapshotConfig, SnapshotStore};
+use wonopcode_tools::{mcp::McpToolsBuilder, task, todo, FileFormatter, ToolRegistry};
 use wonopcode_tui::{
-    AppAction, AppUpdate, GitCommitUpdate, GitFileUpdate, GitStatusUpdate, LspStatusUpdate,
-    McpStatusUpdate, ModifiedFileUpdate, PermissionRequestUpdate, PhaseUpdate, SaveScope,
-    TodoUpdate,
+    AppAction, AppUpdate, CustomCommandUpdate, GitCommitUpdate, GitFileUpdate, GitStatusUpdate,
+    InstructionFileUpdate, LspStatusUpdate, McpStatusUpdate, ModifiedFileUpdate,
+    OrchestrationAgentUpdate, PermissionAuditUpdate, PermissionRequestUpdate, PhaseUpdate,
+    ProjectUpdate, ReviewedFile, SandboxResourcesUpdate, SaveScope, SymbolSuggestion, TodoUpdate,
+    ToolLatency, UsageAmount,
 };
 use wonopcode_util::perf;
 use wonopcode_util::FileTimeState;
+use wonopcode_util::Identifier;
 
-use crate::compaction::{self, CompactionConfig, CompactionResult};
+use crate::commit_message;
+use crate::compaction::{self, CompactionConfig, CompactionResult, MeasuredContext, TokenUsage};
+use crate::pinned::{self, PinnedContext};
+use crate::retrieval::{self, ContextIndex};
+use crate::telemetry;
 
 /// Helper to send updates to the TUI with proper error logging.
 /// This replaces `let _ = update_tx.send(...)` to avoid silent failures.
@@ -108,6 +133,15 @@ const AUTO_COMPACT_MESSAGE_THRESHOLD: usize = 100;
 /// Target message count after automatic compaction.
 const AUTO_COMPACT_TARGET_MESSAGES: usize = 50;
 
+/// Times in a row Stop hooks can keep the agent going before it stops anyway.
+const MAX_STOP_HOOK_BLOCKS: usize = 5;
+
+/// Number of recent permission decisions shown in the status dialog.
+const PERMISSION_AUDIT_LIMIT: usize = 100;
+
+/// Number of recent durations kept per tool for the usage dashboard.
+const MAX_TOOL_LATENCY_SAMPLES: usize = 100;
+
 /// Represents a tool call for doom loop tracking.
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ToolCallRecord {
@@ -170,6 +204,44 @@ impl DoomLoopDetector {
     }
 }
 
+/// Where each prompt starts in the conversation history, so the history can
+/// be cut back to a prompt when reverting or forking.
+#[derive(Debug, Clone, Default)]
+struct PromptMarks {
+    /// When each prompt was received and the history length before it.
+    marks: Vec<(chrono::DateTime<chrono::Utc>, usize)>,
+}
+
+impl PromptMarks {
+    /// Record a prompt about to be added to a history of `len` messages.
+    fn push(&mut self, at: chrono::DateTime<chrono::Utc>, len: usize) {
+        self.marks.push((at, len));
+    }
+
+    /// Forget all prompts, when the history is cleared.
+    fn clear(&mut self) {
+        self.marks.clear();
+    }
+
+    /// The history was compacted, so the prompts so far can no longer be told
+    /// apart: cutting back to any of them drops the whole history.
+    fn compacted(&mut self) {
+        if let Some(&(at, _)) = self.marks.last() {
+            self.marks = vec![(at, 0)];
+        }
+    }
+
+    /// The history length before the first prompt received at or after
+    /// `since`, forgetting that prompt and the ones after it. `None` if no
+    /// prompt was received since then.
+    fn cut(&mut self, since: chrono::DateTime<chrono::Utc>) -> Option<usize> {
+        let index = self.marks.iter().position(|&(at, _)| at >= since)?;
+        let len = self.marks[index].1;
+        self.marks.truncate(index);
+        Some(len)
+    }
+}
+
 /// Configuration for the runner.
 #[derive(Debug, Clone)]
 pub struct RunnerConfig {
@@ -238,8 +310,28 @@ pub struct Runner {
     cancel: Arc<RwLock<CancellationToken>>,
     /// Conversation history.
     history: RwLock<Vec<ProviderMessage>>,
-    /// Compaction configuration.
+    /// Where the prompts start in the history.
+    prompt_marks: RwLock<PromptMarks>,
+    /// Current agent.
+    agent: RwLock<String>,
+    /// Compaction configuration for the current agent.
     compaction_config: CompactionConfig,
+    /// Template the current session was started from.
+    template: RwLock<Option<SessionTemplate>>,
+    /// Prompt size reported by the provider for the last request.
+    measured_context: RwLock<Option<MeasuredContext>>,
+    /// Token, cost and tool call budget of the current session.
+    budget: Arc<Mutex<BudgetTracker>>,
+    /// Spend across sessions, against the configured alerts and limits.
+    spend: RwLock<Option<SpendTracker>>,
+    /// Formatter run on files after tools modify them.
+    formatter: RwLock<Option<Arc<dyn FileFormatter>>>,
+    /// Instruction files last reported to the UI.
+    instruction_files: RwLock<Vec<InstructionFileUpdate>>,
+    /// Directory-scoped rules activated by the files the agent worked on.
+    scoped_rules: RwLock<ScopedRuleSet>,
+    /// Embedded session context, for retrieving what compaction condensed.
+    context_index: RwLock<ContextIndex>,
     /// Snapshot store for file versioning.
     snapshot_store: Option<Arc<SnapshotStore>>,
     /// MCP client for external tools.
@@ -255,6 +347,8 @@ pub struct Runner {
     permission_manager: Arc<PermissionManager>,
     /// Event bus for permission and other events.
     bus: Bus,
+    /// Retries and circuit breakers for provider requests.
+    breakers: ProviderBreakers,
     /// File time tracker for detecting external modifications.
     file_time: Arc<FileTimeState>,
     /// Sandbox manager for isolated execution.
@@ -263,6 +357,31 @@ pub struct Runner {
     todo_store: Arc<todo::SharedFileTodoStore>,
     /// Shared LSP client for status reporting.
     lsp_client: Arc<wonopcode_lsp::LspClient>,
+    /// Custom slash commands expanded before prompts are sent.
+    commands: CommandRegistry,
+    /// Tool hooks run around every tool call.
+    hooks: Arc<RwLock<HookRegistry>>,
+    /// The latest multi-agent orchestration.
+    orchestrator: Option<Arc<Orchestrator>>,
+    /// Worktree the session works in instead of the main checkout.
+    worktree: Option<Worktree>,
+    /// Images attached to the next prompt.
+    pending_images: Vec<ContentPart>,
+    /// Files and symbols attached to the next prompt.
+    pending_context: Vec<PinnedContext>,
+    /// Files in the open change review.
+    review: Vec<ReviewFile>,
+    /// Start of the changes the review covers.
+    review_since: chrono::DateTime<chrono::Utc>,
+    /// Stop tokens of the running background processes, by PID.
+    background: Arc<RwLock<HashMap<u32, CancellationToken>>>,
+    /// Cancel tokens of the running tool calls, by call ID.
+    running_tools: Arc<RwLock<HashMap<String, CancellationToken>>>,
+    /// Durations of recent tool calls in milliseconds, by tool.
+    tool_latency: Arc<RwLock<HashMap<String, Vec<u64>>>>,
+    /// Loaded WASM plugins.
+    #[cfg(feature = "plugins")]
+    plugins: Arc<wonopcode_core::plugin::PluginManager>,
 }
 
 impl Runner {
@@ -319,6 +438,8 @@ impl Runner {
         // Create file time tracker
         let file_time = Arc::new(FileTimeState::new());
 
+        let hooks = Arc::new(RwLock::new(HookRegistry::new().with_bus(bus.clone())));
+
         Ok(Self {
             config: Arc::new(RwLock::new(config)),
             instance,
@@ -326,18 +447,42 @@ impl Runner {
             tools: Arc::new(tools),
             cancel: Arc::new(RwLock::new(CancellationToken::new())),
             history: RwLock::new(Vec::new()),
+            prompt_marks: RwLock::new(PromptMarks::default()),
+            agent: RwLock::new("build".to_string()),
             compaction_config: CompactionConfig::default(),
+            template: RwLock::new(None),
+            measured_context: RwLock::new(None),
+            budget: Arc::new(Mutex::new(BudgetTracker::default())),
+            spend: RwLock::new(None),
+            formatter: RwLock::new(None),
+            instruction_files: RwLock::new(Vec::new()),
+            scoped_rules: RwLock::new(ScopedRuleSet::default()),
+            context_index: RwLock::new(ContextIndex::default()),
             snapshot_store: None, // Will be initialized async in new_with_features
             mcp_client: None,     // Will be initialized async if configured
             external_mcp_server_names: Vec::new(), // Will be populated by initialize_mcp
             unsupported_mcp_servers: Vec::new(), // Will be populated by initialize_mcp
             doom_loop_detector: RwLock::new(DoomLoopDetector::new()),
             permission_manager,
+            breakers: ProviderBreakers::new(bus.clone()),
             bus,
             file_time,
             sandbox_manager: None, // Will be initialized async in new_with_features
             todo_store,
             lsp_client,
+            commands: CommandRegistry::new(),
+            hooks,
+            orchestrator: None,
+            worktree: None,
+            pending_images: Vec::new(),
+            pending_context: Vec::new(),
+            review: Vec::new(),
+            review_since: chrono::Utc::now(),
+            background: Arc::new(RwLock::new(HashMap::new())),
+            running_tools: Arc::new(RwLock::new(HashMap::new())),
+            tool_latency: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(feature = "plugins")]
+            plugins: Arc::new(wonopcode_core::plugin::PluginManager::empty()),
         })
     }
 
@@ -420,11 +565,39 @@ impl Runner {
             }
         }
 
+        // Record every permission decision of the project
+        let project_id = runner.instance.project_id().await;
+        runner
+            .permission_manager
+            .set_audit(Some(PermissionAudit::new(
+                runner.instance.storage().clone(),
+                project_id.clone(),
+            )))
+            .await;
+        runner.spend = RwLock::new(Some(SpendTracker::new(
+            runner.instance.storage().clone(),
+            project_id,
+            core_config.spend.clone().unwrap_or_default(),
+        )));
+
+        runner.reload_hooks(&core_config).await;
+        runner.reload_formatters(&core_config).await;
+        runner.budget = Arc::new(Mutex::new(BudgetTracker::new(
+            core_config.budget.clone().unwrap_or_default(),
+        )));
+        let agent = core_config.default_agent.as_deref().unwrap_or("build");
+        runner.compaction_config = CompactionConfig::from_config(&core_config, agent);
+        runner.agent = RwLock::new(agent.to_string());
+
         // Initialize snapshot store with proper directory
         let cwd = runner.instance.directory();
         let snapshot_dir = cwd.join(".wonopcode").join("snapshots");
 
-        match SnapshotStore::new(snapshot_dir, cwd.to_path_buf(), SnapshotConfig::default()).await {
+        let snapshot_config = SnapshotConfig {
+            track_commands: core_config.snapshot_commands.unwrap_or(false),
+            ..Default::default()
+        };
+        match SnapshotStore::new(snapshot_dir, cwd.to_path_buf(), snapshot_config).await {
             Ok(store) => {
                 info!("Snapshot store initialized");
                 runner.snapshot_store = Some(Arc::new(store));
@@ -439,7 +612,7 @@ impl Runner {
         if let Some(sandbox_cfg) = &core_config.sandbox {
             info!(enabled = ?sandbox_cfg.enabled, runtime = ?sandbox_cfg.runtime, "Sandbox config found");
             if sandbox_cfg.enabled.unwrap_or(false) {
-                let sandbox_config = convert_sandbox_config(sandbox_cfg);
+                let sandbox_config = sandbox_cfg.to_runtime_config();
                 // Use lazy initialization to avoid blocking startup with runtime detection
                 let manager = SandboxManager::new_lazy(sandbox_config, cwd.to_path_buf());
 
@@ -571,11 +744,34 @@ impl Runner {
         // This should always succeed since we just created the runner and haven't shared the Arc yet
         if let Some(tools) = Arc::get_mut(&mut runner.tools) {
             tools.register(Arc::new(skill_tool));
+            tools.register(Arc::new(wonopcode_core::memory::MemoryTool::new(cwd)));
         } else {
             // This should never happen during initialization, but log if it does
             warn!("Could not register skill tool: tools registry already shared");
         }
 
+        // Load custom commands; plugin commands don't override them
+        runner.commands.load_project(cwd, &core_config).await;
+
+        // Load WASM plugins and register their tools and commands
+        #[cfg(feature = "plugins")]
+        {
+            let plugins =
+                wonopcode_core::plugin::PluginManager::load(core_config.plugins.as_ref(), cwd)
+                    .await;
+            if !plugins.is_empty() {
+                if let Some(tools) = Arc::get_mut(&mut runner.tools) {
+                    for tool in plugins.tools() {
+                        tools.register(tool);
+                    }
+                } else {
+                    warn!("Could not register plugin tools: tools registry already shared");
+                }
+                plugins.register_commands(&mut runner.commands);
+                runner.plugins = Arc::new(plugins);
+            }
+        }
+
         // Initialize MCP client if configured
         if let Some(configs) = mcp_configs {
             if !configs.is_empty() {
@@ -587,7 +783,16 @@ impl Runner {
     }
 
     /// Initialize MCP client and connect to configured servers.
+    ///
+    /// Servers of a previous client are disconnected and their tools removed,
+    /// so servers no longer in `configs` go away.
     async fn initialize_mcp(&mut self, configs: HashMap<String, McpConfig>) {
+        let previous = self.mcp_client.take();
+        if let Some(previous) = &previous {
+            if let Err(e) = previous.close_all().await {
+                warn!(error = %e, "Failed to disconnect MCP servers");
+            }
+        }
         let mcp_client = Arc::new(McpClient::new());
 
         // Collect enabled remote server configs for parallel connection
@@ -652,7 +857,7 @@ impl Runner {
             }
         }
 
-        if connected_servers > 0 {
+        if connected_servers > 0 || previous.is_some() {
             // Register MCP tools
             let builder = McpToolsBuilder::new(mcp_client.clone());
             let mcp_tools = builder.build_all().await;
@@ -689,11 +894,528 @@ impl Runner {
                 new_tools.register(tool);
             }
 
+            // Keep plugin tools
+            #[cfg(feature = "plugins")]
+            for tool in self.plugins.tools() {
+                new_tools.register(tool);
+            }
+
             self.tools = Arc::new(new_tools);
             self.mcp_client = Some(mcp_client);
         }
     }
 
+    /// Switch to a config profile and apply its model, permissions and MCP servers.
+    async fn switch_profile(&mut self, name: &str, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        info!(profile = %name, "Switching config profile");
+        if let Err(e) = self.instance.switch_profile(name).await {
+            error!("Failed to switch profile: {}", e);
+            send_update(
+                update_tx,
+                AppUpdate::Error(format!("Failed to switch profile: {e}")),
+            );
+            return;
+        }
+        let config = self.instance.config().await;
+
+        self.permission_manager
+            .reload_from_config(&config.permission.clone().unwrap_or_default())
+            .await;
+        self.reload_hooks(&config).await;
+
+        if let Some(model) = &config.model {
+            match self.change_model(model).await {
+                Ok(()) => {
+                    let provider = self.provider.read().await;
+                    send_update(
+                        update_tx,
+                        AppUpdate::ModelInfo {
+                            context_limit: provider.model_info().limit.context,
+                        },
+                    );
+                }
+                Err(e) => warn!(model = %model, error = %e, "Failed to apply profile model"),
+            }
+        }
+
+        // Replaces the previous profile's servers
+        self.initialize_mcp(config.mcp.clone().unwrap_or_default())
+            .await;
+        let mcp_updates = self.build_mcp_status().await;
+        send_update(update_tx, AppUpdate::McpUpdated(mcp_updates));
+
+        let status = match &config.model {
+            Some(model) => format!("Switched to profile '{name}' (model: {model})"),
+            None => format!("Switched to profile '{name}'"),
+        };
+        send_update(update_tx, AppUpdate::Status(status));
+    }
+
+    /// Apply a configuration change detected by the config watcher.
+    async fn apply_config_change(
+        &mut self,
+        change: ConfigChanged,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        let config = self.instance.config().await;
+        let changed = |key: &str| change.applied.iter().any(|k| k == key);
+        let (permission, mcp, theme) = (changed("permission"), changed("mcp"), changed("theme"));
+
+        if permission {
+            self.permission_manager
+                .reload_from_config(&config.permission.clone().unwrap_or_default())
+                .await;
+        }
+
+        if changed("hooks") {
+            self.reload_hooks(&config).await;
+        }
+
+        if changed("budget") {
+            self.budget
+                .lock()
+                .await
+                .set_config(config.budget.clone().unwrap_or_default());
+        }
+
+        if changed("spend") {
+            if let Some(spend) = self.spend.write().await.as_mut() {
+                spend.set_config(config.spend.clone().unwrap_or_default());
+            }
+        }
+
+        if changed("formatter") {
+            self.reload_formatters(&config).await;
+        }
+
+        if changed("agent") {
+            let agent = self.agent.read().await.clone();
+            self.compaction_config = CompactionConfig::from_config(&config, &agent);
+        }
+
+        if mcp {
+            self.initialize_mcp(config.mcp.clone().unwrap_or_default())
+                .await;
+            let mcp_updates = self.build_mcp_status().await;
+            send_update(update_tx, AppUpdate::McpUpdated(mcp_updates));
+        }
+
+        send_update(
+            update_tx,
+            AppUpdate::ConfigReloaded {
+                theme: if theme { config.theme.clone() } else { None },
+                applied: change.applied,
+                restart_required: change.restart_required,
+            },
+        );
+    }
+
+    /// Rebuild the formatters run after file edits from the config.
+    async fn reload_formatters(&self, config: &wonopcode_core::Config) {
+        let registry =
+            FormatterRegistry::for_project(self.instance.directory(), config.formatter.as_ref());
+        *self.formatter.write().await = Some(Arc::new(registry));
+    }
+
+    /// Replace the config-declared tool rules and shell hooks.
+    async fn reload_hooks(&self, config: &wonopcode_core::Config) {
+        if config.hooks.is_some() {
+            info!("Loaded hooks from config");
+        }
+        self.hooks.write().await.configure(config.hooks.as_ref());
+    }
+
+    /// Check spend across sessions before a prompt or step.
+    ///
+    /// Alerts once per period when an alert amount is crossed. Returns the
+    /// reached limit if no more prompts may run until it is acknowledged.
+    async fn check_spend(
+        &self,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) -> Option<SpendStatus> {
+        let mut spend = self.spend.write().await;
+        let check = match spend.as_mut()?.check().await {
+            Ok(check) => check,
+            Err(e) => {
+                warn!("Failed to check spend: {}", e);
+                return None;
+            }
+        };
+        match check {
+            SpendCheck::Ok => None,
+            SpendCheck::Alert(status) => {
+                warn!(scope = status.scope.as_str(), "{}", status.describe());
+                send_update(
+                    update_tx,
+                    AppUpdate::SpendAlert {
+                        scope: status.scope.as_str().to_string(),
+                        spent: status.spent,
+                        alert: status.alert.unwrap_or_default(),
+                        message: status.describe(),
+                    },
+                );
+                send_update(
+                    update_tx,
+                    AppUpdate::SystemMessage(format!("Spend alert: {}", status.describe())),
+                );
+                None
+            }
+            SpendCheck::LimitReached(status) => {
+                warn!(
+                    scope = status.scope.as_str(),
+                    "Spending limit reached: {}",
+                    status.describe()
+                );
+                Some(status)
+            }
+        }
+    }
+
+    /// Record the cost of a model call.
+    async fn record_spend(&self, model: &str, amount: SpendAmount) {
+        if let Some(spend) = self.spend.read().await.as_ref() {
+            if let Err(e) = spend.record(model, &amount).await {
+                warn!("Failed to record spend: {}", e);
+            }
+        }
+    }
+
+    /// Report spend against the configured alerts and limits, acknowledging
+    /// reached limits first if `acknowledge` is set.
+    async fn report_spend(&self, acknowledge: bool, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        let spend = self.spend.read().await;
+        let Some(spend) = spend.as_ref() else {
+            return;
+        };
+        let mut lines = Vec::new();
+        if acknowledge {
+            match spend.acknowledge().await {
+                Ok(acknowledged) if acknowledged.is_empty() => {
+                    lines.push("No spending limit is reached.".to_string());
+                }
+                Ok(acknowledged) => {
+                    for status in acknowledged {
+                        info!(scope = status.scope.as_str(), "Spending limit acknowledged");
+                        lines.push(format!("Acknowledged: {}", status.describe()));
+                    }
+                }
+                Err(e) => {
+                    send_update(
+                        update_tx,
+                        AppUpdate::Error(format!("Failed to acknowledge spending limit: {e}")),
+                    );
+                    return;
+                }
+            }
+        }
+        match spend.statuses().await {
+            Ok(statuses) => lines.extend(statuses.iter().map(SpendStatus::describe)),
+            Err(e) => {
+                send_update(
+                    update_tx,
+                    AppUpdate::Error(format!("Failed to read spend: {e}")),
+                );
+                return;
+            }
+        }
+        send_update(update_tx, AppUpdate::SystemMessage(lines.join("\n")));
+    }
+
+    /// Send the usage dashboard data for the last `days` days.
+    ///
+    /// Cost and tokens come from the recorded spend. Tool latencies come from
+    /// saved sessions, followed by the calls made since startup.
+    async fn report_usage(&self, days: u32, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        let stats = crate::stats::aggregate_instance_stats(&self.instance, Some(days), None).await;
+        let amount = |(name, amount): (String, SpendAmount)| UsageAmount {
+            name,
+            cost: amount.cost,
+            input_tokens: amount.input_tokens,
+            output_tokens: amount.output_tokens,
+        };
+        let spend = stats.spend.unwrap_or_default();
+        let daily = spend.days.into_iter().map(amount).collect();
+        let models = spend.models.into_iter().map(amount).collect();
+
+        let mut latency = stats.tool_latency;
+        for (tool, durations) in self.tool_latency.read().await.iter() {
+            latency
+                .entry(tool.clone())
+                .or_default()
+                .extend(durations.iter().copied());
+        }
+        let tools = latency
+            .into_iter()
+            .map(|(tool, durations_ms)| ToolLatency { tool, durations_ms })
+            .collect();
+
+        send_update(
+            update_tx,
+            AppUpdate::Usage {
+                days,
+                daily,
+                models,
+                tools,
+            },
+        );
+    }
+
+    /// Ask the user whether to continue past a reached budget limit (never
+    /// auto-approved by permission rules).
+    ///
+    /// Returns `false` if the prompt should stop.
+    async fn continue_past_budget(
+        &self,
+        status: BudgetStatus,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) -> bool {
+        let check = PermissionCheck {
+            id: format!("budget_{}", uuid::Uuid::new_v4()),
+            tool: "budget".to_string(),
+            action: "continue".to_string(),
+            description: format!("{}. Continue with another budget?", status.describe()),
+            path: None,
+            details: serde_json::to_value(&status).unwrap_or_default(),
+        };
+        if self.permission_manager.confirm("default", check).await {
+            info!(limit = status.limit.as_str(), "Session budget extended");
+            self.budget.lock().await.extend();
+            true
+        } else {
+            send_update(
+                update_tx,
+                AppUpdate::SystemMessage(format!(
+                    "Stopped: {}. Send another message to continue.",
+                    status.describe()
+                )),
+            );
+            false
+        }
+    }
+
+    /// Token usage of `messages`, using the last measured prompt size if it
+    /// still applies.
+    async fn context_usage(&self, messages: &[ProviderMessage]) -> TokenUsage {
+        let measured = *self.measured_context.read().await;
+        compaction::context_usage(messages, measured)
+    }
+
+    /// Tell the user what compaction is about to condense.
+    fn send_compaction_preview(
+        &self,
+        messages: &[ProviderMessage],
+        tokens: &TokenUsage,
+        context_limit: u32,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        if !self.compaction_config.preview {
+            return;
+        }
+        let preview = compaction::preview(messages, &self.compaction_config, tokens, context_limit);
+        if !preview.is_empty() {
+            send_update(update_tx, AppUpdate::SystemMessage(preview.describe()));
+        }
+    }
+
+    /// Expand a `/command args` prompt using the custom command registry.
+    ///
+    /// Prompts that don't name a custom command are returned unchanged,
+    /// without a command.
+    fn expand_custom_command(&self, text: String) -> (String, Option<Command>) {
+        let Some(stripped) = text.strip_prefix('/') else {
+            return (text, None);
+        };
+        let (name, args) = stripped.split_once(' ').unwrap_or((stripped, ""));
+        match self.commands.get(name) {
+            Some(command) => {
+                debug!(command = %name, "Expanding custom command");
+                (command.expand(args.trim()), Some(command.clone()))
+            }
+            None => (text, None),
+        }
+    }
+
+    /// Switch to a custom command's agent and model for one prompt.
+    ///
+    /// Returns the agent and model to switch back to afterwards.
+    async fn apply_command_overrides(
+        &mut self,
+        command: &Command,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) -> (Option<String>, Option<String>) {
+        let mut previous_agent = None;
+        if let Some(agent) = &command.agent {
+            let current = self.agent.read().await.clone();
+            if *agent != current {
+                self.set_agent(agent).await;
+                previous_agent = Some(current);
+            }
+        }
+
+        let mut previous_model = None;
+        if let Some(model) = &command.model {
+            let current = {
+                let config = self.config.read().await;
+                format!("{}/{}", config.provider, config.model_id)
+            };
+            if *model != current {
+                match self.change_model(model).await {
+                    Ok(()) => previous_model = Some(current),
+                    Err(e) => {
+                        warn!(model = %model, error = %e, "Failed to switch to command model");
+                        send_update(
+                            update_tx,
+                            AppUpdate::Status(format!("Command model {model} unavailable: {e}")),
+                        );
+                    }
+                }
+            }
+        }
+        (previous_agent, previous_model)
+    }
+
+    /// Variables for prompt templates in `cwd`.
+    async fn prompt_vars(&self, cwd: &Path) -> PromptVars {
+        let mut vars = PromptVars::detect(cwd).with("agent", self.agent.read().await.clone());
+        {
+            let config = self.config.read().await;
+            vars.set("provider", config.provider.clone());
+            vars.set("model", config.model_id.clone());
+        }
+        match &self.instance.config().await.prompt_variables {
+            Some(custom) => vars.with_custom(custom),
+            None => vars,
+        }
+    }
+
+    /// Compose the instruction files and project memory for the system
+    /// prompt, reporting the files to the UI when they change.
+    async fn compose_instructions(
+        &self,
+        cwd: &Path,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) -> Option<String> {
+        let config = self.instance.config().await;
+        let instructions = Instructions::discover(cwd, &InstructionOptions::from_config(&config));
+
+        let mut report = instruction_report(&instructions);
+        report.extend(scoped_rules_report(self.scoped_rules.read().await.active()));
+        let mut reported = self.instruction_files.write().await;
+        if *reported != report {
+            *reported = report.clone();
+            send_update(update_tx, AppUpdate::Instructions(report));
+        }
+
+        let sections: Vec<String> = instructions
+            .render()
+            .into_iter()
+            .chain(ProjectMemory::new(cwd).prompt_section())
+            .collect();
+        if sections.is_empty() {
+            None
+        } else {
+            Some(sections.join("\n\n"))
+        }
+    }
+
+    /// Activate the scoped rules for the files the tool calls read or edit,
+    /// reporting them to the UI when new rules apply.
+    async fn activate_scoped_rules(
+        &self,
+        cwd: &Path,
+        tool_calls: &[(String, String, String)],
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        let mut scoped_rules = self.scoped_rules.write().await;
+        let mut activated = Vec::new();
+        for (_, tool_name, args_str) in tool_calls {
+            for path in tool_file_paths(tool_name, args_str) {
+                activated.extend(scoped_rules.activate_for(cwd, &cwd.join(path)));
+            }
+        }
+        if activated.is_empty() {
+            return;
+        }
+        for rules in &activated {
+            info!(rules = %rules.display, "Activated scoped rules");
+        }
+
+        let mut reported = self.instruction_files.write().await;
+        reported.extend(scoped_rules_report(&activated));
+        send_update(update_tx, AppUpdate::Instructions(reported.clone()));
+    }
+
+    /// Embedding model for retrieving compacted context, if configured.
+    fn embedding_model(&self) -> Option<BoxedEmbeddingModel> {
+        let spec = self.compaction_config.embedding_model.as_deref()?;
+        let (provider, model) = spec.split_once('/').unwrap_or(("openai", spec));
+        let api_key = load_api_key(provider).unwrap_or_default();
+        let embeddings = match &self.compaction_config.embedding_url {
+            Some(url) => OpenAIEmbeddings::with_base_url(&api_key, url, model),
+            None => OpenAIEmbeddings::for_provider(provider, &api_key, model),
+        };
+        match embeddings {
+            Ok(embeddings) => Some(Arc::new(embeddings)),
+            Err(e) => {
+                warn!(model = %spec, error = %e, "Embedding model unavailable");
+                None
+            }
+        }
+    }
+
+    /// Index the session before compaction, so the parts it condenses can be
+    /// retrieved for later prompts.
+    async fn index_context(&self, messages: &[ProviderMessage]) {
+        let Some(model) = self.embedding_model() else {
+            return;
+        };
+        match retrieval::index_messages(&self.context_index, model.as_ref(), messages).await {
+            Ok(0) => {}
+            Ok(added) => {
+                let total = self.context_index.read().await.len();
+                debug!(added, total, "Indexed session context");
+            }
+            Err(e) => warn!(error = %e, "Failed to index session context"),
+        }
+    }
+
+    /// Compacted context relevant to the prompt, formatted for the system
+    /// prompt.
+    async fn retrieve_context(&self, prompt: &str, messages: &[ProviderMessage]) -> Option<String> {
+        if self.context_index.read().await.is_empty() {
+            return None;
+        }
+        let model = self.embedding_model()?;
+        let query = match model.embed(&[prompt.to_string()]).await {
+            Ok(mut embeddings) => embeddings.pop()?,
+            Err(e) => {
+                warn!(error = %e, "Failed to embed prompt for retrieval");
+                return None;
+            }
+        };
+
+        let index = self.context_index.read().await;
+        let chunks = index.retrieve(&query, messages, self.compaction_config.retrieval_tokens);
+        if !chunks.is_empty() {
+            info!(chunks = chunks.len(), "Retrieved compacted context");
+        }
+        retrieval::render(&chunks)
+    }
+
+    /// Set the agent used for compaction and templates.
+    async fn set_agent(&mut self, agent: &str) {
+        let core_config = self.instance.config().await;
+        self.compaction_config = CompactionConfig::from_config(&core_config, agent);
+        *self.agent.write().await = agent.to_string();
+    }
+
+    /// Deliver a hook event to plugins that handle it.
+    #[cfg_attr(not(feature = "plugins"), allow(unused_variables))]
+    fn dispatch_plugin_event(&self, event: HookEvent, payload: serde_json::Value) {
+        #[cfg(feature = "plugins")]
+        self.plugins.dispatch(event, payload);
+    }
+
     /// Get the current cancellation token.
     async fn get_cancel_token(&self) -> CancellationToken {
         self.cancel.read().await.clone()
@@ -705,6 +1427,63 @@ impl Runner {
         *guard = CancellationToken::new();
     }
 
+    /// Cancel a running tool call. The model gets an error for it and the
+    /// prompt goes on.
+    async fn cancel_tool(&self, id: &str, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        match self.running_tools.read().await.get(id) {
+            Some(cancel) => {
+                info!(call_id = %id, "Cancelling tool call");
+                cancel.cancel();
+            }
+            None => send_update(
+                update_tx,
+                AppUpdate::Error(format!("No running tool call with ID {id}")),
+            ),
+        }
+    }
+
+    /// Search the language servers' workspace symbols for an `@` mention,
+    /// sending what they find to the UI.
+    fn search_symbols(
+        &self,
+        query: String,
+        cwd: &Path,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        let lsp_client = self.lsp_client.clone();
+        let cwd = cwd.to_path_buf();
+        let update_tx = update_tx.clone();
+        tokio::spawn(async move {
+            let symbols = match lsp_client.workspace_symbol(&query).await {
+                Ok(symbols) => symbols,
+                Err(e) => {
+                    debug!(error = %e, "Workspace symbol search failed");
+                    return;
+                }
+            };
+            let symbols = symbols
+                .into_iter()
+                .map(|symbol| {
+                    let uri = symbol.location.uri.as_str();
+                    let path = Path::new(uri.strip_prefix("file://").unwrap_or(uri));
+                    let range = symbol.location.range;
+                    SymbolSuggestion {
+                        name: symbol.name,
+                        kind: symbol_kind_name(symbol.kind).to_string(),
+                        path: path
+                            .strip_prefix(&cwd)
+                            .unwrap_or(path)
+                            .display()
+                            .to_string(),
+                        start_line: range.start.line + 1,
+                        end_line: range.end.line + 1,
+                    }
+                })
+                .collect();
+            send_update(&update_tx, AppUpdate::Symbols { query, symbols });
+        });
+    }
+
     /// Send LSP status updates to the UI.
     async fn send_lsp_status(&self, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
         let servers = self.lsp_client.status().await;
@@ -833,7 +1612,7 @@ impl Runner {
 
     /// Run the action handler loop.
     pub async fn run(
-        self,
+        mut self,
         mut action_rx: mpsc::UnboundedReceiver<AppAction>,
         update_tx: mpsc::UnboundedSender<AppUpdate>,
     ) {
@@ -856,9 +1635,124 @@ impl Runner {
             }
         });
 
-        // Send initial model info
-        {
-            let provider = self.provider.read().await;
+        // Surface shell hook failures
+        let mut hook_failed_rx = self.bus.subscribe::<HookFailed>().await;
+        let hook_update_tx = update_tx.clone();
+        tokio::spawn(async move {
+            while let Ok(failure) = hook_failed_rx.recv().await {
+                let _ = hook_update_tx.send(AppUpdate::SystemMessage(format!(
+                    "{} hook `{}` failed: {}",
+                    failure.event, failure.command, failure.error
+                )));
+            }
+        });
+
+        // Surface budget warnings and limits, including those of orchestrated agents
+        let mut budget_warning_rx = self.bus.subscribe::<BudgetWarning>().await;
+        let mut budget_exceeded_rx = self.bus.subscribe::<BudgetExceeded>().await;
+        let budget_update_tx = update_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let update = tokio::select! {
+                    Ok(event) = budget_warning_rx.recv() => AppUpdate::BudgetWarning {
+                        limit: event.status.limit.as_str().to_string(),
+                        used: event.status.used,
+                        max: event.status.max,
+                        message: event.status.describe(),
+                    },
+                    Ok(event) = budget_exceeded_rx.recv() => AppUpdate::Status(format!(
+                        "Session budget reached: {}",
+                        event.status.describe()
+                    )),
+                    else => break,
+                };
+                if budget_update_tx.send(update).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Show provider backoff instead of silently stalling
+        let mut degraded_rx = self.bus.subscribe::<ProviderDegraded>().await;
+        let mut recovered_rx = self.bus.subscribe::<ProviderRecovered>().await;
+        let provider_update_tx = update_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                let status = tokio::select! {
+                    Ok(event) = degraded_rx.recv() => {
+                        let state = if event.circuit_open { "unavailable" } else { "degraded" };
+                        format!(
+                            "Provider {} {} ({}), backing off {}s",
+                            event.provider,
+                            state,
+                            event.reason.to_lowercase(),
+                            event.retry_in_ms.div_ceil(1000)
+                        )
+                    }
+                    Ok(event) = recovered_rx.recv() => {
+                        format!("Provider {} recovered", event.provider)
+                    }
+                    else => break,
+                };
+                if provider_update_tx.send(AppUpdate::Status(status)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        // Show the sandbox's resource usage while it runs
+        if let Some(manager) = &self.sandbox_manager {
+            let mut resources_rx = manager.subscribe_resources();
+            let resources_update_tx = update_tx.clone();
+            tokio::spawn(async move {
+                loop {
+                    let stats = match resources_rx.recv().await {
+                        Ok(stats) => stats,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    let update = AppUpdate::SandboxResources(SandboxResourcesUpdate {
+                        cpu_percent: stats.cpu_percent,
+                        memory_bytes: stats.memory_bytes,
+                        memory_limit: stats.memory_limit,
+                        pids: stats.pids,
+                    });
+                    if resources_update_tx.send(update).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        // Forward orchestration progress to the coordinator view
+        let mut orchestration_rx = self.bus.subscribe::<OrchestrationUpdated>().await;
+        let orchestration_update_tx = update_tx.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = orchestration_rx.recv().await {
+                let agents = event
+                    .agents
+                    .into_iter()
+                    .map(|a| OrchestrationAgentUpdate {
+                        name: a.name,
+                        task: a.task,
+                        state: a.state.as_str().to_string(),
+                        branch: a.branch,
+                        steps: a.steps,
+                        tool_calls: a.tool_calls,
+                        changed_files: a.changed_files,
+                        summary: a.summary,
+                    })
+                    .collect();
+                let _ = orchestration_update_tx.send(AppUpdate::Orchestration {
+                    id: event.orchestration_id,
+                    agents,
+                });
+            }
+        });
+
+        // Send initial model info
+        {
+            let provider = self.provider.read().await;
             let model_info = provider.model_info();
             send_update(
                 &update_tx,
@@ -934,10 +1828,109 @@ impl Runner {
             }
         }
 
-        while let Some(action) = action_rx.recv().await {
+        // Send storage usage and warn about exceeded soft quotas
+        match self.instance.storage_usage().await {
+            Ok(usage) => send_update(
+                &update_tx,
+                AppUpdate::StorageUsage {
+                    total_bytes: usage.stats.total_bytes(),
+                    quota_bytes: usage.quota,
+                    warnings: usage.warnings.iter().map(|w| w.to_string()).collect(),
+                },
+            ),
+            Err(e) => warn!("Failed to collect storage usage: {}", e),
+        }
+
+        // Report the instruction files the system prompt will include
+        self.compose_instructions(&cwd, &update_tx).await;
+
+        // Send custom slash commands so the TUI can offer them
+        if !self.commands.is_empty() {
+            let commands = self
+                .commands
+                .list()
+                .into_iter()
+                .map(|c| CustomCommandUpdate {
+                    name: c.name.clone(),
+                    description: c.description.clone(),
+                })
+                .collect();
+            send_update(&update_tx, AppUpdate::CustomCommands(commands));
+        }
+
+        // Watch config files and apply safe changes at runtime
+        let mut config_rx = self.bus.subscribe::<ConfigChanged>().await;
+        let config_watcher = config_watch::spawn(
+            self.instance.clone(),
+            self.bus.clone(),
+            config_watch::DEFAULT_POLL_INTERVAL,
+        );
+
+        loop {
+            let action = tokio::select! {
+                action = action_rx.recv() => match action {
+                    Some(action) => action,
+                    None => break,
+                },
+                Ok(change) = config_rx.recv() => {
+                    self.apply_config_change(change, &update_tx).await;
+                    continue;
+                }
+            };
+
             match action {
+                AppAction::AttachImage { media_type, data } => {
+                    debug!(media_type = %media_type, bytes = data.len(), "Attaching image");
+                    self.pending_images.push(ContentPart::Image {
+                        source: ImageSource::Base64 { media_type, data },
+                    });
+                }
+                AppAction::AttachContext {
+                    path,
+                    symbol,
+                    lines,
+                } => {
+                    debug!(path = %path, "Attaching context");
+                    self.pending_context.push(PinnedContext {
+                        path,
+                        symbol,
+                        lines,
+                    });
+                }
+                AppAction::SearchSymbols { query } => self.search_symbols(query, &cwd, &update_tx),
                 AppAction::SendPrompt(text) => {
                     debug!(prompt_text = %text, "Received SendPrompt action");
+                    let images = std::mem::take(&mut self.pending_images);
+                    let attached = std::mem::take(&mut self.pending_context);
+                    self.bus
+                        .publish(PromptSubmitted { text: text.clone() })
+                        .await;
+                    if let Some(status) = self.check_spend(&update_tx).await {
+                        send_update(
+                            &update_tx,
+                            AppUpdate::SystemMessage(format!(
+                                "{}. New prompts are blocked until you run /spend ack.",
+                                status.describe()
+                            )),
+                        );
+                        send_update(
+                            &update_tx,
+                            AppUpdate::Error("Spending limit reached".to_string()),
+                        );
+                        continue;
+                    }
+                    let (text, command) = self.expand_custom_command(text);
+                    let (previous_agent, previous_model) = match &command {
+                        Some(command) => self.apply_command_overrides(command, &update_tx).await,
+                        None => (None, None),
+                    };
+                    let allowed_tools = command
+                        .map(|c| c.allowed_tools)
+                        .filter(|tools| !tools.is_empty());
+                    self.dispatch_plugin_event(
+                        HookEvent::MessageSent,
+                        serde_json::json!({ "text": text }),
+                    );
                     // Reset cancellation token for new prompt
                     self.reset_cancel_token().await;
 
@@ -951,63 +1944,99 @@ impl Runner {
                     let cancel_token = self.get_cancel_token().await;
 
                     // Use a loop to process Cancel actions while the prompt runs
-                    let prompt_future = self.run_prompt(&text, &cwd, &update_tx);
-                    tokio::pin!(prompt_future);
-
-                    let result = loop {
-                        tokio::select! {
-                            biased;
-
-                            // Check for incoming actions (especially Cancel)
-                            Some(inner_action) = action_rx.recv() => {
-                                match inner_action {
-                                    AppAction::Cancel => {
-                                        info!("Cancelling current operation");
-                                        cancel_token.cancel();
-                                        // Don't break - let the prompt handle the cancellation
-                                    }
-                                    AppAction::Quit => {
-                                        info!("Quit requested during prompt");
-                                        cancel_token.cancel();
-                                        // Return after prompt finishes
-                                    }
-                                    AppAction::PermissionResponse {
-                                        request_id,
-                                        allow,
-                                        remember,
-                                    } => {
-                                        // Permission responses must be handled even during prompt execution
-                                        // because MCP tools wait for them
-                                        info!(
-                                            request_id = %request_id,
-                                            allow = allow,
-                                            remember = remember,
-                                            "Received permission response during prompt execution"
-                                        );
-                                        self.permission_manager
-                                            .respond(&request_id, allow, remember)
-                                            .await;
-                                    }
-                                    _ => {
-                                        // Ignore other actions during prompt execution
-                                        debug!("Ignoring action during prompt execution: {:?}", inner_action);
+                    let prompt_cwd = self
+                        .worktree
+                        .as_ref()
+                        .map_or_else(|| cwd.clone(), |w| w.path.clone());
+                    let trace = {
+                        let agent = self.agent.read().await.clone();
+                        let provider = self.provider.read().await;
+                        let model_info = provider.model_info();
+                        telemetry::PromptTrace::start(
+                            &model_info.provider_id,
+                            &model_info.id,
+                            &agent,
+                        )
+                    };
+                    let result = {
+                        let prompt_future = self
+                            .run_prompt(
+                                &text,
+                                images,
+                                &attached,
+                                &prompt_cwd,
+                                allowed_tools.as_deref(),
+                                &update_tx,
+                            )
+                            .with_context(trace.context());
+                        tokio::pin!(prompt_future);
+
+                        loop {
+                            tokio::select! {
+                                biased;
+
+                                // Check for incoming actions (especially Cancel)
+                                Some(inner_action) = action_rx.recv() => {
+                                    match inner_action {
+                                        AppAction::Cancel => {
+                                            info!("Cancelling current operation");
+                                            cancel_token.cancel();
+                                            // Don't break - let the prompt handle the cancellation
+                                        }
+                                        AppAction::Quit => {
+                                            info!("Quit requested during prompt");
+                                            cancel_token.cancel();
+                                            // Return after prompt finishes
+                                        }
+                                        AppAction::PermissionResponse {
+                                            request_id,
+                                            allow,
+                                            remember,
+                                        } => {
+                                            // Permission responses must be handled even during prompt execution
+                                            // because MCP tools wait for them
+                                            info!(
+                                                request_id = %request_id,
+                                                allow = allow,
+                                                remember = remember,
+                                                "Received permission response during prompt execution"
+                                            );
+                                            self.permission_manager
+                                                .respond(&request_id, allow, remember)
+                                                .await;
+                                        }
+                                        AppAction::CancelTool { id } => {
+                                            self.cancel_tool(&id, &update_tx).await;
+                                        }
+                                        AppAction::SearchSymbols { query } => {
+                                            self.search_symbols(query, &cwd, &update_tx);
+                                        }
+                                        _ => {
+                                            // Ignore other actions during prompt execution
+                                            debug!("Ignoring action during prompt execution: {:?}", inner_action);
+                                        }
                                     }
                                 }
-                            }
 
-                            // Wait for prompt to complete
-                            res = &mut prompt_future => {
-                                break res;
+                                // Wait for prompt to complete
+                                res = &mut prompt_future => {
+                                    break res;
+                                }
                             }
                         }
                     };
 
+                    trace.finish(result.as_ref().err().map(|e| e.to_string()).as_deref());
                     match result {
                         Ok(result_text) => {
                             info!(
                                 result_len = result_text.len(),
                                 "Prompt completed successfully"
                             );
+                            self.dispatch_plugin_event(
+                                HookEvent::SessionCompleted,
+                                serde_json::json!({ "text": result_text }),
+                            );
                             send_update(&update_tx, AppUpdate::Completed { text: result_text });
 
                             // Sync todos to TUI
@@ -1024,6 +2053,16 @@ impl Runner {
                             }
                         }
                     }
+
+                    // A command's agent and model only apply to its prompt
+                    if let Some(agent) = previous_agent {
+                        self.set_agent(&agent).await;
+                    }
+                    if let Some(model) = previous_model {
+                        if let Err(e) = self.change_model(&model).await {
+                            warn!(model = %model, error = %e, "Failed to switch back after command");
+                        }
+                    }
                 }
                 AppAction::Cancel => {
                     // Cancel received outside of prompt execution - just log it
@@ -1035,11 +2074,14 @@ impl Runner {
                 }
                 AppAction::SwitchSession(session_id) => {
                     info!(session_id = %session_id, "Switching session");
+                    self.leave_worktree(&update_tx);
                     // Clear history for session switch
-                    {
-                        let mut history = self.history.write().await;
-                        history.clear();
-                    }
+                    self.history.write().await.clear();
+                    self.prompt_marks.write().await.clear();
+                    self.budget.lock().await.reset();
+                    self.scoped_rules.write().await.clear();
+                    self.context_index.write().await.clear();
+                    *self.template.write().await = None;
                 }
                 AppAction::ChangeModel(model_spec) => {
                     info!(model = %model_spec, "Changing model");
@@ -1061,6 +2103,7 @@ impl Runner {
                 }
                 AppAction::ChangeAgent(agent_name) => {
                     info!(agent = %agent_name, "Changing agent");
+                    self.set_agent(&agent_name).await;
                     // Agent change is mostly a TUI concern for now
                     // Future: could change tool permissions, system prompt, etc.
                     let _ =
@@ -1068,15 +2111,112 @@ impl Runner {
                 }
                 AppAction::NewSession => {
                     info!("Creating new session");
+                    self.leave_worktree(&update_tx);
                     // Clear history for new session
-                    {
-                        let mut history = self.history.write().await;
-                        history.clear();
-                    }
+                    self.history.write().await.clear();
+                    self.prompt_marks.write().await.clear();
+                    self.budget.lock().await.reset();
+                    self.scoped_rules.write().await.clear();
+                    self.context_index.write().await.clear();
+                    *self.template.write().await = None;
+                    self.review.clear();
+                    self.review_since = chrono::Utc::now();
                 }
                 AppAction::OpenEditor { .. } => {
                     // Editor is handled synchronously in the TUI, nothing to do here
                 }
+                AppAction::LoadMemory => {
+                    let memory = ProjectMemory::new(&cwd);
+                    match memory.read() {
+                        Ok(content) if content.trim().is_empty() => {
+                            send_update(&update_tx, AppUpdate::Memory(MEMORY_TEMPLATE.to_string()));
+                        }
+                        Ok(content) => send_update(&update_tx, AppUpdate::Memory(content)),
+                        Err(e) => {
+                            error!("Failed to read project memory: {}", e);
+                            send_update(
+                                &update_tx,
+                                AppUpdate::Error(format!("Failed to read project memory: {e}")),
+                            );
+                        }
+                    }
+                }
+                AppAction::LoadPermissionAudit => {
+                    let audit = PermissionAudit::new(
+                        self.instance.storage().clone(),
+                        self.instance.project_id().await,
+                    );
+                    let filter = PermissionAuditFilter {
+                        limit: Some(PERMISSION_AUDIT_LIMIT),
+                        ..Default::default()
+                    };
+                    match audit.list(&filter).await {
+                        Ok(entries) => {
+                            let entries = entries
+                                .into_iter()
+                                .map(|e| PermissionAuditUpdate {
+                                    source: e.source(),
+                                    time: e.time,
+                                    tool: e.tool,
+                                    action: e.action,
+                                    path: e.path,
+                                    description: e.description,
+                                    allowed: e.allowed,
+                                    prompted: e.prompted,
+                                })
+                                .collect();
+                            send_update(&update_tx, AppUpdate::PermissionAudit(entries));
+                        }
+                        Err(e) => {
+                            warn!("Failed to read permission audit log: {}", e);
+                        }
+                    }
+                }
+                AppAction::LoadSpend => {
+                    self.report_spend(false, &update_tx).await;
+                }
+                AppAction::AcknowledgeSpendLimit => {
+                    self.report_spend(true, &update_tx).await;
+                }
+                AppAction::LoadUsage { days } => {
+                    self.report_usage(days, &update_tx).await;
+                }
+                AppAction::StartReview => {
+                    self.start_review(&update_tx).await;
+                }
+                AppAction::ApplyReview { files } => {
+                    self.apply_review(files, &update_tx).await;
+                }
+                AppAction::CancelTool { id } => self.cancel_tool(&id, &update_tx).await,
+                AppAction::StopProcess { pid } => match self.background.read().await.get(&pid) {
+                    Some(stop) => stop.cancel(),
+                    None => send_update(
+                        &update_tx,
+                        AppUpdate::Error(format!("No running background process with PID {pid}")),
+                    ),
+                },
+                AppAction::SaveMemory { content } => {
+                    let memory = ProjectMemory::new(&cwd);
+                    match memory.write(&content) {
+                        Ok(()) => {
+                            info!(path = %memory.path().display(), "Project memory saved");
+                            send_update(
+                                &update_tx,
+                                AppUpdate::SystemMessage(format!(
+                                    "Project memory saved to {}",
+                                    memory.path().display()
+                                )),
+                            );
+                        }
+                        Err(e) => {
+                            error!("Failed to save project memory: {}", e);
+                            send_update(
+                                &update_tx,
+                                AppUpdate::Error(format!("Failed to save project memory: {e}")),
+                            );
+                        }
+                    }
+                }
                 AppAction::Undo => {
                     info!("Undo requested");
                     // For now, history sync is handled by the TUI
@@ -1112,20 +2252,34 @@ impl Runner {
                         part_id: None,
                     };
 
-                    match session_revert.revert(&project_id, input).await {
-                        Ok(_session) => {
-                            // Also clear runner's in-memory history after the revert point
-                            let mut history = self.history.write().await;
-                            // Find and truncate history at the revert point
-                            // For now, clear all - proper implementation would track message IDs
-                            history.clear();
+                    let since = self
+                        .revert_point(&project_id, &session_id, &message_id)
+                        .await;
+                    let reverted = session_revert.revert(&project_id, input).await;
+                    if let Err(e) = &reverted {
+                        warn!(error = %e, "Failed to revert session");
+                    }
+
+                    // The in-memory history and the files can be reverted even
+                    // when the session was never stored, as long as we know when
+                    // the message was sent
+                    match (reverted, since) {
+                        (Err(e), None) => {
+                            let _ =
+                                update_tx.send(AppUpdate::Status(format!("Revert failed: {e}")));
+                        }
+                        (_, Some(since)) => {
+                            self.truncate_history(since).await;
+                            self.rollback_files(&session_id, since, &update_tx).await;
                             let _ =
                                 update_tx.send(AppUpdate::Status("Revert complete".to_string()));
                         }
-                        Err(e) => {
-                            warn!(error = %e, "Failed to revert session");
+                        (Ok(_session), None) => {
+                            warn!(message_id = %message_id, "Unknown revert point, history cleared");
+                            self.history.write().await.clear();
+                            self.prompt_marks.write().await.clear();
                             let _ =
-                                update_tx.send(AppUpdate::Status(format!("Revert failed: {e}")));
+                                update_tx.send(AppUpdate::Status("Revert complete".to_string()));
                         }
                     }
                 }
@@ -1141,16 +2295,20 @@ impl Runner {
                     // Get current session ID
                     let session_id = "default".to_string();
 
-                    match session_revert.unrevert(&project_id, &session_id).await {
-                        Ok(_session) => {
-                            let _ =
-                                update_tx.send(AppUpdate::Status("Unrevert complete".to_string()));
-                        }
-                        Err(e) => {
+                    // The files can be brought back even when the session was
+                    // never stored
+                    let unreverted = session_revert.unrevert(&project_id, &session_id).await;
+                    let restored = self.undo_rollback(&session_id, &update_tx).await;
+                    match (unreverted, restored) {
+                        (Err(e), false) => {
                             warn!(error = %e, "Failed to unrevert session");
                             let _ =
                                 update_tx.send(AppUpdate::Status(format!("Unrevert failed: {e}")));
                         }
+                        _ => {
+                            let _ =
+                                update_tx.send(AppUpdate::Status("Unrevert complete".to_string()));
+                        }
                     }
                 }
                 AppAction::Compact => {
@@ -1172,15 +2330,21 @@ impl Runner {
                         continue;
                     }
 
-                    // Get context limit and estimate token usage
-                    let (context_limit, estimated_tokens) = {
+                    // Get context limit and token usage
+                    let context_limit = {
                         let provider = self.provider.read().await;
-                        let limit = provider.model_info().limit.context;
-                        let tokens = compaction::estimate_token_usage(&messages);
-                        (limit, tokens)
+                        provider.model_info().limit.context
                     };
+                    let estimated_tokens = self.context_usage(&messages).await;
+                    self.send_compaction_preview(
+                        &messages,
+                        &estimated_tokens,
+                        context_limit,
+                        &update_tx,
+                    );
 
                     // Perform full compaction: prune first, then summarize if still needed
+                    self.index_context(&messages).await;
                     let provider = self.provider.read().await;
                     match compaction::compact(
                         &mut messages,
@@ -1210,10 +2374,7 @@ impl Runner {
                             );
 
                             // Update history
-                            {
-                                let mut history = self.history.write().await;
-                                *history = new_messages;
-                            }
+                            self.set_compacted_history(new_messages).await;
 
                             let status = if messages_summarized > 0 {
                                 format!("Compacted {messages_summarized} messages")
@@ -1327,20 +2488,30 @@ impl Runner {
                 AppAction::ForkSession { message_id } => {
                     info!(message_id = ?message_id, "Fork session requested");
                     let project_id = self.instance.project_id().await;
-                    let session_repo = self.instance.session_repo();
+                    let session_repo = Arc::new(self.instance.session_repo());
+                    let branches =
+                        wonopcode_core::SessionBranch::new(session_repo, self.bus.clone());
 
-                    // Fork the current session
-                    match session_repo
+                    // Fork the current session into a branch
+                    match branches
                         .fork(&project_id, "default", message_id.as_deref())
                         .await
                     {
                         Ok(forked) => {
                             info!(forked_id = %forked.id, "Session forked successfully");
-                            // Clear runner's history for the new session
-                            {
-                                let mut history = self.history.write().await;
-                                history.clear();
+                            // The fork continues the conversation up to the fork point
+                            if let Some(message_id) = &message_id {
+                                match self.revert_point(&project_id, "default", message_id).await {
+                                    Some(since) => self.truncate_history(since).await,
+                                    None => {
+                                        self.history.write().await.clear();
+                                        self.prompt_marks.write().await.clear();
+                                    }
+                                }
                             }
+                            self.budget.lock().await.reset();
+                            self.scoped_rules.write().await.clear();
+                            self.context_index.write().await.clear();
                             send_update(
                                 &update_tx,
                                 AppUpdate::Status(format!(
@@ -1355,26 +2526,80 @@ impl Runner {
                         }
                     }
                 }
-                AppAction::ShareSession => {
+                AppAction::MergeSession => {
+                    info!("Merge session requested");
+                    let project_id = self.instance.project_id().await;
+                    let session_repo = Arc::new(self.instance.session_repo());
+                    let branches =
+                        wonopcode_core::SessionBranch::new(session_repo, self.bus.clone())
+                            .with_snapshots(self.snapshot_store.clone());
+
+                    match branches
+                        .merge(&project_id, "default", self.instance.directory())
+                        .await
+                    {
+                        Ok(result) => {
+                            let mut status =
+                                format!("Merged branch: {} file(s) applied", result.applied.len());
+                            if !result.conflicts.is_empty() {
+                                status.push_str(&format!(
+                                    ", conflicts in {}",
+                                    result.conflicts.join(", ")
+                                ));
+                            }
+                            send_update(&update_tx, AppUpdate::Status(status));
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "Failed to merge session");
+                            send_update(&update_tx, AppUpdate::Error(format!("Merge failed: {e}")));
+                        }
+                    }
+                }
+                AppAction::ShareSession { expires, password } => {
                     info!("Share session requested");
                     let project_id = self.instance.project_id().await;
+                    let session_repo = self.instance.session_repo();
+                    let config = self.instance.config().await;
 
                     // Use the share module to create a share
-                    let session_repo = self.instance.session_repo();
-                    match wonopcode_core::share::share_session(
-                        &session_repo,
-                        &project_id,
-                        "default",
-                        None,
-                    )
-                    .await
-                    {
+                    let result = async {
+                        let settings = ShareSettings::from_config(&config)?;
+                        let options = ShareOptions {
+                            expires_in: match &expires {
+                                Some(expires) => wonopcode_core::share::parse_expiry(expires)?,
+                                None => settings.expires_in,
+                            },
+                            password,
+                        };
+                        wonopcode_core::share::share_session(
+                            &session_repo,
+                            &project_id,
+                            "default",
+                            &settings,
+                            &options,
+                        )
+                        .await
+                    }
+                    .await;
+                    match result {
                         Ok(share_info) => {
                             info!(url = %share_info.url, "Session shared successfully");
-                            send_update(
-                                &update_tx,
-                                AppUpdate::Status(format!("Shared at: {}", share_info.url)),
-                            );
+                            let mut status = format!("Shared at: {}", share_info.url);
+                            if let Some(expires_at) = share_info
+                                .expires_at
+                                .and_then(chrono::DateTime::from_timestamp_millis)
+                            {
+                                status.push_str(&format!(
+                                    " (expires {})",
+                                    expires_at
+                                        .with_timezone(&chrono::Local)
+                                        .format("%Y-%m-%d %H:%M")
+                                ));
+                            }
+                            if share_info.password_protected {
+                                status.push_str(", password protected");
+                            }
+                            send_update(&update_tx, AppUpdate::Status(status));
                         }
                         Err(e) => {
                             warn!(error = %e, "Failed to share session");
@@ -1384,15 +2609,45 @@ impl Runner {
                 }
                 AppAction::UnshareSession => {
                     info!("Unshare session requested");
-                    // Unsharing requires the share secret which we don't store in the TUI
-                    // This would need to be retrieved from session metadata
-                    send_update(
-                        &update_tx,
-                        AppUpdate::Status(
-                            "Unshare requires share secret - use CLI: wonopcode unshare"
-                                .to_string(),
-                        ),
-                    );
+                    let project_id = self.instance.project_id().await;
+                    let session_repo = self.instance.session_repo();
+                    let config = self.instance.config().await;
+
+                    // Revoke with the secret stored when the session was shared
+                    let result = match ShareSettings::from_config(&config) {
+                        Ok(settings) => {
+                            wonopcode_core::share::unshare_session(
+                                &session_repo,
+                                &project_id,
+                                "default",
+                                None,
+                                &settings,
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    match result {
+                        Ok(()) => {
+                            send_update(
+                                &update_tx,
+                                AppUpdate::Status("Share link revoked".to_string()),
+                            );
+                        }
+                        Err(ShareError::ShareNotFound) => {
+                            send_update(
+                                &update_tx,
+                                AppUpdate::Status("Session is not shared".to_string()),
+                            );
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "Failed to unshare session");
+                            send_update(
+                                &update_tx,
+                                AppUpdate::Error(format!("Unshare failed: {e}")),
+                            );
+                        }
+                    }
                 }
                 AppAction::GotoMessage { message_id } => {
                     info!(message_id = %message_id, "Go to message requested");
@@ -1415,6 +2670,124 @@ impl Runner {
                     self.handle_sandbox_stop(&update_tx).await;
                     self.handle_sandbox_start(&update_tx).await;
                 }
+                AppAction::SearchSessions { query } => {
+                    match self.instance.search_sessions(&query).await {
+                        Ok(hits) => {
+                            let results = hits
+                                .into_iter()
+                                .map(|hit| {
+                                    let snippet = hit.snippet.unwrap_or_else(|| {
+                                        hit.session
+                                            .tags
+                                            .iter()
+                                            .map(|t| format!("#{t}"))
+                                            .collect::<Vec<_>>()
+                                            .join(" ")
+                                    });
+                                    (hit.session.id, hit.session.title, snippet)
+                                })
+                                .collect();
+                            send_update(
+                                &update_tx,
+                                AppUpdate::SessionSearchResults { query, results },
+                            );
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "Session search failed");
+                            send_update(
+                                &update_tx,
+                                AppUpdate::Status(format!("Session search failed: {e}")),
+                            );
+                        }
+                    }
+                }
+                AppAction::Orchestrate { tasks } => {
+                    self.start_orchestration(tasks, &update_tx).await;
+                }
+                AppAction::MergeOrchestration => {
+                    self.merge_orchestration(&update_tx).await;
+                }
+                AppAction::CancelOrchestration => match &self.orchestrator {
+                    Some(orchestrator) => {
+                        info!(id = %orchestrator.id(), "Cancelling orchestration");
+                        orchestrator.cancel();
+                    }
+                    None => send_update(
+                        &update_tx,
+                        AppUpdate::Status("No orchestration running".to_string()),
+                    ),
+                },
+                AppAction::CreateWorktree { name } => {
+                    self.create_worktree(name, &update_tx).await;
+                }
+                AppAction::ListWorktrees => {
+                    self.list_worktrees(&update_tx).await;
+                }
+                AppAction::FinishWorktree { action } => {
+                    self.finish_worktree(&action, &update_tx).await;
+                }
+                AppAction::ApplyTemplate { name } => {
+                    if name.is_empty() {
+                        let templates = TemplateStore::new(&cwd)
+                            .list()
+                            .into_iter()
+                            .map(|t| (t.name, t.description))
+                            .collect();
+                        send_update(&update_tx, AppUpdate::Templates(templates));
+                    } else {
+                        self.apply_template(&name, &cwd, &update_tx).await;
+                    }
+                }
+                AppAction::SaveTemplate { name } => {
+                    self.save_template(&name, &cwd, &update_tx).await;
+                }
+                AppAction::ListProjects => {
+                    // Only a headless server manages several projects
+                    let project = ProjectUpdate {
+                        path: cwd.display().to_string(),
+                        name: cwd
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                        active: true,
+                        loaded: true,
+                    };
+                    send_update(&update_tx, AppUpdate::Projects(vec![project]));
+                }
+                AppAction::SwitchProject { .. } => {
+                    send_update(
+                        &update_tx,
+                        AppUpdate::Status(
+                            "Switching projects needs a headless server with server.projects"
+                                .to_string(),
+                        ),
+                    );
+                }
+                AppAction::NewTab | AppAction::SwitchTab(_) | AppAction::CloseTab(_) => {
+                    send_update(
+                        &update_tx,
+                        AppUpdate::Status("Session tabs need the local TUI".to_string()),
+                    );
+                }
+                AppAction::SwitchProfile { name } => {
+                    if name.is_empty() {
+                        let config = self.instance.config().await;
+                        let names = config.profile_names();
+                        let status = if names.is_empty() {
+                            "No profiles configured".to_string()
+                        } else {
+                            format!(
+                                "Profiles: {} (active: {})",
+                                names.join(", "),
+                                config.profile.as_deref().unwrap_or("none")
+                            )
+                        };
+                        send_update(&update_tx, AppUpdate::Status(status));
+                    } else {
+                        self.switch_profile(&name, &update_tx).await;
+                    }
+                }
                 AppAction::SaveSettings { scope, config } => {
                     info!("Saving settings to {:?}", scope);
                     let project_dir = match scope {
@@ -1492,26 +2865,676 @@ impl Runner {
                 AppAction::GitCheckout { paths } => {
                     self.handle_git_checkout(&update_tx, paths).await;
                 }
-                AppAction::GitCommit { message } => {
-                    self.handle_git_commit(&update_tx, message).await;
+                AppAction::GitDiff { path, staged } => {
+                    self.handle_git_diff(&update_tx, path, staged).await;
+                }
+                AppAction::GitStageHunk { path, hunk } => {
+                    self.handle_git_hunk(&update_tx, path, hunk, false).await;
+                }
+                AppAction::GitUnstageHunk { path, hunk } => {
+                    self.handle_git_hunk(&update_tx, path, hunk, true).await;
+                }
+                AppAction::GitGenerateCommitMessage => {
+                    self.handle_git_generate_commit_message(&update_tx);
+                }
+                AppAction::GitCommit { message, push } => {
+                    let committed = self.handle_git_commit(&update_tx, message).await;
+                    if committed && push {
+                        self.handle_git_push(&update_tx).await;
+                    }
+                }
+                AppAction::GitHistory => {
+                    self.handle_git_history(&update_tx).await;
+                }
+                AppAction::GitPush => {
+                    self.handle_git_push(&update_tx).await;
+                }
+                AppAction::GitPull => {
+                    self.handle_git_pull(&update_tx).await;
+                }
+                // Only remote projects' files are listed and read through
+                // the backend
+                AppAction::ListFiles { .. } | AppAction::ReadFile { .. } => {}
+                // Only sessions on a headless server are shared
+                AppAction::TakeControl => {}
+                // Handled by the backend switch in front of the runners
+                AppAction::BrowseServers
+                | AppAction::StopBrowsingServers
+                | AppAction::ConnectServer { .. } => {}
+            }
+        }
+
+        config_watcher.abort();
+
+        // Cleanup: stop sandbox container on exit
+        self.cleanup_sandbox().await;
+
+        // Cleanup: remove temp todo file
+        self.todo_store.cleanup();
+    }
+
+    /// Move the session into a new worktree and branch.
+    async fn create_worktree(
+        &mut self,
+        name: Option<String>,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        if let Some(worktree) = &self.worktree {
+            send_update(
+                update_tx,
+                AppUpdate::Error(format!(
+                    "Already working in worktree {} - finish it with /worktree merge, pr or discard",
+                    worktree.name
+                )),
+            );
+            return;
+        }
+        let name = name
+            .unwrap_or_else(|| format!("session-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+
+        let result = match self.instance.worktrees().await {
+            Ok(manager) => manager.create(&name).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(worktree) => {
+                info!(name = %worktree.name, path = %worktree.path.display(), "Created session worktree");
+                send_update(
+                    update_tx,
+                    AppUpdate::SystemMessage(format!(
+                        "Working in worktree {} on branch {} (based on {}). \
+                         Finish with /worktree merge, /worktree pr or /worktree discard.",
+                        worktree.path.display(),
+                        worktree.branch,
+                        worktree.base
+                    )),
+                );
+                self.worktree = Some(worktree);
+            }
+            Err(e) => send_update(
+                update_tx,
+                AppUpdate::Error(format!("Failed to create worktree: {e}")),
+            ),
+        }
+    }
+
+    /// List the worktrees of the project, marking the session's.
+    async fn list_worktrees(&self, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        let result = match self.instance.worktrees().await {
+            Ok(manager) => manager.list().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(worktrees) if worktrees.is_empty() => send_update(
+                update_tx,
+                AppUpdate::Status("No worktrees - create one with /worktree new".to_string()),
+            ),
+            Ok(worktrees) => {
+                let mut lines = vec!["Worktrees:".to_string()];
+                for worktree in worktrees {
+                    let active = self
+                        .worktree
+                        .as_ref()
+                        .is_some_and(|w| w.path == worktree.path);
+                    lines.push(format!(
+                        "{} {} ({})",
+                        if active { "*" } else { " " },
+                        worktree.branch,
+                        worktree.path.display()
+                    ));
+                }
+                send_update(update_tx, AppUpdate::SystemMessage(lines.join("\n")));
+            }
+            Err(e) => send_update(
+                update_tx,
+                AppUpdate::Error(format!("Failed to list worktrees: {e}")),
+            ),
+        }
+    }
+
+    /// Merge the session's worktree back, open a pull request from it, or
+    /// discard it.
+    async fn finish_worktree(
+        &mut self,
+        action: &str,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        let Some(finish) = WorktreeFinish::parse(action) else {
+            send_update(
+                update_tx,
+                AppUpdate::Error(format!(
+                    "Unknown worktree action: {action} (expected merge, pr or discard)"
+                )),
+            );
+            return;
+        };
+        let Some(worktree) = self.worktree.clone() else {
+            send_update(
+                update_tx,
+                AppUpdate::Status("Not working in a worktree".to_string()),
+            );
+            return;
+        };
+
+        let title = format!("wonopcode: {}", worktree.name);
+        let result = match self.instance.worktrees().await {
+            Ok(manager) => manager.finish(&worktree, finish, &title).await,
+            Err(e) => Err(e),
+        };
+        let message = match result {
+            Ok(WorktreeOutcome::Closed(WorktreeMerge::Merged(files))) => format!(
+                "Merged {} into {} ({} files changed)",
+                worktree.branch,
+                worktree.base,
+                files.len()
+            ),
+            Ok(WorktreeOutcome::Closed(_)) if finish == WorktreeFinish::Discard => {
+                format!("Discarded worktree {}", worktree.name)
+            }
+            Ok(WorktreeOutcome::Closed(_)) => {
+                format!("No changes in worktree {}; removed it", worktree.name)
+            }
+            Ok(WorktreeOutcome::PullRequest(url)) => {
+                format!("Opened pull request from {}: {url}", worktree.branch)
+            }
+            Ok(WorktreeOutcome::Conflict(files)) => {
+                send_update(
+                    update_tx,
+                    AppUpdate::Error(format!(
+                        "Merging {} conflicts in {}; the worktree was kept",
+                        worktree.branch,
+                        files.join(", ")
+                    )),
+                );
+                return;
+            }
+            Err(e) => {
+                send_update(
+                    update_tx,
+                    AppUpdate::Error(format!("Failed to {action} worktree: {e}")),
+                );
+                return;
+            }
+        };
+        info!(name = %worktree.name, action, "Finished session worktree");
+        self.worktree = None;
+        send_update(update_tx, AppUpdate::SystemMessage(message));
+    }
+
+    /// When a message was sent, from storage or from its ID.
+    async fn revert_point(
+        &self,
+        project_id: &str,
+        session_id: &str,
+        message_id: &str,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        let stored = self
+            .instance
+            .session_repo()
+            .messages(project_id, session_id, None)
+            .await
+            .ok()
+            .and_then(|messages| {
+                messages
+                    .into_iter()
+                    .find(|m| m.message.id() == message_id)
+                    .map(|m| m.message.created_at())
+            });
+        let created = stored
+            .or_else(|| Identifier::parse(message_id).map(|(_, ulid)| ulid.timestamp_ms() as i64));
+        created.and_then(chrono::DateTime::from_timestamp_millis)
+    }
+
+    /// Replace the history with a compacted one.
+    async fn set_compacted_history(&self, messages: Vec<ProviderMessage>) {
+        *self.history.write().await = messages;
+        self.prompt_marks.write().await.compacted();
+    }
+
+    /// Cut the history back to before the first prompt received at or after
+    /// `since`.
+    async fn truncate_history(&self, since: chrono::DateTime<chrono::Utc>) {
+        let mut history = self.history.write().await;
+        if let Some(len) = self.prompt_marks.write().await.cut(since) {
+            history.truncate(len);
+        }
+    }
+
+    /// Undo the file changes made since a point in time: edits from
+    /// snapshots, and files changed by shell commands where possible.
+    /// Reports what could not be undone. An unrevert brings the files back
+    /// until the next prompt.
+    async fn rollback_files(
+        &self,
+        session_id: &str,
+        since: chrono::DateTime<chrono::Utc>,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        let Some(store) = &self.snapshot_store else {
+            return;
+        };
+
+        match store.rollback(session_id, since).await {
+            Ok(report) if !report.is_empty() => {
+                send_update(
+                    update_tx,
+                    AppUpdate::SystemMessage(format!(
+                        "Rolled back file changes:\n{}\n\nUnrevert before sending another prompt to bring them back.",
+                        report.summary()
+                    )),
+                );
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!(error = %e, "Failed to roll back file changes");
+                send_update(
+                    update_tx,
+                    AppUpdate::Error(format!("Failed to roll back file changes: {e}")),
+                );
+            }
+        }
+    }
+
+    /// Bring back the files as they were before the last revert rolled them
+    /// back. Returns whether any were.
+    async fn undo_rollback(
+        &self,
+        session_id: &str,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) -> bool {
+        let Some(store) = &self.snapshot_store else {
+            return false;
+        };
+
+        match store.undo_rollback(session_id).await {
+            Ok(report) if !report.is_empty() => {
+                send_update(
+                    update_tx,
+                    AppUpdate::SystemMessage(format!(
+                        "Brought back file changes:\n{}",
+                        report.summary()
+                    )),
+                );
+                true
+            }
+            Ok(_) => false,
+            Err(e) => {
+                warn!(error = %e, "Failed to undo file rollback");
+                send_update(
+                    update_tx,
+                    AppUpdate::Error(format!("Failed to bring back file changes: {e}")),
+                );
+                false
+            }
+        }
+    }
+
+    /// Collect the files changed since the review baseline and send their
+    /// diff to the UI.
+    async fn start_review(&mut self, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        self.review.clear();
+        if let Some(store) = &self.snapshot_store {
+            match store.review_files("default", self.review_since).await {
+                Ok(files) => self.review = files,
+                Err(e) => {
+                    warn!(error = %e, "Failed to collect changes for review");
+                    send_update(
+                        update_tx,
+                        AppUpdate::Error(format!("Failed to collect changes for review: {e}")),
+                    );
+                    return;
+                }
+            }
+        }
+        let diff = self.review.iter().map(ReviewFile::diff).collect();
+        send_update(update_tx, AppUpdate::Review(diff));
+    }
+
+    /// Apply the decisions of a finished review to the files under review.
+    async fn apply_review(
+        &mut self,
+        reviewed: Vec<ReviewedFile>,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        use wonopcode_snapshot::HunkDecision;
+        use wonopcode_tui::HunkDecision as Decision;
+
+        let files = std::mem::take(&mut self.review);
+        let Some(store) = &self.snapshot_store else {
+            return;
+        };
+
+        let (mut accepted, mut rejected, mut edited, mut undecided) = (0, 0, 0, 0);
+        let mut errors = Vec::new();
+        for reviewed in reviewed {
+            let Some(file) = files
+                .iter()
+                .find(|f| f.path.display().to_string() == reviewed.path)
+            else {
+                errors.push(format!("{}: not under review", reviewed.path));
+                continue;
+            };
+            let decisions: Vec<Option<HunkDecision>> = reviewed
+                .decisions
+                .into_iter()
+                .map(|decision| {
+                    let decision = match decision? {
+                        Decision::Accept => HunkDecision::Accept,
+                        Decision::Reject => HunkDecision::Reject,
+                        Decision::Edit(text) => HunkDecision::Edit(text),
+                    };
+                    Some(decision)
+                })
+                .collect();
+            if let Err(e) = store.apply_review("default", file, &decisions).await {
+                errors.push(e.to_string());
+                continue;
+            }
+            for decision in &decisions {
+                match decision {
+                    Some(HunkDecision::Accept) => accepted += 1,
+                    Some(HunkDecision::Reject) => rejected += 1,
+                    Some(HunkDecision::Edit(_)) => edited += 1,
+                    None => undecided += 1,
                 }
-                AppAction::GitHistory => {
-                    self.handle_git_history(&update_tx).await;
+            }
+        }
+
+        let mut summary =
+            format!("Review applied: {accepted} accepted, {rejected} rejected, {edited} edited");
+        if undecided > 0 {
+            summary.push_str(&format!(", {undecided} left for later"));
+        }
+        send_update(update_tx, AppUpdate::SystemMessage(summary));
+        if !errors.is_empty() {
+            warn!(errors = ?errors, "Some review decisions were not applied");
+            send_update(
+                update_tx,
+                AppUpdate::Error(format!(
+                    "Some review decisions were not applied:\n{}",
+                    errors.join("\n")
+                )),
+            );
+        }
+    }
+
+    /// Stop working in the session's worktree, leaving it on disk.
+    fn leave_worktree(&mut self, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        if let Some(worktree) = self.worktree.take() {
+            send_update(
+                update_tx,
+                AppUpdate::Status(format!(
+                    "Left worktree {}; it is kept at {}",
+                    worktree.name,
+                    worktree.path.display()
+                )),
+            );
+        }
+    }
+
+    /// Start agents on the given tasks, each in its own git worktree.
+    ///
+    /// The agents run in the background; progress is forwarded from the bus.
+    async fn start_orchestration(
+        &mut self,
+        tasks: Vec<String>,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        if tasks.is_empty() {
+            send_update(
+                update_tx,
+                AppUpdate::Error("No tasks to orchestrate".to_string()),
+            );
+            return;
+        }
+        if let Some(orchestrator) = &self.orchestrator {
+            if orchestrator
+                .agents()
+                .await
+                .iter()
+                .any(|a| !a.state.is_finished())
+            {
+                send_update(
+                    update_tx,
+                    AppUpdate::Error("An orchestration is already running".to_string()),
+                );
+                return;
+            }
+        }
+        let project = self.instance.project().await;
+        if project.vcs != Some(Vcs::Git) {
+            send_update(
+                update_tx,
+                AppUpdate::Error("Orchestration needs a git repository".to_string()),
+            );
+            return;
+        }
+
+        let prompt_vars = self.prompt_vars(&project.worktree).await;
+        let instructions = self
+            .compose_instructions(&project.worktree, update_tx)
+            .await;
+        let prompt_config = {
+            let config = self.config.read().await;
+            PromptConfig {
+                max_tokens: config.max_tokens,
+                temperature: config.temperature,
+                system: Some(config.system_prompt.as_deref().map_or_else(
+                    || {
+                        build_system_prompt_for_session(
+                            &config.provider,
+                            &config.model_id,
+                            &project.worktree,
+                            instructions.as_deref(),
+                            &prompt_vars,
+                        )
+                    },
+                    |system| render_prompt(system, &prompt_vars),
+                )),
+                ..Default::default()
+            }
+        };
+        let max_parallel = self
+            .instance
+            .config()
+            .await
+            .orchestration
+            .and_then(|o| o.max_parallel)
+            .unwrap_or(wonopcode_core::orchestrate::DEFAULT_MAX_PARALLEL);
+        // Each agent gets a sandbox of its own worktree, configured like this one
+        let sandbox = match &self.sandbox_manager {
+            Some(manager) if manager.is_enabled() && !manager.is_explicitly_stopped().await => {
+                Some(manager.config().clone())
+            }
+            _ => None,
+        };
+        let allow_all_in_sandbox = self.config.read().await.allow_all_in_sandbox;
+        let orchestrator = Arc::new(
+            Orchestrator::new(
+                self.provider.read().await.clone(),
+                self.tools.clone(),
+                Arc::new(self.instance.session_repo()),
+                self.bus.clone(),
+                WorktreeManager::new(&project.worktree),
+                project.id,
+            )
+            .with_prompt_config(prompt_config)
+            .with_max_parallel(max_parallel)
+            .with_sandbox(sandbox)
+            .with_permissions(self.permission_manager.clone(), allow_all_in_sandbox)
+            .with_hooks(self.hooks.clone())
+            .with_budget(self.budget.clone()),
+        );
+        self.orchestrator = Some(orchestrator.clone());
+
+        info!(id = %orchestrator.id(), tasks = tasks.len(), "Starting orchestration");
+        let update_tx = update_tx.clone();
+        tokio::spawn(async move {
+            match orchestrator.run(tasks).await {
+                Ok(agents) => {
+                    let done = agents
+                        .iter()
+                        .filter(|a| a.state == AgentState::Done)
+                        .count();
+                    send_update(
+                        &update_tx,
+                        AppUpdate::SystemMessage(format!(
+                            "Orchestration {} finished: {done} of {} agents done. \
+                             Review with /orchestrate and merge with /orchestrate merge.",
+                            orchestrator.id(),
+                            agents.len()
+                        )),
+                    );
                 }
-                AppAction::GitPush => {
-                    self.handle_git_push(&update_tx).await;
+                Err(e) => {
+                    warn!(error = %e, "Orchestration failed");
+                    send_update(
+                        &update_tx,
+                        AppUpdate::Error(format!("Orchestration failed: {e}")),
+                    );
                 }
-                AppAction::GitPull => {
-                    self.handle_git_pull(&update_tx).await;
+            }
+        });
+    }
+
+    /// Merge the branches of the finished orchestrated agents.
+    async fn merge_orchestration(&self, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        let Some(orchestrator) = &self.orchestrator else {
+            send_update(
+                update_tx,
+                AppUpdate::Status("No orchestration to merge".to_string()),
+            );
+            return;
+        };
+        if orchestrator
+            .agents()
+            .await
+            .iter()
+            .any(|a| !a.state.is_finished())
+        {
+            send_update(
+                update_tx,
+                AppUpdate::Status("Wait for all agents to finish before merging".to_string()),
+            );
+            return;
+        }
+
+        match orchestrator.merge().await {
+            Ok(agents) => {
+                let mut lines = vec![format!("Merged orchestration {}:", orchestrator.id())];
+                for agent in &agents {
+                    let detail = match agent.state {
+                        AgentState::Merged if agent.changed_files.is_empty() => {
+                            "no changes".to_string()
+                        }
+                        AgentState::Merged => format!("merged {}", agent.changed_files.join(", ")),
+                        AgentState::Conflict => agent.summary.clone().unwrap_or_default(),
+                        state => state.as_str().to_string(),
+                    };
+                    lines.push(format!("- {}: {detail}", agent.task));
                 }
+                send_update(update_tx, AppUpdate::SystemMessage(lines.join("\n")));
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to merge orchestration");
+                send_update(update_tx, AppUpdate::Error(format!("Merge failed: {e}")));
             }
         }
+    }
 
-        // Cleanup: stop sandbox container on exit
-        self.cleanup_sandbox().await;
+    /// Start the session from a template: switch agent and model, add its
+    /// instructions and pinned files to the system prompt and seed the todos.
+    async fn apply_template(
+        &mut self,
+        name: &str,
+        cwd: &Path,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        let template = match TemplateStore::new(cwd).load(name) {
+            Ok(template) => template,
+            Err(e) => {
+                warn!(template = %name, error = %e, "Failed to load session template");
+                send_update(
+                    update_tx,
+                    AppUpdate::Error(format!("Failed to load template: {e}")),
+                );
+                return;
+            }
+        };
+        info!(template = %name, "Applying session template");
 
-        // Cleanup: remove temp todo file
-        self.todo_store.cleanup();
+        let current_model = {
+            let config = self.config.read().await;
+            format!("{}/{}", config.provider, config.model_id)
+        };
+        if let Some(model) = template.model.as_ref().filter(|m| **m != current_model) {
+            if let Err(e) = self.change_model(model).await {
+                warn!(model = %model, error = %e, "Failed to switch to template model");
+                send_update(
+                    update_tx,
+                    AppUpdate::Status(format!("Template model {model} unavailable: {e}")),
+                );
+            }
+        }
+        if let Some(agent) = &template.agent {
+            self.set_agent(agent).await;
+            send_update(update_tx, AppUpdate::AgentChanged(agent.clone()));
+        }
+        if !template.phases.is_empty() {
+            if let Err(e) =
+                todo::TodoStore::set_phased(self.todo_store.as_ref(), cwd, template.todos())
+            {
+                warn!(error = %e, "Failed to seed template todos");
+            }
+            self.sync_todos_to_tui(cwd, update_tx);
+        }
+
+        send_update(
+            update_tx,
+            AppUpdate::Status(format!("Started from template '{name}'")),
+        );
+        *self.template.write().await = Some(template);
+    }
+
+    /// Save the current session's setup as a template.
+    async fn save_template(
+        &self,
+        name: &str,
+        cwd: &Path,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+    ) {
+        let mut template = SessionTemplate::new(name)
+            .with_todos(&todo::get_phased_todos(self.todo_store.as_ref(), cwd));
+        if let Some(active) = self.template.read().await.as_ref() {
+            template.description = active.description.clone();
+            template.system = active.system.clone();
+            template.files = active.files.clone();
+        }
+        template.agent = Some(self.agent.read().await.clone());
+        {
+            let config = self.config.read().await;
+            template.model = Some(format!("{}/{}", config.provider, config.model_id));
+        }
+
+        match TemplateStore::new(cwd).save(&template) {
+            Ok(path) => {
+                info!(path = %path.display(), "Session template saved");
+                send_update(
+                    update_tx,
+                    AppUpdate::SystemMessage(format!(
+                        "Template '{name}' saved to {}",
+                        path.strip_prefix(cwd).unwrap_or(&path).display()
+                    )),
+                );
+            }
+            Err(e) => {
+                error!("Failed to save session template: {}", e);
+                send_update(
+                    update_tx,
+                    AppUpdate::Error(format!("Failed to save template: {e}")),
+                );
+            }
+        }
     }
 
     /// Sync todos from store to TUI.
@@ -1759,7 +3782,10 @@ impl Runner {
     async fn run_prompt(
         &self,
         user_input: &str,
+        images: Vec<ContentPart>,
+        attached: &[PinnedContext],
         cwd: &Path,
+        allowed_tools: Option<&[String]>,
         update_tx: &mpsc::UnboundedSender<AppUpdate>,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         use futures::StreamExt;
@@ -1773,6 +3799,13 @@ impl Runner {
             detector.reset();
         }
 
+        // A new prompt builds on a revert, so its file rollback can't be undone
+        if let Some(store) = &self.snapshot_store {
+            if let Err(e) = store.commit_rollback("default").await {
+                warn!(error = %e, "Failed to commit file rollback");
+            }
+        }
+
         // Get existing history and check context limit
         let mut messages: Vec<ProviderMessage> = {
             let history = self.history.read().await;
@@ -1822,10 +3855,12 @@ impl Runner {
             let compact_start = Instant::now();
             let messages_before = messages.len();
 
-            // Estimate token usage for compaction
-            let estimated_tokens = compaction::estimate_token_usage(&messages);
+            // Token usage for compaction
+            let estimated_tokens = self.context_usage(&messages).await;
+            self.send_compaction_preview(&messages, &estimated_tokens, context_limit, &update_tx);
 
             // Perform compaction
+            self.index_context(&messages).await;
             let provider = self.provider.read().await;
             match compaction::compact(
                 &mut messages,
@@ -1855,10 +3890,7 @@ impl Runner {
                     messages = new_messages;
 
                     // Update history
-                    {
-                        let mut history = self.history.write().await;
-                        *history = messages.clone();
-                    }
+                    self.set_compacted_history(messages.clone()).await;
 
                     send_update(
                         &update_tx,
@@ -1892,10 +3924,7 @@ impl Runner {
                             compact_start.elapsed(),
                         );
 
-                        {
-                            let mut history = self.history.write().await;
-                            *history = messages.clone();
-                        }
+                        self.set_compacted_history(messages.clone()).await;
 
                         send_update(
                             &update_tx,
@@ -1914,21 +3943,26 @@ impl Runner {
         }
 
         // Check if compaction is needed
-        if compaction::needs_compaction(&messages, context_limit, &self.compaction_config) {
+        let estimated_tokens = self.context_usage(&messages).await;
+        if compaction::needs_compaction(
+            &messages,
+            &estimated_tokens,
+            context_limit,
+            &self.compaction_config,
+        ) {
             info!(
                 messages = messages.len(),
                 context_limit = context_limit,
                 "Context approaching limit, attempting smart compaction"
             );
+            self.send_compaction_preview(&messages, &estimated_tokens, context_limit, &update_tx);
             send_update(
                 &update_tx,
                 AppUpdate::Status("Compacting conversation...".to_string()),
             );
 
-            // Estimate token usage for compaction decision
-            let estimated_tokens = compaction::estimate_token_usage(&messages);
-
             // Perform full compaction: prune first, then summarize if still needed
+            self.index_context(&messages).await;
             let provider = self.provider.read().await;
             match compaction::compact(
                 &mut messages,
@@ -1959,10 +3993,7 @@ impl Runner {
                     messages = new_messages;
 
                     // Update history
-                    {
-                        let mut history = self.history.write().await;
-                        *history = messages.clone();
-                    }
+                    self.set_compacted_history(messages.clone()).await;
 
                     let status = if messages_summarized > 0 {
                         format!("Summarized {messages_summarized} messages to save context")
@@ -1994,10 +4025,7 @@ impl Runner {
                         )));
                         messages.extend(recent);
 
-                        {
-                            let mut history = self.history.write().await;
-                            *history = messages.clone();
-                        }
+                        self.set_compacted_history(messages.clone()).await;
                     }
                 }
             }
@@ -2011,20 +4039,72 @@ impl Runner {
         let mut total_input: u32 = 0;
         let mut total_output: u32 = 0;
 
+        // Run SessionStart hooks before the first prompt of a session;
+        // their context is sent along with the prompt
+        let session_context = if messages.is_empty() {
+            let hooks = self.hooks.read().await;
+            hooks
+                .run_shell_hooks(
+                    ShellHookEvent::SessionStart,
+                    None,
+                    serde_json::json!({ "prompt": user_input }),
+                    cwd,
+                )
+                .await
+                .context
+        } else {
+            Vec::new()
+        };
+
         // Add user message
-        let user_msg = ProviderMessage::user(user_input);
+        let mut user_msg = if session_context.is_empty() {
+            ProviderMessage::user(user_input)
+        } else {
+            ProviderMessage::user(format!("{}\n\n{user_input}", session_context.join("\n\n")))
+        };
+        if !images.is_empty() {
+            let provider = self.provider.read().await;
+            let model = provider.model_info();
+            if model.capabilities.input.image {
+                for image in images {
+                    user_msg = user_msg.with_part(image);
+                }
+            } else {
+                send_update(
+                    update_tx,
+                    AppUpdate::SystemMessage(format!(
+                        "{} doesn't accept images; sending the prompt without the {} attached.",
+                        model.name,
+                        if images.len() == 1 {
+                            "image".to_string()
+                        } else {
+                            format!("{} images", images.len())
+                        }
+                    )),
+                );
+            }
+        }
         messages.push(user_msg.clone());
 
         // Store user message in history
         {
             let mut history = self.history.write().await;
+            self.prompt_marks
+                .write()
+                .await
+                .push(chrono::Utc::now(), history.len());
             history.push(user_msg);
         }
 
-        // Build tool definitions
+        // Build tool definitions, limited to a custom command's tools
+        let tool_allowed = |name: &str| match allowed_tools {
+            Some(tools) => tools.iter().any(|tool| tool.eq_ignore_ascii_case(name)),
+            None => true,
+        };
         let tool_defs: Vec<ToolDefinition> = self
             .tools
             .all()
+            .filter(|t| tool_allowed(t.id()))
             .map(|t| ToolDefinition {
                 name: t.id().to_string(),
                 description: t.description().to_string(),
@@ -2032,6 +4112,31 @@ impl Runner {
             })
             .collect();
 
+        // Variables for prompt templates and instructions, fixed for this prompt
+        let prompt_vars = self.prompt_vars(cwd).await;
+        let instructions = self.compose_instructions(cwd, update_tx).await;
+
+        // Bring back compacted context that is relevant to this prompt
+        let instructions = match self.retrieve_context(user_input, &messages).await {
+            Some(retrieved) => Some(match instructions {
+                Some(instructions) => format!("{instructions}\n\n{retrieved}"),
+                None => retrieved,
+            }),
+            None => instructions,
+        };
+
+        // Pin the files attached with `@` for this turn
+        let instructions = match pinned::render(attached, cwd) {
+            Some(pinned) => Some(match instructions {
+                Some(instructions) => format!("{instructions}\n\n{pinned}"),
+                None => pinned,
+            }),
+            None => instructions,
+        };
+
+        // Times Stop hooks have asked the agent to keep going
+        let mut stop_hook_blocks = 0;
+
         // Main loop
         loop {
             if cancel.is_cancelled() {
@@ -2053,6 +4158,23 @@ impl Runner {
                 break;
             }
 
+            if let Some(status) = self.check_spend(update_tx).await {
+                send_update(
+                    update_tx,
+                    AppUpdate::SystemMessage(format!(
+                        "Stopped: {}. Run /spend ack to allow more spending.",
+                        status.describe()
+                    )),
+                );
+                break;
+            }
+
+            if let Some(status) = prompt::check_budget(&self.budget, &self.bus, "default").await {
+                if !self.continue_past_budget(status, update_tx).await {
+                    break;
+                }
+            }
+
             steps += 1;
             debug!(
                 step = steps,
@@ -2061,17 +4183,40 @@ impl Runner {
             );
 
             // Build options
+            let template_section = self
+                .template
+                .read()
+                .await
+                .as_ref()
+                .and_then(|t| t.prompt_section(cwd))
+                .map(|section| render_prompt(&section, &prompt_vars));
+            let instructions = match self.scoped_rules.read().await.render() {
+                Some(rules) => Some(match &instructions {
+                    Some(instructions) => format!("{instructions}\n\n{rules}"),
+                    None => rules,
+                }),
+                None => instructions.clone(),
+            };
             let options = {
                 let config = self.config.read().await;
-                GenerateOptions {
-                    temperature: config.temperature,
-                    max_tokens: config.max_tokens,
-                    system: config.system_prompt.clone().or_else(|| {
-                        Some(build_system_prompt_for_session(
+                let system = config.system_prompt.as_deref().map_or_else(
+                    || {
+                        build_system_prompt_for_session(
                             &config.provider,
                             &config.model_id,
                             cwd,
-                        ))
+                            instructions.as_deref(),
+                            &prompt_vars,
+                        )
+                    },
+                    |system| render_prompt(system, &prompt_vars),
+                );
+                GenerateOptions {
+                    temperature: config.temperature,
+                    max_tokens: config.max_tokens,
+                    system: Some(match &template_section {
+                        Some(section) => format!("{system}\n\n{section}"),
+                        None => system,
                     }),
                     tools: tool_defs.clone(),
                     abort: Some(cancel.clone()),
@@ -2109,9 +4254,23 @@ impl Runner {
                 "Calling provider.generate()"
             );
 
-            let stream = {
-                let provider = self.provider.read().await;
-                provider.generate(messages.clone(), options).await?
+            let provider_id = self.provider.read().await.model_info().provider_id.clone();
+            let stream = self
+                .breakers
+                .call(&provider_id, &cancel, || {
+                    let provider = self.provider.clone();
+                    let messages = messages.clone();
+                    let options = options.clone();
+                    async move { provider.read().await.generate(messages, options).await }
+                })
+                .await;
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(ProviderError::Cancelled) if cancel.is_cancelled() => {
+                    info!("Prompt cancelled while waiting to retry the provider");
+                    return Ok(final_text);
+                }
+                Err(e) => return Err(e.into()),
             };
 
             info!(
@@ -2349,6 +4508,45 @@ impl Runner {
                 }
             }
 
+            // Remember the real prompt size for compaction decisions
+            if step_usage.input_tokens > 0 {
+                *self.measured_context.write().await = Some(MeasuredContext::new(
+                    &messages,
+                    step_usage.input_tokens + step_usage.cache_read_tokens,
+                ));
+            }
+
+            {
+                let (model_id, step_cost) = {
+                    let provider = self.provider.read().await;
+                    let model_info = provider.model_info();
+                    telemetry::record_tokens(
+                        &model_info.provider_id,
+                        &model_info.id,
+                        step_usage.input_tokens,
+                        step_usage.output_tokens,
+                    );
+                    let cost = model_info
+                        .cost
+                        .calculate(step_usage.input_tokens, step_usage.output_tokens);
+                    (model_info.id.clone(), cost)
+                };
+                self.budget.lock().await.record_step(
+                    step_usage.input_tokens,
+                    step_usage.output_tokens,
+                    step_cost,
+                );
+                self.record_spend(
+                    &model_id,
+                    SpendAmount {
+                        cost: step_cost,
+                        input_tokens: u64::from(step_usage.input_tokens),
+                        output_tokens: u64::from(step_usage.output_tokens),
+                    },
+                )
+                .await;
+            }
+
             // Accumulate usage for this step
             total_input += step_usage.input_tokens;
             total_output += step_usage.output_tokens;
@@ -2393,13 +4591,15 @@ impl Runner {
                     config.doom_loop
                 };
 
-                // Track which tools are blocked by doom loop or permissions
+                // Track which tools are blocked by doom loop, hooks, the
+                // command or permissions
                 let mut doom_loop_blocked: Vec<(String, String, String)> = Vec::new();
+                let mut rejected: Vec<(String, String, String)> = Vec::new();
                 let mut permission_blocked: Vec<(String, String, String)> = Vec::new();
                 let mut allowed_calls: Vec<(String, String, String)> = Vec::new();
 
-                for (call_id, tool_name, args_str) in tool_calls {
-                    let input: serde_json::Value =
+                for (call_id, tool_name, mut args_str) in tool_calls {
+                    let mut input: serde_json::Value =
                         serde_json::from_str(&args_str).unwrap_or(serde_json::Value::Null);
 
                     // Check doom loop detector first
@@ -2435,21 +4635,40 @@ impl Runner {
                         }
                     }
 
-                    // Check tool permissions
                     // Normalize tool name - MCP tools have prefix like "mcp__wonopcode-tools__read"
                     let normalized_tool_name = tool_name.rsplit("__").next().unwrap_or(&tool_name);
-                    let path = extract_path_from_input(&input);
-                    let action = determine_tool_action(normalized_tool_name, &input);
-                    let description = format_tool_description(normalized_tool_name, &input);
-
-                    let check = PermissionCheck {
-                        id: call_id.clone(),
-                        tool: normalized_tool_name.to_string(),
-                        action: action.clone(),
-                        description,
-                        path: path.clone(),
-                        details: input.clone(),
-                    };
+
+                    if !tool_allowed(normalized_tool_name) {
+                        let error_msg = format!(
+                            "Tool execution blocked: '{normalized_tool_name}' is not allowed by this command"
+                        );
+                        rejected.push((call_id, tool_name, error_msg));
+                        continue;
+                    }
+
+                    // Run pre-tool hooks, which may deny the call or rewrite its arguments
+                    {
+                        let hooks = self.hooks.read().await;
+                        if hooks.has_tool_hooks() {
+                            let mut call = ToolCall::new(normalized_tool_name, input, cwd);
+                            if let Err(reason) = hooks.before_tool(&mut call).await {
+                                let error_msg = format!("Tool execution blocked by hook: {reason}");
+                                rejected.push((call_id, tool_name, error_msg));
+                                continue;
+                            }
+                            input = call.args;
+                            args_str = input.to_string();
+                        }
+                    }
+
+                    // Check tool permissions
+                    let check = PermissionCheck::for_tool(
+                        call_id.clone(),
+                        normalized_tool_name,
+                        &input,
+                        cwd,
+                    );
+                    let action = check.action.clone();
 
                     // Check if sandbox is actually running and allow_all_in_sandbox is enabled
                     let sandbox_running = if let Some(ref manager) = self.sandbox_manager {
@@ -2464,22 +4683,45 @@ impl Runner {
                         .check_with_sandbox("default", check, sandbox_running)
                         .await;
 
-                    if allowed {
-                        allowed_calls.push((call_id, tool_name, args_str));
-                    } else {
-                        warn!(tool = %tool_name, action = %action, "Tool execution denied by permission manager");
-                        permission_blocked.push((call_id, tool_name, args_str));
-                    }
+                    if allowed {
+                        allowed_calls.push((call_id, tool_name, args_str));
+                    } else {
+                        warn!(tool = %tool_name, action = %action, "Tool execution denied by permission manager");
+                        permission_blocked.push((call_id, tool_name, args_str));
+                    }
+                }
+
+                // Handle doom loop blocked tools - add error responses to messages
+                for (call_id, tool_name, _args_str) in &doom_loop_blocked {
+                    let error_msg = format!(
+                        "Tool execution blocked: doom loop detected. \
+                        You have called '{tool_name}' {DOOM_LOOP_THRESHOLD} times in a row with identical arguments. \
+                        Please try a different approach or use different arguments."
+                    );
+
+                    send_update(
+                        &update_tx,
+                        AppUpdate::ToolStarted {
+                            name: tool_name.clone(),
+                            id: call_id.clone(),
+                            input: "{}".to_string(),
+                        },
+                    );
+                    send_update(
+                        &update_tx,
+                        AppUpdate::ToolCompleted {
+                            id: call_id.clone(),
+                            success: false,
+                            output: error_msg.clone(),
+                            metadata: None,
+                        },
+                    );
+
+                    messages.push(ProviderMessage::tool_result(call_id, &error_msg));
                 }
 
-                // Handle doom loop blocked tools - add error responses to messages
-                for (call_id, tool_name, _args_str) in &doom_loop_blocked {
-                    let error_msg = format!(
-                        "Tool execution blocked: doom loop detected. \
-                        You have called '{tool_name}' {DOOM_LOOP_THRESHOLD} times in a row with identical arguments. \
-                        Please try a different approach or use different arguments."
-                    );
-
+                // Handle tools blocked by hooks or the command - add error responses to messages
+                for (call_id, tool_name, error_msg) in &rejected {
                     send_update(
                         &update_tx,
                         AppUpdate::ToolStarted {
@@ -2498,7 +4740,7 @@ impl Runner {
                         },
                     );
 
-                    messages.push(ProviderMessage::tool_result(call_id, &error_msg));
+                    messages.push(ProviderMessage::tool_result(call_id, error_msg.as_str()));
                 }
 
                 // Handle permission blocked tools - add error responses to messages
@@ -2531,10 +4773,11 @@ impl Runner {
 
                 // Replace tool_calls with allowed_calls
                 let tool_calls = allowed_calls;
+                self.budget.lock().await.record_tool_calls(tool_calls.len());
 
                 if tool_calls.is_empty() {
                     // All tools were blocked, continue to get model response
-                    debug!("All tool calls blocked by doom loop or permission checks");
+                    debug!("All tool calls blocked by doom loop, hook or permission checks");
                     continue;
                 }
 
@@ -2568,7 +4811,12 @@ impl Runner {
                     );
                 }
 
+                // Rules for the subtrees these tools touch apply from the next step
+                self.activate_scoped_rules(cwd, &tool_calls, update_tx)
+                    .await;
+
                 // Spawn all tools concurrently (Box::pin for select_all compatibility)
+                let formatter = self.formatter.read().await.clone();
                 let tool_futures: Vec<_> = tool_calls
                     .into_iter()
                     .map(|(call_id, tool_name, args_str)| {
@@ -2580,11 +4828,20 @@ impl Runner {
                         let cancel = cancel.clone();
                         let snapshot_store = self.snapshot_store.clone();
                         let file_time = self.file_time.clone();
+                        let formatter = formatter.clone();
+                        let breakers = self.breakers.clone();
                         let sandbox_manager = self.sandbox_manager.clone();
                         let todo_store = self.todo_store.clone();
+                        let hooks = self.hooks.clone();
+                        #[cfg(feature = "plugins")]
+                        let plugins = self.plugins.clone();
                         // Create event channel for immediate tool event notifications
                         let (tool_event_tx, mut tool_event_rx) = tokio::sync::mpsc::unbounded_channel();
                         let update_tx_for_events = update_tx.clone();
+                        let background = self.background.clone();
+                        let call_id_for_events = call_id.clone();
+                        let running_tools = self.running_tools.clone();
+                        let tool_latency = self.tool_latency.clone();
 
                         // Spawn task to forward tool events to TUI updates
                         tokio::spawn(async move {
@@ -2606,6 +4863,30 @@ impl Runner {
                                         );
                                         send_update(&update_tx_for_events, AppUpdate::TodosUpdated { phases, todos });
                                     }
+                                    wonopcode_tools::ToolEvent::ProcessStarted { pid, command, stop } => {
+                                        background.write().await.insert(pid, stop);
+                                        send_update(&update_tx_for_events, AppUpdate::ProcessStarted { pid, command });
+                                    }
+                                    wonopcode_tools::ToolEvent::ProcessOutput { pid, text } => {
+                                        send_update(&update_tx_for_events, AppUpdate::ProcessOutput { pid, text });
+                                    }
+                                    wonopcode_tools::ToolEvent::ProcessExited { pid, exit_code } => {
+                                        background.write().await.remove(&pid);
+                                        send_update(&update_tx_for_events, AppUpdate::ProcessExited { pid, exit_code });
+                                    }
+                                    wonopcode_tools::ToolEvent::Progress { fraction, message } => {
+                                        send_update(&update_tx_for_events, AppUpdate::ToolProgress {
+                                            id: call_id_for_events.clone(),
+                                            fraction,
+                                            message,
+                                        });
+                                    }
+                                    wonopcode_tools::ToolEvent::Output { text } => {
+                                        send_update(&update_tx_for_events, AppUpdate::ToolOutput {
+                                            id: call_id_for_events.clone(),
+                                            text,
+                                        });
+                                    }
                                 }
                             }
                             debug!("Tool event receiver task ended");
@@ -2616,96 +4897,124 @@ impl Runner {
                             let input: serde_json::Value =
                                 serde_json::from_str(&args_str).unwrap_or(serde_json::Value::Null);
 
-                            // Get sandbox runtime for this tool (if enabled and not bypassed)
-                            let sandbox = get_sandbox_for_tool(&tool_name, &sandbox_manager).await;
-
-                            // Special handling for task tool - run subagent
-                            let result = if tool_name == "task" {
-                                match serde_json::from_value::<task::TaskArgs>(input.clone()) {
-                                    Ok(args) => {
-                                        send_update(&update_tx, AppUpdate::Status(format!(
-                                            "Running {} subagent: {}",
-                                            args.subagent_type, args.description
-                                        )));
-
-                                        let subagent_result = run_subagent_standalone(
-                                            &args.subagent_type,
-                                            &args.prompt,
-                                            &cwd,
-                                            provider,
-                                            config,
-                                            tools.clone(),
-                                            cancel,
-                                            snapshot_store.clone(),
-                                            file_time.clone(),
-                                            sandbox.clone(),
-                                        )
+                            // The call can be cancelled on its own, without the prompt
+                            let cancel = cancel.child_token();
+                            running_tools
+                                .write()
+                                .await
+                                .insert(call_id.clone(), cancel.clone());
+                            let tool_cancel = cancel.clone();
+
+                            let run = async {
+                                // Get sandbox runtime for this tool (if enabled and not bypassed)
+                                let sandbox =
+                                    get_sandbox_for_tool(&tool_name, &sandbox_manager, Some(&tool_event_tx))
                                         .await;
 
-                                        // Sync todos after subagent completes (subagents may have called todowrite)
-                                        let phased = todo::get_phased_todos(todo_store.as_ref(), &cwd);
-                                        if !phased.is_empty() {
-                                            debug!(
-                                                phase_count = phased.phases.len(),
-                                                todo_count = phased.total_todos(),
-                                                "Syncing todos after subagent completion"
-                                            );
-                                            let (phases, todos) = convert_phased_todos_to_updates(&phased);
-                                            send_update(&update_tx, AppUpdate::TodosUpdated { phases, todos });
-                                        }
+                                // Special handling for task tool - run subagent
+                                if tool_name == "task" {
+                                    match serde_json::from_value::<task::TaskArgs>(input.clone()) {
+                                        Ok(args) => {
+                                            send_update(&update_tx, AppUpdate::Status(format!(
+                                                "Running {} subagent: {}",
+                                                args.subagent_type, args.description
+                                            )));
+
+                                            let subagent_result = run_subagent_standalone(
+                                                &args.subagent_type,
+                                                &args.prompt,
+                                                &cwd,
+                                                provider,
+                                                config,
+                                                tools.clone(),
+                                                cancel,
+                                                snapshot_store.clone(),
+                                                file_time.clone(),
+                                                formatter.clone(),
+                                                sandbox.clone(),
+                                                hooks.clone(),
+                                                breakers.clone(),
+                                            )
+                                            .await;
 
-                                        match subagent_result {
-                                            Ok(response) => Ok(wonopcode_tools::ToolOutput::new(
-                                                format!("Task completed: {}", args.description),
-                                                response,
-                                            )),
-                                            Err(e) => {
-                                                error!(
-                                                    tool = "task",
-                                                    subagent_type = %args.subagent_type,
-                                                    description = %args.description,
-                                                    error = %e,
-                                                    "Subagent execution failed"
+                                            // Sync todos after subagent completes (subagents may have called todowrite)
+                                            let phased = todo::get_phased_todos(todo_store.as_ref(), &cwd);
+                                            if !phased.is_empty() {
+                                                debug!(
+                                                    phase_count = phased.phases.len(),
+                                                    todo_count = phased.total_todos(),
+                                                    "Syncing todos after subagent completion"
                                                 );
-                                                Err(wonopcode_tools::ToolError::execution_failed(
-                                                    format!("Subagent failed: {e}"),
-                                                ))
+                                                let (phases, todos) = convert_phased_todos_to_updates(&phased);
+                                                send_update(&update_tx, AppUpdate::TodosUpdated { phases, todos });
+                                            }
+
+                                            match subagent_result {
+                                                Ok(response) => Ok(wonopcode_tools::ToolOutput::new(
+                                                    format!("Task completed: {}", args.description),
+                                                    response,
+                                                )),
+                                                Err(e) => {
+                                                    error!(
+                                                        tool = "task",
+                                                        subagent_type = %args.subagent_type,
+                                                        description = %args.description,
+                                                        error = %e,
+                                                        "Subagent execution failed"
+                                                    );
+                                                    Err(wonopcode_tools::ToolError::execution_failed(
+                                                        format!("Subagent failed: {e}"),
+                                                    ))
+                                                }
                                             }
                                         }
+                                        Err(e) => {
+                                            // Truncate prompt for logging
+                                            let prompt_truncated = if args_str.len() > 500 {
+                                                format!("{}... [truncated]", &args_str[..500])
+                                            } else {
+                                                args_str.clone()
+                                            };
+                                            error!(
+                                                tool = "task",
+                                                error = %e,
+                                                arguments = %prompt_truncated,
+                                                "Invalid task arguments"
+                                            );
+                                            Err(wonopcode_tools::ToolError::validation(format!(
+                                                "Invalid task arguments: {e}"
+                                            )))
+                                        }
                                     }
-                                    Err(e) => {
-                                        // Truncate prompt for logging
-                                        let prompt_truncated = if args_str.len() > 500 {
-                                            format!("{}... [truncated]", &args_str[..500])
-                                        } else {
-                                            args_str.clone()
-                                        };
-                                        error!(
-                                            tool = "task",
-                                            error = %e,
-                                            arguments = %prompt_truncated,
-                                            "Invalid task arguments"
-                                        );
-                                        Err(wonopcode_tools::ToolError::validation(format!(
-                                            "Invalid task arguments: {e}"
-                                        )))
-                                    }
+                                } else {
+                                    // Execute regular tool
+                                    execute_tool_standalone(
+                                        &tool_name,
+                                        input,
+                                        &cwd,
+                                        tools,
+                                        cancel,
+                                        snapshot_store,
+                                        file_time,
+                                        formatter,
+                                        sandbox,
+                                        Some(tool_event_tx.clone()),
+                                    )
+                                    .await
                                 }
-                            } else {
-                                // Execute regular tool
-                                execute_tool_standalone(
-                                    &tool_name,
-                                    input,
-                                    &cwd,
-                                    tools,
-                                    cancel,
-                                    snapshot_store,
-                                    file_time,
-                                    sandbox,
-                                    Some(tool_event_tx),
-                                )
-                                .await
                             };
+                            let result = tokio::select! {
+                                biased;
+                                _ = tool_cancel.cancelled() => {
+                                    info!(tool = %tool_name, call_id = %call_id, "Tool call cancelled");
+                                    Err(wonopcode_tools::ToolError::execution_failed(
+                                        "Cancelled by the user",
+                                    ))
+                                }
+                                result = run => result,
+                            };
+                            running_tools.write().await.remove(&call_id);
+                            drop(tool_event_tx);
 
                             let (output, success, metadata) = match result {
                                 Ok(out) => {
@@ -2723,6 +5032,19 @@ impl Runner {
                                 Err(e) => (format!("Error: {e}"), false, serde_json::Value::Null),
                             };
 
+                            // Run post-tool hooks, which may transform the output
+                            let output = {
+                                let hooks = hooks.read().await;
+                                if hooks.has_tool_hooks() {
+                                    let normalized = tool_name.rsplit("__").next().unwrap_or(&tool_name);
+                                    let input = serde_json::from_str(&args_str).unwrap_or_default();
+                                    let call = ToolCall::new(normalized, input, &cwd);
+                                    hooks.after_tool(&call, output, success).await
+                                } else {
+                                    output
+                                }
+                            };
+
                             // Log tool completion with performance metrics
                             let tool_duration = tool_start.elapsed();
                             info!(
@@ -2734,6 +5056,15 @@ impl Runner {
                                 "Tool completed"
                             );
                             perf::log_tool(&tool_name, tool_duration, success);
+                            telemetry::record_tool(&tool_name, tool_duration, success);
+                            {
+                                let mut latency = tool_latency.write().await;
+                                let durations = latency.entry(tool_name.clone()).or_default();
+                                durations.push(tool_duration.as_millis() as u64);
+                                if durations.len() > MAX_TOOL_LATENCY_SAMPLES {
+                                    durations.remove(0);
+                                }
+                            }
 
                             send_update(&update_tx, AppUpdate::ToolCompleted {
                                 id: call_id.clone(),
@@ -2742,6 +5073,30 @@ impl Runner {
                                 metadata: Some(metadata.clone()),
                             });
 
+                            #[cfg(feature = "plugins")]
+                            {
+                                plugins.dispatch(HookEvent::ToolExecuted, serde_json::json!({
+                                    "tool": tool_name,
+                                    "call_id": call_id,
+                                    "input": serde_json::from_str::<serde_json::Value>(&args_str)
+                                        .unwrap_or_default(),
+                                    "success": success,
+                                    "output": output,
+                                }));
+                                if success && matches!(tool_name.as_str(), "write" | "edit" | "multiedit") {
+                                    if let Some(path) = metadata
+                                        .get("path")
+                                        .or_else(|| metadata.get("file"))
+                                        .and_then(|v| v.as_str())
+                                    {
+                                        plugins.dispatch(
+                                            HookEvent::FileEdited,
+                                            serde_json::json!({ "path": path }),
+                                        );
+                                    }
+                                }
+                            }
+
                             // Check for agent change in metadata (from plan mode tools)
                             if success {
                                 if let Some(agent) =
@@ -2927,6 +5282,45 @@ impl Runner {
                 continue;
             }
 
+            // Stop hooks can block the agent from finishing
+            let stop_reason = {
+                let hooks = self.hooks.read().await;
+                hooks
+                    .run_shell_hooks(
+                        ShellHookEvent::Stop,
+                        None,
+                        serde_json::json!({
+                            "stop_hook_active": stop_hook_blocks > 0,
+                            "last_message": final_text,
+                        }),
+                        cwd,
+                    )
+                    .await
+                    .blocked
+            };
+            match stop_reason {
+                Some(reason) if stop_hook_blocks >= MAX_STOP_HOOK_BLOCKS => {
+                    warn!(reason = %reason, "Stop hook kept blocking, stopping anyway");
+                    send_update(
+                        &update_tx,
+                        AppUpdate::Error(format!(
+                            "Stop hook blocked {MAX_STOP_HOOK_BLOCKS} times in a row; stopping anyway: {reason}"
+                        )),
+                    );
+                }
+                Some(reason) => {
+                    info!(reason = %reason, "Stop hook blocked, continuing");
+                    send_update(
+                        &update_tx,
+                        AppUpdate::Status(format!("Stop hook: {reason}")),
+                    );
+                    stop_hook_blocks += 1;
+                    messages.push(ProviderMessage::user(reason));
+                    continue;
+                }
+                None => {}
+            }
+
             debug!(
                 final_text_len = final_text.len(),
                 finish_reason = ?finish_reason,
@@ -3097,12 +5491,116 @@ impl Runner {
         }
     }
 
-    /// Handle git commit action.
+    /// Handle git diff action.
+    async fn handle_git_diff(
+        &self,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+        path: String,
+        staged: bool,
+    ) {
+        let cwd = self.instance.directory();
+        let git = GitOperations::new(cwd);
+
+        match git.diff(Some(&path), staged) {
+            Ok(diff) => {
+                send_update(&update_tx, AppUpdate::GitDiffUpdated { path, staged, diff });
+            }
+            Err(e) => {
+                send_update(
+                    &update_tx,
+                    AppUpdate::GitOperationResult {
+                        success: false,
+                        message: e.to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Handle git stage or unstage hunk action.
+    async fn handle_git_hunk(
+        &self,
+        update_tx: &mpsc::UnboundedSender<AppUpdate>,
+        path: String,
+        hunk: usize,
+        staged: bool,
+    ) {
+        let cwd = self.instance.directory();
+        let git = GitOperations::new(cwd);
+
+        let (result, verb) = if staged {
+            (git.unstage_hunk(&path, hunk), "Unstaged")
+        } else {
+            (git.stage_hunk(&path, hunk), "Staged")
+        };
+        match result {
+            Ok(()) => {
+                send_update(
+                    &update_tx,
+                    AppUpdate::GitOperationResult {
+                        success: true,
+                        message: format!("{verb} hunk {} of {path}", hunk + 1),
+                    },
+                );
+                // Refresh the hunks left, then status
+                self.handle_git_diff(update_tx, path, staged).await;
+                self.handle_git_status(update_tx).await;
+            }
+            Err(e) => {
+                send_update(
+                    &update_tx,
+                    AppUpdate::GitOperationResult {
+                        success: false,
+                        message: e.to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Handle git generate commit message action.
+    ///
+    /// The model call runs in the background, so the runner keeps taking
+    /// actions meanwhile.
+    fn handle_git_generate_commit_message(&self, update_tx: &mpsc::UnboundedSender<AppUpdate>) {
+        let cwd = self.instance.directory();
+        let git = GitOperations::new(cwd);
+
+        let diff = match git.diff(None, true) {
+            Ok(diff) => diff,
+            Err(e) => {
+                send_update(
+                    update_tx,
+                    AppUpdate::GitOperationResult {
+                        success: false,
+                        message: e.to_string(),
+                    },
+                );
+                return;
+            }
+        };
+
+        let provider = self.provider.clone();
+        let update_tx = update_tx.clone();
+        tokio::spawn(async move {
+            let provider = provider.read().await;
+            let update = match commit_message::draft(&provider, &diff).await {
+                Ok(message) => AppUpdate::GitCommitMessageGenerated(message),
+                Err(message) => AppUpdate::GitOperationResult {
+                    success: false,
+                    message,
+                },
+            };
+            send_update(&update_tx, update);
+        });
+    }
+
+    /// Handle git commit action, returning whether it committed.
     async fn handle_git_commit(
         &self,
         update_tx: &mpsc::UnboundedSender<AppUpdate>,
         message: String,
-    ) {
+    ) -> bool {
         let cwd = self.instance.directory();
         let git = GitOperations::new(cwd);
 
@@ -3117,6 +5615,7 @@ impl Runner {
                 );
                 // Refresh status
                 self.handle_git_status(update_tx).await;
+                true
             }
             Err(e) => {
                 send_update(
@@ -3126,6 +5625,7 @@ impl Runner {
                         message: e.to_string(),
                     },
                 );
+                false
             }
         }
     }
@@ -3276,7 +5776,10 @@ async fn run_subagent_standalone(
     cancel: CancellationToken,
     snapshot_store: Option<Arc<SnapshotStore>>,
     file_time: Arc<FileTimeState>,
+    formatter: Option<Arc<dyn FileFormatter>>,
     sandbox: Option<Arc<dyn SandboxRuntime>>,
+    hooks: Arc<RwLock<HookRegistry>>,
+    breakers: ProviderBreakers,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     use futures::StreamExt;
     use wonopcode_provider::message::ContentPart;
@@ -3341,10 +5844,15 @@ async fn run_subagent_standalone(
         };
 
         // Call provider
-        let stream = {
-            let provider = provider.read().await;
-            provider.generate(messages.clone(), options).await?
-        };
+        let provider_id = provider.read().await.model_info().provider_id.clone();
+        let stream = breakers
+            .call(&provider_id, &cancel, || {
+                let provider = provider.clone();
+                let messages = messages.clone();
+                let options = options.clone();
+                async move { provider.read().await.generate(messages, options).await }
+            })
+            .await?;
         tokio::pin!(stream);
 
         let mut current_text = String::new();
@@ -3440,6 +5948,18 @@ async fn run_subagent_standalone(
                 let args: serde_json::Value = serde_json::from_str(args_str)
                     .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
 
+                // Run pre-tool hooks, without holding the registry while the
+                // tool runs, so that hooks can be reloaded meanwhile
+                let mut call = ToolCall::new(name, args, cwd);
+                let allowed = hooks.read().await.before_tool(&mut call).await;
+                if let Err(reason) = allowed {
+                    messages.push(ProviderMessage::tool_result(
+                        id,
+                        format!("Tool execution blocked by hook: {reason}"),
+                    ));
+                    continue;
+                }
+
                 // Execute tool
                 let tool = match tools.get(name) {
                     Some(t) => t,
@@ -3463,12 +5983,15 @@ async fn run_subagent_standalone(
                     file_time: Some(file_time.clone()),
                     sandbox: sandbox.clone(),
                     event_tx: None, // Subagents don't need event_tx for now
+                    formatter: formatter.clone(),
+                    client_fs: None,
                 };
 
-                match tool.execute(args, &ctx).await {
-                    Ok(out) => out.output,
-                    Err(e) => format!("Error: {e}"),
-                }
+                let (output, success) = match tool.execute(call.args.clone(), &ctx).await {
+                    Ok(out) => (out.output, true),
+                    Err(e) => (format!("Error: {e}"), false),
+                };
+                hooks.read().await.after_tool(&call, output, success).await
             };
 
             // Add tool result
@@ -3506,6 +6029,7 @@ async fn execute_tool_standalone(
     cancel: CancellationToken,
     snapshot_store: Option<Arc<SnapshotStore>>,
     file_time: Arc<FileTimeState>,
+    formatter: Option<Arc<dyn FileFormatter>>,
     sandbox: Option<Arc<dyn SandboxRuntime>>,
     event_tx: Option<tokio::sync::mpsc::UnboundedSender<wonopcode_tools::ToolEvent>>,
 ) -> Result<wonopcode_tools::ToolOutput, wonopcode_tools::ToolError> {
@@ -3524,6 +6048,8 @@ async fn execute_tool_standalone(
         file_time: Some(file_time),
         sandbox,
         event_tx,
+        formatter,
+        client_fs: None,
     };
 
     info!(tool = tool_name, "Executing tool");
@@ -3562,10 +6088,12 @@ async fn execute_tool_standalone(
 /// Get sandbox runtime for a tool from the sandbox manager.
 ///
 /// Returns None if the sandbox manager is not configured, the tool bypasses sandbox,
-/// the sandbox was explicitly stopped, or the sandbox failed to start.
+/// the sandbox was explicitly stopped, or the sandbox failed to start. The
+/// progress of starting it is reported through the event sender, if any.
 async fn get_sandbox_for_tool(
     tool_name: &str,
     sandbox_manager: &Option<Arc<SandboxManager>>,
+    event_tx: Option<&tokio::sync::mpsc::UnboundedSender<wonopcode_tools::ToolEvent>>,
 ) -> Option<Arc<dyn SandboxRuntime>> {
     let manager = match sandbox_manager.as_ref() {
         Some(m) => m,
@@ -3603,7 +6131,24 @@ async fn get_sandbox_for_tool(
             if !is_ready {
                 // Auto-start sandbox for tool execution
                 info!(tool = tool_name, "Starting sandbox for tool");
-                if let Err(e) = runtime.start().await {
+                let started = match event_tx {
+                    Some(event_tx) => {
+                        // Report the progress of starting, such as an image pull
+                        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<StartProgress>();
+                        let event_tx = event_tx.clone();
+                        tokio::spawn(async move {
+                            while let Some(progress) = rx.recv().await {
+                                let _ = event_tx.send(wonopcode_tools::ToolEvent::Progress {
+                                    fraction: progress.fraction,
+                                    message: Some(progress.message),
+                                });
+                            }
+                        });
+                        runtime.start_with_progress(tx).await
+                    }
+                    None => runtime.start().await,
+                };
+                if let Err(e) = started {
                     warn!(tool = tool_name, error = %e, "Failed to start sandbox for tool");
                     return None;
                 }
@@ -3897,7 +6442,13 @@ fn infer_provider_from_model(model: &str) -> Option<&'static str> {
 }
 
 /// Build system prompt with environment context.
-fn build_system_prompt_for_session(provider: &str, model: &str, cwd: &Path) -> String {
+fn build_system_prompt_for_session(
+    provider: &str,
+    model: &str,
+    cwd: &Path,
+    custom_instructions: Option<&str>,
+    vars: &PromptVars,
+) -> String {
     // Detect if git repo
     let is_git_repo = cwd.join(".git").exists();
 
@@ -3907,9 +6458,6 @@ fn build_system_prompt_for_session(provider: &str, model: &str, cwd: &Path) -> S
     // Generate file tree (limited to top-level for now)
     let file_tree = generate_file_tree(cwd, 2, 20);
 
-    // Load custom instructions from AGENTS.md, CLAUDE.md, etc.
-    let custom_instructions = load_custom_instructions(cwd);
-
     // Generate environment context
     let environment =
         system_prompt::environment_context(cwd, is_git_repo, platform, file_tree.as_deref());
@@ -3919,8 +6467,9 @@ fn build_system_prompt_for_session(provider: &str, model: &str, cwd: &Path) -> S
         provider,
         model,
         None, // agent_prompt - will be added for subagents
-        custom_instructions.as_deref(),
+        custom_instructions,
         &environment,
+        vars,
     )
 }
 
@@ -4001,39 +6550,74 @@ fn generate_file_tree(dir: &Path, max_depth: usize, max_files: usize) -> Option<
     }
 }
 
-/// Load custom instructions from common instruction files.
-fn load_custom_instructions(cwd: &Path) -> Option<String> {
-    // Look for custom instruction files in order of priority
-    let instruction_files = [
-        ".wonopcode/AGENTS.md",
-        "AGENTS.md",
-        ".claude/CLAUDE.md",
-        "CLAUDE.md",
-        ".wonopcode/instructions.md",
-        ".cursor/rules",
-    ];
-
-    let mut instructions = Vec::new();
-
-    for file in &instruction_files {
-        let path = cwd.join(file);
-        if path.exists() {
-            if let Ok(content) = std::fs::read_to_string(&path) {
-                if !content.trim().is_empty() {
-                    instructions.push(format!(
-                        "# Instructions from {}\n\n{}",
-                        file,
-                        content.trim()
-                    ));
-                }
-            }
-        }
+/// Instruction files as reported to the UI: included files first.
+fn instruction_report(instructions: &Instructions) -> Vec<InstructionFileUpdate> {
+    let included = instructions.files.iter().map(|f| InstructionFileUpdate {
+        path: f.display.clone(),
+        tokens: f.tokens,
+        included: true,
+        note: f
+            .truncated
+            .then(|| "truncated to fit the budget".to_string()),
+    });
+    let skipped = instructions.skipped.iter().map(|f| InstructionFileUpdate {
+        path: f.display.clone(),
+        tokens: f.tokens,
+        included: false,
+        note: Some(f.reason.clone()),
+    });
+    included.chain(skipped).collect()
+}
+
+/// Active scoped rules as reported to the UI.
+fn scoped_rules_report(rules: &[ScopedRules]) -> Vec<InstructionFileUpdate> {
+    rules
+        .iter()
+        .map(|rules| InstructionFileUpdate {
+            path: rules.display.clone(),
+            tokens: rules.tokens,
+            included: true,
+            note: Some(format!("scoped to {}", rules.dir)),
+        })
+        .collect()
+}
+
+/// Files a tool call reads or edits, as given in its arguments.
+fn tool_file_paths(tool_name: &str, args_str: &str) -> Vec<String> {
+    let base_tool_name = tool_name.rsplit("__").next().unwrap_or(tool_name);
+    let Ok(args) = serde_json::from_str::<serde_json::Value>(args_str) else {
+        return Vec::new();
+    };
+    let file_path = |value: &serde_json::Value| {
+        value
+            .get("filePath")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    };
+    match base_tool_name {
+        "read" | "edit" | "write" => file_path(&args).into_iter().collect(),
+        "multiedit" => args
+            .get("edits")
+            .and_then(|v| v.as_array())
+            .map(|edits| edits.iter().filter_map(file_path).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
     }
+}
 
-    if instructions.is_empty() {
-        None
-    } else {
-        Some(instructions.join("\n\n"))
+/// Name of a symbol kind shown next to symbols suggested for `@` mentions.
+fn symbol_kind_name(kind: wonopcode_lsp::SymbolKind) -> &'static str {
+    use wonopcode_lsp::SymbolKind;
+    match kind {
+        SymbolKind::CLASS => "class",
+        SymbolKind::FUNCTION => "function",
+        SymbolKind::METHOD => "method",
+        SymbolKind::INTERFACE => "interface",
+        SymbolKind::VARIABLE => "variable",
+        SymbolKind::CONSTANT => "constant",
+        SymbolKind::STRUCT => "struct",
+        SymbolKind::ENUM => "enum",
+        _ => "symbol",
     }
 }
 
@@ -4123,31 +6707,6 @@ fn convert_mcp_remote_config(name: &str, config: &McpRemoteConfig) -> McpServerC
     server_config
 }
 
-/// Extract path from tool input for permission checking.
-fn extract_path_from_input(input: &serde_json::Value) -> Option<String> {
-    // Different tools use different field names for paths
-    let path_fields = ["filePath", "path", "file", "directory", "workdir"];
-
-    for field in &path_fields {
-        if let Some(path) = input.get(field).and_then(|v| v.as_str()) {
-            return Some(path.to_string());
-        }
-    }
-
-    // For bash tool, try to extract path from command
-    if let Some(command) = input.get("command").and_then(|v| v.as_str()) {
-        // Extract first path-like argument from command
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        for part in parts.iter().skip(1) {
-            if part.starts_with('/') || part.starts_with("./") || part.starts_with("../") {
-                return Some(part.to_string());
-            }
-        }
-    }
-
-    None
-}
-
 /// Extract modified file info from an observed tool (Claude CLI MCP tools).
 /// Returns a ModifiedFileUpdate if the tool modifies files.
 fn extract_modified_file_from_observed_tool(
@@ -4387,195 +6946,6 @@ fn extract_count_before(text: &str, suffix: &str) -> Option<u64> {
     num_str.parse().ok()
 }
 
-/// Determine the action type for a tool call.
-fn determine_tool_action(tool_name: &str, input: &serde_json::Value) -> String {
-    match tool_name {
-        "read" => "read".to_string(),
-        "write" => "write".to_string(),
-        "edit" | "multiedit" => "edit".to_string(),
-        "glob" | "grep" => "search".to_string(),
-        "bash" => {
-            // Determine if it's a read or write operation
-            if let Some(command) = input.get("command").and_then(|v| v.as_str()) {
-                let read_commands = [
-                    "cat",
-                    "head",
-                    "tail",
-                    "less",
-                    "more",
-                    "ls",
-                    "pwd",
-                    "find",
-                    "grep",
-                    "rg",
-                    "tree",
-                    "git status",
-                    "git log",
-                    "git diff",
-                ];
-                let write_commands = [
-                    "rm",
-                    "mv",
-                    "cp",
-                    "mkdir",
-                    "rmdir",
-                    "touch",
-                    "chmod",
-                    "chown",
-                    "git add",
-                    "git commit",
-                    "git push",
-                ];
-
-                if read_commands.iter().any(|c| command.starts_with(c)) {
-                    "execute_read".to_string()
-                } else if write_commands.iter().any(|c| command.starts_with(c)) {
-                    "execute_write".to_string()
-                } else {
-                    "execute".to_string()
-                }
-            } else {
-                "execute".to_string()
-            }
-        }
-        "webfetch" => "fetch".to_string(),
-        "task" => "spawn_agent".to_string(),
-        "todowrite" | "todoread" => "manage_todos".to_string(),
-        "lsp" => "lsp_query".to_string(),
-        "skill" => "load_skill".to_string(),
-        _ => "execute".to_string(),
-    }
-}
-
-/// Format a human-readable description of the tool call for permission prompts.
-fn format_tool_description(tool_name: &str, input: &serde_json::Value) -> String {
-    match tool_name {
-        "read" => {
-            if let Some(path) = input.get("filePath").and_then(|v| v.as_str()) {
-                format!("Read file: {path}")
-            } else {
-                "Read a file".to_string()
-            }
-        }
-        "write" => {
-            if let Some(path) = input.get("filePath").and_then(|v| v.as_str()) {
-                format!("Write to file: {path}")
-            } else {
-                "Write to a file".to_string()
-            }
-        }
-        "edit" => {
-            if let Some(path) = input.get("filePath").and_then(|v| v.as_str()) {
-                format!("Edit file: {path}")
-            } else {
-                "Edit a file".to_string()
-            }
-        }
-        "bash" => {
-            if let Some(command) = input.get("command").and_then(|v| v.as_str()) {
-                let truncated = if command.len() > 60 {
-                    format!("{}...", &command[..60])
-                } else {
-                    command.to_string()
-                };
-                format!("Execute: {truncated}")
-            } else {
-                "Execute a bash command".to_string()
-            }
-        }
-        "glob" => {
-            if let Some(pattern) = input.get("pattern").and_then(|v| v.as_str()) {
-                format!("Search files: {pattern}")
-            } else {
-                "Search for files".to_string()
-            }
-        }
-        "grep" => {
-            if let Some(pattern) = input.get("pattern").and_then(|v| v.as_str()) {
-                format!("Search content: {pattern}")
-            } else {
-                "Search file contents".to_string()
-            }
-        }
-        "webfetch" => {
-            if let Some(url) = input.get("url").and_then(|v| v.as_str()) {
-                format!("Fetch URL: {url}")
-            } else {
-                "Fetch a web page".to_string()
-            }
-        }
-        "task" => {
-            if let Some(desc) = input.get("description").and_then(|v| v.as_str()) {
-                format!("Run task: {desc}")
-            } else {
-                "Run a sub-task".to_string()
-            }
-        }
-        _ => format!("Execute tool: {tool_name}"),
-    }
-}
-
-/// Convert core SandboxConfig to wonopcode-sandbox SandboxConfig.
-fn convert_sandbox_config(core_config: &CoreSandboxConfig) -> SandboxConfig {
-    use wonopcode_sandbox::{MountConfig, NetworkPolicy, ResourceLimits};
-
-    // Parse runtime type
-    let runtime = match core_config.runtime.as_deref() {
-        Some("docker") => SandboxRuntimeType::Docker,
-        Some("podman") => SandboxRuntimeType::Podman,
-        Some("lima") => SandboxRuntimeType::Lima,
-        Some("none") => SandboxRuntimeType::None,
-        _ => SandboxRuntimeType::Auto,
-    };
-
-    // Parse network policy
-    let network = match core_config.network.as_deref() {
-        Some("full") => NetworkPolicy::Full,
-        Some("none") => NetworkPolicy::None,
-        _ => NetworkPolicy::Limited,
-    };
-
-    // Build resource limits
-    let resources = if let Some(res) = &core_config.resources {
-        ResourceLimits {
-            memory: res.memory.clone().unwrap_or_else(|| "2G".to_string()),
-            cpus: res.cpus.unwrap_or(2.0),
-            disk: None,
-            pids: res.pids.unwrap_or(256),
-            readonly_rootfs: false,
-        }
-    } else {
-        ResourceLimits::default()
-    };
-
-    // Build mount config
-    let mounts = if let Some(m) = &core_config.mounts {
-        MountConfig {
-            workspace_writable: m.workspace_writable.unwrap_or(true),
-            readonly: std::collections::HashMap::new(),
-            persist_caches: m.persist_caches.unwrap_or(true),
-            workspace_path: m
-                .workspace_path
-                .clone()
-                .unwrap_or_else(|| "/workspace".to_string()),
-        }
-    } else {
-        MountConfig::default()
-    };
-
-    SandboxConfig {
-        enabled: core_config.enabled.unwrap_or(false),
-        runtime,
-        image: core_config.image.clone(),
-        resources,
-        network,
-        mounts,
-        bypass_tools: core_config.bypass_tools.clone().unwrap_or_default(),
-        keep_alive: core_config.keep_alive.unwrap_or(true),
-        startup_timeout_secs: 60,
-    }
-}
-
 #[cfg(test)]
 mod observed_tool_tests {
     use super::*;
@@ -4621,3 +6991,39 @@ mod observed_tool_tests {
         assert_eq!(m.get("completed").and_then(|v| v.as_u64()), Some(2));
     }
 }
+
+#[cfg(test)]
+mod prompt_mark_tests {
+    use super::*;
+
+    fn at(secs: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_cut_at_prompt() {
+        let mut marks = PromptMarks::default();
+        marks.push(at(10), 0);
+        marks.push(at(20), 2);
+        marks.push(at(30), 5);
+
+        // Reverting between two prompts cuts before the later one
+        assert_eq!(marks.cut(at(15)), Some(2));
+        assert_eq!(marks.marks.len(), 1);
+        assert_eq!(marks.cut(at(40)), None);
+        assert_eq!(marks.cut(at(10)), Some(0));
+    }
+
+    #[test]
+    fn test_cut_after_compaction() {
+        let mut marks = PromptMarks::default();
+        marks.push(at(10), 0);
+        marks.push(at(20), 2);
+        marks.compacted();
+        marks.push(at(30), 3);
+
+        assert_eq!(marks.clone().cut(at(25)), Some(3));
+        // Prompts before the compaction drop the whole history
+        assert_eq!(marks.cut(at(15)), Some(0));
+    }
+}
@@ -0,0 +1,115 @@
+//! Running scheduled tasks.
+//!
+//! Headless mode spawns [`spawn`], which checks the `schedule` config every
+//! [`CHECK_INTERVAL`] and runs due tasks one at a time. Each task runs in its
+//! own runner, like `wonopcode run`, so it doesn't disturb connected clients.
+
+use crate::commands::parse_model_spec;
+use crate::unattended::Unattended;
+use chrono::{Local, Utc};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use wonopcode_core::{Instance, ScheduledRun, ScheduledTask, Scheduler};
+use wonopcode_tui::AppAction;
+
+/// How often to check for due tasks.
+const CHECK_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Run scheduled tasks until aborted.
+///
+/// `default_model` (provider/model) is used for tasks without a model.
+/// Config reloads are picked up on the next check.
+pub fn spawn(instance: Instance, default_model: String) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let config = instance.config().await;
+        let mut scheduler = Scheduler::new(ScheduledTask::all(&config), Local::now());
+        for task in scheduler.tasks() {
+            if let Some(next) = scheduler.next_run(&task.name) {
+                info!(task = %task.name, next = %next, "Scheduled task");
+            }
+        }
+
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let config = instance.config().await;
+            scheduler.update(ScheduledTask::all(&config), Local::now());
+
+            for task in scheduler.due(Local::now()) {
+                run_and_record(&instance, &task, &default_model).await;
+            }
+        }
+    })
+}
+
+/// Run a task, store it as a session and notify its webhook.
+pub async fn run_and_record(
+    instance: &Instance,
+    task: &ScheduledTask,
+    default_model: &str,
+) -> ScheduledRun {
+    info!(task = %task.name, "Running scheduled task");
+    let mut run = run_task(instance, task, default_model).await;
+
+    let directory = instance.directory().display().to_string();
+    let project_id = instance.project_id().await;
+    if let Err(e) = run
+        .save(&instance.session_repo(), &project_id, &directory)
+        .await
+    {
+        warn!(task = %task.name, error = %e, "Failed to store scheduled run");
+    }
+    match &run.error {
+        Some(error) => warn!(task = %task.name, error = %error, "Scheduled task failed"),
+        None => info!(task = %task.name, session = ?run.session_id, "Scheduled task finished"),
+    }
+
+    if let Some(url) = &task.webhook {
+        if let Err(e) = run.notify(url).await {
+            warn!(task = %task.name, error = %e, "Failed to notify scheduled task webhook");
+        }
+    }
+    run
+}
+
+/// Run a task's prompt in a fresh runner and collect the response.
+async fn run_task(instance: &Instance, task: &ScheduledTask, default_model: &str) -> ScheduledRun {
+    let model = task.model.as_deref().unwrap_or(default_model);
+    let (provider, model_id) = parse_model_spec(model, "anthropic");
+    let mut run = ScheduledRun {
+        task: task.name.clone(),
+        prompt: task.prompt.clone(),
+        agent: task.agent.clone().unwrap_or_else(|| "build".to_string()),
+        model: format!("{provider}/{model_id}"),
+        started_at: Utc::now().timestamp_millis(),
+        finished_at: 0,
+        output: String::new(),
+        error: None,
+        session_id: None,
+    };
+
+    let result = execute(instance, task, provider, model_id).await;
+    run.finished_at = Utc::now().timestamp_millis();
+    match result {
+        Ok(output) => run.output = output,
+        Err(error) => run.error = Some(error),
+    }
+    run
+}
+
+async fn execute(
+    instance: &Instance,
+    task: &ScheduledTask,
+    provider: String,
+    model_id: String,
+) -> Result<String, String> {
+    let mut runner = Unattended::start(instance, provider, model_id).await?;
+    if let Some(agent) = &task.agent {
+        runner.send(AppAction::ChangeAgent(agent.clone()));
+    }
+    let result = runner.prompt(task.prompt.clone()).await;
+    runner.stop();
+    result
+}
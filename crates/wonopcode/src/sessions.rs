@@ -0,0 +1,83 @@
+//! Scoped sessions of a headless server.
+//!
+//! Each scoped session a client opens at `/sessions` gets a runner of its own
+//! in the primary project, next to the runner of the shared session. The
+//! server queues its prompts and streams its updates; this module starts the
+//! runner and converts between the two.
+
+use crate::projects::start_runner;
+use crate::runner::RunnerConfig;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use wonopcode_core::Instance;
+use wonopcode_protocol::Action;
+use wonopcode_server::{HeadlessState, SessionRequest};
+
+/// Start runners for the scoped sessions requested by the server.
+pub fn spawn_host(
+    instance: Instance,
+    base_config: RunnerConfig,
+    state: HeadlessState,
+    mut requests: mpsc::UnboundedReceiver<SessionRequest>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(request) = requests.recv().await {
+            let instance = instance.clone();
+            let base_config = base_config.clone();
+            let state = state.clone();
+            tokio::spawn(async move {
+                let result =
+                    open(&instance, &base_config, state, &request.id, request.actions).await;
+                if let Err(e) = &result {
+                    warn!(session_id = %request.id, error = %e, "Failed to start scoped session");
+                }
+                let _ = request.reply.send(result);
+            });
+        }
+    })
+}
+
+/// Start the runner of a scoped session, which stops when the server drops
+/// the session's action sender.
+async fn open(
+    instance: &Instance,
+    base_config: &RunnerConfig,
+    state: HeadlessState,
+    session_id: &str,
+    mut actions: mpsc::UnboundedReceiver<Action>,
+) -> Result<(), String> {
+    let (runner, tasks) = start_runner(instance, base_config).await?;
+    let (action_tx, action_rx) = mpsc::unbounded_channel();
+    let (update_tx, mut update_rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some(action) = actions.recv().await {
+            let Some(action) = crate::protocol_action_to_app(action) else {
+                continue;
+            };
+            if action_tx.send(action).is_err() {
+                break;
+            }
+        }
+    });
+
+    let id = session_id.to_string();
+    tokio::spawn(async move {
+        while let Some(update) = update_rx.recv().await {
+            if let Some(update) = crate::app_update_to_protocol(update) {
+                state.send_session_update(&id, update);
+            }
+        }
+    });
+
+    let id = session_id.to_string();
+    tokio::spawn(async move {
+        runner.run(action_rx, update_tx).await;
+        for task in tasks {
+            task.abort();
+        }
+        info!(session_id = %id, "Scoped session stopped");
+    });
+    Ok(())
+}
@@ -1,10 +1,24 @@
 //! Usage statistics command.
 //!
-//! Aggregates token usage, costs, and tool statistics across sessions.
-
-use std::collections::HashMap;
+//! Aggregates token usage, costs, and tool statistics across sessions, and
+//! recorded spend by day and model. Also backs the TUI's usage dashboard.
+//!
+//! `--export` writes a cost report instead: the cost and tokens of every
+//! assistant message, across projects, summed by day, model, project or
+//! agent (and optionally by month) as CSV or JSON.
+
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
 use std::path::Path;
-use wonopcode_core::{message::MessagePart, Instance};
+use wonopcode_core::message::{Message, MessagePart, ToolState};
+use wonopcode_core::quota::{format_bytes, StorageUsage};
+use wonopcode_core::{Instance, Project, SpendHistory, SpendTracker};
+use wonopcode_storage::Storage;
+
+/// Days of spend history shown when statistics cover all time.
+const DEFAULT_HISTORY_DAYS: u32 = 30;
 
 /// Aggregated session statistics.
 #[derive(Debug, Default)]
@@ -19,9 +33,15 @@ pub struct SessionStats {
     pub cost_per_day: f64,
     pub tokens_per_session: f64,
     pub median_tokens_per_session: f64,
+    pub storage: Option<StorageUsage>,
+    /// Recorded spend by day and model.
+    pub spend: Option<SpendHistory>,
+    /// Durations of completed tool calls in milliseconds, by tool, oldest
+    /// first.
+    pub tool_latency: HashMap<String, Vec<u64>>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default)]
 pub struct TokenStats {
     pub input: u64,
     pub output: u64,
@@ -49,6 +69,20 @@ pub async fn aggregate_session_stats(
     project_filter: Option<String>,
 ) -> anyhow::Result<SessionStats> {
     let instance = Instance::new(cwd).await?;
+    let stats = aggregate_instance_stats(&instance, days, project_filter).await;
+    instance.dispose().await;
+    Ok(stats)
+}
+
+/// Aggregate statistics from the sessions and spend of an instance.
+///
+/// `days` limits the statistics to the last days (0 for today only), and
+/// `project_filter` to a project: empty for the current one.
+pub async fn aggregate_instance_stats(
+    instance: &Instance,
+    days: Option<u32>,
+    project_filter: Option<String>,
+) -> SessionStats {
     let current_project_id = instance.project_id().await;
 
     let mut stats = SessionStats::default();
@@ -56,24 +90,30 @@ pub async fn aggregate_session_stats(
     stats.date_range.earliest = now;
     stats.date_range.latest = 0;
 
-    const MS_IN_DAY: i64 = 24 * 60 * 60 * 1000;
-
-    // Calculate cutoff time
-    let cutoff_time = if let Some(d) = days {
-        if d == 0 {
-            // Today only - and_hms_opt(0, 0, 0) is guaranteed to succeed for valid dates
+    match instance.storage_usage().await {
+        Ok(usage) => stats.storage = Some(usage),
+        Err(e) => tracing::warn!("Failed to collect storage usage: {}", e),
+    }
 
-            chrono::Utc::now()
-                .date_naive()
-                .and_hms_opt(0, 0, 0)
-                .map(|dt| dt.and_utc().timestamp_millis())
-                .unwrap_or(now)
+    let spend_project = project_filter.as_deref().map(|filter| {
+        if filter.is_empty() {
+            current_project_id.as_str()
         } else {
-            now - (d as i64) * MS_IN_DAY
+            filter
         }
-    } else {
-        0 // All time
-    };
+    });
+    let tracker = SpendTracker::new(
+        instance.storage().clone(),
+        current_project_id.clone(),
+        Default::default(),
+    );
+    let history_days = days.unwrap_or(DEFAULT_HISTORY_DAYS).max(1);
+    match tracker.history(history_days, spend_project).await {
+        Ok(history) => stats.spend = Some(history),
+        Err(e) => tracing::warn!("Failed to read spend history: {}", e),
+    }
+
+    let cutoff_time = cutoff_time(days, now);
 
     // Get all sessions
     let sessions = instance.list_sessions().await;
@@ -105,8 +145,7 @@ pub async fn aggregate_session_stats(
         .collect();
 
     if filtered_sessions.is_empty() {
-        instance.dispose().await;
-        return Ok(stats);
+        return stats;
     }
 
     stats.total_sessions = filtered_sessions.len();
@@ -145,10 +184,21 @@ pub async fn aggregate_session_stats(
                     + assistant_msg.tokens.reasoning) as u64;
             }
 
-            // Count tool usage
+            // Count tool usage and latency
             for part in &msg_with_parts.parts {
                 if let MessagePart::Tool(tool_part) = part {
                     *stats.tool_usage.entry(tool_part.tool.clone()).or_insert(0) += 1;
+                    if let ToolState::Completed { time, .. } | ToolState::Error { time, .. } =
+                        &tool_part.state
+                    {
+                        if let Some(end) = time.end {
+                            stats
+                                .tool_latency
+                                .entry(tool_part.tool.clone())
+                                .or_default()
+                                .push((end - time.start).max(0) as u64);
+                        }
+                    }
                 }
             }
         }
@@ -159,6 +209,7 @@ pub async fn aggregate_session_stats(
     }
 
     // Calculate derived statistics
+    const MS_IN_DAY: i64 = 24 * 60 * 60 * 1000;
     stats.date_range.earliest = earliest_time;
     stats.date_range.latest = latest_time;
     stats.days = ((latest_time - earliest_time) / MS_IN_DAY).max(1) as usize;
@@ -180,8 +231,292 @@ pub async fn aggregate_session_stats(
         session_total_tokens[len / 2] as f64
     };
 
-    instance.dispose().await;
-    Ok(stats)
+    stats
+}
+
+/// The start of the last `days` in milliseconds (0 for today only, `None`
+/// for all time).
+fn cutoff_time(days: Option<u32>, now: i64) -> i64 {
+    const MS_IN_DAY: i64 = 24 * 60 * 60 * 1000;
+
+    match days {
+        // Today only - and_hms_opt(0, 0, 0) is guaranteed to succeed for valid dates
+        Some(0) => chrono::Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .map(|dt| dt.and_utc().timestamp_millis())
+            .unwrap_or(now),
+        Some(d) => now - (d as i64) * MS_IN_DAY,
+        None => 0,
+    }
+}
+
+/// Formats of `wonopcode stats --export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format '{format}'; use csv or json")),
+        }
+    }
+}
+
+/// What the rows of a cost report sum up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportGroup {
+    Day,
+    Model,
+    Project,
+    Agent,
+}
+
+impl ReportGroup {
+    pub fn parse(group: &str) -> Result<Self, String> {
+        match group {
+            "day" => Ok(Self::Day),
+            "model" => Ok(Self::Model),
+            "project" => Ok(Self::Project),
+            "agent" => Ok(Self::Agent),
+            _ => Err(format!(
+                "unknown group '{group}'; use day, model, project or agent"
+            )),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Model => "model",
+            Self::Project => "project",
+            Self::Agent => "agent",
+        }
+    }
+
+    fn key(&self, usage: &MessageUsage) -> String {
+        match self {
+            Self::Day => usage.day.to_string(),
+            Self::Model => usage.model.clone(),
+            Self::Project => usage.project.clone(),
+            Self::Agent => usage.agent.clone(),
+        }
+    }
+}
+
+/// The cost and tokens of one assistant message.
+#[derive(Debug, Clone)]
+pub struct MessageUsage {
+    /// Local day the message was created.
+    pub day: NaiveDate,
+    /// `provider/model`.
+    pub model: String,
+    /// Project name, or its directory.
+    pub project: String,
+    pub agent: String,
+    pub cost: f64,
+    pub tokens: TokenStats,
+}
+
+/// One row of a cost report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReportRow {
+    /// `YYYY-MM`, in monthly reports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month: Option<String>,
+    /// The day, model, project or agent.
+    pub key: String,
+    pub messages: u64,
+    pub cost: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+}
+
+impl ReportRow {
+    fn add(&mut self, usage: &MessageUsage) {
+        self.messages += 1;
+        self.cost += usage.cost;
+        self.input_tokens += usage.tokens.input;
+        self.output_tokens += usage.tokens.output;
+        self.reasoning_tokens += usage.tokens.reasoning;
+        self.cache_read_tokens += usage.tokens.cache_read;
+        self.cache_write_tokens += usage.tokens.cache_write;
+    }
+}
+
+/// The cost and tokens of the assistant messages of all projects, or of the
+/// project given as in [`aggregate_instance_stats`], in the last `days`.
+pub async fn collect_usage(
+    instance: &Instance,
+    days: Option<u32>,
+    project_filter: Option<String>,
+) -> anyhow::Result<Vec<MessageUsage>> {
+    let cutoff = cutoff_time(days, chrono::Utc::now().timestamp_millis());
+    let project_filter = match project_filter {
+        Some(filter) if filter.is_empty() => Some(instance.project_id().await),
+        filter => filter,
+    };
+    let storage = instance.storage();
+
+    let mut usage = Vec::new();
+    for key in storage.list(&["project"]).await? {
+        let id = &key[1];
+        if project_filter.as_ref().is_some_and(|filter| filter != id) {
+            continue;
+        }
+        let project = match Project::load(storage, id).await? {
+            Some(project) => project
+                .name
+                .unwrap_or_else(|| project.worktree.display().to_string()),
+            None => id.clone(),
+        };
+
+        for session in instance.session_repo().list(id).await? {
+            if session.time.updated < cutoff {
+                continue;
+            }
+            let messages = instance
+                .session_repo()
+                .messages(id, &session.id, None)
+                .await
+                .unwrap_or_default();
+            for message in messages {
+                let Message::Assistant(message) = message.message else {
+                    continue;
+                };
+                if message.time.created < cutoff {
+                    continue;
+                }
+                let Some(created) = Local.timestamp_millis_opt(message.time.created).single()
+                else {
+                    continue;
+                };
+                usage.push(MessageUsage {
+                    day: created.date_naive(),
+                    model: format!("{}/{}", message.provider_id, message.model_id),
+                    project: project.clone(),
+                    agent: message.agent,
+                    cost: message.cost,
+                    tokens: TokenStats {
+                        input: message.tokens.input as u64,
+                        output: message.tokens.output as u64,
+                        reasoning: message.tokens.reasoning as u64,
+                        cache_read: message.tokens.cache.read as u64,
+                        cache_write: message.tokens.cache.write as u64,
+                    },
+                });
+            }
+        }
+    }
+    Ok(usage)
+}
+
+/// Sum `usage` into rows by `group`, and by month first if `monthly`.
+pub fn group_usage(usage: &[MessageUsage], group: ReportGroup, monthly: bool) -> Vec<ReportRow> {
+    let mut rows: BTreeMap<(Option<String>, String), ReportRow> = BTreeMap::new();
+    for usage in usage {
+        let month = monthly.then(|| usage.day.format("%Y-%m").to_string());
+        let key = group.key(usage);
+        rows.entry((month.clone(), key.clone()))
+            .or_insert_with(|| ReportRow {
+                month,
+                key,
+                ..Default::default()
+            })
+            .add(usage);
+    }
+    rows.into_values().collect()
+}
+
+/// A cost report as JSON.
+#[derive(Serialize)]
+struct Report<'a> {
+    group_by: ReportGroup,
+    monthly: bool,
+    rows: &'a [ReportRow],
+    total: ReportRow,
+}
+
+/// Write a cost report of `rows` from [`group_usage`] to `out`.
+pub fn write_report(
+    out: &mut impl Write,
+    rows: &[ReportRow],
+    format: ReportFormat,
+    group: ReportGroup,
+    monthly: bool,
+) -> std::io::Result<()> {
+    match format {
+        ReportFormat::Csv => {
+            if monthly {
+                write!(out, "month,")?;
+            }
+            writeln!(
+                out,
+                "{},messages,cost,input_tokens,output_tokens,reasoning_tokens,\
+                 cache_read_tokens,cache_write_tokens",
+                group.as_str()
+            )?;
+            for row in rows {
+                if let Some(month) = &row.month {
+                    write!(out, "{month},")?;
+                }
+                writeln!(
+                    out,
+                    "{},{},{:.6},{},{},{},{},{}",
+                    csv_field(&row.key),
+                    row.messages,
+                    row.cost,
+                    row.input_tokens,
+                    row.output_tokens,
+                    row.reasoning_tokens,
+                    row.cache_read_tokens,
+                    row.cache_write_tokens
+                )?;
+            }
+        }
+        ReportFormat::Json => {
+            let mut total = ReportRow {
+                key: "total".to_string(),
+                ..Default::default()
+            };
+            for row in rows {
+                total.messages += row.messages;
+                total.cost += row.cost;
+                total.input_tokens += row.input_tokens;
+                total.output_tokens += row.output_tokens;
+                total.reasoning_tokens += row.reasoning_tokens;
+                total.cache_read_tokens += row.cache_read_tokens;
+                total.cache_write_tokens += row.cache_write_tokens;
+            }
+            let report = Report {
+                group_by: group,
+                monthly,
+                rows,
+                total,
+            };
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// `field` quoted for CSV if it needs to be.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
 }
 
 /// Display statistics in a nice format.
@@ -321,6 +656,66 @@ pub fn display_stats(stats: &SessionStats, tool_limit: Option<usize>) {
         println!("└{}┘", "─".repeat(WIDTH));
     }
     println!();
+
+    // Cost by model section
+    if let Some(spend) = stats.spend.as_ref().filter(|s| !s.models.is_empty()) {
+        let mut models: Vec<_> = spend.models.iter().collect();
+        models.sort_by(|a, b| b.1.cost.total_cmp(&a.1.cost));
+
+        println!("┌{}┐", "─".repeat(WIDTH));
+        println!(
+            "│{:^WIDTH$}│",
+            format!("COST BY MODEL (LAST {} DAYS)", spend.days.len())
+        );
+        println!("├{}┤", "─".repeat(WIDTH));
+        for (model, amount) in models {
+            let value = format!(
+                "${:.2}, {} tokens",
+                amount.cost,
+                format_number(amount.input_tokens + amount.output_tokens)
+            );
+            let model = if model.len() > 28 {
+                format!("{}...", &model[..25])
+            } else {
+                model.clone()
+            };
+            println!("{}", render_row(&model, &value));
+        }
+        println!("└{}┘", "─".repeat(WIDTH));
+        println!();
+    }
+
+    // Storage section
+    if let Some(ref usage) = stats.storage {
+        println!("┌{}┐", "─".repeat(WIDTH));
+        println!("│{:^WIDTH$}│", "STORAGE");
+        println!("├{}┤", "─".repeat(WIDTH));
+
+        for (namespace, ns) in &usage.stats.namespaces {
+            let value = format!(
+                "{} items, {}",
+                format_number(ns.items),
+                format_bytes(ns.bytes)
+            );
+            println!("{}", render_row(namespace, &value));
+        }
+
+        let total = match usage.quota {
+            Some(quota) => format!(
+                "{} / {}",
+                format_bytes(usage.stats.total_bytes()),
+                format_bytes(quota)
+            ),
+            None => format_bytes(usage.stats.total_bytes()),
+        };
+        println!("{}", render_row("Total", &total));
+        println!("└{}┘", "─".repeat(WIDTH));
+
+        for warning in &usage.warnings {
+            println!("⚠ {warning}");
+        }
+        println!();
+    }
 }
 
 /// Format a number with K/M suffixes.
@@ -344,4 +739,91 @@ mod tests {
         assert_eq!(format_number(1500), "1.5K");
         assert_eq!(format_number(1_500_000), "1.5M");
     }
+
+    fn usage(day: &str, model: &str, project: &str, cost: f64) -> MessageUsage {
+        MessageUsage {
+            day: day.parse().unwrap(),
+            model: model.to_string(),
+            project: project.to_string(),
+            agent: "build".to_string(),
+            cost,
+            tokens: TokenStats {
+                input: 100,
+                output: 10,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_group_usage() {
+        let usage = [
+            usage("2026-09-30", "anthropic/claude", "/work/acme", 0.5),
+            usage("2026-10-01", "openai/gpt", "/work/acme", 0.25),
+            usage("2026-10-02", "anthropic/claude", "/work/globex", 1.0),
+        ];
+
+        let rows = group_usage(&usage, ReportGroup::Project, false);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].key, "/work/acme");
+        assert_eq!(rows[0].messages, 2);
+        assert_eq!(rows[0].cost, 0.75);
+        assert_eq!(rows[0].input_tokens, 200);
+        assert_eq!(rows[0].month, None);
+
+        let rows = group_usage(&usage, ReportGroup::Model, true);
+        let keys: Vec<_> = rows
+            .iter()
+            .map(|row| (row.month.as_deref().unwrap(), row.key.as_str()))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                ("2026-09", "anthropic/claude"),
+                ("2026-10", "anthropic/claude"),
+                ("2026-10", "openai/gpt")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_report() {
+        let usage = [
+            usage("2026-10-01", "anthropic/claude", "Acme, Inc.", 0.5),
+            usage("2026-10-01", "anthropic/claude", "/work/globex", 0.25),
+        ];
+        let rows = group_usage(&usage, ReportGroup::Project, true);
+
+        let mut csv = Vec::new();
+        write_report(
+            &mut csv,
+            &rows,
+            ReportFormat::Csv,
+            ReportGroup::Project,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "month,project,messages,cost,input_tokens,output_tokens,reasoning_tokens,\
+             cache_read_tokens,cache_write_tokens\n\
+             2026-10,/work/globex,1,0.250000,100,10,0,0,0\n\
+             2026-10,\"Acme, Inc.\",1,0.500000,100,10,0,0,0\n"
+        );
+
+        let mut json = Vec::new();
+        write_report(
+            &mut json,
+            &rows,
+            ReportFormat::Json,
+            ReportGroup::Project,
+            true,
+        )
+        .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json["group_by"], "project");
+        assert_eq!(json["rows"][1]["key"], "Acme, Inc.");
+        assert_eq!(json["total"]["cost"], 0.75);
+        assert_eq!(json["total"]["messages"], 2);
+    }
 }
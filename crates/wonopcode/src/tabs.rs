@@ -0,0 +1,397 @@
+//! Several sessions as tabs in one TUI.
+//!
+//! Each tab has its own runner over the shared instance, bus and permission
+//! manager, so a long task can run in one tab while another is used for
+//! questions. Only the shown tab's updates reach the TUI. The others are held
+//! back and replayed when their tab is shown again, so the TUI applies them
+//! to the right session.
+//!
+//! Every runner forwards the permission requests of the shared bus. Those of
+//! tabs in the background are dropped: the shown tab's runner forwards the
+//! same request, and the shared permission manager takes the answer for any
+//! tab.
+
+use crate::runner::{Runner, RunnerConfig};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use wonopcode_core::bus::Bus;
+use wonopcode_core::config::McpConfig;
+use wonopcode_core::{Instance, PermissionManager};
+use wonopcode_tui::{AppAction, AppUpdate};
+
+/// Which tab is shown, and what the others hold back.
+#[derive(Default)]
+struct Routing {
+    /// Tab ids, in order.
+    order: Vec<u64>,
+    /// Id of the tab shown.
+    active: u64,
+    /// Updates of tabs in the background, oldest first.
+    held: HashMap<u64, Vec<AppUpdate>>,
+}
+
+impl Routing {
+    /// Route an update of a tab, returning what to send to the TUI now.
+    ///
+    /// Updates of background tabs are held back. When such a tab starts or
+    /// stops working, the TUI hears about it for the tab bar.
+    fn route(&mut self, id: u64, update: AppUpdate) -> Vec<AppUpdate> {
+        if id == self.active {
+            return vec![update];
+        }
+        if matches!(update, AppUpdate::PermissionRequest(_)) {
+            return Vec::new();
+        }
+        // Closed tabs still finish what they were doing
+        let Some(index) = self.order.iter().position(|&tab| tab == id) else {
+            return Vec::new();
+        };
+        let busy = match &update {
+            AppUpdate::Started => Some(true),
+            AppUpdate::Completed { .. } | AppUpdate::Error(_) => Some(false),
+            _ => None,
+        };
+        self.held.entry(id).or_default().push(update);
+        busy.map(|busy| AppUpdate::TabActivity { index, busy })
+            .into_iter()
+            .collect()
+    }
+
+    /// Show the tab at an index, returning the switch and what it held back.
+    fn show(&mut self, index: usize) -> Vec<AppUpdate> {
+        let id = self.order[index];
+        self.active = id;
+        let mut updates = vec![AppUpdate::TabSwitched {
+            index,
+            count: self.order.len(),
+        }];
+        updates.extend(self.held.remove(&id).unwrap_or_default());
+        updates
+    }
+
+    /// Forget the tab at an index, which must not be shown.
+    fn remove(&mut self, index: usize) -> AppUpdate {
+        let id = self.order.remove(index);
+        self.held.remove(&id);
+        AppUpdate::TabClosed { index }
+    }
+
+    /// Index of the tab shown.
+    fn active_index(&self) -> Option<usize> {
+        self.order.iter().position(|&tab| tab == self.active)
+    }
+}
+
+/// What the runners of new tabs are created from.
+pub struct TabTemplate {
+    pub config: RunnerConfig,
+    pub instance: Instance,
+    pub mcp_configs: Option<HashMap<String, McpConfig>>,
+    pub bus: Bus,
+    pub permission_manager: Arc<PermissionManager>,
+}
+
+/// The session tabs of a TUI and their runners.
+pub struct SessionTabs {
+    /// Action senders of the tabs' runners, in tab order.
+    runners: Vec<mpsc::UnboundedSender<AppAction>>,
+    routing: Arc<Mutex<Routing>>,
+    next_id: u64,
+    template: TabTemplate,
+    /// Model and agent picked since startup, for the runners of new tabs.
+    model: Option<String>,
+    agent: Option<String>,
+    update_tx: mpsc::UnboundedSender<AppUpdate>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl SessionTabs {
+    /// Create tabs sending their updates to `update_tx`, with `runner` in the
+    /// first tab.
+    pub fn new(
+        runner: Runner,
+        template: TabTemplate,
+        update_tx: mpsc::UnboundedSender<AppUpdate>,
+    ) -> Self {
+        let mut tabs = Self {
+            runners: Vec::new(),
+            routing: Arc::new(Mutex::new(Routing::default())),
+            next_id: 0,
+            template,
+            model: None,
+            agent: None,
+            update_tx,
+            handles: Vec::new(),
+        };
+        tabs.add(runner);
+        tabs
+    }
+
+    /// Route the TUI's actions until it quits, then wait for the runners.
+    pub async fn run(mut self, mut action_rx: mpsc::UnboundedReceiver<AppAction>) {
+        while let Some(action) = action_rx.recv().await {
+            match action {
+                AppAction::NewTab => {
+                    if let Err(e) = self.open().await {
+                        warn!(error = %e, "Failed to open tab");
+                        let _ = self.update_tx.send(AppUpdate::Status(e));
+                    }
+                }
+                AppAction::SwitchTab(index) => self.switch(index),
+                AppAction::CloseTab(index) => self.close(index),
+                AppAction::Quit => {
+                    for runner in &self.runners {
+                        let _ = runner.send(AppAction::Quit);
+                    }
+                    break;
+                }
+                action => {
+                    match &action {
+                        AppAction::ChangeModel(model) => self.model = Some(model.clone()),
+                        AppAction::ChangeAgent(agent) => self.agent = Some(agent.clone()),
+                        _ => {}
+                    }
+                    self.send(action);
+                }
+            }
+        }
+
+        // Runners stop once their action channel closes
+        self.runners.clear();
+        for handle in self.handles {
+            let _ = handle.await;
+        }
+    }
+
+    /// Add a tab running `runner`, returning its index.
+    fn add(&mut self, runner: Runner) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let index = {
+            let mut routing = self.routing.lock().unwrap_or_else(|e| e.into_inner());
+            routing.order.push(id);
+            routing.order.len() - 1
+        };
+
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let (update_tx, mut update_rx) = mpsc::unbounded_channel();
+
+        // Forward under the lock, so a switch can't slip between routing an
+        // update and sending it
+        let routing = self.routing.clone();
+        let forward_tx = self.update_tx.clone();
+        tokio::spawn(async move {
+            while let Some(update) = update_rx.recv().await {
+                let Ok(mut routing) = routing.lock() else {
+                    break;
+                };
+                for update in routing.route(id, update) {
+                    if forward_tx.send(update).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        self.handles.push(tokio::spawn(async move {
+            runner.run(action_rx, update_tx).await;
+        }));
+        self.runners.push(action_tx);
+        index
+    }
+
+    /// Open a tab with a new runner and show it.
+    async fn open(&mut self) -> Result<(), String> {
+        let template = &self.template;
+        let runner = Runner::new_with_shared(
+            template.config.clone(),
+            template.instance.clone(),
+            template.mcp_configs.clone(),
+            Some(template.bus.clone()),
+            Some(template.permission_manager.clone()),
+        )
+        .await
+        .map_err(|e| format!("Failed to open a tab: {e}"))?;
+
+        let index = self.add(runner);
+        let runner = &self.runners[index];
+        if let Some(model) = &self.model {
+            let _ = runner.send(AppAction::ChangeModel(model.clone()));
+        }
+        if let Some(agent) = &self.agent {
+            let _ = runner.send(AppAction::ChangeAgent(agent.clone()));
+        }
+        self.switch(index);
+        info!(tab = index + 1, "Opened tab");
+        Ok(())
+    }
+
+    /// Show the tab at an index.
+    fn switch(&self, index: usize) {
+        let Ok(mut routing) = self.routing.lock() else {
+            return;
+        };
+        if index >= routing.order.len() {
+            return;
+        }
+        for update in routing.show(index) {
+            let _ = self.update_tx.send(update);
+        }
+    }
+
+    /// Close the tab at an index, cancelling what it runs. The last tab
+    /// stays open.
+    fn close(&mut self, index: usize) {
+        let Ok(mut routing) = self.routing.lock() else {
+            return;
+        };
+        let count = routing.order.len();
+        if count < 2 || index >= count {
+            return;
+        }
+        if routing.active_index() == Some(index) {
+            let next = if index + 1 < count {
+                index + 1
+            } else {
+                index - 1
+            };
+            for update in routing.show(next) {
+                let _ = self.update_tx.send(update);
+            }
+        }
+        let _ = self.update_tx.send(routing.remove(index));
+
+        let runner = self.runners.remove(index);
+        let _ = runner.send(AppAction::Cancel);
+        info!(tab = index + 1, "Closed tab");
+    }
+
+    /// Send an action to the runner of the tab shown.
+    fn send(&self, action: AppAction) {
+        let index = self
+            .routing
+            .lock()
+            .ok()
+            .and_then(|routing| routing.active_index());
+        let sent = index
+            .and_then(|index| self.runners.get(index))
+            .is_some_and(|runner| runner.send(action).is_ok());
+        if !sent {
+            warn!("Runner of the tab shown is gone");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn routing(tabs: u64) -> Routing {
+        Routing {
+            order: (0..tabs).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn text(update: &AppUpdate) -> &str {
+        match update {
+            AppUpdate::TextDelta(text) => text,
+            _ => "",
+        }
+    }
+
+    #[test]
+    fn test_shown_tab_updates_pass() {
+        let mut routing = routing(2);
+        let updates = routing.route(0, AppUpdate::TextDelta("hi".to_string()));
+        assert_eq!(updates.len(), 1);
+        assert_eq!(text(&updates[0]), "hi");
+        assert!(routing.held.is_empty());
+    }
+
+    #[test]
+    fn test_background_updates_are_replayed() {
+        let mut routing = routing(2);
+        let updates = routing.route(1, AppUpdate::Started);
+        assert!(matches!(
+            updates[..],
+            [AppUpdate::TabActivity {
+                index: 1,
+                busy: true
+            }]
+        ));
+        assert!(routing
+            .route(1, AppUpdate::TextDelta("a".to_string()))
+            .is_empty());
+        assert!(routing
+            .route(1, AppUpdate::TextDelta("b".to_string()))
+            .is_empty());
+
+        let updates = routing.show(1);
+        assert!(matches!(
+            updates[0],
+            AppUpdate::TabSwitched { index: 1, count: 2 }
+        ));
+        assert!(matches!(updates[1], AppUpdate::Started));
+        assert_eq!(text(&updates[2]), "a");
+        assert_eq!(text(&updates[3]), "b");
+        assert_eq!(routing.active_index(), Some(1));
+
+        // The first tab is in the background now
+        let updates = routing.route(
+            0,
+            AppUpdate::Completed {
+                text: String::new(),
+            },
+        );
+        assert!(matches!(
+            updates[..],
+            [AppUpdate::TabActivity {
+                index: 0,
+                busy: false
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_background_permission_requests_are_dropped() {
+        let mut routing = routing(2);
+        let request = AppUpdate::PermissionRequest(wonopcode_tui::PermissionRequestUpdate {
+            id: "perm_1".to_string(),
+            tool: "bash".to_string(),
+            action: "execute".to_string(),
+            description: "Run ls".to_string(),
+            path: None,
+        });
+        assert!(routing.route(1, request.clone()).is_empty());
+        assert!(routing.held.is_empty());
+        assert_eq!(routing.route(0, request).len(), 1);
+    }
+
+    #[test]
+    fn test_removed_tab_updates_are_dropped() {
+        let mut routing = routing(3);
+        routing.route(1, AppUpdate::TextDelta("a".to_string()));
+        assert!(matches!(
+            routing.remove(1),
+            AppUpdate::TabClosed { index: 1 }
+        ));
+        assert!(routing.held.is_empty());
+        assert!(routing
+            .route(1, AppUpdate::TextDelta("b".to_string()))
+            .is_empty());
+        assert!(routing.held.is_empty());
+
+        // Tab ids stay with their tabs as indices shift
+        assert!(matches!(
+            routing.route(2, AppUpdate::Started)[..],
+            [AppUpdate::TabActivity {
+                index: 1,
+                busy: true
+            }]
+        ));
+    }
+}
@@ -0,0 +1,338 @@
+//! OpenTelemetry export.
+//!
+//! When `telemetry.enabled` is set, [`init`] starts exporting to an OTLP/HTTP
+//! collector: a `prompt` span per prompt with a child span per tool call, and
+//! metrics for prompt latency, tool durations, token usage and errors.
+//! Otherwise nothing is set up and the `record_*` functions do nothing.
+
+use opentelemetry::metrics::{Counter, Histogram};
+use opentelemetry::trace::{Span, SpanKind, Status, TraceContextExt, Tracer};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::metrics::{PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+use wonopcode_core::config::TelemetryConfig;
+
+/// Instrumentation scope of all spans and metrics.
+const SCOPE: &str = "wonopcode";
+
+/// Default seconds between metric exports.
+const METRICS_INTERVAL: u64 = 60;
+
+/// Metric instruments, created once export is set up.
+static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+
+struct Instruments {
+    prompt_duration: Histogram<f64>,
+    tool_duration: Histogram<f64>,
+    tokens: Counter<u64>,
+    errors: Counter<u64>,
+}
+
+impl Instruments {
+    fn new() -> Self {
+        let meter = global::meter(SCOPE);
+        Self {
+            prompt_duration: meter
+                .f64_histogram("wonopcode.prompt.duration")
+                .with_unit("s")
+                .with_description("Time from sending a prompt to its response")
+                .build(),
+            tool_duration: meter
+                .f64_histogram("wonopcode.tool.duration")
+                .with_unit("s")
+                .with_description("Duration of tool calls")
+                .build(),
+            tokens: meter
+                .u64_counter("wonopcode.tokens")
+                .with_unit("{token}")
+                .with_description("Tokens used, by type (input or output)")
+                .build(),
+            errors: meter
+                .u64_counter("wonopcode.errors")
+                .with_unit("{error}")
+                .with_description("Failed prompts and tool calls")
+                .build(),
+        }
+    }
+}
+
+/// Running exporters. Call [`Telemetry::shutdown`] before exiting so the
+/// last spans and metrics are sent.
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Telemetry {
+    /// Flush and stop the exporters.
+    pub async fn shutdown(self) {
+        let result = tokio::task::spawn_blocking(move || {
+            if let Err(e) = self.tracer_provider.shutdown() {
+                warn!(error = %e, "Failed to flush traces");
+            }
+            if let Err(e) = self.meter_provider.shutdown() {
+                warn!(error = %e, "Failed to flush metrics");
+            }
+        })
+        .await;
+        if let Err(e) = result {
+            warn!(error = %e, "Telemetry shutdown failed");
+        }
+    }
+}
+
+/// Start exporting if the project's config enables telemetry.
+pub async fn init(cwd: &Path) -> Option<Telemetry> {
+    // Config errors are reported by whatever runs next
+    let (config, _) = wonopcode_core::config::Config::load(Some(cwd)).await.ok()?;
+    let telemetry = config.telemetry.filter(|t| t.enabled == Some(true))?;
+    match start(&telemetry) {
+        Ok(started) => {
+            info!(
+                endpoint = telemetry.endpoint.as_deref().unwrap_or("(environment)"),
+                "OpenTelemetry export enabled"
+            );
+            Some(started)
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to start OpenTelemetry export");
+            None
+        }
+    }
+}
+
+fn start(config: &TelemetryConfig) -> anyhow::Result<Telemetry> {
+    let resource = resource(config);
+    let headers = config.headers.clone().unwrap_or_default();
+
+    let mut spans = SpanExporter::builder()
+        .with_http()
+        .with_headers(headers.clone());
+    if let Some(url) = signal_endpoint(config, "traces") {
+        spans = spans.with_endpoint(url);
+    }
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(spans.build()?)
+        .with_resource(resource.clone())
+        .build();
+
+    let mut metrics = MetricExporter::builder().with_http().with_headers(headers);
+    if let Some(url) = signal_endpoint(config, "metrics") {
+        metrics = metrics.with_endpoint(url);
+    }
+    let interval = config.metrics_interval.unwrap_or(METRICS_INTERVAL).max(1);
+    let reader = PeriodicReader::builder(metrics.build()?)
+        .with_interval(Duration::from_secs(interval))
+        .build();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(reader)
+        .with_resource(resource)
+        .build();
+
+    global::set_tracer_provider(tracer_provider.clone());
+    global::set_meter_provider(meter_provider.clone());
+    let _ = INSTRUMENTS.set(Instruments::new());
+
+    Ok(Telemetry {
+        tracer_provider,
+        meter_provider,
+    })
+}
+
+/// The resource describing this process.
+fn resource(config: &TelemetryConfig) -> Resource {
+    let service_name = config
+        .service_name
+        .clone()
+        .unwrap_or_else(|| SCOPE.to_string());
+    let mut attributes = vec![KeyValue::new("service.version", env!("CARGO_PKG_VERSION"))];
+    if let Some(extra) = &config.attributes {
+        let mut extra: Vec<_> = extra.iter().collect();
+        extra.sort();
+        attributes.extend(
+            extra
+                .into_iter()
+                .map(|(k, v)| KeyValue::new(k.clone(), v.clone())),
+        );
+    }
+    Resource::builder()
+        .with_service_name(service_name)
+        .with_attributes(attributes)
+        .build()
+}
+
+/// The OTLP/HTTP URL for a signal ("traces" or "metrics"), if an endpoint is
+/// configured.
+fn signal_endpoint(config: &TelemetryConfig, signal: &str) -> Option<String> {
+    let endpoint = config.endpoint.as_deref()?.trim_end_matches('/');
+    (!endpoint.is_empty()).then(|| format!("{endpoint}/v1/{signal}"))
+}
+
+/// Whether export is running.
+fn enabled() -> bool {
+    INSTRUMENTS.get().is_some()
+}
+
+/// A prompt being traced.
+pub struct PromptTrace {
+    context: Context,
+    attributes: Vec<KeyValue>,
+    started: std::time::Instant,
+}
+
+impl PromptTrace {
+    /// Start the span of a prompt.
+    pub fn start(provider: &str, model: &str, agent: &str) -> Self {
+        let attributes = vec![
+            KeyValue::new("provider", provider.to_string()),
+            KeyValue::new("model", model.to_string()),
+            KeyValue::new("agent", agent.to_string()),
+        ];
+        let context = if enabled() {
+            let tracer = global::tracer(SCOPE);
+            let span = tracer
+                .span_builder("prompt")
+                .with_kind(SpanKind::Internal)
+                .with_attributes(attributes.clone())
+                .start(&tracer);
+            Context::current_with_span(span)
+        } else {
+            Context::current()
+        };
+        Self {
+            context,
+            attributes,
+            started: std::time::Instant::now(),
+        }
+    }
+
+    /// The context to run the prompt in, so tool spans become its children.
+    pub fn context(&self) -> Context {
+        self.context.clone()
+    }
+
+    /// End the span and record the prompt's duration.
+    ///
+    /// `error` is `None` on success; cancelled prompts aren't counted as
+    /// errors.
+    pub fn finish(self, error: Option<&str>) {
+        let Some(instruments) = INSTRUMENTS.get() else {
+            return;
+        };
+        let outcome = prompt_outcome(error);
+        let mut attributes = self.attributes;
+        attributes.push(KeyValue::new("outcome", outcome));
+        instruments
+            .prompt_duration
+            .record(self.started.elapsed().as_secs_f64(), &attributes);
+
+        let span = self.context.span();
+        if outcome == "error" {
+            instruments
+                .errors
+                .add(1, &[KeyValue::new("kind", "prompt")]);
+            span.set_status(Status::error(error.unwrap_or_default().to_string()));
+        }
+        span.set_attribute(KeyValue::new("outcome", outcome));
+        span.end();
+    }
+}
+
+/// How a prompt ended: "ok", "cancelled" or "error".
+fn prompt_outcome(error: Option<&str>) -> &'static str {
+    match error {
+        None => "ok",
+        Some(e) if e.contains("Cancelled") => "cancelled",
+        Some(_) => "error",
+    }
+}
+
+/// Record a finished tool call as a span under the current prompt.
+pub fn record_tool(tool: &str, duration: Duration, success: bool) {
+    let Some(instruments) = INSTRUMENTS.get() else {
+        return;
+    };
+    let attributes = [
+        KeyValue::new("tool", tool.to_string()),
+        KeyValue::new("success", success),
+    ];
+    instruments
+        .tool_duration
+        .record(duration.as_secs_f64(), &attributes);
+    if !success {
+        instruments.errors.add(1, &[KeyValue::new("kind", "tool")]);
+    }
+
+    let tracer = global::tracer(SCOPE);
+    let end = SystemTime::now();
+    let mut span = tracer
+        .span_builder(format!("tool {tool}"))
+        .with_kind(SpanKind::Internal)
+        .with_start_time(end.checked_sub(duration).unwrap_or(end))
+        .with_attributes(attributes)
+        .start_with_context(&tracer, &Context::current());
+    if !success {
+        span.set_status(Status::error("tool failed"));
+    }
+    span.end_with_timestamp(end);
+}
+
+/// Record the tokens used by a model step.
+pub fn record_tokens(provider: &str, model: &str, input: u32, output: u32) {
+    let Some(instruments) = INSTRUMENTS.get() else {
+        return;
+    };
+    for (kind, count) in [("input", input), ("output", output)] {
+        instruments.tokens.add(
+            u64::from(count),
+            &[
+                KeyValue::new("provider", provider.to_string()),
+                KeyValue::new("model", model.to_string()),
+                KeyValue::new("type", kind),
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_endpoint() {
+        let mut config = TelemetryConfig::default();
+        assert_eq!(signal_endpoint(&config, "traces"), None);
+
+        config.endpoint = Some("http://collector:4318/".to_string());
+        assert_eq!(
+            signal_endpoint(&config, "traces").as_deref(),
+            Some("http://collector:4318/v1/traces")
+        );
+        assert_eq!(
+            signal_endpoint(&config, "metrics").as_deref(),
+            Some("http://collector:4318/v1/metrics")
+        );
+    }
+
+    #[test]
+    fn test_prompt_outcome() {
+        assert_eq!(prompt_outcome(None), "ok");
+        assert_eq!(prompt_outcome(Some("Cancelled")), "cancelled");
+        assert_eq!(prompt_outcome(Some("Rate limited")), "error");
+    }
+
+    #[test]
+    fn test_recording_without_export_is_a_no_op() {
+        record_tool("bash", Duration::from_millis(5), false);
+        record_tokens("anthropic", "claude", 10, 20);
+        let trace = PromptTrace::start("anthropic", "claude", "build");
+        trace.finish(Some("boom"));
+        assert!(!enabled());
+    }
+}
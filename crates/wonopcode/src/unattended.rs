@@ -0,0 +1,148 @@
+//! Runners nobody watches.
+//!
+//! Scheduled tasks and batch steps send prompts to a runner of their own,
+//! like `wonopcode run`. Nobody is around to answer permission prompts, so
+//! tools are allowed as in `wonopcode run`.
+
+use crate::commands::start_mcp_server;
+use crate::runner::{load_api_key, Runner, RunnerConfig};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::warn;
+use wonopcode_core::Instance;
+use wonopcode_tui::{AppAction, AppUpdate};
+
+/// A runner taking prompts in order, with the MCP server for its tools.
+pub struct Unattended {
+    action_tx: mpsc::UnboundedSender<AppAction>,
+    update_rx: mpsc::UnboundedReceiver<AppUpdate>,
+    runner_handle: JoinHandle<()>,
+    mcp_server_handle: Option<JoinHandle<()>>,
+}
+
+impl Unattended {
+    /// Start a runner for `instance` with a model of `provider`.
+    pub async fn start(
+        instance: &Instance,
+        provider: String,
+        model_id: String,
+    ) -> Result<Self, String> {
+        let core_config = instance.config().await;
+
+        let bus = wonopcode_core::bus::Bus::new();
+        let permission_manager = Arc::new(wonopcode_core::PermissionManager::new(bus.clone()));
+        for rule in wonopcode_core::PermissionManager::default_rules() {
+            permission_manager.add_rule(rule).await;
+        }
+        for rule in wonopcode_core::PermissionManager::sandbox_allow_all_rules() {
+            permission_manager.add_rule(rule).await;
+        }
+
+        let (mcp_url, mcp_server_handle) =
+            match start_mcp_server(instance.directory(), permission_manager.clone()).await {
+                Ok((url, handle)) => (Some(url), Some(handle)),
+                Err(e) => {
+                    warn!(error = %e, "Failed to start MCP server for unattended runner");
+                    (None, None)
+                }
+            };
+
+        let config = RunnerConfig {
+            api_key: load_api_key(&provider).unwrap_or_default(),
+            provider,
+            model_id,
+            system_prompt: None,
+            max_tokens: Some(8192),
+            temperature: Some(0.7),
+            doom_loop: wonopcode_core::permission::Decision::Ask,
+            test_provider_settings: None,
+            allow_all: false,
+            allow_all_in_sandbox: core_config
+                .permission
+                .as_ref()
+                .and_then(|p| p.allow_all_in_sandbox)
+                .unwrap_or(true),
+            mcp_url,
+            mcp_secret: None,
+            external_mcp_servers: std::collections::HashMap::new(),
+        };
+
+        let runner = Runner::new_with_shared(
+            config,
+            instance.clone(),
+            core_config.mcp.clone(),
+            Some(bus),
+            Some(permission_manager),
+        )
+        .await;
+        let runner = match runner {
+            Ok(runner) => runner,
+            Err(e) => {
+                if let Some(handle) = mcp_server_handle {
+                    handle.abort();
+                }
+                return Err(format!("Failed to create runner: {e}"));
+            }
+        };
+
+        let (action_tx, action_rx) = mpsc::unbounded_channel();
+        let (update_tx, update_rx) = mpsc::unbounded_channel();
+        let runner_handle = tokio::spawn(async move {
+            runner.run(action_rx, update_tx).await;
+        });
+
+        Ok(Self {
+            action_tx,
+            update_rx,
+            runner_handle,
+            mcp_server_handle,
+        })
+    }
+
+    /// Send an action without waiting for it. Actions are handled in order,
+    /// so e.g. an agent change applies to the next prompt.
+    pub fn send(&self, action: AppAction) {
+        let _ = self.action_tx.send(action);
+    }
+
+    /// Send an action the runner answers with a status, such as a model or
+    /// agent change, and wait for it to be done.
+    pub async fn configure(&mut self, action: AppAction) -> Result<(), String> {
+        self.send(action);
+        loop {
+            match self.update_rx.recv().await {
+                Some(AppUpdate::Status(_)) => return Ok(()),
+                Some(AppUpdate::Error(e)) => return Err(e),
+                Some(_) => {}
+                None => return Err("Runner stopped".to_string()),
+            }
+        }
+    }
+
+    /// Send a prompt and wait for the response.
+    pub async fn prompt(&mut self, prompt: String) -> Result<String, String> {
+        self.send(AppAction::SendPrompt(prompt));
+        let mut streamed = String::new();
+        loop {
+            match self.update_rx.recv().await {
+                Some(AppUpdate::TextDelta(delta)) => streamed.push_str(&delta),
+                Some(AppUpdate::Completed { text }) => {
+                    return Ok(if text.is_empty() { streamed } else { text });
+                }
+                Some(AppUpdate::Error(e)) => return Err(e),
+                Some(_) => {}
+                None => return Err("Runner stopped before the prompt finished".to_string()),
+            }
+        }
+    }
+
+    /// Stop the runner and its MCP server.
+    pub fn stop(self) {
+        self.send(AppAction::Quit);
+        if let Some(handle) = self.mcp_server_handle {
+            handle.abort();
+        }
+        self.runner_handle.abort();
+    }
+}
@@ -2,14 +2,21 @@
 //!
 //! This module handles:
 //! - Checking for updates from GitHub releases
-//! - Downloading and installing new versions
+//! - Downloading and installing new versions, through the package manager
+//!   wonopcode was installed with (Homebrew, cargo, npm) or, for release
+//!   binaries, by replacing the binary after verifying its checksum and
+//!   signature
+//! - Rolling back to the version before the last upgrade
 //! - Release channel filtering (stable, beta, nightly)
 //! - Auto-update on startup
 
 use anyhow::{Context, Result};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use wonopcode_core::config::{AutoUpdateMode, Config};
 use wonopcode_core::version::{ReleaseChannel, Version};
+use wonopcode_util::size::format_bytes;
 
 /// GitHub release information.
 #[derive(Debug, Clone)]
@@ -56,6 +63,140 @@ pub struct CheckResult {
 /// GitHub repository for releases.
 const GITHUB_REPO: &str = "wonop-io/wonopcode";
 
+/// Release asset with the SHA-256 checksums of the other assets.
+const CHECKSUMS_ASSET: &str = "checksums.txt";
+
+/// Release asset with the Ed25519 signature of [`CHECKSUMS_ASSET`].
+const SIGNATURE_ASSET: &str = "checksums.txt.sig";
+
+/// Key that release signatures are checked with: a raw Ed25519 public key
+/// in base64, set when building releases. Without it only checksums are
+/// verified, so binaries are never installed automatically.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("WONOPCODE_RELEASE_PUBLIC_KEY");
+
+/// Warning shown before installing a binary whose signature can't be checked.
+const UNSIGNED_WARNING: &str = "This build has no release signing key, so the downloaded \
+     binary is only checked against checksums from the same server it came from.";
+
+/// The key release signatures are checked with, if this build has one.
+fn release_public_key() -> Option<&'static str> {
+    RELEASE_PUBLIC_KEY.filter(|key| !key.trim().is_empty())
+}
+
+/// How wonopcode was installed, which decides how it is upgraded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallMethod {
+    Homebrew,
+    Cargo,
+    Npm,
+    /// A binary from the GitHub releases, e.g. from `install.sh`.
+    Binary,
+}
+
+impl InstallMethod {
+    /// The install method of the running binary.
+    pub fn current() -> Self {
+        let exe = std::env::current_exe()
+            .and_then(|exe| exe.canonicalize())
+            .unwrap_or_default();
+        let cargo_home = std::env::var_os("CARGO_HOME")
+            .map(PathBuf::from)
+            .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")));
+        Self::detect(&exe, cargo_home.as_deref())
+    }
+
+    /// The install method of the binary at `exe`.
+    fn detect(exe: &Path, cargo_home: Option<&Path>) -> Self {
+        let path = exe.to_string_lossy().replace('\\', "/");
+        if path.contains("/Cellar/") {
+            Self::Homebrew
+        } else if path.contains("/node_modules/") {
+            Self::Npm
+        } else if cargo_home.is_some_and(|home| exe.starts_with(home.join("bin"))) {
+            Self::Cargo
+        } else {
+            Self::Binary
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Homebrew => "Homebrew",
+            Self::Cargo => "cargo install",
+            Self::Npm => "npm",
+            Self::Binary => "release binary",
+        }
+    }
+
+    /// The package manager command installing `version`, or `None` for
+    /// release binaries. Homebrew only upgrades to its latest version, so it
+    /// fails when the version is `pinned`.
+    fn command(&self, version: &Version, pinned: bool) -> Result<Option<Vec<String>>> {
+        let args: &[&str] = match self {
+            Self::Homebrew if pinned => anyhow::bail!(
+                "Homebrew can't install a specific version; reinstall wonopcode with install.sh to pick one"
+            ),
+            Self::Homebrew => &["brew", "upgrade", "wonopcode"],
+            Self::Cargo => &[
+                "cargo",
+                "install",
+                "wonopcode",
+                "--locked",
+                "--force",
+                "--version",
+                &version.to_string(),
+            ],
+            Self::Npm => &["npm", "install", "--global", &format!("wonopcode@{version}")],
+            Self::Binary => return Ok(None),
+        };
+        Ok(Some(args.iter().map(|arg| arg.to_string()).collect()))
+    }
+}
+
+/// What `wonopcode upgrade --rollback` goes back to.
+#[derive(Debug, Serialize, Deserialize)]
+struct Rollback {
+    /// The version before the last upgrade.
+    version: String,
+    /// How that upgrade was done.
+    method: InstallMethod,
+}
+
+fn rollback_path() -> Option<PathBuf> {
+    Config::data_dir().map(|dir| dir.join("upgrade_rollback.json"))
+}
+
+fn load_rollback() -> Option<Rollback> {
+    let content = std::fs::read_to_string(rollback_path()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn save_rollback(version: &Version, method: InstallMethod) {
+    let Some(path) = rollback_path() else {
+        return;
+    };
+    let rollback = Rollback {
+        version: version.to_string(),
+        method,
+    };
+    let saved = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, serde_json::to_string(&rollback).unwrap_or_default()));
+    if let Err(e) = saved {
+        tracing::warn!("Failed to save rollback information: {}", e);
+    }
+}
+
+/// Where the binary before the last upgrade is kept, next to `exe`.
+fn previous_binary_path(exe: &Path) -> Result<PathBuf> {
+    let parent = exe
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Cannot determine binary directory"))?;
+    Ok(parent.join(".wonopcode.previous"))
+}
+
 /// Fetch releases from GitHub API.
 pub async fn fetch_releases(channel: ReleaseChannel) -> Result<Vec<Release>> {
     let client = reqwest::Client::builder().user_agent("wonopcode").build()?;
@@ -164,43 +305,113 @@ pub fn find_platform_asset(release: &Release) -> Result<&Asset> {
         .ok_or_else(|| anyhow::anyhow!("No binary available for {os}-{arch}"))
 }
 
-/// Format file size for display.
-pub fn format_size(bytes: u64) -> String {
-    if bytes >= 1_000_000 {
-        format!("{:.1} MB", bytes as f64 / 1_000_000.0)
-    } else if bytes >= 1_000 {
-        format!("{:.1} KB", bytes as f64 / 1_000.0)
-    } else {
-        format!("{bytes} B")
-    }
-}
-
-/// Download and install a release.
+/// Download, verify and install the binary of a release, keeping the
+/// current one for a rollback.
 pub async fn install_release(release: &Release) -> Result<()> {
     let asset = find_platform_asset(release)?;
 
     println!(
         "Downloading {} ({})...",
         release.tag,
-        format_size(asset.size)
+        format_bytes(asset.size)
     );
 
     // Download
     let client = reqwest::Client::builder().user_agent("wonopcode").build()?;
+    let bytes = download(&client, &asset.download_url).await?;
 
-    let response = client.get(&asset.download_url).send().await?;
+    verify_release_asset(&client, release, &asset.name, &bytes).await?;
+
+    // Install binary
+    install_binary(&bytes, &asset.name).await?;
+
+    Ok(())
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let response = client.get(url).send().await?;
     if !response.status().is_success() {
         anyhow::bail!("Download failed: {}", response.status());
     }
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Check `bytes`, the asset `name` of `release`, against the release
+/// checksums, and the checksums against their signature if releases are
+/// signed.
+async fn verify_release_asset(
+    client: &reqwest::Client,
+    release: &Release,
+    name: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let find = |name: &str| release.assets.iter().find(|asset| asset.name == name);
+
+    let checksums = find(CHECKSUMS_ASSET).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Release {} has no {CHECKSUMS_ASSET}; not installing an unverified binary",
+            release.tag
+        )
+    })?;
+    let checksums = download(client, &checksums.download_url).await?;
+
+    match release_public_key() {
+        Some(public_key) => {
+            let signature = find(SIGNATURE_ASSET)
+                .ok_or_else(|| anyhow::anyhow!("Release {} is not signed", release.tag))?;
+            let signature = download(client, &signature.download_url).await?;
+            verify_signature(&checksums, &signature, public_key)?;
+            println!("Verified the release signature");
+        }
+        None => tracing::warn!(release = %release.tag, "Release signature not checked"),
+    }
 
-    let bytes = response.bytes().await?;
+    verify_checksum(&String::from_utf8_lossy(&checksums), name, bytes)?;
+    println!("Verified the checksum of {name}");
+    Ok(())
+}
 
-    // Install binary
-    install_binary(&bytes, &asset.name).await?;
+/// Check `bytes` against the entry for `name` in `checksums`, in the
+/// format of `sha256sum`.
+fn verify_checksum(checksums: &str, name: &str, bytes: &[u8]) -> Result<()> {
+    let expected = checksums
+        .lines()
+        .find_map(|line| {
+            let (hash, file) = line.split_once(char::is_whitespace)?;
+            (file.trim_start().trim_start_matches('*') == name).then(|| hash.to_lowercase())
+        })
+        .ok_or_else(|| anyhow::anyhow!("{name} is not in {CHECKSUMS_ASSET}"))?;
 
+    let actual = hex::encode(Sha256::digest(bytes));
+    if actual != expected {
+        anyhow::bail!("Checksum mismatch for {name}: expected {expected}, got {actual}");
+    }
     Ok(())
 }
 
+/// Check the Ed25519 `signature` of `message`, either the raw 64 bytes or
+/// base64, with a base64 `public_key`.
+fn verify_signature(message: &[u8], signature: &[u8], public_key: &str) -> Result<()> {
+    use base64::Engine;
+    use ring::signature::{UnparsedPublicKey, ED25519};
+
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let public_key = base64
+        .decode(public_key.trim())
+        .context("Invalid release public key")?;
+    let signature = if signature.len() == 64 {
+        signature.to_vec()
+    } else {
+        base64
+            .decode(String::from_utf8_lossy(signature).trim())
+            .context("Invalid release signature")?
+    };
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(message, &signature)
+        .map_err(|_| anyhow::anyhow!("The signature of {CHECKSUMS_ASSET} doesn't match"))
+}
+
 /// Install binary from downloaded bytes.
 async fn install_binary(bytes: &[u8], filename: &str) -> Result<()> {
     let current_exe = std::env::current_exe()?;
@@ -264,17 +475,29 @@ async fn install_binary(bytes: &[u8], filename: &str) -> Result<()> {
         std::fs::set_permissions(&temp_path, perms)?;
     }
 
-    // Atomic replace
-    let backup_path = parent.join(".wonopcode.bak");
-    if backup_path.exists() {
-        std::fs::remove_file(&backup_path)?;
+    // Atomic replace, keeping the current binary for a rollback
+    let previous_path = previous_binary_path(&current_exe)?;
+    if previous_path.exists() {
+        std::fs::remove_file(&previous_path)?;
+    }
+    std::fs::rename(&current_exe, &previous_path)?;
+    if let Err(e) = std::fs::rename(&temp_path, &current_exe) {
+        let _ = std::fs::rename(&previous_path, &current_exe);
+        return Err(e.into());
     }
-    std::fs::rename(&current_exe, &backup_path)?;
-    std::fs::rename(&temp_path, &current_exe)?;
 
-    // Clean up backup
-    let _ = std::fs::remove_file(&backup_path);
+    Ok(())
+}
 
+/// Run a package manager command.
+fn run_command(command: &[String]) -> Result<()> {
+    let status = std::process::Command::new(&command[0])
+        .args(&command[1..])
+        .status()
+        .with_context(|| format!("Failed to run {}", command[0]))?;
+    if !status.success() {
+        anyhow::bail!("{} exited with {status}", command.join(" "));
+    }
     Ok(())
 }
 
@@ -387,13 +610,30 @@ pub async fn check_updates_on_startup(cwd: &Path) -> Option<String> {
     let latest = result.latest.as_ref()?;
 
     match mode {
+        // Package managers are only run when asked to
+        AutoUpdateMode::Auto if InstallMethod::current() != InstallMethod::Binary => Some(format!(
+            "Update available: {} -> {} (run 'wonopcode upgrade' to install)",
+            result.current, latest.version
+        )),
+        // Unverifiable binaries are only installed when asked to
+        AutoUpdateMode::Auto if release_public_key().is_none() => {
+            tracing::warn!("Not auto-updating: this build can't check release signatures");
+            Some(format!(
+                "Update available: {} -> {} (run 'wonopcode upgrade' to install; \
+                 this build can't verify release signatures)",
+                result.current, latest.version
+            ))
+        }
         AutoUpdateMode::Auto => {
             // Auto-install
             match install_release(latest).await {
-                Ok(_) => Some(format!(
-                    "Updated wonopcode to {} - restart to use new version",
-                    latest.version
-                )),
+                Ok(_) => {
+                    save_rollback(&result.current, InstallMethod::Binary);
+                    Some(format!(
+                        "Updated wonopcode to {} - restart to use new version",
+                        latest.version
+                    ))
+                }
                 Err(e) => {
                     tracing::warn!("Auto-update failed: {}", e);
                     Some(format!(
@@ -473,7 +713,9 @@ pub async fn handle_upgrade(
     let current = Version::parse(env!("CARGO_PKG_VERSION"))
         .ok_or_else(|| anyhow::anyhow!("Invalid current version"))?;
 
+    let method = InstallMethod::current();
     println!("Current version: {current}");
+    println!("Installed with:  {}", method.name());
 
     // Determine which release to install
     let release = if let Some(ref ver) = version {
@@ -511,6 +753,11 @@ pub async fn handle_upgrade(
         return Ok(());
     }
 
+    if method == InstallMethod::Binary && release_public_key().is_none() {
+        eprintln!("WARNING: {UNSIGNED_WARNING}");
+        eprintln!();
+    }
+
     // Confirm upgrade
     if !yes {
         print!("Upgrade to {}? [y/N] ", release.version);
@@ -524,20 +771,92 @@ pub async fn handle_upgrade(
         }
     }
 
-    // Verify platform asset exists before downloading
-    let _asset = find_platform_asset(&release).context("No binary available for your platform")?;
-
     println!();
-    println!("Downloading {}...", release.version);
-
-    // Download and install
-    install_release(&release).await?;
+    match method.command(&release.version, version.is_some())? {
+        Some(command) => {
+            println!("Running {}", command.join(" "));
+            run_command(&command)?;
+        }
+        None => {
+            // Verify platform asset exists before downloading
+            let _asset =
+                find_platform_asset(&release).context("No binary available for your platform")?;
+            install_release(&release).await?;
+        }
+    }
+    save_rollback(&current, method);
 
     println!();
     println!("Successfully upgraded to {}!", release.version);
     println!();
     println!("Restart wonopcode to use the new version.");
+    println!("Run 'wonopcode upgrade --rollback' to go back to {current}.");
+
+    Ok(())
+}
+
+/// Handle `wonopcode upgrade --rollback`: go back to the version before the
+/// last upgrade.
+pub async fn handle_rollback(yes: bool) -> Result<()> {
+    use std::io::{self, Write};
 
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))
+        .ok_or_else(|| anyhow::anyhow!("Invalid current version"))?;
+    let rollback =
+        load_rollback().ok_or_else(|| anyhow::anyhow!("No earlier version to roll back to"))?;
+    let previous = Version::parse(&rollback.version)
+        .ok_or_else(|| anyhow::anyhow!("Invalid version: {}", rollback.version))?;
+
+    let method = InstallMethod::current();
+    if method != rollback.method {
+        anyhow::bail!(
+            "The last upgrade went through {}, but wonopcode is now installed with {}",
+            rollback.method.name(),
+            method.name()
+        );
+    }
+
+    println!("Current version:  {current}");
+    println!("Previous version: {previous}");
+    println!();
+
+    if !yes {
+        print!("Roll back to {previous}? [y/N] ");
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !input.trim().eq_ignore_ascii_case("y") {
+            println!("Rollback cancelled.");
+            return Ok(());
+        }
+    }
+
+    match method.command(&previous, true)? {
+        Some(command) => {
+            println!("Running {}", command.join(" "));
+            run_command(&command)?;
+        }
+        None => {
+            // Swap the binaries, so the rollback can be undone the same way
+            let current_exe = std::env::current_exe()?;
+            let previous_path = previous_binary_path(&current_exe)?;
+            if !previous_path.exists() {
+                anyhow::bail!("The previous binary is gone: {}", previous_path.display());
+            }
+            let swap_path = previous_path.with_extension("swap");
+            std::fs::rename(&current_exe, &swap_path)?;
+            if let Err(e) = std::fs::rename(&previous_path, &current_exe) {
+                let _ = std::fs::rename(&swap_path, &current_exe);
+                return Err(e.into());
+            }
+            std::fs::rename(&swap_path, &previous_path)?;
+        }
+    }
+    save_rollback(&current, method);
+
+    println!();
+    println!("Rolled back to {previous}.");
     Ok(())
 }
 
@@ -546,9 +865,66 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_format_size() {
-        assert_eq!(format_size(500), "500 B");
-        assert_eq!(format_size(1500), "1.5 KB");
-        assert_eq!(format_size(1_500_000), "1.5 MB");
+    fn test_detect_install_method() {
+        let cargo_home = Path::new("/home/me/.cargo");
+        let detect = |exe: &str| InstallMethod::detect(Path::new(exe), Some(cargo_home));
+        assert_eq!(
+            detect("/opt/homebrew/Cellar/wonopcode/0.1.2/bin/wonopcode"),
+            InstallMethod::Homebrew
+        );
+        assert_eq!(
+            detect("/usr/lib/node_modules/wonopcode-linux-x64/bin/wonopcode"),
+            InstallMethod::Npm
+        );
+        assert_eq!(
+            detect("/home/me/.cargo/bin/wonopcode"),
+            InstallMethod::Cargo
+        );
+        assert_eq!(
+            detect("/home/me/.local/bin/wonopcode"),
+            InstallMethod::Binary
+        );
+
+        let version = Version::parse("0.2.0").unwrap();
+        assert_eq!(
+            InstallMethod::Npm
+                .command(&version, false)
+                .unwrap()
+                .unwrap(),
+            ["npm", "install", "--global", "wonopcode@0.2.0"]
+        );
+        assert!(InstallMethod::Binary
+            .command(&version, true)
+            .unwrap()
+            .is_none());
+        assert!(InstallMethod::Homebrew.command(&version, true).is_err());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let bytes = b"binary";
+        let hash = hex::encode(Sha256::digest(bytes));
+        let checksums = format!("0000  other.zip\n{hash}  wonopcode-linux-x86_64.tar.gz\n");
+        assert!(verify_checksum(&checksums, "wonopcode-linux-x86_64.tar.gz", bytes).is_ok());
+        assert!(verify_checksum(&checksums, "wonopcode-linux-x86_64.tar.gz", b"evil").is_err());
+        assert!(verify_checksum(&checksums, "missing.zip", bytes).is_err());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        use base64::Engine;
+        use ring::signature::{Ed25519KeyPair, KeyPair};
+
+        let rng = ring::rand::SystemRandom::new();
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&rng).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let public_key = base64.encode(key_pair.public_key().as_ref());
+
+        let signature = key_pair.sign(b"checksums");
+        assert!(verify_signature(b"checksums", signature.as_ref(), &public_key).is_ok());
+        let encoded = base64.encode(signature.as_ref());
+        assert!(verify_signature(b"checksums", encoded.as_bytes(), &public_key).is_ok());
+        assert!(verify_signature(b"tampered", signature.as_ref(), &public_key).is_err());
     }
 }